@@ -0,0 +1,3 @@
+-- Remove avatar sources and gallery
+DROP TABLE IF EXISTS avatar_gallery;
+ALTER TABLE users DROP COLUMN IF EXISTS avatar_source;
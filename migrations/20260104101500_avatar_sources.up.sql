@@ -0,0 +1,17 @@
+-- Avatar sources: uploaded file, Gravatar, or an admin-provided stock gallery image.
+-- Uploads and gallery picks are both stored through user_avatars; Gravatar needs no row.
+
+ALTER TABLE users
+    ADD COLUMN avatar_source VARCHAR(16) NOT NULL DEFAULT 'upload'
+    CHECK (avatar_source IN ('upload', 'gravatar', 'gallery'));
+
+CREATE TABLE IF NOT EXISTS avatar_gallery (
+    id SERIAL PRIMARY KEY,
+    attachment_id INT NOT NULL REFERENCES attachments(id) ON DELETE CASCADE,
+    label VARCHAR(100) NOT NULL,
+    display_order INT NOT NULL DEFAULT 0,
+    is_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_avatar_gallery_order ON avatar_gallery(display_order) WHERE is_enabled = TRUE;
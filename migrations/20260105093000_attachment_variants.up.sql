@@ -0,0 +1,11 @@
+-- Re-encoded copies of image attachments (WebP, AVIF) served through content negotiation.
+-- Each variant is an attachment of its own; the original stays untouched.
+
+CREATE TABLE IF NOT EXISTS attachment_variants (
+    attachment_id INT NOT NULL REFERENCES attachments(id) ON DELETE CASCADE,
+    variant_id INT NOT NULL REFERENCES attachments(id) ON DELETE CASCADE,
+    mime TEXT NOT NULL,
+    PRIMARY KEY (attachment_id, mime)
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachment_variants_variant ON attachment_variants(variant_id);
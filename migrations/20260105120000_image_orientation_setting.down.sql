@@ -0,0 +1,2 @@
+-- Remove image metadata setting
+DELETE FROM settings WHERE key = 'preserve_image_orientation';
@@ -0,0 +1,5 @@
+-- Add image metadata setting
+INSERT INTO settings (key, value, value_type, description, category, is_public)
+VALUES
+    ('preserve_image_orientation', 'true', 'bool', 'Rotate uploaded images to match their EXIF orientation before the metadata is stripped', 'storage', false)
+ON CONFLICT (key) DO NOTHING;
@@ -0,0 +1,14 @@
+-- Background transcoding of uploaded videos into streamable renditions.
+-- Renditions are linked through attachment_variants and the poster frame
+-- through attachment_thumbnails.
+CREATE TABLE video_transcodes (
+    attachment_id INTEGER PRIMARY KEY REFERENCES attachments(id) ON DELETE CASCADE,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    duration_ms INTEGER,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_video_transcodes_pending ON video_transcodes(created_at) WHERE status = 'pending';
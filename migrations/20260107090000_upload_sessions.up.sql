@@ -0,0 +1,26 @@
+-- Chunked, resumable uploads. A session tracks a multipart upload in the
+-- storage backend until its parts are joined into an attachment.
+CREATE TABLE upload_sessions (
+    id VARCHAR(36) PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    filename TEXT NOT NULL,
+    mime VARCHAR(255) NOT NULL,
+    filesize BIGINT NOT NULL,
+    hash VARCHAR(64) NOT NULL,
+    storage_key TEXT NOT NULL,
+    storage_upload_id TEXT NOT NULL,
+    chunk_size INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_upload_sessions_user ON upload_sessions(user_id);
+CREATE INDEX idx_upload_sessions_expires ON upload_sessions(expires_at);
+
+CREATE TABLE upload_session_parts (
+    upload_id VARCHAR(36) NOT NULL REFERENCES upload_sessions(id) ON DELETE CASCADE,
+    part_number INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    e_tag TEXT NOT NULL,
+    PRIMARY KEY (upload_id, part_number)
+);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS user_uploads;
+ALTER TABLE groups DROP COLUMN IF EXISTS storage_quota_mb;
@@ -0,0 +1,21 @@
+-- Attachment storage quotas, set per group in megabytes. NULL is unlimited;
+-- members of several groups get the most generous quota among them.
+ALTER TABLE groups ADD COLUMN storage_quota_mb INTEGER;
+
+-- Attachments each user has uploaded. A file counts once per uploader, even
+-- when it was already stored for someone else.
+CREATE TABLE user_uploads (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    attachment_id INTEGER NOT NULL REFERENCES attachments(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, attachment_id)
+);
+
+CREATE INDEX idx_user_uploads_attachment ON user_uploads(attachment_id);
+
+-- Existing post attachments count toward their authors' usage.
+INSERT INTO user_uploads (user_id, attachment_id, created_at)
+SELECT user_id, attachment_id, MIN(created_at)
+FROM ugc_attachments
+WHERE user_id IS NOT NULL
+GROUP BY user_id, attachment_id;
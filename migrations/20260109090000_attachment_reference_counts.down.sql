@@ -0,0 +1,12 @@
+DROP TRIGGER IF EXISTS trigger_attachment_variants_reference_count ON attachment_variants;
+DROP TRIGGER IF EXISTS trigger_attachment_thumbnails_reference_count ON attachment_thumbnails;
+DROP TRIGGER IF EXISTS trigger_forums_icon_new_reference_count ON forums;
+DROP TRIGGER IF EXISTS trigger_forums_icon_reference_count ON forums;
+DROP TRIGGER IF EXISTS trigger_reaction_types_reference_count ON reaction_types;
+DROP TRIGGER IF EXISTS trigger_avatar_gallery_reference_count ON avatar_gallery;
+DROP TRIGGER IF EXISTS trigger_user_avatars_reference_count ON user_avatars;
+DROP TRIGGER IF EXISTS trigger_ugc_attachments_reference_count ON ugc_attachments;
+DROP FUNCTION IF EXISTS update_attachment_reference_count();
+DROP INDEX IF EXISTS idx_attachments_unreferenced;
+DROP INDEX IF EXISTS idx_attachments_hash;
+ALTER TABLE attachments DROP COLUMN IF EXISTS reference_count;
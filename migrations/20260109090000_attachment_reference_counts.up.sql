@@ -0,0 +1,82 @@
+-- Content-addressed deduplication: each stored file is one attachments row,
+-- and reference_count tracks how many places use it. Files with no
+-- references left are removed from storage by the cleanup task.
+ALTER TABLE attachments ADD COLUMN reference_count INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_attachments_hash ON attachments(hash);
+CREATE INDEX idx_attachments_unreferenced ON attachments(last_seen_at) WHERE reference_count = 0;
+
+-- Maintains attachments.reference_count for the column named by the first
+-- trigger argument. Thumbnails and variants count as references to the
+-- derived file, so they live as long as the original does.
+CREATE OR REPLACE FUNCTION update_attachment_reference_count()
+RETURNS TRIGGER AS $$
+DECLARE
+    old_id INTEGER;
+    new_id INTEGER;
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        old_id := (to_jsonb(OLD) ->> TG_ARGV[0])::INTEGER;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        new_id := (to_jsonb(NEW) ->> TG_ARGV[0])::INTEGER;
+    END IF;
+
+    IF old_id IS NOT DISTINCT FROM new_id THEN
+        RETURN NULL;
+    END IF;
+
+    IF old_id IS NOT NULL THEN
+        UPDATE attachments SET reference_count = GREATEST(reference_count - 1, 0) WHERE id = old_id;
+    END IF;
+    IF new_id IS NOT NULL THEN
+        UPDATE attachments SET reference_count = reference_count + 1 WHERE id = new_id;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_ugc_attachments_reference_count
+AFTER INSERT OR UPDATE OF attachment_id OR DELETE ON ugc_attachments
+FOR EACH ROW EXECUTE FUNCTION update_attachment_reference_count('attachment_id');
+
+CREATE TRIGGER trigger_user_avatars_reference_count
+AFTER INSERT OR UPDATE OF attachment_id OR DELETE ON user_avatars
+FOR EACH ROW EXECUTE FUNCTION update_attachment_reference_count('attachment_id');
+
+CREATE TRIGGER trigger_avatar_gallery_reference_count
+AFTER INSERT OR UPDATE OF attachment_id OR DELETE ON avatar_gallery
+FOR EACH ROW EXECUTE FUNCTION update_attachment_reference_count('attachment_id');
+
+CREATE TRIGGER trigger_reaction_types_reference_count
+AFTER INSERT OR UPDATE OF attachment_id OR DELETE ON reaction_types
+FOR EACH ROW EXECUTE FUNCTION update_attachment_reference_count('attachment_id');
+
+CREATE TRIGGER trigger_forums_icon_reference_count
+AFTER INSERT OR UPDATE OF icon_attachment_id OR DELETE ON forums
+FOR EACH ROW EXECUTE FUNCTION update_attachment_reference_count('icon_attachment_id');
+
+CREATE TRIGGER trigger_forums_icon_new_reference_count
+AFTER INSERT OR UPDATE OF icon_new_attachment_id OR DELETE ON forums
+FOR EACH ROW EXECUTE FUNCTION update_attachment_reference_count('icon_new_attachment_id');
+
+CREATE TRIGGER trigger_attachment_thumbnails_reference_count
+AFTER INSERT OR UPDATE OF thumbnail_id OR DELETE ON attachment_thumbnails
+FOR EACH ROW EXECUTE FUNCTION update_attachment_reference_count('thumbnail_id');
+
+CREATE TRIGGER trigger_attachment_variants_reference_count
+AFTER INSERT OR UPDATE OF variant_id OR DELETE ON attachment_variants
+FOR EACH ROW EXECUTE FUNCTION update_attachment_reference_count('variant_id');
+
+-- Backfill existing references
+UPDATE attachments a SET reference_count = (
+    (SELECT COUNT(*) FROM ugc_attachments WHERE attachment_id = a.id) +
+    (SELECT COUNT(*) FROM user_avatars WHERE attachment_id = a.id) +
+    (SELECT COUNT(*) FROM avatar_gallery WHERE attachment_id = a.id) +
+    (SELECT COUNT(*) FROM reaction_types WHERE attachment_id = a.id) +
+    (SELECT COUNT(*) FROM forums WHERE icon_attachment_id = a.id) +
+    (SELECT COUNT(*) FROM forums WHERE icon_new_attachment_id = a.id) +
+    (SELECT COUNT(*) FROM attachment_thumbnails WHERE thumbnail_id = a.id) +
+    (SELECT COUNT(*) FROM attachment_variants WHERE variant_id = a.id)
+);
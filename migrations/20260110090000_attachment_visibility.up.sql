@@ -0,0 +1,28 @@
+-- Who may fetch an attachment: anyone ('public'), signed-in users
+-- ('members'), or only participants of the conversations it was sent in
+-- ('conversation'). Restricted files are served through signed, expiring URLs.
+-- Thumbnails and other renditions carry the visibility of their original.
+ALTER TABLE attachments ADD COLUMN visibility VARCHAR(16) NOT NULL DEFAULT 'public';
+
+UPDATE attachments a SET visibility = 'conversation'
+WHERE EXISTS (
+    SELECT 1 FROM ugc_attachments ua
+    JOIN private_messages pm ON pm.ugc_id = ua.ugc_id
+    WHERE ua.attachment_id = a.id
+)
+AND a.reference_count = (
+    SELECT COUNT(*) FROM ugc_attachments ua
+    JOIN private_messages pm ON pm.ugc_id = ua.ugc_id
+    WHERE ua.attachment_id = a.id
+);
+
+UPDATE attachments d SET visibility = 'conversation'
+WHERE d.id IN (
+    SELECT at.thumbnail_id FROM attachment_thumbnails at
+    JOIN attachments a ON a.id = at.attachment_id
+    WHERE a.visibility = 'conversation'
+    UNION
+    SELECT av.variant_id FROM attachment_variants av
+    JOIN attachments a ON a.id = av.attachment_id
+    WHERE a.visibility = 'conversation'
+);
@@ -0,0 +1,9 @@
+-- Antivirus scan verdicts for uploads. Files that fail the scan are
+-- quarantined: their data is removed from storage and the row is kept so the
+-- same file is refused if it is uploaded again.
+ALTER TABLE attachments
+    ADD COLUMN scan_status VARCHAR(16) NOT NULL DEFAULT 'unscanned',
+    ADD COLUMN scan_result TEXT,
+    ADD COLUMN scanned_at TIMESTAMP;
+
+CREATE INDEX idx_attachments_scan_pending ON attachments(id) WHERE scan_status = 'pending';
@@ -0,0 +1,4 @@
+-- Remove attachment browser permission
+
+DELETE FROM permission_values WHERE permission_id = 49;
+DELETE FROM permissions WHERE id = 49;
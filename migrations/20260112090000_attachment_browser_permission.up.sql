@@ -0,0 +1,11 @@
+-- Add attachment browser permission
+
+INSERT INTO permissions (id, category_id, label, sort) VALUES
+    (49, 2, 'moderate.attachments.manage', 75)  -- Browse and delete uploaded files
+ON CONFLICT (id) DO NOTHING;
+
+-- Grant to Moderators (collection_id 3) and Administrators (collection_id 4)
+INSERT INTO permission_values (permission_id, collection_id, value) VALUES
+    (49, 3, 'yes'),
+    (49, 4, 'yes')
+ON CONFLICT (permission_id, collection_id) DO NOTHING;
@@ -0,0 +1,2 @@
+DELETE FROM private_messages WHERE kind <> 'message';
+ALTER TABLE private_messages DROP COLUMN kind;
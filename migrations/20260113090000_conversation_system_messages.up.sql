@@ -0,0 +1,2 @@
+-- System messages record participant changes in the conversation itself
+ALTER TABLE private_messages ADD COLUMN kind VARCHAR(16) NOT NULL DEFAULT 'message';
@@ -0,0 +1,9 @@
+ALTER TABLE conversation_participants ADD COLUMN last_read_at TIMESTAMP;
+
+UPDATE conversation_participants cp
+SET last_read_at = (
+    SELECT pm.created_at FROM private_messages pm WHERE pm.id = cp.last_read_message_id
+)
+WHERE cp.last_read_message_id IS NOT NULL;
+
+ALTER TABLE conversation_participants DROP COLUMN last_read_message_id;
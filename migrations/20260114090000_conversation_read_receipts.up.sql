@@ -0,0 +1,14 @@
+-- Track reading per message: each participant has read every message up to
+-- and including last_read_message_id.
+ALTER TABLE conversation_participants ADD COLUMN last_read_message_id INT;
+
+UPDATE conversation_participants cp
+SET last_read_message_id = (
+    SELECT MAX(pm.id)
+    FROM private_messages pm
+    WHERE pm.conversation_id = cp.conversation_id
+      AND pm.created_at <= cp.last_read_at
+)
+WHERE cp.last_read_at IS NOT NULL;
+
+ALTER TABLE conversation_participants DROP COLUMN last_read_at;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS notification_quiet_hours;
+ALTER TABLE conversation_participants DROP COLUMN IF EXISTS is_muted;
@@ -0,0 +1,13 @@
+-- Muted conversations still track unread messages but raise no notifications.
+ALTER TABLE conversation_participants ADD COLUMN is_muted BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Daily quiet hours, in minutes after local midnight. The window wraps past
+-- midnight when start_minute > end_minute. Local time is UTC plus
+-- utc_offset_minutes.
+CREATE TABLE notification_quiet_hours (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    start_minute SMALLINT NOT NULL CHECK (start_minute BETWEEN 0 AND 1439),
+    end_minute SMALLINT NOT NULL CHECK (end_minute BETWEEN 0 AND 1439),
+    utc_offset_minutes SMALLINT NOT NULL DEFAULT 0 CHECK (utc_offset_minutes BETWEEN -720 AND 840)
+);
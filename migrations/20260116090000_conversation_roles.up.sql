@@ -0,0 +1,8 @@
+-- Per-conversation roles. The owner can rename the conversation and invite or
+-- remove participants; conversations.creator_id keeps who started it.
+ALTER TABLE conversation_participants ADD COLUMN role VARCHAR(16) NOT NULL DEFAULT 'member';
+
+UPDATE conversation_participants cp
+SET role = 'owner'
+FROM conversations c
+WHERE c.id = cp.conversation_id AND c.creator_id = cp.user_id;
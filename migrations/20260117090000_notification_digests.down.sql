@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_notifications_pending_email;
+DROP TABLE IF EXISTS notification_digests;
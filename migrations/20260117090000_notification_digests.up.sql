@@ -0,0 +1,10 @@
+-- When each digest was last sent, so a scheduler restart can't send the same
+-- period twice. One row per user and digest frequency.
+CREATE TABLE notification_digests (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    frequency VARCHAR(16) NOT NULL,
+    last_sent_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (user_id, frequency)
+);
+
+CREATE INDEX idx_notifications_pending_email ON notifications(user_id) WHERE is_emailed = FALSE;
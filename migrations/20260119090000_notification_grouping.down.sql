@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS notification_actors;
+DROP INDEX IF EXISTS idx_notifications_open_group;
+ALTER TABLE notifications DROP COLUMN IF EXISTS actor_count;
+ALTER TABLE notifications DROP COLUMN IF EXISTS group_key;
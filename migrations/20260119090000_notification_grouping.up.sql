@@ -0,0 +1,18 @@
+-- Repeated events on the same target collapse into one unread notification.
+-- group_key identifies the target (e.g. "reaction:ugc:42"); actor_count is
+-- how many different users the notification now covers. created_at moves to
+-- the time of the latest event so updated groups sort to the top.
+ALTER TABLE notifications ADD COLUMN group_key VARCHAR(100);
+ALTER TABLE notifications ADD COLUMN actor_count INTEGER NOT NULL DEFAULT 1;
+
+-- Only one open (unread) notification per group; once read, the next event
+-- starts a new one.
+CREATE UNIQUE INDEX idx_notifications_open_group
+    ON notifications(user_id, group_key)
+    WHERE group_key IS NOT NULL AND is_read = FALSE;
+
+CREATE TABLE notification_actors (
+    notification_id INTEGER NOT NULL REFERENCES notifications(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (notification_id, user_id)
+);
@@ -0,0 +1,2 @@
+-- Per-type switch for browser push notifications
+ALTER TABLE notification_preferences ADD COLUMN push BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,14 @@
+-- Watched forums: notify watchers when a new thread is posted
+CREATE TABLE watched_forums (
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    forum_id INT NOT NULL REFERENCES forums(id) ON DELETE CASCADE,
+    notify_on_thread BOOLEAN NOT NULL DEFAULT TRUE,
+    email_on_thread BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, forum_id)
+);
+
+CREATE INDEX idx_watched_forums_forum ON watched_forums(forum_id);
+
+-- Index for finding users to email when a forum gets a new thread
+CREATE INDEX idx_watched_forums_email ON watched_forums (forum_id) WHERE email_on_thread = true;
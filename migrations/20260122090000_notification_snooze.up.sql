@@ -0,0 +1,8 @@
+-- Snoozed notifications are hidden from the list and the unread count until
+-- snoozed_until passes; the maintenance task then clears it and pushes the
+-- notification again.
+ALTER TABLE notifications ADD COLUMN snoozed_until TIMESTAMP;
+
+CREATE INDEX idx_notifications_snoozed
+    ON notifications(snoozed_until)
+    WHERE snoozed_until IS NOT NULL;
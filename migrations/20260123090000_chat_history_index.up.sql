@@ -0,0 +1,2 @@
+-- Chat scrollback pages through a room by message ID, newest first
+CREATE INDEX idx_chat_messages_room_history ON chat_messages(chat_room_id, id DESC);
@@ -0,0 +1,7 @@
+ALTER TABLE chat_rooms DROP COLUMN IF EXISTS topic;
+
+DELETE FROM permission_collections WHERE id IN (SELECT collection_id FROM chat_room_permissions);
+DROP TABLE IF EXISTS chat_room_permissions;
+
+DELETE FROM permission_values WHERE permission_id IN (50, 51);
+DELETE FROM permissions WHERE id IN (50, 51);
@@ -0,0 +1,29 @@
+-- Chat permissions: joining a room and sending messages in it
+INSERT INTO permissions (id, category_id, label, sort) VALUES
+    (50, 1, 'chat.view', 60),
+    (51, 1, 'chat.post', 70)
+ON CONFLICT (id) DO NOTHING;
+
+-- Grant to Registered Users, Moderators and Administrators
+INSERT INTO permission_values (permission_id, collection_id, value) VALUES
+    (50, 2, 'yes'),
+    (51, 2, 'yes'),
+    (50, 3, 'yes'),
+    (51, 3, 'yes'),
+    (50, 4, 'yes'),
+    (51, 4, 'yes')
+ON CONFLICT (permission_id, collection_id) DO NOTHING;
+
+-- Per-room permission overrides, like forum_permissions
+CREATE TABLE chat_room_permissions
+(
+    chat_room_id integer NOT NULL REFERENCES chat_rooms ( id ) ON DELETE CASCADE,
+    collection_id integer NOT NULL REFERENCES permission_collections ( id ) ON DELETE CASCADE,
+    PRIMARY KEY (chat_room_id, collection_id)
+);
+
+CREATE INDEX ON chat_room_permissions ( chat_room_id );
+CREATE INDEX ON chat_room_permissions ( collection_id );
+
+-- Topic shown to users when they join a room
+ALTER TABLE chat_rooms ADD COLUMN topic TEXT;
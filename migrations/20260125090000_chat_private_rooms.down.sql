@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS chat_room_members;
+
+DELETE FROM chat_rooms WHERE is_private;
+
+ALTER TABLE chat_rooms DROP COLUMN IF EXISTS direct_key;
+ALTER TABLE chat_rooms DROP COLUMN IF EXISTS owner_id;
+ALTER TABLE chat_rooms DROP COLUMN IF EXISTS is_private;
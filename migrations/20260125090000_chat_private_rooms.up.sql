@@ -0,0 +1,18 @@
+-- Private rooms are only visible to their members.
+-- Direct message rooms are private rooms keyed by the pair of users in them.
+ALTER TABLE chat_rooms ADD COLUMN is_private BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE chat_rooms ADD COLUMN owner_id INT REFERENCES users ( id ) ON DELETE SET NULL;
+ALTER TABLE chat_rooms ADD COLUMN direct_key TEXT UNIQUE;
+
+CREATE TABLE chat_room_members
+(
+    chat_room_id integer NOT NULL REFERENCES chat_rooms ( id ) ON DELETE CASCADE,
+    user_id integer NOT NULL REFERENCES users ( id ) ON DELETE CASCADE,
+    invited_by_id integer REFERENCES users ( id ) ON DELETE SET NULL,
+    joined_at timestamp without time zone NOT NULL DEFAULT NOW(),
+    -- Newest message the member has seen, for unread indicators
+    last_read_message_id integer,
+    PRIMARY KEY (chat_room_id, user_id)
+);
+
+CREATE INDEX ON chat_room_members ( user_id );
@@ -0,0 +1,2 @@
+DELETE FROM permission_values WHERE permission_id = 52;
+DELETE FROM permissions WHERE id = 52;
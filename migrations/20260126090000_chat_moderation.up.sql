@@ -0,0 +1,10 @@
+-- Chat moderation: /kick, /ban, /mute and /purge, and deleting others' messages
+INSERT INTO permissions (id, category_id, label, sort) VALUES
+    (52, 2, 'chat.moderate', 80)
+ON CONFLICT (id) DO NOTHING;
+
+-- Grant to Moderators and Administrators
+INSERT INTO permission_values (permission_id, collection_id, value) VALUES
+    (52, 3, 'yes'),
+    (52, 4, 'yes')
+ON CONFLICT (permission_id, collection_id) DO NOTHING;
@@ -0,0 +1 @@
+DELETE FROM settings WHERE key = 'chat_edit_window_seconds';
@@ -0,0 +1,4 @@
+-- Chat reactions are kept in ugc_reactions, like post reactions.
+INSERT INTO settings (key, value, value_type, description, category, is_public) VALUES
+('chat_edit_window_seconds', '300', 'int', 'How long authors can edit their chat messages, in seconds (0 for no limit)', 'chat', FALSE)
+ON CONFLICT (key) DO NOTHING;
@@ -0,0 +1,11 @@
+DELETE FROM settings WHERE key IN (
+    'chat_user_messages_per_10s',
+    'chat_room_messages_per_10s',
+    'chat_duplicate_window_seconds',
+    'chat_new_user_hours',
+    'chat_new_user_max_links'
+);
+
+INSERT INTO settings (key, value, value_type, description, category, is_public) VALUES
+('chat_rate_limit_seconds', '0', 'int', 'Minimum seconds between chat messages per user (0 to disable)', 'chat', FALSE)
+ON CONFLICT (key) DO NOTHING;
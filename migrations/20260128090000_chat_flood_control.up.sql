@@ -0,0 +1,10 @@
+-- Chat flood control replaces the single per-user message interval.
+DELETE FROM settings WHERE key = 'chat_rate_limit_seconds';
+
+INSERT INTO settings (key, value, value_type, description, category, is_public) VALUES
+('chat_user_messages_per_10s', '5', 'int', 'How many chat messages a user can send per 10 seconds (0 to disable)', 'chat', FALSE),
+('chat_room_messages_per_10s', '30', 'int', 'How many chat messages a room takes per 10 seconds (0 to disable)', 'chat', FALSE),
+('chat_duplicate_window_seconds', '30', 'int', 'How long a user cannot repeat a chat message, in seconds (0 to disable)', 'chat', FALSE),
+('chat_new_user_hours', '24', 'int', 'How many hours an account counts as new for chat link limits', 'chat', FALSE),
+('chat_new_user_max_links', '0', 'int', 'How many links new accounts can put in one chat message', 'chat', FALSE)
+ON CONFLICT (key) DO NOTHING;
@@ -0,0 +1 @@
+DELETE FROM settings WHERE key IN ('chat_uploads_enabled', 'chat_upload_mime_types');
@@ -0,0 +1,5 @@
+-- Files shared in chat are stored as attachments on the chat message's UGC.
+INSERT INTO settings (key, value, value_type, description, category, is_public) VALUES
+('chat_uploads_enabled', 'true', 'bool', 'Allow users to share files in chat', 'chat', FALSE),
+('chat_upload_mime_types', 'image/*,video/*,audio/*', 'string', 'Comma-separated MIME types that can be shared in chat (image/* matches all images, * matches anything)', 'chat', FALSE)
+ON CONFLICT (key) DO NOTHING;
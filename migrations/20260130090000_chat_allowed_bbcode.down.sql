@@ -0,0 +1 @@
+DELETE FROM settings WHERE key = 'chat_allowed_bbcode';
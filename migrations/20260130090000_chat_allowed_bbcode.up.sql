@@ -0,0 +1,4 @@
+-- Chat renders a subset of BBCode; tags outside it are shown as typed.
+INSERT INTO settings (key, value, value_type, description, category, is_public) VALUES
+('chat_allowed_bbcode', 'b,i,u,s,color,url,img,thumb,spoiler,code', 'string', 'Comma-separated BBCode tags that render in chat messages (* for all)', 'chat', FALSE)
+ON CONFLICT (key) DO NOTHING;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_user_ignores_ignored;
+DROP TABLE IF EXISTS user_ignores;
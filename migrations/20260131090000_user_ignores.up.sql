@@ -0,0 +1,13 @@
+-- Users a user has chosen to ignore in chat
+CREATE TABLE IF NOT EXISTS user_ignores (
+    -- The user doing the ignoring
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- The user being ignored
+    ignored_user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, ignored_user_id),
+    CONSTRAINT no_self_ignore CHECK (user_id != ignored_user_id)
+);
+
+-- Index for finding who ignores a user
+CREATE INDEX idx_user_ignores_ignored ON user_ignores(ignored_user_id);
@@ -0,0 +1,10 @@
+DROP TRIGGER IF EXISTS search_index_ugc_deletions ON ugc_deletions;
+DROP TRIGGER IF EXISTS search_index_ugc ON ugc;
+DROP TRIGGER IF EXISTS search_index_posts ON posts;
+DROP TRIGGER IF EXISTS search_index_threads ON threads;
+
+DROP FUNCTION IF EXISTS search_index_queue_ugc();
+DROP FUNCTION IF EXISTS search_index_queue_posts();
+DROP FUNCTION IF EXISTS search_index_queue_threads();
+
+DROP TABLE IF EXISTS search_index_queue;
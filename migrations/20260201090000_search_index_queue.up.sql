@@ -0,0 +1,86 @@
+-- Queue of threads and posts whose search documents need updating.
+-- Filled by triggers and drained by the search indexing worker.
+CREATE TABLE search_index_queue (
+    kind VARCHAR(16) NOT NULL,
+    id INT NOT NULL,
+    queued_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (kind, id)
+);
+
+CREATE INDEX idx_search_index_queue_queued_at ON search_index_queue(queued_at);
+
+CREATE OR REPLACE FUNCTION search_index_queue_threads() RETURNS trigger AS $$
+DECLARE
+    changed_thread INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_thread := OLD.id;
+    ELSE
+        changed_thread := NEW.id;
+    END IF;
+
+    INSERT INTO search_index_queue (kind, id) VALUES ('thread', changed_thread)
+        ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+
+    -- Post documents carry the forum and are hidden with their thread.
+    IF TG_OP = 'UPDATE' THEN
+        INSERT INTO search_index_queue (kind, id)
+            SELECT 'post', p.id FROM posts p WHERE p.thread_id = changed_thread
+            ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+    END IF;
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION search_index_queue_posts() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO search_index_queue (kind, id)
+        VALUES ('post', CASE WHEN TG_OP = 'DELETE' THEN OLD.id ELSE NEW.id END)
+        ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION search_index_queue_ugc() RETURNS trigger AS $$
+DECLARE
+    changed_ugc INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_ugc := OLD.id;
+    ELSE
+        changed_ugc := NEW.id;
+    END IF;
+
+    INSERT INTO search_index_queue (kind, id)
+        SELECT 'post', p.id FROM posts p WHERE p.ugc_id = changed_ugc
+        ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER search_index_threads
+    AFTER INSERT OR DELETE OR UPDATE OF title, forum_id, deleted_at, merged_into_id
+    ON threads
+    FOR EACH ROW
+    EXECUTE FUNCTION search_index_queue_threads();
+
+CREATE TRIGGER search_index_posts
+    AFTER INSERT OR DELETE OR UPDATE OF thread_id, moderation_status
+    ON posts
+    FOR EACH ROW
+    EXECUTE FUNCTION search_index_queue_posts();
+
+-- Edits point the UGC at a new revision.
+CREATE TRIGGER search_index_ugc
+    AFTER UPDATE OF ugc_revision_id
+    ON ugc
+    FOR EACH ROW
+    EXECUTE FUNCTION search_index_queue_ugc();
+
+-- Deleting or restoring a post.
+CREATE TRIGGER search_index_ugc_deletions
+    AFTER INSERT OR DELETE
+    ON ugc_deletions
+    FOR EACH ROW
+    EXECUTE FUNCTION search_index_queue_ugc();
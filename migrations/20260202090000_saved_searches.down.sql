@@ -0,0 +1,25 @@
+CREATE OR REPLACE FUNCTION search_index_queue_ugc() RETURNS trigger AS $$
+DECLARE
+    changed_ugc INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_ugc := OLD.id;
+    ELSE
+        changed_ugc := NEW.id;
+    END IF;
+
+    INSERT INTO search_index_queue (kind, id)
+        SELECT 'post', p.id FROM posts p WHERE p.ugc_id = changed_ugc
+        ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS search_index_ugc ON ugc;
+CREATE TRIGGER search_index_ugc
+    AFTER UPDATE OF ugc_revision_id
+    ON ugc
+    FOR EACH ROW
+    EXECUTE FUNCTION search_index_queue_ugc();
+
+DROP TABLE IF EXISTS saved_searches;
@@ -0,0 +1,45 @@
+-- Searches users have saved to run again from their account page
+CREATE TABLE IF NOT EXISTS saved_searches (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    -- Query string of the search page, filters included
+    query TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
+
+-- Reaction counts are a search filter, so reactions change the index too.
+-- A thread is filtered by the reactions on its first post.
+CREATE OR REPLACE FUNCTION search_index_queue_ugc() RETURNS trigger AS $$
+DECLARE
+    changed_ugc INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_ugc := OLD.id;
+    ELSE
+        changed_ugc := NEW.id;
+    END IF;
+
+    INSERT INTO search_index_queue (kind, id)
+        SELECT 'post', p.id FROM posts p WHERE p.ugc_id = changed_ugc
+        ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+    INSERT INTO search_index_queue (kind, id)
+        SELECT 'thread', t.id FROM threads t
+        JOIN posts p ON p.id = t.first_post_id
+        WHERE p.ugc_id = changed_ugc
+        ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS search_index_ugc ON ugc;
+CREATE TRIGGER search_index_ugc
+    AFTER UPDATE OF ugc_revision_id, reaction_count
+    ON ugc
+    FOR EACH ROW
+    EXECUTE FUNCTION search_index_queue_ugc();
+
+-- Documents indexed before now lack reaction counts.
+INSERT INTO search_index_queue (kind, id) SELECT 'thread', id FROM threads ON CONFLICT DO NOTHING;
+INSERT INTO search_index_queue (kind, id) SELECT 'post', id FROM posts ON CONFLICT DO NOTHING;
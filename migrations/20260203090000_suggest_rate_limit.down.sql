@@ -0,0 +1,2 @@
+-- Remove search suggestion rate limit
+DELETE FROM settings WHERE key IN ('rate_limit.suggest.max_requests', 'rate_limit.suggest.window_seconds');
@@ -0,0 +1,5 @@
+-- Rate limit for search-while-you-type suggestions, separate from full search
+INSERT INTO settings (key, value, value_type, description, category, is_public) VALUES
+('rate_limit.suggest.max_requests', '120', 'int', 'Maximum search suggestion requests per window', 'rate_limits', FALSE),
+('rate_limit.suggest.window_seconds', '60', 'int', 'Search suggestion window in seconds (1 min)', 'rate_limits', FALSE)
+ON CONFLICT (key) DO NOTHING;
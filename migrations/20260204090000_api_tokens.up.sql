@@ -0,0 +1,17 @@
+-- Personal access tokens for the JSON API
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    -- BLAKE3 hash of the token; the token itself is only shown once
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- Start of the token, so its owner can tell tokens apart
+    token_prefix VARCHAR(16) NOT NULL,
+    -- Space separated scopes, such as "read notifications:read"
+    scopes TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP,
+    expires_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user_id ON api_tokens(user_id);
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS activitypub_followers;
+DROP TABLE IF EXISTS activitypub_keys;
+
+ALTER TABLE forums
+DROP COLUMN IF EXISTS is_federated;
@@ -0,0 +1,26 @@
+-- ActivityPub publishing. Forums opted in get an actor that Fediverse users
+-- can follow; new public threads are sent to followers as Notes.
+ALTER TABLE forums
+ADD COLUMN is_federated BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN forums.is_federated IS 'Whether the forum publishes new threads over ActivityPub';
+
+-- Key pair each forum actor signs its deliveries with
+CREATE TABLE activitypub_keys (
+    forum_id INT PRIMARY KEY REFERENCES forums(id) ON DELETE CASCADE,
+    public_key_pem TEXT NOT NULL,
+    private_key_pem TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Remote actors following a forum actor
+CREATE TABLE activitypub_followers (
+    id SERIAL PRIMARY KEY,
+    forum_id INT NOT NULL REFERENCES forums(id) ON DELETE CASCADE,
+    actor_uri TEXT NOT NULL,
+    inbox_url TEXT NOT NULL,
+    -- Servers with a shared inbox get one delivery for all their followers
+    shared_inbox_url TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (forum_id, actor_uri)
+);
@@ -0,0 +1,17 @@
+-- Mobile app devices registered for push through FCM (Android) or APNs (iOS).
+-- A device token is unique; if another account signs in on the same device
+-- the registration moves to them.
+CREATE TABLE push_devices (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    platform VARCHAR(8) NOT NULL CHECK (platform IN ('fcm', 'apns')),
+    token TEXT NOT NULL UNIQUE,
+    -- Shown to the user when listing their devices, e.g. "Pixel 8"
+    name VARCHAR(100),
+    -- The user can pause pushes to one device without unregistering it
+    is_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMP
+);
+
+CREATE INDEX idx_push_devices_user ON push_devices(user_id);
@@ -0,0 +1,3 @@
+-- Request that made each moderation action, matching the request_id in the
+-- server logs. NULL for actions taken outside an HTTP request (chat).
+ALTER TABLE mod_log ADD COLUMN request_id VARCHAR(64);
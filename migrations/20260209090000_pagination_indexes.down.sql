@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_private_messages_conversation_created;
+DROP INDEX IF EXISTS idx_threads_forum_listing;
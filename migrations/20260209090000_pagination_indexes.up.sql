@@ -0,0 +1,8 @@
+-- Indexes in the sort order of cursor-paginated lists, so the page after a
+-- cursor is read straight from the index.
+CREATE INDEX idx_threads_forum_listing
+    ON threads (forum_id, is_pinned DESC, COALESCE(last_post_at, created_at) DESC, id DESC)
+    WHERE deleted_at IS NULL AND merged_into_id IS NULL;
+
+CREATE INDEX idx_private_messages_conversation_created
+    ON private_messages (conversation_id, created_at, id);
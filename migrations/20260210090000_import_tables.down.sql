@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS import_progress;
+DROP TABLE IF EXISTS import_id_map;
@@ -0,0 +1,20 @@
+-- Imports from other forum software. Each imported row is mapped from its ID
+-- in the source to the row it became here, so later steps can find the users,
+-- threads and conversations they refer to.
+CREATE TABLE import_id_map (
+    platform VARCHAR(32) NOT NULL,
+    kind VARCHAR(32) NOT NULL,
+    source_id BIGINT NOT NULL,
+    target_id INT NOT NULL,
+    PRIMARY KEY (platform, kind, source_id)
+);
+
+-- Last source ID read by each step, so an interrupted import resumes after
+-- the last batch it finished.
+CREATE TABLE import_progress (
+    platform VARCHAR(32) NOT NULL,
+    step VARCHAR(32) NOT NULL,
+    last_source_id BIGINT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (platform, step)
+);
@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS config_version_word_filters ON word_filters;
+DROP TRIGGER IF EXISTS config_version_feature_flags ON feature_flags;
+DROP TRIGGER IF EXISTS config_version_settings ON settings;
+DROP FUNCTION IF EXISTS bump_config_version();
+DROP TABLE IF EXISTS config_versions;
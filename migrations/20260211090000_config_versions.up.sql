@@ -0,0 +1,37 @@
+-- Version counters for data each process holds in memory. Triggers bump a
+-- counter on every change, however it's made, and every instance polls the
+-- counters to reload what changed.
+CREATE TABLE config_versions (
+    scope VARCHAR(32) PRIMARY KEY,
+    version BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO config_versions (scope) VALUES ('settings'), ('word_filters');
+
+CREATE OR REPLACE FUNCTION bump_config_version() RETURNS trigger AS $$
+BEGIN
+    UPDATE config_versions SET version = version + 1, updated_at = NOW()
+        WHERE scope = TG_ARGV[0];
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER config_version_settings
+    AFTER INSERT OR UPDATE OR DELETE
+    ON settings
+    FOR EACH STATEMENT
+    EXECUTE FUNCTION bump_config_version('settings');
+
+-- Feature flags are loaded with the settings.
+CREATE TRIGGER config_version_feature_flags
+    AFTER INSERT OR UPDATE OR DELETE
+    ON feature_flags
+    FOR EACH STATEMENT
+    EXECUTE FUNCTION bump_config_version('settings');
+
+CREATE TRIGGER config_version_word_filters
+    AFTER INSERT OR UPDATE OR DELETE
+    ON word_filters
+    FOR EACH STATEMENT
+    EXECUTE FUNCTION bump_config_version('word_filters');
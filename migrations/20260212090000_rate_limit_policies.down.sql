@@ -0,0 +1,13 @@
+INSERT INTO settings (key, value, value_type, description, category, is_public)
+SELECT 'rate_limit.' || route_class || '.max_requests', max_requests::TEXT, 'int',
+    'Maximum requests per window', 'rate_limits', FALSE
+FROM rate_limit_policies
+UNION ALL
+SELECT 'rate_limit.' || route_class || '.window_seconds', window_seconds::TEXT, 'int',
+    'Rate limit window in seconds', 'rate_limits', FALSE
+FROM rate_limit_policies
+ON CONFLICT (key) DO NOTHING;
+
+DELETE FROM config_versions WHERE scope = 'rate_limits';
+DROP TABLE IF EXISTS rate_limit_exemptions;
+DROP TABLE IF EXISTS rate_limit_policies;
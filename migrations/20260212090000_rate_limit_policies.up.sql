@@ -0,0 +1,62 @@
+-- Rate limit budgets per route class, edited at /admin/rate-limits.
+-- They replace the rate_limit.* settings, whose values are carried over.
+CREATE TABLE rate_limit_policies (
+    route_class VARCHAR(32) PRIMARY KEY,
+    max_requests INT NOT NULL CHECK (max_requests > 0),
+    window_seconds INT NOT NULL CHECK (window_seconds > 0),
+    -- Requests that may come back to back; after that they are spaced
+    -- evenly over the window. 0 leaves them unspaced.
+    burst INT NOT NULL DEFAULT 0 CHECK (burst >= 0),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_by INT REFERENCES users(id) ON DELETE SET NULL
+);
+
+-- Groups whose members a route class doesn't limit
+CREATE TABLE rate_limit_exemptions (
+    route_class VARCHAR(32) NOT NULL REFERENCES rate_limit_policies(route_class) ON DELETE CASCADE,
+    group_id INT NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    PRIMARY KEY (route_class, group_id)
+);
+
+INSERT INTO rate_limit_policies (route_class, max_requests, window_seconds)
+SELECT c.route_class,
+    COALESCE(
+        (SELECT value::INT FROM settings WHERE key = 'rate_limit.' || c.route_class || '.max_requests'),
+        c.max_requests
+    ),
+    COALESCE(
+        (SELECT value::INT FROM settings WHERE key = 'rate_limit.' || c.route_class || '.window_seconds'),
+        c.window_seconds
+    )
+FROM (VALUES
+    ('login', 5, 300),
+    ('two_factor', 5, 300),
+    ('password_reset', 3, 3600),
+    ('email_verification', 3, 3600),
+    ('registration', 3, 3600),
+    ('post_creation', 10, 60),
+    ('thread_creation', 5, 300),
+    ('search', 30, 60),
+    ('suggest', 120, 60),
+    ('api', 60, 60),
+    ('file_upload', 20, 60),
+    ('report', 5, 300),
+    ('reaction', 30, 60)
+) AS c(route_class, max_requests, window_seconds);
+
+DELETE FROM settings WHERE key LIKE 'rate_limit.%';
+
+-- Instances reload the policies when they change.
+INSERT INTO config_versions (scope) VALUES ('rate_limits');
+
+CREATE TRIGGER config_version_rate_limit_policies
+    AFTER INSERT OR UPDATE OR DELETE
+    ON rate_limit_policies
+    FOR EACH STATEMENT
+    EXECUTE FUNCTION bump_config_version('rate_limits');
+
+CREATE TRIGGER config_version_rate_limit_exemptions
+    AFTER INSERT OR UPDATE OR DELETE
+    ON rate_limit_exemptions
+    FOR EACH STATEMENT
+    EXECUTE FUNCTION bump_config_version('rate_limits');
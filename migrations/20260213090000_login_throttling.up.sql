@@ -0,0 +1,12 @@
+-- When an account last failed to sign in, for progressive delays
+ALTER TABLE users ADD COLUMN last_failed_login_at TIMESTAMP NULL;
+
+-- Failed sign-ins per address, shared by every instance
+CREATE TABLE login_failures (
+    ip_address INET PRIMARY KEY,
+    failures INT NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMP NULL
+);
+
+CREATE INDEX idx_login_failures_last_failed_at ON login_failures(last_failed_at);
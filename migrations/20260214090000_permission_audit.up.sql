@@ -0,0 +1,16 @@
+-- Every change to permission values, group membership and forum or chat
+-- room permissions, with the state before and after
+CREATE TABLE permission_audit (
+    id SERIAL PRIMARY KEY,
+    actor_id INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    change_type VARCHAR(32) NOT NULL,
+    target_id INT NOT NULL,
+    before JSONB NOT NULL DEFAULT '{}',
+    after JSONB NOT NULL DEFAULT '{}',
+    request_id VARCHAR(64) NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_permission_audit_created_at ON permission_audit(created_at DESC);
+CREATE INDEX idx_permission_audit_target ON permission_audit(change_type, target_id);
+CREATE INDEX idx_permission_audit_actor_id ON permission_audit(actor_id);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS permission_collections_expires_at_idx;
+ALTER TABLE permission_collections DROP COLUMN IF EXISTS expires_at;
@@ -0,0 +1,7 @@
+-- Permission collections may lapse, e.g. for a temporary moderator or a
+-- temporary posting restriction. Expired collections are ignored by
+-- permission checks and deleted by the maintenance task.
+ALTER TABLE permission_collections ADD COLUMN expires_at TIMESTAMP;
+
+CREATE INDEX permission_collections_expires_at_idx
+    ON permission_collections ( expires_at ) WHERE expires_at IS NOT NULL;
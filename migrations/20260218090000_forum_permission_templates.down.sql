@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS trigger_forums_default_permission_template ON forums;
+DROP FUNCTION IF EXISTS apply_default_forum_permission_template();
+DROP TABLE IF EXISTS forum_permission_template_values;
+DROP TABLE IF EXISTS forum_permission_templates;
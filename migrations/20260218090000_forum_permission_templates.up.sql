@@ -0,0 +1,57 @@
+-- Saved copies of a forum's per-group permission matrix, for applying to
+-- other forums. The default template is applied to every new forum.
+CREATE TABLE forum_permission_templates (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    created_by INT NULL REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- At most one default template
+CREATE UNIQUE INDEX idx_forum_permission_templates_default
+    ON forum_permission_templates(is_default) WHERE is_default;
+
+CREATE TABLE forum_permission_template_values (
+    template_id INT NOT NULL REFERENCES forum_permission_templates(id) ON DELETE CASCADE,
+    group_id INT NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    permission_id INT NOT NULL REFERENCES permissions(id) ON DELETE CASCADE,
+    value PERMISSION_FLAG NOT NULL,
+    PRIMARY KEY (template_id, group_id, permission_id)
+);
+
+-- Give a new forum one permission collection per group in the default
+-- template, as if its matrix had been saved by hand
+CREATE OR REPLACE FUNCTION apply_default_forum_permission_template()
+RETURNS TRIGGER AS $$
+DECLARE
+    template_group RECORD;
+    new_collection_id INT;
+BEGIN
+    FOR template_group IN
+        SELECT DISTINCT v.template_id, v.group_id
+        FROM forum_permission_template_values v
+        JOIN forum_permission_templates t ON t.id = v.template_id
+        WHERE t.is_default
+    LOOP
+        INSERT INTO permission_collections (group_id)
+        VALUES (template_group.group_id)
+        RETURNING id INTO new_collection_id;
+
+        INSERT INTO forum_permissions (forum_id, collection_id)
+        VALUES (NEW.id, new_collection_id);
+
+        INSERT INTO permission_values (permission_id, collection_id, value)
+        SELECT v.permission_id, new_collection_id, v.value
+        FROM forum_permission_template_values v
+        WHERE v.template_id = template_group.template_id
+            AND v.group_id = template_group.group_id;
+    END LOOP;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_forums_default_permission_template
+AFTER INSERT ON forums
+FOR EACH ROW EXECUTE FUNCTION apply_default_forum_permission_template();
@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS trigger_thread_permissions_delete_collection ON thread_permissions;
+DROP FUNCTION IF EXISTS delete_thread_permission_collection();
+DROP TABLE IF EXISTS thread_permissions;
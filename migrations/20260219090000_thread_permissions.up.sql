@@ -0,0 +1,24 @@
+-- Per-thread permission overrides, applied before those of the thread's
+-- forum and its parents
+CREATE TABLE thread_permissions
+(
+    thread_id integer NOT NULL REFERENCES threads ( id ) ON DELETE CASCADE,
+    collection_id integer NOT NULL REFERENCES permission_collections ( id ) ON DELETE CASCADE,
+    PRIMARY KEY (thread_id, collection_id)
+);
+
+CREATE INDEX ON thread_permissions ( collection_id );
+
+-- A thread's collections only apply to it. Delete them along with the link,
+-- so they don't turn into global permissions when the thread goes.
+CREATE OR REPLACE FUNCTION delete_thread_permission_collection()
+RETURNS TRIGGER AS $$
+BEGIN
+    DELETE FROM permission_collections WHERE id = OLD.collection_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_thread_permissions_delete_collection
+AFTER DELETE ON thread_permissions
+FOR EACH ROW EXECUTE FUNCTION delete_thread_permission_collection();
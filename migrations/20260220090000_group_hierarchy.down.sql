@@ -0,0 +1,6 @@
+DELETE FROM settings WHERE key = 'permission_priority_wins';
+ALTER TABLE groups
+    DROP COLUMN IF EXISTS banner_text,
+    DROP COLUMN IF EXISTS username_color,
+    DROP COLUMN IF EXISTS parent_id,
+    DROP COLUMN IF EXISTS display_priority;
@@ -0,0 +1,13 @@
+-- The highest priority of a member's groups styles their name. Members of a
+-- group with a parent are members of the parent too.
+ALTER TABLE groups
+    ADD COLUMN display_priority INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN parent_id INTEGER REFERENCES groups ( id ) ON DELETE SET NULL,
+    ADD COLUMN username_color VARCHAR(7),
+    ADD COLUMN banner_text VARCHAR(50);
+
+CREATE INDEX ON groups ( parent_id );
+
+INSERT INTO settings (key, value, value_type, description, category, is_public) VALUES
+('permission_priority_wins', 'false', 'bool', 'Take each permission from the highest priority group that sets it, instead of combining all of a member''s groups', 'moderation', FALSE)
+ON CONFLICT (key) DO NOTHING;
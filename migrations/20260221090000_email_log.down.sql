@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS email_log_attempts;
+DROP TABLE IF EXISTS email_log;
@@ -0,0 +1,28 @@
+-- Outgoing emails. Each one is sent by a job from the queue; the log keeps
+-- who it went to and how each attempt went, but not the body, which can
+-- carry password reset and verification tokens.
+CREATE TABLE email_log (
+    id SERIAL PRIMARY KEY,
+    recipient VARCHAR(255) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    -- pending, sent, retrying or failed
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_attempt_at TIMESTAMP,
+    sent_at TIMESTAMP
+);
+
+CREATE INDEX idx_email_log_created_at ON email_log(created_at DESC);
+
+CREATE TABLE email_log_attempts (
+    id SERIAL PRIMARY KEY,
+    email_id INT NOT NULL REFERENCES email_log(id) ON DELETE CASCADE,
+    attempt INT NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    -- What the SMTP server answered, or why the message could not be sent
+    response TEXT NOT NULL,
+    attempted_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_log_attempts_email ON email_log_attempts(email_id, attempt);
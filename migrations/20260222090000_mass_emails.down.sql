@@ -0,0 +1,3 @@
+ALTER TABLE email_log DROP COLUMN IF EXISTS mass_email_id;
+DROP TABLE IF EXISTS mass_emails;
+ALTER TABLE users DROP COLUMN IF EXISTS email_announcements;
@@ -0,0 +1,32 @@
+-- Whether a member wants announcements sent to everyone by email
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_announcements BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Announcements emailed to a filtered set of members. Recipients are queued
+-- in batches, in user id order, so sending can pick up where it left off.
+CREATE TABLE mass_emails (
+    id SERIAL PRIMARY KEY,
+    subject VARCHAR(255) NOT NULL,
+    -- BBCode
+    body TEXT NOT NULL,
+    -- Audience. No foreign key on the group: if it is deleted the remaining
+    -- batches find no members instead of widening to everyone.
+    group_id INT,
+    active_within_days INT,
+    opted_in_only BOOLEAN NOT NULL DEFAULT TRUE,
+    -- sending, done or cancelled
+    status VARCHAR(16) NOT NULL DEFAULT 'sending',
+    total_recipients INT NOT NULL DEFAULT 0,
+    queued_count INT NOT NULL DEFAULT 0,
+    -- Highest user id queued so far
+    last_user_id INT NOT NULL DEFAULT 0,
+    created_by INT REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMP
+);
+
+CREATE INDEX idx_mass_emails_created_at ON mass_emails(created_at DESC);
+
+ALTER TABLE email_log ADD COLUMN mass_email_id INT REFERENCES mass_emails(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_email_log_mass_email ON email_log(mass_email_id, status)
+    WHERE mass_email_id IS NOT NULL;
@@ -0,0 +1,22 @@
+-- Requests to change a member's email address. The new address takes effect
+-- once confirmed from a link sent to it; the old address gets a link that
+-- cancels the request or, once confirmed, puts the old address back.
+CREATE TABLE email_changes (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    old_email VARCHAR(255),
+    new_email VARCHAR(255) NOT NULL,
+    -- Sent to the new address
+    confirm_token VARCHAR(64) NOT NULL UNIQUE,
+    -- Sent to the old address
+    revert_token VARCHAR(64) NOT NULL UNIQUE,
+    -- pending, confirmed, cancelled or reverted
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP NOT NULL,
+    confirmed_at TIMESTAMP,
+    reverted_at TIMESTAMP
+);
+
+CREATE INDEX idx_email_changes_user ON email_changes(user_id, status);
+CREATE INDEX idx_email_changes_created_at ON email_changes(created_at DESC);
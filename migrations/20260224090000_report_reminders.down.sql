@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_reports_status_created_at;
+ALTER TABLE reports DROP COLUMN IF EXISTS reminded_at;
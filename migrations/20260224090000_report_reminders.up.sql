@@ -0,0 +1,5 @@
+-- When moderators were last reminded about a report left open past the
+-- response time
+ALTER TABLE reports ADD COLUMN reminded_at TIMESTAMP;
+
+CREATE INDEX idx_reports_status_created_at ON reports(status, created_at);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS notice_dismissals;
+DROP TABLE IF EXISTS notices;
@@ -0,0 +1,34 @@
+-- Notices shown at the top of pages: across the whole site, or in one
+-- forum. Bodies are BBCode. A notice can be scheduled to start and end, and
+-- critical ones can ask each member to acknowledge them.
+CREATE TABLE notices (
+    id SERIAL PRIMARY KEY,
+    title VARCHAR(255) NOT NULL,
+    body TEXT NOT NULL,
+    -- info, warning or critical
+    style VARCHAR(16) NOT NULL DEFAULT 'info',
+    -- NULL for a site-wide banner
+    forum_id INT REFERENCES forums(id) ON DELETE CASCADE,
+    starts_at TIMESTAMP,
+    ends_at TIMESTAMP,
+    is_dismissible BOOLEAN NOT NULL DEFAULT TRUE,
+    requires_acknowledgment BOOLEAN NOT NULL DEFAULT FALSE,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by INT REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_notices_forum ON notices(forum_id);
+
+-- Members who dismissed or acknowledged a notice, so it isn't shown to them
+-- again
+CREATE TABLE notice_dismissals (
+    notice_id INT NOT NULL REFERENCES notices(id) ON DELETE CASCADE,
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    acknowledged BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (notice_id, user_id)
+);
+
+CREATE INDEX idx_notice_dismissals_user ON notice_dismissals(user_id);
@@ -0,0 +1,4 @@
+ALTER TABLE polls ADD COLUMN show_results_before_vote BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE polls SET show_results_before_vote = TRUE WHERE results_visibility = 'always';
+ALTER TABLE polls DROP COLUMN results_visibility;
+ALTER TABLE polls DROP COLUMN public_votes;
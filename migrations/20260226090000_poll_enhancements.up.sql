@@ -0,0 +1,14 @@
+-- Polls: public voter lists, and results that can stay hidden until the poll
+-- closes as well as until voting.
+ALTER TABLE polls ADD COLUMN public_votes BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- always, after_vote or after_close
+ALTER TABLE polls ADD COLUMN results_visibility VARCHAR(16) NOT NULL DEFAULT 'after_vote';
+UPDATE polls SET results_visibility = 'always' WHERE show_results_before_vote;
+ALTER TABLE polls DROP COLUMN show_results_before_vote;
+
+-- Votes were counted by the voting handler as well as by
+-- trigger_poll_vote_count. The trigger alone keeps the counts now; recount
+-- the doubled ones.
+UPDATE poll_options o
+SET vote_count = (SELECT COUNT(*) FROM poll_votes v WHERE v.option_id = o.id);
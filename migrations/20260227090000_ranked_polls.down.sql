@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS poll_runoff_results;
+ALTER TABLE poll_votes DROP COLUMN IF EXISTS rank;
+ALTER TABLE polls DROP COLUMN IF EXISTS poll_type;
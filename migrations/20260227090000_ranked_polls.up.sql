@@ -0,0 +1,18 @@
+-- Ranked polls: voters order the options and the winner is found by
+-- instant runoff.
+
+-- choice or ranked
+ALTER TABLE polls ADD COLUMN poll_type VARCHAR(16) NOT NULL DEFAULT 'choice';
+
+-- Position of the option on a ranked ballot, 1 for the first preference
+ALTER TABLE poll_votes ADD COLUMN rank INT NULL;
+
+-- The count of a ranked poll, taken once it closes, so its result stays the
+-- same when ballots are later removed with their voters.
+CREATE TABLE poll_runoff_results (
+    poll_id INT PRIMARY KEY REFERENCES polls(id) ON DELETE CASCADE,
+    rounds JSONB NOT NULL,
+    winner_option_id INT NULL REFERENCES poll_options(id) ON DELETE SET NULL,
+    ballot_count INT NOT NULL,
+    tabulated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
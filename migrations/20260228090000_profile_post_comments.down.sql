@@ -0,0 +1,8 @@
+DELETE FROM permission_values WHERE permission_id = 53;
+DELETE FROM permissions WHERE id = 53;
+
+DROP TABLE IF EXISTS profile_post_comments;
+
+ALTER TABLE users ADD COLUMN allow_profile_posts BOOLEAN NOT NULL DEFAULT TRUE;
+UPDATE users SET allow_profile_posts = FALSE WHERE profile_post_privacy = 'nobody';
+ALTER TABLE users DROP COLUMN profile_post_privacy;
@@ -0,0 +1,33 @@
+-- Profile wall comments, a choice of who may post on a wall, and a
+-- permission for moderating wall content
+
+-- Who may post on a member's wall: 'everyone', 'following' (members they
+-- follow) or 'nobody'. The owner may always post on their own wall.
+ALTER TABLE users ADD COLUMN profile_post_privacy VARCHAR(16) NOT NULL DEFAULT 'everyone'
+    CHECK (profile_post_privacy IN ('everyone', 'following', 'nobody'));
+UPDATE users SET profile_post_privacy = 'nobody' WHERE NOT allow_profile_posts;
+ALTER TABLE users DROP COLUMN allow_profile_posts;
+
+-- Comments on profile wall posts
+CREATE TABLE profile_post_comments (
+    id SERIAL PRIMARY KEY,
+    profile_post_id INT NOT NULL REFERENCES profile_posts(id) ON DELETE CASCADE,
+    -- NULL if the author was deleted
+    author_id INT REFERENCES users(id) ON DELETE SET NULL,
+    ugc_id INT NOT NULL REFERENCES ugc(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_profile_post_comments_post ON profile_post_comments(profile_post_id, created_at);
+CREATE INDEX idx_profile_post_comments_author ON profile_post_comments(author_id);
+
+-- Delete anyone's profile posts and comments
+INSERT INTO permissions (id, category_id, label, sort) VALUES
+    (53, 2, 'moderate.profile_posts.manage', 90)
+ON CONFLICT (id) DO NOTHING;
+
+-- Grant to Moderators and Administrators
+INSERT INTO permission_values (permission_id, collection_id, value) VALUES
+    (53, 3, 'yes'),
+    (53, 4, 'yes')
+ON CONFLICT (permission_id, collection_id) DO NOTHING;
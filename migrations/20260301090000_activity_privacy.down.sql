@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_activities_type_created;
+DROP TABLE IF EXISTS user_hidden_activity_types;
@@ -0,0 +1,10 @@
+-- Activity types a member keeps off the activity feeds. Their own activity
+-- page still shows them everything.
+CREATE TABLE user_hidden_activity_types (
+    user_id INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    activity_type activity_type NOT NULL,
+    PRIMARY KEY (user_id, activity_type)
+);
+
+-- Feeds filtered by type
+CREATE INDEX idx_activities_type_created ON activities(activity_type, created_at DESC);
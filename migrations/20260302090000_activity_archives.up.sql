@@ -0,0 +1,10 @@
+-- Activities past the retention horizon, in chunks of a day's rows. Each
+-- chunk is kept as a JSON array, which Postgres stores compressed.
+CREATE TABLE activity_archives (
+    day DATE NOT NULL,
+    chunk INT NOT NULL,
+    activity_count INT NOT NULL,
+    activities JSONB NOT NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (day, chunk)
+);
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_unfurl_cache_expires_at;
+
+ALTER TABLE unfurl_cache
+    DROP COLUMN IF EXISTS expires_at,
+    DROP COLUMN IF EXISTS embed_type,
+    DROP COLUMN IF EXISTS author_name;
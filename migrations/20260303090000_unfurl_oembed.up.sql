@@ -0,0 +1,10 @@
+-- oEmbed details for link previews, and a per-entry expiry so failures and
+-- providers asking for a shorter cache age are refetched sooner
+ALTER TABLE unfurl_cache
+    ADD COLUMN author_name TEXT,
+    ADD COLUMN embed_type VARCHAR(16),
+    ADD COLUMN expires_at TIMESTAMP NOT NULL DEFAULT NOW();
+
+UPDATE unfurl_cache SET expires_at = fetched_at + INTERVAL '24 hours';
+
+CREATE INDEX idx_unfurl_cache_expires_at ON unfurl_cache(expires_at);
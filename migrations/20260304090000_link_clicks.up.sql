@@ -0,0 +1,8 @@
+-- Clicks on links to other sites, counted per thread for its author
+CREATE TABLE link_clicks (
+    thread_id INT NOT NULL REFERENCES threads(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    clicks INT NOT NULL DEFAULT 0,
+    last_clicked_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (thread_id, url)
+);
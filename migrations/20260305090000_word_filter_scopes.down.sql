@@ -0,0 +1,6 @@
+ALTER TABLE word_filters
+    DROP COLUMN IF EXISTS applies_to_signatures,
+    DROP COLUMN IF EXISTS applies_to_chat,
+    DROP COLUMN IF EXISTS applies_to_usernames,
+    DROP COLUMN IF EXISTS applies_to_titles,
+    DROP COLUMN IF EXISTS applies_to_posts;
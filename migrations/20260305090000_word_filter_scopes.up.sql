@@ -0,0 +1,8 @@
+-- Which kinds of content each word filter applies to. Existing filters keep
+-- applying where they already did: posts, thread titles and chat.
+ALTER TABLE word_filters
+    ADD COLUMN applies_to_posts BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN applies_to_titles BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN applies_to_usernames BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN applies_to_chat BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN applies_to_signatures BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS theme_revisions;
+ALTER TABLE themes DROP COLUMN IF EXISTS version;
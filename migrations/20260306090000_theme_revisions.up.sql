@@ -0,0 +1,17 @@
+-- Versioned saves of each theme's CSS, so earlier looks can be restored
+ALTER TABLE themes ADD COLUMN version INT NOT NULL DEFAULT 1;
+
+CREATE TABLE theme_revisions (
+    id SERIAL PRIMARY KEY,
+    theme_id INT NOT NULL REFERENCES themes(id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    css_variables TEXT,
+    css_custom TEXT,
+    created_by INT REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (theme_id, version)
+);
+
+-- Existing themes start at version 1
+INSERT INTO theme_revisions (theme_id, version, css_variables, css_custom, created_by, created_at)
+SELECT id, 1, css_variables, css_custom, created_by, updated_at FROM themes;
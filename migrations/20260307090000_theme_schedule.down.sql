@@ -0,0 +1,5 @@
+ALTER TABLE users
+    DROP COLUMN IF EXISTS theme_dark,
+    DROP COLUMN IF EXISTS theme_schedule,
+    DROP COLUMN IF EXISTS theme_dark_start,
+    DROP COLUMN IF EXISTS theme_dark_end;
@@ -0,0 +1,7 @@
+-- Auto theme mode switches between a chosen light and dark theme, following
+-- the system color scheme or, when scheduled, the time of day
+ALTER TABLE users
+    ADD COLUMN theme_dark VARCHAR(50),
+    ADD COLUMN theme_schedule BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN theme_dark_start SMALLINT NOT NULL DEFAULT 1200,
+    ADD COLUMN theme_dark_end SMALLINT NOT NULL DEFAULT 420;
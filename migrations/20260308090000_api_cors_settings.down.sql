@@ -0,0 +1 @@
+DELETE FROM settings WHERE key IN ('api_cors_allowed_origins', 'api_cors_allow_credentials', 'api_cors_max_age_seconds');
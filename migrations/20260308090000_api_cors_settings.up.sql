@@ -0,0 +1,6 @@
+-- Cross-origin access to the JSON API for browser-based clients.
+INSERT INTO settings (key, value, value_type, description, category, is_public) VALUES
+('api_cors_allowed_origins', '', 'string', 'Comma-separated origins allowed to call the API from a browser, such as https://app.example.com (* allows any, empty allows none)', 'api', FALSE),
+('api_cors_allow_credentials', 'false', 'bool', 'Let allowed origins send cookies with API requests (never sent when any origin is allowed)', 'api', FALSE),
+('api_cors_max_age_seconds', '3600', 'int', 'How long browsers may cache an API preflight response, in seconds', 'api', FALSE)
+ON CONFLICT (key) DO NOTHING;
@@ -0,0 +1,67 @@
+-- Back to search_index_queue, taking over the indexing jobs still waiting.
+CREATE TABLE search_index_queue (
+    kind VARCHAR(16) NOT NULL,
+    id INT NOT NULL,
+    queued_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (kind, id)
+);
+
+CREATE INDEX idx_search_index_queue_queued_at ON search_index_queue(queued_at);
+
+CREATE OR REPLACE FUNCTION search_index_queue_threads() RETURNS trigger AS $$
+DECLARE
+    changed_thread INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_thread := OLD.id;
+    ELSE
+        changed_thread := NEW.id;
+    END IF;
+
+    INSERT INTO search_index_queue (kind, id) VALUES ('thread', changed_thread)
+        ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+
+    -- Post documents carry the forum and are hidden with their thread.
+    IF TG_OP = 'UPDATE' THEN
+        INSERT INTO search_index_queue (kind, id)
+            SELECT 'post', p.id FROM posts p WHERE p.thread_id = changed_thread
+            ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+    END IF;
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION search_index_queue_posts() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO search_index_queue (kind, id)
+        VALUES ('post', CASE WHEN TG_OP = 'DELETE' THEN OLD.id ELSE NEW.id END)
+        ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION search_index_queue_ugc() RETURNS trigger AS $$
+DECLARE
+    changed_ugc INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_ugc := OLD.id;
+    ELSE
+        changed_ugc := NEW.id;
+    END IF;
+
+    INSERT INTO search_index_queue (kind, id)
+        SELECT 'post', p.id FROM posts p WHERE p.ugc_id = changed_ugc
+        ON CONFLICT (kind, id) DO UPDATE SET queued_at = NOW();
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+INSERT INTO search_index_queue (kind, id)
+    SELECT payload->>'kind', jsonb_array_elements_text(payload->'ids')::INT
+    FROM jobs
+    WHERE kind = 'search.index' AND failed_at IS NULL
+    ON CONFLICT (kind, id) DO NOTHING;
+
+DELETE FROM jobs WHERE kind = 'search.index';
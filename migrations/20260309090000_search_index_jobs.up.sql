@@ -0,0 +1,65 @@
+-- Search indexing runs on the job queue. The triggers queue a 'search.index'
+-- job naming the threads or posts whose documents need updating, in place of
+-- a row in search_index_queue. The jobs take the max_attempts column default.
+
+CREATE OR REPLACE FUNCTION search_index_queue_threads() RETURNS trigger AS $$
+DECLARE
+    changed_thread INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_thread := OLD.id;
+    ELSE
+        changed_thread := NEW.id;
+    END IF;
+
+    INSERT INTO jobs (kind, payload)
+        VALUES ('search.index', jsonb_build_object('kind', 'thread', 'ids', jsonb_build_array(changed_thread)));
+
+    -- Post documents carry the forum and are hidden with their thread.
+    IF TG_OP = 'UPDATE' THEN
+        INSERT INTO jobs (kind, payload)
+            SELECT 'search.index', jsonb_build_object('kind', 'post', 'ids', jsonb_agg(p.id))
+            FROM posts p WHERE p.thread_id = changed_thread
+            HAVING COUNT(*) > 0;
+    END IF;
+
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION search_index_queue_posts() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO jobs (kind, payload)
+        VALUES ('search.index', jsonb_build_object(
+            'kind', 'post',
+            'ids', jsonb_build_array(CASE WHEN TG_OP = 'DELETE' THEN OLD.id ELSE NEW.id END)
+        ));
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION search_index_queue_ugc() RETURNS trigger AS $$
+DECLARE
+    changed_ugc INT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_ugc := OLD.id;
+    ELSE
+        changed_ugc := NEW.id;
+    END IF;
+
+    INSERT INTO jobs (kind, payload)
+        SELECT 'search.index', jsonb_build_object('kind', 'post', 'ids', jsonb_agg(p.id))
+        FROM posts p WHERE p.ugc_id = changed_ugc
+        HAVING COUNT(*) > 0;
+    RETURN NULL;
+END
+$$ LANGUAGE plpgsql;
+
+-- Carry over whatever was still waiting.
+INSERT INTO jobs (kind, payload)
+    SELECT 'search.index', jsonb_build_object('kind', kind, 'ids', jsonb_agg(id))
+    FROM search_index_queue
+    GROUP BY kind;
+
+DROP TABLE search_index_queue;
@@ -1,3 +1,9 @@
+import { createBLAKE3 } from 'hash-wasm';
+
+// Files larger than this are sent in parts that are retried individually.
+const CHUNKED_UPLOAD_THRESHOLD = 8 * 1024 * 1024;
+const PART_ATTEMPTS = 5;
+
 document.addEventListener("DOMContentLoaded", function () {
     function attachmentEventListeners() {
         const inputEl = document.querySelector('.attachment-input');
@@ -127,11 +133,25 @@ document.addEventListener("DOMContentLoaded", function () {
             refreshPreviews();
         }
 
-        // Update the file input with current files
+        // Update the file input with current files.
+        // Files sent in parts are already stored and are referenced by hash instead.
         function updateFileInput() {
             const newFileList = new DataTransfer();
+            if (inputEl.form) {
+                inputEl.form.querySelectorAll('input[name="uploaded_attachment"]').forEach(el => el.remove());
+            }
             for (const fileData of uploadedFiles) {
-                newFileList.items.add(fileData.file);
+                if (fileData.chunked) {
+                    if (fileData.uploadResponse && inputEl.form) {
+                        const hiddenEl = document.createElement('input');
+                        hiddenEl.type = 'hidden';
+                        hiddenEl.name = 'uploaded_attachment';
+                        hiddenEl.value = `${fileData.uploadResponse.hash}/${fileData.file.name}`;
+                        inputEl.form.appendChild(hiddenEl);
+                    }
+                } else {
+                    newFileList.items.add(fileData.file);
+                }
             }
             inputEl.files = newFileList.files;
         }
@@ -160,6 +180,9 @@ document.addEventListener("DOMContentLoaded", function () {
                     if (results.length > 0) {
                         return results[0];
                     }
+                } else if (response.status === 413) {
+                    // Over the storage quota
+                    alert(await response.text());
                 }
             } catch (err) {
                 // Upload failed silently
@@ -167,6 +190,84 @@ document.addEventListener("DOMContentLoaded", function () {
             return null;
         }
 
+        // Hash a file without reading it into memory all at once
+        async function hashFile(file) {
+            const hasher = await createBLAKE3();
+            hasher.init();
+            for (let offset = 0; offset < file.size; offset += CHUNKED_UPLOAD_THRESHOLD) {
+                const slice = file.slice(offset, offset + CHUNKED_UPLOAD_THRESHOLD);
+                hasher.update(new Uint8Array(await slice.arrayBuffer()));
+            }
+            return hasher.digest('hex');
+        }
+
+        // Send one part, retrying with backoff when the connection drops
+        async function uploadPart(sessionId, partNumber, blob) {
+            for (let attempt = 1; attempt <= PART_ATTEMPTS; attempt++) {
+                try {
+                    const response = await fetch(`/fs/uploads/${sessionId}/parts/${partNumber}`, {
+                        method: 'PUT',
+                        body: blob,
+                    });
+                    if (response.ok) return true;
+                    // The server rejected the part itself; retrying won't help.
+                    if (response.status >= 400 && response.status < 500) return false;
+                } catch (err) {
+                    // Network error, retry below
+                }
+                await new Promise(resolve => setTimeout(resolve, 1000 * 2 ** (attempt - 1)));
+            }
+            return false;
+        }
+
+        // Upload a large file in parts, resuming an earlier attempt if one exists
+        async function uploadFileChunked(file) {
+            try {
+                const hash = await hashFile(file);
+                const storageKey = `chunked-upload:${hash}`;
+                let session = null;
+
+                const previousId = localStorage.getItem(storageKey);
+                if (previousId) {
+                    const response = await fetch(`/fs/uploads/${previousId}`);
+                    if (response.ok) session = await response.json();
+                }
+
+                if (!session) {
+                    const response = await fetch('/fs/uploads', {
+                        method: 'POST',
+                        headers: { 'Content-Type': 'application/json' },
+                        body: JSON.stringify({
+                            filename: file.name,
+                            filesize: file.size,
+                            mime: file.type || null,
+                            hash,
+                        }),
+                    });
+                    if (response.status === 413) alert(await response.text());
+                    if (!response.ok) return null;
+                    session = await response.json();
+                    if (session.attachment) return session.attachment;
+                    localStorage.setItem(storageKey, session.id);
+                }
+
+                const received = new Set(session.received_parts);
+                for (let part = 1; part <= session.part_count; part++) {
+                    if (received.has(part)) continue;
+                    const start = (part - 1) * session.chunk_size;
+                    const blob = file.slice(start, start + session.chunk_size);
+                    if (!await uploadPart(session.id, part, blob)) return null;
+                }
+
+                const response = await fetch(`/fs/uploads/${session.id}/complete`, { method: 'POST' });
+                localStorage.removeItem(storageKey);
+                if (response.ok) return await response.json();
+            } catch (err) {
+                // Upload failed silently; it can resume on the next attempt
+            }
+            return null;
+        }
+
         // Handle file selection
         inputEl.addEventListener('change', async function (event) {
             const newFiles = event.target.files;
@@ -174,12 +275,13 @@ document.addEventListener("DOMContentLoaded", function () {
             // Add new files to our list and start uploading
             for (let i = 0; i < newFiles.length; i++) {
                 const file = newFiles[i];
-                const fileData = { file, uploadResponse: null, uploading: true };
+                const chunked = file.size > CHUNKED_UPLOAD_THRESHOLD;
+                const fileData = { file, uploadResponse: null, uploading: true, chunked };
                 uploadedFiles.push(fileData);
                 refreshPreviews();
 
                 // Upload in background
-                const response = await uploadFile(file);
+                const response = chunked ? await uploadFileChunked(file) : await uploadFile(file);
                 fileData.uploadResponse = response;
                 fileData.uploading = false;
                 refreshPreviews();
@@ -14,6 +14,13 @@ document.addEventListener("DOMContentLoaded", function () {
     let userHover = null;
     let lastScrollPos = 0;
     let userActivityData = {};
+    // Scrollback state for the current room.
+    let historyLoading = false;
+    let historyComplete = false;
+    // Who is typing in the current room, by user id.
+    let typingUsers = {};
+    // When we last told the server we are typing.
+    let typingSentAt = 0;
 
     function inputAddEventListeners(el) {
         // Keyboard shortcuts for formatting (Ctrl+B, Ctrl+I, Ctrl+U)
@@ -120,6 +127,7 @@ document.addEventListener("DOMContentLoaded", function () {
 
         Array.from(element.querySelectorAll('.button')).forEach(function (buttonEl) {
             switch (buttonEl.classList[1]) {
+                case 'react': buttonEl.addEventListener('click', messageButtonReact); break;
                 case 'edit': buttonEl.addEventListener('click', messageButtonEdit); break;
                 case 'delete': buttonEl.addEventListener('click', messageButtonDelete); break;
                 case 'report': /* buttonEl.addEventListener('click', messageButtonReport); */ break;
@@ -176,6 +184,37 @@ document.addEventListener("DOMContentLoaded", function () {
         }
     }
 
+    function messageButtonReact(event) {
+        let messageEl = this.closest(".chat-message");
+        let pickerEl = messageEl.querySelector('.reaction-picker');
+
+        event.stopPropagation();
+
+        // Second click closes the picker.
+        if (pickerEl !== null) {
+            pickerEl.remove();
+            return;
+        }
+
+        pickerEl = document.createElement('div');
+        pickerEl.classList.add('reaction-picker');
+
+        APP.reaction_types.forEach(function (reactionType) {
+            let optionEl = document.createElement('button');
+            optionEl.type = 'button';
+            optionEl.classList.add('reaction-option');
+            optionEl.textContent = reactionType.emoji;
+            optionEl.title = reactionType.name;
+            optionEl.addEventListener('click', function () {
+                messageSend(`/react ${messageEl.dataset.id} ${reactionType.id}`);
+                pickerEl.remove();
+            });
+            pickerEl.appendChild(optionEl);
+        });
+
+        messageEl.querySelector('.right-content').appendChild(pickerEl);
+    }
+
     function messageDelete(message) {
         let el = document.getElementById(`chat-message-${message}`);
         let next = el.nextElementSibling;
@@ -290,7 +329,40 @@ document.addEventListener("DOMContentLoaded", function () {
         }
     }
 
-    function messagePush(message, author) {
+    function historyReceive(history, complete) {
+        let messagesEl = document.getElementById('chat-messages');
+        let heightBefore = scrollEl.scrollHeight;
+
+        // Messages arrive oldest first, so prepend newest first.
+        history.slice().reverse().forEach(message => messagePush(message, message.author, true));
+
+        // Keep the messages the user was reading in view.
+        scrollEl.scrollTop += scrollEl.scrollHeight - heightBefore;
+        lastScrollPos = scrollEl.scrollTop;
+
+        if (messagesEl.children.length > 0) {
+            messagesEl.children[0].classList.remove("chat-message--hasParent");
+        }
+
+        historyLoading = false;
+        historyComplete = complete;
+    }
+
+    function historyRequest() {
+        if (historyLoading || historyComplete) {
+            return;
+        }
+
+        let oldestEl = document.querySelector('#chat-messages .chat-message[data-id]');
+        if (oldestEl === null) {
+            return;
+        }
+
+        historyLoading = true;
+        messageSend(`/history ${oldestEl.dataset.id}`);
+    }
+
+    function messagePush(message, author, prepend = false) {
         if (typeof message === 'string') {
             message = { message: message };
         }
@@ -355,9 +427,19 @@ document.addEventListener("DOMContentLoaded", function () {
                 template.querySelector('.avatar').remove();
             }
 
+            reactionsRender(template.children[0], message.reactions || []);
+
             // Add right-content details
-            if (message.author.id != APP.user.id) {
+            if (!APP.reaction_types || APP.reaction_types.length === 0) {
+                template.querySelector('.react').remove();
+            }
+
+            if (APP.edit_window > 0 && Date.now() / 1000 - message.message_date > APP.edit_window) {
                 template.querySelector('.edit').remove();
+            }
+
+            if (message.author.id != APP.user.id) {
+                template.querySelector('.edit')?.remove();
 
                 if (!APP.user.is_staff) {
                     template.querySelector('.delete').remove();
@@ -368,6 +450,7 @@ document.addEventListener("DOMContentLoaded", function () {
         else {
             template.children[0].classList.add("chat-message--systemMsg");
             template.querySelector('.meta').remove();
+            template.querySelector('.reactions').remove();
             template.querySelector('.left-content').remove();
             //template.querySelector('.right-content').remove();
         }
@@ -389,16 +472,30 @@ document.addEventListener("DOMContentLoaded", function () {
         if (extantEl !== null) {
             extantEl.replaceWith(el);
         }
+        else if (prepend) {
+            el = messagesEl.insertBefore(el, messagesEl.firstChild);
+        }
         else {
             el = messagesEl.appendChild(el);
         }
 
+        if (prepend) {
+            // The message below may now continue this one's group.
+            if (el.nextElementSibling !== null) {
+                messageSetHasParent(el.nextElementSibling);
+            }
+            return el;
+        }
+
         messageSetHasParent(el);
 
-        // Prune oldest messages.
-        while (messagesEl.children.length > 200) {
-            messagesEl.children[0].remove();
-            lastScrollPos = 0;
+        // Prune oldest messages, unless the user is reading back through them.
+        if (!scrollEl.classList.contains('ScrollAnchored')) {
+            while (messagesEl.children.length > 200) {
+                messagesEl.children[0].remove();
+                historyComplete = false;
+                lastScrollPos = 0;
+            }
         }
 
         messagesEl.children[0].classList.remove("chat-message--hasParent");
@@ -449,10 +546,23 @@ document.addEventListener("DOMContentLoaded", function () {
             json.messages.forEach(message => messagePush(message, message.author));
         }
 
+        if (json.hasOwnProperty('history')) {
+            historyReceive(json.history, json.complete);
+        }
+
         if (json.hasOwnProperty('delete')) {
             json.delete.forEach(message => messageDelete(message));
         }
 
+        if (json.hasOwnProperty('reactions')) {
+            Object.entries(json.reactions).forEach(([id, tallies]) => {
+                let messageEl = document.getElementById(`chat-message-${id}`);
+                if (messageEl !== null) {
+                    reactionsRender(messageEl, tallies);
+                }
+            });
+        }
+
         if (json.hasOwnProperty('users')) {
             Object.entries(json.users).forEach(user => {
                 let [key, value] = user;
@@ -460,6 +570,36 @@ document.addEventListener("DOMContentLoaded", function () {
             });
             userActivitySort();
         }
+
+        if (json.hasOwnProperty('typing')) {
+            Object.entries(json.typing).forEach(([id, username]) => typingUpdate(id, username));
+            typingRender();
+        }
+
+        if (json.hasOwnProperty('room')) {
+            roomAdd(json.room, json.join);
+        }
+
+        if (json.hasOwnProperty('unread')) {
+            roomMarkUnread(json.unread);
+        }
+
+        if (json.hasOwnProperty('left')) {
+            roomRemove(json.left);
+        }
+
+        if (json.hasOwnProperty('ignored')) {
+            ignoredUpdate(json.ignored);
+        }
+    }
+
+    // Collapse or expand messages already shown when the ignore list changes.
+    function ignoredUpdate(ignored) {
+        APP.user.ignored_users = ignored;
+
+        Array.from(document.querySelectorAll('#chat-messages [data-author]')).forEach(el => {
+            el.classList.toggle("chat-message--isIgnored", ignored.includes(parseInt(el.dataset.author, 10)));
+        });
     }
 
     function messagesDelete() {
@@ -469,12 +609,88 @@ document.addEventListener("DOMContentLoaded", function () {
         }
     }
 
+    // Add a private room or direct message to the room list.
+    function reactionsRender(messageEl, tallies) {
+        let reactionsEl = messageEl.querySelector('.reactions');
+        while (reactionsEl.firstChild) {
+            reactionsEl.removeChild(reactionsEl.firstChild);
+        }
+
+        tallies.forEach(function (tally) {
+            let tallyEl = document.createElement('button');
+            tallyEl.type = 'button';
+            tallyEl.classList.add('reaction');
+            tallyEl.classList.toggle('reaction--mine', tally.users.includes(APP.user.id));
+            tallyEl.textContent = `${tally.emoji} ${tally.count}`;
+            tallyEl.addEventListener('click', function () {
+                messageSend(`/react ${messageEl.dataset.id} ${tally.id}`);
+            });
+            reactionsEl.appendChild(tallyEl);
+        });
+    }
+
+    function roomAdd(data, join) {
+        let roomsEl = document.getElementById('chat-rooms');
+        let roomEl = roomsEl.querySelector(`.chat-room[data-id="${data.id}"]`);
+
+        if (roomEl === null) {
+            roomEl = document.createElement('a');
+            roomEl.classList.add('chat-room', 'chat-room--private');
+            roomEl.setAttribute('role', 'button');
+            roomEl.href = `#${data.id}`;
+            roomEl.dataset.id = data.id;
+            // Title is sanitized by the server.
+            roomEl.innerHTML = data.title;
+            roomsEl.appendChild(roomEl);
+        }
+
+        if (join) {
+            window.location.hash = `#${data.id}`;
+        }
+        else if (data.unread) {
+            roomMarkUnread(data.id);
+        }
+    }
+
+    function roomMarkUnread(id) {
+        let roomEl = document.querySelector(`#chat-rooms .chat-room[data-id="${id}"]`);
+
+        if (roomEl !== null && window.location.hash !== `#${id}`) {
+            roomEl.classList.add('chat-room--unread');
+        }
+    }
+
+    // Drop a private room the user has left.
+    function roomRemove(id) {
+        let roomEl = document.querySelector(`#chat-rooms .chat-room[data-id="${id}"]`);
+
+        if (roomEl !== null) {
+            roomEl.remove();
+        }
+
+        if (window.location.hash === `#${id}`) {
+            messagesDelete();
+            userActivityDelete();
+            window.location.hash = APP.default_room > 0 ? `#${APP.default_room}` : '';
+        }
+    }
+
     function roomJoin(id) {
         if (Number.isInteger(id) && id > 0) {
+            let roomEl = document.querySelector(`#chat-rooms .chat-room[data-id="${id}"]`);
+            if (roomEl !== null) {
+                roomEl.classList.remove('chat-room--unread');
+            }
+
             scrollEl.classList.remove('ScrollAnchored');
             scrollEl.classList.add('ScrollAnchorConsume');
             messagesDelete();
             userActivityDelete();
+            typingUsers = {};
+            typingSentAt = 0;
+            typingRender();
+            historyLoading = false;
+            historyComplete = false;
             messageSend(`/join ${id}`);
             //document.getElementById("chat-input").focus({ preventScroll: true });
             return true;
@@ -519,6 +735,11 @@ document.addEventListener("DOMContentLoaded", function () {
         }
 
         lastScrollPos = this.scrollTop;
+
+        // Load older messages when reaching the top.
+        if (this.scrollTop <= clampHeight && this.classList.contains('ScrollAnchored')) {
+            historyRequest();
+        }
     }
 
     function scrollToNew() {
@@ -527,6 +748,93 @@ document.addEventListener("DOMContentLoaded", function () {
         }
     }
 
+    // Tell the server we are typing, renewing it every few seconds, or that we stopped.
+    function typingNotify(typing) {
+        if (ws === null || ws.readyState !== WebSocket.OPEN) {
+            return;
+        }
+
+        let now = Date.now();
+        if (typing && now - typingSentAt > 3000) {
+            typingSentAt = now;
+            messageSend('/typing start');
+        }
+        else if (!typing && typingSentAt > 0) {
+            typingSentAt = 0;
+            messageSend('/typing stop');
+        }
+    }
+
+    function typingUpdate(id, username) {
+        if (id == APP.user.id) {
+            return;
+        }
+
+        if (username !== false) {
+            typingUsers[id] = username;
+        }
+        else {
+            delete typingUsers[id];
+        }
+
+        let userEl = document.getElementById(`chat-activity-${id}`);
+        if (userEl) {
+            userEl.classList.toggle('activity--typing', username !== false);
+        }
+    }
+
+    function typingRender() {
+        let typingEl = document.getElementById('chat-typing');
+        let names = Object.values(typingUsers);
+
+        switch (names.length) {
+            case 0: typingEl.textContent = ''; break;
+            case 1: typingEl.textContent = `${names[0]} is typing…`; break;
+            case 2: typingEl.textContent = `${names[0]} and ${names[1]} are typing…`; break;
+            default: typingEl.textContent = 'Several people are typing…'; break;
+        }
+    }
+
+    // Files dropped, pasted or picked are uploaded, then posted to the room
+    // as a thumbnail or a link.
+    function uploadFiles(files) {
+        if (!APP.uploads || room === null) {
+            return;
+        }
+
+        Array.from(files).forEach(uploadFile);
+    }
+
+    function uploadFile(file) {
+        let nameEl = document.createElement('em');
+        nameEl.textContent = file.name;
+        messagePush(`Uploading ${nameEl.outerHTML}…`);
+
+        let data = new FormData();
+        data.append('csrf_token', APP.csrf_token);
+        data.append('file', file);
+
+        fetch('/chat/upload', { method: 'POST', body: data, credentials: 'same-origin' })
+            .then(async function (response) {
+                if (!response.ok) {
+                    throw new Error(await response.text());
+                }
+                return response.json();
+            })
+            .then(function (upload) {
+                const url = `/content/${upload.hash}/${encodeURIComponent(upload.filename)}`;
+                const label = upload.filename.replace(/[\[\]]/g, '');
+                messageSend(upload.is_image
+                    ? `[thumb]${url}[/thumb]`
+                    : `[url=${window.location.origin}${url}]${label}[/url]`);
+            })
+            .catch(function (error) {
+                let errorEl = document.createElement('span');
+                errorEl.textContent = error.message;
+                messagePush(`Could not share ${nameEl.outerHTML}: ${errorEl.innerHTML}`);
+            });
+    }
+
     function userActivity(id, activity) {
         if (id == 0)
             return;
@@ -721,6 +1029,8 @@ document.addEventListener("DOMContentLoaded", function () {
                 event.preventDefault();
 
                 messageSend(getInputBBCode(this));
+                // Posting clears our typing indicator on the server.
+                typingSentAt = 0;
                 this.innerHTML = "";
                 this.style.height = '44px'; // Reset to min height
 
@@ -740,8 +1050,60 @@ document.addEventListener("DOMContentLoaded", function () {
                 }
         }
     });
+    document.getElementById('new-message-input').addEventListener('input', function () {
+        typingNotify(this.textContent.trim().length > 0);
+    });
+    // Pasted files are shared instead of going into the message.
+    document.getElementById('new-message-input').addEventListener('paste', function (event) {
+        if (APP.uploads && event.clipboardData.files.length > 0) {
+            event.preventDefault();
+            event.stopImmediatePropagation();
+            uploadFiles(event.clipboardData.files);
+        }
+    });
     inputAddEventListeners(document.getElementById('new-message-input'));
 
+    // File sharing
+    if (APP.uploads) {
+        const chatEl = document.getElementById('chat');
+        const uploadInputEl = document.getElementById('chat-upload-input');
+
+        document.getElementById('chat-upload-button').addEventListener('click', function () {
+            uploadInputEl.click();
+        });
+        uploadInputEl.addEventListener('change', function () {
+            uploadFiles(this.files);
+            this.value = '';
+        });
+
+        chatEl.addEventListener('dragover', function (event) {
+            if (event.dataTransfer.types.includes('Files')) {
+                event.preventDefault();
+                chatEl.classList.add('chat--dropping');
+            }
+        });
+        chatEl.addEventListener('dragleave', function (event) {
+            if (!chatEl.contains(event.relatedTarget)) {
+                chatEl.classList.remove('chat--dropping');
+            }
+        });
+        chatEl.addEventListener('drop', function (event) {
+            chatEl.classList.remove('chat--dropping');
+            if (event.dataTransfer.files.length > 0) {
+                event.preventDefault();
+                uploadFiles(event.dataTransfer.files);
+            }
+        });
+    }
+    else {
+        document.getElementById('chat-upload-button').remove();
+    }
+
+    // Clicking anywhere else closes reaction pickers.
+    document.addEventListener('click', function () {
+        Array.from(document.querySelectorAll('.reaction-picker')).forEach(el => el.remove());
+    });
+
     // Auto-resize chat input
     initAutoResize(document.getElementById('new-message-input'));
 
@@ -1076,6 +1438,7 @@ document.addEventListener("DOMContentLoaded", function () {
         let input = document.getElementById('new-message-input');
 
         messageSend(getInputBBCode(input));
+        typingSentAt = 0;
         input.innerHTML = "";
         input.style.height = '44px'; // Reset to min height
 
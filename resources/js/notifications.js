@@ -3,7 +3,14 @@
  *
  * Connects to /notifications.ws and handles incoming notifications:
  * - Updates the notification badge count
- * - Shows toast notifications for new notifications
+ * - Shows toast notifications for new notifications, refreshing the toast
+ *   in place when a grouped notification is updated
+ * - Re-dispatches conversation events as `ruforo:conversation` DOM events
+ *   and exposes `window.RuforoSocket.send()` for typing/read commands
+ *
+ * If the WebSocket can't be opened, falls back to the Server-Sent Events
+ * stream at /notifications/stream, or failing that polls
+ * /api/notifications/unread-count to keep the badge current.
  */
 
 document.addEventListener("DOMContentLoaded", function() {
@@ -13,16 +20,26 @@ document.addEventListener("DOMContentLoaded", function() {
     }
 
     let ws = null;
+    let eventSource = null;
+    let pollTimer = null;
+    let wsEverOpened = false;
     let reconnectTimer = null;
     let reconnectAttempts = 0;
     const MAX_RECONNECT_ATTEMPTS = 10;
+    const FALLBACK_AFTER_ATTEMPTS = 3; // Give up on a WebSocket that never opened
     const RECONNECT_DELAY_BASE = 1000; // Start with 1 second
     const TOAST_DURATION = 5000; // 5 seconds
+    const POLL_INTERVAL = 60000; // 1 minute
 
     /**
      * Connect to the notification WebSocket
      */
     function connect() {
+        if (typeof window.WebSocket === 'undefined') {
+            startFallback();
+            return;
+        }
+
         // Build WebSocket URL
         const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
         const wsUrl = `${protocol}//${window.location.host}/notifications.ws`;
@@ -37,6 +54,7 @@ document.addEventListener("DOMContentLoaded", function() {
 
         ws.addEventListener('open', function() {
             console.log('Notification WebSocket connected');
+            wsEverOpened = true;
             reconnectAttempts = 0;
         });
 
@@ -62,6 +80,12 @@ document.addEventListener("DOMContentLoaded", function() {
             clearTimeout(reconnectTimer);
         }
 
+        // Proxies that block the upgrade fail every time; stop trying
+        if (!wsEverOpened && reconnectAttempts >= FALLBACK_AFTER_ATTEMPTS) {
+            startFallback();
+            return;
+        }
+
         if (reconnectAttempts >= MAX_RECONNECT_ATTEMPTS) {
             console.log('Max reconnection attempts reached');
             return;
@@ -75,7 +99,47 @@ document.addEventListener("DOMContentLoaded", function() {
     }
 
     /**
-     * Handle incoming WebSocket message
+     * Receive notifications over Server-Sent Events, or poll the unread
+     * count if the browser can't do that either
+     */
+    function startFallback() {
+        if (eventSource || pollTimer) return;
+
+        if (typeof window.EventSource === 'undefined') {
+            console.log('Polling for notifications');
+            refreshBadge();
+            pollTimer = setInterval(refreshBadge, POLL_INTERVAL);
+            return;
+        }
+
+        console.log('Falling back to notification event stream');
+        // The browser reconnects the stream by itself
+        eventSource = new EventSource('/notifications/stream');
+        eventSource.addEventListener('message', function(event) {
+            handleMessage(event.data);
+        });
+    }
+
+    /**
+     * Fetch the unread count and show it on the badge
+     */
+    function refreshBadge() {
+        fetch('/api/notifications/unread-count', { credentials: 'same-origin' })
+            .then(function(response) {
+                return response.ok ? response.json() : null;
+            })
+            .then(function(json) {
+                if (json && typeof json.count === 'number') {
+                    setBadge(json.count);
+                }
+            })
+            .catch(function(err) {
+                console.error('Failed to fetch unread count:', err);
+            });
+    }
+
+    /**
+     * Handle incoming WebSocket or event stream message
      */
     function handleMessage(data) {
         let json;
@@ -88,6 +152,12 @@ document.addEventListener("DOMContentLoaded", function() {
 
         if (json.type === 'notification' && json.data) {
             handleNotification(json.data);
+        } else if (json.type === 'unread_count' && json.data) {
+            setBadge(json.data.count);
+        } else if (typeof json.type === 'string' && json.type.startsWith('conversation_')) {
+            document.dispatchEvent(new CustomEvent('ruforo:conversation', {
+                detail: { type: json.type, data: json.data }
+            }));
         } else if (json.type === 'pong') {
             // Keep-alive response, ignore
         }
@@ -97,11 +167,21 @@ document.addEventListener("DOMContentLoaded", function() {
      * Handle a new notification
      */
     function handleNotification(notification) {
-        // Update badge count
-        updateBadge();
+        // Grouped notifications that were updated are already counted
+        if (!notification.updated) {
+            updateBadge();
+        }
 
-        // Show toast notification
-        showToast(notification);
+        // Refresh a toast still showing this notification, or show a new one
+        const existing = document.querySelector(
+            `.notification-toast[data-notification-id="${notification.id}"]`
+        );
+        if (existing) {
+            existing.querySelector('.notification-toast-title').textContent = notification.title;
+            existing.querySelector('.notification-toast-message').textContent = notification.message;
+        } else {
+            showToast(notification);
+        }
 
         // Play notification sound if enabled (future feature)
         // playNotificationSound();
@@ -114,12 +194,19 @@ document.addEventListener("DOMContentLoaded", function() {
         const badge = document.getElementById('notification-badge');
         if (!badge) return;
 
-        let count = parseInt(badge.textContent, 10) || 0;
-        count++;
+        setBadge((parseInt(badge.textContent, 10) || 0) + 1);
+    }
+
+    /**
+     * Set the notification badge count, hiding it at zero
+     */
+    function setBadge(count) {
+        const badge = document.getElementById('notification-badge');
+        if (!badge) return;
 
         badge.textContent = count;
         badge.setAttribute('aria-label', `${count} unread notifications`);
-        badge.classList.remove('hidden');
+        badge.classList.toggle('hidden', count === 0);
     }
 
     /**
@@ -132,6 +219,7 @@ document.addEventListener("DOMContentLoaded", function() {
         const toast = document.createElement('div');
         toast.className = 'notification-toast';
         toast.setAttribute('role', 'alert');
+        toast.dataset.notificationId = notification.id;
 
         // Toast content
         const content = document.createElement('div');
@@ -210,13 +298,30 @@ document.addEventListener("DOMContentLoaded", function() {
         }
     }
 
+    /**
+     * Send a JSON command to the server, dropped if not connected
+     */
+    window.RuforoSocket = {
+        send: function(command) {
+            if (ws && ws.readyState === WebSocket.OPEN) {
+                ws.send(JSON.stringify(command));
+            }
+        }
+    };
+
     // Start ping interval (every 25 seconds, before 30 second timeout)
     setInterval(sendPing, 25000);
 
     // Handle page visibility changes
     document.addEventListener('visibilitychange', function() {
         if (document.visibilityState === 'visible') {
+            // Notifications may have been read in another tab
+            refreshBadge();
+
             // Page became visible, check connection
+            if (eventSource || pollTimer) {
+                return;
+            }
             if (!ws || ws.readyState !== WebSocket.OPEN) {
                 reconnectAttempts = 0; // Reset attempts when user returns
                 connect();
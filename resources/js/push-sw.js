@@ -0,0 +1,39 @@
+/**
+ * Push service worker
+ *
+ * Shows notifications pushed by the forum and opens the linked page when one
+ * is clicked. Built as its own bundle so it can be registered on its own.
+ */
+
+self.addEventListener('push', function(event) {
+    if (!event.data) {
+        return;
+    }
+
+    let payload;
+    try {
+        payload = event.data.json();
+    } catch (err) {
+        payload = { title: event.data.text() };
+    }
+
+    event.waitUntil(self.registration.showNotification(payload.title || 'New notification', {
+        body: payload.body || '',
+        tag: payload.tag,
+        data: { url: payload.url || '/notifications' },
+    }));
+});
+
+self.addEventListener('notificationclick', function(event) {
+    event.notification.close();
+    const url = new URL(event.notification.data.url, self.location.origin).href;
+
+    event.waitUntil(self.clients.matchAll({ type: 'window', includeUncontrolled: true }).then(function(windows) {
+        for (const client of windows) {
+            if (client.url === url && 'focus' in client) {
+                return client.focus();
+            }
+        }
+        return self.clients.openWindow(url);
+    }));
+});
@@ -0,0 +1,100 @@
+/**
+ * Browser push notification settings
+ *
+ * Drives the "Browser Notifications" section of the notification preferences
+ * page: registers the push service worker and subscribes or unsubscribes this
+ * browser with the site's VAPID key.
+ */
+
+const PUSH_WORKER_URL = '/public/assets/push-sw.js';
+
+/**
+ * Convert a base64url VAPID key into the bytes PushManager expects
+ */
+function urlBase64ToUint8Array(value) {
+    const padding = '='.repeat((4 - value.length % 4) % 4);
+    const base64 = (value + padding).replace(/-/g, '+').replace(/_/g, '/');
+    const raw = window.atob(base64);
+    return Uint8Array.from(raw, (c) => c.charCodeAt(0));
+}
+
+async function postForm(url, fields) {
+    const response = await fetch(url, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+        body: new URLSearchParams(fields).toString(),
+    });
+    if (!response.ok) {
+        throw new Error(`Request failed: ${response.status}`);
+    }
+    return response.json();
+}
+
+document.addEventListener('DOMContentLoaded', async function() {
+    const container = document.getElementById('push-settings');
+    if (!container || !('serviceWorker' in navigator) || !('PushManager' in window)) {
+        return;
+    }
+
+    const status = document.getElementById('push-status');
+    const button = document.getElementById('push-toggle');
+    const csrfToken = container.dataset.csrf;
+
+    let registration;
+    try {
+        registration = await navigator.serviceWorker.register(PUSH_WORKER_URL);
+    } catch (err) {
+        console.error('Failed to register push service worker:', err);
+        status.textContent = 'Push notifications could not be set up in this browser.';
+        return;
+    }
+
+    let subscription = await registration.pushManager.getSubscription();
+
+    function render() {
+        if (Notification.permission === 'denied') {
+            status.textContent = 'Notifications are blocked for this site in your browser settings.';
+            button.hidden = true;
+        } else if (subscription) {
+            status.textContent = 'Push notifications are on for this browser.';
+            button.textContent = 'Turn off on this browser';
+            button.hidden = false;
+        } else {
+            status.textContent = 'Push notifications are off for this browser.';
+            button.textContent = 'Enable on this browser';
+            button.hidden = false;
+        }
+    }
+
+    button.addEventListener('click', async function() {
+        button.disabled = true;
+        try {
+            if (subscription) {
+                await postForm('/account/notifications/push/delete', {
+                    csrf_token: csrfToken,
+                    endpoint: subscription.endpoint,
+                });
+                await subscription.unsubscribe();
+                subscription = null;
+            } else {
+                subscription = await registration.pushManager.subscribe({
+                    userVisibleOnly: true,
+                    applicationServerKey: urlBase64ToUint8Array(container.dataset.vapidKey),
+                });
+                const keys = subscription.toJSON().keys;
+                await postForm('/account/notifications/push', {
+                    csrf_token: csrfToken,
+                    endpoint: subscription.endpoint,
+                    p256dh: keys.p256dh,
+                    auth: keys.auth,
+                });
+            }
+        } catch (err) {
+            console.error('Failed to update push subscription:', err);
+        }
+        button.disabled = false;
+        render();
+    });
+
+    render();
+});
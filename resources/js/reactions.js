@@ -7,8 +7,40 @@
 // Cache for reaction types
 let reactionTypesCache = null;
 
+function escapeHtml(text) {
+    const div = document.createElement('div');
+    div.textContent = text;
+    return div.innerHTML;
+}
+
+function reactionIcon(reaction, className) {
+    return reaction.image_url
+        ? `<img src="${reaction.image_url}" alt="${escapeHtml(reaction.name)}" class="${className}" />`
+        : `<span class="reaction-users-emoji">${reaction.emoji}</span>`;
+}
+
+function reactionUsersHtml(users) {
+    if (users.length === 0) {
+        return '<div class="reaction-users-empty">No reactions</div>';
+    }
+    const usersHtml = users.map(user => {
+        const name = escapeHtml(user.name);
+        const avatar = user.avatar_url
+            ? `<img src="${user.avatar_url}" alt="${name}" class="reaction-user-avatar" />`
+            : `<div class="reaction-user-avatar reaction-user-avatar--placeholder">${name.charAt(0).toUpperCase()}</div>`;
+        return `
+            <a href="/members/${user.id}" class="reaction-user-item">
+                ${avatar}
+                <span class="reaction-user-name">${name}</span>
+            </a>
+        `;
+    }).join('');
+    return `<div class="reaction-users-list">${usersHtml}</div>`;
+}
+
 /**
- * Create and show reaction users overlay
+ * Create and show the overlay of who reacted to a post, with a tab per
+ * reaction type. Opens on the given type's tab.
  */
 async function showReactionUsersOverlay(ugcId, reactionTypeId) {
     // Remove any existing overlay
@@ -23,9 +55,10 @@ async function showReactionUsersOverlay(ugcId, reactionTypeId) {
     overlay.innerHTML = `
         <div class="reaction-users-modal">
             <div class="reaction-users-header">
-                <span class="reaction-users-title">Loading...</span>
+                <span class="reaction-users-title">Reactions</span>
                 <button type="button" class="reaction-users-close">&times;</button>
             </div>
+            <div class="reaction-users-tabs"></div>
             <div class="reaction-users-content">
                 <div class="reaction-users-loading">Loading...</div>
             </div>
@@ -50,37 +83,52 @@ async function showReactionUsersOverlay(ugcId, reactionTypeId) {
     };
     document.addEventListener('keydown', escHandler);
 
-    // Fetch users
+    // Fetch everyone who reacted, by type
     try {
-        const response = await fetch(`/reactions/${ugcId}/users?reaction_type_id=${reactionTypeId}`);
+        const response = await fetch(`/reactions/${ugcId}/reactors`);
         if (!response.ok) throw new Error('Failed to load users');
         const data = await response.json();
 
-        // Update header with reaction info
-        const icon = data.reaction_image_url
-            ? `<img src="${data.reaction_image_url}" alt="${data.reaction_name}" class="reaction-users-icon" />`
-            : `<span class="reaction-users-emoji">${data.reaction_emoji}</span>`;
-
-        overlay.querySelector('.reaction-users-title').innerHTML = `${icon} ${data.reaction_name}`;
-
-        // Build user list
+        const tabsEl = overlay.querySelector('.reaction-users-tabs');
         const contentEl = overlay.querySelector('.reaction-users-content');
-        if (data.users.length === 0) {
+        if (data.reactions.length === 0) {
             contentEl.innerHTML = '<div class="reaction-users-empty">No reactions</div>';
-        } else {
-            const usersHtml = data.users.map(user => {
-                const avatar = user.avatar_url
-                    ? `<img src="${user.avatar_url}" alt="${user.name}" class="reaction-user-avatar" />`
-                    : `<div class="reaction-user-avatar reaction-user-avatar--placeholder">${user.name.charAt(0).toUpperCase()}</div>`;
-                return `
-                    <a href="/members/${user.id}" class="reaction-user-item">
-                        ${avatar}
-                        <span class="reaction-user-name">${user.name}</span>
-                    </a>
-                `;
-            }).join('');
-            contentEl.innerHTML = `<div class="reaction-users-list">${usersHtml}</div>`;
+            return;
         }
+
+        const total = data.reactions.reduce((sum, reaction) => sum + reaction.count, 0);
+        tabsEl.innerHTML = `<button type="button" class="reaction-users-tab" data-reaction-type="all">All ${total}</button>` +
+            data.reactions.map(reaction => `
+                <button type="button" class="reaction-users-tab" data-reaction-type="${reaction.reaction_type_id}" title="${escapeHtml(reaction.name)}">
+                    ${reactionIcon(reaction, 'reaction-users-icon')} ${reaction.count}
+                </button>
+            `).join('');
+
+        const showTab = (typeId) => {
+            tabsEl.querySelectorAll('.reaction-users-tab').forEach(tab => {
+                tab.classList.toggle('reaction-users-tab--active', tab.dataset.reactionType === typeId);
+            });
+            if (typeId === 'all') {
+                contentEl.innerHTML = data.reactions.map(reaction => `
+                    <div class="reaction-users-group">
+                        <div class="reaction-users-group-title">${reactionIcon(reaction, 'reaction-users-icon')} ${escapeHtml(reaction.name)}</div>
+                        ${reactionUsersHtml(reaction.users)}
+                    </div>
+                `).join('');
+            } else {
+                const reaction = data.reactions.find(r => String(r.reaction_type_id) === typeId);
+                contentEl.innerHTML = reactionUsersHtml(reaction ? reaction.users : []);
+            }
+        };
+
+        tabsEl.querySelectorAll('.reaction-users-tab').forEach(tab => {
+            tab.addEventListener('click', () => showTab(tab.dataset.reactionType));
+        });
+
+        const initial = data.reactions.some(r => String(r.reaction_type_id) === String(reactionTypeId))
+            ? String(reactionTypeId)
+            : 'all';
+        showTab(initial);
     } catch (error) {
         console.error('Error loading reaction users:', error);
         overlay.querySelector('.reaction-users-content').innerHTML =
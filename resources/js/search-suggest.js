@@ -0,0 +1,187 @@
+/**
+ * Search-while-you-type suggestions for the header search box
+ * Shows matching thread titles and member names as the user types
+ */
+
+(function() {
+    'use strict';
+
+    // Debounce helper to limit API calls
+    function debounce(func, wait) {
+        let timeout;
+        return function executedFunction(...args) {
+            const later = () => {
+                clearTimeout(timeout);
+                func(...args);
+            };
+            clearTimeout(timeout);
+            timeout = setTimeout(later, wait);
+        };
+    }
+
+    const MIN_LENGTH = 2;
+
+    let input = null;
+    let dropdown = null;
+    let items = [];
+    let selectedIndex = -1;
+    let lastQuery = '';
+
+    // Fetch suggestions for the typed text
+    async function fetchSuggestions(query) {
+        try {
+            const response = await fetch(`${input.dataset.suggestUrl}?q=${encodeURIComponent(query)}`);
+            if (!response.ok) {
+                return null;
+            }
+            return await response.json();
+        } catch (e) {
+            console.error('Search suggestion error:', e);
+            return null;
+        }
+    }
+
+    function createDropdown() {
+        const dd = document.createElement('div');
+        dd.className = 'search-suggest';
+        dd.setAttribute('role', 'listbox');
+        dd.style.display = 'none';
+        input.parentElement.appendChild(dd);
+        return dd;
+    }
+
+    function addHeading(text) {
+        const heading = document.createElement('div');
+        heading.className = 'search-suggest-heading';
+        heading.textContent = text;
+        dropdown.appendChild(heading);
+    }
+
+    function addItem(label, url) {
+        const item = document.createElement('a');
+        item.className = 'search-suggest-item';
+        item.setAttribute('role', 'option');
+        item.href = url;
+        item.textContent = label;
+        item.addEventListener('mousedown', (e) => {
+            // Keep focus in the input until the link is followed
+            e.preventDefault();
+            window.location.href = url;
+        });
+        dropdown.appendChild(item);
+        items.push(item);
+    }
+
+    // Render dropdown with suggestions
+    function renderDropdown(suggestions) {
+        if (!dropdown) {
+            dropdown = createDropdown();
+        }
+        dropdown.replaceChildren();
+        items = [];
+        selectedIndex = -1;
+
+        if (suggestions.threads.length > 0) {
+            addHeading('Threads');
+            suggestions.threads.forEach(thread => addItem(thread.title, `/threads/${thread.id}`));
+        }
+        if (suggestions.members.length > 0) {
+            addHeading('Members');
+            suggestions.members.forEach(member => addItem(member.username, `/members/${member.id}/`));
+        }
+
+        dropdown.style.display = items.length > 0 ? 'block' : 'none';
+    }
+
+    function hideDropdown() {
+        if (dropdown) {
+            dropdown.style.display = 'none';
+        }
+        items = [];
+        selectedIndex = -1;
+    }
+
+    // Update selected item
+    function updateSelection(newIndex) {
+        if (items.length === 0) return;
+
+        selectedIndex = Math.max(-1, Math.min(newIndex, items.length - 1));
+        items.forEach((item, index) => {
+            item.classList.toggle('search-suggest-item--selected', index === selectedIndex);
+        });
+    }
+
+    // Debounced lookup
+    const debouncedSuggest = debounce(async (query) => {
+        const suggestions = await fetchSuggestions(query);
+        // Ignore answers to text the user has since changed
+        if (suggestions && query === lastQuery) {
+            renderDropdown(suggestions);
+        }
+    }, 200);
+
+    function handleInput() {
+        const query = input.value.trim();
+        lastQuery = query;
+
+        if (query.length >= MIN_LENGTH) {
+            debouncedSuggest(query);
+        } else {
+            hideDropdown();
+        }
+    }
+
+    function handleKeydown(e) {
+        if (!dropdown || dropdown.style.display === 'none') {
+            return;
+        }
+
+        switch (e.key) {
+            case 'ArrowDown':
+                e.preventDefault();
+                updateSelection(selectedIndex + 1);
+                break;
+
+            case 'ArrowUp':
+                e.preventDefault();
+                updateSelection(selectedIndex - 1);
+                break;
+
+            case 'Enter':
+                // With nothing selected, Enter runs a full search
+                if (selectedIndex >= 0) {
+                    e.preventDefault();
+                    window.location.href = items[selectedIndex].href;
+                }
+                break;
+
+            case 'Escape':
+                e.preventDefault();
+                hideDropdown();
+                break;
+        }
+    }
+
+    function handleBlur() {
+        // Delay to allow click on dropdown
+        setTimeout(hideDropdown, 200);
+    }
+
+    // Initialize
+    function init() {
+        input = document.querySelector('input[data-suggest-url]');
+        if (!input) return;
+
+        input.addEventListener('input', handleInput);
+        input.addEventListener('keydown', handleKeydown);
+        input.addEventListener('blur', handleBlur);
+    }
+
+    // Run on DOMContentLoaded
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', init);
+    } else {
+        init();
+    }
+
+})();
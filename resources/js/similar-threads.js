@@ -0,0 +1,98 @@
+/**
+ * Similar thread suggestions on the new thread form
+ * Lists existing threads with titles like the one being typed, so duplicate
+ * topics can be found before posting
+ */
+
+(function() {
+    'use strict';
+
+    // Debounce helper to limit API calls
+    function debounce(func, wait) {
+        let timeout;
+        return function executedFunction(...args) {
+            const later = () => {
+                clearTimeout(timeout);
+                func(...args);
+            };
+            clearTimeout(timeout);
+            timeout = setTimeout(later, wait);
+        };
+    }
+
+    const MIN_LENGTH = 4;
+
+    let input = null;
+    let container = null;
+    let lastTitle = '';
+
+    async function fetchSimilar(title) {
+        try {
+            const response = await fetch(`${input.dataset.similarUrl}?title=${encodeURIComponent(title)}`);
+            if (!response.ok) {
+                return null;
+            }
+            return await response.json();
+        } catch (e) {
+            console.error('Similar thread lookup error:', e);
+            return null;
+        }
+    }
+
+    function render(threads) {
+        const list = container.querySelector('ul');
+        list.replaceChildren();
+
+        threads.forEach(thread => {
+            const link = document.createElement('a');
+            link.href = `/threads/${thread.id}/`;
+            link.target = '_blank';
+            link.textContent = thread.title;
+
+            const item = document.createElement('li');
+            item.appendChild(link);
+            list.appendChild(item);
+        });
+
+        container.hidden = threads.length === 0;
+    }
+
+    const debouncedLookup = debounce(async (title) => {
+        const result = await fetchSimilar(title);
+        // Ignore answers to a title the user has since changed
+        if (result && title === lastTitle) {
+            render(result.threads);
+        }
+    }, 400);
+
+    function handleInput() {
+        const title = input.value.trim();
+        if (title === lastTitle) return;
+        lastTitle = title;
+
+        if (title.length >= MIN_LENGTH) {
+            debouncedLookup(title);
+        } else {
+            render([]);
+        }
+    }
+
+    // Initialize
+    function init() {
+        input = document.querySelector('input[data-similar-url]');
+        if (!input) return;
+        container = input.parentElement.querySelector('.similar-threads');
+        if (!container) return;
+
+        lastTitle = input.value.trim();
+        input.addEventListener('input', handleInput);
+    }
+
+    // Run on DOMContentLoaded
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', init);
+    } else {
+        init();
+    }
+
+})();
@@ -137,6 +137,11 @@ function renderGitHubCard(data) {
         html += `<div class="unfurl-title">${escapeHtml(data.title)}</div>`;
     }
 
+    // Author, from oEmbed
+    if (data.author_name) {
+        html += `<div class="unfurl-author">${escapeHtml(data.author_name)}</div>`;
+    }
+
     // Description
     if (data.description) {
         html += `<div class="unfurl-description">${escapeHtml(data.description)}</div>`;
@@ -156,7 +161,11 @@ function renderGenericCard(data) {
         return '';
     }
 
-    let html = '<div class="unfurl-card">';
+    // oEmbed providers say what kind of thing the link is
+    const embedClass = ['photo', 'video', 'rich'].includes(data.embed_type)
+        ? ` unfurl-card--${data.embed_type}`
+        : '';
+    let html = `<div class="unfurl-card${embedClass}">`;
 
     // Image (if available)
     if (data.image_url) {
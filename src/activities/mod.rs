@@ -1,10 +1,13 @@
 //! Activity feed system for tracking user actions
 
+pub mod retention;
+
 use crate::db::get_db_pool;
 use crate::orm::activities::{self, ActivityType};
 use chrono::{DateTime, Utc};
 use sea_orm::prelude::DateTimeWithTimeZone;
 use sea_orm::{entity::*, query::*, DbErr, Set};
+use serde::{Deserialize, Serialize};
 
 pub use crate::orm::activities::ActivityType as Type;
 
@@ -158,11 +161,26 @@ pub struct ActivityDisplay {
     pub title: Option<String>,
     pub content_preview: Option<String>,
     pub target_url: String,
+    pub target_thread_id: Option<i32>,
     pub reaction_emoji: Option<String>,
+    /// How many activities this entry stands for once aggregated
+    pub count: usize,
+}
+
+impl ActivityDisplay {
+    /// What the actor did, counting aggregated activities
+    pub fn description(&self) -> String {
+        match (self.count, &self.activity_type) {
+            (1, activity_type) => activity_type.description().to_string(),
+            (count, ActivityType::PostCreated) => format!("posted {} replies in", count),
+            (count, ActivityType::ReactionGiven) => format!("reacted to {} posts in", count),
+            (count, activity_type) => format!("{} ({} times)", activity_type.description(), count),
+        }
+    }
 }
 
 /// Pagination cursor for activity feeds
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActivityCursor {
     pub created_at: DateTime<Utc>,
     pub id: i32,
@@ -170,22 +188,13 @@ pub struct ActivityCursor {
 
 impl ActivityCursor {
     pub fn parse(s: &str) -> Option<Self> {
-        let parts: Vec<&str> = s.split('_').collect();
-        if parts.len() != 2 {
-            return None;
-        }
-        let timestamp = parts[0].parse::<i64>().ok()?;
-        let id = parts[1].parse::<i32>().ok()?;
-        Some(Self {
-            created_at: DateTime::from_timestamp(timestamp, 0)?,
-            id,
-        })
+        crate::pagination::decode(s)
     }
 }
 
 impl std::fmt::Display for ActivityCursor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}_{}", self.created_at.timestamp(), self.id)
+        f.write_str(&crate::pagination::encode(self))
     }
 }
 
@@ -193,28 +202,100 @@ impl std::fmt::Display for ActivityCursor {
 // Activity Query Functions
 // =============================================================================
 
-/// Get personal feed (activities from users you follow)
-pub async fn get_personal_feed(
-    user_id: i32,
+/// What a viewer may see of a feed
+#[derive(Debug, Clone, Default)]
+pub struct FeedFilter {
+    /// The member viewing the feed, who always sees their own activity
+    pub viewer_id: Option<i32>,
+    /// Only activities of this type
+    pub activity_type: Option<ActivityType>,
+    /// Forums the viewer can't see; activities in them are left out
+    pub hidden_forum_ids: Vec<i32>,
+    /// Threads the viewer can't see because of their own overrides
+    pub hidden_thread_ids: Vec<i32>,
+}
+
+/// Bind `ids` after those already in `values`, returning their placeholders
+fn bind_ids(values: &mut Vec<sea_orm::Value>, ids: &[i32]) -> String {
+    ids.iter()
+        .map(|id| {
+            values.push((*id).into());
+            format!("${}", values.len())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl FeedFilter {
+    /// SQL conditions on `activities a` and its `threads target_thread`,
+    /// binding their values after those already in `values`
+    fn conditions(&self, values: &mut Vec<sea_orm::Value>) -> String {
+        const PUBLISHED: &str = "NOT EXISTS (
+            SELECT 1 FROM user_hidden_activity_types h
+            WHERE h.user_id = a.user_id AND h.activity_type = a.activity_type
+        )";
+
+        let mut conditions = Vec::new();
+        match self.viewer_id {
+            Some(viewer_id) => {
+                values.push(viewer_id.into());
+                conditions.push(format!("(a.user_id = ${} OR {})", values.len(), PUBLISHED));
+            }
+            None => conditions.push(PUBLISHED.to_owned()),
+        }
+        if let Some(activity_type) = &self.activity_type {
+            values.push(activity_type.as_str().into());
+            conditions.push(format!(
+                "a.activity_type = ${}::activity_type",
+                values.len()
+            ));
+        }
+        if !self.hidden_forum_ids.is_empty() {
+            // Threads may have moved since the activity was recorded, so
+            // their current forum decides. Follows and profile posts are in
+            // no forum at all and always pass.
+            conditions.push(format!(
+                "(COALESCE(target_thread.forum_id, a.target_forum_id) IS NULL
+                  OR COALESCE(target_thread.forum_id, a.target_forum_id) NOT IN ({}))",
+                bind_ids(values, &self.hidden_forum_ids)
+            ));
+        }
+        if !self.hidden_thread_ids.is_empty() {
+            conditions.push(format!(
+                "(a.target_thread_id IS NULL OR a.target_thread_id NOT IN ({}))",
+                bind_ids(values, &self.hidden_thread_ids)
+            ));
+        }
+        conditions.join(" AND ")
+    }
+}
+
+/// One page of a feed. `condition` selects the feed's activities from
+/// `activities a` and anything in `joins`, using `values` as `$1`, `$2`...
+async fn query_feed(
+    joins: &str,
+    condition: &str,
+    mut values: Vec<sea_orm::Value>,
+    filter: &FeedFilter,
     cursor: Option<ActivityCursor>,
     limit: u64,
 ) -> Result<Vec<ActivityDisplay>, DbErr> {
     use sea_orm::{DbBackend, Statement};
 
-    let db = get_db_pool();
-
-    let (cursor_clause, values) = match &cursor {
-        Some(c) => (
-            "AND (a.created_at, a.id) < ($3, $4)",
-            vec![
-                user_id.into(),
-                (limit as i64).into(),
-                c.created_at.into(),
-                c.id.into(),
-            ],
-        ),
-        None => ("", vec![user_id.into(), (limit as i64).into()]),
+    let filter_conditions = filter.conditions(&mut values);
+    let cursor_clause = match cursor {
+        Some(c) => {
+            values.push(c.created_at.into());
+            values.push(c.id.into());
+            format!(
+                "AND (a.created_at, a.id) < (${}, ${})",
+                values.len() - 1,
+                values.len()
+            )
+        }
+        None => String::new(),
     };
+    values.push((limit as i64).into());
 
     let sql = format!(
         r#"
@@ -232,19 +313,25 @@ pub async fn get_personal_feed(
             a.target_user_id,
             a.reaction_emoji
         FROM activities a
-        JOIN user_follows uf ON a.user_id = uf.following_id
+        {}
+        LEFT JOIN threads target_thread ON target_thread.id = a.target_thread_id
         LEFT JOIN user_names un ON un.user_id = a.user_id
         LEFT JOIN user_avatars ua ON ua.user_id = a.user_id
         LEFT JOIN attachments att ON att.id = ua.attachment_id
-        WHERE uf.follower_id = $1
+        WHERE {}
+          AND {}
         {}
         ORDER BY a.created_at DESC, a.id DESC
-        LIMIT $2
+        LIMIT ${}
         "#,
-        cursor_clause
+        joins,
+        condition,
+        filter_conditions,
+        cursor_clause,
+        values.len()
     );
 
-    let results = db
+    let results = get_db_pool()
         .query_all(Statement::from_sql_and_values(
             DbBackend::Postgres,
             &sql,
@@ -255,121 +342,149 @@ pub async fn get_personal_feed(
     Ok(results.iter().map(parse_activity_row).collect())
 }
 
+/// Get personal feed (activities from users you follow)
+pub async fn get_personal_feed(
+    user_id: i32,
+    filter: &FeedFilter,
+    cursor: Option<ActivityCursor>,
+    limit: u64,
+) -> Result<Vec<ActivityDisplay>, DbErr> {
+    query_feed(
+        "JOIN user_follows uf ON a.user_id = uf.following_id",
+        "uf.follower_id = $1",
+        vec![user_id.into()],
+        filter,
+        cursor,
+        limit,
+    )
+    .await
+}
+
 /// Get user profile feed (specific user's activities)
 pub async fn get_user_feed(
     profile_user_id: i32,
+    filter: &FeedFilter,
     cursor: Option<ActivityCursor>,
     limit: u64,
 ) -> Result<Vec<ActivityDisplay>, DbErr> {
-    use sea_orm::{DbBackend, Statement};
-
-    let db = get_db_pool();
-
-    let (cursor_clause, values) = match &cursor {
-        Some(c) => (
-            "AND (a.created_at, a.id) < ($3, $4)",
-            vec![
-                profile_user_id.into(),
-                (limit as i64).into(),
-                c.created_at.into(),
-                c.id.into(),
-            ],
-        ),
-        None => ("", vec![profile_user_id.into(), (limit as i64).into()]),
-    };
-
-    let sql = format!(
-        r#"
-        SELECT
-            a.id,
-            a.activity_type::text,
-            a.created_at,
-            a.user_id as actor_id,
-            un.name as actor_name,
-            att.filename as actor_avatar,
-            a.title,
-            a.content_preview,
-            a.target_thread_id,
-            a.target_post_id,
-            a.target_user_id,
-            a.reaction_emoji
-        FROM activities a
-        LEFT JOIN user_names un ON un.user_id = a.user_id
-        LEFT JOIN user_avatars ua ON ua.user_id = a.user_id
-        LEFT JOIN attachments att ON att.id = ua.attachment_id
-        WHERE a.user_id = $1
-        {}
-        ORDER BY a.created_at DESC, a.id DESC
-        LIMIT $2
-        "#,
-        cursor_clause
-    );
-
-    let results = db
-        .query_all(Statement::from_sql_and_values(
-            DbBackend::Postgres,
-            &sql,
-            values,
-        ))
-        .await?;
-
-    Ok(results.iter().map(parse_activity_row).collect())
+    query_feed(
+        "",
+        "a.user_id = $1",
+        vec![profile_user_id.into()],
+        filter,
+        cursor,
+        limit,
+    )
+    .await
 }
 
 /// Get global feed (all site activity, respecting privacy)
 pub async fn get_global_feed(
+    filter: &FeedFilter,
     cursor: Option<ActivityCursor>,
     limit: u64,
 ) -> Result<Vec<ActivityDisplay>, DbErr> {
-    use sea_orm::{DbBackend, Statement};
+    query_feed(
+        "JOIN users u ON a.user_id = u.id",
+        "u.show_online = TRUE",
+        vec![],
+        filter,
+        cursor,
+        limit,
+    )
+    .await
+}
 
-    let db = get_db_pool();
+// =============================================================================
+// Activity Aggregation
+// =============================================================================
 
-    let (cursor_clause, values) = match &cursor {
-        Some(c) => (
-            "AND (a.created_at, a.id) < ($2, $3)",
-            vec![(limit as i64).into(), c.created_at.into(), c.id.into()],
-        ),
-        None => ("", vec![(limit as i64).into()]),
-    };
+/// Activities further apart than this are never collapsed together
+const AGGREGATE_WINDOW_HOURS: i64 = 6;
+
+/// Collapse bursts of replies or reactions by one actor in one thread into a
+/// single entry. Feeds are newest first, so each entry keeps its newest
+/// activity and counts the older ones that follow within the window.
+pub fn aggregate(activities: Vec<ActivityDisplay>) -> Vec<ActivityDisplay> {
+    use std::collections::HashMap;
+
+    let window = chrono::Duration::hours(AGGREGATE_WINDOW_HOURS);
+    let mut entries: Vec<ActivityDisplay> = Vec::with_capacity(activities.len());
+    // Index of the open entry for each burst, and when its oldest activity was
+    let mut open: HashMap<(i32, i32, &'static str), (usize, DateTime<Utc>)> = HashMap::new();
+
+    for activity in activities {
+        let key = match (&activity.activity_type, activity.target_thread_id) {
+            (ActivityType::PostCreated | ActivityType::ReactionGiven, Some(thread_id)) => Some((
+                activity.actor_id,
+                thread_id,
+                activity.activity_type.as_str(),
+            )),
+            _ => None,
+        };
+
+        if let Some(key) = key {
+            if let Some((index, oldest)) = open.get_mut(&key) {
+                if *oldest - activity.created_at <= window {
+                    entries[*index].count += 1;
+                    *oldest = activity.created_at;
+                    continue;
+                }
+            }
+            open.insert(key, (entries.len(), activity.created_at));
+        }
+        entries.push(activity);
+    }
 
-    let sql = format!(
-        r#"
-        SELECT
-            a.id,
-            a.activity_type::text,
-            a.created_at,
-            a.user_id as actor_id,
-            un.name as actor_name,
-            att.filename as actor_avatar,
-            a.title,
-            a.content_preview,
-            a.target_thread_id,
-            a.target_post_id,
-            a.target_user_id,
-            a.reaction_emoji
-        FROM activities a
-        JOIN users u ON a.user_id = u.id
-        LEFT JOIN user_names un ON un.user_id = a.user_id
-        LEFT JOIN user_avatars ua ON ua.user_id = a.user_id
-        LEFT JOIN attachments att ON att.id = ua.attachment_id
-        WHERE u.show_online = TRUE
-        {}
-        ORDER BY a.created_at DESC, a.id DESC
-        LIMIT $1
-        "#,
-        cursor_clause
-    );
+    entries
+}
 
-    let results = db
-        .query_all(Statement::from_sql_and_values(
+// =============================================================================
+// Activity Privacy
+// =============================================================================
+
+/// Activity types the user keeps off the feeds
+pub async fn get_hidden_types(user_id: i32) -> Result<Vec<ActivityType>, DbErr> {
+    use sea_orm::{DbBackend, FromQueryResult, Statement};
+
+    #[derive(FromQueryResult)]
+    struct HiddenType {
+        activity_type: String,
+    }
+
+    Ok(HiddenType::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT activity_type::text AS activity_type FROM user_hidden_activity_types WHERE user_id = $1",
+        vec![user_id.into()],
+    ))
+    .all(get_db_pool())
+    .await?
+    .into_iter()
+    .filter_map(|row| ActivityType::parse(&row.activity_type))
+    .collect())
+}
+
+/// Replace the activity types the user keeps off the feeds
+pub async fn set_hidden_types(user_id: i32, hidden: &[ActivityType]) -> Result<(), DbErr> {
+    use sea_orm::{DbBackend, Statement, TransactionTrait};
+
+    let txn = get_db_pool().begin().await?;
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "DELETE FROM user_hidden_activity_types WHERE user_id = $1",
+        vec![user_id.into()],
+    ))
+    .await?;
+    for activity_type in hidden {
+        txn.execute(Statement::from_sql_and_values(
             DbBackend::Postgres,
-            &sql,
-            values,
+            "INSERT INTO user_hidden_activity_types (user_id, activity_type)
+             VALUES ($1, $2::activity_type) ON CONFLICT DO NOTHING",
+            vec![user_id.into(), activity_type.as_str().into()],
         ))
         .await?;
-
-    Ok(results.iter().map(parse_activity_row).collect())
+    }
+    txn.commit().await
 }
 
 /// Parse a database row into an ActivityDisplay
@@ -378,13 +493,8 @@ fn parse_activity_row(row: &sea_orm::QueryResult) -> ActivityDisplay {
         .try_get::<String>("", "activity_type")
         .unwrap_or_else(|_| "post_created".to_string());
 
-    let activity_type = match activity_type_str.as_str() {
-        "thread_created" => ActivityType::ThreadCreated,
-        "profile_post_created" => ActivityType::ProfilePostCreated,
-        "user_followed" => ActivityType::UserFollowed,
-        "reaction_given" => ActivityType::ReactionGiven,
-        _ => ActivityType::PostCreated,
-    };
+    let activity_type =
+        ActivityType::parse(&activity_type_str).unwrap_or(ActivityType::PostCreated);
 
     let id: i32 = row.try_get("", "id").unwrap_or(0);
     let target_thread_id: Option<i32> = row.try_get("", "target_thread_id").ok();
@@ -436,9 +546,69 @@ fn parse_activity_row(row: &sea_orm::QueryResult) -> ActivityDisplay {
             .ok()
             .flatten(),
         target_url,
+        target_thread_id,
         reaction_emoji: row
             .try_get::<Option<String>>("", "reaction_emoji")
             .ok()
             .flatten(),
+        count: 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activity(
+        id: i32,
+        actor_id: i32,
+        activity_type: ActivityType,
+        thread_id: i32,
+        minutes_ago: i64,
+    ) -> ActivityDisplay {
+        ActivityDisplay {
+            id,
+            activity_type,
+            created_at: Utc::now() - chrono::Duration::minutes(minutes_ago),
+            actor_id,
+            actor_name: "alice".to_string(),
+            actor_avatar: None,
+            title: Some("Thread".to_string()),
+            content_preview: None,
+            target_url: format!("/threads/{}/", thread_id),
+            target_thread_id: Some(thread_id),
+            reaction_emoji: None,
+            count: 1,
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let entries = aggregate(vec![
+            activity(9, 1, ActivityType::PostCreated, 10, 0),
+            activity(8, 2, ActivityType::PostCreated, 10, 5),
+            activity(7, 1, ActivityType::PostCreated, 10, 10),
+            activity(6, 1, ActivityType::ReactionGiven, 10, 15),
+            activity(5, 1, ActivityType::PostCreated, 11, 20),
+            activity(4, 1, ActivityType::PostCreated, 10, 25),
+            activity(3, 1, ActivityType::ThreadCreated, 10, 30),
+            activity(2, 1, ActivityType::ThreadCreated, 10, 35),
+            // More than the window after the burst's oldest reply
+            activity(
+                1,
+                1,
+                ActivityType::PostCreated,
+                10,
+                25 + 60 * AGGREGATE_WINDOW_HOURS + 1,
+            ),
+        ]);
+
+        let summary: Vec<(i32, usize)> = entries.iter().map(|a| (a.id, a.count)).collect();
+        assert_eq!(
+            summary,
+            vec![(9, 3), (8, 1), (6, 1), (5, 1), (3, 1), (2, 1), (1, 1)]
+        );
+        assert_eq!(entries[0].description(), "posted 3 replies in");
+        assert_eq!(entries[1].description(), "posted a reply");
     }
 }
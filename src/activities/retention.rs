@@ -0,0 +1,167 @@
+//! Activity retention
+//!
+//! Feeds only page through recent activity, but every post, reaction and
+//! follow adds a row to `activities`. Activities older than
+//! `[activities] retention_days` are moved a day at a time into
+//! `activity_archives`, split into chunks so no archive row grows without
+//! bound, or deleted when archiving is turned off.
+
+use crate::db::get_db_pool;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use sea_orm::{ConnectionTrait, DbBackend, DbErr, FromQueryResult, Statement};
+
+/// How often the worker looks for activities past the horizon
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Most activities deleted in one statement when not archiving
+const DELETE_BATCH_SIZE: i64 = 10000;
+
+/// Most activities stored in one archive row
+const ARCHIVE_CHUNK_SIZE: i64 = 1000;
+
+/// Moves up to `$4` of one day's activities into a new archive row for the
+/// day, numbered after any chunks archived before. Counts the activities
+/// moved.
+const ARCHIVE_CHUNK_SQL: &str = r#"WITH moved AS (
+        DELETE FROM activities WHERE id IN (
+            SELECT id FROM activities
+            WHERE created_at >= $1 AND created_at < $2
+            ORDER BY id
+            LIMIT $4
+        )
+        RETURNING *
+    ), archived AS (
+        INSERT INTO activity_archives (day, chunk, activity_count, activities)
+        SELECT
+            $3,
+            (SELECT COALESCE(MAX(chunk) + 1, 0) FROM activity_archives WHERE day = $3),
+            COUNT(*),
+            jsonb_agg(to_jsonb(moved) ORDER BY moved.id)
+        FROM moved
+        HAVING COUNT(*) > 0
+    )
+    SELECT COUNT(*) AS count FROM moved"#;
+
+#[derive(FromQueryResult)]
+struct Count {
+    count: i64,
+}
+
+#[derive(FromQueryResult)]
+struct OldestDay {
+    day: Option<NaiveDate>,
+}
+
+/// Start of the first day kept when activities are kept `retention_days`
+fn horizon(now: DateTime<Utc>, retention_days: i64) -> DateTime<Utc> {
+    let day = (now - Duration::days(retention_days)).date_naive();
+    Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap())
+}
+
+/// Move every activity from before `cutoff` into the archive, a chunk of a
+/// day at a time. `cutoff` should fall on midnight UTC so no day is split.
+/// Returns the number of activities moved.
+pub async fn archive_before(cutoff: DateTime<Utc>) -> Result<u64, DbErr> {
+    let db = get_db_pool();
+    let mut moved = 0;
+
+    loop {
+        let oldest = OldestDay::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT (MIN(created_at) AT TIME ZONE 'UTC')::date AS day
+             FROM activities WHERE created_at < $1",
+            vec![cutoff.into()],
+        ))
+        .one(db)
+        .await?
+        .and_then(|row| row.day);
+        let day = match oldest {
+            Some(day) => day,
+            None => return Ok(moved),
+        };
+
+        let start = Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap());
+        let end = (start + Duration::days(1)).min(cutoff);
+        let count = Count::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            ARCHIVE_CHUNK_SQL,
+            vec![
+                start.into(),
+                end.into(),
+                day.into(),
+                ARCHIVE_CHUNK_SIZE.into(),
+            ],
+        ))
+        .one(db)
+        .await?
+        .map_or(0, |row| row.count);
+        moved += count as u64;
+    }
+}
+
+/// Delete every activity from before `cutoff`. Returns the number deleted.
+pub async fn delete_before(cutoff: DateTime<Utc>) -> Result<u64, DbErr> {
+    let db = get_db_pool();
+    let mut deleted = 0;
+
+    loop {
+        let result = db
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "DELETE FROM activities WHERE id IN (
+                    SELECT id FROM activities WHERE created_at < $1 LIMIT $2
+                )",
+                vec![cutoff.into(), DELETE_BATCH_SIZE.into()],
+            ))
+            .await?;
+        deleted += result.rows_affected();
+        if result.rows_affected() < DELETE_BATCH_SIZE as u64 {
+            return Ok(deleted);
+        }
+    }
+}
+
+/// Archive or delete activities past the configured horizon. Returns the
+/// number of activities taken off the feeds.
+pub async fn apply(now: DateTime<Utc>) -> Result<u64, DbErr> {
+    let config = crate::app_config::activities();
+    if config.retention_days <= 0 {
+        return Ok(0);
+    }
+
+    let cutoff = horizon(now, config.retention_days);
+    if config.archive {
+        archive_before(cutoff).await
+    } else {
+        delete_before(cutoff).await
+    }
+}
+
+/// Start the activity retention worker
+pub fn spawn_worker() {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match apply(Utc::now()).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Retired {} activities past the retention horizon", count),
+                Err(e) => log::error!("Failed to retire old activities: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_horizon() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 10, 15, 30, 0).unwrap();
+        assert_eq!(
+            horizon(now, 30),
+            Utc.with_ymd_and_hms(2026, 2, 8, 0, 0, 0).unwrap()
+        );
+    }
+}
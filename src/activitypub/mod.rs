@@ -0,0 +1,616 @@
+//! ActivityPub publishing.
+//!
+//! With `activitypub.enabled` set, every forum an administrator marks as
+//! federated gets a `Group` actor, `@forum-{id}@{host}`, that Fediverse users
+//! can find through WebFinger and follow. When a thread guests can read is
+//! started in such a forum, a `Create` activity wrapping a `Note` is queued
+//! for each follower's inbox (or their server's shared inbox) and sent by the
+//! job queue, signed with the forum's key.
+//!
+//! Publishing is one way. The inbox accepts follows and unfollows, checking
+//! the sender's HTTP signature, and ignores everything else.
+
+pub mod signatures;
+
+use crate::db::get_db_pool;
+use crate::jobs::{self, Job, JobKind};
+use crate::middleware::ClientCtx;
+use crate::orm::{activitypub_followers, activitypub_keys, forums, threads};
+use crate::web::oembed::{escape_html, find_embed, EmbedTarget};
+use chrono::{NaiveDateTime, SecondsFormat, Utc};
+use sea_orm::{entity::*, query::*, ConnectionTrait, DbBackend, DbErr, Statement};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+/// Media type of ActivityPub documents
+pub const CONTENT_TYPE: &str = "application/activity+json";
+
+/// Audience that means anyone
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+/// Attempts at delivering an activity to one inbox
+const DELIVERY_ATTEMPTS: i32 = 8;
+
+/// Most recent threads listed in an outbox
+const OUTBOX_LIMIT: u64 = 20;
+
+/// Seconds to wait for a remote server
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+/// Redirects followed when fetching a document
+const MAX_REDIRECTS: usize = 3;
+
+/// Largest remote document read (1MB)
+const MAX_DOCUMENT_SIZE: usize = 1024 * 1024;
+
+pub fn is_enabled() -> bool {
+    crate::app_config::activitypub().enabled
+}
+
+fn base_url() -> String {
+    crate::notifications::dispatcher::get_base_url()
+}
+
+/// Host, and port if any, that actor handles are qualified with.
+pub fn authority(base_url: &str) -> Option<String> {
+    let url = url::Url::parse(base_url).ok()?;
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    })
+}
+
+/// Username of a forum's actor.
+pub fn username(forum_id: i32) -> String {
+    format!("forum-{}", forum_id)
+}
+
+pub fn actor_url(forum_id: i32) -> String {
+    format!("{}/ap/forums/{}", base_url(), forum_id)
+}
+
+pub fn note_url(thread_id: i32) -> String {
+    format!("{}/ap/threads/{}", base_url(), thread_id)
+}
+
+fn followers_url(forum_id: i32) -> String {
+    format!("{}/followers", actor_url(forum_id))
+}
+
+/// The forum a WebFinger resource names, either as `acct:forum-{id}@{host}`
+/// or by its actor URL.
+pub fn parse_resource(resource: &str, base_url: &str) -> Option<i32> {
+    match resource.strip_prefix("acct:") {
+        Some(account) => {
+            let (name, host) = account.split_once('@')?;
+            if !authority(base_url).is_some_and(|ours| ours.eq_ignore_ascii_case(host)) {
+                return None;
+            }
+            name.strip_prefix("forum-")?.parse().ok()
+        }
+        None => resource
+            .strip_prefix(&format!("{}/ap/forums/", base_url))?
+            .parse()
+            .ok(),
+    }
+}
+
+/// Adds the ActivityStreams context to a top-level document.
+pub fn document(mut value: Value) -> Value {
+    if let Some(object) = value.as_object_mut() {
+        object.insert(
+            "@context".to_owned(),
+            json!("https://www.w3.org/ns/activitystreams"),
+        );
+    }
+    value
+}
+
+fn rfc3339(at: NaiveDateTime) -> String {
+    at.and_utc().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// A forum that has an actor: federation is on, the forum has opted in, and
+/// guests can read it.
+pub async fn federated_forum(
+    guest: &ClientCtx,
+    forum_id: i32,
+) -> Result<Option<forums::Model>, DbErr> {
+    if !is_enabled() || !guest.can_view_forum(&forum_id) {
+        return Ok(None);
+    }
+
+    Ok(forums::Entity::find_by_id(forum_id)
+        .one(get_db_pool())
+        .await?
+        .filter(|forum| forum.is_federated))
+}
+
+/// A forum's key pair, made the first time it is needed.
+pub async fn forum_key(forum_id: i32) -> Result<activitypub_keys::Model, DbErr> {
+    let db = get_db_pool();
+    if let Some(key) = activitypub_keys::Entity::find_by_id(forum_id)
+        .one(db)
+        .await?
+    {
+        return Ok(key);
+    }
+
+    let (public_key_pem, private_key_pem) = actix_web::web::block(signatures::generate_key_pair)
+        .await
+        .map_err(|e| DbErr::Custom(e.to_string()))?
+        .map_err(DbErr::Custom)?;
+
+    // Another request may have made one meanwhile, in which case it stands.
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"INSERT INTO activitypub_keys (forum_id, public_key_pem, private_key_pem)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (forum_id) DO NOTHING"#,
+        vec![
+            forum_id.into(),
+            public_key_pem.into(),
+            private_key_pem.into(),
+        ],
+    ))
+    .await?;
+
+    activitypub_keys::Entity::find_by_id(forum_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound("activitypub_keys".to_owned()))
+}
+
+/// The actor document for a forum.
+pub fn actor(forum: &forums::Model, key: &activitypub_keys::Model) -> Value {
+    let id = actor_url(forum.id);
+    json!({
+        "@context": [
+            "https://www.w3.org/ns/activitystreams",
+            "https://w3id.org/security/v1",
+        ],
+        "id": id,
+        "type": "Group",
+        "preferredUsername": username(forum.id),
+        "name": forum.label,
+        "summary": forum.description.as_deref().map(escape_html).unwrap_or_default(),
+        "url": format!("{}/forums/{}/", base_url(), forum.id),
+        "inbox": format!("{}/inbox", id),
+        "outbox": format!("{}/outbox", id),
+        "followers": followers_url(forum.id),
+        "manuallyApprovesFollowers": false,
+        "discoverable": true,
+        "publicKey": {
+            "id": format!("{}#main-key", id),
+            "owner": id,
+            "publicKeyPem": key.public_key_pem,
+        },
+    })
+}
+
+/// The Note for a thread, if guests can read it.
+pub async fn note(guest: &ClientCtx, thread: &threads::Model) -> Result<Option<Value>, DbErr> {
+    let Some(embed) = find_embed(guest, EmbedTarget::Thread(thread.id)).await? else {
+        return Ok(None);
+    };
+
+    let mut content = format!("<p><strong>{}</strong></p>", escape_html(&embed.title));
+    if !embed.excerpt.is_empty() {
+        content.push_str(&format!("<p>{}</p>", escape_html(&embed.excerpt)));
+    }
+    let byline = match &embed.author_name {
+        Some(name) => format!("Started by {} &middot; ", escape_html(name)),
+        None => String::new(),
+    };
+    content.push_str(&format!(
+        "<p>{}<a href=\"{}\">{}</a></p>",
+        byline,
+        escape_html(&embed.url),
+        escape_html(&embed.url)
+    ));
+
+    Ok(Some(json!({
+        "id": note_url(thread.id),
+        "type": "Note",
+        "attributedTo": actor_url(thread.forum_id),
+        "content": content,
+        "url": embed.url,
+        "published": rfc3339(thread.created_at),
+        "to": [PUBLIC],
+        "cc": [followers_url(thread.forum_id)],
+    })))
+}
+
+/// The activity that publishes a Note.
+pub fn create_activity(forum_id: i32, note: Value) -> Value {
+    json!({
+        "id": format!("{}/activity", note["id"].as_str().unwrap_or_default()),
+        "type": "Create",
+        "actor": actor_url(forum_id),
+        "published": note["published"],
+        "to": [PUBLIC],
+        "cc": [followers_url(forum_id)],
+        "object": note,
+    })
+}
+
+/// A forum's most recent threads, as an outbox collection.
+pub async fn outbox(guest: &ClientCtx, forum_id: i32) -> Result<Value, DbErr> {
+    let select = threads::Entity::find()
+        .filter(threads::Column::ForumId.eq(forum_id))
+        .filter(threads::Column::DeletedAt.is_null());
+    let total = select.clone().count(get_db_pool()).await?;
+
+    let mut items = Vec::new();
+    for thread in select
+        .order_by_desc(threads::Column::Id)
+        .limit(OUTBOX_LIMIT)
+        .all(get_db_pool())
+        .await?
+    {
+        if let Some(note) = note(guest, &thread).await? {
+            items.push(create_activity(forum_id, note));
+        }
+    }
+
+    Ok(json!({
+        "id": format!("{}/outbox", actor_url(forum_id)),
+        "type": "OrderedCollection",
+        "totalItems": total,
+        "orderedItems": items,
+    }))
+}
+
+/// A forum's followers collection. Only the count is shown.
+pub async fn followers(forum_id: i32) -> Result<Value, DbErr> {
+    let total = activitypub_followers::Entity::find()
+        .filter(activitypub_followers::Column::ForumId.eq(forum_id))
+        .count(get_db_pool())
+        .await?;
+
+    Ok(json!({
+        "id": followers_url(forum_id),
+        "type": "OrderedCollection",
+        "totalItems": total,
+    }))
+}
+
+/// Queues an activity to be signed and sent to an inbox.
+async fn queue_delivery(forum_id: i32, inbox: &str, activity: &Value) -> Result<(), DbErr> {
+    jobs::enqueue(
+        JobKind::ActivityPubDelivery,
+        json!({ "forum_id": forum_id, "inbox": inbox, "activity": activity }),
+        DELIVERY_ATTEMPTS,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Sends a new thread to the followers of its forum. Returns the number of
+/// deliveries queued.
+async fn publish_thread(guest: &ClientCtx, forum_id: i32, thread_id: i32) -> Result<usize, DbErr> {
+    if federated_forum(guest, forum_id).await?.is_none() {
+        return Ok(0);
+    }
+    let Some(thread) = threads::Entity::find_by_id(thread_id)
+        .one(get_db_pool())
+        .await?
+    else {
+        return Ok(0);
+    };
+    let Some(note) = note(guest, &thread).await? else {
+        return Ok(0);
+    };
+    let activity = create_activity(forum_id, note);
+
+    // Followers on a server with a shared inbox share one delivery.
+    let inboxes: BTreeSet<String> = activitypub_followers::Entity::find()
+        .filter(activitypub_followers::Column::ForumId.eq(forum_id))
+        .all(get_db_pool())
+        .await?
+        .into_iter()
+        .map(|follower| follower.shared_inbox_url.unwrap_or(follower.inbox_url))
+        .collect();
+
+    for inbox in &inboxes {
+        queue_delivery(forum_id, inbox, &activity).await?;
+    }
+
+    Ok(inboxes.len())
+}
+
+/// A visible thread was started. Publishes it in the background if its forum
+/// is federated.
+pub fn thread_created(client: &ClientCtx, forum_id: i32, thread_id: i32) {
+    if !is_enabled() {
+        return;
+    }
+
+    let client = client.clone();
+    actix::spawn(async move {
+        let guest = client.as_guest().await;
+        if let Err(e) = publish_thread(&guest, forum_id, thread_id).await {
+            log::error!(
+                "Failed to publish thread {} over ActivityPub: {}",
+                thread_id,
+                e
+            );
+        }
+    });
+}
+
+/// The parts of a remote actor we use.
+#[derive(Debug)]
+pub struct RemoteActor {
+    pub id: String,
+    pub inbox: String,
+    pub shared_inbox: Option<String>,
+    pub public_key_pem: String,
+}
+
+/// A client for `url` that only connects to the public address its host was
+/// checked to resolve to, and follows no redirects.
+async fn http_client(url: &url::Url) -> Result<reqwest::Client, String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("unsupported URL {}", url));
+    }
+    crate::ssrf::pinned_client(
+        url,
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .user_agent("Dumpster-ActivityPub/1.0"),
+    )
+    .await
+}
+
+/// Fetches an ActivityPub document, following redirects by hand so that
+/// every hop is checked.
+async fn fetch_document(url: &str) -> Result<Value, String> {
+    let mut url = url::Url::parse(url).map_err(|_| format!("invalid URL {:?}", url))?;
+
+    let mut redirects = 0;
+    let response = loop {
+        let response = http_client(&url)
+            .await?
+            .get(url.clone())
+            .header(
+                reqwest::header::ACCEPT,
+                "application/activity+json, application/ld+json; profile=\"https://www.w3.org/ns/activitystreams\"",
+            )
+            .send()
+            .await
+            .map_err(|e| format!("failed to fetch {}: {}", url, e))?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+
+        redirects += 1;
+        if redirects > MAX_REDIRECTS {
+            return Err(format!("{} redirects too many times", url));
+        }
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| format!("{} redirects without a location", url))?;
+        url = url
+            .join(location)
+            .map_err(|_| format!("{} redirects to an invalid URL", url))?;
+    };
+    if !response.status().is_success() {
+        return Err(format!("{} answered {}", url, response.status()));
+    }
+
+    let body = crate::ssrf::read_limited(response, MAX_DOCUMENT_SIZE)
+        .await
+        .map_err(|e| format!("failed to read {}: {}", url, e))?;
+
+    serde_json::from_slice(&body).map_err(|e| format!("{} is not JSON: {}", url, e))
+}
+
+/// Fetches the actor that owns the key `key_id`.
+pub async fn fetch_signer(key_id: &str) -> Result<RemoteActor, String> {
+    let key_url = key_id.split('#').next().unwrap_or(key_id);
+    let mut actor = fetch_document(key_url).await?;
+
+    // Most servers serve the key inside the actor, but some give keys
+    // documents of their own that name the owner.
+    if actor.get("publicKey").is_none() {
+        let owner = actor["owner"]
+            .as_str()
+            .ok_or("key names no owner")?
+            .to_owned();
+        actor = fetch_document(&owner).await?;
+    }
+
+    let field = |value: &Value, name: &str| {
+        value[name]
+            .as_str()
+            .map(str::to_owned)
+            .ok_or_else(|| format!("actor has no {}", name))
+    };
+    let id = field(&actor, "id")?;
+    let key = &actor["publicKey"];
+    if key["id"].as_str() != Some(key_id) || key["owner"].as_str() != Some(id.as_str()) {
+        return Err(format!("{} is not the key of {}", key_id, id));
+    }
+    // An actor can only speak for its own server.
+    let host = |url: &str| {
+        url::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned))
+    };
+    if host(&id).is_none() || host(&id) != host(key_id) {
+        return Err(format!("{} is not on the same server as {}", key_id, id));
+    }
+
+    Ok(RemoteActor {
+        inbox: field(&actor, "inbox")?,
+        shared_inbox: actor["endpoints"]["sharedInbox"]
+            .as_str()
+            .map(str::to_owned),
+        public_key_pem: field(key, "publicKeyPem")?,
+        id,
+    })
+}
+
+/// The id of an activity's object, given inline or by reference.
+fn object_id(object: &Value) -> Option<&str> {
+    object.as_str().or_else(|| object["id"].as_str())
+}
+
+/// Handles an activity `sender` signed and sent to a forum's inbox.
+pub async fn receive(forum_id: i32, sender: &RemoteActor, activity: &Value) -> Result<(), DbErr> {
+    let actor = actor_url(forum_id);
+
+    match activity["type"].as_str() {
+        Some("Follow") if object_id(&activity["object"]) == Some(actor.as_str()) => {
+            get_db_pool()
+                .execute(Statement::from_sql_and_values(
+                    DbBackend::Postgres,
+                    r#"INSERT INTO activitypub_followers (forum_id, actor_uri, inbox_url, shared_inbox_url)
+                        VALUES ($1, $2, $3, $4)
+                        ON CONFLICT (forum_id, actor_uri)
+                        DO UPDATE SET inbox_url = EXCLUDED.inbox_url,
+                                      shared_inbox_url = EXCLUDED.shared_inbox_url"#,
+                    vec![
+                        forum_id.into(),
+                        sender.id.clone().into(),
+                        sender.inbox.clone().into(),
+                        sender.shared_inbox.clone().into(),
+                    ],
+                ))
+                .await?;
+
+            let accept = json!({
+                "id": format!("{}#accepts/{}", actor, uuid::Uuid::new_v4()),
+                "type": "Accept",
+                "actor": actor,
+                "object": activity,
+            });
+            queue_delivery(forum_id, &sender.inbox, &accept).await?;
+            log::info!("{} followed forum {}", sender.id, forum_id);
+        }
+        Some("Undo") if activity["object"]["type"] == "Follow" => {
+            activitypub_followers::Entity::delete_many()
+                .filter(activitypub_followers::Column::ForumId.eq(forum_id))
+                .filter(activitypub_followers::Column::ActorUri.eq(sender.id.as_str()))
+                .exec(get_db_pool())
+                .await?;
+            log::info!("{} unfollowed forum {}", sender.id, forum_id);
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Runs an `activitypub.delivery` job: signs the activity with the forum's
+/// key and POSTs it to the inbox.
+pub async fn run_delivery_job(job: &Job) -> Result<(), String> {
+    let forum_id = job.payload["forum_id"]
+        .as_i64()
+        .ok_or("job has no forum_id")? as i32;
+    let inbox = job.payload["inbox"].as_str().ok_or("job has no inbox")?;
+    let inbox = url::Url::parse(inbox).map_err(|_| format!("invalid inbox {:?}", inbox))?;
+
+    let Some(key) = activitypub_keys::Entity::find_by_id(forum_id)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        // The forum was deleted, taking its key and followers with it.
+        return Ok(());
+    };
+
+    let body = document(job.payload["activity"].clone()).to_string();
+    let headers = signatures::sign_post(
+        &key.private_key_pem,
+        &format!("{}#main-key", actor_url(forum_id)),
+        &inbox,
+        body.as_bytes(),
+        Utc::now(),
+    )?;
+
+    let mut request = http_client(&inbox)
+        .await?
+        .post(inbox.clone())
+        .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE);
+    for (name, value) in headers {
+        // reqwest sends the same Host itself.
+        if name != "Host" {
+            request = request.header(name, value);
+        }
+    }
+
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("request failed: {}", e))?;
+    match response.status().is_success() {
+        true => Ok(()),
+        false => Err(format!("{} answered {}", inbox, response.status())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resource() {
+        let base = "https://forum.example.com";
+        assert_eq!(
+            parse_resource("acct:forum-3@forum.example.com", base),
+            Some(3)
+        );
+        assert_eq!(
+            parse_resource("acct:forum-3@FORUM.example.com", base),
+            Some(3)
+        );
+        assert_eq!(
+            parse_resource("https://forum.example.com/ap/forums/3", base),
+            Some(3)
+        );
+        assert_eq!(parse_resource("acct:forum-3@elsewhere.example", base), None);
+        assert_eq!(parse_resource("acct:admin@forum.example.com", base), None);
+        assert_eq!(parse_resource("acct:forum-x@forum.example.com", base), None);
+
+        assert_eq!(
+            parse_resource("acct:forum-1@localhost:8080", "http://localhost:8080"),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_object_id() {
+        assert_eq!(
+            object_id(&json!("https://a.example/1")),
+            Some("https://a.example/1")
+        );
+        assert_eq!(
+            object_id(&json!({ "id": "https://a.example/1", "type": "Group" })),
+            Some("https://a.example/1")
+        );
+        assert_eq!(object_id(&json!(null)), None);
+    }
+
+    #[test]
+    fn test_document_adds_context() {
+        let value = document(json!({ "type": "Note" }));
+        assert_eq!(value["@context"], "https://www.w3.org/ns/activitystreams");
+        assert_eq!(value["type"], "Note");
+    }
+
+    #[actix_rt::test]
+    async fn test_fetch_refuses_private_addresses() {
+        let err = fetch_document("http://127.0.0.1:8080/users/admin")
+            .await
+            .unwrap_err();
+        assert!(err.contains("private address"), "{}", err);
+        assert!(fetch_signer("http://[::1]/actor#main-key").await.is_err());
+    }
+}
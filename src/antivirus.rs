@@ -0,0 +1,404 @@
+//! Antivirus scanning of uploads with ClamAV.
+//!
+//! Files are streamed to clamd with the INSTREAM command. In `sync` mode an
+//! upload is scanned before the response is sent; in `queue` mode it is marked
+//! pending and scanned by the job queue. Files that fail are quarantined:
+//! their data is deleted from storage, the verdict stays on the attachment row
+//! so the same file is refused if it is uploaded again, and moderators are
+//! notified.
+
+use crate::app_config::AntivirusConfig;
+use crate::db::get_db_pool;
+use crate::filesystem::get_storage;
+use crate::jobs::{self, Job, JobKind};
+use crate::orm::attachments::{self, ScanStatus};
+use crate::orm::user_uploads;
+use actix_web::{error, Error};
+use chrono::Utc;
+use futures::StreamExt;
+use sea_orm::{entity::*, query::*, sea_query::Expr, DbErr};
+use serde_json::json;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+/// Bytes sent to clamd per INSTREAM chunk.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// When uploads are scanned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanMode {
+    /// Before the upload is accepted.
+    Sync,
+    /// In the background, after the upload is accepted.
+    Queue,
+}
+
+impl ScanMode {
+    /// The configured mode, or `None` when scanning is disabled.
+    pub fn from_config(config: &AntivirusConfig) -> Option<ScanMode> {
+        if !config.enabled {
+            return None;
+        }
+        match config.mode.trim().to_ascii_lowercase().as_str() {
+            "sync" => Some(ScanMode::Sync),
+            "queue" => Some(ScanMode::Queue),
+            other => {
+                log::warn!("Unsupported antivirus mode {:?}; using queue.", other);
+                Some(ScanMode::Queue)
+            }
+        }
+    }
+}
+
+/// What clamd made of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    /// Name of the signature that matched.
+    Infected(String),
+    /// clamd refused to scan the file; holds its error.
+    Failed(String),
+}
+
+/// Sends `data` to clamd over `stream` with INSTREAM and returns its reply.
+pub fn instream<S: Read + Write>(stream: &mut S, data: &[u8]) -> Result<String, String> {
+    let io_error = |e: std::io::Error| format!("clamd connection error: {}", e);
+
+    stream.write_all(b"zINSTREAM\0").map_err(io_error)?;
+    for chunk in data.chunks(CHUNK_SIZE) {
+        stream
+            .write_all(&(chunk.len() as u32).to_be_bytes())
+            .map_err(io_error)?;
+        stream.write_all(chunk).map_err(io_error)?;
+    }
+    stream.write_all(&[0; 4]).map_err(io_error)?;
+    stream.flush().map_err(io_error)?;
+
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).map_err(io_error)?;
+    Ok(String::from_utf8_lossy(&reply).into_owned())
+}
+
+/// Parses clamd's reply to a scan, e.g. `stream: Eicar-Signature FOUND`.
+pub fn parse_reply(reply: &str) -> ScanVerdict {
+    let reply = reply.trim_end_matches(['\0', '\n', '\r']);
+    let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+
+    if result == "OK" {
+        ScanVerdict::Clean
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        ScanVerdict::Infected(signature.to_owned())
+    } else {
+        ScanVerdict::Failed(result.trim_end_matches(" ERROR").to_owned())
+    }
+}
+
+fn scan_blocking(data: &[u8], config: &AntivirusConfig) -> Result<ScanVerdict, String> {
+    let timeout = Some(Duration::from_secs(config.timeout_seconds.max(1)));
+    let address = config.clamd_address.trim();
+
+    let reply = if address.starts_with('/') {
+        let mut stream = std::os::unix::net::UnixStream::connect(address)
+            .map_err(|e| format!("failed to connect to clamd at {}: {}", address, e))?;
+        stream
+            .set_read_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+        stream
+            .set_write_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+        instream(&mut stream, data)?
+    } else {
+        let mut stream = std::net::TcpStream::connect(address)
+            .map_err(|e| format!("failed to connect to clamd at {}: {}", address, e))?;
+        stream
+            .set_read_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+        stream
+            .set_write_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+        instream(&mut stream, data)?
+    };
+
+    Ok(parse_reply(&reply))
+}
+
+/// Scans file data with clamd. Errors mean clamd could not be reached.
+pub async fn scan(data: Vec<u8>, config: &AntivirusConfig) -> Result<ScanVerdict, String> {
+    let config = config.clone();
+    actix_web::web::block(move || scan_blocking(&data, &config))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Error returned for uploads of a quarantined file.
+pub fn quarantined_error() -> Error {
+    error::ErrorUnprocessableEntity("This file was quarantined by the virus scanner.")
+}
+
+async fn set_scan_status(attachment_id: i32, status: ScanStatus) -> Result<(), DbErr> {
+    attachments::Entity::update_many()
+        .col_expr(attachments::Column::ScanStatus, Expr::value(status))
+        .filter(attachments::Column::Id.eq(attachment_id))
+        .exec(get_db_pool())
+        .await?;
+    Ok(())
+}
+
+/// Queues an attachment for scanning unless it already has a verdict.
+pub async fn enqueue(attachment_id: i32) {
+    let queued = attachments::Entity::update_many()
+        .col_expr(
+            attachments::Column::ScanStatus,
+            Expr::value(ScanStatus::Pending),
+        )
+        .filter(attachments::Column::Id.eq(attachment_id))
+        .filter(attachments::Column::ScanStatus.eq(ScanStatus::Unscanned))
+        .exec(get_db_pool())
+        .await;
+
+    let result = match queued {
+        Ok(res) if res.rows_affected > 0 => jobs::enqueue(
+            JobKind::AntivirusScan,
+            json!({ "attachment_id": attachment_id }),
+            crate::app_config::antivirus().max_attempts,
+        )
+        .await
+        .map(|_| ()),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        log::error!("antivirus::enqueue: {}", e);
+    }
+}
+
+/// Records the verdict of a scan on the attachment row.
+pub async fn record_verdict(attachment_id: i32, verdict: &ScanVerdict) -> Result<(), DbErr> {
+    let (status, result) = match verdict {
+        ScanVerdict::Clean => (ScanStatus::Clean, None),
+        ScanVerdict::Infected(signature) => (ScanStatus::Infected, Some(signature.to_owned())),
+        ScanVerdict::Failed(error) => (ScanStatus::Failed, Some(error.to_owned())),
+    };
+
+    attachments::Entity::update_many()
+        .col_expr(attachments::Column::ScanStatus, Expr::value(status))
+        .col_expr(attachments::Column::ScanResult, Expr::value(result))
+        .col_expr(
+            attachments::Column::ScannedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(attachments::Column::Id.eq(attachment_id))
+        .exec(get_db_pool())
+        .await?;
+
+    Ok(())
+}
+
+/// Removes an infected file from storage and tells moderators. `uploader_id`
+/// is named in the notification; without it the first recorded uploader is.
+async fn quarantine(attachment: &attachments::Model, signature: &str, uploader_id: Option<i32>) {
+    log::warn!(
+        "antivirus: quarantined attachment {} ({}): {}",
+        attachment.id,
+        attachment.filename,
+        signature
+    );
+
+    if let Err(e) = get_storage().delete_object(&attachment.filename).await {
+        log::error!("antivirus: failed to delete {}: {}", attachment.filename, e);
+    }
+
+    let uploader_id = match uploader_id {
+        Some(uploader_id) => Some(uploader_id),
+        None => user_uploads::Entity::find()
+            .filter(user_uploads::Column::AttachmentId.eq(attachment.id))
+            .order_by_asc(user_uploads::Column::CreatedAt)
+            .one(get_db_pool())
+            .await
+            .map_err(|e| log::error!("antivirus: {}", e))
+            .unwrap_or_default()
+            .map(|upload| upload.user_id),
+    };
+
+    if let Err(e) = crate::notifications::dispatcher::notify_attachment_quarantined(
+        attachment.id,
+        &attachment.filename,
+        signature,
+        uploader_id,
+    )
+    .await
+    {
+        log::error!("antivirus: failed to notify moderators: {}", e);
+    }
+}
+
+/// Fetches a stored attachment, scans it and records the verdict. Infected
+/// files are quarantined.
+pub async fn scan_attachment(
+    attachment_id: i32,
+    uploader_id: Option<i32>,
+    config: &AntivirusConfig,
+) -> Result<ScanVerdict, String> {
+    let attachment = attachments::Entity::find_by_id(attachment_id)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("attachment no longer exists")?;
+
+    let object = get_storage()
+        .get_object(&attachment.filename, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut data = Vec::with_capacity(object.content_length.unwrap_or(0).max(0) as usize);
+    let mut body = object.body;
+    while let Some(chunk) = body.next().await {
+        data.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+    }
+
+    let verdict = scan(data, config).await?;
+    record_verdict(attachment.id, &verdict)
+        .await
+        .map_err(|e| e.to_string())?;
+    match &verdict {
+        ScanVerdict::Infected(signature) => quarantine(&attachment, signature, uploader_id).await,
+        ScanVerdict::Failed(error) => log::error!(
+            "antivirus: clamd could not scan attachment {}: {}",
+            attachment.id,
+            error
+        ),
+        ScanVerdict::Clean => {}
+    }
+
+    Ok(verdict)
+}
+
+/// Scans a newly stored upload as configured: right away in sync mode, or by
+/// queueing it. Fails if the file is quarantined. If clamd cannot be reached
+/// in sync mode the upload is accepted and queued instead.
+pub async fn scan_upload(attachment_id: i32, uploader_id: Option<i32>) -> Result<(), Error> {
+    let config = crate::app_config::antivirus();
+    let mode = match ScanMode::from_config(&config) {
+        Some(mode) => mode,
+        None => return Ok(()),
+    };
+
+    let attachment = attachments::Entity::find_by_id(attachment_id)
+        .one(get_db_pool())
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Attachment not found."))?;
+
+    match attachment.scan_status {
+        ScanStatus::Infected => return Err(quarantined_error()),
+        ScanStatus::Unscanned => {}
+        _ => return Ok(()),
+    }
+
+    if mode == ScanMode::Queue {
+        enqueue(attachment_id).await;
+        return Ok(());
+    }
+
+    match scan_attachment(attachment_id, uploader_id, &config).await {
+        Ok(ScanVerdict::Infected(_)) => Err(quarantined_error()),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            log::error!(
+                "antivirus: scan of attachment {} failed, queueing: {}",
+                attachment_id,
+                e
+            );
+            enqueue(attachment_id).await;
+            Ok(())
+        }
+    }
+}
+
+/// Runs a queued scan. While clamd cannot be reached the attachment goes back
+/// to pending for the job to be retried; once the job is out of attempts it is
+/// marked as failed.
+pub async fn run_scan_job(job: &Job) -> Result<(), String> {
+    let attachment_id = job
+        .payload
+        .get("attachment_id")
+        .and_then(|id| id.as_i64())
+        .ok_or_else(|| "job has no attachment_id".to_owned())? as i32;
+
+    set_scan_status(attachment_id, ScanStatus::Scanning)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let config = crate::app_config::antivirus();
+    let Err(e) = scan_attachment(attachment_id, None, &config).await else {
+        return Ok(());
+    };
+
+    let recorded = match job.is_last_attempt() {
+        true => record_verdict(attachment_id, &ScanVerdict::Failed(e.clone())).await,
+        false => set_scan_status(attachment_id, ScanStatus::Pending).await,
+    };
+    if let Err(e) = recorded {
+        log::error!("antivirus::run_scan_job: {}", e);
+    }
+    Err(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// In-memory clamd connection: records what is written, replies with `reply`.
+    struct FakeClamd {
+        written: Vec<u8>,
+        reply: Cursor<Vec<u8>>,
+    }
+
+    impl Read for FakeClamd {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.reply.read(buf)
+        }
+    }
+
+    impl Write for FakeClamd {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn instream_frames_chunks_and_terminates() {
+        let mut clamd = FakeClamd {
+            written: Vec::new(),
+            reply: Cursor::new(b"stream: OK\0".to_vec()),
+        };
+        let data = vec![7u8; CHUNK_SIZE + 10];
+
+        assert_eq!(instream(&mut clamd, &data).unwrap(), "stream: OK\0");
+
+        let written = clamd.written;
+        assert!(written.starts_with(b"zINSTREAM\0"));
+        let first = &written[10..];
+        assert_eq!(&first[..4], &(CHUNK_SIZE as u32).to_be_bytes());
+        let second = &first[4 + CHUNK_SIZE..];
+        assert_eq!(&second[..4], &10u32.to_be_bytes());
+        assert_eq!(&second[4 + 10..], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn replies_are_parsed() {
+        assert_eq!(parse_reply("stream: OK\0"), ScanVerdict::Clean);
+        assert_eq!(
+            parse_reply("stream: Win.Test.EICAR_HDB-1 FOUND\0"),
+            ScanVerdict::Infected("Win.Test.EICAR_HDB-1".to_owned())
+        );
+        assert_eq!(
+            parse_reply("INSTREAM size limit exceeded. ERROR\0"),
+            ScanVerdict::Failed("INSTREAM size limit exceeded.".to_owned())
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! Personal access tokens for the JSON API.
+//!
+//! A token is shown to its owner once, when it is created. Only its BLAKE3
+//! hash is stored, which is enough for tokens this long and random, and lets
+//! every request be checked with a single indexed lookup. Each token carries
+//! the scopes its owner chose, limiting what it can do on top of the owner's
+//! own permissions.
+
+use crate::db::get_db_pool;
+use crate::orm::api_tokens;
+use crate::user::Profile;
+use chrono::{NaiveDateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::{entity::*, query::*, sea_query::Expr, DbErr};
+
+/// Start of every token, so leaked tokens are easy to recognise.
+pub const TOKEN_PREFIX: &str = "dpt_";
+
+/// Random characters after the prefix
+const TOKEN_LENGTH: usize = 40;
+
+/// Characters of a token kept to tell tokens apart
+const DISPLAY_PREFIX_LENGTH: usize = 12;
+
+/// Tokens one user can hold
+pub const MAX_TOKENS_PER_USER: u64 = 20;
+
+/// Longest name for a token
+pub const MAX_NAME_LENGTH: usize = 100;
+
+/// How often `last_used_at` is written for a busy token
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+/// What a token may be used for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiScope {
+    /// Forums, threads, posts and member profiles, as the token's owner sees them
+    Read,
+    /// The owner's conversations and their messages
+    ConversationsRead,
+    /// Sending messages in the owner's conversations
+    ConversationsWrite,
+    /// The owner's notifications
+    NotificationsRead,
+    /// Marking the owner's notifications as read
+    NotificationsWrite,
+    /// Registering the owner's mobile devices for push notifications
+    Push,
+}
+
+impl ApiScope {
+    pub fn all() -> [ApiScope; 6] {
+        [
+            ApiScope::Read,
+            ApiScope::ConversationsRead,
+            ApiScope::ConversationsWrite,
+            ApiScope::NotificationsRead,
+            ApiScope::NotificationsWrite,
+            ApiScope::Push,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::ConversationsRead => "conversations:read",
+            ApiScope::ConversationsWrite => "conversations:write",
+            ApiScope::NotificationsRead => "notifications:read",
+            ApiScope::NotificationsWrite => "notifications:write",
+            ApiScope::Push => "push",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        Self::all().into_iter().find(|s| s.as_str() == scope)
+    }
+
+    /// Description shown when choosing scopes for a new token
+    pub fn description(&self) -> &'static str {
+        match self {
+            ApiScope::Read => "Read forums, threads, posts and member profiles",
+            ApiScope::ConversationsRead => "Read your conversations",
+            ApiScope::ConversationsWrite => "Send messages in your conversations",
+            ApiScope::NotificationsRead => "Read your notifications",
+            ApiScope::NotificationsWrite => "Mark your notifications as read",
+            ApiScope::Push => "Receive push notifications on your mobile devices",
+        }
+    }
+}
+
+/// Scopes in a space separated list, ignoring unknown ones and repeats.
+pub fn parse_scopes(scopes: &str) -> Vec<ApiScope> {
+    let mut parsed = Vec::new();
+    for scope in scopes.split_whitespace().filter_map(ApiScope::parse) {
+        if !parsed.contains(&scope) {
+            parsed.push(scope);
+        }
+    }
+    parsed
+}
+
+/// Space separated list of `scopes`, as stored.
+pub fn format_scopes(scopes: &[ApiScope]) -> String {
+    scopes
+        .iter()
+        .map(ApiScope::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn generate_token() -> String {
+    let random: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("{}{}", TOKEN_PREFIX, random)
+}
+
+fn hash_token(token: &str) -> String {
+    blake3::hash(token.as_bytes()).to_hex().to_string()
+}
+
+/// Creates a token for `user_id`, returning it with the token itself. The
+/// token cannot be recovered afterwards.
+pub async fn create_token(
+    user_id: i32,
+    name: &str,
+    scopes: &[ApiScope],
+    expires_at: Option<NaiveDateTime>,
+) -> Result<(api_tokens::Model, String), DbErr> {
+    let token = generate_token();
+    let model = api_tokens::ActiveModel {
+        user_id: Set(user_id),
+        name: Set(name.to_owned()),
+        token_hash: Set(hash_token(&token)),
+        token_prefix: Set(token.chars().take(DISPLAY_PREFIX_LENGTH).collect()),
+        scopes: Set(format_scopes(scopes)),
+        created_at: Set(Utc::now().naive_utc()),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    }
+    .insert(get_db_pool())
+    .await?;
+
+    Ok((model, token))
+}
+
+/// Tokens belonging to `user_id`, newest first.
+pub async fn get_user_tokens(user_id: i32) -> Result<Vec<api_tokens::Model>, DbErr> {
+    api_tokens::Entity::find()
+        .filter(api_tokens::Column::UserId.eq(user_id))
+        .order_by_desc(api_tokens::Column::CreatedAt)
+        .all(get_db_pool())
+        .await
+}
+
+pub async fn count_user_tokens(user_id: i32) -> Result<u64, DbErr> {
+    api_tokens::Entity::find()
+        .filter(api_tokens::Column::UserId.eq(user_id))
+        .count(get_db_pool())
+        .await
+}
+
+/// Deletes one of `user_id`'s tokens. Returns false if they have no such token.
+pub async fn revoke_token(user_id: i32, token_id: i32) -> Result<bool, DbErr> {
+    let result = api_tokens::Entity::delete_many()
+        .filter(api_tokens::Column::Id.eq(token_id))
+        .filter(api_tokens::Column::UserId.eq(user_id))
+        .exec(get_db_pool())
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// The owner and scopes of a token, if it exists, has not expired and its
+/// owner is not banned.
+pub async fn authenticate_token(token: &str) -> Result<Option<(Profile, Vec<ApiScope>)>, DbErr> {
+    if !token.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+
+    let db = get_db_pool();
+    let now = Utc::now().naive_utc();
+    let Some(model) = api_tokens::Entity::find()
+        .filter(api_tokens::Column::TokenHash.eq(hash_token(token)))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    if model.expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Ok(None);
+    }
+
+    // Banned members can't sign in, so their tokens stop working too.
+    if crate::user::get_active_ban(db, model.user_id)
+        .await?
+        .is_some()
+    {
+        return Ok(None);
+    }
+
+    let Some(profile) = Profile::get_by_id(db, model.user_id).await? else {
+        return Ok(None);
+    };
+
+    // Busy tokens would otherwise write on every request.
+    let stale = model.last_used_at.is_none_or(|last_used_at| {
+        (now - last_used_at).num_seconds() >= LAST_USED_RESOLUTION_SECONDS
+    });
+    if stale {
+        api_tokens::Entity::update_many()
+            .col_expr(api_tokens::Column::LastUsedAt, Expr::value(now))
+            .filter(api_tokens::Column::Id.eq(model.id))
+            .exec(db)
+            .await?;
+    }
+
+    Ok(Some((profile, parse_scopes(&model.scopes))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scopes_round_trip() {
+        for scope in ApiScope::all() {
+            assert_eq!(ApiScope::parse(scope.as_str()), Some(scope));
+        }
+        assert_eq!(ApiScope::parse("admin"), None);
+    }
+
+    #[test]
+    fn test_parse_scopes() {
+        assert_eq!(
+            parse_scopes("read  notifications:read bogus read"),
+            vec![ApiScope::Read, ApiScope::NotificationsRead]
+        );
+        assert_eq!(
+            format_scopes(&[ApiScope::Read, ApiScope::ConversationsWrite]),
+            "read conversations:write"
+        );
+        assert!(parse_scopes("").is_empty());
+    }
+
+    #[test]
+    fn test_generate_token() {
+        let token = generate_token();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + TOKEN_LENGTH);
+        assert_ne!(token, generate_token());
+        assert_eq!(hash_token(&token).len(), 64);
+        assert_eq!(hash_token(&token), hash_token(&token));
+    }
+}
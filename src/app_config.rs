@@ -40,6 +40,53 @@ impl Default for SiteConfig {
     }
 }
 
+/// HTTP server configuration. Read once at startup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Addresses to serve plain HTTP on
+    pub listen: Vec<String>,
+    /// Addresses to serve HTTPS on, with the certificate below
+    pub tls_listen: Vec<String>,
+    /// PEM file with the certificate chain, leaf first
+    pub tls_cert_file: String,
+    /// PEM file with the private key (PKCS#8, RSA or SEC1)
+    pub tls_key_file: String,
+    /// Worker threads; 0 starts one per CPU core
+    pub workers: usize,
+    /// Seconds an idle connection is kept open; 0 disables keep-alive
+    pub keep_alive_seconds: u64,
+    /// Seconds a client has to send its request headers
+    pub client_request_timeout_seconds: u64,
+    /// Seconds requests in flight get to finish on shutdown
+    pub shutdown_timeout_seconds: u64,
+    /// Largest JSON request body, in bytes
+    pub json_limit_bytes: usize,
+    /// Largest URL-encoded form body, in bytes
+    pub form_limit_bytes: usize,
+    /// Largest raw request body, in bytes. Uploads are limited by
+    /// `limits.max_upload_size_mb` instead.
+    pub payload_limit_bytes: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen: vec!["0.0.0.0:8080".to_string()],
+            tls_listen: Vec::new(),
+            tls_cert_file: String::new(),
+            tls_key_file: String::new(),
+            workers: 0,
+            keep_alive_seconds: 5,
+            client_request_timeout_seconds: 5,
+            shutdown_timeout_seconds: 30,
+            json_limit_bytes: 2 * 1024 * 1024,
+            form_limit_bytes: 1024 * 1024,
+            payload_limit_bytes: 256 * 1024,
+        }
+    }
+}
+
 /// CAPTCHA configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -74,6 +121,19 @@ pub struct SecurityConfig {
     pub max_failed_logins: u32,
     /// Account lockout duration in minutes
     pub lockout_duration_minutes: u32,
+    /// Failed logins from one IP address before the address is locked out
+    pub max_failed_logins_per_ip: u32,
+    /// IP address lockout duration in minutes
+    pub ip_lockout_duration_minutes: u32,
+    /// Minutes without a failure after which an account's or address's
+    /// failures are forgotten
+    pub failure_window_minutes: u32,
+    /// Failed logins allowed before each further attempt has to wait
+    pub login_delay_after: u32,
+    /// First wait in seconds, doubling with every further failure
+    pub login_delay_seconds: u32,
+    /// Longest wait in seconds
+    pub login_delay_max_seconds: u32,
     /// Session timeout in minutes (default: 24 hours)
     pub session_timeout_minutes: u32,
     /// Remember me session duration in days
@@ -85,6 +145,12 @@ impl Default for SecurityConfig {
         Self {
             max_failed_logins: 5,
             lockout_duration_minutes: 15,
+            max_failed_logins_per_ip: 20,
+            ip_lockout_duration_minutes: 30,
+            failure_window_minutes: 60,
+            login_delay_after: 2,
+            login_delay_seconds: 2,
+            login_delay_max_seconds: 60,
             session_timeout_minutes: 1440,
             remember_me_days: 30,
         }
@@ -95,6 +161,9 @@ impl Default for SecurityConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct RateLimitConfig {
+    /// "memory" counts requests per instance; "redis" shares token buckets
+    /// in the Redis server at `REDIS_URL` across every instance
+    pub backend: String,
     /// Login attempts per window
     pub login_max_attempts: u32,
     /// Login rate limit window in seconds
@@ -110,6 +179,7 @@ pub struct RateLimitConfig {
 impl Default for RateLimitConfig {
     fn default() -> Self {
         Self {
+            backend: "memory".to_string(),
             login_max_attempts: 5,
             login_window_seconds: 300,
             registration_per_hour: 3,
@@ -133,6 +203,8 @@ pub struct LimitsConfig {
     pub max_post_length: u32,
     /// Maximum post length for moderators
     pub max_post_length_mod: u32,
+    /// Maximum number of people in one conversation, including the owner
+    pub max_conversation_participants: u32,
 }
 
 impl Default for LimitsConfig {
@@ -143,6 +215,7 @@ impl Default for LimitsConfig {
             max_upload_size_mb: 10,
             max_post_length: 50000,
             max_post_length_mod: 100000,
+            max_conversation_participants: 20,
         }
     }
 }
@@ -166,6 +239,12 @@ pub struct EmailConfig {
     pub from_address: String,
     /// From name for emails
     pub from_name: String,
+    /// Attempts at sending an email before it is given up
+    pub max_attempts: i32,
+    /// Announcement emails queued at a time
+    pub mass_batch_size: u64,
+    /// Seconds between batches of announcement emails
+    pub mass_batch_interval_seconds: i64,
 }
 
 impl Default for EmailConfig {
@@ -178,6 +257,9 @@ impl Default for EmailConfig {
             smtp_password: String::new(),
             from_address: "noreply@localhost".to_string(),
             from_name: "Dumpster".to_string(),
+            max_attempts: 6,
+            mass_batch_size: 100,
+            mass_batch_interval_seconds: 60,
         }
     }
 }
@@ -186,7 +268,7 @@ impl Default for EmailConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct StorageConfig {
-    /// Storage backend: "local" or "s3"
+    /// Storage backend: "local", "s3", "gcs" or "azure"
     pub backend: String,
     /// Local storage path (used when backend = "local")
     pub local_path: String,
@@ -204,6 +286,41 @@ pub struct StorageConfig {
     /// S3 secret key (should be in env var RUFORO_STORAGE_S3_SECRET_KEY)
     #[serde(default)]
     pub s3_secret_key: String,
+    /// GCS bucket name (used when backend = "gcs")
+    pub gcs_bucket: String,
+    /// GCS XML API endpoint (used when backend = "gcs")
+    pub gcs_endpoint: String,
+    /// GCS HMAC access ID (should be in env var RUFORO_STORAGE_GCS_ACCESS_ID)
+    #[serde(default)]
+    pub gcs_access_id: String,
+    /// GCS HMAC secret (should be in env var RUFORO_STORAGE_GCS_SECRET)
+    #[serde(default)]
+    pub gcs_secret: String,
+    /// Azure storage account name (used when backend = "azure")
+    pub azure_account: String,
+    /// Azure Blob container name (used when backend = "azure")
+    pub azure_container: String,
+    /// Azure Blob endpoint; empty uses the account's public endpoint
+    pub azure_endpoint: String,
+    /// Azure account key (should be in env var RUFORO_STORAGE_AZURE_ACCESS_KEY)
+    #[serde(default)]
+    pub azure_access_key: String,
+    /// Part size for chunked uploads, in megabytes (S3 requires at least 5)
+    pub chunk_size_mb: u32,
+    /// Largest file accepted through chunked uploads, in megabytes
+    pub max_chunked_upload_mb: u32,
+    /// Hours before an unfinished chunked upload is discarded
+    pub upload_expiry_hours: u32,
+    /// Hours an attachment nothing refers to is kept before its file is deleted
+    pub unreferenced_retention_hours: u32,
+    /// Visibility of forum post attachments: "public" or "members"
+    pub post_attachment_visibility: String,
+    /// Minutes a signed link to a restricted attachment stays valid (at least)
+    pub signed_url_expiry_minutes: u32,
+    /// Key for signing attachment links (should be in env var RUFORO_STORAGE_URL_SIGNING_KEY).
+    /// Falls back to SECRET_KEY.
+    #[serde(default)]
+    pub url_signing_key: String,
 }
 
 impl Default for StorageConfig {
@@ -217,6 +334,21 @@ impl Default for StorageConfig {
             s3_public_url: "http://localhost:9000/dumpster".to_string(),
             s3_access_key: String::new(),
             s3_secret_key: String::new(),
+            gcs_bucket: "dumpster".to_string(),
+            gcs_endpoint: "https://storage.googleapis.com".to_string(),
+            gcs_access_id: String::new(),
+            gcs_secret: String::new(),
+            azure_account: String::new(),
+            azure_container: "dumpster".to_string(),
+            azure_endpoint: String::new(),
+            azure_access_key: String::new(),
+            chunk_size_mb: 8,
+            max_chunked_upload_mb: 4096,
+            upload_expiry_hours: 24,
+            unreferenced_retention_hours: 24,
+            post_attachment_visibility: "public".to_string(),
+            signed_url_expiry_minutes: 60,
+            url_signing_key: String::new(),
         }
     }
 }
@@ -246,11 +378,450 @@ impl Default for SpamConfig {
     }
 }
 
+/// Avatar configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AvatarConfig {
+    /// Show a user's Gravatar when they have not set any avatar
+    pub gravatar_fallback: bool,
+    /// Base URL for Gravatar images
+    pub gravatar_url: String,
+    /// Gravatar default image style for addresses without a Gravatar
+    /// (e.g. "mp", "identicon", "retro", "blank")
+    pub gravatar_default: String,
+}
+
+impl Default for AvatarConfig {
+    fn default() -> Self {
+        Self {
+            gravatar_fallback: false,
+            gravatar_url: "https://www.gravatar.com/avatar".to_string(),
+            gravatar_default: "mp".to_string(),
+        }
+    }
+}
+
+/// Image optimization configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageConfig {
+    /// Store WebP copies of uploaded images for browsers that accept them
+    pub webp: bool,
+    /// Store AVIF copies of uploaded images (requires building with the `avif` feature)
+    pub avif: bool,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            webp: true,
+            avif: false,
+        }
+    }
+}
+
+/// Video transcoding configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoConfig {
+    /// Convert uploaded videos into streamable renditions in the background
+    pub transcode: bool,
+    /// Path to the ffmpeg executable
+    pub ffmpeg_path: String,
+    /// Renditions are scaled down to at most this height
+    pub max_height: u32,
+    /// Also produce a VP9/Opus WebM rendition next to the H.264/AAC MP4
+    pub webm: bool,
+    /// Attempts before a video is marked as failed
+    pub max_attempts: i32,
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        Self {
+            transcode: true,
+            ffmpeg_path: "ffmpeg".to_string(),
+            max_height: 720,
+            webm: false,
+            max_attempts: 3,
+        }
+    }
+}
+
+/// Antivirus scanning configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AntivirusConfig {
+    /// Scan uploads with ClamAV
+    pub enabled: bool,
+    /// clamd address: "host:port" for TCP, or the path of its unix socket
+    pub clamd_address: String,
+    /// "sync" scans uploads before accepting them; "queue" scans them in the background
+    pub mode: String,
+    /// Seconds to wait on clamd before a scan is given up
+    pub timeout_seconds: u64,
+    /// Attempts to reach clamd before a queued file is marked as failed
+    pub max_attempts: i32,
+}
+
+impl Default for AntivirusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            clamd_address: "127.0.0.1:3310".to_string(),
+            mode: "queue".to_string(),
+            timeout_seconds: 60,
+            max_attempts: 8,
+        }
+    }
+}
+
+/// Search backend configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// "postgres" searches the database directly; "meilisearch" uses a Meilisearch server
+    pub backend: String,
+    /// Meilisearch server address
+    pub meilisearch_url: String,
+    /// Meilisearch API key with search and document rights (should be in env var RUFORO_SEARCH_MEILISEARCH_API_KEY)
+    #[serde(default)]
+    pub meilisearch_api_key: String,
+    /// Prefix for index names, so several boards can share one server
+    pub index_prefix: String,
+    /// Documents sent to the backend per indexing request
+    pub batch_size: u64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            backend: "postgres".to_string(),
+            meilisearch_url: "http://127.0.0.1:7700".to_string(),
+            meilisearch_api_key: String::new(),
+            index_prefix: "dumpster".to_string(),
+            batch_size: 500,
+        }
+    }
+}
+
+/// Web Push configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PushConfig {
+    /// Send browser push notifications
+    pub enabled: bool,
+    /// VAPID public key: uncompressed P-256 point, base64url encoded
+    pub vapid_public_key: String,
+    /// VAPID private key: raw 32-byte P-256 scalar, base64url encoded
+    pub vapid_private_key: String,
+    /// Contact for push services, a "mailto:" or "https:" URL
+    pub vapid_subject: String,
+    /// Seconds a push service keeps an undelivered message
+    pub ttl_seconds: u32,
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            vapid_public_key: String::new(),
+            vapid_private_key: String::new(),
+            vapid_subject: "mailto:admin@localhost".to_string(),
+            ttl_seconds: 86400,
+        }
+    }
+}
+
+/// Mobile app push configuration. Each service is used once its
+/// credentials are filled in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MobilePushConfig {
+    /// Firebase project the Android app belongs to
+    pub fcm_project_id: String,
+    /// Path to the JSON key of a service account allowed to send messages
+    pub fcm_service_account_file: String,
+    /// Path to the APNs authentication key (.p8)
+    pub apns_key_file: String,
+    /// ID of the APNs authentication key
+    pub apns_key_id: String,
+    /// Apple developer team the key belongs to
+    pub apns_team_id: String,
+    /// Bundle ID of the iOS app
+    pub apns_topic: String,
+    /// Send to the APNs development environment, for debug builds
+    pub apns_sandbox: bool,
+    /// Seconds a push service keeps an undelivered message
+    pub ttl_seconds: u32,
+}
+
+impl Default for MobilePushConfig {
+    fn default() -> Self {
+        Self {
+            fcm_project_id: String::new(),
+            fcm_service_account_file: String::new(),
+            apns_key_file: String::new(),
+            apns_key_id: String::new(),
+            apns_team_id: String::new(),
+            apns_topic: String::new(),
+            apns_sandbox: false,
+            ttl_seconds: 86400,
+        }
+    }
+}
+
+/// Cache configuration. Entries go to Redis when `REDIS_URL` is set and
+/// stay in process memory otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Most entries held in process memory
+    pub memory_max_entries: usize,
+    /// Seconds rendered post HTML is kept
+    pub post_html_ttl_seconds: u64,
+    /// Seconds the forum list and its statistics are kept
+    pub forum_tree_ttl_seconds: u64,
+    /// Seconds a user's group memberships are kept
+    pub group_ids_ttl_seconds: u64,
+    /// Seconds rendered sidebars and lists are kept
+    pub fragment_ttl_seconds: u64,
+    /// Seconds between checks for settings and word filters changed
+    /// elsewhere; 0 turns the check off
+    pub config_poll_seconds: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            memory_max_entries: 10000,
+            post_html_ttl_seconds: 86400,
+            forum_tree_ttl_seconds: 30,
+            group_ids_ttl_seconds: 300,
+            fragment_ttl_seconds: 60,
+            config_poll_seconds: 10,
+        }
+    }
+}
+
+/// Background job queue configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobsConfig {
+    /// Jobs claimed by the worker at once
+    pub batch_size: u64,
+    /// Seconds between checks of the job queue when it is empty
+    pub poll_interval_seconds: u64,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 20,
+            poll_interval_seconds: 5,
+        }
+    }
+}
+
+/// Activity feed retention
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivitiesConfig {
+    /// Days activities stay on the feeds (0 = forever)
+    pub retention_days: i64,
+    /// Move older activities to `activity_archives` rather than deleting them
+    pub archive: bool,
+}
+
+impl Default for ActivitiesConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 365,
+            archive: true,
+        }
+    }
+}
+
+/// Outgoing webhook configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// Seconds to wait for a webhook endpoint to answer
+    pub timeout_seconds: u64,
+    /// Attempts at a delivery before it is given up
+    pub max_attempts: i32,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 10,
+            max_attempts: 8,
+        }
+    }
+}
+
+/// Content report configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReportsConfig {
+    /// Hours a report may stay open before moderators are reminded about it
+    /// (0 = no reminders)
+    pub sla_hours: i64,
+    /// Hours between reminders about the same overdue report
+    pub reminder_interval_hours: i64,
+    /// Email moderators about new and overdue reports as well as notifying
+    /// them in the forum
+    pub email_moderators: bool,
+}
+
+impl Default for ReportsConfig {
+    fn default() -> Self {
+        Self {
+            sla_hours: 24,
+            reminder_interval_hours: 24,
+            email_moderators: false,
+        }
+    }
+}
+
+/// oEmbed provider configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OembedConfig {
+    /// Origins whose pages may fetch `/oembed` from the browser, such as
+    /// "https://blog.example.com". "*" allows any origin.
+    pub allowed_origins: Vec<String>,
+    /// Seconds consumers are told they may cache an embed
+    pub cache_age_seconds: u64,
+}
+
+impl Default for OembedConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            cache_age_seconds: 3600,
+        }
+    }
+}
+
+/// Link preview configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnfurlConfig {
+    /// Hours a link's preview is kept before it is fetched again
+    pub cache_ttl_hours: i64,
+    /// Hours a failed fetch is remembered before it is tried again
+    pub error_cache_ttl_hours: i64,
+    /// Sites whose oEmbed descriptions are consulted, by host. Subdomains
+    /// match too. Other sites are described from their Open Graph tags.
+    pub oembed_providers: Vec<String>,
+}
+
+impl Default for UnfurlConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl_hours: 24,
+            error_cache_ttl_hours: 1,
+            oembed_providers: [
+                "youtube.com",
+                "youtu.be",
+                "vimeo.com",
+                "soundcloud.com",
+                "flickr.com",
+                "spotify.com",
+                "twitter.com",
+                "x.com",
+                "reddit.com",
+                "tiktok.com",
+                "codepen.io",
+            ]
+            .iter()
+            .map(|host| host.to_string())
+            .collect(),
+        }
+    }
+}
+
+/// Outbound link configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboundLinksConfig {
+    /// Send links to other sites in posts through `/goto`
+    pub enabled: bool,
+    /// Count clicks on each link, shown to the thread's author
+    pub track_clicks: bool,
+    /// Show a warning page before leaving for a domain not trusted
+    pub warn_untrusted: bool,
+    /// Domains followed without a warning. Subdomains match too.
+    pub trusted_domains: Vec<String>,
+}
+
+impl Default for OutboundLinksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            track_clicks: true,
+            warn_untrusted: true,
+            trusted_domains: Vec::new(),
+        }
+    }
+}
+
+/// Static asset configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssetsConfig {
+    /// Link to stylesheets and scripts by content-hashed names, cached for
+    /// good; hashes are taken at startup
+    pub fingerprint: bool,
+}
+
+impl Default for AssetsConfig {
+    fn default() -> Self {
+        Self { fingerprint: true }
+    }
+}
+
+/// ActivityPub publishing configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityPubConfig {
+    /// Serve actors for forums marked as federated and publish their new
+    /// threads to followers
+    pub enabled: bool,
+}
+
+/// Tracing configuration. Spans are always logged; they are also exported
+/// when an OTLP endpoint is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector address, e.g. "http://127.0.0.1:4318"; empty
+    /// disables export
+    pub otlp_endpoint: String,
+    /// Service name spans are reported under
+    pub service_name: String,
+    /// Fraction of requests exported, from 0.0 to 1.0
+    pub sample_ratio: f64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: String::new(),
+            service_name: "dumpster".to_string(),
+            sample_ratio: 1.0,
+        }
+    }
+}
+
 /// Main application configuration
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct AppConfig {
     pub site: SiteConfig,
+    pub server: ServerConfig,
     pub captcha: CaptchaConfig,
     pub security: SecurityConfig,
     pub rate_limit: RateLimitConfig,
@@ -258,6 +829,24 @@ pub struct AppConfig {
     pub email: EmailConfig,
     pub storage: StorageConfig,
     pub spam: SpamConfig,
+    pub avatars: AvatarConfig,
+    pub images: ImageConfig,
+    pub video: VideoConfig,
+    pub antivirus: AntivirusConfig,
+    pub push: PushConfig,
+    pub mobile_push: MobilePushConfig,
+    pub search: SearchConfig,
+    pub cache: CacheConfig,
+    pub jobs: JobsConfig,
+    pub activities: ActivitiesConfig,
+    pub webhooks: WebhookConfig,
+    pub reports: ReportsConfig,
+    pub oembed: OembedConfig,
+    pub unfurl: UnfurlConfig,
+    pub outbound_links: OutboundLinksConfig,
+    pub assets: AssetsConfig,
+    pub activitypub: ActivityPubConfig,
+    pub telemetry: TelemetryConfig,
 }
 
 impl AppConfig {
@@ -320,6 +909,11 @@ pub fn site() -> SiteConfig {
     get_config().site
 }
 
+/// Get HTTP server configuration
+pub fn server() -> ServerConfig {
+    get_config().server
+}
+
 /// Get CAPTCHA configuration
 pub fn captcha() -> CaptchaConfig {
     get_config().captcha
@@ -355,6 +949,96 @@ pub fn spam() -> SpamConfig {
     get_config().spam
 }
 
+/// Get avatar configuration
+pub fn avatars() -> AvatarConfig {
+    get_config().avatars
+}
+
+/// Get image optimization configuration
+pub fn images() -> ImageConfig {
+    get_config().images
+}
+
+/// Get video transcoding configuration
+pub fn video() -> VideoConfig {
+    get_config().video
+}
+
+/// Get antivirus scanning configuration
+pub fn antivirus() -> AntivirusConfig {
+    get_config().antivirus
+}
+
+/// Get Web Push configuration
+pub fn push() -> PushConfig {
+    get_config().push
+}
+
+/// Get mobile app push configuration
+pub fn mobile_push() -> MobilePushConfig {
+    get_config().mobile_push
+}
+
+/// Get search backend configuration
+pub fn search() -> SearchConfig {
+    get_config().search
+}
+
+/// Get cache configuration
+pub fn cache() -> CacheConfig {
+    get_config().cache
+}
+
+/// Get background job queue configuration
+pub fn jobs() -> JobsConfig {
+    get_config().jobs
+}
+
+/// Get activity feed retention configuration
+pub fn activities() -> ActivitiesConfig {
+    get_config().activities
+}
+
+/// Get outgoing webhook configuration
+pub fn webhooks() -> WebhookConfig {
+    get_config().webhooks
+}
+
+/// Get content report configuration
+pub fn reports() -> ReportsConfig {
+    get_config().reports
+}
+
+/// Get oEmbed provider configuration
+pub fn oembed() -> OembedConfig {
+    get_config().oembed
+}
+
+/// Get link preview configuration
+pub fn unfurl() -> UnfurlConfig {
+    get_config().unfurl
+}
+
+/// Get outbound link configuration
+pub fn outbound_links() -> OutboundLinksConfig {
+    get_config().outbound_links
+}
+
+/// Get static asset configuration
+pub fn assets() -> AssetsConfig {
+    get_config().assets
+}
+
+/// Get ActivityPub publishing configuration
+pub fn activitypub() -> ActivityPubConfig {
+    get_config().activitypub
+}
+
+/// Get tracing configuration
+pub fn telemetry() -> TelemetryConfig {
+    get_config().telemetry
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -423,6 +1107,27 @@ posts_per_page = 50
         assert_eq!(config.limits.threads_per_page, 20);
     }
 
+    #[test]
+    fn test_server_listen_addresses_replace_default() {
+        let mut temp_file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(
+            temp_file,
+            r#"
+[server]
+listen = ["127.0.0.1:3000", "[::1]:3000"]
+workers = 4
+"#
+        )
+        .unwrap();
+
+        let config = AppConfig::load_from_path(temp_file.path().to_str().unwrap()).unwrap();
+
+        assert_eq!(config.server.listen, vec!["127.0.0.1:3000", "[::1]:3000"]);
+        assert_eq!(config.server.workers, 4);
+        assert!(config.server.tls_listen.is_empty());
+        assert_eq!(config.server.keep_alive_seconds, 5);
+    }
+
     #[test]
     fn test_missing_config_file_uses_defaults() {
         let config = AppConfig::load_from_path("/nonexistent/config.toml").unwrap();
@@ -0,0 +1,49 @@
+//! State of one running forum
+//!
+//! Handlers reach it through actix `Data<AppState>` instead of process-wide
+//! statics, so several forums can run in one process, as tests do, without
+//! seeing each other's state. Background tasks started for a forum are given
+//! a clone.
+//!
+//! Only state that one forum builds up while it runs lives here. These stay
+//! process-wide, each for its own reason:
+//!
+//! - The cache, search and backplane clients (`crate::cache`,
+//!   `crate::search`, `crate::backplane`) are connections made once from the
+//!   process configuration.
+//! - Signing keys ([`crate::signing`]) are derived from `SECRET_KEY` in that
+//!   same environment.
+//! - `APP_CONFIG` in [`crate::app_config`] is the config file and environment
+//!   of the process. Its accessors are read by job handlers, the chat actor,
+//!   BBCode rendering and the CLI, none of which has an `AppState`.
+//! - `RATE_LIMIT_CONFIG` in [`crate::rate_limit`], `FILTER_CACHE` in
+//!   [`crate::word_filter`] and the `THEME_CACHE`, `THEME_CACHE_BY_ID` and
+//!   `THEME_CSS_CACHE` maps in [`crate::theme`] are copies of database
+//!   tables. Forums sharing a process share the database pool
+//!   ([`crate::db`]), which is process-wide itself, so they would load the
+//!   same rows; the chat actor and job handlers read them without a request.
+//!
+//! Reloading these when `config_versions` moves is driven by each forum's
+//! poller ([`crate::cache::versions`]), which keeps the counts it has seen
+//! in its own task.
+
+use crate::permission::Permissions;
+use crate::presence::Presence;
+
+/// State shared by every worker of one forum. Clones share it.
+#[derive(Clone, Debug, Default)]
+pub struct AppState {
+    /// Loaded permissions, reloaded in place when they change
+    pub permissions: Permissions,
+    /// Who is online, recorded by the client context middleware
+    pub presence: Presence,
+}
+
+impl AppState {
+    pub fn new(permissions: Permissions) -> Self {
+        Self {
+            permissions,
+            presence: Presence::default(),
+        }
+    }
+}
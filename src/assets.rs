@@ -0,0 +1,187 @@
+//! Fingerprinted static assets
+//!
+//! At startup the stylesheets and scripts webpack builds into `public/assets`
+//! are hashed, and pages link to them by names carrying the hash, such as
+//! `/public/assets/style.1a2b3c4d5e6f7a8b.css`. Those addresses change with
+//! the file's content, so they are served with an immutable cache lifetime;
+//! the plain names keep working and are revalidated on each use.
+//!
+//! Nothing is written to disk: `/public/assets/` maps a fingerprinted name
+//! back to the file it was made from. Assets rebuilt while the server runs
+//! keep their old hash until it restarts, so turn `[assets] fingerprint` off
+//! when running webpack in watch mode.
+
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Where webpack writes the forum's assets, served at `/public/assets/`.
+pub const PUBLIC_ASSET_DIR: &str = "public/assets";
+
+/// File extensions that are fingerprinted
+const FINGERPRINTED_EXTENSIONS: &[&str] = &["css", "js", "mjs"];
+
+/// Hex digits of the content hash kept in file names
+const HASH_LENGTH: usize = 16;
+
+static MANIFEST: OnceCell<AssetManifest> = OnceCell::new();
+
+/// Content hashes of the assets in a directory
+#[derive(Debug, Default)]
+pub struct AssetManifest {
+    /// Logical name -> content hash
+    hashes: HashMap<String, String>,
+    /// Fingerprinted name -> logical name
+    files: HashMap<String, String>,
+}
+
+impl AssetManifest {
+    /// Hash the assets in `dir`.
+    pub fn build(dir: &Path) -> std::io::Result<Self> {
+        let mut manifest = Self::default();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            let fingerprinted = Path::new(&name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| FINGERPRINTED_EXTENSIONS.contains(&ext));
+            if fingerprinted {
+                let contents = std::fs::read(entry.path())?;
+                manifest.insert(&name, &contents);
+            }
+        }
+        Ok(manifest)
+    }
+
+    fn insert(&mut self, name: &str, contents: &[u8]) {
+        let hash = blake3::hash(contents).to_hex()[..HASH_LENGTH].to_string();
+        self.files
+            .insert(fingerprinted_name(name, &hash), name.to_string());
+        self.hashes.insert(name.to_string(), hash);
+    }
+
+    /// Number of assets fingerprinted
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Content hash of the asset with logical name `name`.
+    pub fn hash(&self, name: &str) -> Option<&str> {
+        self.hashes.get(name).map(String::as_str)
+    }
+
+    /// Name to link to for the asset `name`, which is `name` itself for
+    /// files that weren't hashed.
+    pub fn file_name(&self, name: &str) -> String {
+        match self.hash(name) {
+            Some(hash) => fingerprinted_name(name, hash),
+            None => name.to_string(),
+        }
+    }
+
+    /// Logical name of the asset a fingerprinted name was made for.
+    pub fn resolve(&self, file_name: &str) -> Option<&str> {
+        self.files.get(file_name).map(String::as_str)
+    }
+}
+
+/// `style.css` with hash `abc` is `style.abc.css`.
+fn fingerprinted_name(name: &str, hash: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}.{}.{}", stem, hash, ext),
+        _ => format!("{}.{}", name, hash),
+    }
+}
+
+/// Hash the assets in `dir` for the rest of the process's life. Does nothing
+/// with `[assets] fingerprint` off, or when the directory can't be read, in
+/// which case plain names are used throughout.
+pub fn init(dir: &str) {
+    if !crate::app_config::assets().fingerprint {
+        log::info!("Asset fingerprinting is off");
+        return;
+    }
+    match AssetManifest::build(Path::new(dir)) {
+        Ok(manifest) => {
+            log::info!("Fingerprinted {} assets in {}", manifest.len(), dir);
+            let _ = MANIFEST.set(manifest);
+        }
+        Err(e) => log::warn!("Unable to fingerprint assets in {}: {}", dir, e),
+    }
+}
+
+/// Address of a forum asset by its logical name, e.g. `url("main.js")`.
+pub fn url(name: &str) -> String {
+    let file_name = match MANIFEST.get() {
+        Some(manifest) => manifest.file_name(name),
+        None => name.to_string(),
+    };
+    format!("/public/assets/{}", file_name)
+}
+
+/// Content hash of an asset, for cache-busting links to copies served by
+/// another web server, as the XenForo chat shim does.
+pub fn version(name: &str) -> Option<&'static str> {
+    MANIFEST.get()?.hash(name)
+}
+
+/// Logical name of the asset a fingerprinted file name refers to.
+pub fn resolve(file_name: &str) -> Option<&'static str> {
+    MANIFEST.get()?.resolve(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprinted_name() {
+        assert_eq!(fingerprinted_name("style.css", "abc"), "style.abc.css");
+        assert_eq!(
+            fingerprinted_name("push-sw.min.js", "abc"),
+            "push-sw.min.abc.js"
+        );
+        assert_eq!(fingerprinted_name("LICENSE", "abc"), "LICENSE.abc");
+    }
+
+    #[test]
+    fn test_manifest_build() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.js"), "console.log(1);").unwrap();
+        std::fs::write(dir.path().join("style.css"), "body {}").unwrap();
+        std::fs::write(dir.path().join("logo.png"), [0u8; 4]).unwrap();
+
+        let manifest = AssetManifest::build(dir.path()).unwrap();
+        assert_eq!(manifest.len(), 2);
+
+        let hash = manifest.hash("main.js").unwrap();
+        assert_eq!(hash.len(), HASH_LENGTH);
+        let file_name = manifest.file_name("main.js");
+        assert_eq!(file_name, format!("main.{}.js", hash));
+        assert_eq!(manifest.resolve(&file_name), Some("main.js"));
+
+        // Files that aren't hashed are linked as they are
+        assert_eq!(manifest.file_name("logo.png"), "logo.png");
+        assert_eq!(manifest.resolve("main.js"), None);
+    }
+
+    #[test]
+    fn test_hash_follows_content() {
+        let mut a = AssetManifest::default();
+        let mut b = AssetManifest::default();
+        a.insert("main.js", b"one");
+        b.insert("main.js", b"two");
+        assert_ne!(a.hash("main.js"), b.hash("main.js"));
+    }
+}
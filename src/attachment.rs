@@ -1,5 +1,9 @@
 use crate::db::get_db_pool;
 use crate::filesystem::get_file_url_by_filename;
+use crate::filesystem::visibility::get_file_url;
+use crate::orm::attachments::{AttachmentVisibility, ScanStatus};
+use crate::orm::users::AvatarSource;
+use crate::orm::video_transcodes::TranscodeStatus;
 use crate::orm::{attachments, ugc_attachments};
 use chrono::Utc;
 use sea_orm::{entity::*, query::*, sea_query::Expr, FromQueryResult};
@@ -20,6 +24,13 @@ pub struct AttachmentForTemplate {
     pub file_height: Option<i32>,
     pub file_width: Option<i32>,
     pub mime: String,
+    pub visibility: AttachmentVisibility,
+    pub scan_status: ScanStatus,
+    // smallest generated rendition, if any
+    pub thumbnail_filename: Option<String>,
+    // videos: transcode queue state and `mime filename` pairs of finished renditions
+    pub transcode_status: Option<TranscodeStatus>,
+    pub video_sources: Option<String>,
 }
 
 /// Enum of standarized attachment thumbnailing sizes.
@@ -34,7 +45,61 @@ pub enum AttachmentSize {
 
 impl AttachmentForTemplate {
     pub fn get_download_url(&self) -> String {
-        get_file_url_by_filename(&self.hash, &self.ugc_filename)
+        get_file_url(&self.hash, &self.ugc_filename, self.visibility)
+    }
+
+    /// URL of the small rendition for embeds, falling back to the original.
+    pub fn get_thumbnail_url(&self) -> String {
+        match &self.thumbnail_filename {
+            Some(filename) => get_file_url(filename, filename, self.visibility),
+            None => self.get_download_url(),
+        }
+    }
+
+    /// Whether the viewer may see the file. Conversation-only files are only
+    /// ever listed to the conversation's participants, and quarantined files
+    /// to no one.
+    pub fn is_visible_to(&self, client: &crate::middleware::ClientCtx) -> bool {
+        if self.scan_status.is_quarantined() {
+            return false;
+        }
+        match self.visibility {
+            AttachmentVisibility::Members => client.is_user(),
+            AttachmentVisibility::Public | AttachmentVisibility::Conversation => true,
+        }
+    }
+
+    pub fn is_video(&self) -> bool {
+        self.mime.starts_with("video/")
+    }
+
+    /// Whether the video is still waiting on the transcode queue.
+    pub fn is_transcoding(&self) -> bool {
+        self.transcode_status
+            .is_some_and(|status| status.is_processing())
+    }
+
+    /// Poster frame for videos, once one has been generated.
+    pub fn get_poster_url(&self) -> Option<String> {
+        self.thumbnail_filename
+            .as_ref()
+            .map(|filename| get_file_url(filename, filename, self.visibility))
+    }
+
+    /// URLs and MIME types of the streamable renditions, preferred first.
+    pub fn get_video_sources(&self) -> Vec<(String, String)> {
+        self.video_sources
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|source| source.split_once(' '))
+            .map(|(mime, filename)| {
+                (
+                    get_file_url(filename, filename, self.visibility),
+                    mime.to_owned(),
+                )
+            })
+            .collect()
     }
 
     pub fn to_html(&self) -> String {
@@ -62,6 +127,47 @@ pub async fn get_attachment_by_hash(hash: String) -> Option<attachments::Model>
         .unwrap_or_default()
 }
 
+/// Returns the stored filename of each re-encoded copy of an attachment, keyed by MIME type.
+pub async fn get_attachment_variants(attachment_id: i32) -> HashMap<String, String> {
+    use crate::orm::attachment_variants;
+
+    attachment_variants::Entity::find()
+        .filter(attachment_variants::Column::AttachmentId.eq(attachment_id))
+        .find_also_related(attachments::Entity)
+        .all(get_db_pool())
+        .await
+        .map_err(|e| log::error!("get_attachment_variants: {}", e))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|(variant, file)| Some((variant.mime, file?.filename)))
+        .collect()
+}
+
+/// Picks the variant to serve for an `Accept` header, most compact format first.
+/// Returns `None` when the client should receive the original.
+pub fn preferred_variant<'a>(
+    accept: &str,
+    available: &'a HashMap<String, String>,
+) -> Option<&'a str> {
+    let accepts = |mime: &str| {
+        accept.split(',').any(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            parts.next().is_some_and(|m| m.eq_ignore_ascii_case(mime))
+                && parts.all(|param| {
+                    param
+                        .strip_prefix("q=")
+                        .is_none_or(|q| q.parse::<f32>().map_or(true, |q| q > 0.0))
+                })
+        })
+    };
+
+    crate::imaging::VariantFormat::ALL
+        .iter()
+        .map(|format| format.mime())
+        .filter(|mime| accepts(mime))
+        .find_map(|mime| available.get(mime).map(String::as_str))
+}
+
 // Returns attachments through their ugc_attachment.id.
 pub async fn get_attachments_by_ugc_attachment_id(ugc: Vec<i32>) -> Vec<AttachmentForTemplate> {
     if ugc.is_empty() {
@@ -81,6 +187,20 @@ pub async fn get_attachments_by_ugc_attachment_id(ugc: Vec<i32>) -> Vec<Attachme
         .column(attachments::Column::FileHeight)
         .column(attachments::Column::FileWidth)
         .column(attachments::Column::Mime)
+        .column(attachments::Column::Visibility)
+        .column(attachments::Column::ScanStatus)
+        .column_as(
+            Expr::cust(&thumbnail_filename_sql("attachments")),
+            "thumbnail_filename",
+        )
+        .column_as(
+            Expr::cust(&transcode_status_sql("attachments")),
+            "transcode_status",
+        )
+        .column_as(
+            Expr::cust(&video_sources_sql("attachments")),
+            "video_sources",
+        )
         .filter(ugc_attachments::Column::Id.is_in(ugc))
         .order_by_asc(ugc_attachments::Column::CreatedAt)
         .into_model::<AttachmentForTemplate>()
@@ -109,6 +229,20 @@ pub async fn get_attachments_for_ugc_by_id(
         .column(attachments::Column::FileHeight)
         .column(attachments::Column::FileWidth)
         .column(attachments::Column::Mime)
+        .column(attachments::Column::Visibility)
+        .column(attachments::Column::ScanStatus)
+        .column_as(
+            Expr::cust(&thumbnail_filename_sql("attachments")),
+            "thumbnail_filename",
+        )
+        .column_as(
+            Expr::cust(&transcode_status_sql("attachments")),
+            "transcode_status",
+        )
+        .column_as(
+            Expr::cust(&video_sources_sql("attachments")),
+            "video_sources",
+        )
         .filter(ugc_attachments::Column::UgcId.is_in(ugc))
         .order_by_asc(ugc_attachments::Column::CreatedAt)
         .into_model::<AttachmentForTemplate>()
@@ -129,25 +263,177 @@ pub async fn get_attachments_for_ugc_by_id(
     result
 }
 
+impl AttachmentSize {
+    /// Returns the bounding box in pixels, or None for native size.
+    pub fn constraint(&self) -> Option<i32> {
+        match self {
+            AttachmentSize::Xs => Some(24),
+            AttachmentSize::S => Some(48),
+            AttachmentSize::M => Some(96),
+            AttachmentSize::L => Some(144),
+            AttachmentSize::Native => None,
+        }
+    }
+}
+
+/// Largest size Gravatar will serve, used in place of a native size.
+const GRAVATAR_MAX_SIZE: i32 = 512;
+
+/// Returns the Gravatar hash for an email address.
+/// Gravatar accepts SHA-256 of the trimmed, lowercased address.
+pub fn get_gravatar_hash(email: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let digest = Sha256::digest(email.trim().to_lowercase().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Constructs a Gravatar URL for an email hash at a specific pixel size.
+/// The default image may be a URL of its own, so it is percent-encoded.
+pub fn get_gravatar_url(hash: &str, px: i32) -> String {
+    let config = crate::app_config::avatars();
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("s", &px.to_string())
+        .append_pair("d", &config.gravatar_default)
+        .finish();
+    format!(
+        "{}/{}?{}",
+        config.gravatar_url.trim_end_matches('/'),
+        hash,
+        query
+    )
+}
+
+/// Constructs an HTML image tag for a Gravatar at a specific dimension.
+pub fn get_gravatar_html(email: &str, size: AttachmentSize) -> String {
+    let px = size.constraint().unwrap_or(GRAVATAR_MAX_SIZE);
+
+    format!(
+        "<img src=\"{}\" class=\"avatar\" width=\"{}\" height=\"{}\" />",
+        get_gravatar_url(&get_gravatar_hash(email), px),
+        px,
+        px
+    )
+}
+
+/// A stored avatar image and its pre-rendered smaller sizes.
+pub struct AvatarFile<'a> {
+    pub filename: &'a str,
+    pub dimensions: (i32, i32),
+    /// Smaller renditions, as listed by `avatar_renditions_sql`.
+    pub renditions: Option<&'a str>,
+}
+
+/// SQL subquery selecting the filename of the smallest thumbnail of an attachment.
+/// `attachments` is the table or alias the attachment was joined as.
+pub fn thumbnail_filename_sql(attachments: &str) -> String {
+    format!(
+        "(SELECT t.filename FROM attachment_thumbnails at \
+         JOIN attachments t ON t.id = at.thumbnail_id \
+         WHERE at.attachment_id = {}.id \
+         ORDER BY t.file_width ASC NULLS LAST LIMIT 1)",
+        attachments
+    )
+}
+
+/// SQL subquery selecting the transcode queue status of a video attachment.
+pub fn transcode_status_sql(attachments: &str) -> String {
+    format!(
+        "(SELECT status FROM video_transcodes WHERE attachment_id = {}.id)",
+        attachments
+    )
+}
+
+/// SQL subquery listing the video renditions of an attachment as comma-separated
+/// `mime filename` pairs, WebM first as it is usually smaller.
+pub fn video_sources_sql(attachments: &str) -> String {
+    format!(
+        "(SELECT string_agg(v.mime || ' ' || r.filename, ',' ORDER BY v.mime DESC) \
+         FROM attachment_variants v \
+         JOIN attachments r ON r.id = v.variant_id \
+         WHERE v.attachment_id = {}.id AND v.mime LIKE 'video/%')",
+        attachments
+    )
+}
+
+/// SQL subquery listing the thumbnails of an avatar attachment as comma-separated
+/// `filename width` pairs, smallest first. The srcset is built from these in Rust
+/// so each candidate's URL comes from the storage backend like any other file.
+/// `attachments` is the table or alias the avatar attachment was joined as.
+pub fn avatar_renditions_sql(attachments: &str) -> String {
+    format!(
+        "(SELECT string_agg(t.filename || ' ' || t.file_width, ',' ORDER BY t.file_width) \
+         FROM attachment_thumbnails at \
+         JOIN attachments t ON t.id = at.thumbnail_id \
+         WHERE at.attachment_id = {}.id AND t.file_width IS NOT NULL)",
+        attachments
+    )
+}
+
+/// Srcset candidates (`url widthw`) for renditions listed by `avatar_renditions_sql`.
+pub fn avatar_srcset(renditions: &str) -> Vec<String> {
+    renditions
+        .split(',')
+        .filter_map(|rendition| rendition.split_once(' '))
+        .map(|(filename, width)| {
+            format!(
+                "{} {}w",
+                get_file_url_by_filename(filename, filename),
+                width
+            )
+        })
+        .collect()
+}
+
+/// Constructs avatar HTML for any avatar source.
+///
+/// Uploaded and gallery avatars are both stored attachments. Gravatar is used
+/// when selected, or as a fallback for users without an image when enabled.
+pub fn get_avatar_html_for_source(
+    source: AvatarSource,
+    file: Option<AvatarFile>,
+    email: Option<&str>,
+    size: AttachmentSize,
+) -> String {
+    match (source, file, email) {
+        (AvatarSource::Gravatar, _, Some(email)) => get_gravatar_html(email, size),
+        (_, Some(file), _) => get_avatar_html(file, size),
+        (_, None, Some(email)) if crate::app_config::avatars().gravatar_fallback => {
+            get_gravatar_html(email, size)
+        }
+        _ => String::new(),
+    }
+}
+
 /// Constructs an HTML image tag at a specific dimension.
-pub fn get_avatar_html(filename: &str, dimensions: (i32, i32), size: AttachmentSize) -> String {
-    let constraint = match size {
-        AttachmentSize::Xs => 24,
-        AttachmentSize::S => 48,
-        AttachmentSize::M => 96,
-        AttachmentSize::L => 144,
-        AttachmentSize::Native => dimensions.0.to_owned().max(dimensions.1.to_owned()),
-    };
+/// When smaller renditions exist they are offered through srcset so the
+/// browser downloads the best fit for the display size and pixel density.
+pub fn get_avatar_html(file: AvatarFile, size: AttachmentSize) -> String {
+    let (width, height) = file.dimensions;
+    let constraint = size.constraint().unwrap_or_else(|| width.max(height));
 
-    let (x, y): (i32, i32) = match dimensions.0.cmp(&dimensions.1) {
-        std::cmp::Ordering::Greater => (constraint, constraint * (dimensions.1 / dimensions.0)),
-        std::cmp::Ordering::Less => (constraint * (dimensions.0 / dimensions.1), constraint),
+    let (x, y): (i32, i32) = match width.cmp(&height) {
+        std::cmp::Ordering::Greater => (constraint, (constraint * height / width.max(1)).max(1)),
+        std::cmp::Ordering::Less => ((constraint * width / height.max(1)).max(1), constraint),
         std::cmp::Ordering::Equal => (constraint, constraint),
     };
 
+    let url = get_file_url_by_filename(file.filename, file.filename);
+
+    let mut srcset = avatar_srcset(file.renditions.unwrap_or_default());
+    if srcset.is_empty() {
+        return format!(
+            "<img src=\"{}\" class=\"avatar\" width=\"{}\" height=\"{}\" />",
+            url, x, y
+        );
+    }
+    srcset.push(format!("{} {}w", url, width));
+
     format!(
-        "<img src=\"{}\" class=\"avatar\" width=\"{}\" height=\"{}\" />",
-        get_file_url_by_filename(filename, filename),
+        "<img src=\"{}\" srcset=\"{}\" sizes=\"{}px\" class=\"avatar\" width=\"{}\" height=\"{}\" />",
+        url,
+        srcset.join(", "),
+        x,
         x,
         y
     )
@@ -0,0 +1,228 @@
+//! Redis pub/sub backplane for running several app instances
+//!
+//! The chat and notification servers only reach WebSocket connections held by
+//! their own process. When `REDIS_URL` is set, they also publish what they send
+//! to a Redis channel and deliver what other instances publish there, so a
+//! load balancer can spread connections across instances. Without it nothing
+//! leaves the process.
+
+use futures::{Stream, StreamExt};
+use once_cell::sync::OnceCell;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+
+/// Channel for chat room broadcasts, presence and sanctions
+pub const CHAT_CHANNEL: &str = "dumpster:chat";
+/// Channel for the notification WebSocket fan-out
+pub const NOTIFICATION_CHANNEL: &str = "dumpster:notifications";
+/// Channel for cache invalidations
+pub const CACHE_CHANNEL: &str = "dumpster:cache";
+/// How long to wait before subscribing again after losing Redis
+pub const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+static BACKPLANE: OnceCell<Backplane> = OnceCell::new();
+
+pub struct Backplane {
+    client: redis::Client,
+    publisher: redis::aio::MultiplexedConnection,
+    /// Random ID for this instance, so it can skip its own messages
+    origin: String,
+}
+
+/// What goes over the wire: an event and the instance that sent it.
+#[derive(Deserialize)]
+struct Envelope<T> {
+    origin: String,
+    event: T,
+}
+
+/// Wrap an event in an envelope from `origin`.
+fn encode<T: Serialize>(origin: &str, event: &T) -> serde_json::Result<String> {
+    serde_json::to_string(&serde_json::json!({
+        "origin": origin,
+        "event": event,
+    }))
+}
+
+/// Unwrap an event received by the instance `origin`. Events it published
+/// itself come back from Redis too, and are None.
+fn decode<T: DeserializeOwned>(origin: &str, payload: &str) -> serde_json::Result<Option<T>> {
+    let envelope: Envelope<T> = serde_json::from_str(payload)?;
+    Ok((envelope.origin != origin).then_some(envelope.event))
+}
+
+/// Connect to Redis. Called once at startup when `REDIS_URL` is set.
+pub async fn init_backplane(url: &str) -> redis::RedisResult<()> {
+    let client = redis::Client::open(url)?;
+    let publisher = client.get_multiplexed_tokio_connection().await?;
+    let origin = uuid::Uuid::new_v4().to_string();
+
+    log::info!("Backplane connected to Redis as instance {}.", origin);
+
+    if BACKPLANE
+        .set(Backplane {
+            client,
+            publisher,
+            origin,
+        })
+        .is_err()
+    {
+        log::warn!("Backplane already initialized.");
+    }
+
+    Ok(())
+}
+
+/// The backplane, if this instance has one.
+pub fn get_backplane() -> Option<&'static Backplane> {
+    BACKPLANE.get()
+}
+
+impl Backplane {
+    /// Random ID other instances know this one by
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Publish an event for the other instances. Local delivery is the
+    /// caller's job; failures here are only logged.
+    pub fn publish<T: Serialize>(&self, channel: &'static str, event: &T) {
+        let payload = match encode(&self.origin, event) {
+            Ok(payload) => payload,
+            Err(err) => {
+                log::error!("Failed to serialize backplane event: {:?}", err);
+                return;
+            }
+        };
+
+        let mut publisher = self.publisher.clone();
+        actix::spawn(async move {
+            if let Err(err) = redis::cmd("PUBLISH")
+                .arg(channel)
+                .arg(payload)
+                .query_async::<_, ()>(&mut publisher)
+                .await
+            {
+                log::warn!("Failed to publish to backplane {}: {:?}", channel, err);
+            }
+        });
+    }
+
+    /// Events other instances publish on a channel. The stream ends if the
+    /// subscription is lost; subscribe again after `RESUBSCRIBE_DELAY`.
+    pub fn subscribe<T: DeserializeOwned + 'static>(
+        &self,
+        channel: &'static str,
+    ) -> impl Stream<Item = T> {
+        let client = self.client.clone();
+        let origin = self.origin.clone();
+
+        futures::stream::once(async move {
+            let mut pubsub = client.get_async_connection().await?.into_pubsub();
+            pubsub.subscribe(channel).await?;
+            Ok::<_, redis::RedisError>(pubsub.into_on_message())
+        })
+        .filter_map(move |subscription| async move {
+            match subscription {
+                Ok(messages) => Some(messages),
+                Err(err) => {
+                    log::error!("Failed to subscribe to backplane {}: {:?}", channel, err);
+                    None
+                }
+            }
+        })
+        .flatten()
+        .filter_map(move |message| {
+            let origin = origin.clone();
+            async move {
+                let payload: String = message.get_payload().ok()?;
+                match decode(&origin, &payload) {
+                    Ok(event) => event,
+                    Err(err) => {
+                        log::warn!("Unreadable backplane event on {}: {:?}", channel, err);
+                        None
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::chat::message::Relay;
+
+    #[test]
+    fn test_envelope_encoding() {
+        let payload = encode(
+            "instance-a",
+            &Relay::Room {
+                room_id: 3,
+                sender: Some(7),
+                message: "hello".to_owned(),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&payload).unwrap(),
+            serde_json::json!({
+                "origin": "instance-a",
+                "event": {
+                    "type": "Room",
+                    "room_id": 3,
+                    "sender": 7,
+                    "message": "hello",
+                },
+            })
+        );
+
+        match decode::<Relay>("instance-b", &payload).unwrap() {
+            Some(Relay::Room {
+                room_id,
+                sender,
+                message,
+            }) => {
+                assert_eq!(room_id, 3);
+                assert_eq!(sender, Some(7));
+                assert_eq!(message, "hello");
+            }
+            _ => panic!("expected a room relay"),
+        }
+    }
+
+    #[test]
+    fn test_own_events_are_skipped() {
+        let payload = encode(
+            "instance-a",
+            &Relay::User {
+                user_id: 7,
+                message: "hello".to_owned(),
+            },
+        )
+        .unwrap();
+
+        assert!(decode::<Relay>("instance-a", &payload).unwrap().is_none());
+        assert!(decode::<Relay>("instance-b", &payload).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_unreadable_events_are_errors() {
+        assert!(decode::<Relay>("instance-a", "not json").is_err());
+        assert!(decode::<Relay>("instance-a", r#"{"event":{"type":"Room"}}"#).is_err());
+        assert!(decode::<Relay>(
+            "instance-a",
+            r#"{"origin":"instance-b","event":{"type":"Unknown"}}"#
+        )
+        .is_err());
+
+        // Senders from before `sender` was added still read
+        let relay = decode::<Relay>(
+            "instance-a",
+            r#"{"origin":"instance-b","event":{"type":"Room","room_id":1,"message":"hi"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(relay, Some(Relay::Room { sender: None, .. })));
+    }
+}
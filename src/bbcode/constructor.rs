@@ -12,6 +12,8 @@ pub struct Constructor {
     pub enable_youtube_embeds: bool,
     /// If Some, only allow image thumbnails from these domains. None = allow all.
     pub image_domain_whitelist: Option<Vec<String>>,
+    /// If Some, only these tags render and the rest are left as text. None = allow all.
+    pub allowed_tags: Option<Vec<String>>,
 }
 
 impl Default for Constructor {
@@ -21,6 +23,7 @@ impl Default for Constructor {
             inline_spoilers: false,
             enable_youtube_embeds: true, // Enable embeds by default for forum posts
             image_domain_whitelist: None, // Allow all domains by default
+            allowed_tags: None,          // Allow all tags by default
         }
     }
 }
@@ -53,6 +56,17 @@ impl Constructor {
         }
     }
 
+    /// Check if a tag may render. Line breaks always may.
+    fn is_tag_allowed(&self, tag: &str) -> bool {
+        match &self.allowed_tags {
+            None => true,
+            Some(allowed) => {
+                let tag = tag.to_lowercase();
+                tag == "br" || allowed.contains(&tag)
+            }
+        }
+    }
+
     pub fn build(&self, node: Node<Element>) -> String {
         // Pre-allocate with reasonable capacity to reduce reallocations
         let mut output = String::with_capacity(256);
@@ -135,7 +149,10 @@ impl Constructor {
         use super::tag::*;
 
         if let Some(tag) = el.get_tag_name() {
-            if !el.is_broken() {
+            // Tags that aren't allowed show as the text that was typed.
+            if !self.is_tag_allowed(tag) {
+                Self::sanitize(&el.to_open_str())
+            } else if !el.is_broken() {
                 match Tag::get_by_name(tag) {
                     Tag::HorizontalRule => Tag::self_closing_tag("hr"),
                     Tag::Linebreak => Tag::self_closing_tag("br"),
@@ -195,6 +212,10 @@ impl Constructor {
 
     fn element_contents(&self, el: RefMut<Element>, contents: String) -> String {
         if let Some(tag) = el.get_tag_name() {
+            if !self.is_tag_allowed(tag) {
+                return contents;
+            }
+
             match Tag::get_by_name(tag) {
                 Tag::Image => {
                     if self.is_image_domain_allowed(&contents) {
@@ -237,8 +258,11 @@ impl Constructor {
     fn element_close(&self, el: RefMut<Element>) -> String {
         // Only named elements close with output.
         if let Some(tag) = el.get_tag_name() {
+            if !self.is_tag_allowed(tag) {
+                Self::sanitize(&el.to_close_str())
+            }
             // Only unbroken tags render HTML.
-            if !el.is_broken() {
+            else if !el.is_broken() {
                 match Tag::get_by_name(tag) {
                     Tag::Invalid => el.to_close_str(),
 
@@ -374,6 +398,7 @@ mod tests {
             inline_spoilers: false,
             enable_youtube_embeds: true,
             image_domain_whitelist: None,
+            allowed_tags: None,
         };
 
         let mut ast = Node::new(Element::new_root());
@@ -427,6 +452,7 @@ mod tests {
             inline_spoilers: false,
             enable_youtube_embeds: true,
             image_domain_whitelist: None,
+            allowed_tags: None,
         };
 
         let input = "[spoiler]Hidden content[/spoiler]";
@@ -459,6 +485,7 @@ mod tests {
             inline_spoilers: true,
             enable_youtube_embeds: true,
             image_domain_whitelist: None,
+            allowed_tags: None,
         };
 
         let input = "[spoiler]Hidden content[/spoiler]";
@@ -491,6 +518,7 @@ mod tests {
             inline_spoilers: true,
             enable_youtube_embeds: true,
             image_domain_whitelist: None,
+            allowed_tags: None,
         };
 
         let input = "[spoiler=Custom Title]Hidden content[/spoiler]";
@@ -508,4 +536,25 @@ mod tests {
             "Inline mode should include custom title in data attribute"
         );
     }
+
+    #[test]
+    fn disallowed_tags_render_as_text() {
+        use super::Constructor;
+        use crate::bbcode::{tokenize, Parser};
+
+        let con = Constructor {
+            allowed_tags: Some(vec!["b".to_string(), "url".to_string()]),
+            ..Constructor::new()
+        };
+
+        let input = "[b]bold[/b] [size=7]<big>[/size] [url=https://example.com]link[/url]";
+        let tokens = tokenize(input).expect("Failed to tokenize").1;
+        let mut parser = Parser::new();
+        let ast = parser.parse(&tokens);
+        let output = con.build(ast);
+
+        assert!(output.contains("<b>bold</b>"));
+        assert!(output.contains("[size=7]&lt;big&gt;[/size]"));
+        assert!(output.contains("href=\"https://example.com/\""));
+    }
 }
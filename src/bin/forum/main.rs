@@ -2,23 +2,30 @@ use actix::Actor;
 use actix_session::{config::PersistentSession, storage::CookieSessionStore, SessionMiddleware};
 use actix_web::cookie::{Key, SameSite};
 use actix_web::http::header;
+use actix_web::http::KeepAlive;
 use actix_web::http::StatusCode;
-use actix_web::middleware::{DefaultHeaders, ErrorHandlers, Logger};
-use actix_web::web::Data;
+use actix_web::middleware::{DefaultHeaders, ErrorHandlers};
+use actix_web::web::{self, Data};
 use actix_web::{App, HttpServer};
-use env_logger::Env;
 use rand::{distributions::Alphanumeric, Rng};
+use dumpster::app_state::AppState;
 use dumpster::config::create_config;
-use dumpster::db::{get_db_pool, init_db};
+use dumpster::db::{get_db_pool, get_db_read_pool, init_db, init_db_replicas};
+use dumpster::middleware::cors::ApiCors;
+use dumpster::middleware::request_id::RequestId;
 use dumpster::middleware::ClientCtx;
+use dumpster::permission::Permissions;
+use dumpster::telemetry::RequestSpan;
 use std::sync::Arc;
 use std::time::Duration;
+use tracing_actix_web::TracingLogger;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     init_lib_mods();
     init_our_mods();
     init_db(std::env::var("DATABASE_URL").expect("DATABASE_URL must be set.")).await;
+    init_db_replicas(&std::env::var("DATABASE_REPLICA_URLS").unwrap_or_default()).await;
 
     // Load configuration from database
     let config = create_config();
@@ -27,14 +34,19 @@ async fn main() -> std::io::Result<()> {
         .await
         .expect("Failed to load configuration from database");
 
-    // Initialize rate limits from database settings
-    dumpster::rate_limit::init_rate_limits(&config);
+    // Initialize rate limit policies from database
+    dumpster::rate_limit::reload_rate_limits(get_db_pool())
+        .await
+        .expect("Failed to load rate limit policies from database");
 
     // Initialize word filters from database
     dumpster::word_filter::init_filters(get_db_pool())
         .await
         .expect("Failed to load word filters from database");
 
+    // Hash static assets for cache-busting links
+    dumpster::assets::init(dumpster::assets::PUBLIC_ASSET_DIR);
+
     // Load themes into cache
     dumpster::theme::load_themes()
         .await
@@ -43,9 +55,10 @@ async fn main() -> std::io::Result<()> {
     let permissions = dumpster::permission::new()
         .await
         .expect("Permission System failed to initialize.");
-
-    // Initialize global permission data store for cache invalidation
-    dumpster::permission::init_permission_data(permissions.clone());
+    let state = AppState::new(Permissions::new(permissions));
+    state
+        .permissions
+        .set_priority_wins(config.get_bool_or(dumpster::permission::PRIORITY_WINS_SETTING, false));
 
     let secret_key = match std::env::var("SECRET_KEY") {
         Ok(key) => Key::from(key.as_bytes()),
@@ -60,9 +73,25 @@ async fn main() -> std::io::Result<()> {
         }
     };
 
+    // Share chat and notifications with other instances through Redis
+    if let Ok(url) = std::env::var("REDIS_URL") {
+        dumpster::backplane::init_backplane(&url)
+            .await
+            .expect("Failed to connect to the Redis backplane");
+    }
+
+    // Keep cached reads in Redis when it's there, in memory otherwise
+    dumpster::cache::init(std::env::var("REDIS_URL").ok().as_deref()).await;
+    dumpster::cache::spawn_listener(config.clone(), state.clone());
+    // Enforce rate limits across instances when configured to
+    dumpster::rate_limit::init(std::env::var("REDIS_URL").ok().as_deref()).await;
+    // Pick up settings and word filters changed by other instances without Redis
+    dumpster::cache::versions::spawn_poller(config.clone(), state.clone());
+
     let layer = Arc::new(dumpster::web::chat::implement::default::Layer {
         db: get_db_pool().to_owned(),
         config: config.clone(),
+        permissions: state.permissions.clone(),
     });
     let chat = dumpster::web::chat::server::ChatServer::new(layer.clone(), config.clone())
         .await
@@ -73,17 +102,78 @@ async fn main() -> std::io::Result<()> {
     dumpster::web::notifications_ws::init_notification_server(notification_server.clone());
 
     // Spawn rate limiter cleanup task
-    actix_web::rt::spawn(async {
+    let maintenance_permissions = state.permissions.clone();
+    let maintenance_presence = state.presence.clone();
+    actix_web::rt::spawn(async move {
         let mut interval = actix_web::rt::time::interval(Duration::from_secs(300)); // Every 5 minutes
         loop {
             interval.tick().await;
             dumpster::rate_limit::cleanup_old_entries_public();
             dumpster::user::cleanup_activity_cache();
-            log::debug!("Rate limiter and activity cache cleanup completed");
+            maintenance_presence.prune();
+            dumpster::filesystem::chunked::cleanup_expired_uploads().await;
+            dumpster::filesystem::dedup::cleanup_unreferenced_attachments().await;
+            if let Err(e) = dumpster::login_throttle::prune().await {
+                log::error!("Failed to prune failed login counts: {}", e);
+            }
+            match dumpster::permission::delete_expired_collections(get_db_pool()).await {
+                Ok(0) => {}
+                Ok(deleted) => {
+                    log::info!("Deleted {} expired permission collections", deleted);
+                    if let Err(e) = maintenance_permissions.reload_forum_permissions().await {
+                        log::error!("Failed to reload forum permissions: {}", e);
+                    }
+                    if let Err(e) = maintenance_permissions.reload_chat_room_permissions().await {
+                        log::error!("Failed to reload chat room permissions: {}", e);
+                    }
+                }
+                Err(e) => log::error!("Failed to delete expired permissions: {}", e),
+            }
+            if let Err(e) = dumpster::notifications::snooze::wake_snoozed_notifications(
+                chrono::Utc::now().naive_utc(),
+            )
+            .await
+            {
+                log::error!("Failed to wake snoozed notifications: {}", e);
+            }
+            log::debug!(
+                "Rate limiter, activity cache, upload, snooze and permission maintenance completed"
+            );
         }
     });
 
-    HttpServer::new(move || {
+    // Fill an empty search index
+    dumpster::search::indexer::spawn_prepare();
+
+    // Start the notification digest scheduler
+    dumpster::notifications::digest::spawn_worker();
+
+    // Start the overdue report reminder scheduler
+    dumpster::notifications::reports::spawn_worker();
+
+    // Start the nightly reputation recalculation
+    dumpster::reputation::spawn_worker();
+
+    // Start archiving activities past the retention horizon
+    dumpster::activities::retention::spawn_worker();
+
+    // Start the background job queue (deliveries, scans and transcodes)
+    dumpster::jobs::spawn_worker();
+
+    let server_config = dumpster::app_config::server();
+    if server_config.listen.is_empty() && server_config.tls_listen.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "No listen addresses configured in [server]",
+        ));
+    }
+    let (json_limit, form_limit, payload_limit) = (
+        server_config.json_limit_bytes,
+        server_config.form_limit_bytes,
+        server_config.payload_limit_bytes,
+    );
+
+    let mut server = HttpServer::new(move || {
         let layer_data: Data<Arc<dyn dumpster::web::chat::implement::ChatLayer>> =
             Data::new(layer.clone());
 
@@ -92,11 +182,16 @@ async fn main() -> std::io::Result<()> {
         // placed higher
         App::new()
             .app_data(Data::new(get_db_pool()))
-            .app_data(Data::new(permissions.clone()))
+            // Feeds take their connection from app data and read from a replica.
+            .app_data(Data::new(get_db_read_pool().clone()))
+            .app_data(Data::new(state.clone()))
             .app_data(Data::new(config.clone()))
             .app_data(layer_data)
             .app_data(chat.clone())
             .app_data(Data::new(notification_server.clone()))
+            .app_data(web::JsonConfig::default().limit(json_limit))
+            .app_data(web::FormConfig::default().limit(form_limit))
+            .app_data(web::PayloadConfig::new(payload_limit))
             // Security headers - applied to all responses
             .wrap(
                 DefaultHeaders::new()
@@ -126,7 +221,9 @@ async fn main() -> std::io::Result<()> {
                     .session_lifecycle(PersistentSession::default())
                     .build(),
             )
-            .wrap(Logger::new("%a %{User-Agent}i"))
+            .wrap(ApiCors)
+            .wrap(RequestId)
+            .wrap(TracingLogger::<RequestSpan>::new())
             .configure(dumpster::web::configure)
     })
     // https://www.restapitutorial.com/lessons/httpmethods.html
@@ -134,16 +231,40 @@ async fn main() -> std::io::Result<()> {
     // PATCH  update_ (apply edit)
     // GET    view_ (read/view/render entity)
     // Note: PUT and PATCH were added, removed, and re-added(?) to the HTML5 spec for <form method="">
-    .bind("0.0.0.0:8080")?
-    .run()
-    .await
+    .keep_alive(match server_config.keep_alive_seconds {
+        0 => KeepAlive::Disabled,
+        seconds => KeepAlive::Timeout(Duration::from_secs(seconds)),
+    })
+    .client_request_timeout(Duration::from_secs(
+        server_config.client_request_timeout_seconds,
+    ))
+    .shutdown_timeout(server_config.shutdown_timeout_seconds);
+
+    if server_config.workers > 0 {
+        server = server.workers(server_config.workers);
+    }
+    for addr in &server_config.listen {
+        server = server.bind(addr)?;
+        log::info!("Listening on http://{}", addr);
+    }
+    if !server_config.tls_listen.is_empty() {
+        let tls = dumpster::tls::load_server_config(&server_config)?;
+        for addr in &server_config.tls_listen {
+            server = server.bind_rustls_021(addr, tls.clone())?;
+            log::info!("Listening on https://{}", addr);
+        }
+    }
+
+    server.run().await?;
+
+    dumpster::telemetry::shutdown();
+    Ok(())
 }
 
 /// Initialize third party crates we rely on but don't have control over.
 pub fn init_lib_mods() {
     // This should be calls to crates without any transformative work applied.
     dotenv::dotenv().expect("DotEnv failed to initialize.");
-    env_logger::Builder::from_env(Env::default().default_filter_or("debug")).init();
     ffmpeg_next::init().expect("FFMPEG failed to initialize.");
 }
 
@@ -153,8 +274,10 @@ pub fn init_our_mods() {
     // This should be a list of simple function calls.
     // Each module should work mostly independent of others.
     // This way, we can unit test individual modules without loading the entire application.
+    dumpster::telemetry::init();
     dumpster::app_config::init();
     dumpster::global::init();
     dumpster::session::init();
     dumpster::filesystem::init();
+    dumpster::search::init();
 }
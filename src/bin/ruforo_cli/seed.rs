@@ -0,0 +1,591 @@
+//! Development data: members with avatars, a forum tree, threads of BBCode
+//! posts, conversations and the notifications they raise.
+//!
+//! Everything is drawn from a seeded generator, so the same options build the
+//! same forum on every machine. Dates are spread over the year before the run.
+//! A few threads are made very long so pagination has something to page.
+
+use super::CliResult;
+use chrono::{Duration, NaiveDateTime, Utc};
+use dumpster::db::get_db_pool;
+use dumpster::notifications::grouping::{self, GroupEvent};
+use dumpster::notifications::NotificationType;
+use dumpster::orm::{
+    conversation_participants, conversations, forums, posts, private_messages, threads,
+    user_avatars, user_name_history, user_names, users,
+};
+use dumpster::ugc::create_dated_ugc;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use sea_orm::{entity::*, query::*, ConnectionTrait, DbBackend, Statement};
+
+/// Password of every seeded account
+pub const PASSWORD: &str = "password";
+
+pub struct SeedOptions {
+    pub seed: u64,
+    pub users: usize,
+    pub threads: usize,
+    pub posts: usize,
+    pub conversations: usize,
+    pub avatars: bool,
+}
+
+/// Categories and the forums under them.
+const FORUMS: [(&str, &[(&str, &str)]); 3] = [
+    (
+        "Community",
+        &[
+            ("Announcements", "News about the site."),
+            ("Introductions", "Say hello."),
+            ("Off Topic", "Anything goes, within the rules."),
+        ],
+    ),
+    (
+        "Technology",
+        &[
+            ("Programming", "Languages, tools and code review."),
+            ("Hardware", "Builds, parts and peripherals."),
+            ("Self-Hosting", "Servers, homelabs and networking."),
+        ],
+    ),
+    (
+        "Hobbies",
+        &[
+            ("Games", "What you're playing."),
+            ("Music", "Listening, playing and recording."),
+            ("Cooking", "Recipes and kitchen disasters."),
+        ],
+    ),
+];
+
+const ADJECTIVES: [&str; 24] = [
+    "quiet", "brave", "lazy", "clever", "rusty", "sleepy", "wild", "gentle", "cosmic", "grumpy",
+    "lucky", "noisy", "shiny", "silent", "swift", "tiny", "vivid", "wise", "bold", "calm", "eager",
+    "fuzzy", "jolly", "mellow",
+];
+
+const NOUNS: [&str; 24] = [
+    "otter", "falcon", "badger", "comet", "walrus", "panda", "lynx", "heron", "moose", "gecko",
+    "raven", "koala", "bison", "squid", "finch", "yak", "marmot", "tapir", "ferret", "puffin",
+    "newt", "okapi", "dingo", "wombat",
+];
+
+const WORDS: [&str; 64] = [
+    "the", "a", "of", "and", "to", "in", "is", "it", "that", "for", "on", "with", "this", "was",
+    "but", "not", "server", "build", "thread", "update", "weekend", "question", "problem",
+    "answer", "idea", "version", "release", "setup", "config", "kernel", "driver", "recipe",
+    "album", "level", "boss", "patch", "forum", "post", "reply", "works", "broke", "tried",
+    "fixed", "think", "maybe", "really", "probably", "again", "today", "finally", "honestly",
+    "great", "strange", "slow", "fast", "better", "worse", "old", "new", "cheap", "loud", "simple",
+    "random", "anyway",
+];
+
+/// Run the generator against an empty forum, or alongside existing data when
+/// `force` is set.
+pub async fn run(options: &SeedOptions, force: bool) -> CliResult {
+    let db = get_db_pool();
+    if !force && threads::Entity::find().one(db).await?.is_some() {
+        return Err("The database already has threads; pass --force to seed it anyway.".into());
+    }
+    if options.users < 2 {
+        return Err("Seeding needs at least two users.".into());
+    }
+    if options.avatars {
+        dumpster::filesystem::init();
+    }
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let now = Utc::now().naive_utc();
+
+    let users = seed_users(&mut rng, options, now).await?;
+    println!("users: {}", users.len());
+
+    let forum_ids = seed_forums().await?;
+    println!("forums: {}", forum_ids.len());
+
+    let posts = seed_threads(&mut rng, options, now, &users, &forum_ids).await?;
+    println!("threads: {}, posts: {}", options.threads, posts);
+
+    let messages = seed_conversations(&mut rng, options, now, &users).await?;
+    println!(
+        "conversations: {}, messages: {}",
+        options.conversations, messages
+    );
+
+    println!("Seeded accounts sign in with the password {:?}.", PASSWORD);
+    Ok(())
+}
+
+/// A seeded member, by ID and name.
+struct SeedUser {
+    id: i32,
+    name: String,
+}
+
+async fn seed_users(
+    rng: &mut StdRng,
+    options: &SeedOptions,
+    now: NaiveDateTime,
+) -> Result<Vec<SeedUser>, Box<dyn std::error::Error>> {
+    let db = get_db_pool();
+    let password = dumpster::session::hash_password(PASSWORD)
+        .map_err(|e| format!("Failed to hash password: {}", e))?;
+
+    let mut seeded = Vec::with_capacity(options.users);
+    for i in 0..options.users {
+        let mut name = format!(
+            "{}_{}",
+            ADJECTIVES.choose(rng).unwrap(),
+            NOUNS.choose(rng).unwrap()
+        );
+        if name_taken(&name).await? {
+            name = format!("{}{}", name, i);
+        }
+        let created_at = now - Duration::days(rng.gen_range(30..730));
+
+        let txn = db.begin().await?;
+        let user = users::ActiveModel {
+            created_at: Set(created_at),
+            password: Set(password.to_owned()),
+            password_cipher: Set(users::Cipher::Argon2id),
+            email: Set(Some(format!("{}@example.com", name))),
+            email_verified: Set(true),
+            signature: Set(rng
+                .gen_bool(0.3)
+                .then(|| format!("[i]{}[/i]", sentence(rng)))),
+            approval_status: Set(users::ApprovalStatus::Approved),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+        user_names::Entity::insert(user_names::ActiveModel {
+            user_id: Set(user.id),
+            name: Set(name.to_owned()),
+        })
+        .exec(&txn)
+        .await?;
+        user_name_history::Entity::insert(user_name_history::ActiveModel {
+            user_id: Set(user.id),
+            created_at: Set(created_at),
+            approved_at: Set(created_at),
+            name: Set(name.to_owned()),
+            is_public: Set(true),
+            ..Default::default()
+        })
+        .exec(&txn)
+        .await?;
+        txn.commit().await?;
+
+        // Drawn even without avatars so the rest of the data stays the same.
+        let avatar = identicon(rng);
+        if options.avatars {
+            set_avatar(user.id, avatar, now).await?;
+        }
+
+        seeded.push(SeedUser { id: user.id, name });
+    }
+
+    Ok(seeded)
+}
+
+async fn name_taken(name: &str) -> Result<bool, sea_orm::DbErr> {
+    Ok(get_db_pool()
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT user_id FROM user_names WHERE LOWER(name) = LOWER($1) LIMIT 1",
+            vec![name.into()],
+        ))
+        .await?
+        .is_some())
+}
+
+/// A mirrored five by five pattern in one colour, like the default avatars
+/// of older forum software.
+fn identicon(rng: &mut StdRng) -> RgbImage {
+    const CELLS: u32 = 5;
+    const CELL: u32 = 48;
+    const MARGIN: u32 = 24;
+
+    let colour = Rgb([
+        rng.gen_range(40..200),
+        rng.gen_range(40..200),
+        rng.gen_range(40..200),
+    ]);
+    let cells: Vec<bool> = (0..CELLS * CELLS.div_ceil(2))
+        .map(|_| rng.gen_bool(0.5))
+        .collect();
+
+    let side = CELLS * CELL + MARGIN * 2;
+    RgbImage::from_fn(side, side, |x, y| {
+        if x < MARGIN || y < MARGIN || x >= side - MARGIN || y >= side - MARGIN {
+            return Rgb([240, 240, 240]);
+        }
+        let (col, row) = ((x - MARGIN) / CELL, (y - MARGIN) / CELL);
+        let col = col.min(CELLS - 1 - col);
+        match cells[(row * CELLS.div_ceil(2) + col) as usize] {
+            true => colour,
+            false => Rgb([240, 240, 240]),
+        }
+    })
+}
+
+async fn set_avatar(user_id: i32, avatar: RgbImage, now: NaiveDateTime) -> CliResult {
+    let encoded = dumpster::imaging::encode(&DynamicImage::ImageRgb8(avatar), ImageFormat::Png)
+        .ok_or("Failed to encode an avatar.")?;
+    let file = dumpster::filesystem::insert_image_as_avatar(encoded.data, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("Failed to store an avatar.")?;
+
+    user_avatars::Entity::insert(user_avatars::ActiveModel {
+        user_id: Set(user_id),
+        attachment_id: Set(file.id),
+        created_at: Set(now),
+    })
+    .exec(get_db_pool())
+    .await?;
+    dumpster::filesystem::visibility::refresh_attachments_visibility([file.id]).await;
+
+    Ok(())
+}
+
+/// Creates the forum tree and returns the forums threads can go in.
+async fn seed_forums() -> Result<Vec<i32>, sea_orm::DbErr> {
+    let db = get_db_pool();
+    let mut leaves = Vec::new();
+
+    for (order, (category, children)) in FORUMS.iter().enumerate() {
+        let parent = forums::ActiveModel {
+            label: Set(category.to_string()),
+            display_order: Set(order as i32),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        for (order, (label, description)) in children.iter().enumerate() {
+            let forum = forums::ActiveModel {
+                label: Set(label.to_string()),
+                description: Set(Some(description.to_string())),
+                parent_id: Set(Some(parent.id)),
+                display_order: Set(order as i32),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+            leaves.push(forum.id);
+        }
+    }
+
+    Ok(leaves)
+}
+
+/// Creates the threads and their posts, and returns how many posts were made.
+async fn seed_threads(
+    rng: &mut StdRng,
+    options: &SeedOptions,
+    now: NaiveDateTime,
+    users: &[SeedUser],
+    forum_ids: &[i32],
+) -> Result<usize, Box<dyn std::error::Error>> {
+    if options.threads == 0 {
+        return Ok(0);
+    }
+
+    // Every thread gets an opening post. The rest are dealt out unevenly so
+    // the first threads run to many pages and most stay short.
+    let mut lengths = vec![1usize; options.threads];
+    for _ in options.threads..options.posts {
+        let skewed = rng.gen::<f64>().powi(3);
+        lengths[(skewed * options.threads as f64) as usize] += 1;
+    }
+
+    let db = get_db_pool();
+    let mut total = 0;
+    for length in lengths {
+        let forum_id = *forum_ids.choose(rng).unwrap();
+        let author = users.choose(rng).unwrap();
+        let title = title(rng);
+        let mut created_at = now - Duration::minutes(rng.gen_range(60..525_600));
+
+        let txn = db.begin().await?;
+        let thread = threads::ActiveModel {
+            forum_id: Set(forum_id),
+            user_id: Set(Some(author.id)),
+            created_at: Set(created_at),
+            title: Set(title.to_owned()),
+            view_count: Set(rng.gen_range(length as i32..length as i32 * 40 + 10)),
+            post_count: Set(0),
+            is_locked: Set(rng.gen_bool(0.03)),
+            is_pinned: Set(rng.gen_bool(0.02)),
+            is_announcement: Set(false),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let mut replies = Vec::new();
+        let mut first_post_id = None;
+        let mut last_post = None;
+        for position in 1..=length {
+            let poster = match position {
+                1 => author,
+                _ => users.choose(rng).unwrap(),
+            };
+            let quoted = (position > 1 && rng.gen_bool(0.15)).then(|| users.choose(rng).unwrap());
+            let content = post_content(rng, quoted.map(|user| user.name.as_str()));
+
+            let ugc_id = create_dated_ugc(&txn, Some(poster.id), &content, created_at).await?;
+            let post = posts::ActiveModel {
+                thread_id: Set(thread.id),
+                position: Set(position as i32),
+                ugc_id: Set(ugc_id),
+                user_id: Set(Some(poster.id)),
+                created_at: Set(created_at),
+                moderation_status: Set(posts::ModerationStatus::Approved),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+
+            first_post_id.get_or_insert(post.id);
+            last_post = Some((post.id, created_at));
+            if poster.id != author.id {
+                replies.push((post.id, poster));
+            }
+
+            // Replies come minutes to days apart, but never after the run.
+            created_at = (created_at + Duration::minutes(rng.gen_range(1..2880))).min(now);
+        }
+
+        let mut thread: threads::ActiveModel = thread.into();
+        thread.post_count = Set(length as i32);
+        thread.first_post_id = Set(first_post_id);
+        thread.last_post_id = Set(last_post.map(|(id, _)| id));
+        thread.last_post_at = Set(last_post.map(|(_, at)| at));
+        let thread = thread.update(&txn).await?;
+        txn.commit().await?;
+
+        for (post_id, poster) in replies {
+            grouping::notify_grouped(GroupEvent {
+                user_id: author.id,
+                notification_type: NotificationType::Reply,
+                group_key: grouping::group_key(&NotificationType::Reply, "thread", thread.id),
+                actor_id: poster.id,
+                actor_name: poster.name.to_owned(),
+                action: "replied to your thread".to_string(),
+                message: format!("New replies in: {}", thread.title),
+                url: Some(format!("/threads/{}#post-{}", thread.id, post_id)),
+                source_content_type: Some("thread".to_string()),
+                source_content_id: Some(thread.id),
+            })
+            .await?;
+        }
+
+        total += length;
+    }
+
+    // Forums pick up their last posts here.
+    super::recount().await?;
+
+    Ok(total)
+}
+
+/// Creates conversations between a few members each, and returns how many
+/// messages were sent.
+async fn seed_conversations(
+    rng: &mut StdRng,
+    options: &SeedOptions,
+    now: NaiveDateTime,
+    users: &[SeedUser],
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let db = get_db_pool();
+    let mut total = 0;
+
+    for _ in 0..options.conversations {
+        let size = rng.gen_range(2..=4.min(users.len()));
+        let members: Vec<&SeedUser> = users.choose_multiple(rng, size).collect();
+        let creator = members[0];
+        let mut created_at = now - Duration::minutes(rng.gen_range(60..262_800));
+        let count = rng.gen_range(1..=15);
+
+        let txn = db.begin().await?;
+        let conversation = conversations::ActiveModel {
+            title: Set(Some(title(rng))),
+            creator_id: Set(Some(creator.id)),
+            created_at: Set(created_at),
+            updated_at: Set(created_at),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let mut sent = Vec::with_capacity(count);
+        for i in 0..count {
+            let sender = match i {
+                0 => creator,
+                _ => *members.choose(rng).unwrap(),
+            };
+            let content = post_content(rng, None);
+            let ugc_id = create_dated_ugc(&txn, Some(sender.id), &content, created_at).await?;
+            let message = private_messages::ActiveModel {
+                conversation_id: Set(conversation.id),
+                ugc_id: Set(ugc_id),
+                user_id: Set(Some(sender.id)),
+                created_at: Set(created_at),
+                kind: Set(private_messages::MessageKind::Message),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            sent.push((message.id, sender, content));
+            created_at = (created_at + Duration::minutes(rng.gen_range(1..720))).min(now);
+        }
+
+        // Whoever sent the last message has read the conversation; everyone
+        // else has it waiting.
+        let (last_id, last_sender, _) = sent.last().unwrap();
+        for member in &members {
+            conversation_participants::Entity::insert(conversation_participants::ActiveModel {
+                conversation_id: Set(conversation.id),
+                user_id: Set(member.id),
+                joined_at: Set(conversation.created_at),
+                last_read_message_id: Set((member.id == last_sender.id).then_some(*last_id)),
+                is_archived: Set(false),
+                is_muted: Set(false),
+                role: Set(match member.id == creator.id {
+                    true => conversation_participants::ParticipantRole::Owner,
+                    false => conversation_participants::ParticipantRole::Member,
+                }),
+            })
+            .exec(&txn)
+            .await?;
+        }
+
+        let mut conversation: conversations::ActiveModel = conversation.into();
+        conversation.updated_at = Set(created_at);
+        let conversation = conversation.update(&txn).await?;
+        txn.commit().await?;
+
+        for (_, sender, content) in &sent {
+            for member in members.iter().filter(|member| member.id != sender.id) {
+                grouping::notify_grouped(GroupEvent {
+                    user_id: member.id,
+                    notification_type: NotificationType::PrivateMessage,
+                    group_key: grouping::group_key(
+                        &NotificationType::PrivateMessage,
+                        "conversation",
+                        conversation.id,
+                    ),
+                    actor_id: sender.id,
+                    actor_name: sender.name.to_owned(),
+                    action: "messaged you".to_string(),
+                    message: content.to_owned(),
+                    url: Some(format!("/conversations/{}", conversation.id)),
+                    source_content_type: Some("conversation".to_string()),
+                    source_content_id: Some(conversation.id),
+                })
+                .await?;
+            }
+        }
+
+        total += count;
+    }
+
+    Ok(total)
+}
+
+fn words(rng: &mut StdRng, count: usize) -> String {
+    (0..count)
+        .map(|_| *WORDS.choose(rng).unwrap())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn sentence(rng: &mut StdRng) -> String {
+    let count = rng.gen_range(4..16);
+    let mut sentence = words(rng, count);
+    sentence[..1].make_ascii_uppercase();
+    sentence.push(*['.', '.', '.', '?', '!'].choose(rng).unwrap());
+    sentence
+}
+
+fn title(rng: &mut StdRng) -> String {
+    let count = rng.gen_range(2..8);
+    let mut title = words(rng, count);
+    title[..1].make_ascii_uppercase();
+    title
+}
+
+/// A post of a few paragraphs, using the BBCode members commonly reach for.
+fn post_content(rng: &mut StdRng, quoted: Option<&str>) -> String {
+    let mut blocks = Vec::new();
+    if let Some(name) = quoted {
+        blocks.push(format!("[quote={}]{}[/quote]", name, sentence(rng)));
+    }
+
+    for _ in 0..rng.gen_range(1..4) {
+        let mut paragraph: Vec<String> = (0..rng.gen_range(1..5)).map(|_| sentence(rng)).collect();
+        let i = rng.gen_range(0..paragraph.len());
+        paragraph[i] = match rng.gen_range(0..8) {
+            0 => format!("[b]{}[/b]", paragraph[i]),
+            1 => format!("[i]{}[/i]", paragraph[i]),
+            2 => format!("[u]{}[/u]", paragraph[i]),
+            3 => format!("[s]{}[/s]", paragraph[i]),
+            4 => format!(
+                "{} [url=https://example.com/{}]{}[/url]",
+                paragraph[i],
+                rng.gen_range(1..1000),
+                words(rng, 2)
+            ),
+            _ => paragraph[i].to_owned(),
+        };
+        blocks.push(paragraph.join(" "));
+    }
+
+    match rng.gen_range(0..12) {
+        0 => blocks.push(format!(
+            "[list]\n{}\n[/list]",
+            (0..rng.gen_range(2..6))
+                .map(|_| format!("[*]{}", words(rng, 3)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )),
+        1 => blocks.push(format!(
+            "[code]fn main() {{\n    println!(\"{}\");\n}}[/code]",
+            words(rng, 3)
+        )),
+        2 => blocks.push(format!("[spoiler]{}[/spoiler]", sentence(rng))),
+        _ => {}
+    }
+
+    blocks.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_content() {
+        let mut a = StdRng::seed_from_u64(7);
+        let mut b = StdRng::seed_from_u64(7);
+        assert_eq!(
+            post_content(&mut a, Some("alice")),
+            post_content(&mut b, Some("alice"))
+        );
+        assert_eq!(identicon(&mut a), identicon(&mut b));
+    }
+
+    #[test]
+    fn test_identicon_is_mirrored() {
+        let image = identicon(&mut StdRng::seed_from_u64(1));
+        let side = image.width();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel, image.get_pixel(side - 1 - x, y));
+        }
+    }
+}
@@ -60,6 +60,9 @@ async fn main() -> std::io::Result<()> {
     //        panic!("{:?}", err);
     //    }
     //};
+    // Hash the chat assets for cache-busting links in the shim
+    dumpster::assets::init(&std::env::var("CHAT_ASSET_DIR").unwrap_or_else(|_| ".".to_string()));
+
     // Create a default config for XF compatibility (uses built-in defaults)
     let config = create_config();
 
@@ -13,7 +13,7 @@ pub struct XfLayer {
 
 #[async_trait::async_trait]
 impl implement::ChatLayer for XfLayer {
-    async fn can_send_message(&self, session: &implement::Session) -> bool {
+    async fn can_send_message(&self, session: &implement::Session, _room_id: u32) -> bool {
         session::can_send_message(&self.db, session.id).await
     }
 
@@ -48,6 +48,8 @@ impl implement::ChatLayer for XfLayer {
                 description: room.description,
                 motd: None,
                 display_order: room.display_order,
+                is_private: false,
+                unread: false,
             })
             .collect()
     }
@@ -55,9 +57,10 @@ impl implement::ChatLayer for XfLayer {
     async fn get_room_history(
         &self,
         room_id: u32,
+        before: Option<u32>,
         limit: usize,
     ) -> Vec<(implement::Author, implement::Message)> {
-        room::get_room_history(&self.db, room_id, limit).await
+        room::get_room_history(&self.db, room_id, before, limit).await
     }
 
     async fn get_smilie_list(&self) -> Vec<implement::Smilie> {
@@ -83,7 +86,10 @@ impl implement::ChatLayer for XfLayer {
     }
 
     async fn insert_chat_message(&self, message: &Post) -> Option<implement::Message> {
-        if self.can_send_message(&message.session).await {
+        if self
+            .can_send_message(&message.session, message.room_id)
+            .await
+        {
             match message::insert_chat_message(&self.db, message).await {
                 Ok(model) => Some(model),
                 Err(err) => {
@@ -95,6 +101,10 @@ impl implement::ChatLayer for XfLayer {
             None
         }
     }
+
+    async fn is_ignoring(&self, user_id: u32, other_id: u32) -> bool {
+        session::is_ignoring(&self.db, user_id, other_id).await
+    }
 }
 
 impl From<orm::chat_message::Model> for implement::Message {
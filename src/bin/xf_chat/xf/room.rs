@@ -85,10 +85,16 @@ pub async fn get_room_list(db: &DatabaseConnection) -> Vec<chat_room::Model> {
 pub async fn get_room_history(
     db: &DatabaseConnection,
     id: u32,
+    before: Option<u32>,
     count: usize,
 ) -> Vec<(implement::Author, implement::Message)> {
-    chat_message::Entity::find()
-        .filter(chat_message::Column::RoomId.eq(id as u32))
+    let mut select = chat_message::Entity::find().filter(chat_message::Column::RoomId.eq(id));
+
+    if let Some(before) = before {
+        select = select.filter(chat_message::Column::MessageId.lt(before));
+    }
+
+    select
         .order_by_desc(chat_message::Column::MessageId)
         .limit(count as u64)
         .find_also_related(user::Entity)
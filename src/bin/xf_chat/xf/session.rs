@@ -13,6 +13,7 @@ struct XfSession {
     pub id: u32,
     pub username: String,
     pub avatar_date: u32,
+    pub register_date: u32,
     pub is_staff: bool,
 }
 
@@ -36,6 +37,7 @@ impl Default for XfSession {
             id: 0,
             username: dumpster::constants::GUEST_USERNAME.to_owned(),
             avatar_date: 0,
+            register_date: 0,
             is_staff: false,
         }
     }
@@ -121,6 +123,7 @@ pub async fn get_session_with_user_id(db: &DatabaseConnection, id: u32) -> imple
             .column_as(user::Column::UserId, "id")
             .column(user::Column::Username)
             .column(user::Column::AvatarDate)
+            .column(user::Column::RegisterDate)
             .column(user::Column::IsStaff)
             .filter(user::Column::UserId.eq(id))
             .filter(user::Column::UserState.eq("valid"))
@@ -171,5 +174,19 @@ pub async fn get_session_with_user_id(db: &DatabaseConnection, id: u32) -> imple
         avatar_url: avatar_uri(session.id, session.avatar_date),
         ignored_users,
         is_staff: session.is_staff,
+        joined_at: Some(session.register_date as u64).filter(|date| *date > 0),
+    }
+}
+
+pub async fn is_ignoring(db: &DatabaseConnection, user_id: u32, other_id: u32) -> bool {
+    match user_ignored::Entity::find_by_id((user_id, other_id))
+        .one(db)
+        .await
+    {
+        Ok(res) => res.is_some(),
+        Err(err) => {
+            log::warn!("MySQL Error: {:?}", err);
+            false
+        }
     }
 }
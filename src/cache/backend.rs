@@ -0,0 +1,46 @@
+//! The interface every cache backend provides.
+
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Cache operation errors.
+#[derive(Debug)]
+pub enum CacheError {
+    /// Redis could not be reached or refused the command
+    Redis(redis::RedisError),
+}
+
+impl std::fmt::Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheError::Redis(e) => write!(f, "redis error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+impl From<redis::RedisError> for CacheError {
+    fn from(e: redis::RedisError) -> Self {
+        CacheError::Redis(e)
+    }
+}
+
+/// A store of serialized values that expire.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    /// Values for several keys, in the same order; `None` for missing ones.
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<String>>, CacheError>;
+
+    /// Store a value until `ttl` has passed.
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> Result<(), CacheError>;
+
+    /// Drop one key.
+    async fn delete(&self, key: &str) -> Result<(), CacheError>;
+
+    /// Drop every key starting with `prefix`.
+    async fn clear_prefix(&self, prefix: &str) -> Result<(), CacheError>;
+
+    /// Whether other instances read and write the same entries.
+    fn is_shared(&self) -> bool;
+}
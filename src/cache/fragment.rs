@@ -0,0 +1,98 @@
+//! Rendered template fragments
+//!
+//! Parts of a page that take several queries to build are kept as HTML. The
+//! key holds the viewer's groups, which decide what they may see, and the
+//! build, so a deploy with changed templates starts afresh. Write paths that
+//! change what a fragment shows clear it through [`invalidate`] or
+//! [`posts_changed`].
+
+use super::CacheScope;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// A cached part of a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fragment {
+    /// Forums with their statistics, on the forum index
+    ForumList,
+    /// Members online now
+    OnlineUsers,
+    /// Newest posts, on the forum index
+    LatestPosts,
+    /// Posts with the most reactions in the past week
+    MostReacted,
+}
+
+impl Fragment {
+    pub fn all() -> [Fragment; 4] {
+        [
+            Fragment::ForumList,
+            Fragment::OnlineUsers,
+            Fragment::LatestPosts,
+            Fragment::MostReacted,
+        ]
+    }
+
+    pub(super) fn prefix(self) -> &'static str {
+        match self {
+            Fragment::ForumList => "dumpster:cache:fragment:forum_list:",
+            Fragment::OnlineUsers => "dumpster:cache:fragment:online_users:",
+            Fragment::LatestPosts => "dumpster:cache:fragment:latest_posts:",
+            Fragment::MostReacted => "dumpster:cache:fragment:most_reacted:",
+        }
+    }
+}
+
+/// Key part for viewers in these groups.
+pub fn groups_key(groups: &[i32]) -> String {
+    let mut groups = groups.to_vec();
+    groups.sort_unstable();
+    groups.dedup();
+    groups
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The cached fragment, or the one `render` builds, which is then cached.
+/// Failed renders are not cached.
+pub async fn get_or_render<F, Fut, E>(fragment: Fragment, key: &str, render: F) -> Result<String, E>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String, E>>,
+{
+    let scope = CacheScope::Fragment(fragment);
+    let key = format!("{}:{}", env!("CARGO_PKG_VERSION"), key);
+    if let Some(html) = super::get::<String>(scope, &key).await {
+        return Ok(html);
+    }
+
+    let html = render().await?;
+    super::set(scope, &key, &html).await;
+    Ok(html)
+}
+
+/// Drop every copy of a fragment, here and on other instances.
+pub async fn invalidate(fragment: Fragment) {
+    super::invalidate(CacheScope::Fragment(fragment)).await;
+}
+
+/// Posts were added, removed or moved between forums: forum statistics and
+/// the newest posts have changed.
+pub async fn posts_changed() {
+    super::invalidate(CacheScope::ForumTree).await;
+    invalidate(Fragment::ForumList).await;
+    invalidate(Fragment::LatestPosts).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups_key_ignores_order() {
+        assert_eq!(groups_key(&[3, 1, 2, 1]), "1-2-3");
+        assert_eq!(groups_key(&[]), "");
+    }
+}
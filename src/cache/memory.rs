@@ -0,0 +1,134 @@
+//! In-process cache, used when Redis is not configured.
+
+use super::backend::{CacheBackend, CacheError};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+
+pub struct MemoryCache {
+    entries: DashMap<String, (Instant, String)>,
+    /// Most entries held at once
+    max_entries: usize,
+}
+
+impl MemoryCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: DashMap::new(),
+            max_entries,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        let entry = self.entries.get(key)?;
+        if entry.0 > Instant::now() {
+            return Some(entry.1.clone());
+        }
+        drop(entry);
+        self.entries.remove(key);
+        None
+    }
+
+    /// Make room for one more entry: expired ones go first, then whichever
+    /// comes to hand.
+    fn make_room(&self) {
+        if self.entries.len() < self.max_entries {
+            return;
+        }
+
+        let now = Instant::now();
+        self.entries.retain(|_, (expires_at, _)| *expires_at > now);
+
+        while self.entries.len() >= self.max_entries {
+            let Some(key) = self.entries.iter().next().map(|entry| entry.key().clone()) else {
+                break;
+            };
+            self.entries.remove(&key);
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<String>>, CacheError> {
+        Ok(keys.iter().map(|key| self.get(key)).collect())
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> Result<(), CacheError> {
+        if self.max_entries == 0 {
+            return Ok(());
+        }
+        if !self.entries.contains_key(key) {
+            self.make_room();
+        }
+        self.entries
+            .insert(key.to_string(), (Instant::now() + ttl, value));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CacheError> {
+        self.entries.remove(key);
+        Ok(())
+    }
+
+    async fn clear_prefix(&self, prefix: &str) -> Result<(), CacheError> {
+        self.entries.retain(|key, _| !key.starts_with(prefix));
+        Ok(())
+    }
+
+    fn is_shared(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_set_get_and_expiry() {
+        let cache = MemoryCache::new(10);
+        cache
+            .set("a", "1".to_string(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        cache
+            .set("b", "2".to_string(), Duration::ZERO)
+            .await
+            .unwrap();
+
+        let values = cache
+            .get_many(&["a".to_string(), "b".to_string(), "c".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(values, vec![Some("1".to_string()), None, None]);
+    }
+
+    #[actix_rt::test]
+    async fn test_capacity() {
+        let cache = MemoryCache::new(2);
+        for key in ["a", "b", "c"] {
+            cache
+                .set(key, key.to_string(), Duration::from_secs(60))
+                .await
+                .unwrap();
+        }
+        assert_eq!(cache.entries.len(), 2);
+        assert!(cache.get("c").is_some());
+    }
+
+    #[actix_rt::test]
+    async fn test_clear_prefix() {
+        let cache = MemoryCache::new(10);
+        for key in ["x:1", "x:2", "y:1"] {
+            cache
+                .set(key, key.to_string(), Duration::from_secs(60))
+                .await
+                .unwrap();
+        }
+        cache.clear_prefix("x:").await.unwrap();
+        assert!(cache.get("x:1").is_none());
+        assert!(cache.get("x:2").is_none());
+        assert!(cache.get("y:1").is_some());
+    }
+}
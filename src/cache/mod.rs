@@ -0,0 +1,355 @@
+//! Cache for hot reads
+//!
+//! Rendered post HTML, the forum list, users' group memberships, notices and
+//! rendered page fragments ([`fragment`]) are kept in Redis when `REDIS_URL` is set, so
+//! every instance shares one copy, and in process memory otherwise. Admin
+//! changes call [`invalidate`], which also tells other instances to reload
+//! what they hold themselves: settings, word filters, rate limit policies and
+//! permission masks are always loaded per process. All but the permission
+//! masks are also reloaded when [`versions`] sees them change, which needs no
+//! Redis.
+
+pub mod backend;
+pub mod fragment;
+pub mod memory;
+pub mod redis_cache;
+pub mod versions;
+
+use crate::app_state::AppState;
+use crate::backplane::{get_backplane, CACHE_CHANNEL, RESUBSCRIBE_DELAY};
+use crate::config::Config;
+use backend::CacheBackend;
+use fragment::Fragment;
+use futures::StreamExt;
+use memory::MemoryCache;
+use once_cell::sync::OnceCell;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+static CACHE: OnceCell<Box<dyn CacheBackend>> = OnceCell::new();
+
+/// A kind of cached data, invalidated as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CacheScope {
+    /// BBCode rendered to HTML, by UGC revision
+    PostHtml,
+    /// Forums with their statistics, for the forum list
+    ForumTree,
+    /// Group memberships, by user
+    GroupIds,
+    /// Settings and feature flags, held by each process
+    Settings,
+    /// Forum and chat room permission masks, held by each process
+    Permissions,
+    /// Compiled word filters, held by each process
+    WordFilters,
+    /// Rate limit policies, held by each process
+    RateLimits,
+    /// Site and forum notices that haven't ended, with rendered bodies
+    Notices,
+    /// Rendered page fragments, by viewer groups
+    Fragment(Fragment),
+}
+
+impl CacheScope {
+    /// Every scope, for clearing the whole cache.
+    pub fn all() -> [CacheScope; 12] {
+        [
+            CacheScope::PostHtml,
+            CacheScope::ForumTree,
+            CacheScope::GroupIds,
+            CacheScope::Settings,
+            CacheScope::Permissions,
+            CacheScope::WordFilters,
+            CacheScope::RateLimits,
+            CacheScope::Notices,
+            CacheScope::Fragment(Fragment::ForumList),
+            CacheScope::Fragment(Fragment::OnlineUsers),
+            CacheScope::Fragment(Fragment::LatestPosts),
+            CacheScope::Fragment(Fragment::MostReacted),
+        ]
+    }
+
+    /// Key prefix of the scope's entries, if it has any in the cache.
+    fn prefix(self) -> Option<&'static str> {
+        match self {
+            CacheScope::PostHtml => Some("dumpster:cache:post_html:"),
+            CacheScope::ForumTree => Some("dumpster:cache:forum_tree:"),
+            CacheScope::GroupIds => Some("dumpster:cache:group_ids:"),
+            CacheScope::Notices => Some("dumpster:cache:notices:"),
+            CacheScope::Fragment(fragment) => Some(fragment.prefix()),
+            CacheScope::Settings
+            | CacheScope::Permissions
+            | CacheScope::WordFilters
+            | CacheScope::RateLimits => None,
+        }
+    }
+
+    fn ttl(self) -> Duration {
+        let config = crate::app_config::cache();
+        Duration::from_secs(match self {
+            CacheScope::PostHtml => config.post_html_ttl_seconds,
+            CacheScope::ForumTree => config.forum_tree_ttl_seconds,
+            CacheScope::GroupIds => config.group_ids_ttl_seconds,
+            CacheScope::Notices | CacheScope::Fragment(_) => config.fragment_ttl_seconds,
+            CacheScope::Settings
+            | CacheScope::Permissions
+            | CacheScope::WordFilters
+            | CacheScope::RateLimits => 0,
+        })
+    }
+
+    fn key(self, key: &str) -> Option<String> {
+        self.prefix().map(|prefix| format!("{}{}", prefix, key))
+    }
+}
+
+/// Pick the backend. Called once at startup; without a Redis URL, or if
+/// Redis can't be reached, entries stay in process memory.
+pub async fn init(redis_url: Option<&str>) {
+    let backend: Box<dyn CacheBackend> = match redis_url {
+        Some(url) => match redis_cache::RedisCache::connect(url).await {
+            Ok(cache) => {
+                log::info!("Cache stored in Redis.");
+                Box::new(cache)
+            }
+            Err(err) => {
+                log::error!(
+                    "Failed to connect the cache to Redis, using memory: {}",
+                    err
+                );
+                Box::new(memory_cache())
+            }
+        },
+        None => Box::new(memory_cache()),
+    };
+
+    if CACHE.set(backend).is_err() {
+        log::warn!("Cache already initialized.");
+    }
+}
+
+fn memory_cache() -> MemoryCache {
+    MemoryCache::new(crate::app_config::cache().memory_max_entries)
+}
+
+fn backend() -> &'static dyn CacheBackend {
+    CACHE.get_or_init(|| Box::new(memory_cache())).as_ref()
+}
+
+/// A cached value, or `None` on a miss or cache failure.
+pub async fn get<T: DeserializeOwned>(scope: CacheScope, key: &str) -> Option<T> {
+    get_many(scope, &[key.to_string()]).await.pop().flatten()
+}
+
+/// Cached values for several keys, in the same order.
+pub async fn get_many<T: DeserializeOwned>(scope: CacheScope, keys: &[String]) -> Vec<Option<T>> {
+    let full_keys: Option<Vec<String>> = keys.iter().map(|key| scope.key(key)).collect();
+    let Some(full_keys) = full_keys else {
+        return keys.iter().map(|_| None).collect();
+    };
+
+    match backend().get_many(&full_keys).await {
+        Ok(values) => values
+            .into_iter()
+            .map(|value| value.and_then(|value| serde_json::from_str(&value).ok()))
+            .collect(),
+        Err(err) => {
+            log::warn!("Failed to read from cache: {}", err);
+            keys.iter().map(|_| None).collect()
+        }
+    }
+}
+
+/// Cache a value for the scope's configured lifetime.
+pub async fn set<T: Serialize>(scope: CacheScope, key: &str, value: &T) {
+    let Some(full_key) = scope.key(key) else {
+        return;
+    };
+    let ttl = scope.ttl();
+    if ttl.is_zero() {
+        return;
+    }
+
+    let value = match serde_json::to_string(value) {
+        Ok(value) => value,
+        Err(err) => {
+            log::error!("Failed to serialize cache entry {}: {}", full_key, err);
+            return;
+        }
+    };
+
+    if let Err(err) = backend().set(&full_key, value, ttl).await {
+        log::warn!("Failed to write to cache: {}", err);
+    }
+}
+
+/// Drop one cached value, here and on other instances.
+pub async fn remove(scope: CacheScope, key: &str) {
+    let Some(full_key) = scope.key(key) else {
+        return;
+    };
+
+    if let Err(err) = backend().delete(&full_key).await {
+        log::warn!("Failed to delete from cache: {}", err);
+    }
+    if !backend().is_shared() {
+        if let Some(backplane) = get_backplane() {
+            backplane.publish(CACHE_CHANNEL, &Invalidation::Key(full_key));
+        }
+    }
+}
+
+/// Drop everything in a scope, here and on other instances. Settings, word
+/// filters and permissions are reloaded by the other instances; this one has already
+/// done so in the admin handler that made the change.
+pub async fn invalidate(scope: CacheScope) {
+    let mut scopes = vec![scope];
+    // Fragments are keyed by groups, so what they show depends on permissions.
+    if scope == CacheScope::Permissions {
+        scopes.extend(Fragment::all().map(CacheScope::Fragment));
+    }
+
+    for scope in scopes {
+        clear_local(scope).await;
+        if let Some(backplane) = get_backplane() {
+            backplane.publish(CACHE_CHANNEL, &Invalidation::Scope(scope));
+        }
+    }
+}
+
+async fn clear_local(scope: CacheScope) {
+    if let Some(prefix) = scope.prefix() {
+        if let Err(err) = backend().clear_prefix(prefix).await {
+            log::warn!("Failed to clear cache {:?}: {}", scope, err);
+        }
+    }
+}
+
+/// What other instances are told to drop.
+#[derive(Debug, Serialize, Deserialize)]
+enum Invalidation {
+    Scope(CacheScope),
+    Key(String),
+}
+
+/// Apply invalidations published by other instances. Does nothing without
+/// the backplane.
+pub fn spawn_listener(config: Arc<Config>, state: AppState) {
+    let Some(backplane) = get_backplane() else {
+        return;
+    };
+
+    actix_web::rt::spawn(async move {
+        loop {
+            let mut events = Box::pin(backplane.subscribe::<Invalidation>(CACHE_CHANNEL));
+            while let Some(event) = events.next().await {
+                apply(&config, &state, event).await;
+            }
+            actix_web::rt::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    });
+}
+
+async fn apply(config: &Config, state: &AppState, event: Invalidation) {
+    match event {
+        Invalidation::Key(key) => {
+            if !backend().is_shared() {
+                backend().delete(&key).await.ok();
+            }
+        }
+        Invalidation::Scope(
+            scope @ (CacheScope::Settings
+            | CacheScope::Permissions
+            | CacheScope::WordFilters
+            | CacheScope::RateLimits),
+        ) => reload(config, state, scope).await,
+        // A shared backend was already cleared by the publishing instance.
+        Invalidation::Scope(scope) => {
+            if !backend().is_shared() {
+                clear_local(scope).await;
+            }
+        }
+    }
+}
+
+/// Load again what this process holds itself for a scope.
+async fn reload(config: &Config, state: &AppState, scope: CacheScope) {
+    let db = crate::db::get_db_pool();
+    match scope {
+        CacheScope::Settings => {
+            if let Err(err) = config.load_from_database(db).await {
+                log::error!("Failed to reload settings: {}", err);
+            }
+            state.permissions.set_priority_wins(
+                config.get_bool_or(crate::permission::PRIORITY_WINS_SETTING, false),
+            );
+        }
+        CacheScope::RateLimits => {
+            if let Err(err) = crate::rate_limit::reload_rate_limits(db).await {
+                log::error!("Failed to reload rate limit policies: {}", err);
+            }
+        }
+        CacheScope::WordFilters => {
+            if let Err(err) = crate::word_filter::reload_filters(db).await {
+                log::error!("Failed to reload word filters: {}", err);
+            }
+        }
+        CacheScope::Permissions => {
+            if let Err(err) = state.permissions.reload_forum_permissions().await {
+                log::error!("Failed to reload forum permissions: {}", err);
+            }
+            if let Err(err) = state.permissions.reload_chat_room_permissions().await {
+                log::error!("Failed to reload chat room permissions: {}", err);
+            }
+            if let Err(err) = state.permissions.reload_thread_permissions().await {
+                log::error!("Failed to reload thread permissions: {}", err);
+            }
+            if let Err(err) = state.permissions.reload_group_priorities().await {
+                log::error!("Failed to reload group priorities: {}", err);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_scopes_have_no_keys() {
+        assert!(CacheScope::Settings.key("x").is_none());
+        assert!(CacheScope::Permissions.key("x").is_none());
+        assert!(CacheScope::WordFilters.key("x").is_none());
+        assert!(CacheScope::RateLimits.key("x").is_none());
+        assert_eq!(
+            CacheScope::GroupIds.key("7").as_deref(),
+            Some("dumpster:cache:group_ids:7")
+        );
+        assert_eq!(
+            CacheScope::Notices.key("all").as_deref(),
+            Some("dumpster:cache:notices:all")
+        );
+    }
+
+    #[test]
+    fn test_fragment_keys() {
+        assert_eq!(
+            CacheScope::Fragment(Fragment::OnlineUsers)
+                .key("all")
+                .as_deref(),
+            Some("dumpster:cache:fragment:online_users:all")
+        );
+    }
+
+    #[test]
+    fn test_invalidation_round_trip() {
+        let json = serde_json::to_string(&Invalidation::Scope(CacheScope::ForumTree)).unwrap();
+        assert!(matches!(
+            serde_json::from_str::<Invalidation>(&json).unwrap(),
+            Invalidation::Scope(CacheScope::ForumTree)
+        ));
+    }
+}
@@ -0,0 +1,91 @@
+//! Redis cache, shared by every instance pointed at the same server.
+
+use super::backend::{CacheBackend, CacheError};
+use async_trait::async_trait;
+use redis::aio::MultiplexedConnection;
+use std::time::Duration;
+
+/// Keys fetched per SCAN while clearing a prefix
+const SCAN_COUNT: usize = 500;
+
+pub struct RedisCache {
+    connection: MultiplexedConnection,
+}
+
+impl RedisCache {
+    pub async fn connect(url: &str) -> Result<Self, CacheError> {
+        let client = redis::Client::open(url)?;
+        let connection = client.get_multiplexed_tokio_connection().await?;
+        Ok(Self { connection })
+    }
+}
+
+#[async_trait]
+impl CacheBackend for RedisCache {
+    async fn get_many(&self, keys: &[String]) -> Result<Vec<Option<String>>, CacheError> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut connection = self.connection.clone();
+        Ok(redis::cmd("MGET")
+            .arg(keys)
+            .query_async(&mut connection)
+            .await?)
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> Result<(), CacheError> {
+        let mut connection = self.connection.clone();
+        redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("EX")
+            .arg(ttl.as_secs().max(1))
+            .query_async::<_, ()>(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), CacheError> {
+        let mut connection = self.connection.clone();
+        redis::cmd("DEL")
+            .arg(key)
+            .query_async::<_, ()>(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    async fn clear_prefix(&self, prefix: &str) -> Result<(), CacheError> {
+        let mut connection = self.connection.clone();
+        let pattern = format!("{}*", prefix);
+        let mut cursor: u64 = 0;
+
+        // SCAN rather than KEYS, so a large cache doesn't block the server.
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(&mut connection)
+                .await?;
+
+            if !keys.is_empty() {
+                redis::cmd("UNLINK")
+                    .arg(&keys)
+                    .query_async::<_, ()>(&mut connection)
+                    .await?;
+            }
+
+            if next == 0 {
+                return Ok(());
+            }
+            cursor = next;
+        }
+    }
+
+    fn is_shared(&self) -> bool {
+        true
+    }
+}
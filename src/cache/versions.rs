@@ -0,0 +1,107 @@
+//! Reloading settings, word filters and rate limits changed elsewhere
+//!
+//! Triggers count every change to settings, feature flags, word filters and
+//! rate limit policies in
+//! `config_versions`. Each instance reads the counts every
+//! `config_poll_seconds` and reloads whatever moved since it last looked, so
+//! changes reach every instance without Redis, including ones made by the CLI
+//! or straight in the database.
+
+use super::CacheScope;
+use crate::app_state::AppState;
+use crate::config::Config;
+use crate::db::get_db_pool;
+use sea_orm::{DbBackend, DbErr, FromQueryResult, Statement};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, FromQueryResult)]
+struct ConfigVersion {
+    scope: String,
+    version: i64,
+}
+
+/// Scope reloaded when a `config_versions` row moves.
+fn scope_for(name: &str) -> Option<CacheScope> {
+    match name {
+        "settings" => Some(CacheScope::Settings),
+        "word_filters" => Some(CacheScope::WordFilters),
+        "rate_limits" => Some(CacheScope::RateLimits),
+        _ => None,
+    }
+}
+
+/// Scopes whose count changed from the one in `seen`, the counts a poller
+/// has acted on by scope name. The first sighting of a scope only records
+/// it, as startup has just loaded it.
+fn changed(seen: &mut HashMap<String, i64>, rows: Vec<ConfigVersion>) -> Vec<CacheScope> {
+    rows.into_iter()
+        .filter(|row| {
+            let previous = seen.insert(row.scope.to_owned(), row.version);
+            matches!(previous, Some(previous) if previous != row.version)
+        })
+        .filter_map(|row| scope_for(&row.scope))
+        .collect()
+}
+
+async fn poll(
+    config: &Config,
+    state: &AppState,
+    seen: &mut HashMap<String, i64>,
+) -> Result<(), DbErr> {
+    let rows = ConfigVersion::find_by_statement(Statement::from_string(
+        DbBackend::Postgres,
+        "SELECT scope, version FROM config_versions".to_owned(),
+    ))
+    .all(get_db_pool())
+    .await?;
+
+    for scope in changed(seen, rows) {
+        log::info!("{:?} changed elsewhere; reloading.", scope);
+        super::reload(config, state, scope).await;
+    }
+    Ok(())
+}
+
+/// Start checking for changes. Does nothing when `config_poll_seconds` is 0.
+pub fn spawn_poller(config: Arc<Config>, state: AppState) {
+    let seconds = crate::app_config::cache().config_poll_seconds;
+    if seconds == 0 {
+        return;
+    }
+
+    actix_web::rt::spawn(async move {
+        let mut seen = HashMap::new();
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(seconds));
+        loop {
+            interval.tick().await;
+            if let Err(err) = poll(&config, &state, &mut seen).await {
+                log::error!("Failed to check for settings changes: {}", err);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(scope: &str, version: i64) -> ConfigVersion {
+        ConfigVersion {
+            scope: scope.to_owned(),
+            version,
+        }
+    }
+
+    #[test]
+    fn test_changed_after_first_sighting() {
+        let mut seen = HashMap::new();
+        assert!(changed(&mut seen, vec![row("word_filters", 3), row("unknown", 1)]).is_empty());
+        assert!(changed(&mut seen, vec![row("word_filters", 3)]).is_empty());
+        assert_eq!(
+            changed(&mut seen, vec![row("word_filters", 4), row("unknown", 2)]),
+            vec![CacheScope::WordFilters]
+        );
+    }
+}
@@ -388,6 +388,13 @@ impl Config {
         self.get_int_or("thumbnail_max_size", 150)
     }
 
+    // Upload settings
+
+    /// Check if uploaded images are rotated to their EXIF orientation before metadata is stripped
+    pub fn preserve_image_orientation(&self) -> bool {
+        self.get_bool_or("preserve_image_orientation", true)
+    }
+
     // Chat settings
 
     /// Get maximum chat message length in bytes (0 for unlimited)
@@ -400,9 +407,34 @@ impl Config {
         self.get_int_or("chat_history_limit", 40) as usize
     }
 
-    /// Get minimum seconds between chat messages per user (0 to disable)
-    pub fn chat_rate_limit_seconds(&self) -> u64 {
-        self.get_int_or("chat_rate_limit_seconds", 0) as u64
+    /// Get how many chat messages a user can send per 10 seconds (0 to disable)
+    pub fn chat_user_messages_per_10s(&self) -> u32 {
+        self.get_int_or("chat_user_messages_per_10s", 5) as u32
+    }
+
+    /// Get how many chat messages a room takes per 10 seconds (0 to disable)
+    pub fn chat_room_messages_per_10s(&self) -> u32 {
+        self.get_int_or("chat_room_messages_per_10s", 30) as u32
+    }
+
+    /// Get how long a user can't repeat a chat message, in seconds (0 to disable)
+    pub fn chat_duplicate_window_seconds(&self) -> u64 {
+        self.get_int_or("chat_duplicate_window_seconds", 30) as u64
+    }
+
+    /// Get how many hours an account counts as new for chat link limits
+    pub fn chat_new_user_hours(&self) -> u64 {
+        self.get_int_or("chat_new_user_hours", 24) as u64
+    }
+
+    /// Get how many links new users can put in one chat message
+    pub fn chat_new_user_max_links(&self) -> usize {
+        self.get_int_or("chat_new_user_max_links", 0) as usize
+    }
+
+    /// Get how long authors can edit their chat messages, in seconds (0 for no limit)
+    pub fn chat_edit_window_seconds(&self) -> u64 {
+        self.get_int_or("chat_edit_window_seconds", 300) as u64
     }
 
     /// Get default chat room ID to auto-join (0 = none)
@@ -431,6 +463,61 @@ impl Config {
             )
         }
     }
+
+    /// Get the BBCode tags that render in chat messages
+    /// Returns None if all tags are allowed ("*"), or Some(Vec) of allowed tag names
+    pub fn chat_allowed_bbcode(&self) -> Option<Vec<String>> {
+        let value = self.get_string_or(
+            "chat_allowed_bbcode",
+            "b,i,u,s,color,url,img,thumb,spoiler,code",
+        );
+        if value.trim() == "*" {
+            None // All tags allowed
+        } else {
+            Some(
+                value
+                    .split(',')
+                    .map(|s| s.trim().to_lowercase())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+            )
+        }
+    }
+
+    /// Whether users can share files in chat
+    pub fn chat_uploads_enabled(&self) -> bool {
+        self.get_bool_or("chat_uploads_enabled", true)
+    }
+
+    /// Get the MIME types that can be shared in chat, such as `image/*` or `application/pdf`
+    pub fn chat_upload_mime_types(&self) -> Vec<String> {
+        self.get_string_or("chat_upload_mime_types", "image/*,video/*,audio/*")
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    // API settings
+
+    /// Get the origins allowed to call the API from a browser ("*" for any)
+    pub fn api_cors_allowed_origins(&self) -> Vec<String> {
+        self.get_string_or("api_cors_allowed_origins", "")
+            .split(',')
+            .map(|s| s.trim().trim_end_matches('/').to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// Whether allowed origins may send cookies with API requests
+    pub fn api_cors_allow_credentials(&self) -> bool {
+        self.get_bool_or("api_cors_allow_credentials", false)
+    }
+
+    /// Get how long browsers may cache an API preflight response, in seconds
+    pub fn api_cors_max_age_seconds(&self) -> u64 {
+        self.get_int_or("api_cors_max_age_seconds", 3600).max(0) as u64
+    }
 }
 
 /// Create a new Arc-wrapped Config
@@ -1,16 +1,52 @@
 //! Conversation management for private messaging
 
+pub mod realtime;
+
 use crate::db::get_db_pool;
-use crate::orm::{conversation_participants, conversations, private_messages, ugc, ugc_revisions};
+use crate::orm::conversation_participants::ParticipantRole;
+use crate::orm::private_messages::MessageKind;
+use crate::orm::{
+    conversation_participants, conversations, private_messages, ugc, ugc_revisions, user_names,
+};
+use crate::pagination::PageStart;
 use crate::ugc::{create_ugc, NewUgcPartial};
-use sea_orm::{entity::*, query::*, sea_query::Expr, ActiveValue::Set, DatabaseConnection, DbErr};
+use sea_orm::{
+    entity::*, query::*, sea_query::Expr, ActiveValue::Set, ConnectionTrait, DatabaseConnection,
+    DbBackend, DbErr, FromQueryResult, Statement,
+};
+use std::collections::HashMap;
+
+/// Maximum number of people in one conversation, including the owner
+pub fn max_participants() -> usize {
+    crate::app_config::limits().max_conversation_participants as usize
+}
 
-/// Create a new conversation with participants
+fn participant_limit_error() -> DbErr {
+    DbErr::Custom(format!(
+        "Conversations are limited to {} participants",
+        max_participants()
+    ))
+}
+
+/// Create a new conversation with participants. The creator becomes its owner.
+#[tracing::instrument(skip(title))]
 pub async fn create_conversation(
     creator_id: i32,
     participant_ids: &[i32],
     title: Option<&str>,
 ) -> Result<i32, DbErr> {
+    let mut others: Vec<i32> = participant_ids
+        .iter()
+        .copied()
+        .filter(|&id| id != creator_id)
+        .collect();
+    others.sort_unstable();
+    others.dedup();
+
+    if others.len() + 1 > max_participants() {
+        return Err(participant_limit_error());
+    }
+
     let db = get_db_pool();
     let txn = db.begin().await?;
 
@@ -22,25 +58,23 @@ pub async fn create_conversation(
     };
     let conversation_model = conversation.insert(&txn).await?;
 
-    // Add creator as participant (with last_read_at set so they don't see their own conversation as unread)
+    // Add creator as owner
     let creator_participant = conversation_participants::ActiveModel {
         conversation_id: Set(conversation_model.id),
         user_id: Set(creator_id),
-        last_read_at: Set(Some(conversation_model.updated_at)),
+        role: Set(ParticipantRole::Owner),
         ..Default::default()
     };
     creator_participant.insert(&txn).await?;
 
     // Add other participants
-    for &participant_id in participant_ids {
-        if participant_id != creator_id {
-            let participant = conversation_participants::ActiveModel {
-                conversation_id: Set(conversation_model.id),
-                user_id: Set(participant_id),
-                ..Default::default()
-            };
-            participant.insert(&txn).await?;
-        }
+    for participant_id in others {
+        let participant = conversation_participants::ActiveModel {
+            conversation_id: Set(conversation_model.id),
+            user_id: Set(participant_id),
+            ..Default::default()
+        };
+        participant.insert(&txn).await?;
     }
 
     txn.commit().await?;
@@ -49,6 +83,7 @@ pub async fn create_conversation(
 }
 
 /// Send a message in a conversation
+#[tracing::instrument(skip(content))]
 pub async fn send_message(
     conversation_id: i32,
     sender_id: i32,
@@ -92,11 +127,11 @@ pub async fn send_message(
         .exec(&txn)
         .await?;
 
-    // Update sender's last_read_at so they don't see their own message as unread
+    // The sender has read everything up to their own message
     conversation_participants::Entity::update_many()
         .col_expr(
-            conversation_participants::Column::LastReadAt,
-            Expr::value(ugc_revision.created_at),
+            conversation_participants::Column::LastReadMessageId,
+            Expr::value(message_model.id),
         )
         .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
         .filter(conversation_participants::Column::UserId.eq(sender_id))
@@ -128,51 +163,81 @@ where
     Ok(())
 }
 
-/// Mark a conversation as read for a user
+/// Mark a conversation as read for a user, up to its latest message
+#[tracing::instrument]
 pub async fn mark_conversation_read(user_id: i32, conversation_id: i32) -> Result<(), DbErr> {
     let db = get_db_pool();
 
-    conversation_participants::Entity::update_many()
-        .col_expr(
-            conversation_participants::Column::LastReadAt,
-            Expr::value(chrono::Utc::now().naive_utc()),
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        UPDATE conversation_participants
+        SET last_read_message_id = GREATEST(
+            COALESCE(last_read_message_id, 0),
+            (SELECT COALESCE(MAX(id), 0) FROM private_messages WHERE conversation_id = $1)
         )
-        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
-        .filter(conversation_participants::Column::UserId.eq(user_id))
-        .exec(db)
-        .await?;
+        WHERE conversation_id = $1 AND user_id = $2
+        "#,
+        vec![conversation_id.into(), user_id.into()],
+    ))
+    .await?;
 
     Ok(())
 }
 
-/// Count unread conversations for a user
-pub async fn count_unread_conversations(user_id: i32) -> Result<i64, DbErr> {
-    let db = get_db_pool();
+/// Unread message counts for a user, keyed by conversation. Only conversations
+/// with unread messages are included. Messages the user sent, system messages
+/// and deleted messages never count as unread.
+#[tracing::instrument(skip(db))]
+async fn get_unread_counts<C>(
+    db: &C,
+    user_id: i32,
+    archived: bool,
+) -> Result<HashMap<i32, i64>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    #[derive(FromQueryResult)]
+    struct UnreadCount {
+        conversation_id: i32,
+        unread: i64,
+    }
 
-    // Get all non-archived conversation participants with their conversations
-    let participants = conversation_participants::Entity::find()
-        .filter(conversation_participants::Column::UserId.eq(user_id))
-        .filter(conversation_participants::Column::IsArchived.eq(false))
-        .find_also_related(conversations::Entity)
-        .all(db)
-        .await?;
+    let counts = UnreadCount::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT cp.conversation_id, COUNT(*) AS unread
+        FROM conversation_participants cp
+        JOIN private_messages pm ON pm.conversation_id = cp.conversation_id
+        WHERE cp.user_id = $1
+          AND cp.is_archived = $2
+          AND pm.id > COALESCE(cp.last_read_message_id, 0)
+          AND pm.kind = 'message'
+          AND pm.user_id IS DISTINCT FROM $1
+          AND NOT EXISTS (SELECT 1 FROM ugc_deletions d WHERE d.id = pm.ugc_id)
+        GROUP BY cp.conversation_id
+        "#,
+        vec![user_id.into(), archived.into()],
+    ))
+    .all(db)
+    .await?;
+
+    Ok(counts
+        .into_iter()
+        .map(|count| (count.conversation_id, count.unread))
+        .collect())
+}
 
-    // Count conversations where updated_at is after last_read_at
-    let mut count = 0i64;
-    for (participant, conversation) in participants {
-        if let Some(conv) = conversation {
-            let is_unread = if let Some(last_read) = participant.last_read_at {
-                conv.updated_at > last_read
-            } else {
-                true // Never read
-            };
-            if is_unread {
-                count += 1;
-            }
-        }
-    }
+/// Count unread conversations for a user
+pub async fn count_unread_conversations(user_id: i32) -> Result<i64, DbErr> {
+    let counts = get_unread_counts(get_db_pool(), user_id, false).await?;
+    Ok(counts.len() as i64)
+}
 
-    Ok(count)
+/// Count unread messages across a user's conversations
+pub async fn count_unread_messages(user_id: i32) -> Result<i64, DbErr> {
+    let counts = get_unread_counts(get_db_pool(), user_id, false).await?;
+    Ok(counts.values().sum())
 }
 
 /// Get list of conversations for a user with preview data
@@ -180,54 +245,7 @@ pub async fn get_user_conversations(
     user_id: i32,
     limit: u64,
 ) -> Result<Vec<ConversationPreview>, DbErr> {
-    let db = get_db_pool();
-
-    // Get conversations where user is participant
-    let participants = conversation_participants::Entity::find()
-        .filter(conversation_participants::Column::UserId.eq(user_id))
-        .filter(conversation_participants::Column::IsArchived.eq(false))
-        .find_also_related(conversations::Entity)
-        .order_by_desc(conversations::Column::UpdatedAt)
-        .limit(limit)
-        .all(db)
-        .await?;
-
-    let mut previews = Vec::new();
-
-    for (participant, conversation) in participants {
-        if let Some(conv) = conversation {
-            // Get other participants
-            let other_participants =
-                get_conversation_participants(db, conv.id, Some(user_id)).await?;
-
-            // Get last message
-            let last_message = get_last_message(db, conv.id).await?;
-
-            // Check if unread
-            let is_unread = if let Some(last_read) = participant.last_read_at {
-                conv.updated_at > last_read
-            } else {
-                true
-            };
-
-            // Extract content and timestamp from last_message
-            let (last_content, last_timestamp) = match last_message {
-                Some((content, timestamp)) => (Some(content), Some(timestamp)),
-                None => (None, None),
-            };
-
-            previews.push(ConversationPreview {
-                id: conv.id,
-                title: conv.title,
-                participants: other_participants,
-                last_message_content: last_content,
-                last_message_at: last_timestamp,
-                is_unread,
-            });
-        }
-    }
-
-    Ok(previews)
+    get_conversation_previews(user_id, false, limit).await
 }
 
 /// Conversation preview data for inbox listing
@@ -239,170 +257,235 @@ pub struct ConversationPreview {
     pub last_message_content: Option<String>,
     pub last_message_at: Option<chrono::NaiveDateTime>,
     pub is_unread: bool,
+    pub unread_count: i64,
+    pub is_muted: bool,
 }
 
-/// Get participant names for a conversation (excluding optional user_id)
-async fn get_conversation_participants(
-    db: &DatabaseConnection,
-    conversation_id: i32,
-    exclude_user_id: Option<i32>,
-) -> Result<Vec<String>, DbErr> {
-    use crate::user::Profile;
-
-    // Get participant user IDs
-    let mut query = conversation_participants::Entity::find()
-        .filter(conversation_participants::Column::ConversationId.eq(conversation_id));
-
-    if let Some(exclude_id) = exclude_user_id {
-        query = query.filter(conversation_participants::Column::UserId.ne(exclude_id));
+/// Build inbox previews with a fixed number of queries: one for the
+/// conversations and their last messages, one for participant names and one
+/// for unread counts.
+#[tracing::instrument]
+async fn get_conversation_previews(
+    user_id: i32,
+    archived: bool,
+    limit: u64,
+) -> Result<Vec<ConversationPreview>, DbErr> {
+    #[derive(FromQueryResult)]
+    struct PreviewRow {
+        id: i32,
+        title: Option<String>,
+        last_message_content: Option<String>,
+        last_message_at: Option<chrono::NaiveDateTime>,
+        is_muted: bool,
     }
 
-    let participants = query.all(db).await?;
-
-    // Fetch names for each participant
-    let mut names = Vec::new();
-    for participant in participants {
-        if let Some(profile) = Profile::get_by_id(db, participant.user_id).await? {
-            names.push(profile.name);
-        }
-    }
+    let db = get_db_pool();
 
-    Ok(names)
+    let rows = PreviewRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT c.id, c.title,
+               lm.content AS last_message_content,
+               lm.created_at AS last_message_at,
+               cp.is_muted
+        FROM conversation_participants cp
+        JOIN conversations c ON c.id = cp.conversation_id
+        LEFT JOIN LATERAL (
+            SELECT ur.content, ur.created_at
+            FROM private_messages pm
+            JOIN ugc u ON u.id = pm.ugc_id
+            JOIN ugc_revisions ur ON ur.id = u.ugc_revision_id
+            WHERE pm.conversation_id = c.id
+            ORDER BY pm.created_at DESC, pm.id DESC
+            LIMIT 1
+        ) lm ON TRUE
+        WHERE cp.user_id = $1 AND cp.is_archived = $2
+        ORDER BY c.updated_at DESC
+        LIMIT $3
+        "#,
+        vec![user_id.into(), archived.into(), (limit as i64).into()],
+    ))
+    .all(db)
+    .await?;
+
+    let mut participants =
+        get_other_participant_names(db, rows.iter().map(|row| row.id), user_id).await?;
+    let unread_counts = get_unread_counts(db, user_id, archived).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let unread_count = unread_counts.get(&row.id).copied().unwrap_or(0);
+            ConversationPreview {
+                id: row.id,
+                title: row.title,
+                participants: participants.remove(&row.id).unwrap_or_default(),
+                last_message_content: row.last_message_content,
+                last_message_at: row.last_message_at,
+                is_unread: unread_count > 0,
+                unread_count,
+                is_muted: row.is_muted,
+            }
+        })
+        .collect())
 }
 
-/// Get the last message in a conversation
-async fn get_last_message(
+/// Get participant names for several conversations at once, excluding `user_id`
+#[tracing::instrument(skip_all)]
+async fn get_other_participant_names(
     db: &DatabaseConnection,
-    conversation_id: i32,
-) -> Result<Option<(String, chrono::NaiveDateTime)>, DbErr> {
-    // Get the last message
-    let message = private_messages::Entity::find()
-        .filter(private_messages::Column::ConversationId.eq(conversation_id))
-        .order_by_desc(private_messages::Column::CreatedAt)
-        .one(db)
+    conversation_ids: impl Iterator<Item = i32>,
+    user_id: i32,
+) -> Result<HashMap<i32, Vec<String>>, DbErr> {
+    let conversation_ids: Vec<i32> = conversation_ids.collect();
+    if conversation_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let participants = conversation_participants::Entity::find()
+        .filter(conversation_participants::Column::ConversationId.is_in(conversation_ids))
+        .filter(conversation_participants::Column::UserId.ne(user_id))
+        .order_by_asc(conversation_participants::Column::JoinedAt)
+        .order_by_asc(conversation_participants::Column::UserId)
+        .all(db)
         .await?;
 
-    if let Some(msg) = message {
-        // Get the UGC content
-        let ugc_model = ugc::Entity::find_by_id(msg.ugc_id).one(db).await?;
-        if let Some(ugc) = ugc_model {
-            if let Some(revision_id) = ugc.ugc_revision_id {
-                let revision = ugc_revisions::Entity::find_by_id(revision_id)
-                    .one(db)
-                    .await?;
-                if let Some(rev) = revision {
-                    return Ok(Some((rev.content, rev.created_at)));
-                }
-            }
+    let names: HashMap<i32, String> = user_names::Entity::find()
+        .filter(user_names::Column::UserId.is_in(participants.iter().map(|p| p.user_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|user_name| (user_name.user_id, user_name.name))
+        .collect();
+
+    let mut by_conversation: HashMap<i32, Vec<String>> = HashMap::new();
+    for participant in participants {
+        if let Some(name) = names.get(&participant.user_id) {
+            by_conversation
+                .entry(participant.conversation_id)
+                .or_default()
+                .push(name.clone());
         }
     }
 
-    Ok(None)
+    Ok(by_conversation)
 }
 
-/// Get messages for a conversation
+/// Get messages for a conversation, oldest first. A cursor start is the
+/// `(created_at, id)` of the last message already shown.
+#[tracing::instrument]
 pub async fn get_conversation_messages(
     conversation_id: i32,
     limit: u64,
-    offset: u64,
+    start: PageStart<(chrono::NaiveDateTime, i32)>,
 ) -> Result<Vec<MessageDisplay>, DbErr> {
-    use crate::orm::ugc_deletions;
-    use crate::user::Profile;
-
-    let db = get_db_pool();
+    #[derive(FromQueryResult)]
+    struct MessageRow {
+        id: i32,
+        ugc_id: i32,
+        user_id: Option<i32>,
+        kind: MessageKind,
+        content: String,
+        created_at: chrono::NaiveDateTime,
+        is_deleted: bool,
+        author_name: Option<String>,
+        author_email: Option<String>,
+        avatar_source: Option<crate::orm::users::AvatarSource>,
+        avatar_filename: Option<String>,
+        avatar_width: Option<i32>,
+        avatar_height: Option<i32>,
+        avatar_renditions: Option<String>,
+        user_created_at: Option<chrono::NaiveDateTime>,
+        post_count: Option<i64>,
+        reputation_score: Option<i32>,
+        custom_title: Option<String>,
+        signature: Option<String>,
+    }
 
-    // Get all messages for the conversation
-    let messages = private_messages::Entity::find()
-        .filter(private_messages::Column::ConversationId.eq(conversation_id))
-        .order_by_asc(private_messages::Column::CreatedAt)
-        .limit(limit)
-        .offset(offset)
-        .all(db)
-        .await?;
+    let sql = format!(
+        r#"
+        SELECT
+            pm.id, pm.ugc_id, pm.user_id, pm.kind, pm.created_at,
+            ur.content,
+            (d.id IS NOT NULL) AS is_deleted,
+            un.name AS author_name,
+            u.email AS author_email,
+            u.avatar_source,
+            a.filename AS avatar_filename,
+            a.file_width AS avatar_width,
+            a.file_height AS avatar_height,
+            {} AS avatar_renditions,
+            u.created_at AS user_created_at,
+            CASE WHEN u.id IS NULL THEN NULL
+                 ELSE (SELECT COUNT(*) FROM posts p WHERE p.user_id = u.id)
+            END AS post_count,
+            u.reputation_score,
+            u.custom_title,
+            u.signature
+        FROM private_messages pm
+        JOIN ugc g ON g.id = pm.ugc_id
+        JOIN ugc_revisions ur ON ur.id = g.ugc_revision_id
+        LEFT JOIN ugc_deletions d ON d.id = pm.ugc_id
+        LEFT JOIN users u ON u.id = pm.user_id
+        LEFT JOIN user_names un ON un.user_id = u.id
+        LEFT JOIN user_avatars ua ON ua.user_id = u.id
+        LEFT JOIN attachments a ON a.id = ua.attachment_id
+        WHERE pm.conversation_id = $1 {}
+        ORDER BY pm.created_at ASC, pm.id ASC
+        LIMIT $2 {}
+        "#,
+        crate::attachment::avatar_renditions_sql("a"),
+        match start {
+            PageStart::After(_) => "AND (pm.created_at, pm.id) > ($3, $4)",
+            PageStart::Offset(_) => "",
+        },
+        match start {
+            PageStart::After(_) => "",
+            PageStart::Offset(_) => "OFFSET $3",
+        },
+    );
 
-    let mut displays = Vec::new();
-
-    for msg in messages {
-        // Check if message is deleted
-        let deletion = ugc_deletions::Entity::find_by_id(msg.ugc_id)
-            .one(db)
-            .await?;
-        let is_deleted = deletion.is_some();
-
-        // Get UGC content
-        let ugc_model = ugc::Entity::find_by_id(msg.ugc_id).one(db).await?;
-        if let Some(ugc) = ugc_model {
-            if let Some(revision_id) = ugc.ugc_revision_id {
-                let revision = ugc_revisions::Entity::find_by_id(revision_id)
-                    .one(db)
-                    .await?;
-                if let Some(rev) = revision {
-                    // Get author profile (includes name, avatar, and user info)
-                    let profile = if let Some(author_id) = msg.user_id {
-                        Profile::get_by_id(db, author_id).await?
-                    } else {
-                        None
-                    };
-
-                    let (
-                        author_name,
-                        avatar_filename,
-                        avatar_width,
-                        avatar_height,
-                        user_created_at,
-                        post_count,
-                        reputation_score,
-                        custom_title,
-                        signature,
-                    ) = if let Some(p) = profile {
-                        (
-                            p.name,
-                            p.avatar_filename,
-                            p.avatar_width,
-                            p.avatar_height,
-                            Some(p.created_at),
-                            p.post_count,
-                            p.reputation_score,
-                            p.custom_title,
-                            p.signature,
-                        )
-                    } else {
-                        (
-                            "Unknown".to_string(),
-                            None,
-                            None,
-                            None,
-                            None,
-                            None,
-                            0,
-                            None,
-                            None,
-                        )
-                    };
-
-                    displays.push(MessageDisplay {
-                        id: msg.id,
-                        ugc_id: msg.ugc_id,
-                        user_id: msg.user_id,
-                        author_name,
-                        content: rev.content,
-                        created_at: msg.created_at,
-                        avatar_filename,
-                        avatar_width,
-                        avatar_height,
-                        user_created_at,
-                        post_count,
-                        reputation_score,
-                        custom_title,
-                        signature,
-                        is_deleted,
-                    });
-                }
-            }
+    let mut values = vec![conversation_id.into(), (limit as i64).into()];
+    match start {
+        PageStart::After((created_at, id)) => {
+            values.push(created_at.into());
+            values.push(id.into());
         }
+        PageStart::Offset(offset) => values.push((offset as i64).into()),
     }
 
-    Ok(displays)
+    let rows = MessageRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &sql,
+        values,
+    ))
+    .all(get_db_pool())
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| MessageDisplay {
+            id: row.id,
+            ugc_id: row.ugc_id,
+            user_id: row.user_id,
+            kind: row.kind,
+            author_name: row.author_name.unwrap_or_else(|| "Unknown".to_string()),
+            content: row.content,
+            created_at: row.created_at,
+            author_email: row.author_email,
+            avatar_source: row.avatar_source.unwrap_or_default(),
+            avatar_filename: row.avatar_filename,
+            avatar_width: row.avatar_width,
+            avatar_height: row.avatar_height,
+            avatar_renditions: row.avatar_renditions,
+            user_created_at: row.user_created_at,
+            post_count: row.post_count,
+            reputation_score: row.reputation_score.unwrap_or(0),
+            custom_title: row.custom_title,
+            signature: row.signature,
+            is_deleted: row.is_deleted,
+        })
+        .collect())
 }
 
 /// Message display data for templates
@@ -411,12 +494,16 @@ pub struct MessageDisplay {
     pub id: i32,
     pub ugc_id: i32,
     pub user_id: Option<i32>,
+    pub kind: MessageKind,
     pub author_name: String,
     pub content: String,
     pub created_at: chrono::NaiveDateTime,
+    pub author_email: Option<String>,
+    pub avatar_source: crate::orm::users::AvatarSource,
     pub avatar_filename: Option<String>,
     pub avatar_width: Option<i32>,
     pub avatar_height: Option<i32>,
+    pub avatar_renditions: Option<String>,
     pub user_created_at: Option<chrono::NaiveDateTime>,
     pub post_count: Option<i64>,
     pub reputation_score: i32,
@@ -426,17 +513,32 @@ pub struct MessageDisplay {
 }
 
 impl MessageDisplay {
+    /// Whether this message records a participant change rather than being written
+    pub fn is_system(&self) -> bool {
+        self.kind.is_system()
+    }
+
     /// Provides semantically correct HTML for an avatar.
     pub fn get_avatar_html(&self, size: crate::attachment::AttachmentSize) -> String {
-        if let (Some(filename), Some(width), Some(height)) = (
-            self.avatar_filename.as_ref(),
+        let file = match (
+            self.avatar_filename.as_deref(),
             self.avatar_width,
             self.avatar_height,
         ) {
-            crate::attachment::get_avatar_html(filename, (width, height), size)
-        } else {
-            String::new()
-        }
+            (Some(filename), Some(width), Some(height)) => Some(crate::attachment::AvatarFile {
+                filename,
+                dimensions: (width, height),
+                renditions: self.avatar_renditions.as_deref(),
+            }),
+            _ => None,
+        };
+
+        crate::attachment::get_avatar_html_for_source(
+            self.avatar_source,
+            file,
+            self.author_email.as_deref(),
+            size,
+        )
     }
 
     /// Get URL token for user profile link
@@ -503,59 +605,37 @@ pub async fn unarchive_conversation(user_id: i32, conversation_id: i32) -> Resul
     Ok(())
 }
 
-/// Get archived conversations for a user
-pub async fn get_archived_conversations(
+/// Mute or unmute a conversation for a user. Muted conversations raise no
+/// notifications but unread messages are still counted.
+pub async fn set_conversation_muted(
     user_id: i32,
-    limit: u64,
-) -> Result<Vec<ConversationPreview>, DbErr> {
+    conversation_id: i32,
+    muted: bool,
+) -> Result<(), DbErr> {
     let db = get_db_pool();
 
-    // Get archived conversations where user is participant
-    let participants = conversation_participants::Entity::find()
+    // Verify user is a participant
+    verify_participant(db, user_id, conversation_id).await?;
+
+    conversation_participants::Entity::update_many()
+        .col_expr(
+            conversation_participants::Column::IsMuted,
+            Expr::value(muted),
+        )
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
         .filter(conversation_participants::Column::UserId.eq(user_id))
-        .filter(conversation_participants::Column::IsArchived.eq(true))
-        .find_also_related(conversations::Entity)
-        .order_by_desc(conversations::Column::UpdatedAt)
-        .limit(limit)
-        .all(db)
+        .exec(db)
         .await?;
 
-    let mut previews = Vec::new();
-
-    for (participant, conversation) in participants {
-        if let Some(conv) = conversation {
-            // Get other participants
-            let other_participants =
-                get_conversation_participants(db, conv.id, Some(user_id)).await?;
-
-            // Get last message
-            let last_message = get_last_message(db, conv.id).await?;
-
-            // Check if unread
-            let is_unread = if let Some(last_read) = participant.last_read_at {
-                conv.updated_at > last_read
-            } else {
-                true
-            };
-
-            // Extract content and timestamp from last_message
-            let (last_content, last_timestamp) = match last_message {
-                Some((content, timestamp)) => (Some(content), Some(timestamp)),
-                None => (None, None),
-            };
-
-            previews.push(ConversationPreview {
-                id: conv.id,
-                title: conv.title,
-                participants: other_participants,
-                last_message_content: last_content,
-                last_message_at: last_timestamp,
-                is_unread,
-            });
-        }
-    }
+    Ok(())
+}
 
-    Ok(previews)
+/// Get archived conversations for a user
+pub async fn get_archived_conversations(
+    user_id: i32,
+    limit: u64,
+) -> Result<Vec<ConversationPreview>, DbErr> {
+    get_conversation_previews(user_id, true, limit).await
 }
 
 /// Update a message's content (only by the message author)
@@ -568,6 +648,12 @@ pub async fn update_message(message_id: i32, user_id: i32, new_content: &str) ->
         .await?
         .ok_or_else(|| DbErr::Custom("Message not found".to_string()))?;
 
+    if message.kind.is_system() {
+        return Err(DbErr::Custom(
+            "System messages cannot be edited".to_string(),
+        ));
+    }
+
     // Verify the user is the message author
     if message.user_id != Some(user_id) {
         return Err(DbErr::Custom(
@@ -624,6 +710,12 @@ pub async fn delete_message(
         .await?
         .ok_or_else(|| DbErr::Custom("Message not found".to_string()))?;
 
+    if message.kind.is_system() {
+        return Err(DbErr::Custom(
+            "System messages cannot be deleted".to_string(),
+        ));
+    }
+
     // Verify the user is the message author OR has moderation permission
     if message.user_id != Some(user_id) && !can_moderate {
         return Err(DbErr::Custom(
@@ -658,13 +750,14 @@ pub async fn delete_message(
 }
 
 /// Leave a conversation (remove user as participant)
-/// If no participants remain, the conversation is deleted
+/// If no participants remain, the conversation is deleted. If the owner leaves,
+/// ownership passes to the longest-standing remaining participant.
 pub async fn leave_conversation(user_id: i32, conversation_id: i32) -> Result<(), DbErr> {
     let db = get_db_pool();
     let txn = db.begin().await?;
 
     // Verify user is a participant
-    verify_participant(&txn, user_id, conversation_id).await?;
+    let leaving = get_participant(&txn, user_id, conversation_id).await?;
 
     // Delete the participant record
     conversation_participants::Entity::delete_many()
@@ -673,17 +766,38 @@ pub async fn leave_conversation(user_id: i32, conversation_id: i32) -> Result<()
         .exec(&txn)
         .await?;
 
-    // Check if any participants remain
-    let remaining = conversation_participants::Entity::find()
+    // Find the longest-standing remaining participant
+    let successor = conversation_participants::Entity::find()
         .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
-        .count(&txn)
+        .order_by_asc(conversation_participants::Column::JoinedAt)
+        .order_by_asc(conversation_participants::Column::UserId)
+        .one(&txn)
         .await?;
 
-    // If no participants remain, delete the conversation (cascade will delete messages)
-    if remaining == 0 {
-        conversations::Entity::delete_by_id(conversation_id)
-            .exec(&txn)
-            .await?;
+    let successor = match successor {
+        Some(successor) => successor,
+        None => {
+            // No participants remain, delete the conversation (cascade will delete messages)
+            conversations::Entity::delete_by_id(conversation_id)
+                .exec(&txn)
+                .await?;
+            txn.commit().await?;
+            return Ok(());
+        }
+    };
+
+    let name = get_user_name(&txn, user_id).await?;
+    record_system_message(
+        &txn,
+        conversation_id,
+        user_id,
+        MessageKind::Left,
+        &format!("{} left the conversation", name),
+    )
+    .await?;
+
+    if leaving.role == ParticipantRole::Owner {
+        set_owner(&txn, conversation_id, user_id, successor.user_id).await?;
     }
 
     txn.commit().await?;
@@ -700,82 +814,342 @@ pub async fn get_conversation_creator(conversation_id: i32) -> Result<Option<i32
     Ok(conversation.and_then(|c| c.creator_id))
 }
 
-/// Kick a participant from a conversation (only creator can do this)
-pub async fn kick_participant(
-    requester_id: i32,
+/// Get the current owner of a conversation
+pub async fn get_conversation_owner(conversation_id: i32) -> Result<Option<i32>, DbErr> {
+    let owner = conversation_participants::Entity::find()
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .filter(conversation_participants::Column::Role.eq(ParticipantRole::Owner))
+        .one(get_db_pool())
+        .await?;
+    Ok(owner.map(|p| p.user_id))
+}
+
+/// Fetch a participant record, failing if the user isn't in the conversation
+async fn get_participant<C>(
+    db: &C,
+    user_id: i32,
     conversation_id: i32,
-    target_user_id: i32,
-) -> Result<(), DbErr> {
-    let db = get_db_pool();
+) -> Result<conversation_participants::Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    conversation_participants::Entity::find()
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .filter(conversation_participants::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::Custom("User is not a participant in this conversation".to_string()))
+}
 
-    // Verify requester is the creator
+/// Fetch a conversation and verify the requester owns it
+async fn require_owner<C>(
+    db: &C,
+    requester_id: i32,
+    conversation_id: i32,
+    action: &str,
+) -> Result<conversations::Model, DbErr>
+where
+    C: ConnectionTrait,
+{
     let conversation = conversations::Entity::find_by_id(conversation_id)
         .one(db)
         .await?
-        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
+        .ok_or_else(|| DbErr::RecordNotFound("Conversation not found".to_string()))?;
 
-    if conversation.creator_id != Some(requester_id) {
-        return Err(DbErr::Custom(
-            "Only the conversation creator can kick participants".to_string(),
-        ));
+    let is_owner = conversation_participants::Entity::find()
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .filter(conversation_participants::Column::UserId.eq(requester_id))
+        .one(db)
+        .await?
+        .map(|p| p.role == ParticipantRole::Owner)
+        .unwrap_or(false);
+
+    if !is_owner {
+        return Err(DbErr::Custom(format!(
+            "Only the conversation owner can {}",
+            action
+        )));
     }
 
+    Ok(conversation)
+}
+
+/// Get a user's display name for system messages
+async fn get_user_name<C>(db: &C, user_id: i32) -> Result<String, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Ok(user_names::Entity::find()
+        .filter(user_names::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .map(|user_name| user_name.name)
+        .unwrap_or_else(|| "Unknown".to_string()))
+}
+
+/// Record a change to the conversation as a system message in the thread
+async fn record_system_message<C>(
+    db: &C,
+    conversation_id: i32,
+    actor_id: i32,
+    kind: MessageKind,
+    content: &str,
+) -> Result<i32, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let ugc_revision = create_ugc(
+        db,
+        NewUgcPartial {
+            ip_id: None,
+            user_id: Some(actor_id),
+            content,
+        },
+    )
+    .await
+    .map_err(|e| DbErr::Custom(format!("Failed to create UGC: {}", e)))?;
+
+    let message = private_messages::ActiveModel {
+        conversation_id: Set(conversation_id),
+        ugc_id: Set(ugc_revision.ugc_id),
+        user_id: Set(Some(actor_id)),
+        created_at: Set(ugc_revision.created_at),
+        kind: Set(kind),
+        ..Default::default()
+    };
+    Ok(message.insert(db).await?.id)
+}
+
+/// Make a participant the conversation owner and record it
+async fn set_owner<C>(
+    db: &C,
+    conversation_id: i32,
+    actor_id: i32,
+    new_owner_id: i32,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    conversation_participants::Entity::update_many()
+        .col_expr(
+            conversation_participants::Column::Role,
+            Expr::value(ParticipantRole::Member),
+        )
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .filter(conversation_participants::Column::Role.eq(ParticipantRole::Owner))
+        .exec(db)
+        .await?;
+
+    conversation_participants::Entity::update_many()
+        .col_expr(
+            conversation_participants::Column::Role,
+            Expr::value(ParticipantRole::Owner),
+        )
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .filter(conversation_participants::Column::UserId.eq(new_owner_id))
+        .exec(db)
+        .await?;
+
+    let name = get_user_name(db, new_owner_id).await?;
+    record_system_message(
+        db,
+        conversation_id,
+        actor_id,
+        MessageKind::OwnerChanged,
+        &format!("{} is now the conversation owner", name),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Kick a participant from a conversation (only the owner can do this)
+pub async fn kick_participant(
+    requester_id: i32,
+    conversation_id: i32,
+    target_user_id: i32,
+) -> Result<(), DbErr> {
+    let db = get_db_pool();
+    let txn = db.begin().await?;
+
+    // Verify requester is the owner
+    require_owner(&txn, requester_id, conversation_id, "kick participants").await?;
+
     // Cannot kick yourself
     if target_user_id == requester_id {
         return Err(DbErr::Custom("Cannot kick yourself".to_string()));
     }
 
     // Verify target is a participant
-    verify_participant(db, target_user_id, conversation_id).await?;
+    verify_participant(&txn, target_user_id, conversation_id).await?;
 
     // Remove the participant
     conversation_participants::Entity::delete_many()
         .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
         .filter(conversation_participants::Column::UserId.eq(target_user_id))
-        .exec(db)
+        .exec(&txn)
         .await?;
 
+    let requester_name = get_user_name(&txn, requester_id).await?;
+    let target_name = get_user_name(&txn, target_user_id).await?;
+    record_system_message(
+        &txn,
+        conversation_id,
+        requester_id,
+        MessageKind::Removed,
+        &format!(
+            "{} removed {} from the conversation",
+            requester_name, target_name
+        ),
+    )
+    .await?;
+
+    txn.commit().await?;
+
     Ok(())
 }
 
-/// Invite a user to a conversation (only creator can do this)
+/// Invite a user to a conversation (only the owner can do this)
 pub async fn invite_participant(
     requester_id: i32,
     conversation_id: i32,
     target_user_id: i32,
 ) -> Result<(), DbErr> {
     let db = get_db_pool();
+    let txn = db.begin().await?;
 
-    // Verify requester is the creator
-    let conversation = conversations::Entity::find_by_id(conversation_id)
-        .one(db)
-        .await?
-        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
-
-    if conversation.creator_id != Some(requester_id) {
-        return Err(DbErr::Custom(
-            "Only the conversation creator can invite participants".to_string(),
-        ));
-    }
+    // Verify requester is the owner
+    require_owner(&txn, requester_id, conversation_id, "invite participants").await?;
 
     // Check if user is already a participant
     let existing = conversation_participants::Entity::find()
         .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
         .filter(conversation_participants::Column::UserId.eq(target_user_id))
-        .one(db)
+        .one(&txn)
         .await?;
 
     if existing.is_some() {
         return Err(DbErr::Custom("User is already a participant".to_string()));
     }
 
+    let participant_count = conversation_participants::Entity::find()
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .count(&txn)
+        .await?;
+    if participant_count as usize >= max_participants() {
+        return Err(participant_limit_error());
+    }
+
     // Add the participant
     let participant = conversation_participants::ActiveModel {
         conversation_id: Set(conversation_id),
         user_id: Set(target_user_id),
         ..Default::default()
     };
-    participant.insert(db).await?;
+    participant.insert(&txn).await?;
+
+    let requester_name = get_user_name(&txn, requester_id).await?;
+    let target_name = get_user_name(&txn, target_user_id).await?;
+    record_system_message(
+        &txn,
+        conversation_id,
+        requester_id,
+        MessageKind::Invited,
+        &format!(
+            "{} invited {} to the conversation",
+            requester_name, target_name
+        ),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Hand ownership of a conversation to another participant (only the owner can do this)
+pub async fn transfer_ownership(
+    requester_id: i32,
+    conversation_id: i32,
+    new_owner_id: i32,
+) -> Result<(), DbErr> {
+    let db = get_db_pool();
+    let txn = db.begin().await?;
+
+    // Verify requester is the owner
+    require_owner(&txn, requester_id, conversation_id, "transfer ownership").await?;
+
+    if new_owner_id == requester_id {
+        return Err(DbErr::Custom(
+            "You already own this conversation".to_string(),
+        ));
+    }
+
+    // Verify the new owner is a participant
+    verify_participant(&txn, new_owner_id, conversation_id).await?;
+
+    set_owner(&txn, conversation_id, requester_id, new_owner_id).await?;
+
+    txn.commit().await?;
+
+    Ok(())
+}
+
+/// Maximum length of a conversation title
+pub const MAX_TITLE_LENGTH: usize = 255;
+
+/// Rename a conversation, or clear its title (only the owner can do this)
+pub async fn rename_conversation(
+    requester_id: i32,
+    conversation_id: i32,
+    title: Option<&str>,
+) -> Result<(), DbErr> {
+    let title = title.map(str::trim).filter(|t| !t.is_empty());
+    if title.is_some_and(|t| t.chars().count() > MAX_TITLE_LENGTH) {
+        return Err(DbErr::Custom(format!(
+            "Titles can be at most {} characters",
+            MAX_TITLE_LENGTH
+        )));
+    }
+
+    let db = get_db_pool();
+    let txn = db.begin().await?;
+
+    let conversation = require_owner(
+        &txn,
+        requester_id,
+        conversation_id,
+        "rename the conversation",
+    )
+    .await?;
+    if conversation.title.as_deref() == title {
+        return Ok(());
+    }
+
+    conversations::Entity::update_many()
+        .col_expr(conversations::Column::Title, Expr::value(title))
+        .filter(conversations::Column::Id.eq(conversation_id))
+        .exec(&txn)
+        .await?;
+
+    let requester_name = get_user_name(&txn, requester_id).await?;
+    let content = match title {
+        Some(title) => format!(
+            "{} renamed the conversation to \"{}\"",
+            requester_name, title
+        ),
+        None => format!("{} removed the conversation title", requester_name),
+    };
+    record_system_message(
+        &txn,
+        conversation_id,
+        requester_id,
+        MessageKind::Renamed,
+        &content,
+    )
+    .await?;
+
+    txn.commit().await?;
 
     Ok(())
 }
@@ -786,22 +1160,27 @@ pub struct ParticipantInfo {
     pub user_id: i32,
     pub name: String,
     pub joined_at: chrono::NaiveDateTime,
-    pub is_creator: bool,
+    pub role: ParticipantRole,
+    pub last_read_message_id: Option<i32>,
+}
+
+impl ParticipantInfo {
+    pub fn is_owner(&self) -> bool {
+        self.role == ParticipantRole::Owner
+    }
 }
 
 /// Get full participant info for a conversation
+#[tracing::instrument]
 pub async fn get_participant_info(conversation_id: i32) -> Result<Vec<ParticipantInfo>, DbErr> {
     use crate::user::Profile;
 
     let db = get_db_pool();
 
-    // Get the conversation to find creator
-    let conversation = conversations::Entity::find_by_id(conversation_id)
+    conversations::Entity::find_by_id(conversation_id)
         .one(db)
         .await?
-        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
-
-    let creator_id = conversation.creator_id;
+        .ok_or_else(|| DbErr::RecordNotFound("Conversation not found".to_string()))?;
 
     // Get all participants
     let participants = conversation_participants::Entity::find()
@@ -816,10 +1195,49 @@ pub async fn get_participant_info(conversation_id: i32) -> Result<Vec<Participan
                 user_id: participant.user_id,
                 name: profile.name,
                 joined_at: participant.joined_at,
-                is_creator: creator_id == Some(participant.user_id),
+                role: participant.role,
+                last_read_message_id: participant.last_read_message_id,
             });
         }
     }
 
     Ok(infos)
 }
+
+/// "Seen by" markers for a conversation view: the names of the other
+/// participants whose reading stops at each message, keyed by message id.
+/// A participant is listed under the latest visible message they have read,
+/// unless they wrote it themselves.
+pub fn get_seen_by(
+    messages: &[MessageDisplay],
+    participants: &[ParticipantInfo],
+    viewer_id: i32,
+) -> HashMap<i32, Vec<String>> {
+    let mut seen_by: HashMap<i32, Vec<String>> = HashMap::new();
+
+    for participant in participants {
+        if participant.user_id == viewer_id {
+            continue;
+        }
+        let last_read = match participant.last_read_message_id {
+            Some(id) => id,
+            None => continue,
+        };
+
+        let message = messages
+            .iter()
+            .filter(|m| !m.is_system() && !m.is_deleted && m.id <= last_read)
+            .max_by_key(|m| m.id);
+
+        if let Some(message) = message {
+            if message.user_id != Some(participant.user_id) {
+                seen_by
+                    .entry(message.id)
+                    .or_default()
+                    .push(participant.name.clone());
+            }
+        }
+    }
+
+    seen_by
+}
@@ -0,0 +1,83 @@
+//! Live conversation updates pushed over the notification WebSocket
+//!
+//! Events go to every participant's open connections; the conversation page
+//! picks out the ones for the conversation it is showing.
+
+use super::{get_user_name, mark_conversation_read, verify_participant};
+use crate::db::get_db_pool;
+use crate::orm::conversation_participants;
+use crate::web::notifications_ws::{
+    get_notification_server, BroadcastConversationEvent, ConversationEvent,
+};
+use sea_orm::{entity::*, query::*, DbErr};
+
+/// Send an event to the participants of a conversation, skipping `except`
+pub async fn broadcast_to_participants(
+    conversation_id: i32,
+    event: ConversationEvent,
+    except: Option<i32>,
+) -> Result<(), DbErr> {
+    let server = match get_notification_server() {
+        Some(server) => server,
+        None => return Ok(()),
+    };
+
+    let user_ids: Vec<i32> = conversation_participants::Entity::find()
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .all(get_db_pool())
+        .await?
+        .into_iter()
+        .map(|participant| participant.user_id)
+        .filter(|user_id| Some(*user_id) != except)
+        .collect();
+
+    if !user_ids.is_empty() {
+        server.do_send(BroadcastConversationEvent { user_ids, event });
+    }
+
+    Ok(())
+}
+
+/// Tell the other participants that a user is typing
+pub async fn broadcast_typing(user_id: i32, conversation_id: i32) -> Result<(), DbErr> {
+    let db = get_db_pool();
+    verify_participant(db, user_id, conversation_id).await?;
+
+    let event = ConversationEvent::Typing {
+        conversation_id,
+        user_id,
+        name: get_user_name(db, user_id).await?,
+    };
+
+    broadcast_to_participants(conversation_id, event, Some(user_id)).await
+}
+
+/// Tell the other participants how far a user has read
+pub async fn broadcast_read_receipt(user_id: i32, conversation_id: i32) -> Result<(), DbErr> {
+    let db = get_db_pool();
+
+    let participant = conversation_participants::Entity::find()
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .filter(conversation_participants::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .ok_or_else(|| {
+            DbErr::Custom("User is not a participant in this conversation".to_string())
+        })?;
+
+    let event = ConversationEvent::Read {
+        conversation_id,
+        user_id,
+        name: get_user_name(db, user_id).await?,
+        last_read_message_id: participant.last_read_message_id,
+    };
+
+    broadcast_to_participants(conversation_id, event, Some(user_id)).await
+}
+
+/// Mark a conversation read from an open page and share the receipt
+pub async fn mark_read(user_id: i32, conversation_id: i32) -> Result<(), DbErr> {
+    verify_participant(get_db_pool(), user_id, conversation_id).await?;
+    mark_conversation_read(user_id, conversation_id).await?;
+    broadcast_read_receipt(user_id, conversation_id).await
+}
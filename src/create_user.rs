@@ -55,7 +55,8 @@ impl std::fmt::Display for CreateUserError {
     }
 }
 
-async fn insert_new_user(
+/// Insert an account with its name, given an already hashed password.
+pub async fn insert_new_user(
     name: &str,
     pass: &str,
     email: &str,
@@ -152,7 +153,7 @@ pub async fn create_user_post(
         .unwrap_or_else(|| "unknown".to_string());
 
     // Rate limiting - prevent registration spam
-    if let Err(e) = crate::rate_limit::check_registration_rate_limit(&ip) {
+    if let Err(e) = crate::rate_limit::check_registration_rate_limit(&ip).await {
         log::warn!("Rate limit exceeded for registration: ip={}", ip);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many registration attempts. Please wait {} seconds.",
@@ -216,6 +217,18 @@ pub async fn create_user_post(
     let username = form.username.trim();
     let email = form.email.trim().to_lowercase();
 
+    // Word filter: a username is never rewritten, so any filter changing it
+    // refuses it as a block would
+    if !crate::word_filter::is_username_allowed(username) {
+        log::info!(
+            "Registration failed - username matched word filter: {}",
+            username
+        );
+        return Err(error::ErrorBadRequest(
+            "That username isn't allowed. Please choose another.",
+        ));
+    }
+
     // Hash password
     let password_hash = get_argon2()
         .hash_password(form.password.as_bytes(), &SaltString::generate(&mut OsRng))
@@ -272,6 +285,8 @@ pub async fn create_user_post(
 
     log::info!("New user registered: {} (user_id: {})", username, user_id);
 
+    crate::webhooks::user_registered(user_id, username);
+
     // Return success - could redirect to a "check your email" page
     Ok(HttpResponse::Ok()
         .content_type("text/html")
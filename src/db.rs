@@ -1,25 +1,47 @@
 use crate::session::{get_sess, reload_session_cache};
 use once_cell::sync::OnceCell;
 use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
 static DB_POOL: OnceCell<DatabaseConnection> = OnceCell::new();
+/// Read replicas, from `DATABASE_REPLICA_URLS`. Empty without any.
+static DB_REPLICAS: OnceCell<Vec<DatabaseConnection>> = OnceCell::new();
+/// Replica the next read goes to
+static NEXT_REPLICA: AtomicUsize = AtomicUsize::new(0);
 
+/// The primary database. Writes, transactions and reads that must see them
+/// go here.
 #[inline(always)]
 pub fn get_db_pool() -> &'static DatabaseConnection {
     unsafe { DB_POOL.get_unchecked() }
 }
 
-/// Opens the database URL and initializes the DB_POOL static.
-pub async fn init_db(database_url: String) -> &'static DatabaseConnection {
+/// A connection for reads that can tolerate replication lag: one of the
+/// replicas in turn, or the primary when there are none.
+pub fn get_db_read_pool() -> &'static DatabaseConnection {
+    match DB_REPLICAS.get() {
+        Some(replicas) if !replicas.is_empty() => {
+            let next = NEXT_REPLICA.fetch_add(1, Ordering::Relaxed);
+            &replicas[next % replicas.len()]
+        }
+        _ => get_db_pool(),
+    }
+}
+
+fn connect_options(database_url: String) -> ConnectOptions {
     let mut opt = ConnectOptions::new(database_url);
     opt.max_connections(100)
         .min_connections(5)
         .connect_timeout(Duration::from_secs(1))
         .idle_timeout(Duration::from_secs(1))
         .sqlx_logging(true);
+    opt
+}
 
-    let pool = Database::connect(opt)
+/// Opens the database URL and initializes the DB_POOL static.
+pub async fn init_db(database_url: String) -> &'static DatabaseConnection {
+    let pool = Database::connect(connect_options(database_url))
         .await
         .expect("Database connection was not established.");
     DB_POOL.set(pool).unwrap();
@@ -32,3 +54,41 @@ pub async fn init_db(database_url: String) -> &'static DatabaseConnection {
         .get()
         .expect("DatabaseConnection in DB_POOL failed in init_db()")
 }
+
+/// Opens the comma-separated replica URLs. A replica that can't be reached is
+/// left out, so its reads go to the others or the primary.
+pub async fn init_db_replicas(database_urls: &str) {
+    let mut replicas = Vec::new();
+    for url in parse_replica_urls(database_urls) {
+        match Database::connect(connect_options(url.to_owned())).await {
+            Ok(pool) => replicas.push(pool),
+            Err(err) => log::error!("Failed to connect to read replica: {}", err),
+        }
+    }
+
+    if !replicas.is_empty() {
+        log::info!("Reading from {} database replica(s).", replicas.len());
+    }
+    if DB_REPLICAS.set(replicas).is_err() {
+        log::warn!("Database replicas already initialized.");
+    }
+}
+
+fn parse_replica_urls(database_urls: &str) -> impl Iterator<Item = &str> {
+    database_urls
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_replica_urls() {
+        let urls: Vec<&str> = parse_replica_urls(" postgres://a/db, ,postgres://b/db,").collect();
+        assert_eq!(urls, vec!["postgres://a/db", "postgres://b/db"]);
+        assert_eq!(parse_replica_urls("").count(), 0);
+    }
+}
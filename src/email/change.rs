@@ -0,0 +1,283 @@
+//! Changing a member's email address.
+//!
+//! A member asks for the change from their account page, giving their
+//! password. The new address gets a link that makes the change, so it's
+//! known to work before it replaces the old one. The old address is told
+//! about the request and gets a link that cancels it, or puts the old
+//! address back if it was already confirmed, in case the account was taken
+//! over. Admins see pending and recent changes at `/admin/emails/changes`.
+
+use crate::db::get_db_pool;
+use crate::orm::{email_changes, user_names, users};
+use chrono::{Duration, Utc};
+use rand::Rng;
+use sea_orm::{entity::*, query::*, sea_query::Expr, DbErr};
+use std::collections::HashMap;
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_CONFIRMED: &str = "confirmed";
+pub const STATUS_CANCELLED: &str = "cancelled";
+pub const STATUS_REVERTED: &str = "reverted";
+
+/// How long the link sent to the new address works
+const CONFIRM_HOURS: i64 = 24;
+
+/// How long the link sent to the old address works
+const REVERT_DAYS: i64 = 7;
+
+/// Why an email change can't go ahead
+#[derive(Debug)]
+pub enum ChangeError {
+    InvalidEmail,
+    SameEmail,
+    EmailTaken,
+    Database(DbErr),
+}
+
+impl From<DbErr> for ChangeError {
+    fn from(err: DbErr) -> Self {
+        ChangeError::Database(err)
+    }
+}
+
+impl std::fmt::Display for ChangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeError::InvalidEmail => write!(f, "Please enter a valid email address."),
+            ChangeError::SameEmail => write!(f, "That is already your email address."),
+            ChangeError::EmailTaken => {
+                write!(f, "That email address is in use by another account.")
+            }
+            ChangeError::Database(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+fn generate_token() -> String {
+    use rand::distributions::Alphanumeric;
+
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(64)
+        .map(char::from)
+        .collect()
+}
+
+/// Trims and lowercases an address, if it is one.
+pub fn normalize(email: &str) -> Option<String> {
+    let email = email.trim().to_lowercase();
+    validator::validate_email(&email).then(|| email)
+}
+
+/// Whether an account other than `user_id` uses `email`.
+async fn is_taken(db: &impl ConnectionTrait, email: &str, user_id: i32) -> Result<bool, DbErr> {
+    Ok(users::Entity::find()
+        .filter(users::Column::Email.eq(email))
+        .filter(users::Column::Id.ne(user_id))
+        .count(db)
+        .await?
+        > 0)
+}
+
+/// Starts changing a member's address to `new_email`, replacing any change
+/// already waiting for confirmation. The caller sends the emails.
+pub async fn request(
+    user: &users::Model,
+    new_email: &str,
+) -> Result<email_changes::Model, ChangeError> {
+    let db = get_db_pool();
+    let new_email = normalize(new_email).ok_or(ChangeError::InvalidEmail)?;
+    if user.email.as_deref().map(str::to_lowercase).as_deref() == Some(new_email.as_str()) {
+        return Err(ChangeError::SameEmail);
+    }
+    if is_taken(db, &new_email, user.id).await? {
+        return Err(ChangeError::EmailTaken);
+    }
+
+    cancel_pending(user.id).await?;
+
+    let now = Utc::now().naive_utc();
+    let change = email_changes::ActiveModel {
+        user_id: Set(user.id),
+        old_email: Set(user.email.clone()),
+        new_email: Set(new_email),
+        confirm_token: Set(generate_token()),
+        revert_token: Set(generate_token()),
+        status: Set(STATUS_PENDING.to_string()),
+        created_at: Set(now),
+        expires_at: Set(now + Duration::hours(CONFIRM_HOURS)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    log::info!("User {} asked to change their email address", user.id);
+    Ok(change)
+}
+
+/// The change waiting for confirmation from the new address, if any.
+pub async fn pending_for_user(user_id: i32) -> Result<Option<email_changes::Model>, DbErr> {
+    email_changes::Entity::find()
+        .filter(email_changes::Column::UserId.eq(user_id))
+        .filter(email_changes::Column::Status.eq(STATUS_PENDING))
+        .filter(email_changes::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+        .order_by_desc(email_changes::Column::CreatedAt)
+        .one(get_db_pool())
+        .await
+}
+
+/// Cancels a member's unconfirmed changes. Returns whether there were any.
+pub async fn cancel_pending(user_id: i32) -> Result<bool, DbErr> {
+    let result = email_changes::Entity::update_many()
+        .col_expr(email_changes::Column::Status, Expr::value(STATUS_CANCELLED))
+        .filter(email_changes::Column::UserId.eq(user_id))
+        .filter(email_changes::Column::Status.eq(STATUS_PENDING))
+        .exec(get_db_pool())
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// Makes the change a confirmation link is for, marking the new address
+/// verified. None if the link is unknown, used or expired.
+pub async fn confirm(token: &str) -> Result<Option<email_changes::Model>, ChangeError> {
+    let db = get_db_pool();
+    let Some(change) = email_changes::Entity::find()
+        .filter(email_changes::Column::ConfirmToken.eq(token))
+        .filter(email_changes::Column::Status.eq(STATUS_PENDING))
+        .filter(email_changes::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    // Someone may have taken the address since the change was asked for
+    if is_taken(db, &change.new_email, change.user_id).await? {
+        return Err(ChangeError::EmailTaken);
+    }
+
+    let txn = db.begin().await?;
+    users::Entity::update_many()
+        .col_expr(users::Column::Email, Expr::value(change.new_email.clone()))
+        .col_expr(users::Column::EmailVerified, Expr::value(true))
+        .filter(users::Column::Id.eq(change.user_id))
+        .exec(&txn)
+        .await?;
+
+    let mut active: email_changes::ActiveModel = change.into();
+    active.status = Set(STATUS_CONFIRMED.to_string());
+    active.confirmed_at = Set(Some(Utc::now().naive_utc()));
+    let change = active.update(&txn).await?;
+    txn.commit().await?;
+
+    log::info!("User {} confirmed their new email address", change.user_id);
+    Ok(Some(change))
+}
+
+/// The change a link sent to the old address is for, while it can still be
+/// cancelled or undone.
+pub async fn find_revertible(token: &str) -> Result<Option<email_changes::Model>, DbErr> {
+    email_changes::Entity::find()
+        .filter(email_changes::Column::RevertToken.eq(token))
+        .filter(email_changes::Column::Status.is_in([STATUS_PENDING, STATUS_CONFIRMED]))
+        .filter(
+            email_changes::Column::CreatedAt
+                .gt(Utc::now().naive_utc() - Duration::days(REVERT_DAYS)),
+        )
+        .one(get_db_pool())
+        .await
+}
+
+/// Cancels a change, and if it was already made, puts the old address back.
+/// Any other change waiting for confirmation is cancelled too.
+pub async fn revert(change: email_changes::Model) -> Result<email_changes::Model, ChangeError> {
+    let db = get_db_pool();
+    let restore = change.status == STATUS_CONFIRMED;
+    if restore {
+        if let Some(old_email) = &change.old_email {
+            if is_taken(db, &old_email.to_lowercase(), change.user_id).await? {
+                return Err(ChangeError::EmailTaken);
+            }
+        }
+    }
+
+    let txn = db.begin().await?;
+    if restore {
+        // Following the link proves the old address still works
+        users::Entity::update_many()
+            .col_expr(users::Column::Email, Expr::value(change.old_email.clone()))
+            .col_expr(
+                users::Column::EmailVerified,
+                Expr::value(change.old_email.is_some()),
+            )
+            .filter(users::Column::Id.eq(change.user_id))
+            .exec(&txn)
+            .await?;
+    }
+
+    let mut active: email_changes::ActiveModel = change.into();
+    active.status = Set(STATUS_REVERTED.to_string());
+    active.reverted_at = Set(Some(Utc::now().naive_utc()));
+    let change = active.update(&txn).await?;
+    txn.commit().await?;
+    cancel_pending(change.user_id).await?;
+
+    log::warn!(
+        "Email change for user {} was {} from the old address",
+        change.user_id,
+        if restore { "undone" } else { "cancelled" }
+    );
+    Ok(change)
+}
+
+/// The most recent changes, newest first, with each member's name.
+pub async fn recent(limit: u64) -> Result<Vec<(email_changes::Model, String)>, DbErr> {
+    let db = get_db_pool();
+    let changes = email_changes::Entity::find()
+        .order_by_desc(email_changes::Column::CreatedAt)
+        .limit(limit)
+        .all(db)
+        .await?;
+
+    let names: HashMap<i32, String> = user_names::Entity::find()
+        .filter(user_names::Column::UserId.is_in(changes.iter().map(|c| c.user_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|name| (name.user_id, name.name))
+        .collect();
+
+    Ok(changes
+        .into_iter()
+        .map(|change| {
+            let name = names
+                .get(&change.user_id)
+                .cloned()
+                .unwrap_or_else(|| format!("User #{}", change.user_id));
+            (change, name)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("  Alice@Example.COM "),
+            Some("alice@example.com".to_string())
+        );
+        assert_eq!(normalize("not an address"), None);
+        assert_eq!(normalize(""), None);
+    }
+
+    #[test]
+    fn test_tokens_are_unguessable() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(token, generate_token());
+    }
+}
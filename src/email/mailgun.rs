@@ -0,0 +1,98 @@
+//! Sending through Mailgun's messages API
+
+use super::{api_response, http_client, EmailConfig, EmailResult, EmailTransport, OutgoingEmail};
+use async_trait::async_trait;
+
+pub struct MailgunMailer {
+    config: EmailConfig,
+}
+
+impl MailgunMailer {
+    pub fn new(config: EmailConfig) -> Self {
+        MailgunMailer { config }
+    }
+
+    fn send_url(&self) -> String {
+        format!(
+            "{}/v3/{}/messages",
+            self.config.mailgun_api_url.trim_end_matches('/'),
+            self.config.mailgun_domain
+        )
+    }
+}
+
+/// The form fields for an email. Custom headers are fields prefixed `h:`.
+fn form_fields(config: &EmailConfig, email: &OutgoingEmail<'_>) -> Vec<(&'static str, String)> {
+    let mut fields = vec![
+        (
+            "from",
+            format!("{} <{}>", config.from_name, config.from_email),
+        ),
+        ("to", email.to.to_string()),
+        ("subject", email.subject.to_string()),
+        ("text", email.body_text.to_string()),
+    ];
+    if let Some(html) = email.body_html {
+        fields.push(("html", html.to_string()));
+    }
+    if let Some(url) = email.unsubscribe_url {
+        fields.push(("h:List-Unsubscribe", format!("<{}>", url)));
+        fields.push((
+            "h:List-Unsubscribe-Post",
+            "List-Unsubscribe=One-Click".to_string(),
+        ));
+    }
+    fields
+}
+
+#[async_trait]
+impl EmailTransport for MailgunMailer {
+    async fn send(&self, email: &OutgoingEmail<'_>) -> EmailResult<String> {
+        let response = http_client()
+            .post(self.send_url())
+            .basic_auth("api", Some(&self.config.mailgun_api_key))
+            .form(&form_fields(&self.config, email))
+            .send()
+            .await?;
+        let answer = api_response(response).await?;
+
+        log::info!("Email sent through Mailgun to: {}", email.to);
+
+        Ok(answer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_form_fields() {
+        let config = EmailConfig {
+            from_email: "noreply@example.com".to_string(),
+            from_name: "Example".to_string(),
+            mailgun_api_url: "https://api.eu.mailgun.net/".to_string(),
+            mailgun_domain: "mg.example.com".to_string(),
+            ..Default::default()
+        };
+        let email = OutgoingEmail {
+            to: "alice@example.com",
+            subject: "Hello",
+            body_text: "Hi",
+            body_html: Some("<p>Hi</p>"),
+            unsubscribe_url: Some("https://example.com/u"),
+        };
+        let fields = form_fields(&config, &email);
+        assert_eq!(
+            fields[0],
+            ("from", "Example <noreply@example.com>".to_string())
+        );
+        assert!(fields.contains(&("html", "<p>Hi</p>".to_string())));
+        assert!(fields.contains(&("h:List-Unsubscribe", "<https://example.com/u>".to_string())));
+
+        assert_eq!(
+            MailgunMailer::new(config).send_url(),
+            "https://api.eu.mailgun.net/v3/mg.example.com/messages"
+        );
+    }
+}
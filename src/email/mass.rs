@@ -0,0 +1,345 @@
+//! Announcements emailed to many members at once.
+//!
+//! An admin writes the announcement and picks its audience at
+//! `/admin/emails/compose`. Sending happens in the job queue: each
+//! `email.mass_batch` job queues the emails for the next batch of recipients,
+//! in user id order, then schedules the following batch after a pause so a
+//! large audience doesn't exceed the mail provider's sending limits. Every
+//! email carries a link that turns announcement emails off.
+
+use super::templates::{preferences_link, Branding};
+use super::{plaintext, queue, unsubscribe, OutgoingEmail};
+use crate::db::get_db_pool;
+use crate::jobs::{self, Job, JobKind};
+use crate::orm::{email_log, mass_emails, user_bans, user_groups, user_names, users};
+use askama::Template;
+use chrono::Utc;
+use sea_orm::{entity::*, query::*, sea_query::Expr, DbErr, FromQueryResult};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+
+pub const STATUS_SENDING: &str = "sending";
+pub const STATUS_DONE: &str = "done";
+pub const STATUS_CANCELLED: &str = "cancelled";
+
+/// Attempts at queueing a batch before the announcement stalls
+const BATCH_MAX_ATTEMPTS: i32 = 5;
+
+/// Who an announcement goes to. Only approved, unbanned members with a
+/// verified email address are ever included.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Audience {
+    /// Only members of this group
+    pub group_id: Option<i32>,
+    /// Only members active within this many days
+    pub active_within_days: Option<i32>,
+    /// Only members who accept announcement emails
+    pub opted_in_only: bool,
+}
+
+impl From<&mass_emails::Model> for Audience {
+    fn from(mass: &mass_emails::Model) -> Self {
+        Audience {
+            group_id: mass.group_id,
+            active_within_days: mass.active_within_days,
+            opted_in_only: mass.opted_in_only,
+        }
+    }
+}
+
+impl Audience {
+    /// Recipients with an id above `after_user_id`, in id order
+    fn recipients(&self, after_user_id: i32) -> Select<users::Entity> {
+        let now = Utc::now();
+        let banned = user_bans::Entity::find()
+            .select_only()
+            .column(user_bans::Column::UserId)
+            .filter(
+                Condition::any()
+                    .add(user_bans::Column::ExpiresAt.is_null())
+                    .add(user_bans::Column::ExpiresAt.gt(now.naive_utc())),
+            )
+            .into_query();
+
+        let mut select = users::Entity::find()
+            .filter(users::Column::Id.gt(after_user_id))
+            .filter(users::Column::Email.is_not_null())
+            .filter(users::Column::EmailVerified.eq(true))
+            .filter(users::Column::ApprovalStatus.eq(users::ApprovalStatus::Approved))
+            .filter(users::Column::Id.not_in_subquery(banned));
+
+        if let Some(group_id) = self.group_id {
+            let members = user_groups::Entity::find()
+                .select_only()
+                .column(user_groups::Column::UserId)
+                .filter(user_groups::Column::GroupId.eq(group_id))
+                .into_query();
+            select = select.filter(users::Column::Id.in_subquery(members));
+        }
+        if let Some(days) = self.active_within_days {
+            let since = now - chrono::Duration::days(days.into());
+            select = select.filter(users::Column::LastActivityAt.gte(since));
+        }
+        if self.opted_in_only {
+            select = select.filter(users::Column::EmailAnnouncements.eq(true));
+        }
+
+        select.order_by_asc(users::Column::Id)
+    }
+
+    /// Number of members the announcement would go to now
+    pub async fn count(&self) -> Result<u64, DbErr> {
+        self.recipients(0).count(get_db_pool()).await
+    }
+}
+
+/// How the emails of an announcement are getting on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeliveryCounts {
+    pub pending: i64,
+    pub retrying: i64,
+    pub sent: i64,
+    pub failed: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct StatusCount {
+    mass_email_id: i32,
+    status: String,
+    count: i64,
+}
+
+/// Delivery counts from the email log for each of these announcements
+pub async fn delivery_counts(ids: &[i32]) -> Result<HashMap<i32, DeliveryCounts>, DbErr> {
+    let mut counts: HashMap<i32, DeliveryCounts> = HashMap::new();
+    if ids.is_empty() {
+        return Ok(counts);
+    }
+
+    let rows = email_log::Entity::find()
+        .select_only()
+        .column(email_log::Column::MassEmailId)
+        .column(email_log::Column::Status)
+        .column_as(Expr::col(email_log::Column::Id).count(), "count")
+        .filter(email_log::Column::MassEmailId.is_in(ids.iter().copied()))
+        .group_by(email_log::Column::MassEmailId)
+        .group_by(email_log::Column::Status)
+        .into_model::<StatusCount>()
+        .all(get_db_pool())
+        .await?;
+
+    for row in rows {
+        let entry = counts.entry(row.mass_email_id).or_default();
+        match row.status.as_str() {
+            "sent" => entry.sent += row.count,
+            "failed" => entry.failed += row.count,
+            "retrying" => entry.retrying += row.count,
+            _ => entry.pending += row.count,
+        }
+    }
+    Ok(counts)
+}
+
+/// Records an announcement and queues its first batch.
+pub async fn create(
+    subject: &str,
+    body: &str,
+    audience: &Audience,
+    created_by: i32,
+) -> Result<mass_emails::Model, DbErr> {
+    let total = audience.count().await?;
+    let mass = mass_emails::ActiveModel {
+        subject: Set(subject.to_owned()),
+        body: Set(body.to_owned()),
+        group_id: Set(audience.group_id),
+        active_within_days: Set(audience.active_within_days),
+        opted_in_only: Set(audience.opted_in_only),
+        status: Set(STATUS_SENDING.to_owned()),
+        total_recipients: Set(total.min(i32::MAX as u64) as i32),
+        queued_count: Set(0),
+        last_user_id: Set(0),
+        created_by: Set(Some(created_by)),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(get_db_pool())
+    .await?;
+
+    jobs::enqueue(
+        JobKind::MassEmailBatch,
+        json!({ "mass_email_id": mass.id }),
+        BATCH_MAX_ATTEMPTS,
+    )
+    .await?;
+
+    log::info!(
+        "User {} started announcement {} to {} members",
+        created_by,
+        mass.id,
+        total
+    );
+    Ok(mass)
+}
+
+/// Stops queueing an announcement. Emails already queued still go out.
+/// Returns false if it had already finished.
+pub async fn cancel(id: i32) -> Result<bool, DbErr> {
+    let result = mass_emails::Entity::update_many()
+        .col_expr(mass_emails::Column::Status, Expr::value(STATUS_CANCELLED))
+        .col_expr(
+            mass_emails::Column::FinishedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(mass_emails::Column::Id.eq(id))
+        .filter(mass_emails::Column::Status.eq(STATUS_SENDING))
+        .exec(get_db_pool())
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// Whether a member accepts announcement emails.
+pub async fn announcements_enabled(user_id: i32) -> Result<bool, DbErr> {
+    Ok(users::Entity::find_by_id(user_id)
+        .one(get_db_pool())
+        .await?
+        .map_or(true, |user| user.email_announcements))
+}
+
+/// Turns a member's announcement emails on or off.
+pub async fn set_announcements(user_id: i32, enabled: bool) -> Result<(), DbErr> {
+    users::Entity::update_many()
+        .col_expr(users::Column::EmailAnnouncements, Expr::value(enabled))
+        .filter(users::Column::Id.eq(user_id))
+        .exec(get_db_pool())
+        .await?;
+    Ok(())
+}
+
+#[derive(Template)]
+#[template(path = "email/announcement.html")]
+struct AnnouncementEmail<'a> {
+    branding: &'a Branding,
+    subject: &'a str,
+    username: &'a str,
+    body_html: &'a str,
+    preferences_link: &'a str,
+    unsubscribe_link: String,
+}
+
+/// Payload of an `email.mass_batch` job.
+#[derive(Debug, Deserialize)]
+struct BatchJob {
+    mass_email_id: i32,
+}
+
+/// Runs an `email.mass_batch` job: queues emails for the next batch of
+/// recipients, then schedules the next batch or marks the announcement done.
+/// Progress is saved after every email, so a retried batch carries on from
+/// where it failed instead of emailing anyone twice.
+pub async fn run_batch_job(job: &Job) -> Result<(), String> {
+    let payload: BatchJob = serde_json::from_value(job.payload.clone())
+        .map_err(|e| format!("invalid mass email job payload: {}", e))?;
+    let db = get_db_pool();
+
+    let Some(mass) = mass_emails::Entity::find_by_id(payload.mass_email_id)
+        .one(db)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(());
+    };
+    if mass.status != STATUS_SENDING {
+        return Ok(());
+    }
+
+    let config = crate::app_config::email();
+    let batch_size = config.mass_batch_size.max(1);
+    let recipients = Audience::from(&mass)
+        .recipients(mass.last_user_id)
+        .limit(batch_size)
+        .all(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let names: HashMap<i32, String> = user_names::Entity::find()
+        .filter(user_names::Column::UserId.is_in(recipients.iter().map(|u| u.id)))
+        .all(db)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|name| (name.user_id, name.name))
+        .collect();
+
+    let base_url = crate::notifications::dispatcher::get_base_url();
+    let branding = Branding::load(&base_url).await;
+    let body_html = crate::bbcode::parse(&mass.body);
+    let preferences_link = preferences_link(&base_url);
+
+    for user in &recipients {
+        let Some(address) = user.email.as_deref() else {
+            continue;
+        };
+        let template = AnnouncementEmail {
+            branding: &branding,
+            subject: &mass.subject,
+            username: names.get(&user.id).map(String::as_str).unwrap_or("member"),
+            body_html: &body_html,
+            preferences_link: &preferences_link,
+            unsubscribe_link: unsubscribe::url(&base_url, user.id, unsubscribe::ANNOUNCEMENTS),
+        };
+        let html = template.render().map_err(|e| e.to_string())?;
+        let text = plaintext::html_to_text(&html);
+
+        queue::enqueue(
+            &OutgoingEmail {
+                to: address,
+                subject: &mass.subject,
+                body_text: &text,
+                body_html: Some(&html),
+                unsubscribe_url: Some(&template.unsubscribe_link),
+            },
+            Some(mass.id),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+        mass_emails::Entity::update_many()
+            .col_expr(mass_emails::Column::LastUserId, Expr::value(user.id))
+            .col_expr(
+                mass_emails::Column::QueuedCount,
+                Expr::col(mass_emails::Column::QueuedCount).add(1),
+            )
+            .filter(mass_emails::Column::Id.eq(mass.id))
+            .exec(db)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    if (recipients.len() as u64) < batch_size {
+        mass_emails::Entity::update_many()
+            .col_expr(mass_emails::Column::Status, Expr::value(STATUS_DONE))
+            .col_expr(
+                mass_emails::Column::FinishedAt,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .filter(mass_emails::Column::Id.eq(mass.id))
+            .filter(mass_emails::Column::Status.eq(STATUS_SENDING))
+            .exec(db)
+            .await
+            .map_err(|e| e.to_string())?;
+        log::info!("Announcement {} has been queued in full", mass.id);
+    } else {
+        jobs::enqueue_at(
+            JobKind::MassEmailBatch,
+            json!({ "mass_email_id": mass.id }),
+            BATCH_MAX_ATTEMPTS,
+            Utc::now().naive_utc()
+                + chrono::Duration::seconds(config.mass_batch_interval_seconds.max(0)),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
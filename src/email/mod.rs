@@ -1,11 +1,32 @@
 /// Email sending functionality
 ///
-/// This module provides email sending capabilities using lettre with SMTP.
-/// Supports both real SMTP sending and mock mode for development/testing.
+/// This module provides email sending capabilities over SMTP, using lettre,
+/// or the HTTP APIs of SendGrid, Mailgun and Amazon SES for hosts that block
+/// outbound SMTP. `EMAIL_TRANSPORT` picks one. Mock mode logs emails instead
+/// of sending them, for development/testing.
+/// Emails are sent from the job queue, which retries failures and logs every
+/// attempt for `/admin/emails`.
+pub mod change;
+pub mod mailgun;
+pub mod mass;
+pub mod plaintext;
+pub mod queue;
+pub mod sendgrid;
+pub mod ses;
 pub mod smtp;
 pub mod templates;
+pub mod unsubscribe;
 
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
 use std::env;
+use std::time::Duration;
+
+/// Longest part of an API response kept in the delivery log
+const MAX_RESPONSE_LENGTH: usize = 500;
+
+/// How long an HTTP API request may take
+const API_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Email sending result
 pub type EmailResult<T> = Result<T, EmailError>;
@@ -19,6 +40,14 @@ pub enum EmailError {
     BuildError(lettre::error::Error),
     /// Email sending error
     SendError(lettre::transport::smtp::Error),
+    /// Request to an email API failed
+    HttpError(reqwest::Error),
+    /// An email API refused the email
+    ApiError { status: u16, message: String },
+    /// Email could not be queued
+    QueueError(sea_orm::DbErr),
+    /// Email template could not be rendered
+    TemplateError(askama::Error),
 }
 
 impl std::fmt::Display for EmailError {
@@ -27,12 +56,34 @@ impl std::fmt::Display for EmailError {
             EmailError::ConfigError(msg) => write!(f, "Email config error: {}", msg),
             EmailError::BuildError(e) => write!(f, "Email build error: {}", e),
             EmailError::SendError(e) => write!(f, "Email send error: {}", e),
+            EmailError::HttpError(e) => write!(f, "Email API request failed: {}", e),
+            EmailError::ApiError { status, message } => {
+                write!(f, "Email API error: {} {}", status, message)
+            }
+            EmailError::QueueError(e) => write!(f, "Email queue error: {}", e),
+            EmailError::TemplateError(e) => write!(f, "Email template error: {}", e),
         }
     }
 }
 
 impl std::error::Error for EmailError {}
 
+impl EmailError {
+    /// Whether sending again later might work. Bad addresses and permanent
+    /// SMTP rejections will fail the same way every time, as will API
+    /// requests refused for anything but rate limiting or a server error.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            EmailError::SendError(e) => !e.is_permanent(),
+            EmailError::HttpError(_) | EmailError::QueueError(_) => true,
+            EmailError::ApiError { status, .. } => *status == 429 || *status >= 500,
+            EmailError::ConfigError(_)
+            | EmailError::BuildError(_)
+            | EmailError::TemplateError(_) => false,
+        }
+    }
+}
+
 impl From<lettre::error::Error> for EmailError {
     fn from(e: lettre::error::Error) -> Self {
         EmailError::BuildError(e)
@@ -45,9 +96,29 @@ impl From<lettre::transport::smtp::Error> for EmailError {
     }
 }
 
+impl From<reqwest::Error> for EmailError {
+    fn from(e: reqwest::Error) -> Self {
+        EmailError::HttpError(e)
+    }
+}
+
+impl From<askama::Error> for EmailError {
+    fn from(e: askama::Error) -> Self {
+        EmailError::TemplateError(e)
+    }
+}
+
+impl From<sea_orm::DbErr> for EmailError {
+    fn from(e: sea_orm::DbErr) -> Self {
+        EmailError::QueueError(e)
+    }
+}
+
 /// Email configuration from environment variables
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct EmailConfig {
+    /// `smtp`, `sendgrid`, `mailgun` or `ses`
+    pub transport: String,
     pub smtp_host: String,
     pub smtp_port: u16,
     pub smtp_username: String,
@@ -56,12 +127,22 @@ pub struct EmailConfig {
     pub from_name: String,
     pub use_tls: bool,
     pub mock: bool,
+    pub sendgrid_api_key: String,
+    pub mailgun_api_key: String,
+    pub mailgun_domain: String,
+    /// `https://api.mailgun.net`, or `https://api.eu.mailgun.net` for
+    /// domains in Mailgun's EU region
+    pub mailgun_api_url: String,
+    pub ses_region: String,
+    pub ses_access_key_id: String,
+    pub ses_secret_access_key: String,
 }
 
 impl EmailConfig {
     /// Load email configuration from environment variables
     pub fn from_env() -> EmailResult<Self> {
         Ok(EmailConfig {
+            transport: env::var("EMAIL_TRANSPORT").unwrap_or_else(|_| "smtp".to_string()),
             smtp_host: env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string()),
             smtp_port: env::var("SMTP_PORT")
                 .unwrap_or_else(|_| "587".to_string())
@@ -81,17 +162,123 @@ impl EmailConfig {
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
                 .unwrap_or(false),
+            sendgrid_api_key: env::var("SENDGRID_API_KEY").unwrap_or_default(),
+            mailgun_api_key: env::var("MAILGUN_API_KEY").unwrap_or_default(),
+            mailgun_domain: env::var("MAILGUN_DOMAIN").unwrap_or_default(),
+            mailgun_api_url: env::var("MAILGUN_API_URL")
+                .unwrap_or_else(|_| "https://api.mailgun.net".to_string()),
+            ses_region: env::var("SES_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            ses_access_key_id: env::var("SES_ACCESS_KEY_ID").unwrap_or_default(),
+            ses_secret_access_key: env::var("SES_SECRET_ACCESS_KEY").unwrap_or_default(),
         })
     }
+
+    /// The transport `EMAIL_TRANSPORT` names, if its settings are complete
+    pub fn transport(&self) -> EmailResult<Box<dyn EmailTransport>> {
+        fn require(value: &str, name: &str) -> EmailResult<()> {
+            if value.is_empty() {
+                return Err(EmailError::ConfigError(format!("{} is not set", name)));
+            }
+            Ok(())
+        }
+
+        let config = self.clone();
+        let transport: Box<dyn EmailTransport> = match self.transport.as_str() {
+            "smtp" => Box::new(smtp::SmtpMailer::new(config)),
+            "sendgrid" => {
+                require(&self.sendgrid_api_key, "SENDGRID_API_KEY")?;
+                Box::new(sendgrid::SendGridMailer::new(config))
+            }
+            "mailgun" => {
+                require(&self.mailgun_api_key, "MAILGUN_API_KEY")?;
+                require(&self.mailgun_domain, "MAILGUN_DOMAIN")?;
+                Box::new(mailgun::MailgunMailer::new(config))
+            }
+            "ses" => {
+                require(&self.ses_access_key_id, "SES_ACCESS_KEY_ID")?;
+                require(&self.ses_secret_access_key, "SES_SECRET_ACCESS_KEY")?;
+                Box::new(ses::SesMailer::new(config))
+            }
+            other => {
+                return Err(EmailError::ConfigError(format!(
+                    "Unknown EMAIL_TRANSPORT {:?}",
+                    other
+                )))
+            }
+        };
+        Ok(transport)
+    }
+}
+
+/// An email ready to send
+#[derive(Clone, Copy, Debug)]
+pub struct OutgoingEmail<'a> {
+    pub to: &'a str,
+    pub subject: &'a str,
+    pub body_text: &'a str,
+    pub body_html: Option<&'a str>,
+    /// Link for one-click `List-Unsubscribe` headers
+    pub unsubscribe_url: Option<&'a str>,
 }
 
-/// Send an email
+/// A way of handing emails over for delivery.
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    /// Send an email, returning what the server answered for the log
+    async fn send(&self, email: &OutgoingEmail<'_>) -> EmailResult<String>;
+}
+
+/// Client shared by the HTTP API transports
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+        reqwest::Client::builder()
+            .timeout(API_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    });
+    &CLIENT
+}
+
+/// The status and body of an API response, or an error if it failed
+async fn api_response(response: reqwest::Response) -> EmailResult<String> {
+    let status = response.status().as_u16();
+    let body = response.text().await?;
+    let message: String = body.trim().chars().take(MAX_RESPONSE_LENGTH).collect();
+    if (200..300).contains(&status) {
+        Ok(format!("{} {}", status, message).trim_end().to_string())
+    } else {
+        Err(EmailError::ApiError { status, message })
+    }
+}
+
+/// Queue an email to be sent by the job queue. Emails with an
+/// `unsubscribe_url` carry one-click `List-Unsubscribe` headers.
 pub async fn send_email(
     to: &str,
     subject: &str,
     body_text: &str,
     body_html: Option<&str>,
+    unsubscribe_url: Option<&str>,
 ) -> EmailResult<()> {
+    let email = OutgoingEmail {
+        to,
+        subject,
+        body_text,
+        body_html,
+        unsubscribe_url,
+    };
+    queue::enqueue(&email, None).await?;
+    Ok(())
+}
+
+/// Send an email now, returning what the server answered
+pub async fn deliver(
+    to: &str,
+    subject: &str,
+    body_text: &str,
+    body_html: Option<&str>,
+    unsubscribe_url: Option<&str>,
+) -> EmailResult<String> {
     let config = EmailConfig::from_env()?;
 
     if config.mock {
@@ -100,8 +287,57 @@ pub async fn send_email(
         log::info!("  To: {}", to);
         log::info!("  Subject: {}", subject);
         log::info!("  Body: {}", body_text);
-        return Ok(());
+        return Ok("mock mode, logged instead of sent".to_string());
+    }
+
+    let email = OutgoingEmail {
+        to,
+        subject,
+        body_text,
+        body_html,
+        unsubscribe_url,
+    };
+    config.transport()?.send(&email).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_errors_retry_only_when_worth_it() {
+        let error = |status| EmailError::ApiError {
+            status,
+            message: String::new(),
+        };
+        assert!(error(429).is_transient());
+        assert!(error(503).is_transient());
+        assert!(!error(400).is_transient());
+        assert!(!error(401).is_transient());
     }
 
-    smtp::send_email(&config, to, subject, body_text, body_html).await
+    #[test]
+    fn test_transport_needs_its_settings() {
+        let config = EmailConfig {
+            transport: "mailgun".to_string(),
+            mailgun_api_key: "key".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.transport(),
+            Err(EmailError::ConfigError(_))
+        ));
+
+        let config = EmailConfig {
+            mailgun_domain: "mg.example.com".to_string(),
+            ..config
+        };
+        assert!(config.transport().is_ok());
+
+        let config = EmailConfig {
+            transport: "carrier-pigeon".to_string(),
+            ..Default::default()
+        };
+        assert!(config.transport().is_err());
+    }
 }
@@ -0,0 +1,166 @@
+//! Plain text alternatives for HTML emails.
+//!
+//! Every email carries a plain text part for clients that don't show HTML.
+//! It is made from the rendered HTML rather than kept as a second template:
+//! block elements become line breaks, list items get a bullet, and links keep
+//! their address after the link text unless the text already is the address.
+
+use scraper::{ElementRef, Html, Node};
+
+/// Builds the text, collapsing whitespace the way a browser would.
+#[derive(Default)]
+struct TextWriter {
+    text: String,
+    pending_space: bool,
+}
+
+impl TextWriter {
+    fn push_text(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.pending_space = true;
+                continue;
+            }
+            if self.pending_space && !self.text.is_empty() && !self.text.ends_with('\n') {
+                self.text.push(' ');
+            }
+            self.pending_space = false;
+            self.text.push(c);
+        }
+    }
+
+    /// Ends the current line, leaving `count` line breaks at most.
+    fn newlines(&mut self, count: usize) {
+        self.pending_space = false;
+        if self.text.is_empty() {
+            return;
+        }
+        let trimmed = self.text.trim_end_matches(' ').len();
+        self.text.truncate(trimmed);
+        let existing = self.text.len() - self.text.trim_end_matches('\n').len();
+        for _ in existing..count {
+            self.text.push('\n');
+        }
+    }
+
+    /// A `<br>`: always a new line, so two in a row leave a blank one.
+    fn line_break(&mut self) {
+        self.pending_space = false;
+        let trimmed = self.text.trim_end_matches(' ').len();
+        self.text.truncate(trimmed);
+        self.text.push('\n');
+    }
+
+    fn finish(self) -> String {
+        let mut text = self.text.trim().to_string();
+        text.push('\n');
+        text
+    }
+}
+
+fn text_of(element: ElementRef) -> String {
+    element.text().collect::<Vec<_>>().join(" ")
+}
+
+fn walk(element: ElementRef, out: &mut TextWriter) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_text(text),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_element(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_element(element: ElementRef, out: &mut TextWriter) {
+    match element.value().name() {
+        "head" | "title" | "style" | "script" => {}
+        "br" => out.line_break(),
+        "hr" => {
+            out.newlines(2);
+            out.push_text("---");
+            out.newlines(2);
+        }
+        "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "blockquote" | "ul" | "ol" | "table" => {
+            out.newlines(2);
+            walk(element, out);
+            out.newlines(2);
+        }
+        "div" | "tr" => {
+            out.newlines(1);
+            walk(element, out);
+            out.newlines(1);
+        }
+        "li" => {
+            out.newlines(1);
+            out.push_text("* ");
+            walk(element, out);
+            out.newlines(1);
+        }
+        "a" => {
+            walk(element, out);
+            if let Some(href) = element.value().attr("href") {
+                let text = text_of(element);
+                if !href.starts_with('#') && !href.starts_with("mailto:") && text.trim() != href {
+                    out.push_text(&format!(" ({})", href));
+                }
+            }
+        }
+        _ => walk(element, out),
+    }
+}
+
+/// Converts a rendered HTML email to plain text.
+pub fn html_to_text(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let mut out = TextWriter::default();
+    walk(document.root_element(), &mut out);
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks_and_whitespace() {
+        let html = r#"<html><head><title>Ignored</title><style>p { color: red; }</style></head>
+            <body>
+                <h2>Hello   there</h2>
+                <p>First
+                   paragraph.</p>
+                <div>One</div><div>Two</div>
+                <hr>
+                <p>Last &amp; final</p>
+            </body></html>"#;
+        assert_eq!(
+            html_to_text(html),
+            "Hello there\n\nFirst paragraph.\n\nOne\nTwo\n\n---\n\nLast & final\n"
+        );
+    }
+
+    #[test]
+    fn test_links_and_lists() {
+        let html = r#"<p><a href="https://example.com/reset/abc">Reset Password</a></p>
+            <p><a href="https://example.com/x">https://example.com/x</a></p>
+            <ul><li>One <a href="/n/1">note</a></li><li>Two</li></ul>"#;
+        assert_eq!(
+            html_to_text(html),
+            "Reset Password (https://example.com/reset/abc)\n\n\
+             https://example.com/x\n\n\
+             * One note (/n/1)\n* Two\n"
+        );
+    }
+
+    #[test]
+    fn test_line_breaks_are_kept() {
+        assert_eq!(
+            html_to_text("<p>line one<br/>line two<br/><br/>line four</p>"),
+            "line one\nline two\n\nline four\n"
+        );
+    }
+}
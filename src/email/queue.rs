@@ -0,0 +1,171 @@
+//! Queued sending of email.
+//!
+//! `send_email` records the message in `email_log` and queues a job to send
+//! it. The body only travels in the job payload, since it can hold password
+//! reset and verification tokens; the log keeps the recipient, the subject
+//! and what the SMTP server said to each attempt. Transient failures are
+//! retried by the job queue with growing delays.
+
+use super::{deliver, EmailError, OutgoingEmail};
+use crate::db::get_db_pool;
+use crate::jobs::{self, Job, JobKind};
+use crate::orm::{email_log, email_log_attempts};
+use chrono::Utc;
+use sea_orm::{entity::*, DbErr};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Longest SMTP response kept for an attempt
+const MAX_RESPONSE_LENGTH: usize = 1000;
+
+/// Longest recipient or subject kept in the log
+const MAX_LOG_FIELD_LENGTH: usize = 255;
+
+/// Payload of an `email.delivery` job.
+#[derive(Debug, Deserialize)]
+struct EmailJob {
+    email_id: i32,
+    to: String,
+    subject: String,
+    body_text: String,
+    body_html: Option<String>,
+    #[serde(default)]
+    unsubscribe_url: Option<String>,
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+/// Records an email in the log and queues a job to send it. Emails sent for
+/// an announcement are logged against it.
+pub async fn enqueue(
+    outgoing: &OutgoingEmail<'_>,
+    mass_email_id: Option<i32>,
+) -> Result<email_log::Model, DbErr> {
+    let email = email_log::ActiveModel {
+        recipient: Set(truncate(outgoing.to, MAX_LOG_FIELD_LENGTH)),
+        subject: Set(truncate(outgoing.subject, MAX_LOG_FIELD_LENGTH)),
+        status: Set("pending".to_owned()),
+        attempts: Set(0),
+        created_at: Set(Utc::now().naive_utc()),
+        mass_email_id: Set(mass_email_id),
+        ..Default::default()
+    }
+    .insert(get_db_pool())
+    .await?;
+
+    jobs::enqueue(
+        JobKind::EmailDelivery,
+        json!({
+            "email_id": email.id,
+            "to": outgoing.to,
+            "subject": outgoing.subject,
+            "body_text": outgoing.body_text,
+            "body_html": outgoing.body_html,
+            "unsubscribe_url": outgoing.unsubscribe_url,
+        }),
+        crate::app_config::email().max_attempts,
+    )
+    .await?;
+
+    Ok(email)
+}
+
+/// Status of an email after an attempt to send it.
+fn status_after(result: &Result<String, EmailError>, last_attempt: bool) -> &'static str {
+    match result {
+        Ok(_) => "sent",
+        Err(e) if e.is_transient() && !last_attempt => "retrying",
+        Err(_) => "failed",
+    }
+}
+
+/// Runs an `email.delivery` job. Transient errors make the job queue try
+/// again later; permanent ones mark the email failed straight away.
+pub async fn run_delivery_job(job: &Job) -> Result<(), String> {
+    let payload: EmailJob = serde_json::from_value(job.payload.clone())
+        .map_err(|e| format!("invalid email job payload: {}", e))?;
+
+    let Some(email) = email_log::Entity::find_by_id(payload.email_id)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        // Removed from the log; nothing to record the attempt against.
+        return Ok(());
+    };
+
+    let result = deliver(
+        &payload.to,
+        &payload.subject,
+        &payload.body_text,
+        payload.body_html.as_deref(),
+        payload.unsubscribe_url.as_deref(),
+    )
+    .await;
+    let now = Utc::now().naive_utc();
+
+    email_log_attempts::ActiveModel {
+        email_id: Set(email.id),
+        attempt: Set(job.attempts),
+        succeeded: Set(result.is_ok()),
+        response: Set(truncate(
+            &match &result {
+                Ok(response) => response.clone(),
+                Err(e) => e.to_string(),
+            },
+            MAX_RESPONSE_LENGTH,
+        )),
+        attempted_at: Set(now),
+        ..Default::default()
+    }
+    .insert(get_db_pool())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let status = status_after(&result, job.is_last_attempt());
+    email_log::ActiveModel {
+        id: Set(email.id),
+        status: Set(status.to_owned()),
+        attempts: Set(job.attempts),
+        last_attempt_at: Set(Some(now)),
+        sent_at: Set(result.is_ok().then_some(now)),
+        ..Default::default()
+    }
+    .update(get_db_pool())
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) if e.is_transient() => Err(e.to_string()),
+        Err(e) => {
+            log::error!(
+                "Cannot send email {} to {}: {}",
+                email.id,
+                email.recipient,
+                e
+            );
+            // Retrying cannot help, so let the job go; the log keeps the reason.
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_after() {
+        let transient = || Err(EmailError::QueueError(DbErr::Custom("down".to_owned())));
+        let permanent = || Err(EmailError::ConfigError("Invalid to address".to_owned()));
+
+        assert_eq!(status_after(&Ok("250 OK".to_owned()), false), "sent");
+        assert_eq!(status_after(&Ok("250 OK".to_owned()), true), "sent");
+        assert_eq!(status_after(&transient(), false), "retrying");
+        assert_eq!(status_after(&transient(), true), "failed");
+        assert_eq!(status_after(&permanent(), false), "failed");
+    }
+}
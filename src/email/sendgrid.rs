@@ -0,0 +1,109 @@
+//! Sending through SendGrid's v3 Mail Send API
+
+use super::{api_response, http_client, EmailConfig, EmailResult, EmailTransport, OutgoingEmail};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+const SEND_URL: &str = "https://api.sendgrid.com/v3/mail/send";
+
+pub struct SendGridMailer {
+    config: EmailConfig,
+}
+
+impl SendGridMailer {
+    pub fn new(config: EmailConfig) -> Self {
+        SendGridMailer { config }
+    }
+}
+
+/// The Mail Send request body for an email
+fn request_body(config: &EmailConfig, email: &OutgoingEmail<'_>) -> Value {
+    let mut content = vec![json!({ "type": "text/plain", "value": email.body_text })];
+    if let Some(html) = email.body_html {
+        content.push(json!({ "type": "text/html", "value": html }));
+    }
+
+    let mut body = json!({
+        "personalizations": [{ "to": [{ "email": email.to }] }],
+        "from": { "email": config.from_email, "name": config.from_name },
+        "subject": email.subject,
+        "content": content,
+    });
+    if let Some(url) = email.unsubscribe_url {
+        body["headers"] = json!({
+            "List-Unsubscribe": format!("<{}>", url),
+            "List-Unsubscribe-Post": "List-Unsubscribe=One-Click",
+        });
+    }
+    body
+}
+
+#[async_trait]
+impl EmailTransport for SendGridMailer {
+    async fn send(&self, email: &OutgoingEmail<'_>) -> EmailResult<String> {
+        let response = http_client()
+            .post(SEND_URL)
+            .bearer_auth(&self.config.sendgrid_api_key)
+            .json(&request_body(&self.config, email))
+            .send()
+            .await?;
+
+        // A successful send has an empty body; the message ID is a header.
+        let message_id = response
+            .headers()
+            .get("X-Message-Id")
+            .and_then(|id| id.to_str().ok())
+            .map(str::to_string);
+        let answer = api_response(response).await?;
+
+        log::info!("Email sent through SendGrid to: {}", email.to);
+
+        Ok(match message_id {
+            Some(id) => format!("{} {}", answer, id),
+            None => answer,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_body() {
+        let config = EmailConfig {
+            from_email: "noreply@example.com".to_string(),
+            from_name: "Example".to_string(),
+            ..Default::default()
+        };
+        let email = OutgoingEmail {
+            to: "alice@example.com",
+            subject: "Hello",
+            body_text: "Hi",
+            body_html: None,
+            unsubscribe_url: Some("https://example.com/u"),
+        };
+        assert_eq!(
+            request_body(&config, &email),
+            json!({
+                "personalizations": [{ "to": [{ "email": "alice@example.com" }] }],
+                "from": { "email": "noreply@example.com", "name": "Example" },
+                "subject": "Hello",
+                "content": [{ "type": "text/plain", "value": "Hi" }],
+                "headers": {
+                    "List-Unsubscribe": "<https://example.com/u>",
+                    "List-Unsubscribe-Post": "List-Unsubscribe=One-Click",
+                },
+            })
+        );
+
+        let email = OutgoingEmail {
+            body_html: Some("<p>Hi</p>"),
+            unsubscribe_url: None,
+            ..email
+        };
+        let body = request_body(&config, &email);
+        assert_eq!(body["content"][1]["type"], "text/html");
+        assert!(body.get("headers").is_none());
+    }
+}
@@ -0,0 +1,78 @@
+//! Sending through Amazon SES's v2 API.
+//!
+//! The email goes as a raw MIME message built the same way as for SMTP, so
+//! it carries exactly the same headers. Requests are signed with AWS
+//! Signature Version 4.
+
+use super::smtp::build_message;
+use super::{api_response, http_client, EmailConfig, EmailResult, EmailTransport, OutgoingEmail};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rusoto_core::credential::AwsCredentials;
+use rusoto_core::signature::SignedRequest;
+use rusoto_core::Region;
+use serde_json::json;
+
+const SEND_PATH: &str = "/v2/email/outbound-emails";
+
+pub struct SesMailer {
+    config: EmailConfig,
+}
+
+impl SesMailer {
+    pub fn new(config: EmailConfig) -> Self {
+        SesMailer { config }
+    }
+
+    fn hostname(&self) -> String {
+        format!("email.{}.amazonaws.com", self.config.ses_region)
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SesMailer {
+    async fn send(&self, email: &OutgoingEmail<'_>) -> EmailResult<String> {
+        let message = build_message(&self.config, email)?;
+        let payload = json!({
+            "Destination": { "ToAddresses": [email.to] },
+            "Content": { "Raw": { "Data": BASE64.encode(message.formatted()) } },
+        })
+        .to_string();
+
+        let hostname = self.hostname();
+        let region = Region::Custom {
+            name: self.config.ses_region.clone(),
+            endpoint: format!("https://{}", hostname),
+        };
+        let mut request = SignedRequest::new("POST", "ses", &region, SEND_PATH);
+        request.set_hostname(Some(hostname.clone()));
+        request.set_content_type("application/json".to_string());
+        request.set_payload(Some(payload.clone()));
+        request.sign(&AwsCredentials::new(
+            &self.config.ses_access_key_id,
+            &self.config.ses_secret_access_key,
+            None,
+            None,
+        ));
+
+        // reqwest sets the host and length itself
+        let mut builder = http_client()
+            .post(format!("https://{}{}", hostname, SEND_PATH))
+            .body(payload);
+        for (name, values) in request.headers() {
+            if name == "host" || name == "content-length" {
+                continue;
+            }
+            for value in values {
+                builder = builder.header(name.as_str(), value.as_slice());
+            }
+        }
+
+        let answer = api_response(builder.send().await?).await?;
+
+        log::info!("Email sent through SES to: {}", email.to);
+
+        Ok(answer)
+    }
+}
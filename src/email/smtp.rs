@@ -1,38 +1,82 @@
 /// SMTP email sending implementation
-use super::{EmailConfig, EmailError, EmailResult};
-use lettre::message::{header::ContentType, Mailbox, MultiPart, SinglePart};
+use super::{EmailConfig, EmailError, EmailResult, EmailTransport, OutgoingEmail};
+use async_trait::async_trait;
+use lettre::message::header::{ContentType, Header, HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart, SinglePart};
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::{Message, SmtpTransport, Transport};
 
-/// Send an email via SMTP
-pub async fn send_email(
-    config: &EmailConfig,
-    to: &str,
-    subject: &str,
-    body_text: &str,
-    body_html: Option<&str>,
-) -> EmailResult<()> {
+/// `List-Unsubscribe` header (RFC 2369) with a single HTTPS link
+#[derive(Clone)]
+struct ListUnsubscribe(String);
+
+impl Header for ListUnsubscribe {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("List-Unsubscribe")
+    }
+
+    fn parse(s: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self(
+            s.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string(),
+        ))
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), format!("<{}>", self.0))
+    }
+}
+
+/// `List-Unsubscribe-Post` header (RFC 8058), saying a POST to the
+/// `List-Unsubscribe` link unsubscribes in one click
+#[derive(Clone)]
+struct ListUnsubscribePost;
+
+impl Header for ListUnsubscribePost {
+    fn name() -> HeaderName {
+        HeaderName::new_from_ascii_str("List-Unsubscribe-Post")
+    }
+
+    fn parse(_: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self)
+    }
+
+    fn display(&self) -> HeaderValue {
+        HeaderValue::new(Self::name(), "List-Unsubscribe=One-Click".to_string())
+    }
+}
+
+/// Build the MIME message for an email, with a plain text part and an HTML
+/// alternative when there is one
+pub fn build_message(config: &EmailConfig, email: &OutgoingEmail<'_>) -> EmailResult<Message> {
     // Parse email addresses
     let from: Mailbox = format!("{} <{}>", config.from_name, config.from_email)
         .parse()
         .map_err(|e| EmailError::ConfigError(format!("Invalid from address: {}", e)))?;
 
-    let to_string = to.to_string();
-    let to: Mailbox = to
+    let to: Mailbox = email
+        .to
         .parse()
         .map_err(|e| EmailError::ConfigError(format!("Invalid to address: {}", e)))?;
 
     // Build the email
-    let email_builder = Message::builder().from(from).to(to).subject(subject);
+    let mut email_builder = Message::builder().from(from).to(to).subject(email.subject);
+    if let Some(url) = email.unsubscribe_url {
+        email_builder = email_builder
+            .header(ListUnsubscribe(url.to_string()))
+            .header(ListUnsubscribePost);
+    }
 
     // Add body (either plain text only, or multipart with HTML)
-    let email = if let Some(html) = body_html {
+    let message = if let Some(html) = email.body_html {
         email_builder.multipart(
             MultiPart::alternative()
                 .singlepart(
                     SinglePart::builder()
                         .header(ContentType::TEXT_PLAIN)
-                        .body(body_text.to_string()),
+                        .body(email.body_text.to_string()),
                 )
                 .singlepart(
                     SinglePart::builder()
@@ -43,28 +87,88 @@ pub async fn send_email(
     } else {
         email_builder
             .header(ContentType::TEXT_PLAIN)
-            .body(body_text.to_string())?
+            .body(email.body_text.to_string())?
     };
 
-    // Create SMTP transport
-    let creds = Credentials::new(config.smtp_username.clone(), config.smtp_password.clone());
+    Ok(message)
+}
 
-    let mailer = if config.use_tls {
-        SmtpTransport::relay(&config.smtp_host)?
-            .credentials(creds)
-            .port(config.smtp_port)
-            .build()
-    } else {
-        SmtpTransport::builder_dangerous(&config.smtp_host)
-            .credentials(creds)
-            .port(config.smtp_port)
-            .build()
-    };
+/// Sends through the configured SMTP server
+pub struct SmtpMailer {
+    config: EmailConfig,
+}
+
+impl SmtpMailer {
+    pub fn new(config: EmailConfig) -> Self {
+        SmtpMailer { config }
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpMailer {
+    async fn send(&self, email: &OutgoingEmail<'_>) -> EmailResult<String> {
+        let config = &self.config;
+        let message = build_message(config, email)?;
+
+        // Create SMTP transport
+        let creds = Credentials::new(config.smtp_username.clone(), config.smtp_password.clone());
+
+        let mailer = if config.use_tls {
+            SmtpTransport::relay(&config.smtp_host)?
+                .credentials(creds)
+                .port(config.smtp_port)
+                .build()
+        } else {
+            SmtpTransport::builder_dangerous(&config.smtp_host)
+                .credentials(creds)
+                .port(config.smtp_port)
+                .build()
+        };
+
+        // Send the email
+        let response = mailer.send(&message)?;
+
+        log::info!("Email sent successfully to: {}", email.to);
+
+        Ok(format!(
+            "{} {}",
+            response.code(),
+            response.message().collect::<Vec<_>>().join(" ")
+        ))
+    }
+}
 
-    // Send the email
-    mailer.send(&email)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    log::info!("Email sent successfully to: {}", to_string);
+    #[test]
+    fn test_build_message_headers() {
+        let config = EmailConfig {
+            from_email: "noreply@example.com".to_string(),
+            from_name: "Example".to_string(),
+            ..Default::default()
+        };
+        let email = OutgoingEmail {
+            to: "alice@example.com",
+            subject: "Hello",
+            body_text: "Hi",
+            body_html: Some("<p>Hi</p>"),
+            unsubscribe_url: Some("https://example.com/email/unsubscribe?token=t"),
+        };
+        let raw = String::from_utf8(build_message(&config, &email).unwrap().formatted()).unwrap();
+        assert!(raw.contains("To: alice@example.com"));
+        assert!(raw.contains("List-Unsubscribe: <https://example.com/email/unsubscribe?token=t>"));
+        assert!(raw.contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click"));
+        assert!(raw.contains("multipart/alternative"));
 
-    Ok(())
+        let email = OutgoingEmail {
+            to: "not an address",
+            ..email
+        };
+        assert!(matches!(
+            build_message(&config, &email),
+            Err(EmailError::ConfigError(_))
+        ));
+    }
 }
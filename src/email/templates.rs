@@ -1,7 +1,89 @@
 /// Email template functions
 ///
-/// This module provides functions to generate common email templates.
-use super::{send_email, EmailResult};
+/// This module provides functions to send common emails. Bodies are Askama
+/// templates under `templates/email/` sharing `email/layout.html`, which adds
+/// the site's name from settings; the plain text part is made from the HTML.
+use super::{plaintext, send_email, unsubscribe, EmailResult};
+use crate::db::get_db_pool;
+use crate::orm::settings;
+use askama::Template;
+use sea_orm::{entity::*, query::*};
+
+/// Longest post preview included in a notification email, in characters
+const MAX_PREVIEW_LENGTH: usize = 500;
+
+/// Site details shown in the header and footer of every email
+pub struct Branding {
+    pub site_name: String,
+    pub site_description: String,
+    pub base_url: String,
+}
+
+impl Branding {
+    /// Reads the site name and description from settings, keeping the
+    /// defaults if they can't be read.
+    pub async fn load(base_url: &str) -> Self {
+        let mut branding = Branding {
+            site_name: "Dumpster".to_string(),
+            site_description: String::new(),
+            base_url: base_url.to_string(),
+        };
+
+        match settings::Entity::find()
+            .filter(settings::Column::Key.is_in(["site_name", "site_description"]))
+            .all(get_db_pool())
+            .await
+        {
+            Ok(rows) => {
+                for row in rows {
+                    match row.key.as_str() {
+                        "site_name" => branding.site_name = row.value,
+                        "site_description" => branding.site_description = row.value,
+                        _ => {}
+                    }
+                }
+            }
+            Err(e) => log::warn!("Failed to load site settings for email: {}", e),
+        }
+
+        branding
+    }
+}
+
+/// Render a template and queue it with a plain text part made from the HTML
+async fn send_template(
+    to: &str,
+    subject: &str,
+    template: &impl Template,
+    unsubscribe_url: Option<&str>,
+) -> EmailResult<()> {
+    let body_html = template.render()?;
+    let body_text = plaintext::html_to_text(&body_html);
+    send_email(to, subject, &body_text, Some(&body_html), unsubscribe_url).await
+}
+
+/// Shorten a post for a notification email
+fn preview(post: &str) -> String {
+    if post.chars().count() > MAX_PREVIEW_LENGTH {
+        let mut preview: String = post.chars().take(MAX_PREVIEW_LENGTH).collect();
+        preview.push_str("...");
+        preview
+    } else {
+        post.to_string()
+    }
+}
+
+pub(super) fn preferences_link(base_url: &str) -> String {
+    format!("{}/account/preferences/notifications", base_url)
+}
+
+#[derive(Template)]
+#[template(path = "email/password_reset.html")]
+struct PasswordResetEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    reset_link: String,
+}
 
 /// Send a password reset email
 pub async fn send_password_reset_email(
@@ -10,60 +92,22 @@ pub async fn send_password_reset_email(
     reset_token: &str,
     base_url: &str,
 ) -> EmailResult<()> {
-    let reset_link = format!("{}/password-reset/{}", base_url, reset_token);
-
-    let body_text = format!(
-        r#"Hello {},
-
-You have requested to reset your password.
-
-Click the link below to reset your password:
-{}
-
-This link will expire in 1 hour.
-
-If you did not request a password reset, please ignore this email.
-
----
-Dumpster Forum
-"#,
-        username, reset_link
-    );
+    let branding = Branding::load(base_url).await;
+    let template = PasswordResetEmail {
+        branding: &branding,
+        username,
+        reset_link: format!("{}/password-reset/{}", base_url, reset_token),
+    };
 
-    let body_html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>Password Reset</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h2>Password Reset Request</h2>
-        <p>Hello <strong>{}</strong>,</p>
-        <p>You have requested to reset your password.</p>
-        <p>Click the button below to reset your password:</p>
-        <p style="margin: 30px 0;">
-            <a href="{}"
-               style="background-color: #007bff; color: white; padding: 12px 24px;
-                      text-decoration: none; border-radius: 4px; display: inline-block;">
-                Reset Password
-            </a>
-        </p>
-        <p>Or copy and paste this link into your browser:</p>
-        <p style="word-break: break-all; color: #007bff;">{}</p>
-        <p><strong>This link will expire in 1 hour.</strong></p>
-        <hr style="margin: 30px 0; border: none; border-top: 1px solid #ddd;">
-        <p style="color: #666; font-size: 0.9em;">
-            If you did not request a password reset, please ignore this email.
-        </p>
-    </div>
-</body>
-</html>"#,
-        username, reset_link, reset_link
-    );
+    send_template(to, "Password Reset Request", &template, None).await
+}
 
-    send_email(to, "Password Reset Request", &body_text, Some(&body_html)).await
+#[derive(Template)]
+#[template(path = "email/verification.html")]
+struct VerificationEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    verification_link: String,
 }
 
 /// Send an email verification email
@@ -73,111 +117,105 @@ pub async fn send_verification_email(
     verification_token: &str,
     base_url: &str,
 ) -> EmailResult<()> {
-    let verification_link = format!("{}/verify-email/{}", base_url, verification_token);
-
-    let body_text = format!(
-        r#"Hello {},
-
-Thank you for registering!
-
-Please verify your email address by clicking the link below:
-{}
-
-This link will expire in 24 hours.
-
----
-Dumpster Forum
-"#,
-        username, verification_link
-    );
+    let branding = Branding::load(base_url).await;
+    let template = VerificationEmail {
+        branding: &branding,
+        username,
+        verification_link: format!("{}/verify-email/{}", base_url, verification_token),
+    };
 
-    let body_html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>Email Verification</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h2>Welcome to Dumpster Forum!</h2>
-        <p>Hello <strong>{}</strong>,</p>
-        <p>Thank you for registering. Please verify your email address to complete your registration.</p>
-        <p style="margin: 30px 0;">
-            <a href="{}"
-               style="background-color: #28a745; color: white; padding: 12px 24px;
-                      text-decoration: none; border-radius: 4px; display: inline-block;">
-                Verify Email Address
-            </a>
-        </p>
-        <p>Or copy and paste this link into your browser:</p>
-        <p style="word-break: break-all; color: #28a745;">{}</p>
-        <p><strong>This link will expire in 24 hours.</strong></p>
-        <hr style="margin: 30px 0; border: none; border-top: 1px solid #ddd;">
-        <p style="color: #666; font-size: 0.9em;">
-            If you did not create an account, please ignore this email.
-        </p>
-    </div>
-</body>
-</html>"#,
-        username, verification_link, verification_link
-    );
+    send_template(to, "Verify Your Email Address", &template, None).await
+}
 
-    send_email(
-        to,
-        "Verify Your Email Address",
-        &body_text,
-        Some(&body_html),
-    )
-    .await
+#[derive(Template)]
+#[template(path = "email/welcome.html")]
+struct WelcomeEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
 }
 
 /// Send a welcome email after verification
 pub async fn send_welcome_email(to: &str, username: &str) -> EmailResult<()> {
-    let body_text = format!(
-        r#"Hello {},
+    let branding = Branding::load(&crate::notifications::dispatcher::get_base_url()).await;
+    let subject = format!("Welcome to {}!", branding.site_name);
+    let template = WelcomeEmail {
+        branding: &branding,
+        username,
+    };
 
-Welcome to Dumpster Forum!
+    send_template(to, &subject, &template, None).await
+}
 
-Your email has been verified and your account is now fully activated.
+#[derive(Template)]
+#[template(path = "email/email_change_verify.html")]
+struct EmailChangeVerifyEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    confirm_link: String,
+}
 
-You can now log in and start participating in discussions.
+/// Send the link confirming a change of email address to the new address
+pub async fn send_email_change_verification_email(
+    to: &str,
+    username: &str,
+    confirm_token: &str,
+    base_url: &str,
+) -> EmailResult<()> {
+    let branding = Branding::load(base_url).await;
+    let template = EmailChangeVerifyEmail {
+        branding: &branding,
+        username,
+        confirm_link: format!("{}/account/email/confirm/{}", base_url, confirm_token),
+    };
 
----
-Dumpster Forum
-"#,
-        username
-    );
+    send_template(to, "Confirm Your New Email Address", &template, None).await
+}
 
-    let body_html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>Welcome!</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h2>Welcome to Dumpster Forum!</h2>
-        <p>Hello <strong>{}</strong>,</p>
-        <p>Your email has been verified and your account is now fully activated.</p>
-        <p>You can now log in and start participating in discussions.</p>
-        <hr style="margin: 30px 0; border: none; border-top: 1px solid #ddd;">
-        <p style="color: #666; font-size: 0.9em;">
-            Thank you for joining our community!
-        </p>
-    </div>
-</body>
-</html>"#,
-        username
-    );
+#[derive(Template)]
+#[template(path = "email/email_change_notice.html")]
+struct EmailChangeNoticeEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    new_email: &'a str,
+    revert_link: String,
+}
+
+/// Tell the old address about a change of email address, with a link to
+/// cancel or undo it
+pub async fn send_email_change_notice_email(
+    to: &str,
+    username: &str,
+    new_email: &str,
+    revert_token: &str,
+    base_url: &str,
+) -> EmailResult<()> {
+    let branding = Branding::load(base_url).await;
+    let template = EmailChangeNoticeEmail {
+        branding: &branding,
+        username,
+        new_email,
+        revert_link: format!("{}/account/email/revert/{}", base_url, revert_token),
+    };
+
+    send_template(to, "Email Address Change Requested", &template, None).await
+}
 
-    send_email(to, "Welcome to Dumpster Forum!", &body_text, Some(&body_html)).await
+#[derive(Template)]
+#[template(path = "email/thread_reply.html")]
+struct ThreadReplyEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    poster: &'a str,
+    thread_title: &'a str,
+    preview: String,
+    link: String,
+    unsubscribe_link: String,
 }
 
 /// Send a thread reply notification email
 pub async fn send_thread_reply_email(
     to: &str,
+    user_id: i32,
     recipient_username: &str,
     thread_title: &str,
     thread_id: i32,
@@ -185,76 +223,84 @@ pub async fn send_thread_reply_email(
     post_preview: &str,
     base_url: &str,
 ) -> EmailResult<()> {
-    let thread_link = format!("{}/threads/{}", base_url, thread_id);
-
-    // Truncate preview to 500 chars
-    let preview = if post_preview.len() > 500 {
-        format!("{}...", &post_preview[..500])
-    } else {
-        post_preview.to_string()
+    let branding = Branding::load(base_url).await;
+    let unsubscribe_url = unsubscribe::url(base_url, user_id, "thread_watch");
+    let template = ThreadReplyEmail {
+        branding: &branding,
+        username: recipient_username,
+        poster: poster_username,
+        thread_title,
+        preview: preview(post_preview),
+        link: format!("{}/threads/{}", base_url, thread_id),
+        unsubscribe_link: unsubscribe_url.clone(),
     };
 
-    let body_text = format!(
-        r#"Hello {},
-
-{} has replied to a thread you're watching:
-
-"{}"
-
----
-{}
----
-
-View the thread: {}
+    let subject = format!("Re: {}", thread_title);
+    send_template(to, &subject, &template, Some(&unsubscribe_url)).await
+}
 
-To stop receiving these emails, visit the thread and disable email notifications.
+#[derive(Template)]
+#[template(path = "email/new_thread.html")]
+struct NewThreadEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    poster: &'a str,
+    forum_name: &'a str,
+    forum_link: String,
+    thread_title: &'a str,
+    preview: String,
+    link: String,
+    unsubscribe_link: String,
+}
 
----
-Dumpster Forum
-"#,
-        recipient_username, poster_username, thread_title, preview, thread_link
-    );
+/// Send a new thread notification email to forum watchers
+pub async fn send_new_thread_email(
+    to: &str,
+    user_id: i32,
+    recipient_username: &str,
+    forum_name: &str,
+    forum_id: i32,
+    thread_title: &str,
+    thread_id: i32,
+    poster_username: &str,
+    post_preview: &str,
+    base_url: &str,
+) -> EmailResult<()> {
+    let branding = Branding::load(base_url).await;
+    let unsubscribe_url = unsubscribe::url(base_url, user_id, "forum_watch");
+    let template = NewThreadEmail {
+        branding: &branding,
+        username: recipient_username,
+        poster: poster_username,
+        forum_name,
+        forum_link: format!("{}/forums/{}/", base_url, forum_id),
+        thread_title,
+        preview: preview(post_preview),
+        link: format!("{}/threads/{}", base_url, thread_id),
+        unsubscribe_link: unsubscribe_url.clone(),
+    };
 
-    let body_html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>New Reply</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h2>New Reply in Watched Thread</h2>
-        <p>Hello <strong>{}</strong>,</p>
-        <p><strong>{}</strong> has replied to a thread you're watching:</p>
-        <h3 style="color: #007bff;">{}</h3>
-        <div style="background: #f8f9fa; border-left: 4px solid #007bff; padding: 15px; margin: 20px 0;">
-            <p style="margin: 0; white-space: pre-wrap;">{}</p>
-        </div>
-        <p style="margin: 30px 0;">
-            <a href="{}"
-               style="background-color: #007bff; color: white; padding: 12px 24px;
-                      text-decoration: none; border-radius: 4px; display: inline-block;">
-                View Thread
-            </a>
-        </p>
-        <hr style="margin: 30px 0; border: none; border-top: 1px solid #ddd;">
-        <p style="color: #666; font-size: 0.9em;">
-            To stop receiving these emails, visit the thread and disable email notifications.
-        </p>
-    </div>
-</body>
-</html>"#,
-        recipient_username, poster_username, thread_title, preview, thread_link
-    );
+    let subject = format!("New thread in {}: {}", forum_name, thread_title);
+    send_template(to, &subject, &template, Some(&unsubscribe_url)).await
+}
 
-    let subject = format!("Re: {}", thread_title);
-    send_email(to, &subject, &body_text, Some(&body_html)).await
+#[derive(Template)]
+#[template(path = "email/mention.html")]
+struct MentionEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    poster: &'a str,
+    thread_title: &'a str,
+    preview: String,
+    link: String,
+    preferences_link: String,
+    unsubscribe_link: String,
 }
 
 /// Send a mention notification email
 pub async fn send_mention_email(
     to: &str,
+    user_id: i32,
     recipient_username: &str,
     mentioner_username: &str,
     thread_title: &str,
@@ -263,76 +309,40 @@ pub async fn send_mention_email(
     post_preview: &str,
     base_url: &str,
 ) -> EmailResult<()> {
-    let post_link = format!("{}/threads/{}#post-{}", base_url, thread_id, post_id);
-
-    // Truncate preview to 500 chars
-    let preview = if post_preview.len() > 500 {
-        format!("{}...", &post_preview[..500])
-    } else {
-        post_preview.to_string()
+    let branding = Branding::load(base_url).await;
+    let unsubscribe_url = unsubscribe::url(base_url, user_id, "mention");
+    let template = MentionEmail {
+        branding: &branding,
+        username: recipient_username,
+        poster: mentioner_username,
+        thread_title,
+        preview: preview(post_preview),
+        link: format!("{}/threads/{}#post-{}", base_url, thread_id, post_id),
+        preferences_link: preferences_link(base_url),
+        unsubscribe_link: unsubscribe_url.clone(),
     };
 
-    let body_text = format!(
-        r#"Hello {},
-
-{} mentioned you in a post:
-
-"{}"
-
----
-{}
----
-
-View the post: {}
-
-To stop receiving these emails, update your notification preferences in your account settings.
-
----
-Dumpster Forum
-"#,
-        recipient_username, mentioner_username, thread_title, preview, post_link
-    );
-
-    let body_html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>You were mentioned</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h2>You were mentioned</h2>
-        <p>Hello <strong>{}</strong>,</p>
-        <p><strong>{}</strong> mentioned you in:</p>
-        <h3 style="color: #007bff;">{}</h3>
-        <div style="background: #f8f9fa; border-left: 4px solid #17a2b8; padding: 15px; margin: 20px 0;">
-            <p style="margin: 0; white-space: pre-wrap;">{}</p>
-        </div>
-        <p style="margin: 30px 0;">
-            <a href="{}"
-               style="background-color: #17a2b8; color: white; padding: 12px 24px;
-                      text-decoration: none; border-radius: 4px; display: inline-block;">
-                View Post
-            </a>
-        </p>
-        <hr style="margin: 30px 0; border: none; border-top: 1px solid #ddd;">
-        <p style="color: #666; font-size: 0.9em;">
-            To stop receiving these emails, update your notification preferences in your account settings.
-        </p>
-    </div>
-</body>
-</html>"#,
-        recipient_username, mentioner_username, thread_title, preview, post_link
-    );
-
     let subject = format!("{} mentioned you in: {}", mentioner_username, thread_title);
-    send_email(to, &subject, &body_text, Some(&body_html)).await
+    send_template(to, &subject, &template, Some(&unsubscribe_url)).await
+}
+
+#[derive(Template)]
+#[template(path = "email/author_reply.html")]
+struct AuthorReplyEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    poster: &'a str,
+    thread_title: &'a str,
+    preview: String,
+    link: String,
+    preferences_link: String,
+    unsubscribe_link: String,
 }
 
 /// Send a thread author reply notification email (for thread owner, not watchers)
 pub async fn send_author_reply_email(
     to: &str,
+    user_id: i32,
     recipient_username: &str,
     replier_username: &str,
     thread_title: &str,
@@ -341,76 +351,40 @@ pub async fn send_author_reply_email(
     post_preview: &str,
     base_url: &str,
 ) -> EmailResult<()> {
-    let post_link = format!("{}/threads/{}#post-{}", base_url, thread_id, post_id);
-
-    // Truncate preview to 500 chars
-    let preview = if post_preview.len() > 500 {
-        format!("{}...", &post_preview[..500])
-    } else {
-        post_preview.to_string()
+    let branding = Branding::load(base_url).await;
+    let unsubscribe_url = unsubscribe::url(base_url, user_id, "reply");
+    let template = AuthorReplyEmail {
+        branding: &branding,
+        username: recipient_username,
+        poster: replier_username,
+        thread_title,
+        preview: preview(post_preview),
+        link: format!("{}/threads/{}#post-{}", base_url, thread_id, post_id),
+        preferences_link: preferences_link(base_url),
+        unsubscribe_link: unsubscribe_url.clone(),
     };
 
-    let body_text = format!(
-        r#"Hello {},
-
-{} replied to your thread:
-
-"{}"
-
----
-{}
----
-
-View the reply: {}
-
-To stop receiving these emails, update your notification preferences in your account settings.
-
----
-Dumpster Forum
-"#,
-        recipient_username, replier_username, thread_title, preview, post_link
-    );
-
-    let body_html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>New Reply to Your Thread</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h2>New Reply to Your Thread</h2>
-        <p>Hello <strong>{}</strong>,</p>
-        <p><strong>{}</strong> replied to your thread:</p>
-        <h3 style="color: #007bff;">{}</h3>
-        <div style="background: #f8f9fa; border-left: 4px solid #28a745; padding: 15px; margin: 20px 0;">
-            <p style="margin: 0; white-space: pre-wrap;">{}</p>
-        </div>
-        <p style="margin: 30px 0;">
-            <a href="{}"
-               style="background-color: #28a745; color: white; padding: 12px 24px;
-                      text-decoration: none; border-radius: 4px; display: inline-block;">
-                View Reply
-            </a>
-        </p>
-        <hr style="margin: 30px 0; border: none; border-top: 1px solid #ddd;">
-        <p style="color: #666; font-size: 0.9em;">
-            To stop receiving these emails, update your notification preferences in your account settings.
-        </p>
-    </div>
-</body>
-</html>"#,
-        recipient_username, replier_username, thread_title, preview, post_link
-    );
-
     let subject = format!("Re: {}", thread_title);
-    send_email(to, &subject, &body_text, Some(&body_html)).await
+    send_template(to, &subject, &template, Some(&unsubscribe_url)).await
+}
+
+#[derive(Template)]
+#[template(path = "email/quote.html")]
+struct QuoteEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    poster: &'a str,
+    thread_title: &'a str,
+    preview: String,
+    link: String,
+    preferences_link: String,
+    unsubscribe_link: String,
 }
 
 /// Send a quote notification email
 pub async fn send_quote_email(
     to: &str,
+    user_id: i32,
     recipient_username: &str,
     quoter_username: &str,
     thread_title: &str,
@@ -419,69 +393,242 @@ pub async fn send_quote_email(
     post_preview: &str,
     base_url: &str,
 ) -> EmailResult<()> {
-    let post_link = format!("{}/threads/{}#post-{}", base_url, thread_id, post_id);
-
-    // Truncate preview to 500 chars
-    let preview = if post_preview.len() > 500 {
-        format!("{}...", &post_preview[..500])
-    } else {
-        post_preview.to_string()
+    let branding = Branding::load(base_url).await;
+    let unsubscribe_url = unsubscribe::url(base_url, user_id, "quote");
+    let template = QuoteEmail {
+        branding: &branding,
+        username: recipient_username,
+        poster: quoter_username,
+        thread_title,
+        preview: preview(post_preview),
+        link: format!("{}/threads/{}#post-{}", base_url, thread_id, post_id),
+        preferences_link: preferences_link(base_url),
+        unsubscribe_link: unsubscribe_url.clone(),
     };
 
-    let body_text = format!(
-        r#"Hello {},
-
-{} quoted you in a post:
+    let subject = format!("{} quoted you in: {}", quoter_username, thread_title);
+    send_template(to, &subject, &template, Some(&unsubscribe_url)).await
+}
 
-"{}"
+/// A single notification line in a digest email
+pub struct DigestEntry {
+    pub title: String,
+    pub message: String,
+    pub url: Option<String>,
+    /// Already formatted in the recipient's local time
+    pub time: String,
+}
 
----
-{}
----
+#[derive(Template)]
+#[template(path = "email/digest.html")]
+struct DigestEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    period: &'a str,
+    entries: &'a [DigestEntry],
+    remaining: usize,
+    total: usize,
+    notifications_link: String,
+    preferences_link: String,
+    unsubscribe_link: String,
+}
 
-View the post: {}
+impl DigestEmail<'_> {
+    /// Absolute link for an entry, or the notifications page if it has none
+    fn link(&self, entry: &DigestEntry) -> String {
+        match &entry.url {
+            Some(url) if url.starts_with('/') => format!("{}{}", self.branding.base_url, url),
+            Some(url) => url.clone(),
+            None => self.notifications_link.clone(),
+        }
+    }
+}
 
-To stop receiving these emails, update your notification preferences in your account settings.
+/// Send a digest summarising notifications the user hasn't seen yet
+pub async fn send_notification_digest_email(
+    to: &str,
+    user_id: i32,
+    recipient_username: &str,
+    period: &str,
+    entries: &[DigestEntry],
+    remaining: usize,
+    base_url: &str,
+) -> EmailResult<()> {
+    let branding = Branding::load(base_url).await;
+    let unsubscribe_url = unsubscribe::url(base_url, user_id, unsubscribe::ALL);
+    let total = entries.len() + remaining;
+    let template = DigestEmail {
+        branding: &branding,
+        username: recipient_username,
+        period,
+        entries,
+        remaining,
+        total,
+        notifications_link: format!("{}/notifications", base_url),
+        preferences_link: preferences_link(base_url),
+        unsubscribe_link: unsubscribe_url.clone(),
+    };
 
----
-Dumpster Forum
-"#,
-        recipient_username, quoter_username, thread_title, preview, post_link
+    let subject = format!(
+        "Your {} digest: {} new notification{}",
+        period,
+        total,
+        if total == 1 { "" } else { "s" }
     );
+    send_template(to, &subject, &template, Some(&unsubscribe_url)).await
+}
 
-    let body_html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <title>You were quoted</title>
-</head>
-<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
-    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
-        <h2>You were quoted</h2>
-        <p>Hello <strong>{}</strong>,</p>
-        <p><strong>{}</strong> quoted your post in:</p>
-        <h3 style="color: #007bff;">{}</h3>
-        <div style="background: #f8f9fa; border-left: 4px solid #6f42c1; padding: 15px; margin: 20px 0;">
-            <p style="margin: 0; white-space: pre-wrap;">{}</p>
-        </div>
-        <p style="margin: 30px 0;">
-            <a href="{}"
-               style="background-color: #6f42c1; color: white; padding: 12px 24px;
-                      text-decoration: none; border-radius: 4px; display: inline-block;">
-                View Post
-            </a>
-        </p>
-        <hr style="margin: 30px 0; border: none; border-top: 1px solid #ddd;">
-        <p style="color: #666; font-size: 0.9em;">
-            To stop receiving these emails, update your notification preferences in your account settings.
-        </p>
-    </div>
-</body>
-</html>"#,
-        recipient_username, quoter_username, thread_title, preview, post_link
-    );
+#[derive(Template)]
+#[template(path = "email/warning.html")]
+struct WarningEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    reason: &'a str,
+    points: i32,
+    total_points: i32,
+    /// Already formatted
+    expires: Option<String>,
+}
 
-    let subject = format!("{} quoted you in: {}", quoter_username, thread_title);
-    send_email(to, &subject, &body_text, Some(&body_html)).await
+/// Send a notice of a moderator's warning to the warned user
+pub async fn send_warning_email(
+    to: &str,
+    username: &str,
+    reason: &str,
+    points: i32,
+    total_points: i32,
+    expires_at: Option<chrono::NaiveDateTime>,
+    base_url: &str,
+) -> EmailResult<()> {
+    let branding = Branding::load(base_url).await;
+    let subject = format!("A warning on your {} account", branding.site_name);
+    let template = WarningEmail {
+        branding: &branding,
+        username,
+        reason,
+        points,
+        total_points,
+        expires: expires_at.map(|at| at.format("%Y-%m-%d").to_string()),
+    };
+
+    send_template(to, &subject, &template, None).await
+}
+
+#[derive(Template)]
+#[template(path = "email/moderator_alert.html")]
+struct ModeratorAlertEmail<'a> {
+    branding: &'a Branding,
+    username: &'a str,
+    title: &'a str,
+    message: &'a str,
+    link: String,
+}
+
+/// Send a moderator an alert about reports needing attention. `path` is the
+/// page to review them on.
+pub async fn send_moderator_alert_email(
+    to: &str,
+    username: &str,
+    title: &str,
+    message: &str,
+    path: &str,
+    base_url: &str,
+) -> EmailResult<()> {
+    let branding = Branding::load(base_url).await;
+    let template = ModeratorAlertEmail {
+        branding: &branding,
+        username,
+        title,
+        message,
+        link: format!("{}{}", base_url, path),
+    };
+
+    send_template(to, title, &template, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn branding() -> Branding {
+        Branding {
+            site_name: "Test Forum".to_string(),
+            site_description: String::new(),
+            base_url: "https://forum.test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_preview_truncates_on_characters() {
+        assert_eq!(preview("short"), "short");
+        let long = "é".repeat(MAX_PREVIEW_LENGTH + 1);
+        let shortened = preview(&long);
+        assert!(shortened.ends_with("..."));
+        assert_eq!(shortened.chars().count(), MAX_PREVIEW_LENGTH + 3);
+    }
+
+    #[test]
+    fn test_layout_and_escaping() {
+        let branding = branding();
+        let html = ThreadReplyEmail {
+            branding: &branding,
+            username: "alice",
+            poster: "<bob>",
+            thread_title: "Hello",
+            preview: "line one\nline two".to_string(),
+            link: "https://forum.test/threads/1".to_string(),
+            unsubscribe_link: "https://forum.test/email/unsubscribe?token=t".to_string(),
+        }
+        .render()
+        .expect("Failed to render");
+
+        assert!(html.contains("Test Forum"));
+        assert!(html.contains("&lt;bob&gt;"));
+        assert!(html.contains("line one<br/>line two"));
+
+        let text = plaintext::html_to_text(&html);
+        assert!(text.contains("<bob> has replied to a thread you're watching:"));
+        assert!(text.contains("View Thread (https://forum.test/threads/1)"));
+        assert!(text.contains("line one\nline two"));
+    }
+
+    #[test]
+    fn test_digest_links() {
+        let branding = branding();
+        let entries = [
+            DigestEntry {
+                title: "Reply".to_string(),
+                message: "Someone replied".to_string(),
+                url: Some("/threads/1".to_string()),
+                time: "09:00".to_string(),
+            },
+            DigestEntry {
+                title: "Other".to_string(),
+                message: "Something else".to_string(),
+                url: None,
+                time: "10:00".to_string(),
+            },
+        ];
+        let template = DigestEmail {
+            branding: &branding,
+            username: "alice",
+            period: "daily",
+            entries: &entries,
+            remaining: 0,
+            total: 2,
+            notifications_link: "https://forum.test/notifications".to_string(),
+            preferences_link: preferences_link("https://forum.test"),
+            unsubscribe_link: "https://forum.test/email/unsubscribe?token=t".to_string(),
+        };
+
+        assert_eq!(template.link(&entries[0]), "https://forum.test/threads/1");
+        assert_eq!(
+            template.link(&entries[1]),
+            "https://forum.test/notifications"
+        );
+        assert!(template
+            .render()
+            .expect("Failed to render")
+            .contains("You have 2 new notifications"));
+    }
 }
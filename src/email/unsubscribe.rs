@@ -0,0 +1,113 @@
+//! Signed one-click unsubscribe links.
+//!
+//! Notification emails carry a link, and RFC 8058 `List-Unsubscribe`
+//! headers, pointing at `/email/unsubscribe?token=`. The token names a user
+//! and what to stop emailing them about (one notification type, `all`
+//! notification types, or `announcements`) and is signed with
+//! [`crate::signing`] keyed by `SECRET_KEY`, so it works without logging in
+//! and can't be altered to unsubscribe someone else. Tokens don't expire;
+//! unsubscribing again does nothing.
+
+use crate::notifications::PREFERENCE_TYPES;
+use crate::signing::Signer;
+use once_cell::sync::Lazy;
+
+/// Scope covering every notification type
+pub const ALL: &str = "all";
+
+/// Scope for announcements emailed to many members from the admin panel
+pub const ANNOUNCEMENTS: &str = "announcements";
+
+static SIGNER: Lazy<Signer> = Lazy::new(|| Signer::from_secret_key("unsubscribe links"));
+
+fn message(user_id: i32, scope: &str) -> String {
+    format!("unsubscribe:{}:{}", user_id, scope)
+}
+
+/// Whether `scope` is `all`, `announcements` or a notification type with
+/// preferences.
+pub fn is_valid_scope(scope: &str) -> bool {
+    scope == ALL || scope == ANNOUNCEMENTS || PREFERENCE_TYPES.iter().any(|(t, _, _)| *t == scope)
+}
+
+/// Token unsubscribing `user_id` from `scope`.
+pub fn token(user_id: i32, scope: &str) -> String {
+    format!(
+        "{}.{}.{}",
+        user_id,
+        scope,
+        SIGNER.sign(&message(user_id, scope))
+    )
+}
+
+/// The user and scope a token was made for, if its signature is good.
+pub fn verify(token: &str) -> Option<(i32, String)> {
+    let mut parts = token.splitn(3, '.');
+    let user_id: i32 = parts.next()?.parse().ok()?;
+    let scope = parts.next()?;
+    let signature = parts.next()?;
+    if !is_valid_scope(scope) {
+        return None;
+    }
+
+    SIGNER
+        .verify(&message(user_id, scope), signature)
+        .then(|| (user_id, scope.to_string()))
+}
+
+/// Link unsubscribing `user_id` from `scope`.
+pub fn url(base_url: &str, user_id: i32, scope: &str) -> String {
+    format!(
+        "{}/email/unsubscribe?token={}",
+        base_url,
+        token(user_id, scope)
+    )
+}
+
+/// Notification types a scope covers.
+pub fn notification_types(scope: &str) -> Vec<&'static str> {
+    PREFERENCE_TYPES
+        .iter()
+        .map(|(t, _, _)| *t)
+        .filter(|t| scope == ALL || *t == scope)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_round_trip() {
+        assert_eq!(
+            verify(&token(42, "mention")),
+            Some((42, "mention".to_string()))
+        );
+        assert_eq!(verify(&token(7, ALL)), Some((7, ALL.to_string())));
+        assert_eq!(
+            verify(&token(7, ANNOUNCEMENTS)),
+            Some((7, ANNOUNCEMENTS.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_tampered_tokens_are_rejected() {
+        let token = token(42, "mention");
+        assert_eq!(verify(&token.replacen("42", "43", 1)), None);
+        assert_eq!(verify(&token.replace("mention", "quote")), None);
+        assert_eq!(verify(&token[..token.len() - 1]), None);
+        assert_eq!(verify("42.mention"), None);
+        assert_eq!(verify(""), None);
+
+        // Well signed, but not something that can be unsubscribed from
+        let bogus = format!("1.bogus.{}", SIGNER.sign(&message(1, "bogus")));
+        assert_eq!(verify(&bogus), None);
+    }
+
+    #[test]
+    fn test_notification_types() {
+        assert_eq!(notification_types("quote"), vec!["quote"]);
+        assert_eq!(notification_types(ALL).len(), PREFERENCE_TYPES.len());
+        assert!(notification_types(ANNOUNCEMENTS).is_empty());
+    }
+}
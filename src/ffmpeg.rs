@@ -11,6 +11,16 @@ pub fn get_dimensions_from_input(ctx: &Input) -> Option<(u32, u32)> {
     Some((decoder.width(), decoder.height()))
 }
 
+/// Container duration in milliseconds, if known.
+pub fn get_duration_from_input(ctx: &Input) -> Option<i32> {
+    // Durations are reported in AV_TIME_BASE (microsecond) units.
+    let duration = ctx.duration();
+    if duration <= 0 {
+        return None;
+    }
+    i32::try_from(duration / 1000).ok()
+}
+
 pub fn get_extension_from_input(ctx: &Input) -> Option<String> {
     let format = ctx.format();
     log::error!("Name: {:#?}", format.name());
@@ -0,0 +1,510 @@
+//! Attachment browser for the admin panel.
+//!
+//! Lists uploaded files with filters, finds where a file is used, and removes
+//! files outright. Removal takes every use of the file with it: posts and
+//! messages lose the attachment, avatars are cleared and icons unset. The
+//! file's thumbnails and other renditions go too once nothing else uses them.
+
+use super::get_storage;
+use super::quota::format_bytes;
+use super::visibility::get_file_url;
+use crate::db::get_db_pool;
+use crate::orm::attachments::{self, AttachmentVisibility, ScanStatus};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use sea_orm::{
+    entity::*, query::*, ConnectionTrait, DbBackend, DbErr, FromQueryResult, Statement,
+    TransactionTrait, Value,
+};
+use serde::Deserialize;
+
+/// Attachments listed per page.
+pub const PAGE_SIZE: i64 = 50;
+
+/// Filters for the attachment list, as submitted by the filter form. Empty
+/// fields are ignored.
+#[derive(Debug, Default, Deserialize)]
+pub struct AttachmentFilter {
+    /// Username of someone who uploaded the file
+    pub uploader: Option<String>,
+    /// `image`, `video`, `audio` or `other`
+    pub kind: Option<String>,
+    /// Smallest file size, in kilobytes
+    pub min_kb: Option<String>,
+    /// Largest file size, in kilobytes
+    pub max_kb: Option<String>,
+    /// First uploaded on or after this date (YYYY-MM-DD)
+    pub from: Option<String>,
+    /// First uploaded on or before this date (YYYY-MM-DD)
+    pub to: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+impl AttachmentFilter {
+    pub fn uploader_value(&self) -> &str {
+        non_empty(&self.uploader).unwrap_or_default()
+    }
+
+    pub fn kind_value(&self) -> &str {
+        non_empty(&self.kind)
+            .filter(|kind| matches!(*kind, "image" | "video" | "audio" | "other"))
+            .unwrap_or_default()
+    }
+
+    pub fn min_kb_value(&self) -> &str {
+        non_empty(&self.min_kb).unwrap_or_default()
+    }
+
+    pub fn max_kb_value(&self) -> &str {
+        non_empty(&self.max_kb).unwrap_or_default()
+    }
+
+    pub fn from_value(&self) -> &str {
+        non_empty(&self.from).unwrap_or_default()
+    }
+
+    pub fn to_value(&self) -> &str {
+        non_empty(&self.to).unwrap_or_default()
+    }
+
+    fn kilobytes(value: &Option<String>) -> Option<i64> {
+        non_empty(value)
+            .and_then(|kb| kb.parse::<i64>().ok())
+            .map(|kb| kb.max(0) * 1024)
+    }
+
+    fn date(value: &Option<String>) -> Option<NaiveDateTime> {
+        non_empty(value)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    }
+
+    /// Query string repeating these filters, for pagination links.
+    pub fn query_string(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in [
+            ("uploader", self.uploader_value()),
+            ("kind", self.kind_value()),
+            ("min_kb", self.min_kb_value()),
+            ("max_kb", self.max_kb_value()),
+            ("from", self.from_value()),
+            ("to", self.to_value()),
+        ] {
+            if !value.is_empty() {
+                query.append_pair(name, value);
+            }
+        }
+        query.finish()
+    }
+
+    /// SQL conditions on `attachments a`, and the values bound to their
+    /// `$n` parameters.
+    fn conditions(&self) -> (String, Vec<Value>) {
+        let mut conditions = vec![
+            // Renditions are listed with their original, not on their own.
+            "NOT EXISTS (SELECT 1 FROM attachment_thumbnails t WHERE t.thumbnail_id = a.id)"
+                .to_owned(),
+            "NOT EXISTS (SELECT 1 FROM attachment_variants v WHERE v.variant_id = a.id)".to_owned(),
+        ];
+        let mut values: Vec<Value> = Vec::new();
+
+        if !self.uploader_value().is_empty() {
+            values.push(self.uploader_value().to_owned().into());
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM user_uploads uu
+                    JOIN user_names un ON un.user_id = uu.user_id
+                    WHERE uu.attachment_id = a.id AND LOWER(un.name) = LOWER(${}))",
+                values.len()
+            ));
+        }
+
+        match self.kind_value() {
+            "" => {}
+            "other" => conditions.push(
+                "a.mime NOT LIKE 'image/%' AND a.mime NOT LIKE 'video/%' \
+                 AND a.mime NOT LIKE 'audio/%'"
+                    .to_owned(),
+            ),
+            kind => {
+                values.push(format!("{}/%", kind).into());
+                conditions.push(format!("a.mime LIKE ${}", values.len()));
+            }
+        }
+
+        if let Some(min) = Self::kilobytes(&self.min_kb) {
+            values.push(min.into());
+            conditions.push(format!("a.filesize >= ${}", values.len()));
+        }
+        if let Some(max) = Self::kilobytes(&self.max_kb) {
+            values.push(max.into());
+            conditions.push(format!("a.filesize <= ${}", values.len()));
+        }
+        if let Some(from) = Self::date(&self.from) {
+            values.push(from.into());
+            conditions.push(format!("a.first_seen_at >= ${}", values.len()));
+        }
+        if let Some(to) = Self::date(&self.to) {
+            values.push((to + Duration::days(1)).into());
+            conditions.push(format!("a.first_seen_at < ${}", values.len()));
+        }
+
+        (conditions.join(" AND "), values)
+    }
+}
+
+/// A row of the attachment list.
+#[derive(Debug, FromQueryResult)]
+pub struct AttachmentListing {
+    pub id: i32,
+    pub filename: String,
+    pub mime: String,
+    pub filesize: i64,
+    pub first_seen_at: NaiveDateTime,
+    pub reference_count: i32,
+    pub visibility: AttachmentVisibility,
+    pub scan_status: ScanStatus,
+    pub thumbnail_filename: Option<String>,
+    /// Whoever uploaded the file first
+    pub uploader_id: Option<i32>,
+    pub uploader_name: Option<String>,
+}
+
+impl AttachmentListing {
+    pub fn size_display(&self) -> String {
+        format_bytes(self.filesize)
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime.starts_with("image/")
+    }
+
+    pub fn is_video(&self) -> bool {
+        self.mime.starts_with("video/")
+    }
+
+    pub fn is_quarantined(&self) -> bool {
+        self.scan_status.is_quarantined()
+    }
+
+    pub fn url(&self) -> String {
+        get_file_url(&self.filename, &self.filename, self.visibility)
+    }
+
+    /// Small rendition for the preview, falling back to the original.
+    pub fn preview_url(&self) -> String {
+        match &self.thumbnail_filename {
+            Some(filename) => get_file_url(filename, filename, self.visibility),
+            None => self.url(),
+        }
+    }
+}
+
+const LISTING_COLUMNS: &str = r#"
+    a.id, a.filename, a.mime, a.filesize, a.first_seen_at, a.reference_count,
+    a.visibility, a.scan_status,
+    (SELECT th.filename FROM attachment_thumbnails t
+     JOIN attachments th ON th.id = t.thumbnail_id
+     WHERE t.attachment_id = a.id
+     ORDER BY th.filesize ASC LIMIT 1) AS thumbnail_filename,
+    uploader.user_id AS uploader_id,
+    un.name AS uploader_name
+"#;
+
+const LISTING_JOINS: &str = r#"
+    LEFT JOIN LATERAL (
+        SELECT user_id FROM user_uploads
+        WHERE attachment_id = a.id
+        ORDER BY created_at ASC LIMIT 1
+    ) uploader ON TRUE
+    LEFT JOIN user_names un ON un.user_id = uploader.user_id
+"#;
+
+/// Uploaded files matching `filter`, newest first, and the total number of matches.
+pub async fn search_attachments(
+    filter: &AttachmentFilter,
+    page: i64,
+) -> Result<(Vec<AttachmentListing>, i64), DbErr> {
+    #[derive(FromQueryResult)]
+    struct Total {
+        count: i64,
+    }
+
+    let db = get_db_pool();
+    let (conditions, values) = filter.conditions();
+
+    let total = Total::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            "SELECT COUNT(*) AS count FROM attachments a WHERE {}",
+            conditions
+        ),
+        values.clone(),
+    ))
+    .one(db)
+    .await?
+    .map(|total| total.count)
+    .unwrap_or(0);
+
+    let mut values = values;
+    values.push(PAGE_SIZE.into());
+    values.push(((page.max(1) - 1) * PAGE_SIZE).into());
+    let listings = AttachmentListing::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            "SELECT {} FROM attachments a {} WHERE {}
+             ORDER BY a.first_seen_at DESC, a.id DESC
+             LIMIT ${} OFFSET ${}",
+            LISTING_COLUMNS,
+            LISTING_JOINS,
+            conditions,
+            values.len() - 1,
+            values.len()
+        ),
+        values,
+    ))
+    .all(db)
+    .await?;
+
+    Ok((listings, total))
+}
+
+/// One attachment for the usage view.
+pub async fn get_attachment_listing(
+    attachment_id: i32,
+) -> Result<Option<AttachmentListing>, DbErr> {
+    AttachmentListing::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            "SELECT {} FROM attachments a {} WHERE a.id = $1",
+            LISTING_COLUMNS, LISTING_JOINS
+        ),
+        vec![attachment_id.into()],
+    ))
+    .one(get_db_pool())
+    .await
+}
+
+/// A forum post that has the file attached.
+#[derive(Debug, FromQueryResult)]
+pub struct PostUse {
+    pub post_id: i32,
+    pub thread_id: i32,
+    pub thread_title: String,
+    pub author_id: Option<i32>,
+    pub author_name: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// A conversation message that has the file attached.
+#[derive(Debug, FromQueryResult)]
+pub struct MessageUse {
+    pub conversation_id: i32,
+    pub message_id: i32,
+    pub sender_id: Option<i32>,
+    pub sender_name: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// A member using the file as their avatar.
+#[derive(Debug, FromQueryResult)]
+pub struct AvatarUse {
+    pub user_id: i32,
+    pub name: Option<String>,
+}
+
+/// Everywhere a file is used.
+#[derive(Debug, Default)]
+pub struct AttachmentUses {
+    pub posts: Vec<PostUse>,
+    pub messages: Vec<MessageUse>,
+    pub avatars: Vec<AvatarUse>,
+}
+
+/// Finds the posts, messages and avatars using a file.
+pub async fn find_attachment_uses(attachment_id: i32) -> Result<AttachmentUses, DbErr> {
+    let db = get_db_pool();
+
+    let posts = PostUse::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT p.id AS post_id, p.thread_id, t.title AS thread_title,
+               p.user_id AS author_id, un.name AS author_name, p.created_at
+        FROM ugc_attachments ua
+        JOIN posts p ON p.ugc_id = ua.ugc_id
+        JOIN threads t ON t.id = p.thread_id
+        LEFT JOIN user_names un ON un.user_id = p.user_id
+        WHERE ua.attachment_id = $1
+        ORDER BY p.created_at DESC
+        "#,
+        vec![attachment_id.into()],
+    ))
+    .all(db)
+    .await?;
+
+    let messages = MessageUse::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT pm.conversation_id, pm.id AS message_id,
+               pm.user_id AS sender_id, un.name AS sender_name, pm.created_at
+        FROM ugc_attachments ua
+        JOIN private_messages pm ON pm.ugc_id = ua.ugc_id
+        LEFT JOIN user_names un ON un.user_id = pm.user_id
+        WHERE ua.attachment_id = $1
+        ORDER BY pm.created_at DESC
+        "#,
+        vec![attachment_id.into()],
+    ))
+    .all(db)
+    .await?;
+
+    let avatars = AvatarUse::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT ua.user_id, un.name
+        FROM user_avatars ua
+        LEFT JOIN user_names un ON un.user_id = ua.user_id
+        WHERE ua.attachment_id = $1
+        ORDER BY ua.user_id
+        "#,
+        vec![attachment_id.into()],
+    ))
+    .all(db)
+    .await?;
+
+    Ok(AttachmentUses {
+        posts,
+        messages,
+        avatars,
+    })
+}
+
+/// What deleting an attachment's row left to clean up.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DeletedAttachment {
+    /// Storage key to remove, unless another row still points at the same file
+    pub filename: Option<String>,
+    /// Renditions of the file that nothing else uses
+    pub orphaned_renditions: Vec<i32>,
+}
+
+/// Deletes an attachment's row along with every use of it. Returns `None` if
+/// the attachment does not exist.
+pub async fn delete_attachment_record(
+    attachment_id: i32,
+) -> Result<Option<DeletedAttachment>, DbErr> {
+    #[derive(FromQueryResult)]
+    struct Deleted {
+        filename: String,
+    }
+
+    #[derive(FromQueryResult)]
+    struct Shared {
+        count: i64,
+    }
+
+    #[derive(FromQueryResult)]
+    struct Rendition {
+        id: i32,
+    }
+
+    let txn = get_db_pool().begin().await?;
+
+    let renditions = Rendition::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT thumbnail_id AS id FROM attachment_thumbnails WHERE attachment_id = $1
+        UNION
+        SELECT variant_id AS id FROM attachment_variants WHERE attachment_id = $1
+        "#,
+        vec![attachment_id.into()],
+    ))
+    .all(&txn)
+    .await?;
+
+    for sql in [
+        "DELETE FROM ugc_attachments WHERE attachment_id = $1",
+        "DELETE FROM user_avatars WHERE attachment_id = $1",
+        "DELETE FROM avatar_gallery WHERE attachment_id = $1",
+        "UPDATE forums SET icon_attachment_id = NULL WHERE icon_attachment_id = $1",
+        "UPDATE forums SET icon_new_attachment_id = NULL WHERE icon_new_attachment_id = $1",
+        "UPDATE reaction_types SET attachment_id = NULL WHERE attachment_id = $1",
+        "DELETE FROM attachment_thumbnails WHERE attachment_id = $1 OR thumbnail_id = $1",
+        "DELETE FROM attachment_variants WHERE attachment_id = $1 OR variant_id = $1",
+    ] {
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            sql,
+            vec![attachment_id.into()],
+        ))
+        .await?;
+    }
+
+    let deleted = Deleted::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "DELETE FROM attachments WHERE id = $1 RETURNING filename",
+        vec![attachment_id.into()],
+    ))
+    .one(&txn)
+    .await?;
+
+    let deleted = match deleted {
+        Some(deleted) => deleted,
+        None => {
+            txn.rollback().await?;
+            return Ok(None);
+        }
+    };
+
+    let shared = Shared::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT COUNT(*) AS count FROM attachments WHERE filename = $1",
+        vec![deleted.filename.to_owned().into()],
+    ))
+    .one(&txn)
+    .await?
+    .map(|shared| shared.count)
+    .unwrap_or(0);
+
+    let orphaned_renditions = attachments::Entity::find()
+        .filter(attachments::Column::Id.is_in(renditions.into_iter().map(|r| r.id)))
+        .filter(attachments::Column::ReferenceCount.eq(0))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|rendition| rendition.id)
+        .collect();
+
+    txn.commit().await?;
+
+    Ok(Some(DeletedAttachment {
+        filename: (shared == 0).then_some(deleted.filename),
+        orphaned_renditions,
+    }))
+}
+
+/// Removes an attachment, every use of it, and its unused renditions, from the
+/// database and storage. Returns whether the attachment existed.
+pub async fn remove_attachment(attachment_id: i32) -> Result<bool, DbErr> {
+    let mut found = false;
+    let mut queue = vec![attachment_id];
+
+    while let Some(id) = queue.pop() {
+        let deleted = match delete_attachment_record(id).await? {
+            Some(deleted) => deleted,
+            None => continue,
+        };
+        found |= id == attachment_id;
+
+        if let Some(filename) = deleted.filename {
+            log::info!("remove_attachment: deleting {}", filename);
+            if let Err(e) = get_storage().delete_object(&filename).await {
+                log::error!("remove_attachment: {}: {}", filename, e);
+            }
+        }
+        queue.extend(deleted.orphaned_renditions);
+    }
+
+    Ok(found)
+}
@@ -0,0 +1,693 @@
+//! Chunked, resumable uploads.
+//!
+//! Large files are sent as a series of fixed-size parts so that a dropped
+//! connection only costs the part in flight. The client announces the file
+//! and its BLAKE3 hash, uploads parts in any order (re-sending a part replaces
+//! it), asks which parts arrived after reconnecting, and finally asks the
+//! server to join them. Parts are joined under a key unique to the session
+//! and hashed again; only a file that matches its announced hash is moved to
+//! its content address and becomes an attachment. A corrupted or forged
+//! upload is discarded, so it can never replace a stored file or be served
+//! under the wrong hash.
+//!
+//! Parts are staged in the storage backend's multipart upload and never
+//! touch the application's temporary directory.
+
+use super::{
+    enqueue_attachment_thumbnails, enqueue_image_variants, get_extension, get_storage,
+    insert_attachment_record, insert_generated_attachment, UploadResponse,
+};
+use crate::attachment::{get_attachment_by_hash, update_attachment_last_seen};
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::orm::{attachments, upload_session_parts, upload_sessions, user_uploads};
+use crate::storage::CompletedPart;
+use actix_multipart::Field;
+use actix_web::{delete, error, get, post, put, web, Error, HttpResponse, Responder};
+use chrono::{Duration, Utc};
+use futures::StreamExt;
+use mime::Mime;
+use sea_orm::{entity::*, query::*, DbErr, QueryFilter};
+use serde::{Deserialize, Serialize};
+
+/// Images larger than this are stored as uploaded, without metadata
+/// stripping or renditions, to bound the memory used while finalizing.
+const MAX_PROCESSED_IMAGE_SIZE: i64 = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct NewUploadForm {
+    pub filename: String,
+    pub filesize: i64,
+    pub mime: Option<String>,
+    pub hash: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadSessionResponse {
+    /// Session ID, or `None` when the file was already uploaded.
+    pub id: Option<String>,
+    pub chunk_size: i32,
+    pub part_count: i32,
+    /// Parts the server holds, in ascending order.
+    pub received_parts: Vec<i32>,
+    /// Set when the file is already stored and nothing needs to be sent.
+    pub attachment: Option<UploadResponse>,
+}
+
+/// Part size for new sessions, in bytes.
+pub fn chunk_size() -> i32 {
+    let mb = crate::app_config::storage().chunk_size_mb.clamp(1, 1024);
+    (mb * 1024 * 1024) as i32
+}
+
+/// Number of parts needed to send `filesize` bytes.
+pub fn part_count(filesize: i64, chunk_size: i32) -> i32 {
+    ((filesize + chunk_size as i64 - 1) / chunk_size as i64) as i32
+}
+
+/// Exact size part `part_number` must have. Every part is full except the last.
+pub fn expected_part_size(filesize: i64, chunk_size: i32, part_number: i32) -> Option<usize> {
+    let count = part_count(filesize, chunk_size);
+    if part_number < 1 || part_number > count {
+        return None;
+    }
+
+    let size = if part_number == count {
+        filesize - chunk_size as i64 * (count as i64 - 1)
+    } else {
+        chunk_size as i64
+    };
+    Some(size as usize)
+}
+
+/// Loads an unexpired session belonging to `user_id`.
+pub async fn get_session(id: &str, user_id: i32) -> Option<upload_sessions::Model> {
+    upload_sessions::Entity::find_by_id(id.to_owned())
+        .filter(upload_sessions::Column::UserId.eq(user_id))
+        .filter(upload_sessions::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+        .one(get_db_pool())
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("get_session: {}", e);
+            None
+        })
+}
+
+/// Parts of a session the storage backend has accepted, in ascending order.
+pub async fn get_received_parts(id: &str) -> Vec<upload_session_parts::Model> {
+    upload_session_parts::Entity::find()
+        .filter(upload_session_parts::Column::UploadId.eq(id.to_owned()))
+        .order_by_asc(upload_session_parts::Column::PartNumber)
+        .all(get_db_pool())
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("get_received_parts: {}", e);
+            Vec::new()
+        })
+}
+
+/// Records that a part was stored, replacing an earlier copy of the same part.
+pub async fn record_part(id: &str, part: &CompletedPart, size: i32) -> Result<(), Error> {
+    let db = get_db_pool();
+    let model = upload_session_parts::ActiveModel {
+        upload_id: Set(id.to_owned()),
+        part_number: Set(part.part_number),
+        size: Set(size),
+        e_tag: Set(part.e_tag.to_owned()),
+    };
+
+    let existing = upload_session_parts::Entity::find_by_id((id.to_owned(), part.part_number))
+        .one(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let result = match existing {
+        Some(_) => model.update(db).await.map(|_| ()),
+        None => upload_session_parts::Entity::insert(model)
+            .exec(db)
+            .await
+            .map(|_| ()),
+    };
+
+    result.map_err(|e| {
+        log::error!("record_part: {}", e);
+        error::ErrorInternalServerError("Failed to record upload part.")
+    })
+}
+
+/// Checks every part of the file arrived with the right size and returns them
+/// in the order they must be joined.
+pub fn completed_parts(
+    session: &upload_sessions::Model,
+    parts: &[upload_session_parts::Model],
+) -> Result<Vec<CompletedPart>, String> {
+    let count = part_count(session.filesize, session.chunk_size);
+    let mut completed = Vec::with_capacity(parts.len());
+
+    for number in 1..=count {
+        let part = parts
+            .iter()
+            .find(|part| part.part_number == number)
+            .ok_or_else(|| format!("Part {} has not been uploaded.", number))?;
+
+        if Some(part.size as usize)
+            != expected_part_size(session.filesize, session.chunk_size, number)
+        {
+            return Err(format!("Part {} has the wrong size.", number));
+        }
+
+        completed.push(CompletedPart {
+            part_number: number,
+            e_tag: part.e_tag.to_owned(),
+        });
+    }
+
+    Ok(completed)
+}
+
+/// Key a session's parts are joined under until the result is verified.
+pub fn staging_key(session_id: &str) -> String {
+    format!("upload-{}", session_id)
+}
+
+/// Key a verified file is stored under: its hash and extension.
+fn content_key(hash: &str, filename: &str, mime: &Mime) -> String {
+    match get_extension(filename, mime) {
+        Some(extension) => format!("{}.{}", hash, extension),
+        None => hash.to_owned(),
+    }
+}
+
+/// Deletes a joined upload that did not become an attachment.
+async fn discard_staged(key: &str) {
+    let storage = get_storage();
+    if !storage.exists(key).await.unwrap_or(true) {
+        return;
+    }
+    if let Err(e) = storage.delete_object(key).await {
+        log::error!("complete_upload: {}", e);
+    }
+}
+
+async fn delete_session(id: &str) {
+    if let Err(e) = upload_sessions::Entity::delete_by_id(id.to_owned())
+        .exec(get_db_pool())
+        .await
+    {
+        log::error!("delete_session: {}", e);
+    }
+}
+
+/// Discards uploads that were never finished. Called periodically.
+pub async fn cleanup_expired_uploads() {
+    let expired = match upload_sessions::Entity::find()
+        .filter(upload_sessions::Column::ExpiresAt.lte(Utc::now().naive_utc()))
+        .all(get_db_pool())
+        .await
+    {
+        Ok(expired) => expired,
+        Err(e) => {
+            log::error!("cleanup_expired_uploads: {}", e);
+            return;
+        }
+    };
+
+    for session in expired {
+        if let Err(e) = get_storage()
+            .abort_multipart_upload(&session.storage_key, &session.storage_upload_id)
+            .await
+        {
+            log::warn!("cleanup_expired_uploads: {}: {}", session.id, e);
+        }
+        delete_session(&session.id).await;
+    }
+}
+
+fn session_response(
+    session: &upload_sessions::Model,
+    parts: &[upload_session_parts::Model],
+) -> UploadSessionResponse {
+    UploadSessionResponse {
+        id: Some(session.id.to_owned()),
+        chunk_size: session.chunk_size,
+        part_count: part_count(session.filesize, session.chunk_size),
+        received_parts: parts.iter().map(|part| part.part_number).collect(),
+        attachment: None,
+    }
+}
+
+/// Starts a chunked upload.
+#[post("/fs/uploads")]
+pub async fn create_upload(
+    client: ClientCtx,
+    form: web::Json<NewUploadForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    let form = form.into_inner();
+
+    if form.hash.len() != 64 || !form.hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(error::ErrorBadRequest(
+            "Malformed BLAKE3 hash: expected 64 hex characters.",
+        ));
+    }
+    let hash = form.hash.to_ascii_lowercase();
+
+    let max_filesize = crate::app_config::storage().max_chunked_upload_mb as i64 * 1024 * 1024;
+    if form.filesize < 1 || form.filesize > max_filesize {
+        return Err(error::ErrorBadRequest("File is empty or too large."));
+    }
+
+    let filename = form.filename.trim().to_owned();
+    if filename.is_empty() {
+        return Err(error::ErrorBadRequest("Missing filename."));
+    }
+
+    super::quota::check_upload_quota(user_id, &hash, form.filesize).await?;
+
+    let chunk_size = chunk_size();
+
+    // The file is already stored; nothing needs to be sent.
+    if let Some(attachment) = get_attachment_by_hash(hash.to_owned()).await {
+        if attachment.scan_status.is_quarantined() {
+            return Err(crate::antivirus::quarantined_error());
+        }
+        if get_storage()
+            .exists(&attachment.filename)
+            .await
+            .unwrap_or(false)
+        {
+            actix_web::rt::spawn(update_attachment_last_seen(attachment.id));
+            super::quota::record_upload(user_id, attachment.id).await;
+            return Ok(web::Json(UploadSessionResponse {
+                id: None,
+                chunk_size,
+                part_count: part_count(form.filesize, chunk_size),
+                received_parts: Vec::new(),
+                attachment: Some(UploadResponse {
+                    id: attachment.id,
+                    hash: attachment.hash,
+                    filename: attachment.filename,
+                }),
+            }));
+        }
+    }
+
+    let mime: Mime = form
+        .mime
+        .as_deref()
+        .and_then(|mime| mime.parse().ok())
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    let id = uuid::Uuid::new_v4().to_string();
+    let storage_key = staging_key(&id);
+
+    let storage_upload_id = get_storage()
+        .create_multipart_upload(&storage_key)
+        .await
+        .map_err(|e| {
+            log::error!("create_upload: {}", e);
+            error::ErrorInternalServerError("Failed to start upload.")
+        })?;
+
+    let now = Utc::now().naive_utc();
+    let expiry_hours = crate::app_config::storage().upload_expiry_hours as i64;
+    let session = upload_sessions::ActiveModel {
+        id: Set(id),
+        user_id: Set(user_id),
+        filename: Set(filename),
+        mime: Set(mime.to_string()),
+        filesize: Set(form.filesize),
+        hash: Set(hash),
+        storage_key: Set(storage_key),
+        storage_upload_id: Set(storage_upload_id),
+        chunk_size: Set(chunk_size),
+        created_at: Set(now),
+        expires_at: Set(now + Duration::hours(expiry_hours)),
+    }
+    .insert(get_db_pool())
+    .await
+    .map_err(|e| {
+        log::error!("create_upload: {}", e);
+        error::ErrorInternalServerError("Failed to start upload.")
+    })?;
+
+    Ok(web::Json(session_response(&session, &[])))
+}
+
+/// Reports which parts have arrived so an interrupted upload can resume.
+#[get("/fs/uploads/{id}")]
+pub async fn view_upload(
+    client: ClientCtx,
+    path: web::Path<String>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    let session = get_session(&path, user_id)
+        .await
+        .ok_or_else(|| error::ErrorNotFound("Upload not found."))?;
+    let parts = get_received_parts(&session.id).await;
+
+    Ok(web::Json(session_response(&session, &parts)))
+}
+
+/// Receives one part of a chunked upload as the raw request body.
+#[put("/fs/uploads/{id}/parts/{part_number}")]
+pub async fn put_upload_part(
+    client: ClientCtx,
+    path: web::Path<(String, i32)>,
+    mut payload: web::Payload,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    let (id, part_number) = path.into_inner();
+    let session = get_session(&id, user_id)
+        .await
+        .ok_or_else(|| error::ErrorNotFound("Upload not found."))?;
+
+    let expected = expected_part_size(session.filesize, session.chunk_size, part_number)
+        .ok_or_else(|| error::ErrorBadRequest("Part number out of range."))?;
+
+    let mut data = Vec::with_capacity(expected);
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        if data.len() + chunk.len() > expected {
+            return Err(error::ErrorPayloadTooLarge("Part is larger than expected."));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    if data.len() != expected {
+        return Err(error::ErrorBadRequest(format!(
+            "Part {} should be {} bytes, got {}.",
+            part_number,
+            expected,
+            data.len()
+        )));
+    }
+
+    let part = get_storage()
+        .upload_part(
+            &session.storage_key,
+            &session.storage_upload_id,
+            part_number,
+            data,
+        )
+        .await
+        .map_err(|e| {
+            log::error!("put_upload_part: {}", e);
+            error::ErrorInternalServerError("Failed to store upload part.")
+        })?;
+    record_part(&session.id, &part, expected as i32).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Joins the parts of an upload, verifies its hash and stores it as an attachment.
+#[post("/fs/uploads/{id}/complete")]
+pub async fn complete_upload(
+    client: ClientCtx,
+    path: web::Path<String>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    let session = get_session(&path, user_id)
+        .await
+        .ok_or_else(|| error::ErrorNotFound("Upload not found."))?;
+    // Other uploads may have used up the quota since this one started.
+    super::quota::check_upload_quota(user_id, &session.hash, session.filesize).await?;
+    let parts = completed_parts(&session, &get_received_parts(&session.id).await)
+        .map_err(error::ErrorBadRequest)?;
+
+    let storage = get_storage();
+    storage
+        .complete_multipart_upload(&session.storage_key, &session.storage_upload_id, parts)
+        .await
+        .map_err(|e| {
+            log::error!("complete_upload: {}", e);
+            error::ErrorInternalServerError("Failed to assemble upload.")
+        })?;
+    // The multipart upload is gone from storage; the session cannot be resumed.
+    delete_session(&session.id).await;
+
+    let mime: Mime = session
+        .mime
+        .parse()
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    let is_still_image = mime.type_() == mime::IMAGE
+        && mime != mime::IMAGE_GIF
+        && mime != mime::IMAGE_SVG
+        && session.filesize <= MAX_PROCESSED_IMAGE_SIZE;
+
+    let (hash, data) = match hash_stored_file(&session.storage_key, is_still_image).await {
+        Ok(hashed) => hashed,
+        Err(e) => {
+            discard_staged(&session.storage_key).await;
+            return Err(e);
+        }
+    };
+    if hash != session.hash {
+        log::warn!(
+            "complete_upload: hash mismatch for {}: expected {}, got {}",
+            session.storage_key,
+            session.hash,
+            hash
+        );
+        discard_staged(&session.storage_key).await;
+        return Err(error::ErrorBadRequest(
+            "Uploaded data does not match the announced hash.",
+        ));
+    }
+
+    // Another session may have finished the same file first.
+    if let Some(attachment) = get_attachment_by_hash(session.hash.to_owned()).await {
+        discard_staged(&session.storage_key).await;
+        if attachment.scan_status.is_quarantined() {
+            return Err(crate::antivirus::quarantined_error());
+        }
+        super::quota::record_upload(user_id, attachment.id).await;
+        return Ok(web::Json(UploadResponse {
+            id: attachment.id,
+            hash: attachment.hash,
+            filename: attachment.filename,
+        }));
+    }
+
+    let content_key = content_key(&session.hash, &session.filename, &mime);
+    let response = match data {
+        Some(data) => {
+            insert_image(
+                &session,
+                &content_key,
+                &mime,
+                data,
+                client.preserve_image_orientation(),
+            )
+            .await
+        }
+        None => insert_file(&session, &content_key, &mime).await,
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            discard_staged(&session.storage_key).await;
+            return Err(e);
+        }
+    };
+
+    crate::antivirus::scan_upload(response.id, Some(user_id)).await?;
+    if mime.type_() == mime::VIDEO {
+        crate::transcode::enqueue(response.id).await;
+    }
+
+    super::quota::record_upload(user_id, response.id).await;
+
+    Ok(web::Json(response))
+}
+
+/// Abandons a chunked upload and discards its parts.
+#[delete("/fs/uploads/{id}")]
+pub async fn delete_upload(
+    client: ClientCtx,
+    path: web::Path<String>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    let session = get_session(&path, user_id)
+        .await
+        .ok_or_else(|| error::ErrorNotFound("Upload not found."))?;
+
+    if let Err(e) = get_storage()
+        .abort_multipart_upload(&session.storage_key, &session.storage_upload_id)
+        .await
+    {
+        log::warn!("delete_upload: {}: {}", session.id, e);
+    }
+    delete_session(&session.id).await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// The stored file with `hash`, if `user_id` uploaded it and it isn't
+/// quarantined. Knowing a file's hash is not enough to attach it, so nobody
+/// can attach another member's private file or one found to be infected.
+pub async fn find_uploaded_attachment(
+    user_id: i32,
+    hash: &str,
+) -> Result<Option<attachments::Model>, DbErr> {
+    let db = get_db_pool();
+    let attachment = match attachments::Entity::find()
+        .filter(attachments::Column::Hash.eq(hash))
+        .one(db)
+        .await?
+    {
+        Some(attachment) if !attachment.scan_status.is_quarantined() => attachment,
+        _ => return Ok(None),
+    };
+    let uploaded = user_uploads::Entity::find_by_id((user_id, attachment.id))
+        .one(db)
+        .await?
+        .is_some();
+    Ok(uploaded.then(|| attachment))
+}
+
+/// Reads an `uploaded_attachment` form field, which refers to a file that was
+/// already sent through a chunked upload as `{hash}/{filename}`. Returns the
+/// user's filename and the attachment, or `None` if `user_id` has no such
+/// file to attach.
+pub async fn read_uploaded_attachment_field(
+    field: &mut Field,
+    user_id: i32,
+) -> Result<Option<(String, UploadResponse)>, Error> {
+    let mut buf: Vec<u8> = Vec::with_capacity(256);
+    while let Some(chunk) = field.next().await {
+        let bytes = chunk.map_err(|e| {
+            log::error!(
+                "read_uploaded_attachment_field: multipart read error: {}",
+                e
+            );
+            error::ErrorBadRequest("Error interpreting user input.")
+        })?;
+        if buf.len() + bytes.len() > 1024 {
+            return Err(error::ErrorBadRequest("Error interpreting user input."));
+        }
+        buf.extend(bytes);
+    }
+
+    let value = String::from_utf8(buf)
+        .map_err(|_| error::ErrorBadRequest("Error interpreting user input."))?;
+    let (hash, filename) = match value.split_once('/') {
+        Some((hash, filename)) if hash.len() == 64 && !filename.trim().is_empty() => {
+            (hash.to_ascii_lowercase(), filename.trim().to_owned())
+        }
+        _ => return Err(error::ErrorBadRequest("Malformed uploaded attachment.")),
+    };
+
+    let attachment = find_uploaded_attachment(user_id, &hash)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(attachment.map(|attachment| {
+        (
+            filename,
+            UploadResponse {
+                id: attachment.id,
+                hash: attachment.hash,
+                filename: attachment.filename,
+            },
+        )
+    }))
+}
+
+/// Streams a stored file through BLAKE3, keeping a copy of the bytes if asked.
+async fn hash_stored_file(key: &str, keep_data: bool) -> Result<(String, Option<Vec<u8>>), Error> {
+    let object = get_storage().get_object(key, None).await.map_err(|e| {
+        log::error!("hash_stored_file: {}", e);
+        error::ErrorInternalServerError("Failed to read assembled upload.")
+    })?;
+
+    let mut hasher = blake3::Hasher::new();
+    let mut data = keep_data.then(Vec::new);
+    let mut body = object.body;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| {
+            log::error!("hash_stored_file: {}", e);
+            error::ErrorInternalServerError("Failed to read assembled upload.")
+        })?;
+        hasher.update(&chunk);
+        if let Some(data) = data.as_mut() {
+            data.extend_from_slice(&chunk);
+        }
+    }
+
+    Ok((hasher.finalize().to_string(), data))
+}
+
+/// Moves a verified upload from its staging key to its content address.
+async fn store_verified(staged: &str, content_key: &str) -> Result<(), Error> {
+    get_storage()
+        .rename_object(staged, content_key)
+        .await
+        .map_err(|e| {
+            log::error!("complete_upload: {}", e);
+            error::ErrorInternalServerError("Failed to store upload.")
+        })
+}
+
+/// Stores a verified upload as it was sent.
+async fn insert_file(
+    session: &upload_sessions::Model,
+    content_key: &str,
+    mime: &Mime,
+) -> Result<UploadResponse, Error> {
+    store_verified(&session.storage_key, content_key).await?;
+    insert_attachment_record(
+        session.hash.to_owned(),
+        content_key.to_owned(),
+        session.filesize,
+        mime.as_ref(),
+        None,
+    )
+    .await
+}
+
+/// Stores a still image the way a direct upload would be: without metadata,
+/// with thumbnails and alternate formats generated in the background.
+async fn insert_image(
+    session: &upload_sessions::Model,
+    content_key: &str,
+    mime: &Mime,
+    data: Vec<u8>,
+    preserve_orientation: bool,
+) -> Result<UploadResponse, Error> {
+    let (stripped, dimensions) = web::block(move || {
+        let stripped = crate::imaging::metadata::strip_metadata(&data, preserve_orientation);
+        let dimensions =
+            image::ImageReader::new(std::io::Cursor::new(stripped.as_deref().unwrap_or(&data)))
+                .with_guessed_format()
+                .ok()
+                .and_then(|reader| reader.into_dimensions().ok());
+        (stripped.unwrap_or(data), dimensions)
+    })
+    .await
+    .map_err(error::ErrorInternalServerError)?;
+
+    let response = if blake3::hash(&stripped).to_string() == session.hash {
+        store_verified(&session.storage_key, content_key).await?;
+        insert_attachment_record(
+            session.hash.to_owned(),
+            content_key.to_owned(),
+            session.filesize,
+            mime.as_ref(),
+            dimensions,
+        )
+        .await?
+    } else {
+        // The copy with metadata is never stored under its own hash.
+        let extension = content_key
+            .split_once('.')
+            .map(|(_, extension)| extension.to_owned())
+            .unwrap_or_default();
+        let response =
+            insert_generated_attachment(stripped, mime.as_ref(), &extension, dimensions).await?;
+        discard_staged(&session.storage_key).await;
+        response
+    };
+
+    enqueue_image_variants(response.id).await;
+    enqueue_attachment_thumbnails(response.id).await;
+
+    Ok(response)
+}
@@ -0,0 +1,154 @@
+//! Reference counting for content-addressed attachments.
+//!
+//! Every distinct file is stored once, under its hash, and shared by every
+//! post, avatar, icon or rendition that uses it. Database triggers keep
+//! `attachments.reference_count` current as those references come and go.
+//! A file is only removed from storage once nothing refers to it and it has
+//! gone unseen for the retention period, which leaves time for fresh uploads
+//! to be attached to the post they were uploaded for. Quarantined files keep
+//! their row so the same file is refused if it is uploaded again.
+
+use super::get_storage;
+use crate::db::get_db_pool;
+use crate::orm::attachments::{self, ScanStatus};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{
+    entity::*, query::*, ConnectionTrait, DbBackend, DbErr, FromQueryResult, Statement,
+    TransactionTrait,
+};
+
+/// Largest number of files removed by a single cleanup pass.
+const CLEANUP_BATCH_SIZE: u64 = 100;
+
+/// Finds attachments nothing refers to that were last seen before `cutoff`.
+pub async fn find_unreferenced_attachments(
+    cutoff: NaiveDateTime,
+    limit: u64,
+) -> Result<Vec<attachments::Model>, DbErr> {
+    attachments::Entity::find()
+        .filter(attachments::Column::ReferenceCount.eq(0))
+        .filter(attachments::Column::LastSeenAt.lt(cutoff))
+        .filter(attachments::Column::ScanStatus.ne(ScanStatus::Infected))
+        .order_by_asc(attachments::Column::LastSeenAt)
+        .limit(limit)
+        .all(get_db_pool())
+        .await
+}
+
+/// Deletes an attachment's row if it is still unreferenced and was last seen
+/// before `cutoff`. Returns the storage key to remove, or `None` if the row
+/// was kept or another row still points at the same file.
+///
+/// Its thumbnail links go with it, which releases the thumbnails in turn.
+pub async fn delete_unreferenced_record(
+    attachment_id: i32,
+    cutoff: NaiveDateTime,
+) -> Result<Option<String>, DbErr> {
+    #[derive(FromQueryResult)]
+    struct Locked {
+        filename: String,
+    }
+
+    #[derive(FromQueryResult)]
+    struct Shared {
+        count: i64,
+    }
+
+    let txn = get_db_pool().begin().await?;
+
+    // The row lock makes a reference added meanwhile wait for our decision.
+    let locked = Locked::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT filename FROM attachments
+        WHERE id = $1 AND reference_count = 0 AND last_seen_at < $2
+          AND scan_status <> 'infected'
+        FOR UPDATE
+        "#,
+        vec![attachment_id.into(), cutoff.into()],
+    ))
+    .one(&txn)
+    .await?;
+
+    let filename = match locked {
+        Some(locked) => locked.filename,
+        None => {
+            txn.rollback().await?;
+            return Ok(None);
+        }
+    };
+
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "DELETE FROM attachment_thumbnails WHERE attachment_id = $1",
+        vec![attachment_id.into()],
+    ))
+    .await?;
+    txn.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "DELETE FROM attachments WHERE id = $1",
+        vec![attachment_id.into()],
+    ))
+    .await?;
+
+    let shared = Shared::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT COUNT(*) AS count FROM attachments WHERE filename = $1",
+        vec![filename.to_owned().into()],
+    ))
+    .one(&txn)
+    .await?
+    .map(|shared| shared.count)
+    .unwrap_or(0);
+
+    txn.commit().await?;
+
+    Ok((shared == 0).then_some(filename))
+}
+
+/// Removes an unreferenced attachment and, if it was the last row using it,
+/// its file in storage. Returns whether the attachment was removed.
+pub async fn release_attachment(attachment_id: i32, cutoff: NaiveDateTime) -> bool {
+    match delete_unreferenced_record(attachment_id, cutoff).await {
+        Ok(Some(filename)) => {
+            log::info!("release_attachment: deleting {}", filename);
+            if let Err(e) = get_storage().delete_object(&filename).await {
+                log::error!("release_attachment: {}: {}", filename, e);
+            }
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            log::error!("release_attachment: {}: {}", attachment_id, e);
+            false
+        }
+    }
+}
+
+/// Deletes files that nothing has referred to for the configured retention period.
+pub async fn cleanup_unreferenced_attachments() {
+    let retention_hours = crate::app_config::storage().unreferenced_retention_hours as i64;
+    let cutoff = Utc::now().naive_utc() - Duration::hours(retention_hours);
+
+    let unreferenced = match find_unreferenced_attachments(cutoff, CLEANUP_BATCH_SIZE).await {
+        Ok(unreferenced) => unreferenced,
+        Err(e) => {
+            log::error!("cleanup_unreferenced_attachments: {}", e);
+            return;
+        }
+    };
+
+    let mut released = 0;
+    for attachment in unreferenced {
+        if release_attachment(attachment.id, cutoff).await {
+            released += 1;
+        }
+    }
+
+    if released > 0 {
+        log::info!(
+            "cleanup_unreferenced_attachments: removed {} unreferenced files",
+            released
+        );
+    }
+}
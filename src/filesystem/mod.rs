@@ -0,0 +1,1250 @@
+use crate::attachment::{get_attachment_by_hash, update_attachment_last_seen};
+use crate::db::get_db_pool;
+use crate::jobs::{self, Job, JobKind};
+use crate::orm::attachments;
+use crate::storage::StorageBackend;
+use actix_multipart::{Field, Multipart};
+use actix_web::{error, get, post, web, Error, Responder};
+use chrono::Utc;
+use futures::{StreamExt, TryStreamExt};
+use mime::Mime;
+use once_cell::sync::OnceCell;
+use sea_orm::{entity::*, query::*, FromQueryResult, QueryFilter};
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+pub mod browser;
+pub mod chunked;
+pub mod dedup;
+pub mod quota;
+pub mod visibility;
+
+static MIME_LOOKUP: OnceCell<HashMap<&'static str, &'static str>> = OnceCell::new();
+static EXT_LOOKUP: OnceCell<HashMap<&'static str, &'static str>> = OnceCell::new();
+static DIR_TMP: OnceCell<String> = OnceCell::new();
+static STORAGE: OnceCell<Box<dyn StorageBackend>> = OnceCell::new();
+
+#[inline(always)]
+fn get_mime_lookup() -> &'static HashMap<&'static str, &'static str> {
+    unsafe { MIME_LOOKUP.get_unchecked() }
+}
+#[inline(always)]
+fn get_ext_lookup() -> &'static HashMap<&'static str, &'static str> {
+    unsafe { EXT_LOOKUP.get_unchecked() }
+}
+#[inline(always)]
+fn get_dir_tmp() -> &'static str {
+    unsafe { DIR_TMP.get_unchecked() }
+}
+#[inline(always)]
+pub fn get_storage() -> &'static dyn StorageBackend {
+    unsafe { STORAGE.get_unchecked().as_ref() }
+}
+
+/// MIME type for a file extension, for files stored without one. Where an
+/// extension is used for audio and video, video is assumed.
+pub fn mime_for_extension(extension: &str) -> Option<&'static str> {
+    let extension = match extension.to_ascii_lowercase().as_str() {
+        "jpg" | "jpe" => "jpeg".to_owned(),
+        other => other.to_owned(),
+    };
+    get_mime_lookup()
+        .iter()
+        .filter(|(_, ext)| **ext == extension)
+        .map(|(mime, _)| *mime)
+        .max()
+}
+
+/// Returns an unused path in the temporary upload directory.
+pub fn get_tmp_path() -> PathBuf {
+    PathBuf::from(format!("{}/{}", get_dir_tmp(), Uuid::new_v4()))
+}
+
+/// MUST be called ONCE before using functions in this module
+pub fn init() {
+    // Check Cache Dir
+    let cache_dir = std::env::var("DIR_TMP")
+        .expect("missing DIR_TMP environment variable (hint: 'DIR_TMP=./tmp')");
+    let cache_path = Path::new(&cache_dir);
+    if !cache_path.exists() {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .create(cache_path)
+            .expect("failed to create DIR_TMP");
+    }
+
+    DIR_TMP
+        .set(
+            std::env::var("DIR_TMP")
+                .expect("missing DIR_TMP environment variable (hint: 'DIR_TMP=./tmp')"),
+        )
+        .unwrap();
+
+    // Initialize storage backend based on config
+    let storage_config = crate::app_config::storage();
+
+    // Restricted attachments are signed with their own key, or the session key.
+    let signing_key = match storage_config.url_signing_key.is_empty() {
+        false => storage_config.url_signing_key.to_owned(),
+        true => std::env::var("SECRET_KEY").unwrap_or_default(),
+    };
+    crate::storage::signing::init(signing_key.as_bytes());
+
+    let storage: Box<dyn StorageBackend> = match storage_config.backend.as_str() {
+        "local" => {
+            log::info!(
+                "Initializing local storage at: {}",
+                storage_config.local_path
+            );
+            Box::new(
+                crate::storage::local::LocalStorage::new(storage_config.local_path.into())
+                    .expect("Failed to initialize local storage"),
+            )
+        }
+        "s3" => {
+            log::info!("Initializing S3 storage: {}", storage_config.s3_bucket);
+            Box::new(crate::storage::s3::S3Storage::new(
+                rusoto_core::Region::Custom {
+                    name: storage_config.s3_region,
+                    endpoint: storage_config.s3_endpoint,
+                },
+                storage_config.s3_bucket,
+                storage_config.s3_public_url,
+                (!storage_config.s3_access_key.is_empty()).then(|| {
+                    rusoto_core::credential::AwsCredentials::new(
+                        storage_config.s3_access_key,
+                        storage_config.s3_secret_key,
+                        None,
+                        None,
+                    )
+                }),
+            ))
+        }
+        "gcs" => {
+            log::info!("Initializing GCS storage: {}", storage_config.gcs_bucket);
+            if storage_config.gcs_access_id.is_empty() || storage_config.gcs_secret.is_empty() {
+                panic!("GCS storage requires gcs_access_id and gcs_secret.");
+            }
+            Box::new(crate::storage::gcs::GcsStorage::new(
+                storage_config.gcs_bucket,
+                storage_config.gcs_endpoint,
+                storage_config.gcs_access_id,
+                storage_config.gcs_secret,
+            ))
+        }
+        "azure" => {
+            log::info!(
+                "Initializing Azure Blob storage: {}",
+                storage_config.azure_container
+            );
+            Box::new(
+                crate::storage::azure::AzureStorage::new(
+                    storage_config.azure_account,
+                    &storage_config.azure_access_key,
+                    storage_config.azure_container,
+                    Some(storage_config.azure_endpoint),
+                )
+                .expect("Failed to initialize Azure Blob storage"),
+            )
+        }
+        other => panic!(
+            "Unknown storage backend: {}. Use 'local', 's3', 'gcs' or 'azure'.",
+            other
+        ),
+    };
+
+    if STORAGE.set(storage).is_err() {
+        panic!("STORAGE already initialized");
+    }
+
+    let map: HashMap<&'static str, &'static str> = HashMap::from([
+        ("aac", "aac"),
+        ("apng", "apng"),
+        ("avi", "avi"),
+        ("avif", "avif"),
+        ("bmp", "bmp"),
+        ("djvu", "djvu"),
+        ("flac", "flac"),
+        ("gif", "gif"),
+        ("htm", "html"),
+        ("html", "html"),
+        ("ico", "ico"),
+        ("jpeg", "jpeg"),
+        ("jpg", "jpeg"),
+        ("jfif", "jpeg"),
+        ("json", "json"),
+        ("ktx", "ktx"),
+        ("m4a", "m4a"),
+        ("mka", "mka"),
+        ("mkv", "mkv"),
+        ("mov", "mov"),
+        ("mp3", "mp3"),
+        ("mp4", "mp4"),
+        ("ogg", "ogg"),
+        ("ogv", "ogv"),
+        ("pdf", "pdf"),
+        ("png", "png"),
+        ("rm", "rm"),
+        ("sh", "sh"),
+        ("svg", "svg"),
+        ("txt", "txt"),
+        ("weba", "weba"),
+        ("webm", "webm"),
+        ("webp", "webp"),
+        ("xml", "xml"),
+        ("zip", "zip"),
+    ]);
+    EXT_LOOKUP.set(map).unwrap();
+
+    let map: HashMap<&'static str, &'static str> = HashMap::from([
+        ("application/json", "json"),
+        ("application/pdf", "pdf"),
+        ("application/vnd.rn-realmedia", "rm"),
+        ("application/x-sh", "sh"),
+        ("application/zip", "zip"),
+        ("audio/aac", "aac"),
+        ("audio/flac", "flac"),
+        ("audio/m4a", "m4a"),
+        ("audio/mp4", "mp4"),
+        ("audio/mpeg", "mp3"),
+        ("audio/ogg", "ogg"),
+        ("audio/webm", "weba"),
+        ("audio/x-matroska", "mka"),
+        ("image/apng", "apng"),
+        ("image/avif", "avif"),
+        ("image/bmp", "bmp"),
+        ("image/gif", "gif"),
+        ("image/jpeg", "jpeg"),
+        ("image/ktx", "ktx"),
+        ("image/png", "png"),
+        ("image/svg+xml", "svg"),
+        ("image/vnd.djvu", "djvu"),
+        ("image/webp", "webp"),
+        ("image/x-icon", "ico"),
+        ("text/html", "html"),
+        ("text/plain", "txt"),
+        ("text/xml", "xml"),
+        ("video/mp4", "mp4"),
+        ("video/ogg", "ogv"),
+        ("video/quicktime", "mov"),
+        ("video/webm", "webm"),
+        ("video/x-matroska", "mkv"),
+        ("video/x-msvideo", "avi"),
+    ]);
+    MIME_LOOKUP.set(map).unwrap();
+}
+
+#[derive(Deserialize)]
+pub struct FileHashFormData {
+    pub hash: String,
+}
+
+pub struct UploadPayload {
+    data: Vec<u8>,
+    filename: String,
+    hash: blake3::Hash,
+    tmp_path: PathBuf,
+    mime: Mime,
+}
+
+impl UploadPayload {
+    /// Check if the payload is an image
+    pub fn is_image(&self) -> bool {
+        self.mime.type_() == mime::IMAGE
+    }
+
+    /// Check if the payload is an SVG file
+    pub fn is_svg(&self) -> bool {
+        self.mime == mime::IMAGE_SVG || self.filename.to_lowercase().ends_with(".svg")
+    }
+
+    /// Check if the payload is an image or SVG (for icon uploads)
+    pub fn is_image_or_svg(&self) -> bool {
+        self.is_image() || self.is_svg()
+    }
+
+    /// Size of the upload in bytes
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Check if the upload contains no data
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Consumes the payload, deleting its temporary file, and returns the raw bytes.
+    /// Use this when the upload is transformed instead of stored as-is.
+    pub fn into_data(self) -> Vec<u8> {
+        if let Err(e) = std::fs::remove_file(&self.tmp_path) {
+            log::error!("into_data: delete tmp file error: {}", e);
+        }
+        self.data
+    }
+}
+
+/// Largest avatar upload accepted before cropping and resizing.
+pub const AVATAR_MAX_FILESIZE: usize = 10 * 1024 * 1024;
+
+#[derive(Debug, FromQueryResult, Serialize)]
+pub struct UploadResponse {
+    pub id: i32,
+    pub hash: String,
+    pub filename: String,
+}
+
+#[post("/fs/check-file")]
+pub async fn post_file_hash(form: web::Json<FileHashFormData>) -> Result<impl Responder, Error> {
+    // BLAKE3 hashes are 32 bytes, but hex-encoded they're 64 characters (2 hex digits per byte)
+    // This validation ensures the hash is properly hex-encoded
+    if form.hash.len() != 64 {
+        return Err(error::ErrorBadRequest(format!(
+            "Malformed BLAKE3 hash: expected 64 hex characters, got {}.",
+            form.hash.len()
+        )));
+    };
+
+    let file = attachments::Entity::find()
+        .column(attachments::Column::Id)
+        .column(attachments::Column::Hash)
+        .column(attachments::Column::Filename)
+        .filter(attachments::Column::Hash.eq(form.hash.to_owned()))
+        .into_model::<UploadResponse>()
+        .one(get_db_pool())
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(web::Json(file))
+}
+
+#[derive(Serialize)]
+pub struct TranscodeStatusResponse {
+    pub status: crate::orm::video_transcodes::TranscodeStatus,
+    pub duration_ms: Option<i32>,
+}
+
+/// Reports the transcode queue state of a video so pages can refresh once it is ready.
+/// Only answers for attachments the visitor could view.
+#[get("/fs/transcode-status/{attachment_id}")]
+pub async fn get_transcode_status(
+    client: crate::middleware::ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    let attachment_id = path.into_inner();
+    let visible = visibility::can_view_attachment(client.get_id(), attachment_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    if !visible {
+        return Err(error::ErrorNotFound(
+            "Attachment was not queued for transcoding.",
+        ));
+    }
+
+    let job = crate::transcode::get_status(attachment_id)
+        .await
+        .ok_or_else(|| error::ErrorNotFound("Attachment was not queued for transcoding."))?;
+
+    Ok(web::Json(TranscodeStatusResponse {
+        status: job.status,
+        duration_ms: job.duration_ms,
+    }))
+}
+
+#[post("/fs/upload-file")]
+pub async fn put_file(
+    client: crate::middleware::ClientCtx,
+    mut mutipart: Multipart,
+) -> Result<impl Responder, Error> {
+    // Require authentication for file uploads
+    client.require_login()?;
+
+    // see: https://users.rust-lang.org/t/file-upload-in-actix-web/64871/3
+    let mut responses: Vec<UploadResponse> = Vec::new();
+
+    // Iterate over multipart stream
+    while let Ok(Some(mut field)) = mutipart.try_next().await {
+        match insert_field_as_attachment(&mut field, &client).await {
+            Ok(response) => match response {
+                Some(response) => responses.push(response),
+                None => log::debug!("Threw out field: (empty)"),
+            },
+            // Going over quota or failing the virus scan is reported so the
+            // user knows why the file is missing.
+            Err(err)
+                if matches!(
+                    err.as_response_error().status_code(),
+                    actix_web::http::StatusCode::PAYLOAD_TOO_LARGE
+                        | actix_web::http::StatusCode::UNPROCESSABLE_ENTITY
+                ) =>
+            {
+                return Err(err)
+            }
+            Err(err) => log::debug!("Threw out field: {}", err),
+        }
+    }
+
+    Ok(web::Json(responses))
+}
+
+/// Attempts to locate existing copies of an upload.
+pub async fn deduplicate_payload(payload: &UploadPayload) -> Option<UploadResponse> {
+    // Look for an existing database entry
+    let model = get_attachment_by_hash(payload.hash.to_string()).await;
+
+    match model {
+        // Attachment exists in database - verify it also exists in storage
+        Some(attachment) => {
+            // Check if the file actually exists in storage
+            // (it might be in DB from old S3 uploads but not in local storage)
+            match get_storage().exists(&attachment.filename).await {
+                Ok(true) => {
+                    // File exists in storage, we can skip processing
+                    // Bump last_seen date on new thread.
+                    actix_web::rt::spawn(update_attachment_last_seen(attachment.id));
+                    // Return response now.
+                    Some(UploadResponse {
+                        id: attachment.id,
+                        hash: attachment.hash,
+                        filename: attachment.filename,
+                    })
+                }
+                Ok(false) => {
+                    // DB record exists but file is missing from storage
+                    // (e.g., migrated from S3 to local without file migration)
+                    log::warn!(
+                        "deduplicate_payload: DB record exists but file missing: {}",
+                        attachment.filename
+                    );
+                    None
+                }
+                Err(e) => {
+                    log::error!("deduplicate_payload: storage.exists error: {}", e);
+                    None
+                }
+            }
+        }
+        // Attachment is new and we need to process it
+        None => None,
+    }
+}
+
+fn get_extension(filename: &str, mime: &Mime) -> Option<String> {
+    // We check the MIME manually because the mime and mime_guess crates are both inadequate. We
+    // are only looking for formats where we can assume it is the only relevant extension.
+    // For example we'd never want to add a format like .gz to the hashmaps, we'd rely on _guess for that.
+    let result = get_mime_lookup().get(mime.as_ref().to_ascii_lowercase().as_str());
+    match result {
+        Some(v) => {
+            log::info!("MIME_LOOKUP: Found {}", v);
+            Some(v.to_string())
+        }
+        None => get_extension_guess(filename),
+    }
+}
+
+/// this is my fancy intelligent extension extractor
+fn get_extension_guess(filename: &str) -> Option<String> {
+    fn get_extension_guess_return(filename: &str, idx: usize) -> Option<String> {
+        Some(filename[idx + 1..].to_ascii_lowercase())
+    }
+    const MAX_EXT_LEN: usize = 24; // tar.zst.sha256sum.gpg rounded up to divisible by 8 for autism
+    const MAX_SUB_EXT_LEN: usize = 9; // longest extensions I can think of: sha256sum/gitignore
+
+    // get and specially check the top-level extension, we intentionally skip some rules
+    let mut begin_idx = match filename.rfind('.') {
+        Some(idx) => {
+            if idx == 0
+                || idx == filename.len()
+                || filename.len() - idx > MAX_SUB_EXT_LEN + 1 // +1 because we count the '.' here
+                || !filename[idx + 1..].chars().all(|x| x.is_ascii_alphanumeric())
+            {
+                return None;
+            }
+
+            // we have a list of extensions that we're okay with just accepting
+            match get_ext_lookup().get(&filename[idx + 1..]) {
+                Some(ext) => {
+                    log::error!("EXT_LOOKUP: {}", ext);
+                    return Some(ext.to_string());
+                }
+                None => idx,
+            }
+        }
+        None => return None,
+    };
+
+    loop {
+        let sub_str = &filename[..begin_idx];
+        log::error!("sub_str: {}", sub_str);
+
+        // find beginning of next possible extension
+        let new_idx = match sub_str.rfind('.') {
+            Some(idx) => idx,
+            None => return get_extension_guess_return(filename, begin_idx),
+        };
+
+        // check if double period
+        if new_idx == begin_idx - 1 {
+            log::info!("get_extension_greedy: found double");
+            return get_extension_guess_return(filename, begin_idx);
+        }
+
+        if filename.len() - new_idx > MAX_EXT_LEN {
+            log::info!("get_extension_greedy: more than MAX_EXT_LEN");
+            return get_extension_guess_return(filename, begin_idx);
+        }
+
+        // new sub-extension
+        let sub_ext = &sub_str[new_idx + 1..];
+
+        // check if too long
+        if sub_ext.len() > MAX_SUB_EXT_LEN {
+            log::info!("get_extension_greedy: more than MAX_SUB_EXT_LEN");
+            return get_extension_guess_return(filename, begin_idx);
+        }
+
+        // check if all numbers
+
+        if sub_ext.parse::<u32>().is_ok() {
+            log::info!("get_extension_greedy: all numbers");
+            return get_extension_guess_return(filename, begin_idx);
+        }
+
+        // check if isn't ASCII
+        if !sub_ext.chars().all(|x| x.is_ascii_alphanumeric()) {
+            log::info!("get_extension_greedy: not ASCII");
+            return get_extension_guess_return(filename, begin_idx);
+        }
+
+        begin_idx = new_idx;
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct SelectFilename {
+    pub filename: String,
+}
+
+#[inline(always)]
+pub fn get_file_url_by_filename(key: &str, filename: &str) -> String {
+    format!("/content/{}/{}", &key[0..=63], filename)
+}
+
+// Direct way of converting an actix_multipart field into an upload response.
+// Uploads by signed-in users are checked against and counted toward their
+// storage quota. New files are scanned for viruses when that is enabled, new
+// still images get thumbnail renditions generated in the background, and new
+// videos are queued for transcoding.
+pub async fn insert_field_as_attachment(
+    field: &mut Field,
+    client: &crate::middleware::ClientCtx,
+) -> Result<Option<UploadResponse>, Error> {
+    let payload = match save_field_as_temp_file(field, client.preserve_image_orientation()).await? {
+        Some(payload) => payload,
+        None => return Ok(None),
+    };
+
+    let user_id = client.get_id();
+    if let Some(user_id) = user_id {
+        let size = payload.len() as i64;
+        if let Err(e) = quota::check_upload_quota(user_id, &payload.hash.to_string(), size).await {
+            payload.into_data();
+            return Err(e);
+        }
+    }
+
+    let response = insert_new_field_payload(payload, user_id).await?;
+
+    if let (Some(user_id), Some(response)) = (user_id, &response) {
+        quota::record_upload(user_id, response.id).await;
+    }
+
+    Ok(response)
+}
+
+async fn insert_new_field_payload(
+    payload: UploadPayload,
+    uploader_id: Option<i32>,
+) -> Result<Option<UploadResponse>, Error> {
+    if let Some(response) = deduplicate_payload(&payload).await {
+        return Ok(Some(response));
+    }
+
+    // Animated GIFs would lose their animation, so only the original is kept.
+    let has_renditions = payload.is_image() && !payload.is_svg() && payload.mime != mime::IMAGE_GIF;
+    let is_video = payload.mime.type_() == mime::VIDEO;
+
+    let response = insert_payload_as_attachment(payload, None).await?;
+
+    if let Some(response) = &response {
+        crate::antivirus::scan_upload(response.id, uploader_id).await?;
+    }
+
+    if let (Some(response), true) = (&response, is_video) {
+        crate::transcode::enqueue(response.id).await;
+    }
+
+    if let (Some(response), true) = (&response, has_renditions) {
+        enqueue_image_variants(response.id).await;
+        enqueue_attachment_thumbnails(response.id).await;
+    }
+
+    Ok(response)
+}
+
+/// Attempts at generating an attachment's renditions before they are given up
+const RENDITION_MAX_ATTEMPTS: i32 = 3;
+
+/// Queues the thumbnail and medium renditions of an image attachment.
+pub async fn enqueue_attachment_thumbnails(attachment_id: i32) {
+    if let Err(e) = jobs::enqueue(
+        JobKind::AttachmentThumbnails,
+        serde_json::json!({ "attachment_id": attachment_id }),
+        RENDITION_MAX_ATTEMPTS,
+    )
+    .await
+    {
+        log::error!("enqueue_attachment_thumbnails: {}", e);
+    }
+}
+
+/// Queues the copies of an image attachment in the formats enabled under
+/// `[images]`, if any are.
+pub async fn enqueue_image_variants(attachment_id: i32) {
+    if crate::imaging::VariantFormat::enabled().is_empty() {
+        return;
+    }
+
+    if let Err(e) = jobs::enqueue(
+        JobKind::ImageVariants,
+        serde_json::json!({ "attachment_id": attachment_id }),
+        RENDITION_MAX_ATTEMPTS,
+    )
+    .await
+    {
+        log::error!("enqueue_image_variants: {}", e);
+    }
+}
+
+fn job_attachment_id(job: &Job) -> Result<i32, String> {
+    job.payload
+        .get("attachment_id")
+        .and_then(|id| id.as_i64())
+        .map(|id| id as i32)
+        .ok_or_else(|| "job has no attachment_id".to_owned())
+}
+
+/// Runs a queued [`JobKind::AttachmentThumbnails`] job.
+pub async fn run_thumbnail_job(job: &Job) -> Result<(), String> {
+    let attachment_id = job_attachment_id(job)?;
+    match read_stored_attachment(attachment_id).await? {
+        Some(data) => generate_attachment_thumbnails(attachment_id, data).await,
+        None => Ok(()),
+    }
+}
+
+/// Runs a queued [`JobKind::ImageVariants`] job.
+pub async fn run_variant_job(job: &Job) -> Result<(), String> {
+    let attachment_id = job_attachment_id(job)?;
+    match read_stored_attachment(attachment_id).await? {
+        Some(data) => generate_image_variants(attachment_id, data).await,
+        None => Ok(()),
+    }
+}
+
+/// Reads a stored attachment into memory, or `None` if it has been deleted.
+async fn read_stored_attachment(attachment_id: i32) -> Result<Option<Vec<u8>>, String> {
+    let Some(attachment) = attachments::Entity::find_by_id(attachment_id)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(None);
+    };
+
+    let object = get_storage()
+        .get_object(&attachment.filename, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut data = Vec::with_capacity(object.content_length.unwrap_or(0).max(0) as usize);
+    let mut body = object.body;
+    while let Some(chunk) = body.next().await {
+        data.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+    }
+    Ok(Some(data))
+}
+
+/// Generates and links the thumbnail and medium renditions of an image attachment.
+/// The original is used in place of any missing rendition.
+pub async fn generate_attachment_thumbnails(
+    attachment_id: i32,
+    data: Vec<u8>,
+) -> Result<(), String> {
+    let renditions = match web::block(move || {
+        crate::imaging::bounded_renditions(&data, &crate::imaging::THUMBNAIL_BOUNDS)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    {
+        Some(renditions) => renditions,
+        None => {
+            log::debug!(
+                "generate_attachment_thumbnails: attachment {} is not a decodable image",
+                attachment_id
+            );
+            return Ok(());
+        }
+    };
+
+    for rendition in renditions {
+        let thumbnail = insert_image_as_attachment(rendition)
+            .await
+            .map_err(|e| e.to_string())?;
+        enqueue_image_variants(thumbnail.id).await;
+        link_attachment_thumbnail(attachment_id, thumbnail.id)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Re-encodes an uploaded image into the formats enabled under `[images]` and
+/// links each copy to the original for content negotiation.
+pub async fn generate_image_variants(attachment_id: i32, data: Vec<u8>) -> Result<(), String> {
+    let formats = crate::imaging::VariantFormat::enabled();
+    if formats.is_empty() {
+        return Ok(());
+    }
+
+    let Some(variants) = web::block(move || crate::imaging::image_variants(&data, &formats))
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(());
+    };
+
+    for (format, variant) in variants {
+        let copy = insert_image_as_attachment(variant)
+            .await
+            .map_err(|e| e.to_string())?;
+        link_attachment_variant(attachment_id, copy.id, format.mime())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+pub type PayloadConstraintFn = fn(&attachments::ActiveModel) -> Result<bool, Error>;
+
+/// Receives a request payload and inserts it into the database and the s3 bucket.
+pub async fn insert_payload_as_attachment(
+    payload: UploadPayload,
+    constraints: Option<PayloadConstraintFn>,
+) -> Result<Option<UploadResponse>, Error> {
+    log::info!("Filename: {}", payload.filename);
+    log::info!("BLAKE3: {}", payload.hash);
+    log::info!("MIME: {}", payload.mime);
+
+    let dimensions: (Option<i32>, Option<i32>);
+    let extension: Option<String>;
+
+    match crate::ffmpeg::open_with_ffmpeg(&payload.tmp_path) {
+        Some(ffmpeg) => {
+            dimensions = match crate::ffmpeg::get_dimensions_from_input(&ffmpeg) {
+                Some(xy) => (Some(xy.0 as i32), Some(xy.1 as i32)),
+                None => (None, None),
+            };
+            extension = match crate::ffmpeg::get_extension_from_input(&ffmpeg) {
+                Some(ffext) => Some(ffext),
+                None => get_extension(&payload.filename, &payload.mime),
+            };
+        }
+        None => {
+            dimensions = (None, None);
+            extension = get_extension(&payload.filename, &payload.mime);
+        }
+    };
+
+    let filesize: i64 = payload.data.len().try_into().map_err(|e| {
+        log::error!(
+            "put_file: failed convert filesize from usize to i64, too big?: {}",
+            e
+        );
+        actix_web::error::ErrorInternalServerError("put_file: file too large")
+    })?;
+
+    let s3_filename = match extension {
+        Some(extension) => format!("{}.{}", payload.hash, extension),
+        None => payload.hash.to_string(),
+    };
+
+    let now = Utc::now().naive_utc();
+    let hash = &payload.hash.to_string();
+    let new_attachment = attachments::ActiveModel {
+        // This is our canonical filename, not the user's filename.
+        // User's filename belongs in ugc_attachments.
+        filename: Set(s3_filename.to_owned()),
+        hash: Set(hash.to_owned()),
+        first_seen_at: Set(now),
+        last_seen_at: Set(now),
+        filesize: Set(filesize),
+        file_width: Set(dimensions.0),
+        file_height: Set(dimensions.1),
+        mime: Set(payload.mime.to_string()),
+        meta: Set(sea_orm::query::JsonValue::Null),
+        ..Default::default()
+    };
+
+    // Custom constraint checks
+    // Before we insert into the database and save the file, ask the specific implementation
+    // if this file meets our requirements.
+    if let Some(constraint_fn) = constraints {
+        match constraint_fn(&new_attachment) {
+            Ok(_) => {}
+            Err(err) => {
+                log::error!("put_file constraints failed: {}", err);
+                return Err(actix_web::error::ErrorBadRequest(err));
+            }
+        }
+    }
+
+    // Each hash has a single row so its references are counted in one place.
+    // A row whose file went missing from storage is reused and the file restored.
+    let (id, s3_filename) = match get_attachment_by_hash(hash.to_owned()).await {
+        // Quarantined files are never restored.
+        Some(attachment) if attachment.scan_status.is_quarantined() => {
+            payload.into_data();
+            return Err(crate::antivirus::quarantined_error());
+        }
+        Some(attachment) => {
+            actix_web::rt::spawn(update_attachment_last_seen(attachment.id));
+            (attachment.id, attachment.filename)
+        }
+        None => {
+            // Insert the attachment into the database.
+            let res = attachments::Entity::insert(new_attachment)
+                .exec(get_db_pool())
+                .await
+                .map_err(|e| {
+                    log::error!("put_file: failed to put_object: {}", e);
+                    actix_web::error::ErrorInternalServerError("put_file: failed to store file")
+                })?;
+            (res.last_insert_id, s3_filename)
+        }
+    };
+
+    let storage = get_storage();
+    let file_exists = storage.exists(&s3_filename).await.map_err(|e| {
+        log::error!("put_file: failed to check if file exists: {}", e);
+        actix_web::error::ErrorInternalServerError("put_file: failed to check if file exists")
+    })?;
+
+    // Only upload if file doesn't already exist (deduplication)
+    if !file_exists {
+        // Insert the file data into storage.
+        storage
+            .put_object(payload.data, &s3_filename)
+            .await
+            .map_err(|e| {
+                log::error!("put_file: failed to put_object: {}", e);
+                actix_web::error::ErrorInternalServerError("put_file: failed to store file")
+            })?;
+    } else {
+        log::info!("put_file: duplicate upload, skipping storage put_object");
+    }
+
+    // !!! WARNING !!! we delete a file, be mindful and don't fucking delete my porn folder
+    log::warn!("Deleting Tmp File: {:#?}", payload.tmp_path);
+    std::fs::remove_file(payload.tmp_path).map_err(|e| {
+        log::error!("put_file: delete tmp file error: {}", e);
+        actix_web::error::ErrorInternalServerError("put_file: failed to store file")
+    })?;
+
+    Ok(Some(UploadResponse {
+        id,
+        hash: hash.to_owned(),
+        filename: s3_filename,
+    }))
+}
+
+/// Stores generated image data (such as a resized rendition) as an attachment.
+/// Identical data is deduplicated by hash like any other upload.
+pub async fn insert_image_as_attachment(
+    image: crate::imaging::EncodedImage,
+) -> Result<UploadResponse, Error> {
+    insert_generated_attachment(
+        image.data,
+        image.mime.as_ref(),
+        image.extension,
+        Some(image.dimensions),
+    )
+    .await
+}
+
+/// Stores server-generated file data, such as an image rendition or a video
+/// transcode, as an attachment. Identical data is deduplicated by hash.
+pub async fn insert_generated_attachment(
+    data: Vec<u8>,
+    mime: &str,
+    extension: &str,
+    dimensions: Option<(u32, u32)>,
+) -> Result<UploadResponse, Error> {
+    let hash = blake3::hash(&data).to_string();
+    let filename = format!("{}.{}", hash, extension);
+    let storage = get_storage();
+
+    if let Some(attachment) = get_attachment_by_hash(hash.to_owned()).await {
+        actix_web::rt::spawn(update_attachment_last_seen(attachment.id));
+        // Known files are never written twice; a missing one is restored in place.
+        if !storage.exists(&attachment.filename).await.unwrap_or(false) {
+            storage
+                .put_object(data, &attachment.filename)
+                .await
+                .map_err(|e| {
+                    log::error!("insert_generated_attachment: failed to put_object: {}", e);
+                    actix_web::error::ErrorInternalServerError("put_file: failed to store file")
+                })?;
+        }
+        return Ok(UploadResponse {
+            id: attachment.id,
+            hash: attachment.hash,
+            filename: attachment.filename,
+        });
+    }
+
+    let filesize: i64 = data.len().try_into().map_err(|e| {
+        log::error!("insert_generated_attachment: filesize overflow: {}", e);
+        actix_web::error::ErrorInternalServerError("put_file: file too large")
+    })?;
+
+    let response = insert_attachment_record(hash, filename, filesize, mime, dimensions).await?;
+
+    storage
+        .put_object(data, &response.filename)
+        .await
+        .map_err(|e| {
+            log::error!("insert_generated_attachment: failed to put_object: {}", e);
+            actix_web::error::ErrorInternalServerError("put_file: failed to store file")
+        })?;
+
+    Ok(response)
+}
+
+/// Inserts the database row for a file that is, or is about to be, in storage.
+/// If another upload of the same file got there first, its row is returned instead.
+async fn insert_attachment_record(
+    hash: String,
+    filename: String,
+    filesize: i64,
+    mime: &str,
+    dimensions: Option<(u32, u32)>,
+) -> Result<UploadResponse, Error> {
+    if let Some(attachment) = get_attachment_by_hash(hash.to_owned()).await {
+        return Ok(UploadResponse {
+            id: attachment.id,
+            hash: attachment.hash,
+            filename: attachment.filename,
+        });
+    }
+
+    let now = Utc::now().naive_utc();
+    let res = attachments::Entity::insert(attachments::ActiveModel {
+        filename: Set(filename.to_owned()),
+        hash: Set(hash.to_owned()),
+        first_seen_at: Set(now),
+        last_seen_at: Set(now),
+        filesize: Set(filesize),
+        file_width: Set(dimensions.map(|(width, _)| width as i32)),
+        file_height: Set(dimensions.map(|(_, height)| height as i32)),
+        mime: Set(mime.to_owned()),
+        meta: Set(sea_orm::query::JsonValue::Null),
+        ..Default::default()
+    })
+    .exec(get_db_pool())
+    .await
+    .map_err(|e| {
+        log::error!("insert_attachment_record: failed to insert: {}", e);
+        actix_web::error::ErrorInternalServerError("put_file: failed to store file")
+    })?;
+
+    Ok(UploadResponse {
+        id: res.last_insert_id,
+        hash,
+        filename,
+    })
+}
+
+/// Records `thumbnail_id` as a smaller rendition of `attachment_id`.
+pub async fn link_attachment_thumbnail(attachment_id: i32, thumbnail_id: i32) -> Result<(), Error> {
+    use crate::orm::attachment_thumbnails;
+
+    if attachment_id == thumbnail_id {
+        return Ok(());
+    }
+
+    let db = get_db_pool();
+    let existing = attachment_thumbnails::Entity::find_by_id((attachment_id, thumbnail_id))
+        .one(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    if existing.is_none() {
+        attachment_thumbnails::Entity::insert(attachment_thumbnails::ActiveModel {
+            attachment_id: Set(attachment_id),
+            thumbnail_id: Set(thumbnail_id),
+        })
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("link_attachment_thumbnail: {}", e);
+            error::ErrorInternalServerError("put_file: failed to store file")
+        })?;
+
+        if let Err(e) = visibility::inherit_visibility(attachment_id, thumbnail_id).await {
+            log::error!("link_attachment_thumbnail: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Records that `variant_id` is a copy of `attachment_id` in another format.
+pub async fn link_attachment_variant(
+    attachment_id: i32,
+    variant_id: i32,
+    mime: &str,
+) -> Result<(), Error> {
+    use crate::orm::attachment_variants;
+
+    if attachment_id == variant_id {
+        return Ok(());
+    }
+
+    let db = get_db_pool();
+    let existing = attachment_variants::Entity::find_by_id((attachment_id, mime.to_owned()))
+        .one(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    if existing.is_none() {
+        attachment_variants::Entity::insert(attachment_variants::ActiveModel {
+            attachment_id: Set(attachment_id),
+            variant_id: Set(variant_id),
+            mime: Set(mime.to_owned()),
+        })
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("link_attachment_variant: {}", e);
+            error::ErrorInternalServerError("put_file: failed to store file")
+        })?;
+
+        if let Err(e) = visibility::inherit_visibility(attachment_id, variant_id).await {
+            log::error!("link_attachment_variant: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Crops and resizes an uploaded image into the square avatar sizes.
+/// Returns the largest rendition; the smaller ones are linked to it as thumbnails.
+/// Animated GIFs are stored untouched so they keep playing.
+pub async fn insert_payload_as_avatar(
+    payload: UploadPayload,
+    crop: Option<crate::imaging::CropBox>,
+) -> Result<Option<UploadResponse>, Error> {
+    if !payload.is_image() || payload.is_svg() {
+        payload.into_data();
+        return Err(error::ErrorBadRequest(
+            "Avatars must be JPEG, PNG, GIF or WebP images.",
+        ));
+    }
+
+    if payload.len() > AVATAR_MAX_FILESIZE {
+        payload.into_data();
+        return Err(error::ErrorBadRequest("Avatar file is too large."));
+    }
+
+    if payload.mime == mime::IMAGE_GIF {
+        return match deduplicate_payload(&payload).await {
+            Some(response) => {
+                payload.into_data();
+                Ok(Some(response))
+            }
+            None => insert_payload_as_attachment(payload, None).await,
+        };
+    }
+
+    insert_image_as_avatar(payload.into_data(), crop).await
+}
+
+/// Stores a still image as an avatar, in every avatar size.
+/// Returns the largest rendition; the smaller ones are linked to it as thumbnails.
+pub async fn insert_image_as_avatar(
+    data: Vec<u8>,
+    crop: Option<crate::imaging::CropBox>,
+) -> Result<Option<UploadResponse>, Error> {
+    let renditions = web::block(move || crate::imaging::avatar_renditions(&data, crop))
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorBadRequest("Unable to read image."))?;
+
+    let mut renditions = renditions.into_iter();
+    let master = match renditions.next() {
+        Some(master) => insert_image_as_attachment(master).await?,
+        None => return Ok(None),
+    };
+
+    for rendition in renditions {
+        let thumbnail = insert_image_as_attachment(rendition).await?;
+        link_attachment_thumbnail(master.id, thumbnail.id).await?;
+    }
+
+    Ok(Some(master))
+}
+
+/// Accepts a multipart field, stores it on the disk, and returns indetifying information about it.
+pub async fn save_field_as_temp_file(
+    field: &mut Field,
+    preserve_orientation: bool,
+) -> Result<Option<UploadPayload>, Error> {
+    let content_type = field.content_disposition();
+    let filename = content_type
+        .get_filename()
+        .ok_or_else(|| actix_web::error::ErrorInternalServerError("put_file: missing filename"))?
+        .to_owned();
+
+    let f = web::block(move || {
+        let mut filepath;
+        let mut uuid;
+        loop {
+            uuid = format!("{}/{}", get_dir_tmp(), Uuid::new_v4());
+            filepath = Path::new(&uuid);
+            match filepath.metadata() {
+                Ok(metadata) => {
+                    log::error!(
+                        "put_file: file already exists: {:#?}\n{:#?}",
+                        filepath,
+                        metadata
+                    );
+                }
+                Err(e) => {
+                    match e.kind() {
+                        std::io::ErrorKind::NotFound => {
+                            break;
+                        }
+                        std::io::ErrorKind::PermissionDenied => {
+                            log::error!("put_file tmp permission error: {}", e);
+                            return Err(e);
+                        }
+                        _ => {
+                            log::error!("put_file unhandled fs error: {}", e);
+                            return Err(e);
+                        }
+                    };
+                }
+            }
+        }
+        log::info!(
+            "put_file: creating tmp file: {}",
+            filepath.to_str().unwrap()
+        );
+        Ok((File::create(filepath), filepath.to_owned()))
+    });
+
+    let mut hasher = blake3::Hasher::new();
+    // Pre-allocate a reasonable buffer for file uploads (64KB)
+    // Multipart streams don't reliably provide Content-Length, so we can't
+    // know the exact size upfront. This capacity will auto-grow as needed.
+    let mut buf: Vec<u8> = Vec::with_capacity(64 * 1024);
+
+    let (f, filepath) = f
+        .await
+        .map_err(|e| {
+            log::error!("put_file: {}", e);
+            actix_web::error::ErrorInternalServerError("put_file: saving data")
+        })?
+        .map_err(|e| {
+            log::error!("put_file: {}", e);
+            actix_web::error::ErrorInternalServerError("put_file: saving data")
+        })?;
+
+    let mut f = f.map_err(|e| {
+        log::error!("put_file: {}", e);
+        actix_web::error::ErrorInternalServerError("put_file: saving data")
+    })?;
+
+    while let Some(chunk) = field.next().await {
+        let bytes = chunk.map_err(|e| {
+            log::error!("put_file: multipart read error: {}", e);
+            actix_web::error::ErrorInternalServerError("put_file: error reading upload data")
+        })?;
+
+        hasher.update(&bytes);
+        buf.extend(bytes.to_owned());
+
+        f = web::block(move || f.write_all(&bytes.clone()).map(|_| f))
+            .await
+            .unwrap()?;
+    }
+
+    if buf.is_empty() {
+        log::debug!("save_field_as_temp_file: empty file, aborting");
+        //std::fs::remove_file(filename); tmp file never created if there are no bytes saved
+        return Ok(None);
+    }
+
+    // Images are stored without their EXIF/XMP metadata, so hash what is kept.
+    let (buf, hash) = match strip_payload_metadata(buf, &filepath, preserve_orientation).await? {
+        (buf, Some(hash)) => (buf, hash),
+        (buf, None) => (buf, hasher.finalize()),
+    };
+
+    Ok(Some(UploadPayload {
+        data: buf,
+        filename,
+        tmp_path: filepath, // Warning: This is deleted at the end of processing.
+        hash,
+        mime: field
+            .content_type()
+            .map(|m| m.to_owned())
+            .unwrap_or_else(|| "application/octet-stream".parse().unwrap()),
+    }))
+}
+
+/// Removes metadata from an uploaded image and rewrites its temporary file.
+/// Returns the new hash if anything was removed.
+async fn strip_payload_metadata(
+    data: Vec<u8>,
+    tmp_path: &Path,
+    preserve_orientation: bool,
+) -> Result<(Vec<u8>, Option<blake3::Hash>), Error> {
+    let tmp_path = tmp_path.to_owned();
+    web::block(move || {
+        match crate::imaging::metadata::strip_metadata(&data, preserve_orientation) {
+            Some(stripped) => {
+                std::fs::write(&tmp_path, &stripped)?;
+                let hash = blake3::hash(&stripped);
+                Ok((stripped, Some(hash)))
+            }
+            None => Ok((data, None)),
+        }
+    })
+    .await
+    .map_err(|e| {
+        log::error!("put_file: {}", e);
+        actix_web::error::ErrorInternalServerError("put_file: saving data")
+    })?
+    .map_err(|e: std::io::Error| {
+        log::error!("put_file: {}", e);
+        actix_web::error::ErrorInternalServerError("put_file: saving data")
+    })
+}
@@ -0,0 +1,246 @@
+//! Per-user attachment storage quotas.
+//!
+//! A user's usage is the total size of the distinct attachments they have
+//! uploaded, whether or not the file was already stored for someone else.
+//! Quotas are set on groups; a user gets the most generous quota among their
+//! groups, and none at all if any of their groups is unlimited.
+
+use crate::db::get_db_pool;
+use crate::orm::user_uploads;
+use actix_web::{error, Error};
+use chrono::Utc;
+use sea_orm::{entity::*, DbBackend, DbErr, FromQueryResult, Statement};
+
+/// A user's storage usage and limit, in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub used: i64,
+    pub files: i64,
+    /// `None` when the user's groups place no limit on uploads.
+    pub quota: Option<i64>,
+}
+
+impl StorageUsage {
+    /// Bytes left before the quota is reached.
+    pub fn remaining(&self) -> Option<i64> {
+        self.quota.map(|quota| (quota - self.used).max(0))
+    }
+
+    /// Share of the quota in use, from 0 to 100.
+    pub fn percent_used(&self) -> Option<i64> {
+        self.quota.map(|quota| match quota {
+            0 => 100,
+            quota => (self.used * 100 / quota).clamp(0, 100),
+        })
+    }
+
+    /// Whether at least 90% of the quota is in use.
+    pub fn is_nearly_full(&self) -> bool {
+        self.percent_used().is_some_and(|percent| percent >= 90)
+    }
+
+    /// Whether another `size` bytes fit within the quota.
+    pub fn allows(&self, size: i64) -> bool {
+        self.remaining().is_none_or(|remaining| size <= remaining)
+    }
+
+    pub fn used_display(&self) -> String {
+        format_bytes(self.used)
+    }
+
+    pub fn quota_display(&self) -> String {
+        self.quota
+            .map(format_bytes)
+            .unwrap_or_else(|| "Unlimited".to_string())
+    }
+}
+
+/// A row of the largest-consumers report.
+#[derive(Debug, FromQueryResult)]
+pub struct StorageConsumer {
+    pub user_id: i32,
+    pub name: Option<String>,
+    pub files: i64,
+    pub bytes: i64,
+    pub quota_mb: Option<i32>,
+}
+
+impl StorageConsumer {
+    pub fn bytes_display(&self) -> String {
+        format_bytes(self.bytes)
+    }
+
+    pub fn quota_display(&self) -> String {
+        self.quota_mb
+            .map(|mb| format_bytes(mb as i64 * 1024 * 1024))
+            .unwrap_or_else(|| "Unlimited".to_string())
+    }
+}
+
+/// Formats a byte count for display, e.g. `1.5 MB`.
+pub fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["bytes", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// SQL for a user's quota in megabytes: NULL if any of their groups is
+/// unlimited, or if they belong to no groups at all.
+const QUOTA_MB_SQL: &str = r#"
+    SELECT CASE WHEN bool_or(g.storage_quota_mb IS NULL) THEN NULL
+                ELSE MAX(g.storage_quota_mb) END
+    FROM user_groups ug
+    JOIN groups g ON g.id = ug.group_id
+    WHERE ug.user_id = u.id
+"#;
+
+/// Fetches a user's storage usage and quota.
+pub async fn get_storage_usage(user_id: i32) -> Result<StorageUsage, DbErr> {
+    #[derive(FromQueryResult)]
+    struct UsageRow {
+        used: i64,
+        files: i64,
+        quota_mb: Option<i32>,
+    }
+
+    let row = UsageRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            r#"
+            SELECT COALESCE(SUM(a.filesize), 0)::BIGINT AS used,
+                   COUNT(a.id) AS files,
+                   ({}) AS quota_mb
+            FROM users u
+            LEFT JOIN user_uploads uu ON uu.user_id = u.id
+            LEFT JOIN attachments a ON a.id = uu.attachment_id
+            WHERE u.id = $1
+            GROUP BY u.id
+            "#,
+            QUOTA_MB_SQL
+        ),
+        vec![user_id.into()],
+    ))
+    .one(get_db_pool())
+    .await?;
+
+    Ok(match row {
+        Some(row) => StorageUsage {
+            used: row.used,
+            files: row.files,
+            quota: row.quota_mb.map(|mb| mb as i64 * 1024 * 1024),
+        },
+        None => StorageUsage {
+            used: 0,
+            files: 0,
+            quota: None,
+        },
+    })
+}
+
+/// Rejects an upload of `size` bytes that would take the user over quota.
+/// Files the user has uploaded before are already counted and always pass.
+pub async fn check_upload_quota(user_id: i32, hash: &str, size: i64) -> Result<(), Error> {
+    #[derive(FromQueryResult)]
+    struct Uploaded {
+        #[allow(dead_code)]
+        attachment_id: i32,
+    }
+
+    let db = get_db_pool();
+    let uploaded = Uploaded::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT uu.attachment_id
+        FROM user_uploads uu
+        JOIN attachments a ON a.id = uu.attachment_id
+        WHERE uu.user_id = $1 AND a.hash = $2
+        LIMIT 1
+        "#,
+        vec![user_id.into(), hash.into()],
+    ))
+    .one(db)
+    .await
+    .map_err(error::ErrorInternalServerError)?;
+
+    if uploaded.is_some() {
+        return Ok(());
+    }
+
+    let usage = get_storage_usage(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    if usage.allows(size) {
+        Ok(())
+    } else {
+        Err(error::ErrorPayloadTooLarge(format!(
+            "This {} file exceeds your storage quota: {} of {} used.",
+            format_bytes(size),
+            usage.used_display(),
+            usage.quota_display()
+        )))
+    }
+}
+
+/// Counts an attachment toward the user's usage. Repeat uploads are ignored.
+pub async fn record_upload(user_id: i32, attachment_id: i32) {
+    let db = get_db_pool();
+
+    match user_uploads::Entity::find_by_id((user_id, attachment_id))
+        .one(db)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            if let Err(e) = user_uploads::Entity::insert(user_uploads::ActiveModel {
+                user_id: Set(user_id),
+                attachment_id: Set(attachment_id),
+                created_at: Set(Utc::now().naive_utc()),
+            })
+            .exec(db)
+            .await
+            {
+                log::error!("record_upload: {}", e);
+            }
+        }
+        Err(e) => log::error!("record_upload: {}", e),
+    }
+}
+
+/// Users with the most attachment data, largest first.
+pub async fn get_largest_consumers(limit: u64) -> Result<Vec<StorageConsumer>, DbErr> {
+    StorageConsumer::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            r#"
+            SELECT u.id AS user_id,
+                   un.name,
+                   COUNT(a.id) AS files,
+                   COALESCE(SUM(a.filesize), 0)::BIGINT AS bytes,
+                   ({}) AS quota_mb
+            FROM users u
+            JOIN user_uploads uu ON uu.user_id = u.id
+            JOIN attachments a ON a.id = uu.attachment_id
+            LEFT JOIN user_names un ON un.user_id = u.id
+            GROUP BY u.id, un.name
+            ORDER BY bytes DESC, u.id ASC
+            LIMIT $1
+            "#,
+            QUOTA_MB_SQL
+        ),
+        vec![(limit as i64).into()],
+    ))
+    .all(get_db_pool())
+    .await
+}
@@ -0,0 +1,211 @@
+//! Attachment visibility levels.
+//!
+//! A stored file is shared by every post, message and avatar that uses it, so
+//! its visibility is the least restrictive of those uses: a file only sent in
+//! conversations is conversation-only, one also posted in the forum takes the
+//! configured post visibility, and one used as an avatar or icon is public.
+//! Thumbnails and other renditions follow their original.
+
+use super::get_storage;
+use crate::db::get_db_pool;
+use crate::orm::attachments::{self, AttachmentVisibility};
+use chrono::Utc;
+use sea_orm::{
+    entity::*, query::*, sea_query::Expr, ConnectionTrait, DbBackend, DbErr, FromQueryResult,
+    Statement,
+};
+
+/// Visibility of attachments in forum posts.
+pub fn post_attachment_visibility() -> AttachmentVisibility {
+    let configured = crate::app_config::storage().post_attachment_visibility;
+    match AttachmentVisibility::from_name(&configured) {
+        Some(AttachmentVisibility::Conversation) | None => {
+            log::warn!(
+                "Unsupported post_attachment_visibility {:?}; using public.",
+                configured
+            );
+            AttachmentVisibility::Public
+        }
+        Some(visibility) => visibility,
+    }
+}
+
+/// Picks the visibility for a file from how it is used. `other_uses` counts
+/// avatars, icons and anything else outside of posts and messages.
+/// Returns `None` for unused files, which keep their current visibility.
+pub fn visibility_for_uses(
+    other_uses: i64,
+    post_uses: i64,
+    conversation_uses: i64,
+    post_visibility: AttachmentVisibility,
+) -> Option<AttachmentVisibility> {
+    if other_uses > 0 {
+        Some(AttachmentVisibility::Public)
+    } else if post_uses > 0 {
+        Some(post_visibility)
+    } else if conversation_uses > 0 {
+        Some(AttachmentVisibility::Conversation)
+    } else {
+        None
+    }
+}
+
+/// Sets the visibility of an attachment and its renditions.
+pub async fn set_attachment_visibility(
+    attachment_id: i32,
+    visibility: AttachmentVisibility,
+) -> Result<(), DbErr> {
+    let db = get_db_pool();
+
+    attachments::Entity::update_many()
+        .col_expr(attachments::Column::Visibility, Expr::value(visibility))
+        .filter(attachments::Column::Id.eq(attachment_id))
+        .exec(db)
+        .await?;
+
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        UPDATE attachments SET visibility = $2
+        WHERE id IN (
+            SELECT thumbnail_id FROM attachment_thumbnails WHERE attachment_id = $1
+            UNION
+            SELECT variant_id FROM attachment_variants WHERE attachment_id = $1
+        ) AND id <> $1
+        "#,
+        vec![attachment_id.into(), visibility.into()],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Recomputes an attachment's visibility after it was linked somewhere.
+pub async fn refresh_attachment_visibility(attachment_id: i32) -> Result<(), DbErr> {
+    #[derive(FromQueryResult)]
+    struct Uses {
+        reference_count: i32,
+        ugc_uses: i64,
+        conversation_uses: i64,
+    }
+
+    let uses = Uses::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT a.reference_count,
+               (SELECT COUNT(*) FROM ugc_attachments ua
+                WHERE ua.attachment_id = a.id) AS ugc_uses,
+               (SELECT COUNT(*) FROM ugc_attachments ua
+                JOIN private_messages pm ON pm.ugc_id = ua.ugc_id
+                WHERE ua.attachment_id = a.id) AS conversation_uses
+        FROM attachments a
+        WHERE a.id = $1
+        "#,
+        vec![attachment_id.into()],
+    ))
+    .one(get_db_pool())
+    .await?;
+
+    let uses = match uses {
+        Some(uses) => uses,
+        None => return Ok(()),
+    };
+
+    let visibility = visibility_for_uses(
+        uses.reference_count as i64 - uses.ugc_uses,
+        uses.ugc_uses - uses.conversation_uses,
+        uses.conversation_uses,
+        post_attachment_visibility(),
+    );
+
+    match visibility {
+        Some(visibility) => set_attachment_visibility(attachment_id, visibility).await,
+        None => Ok(()),
+    }
+}
+
+/// Whether a visitor may see an attachment: anyone for public files, signed-in
+/// users for members-only files, and for conversation files the uploader or a
+/// participant of a conversation it was sent in.
+pub async fn can_view_attachment(user_id: Option<i32>, attachment_id: i32) -> Result<bool, DbErr> {
+    #[derive(FromQueryResult)]
+    struct Access {
+        visibility: AttachmentVisibility,
+        involved: bool,
+    }
+
+    let access = Access::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT a.visibility,
+               (EXISTS (SELECT 1 FROM user_uploads uu
+                        WHERE uu.attachment_id = a.id AND uu.user_id = $2)
+                OR EXISTS (SELECT 1 FROM ugc_attachments ua
+                           JOIN private_messages pm ON pm.ugc_id = ua.ugc_id
+                           JOIN conversation_participants cp
+                             ON cp.conversation_id = pm.conversation_id
+                           WHERE ua.attachment_id = a.id AND cp.user_id = $2)) AS involved
+        FROM attachments a
+        WHERE a.id = $1
+        "#,
+        vec![attachment_id.into(), user_id.into()],
+    ))
+    .one(get_db_pool())
+    .await?;
+
+    Ok(match access {
+        Some(access) => match access.visibility {
+            AttachmentVisibility::Public => true,
+            AttachmentVisibility::Members => user_id.is_some(),
+            AttachmentVisibility::Conversation => access.involved,
+        },
+        None => false,
+    })
+}
+
+/// Recomputes the visibility of each attachment, logging failures.
+pub async fn refresh_attachments_visibility(attachment_ids: impl IntoIterator<Item = i32>) {
+    for attachment_id in attachment_ids {
+        if let Err(e) = refresh_attachment_visibility(attachment_id).await {
+            log::error!("refresh_attachment_visibility: {}: {}", attachment_id, e);
+        }
+    }
+}
+
+/// Gives a newly linked rendition the visibility of its original.
+pub async fn inherit_visibility(attachment_id: i32, derived_id: i32) -> Result<(), DbErr> {
+    get_db_pool()
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+            UPDATE attachments SET visibility = original.visibility
+            FROM attachments original
+            WHERE original.id = $1 AND attachments.id = $2
+            "#,
+            vec![attachment_id.into(), derived_id.into()],
+        ))
+        .await?;
+
+    Ok(())
+}
+
+/// Expiry for a signed URL issued at `now`. Links are rounded up to the end of
+/// the next window, so pages rendered close together share URLs (and browser
+/// caches) and every link stays valid for at least one window.
+pub fn signed_url_expiry(now: i64, window_minutes: u32) -> i64 {
+    let window = window_minutes.max(1) as i64 * 60;
+    (now / window + 2) * window
+}
+
+/// URL of a stored file; restricted files get a signed link that expires.
+pub fn get_file_url(key: &str, filename: &str, visibility: AttachmentVisibility) -> String {
+    if visibility.is_restricted() {
+        let expires = signed_url_expiry(
+            Utc::now().timestamp(),
+            crate::app_config::storage().signed_url_expiry_minutes,
+        );
+        get_storage().signed_url(key, filename, expires)
+    } else {
+        super::get_file_url_by_filename(key, filename)
+    }
+}
@@ -1,7 +1,9 @@
+use crate::cache::{self, CacheScope};
 use crate::orm::{groups, user_groups};
 use crate::user::Profile as Client;
 use sea_orm::entity::prelude::{DeriveActiveEnum, EnumIter};
-use sea_orm::{entity::*, query::*, DatabaseConnection, FromQueryResult};
+use sea_orm::{entity::*, query::*, DatabaseConnection, DbErr, FromQueryResult};
+use std::collections::HashMap;
 
 /// Value set for a single permission.
 /// Compatible with sea_orm enum type.
@@ -32,36 +34,210 @@ pub async fn get_group_ids_for_client(
         pub id: i32,
     }
 
-    match client {
+    let cache_key = match client {
+        Some(user) => user.id.to_string(),
+        None => "guest".to_string(),
+    };
+    if let Some(ids) = cache::get(CacheScope::GroupIds, &cache_key).await {
+        return ids;
+    }
+
+    let result = match client {
         // Select `user_groups` where user_id is our client user.
-        Some(user) => match user_groups::Entity::find()
+        Some(user) => user_groups::Entity::find()
             .select_only()
             .column_as(user_groups::Column::GroupId, "id")
             .filter(user_groups::Column::UserId.eq(user.id))
             .into_model::<GroupId>()
             .all(db)
             .await
-        {
-            Ok(group_result) => group_result.iter().map(|group| group.id).collect(),
-            Err(e) => {
-                log::warn!("DbErr pulling user_groups for client: {:?}", e);
-                Vec::new()
-            }
-        },
+            .map_err(|e| log::warn!("DbErr pulling user_groups for client: {:?}", e)),
         // Select `groups` id for the system guest type.
-        None => match groups::Entity::find()
+        None => groups::Entity::find()
             .select_only()
             .column(groups::Column::Id)
             .filter(groups::Column::GroupType.eq(GroupType::SystemGuest))
             .into_model::<GroupId>()
             .all(db)
             .await
+            .map_err(|e| log::warn!("DbErr pulling groups for guest: {:?}", e)),
+    };
+
+    let parents = match load_parents(db).await {
+        Ok(parents) => parents,
+        Err(e) => {
+            log::warn!("DbErr pulling group parents: {:?}", e);
+            return Vec::new();
+        }
+    };
+
+    match result {
+        Ok(group_result) => {
+            let ids = with_ancestors(group_result.iter().map(|group| group.id), &parents);
+            cache::set(CacheScope::GroupIds, &cache_key, &ids).await;
+            ids
+        }
+        Err(()) => Vec::new(),
+    }
+}
+
+/// Parent of every group that has one.
+async fn load_parents(db: &DatabaseConnection) -> Result<HashMap<i32, i32>, DbErr> {
+    Ok(groups::Entity::find()
+        .filter(groups::Column::ParentId.is_not_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|group| Some((group.id, group.parent_id?)))
+        .collect())
+}
+
+/// `ids` and every group above them, as members of a group are members of
+/// its parent too.
+pub fn with_ancestors(ids: impl IntoIterator<Item = i32>, parents: &HashMap<i32, i32>) -> Vec<i32> {
+    let mut all = Vec::new();
+    for id in ids {
+        let mut current = Some(id);
+        while let Some(group_id) = current.filter(|group_id| !all.contains(group_id)) {
+            all.push(group_id);
+            current = parents.get(&group_id).copied();
+        }
+    }
+    all
+}
+
+/// True if making `parent_id` the parent of `group_id` would make a group
+/// its own ancestor.
+pub fn would_cycle(group_id: i32, parent_id: i32, parents: &HashMap<i32, i32>) -> bool {
+    with_ancestors([parent_id], parents).contains(&group_id)
+}
+
+/// True for a `#rrggbb` colour.
+pub fn is_valid_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// How a member's name is shown, taken from one of their groups.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupStyle {
+    /// The group it came from
+    pub label: String,
+    pub color: Option<String>,
+    pub banner: Option<String>,
+}
+
+impl GroupStyle {
+    /// Style of `group`, taking what it doesn't set from its parents. None if
+    /// neither it nor they set anything.
+    fn of(group: &groups::Model, groups: &HashMap<i32, &groups::Model>) -> Option<Self> {
+        let mut color = group.username_color.clone();
+        let mut banner = group.banner_text.clone();
+        let mut seen = vec![group.id];
+        let mut parent = group.parent_id;
+        while let Some(parent_group) = parent
+            .filter(|id| !seen.contains(id))
+            .and_then(|id| groups.get(&id))
         {
-            Ok(group_result) => group_result.iter().map(|group| group.id).collect(),
-            Err(e) => {
-                log::warn!("DbErr pulling groups for guest: {:?}", e);
-                Vec::new()
-            }
-        },
+            color = color.or_else(|| parent_group.username_color.clone());
+            banner = banner.or_else(|| parent_group.banner_text.clone());
+            seen.push(parent_group.id);
+            parent = parent_group.parent_id;
+        }
+
+        (color.is_some() || banner.is_some()).then(|| Self {
+            label: group.label.to_owned(),
+            color,
+            banner,
+        })
+    }
+}
+
+/// Style for a member of `member_of`: that of their highest priority group
+/// with one, the lowest id winning ties so it doesn't change between pages.
+pub fn display_style(all_groups: &[groups::Model], member_of: &[i32]) -> Option<GroupStyle> {
+    let by_id: HashMap<i32, &groups::Model> = all_groups.iter().map(|g| (g.id, g)).collect();
+    let mut ranked: Vec<&groups::Model> = member_of
+        .iter()
+        .filter_map(|id| by_id.get(id).copied())
+        .collect();
+    ranked.sort_by_key(|group| (std::cmp::Reverse(group.display_priority), group.id));
+    ranked
+        .into_iter()
+        .find_map(|group| GroupStyle::of(group, &by_id))
+}
+
+/// Display styles of `user_ids`, leaving out users none of whose groups
+/// style names.
+pub async fn display_styles(
+    db: &DatabaseConnection,
+    user_ids: &[i32],
+) -> Result<HashMap<i32, GroupStyle>, DbErr> {
+    if user_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let all_groups = groups::Entity::find().all(db).await?;
+    let mut member_of: HashMap<i32, Vec<i32>> = HashMap::new();
+    for membership in user_groups::Entity::find()
+        .filter(user_groups::Column::UserId.is_in(user_ids.to_vec()))
+        .all(db)
+        .await?
+    {
+        member_of
+            .entry(membership.user_id)
+            .or_default()
+            .push(membership.group_id);
+    }
+
+    Ok(member_of
+        .into_iter()
+        .filter_map(|(user_id, ids)| Some((user_id, display_style(&all_groups, &ids)?)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(id: i32, priority: i32, parent_id: Option<i32>, color: Option<&str>) -> groups::Model {
+        groups::Model {
+            id,
+            label: format!("Group {}", id),
+            group_type: GroupType::Normal,
+            storage_quota_mb: None,
+            display_priority: priority,
+            parent_id,
+            username_color: color.map(str::to_owned),
+            banner_text: None,
+        }
+    }
+
+    #[test]
+    fn test_with_ancestors() {
+        let parents = HashMap::from([(3, 2), (2, 1), (5, 4), (4, 5)]);
+        assert_eq!(with_ancestors([3], &parents), vec![3, 2, 1]);
+        assert_eq!(with_ancestors([2, 3], &parents), vec![2, 1, 3]);
+        // A loop stops once it comes round
+        assert_eq!(with_ancestors([4], &parents), vec![4, 5]);
+        assert!(would_cycle(1, 3, &parents));
+        assert!(!would_cycle(3, 1, &parents));
+    }
+
+    #[test]
+    fn test_display_style() {
+        let groups = vec![
+            group(1, 0, None, Some("#111111")),
+            group(2, 10, Some(1), None),
+            group(3, 10, None, Some("#333333")),
+            group(4, 20, None, None),
+        ];
+
+        // Group 4 styles nothing; 2 and 3 tie, and 2 takes its parent's colour
+        let style = display_style(&groups, &[4, 3, 2]).unwrap();
+        assert_eq!(style.label, "Group 2");
+        assert_eq!(style.color.as_deref(), Some("#111111"));
+        assert_eq!(display_style(&groups, &[4]), None);
+        assert!(is_valid_color("#a0B1c2"));
+        assert!(!is_valid_color("red"));
     }
 }
@@ -0,0 +1,333 @@
+//! Removes EXIF, XMP and text metadata from uploaded images.
+//!
+//! Phones embed GPS coordinates, device serials and timestamps in photos.
+//! Chunks that carry metadata are cut out of the container so the pixels are
+//! never re-encoded, except when an image must be rotated to keep its EXIF
+//! orientation.
+
+use super::encode_variant;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat};
+use std::io::Cursor;
+
+/// Returns a copy of the image without metadata, or `None` if the data is not
+/// a JPEG, PNG or WebP image or nothing had to be removed.
+///
+/// With `preserve_orientation`, images carrying an EXIF orientation are
+/// rotated so they still display upright once the tag is gone.
+pub fn strip_metadata(data: &[u8], preserve_orientation: bool) -> Option<Vec<u8>> {
+    let format = image::guess_format(data).ok()?;
+    if !matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP
+    ) {
+        return None;
+    }
+
+    if preserve_orientation {
+        if let Some(rotated) = apply_orientation(data, format) {
+            return Some(rotated);
+        }
+    }
+
+    let stripped = match format {
+        ImageFormat::Jpeg => strip_jpeg(data),
+        ImageFormat::Png => strip_png(data),
+        _ => strip_webp(data),
+    };
+
+    match stripped {
+        Some(stripped) if stripped.len() != data.len() => Some(stripped),
+        Some(_) => None,
+        None => {
+            log::warn!("strip_metadata: malformed {:?} container", format);
+            None
+        }
+    }
+}
+
+/// Re-encodes an image with its EXIF orientation applied to the pixels.
+/// Returns `None` when the image is already upright.
+fn apply_orientation(data: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+    let mut decoder = image::ImageReader::with_format(Cursor::new(data), format)
+        .into_decoder()
+        .ok()?;
+    let orientation = decoder.orientation().ok()?;
+    if orientation == Orientation::NoTransforms {
+        return None;
+    }
+
+    let mut image = match DynamicImage::from_decoder(decoder) {
+        Ok(image) => image,
+        Err(e) => {
+            log::debug!("strip_metadata: {}", e);
+            return None;
+        }
+    };
+    image.apply_orientation(orientation);
+
+    // Fresh encodes carry no metadata.
+    let mut out = Vec::new();
+    let result = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(
+            image::codecs::jpeg::JpegEncoder::new_with_quality(Cursor::new(&mut out), 90),
+        ),
+        ImageFormat::WebP => {
+            return encode_variant(&image, super::VariantFormat::WebP).map(|webp| webp.data)
+        }
+        _ => image.write_to(&mut Cursor::new(&mut out), ImageFormat::Png),
+    };
+
+    match result {
+        Ok(()) => Some(out),
+        Err(e) => {
+            log::error!("strip_metadata: {}", e);
+            None
+        }
+    }
+}
+
+/// Drops APP1 (EXIF/XMP), APP13 (IPTC) and comment segments.
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    const APP1: u8 = 0xE1;
+    const APP13: u8 = 0xED;
+    const COM: u8 = 0xFE;
+    const SOS: u8 = 0xDA;
+
+    if data.get(..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..2]);
+    let mut pos = 2;
+
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+
+        // Fill bytes and standalone markers have no length.
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            out.extend_from_slice(&data[pos..pos + 2]);
+            pos += 2;
+            continue;
+        }
+
+        let len = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > data.len() {
+            return None;
+        }
+
+        // Entropy-coded data follows the scan header; keep everything from here on.
+        if marker == SOS {
+            out.extend_from_slice(&data[pos..]);
+            return Some(out);
+        }
+
+        if !matches!(marker, APP1 | APP13 | COM) {
+            out.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+    }
+}
+
+/// Drops eXIf, text and timestamp chunks.
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    if data.get(..8)? != SIGNATURE {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&SIGNATURE);
+    let mut pos = 8;
+
+    while pos < data.len() {
+        let len = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        // Length, type, data and CRC.
+        let end = pos.checked_add(12 + len)?;
+        let kind = data.get(pos + 4..pos + 8)?;
+        if end > data.len() {
+            return None;
+        }
+
+        if !matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt" | b"tIME") {
+            out.extend_from_slice(&data[pos..end]);
+        }
+        if kind == b"IEND" {
+            return Some(out);
+        }
+        pos = end;
+    }
+
+    None
+}
+
+/// Drops EXIF and XMP chunks and clears their flags in the VP8X header.
+fn strip_webp(data: &[u8]) -> Option<Vec<u8>> {
+    const EXIF_FLAG: u8 = 0x08;
+    const XMP_FLAG: u8 = 0x04;
+
+    if data.get(..4)? != b"RIFF" || data.get(8..12)? != b"WEBP" {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[..12]);
+    let mut pos = 12;
+
+    while pos < data.len() {
+        let kind = data.get(pos..pos + 4)?;
+        let len = u32::from_le_bytes(data.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+        // Chunks are padded to an even length.
+        let end = pos.checked_add(8 + len + (len & 1))?.min(data.len());
+
+        match kind {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let start = out.len();
+                out.extend_from_slice(data.get(pos..end)?);
+                *out.get_mut(start + 8)? &= !(EXIF_FLAG | XMP_FLAG);
+            }
+            _ => out.extend_from_slice(&data[pos..end]),
+        }
+        pos = end;
+    }
+
+    let riff_size = u32::try_from(out.len() - 8).ok()?;
+    out[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An EXIF block in TIFF layout holding only an orientation tag.
+    fn exif_with_orientation(orientation: u16) -> Vec<u8> {
+        let mut tiff = b"MM\x00\x2a\x00\x00\x00\x08\x00\x01".to_vec();
+        tiff.extend_from_slice(&[0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01]);
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        tiff
+    }
+
+    fn jpeg_with_exif(width: u32, height: u32, orientation: u16) -> Vec<u8> {
+        let image = DynamicImage::new_rgb8(width, height);
+        let mut jpeg = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+
+        let mut payload = b"Exif\x00\x00".to_vec();
+        payload.extend(exif_with_orientation(orientation));
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        segment.extend(payload);
+
+        jpeg.splice(2..2, segment);
+        jpeg
+    }
+
+    fn png_with_chunk(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let image = DynamicImage::new_rgb8(4, 4);
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        // Inserted after IHDR; the CRC is not checked when stripping.
+        let mut chunk = (body.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(body);
+        chunk.extend_from_slice(&[0, 0, 0, 0]);
+        png.splice(33..33, chunk);
+        png
+    }
+
+    #[test]
+    fn jpeg_exif_is_removed() {
+        let jpeg = jpeg_with_exif(8, 4, 1);
+        let stripped = strip_metadata(&jpeg, false).unwrap();
+
+        assert!(!stripped.windows(4).any(|w| w == b"Exif"));
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn jpeg_orientation_is_applied() {
+        // Orientation 6 means the camera was rotated 90 degrees clockwise.
+        let jpeg = jpeg_with_exif(8, 4, 6);
+
+        let rotated = strip_metadata(&jpeg, true).unwrap();
+        let image = image::load_from_memory(&rotated).unwrap();
+        assert_eq!((image.width(), image.height()), (4, 8));
+        assert!(!rotated.windows(4).any(|w| w == b"Exif"));
+
+        let stripped = strip_metadata(&jpeg, false).unwrap();
+        let image = image::load_from_memory(&stripped).unwrap();
+        assert_eq!((image.width(), image.height()), (8, 4));
+    }
+
+    #[test]
+    fn png_text_chunks_are_removed() {
+        let png = png_with_chunk(b"tEXt", b"Author\x00Someone");
+        let stripped = strip_metadata(&png, false).unwrap();
+
+        assert!(!stripped.windows(4).any(|w| w == b"tEXt"));
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn clean_images_are_left_alone() {
+        let png = super::super::encode(&DynamicImage::new_rgb8(4, 4), ImageFormat::Png).unwrap();
+        assert!(strip_metadata(&png.data, true).is_none());
+        assert!(strip_metadata(b"not an image", true).is_none());
+    }
+
+    #[test]
+    fn webp_exif_chunk_is_removed() {
+        let webp = encode_variant(
+            &DynamicImage::new_rgb8(4, 4),
+            super::super::VariantFormat::WebP,
+        )
+        .unwrap()
+        .data;
+
+        // Wrap the bitstream in an extended container with an EXIF chunk.
+        let mut vp8x = b"VP8X".to_vec();
+        vp8x.extend_from_slice(&10u32.to_le_bytes());
+        vp8x.extend_from_slice(&[0x08, 0, 0, 0, 3, 0, 0, 3, 0, 0]);
+        let exif = exif_with_orientation(1);
+        let mut exif_chunk = b"EXIF".to_vec();
+        exif_chunk.extend_from_slice(&(exif.len() as u32).to_le_bytes());
+        exif_chunk.extend(&exif);
+        if exif.len() % 2 == 1 {
+            exif_chunk.push(0);
+        }
+
+        let mut extended = b"RIFF\x00\x00\x00\x00WEBP".to_vec();
+        extended.extend(vp8x);
+        extended.extend_from_slice(&webp[12..]);
+        extended.extend(exif_chunk);
+        let size = (extended.len() - 8) as u32;
+        extended[4..8].copy_from_slice(&size.to_le_bytes());
+
+        let stripped = strip_metadata(&extended, false).unwrap();
+        assert!(!stripped.windows(4).any(|w| w == b"EXIF"));
+        assert_eq!(stripped[20] & 0x08, 0);
+        assert_eq!(
+            u32::from_le_bytes(stripped[4..8].try_into().unwrap()) as usize,
+            stripped.len() - 8
+        );
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+}
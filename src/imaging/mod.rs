@@ -0,0 +1,358 @@
+//! Server-side image manipulation for uploads.
+//!
+//! Decoding and resizing are CPU bound; callers on the async runtime should
+//! run these functions through `web::block`.
+
+pub mod metadata;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use std::io::Cursor;
+
+/// Square sizes generated for every avatar, largest first.
+/// The largest is stored as the avatar itself and the rest as its thumbnails.
+/// These cover `AttachmentSize` at 1x and 2x pixel density.
+pub const AVATAR_SIZES: [u32; 5] = [288, 192, 96, 48, 24];
+
+/// Bounding boxes for attachment renditions, smallest first: the thumbnail
+/// shown in post embeds and a medium size for previews.
+pub const THUMBNAIL_BOUNDS: [u32; 2] = [200, 800];
+
+/// A square region of the source image, in source pixels, chosen by the client.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CropBox {
+    pub x: u32,
+    pub y: u32,
+    pub size: u32,
+}
+
+impl CropBox {
+    /// Fits the box inside an image of the given dimensions.
+    /// Falls back to the largest centered square if the box is empty.
+    pub fn clamp_to(self, (width, height): (u32, u32)) -> Self {
+        let max = width.min(height);
+        if self.size == 0 || max == 0 {
+            return Self::centered((width, height));
+        }
+
+        let size = self.size.min(max);
+        Self {
+            x: self.x.min(width - size),
+            y: self.y.min(height - size),
+            size,
+        }
+    }
+
+    /// The largest square centered in an image of the given dimensions.
+    pub fn centered((width, height): (u32, u32)) -> Self {
+        let size = width.min(height);
+        Self {
+            x: (width - size) / 2,
+            y: (height - size) / 2,
+            size,
+        }
+    }
+}
+
+/// An encoded image ready to be stored as an attachment.
+pub struct EncodedImage {
+    pub data: Vec<u8>,
+    pub mime: mime::Mime,
+    pub extension: &'static str,
+    pub dimensions: (u32, u32),
+}
+
+/// Modern formats images are re-encoded into for browsers that accept them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VariantFormat {
+    Avif,
+    WebP,
+}
+
+impl VariantFormat {
+    /// Every variant format, most preferred first.
+    pub const ALL: [VariantFormat; 2] = [VariantFormat::Avif, VariantFormat::WebP];
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            VariantFormat::Avif => "image/avif",
+            VariantFormat::WebP => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VariantFormat::Avif => "avif",
+            VariantFormat::WebP => "webp",
+        }
+    }
+
+    /// Whether this build can encode the format.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            VariantFormat::Avif => cfg!(feature = "avif"),
+            VariantFormat::WebP => true,
+        }
+    }
+
+    /// Formats switched on in the `[images]` config section and supported by this build.
+    pub fn enabled() -> Vec<VariantFormat> {
+        let config = crate::app_config::images();
+        if config.avif && !VariantFormat::Avif.is_supported() {
+            log::warn!("images.avif is enabled but this build lacks the `avif` feature");
+        }
+
+        Self::ALL
+            .into_iter()
+            .filter(|format| match format {
+                VariantFormat::Avif => config.avif,
+                VariantFormat::WebP => config.webp,
+            })
+            .filter(VariantFormat::is_supported)
+            .collect()
+    }
+
+    fn source_format(&self) -> ImageFormat {
+        match self {
+            VariantFormat::Avif => ImageFormat::Avif,
+            VariantFormat::WebP => ImageFormat::WebP,
+        }
+    }
+}
+
+/// Decodes an uploaded image, guessing the format from its contents.
+pub fn decode(data: &[u8]) -> Option<(DynamicImage, ImageFormat)> {
+    let reader = image::ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?;
+    let format = reader.format()?;
+    match reader.decode() {
+        Ok(image) => Some((image, format)),
+        Err(e) => {
+            log::debug!("imaging::decode: {}", e);
+            None
+        }
+    }
+}
+
+/// Crops a square out of an image. Without a crop box the image is center-cropped.
+pub fn crop_square(image: &DynamicImage, crop: Option<CropBox>) -> DynamicImage {
+    let dimensions = image.dimensions();
+    let crop = match crop {
+        Some(crop) => crop.clamp_to(dimensions),
+        None => CropBox::centered(dimensions),
+    };
+    image.crop_imm(crop.x, crop.y, crop.size, crop.size)
+}
+
+/// Scales a square image to `px` by `px`.
+pub fn resize_square(image: &DynamicImage, px: u32) -> DynamicImage {
+    image.resize_exact(px, px, FilterType::Lanczos3)
+}
+
+/// Re-encodes an image. JPEG sources stay JPEG; everything else becomes PNG
+/// so transparency survives.
+pub fn encode(image: &DynamicImage, source: ImageFormat) -> Option<EncodedImage> {
+    let (format, mime, extension) = match source {
+        ImageFormat::Jpeg => (ImageFormat::Jpeg, mime::IMAGE_JPEG, "jpeg"),
+        _ => (ImageFormat::Png, mime::IMAGE_PNG, "png"),
+    };
+
+    let mut data = Vec::new();
+    let result = match format {
+        // JPEG has no alpha channel.
+        ImageFormat::Jpeg => {
+            DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut Cursor::new(&mut data), format)
+        }
+        _ => image.write_to(&mut Cursor::new(&mut data), format),
+    };
+
+    match result {
+        Ok(()) => Some(EncodedImage {
+            data,
+            mime,
+            extension,
+            dimensions: image.dimensions(),
+        }),
+        Err(e) => {
+            log::error!("imaging::encode: {}", e);
+            None
+        }
+    }
+}
+
+/// Encodes an image into a variant format.
+pub fn encode_variant(image: &DynamicImage, format: VariantFormat) -> Option<EncodedImage> {
+    // Both encoders only take 8-bit RGB(A).
+    let image = if image.color().has_alpha() {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    };
+
+    let mut data = Vec::new();
+    let result = match format {
+        VariantFormat::WebP => image.write_with_encoder(
+            image::codecs::webp::WebPEncoder::new_lossless(Cursor::new(&mut data)),
+        ),
+        #[cfg(feature = "avif")]
+        VariantFormat::Avif => image.write_with_encoder(
+            image::codecs::avif::AvifEncoder::new_with_speed_quality(Cursor::new(&mut data), 8, 70),
+        ),
+        #[cfg(not(feature = "avif"))]
+        VariantFormat::Avif => return None,
+    };
+
+    match result {
+        Ok(()) => Some(EncodedImage {
+            data,
+            mime: format.mime().parse().ok()?,
+            extension: format.extension(),
+            dimensions: image.dimensions(),
+        }),
+        Err(e) => {
+            log::error!("imaging::encode_variant: {}", e);
+            None
+        }
+    }
+}
+
+/// Re-encodes an image into each requested format.
+/// Variants that are not smaller than the source are dropped; the original serves best then.
+pub fn image_variants(
+    data: &[u8],
+    formats: &[VariantFormat],
+) -> Option<Vec<(VariantFormat, EncodedImage)>> {
+    let (image, source) = decode(data)?;
+
+    Some(
+        formats
+            .iter()
+            .filter(|format| format.source_format() != source)
+            .filter_map(|format| Some((*format, encode_variant(&image, *format)?)))
+            .filter(|(_, variant)| variant.data.len() < data.len())
+            .collect(),
+    )
+}
+
+/// Produces every avatar rendition from an uploaded image, largest first.
+/// Sizes larger than the cropped region are skipped so small uploads are never upscaled.
+pub fn avatar_renditions(data: &[u8], crop: Option<CropBox>) -> Option<Vec<EncodedImage>> {
+    let (image, format) = decode(data)?;
+    let square = crop_square(&image, crop);
+    let side = square.width();
+
+    let mut sizes: Vec<u32> = AVATAR_SIZES
+        .iter()
+        .copied()
+        .filter(|px| *px <= side)
+        .collect();
+    if sizes.is_empty() {
+        sizes.push(side);
+    }
+
+    sizes
+        .into_iter()
+        .map(|px| encode(&resize_square(&square, px), format))
+        .collect()
+}
+
+/// Scales an image down to fit inside each bounding box, keeping its aspect ratio.
+/// Boxes the image already fits inside are skipped; the original serves those sizes.
+pub fn bounded_renditions(data: &[u8], bounds: &[u32]) -> Option<Vec<EncodedImage>> {
+    let (image, format) = decode(data)?;
+    let (width, height) = image.dimensions();
+
+    bounds
+        .iter()
+        .filter(|px| width.max(height) > **px)
+        .map(|px| encode(&image.resize(*px, *px, FilterType::Lanczos3), format))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crop_box_clamps_to_image() {
+        let crop = CropBox {
+            x: 90,
+            y: 10,
+            size: 50,
+        }
+        .clamp_to((100, 80));
+        assert_eq!(
+            crop,
+            CropBox {
+                x: 50,
+                y: 10,
+                size: 50
+            }
+        );
+
+        let crop = CropBox {
+            x: 0,
+            y: 0,
+            size: 500,
+        }
+        .clamp_to((100, 80));
+        assert_eq!(crop.size, 80);
+    }
+
+    #[test]
+    fn crop_box_defaults_to_center() {
+        assert_eq!(
+            CropBox::default().clamp_to((300, 100)),
+            CropBox {
+                x: 100,
+                y: 0,
+                size: 100
+            }
+        );
+    }
+
+    #[test]
+    fn avatar_renditions_are_square_and_never_upscaled() {
+        let source = DynamicImage::new_rgba8(200, 120);
+        let encoded = encode(&source, ImageFormat::Png).unwrap();
+
+        let renditions = avatar_renditions(&encoded.data, None).unwrap();
+        let sizes: Vec<(u32, u32)> = renditions.iter().map(|r| r.dimensions).collect();
+        assert_eq!(sizes, vec![(96, 96), (48, 48), (24, 24)]);
+        assert!(renditions.iter().all(|r| r.extension == "png"));
+    }
+
+    #[test]
+    fn bounded_renditions_keep_aspect_ratio() {
+        let source = DynamicImage::new_rgb8(1000, 500);
+        let encoded = encode(&source, ImageFormat::Jpeg).unwrap();
+
+        let renditions = bounded_renditions(&encoded.data, &THUMBNAIL_BOUNDS).unwrap();
+        let sizes: Vec<(u32, u32)> = renditions.iter().map(|r| r.dimensions).collect();
+        assert_eq!(sizes, vec![(200, 100), (800, 400)]);
+        assert!(renditions.iter().all(|r| r.extension == "jpeg"));
+
+        let small = encode(&DynamicImage::new_rgb8(150, 150), ImageFormat::Png).unwrap();
+        assert!(bounded_renditions(&small.data, &THUMBNAIL_BOUNDS)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn image_variants_skip_source_format_and_larger_output() {
+        let source = DynamicImage::new_rgb8(64, 64);
+        let png = encode(&source, ImageFormat::Png).unwrap();
+        let variants = image_variants(&png.data, &[VariantFormat::WebP]).unwrap();
+        assert!(variants
+            .iter()
+            .all(|(format, variant)| *format == VariantFormat::WebP
+                && variant.data.len() < png.data.len()
+                && variant.mime.as_ref() == "image/webp"));
+
+        let webp = encode_variant(&source, VariantFormat::WebP).unwrap();
+        assert!(image_variants(&webp.data, &[VariantFormat::WebP])
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,894 @@
+//! Importing a community from other forum software
+//!
+//! An import reads another forum's database and copies its members, forums,
+//! threads, posts, private conversations and attachments into ours, in that
+//! order, so each step finds what the earlier ones created. Every copied row
+//! is recorded in `import_id_map` against its ID in the source, and each
+//! batch commits together with the last source ID it read, in
+//! `import_progress`. Running the same import again carries on after the last
+//! finished batch; rows already copied are never copied twice.
+//!
+//! Passwords hashed by other software can't be checked here, so imported
+//! members get an unusable password and sign in after a password reset.
+
+pub mod phpbb;
+pub mod source;
+pub mod xenforo;
+
+pub use source::ImportSource;
+
+use crate::db::get_db_pool;
+use crate::orm::{
+    conversation_participants, conversations, forums, posts, private_messages, threads, ugc,
+    ugc_attachments, ugc_revisions, user_name_history, user_names, users,
+};
+use crate::ugc::create_dated_ugc;
+use chrono::NaiveDateTime;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    entity::*, query::*, ConnectionTrait, Database, DbBackend, DbErr, FromQueryResult, Statement,
+};
+use source::*;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Software an import reads from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    Phpbb,
+    Xenforo,
+}
+
+impl Platform {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Platform::Phpbb => "phpbb",
+            Platform::Xenforo => "xenforo",
+        }
+    }
+
+    /// Table prefix the software installs with
+    pub fn default_prefix(&self) -> &'static str {
+        match self {
+            Platform::Phpbb => "phpbb_",
+            Platform::Xenforo => "xf_",
+        }
+    }
+}
+
+/// What an import copies, in the order it copies them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Users,
+    Forums,
+    Threads,
+    Posts,
+    Conversations,
+    Messages,
+    Attachments,
+}
+
+impl Step {
+    pub fn all() -> [Step; 7] {
+        [
+            Step::Users,
+            Step::Forums,
+            Step::Threads,
+            Step::Posts,
+            Step::Conversations,
+            Step::Messages,
+            Step::Attachments,
+        ]
+    }
+
+    /// Name in `import_progress`, and the kind of row in `import_id_map`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Step::Users => "user",
+            Step::Forums => "forum",
+            Step::Threads => "thread",
+            Step::Posts => "post",
+            Step::Conversations => "conversation",
+            Step::Messages => "message",
+            Step::Attachments => "attachment",
+        }
+    }
+}
+
+pub struct ImportOptions {
+    pub platform: Platform,
+    /// MySQL URL of the source database
+    pub source_url: String,
+    pub table_prefix: String,
+    /// Directory the source keeps attachment files in. Attachments are
+    /// skipped without one.
+    pub attachment_dir: Option<PathBuf>,
+    /// Rows read and committed at once
+    pub batch_size: u64,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    /// The options can't work
+    Options(String),
+    /// Reading the source database failed
+    Source(DbErr),
+    /// Writing to our database failed
+    Target(DbErr),
+    /// An attachment couldn't be stored
+    Attachment(String),
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Options(msg) => write!(f, "{}", msg),
+            ImportError::Source(e) => write!(f, "reading the source failed: {}", e),
+            ImportError::Target(e) => write!(f, "database error: {}", e),
+            ImportError::Attachment(msg) => write!(f, "storing an attachment failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<DbErr> for ImportError {
+    fn from(e: DbErr) -> Self {
+        ImportError::Target(e)
+    }
+}
+
+/// Run every step of an import to the end. Returns how many rows each step
+/// copied in this run.
+pub async fn run(options: &ImportOptions) -> Result<Vec<(Step, usize)>, ImportError> {
+    if !is_valid_prefix(&options.table_prefix) {
+        return Err(ImportError::Options(format!(
+            "Table prefix {:?} may only hold letters, digits and underscores.",
+            options.table_prefix
+        )));
+    }
+
+    let db = Database::connect(options.source_url.as_str())
+        .await
+        .map_err(ImportError::Source)?;
+    let source: Box<dyn ImportSource> = match options.platform {
+        Platform::Phpbb => Box::new(phpbb::PhpbbSource::new(db, &options.table_prefix)),
+        Platform::Xenforo => Box::new(xenforo::XenforoSource::new(db, &options.table_prefix)),
+    };
+    let password = crate::session::hash_password(&uuid::Uuid::new_v4().to_string())
+        .map_err(|e| ImportError::Options(format!("Failed to hash password: {}", e)))?;
+
+    let importer = Importer {
+        source,
+        platform: options.platform,
+        password,
+        attachment_dir: options.attachment_dir.clone(),
+        batch_size: options.batch_size.max(1),
+    };
+
+    let mut counts = Vec::new();
+    for step in Step::all() {
+        if step == Step::Attachments && importer.attachment_dir.is_none() {
+            log::info!("No attachment directory given; skipping attachments.");
+            continue;
+        }
+        counts.push((step, importer.run_step(step).await?));
+    }
+    importer.finish().await?;
+
+    Ok(counts)
+}
+
+/// Table prefixes are put into queries as they are.
+fn is_valid_prefix(prefix: &str) -> bool {
+    prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn timestamp(seconds: i64) -> NaiveDateTime {
+    NaiveDateTime::from_timestamp_opt(seconds, 0).unwrap_or_default()
+}
+
+struct Importer {
+    source: Box<dyn ImportSource>,
+    platform: Platform,
+    /// Hash of a password nobody knows, for imported members
+    password: String,
+    attachment_dir: Option<PathBuf>,
+    batch_size: u64,
+}
+
+impl Importer {
+    /// Copy batches until the source has no more rows for a step.
+    async fn run_step(&self, step: Step) -> Result<usize, ImportError> {
+        let mut copied = 0;
+        loop {
+            let after = self.progress(step).await?;
+            let batch = match step {
+                Step::Users => self.import_users(after).await?,
+                Step::Forums => self.import_forums(after).await?,
+                Step::Threads => self.import_threads(after).await?,
+                Step::Posts => self.import_posts(after).await?,
+                Step::Conversations => self.import_conversations(after).await?,
+                Step::Messages => self.import_messages(after).await?,
+                Step::Attachments => self.import_attachments(after).await?,
+            };
+            match batch {
+                Some(count) => {
+                    copied += count;
+                    log::info!(
+                        "Imported {} {} rows from {}",
+                        copied,
+                        step.as_str(),
+                        self.platform.as_str()
+                    );
+                }
+                None => return Ok(copied),
+            }
+        }
+    }
+
+    /// Last source ID a step has finished.
+    async fn progress(&self, step: Step) -> Result<i64, DbErr> {
+        let row = get_db_pool()
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT last_source_id FROM import_progress WHERE platform = $1 AND step = $2",
+                vec![self.platform.as_str().into(), step.as_str().into()],
+            ))
+            .await?;
+        match row {
+            Some(row) => row.try_get("", "last_source_id"),
+            None => Ok(0),
+        }
+    }
+
+    async fn set_progress<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        step: Step,
+        last_source_id: i64,
+    ) -> Result<(), DbErr> {
+        conn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO import_progress (platform, step, last_source_id) VALUES ($1, $2, $3)
+                ON CONFLICT (platform, step)
+                DO UPDATE SET last_source_id = EXCLUDED.last_source_id, updated_at = NOW()"#,
+            vec![
+                self.platform.as_str().into(),
+                step.as_str().into(),
+                last_source_id.into(),
+            ],
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Our IDs for rows copied from the source.
+    async fn lookup<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        kind: Step,
+        source_ids: impl IntoIterator<Item = i64>,
+    ) -> Result<HashMap<i64, i32>, DbErr> {
+        #[derive(FromQueryResult)]
+        struct Mapping {
+            source_id: i64,
+            target_id: i32,
+        }
+
+        let ids: Vec<i64> = source_ids
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let sql = format!(
+            r#"SELECT source_id, target_id FROM import_id_map
+                WHERE platform = $1 AND kind = $2 AND source_id IN ({})"#,
+            id_list(&ids)
+        );
+        let rows = Mapping::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            &sql,
+            vec![self.platform.as_str().into(), kind.as_str().into()],
+        ))
+        .all(conn)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.source_id, row.target_id))
+            .collect())
+    }
+
+    async fn record<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        kind: Step,
+        source_id: i64,
+        target_id: i32,
+    ) -> Result<(), DbErr> {
+        conn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO import_id_map (platform, kind, source_id, target_id)
+                VALUES ($1, $2, $3, $4)"#,
+            vec![
+                self.platform.as_str().into(),
+                kind.as_str().into(),
+                source_id.into(),
+                target_id.into(),
+            ],
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn import_users(&self, after: i64) -> Result<Option<usize>, ImportError> {
+        let rows = self
+            .source
+            .users(after, self.batch_size)
+            .await
+            .map_err(ImportError::Source)?;
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+
+        let txn = get_db_pool().begin().await?;
+        for row in &rows {
+            let created_at = timestamp(row.created_at);
+            let name = self.free_name(&txn, row).await?;
+            let email = match &row.email {
+                Some(email) if !email_taken(&txn, email).await? => Some(email.to_lowercase()),
+                _ => None,
+            };
+
+            let user = users::ActiveModel {
+                created_at: Set(created_at),
+                password: Set(self.password.to_owned()),
+                password_cipher: Set(users::Cipher::Argon2id),
+                email_verified: Set(email.is_some()),
+                email: Set(email),
+                signature: Set(row.signature.to_owned()),
+                approval_status: Set(users::ApprovalStatus::Approved),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+
+            user_names::Entity::insert(user_names::ActiveModel {
+                user_id: Set(user.id),
+                name: Set(name.to_owned()),
+            })
+            .exec(&txn)
+            .await?;
+            user_name_history::Entity::insert(user_name_history::ActiveModel {
+                user_id: Set(user.id),
+                created_at: Set(created_at),
+                approved_at: Set(created_at),
+                name: Set(name),
+                is_public: Set(true),
+                ..Default::default()
+            })
+            .exec(&txn)
+            .await?;
+
+            self.record(&txn, Step::Users, row.id, user.id).await?;
+        }
+        self.set_progress(&txn, Step::Users, last).await?;
+        txn.commit().await?;
+
+        Ok(Some(rows.len()))
+    }
+
+    /// The member's name, or the name with their source ID added if someone
+    /// here already has it.
+    async fn free_name<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        row: &SourceUser,
+    ) -> Result<String, DbErr> {
+        let name = row.name.trim();
+        let taken = conn
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT user_id FROM user_names WHERE LOWER(name) = LOWER($1) LIMIT 1",
+                vec![name.into()],
+            ))
+            .await?
+            .is_some();
+        Ok(match taken || name.is_empty() {
+            true => format!("{}_{}", name, row.id),
+            false => name.to_owned(),
+        })
+    }
+
+    async fn import_forums(&self, after: i64) -> Result<Option<usize>, ImportError> {
+        let rows = self
+            .source
+            .forums(after, self.batch_size)
+            .await
+            .map_err(ImportError::Source)?;
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+
+        // Parents may come after their children; they are linked in `finish`.
+        let txn = get_db_pool().begin().await?;
+        for row in &rows {
+            let forum = forums::ActiveModel {
+                label: Set(row.title.to_owned()),
+                description: Set(row.description.to_owned()),
+                display_order: Set(row.display_order.clamp(0, i32::MAX as i64) as i32),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            self.record(&txn, Step::Forums, row.id, forum.id).await?;
+        }
+        self.set_progress(&txn, Step::Forums, last).await?;
+        txn.commit().await?;
+
+        Ok(Some(rows.len()))
+    }
+
+    async fn import_threads(&self, after: i64) -> Result<Option<usize>, ImportError> {
+        let rows = self
+            .source
+            .threads(after, self.batch_size)
+            .await
+            .map_err(ImportError::Source)?;
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+
+        let txn = get_db_pool().begin().await?;
+        let forum_ids = self
+            .lookup(&txn, Step::Forums, rows.iter().map(|row| row.forum_id))
+            .await?;
+        let user_ids = self
+            .lookup(&txn, Step::Users, rows.iter().filter_map(|row| row.user_id))
+            .await?;
+
+        let mut copied = 0;
+        for row in &rows {
+            // Threads in link forums and other nodes that weren't copied.
+            let Some(forum_id) = forum_ids.get(&row.forum_id) else {
+                continue;
+            };
+            let thread = threads::ActiveModel {
+                forum_id: Set(*forum_id),
+                user_id: Set(row.user_id.and_then(|id| user_ids.get(&id).copied())),
+                created_at: Set(timestamp(row.created_at)),
+                title: Set(row.title.to_owned()),
+                view_count: Set(row.view_count.clamp(0, i32::MAX as i64) as i32),
+                post_count: Set(0),
+                is_locked: Set(row.is_locked != 0),
+                is_pinned: Set(row.is_pinned != 0),
+                is_announcement: Set(false),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            self.record(&txn, Step::Threads, row.id, thread.id).await?;
+            copied += 1;
+        }
+        self.set_progress(&txn, Step::Threads, last).await?;
+        txn.commit().await?;
+
+        Ok(Some(copied))
+    }
+
+    async fn import_posts(&self, after: i64) -> Result<Option<usize>, ImportError> {
+        let rows = self
+            .source
+            .posts(after, self.batch_size)
+            .await
+            .map_err(ImportError::Source)?;
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+
+        let txn = get_db_pool().begin().await?;
+        let thread_ids = self
+            .lookup(&txn, Step::Threads, rows.iter().map(|row| row.thread_id))
+            .await?;
+        let user_ids = self
+            .lookup(&txn, Step::Users, rows.iter().filter_map(|row| row.user_id))
+            .await?;
+
+        let mut copied = 0;
+        for row in &rows {
+            let Some(thread_id) = thread_ids.get(&row.thread_id).copied() else {
+                continue;
+            };
+            let user_id = row.user_id.and_then(|id| user_ids.get(&id).copied());
+            let created_at = timestamp(row.created_at);
+
+            // Posts arrive in ID order, which is the order they were written.
+            let position: i32 = txn
+                .query_one(Statement::from_sql_and_values(
+                    DbBackend::Postgres,
+                    "UPDATE threads SET post_count = post_count + 1 WHERE id = $1 RETURNING post_count",
+                    vec![thread_id.into()],
+                ))
+                .await?
+                .ok_or_else(|| DbErr::RecordNotFound(format!("thread {}", thread_id)))?
+                .try_get("", "post_count")?;
+
+            let ugc_id = create_dated_ugc(&txn, user_id, &row.content, created_at).await?;
+            let post = posts::ActiveModel {
+                thread_id: Set(thread_id),
+                position: Set(position),
+                ugc_id: Set(ugc_id),
+                user_id: Set(user_id),
+                created_at: Set(created_at),
+                moderation_status: Set(match row.is_approved {
+                    true => posts::ModerationStatus::Approved,
+                    false => posts::ModerationStatus::Pending,
+                }),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"UPDATE threads SET first_post_id = COALESCE(first_post_id, $1),
+                    last_post_id = $1, last_post_at = $2
+                    WHERE id = $3"#,
+                vec![post.id.into(), created_at.into(), thread_id.into()],
+            ))
+            .await?;
+
+            self.record(&txn, Step::Posts, row.id, post.id).await?;
+            copied += 1;
+        }
+        self.set_progress(&txn, Step::Posts, last).await?;
+        txn.commit().await?;
+
+        Ok(Some(copied))
+    }
+
+    async fn import_conversations(&self, after: i64) -> Result<Option<usize>, ImportError> {
+        let rows = self
+            .source
+            .conversations(after, self.batch_size)
+            .await
+            .map_err(ImportError::Source)?;
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+
+        let conversation_ids: Vec<i64> = rows.iter().map(|row| row.id).collect();
+        let participants = self
+            .source
+            .participants(&conversation_ids)
+            .await
+            .map_err(ImportError::Source)?;
+
+        let txn = get_db_pool().begin().await?;
+        let user_ids = self
+            .lookup(
+                &txn,
+                Step::Users,
+                rows.iter()
+                    .filter_map(|row| row.user_id)
+                    .chain(participants.iter().map(|p| p.user_id)),
+            )
+            .await?;
+
+        for row in &rows {
+            let created_at = timestamp(row.created_at);
+            let creator_id = row.user_id.and_then(|id| user_ids.get(&id).copied());
+            let conversation = conversations::ActiveModel {
+                title: Set(row.title.to_owned()),
+                creator_id: Set(creator_id),
+                created_at: Set(created_at),
+                updated_at: Set(created_at),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+
+            let members: HashSet<i32> = participants
+                .iter()
+                .filter(|p| p.conversation_id == row.id)
+                .filter_map(|p| user_ids.get(&p.user_id).copied())
+                .collect();
+            for user_id in members {
+                conversation_participants::Entity::insert(conversation_participants::ActiveModel {
+                    conversation_id: Set(conversation.id),
+                    user_id: Set(user_id),
+                    joined_at: Set(created_at),
+                    last_read_message_id: Set(None),
+                    is_archived: Set(false),
+                    is_muted: Set(false),
+                    role: Set(match Some(user_id) == creator_id {
+                        true => conversation_participants::ParticipantRole::Owner,
+                        false => conversation_participants::ParticipantRole::Member,
+                    }),
+                })
+                .exec(&txn)
+                .await?;
+            }
+
+            self.record(&txn, Step::Conversations, row.id, conversation.id)
+                .await?;
+        }
+        self.set_progress(&txn, Step::Conversations, last).await?;
+        txn.commit().await?;
+
+        Ok(Some(rows.len()))
+    }
+
+    async fn import_messages(&self, after: i64) -> Result<Option<usize>, ImportError> {
+        let rows = self
+            .source
+            .messages(after, self.batch_size)
+            .await
+            .map_err(ImportError::Source)?;
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+
+        let txn = get_db_pool().begin().await?;
+        let conversation_ids = self
+            .lookup(
+                &txn,
+                Step::Conversations,
+                rows.iter().map(|row| row.conversation_id),
+            )
+            .await?;
+        let user_ids = self
+            .lookup(&txn, Step::Users, rows.iter().filter_map(|row| row.user_id))
+            .await?;
+
+        let mut copied = 0;
+        for row in &rows {
+            let Some(conversation_id) = conversation_ids.get(&row.conversation_id).copied() else {
+                continue;
+            };
+            let user_id = row.user_id.and_then(|id| user_ids.get(&id).copied());
+            let created_at = timestamp(row.created_at);
+
+            let ugc_id = create_dated_ugc(&txn, user_id, &row.content, created_at).await?;
+            let message = private_messages::ActiveModel {
+                conversation_id: Set(conversation_id),
+                ugc_id: Set(ugc_id),
+                user_id: Set(user_id),
+                created_at: Set(created_at),
+                kind: Set(private_messages::MessageKind::Message),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+
+            txn.execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "UPDATE conversations SET updated_at = GREATEST(updated_at, $1) WHERE id = $2",
+                vec![created_at.into(), conversation_id.into()],
+            ))
+            .await?;
+
+            self.record(&txn, Step::Messages, row.id, message.id)
+                .await?;
+            copied += 1;
+        }
+        self.set_progress(&txn, Step::Messages, last).await?;
+        txn.commit().await?;
+
+        Ok(Some(copied))
+    }
+
+    async fn import_attachments(&self, after: i64) -> Result<Option<usize>, ImportError> {
+        let Some(dir) = &self.attachment_dir else {
+            return Ok(None);
+        };
+        let rows = self
+            .source
+            .attachments(after, self.batch_size)
+            .await
+            .map_err(ImportError::Source)?;
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+
+        let db = get_db_pool();
+        let post_ids = self
+            .lookup(db, Step::Posts, rows.iter().filter_map(|row| row.post_id))
+            .await?;
+        let message_ids = self
+            .lookup(
+                db,
+                Step::Messages,
+                rows.iter().filter_map(|row| row.message_id),
+            )
+            .await?;
+        let user_ids = self
+            .lookup(db, Step::Users, rows.iter().filter_map(|row| row.user_id))
+            .await?;
+
+        // Files go to storage first; storing one twice after an interruption
+        // finds the copy already there.
+        let mut stored = Vec::new();
+        for row in &rows {
+            let ugc_id = match (row.post_id, row.message_id) {
+                (Some(id), _) => match post_ids.get(&id) {
+                    Some(post_id) => posts::Entity::find_by_id(*post_id)
+                        .one(db)
+                        .await?
+                        .map(|post| post.ugc_id),
+                    None => None,
+                },
+                (None, Some(id)) => match message_ids.get(&id) {
+                    Some(message_id) => private_messages::Entity::find_by_id(*message_id)
+                        .one(db)
+                        .await?
+                        .map(|message| message.ugc_id),
+                    None => None,
+                },
+                (None, None) => None,
+            };
+            let Some(ugc_id) = ugc_id else {
+                continue;
+            };
+
+            let path = dir.join(&row.path);
+            let data = match std::fs::read(&path) {
+                Ok(data) => data,
+                Err(e) => {
+                    log::warn!("Skipping attachment {}: {}: {}", row.id, path.display(), e);
+                    continue;
+                }
+            };
+            let extension = extension_of(&row.filename);
+            let mime = row
+                .mime
+                .as_deref()
+                .or_else(|| {
+                    extension
+                        .as_deref()
+                        .and_then(crate::filesystem::mime_for_extension)
+                })
+                .unwrap_or("application/octet-stream");
+            let file = crate::filesystem::insert_generated_attachment(
+                data,
+                mime,
+                extension.as_deref().unwrap_or("bin"),
+                None,
+            )
+            .await
+            .map_err(|e| ImportError::Attachment(e.to_string()))?;
+
+            stored.push((row, ugc_id, file.id));
+        }
+
+        let txn = db.begin().await?;
+        for (row, ugc_id, attachment_id) in &stored {
+            let link = ugc_attachments::ActiveModel {
+                attachment_id: Set(*attachment_id),
+                ugc_id: Set(*ugc_id),
+                user_id: Set(row.user_id.and_then(|id| user_ids.get(&id).copied())),
+                created_at: Set(timestamp(row.created_at)),
+                filename: Set(row.filename.to_owned()),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            self.record(&txn, Step::Attachments, row.id, link.id)
+                .await?;
+        }
+        self.set_progress(&txn, Step::Attachments, last).await?;
+        txn.commit().await?;
+
+        Ok(Some(stored.len()))
+    }
+
+    /// Work that needs every row in place: forums are put under their
+    /// parents, and imported conversations start out read.
+    async fn finish(&self) -> Result<(), ImportError> {
+        let db = get_db_pool();
+
+        let mut after = 0;
+        loop {
+            let rows = self
+                .source
+                .forums(after, self.batch_size)
+                .await
+                .map_err(ImportError::Source)?;
+            let Some(last) = rows.last().map(|row| row.id) else {
+                break;
+            };
+            let forum_ids = self
+                .lookup(
+                    db,
+                    Step::Forums,
+                    rows.iter()
+                        .flat_map(|row| std::iter::once(row.id).chain(row.parent_id)),
+                )
+                .await?;
+            for row in &rows {
+                let (Some(id), Some(parent_id)) = (
+                    forum_ids.get(&row.id),
+                    row.parent_id.and_then(|id| forum_ids.get(&id)),
+                ) else {
+                    continue;
+                };
+                forums::Entity::update_many()
+                    .col_expr(forums::Column::ParentId, Expr::value(*parent_id))
+                    .filter(forums::Column::Id.eq(*id))
+                    .exec(db)
+                    .await?;
+            }
+            after = last;
+        }
+
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"UPDATE conversation_participants cp
+                SET last_read_message_id = (
+                    SELECT MAX(pm.id) FROM private_messages pm
+                    WHERE pm.conversation_id = cp.conversation_id
+                )
+                WHERE cp.last_read_message_id IS NULL
+                  AND cp.conversation_id IN (
+                      SELECT target_id FROM import_id_map WHERE platform = $1 AND kind = $2
+                  )"#,
+            vec![
+                self.platform.as_str().into(),
+                Step::Conversations.as_str().into(),
+            ],
+        ))
+        .await?;
+
+        Ok(())
+    }
+}
+
+async fn email_taken<C: ConnectionTrait>(conn: &C, email: &str) -> Result<bool, DbErr> {
+    Ok(users::Entity::find()
+        .filter(users::Column::Email.eq(email.to_lowercase()))
+        .one(conn)
+        .await?
+        .is_some())
+}
+
+/// Lowercase extension of an uploaded file's name, if it has a usable one.
+fn extension_of(filename: &str) -> Option<String> {
+    let (_, extension) = filename.rsplit_once('.')?;
+    let valid = !extension.is_empty()
+        && extension.len() <= 8
+        && extension.chars().all(|c| c.is_ascii_alphanumeric());
+    valid.then(|| extension.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_prefix() {
+        assert!(is_valid_prefix("phpbb_"));
+        assert!(is_valid_prefix(""));
+        assert!(!is_valid_prefix("xf_; DROP TABLE users; --"));
+    }
+
+    #[test]
+    fn test_extension_of() {
+        assert_eq!(extension_of("Photo.JPG").as_deref(), Some("jpg"));
+        assert_eq!(extension_of("archive.tar.gz").as_deref(), Some("gz"));
+        assert_eq!(extension_of("README"), None);
+        assert_eq!(extension_of("odd.ext with space"), None);
+    }
+
+    #[test]
+    fn test_step_names_are_distinct() {
+        let names: HashSet<&str> = Step::all().iter().map(Step::as_str).collect();
+        assert_eq!(names.len(), Step::all().len());
+    }
+}
@@ -0,0 +1,362 @@
+//! phpBB 3.x, read from its MySQL database.
+//!
+//! Bots and the anonymous user are left out, as are moved-topic shadows, link
+//! forums and soft-deleted posts. phpBB 3.0 stores posts as BBCode with a
+//! per-post `bbcode_uid` in every tag; 3.1 and later store them as XML that
+//! keeps the original BBCode inside its elements. Both come out as BBCode.
+
+use super::source::*;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement};
+
+pub struct PhpbbSource {
+    db: DatabaseConnection,
+    /// Table prefix, `phpbb_` unless changed at install
+    prefix: String,
+}
+
+impl PhpbbSource {
+    pub fn new(db: DatabaseConnection, prefix: &str) -> Self {
+        Self {
+            db,
+            prefix: prefix.to_owned(),
+        }
+    }
+
+    async fn page<T: FromQueryResult>(
+        &self,
+        sql: &str,
+        after: i64,
+        limit: u64,
+    ) -> Result<Vec<T>, DbErr> {
+        fetch_page(&self.db, &self.prefix, sql, after, limit).await
+    }
+}
+
+#[derive(FromQueryResult)]
+struct UserRow {
+    id: i64,
+    name: String,
+    email: Option<String>,
+    created_at: i64,
+    signature: Option<String>,
+    signature_uid: String,
+}
+
+#[derive(FromQueryResult)]
+struct ForumRow {
+    id: i64,
+    parent_id: Option<i64>,
+    title: String,
+    description: Option<String>,
+    description_uid: String,
+    display_order: i64,
+}
+
+#[derive(FromQueryResult)]
+struct PostRow {
+    id: i64,
+    parent_id: i64,
+    user_id: Option<i64>,
+    created_at: i64,
+    content: String,
+    bbcode_uid: String,
+    is_approved: i64,
+}
+
+#[async_trait]
+impl ImportSource for PhpbbSource {
+    async fn users(&self, after: i64, limit: u64) -> Result<Vec<SourceUser>, DbErr> {
+        let rows: Vec<UserRow> = self
+            .page(
+                r#"SELECT CAST(user_id AS SIGNED) AS id, username AS name,
+                    NULLIF(user_email, '') AS email,
+                    CAST(user_regdate AS SIGNED) AS created_at,
+                    NULLIF(user_sig, '') AS signature, user_sig_bbcode_uid AS signature_uid
+                FROM {p}users
+                WHERE user_id > ? AND user_type <> 2
+                ORDER BY user_id LIMIT ?"#,
+                after,
+                limit,
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SourceUser {
+                id: row.id,
+                name: unescape(&row.name),
+                email: row.email,
+                created_at: row.created_at,
+                signature: row.signature.map(|s| to_bbcode(&s, &row.signature_uid)),
+            })
+            .collect())
+    }
+
+    async fn forums(&self, after: i64, limit: u64) -> Result<Vec<SourceForum>, DbErr> {
+        let rows: Vec<ForumRow> = self
+            .page(
+                r#"SELECT CAST(forum_id AS SIGNED) AS id,
+                    CAST(NULLIF(parent_id, 0) AS SIGNED) AS parent_id,
+                    forum_name AS title, NULLIF(forum_desc, '') AS description,
+                    forum_desc_uid AS description_uid,
+                    CAST(left_id AS SIGNED) AS display_order
+                FROM {p}forums
+                WHERE forum_id > ? AND forum_type <> 2
+                ORDER BY forum_id LIMIT ?"#,
+                after,
+                limit,
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SourceForum {
+                id: row.id,
+                parent_id: row.parent_id,
+                title: unescape(&row.title),
+                description: row.description.map(|d| to_bbcode(&d, &row.description_uid)),
+                display_order: row.display_order,
+            })
+            .collect())
+    }
+
+    async fn threads(&self, after: i64, limit: u64) -> Result<Vec<SourceThread>, DbErr> {
+        let mut threads: Vec<SourceThread> = self
+            .page(
+                r#"SELECT CAST(topic_id AS SIGNED) AS id, CAST(forum_id AS SIGNED) AS forum_id,
+                    CAST(topic_poster AS SIGNED) AS user_id, topic_title AS title,
+                    CAST(topic_time AS SIGNED) AS created_at,
+                    CAST(topic_views AS SIGNED) AS view_count,
+                    CAST(topic_status = 1 AS SIGNED) AS is_locked,
+                    CAST(topic_type > 0 AS SIGNED) AS is_pinned
+                FROM {p}topics
+                WHERE topic_id > ? AND topic_moved_id = 0
+                ORDER BY topic_id LIMIT ?"#,
+                after,
+                limit,
+            )
+            .await?;
+
+        for thread in &mut threads {
+            thread.title = unescape(&thread.title);
+        }
+        Ok(threads)
+    }
+
+    async fn posts(&self, after: i64, limit: u64) -> Result<Vec<SourcePost>, DbErr> {
+        let rows: Vec<PostRow> = self
+            .page(
+                r#"SELECT CAST(post_id AS SIGNED) AS id, CAST(topic_id AS SIGNED) AS parent_id,
+                    CAST(poster_id AS SIGNED) AS user_id,
+                    CAST(post_time AS SIGNED) AS created_at,
+                    post_text AS content, bbcode_uid,
+                    CAST(post_visibility = 1 AS SIGNED) AS is_approved
+                FROM {p}posts
+                WHERE post_id > ? AND post_visibility IN (0, 1)
+                ORDER BY post_id LIMIT ?"#,
+                after,
+                limit,
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SourcePost {
+                id: row.id,
+                thread_id: row.parent_id,
+                user_id: row.user_id,
+                created_at: row.created_at,
+                content: to_bbcode(&row.content, &row.bbcode_uid),
+                is_approved: row.is_approved != 0,
+            })
+            .collect())
+    }
+
+    async fn conversations(
+        &self,
+        after: i64,
+        limit: u64,
+    ) -> Result<Vec<SourceConversation>, DbErr> {
+        // A private message thread is its first message and the replies that
+        // point back to it with `root_level`.
+        let mut conversations: Vec<SourceConversation> = self
+            .page(
+                r#"SELECT CAST(msg_id AS SIGNED) AS id, message_subject AS title,
+                    CAST(author_id AS SIGNED) AS user_id,
+                    CAST(message_time AS SIGNED) AS created_at
+                FROM {p}privmsgs
+                WHERE msg_id > ? AND root_level = 0
+                ORDER BY msg_id LIMIT ?"#,
+                after,
+                limit,
+            )
+            .await?;
+
+        for conversation in &mut conversations {
+            conversation.title = conversation.title.as_deref().map(unescape);
+        }
+        Ok(conversations)
+    }
+
+    async fn participants(
+        &self,
+        conversation_ids: &[i64],
+    ) -> Result<Vec<SourceParticipant>, DbErr> {
+        let ids = id_list(conversation_ids);
+        let sql = format!(
+            r#"SELECT CAST(IF(m.root_level = 0, m.msg_id, m.root_level) AS SIGNED) AS conversation_id,
+                    CAST(t.user_id AS SIGNED) AS user_id
+                FROM {p}privmsgs m
+                JOIN {p}privmsgs_to t ON t.msg_id = m.msg_id
+                WHERE IF(m.root_level = 0, m.msg_id, m.root_level) IN ({ids})
+                UNION
+                SELECT CAST(msg_id AS SIGNED), CAST(author_id AS SIGNED)
+                FROM {p}privmsgs
+                WHERE msg_id IN ({ids})"#,
+            p = self.prefix,
+            ids = ids
+        );
+        SourceParticipant::find_by_statement(Statement::from_string(DbBackend::MySql, sql))
+            .all(&self.db)
+            .await
+    }
+
+    async fn messages(&self, after: i64, limit: u64) -> Result<Vec<SourceMessage>, DbErr> {
+        let rows: Vec<PostRow> = self
+            .page(
+                r#"SELECT CAST(msg_id AS SIGNED) AS id,
+                    CAST(IF(root_level = 0, msg_id, root_level) AS SIGNED) AS parent_id,
+                    CAST(author_id AS SIGNED) AS user_id,
+                    CAST(message_time AS SIGNED) AS created_at,
+                    message_text AS content, bbcode_uid,
+                    CAST(1 AS SIGNED) AS is_approved
+                FROM {p}privmsgs
+                WHERE msg_id > ?
+                ORDER BY msg_id LIMIT ?"#,
+                after,
+                limit,
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SourceMessage {
+                id: row.id,
+                conversation_id: row.parent_id,
+                user_id: row.user_id,
+                created_at: row.created_at,
+                content: to_bbcode(&row.content, &row.bbcode_uid),
+            })
+            .collect())
+    }
+
+    async fn attachments(&self, after: i64, limit: u64) -> Result<Vec<SourceAttachment>, DbErr> {
+        let mut attachments: Vec<SourceAttachment> = self
+            .page(
+                r#"SELECT CAST(attach_id AS SIGNED) AS id,
+                    CAST(IF(in_message = 0, post_msg_id, NULL) AS SIGNED) AS post_id,
+                    CAST(IF(in_message = 1, post_msg_id, NULL) AS SIGNED) AS message_id,
+                    CAST(poster_id AS SIGNED) AS user_id,
+                    real_filename AS filename, physical_filename AS path,
+                    NULLIF(mimetype, '') AS mime,
+                    CAST(filetime AS SIGNED) AS created_at
+                FROM {p}attachments
+                WHERE attach_id > ? AND is_orphan = 0
+                ORDER BY attach_id LIMIT ?"#,
+                after,
+                limit,
+            )
+            .await?;
+
+        for attachment in &mut attachments {
+            attachment.filename = unescape(&attachment.filename);
+        }
+        Ok(attachments)
+    }
+}
+
+/// Smilies in phpBB 3.0 posts, stored as an image between comments naming
+/// the code that was typed.
+static SMILEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<!-- s(\S+?) -->.*?<!-- s\S+? -->").unwrap());
+
+/// List markers phpBB 3.0 writes once the uid is gone: `[list:o]`, `[/*:m]`.
+static LIST_MARKER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[(/?list):[ou]\]|\[/\*:m\]").unwrap());
+
+static TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+static ENTITY: Lazy<Regex> = Lazy::new(|| Regex::new(r"&(#x?[0-9a-fA-F]+|[a-z]+);").unwrap());
+
+/// BBCode for phpBB's stored text. Links and smilies go back to what was
+/// typed; any other HTML phpBB added is dropped.
+pub fn to_bbcode(text: &str, uid: &str) -> String {
+    let text = match uid.is_empty() {
+        true => text.to_owned(),
+        false => text.replace(&format!(":{}", uid), ""),
+    };
+    let text = SMILEY.replace_all(&text, "$1");
+    let text = LIST_MARKER.replace_all(&text, |caps: &regex::Captures| match caps.get(1) {
+        Some(list) => format!("[{}]", list.as_str()),
+        None => String::new(),
+    });
+    let text = TAG.replace_all(&text, "");
+    unescape(&text)
+}
+
+/// Text with phpBB's HTML escaping undone.
+fn unescape(text: &str) -> String {
+    ENTITY
+        .replace_all(text, |caps: &regex::Captures| {
+            let entity = &caps[1];
+            let decoded = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                }
+                .and_then(char::from_u32),
+            };
+            decoded.map_or_else(|| caps[0].to_owned(), String::from)
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_post() {
+        let stored = "[b:3kd9s2]Hi[/b:3kd9s2] &amp; welcome <!-- s:) --><img src=\"{SMILIES_PATH}/icon_e_smile.gif\" alt=\":)\" /><!-- s:) -->\n[quote=&quot;bob&quot;:3kd9s2]x &lt; y[/quote:3kd9s2]";
+        assert_eq!(
+            to_bbcode(stored, "3kd9s2"),
+            "[b]Hi[/b] & welcome :)\n[quote=\"bob\"]x < y[/quote]"
+        );
+    }
+
+    #[test]
+    fn test_legacy_list() {
+        let stored = "[list=1:ab12][*:ab12]one[/*:m:ab12][/list:o:ab12]";
+        assert_eq!(to_bbcode(stored, "ab12"), "[list=1][*]one[/list]");
+    }
+
+    #[test]
+    fn test_xml_post() {
+        let stored = "<r><B><s>[b]</s>bold<e>[/b]</e></B> &amp; <E>:)</E><br/>\nnext</r>";
+        assert_eq!(to_bbcode(stored, ""), "[b]bold[/b] & :)\nnext");
+        assert_eq!(to_bbcode("<t>plain &#128512;</t>", ""), "plain \u{1F600}");
+    }
+
+    #[test]
+    fn test_unescape_leaves_unknown_entities() {
+        assert_eq!(unescape("&bogus; &#39;"), "&bogus; '");
+    }
+}
@@ -0,0 +1,142 @@
+//! What importers read from another forum's database.
+//!
+//! Each platform maps its own schema onto these rows. IDs are the source's,
+//! timestamps are Unix seconds and content is already converted to BBCode.
+//! Flags are integers because MySQL hands back expressions as numbers.
+
+use async_trait::async_trait;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement, Value};
+
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct SourceUser {
+    pub id: i64,
+    pub name: String,
+    pub email: Option<String>,
+    pub created_at: i64,
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct SourceForum {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub title: String,
+    pub description: Option<String>,
+    pub display_order: i64,
+}
+
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct SourceThread {
+    pub id: i64,
+    pub forum_id: i64,
+    pub user_id: Option<i64>,
+    pub title: String,
+    pub created_at: i64,
+    pub view_count: i64,
+    /// Non-zero if replies are closed
+    pub is_locked: i64,
+    /// Non-zero if kept at the top of the forum
+    pub is_pinned: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourcePost {
+    pub id: i64,
+    pub thread_id: i64,
+    pub user_id: Option<i64>,
+    pub created_at: i64,
+    pub content: String,
+    /// False for posts waiting in the moderation queue
+    pub is_approved: bool,
+}
+
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct SourceConversation {
+    pub id: i64,
+    pub title: Option<String>,
+    pub user_id: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct SourceParticipant {
+    pub conversation_id: i64,
+    pub user_id: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceMessage {
+    pub id: i64,
+    pub conversation_id: i64,
+    pub user_id: Option<i64>,
+    pub created_at: i64,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct SourceAttachment {
+    pub id: i64,
+    /// Post the file is attached to, if it belongs to a post
+    pub post_id: Option<i64>,
+    /// Message the file is attached to, if it belongs to a conversation
+    pub message_id: Option<i64>,
+    pub user_id: Option<i64>,
+    /// Name the member uploaded the file as
+    pub filename: String,
+    /// Location of the file under the source's attachment directory
+    pub path: String,
+    pub mime: Option<String>,
+    pub created_at: i64,
+}
+
+/// A forum database to import from. Every list is ordered by ID and starts
+/// after `after`, so an interrupted import carries on where it stopped.
+#[async_trait]
+pub trait ImportSource: Send + Sync {
+    async fn users(&self, after: i64, limit: u64) -> Result<Vec<SourceUser>, DbErr>;
+
+    async fn forums(&self, after: i64, limit: u64) -> Result<Vec<SourceForum>, DbErr>;
+
+    async fn threads(&self, after: i64, limit: u64) -> Result<Vec<SourceThread>, DbErr>;
+
+    /// Visible and unapproved posts; deleted ones are left behind.
+    async fn posts(&self, after: i64, limit: u64) -> Result<Vec<SourcePost>, DbErr>;
+
+    async fn conversations(&self, after: i64, limit: u64)
+        -> Result<Vec<SourceConversation>, DbErr>;
+
+    /// Members of the given conversations, their authors included.
+    async fn participants(&self, conversation_ids: &[i64])
+        -> Result<Vec<SourceParticipant>, DbErr>;
+
+    async fn messages(&self, after: i64, limit: u64) -> Result<Vec<SourceMessage>, DbErr>;
+
+    async fn attachments(&self, after: i64, limit: u64) -> Result<Vec<SourceAttachment>, DbErr>;
+}
+
+/// Comma separated IDs for an `IN (...)` clause.
+pub(super) fn id_list(ids: &[i64]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Rows of a MySQL query taking `after` and `limit` as its parameters, with
+/// `{p}` in `sql` standing for the table prefix.
+pub(super) async fn fetch_page<T: FromQueryResult>(
+    db: &DatabaseConnection,
+    prefix: &str,
+    sql: &str,
+    after: i64,
+    limit: u64,
+) -> Result<Vec<T>, DbErr> {
+    let values: Vec<Value> = vec![after.into(), (limit as i64).into()];
+    T::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::MySql,
+        &sql.replace("{p}", prefix),
+        values,
+    ))
+    .all(db)
+    .await
+}
@@ -0,0 +1,244 @@
+//! XenForo 2, read from its MySQL database.
+//!
+//! Only forum and category nodes are imported, and deleted or redirect
+//! threads are left out. XenForo stores posts as BBCode already; member
+//! tags become mentions, and inline attachment tags are dropped since the
+//! files are attached to the post instead.
+
+use super::source::*;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use sea_orm::{DatabaseConnection, DbBackend, DbErr, FromQueryResult, Statement};
+
+pub struct XenforoSource {
+    db: DatabaseConnection,
+    /// Table prefix, `xf_` unless changed at install
+    prefix: String,
+}
+
+impl XenforoSource {
+    pub fn new(db: DatabaseConnection, prefix: &str) -> Self {
+        Self {
+            db,
+            prefix: prefix.to_owned(),
+        }
+    }
+
+    async fn page<T: FromQueryResult>(
+        &self,
+        sql: &str,
+        after: i64,
+        limit: u64,
+    ) -> Result<Vec<T>, DbErr> {
+        fetch_page(&self.db, &self.prefix, sql, after, limit).await
+    }
+}
+
+#[derive(FromQueryResult)]
+struct PostRow {
+    id: i64,
+    parent_id: i64,
+    user_id: Option<i64>,
+    created_at: i64,
+    content: String,
+    is_approved: i64,
+}
+
+#[async_trait]
+impl ImportSource for XenforoSource {
+    async fn users(&self, after: i64, limit: u64) -> Result<Vec<SourceUser>, DbErr> {
+        self.page(
+            r#"SELECT CAST(u.user_id AS SIGNED) AS id, u.username AS name,
+                NULLIF(u.email, '') AS email,
+                CAST(u.register_date AS SIGNED) AS created_at,
+                NULLIF(p.signature, '') AS signature
+            FROM {p}user u
+            LEFT JOIN {p}user_profile p ON p.user_id = u.user_id
+            WHERE u.user_id > ?
+            ORDER BY u.user_id LIMIT ?"#,
+            after,
+            limit,
+        )
+        .await
+    }
+
+    async fn forums(&self, after: i64, limit: u64) -> Result<Vec<SourceForum>, DbErr> {
+        self.page(
+            r#"SELECT CAST(node_id AS SIGNED) AS id,
+                CAST(NULLIF(parent_node_id, 0) AS SIGNED) AS parent_id,
+                title, NULLIF(description, '') AS description,
+                CAST(display_order AS SIGNED) AS display_order
+            FROM {p}node
+            WHERE node_id > ? AND node_type_id IN ('Forum', 'Category')
+            ORDER BY node_id LIMIT ?"#,
+            after,
+            limit,
+        )
+        .await
+    }
+
+    async fn threads(&self, after: i64, limit: u64) -> Result<Vec<SourceThread>, DbErr> {
+        self.page(
+            r#"SELECT CAST(thread_id AS SIGNED) AS id, CAST(node_id AS SIGNED) AS forum_id,
+                CAST(NULLIF(user_id, 0) AS SIGNED) AS user_id, title,
+                CAST(post_date AS SIGNED) AS created_at,
+                CAST(view_count AS SIGNED) AS view_count,
+                CAST(discussion_open = 0 AS SIGNED) AS is_locked,
+                CAST(sticky AS SIGNED) AS is_pinned
+            FROM {p}thread
+            WHERE thread_id > ? AND discussion_state <> 'deleted'
+              AND discussion_type <> 'redirect'
+            ORDER BY thread_id LIMIT ?"#,
+            after,
+            limit,
+        )
+        .await
+    }
+
+    async fn posts(&self, after: i64, limit: u64) -> Result<Vec<SourcePost>, DbErr> {
+        let rows: Vec<PostRow> = self
+            .page(
+                r#"SELECT CAST(post_id AS SIGNED) AS id, CAST(thread_id AS SIGNED) AS parent_id,
+                    CAST(NULLIF(user_id, 0) AS SIGNED) AS user_id,
+                    CAST(post_date AS SIGNED) AS created_at, message AS content,
+                    CAST(message_state = 'visible' AS SIGNED) AS is_approved
+                FROM {p}post
+                WHERE post_id > ? AND message_state <> 'deleted'
+                ORDER BY post_id LIMIT ?"#,
+                after,
+                limit,
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SourcePost {
+                id: row.id,
+                thread_id: row.parent_id,
+                user_id: row.user_id,
+                created_at: row.created_at,
+                content: to_bbcode(&row.content),
+                is_approved: row.is_approved != 0,
+            })
+            .collect())
+    }
+
+    async fn conversations(
+        &self,
+        after: i64,
+        limit: u64,
+    ) -> Result<Vec<SourceConversation>, DbErr> {
+        self.page(
+            r#"SELECT CAST(conversation_id AS SIGNED) AS id, title,
+                CAST(NULLIF(user_id, 0) AS SIGNED) AS user_id,
+                CAST(start_date AS SIGNED) AS created_at
+            FROM {p}conversation_master
+            WHERE conversation_id > ?
+            ORDER BY conversation_id LIMIT ?"#,
+            after,
+            limit,
+        )
+        .await
+    }
+
+    async fn participants(
+        &self,
+        conversation_ids: &[i64],
+    ) -> Result<Vec<SourceParticipant>, DbErr> {
+        let sql = format!(
+            r#"SELECT CAST(conversation_id AS SIGNED) AS conversation_id,
+                    CAST(user_id AS SIGNED) AS user_id
+                FROM {p}conversation_recipient
+                WHERE conversation_id IN ({ids})
+                UNION
+                SELECT CAST(conversation_id AS SIGNED), CAST(user_id AS SIGNED)
+                FROM {p}conversation_master
+                WHERE conversation_id IN ({ids}) AND user_id <> 0"#,
+            p = self.prefix,
+            ids = id_list(conversation_ids)
+        );
+        SourceParticipant::find_by_statement(Statement::from_string(DbBackend::MySql, sql))
+            .all(&self.db)
+            .await
+    }
+
+    async fn messages(&self, after: i64, limit: u64) -> Result<Vec<SourceMessage>, DbErr> {
+        let rows: Vec<PostRow> = self
+            .page(
+                r#"SELECT CAST(message_id AS SIGNED) AS id,
+                    CAST(conversation_id AS SIGNED) AS parent_id,
+                    CAST(NULLIF(user_id, 0) AS SIGNED) AS user_id,
+                    CAST(message_date AS SIGNED) AS created_at, message AS content,
+                    CAST(1 AS SIGNED) AS is_approved
+                FROM {p}conversation_message
+                WHERE message_id > ?
+                ORDER BY message_id LIMIT ?"#,
+                after,
+                limit,
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SourceMessage {
+                id: row.id,
+                conversation_id: row.parent_id,
+                user_id: row.user_id,
+                created_at: row.created_at,
+                content: to_bbcode(&row.content),
+            })
+            .collect())
+    }
+
+    async fn attachments(&self, after: i64, limit: u64) -> Result<Vec<SourceAttachment>, DbErr> {
+        // Files live under `internal_data/attachments`, in a directory per
+        // thousand data IDs.
+        self.page(
+            r#"SELECT CAST(a.attachment_id AS SIGNED) AS id,
+                CAST(IF(a.content_type = 'post', a.content_id, NULL) AS SIGNED) AS post_id,
+                CAST(IF(a.content_type = 'conversation_message', a.content_id, NULL) AS SIGNED)
+                    AS message_id,
+                CAST(NULLIF(d.user_id, 0) AS SIGNED) AS user_id, d.filename,
+                CONCAT(FLOOR(d.data_id / 1000), '/', d.data_id, '-', d.file_hash, '.data') AS path,
+                CAST(NULL AS CHAR) AS mime,
+                CAST(d.upload_date AS SIGNED) AS created_at
+            FROM {p}attachment a
+            JOIN {p}attachment_data d ON d.data_id = a.data_id
+            WHERE a.attachment_id > ?
+              AND a.content_type IN ('post', 'conversation_message')
+              AND a.unassociated = 0
+            ORDER BY a.attachment_id LIMIT ?"#,
+            after,
+            limit,
+        )
+        .await
+    }
+}
+
+static USER_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)\[user=\d+\]@?(.*?)\[/user\]").unwrap());
+
+static ATTACH_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)\[attach(?:[= ][^\]]*)?\]\d*\[/attach\]").unwrap());
+
+/// BBCode for XenForo's stored text.
+pub fn to_bbcode(text: &str) -> String {
+    let text = USER_TAG.replace_all(text, "@$1");
+    ATTACH_TAG.replace_all(&text, "").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_bbcode() {
+        assert_eq!(
+            to_bbcode("Thanks [USER=12]@alice[/USER]! [ATTACH type=\"full\" alt=\"x\"]45[/ATTACH]"),
+            "Thanks @alice! "
+        );
+        assert_eq!(to_bbcode("[ATTACH=full]45[/ATTACH]after"), "after");
+        assert_eq!(to_bbcode("[b]kept[/b]"), "[b]kept[/b]");
+    }
+}
@@ -0,0 +1,338 @@
+//! Background job queue.
+//!
+//! Jobs are rows in the `jobs` table with a kind and a JSON payload. The
+//! worker claims due jobs in batches, leasing each one so that a job held by
+//! a worker that died is picked up again, and runs the handler for its kind,
+//! renewing the lease as each job starts.
+//! A job that succeeds is deleted. One that fails is scheduled again after a
+//! delay that doubles with each attempt, until it runs out of attempts and is
+//! kept with `failed_at` set.
+
+use crate::db::get_db_pool;
+use crate::orm::jobs;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{entity::*, query::*, sea_query::Expr, DbBackend, DbErr, FromQueryResult};
+use std::time::Duration;
+
+/// Delay before the first retry of a failed job
+const BASE_RETRY_SECONDS: i64 = 30;
+
+/// Longest delay between attempts
+const MAX_RETRY_SECONDS: i64 = 6 * 60 * 60;
+
+/// How long a claimed job is held before another worker may take it
+const LEASE_SECONDS: i64 = 5 * 60;
+
+/// Longest error message kept on a job
+const MAX_ERROR_LENGTH: usize = 2000;
+
+/// Kinds of job and the work they do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobKind {
+    /// Send one webhook delivery
+    WebhookDelivery,
+    /// Send one ActivityPub activity to a remote inbox
+    ActivityPubDelivery,
+    /// Send one email
+    EmailDelivery,
+    /// Queue the next batch of an announcement's emails
+    MassEmailBatch,
+    /// Scan one attachment with clamd
+    AntivirusScan,
+    /// Produce the renditions of one uploaded video
+    VideoTranscode,
+    /// Update the search index for changed threads or posts
+    SearchIndex,
+    /// Produce the thumbnail renditions of one uploaded image
+    AttachmentThumbnails,
+    /// Re-encode one image into the enabled variant formats
+    ImageVariants,
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::WebhookDelivery => "webhook.delivery",
+            JobKind::ActivityPubDelivery => "activitypub.delivery",
+            JobKind::EmailDelivery => "email.delivery",
+            JobKind::MassEmailBatch => "email.mass_batch",
+            JobKind::AntivirusScan => "antivirus.scan",
+            JobKind::VideoTranscode => "video.transcode",
+            JobKind::SearchIndex => "search.index",
+            JobKind::AttachmentThumbnails => "attachment.thumbnails",
+            JobKind::ImageVariants => "attachment.variants",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "webhook.delivery" => Some(JobKind::WebhookDelivery),
+            "activitypub.delivery" => Some(JobKind::ActivityPubDelivery),
+            "email.delivery" => Some(JobKind::EmailDelivery),
+            "email.mass_batch" => Some(JobKind::MassEmailBatch),
+            "antivirus.scan" => Some(JobKind::AntivirusScan),
+            "video.transcode" => Some(JobKind::VideoTranscode),
+            "search.index" => Some(JobKind::SearchIndex),
+            "attachment.thumbnails" => Some(JobKind::AttachmentThumbnails),
+            "attachment.variants" => Some(JobKind::ImageVariants),
+            _ => None,
+        }
+    }
+}
+
+/// A claimed job, as handed to its handler.
+#[derive(Debug, FromQueryResult)]
+pub struct Job {
+    pub id: i32,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    /// Attempts so far, this one included
+    pub attempts: i32,
+    pub max_attempts: i32,
+}
+
+impl Job {
+    /// Whether a failure now means the job is given up.
+    pub fn is_last_attempt(&self) -> bool {
+        self.attempts >= self.max_attempts
+    }
+}
+
+/// Queues a job to run as soon as the worker gets to it.
+pub async fn enqueue(
+    kind: JobKind,
+    payload: serde_json::Value,
+    max_attempts: i32,
+) -> Result<jobs::Model, DbErr> {
+    enqueue_at(kind, payload, max_attempts, Utc::now().naive_utc()).await
+}
+
+/// Queues a job to run no sooner than `run_at`.
+pub async fn enqueue_at(
+    kind: JobKind,
+    payload: serde_json::Value,
+    max_attempts: i32,
+    run_at: NaiveDateTime,
+) -> Result<jobs::Model, DbErr> {
+    jobs::ActiveModel {
+        kind: Set(kind.as_str().to_owned()),
+        payload: Set(payload),
+        attempts: Set(0),
+        max_attempts: Set(max_attempts.max(1)),
+        run_at: Set(run_at),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(get_db_pool())
+    .await
+}
+
+/// Delay before the next attempt at a job that has failed `attempts` times.
+pub fn retry_delay(attempts: i32) -> chrono::Duration {
+    let doublings = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    let seconds = BASE_RETRY_SECONDS
+        .saturating_mul(1i64 << doublings)
+        .min(MAX_RETRY_SECONDS);
+    chrono::Duration::seconds(seconds)
+}
+
+/// Leases up to `limit` due jobs, oldest first, counting an attempt at each.
+async fn claim_batch(limit: u64, now: NaiveDateTime) -> Result<Vec<Job>, DbErr> {
+    Job::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"UPDATE jobs
+            SET attempts = attempts + 1, locked_until = $2
+            WHERE id IN (
+                SELECT id FROM jobs
+                WHERE failed_at IS NULL
+                  AND run_at <= $1
+                  AND (locked_until IS NULL OR locked_until < $1)
+                ORDER BY run_at
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, kind, payload, attempts, max_attempts"#,
+        vec![
+            now.into(),
+            (now + chrono::Duration::seconds(LEASE_SECONDS)).into(),
+            (limit as i64).into(),
+        ],
+    ))
+    .all(get_db_pool())
+    .await
+}
+
+/// Extends the lease on a claimed job just before it runs, since the jobs
+/// ahead of it in the batch may have used up the lease it was claimed with.
+/// Returns false if another worker has claimed the job since.
+async fn renew_lease(job: &Job, now: NaiveDateTime) -> Result<bool, DbErr> {
+    let result = jobs::Entity::update_many()
+        .col_expr(
+            jobs::Column::LockedUntil,
+            Expr::value(now + chrono::Duration::seconds(LEASE_SECONDS)),
+        )
+        .filter(jobs::Column::Id.eq(job.id))
+        .filter(jobs::Column::Attempts.eq(job.attempts))
+        .filter(jobs::Column::FailedAt.is_null())
+        .exec(get_db_pool())
+        .await?;
+    Ok(result.rows_affected == 1)
+}
+
+/// Removes a job that has done its work.
+async fn complete(job: &Job) -> Result<(), DbErr> {
+    jobs::Entity::delete_by_id(job.id)
+        .exec(get_db_pool())
+        .await?;
+    Ok(())
+}
+
+/// Schedules another attempt at a failed job, or gives it up.
+async fn fail(job: &Job, error: &str, now: NaiveDateTime) -> Result<(), DbErr> {
+    let error: String = error.chars().take(MAX_ERROR_LENGTH).collect();
+    let mut update = jobs::Entity::update_many()
+        .col_expr(jobs::Column::LastError, Expr::value(error))
+        .col_expr(
+            jobs::Column::LockedUntil,
+            Expr::value(None::<NaiveDateTime>),
+        )
+        .filter(jobs::Column::Id.eq(job.id));
+
+    update = match job.is_last_attempt() {
+        true => update.col_expr(jobs::Column::FailedAt, Expr::value(now)),
+        false => update.col_expr(
+            jobs::Column::RunAt,
+            Expr::value(now + retry_delay(job.attempts)),
+        ),
+    };
+
+    update.exec(get_db_pool()).await?;
+    Ok(())
+}
+
+/// Runs one job with the handler for its kind.
+async fn run(job: &Job) -> Result<(), String> {
+    match JobKind::parse(&job.kind) {
+        Some(JobKind::WebhookDelivery) => crate::webhooks::run_delivery_job(job).await,
+        Some(JobKind::ActivityPubDelivery) => crate::activitypub::run_delivery_job(job).await,
+        Some(JobKind::EmailDelivery) => crate::email::queue::run_delivery_job(job).await,
+        Some(JobKind::MassEmailBatch) => crate::email::mass::run_batch_job(job).await,
+        Some(JobKind::AntivirusScan) => crate::antivirus::run_scan_job(job).await,
+        Some(JobKind::VideoTranscode) => crate::transcode::run_transcode_job(job).await,
+        Some(JobKind::SearchIndex) => crate::search::indexer::run_index_job(job).await,
+        Some(JobKind::AttachmentThumbnails) => crate::filesystem::run_thumbnail_job(job).await,
+        Some(JobKind::ImageVariants) => crate::filesystem::run_variant_job(job).await,
+        None => Err(format!("unknown job kind {:?}", job.kind)),
+    }
+}
+
+/// Runs due jobs in batches of `batch_size` until none are left. Returns the
+/// number of jobs run, whether they succeeded or not.
+pub async fn run_due(batch_size: u64) -> usize {
+    let mut ran = 0;
+    loop {
+        let batch = match claim_batch(batch_size.max(1), Utc::now().naive_utc()).await {
+            Ok(batch) if !batch.is_empty() => batch,
+            Ok(_) => break,
+            Err(e) => {
+                log::error!("jobs::claim_batch: {}", e);
+                break;
+            }
+        };
+
+        for job in batch {
+            match renew_lease(&job, Utc::now().naive_utc()).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    log::debug!("jobs: job {} was claimed by another worker", job.id);
+                    continue;
+                }
+                Err(e) => {
+                    log::error!("jobs::renew_lease: {}", e);
+                    continue;
+                }
+            }
+
+            let result = match run(&job).await {
+                Ok(()) => complete(&job).await,
+                Err(e) => {
+                    log::warn!(
+                        "Job {} ({}) failed on attempt {}/{}: {}",
+                        job.id,
+                        job.kind,
+                        job.attempts,
+                        job.max_attempts,
+                        e
+                    );
+                    fail(&job, &e, Utc::now().naive_utc()).await
+                }
+            };
+            if let Err(e) = result {
+                log::error!("jobs: failed to record outcome of job {}: {}", job.id, e);
+            }
+            ran += 1;
+        }
+    }
+    ran
+}
+
+/// Starts the job worker.
+pub fn spawn_worker() {
+    let config = crate::app_config::jobs();
+
+    actix_web::rt::spawn(async move {
+        let mut interval =
+            actix_web::rt::time::interval(Duration::from_secs(config.poll_interval_seconds.max(1)));
+
+        loop {
+            interval.tick().await;
+            run_due(config.batch_size).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_kind_round_trip() {
+        for kind in [
+            JobKind::WebhookDelivery,
+            JobKind::ActivityPubDelivery,
+            JobKind::EmailDelivery,
+            JobKind::MassEmailBatch,
+            JobKind::AntivirusScan,
+            JobKind::VideoTranscode,
+            JobKind::SearchIndex,
+            JobKind::AttachmentThumbnails,
+            JobKind::ImageVariants,
+        ] {
+            assert_eq!(JobKind::parse(kind.as_str()), Some(kind));
+        }
+        assert_eq!(JobKind::parse("nonsense"), None);
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_a_cap() {
+        assert_eq!(retry_delay(1).num_seconds(), 30);
+        assert_eq!(retry_delay(2).num_seconds(), 60);
+        assert_eq!(retry_delay(3).num_seconds(), 120);
+        assert_eq!(retry_delay(20).num_seconds(), MAX_RETRY_SECONDS);
+        assert_eq!(retry_delay(i32::MAX).num_seconds(), MAX_RETRY_SECONDS);
+        assert_eq!(retry_delay(0).num_seconds(), 30);
+    }
+
+    #[test]
+    fn test_is_last_attempt() {
+        let job = Job {
+            id: 1,
+            kind: "webhook.delivery".to_owned(),
+            payload: serde_json::Value::Null,
+            attempts: 3,
+            max_attempts: 3,
+        };
+        assert!(job.is_last_attempt());
+        assert!(!Job { attempts: 2, ..job }.is_last_attempt());
+    }
+}
@@ -3,11 +3,18 @@ extern crate ffmpeg_next;
 extern crate linkify;
 
 pub mod activities;
+pub mod activitypub;
+pub mod antivirus;
+pub mod api_token;
 pub mod app_config;
+pub mod app_state;
+pub mod assets;
 pub mod attachment;
 pub mod auth_2fa;
+pub mod backplane;
 pub mod badges;
 pub mod bbcode;
+pub mod cache;
 pub mod captcha;
 pub mod config;
 pub mod constants;
@@ -19,19 +26,36 @@ pub mod ffmpeg;
 pub mod filesystem;
 pub mod global;
 pub mod group;
+pub mod imaging;
+pub mod import;
 pub mod ip;
+pub mod jobs;
+pub mod login_throttle;
 pub mod middleware;
+pub mod notices;
 pub mod notifications;
 pub mod orm;
+pub mod outbound;
+pub mod pagination;
 pub mod permission;
+pub mod presence;
 pub mod rate_limit;
+pub mod reputation;
+pub mod runoff;
+pub mod search;
 pub mod session;
+pub mod signing;
 pub mod spam;
+pub mod ssrf;
 pub mod storage;
+pub mod telemetry;
 pub mod template;
 pub mod theme;
+pub mod tls;
+pub mod transcode;
 pub mod ugc;
 pub mod url;
 pub mod user;
 pub mod web;
+pub mod webhooks;
 pub mod word_filter;
@@ -0,0 +1,370 @@
+//! Failed sign-in tracking
+//!
+//! Failures are counted per account (on `users`) and per IP address (in
+//! `login_failures`), so every instance sees the same counts. Once either
+//! count passes `login_delay_after`, each further attempt has to wait, twice
+//! as long after every failure, up to `login_delay_max_seconds`. Past
+//! `max_failed_logins` the account is locked, and past
+//! `max_failed_logins_per_ip` the address is. A count is forgotten after
+//! `failure_window_minutes` without a failure. The CAPTCHA comes up once
+//! either count reaches `[captcha] failed_login_threshold`.
+
+use crate::app_config::SecurityConfig;
+use crate::db::get_db_pool;
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{ConnectionTrait, DbBackend, DbErr, FromQueryResult, Statement};
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Where a sign-in attempt stands before the password is checked.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    /// The higher of the account's and the address's recent failures
+    pub failures: u32,
+    /// Time left before another attempt is accepted
+    pub wait: Option<Duration>,
+    /// When the address's lockout ends, if it is locked out
+    pub ip_locked_until: Option<NaiveDateTime>,
+}
+
+/// An account or address with recent failures, for the admin panel.
+#[derive(Debug, FromQueryResult)]
+pub struct Lockout {
+    /// User ID for accounts
+    pub user_id: Option<i32>,
+    /// Username for accounts, the address for addresses
+    pub name: String,
+    pub failures: i32,
+    pub last_failed_at: Option<NaiveDateTime>,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+impl Lockout {
+    pub fn is_locked(&self) -> bool {
+        matches!(self.locked_until, Some(until) if until > Utc::now().naive_utc())
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Failures {
+    failures: i32,
+    last_failed_at: Option<NaiveDateTime>,
+    locked_until: Option<NaiveDateTime>,
+}
+
+/// How long to wait after `failures` failures in a row.
+pub fn delay(failures: u32, config: &SecurityConfig) -> Duration {
+    if failures <= config.login_delay_after || config.login_delay_seconds == 0 {
+        return Duration::ZERO;
+    }
+    let doublings = (failures - config.login_delay_after - 1).min(16);
+    let seconds = (config.login_delay_seconds as u64) << doublings;
+    Duration::from_secs(seconds.min(config.login_delay_max_seconds as u64))
+}
+
+/// Whether failures last seen at `last_failed_at` are old enough to forget.
+fn is_stale(
+    last_failed_at: Option<NaiveDateTime>,
+    now: NaiveDateTime,
+    config: &SecurityConfig,
+) -> bool {
+    match last_failed_at {
+        Some(at) => now - at > chrono::Duration::minutes(config.failure_window_minutes as i64),
+        None => true,
+    }
+}
+
+/// Failures still counted and the wait they impose, given `now`.
+fn assess(row: &Failures, now: NaiveDateTime, config: &SecurityConfig) -> (u32, Option<Duration>) {
+    if is_stale(row.last_failed_at, now, config) {
+        return (0, None);
+    }
+    let failures = row.failures.max(0) as u32;
+    let wait = row.last_failed_at.and_then(|at| {
+        let since = (now - at).to_std().unwrap_or_default();
+        delay(failures, config)
+            .checked_sub(since)
+            .filter(|wait| !wait.is_zero())
+    });
+    (failures, wait)
+}
+
+fn parse_ip(ip: &str) -> Option<String> {
+    ip.parse::<IpAddr>().ok().map(|ip| ip.to_string())
+}
+
+async fn ip_failures_row(ip: &str) -> Result<Option<Failures>, DbErr> {
+    let Some(ip) = parse_ip(ip) else {
+        return Ok(None);
+    };
+    Failures::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT failures, last_failed_at, locked_until FROM login_failures WHERE ip_address = $1::inet",
+        vec![ip.into()],
+    ))
+    .one(get_db_pool())
+    .await
+}
+
+/// Where an attempt to sign in as `username` from `ip` stands.
+pub async fn check(ip: &str, username: &str) -> Result<Throttle, DbErr> {
+    let config = crate::app_config::security();
+    let now = Utc::now().naive_utc();
+
+    let account = Failures::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"SELECT u.failed_login_attempts AS failures, u.last_failed_login_at AS last_failed_at,
+                u.locked_until
+            FROM users u
+            JOIN user_names n ON n.user_id = u.id
+            WHERE LOWER(n.name) = LOWER($1)
+            LIMIT 1"#,
+        vec![username.trim().into()],
+    ))
+    .one(get_db_pool())
+    .await?;
+    let address = ip_failures_row(ip).await?;
+
+    let mut throttle = Throttle::default();
+    for row in account.iter().chain(address.iter()) {
+        let (failures, wait) = assess(row, now, &config);
+        throttle.failures = throttle.failures.max(failures);
+        throttle.wait = throttle.wait.max(wait);
+    }
+    throttle.ip_locked_until = address
+        .and_then(|row| row.locked_until)
+        .filter(|until| *until > now);
+    Ok(throttle)
+}
+
+/// Recent failures from `ip`, for deciding whether to show the CAPTCHA.
+pub async fn ip_failures(ip: &str) -> Result<u32, DbErr> {
+    let config = crate::app_config::security();
+    Ok(ip_failures_row(ip)
+        .await?
+        .map(|row| assess(&row, Utc::now().naive_utc(), &config).0)
+        .unwrap_or(0))
+}
+
+/// Count a failed sign-in from `ip`, locking the address out past the limit.
+pub async fn record_ip_failure(ip: &str) -> Result<(), DbErr> {
+    let Some(ip) = parse_ip(ip) else {
+        return Ok(());
+    };
+    let config = crate::app_config::security();
+    let now = Utc::now().naive_utc();
+    let forget_before = now - chrono::Duration::minutes(config.failure_window_minutes as i64);
+    let lock_until = now + chrono::Duration::minutes(config.ip_lockout_duration_minutes as i64);
+
+    get_db_pool()
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO login_failures (ip_address, failures, last_failed_at)
+                VALUES ($1::inet, 1, $2)
+                ON CONFLICT (ip_address) DO UPDATE SET
+                    failures = CASE WHEN login_failures.last_failed_at < $3 THEN 1
+                        ELSE login_failures.failures + 1 END,
+                    last_failed_at = $2"#,
+            vec![ip.clone().into(), now.into(), forget_before.into()],
+        ))
+        .await?;
+
+    let locked = get_db_pool()
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"UPDATE login_failures SET locked_until = $2
+                WHERE ip_address = $1::inet AND failures >= $3
+                    AND (locked_until IS NULL OR locked_until < $4)"#,
+            vec![
+                ip.clone().into(),
+                lock_until.into(),
+                (config.max_failed_logins_per_ip as i32).into(),
+                now.into(),
+            ],
+        ))
+        .await?;
+    if locked.rows_affected() > 0 {
+        log::warn!("IP address {} locked out after failed logins", ip);
+    }
+    Ok(())
+}
+
+/// Count a failed sign-in on an account. Returns whether it is now locked.
+/// The count is worked out in the update itself, so failures racing each
+/// other are all counted.
+pub async fn record_account_failure(user_id: i32) -> Result<bool, DbErr> {
+    let config = crate::app_config::security();
+    let now = Utc::now().naive_utc();
+    let forget_before = now - chrono::Duration::minutes(config.failure_window_minutes as i64);
+    let lock_until = now + chrono::Duration::minutes(config.lockout_duration_minutes as i64);
+
+    let row = Failures::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"UPDATE users SET
+                failed_login_attempts = CASE
+                    WHEN last_failed_login_at IS NULL OR last_failed_login_at < $3 THEN 0
+                    ELSE failed_login_attempts END + 1,
+                locked_until = CASE
+                    WHEN CASE
+                        WHEN last_failed_login_at IS NULL OR last_failed_login_at < $3 THEN 0
+                        ELSE failed_login_attempts END + 1 >= $4 THEN $5
+                    ELSE locked_until END,
+                last_failed_login_at = $2
+            WHERE id = $1
+            RETURNING failed_login_attempts AS failures, last_failed_login_at AS last_failed_at,
+                locked_until"#,
+        vec![
+            user_id.into(),
+            now.into(),
+            forget_before.into(),
+            (config.max_failed_logins as i32).into(),
+            lock_until.into(),
+        ],
+    ))
+    .one(get_db_pool())
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(false);
+    };
+    let locked = row.failures >= config.max_failed_logins as i32;
+    if locked {
+        log::warn!(
+            "Account locked due to {} failed login attempts: user_id={}",
+            row.failures,
+            user_id
+        );
+    }
+    Ok(locked)
+}
+
+/// Forget an account's failures after it signed in or its lockout ran out.
+/// A lockout that is still running, e.g. one set by a failure racing the
+/// sign-in, is left alone.
+pub async fn clear_account(user_id: i32) -> Result<(), DbErr> {
+    get_db_pool()
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"UPDATE users SET failed_login_attempts = 0, locked_until = NULL,
+                    last_failed_login_at = NULL
+                WHERE id = $1 AND (failed_login_attempts > 0 OR locked_until IS NOT NULL)
+                    AND (locked_until IS NULL OR locked_until <= $2)"#,
+            vec![user_id.into(), Utc::now().naive_utc().into()],
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Forget an account's failures and lift its lockout, running or not.
+pub async fn unlock_account(user_id: i32) -> Result<(), DbErr> {
+    get_db_pool()
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"UPDATE users SET failed_login_attempts = 0, locked_until = NULL,
+                    last_failed_login_at = NULL
+                WHERE id = $1 AND (failed_login_attempts > 0 OR locked_until IS NOT NULL)"#,
+            vec![user_id.into()],
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Forget an address's failures and lift its lockout.
+pub async fn clear_ip(ip: &str) -> Result<(), DbErr> {
+    let Some(ip) = parse_ip(ip) else {
+        return Ok(());
+    };
+    get_db_pool()
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "DELETE FROM login_failures WHERE ip_address = $1::inet",
+            vec![ip.into()],
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Accounts and addresses with failures still counted or a lockout running.
+pub async fn list() -> Result<(Vec<Lockout>, Vec<Lockout>), DbErr> {
+    let config = crate::app_config::security();
+    let now = Utc::now().naive_utc();
+    let forget_before = now - chrono::Duration::minutes(config.failure_window_minutes as i64);
+
+    let accounts = Lockout::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"SELECT u.id AS user_id, n.name, u.failed_login_attempts AS failures,
+                u.last_failed_login_at AS last_failed_at, u.locked_until
+            FROM users u
+            JOIN user_names n ON n.user_id = u.id
+            WHERE u.locked_until > $1
+                OR (u.failed_login_attempts > 0 AND u.last_failed_login_at >= $2)
+            ORDER BY u.last_failed_login_at DESC NULLS LAST"#,
+        vec![now.into(), forget_before.into()],
+    ))
+    .all(get_db_pool())
+    .await?;
+
+    let addresses = Lockout::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"SELECT NULL::INT AS user_id, host(ip_address) AS name, failures, last_failed_at,
+                locked_until
+            FROM login_failures
+            WHERE locked_until > $1 OR last_failed_at >= $2
+            ORDER BY last_failed_at DESC"#,
+        vec![now.into(), forget_before.into()],
+    ))
+    .all(get_db_pool())
+    .await?;
+
+    Ok((accounts, addresses))
+}
+
+/// Drop address counts that are forgotten and no longer locked.
+pub async fn prune() -> Result<(), DbErr> {
+    let config = crate::app_config::security();
+    let now = Utc::now().naive_utc();
+    let forget_before = now - chrono::Duration::minutes(config.failure_window_minutes as i64);
+    get_db_pool()
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"DELETE FROM login_failures
+                WHERE last_failed_at < $1 AND (locked_until IS NULL OR locked_until < $2)"#,
+            vec![forget_before.into(), now.into()],
+        ))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_doubles_up_to_max() {
+        let config = SecurityConfig::default();
+        assert_eq!(delay(0, &config), Duration::ZERO);
+        assert_eq!(delay(2, &config), Duration::ZERO);
+        assert_eq!(delay(3, &config), Duration::from_secs(2));
+        assert_eq!(delay(4, &config), Duration::from_secs(4));
+        assert_eq!(delay(6, &config), Duration::from_secs(16));
+        assert_eq!(delay(40, &config), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_assess_forgets_old_failures() {
+        let config = SecurityConfig::default();
+        let now = Utc::now().naive_utc();
+        let row = |failures, minutes_ago| Failures {
+            failures,
+            last_failed_at: Some(now - chrono::Duration::minutes(minutes_ago)),
+            locked_until: None,
+        };
+
+        let (failures, wait) = assess(&row(4, 0), now, &config);
+        assert_eq!(failures, 4);
+        assert!(matches!(wait, Some(wait) if wait <= Duration::from_secs(4)));
+        // The wait has passed, but the failures still count
+        assert_eq!(assess(&row(4, 5), now, &config), (4, None));
+        assert_eq!(assess(&row(4, 61), now, &config), (0, None));
+    }
+}
@@ -1,7 +1,8 @@
+use crate::app_state::AppState;
 use crate::config::Config;
 use crate::db::get_db_pool;
 use crate::orm::themes;
-use crate::permission::PermissionData;
+use crate::permission::Permissions;
 use crate::user::Profile;
 use actix::fut::ready;
 use actix_session::Session;
@@ -23,7 +24,7 @@ pub struct ClientCtxInner {
     /// List of user group ids. Guests may receive unregistered/portal roles.
     pub groups: Vec<i32>,
     /// Permission data.
-    pub permissions: Data<PermissionData>,
+    pub permissions: Permissions,
     /// Site configuration.
     pub config: Option<Data<Arc<Config>>>,
     /// Randomly generated string for CSR.
@@ -34,19 +35,32 @@ pub struct ClientCtxInner {
     pub unread_notifications: i64,
     /// Unread message count for the user
     pub unread_messages: i64,
+    /// Site-wide notices to show at the top of the page
+    pub notices: Vec<crate::notices::ActiveNotice>,
     /// Time the request started for page load statistics.
     pub request_start: Instant,
     /// Current theme for the user
     pub theme: Option<themes::Model>,
     /// Whether user is in auto theme mode
     pub theme_auto: bool,
+    /// Theme switched to in auto mode when it's dark, where `theme` is the light one
+    pub theme_dark: Option<themes::Model>,
+    /// Time of day auto mode uses the dark theme, instead of the system setting
+    pub theme_schedule: Option<crate::theme::ThemeSchedule>,
+    /// Whether `theme` is one an admin asked to preview with `?preview_theme=`
+    pub theme_preview: bool,
+    /// Scopes of the API token the request was made with. None outside the API.
+    pub api_scopes: Option<Vec<crate::api_token::ApiScope>>,
+    /// Who is making the request, as far as online presence is concerned.
+    /// None outside the session-backed site.
+    pub visitor: Option<crate::presence::Visitor>,
 }
 
 impl Default for ClientCtxInner {
     fn default() -> Self {
         Self {
             // Guests and users.
-            permissions: Data::new(PermissionData::default()),
+            permissions: Permissions::default(),
             config: None,
             groups: Vec::new(),
             // Only users.
@@ -56,17 +70,24 @@ impl Default for ClientCtxInner {
             csrf_token: String::new(), // Will be populated from session
             unread_notifications: 0,
             unread_messages: 0,
+            notices: Vec::new(),
             request_start: Instant::now(),
             theme: crate::theme::get_theme("light"),
             theme_auto: false,
+            theme_dark: None,
+            theme_schedule: None,
+            theme_preview: false,
+            api_scopes: None,
+            visitor: None,
         }
     }
 }
 
 impl ClientCtxInner {
+    #[tracing::instrument(name = "client_context", skip_all)]
     pub async fn from_session(
         session: &Session,
-        permissions: Data<PermissionData>,
+        permissions: Permissions,
         config: Option<Data<Arc<Config>>>,
     ) -> Self {
         use crate::group::get_group_ids_for_client;
@@ -91,13 +112,20 @@ impl ClientCtxInner {
 
         // Get unread message count for logged-in users
         let unread_messages = if let Some(ref user) = client {
-            crate::conversations::count_unread_conversations(user.id)
+            crate::conversations::count_unread_messages(user.id)
                 .await
                 .unwrap_or(0)
         } else {
             0
         };
 
+        let notices = crate::notices::current(client.as_ref().map(|u| u.id), None)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!(error = %e, "Couldn't load notices");
+                Vec::new()
+            });
+
         // Update last activity for logged-in users (rate-limited internally)
         if let Some(ref user) = client {
             let user_id = user.id;
@@ -107,15 +135,25 @@ impl ClientCtxInner {
         }
 
         // Load theme for user
-        let (theme, theme_auto) = if let Some(ref user) = client {
-            let theme_auto = user.theme_auto;
-            let theme_slug = user.theme.as_deref().unwrap_or("light");
-            let theme = crate::theme::get_theme(theme_slug);
-            (theme, theme_auto)
-        } else {
+        let (theme, theme_dark, theme_auto) = match client {
+            Some(ref user) if user.theme_auto => {
+                let (light, dark) = crate::theme::get_auto_themes(
+                    user.theme.as_deref(),
+                    user.theme_dark.as_deref(),
+                );
+                (light, dark, true)
+            }
+            Some(ref user) => {
+                let theme_slug = user.theme.as_deref().unwrap_or("light");
+                (crate::theme::get_theme(theme_slug), None, false)
+            }
             // Guest gets light theme
-            (crate::theme::get_theme("light"), false)
+            None => (crate::theme::get_theme("light"), None, false),
         };
+        let theme_schedule = client
+            .as_ref()
+            .filter(|user| user.theme_auto)
+            .and_then(|user| user.theme_schedule());
 
         ClientCtxInner {
             client,
@@ -125,12 +163,77 @@ impl ClientCtxInner {
             csrf_token,
             unread_notifications,
             unread_messages,
+            notices,
             theme,
             theme_auto,
+            theme_dark,
+            theme_schedule,
             ..Default::default()
         }
     }
 
+    /// Show the page in the theme named by `?preview_theme=`, inactive ones
+    /// included, if the client may manage themes.
+    async fn apply_theme_preview(&mut self, query: &str) {
+        let slug = url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "preview_theme")
+            .map(|(_, slug)| slug.into_owned());
+        let slug = match slug {
+            Some(slug) if !slug.is_empty() => slug,
+            _ => return,
+        };
+        if !ClientCtx(Data::new(self.clone())).can("admin.settings") {
+            return;
+        }
+        if let Some(theme) = crate::theme::find_preview_theme(&slug).await {
+            self.theme = Some(theme);
+            self.theme_auto = false;
+            self.theme_dark = None;
+            self.theme_schedule = None;
+            self.theme_preview = true;
+        }
+    }
+
+    /// Client for a request to the JSON API. The API does not use sessions;
+    /// a request either carries a token, and acts as its owner within the
+    /// token's scopes, or is made as a guest.
+    #[tracing::instrument(name = "client_context", skip_all)]
+    pub async fn from_api_token(
+        token: Option<&str>,
+        permissions: Permissions,
+        config: Option<Data<Arc<Config>>>,
+    ) -> Result<Self, Error> {
+        use crate::group::get_group_ids_for_client;
+
+        let (client, api_scopes) = match token {
+            Some(token) => match crate::api_token::authenticate_token(token).await {
+                Ok(Some((profile, scopes))) => (Some(profile), scopes),
+                Ok(None) => {
+                    return Err(actix_web::error::ErrorUnauthorized(
+                        "Invalid or expired API token",
+                    ))
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "Couldn't check API token");
+                    return Err(actix_web::error::ErrorInternalServerError(
+                        "Couldn't check API token",
+                    ));
+                }
+            },
+            None => (None, Vec::new()),
+        };
+        let groups = get_group_ids_for_client(get_db_pool(), &client).await;
+
+        Ok(ClientCtxInner {
+            client,
+            groups,
+            permissions,
+            config,
+            api_scopes: Some(api_scopes),
+            ..Default::default()
+        })
+    }
+
     /// Returns a hash unique to each request used for CSP.
     /// See: <https://developer.mozilla.org/en-US/docs/Web/HTML/Global_attributes/nonce>
     /// and <https://developer.mozilla.org/en-US/docs/Web/HTTP/CSP>
@@ -171,7 +274,7 @@ impl ClientCtx {
     /// Returns instance of Self with components required for ClientCtxInner.
     pub async fn from_session(
         session: &Session,
-        permissions: Data<PermissionData>,
+        permissions: Permissions,
         config: Option<Data<Arc<Config>>>,
     ) -> Self {
         Self(Data::new(
@@ -179,9 +282,33 @@ impl ClientCtx {
         ))
     }
 
+    /// A guest with the same permission data and configuration, for deciding
+    /// what may be shown to anyone regardless of who is asking.
+    pub async fn as_guest(&self) -> Self {
+        let groups = crate::group::get_group_ids_for_client(get_db_pool(), &None).await;
+        Self(Data::new(ClientCtxInner {
+            groups,
+            permissions: self.0.permissions.clone(),
+            config: self.0.config.clone(),
+            ..Default::default()
+        }))
+    }
+
+    /// A client for `user` in exactly `groups`, for testing what they would
+    /// be allowed to do. Nothing is read from a session, and the groups need
+    /// not be the ones the user is actually in.
+    pub fn impersonate(permissions: Permissions, user: Option<Profile>, groups: Vec<i32>) -> Self {
+        Self(Data::new(ClientCtxInner {
+            client: user,
+            groups,
+            permissions,
+            ..Default::default()
+        }))
+    }
+
     pub fn get_or_default_from_extensions(
         extensions: &mut Extensions,
-        permissions: Data<PermissionData>,
+        permissions: Permissions,
         config: Option<Data<Arc<Config>>>,
     ) -> Self {
         match extensions.get::<Data<ClientCtxInner>>() {
@@ -190,7 +317,7 @@ impl ClientCtx {
             // No existing record; create and insert it.
             None => {
                 let cbox = Data::new(ClientCtxInner {
-                    // Add permission Arc reference to our inner value.
+                    // Share the permission data with our inner value.
                     permissions,
                     config,
                     ..Default::default()
@@ -225,6 +352,11 @@ impl ClientCtx {
         self.0.client.as_ref()
     }
 
+    /// Who is making the request, for online presence
+    pub fn get_visitor(&self) -> Option<&crate::presence::Visitor> {
+        self.0.visitor.as_ref()
+    }
+
     pub fn get_csrf_token(&self) -> &str {
         &self.0.csrf_token
     }
@@ -237,6 +369,11 @@ impl ClientCtx {
         self.0.unread_messages
     }
 
+    /// Site-wide notices to show this client
+    pub fn get_notices(&self) -> &[crate::notices::ActiveNotice] {
+        &self.0.notices
+    }
+
     /// Get the user's active theme
     pub fn get_theme(&self) -> Option<&themes::Model> {
         self.0.theme.as_ref()
@@ -247,6 +384,31 @@ impl ClientCtx {
         self.0.theme_auto
     }
 
+    /// Dark theme of the auto mode pair, shown in place of `get_theme()`
+    /// when the system or schedule says it's dark
+    pub fn get_dark_theme(&self) -> Option<&themes::Model> {
+        self.0.theme_dark.as_ref()
+    }
+
+    /// Get the dark theme's CSS (includes inherited parent CSS)
+    pub fn get_dark_theme_css(&self) -> String {
+        self.0
+            .theme_dark
+            .as_ref()
+            .map(crate::theme::get_theme_full_css)
+            .unwrap_or_default()
+    }
+
+    /// Time of day auto mode uses the dark theme, if it goes by the clock
+    pub fn get_theme_schedule(&self) -> Option<crate::theme::ThemeSchedule> {
+        self.0.theme_schedule
+    }
+
+    /// Check if the theme is being previewed rather than the user's own
+    pub fn is_theme_preview(&self) -> bool {
+        self.0.theme_preview
+    }
+
     /// Get theme CSS to inject into page (includes inherited parent CSS)
     pub fn get_theme_css(&self) -> String {
         self.0
@@ -269,19 +431,59 @@ impl ClientCtx {
         self.0.client.is_some()
     }
 
+    /// Whether the request may act within `scope`. Guests and session clients
+    /// are not limited by scopes, only by their permissions.
+    pub fn has_api_scope(&self, scope: crate::api_token::ApiScope) -> bool {
+        match (&self.0.api_scopes, &self.0.client) {
+            (Some(scopes), Some(_)) => scopes.contains(&scope),
+            _ => true,
+        }
+    }
+
+    /// Require an API token scope. Returns () or ErrorForbidden.
+    pub fn require_api_scope(
+        &self,
+        scope: crate::api_token::ApiScope,
+    ) -> Result<(), actix_web::Error> {
+        if !self.has_api_scope(scope) {
+            return Err(actix_web::error::ErrorForbidden(format!(
+                "This token lacks the {} scope",
+                scope.as_str()
+            )));
+        }
+        Ok(())
+    }
+
     pub fn can(&self, tag: &str) -> bool {
-        self.0.permissions.can(self, tag)
+        self.0.permissions.read().can(self, tag)
     }
 
     /// Check if user can post in a thread (inherits forum permission)
     pub fn can_post_in_thread(&self, thread: &crate::orm::threads::Model) -> bool {
-        // Thread inherits permission from its parent forum
-        self.can_in_forum(&thread.forum_id, "post.create")
+        self.can_in_thread(&thread.id, &thread.forum_id, "post.create")
+    }
+
+    /// Check if user can view a thread, with its overrides applied
+    pub fn can_view_thread(&self, thread_id: &i32, forum_id: &i32) -> bool {
+        self.can_in_thread(thread_id, forum_id, "forum.view")
+            && self.can_in_thread(thread_id, forum_id, "thread.view")
+    }
+
+    /// Check permission in thread context: the thread's own overrides, then
+    /// its forum's with parent inheritance
+    pub fn can_in_thread(&self, thread_id: &i32, forum_id: &i32, permission: &str) -> bool {
+        self.0
+            .permissions
+            .read()
+            .can_in_thread(self, *thread_id, *forum_id, permission)
     }
 
     /// Check permission in forum context with parent inheritance
     pub fn can_in_forum(&self, forum_id: &i32, permission: &str) -> bool {
-        self.0.permissions.can_in_forum(self, *forum_id, permission)
+        self.0
+            .permissions
+            .read()
+            .can_in_forum(self, *forum_id, permission)
     }
 
     /// Check if user can post in a forum
@@ -299,6 +501,12 @@ impl ClientCtx {
         self.can_in_forum(forum_id, "forum.view")
     }
 
+    /// Threads whose own permission overrides may decide visibility apart
+    /// from their forum
+    pub fn threads_with_overrides(&self) -> Vec<i32> {
+        self.0.permissions.read().threads_with_overrides()
+    }
+
     pub fn can_delete_post(&self, post: &crate::web::post::PostForTemplate) -> bool {
         self.is_user() && self.get_id() == post.user_id
     }
@@ -327,7 +535,13 @@ impl ClientCtx {
         &self.0.nonce
     }
 
-    pub fn get_permissions(&self) -> &Data<PermissionData> {
+    /// Address of a static asset by its logical name, fingerprinted when
+    /// hashes were taken at startup
+    pub fn asset_url(&self, name: &str) -> String {
+        crate::assets::url(name)
+    }
+
+    pub fn get_permissions(&self) -> &Permissions {
         &self.0.permissions
     }
 
@@ -382,6 +596,15 @@ impl ClientCtx {
             .unwrap_or(150)
     }
 
+    /// Check if uploaded images are rotated to their EXIF orientation before metadata is stripped
+    pub fn preserve_image_orientation(&self) -> bool {
+        self.0
+            .config
+            .as_ref()
+            .map(|c| c.preserve_image_orientation())
+            .unwrap_or(true)
+    }
+
     /// Require user to be logged in. Returns user_id or ErrorUnauthorized.
     pub fn require_login(&self) -> Result<i32, actix_web::Error> {
         self.get_id()
@@ -436,11 +659,11 @@ impl FromRequest for ClientCtx {
 
     /// Create a Self from request parts asynchronously.
     fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        if let Some(perm_arc) = req.app_data::<Data<PermissionData>>() {
+        if let Some(state) = req.app_data::<Data<AppState>>() {
             let config = req.app_data::<Data<Arc<Config>>>().cloned();
             ready(Ok(ClientCtx::get_or_default_from_extensions(
                 &mut req.extensions_mut(),
-                perm_arc.clone(),
+                state.permissions.clone(),
                 config,
             )))
         } else {
@@ -471,6 +694,42 @@ where
     }
 }
 
+/// Requests under this path are made to the JSON API.
+const API_PATH_PREFIX: &str = "/api/v1/";
+
+/// Record the request in online presence, returning who made it. Only page
+/// loads move a visitor; browsers ask for those with `Accept: text/html`.
+fn record_presence(
+    presence: &crate::presence::Presence,
+    req: &ServiceRequest,
+    user_id: Option<i32>,
+) -> Option<crate::presence::Visitor> {
+    use crate::presence::Visitor;
+    use actix_web::http::header;
+
+    let ip = crate::ip::extract_client_ip(req.request());
+    let visitor = match user_id {
+        Some(user_id) => Visitor::Member(user_id),
+        None => Visitor::Guest(ip.clone()?),
+    };
+    let header_value = |name: header::HeaderName| {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    };
+    let is_page = req.method() == actix_web::http::Method::GET
+        && header_value(header::ACCEPT).map_or(false, |accept| accept.contains("text/html"));
+
+    presence.record_request(
+        visitor.clone(),
+        ip,
+        header_value(header::USER_AGENT),
+        is_page.then(|| req.path().to_owned()),
+    );
+    Some(visitor)
+}
+
 /// Client context middleware
 pub struct ClientCtxMiddleware<S> {
     service: Rc<S>,
@@ -493,6 +752,36 @@ where
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let svc = self.service.clone();
 
+        // The JSON API authenticates with bearer tokens instead of the session cookie.
+        if req.path().starts_with(API_PATH_PREFIX) {
+            let token = req
+                .headers()
+                .get(actix_web::http::header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| {
+                    value
+                        .strip_prefix("Bearer ")
+                        .unwrap_or(value)
+                        .trim()
+                        .to_owned()
+                });
+
+            return Box::pin(async move {
+                if let Some(state) = req.app_data::<Data<AppState>>() {
+                    let permissions = state.permissions.clone();
+                    let config = req.app_data::<Data<Arc<Config>>>().cloned();
+
+                    let inner =
+                        ClientCtxInner::from_api_token(token.as_deref(), permissions, config)
+                            .await?;
+                    crate::telemetry::record_user(&req, inner.client.as_ref().map(|u| u.id));
+                    req.extensions_mut().insert(Data::new(inner));
+                }
+
+                svc.call(req).await
+            });
+        }
+
         // Borrows of `req` must be done in a precise way to avoid conflcits. This order is important.
         let (httpreq, payload) = req.into_parts();
         let session = Session::extract(&httpreq).into_inner();
@@ -500,17 +789,25 @@ where
 
         // If we do not have permission data there is no client interface to access.
         Box::pin(async move {
-            if let Some(perm_arc) = req.app_data::<Data<PermissionData>>() {
-                let perm_arc = perm_arc.clone();
+            if let Some(state) = req.app_data::<Data<AppState>>() {
+                let permissions = state.permissions.clone();
                 let config = req.app_data::<Data<Arc<Config>>>().cloned();
 
                 match session {
                     Ok(session) => {
-                        let inner = ClientCtxInner::from_session(&session, perm_arc, config).await;
+                        let mut inner =
+                            ClientCtxInner::from_session(&session, permissions, config).await;
+                        crate::telemetry::record_user(&req, inner.client.as_ref().map(|u| u.id));
+                        inner.visitor = record_presence(
+                            &state.presence,
+                            &req,
+                            inner.client.as_ref().map(|u| u.id),
+                        );
+                        inner.apply_theme_preview(req.query_string()).await;
                         req.extensions_mut().insert(Data::new(inner))
                     }
                     Err(err) => {
-                        log::error!("Unable to extract Session data in middleware: {}", err);
+                        tracing::error!(error = %err, "Unable to extract Session data in middleware");
                         None
                     }
                 };
@@ -0,0 +1,260 @@
+//! Cross-origin requests to the JSON API
+//!
+//! Browser-based clients on other origins can call `/api/` when their origin
+//! is listed in the `api_cors_allowed_origins` setting. Preflight requests
+//! are answered here without reaching a handler; other requests get the
+//! `Access-Control-*` headers added to their response. Pages outside the API
+//! never get them. The settings are read on every request, so changes made
+//! at Admin → Settings apply at once.
+
+use crate::config::Config;
+use actix::fut::ready;
+use actix_web::body::EitherBody;
+use actix_web::dev::{self, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::http::Method;
+use actix_web::web::Data;
+use actix_web::{Error, HttpResponse};
+use futures::future::{LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Requests under this path get CORS headers.
+const API_PATH_PREFIX: &str = "/api/";
+
+/// Methods the API's handlers answer to
+const ALLOWED_METHODS: &str = "GET, POST, PUT, PATCH, DELETE";
+
+/// Request headers API clients send besides the CORS-safelisted ones
+const ALLOWED_HEADERS: &str = "Authorization, Content-Type";
+
+/// Response headers scripts may read besides the CORS-safelisted ones
+const EXPOSED_HEADERS: &str = "X-Request-Id";
+
+/// Which cross-origin callers the API answers, from the site settings
+#[derive(Debug, Clone)]
+pub struct CorsPolicy {
+    pub allowed_origins: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age_seconds: u64,
+}
+
+impl CorsPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            allowed_origins: config.api_cors_allowed_origins(),
+            allow_credentials: config.api_cors_allow_credentials(),
+            max_age_seconds: config.api_cors_max_age_seconds(),
+        }
+    }
+
+    fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    /// Value of `Access-Control-Allow-Origin` for a request from `origin`,
+    /// or None when that origin may not call the API.
+    pub fn allow_origin(&self, origin: &str) -> Option<String> {
+        if self.allows_any_origin() {
+            return Some("*".to_string());
+        }
+        let origin = origin.trim_end_matches('/');
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+            .then(|| origin.to_string())
+    }
+
+    /// Add the headers answering a request from `origin` to `headers`.
+    /// Credentials are never allowed alongside `*`, which browsers refuse.
+    pub fn apply(&self, origin: &str, preflight: bool, headers: &mut HeaderMap) {
+        let allow_origin = match self
+            .allow_origin(origin)
+            .and_then(|value| HeaderValue::from_str(&value).ok())
+        {
+            Some(value) => value,
+            None => return,
+        };
+        let any_origin = allow_origin == "*";
+
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if !any_origin {
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+            if self.allow_credentials {
+                headers.insert(
+                    header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                );
+            }
+        }
+
+        if preflight {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_METHODS,
+                HeaderValue::from_static(ALLOWED_METHODS),
+            );
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                HeaderValue::from_static(ALLOWED_HEADERS),
+            );
+            headers.insert(header::ACCESS_CONTROL_MAX_AGE, self.max_age_seconds.into());
+        } else {
+            headers.insert(
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                HeaderValue::from_static(EXPOSED_HEADERS),
+            );
+        }
+    }
+}
+
+/// Middleware answering cross-origin requests to the API.
+#[derive(Clone, Copy, Default)]
+pub struct ApiCors;
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for ApiCors
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = ApiCorsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiCorsMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct ApiCorsMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiCorsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        let origin = req
+            .headers()
+            .get(header::ORIGIN)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let policy = req
+            .app_data::<Data<Arc<Config>>>()
+            .map(|config| CorsPolicy::from_config(config));
+        let (origin, policy) = match (origin, policy) {
+            (Some(origin), Some(policy))
+                if req.path().starts_with(API_PATH_PREFIX)
+                    && !policy.allowed_origins.is_empty() =>
+            {
+                (origin, policy)
+            }
+            _ => return Box::pin(async move { Ok(svc.call(req).await?.map_into_left_body()) }),
+        };
+
+        let preflight = req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+        if preflight {
+            let mut res = HttpResponse::NoContent().finish();
+            policy.apply(&origin, true, res.headers_mut());
+            return Box::pin(ready(Ok(req.into_response(res).map_into_right_body())));
+        }
+
+        Box::pin(async move {
+            let mut res = svc.call(req).await?;
+            policy.apply(&origin, false, res.headers_mut());
+            Ok(res.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(origins: &[&str], allow_credentials: bool) -> CorsPolicy {
+        CorsPolicy {
+            allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            allow_credentials,
+            max_age_seconds: 600,
+        }
+    }
+
+    #[test]
+    fn test_allow_origin() {
+        let listed = policy(&["https://app.example.com"], false);
+        assert_eq!(
+            listed.allow_origin("https://app.example.com").as_deref(),
+            Some("https://app.example.com")
+        );
+        assert_eq!(listed.allow_origin("https://evil.example.com"), None);
+        assert_eq!(listed.allow_origin("http://app.example.com"), None);
+
+        let any = policy(&["*"], false);
+        assert_eq!(any.allow_origin("https://x.test").as_deref(), Some("*"));
+    }
+
+    #[test]
+    fn test_apply_preflight() {
+        let mut headers = HeaderMap::new();
+        policy(&["https://app.example.com"], true).apply(
+            "https://app.example.com",
+            true,
+            &mut headers,
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://app.example.com"
+        );
+        assert_eq!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .unwrap(),
+            "true"
+        );
+        assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "600");
+        assert_eq!(headers.get(header::VARY).unwrap(), "Origin");
+        assert!(headers.contains_key(header::ACCESS_CONTROL_ALLOW_METHODS));
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_EXPOSE_HEADERS));
+    }
+
+    #[test]
+    fn test_apply_refused_origin_adds_nothing() {
+        let mut headers = HeaderMap::new();
+        policy(&["https://app.example.com"], true).apply(
+            "https://other.example.com",
+            false,
+            &mut headers,
+        );
+        assert!(headers.is_empty());
+    }
+
+    #[test]
+    fn test_no_credentials_with_any_origin() {
+        let mut headers = HeaderMap::new();
+        policy(&["*"], true).apply("https://x.test", false, &mut headers);
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "*"
+        );
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+        assert!(!headers.contains_key(header::VARY));
+    }
+}
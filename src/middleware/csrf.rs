@@ -97,7 +97,7 @@ pub fn validate_csrf_token(
         .ok_or_else(|| error::ErrorForbidden("CSRF token not found in session"))?;
 
     if provided_token != expected_token {
-        log::warn!("CSRF token validation failed");
+        tracing::warn!("CSRF token validation failed");
         return Err(error::ErrorForbidden("Invalid CSRF token"));
     }
 
@@ -1,5 +1,7 @@
 mod client_ctx;
+pub mod cors;
 pub mod csrf;
+pub mod request_id;
 
 pub use client_ctx::ClientCtx;
 
@@ -0,0 +1,103 @@
+//! Request IDs for correlating complaints with logs
+//!
+//! The ID is the one `TracingLogger` assigns to the request's root span, so it
+//! appears on every log line written while handling it. This middleware makes
+//! it available to handlers through [`current`], returns it in the
+//! `X-Request-Id` header and leaves it in the request extensions for the error
+//! pages, which show it as a reference code.
+
+use actix::fut::ready;
+use actix_web::dev::{self, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error, HttpMessage, HttpRequest};
+use futures::future::{LocalBoxFuture, Ready};
+use std::rc::Rc;
+use tracing_actix_web::RequestId as TracingRequestId;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// ID of the request being handled, if called while handling one. Tasks
+/// spawned off the request don't inherit it.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// ID of a request, once `TracingLogger` has seen it.
+pub fn of_request(req: &HttpRequest) -> Option<String> {
+    req.extensions()
+        .get::<TracingRequestId>()
+        .map(ToString::to_string)
+}
+
+/// Middleware exposing the request ID. Must be wrapped inside `TracingLogger`.
+#[derive(Clone, Copy, Default)]
+pub struct RequestId;
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for RequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestIdMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct RequestIdMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let Some(id) = of_request(req.request()) else {
+            return Box::pin(svc.call(req));
+        };
+
+        Box::pin(REQUEST_ID.scope(id.clone(), async move {
+            let mut res = svc.call(req).await?;
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            Ok(res)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn test_current_in_scope() {
+        assert_eq!(current(), None);
+        let inside = REQUEST_ID
+            .scope("abc".to_string(), async { current() })
+            .await;
+        assert_eq!(inside.as_deref(), Some("abc"));
+    }
+}
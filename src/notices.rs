@@ -0,0 +1,268 @@
+//! Site and forum notices
+//!
+//! Admins post notices shown at the top of every page, or of one forum's
+//! page, between optional start and end times. Members can dismiss the
+//! dismissible ones. Notices that require acknowledgment stay until the
+//! member acknowledges them, and who has is recorded for admins.
+//!
+//! Notices that haven't ended are cached under [`CacheScope::Notices`] with
+//! their bodies rendered, and checked against their start and end times on
+//! every read, so a scheduled notice appears on time without the cache
+//! being cleared.
+
+use crate::cache::CacheScope;
+use crate::db::get_db_pool;
+use crate::orm::{notice_dismissals, notices, user_names};
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{entity::*, query::*, ConnectionTrait, DbBackend, DbErr, Statement};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Looks a notice can have, from least to most urgent
+pub const STYLES: [&str; 3] = ["info", "warning", "critical"];
+
+/// A notice that hasn't ended, ready to show
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActiveNotice {
+    pub id: i32,
+    pub title: String,
+    pub body_html: String,
+    pub style: String,
+    pub forum_id: Option<i32>,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+    pub is_dismissible: bool,
+    pub requires_acknowledgment: bool,
+}
+
+impl ActiveNotice {
+    fn from_model(notice: notices::Model) -> Self {
+        ActiveNotice {
+            id: notice.id,
+            title: notice.title,
+            body_html: crate::bbcode::parse(&notice.body),
+            style: notice.style,
+            forum_id: notice.forum_id,
+            starts_at: notice.starts_at,
+            ends_at: notice.ends_at,
+            is_dismissible: notice.is_dismissible,
+            requires_acknowledgment: notice.requires_acknowledgment,
+        }
+    }
+
+    /// Whether the notice is within its start and end times
+    pub fn is_showing(&self, now: NaiveDateTime) -> bool {
+        !self.starts_at.is_some_and(|at| at > now) && !self.ends_at.is_some_and(|at| at <= now)
+    }
+
+    /// Whether members get a button to hide it. Notices that need
+    /// acknowledging are hidden by acknowledging them instead.
+    pub fn can_dismiss(&self) -> bool {
+        self.is_dismissible && !self.requires_acknowledgment
+    }
+}
+
+/// Active notices that haven't ended, from the cache if it has them
+async fn unended() -> Result<Vec<ActiveNotice>, DbErr> {
+    if let Some(cached) = crate::cache::get(CacheScope::Notices, "all").await {
+        return Ok(cached);
+    }
+
+    let now = Utc::now().naive_utc();
+    let notices: Vec<ActiveNotice> = notices::Entity::find()
+        .filter(notices::Column::IsActive.eq(true))
+        .filter(
+            Condition::any()
+                .add(notices::Column::EndsAt.is_null())
+                .add(notices::Column::EndsAt.gt(now)),
+        )
+        .order_by_desc(notices::Column::CreatedAt)
+        .all(get_db_pool())
+        .await?
+        .into_iter()
+        .map(ActiveNotice::from_model)
+        .collect();
+
+    crate::cache::set(CacheScope::Notices, "all", &notices).await;
+    Ok(notices)
+}
+
+/// Notices to show a member, or a guest, now: the site-wide ones when
+/// `forum_id` is None, otherwise that forum's. Those the member has
+/// dismissed or acknowledged are left out.
+pub async fn current(
+    user_id: Option<i32>,
+    forum_id: Option<i32>,
+) -> Result<Vec<ActiveNotice>, DbErr> {
+    let now = Utc::now().naive_utc();
+    let mut notices: Vec<ActiveNotice> = unended()
+        .await?
+        .into_iter()
+        .filter(|n| n.forum_id == forum_id && n.is_showing(now))
+        .collect();
+
+    if let (Some(user_id), false) = (user_id, notices.is_empty()) {
+        let hidden: HashSet<i32> = notice_dismissals::Entity::find()
+            .filter(notice_dismissals::Column::UserId.eq(user_id))
+            .filter(notice_dismissals::Column::NoticeId.is_in(notices.iter().map(|n| n.id)))
+            .all(get_db_pool())
+            .await?
+            .into_iter()
+            .map(|d| d.notice_id)
+            .collect();
+        notices.retain(|n| !hidden.contains(&n.id));
+    }
+
+    Ok(notices)
+}
+
+/// Hide a dismissible notice from a member. Returns false if the notice
+/// can't be dismissed.
+pub async fn dismiss(notice_id: i32, user_id: i32) -> Result<bool, DbErr> {
+    let db = get_db_pool();
+    let Some(notice) = notices::Entity::find_by_id(notice_id).one(db).await? else {
+        return Ok(false);
+    };
+    if !notice.is_dismissible || notice.requires_acknowledgment {
+        return Ok(false);
+    }
+
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"INSERT INTO notice_dismissals (notice_id, user_id, acknowledged)
+            VALUES ($1, $2, FALSE)
+            ON CONFLICT (notice_id, user_id) DO NOTHING"#,
+        vec![notice_id.into(), user_id.into()],
+    ))
+    .await?;
+
+    Ok(true)
+}
+
+/// Record that a member has read a notice that requires acknowledgment,
+/// which also hides it from them. Returns false if the notice doesn't ask
+/// for it.
+pub async fn acknowledge(notice_id: i32, user_id: i32) -> Result<bool, DbErr> {
+    let db = get_db_pool();
+    let Some(notice) = notices::Entity::find_by_id(notice_id).one(db).await? else {
+        return Ok(false);
+    };
+    if !notice.requires_acknowledgment {
+        return Ok(false);
+    }
+
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"INSERT INTO notice_dismissals (notice_id, user_id, acknowledged)
+            VALUES ($1, $2, TRUE)
+            ON CONFLICT (notice_id, user_id)
+            DO UPDATE SET acknowledged = TRUE, created_at = NOW()"#,
+        vec![notice_id.into(), user_id.into()],
+    ))
+    .await?;
+
+    Ok(true)
+}
+
+/// Members who acknowledged a notice, newest first, with their names
+pub async fn acknowledgments(
+    notice_id: i32,
+) -> Result<Vec<(notice_dismissals::Model, String)>, DbErr> {
+    let db = get_db_pool();
+    let acknowledged = notice_dismissals::Entity::find()
+        .filter(notice_dismissals::Column::NoticeId.eq(notice_id))
+        .filter(notice_dismissals::Column::Acknowledged.eq(true))
+        .order_by_desc(notice_dismissals::Column::CreatedAt)
+        .all(db)
+        .await?;
+
+    let names: HashMap<i32, String> = user_names::Entity::find()
+        .filter(user_names::Column::UserId.is_in(acknowledged.iter().map(|a| a.user_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|name| (name.user_id, name.name))
+        .collect();
+
+    Ok(acknowledged
+        .into_iter()
+        .map(|ack| {
+            let name = names
+                .get(&ack.user_id)
+                .cloned()
+                .unwrap_or_else(|| format!("User #{}", ack.user_id));
+            (ack, name)
+        })
+        .collect())
+}
+
+/// Number of acknowledgments of each notice that has any
+pub async fn acknowledgment_counts() -> Result<HashMap<i32, i64>, DbErr> {
+    #[derive(sea_orm::FromQueryResult)]
+    struct Count {
+        notice_id: i32,
+        count: i64,
+    }
+
+    let counts = Count::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"SELECT notice_id, COUNT(*) AS count
+            FROM notice_dismissals
+            WHERE acknowledged
+            GROUP BY notice_id"#,
+        vec![],
+    ))
+    .all(get_db_pool())
+    .await?;
+
+    Ok(counts.into_iter().map(|c| (c.notice_id, c.count)).collect())
+}
+
+/// Drop cached notices after one is added, changed or removed
+pub async fn changed() {
+    crate::cache::invalidate(CacheScope::Notices).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn notice(starts_at: Option<NaiveDateTime>, ends_at: Option<NaiveDateTime>) -> ActiveNotice {
+        ActiveNotice {
+            id: 1,
+            title: "Maintenance".to_string(),
+            body_html: String::new(),
+            style: "info".to_string(),
+            forum_id: None,
+            starts_at,
+            ends_at,
+            is_dismissible: true,
+            requires_acknowledgment: false,
+        }
+    }
+
+    #[test]
+    fn test_is_showing() {
+        let now = Utc::now().naive_utc();
+        let hour = Duration::hours(1);
+
+        assert!(notice(None, None).is_showing(now));
+        assert!(notice(Some(now - hour), Some(now + hour)).is_showing(now));
+        assert!(notice(Some(now), None).is_showing(now));
+        assert!(!notice(Some(now + hour), None).is_showing(now));
+        assert!(!notice(None, Some(now)).is_showing(now));
+        assert!(!notice(Some(now - hour * 2), Some(now - hour)).is_showing(now));
+    }
+
+    #[test]
+    fn test_acknowledgment_replaces_dismissal() {
+        let mut n = notice(None, None);
+        assert!(n.can_dismiss());
+        n.requires_acknowledgment = true;
+        assert!(!n.can_dismiss());
+        n.requires_acknowledgment = false;
+        n.is_dismissible = false;
+        assert!(!n.can_dismiss());
+    }
+}
@@ -0,0 +1,338 @@
+//! Hourly, daily and weekly notification digests
+//!
+//! Notification types whose preference asks for a digest are left unemailed
+//! when created. A background worker periodically gathers them per user and
+//! sends a single summary, then marks them `is_emailed`. Digests go out at the
+//! top of the hour, at 08:00 local time, or at 08:00 local time on Mondays,
+//! using the UTC offset from the user's quiet hours settings.
+
+use super::quiet_hours::get_quiet_hours;
+use crate::db::get_db_pool;
+use crate::email::templates::{send_notification_digest_email, DigestEntry};
+use crate::orm::{notification_digests, notifications, user_names, users};
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Timelike};
+use sea_orm::{
+    entity::*, query::*, sea_query::Expr, DbBackend, DbErr, FromQueryResult, Set, Statement,
+};
+use std::time::Duration as StdDuration;
+
+/// How often the worker checks for due digests
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(5 * 60);
+
+/// Local hour at which daily and weekly digests are sent
+const DIGEST_HOUR: u32 = 8;
+
+/// Most notifications listed individually in one email
+const MAX_DIGEST_ENTRIES: usize = 50;
+
+/// How often a digest is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestFrequency {
+    Hourly,
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestFrequency::Hourly => "hourly",
+            DigestFrequency::Daily => "daily",
+            DigestFrequency::Weekly => "weekly",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "hourly" => Some(DigestFrequency::Hourly),
+            "daily" => Some(DigestFrequency::Daily),
+            "weekly" => Some(DigestFrequency::Weekly),
+            _ => None,
+        }
+    }
+
+    /// The most recent scheduled send time at or before `now_utc`, in UTC,
+    /// for a user whose local time is UTC plus `utc_offset_minutes`
+    pub fn latest_slot(&self, now_utc: NaiveDateTime, utc_offset_minutes: i16) -> NaiveDateTime {
+        let offset = Duration::minutes(utc_offset_minutes as i64);
+        let local = now_utc + offset;
+        let send_time = NaiveTime::from_hms_opt(DIGEST_HOUR, 0, 0).unwrap();
+
+        let slot = match self {
+            DigestFrequency::Hourly => local.date().and_hms_opt(local.hour(), 0, 0).unwrap(),
+            DigestFrequency::Daily => {
+                let today = local.date().and_time(send_time);
+                if local < today {
+                    today - Duration::days(1)
+                } else {
+                    today
+                }
+            }
+            DigestFrequency::Weekly => {
+                let days_since_monday = local.weekday().num_days_from_monday() as i64;
+                let monday = (local.date() - Duration::days(days_since_monday)).and_time(send_time);
+                if local < monday {
+                    monday - Duration::weeks(1)
+                } else {
+                    monday
+                }
+            }
+        };
+
+        slot - offset
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct DigestCandidate {
+    user_id: i32,
+    frequency: String,
+}
+
+/// Send every digest that is due. Returns how many emails were sent.
+pub async fn run_digests(now_utc: NaiveDateTime) -> Result<usize, DbErr> {
+    let db = get_db_pool();
+
+    // Users with pending notifications of a type they want as a digest
+    let candidates = DigestCandidate::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        SELECT DISTINCT n.user_id, p.frequency
+        FROM notifications n
+        JOIN notification_preferences p
+            ON p.user_id = n.user_id AND p.notification_type = n.type
+        JOIN users u ON u.id = n.user_id
+        WHERE p.email = TRUE
+          AND p.frequency IN ('hourly', 'daily', 'weekly')
+          AND n.is_read = FALSE
+          AND n.is_emailed = FALSE
+          AND n.snoozed_until IS NULL
+          AND u.email_verified = TRUE
+          AND u.email IS NOT NULL
+        "#,
+        vec![],
+    ))
+    .all(db)
+    .await?;
+
+    let mut sent = 0;
+    for candidate in candidates {
+        let frequency = match DigestFrequency::parse(&candidate.frequency) {
+            Some(frequency) => frequency,
+            None => continue,
+        };
+
+        match send_digest_if_due(candidate.user_id, frequency, now_utc).await {
+            Ok(true) => sent += 1,
+            Ok(false) => {}
+            Err(e) => log::error!(
+                "Failed to send {} digest to user {}: {}",
+                frequency.as_str(),
+                candidate.user_id,
+                e
+            ),
+        }
+    }
+
+    Ok(sent)
+}
+
+/// Send one user's digest if its slot has come round since the last one
+async fn send_digest_if_due(
+    user_id: i32,
+    frequency: DigestFrequency,
+    now_utc: NaiveDateTime,
+) -> Result<bool, DbErr> {
+    let db = get_db_pool();
+
+    let quiet_hours = get_quiet_hours(user_id).await?;
+    let slot = frequency.latest_slot(now_utc, quiet_hours.utc_offset_minutes);
+
+    let last_sent =
+        notification_digests::Entity::find_by_id((user_id, frequency.as_str().to_string()))
+            .one(db)
+            .await?;
+
+    if last_sent.as_ref().is_some_and(|d| d.last_sent_at >= slot) {
+        return Ok(false);
+    }
+
+    // Hold the digest until quiet hours end; the slot stays due
+    if quiet_hours.is_active_at(now_utc) {
+        return Ok(false);
+    }
+
+    let user = match users::Entity::find_by_id(user_id).one(db).await? {
+        Some(user) => user,
+        None => return Ok(false),
+    };
+    let email = match user.email.as_deref() {
+        Some(email) if user.email_verified => email,
+        _ => return Ok(false),
+    };
+
+    let pending = notifications::Entity::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+            SELECT n.*
+            FROM notifications n
+            JOIN notification_preferences p
+                ON p.user_id = n.user_id AND p.notification_type = n.type
+            WHERE n.user_id = $1
+              AND p.email = TRUE
+              AND p.frequency = $2
+              AND n.is_read = FALSE
+              AND n.is_emailed = FALSE
+              AND n.snoozed_until IS NULL
+            ORDER BY n.created_at ASC
+            "#,
+            vec![user_id.into(), frequency.as_str().into()],
+        ))
+        .all(db)
+        .await?;
+
+    if pending.is_empty() {
+        return Ok(false);
+    }
+
+    let username = user_names::Entity::find()
+        .filter(user_names::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .map(|un| un.name)
+        .unwrap_or_else(|| "User".to_string());
+
+    let offset = Duration::minutes(quiet_hours.utc_offset_minutes as i64);
+    let entries: Vec<DigestEntry> = pending
+        .iter()
+        .take(MAX_DIGEST_ENTRIES)
+        .map(|n| DigestEntry {
+            title: n.title.clone(),
+            message: n.message.clone(),
+            url: n.url.clone(),
+            time: (n.created_at + offset).format("%b %-d, %H:%M").to_string(),
+        })
+        .collect();
+    let remaining = pending.len() - entries.len();
+
+    send_notification_digest_email(
+        email,
+        user_id,
+        &username,
+        frequency.as_str(),
+        &entries,
+        remaining,
+        &super::dispatcher::get_base_url(),
+    )
+    .await
+    .map_err(|e| DbErr::Custom(format!("Failed to send digest email: {}", e)))?;
+
+    let ids: Vec<i32> = pending.iter().map(|n| n.id).collect();
+    notifications::Entity::update_many()
+        .col_expr(notifications::Column::IsEmailed, Expr::value(true))
+        .filter(notifications::Column::Id.is_in(ids))
+        .exec(db)
+        .await?;
+
+    let record = notification_digests::ActiveModel {
+        user_id: Set(user_id),
+        frequency: Set(frequency.as_str().to_string()),
+        last_sent_at: Set(now_utc),
+    };
+    if last_sent.is_some() {
+        record.update(db).await?;
+    } else {
+        record.insert(db).await?;
+    }
+
+    Ok(true)
+}
+
+/// Start the background digest scheduler
+pub fn spawn_worker() {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match run_digests(chrono::Utc::now().naive_utc()).await {
+                Ok(0) => {}
+                Ok(sent) => log::info!("Sent {} notification digest(s)", sent),
+                Err(e) => log::error!("Failed to run notification digests: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // January 2026: the 12th and 19th are Mondays
+        NaiveDate::from_ymd_opt(2026, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hourly_slot() {
+        let slot = DigestFrequency::Hourly.latest_slot(at(15, 10, 42), 0);
+        assert_eq!(slot, at(15, 10, 0));
+
+        // Half-hour offsets move the top of the hour
+        let slot = DigestFrequency::Hourly.latest_slot(at(15, 10, 42), 330);
+        assert_eq!(slot, at(15, 10, 30));
+    }
+
+    #[test]
+    fn test_daily_slot() {
+        assert_eq!(
+            DigestFrequency::Daily.latest_slot(at(15, 9, 0), 0),
+            at(15, 8, 0)
+        );
+        assert_eq!(
+            DigestFrequency::Daily.latest_slot(at(15, 7, 59), 0),
+            at(14, 8, 0)
+        );
+
+        // 08:00 at UTC-5 is 13:00 UTC
+        assert_eq!(
+            DigestFrequency::Daily.latest_slot(at(15, 12, 0), -300),
+            at(14, 13, 0)
+        );
+        assert_eq!(
+            DigestFrequency::Daily.latest_slot(at(15, 13, 0), -300),
+            at(15, 13, 0)
+        );
+    }
+
+    #[test]
+    fn test_weekly_slot() {
+        assert_eq!(
+            DigestFrequency::Weekly.latest_slot(at(15, 12, 0), 0),
+            at(12, 8, 0)
+        );
+        assert_eq!(
+            DigestFrequency::Weekly.latest_slot(at(12, 7, 0), 0),
+            at(5, 8, 0)
+        );
+
+        // Monday 08:00 at UTC+9 is Sunday 23:00 UTC
+        assert_eq!(
+            DigestFrequency::Weekly.latest_slot(at(18, 23, 30), 540),
+            at(18, 23, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_frequency() {
+        assert_eq!(
+            DigestFrequency::parse("weekly"),
+            Some(DigestFrequency::Weekly)
+        );
+        assert_eq!(DigestFrequency::parse("immediate"), None);
+    }
+}
@@ -1,8 +1,15 @@
 //! Notification dispatcher for detecting events and sending notifications
 
 use crate::db::get_db_pool;
-use crate::notifications::{create_notification, get_user_preferences, NotificationType};
-use crate::orm::{threads, ugc, ugc_revisions, user_names, users, watched_threads};
+use crate::notifications::grouping::{self, GroupEvent, GroupedNotification};
+use crate::notifications::mobile;
+use crate::notifications::push::{self, PushPayload};
+use crate::notifications::{
+    create_notification, get_user_preferences, is_do_not_disturb, NotificationType,
+};
+use crate::orm::{
+    forums, threads, ugc, ugc_revisions, user_names, users, watched_forums, watched_threads,
+};
 use crate::user::Profile;
 use crate::web::notifications_ws::{
     get_notification_server, BroadcastNotification, NotificationData,
@@ -18,12 +25,12 @@ static QUOTE_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(?i)\[quote=([a-zA-Z0-9_-]+)\]").unwrap());
 
 /// Get base URL for email links
-fn get_base_url() -> String {
+pub(crate) fn get_base_url() -> String {
     std::env::var("SITE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string())
 }
 
-/// Broadcast a notification via WebSocket if the server is available
-fn broadcast_realtime_notification(
+/// Broadcast a new notification in real time
+pub(super) async fn broadcast_realtime_notification(
     user_id: i32,
     notification_id: i32,
     notification_type: &str,
@@ -31,16 +38,87 @@ fn broadcast_realtime_notification(
     message: &str,
     url: Option<&str>,
 ) {
-    if let Some(server) = get_notification_server() {
-        let notification = NotificationData {
+    send_realtime(
+        user_id,
+        NotificationData {
             id: notification_id,
             notification_type: notification_type.to_string(),
             title: title.to_string(),
             message: message.to_string(),
             url: url.map(|s| s.to_string()),
             created_at: chrono::Utc::now().to_rfc3339(),
+            actor_count: 1,
+            updated: false,
+        },
+    )
+    .await;
+}
+
+/// Push a notification again when its snooze runs out
+pub(crate) async fn broadcast_woken_notification(notification: &crate::orm::notifications::Model) {
+    send_realtime(
+        notification.user_id,
+        NotificationData {
+            id: notification.id,
+            notification_type: notification.type_.clone(),
+            title: notification.title.clone(),
+            message: notification.message.clone(),
+            url: notification.url.clone(),
+            created_at: notification.created_at.and_utc().to_rfc3339(),
+            actor_count: notification.actor_count,
+            updated: false,
+        },
+    )
+    .await;
+}
+
+/// Broadcast a grouped notification in real time; clients replace the one
+/// they already have when it was updated rather than created
+async fn broadcast_grouped_notification(
+    user_id: i32,
+    grouped: &GroupedNotification,
+    notification_type: &str,
+    message: &str,
+    url: Option<&str>,
+) {
+    send_realtime(
+        user_id,
+        NotificationData {
+            id: grouped.id,
+            notification_type: notification_type.to_string(),
+            title: grouped.title.clone(),
+            message: message.to_string(),
+            url: url.map(|s| s.to_string()),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            actor_count: grouped.actor_count,
+            updated: !grouped.is_new,
+        },
+    )
+    .await;
+}
+
+/// Send a notification over the WebSocket if the server is available, and to
+/// the user's browsers and mobile devices if they want pushes for this type.
+/// Nothing is pushed during the user's quiet hours.
+async fn send_realtime(user_id: i32, notification: NotificationData) {
+    match is_do_not_disturb(user_id).await {
+        Ok(false) => {}
+        Ok(true) => return,
+        Err(e) => log::warn!("Failed to check quiet hours for user {}: {}", user_id, e),
+    }
+
+    if wants_push(user_id, &notification.notification_type).await {
+        let payload = PushPayload {
+            title: notification.title.clone(),
+            body: notification.message.clone(),
+            url: notification.url.clone(),
+            tag: format!("notification-{}", notification.id),
         };
+        mobile::dispatch(user_id, payload.clone());
+        push::dispatch(user_id, payload);
+    }
 
+    if let Some(server) = get_notification_server() {
         server.do_send(BroadcastNotification {
             user_id,
             notification,
@@ -48,6 +126,26 @@ fn broadcast_realtime_notification(
     }
 }
 
+/// Whether the user has push switched on for this notification type
+async fn wants_push(user_id: i32, notification_type: &str) -> bool {
+    let notification_type = match NotificationType::parse(notification_type) {
+        Some(notification_type) => notification_type,
+        None => return true,
+    };
+
+    match get_user_preferences(user_id, &notification_type).await {
+        Ok(prefs) => prefs.push,
+        Err(e) => {
+            log::warn!(
+                "Failed to check push preference for user {}: {}",
+                user_id,
+                e
+            );
+            true
+        }
+    }
+}
+
 /// Detect mentions in content and create notifications
 pub async fn detect_and_notify_mentions(
     content: &str,
@@ -122,12 +220,16 @@ pub async fn detect_and_notify_mentions(
                     &title,
                     &message,
                     Some(&url),
-                );
+                )
+                .await;
             }
 
             // Check if user wants email notifications for mentions
             let prefs = get_user_preferences(mentioned_user_id, &NotificationType::Mention).await?;
-            if prefs.email && prefs.frequency == "immediate" {
+            if prefs.email
+                && prefs.frequency == "immediate"
+                && !is_do_not_disturb(mentioned_user_id).await?
+            {
                 // Get user's email and check if verified
                 if let Some(user) = users::Entity::find_by_id(mentioned_user_id).one(db).await? {
                     if user.email_verified {
@@ -143,6 +245,7 @@ pub async fn detect_and_notify_mentions(
                             // Send mention email
                             if let Err(e) = crate::email::templates::send_mention_email(
                                 email,
+                                mentioned_user_id,
                                 &recipient_name,
                                 &author_name,
                                 &thread_title,
@@ -243,12 +346,16 @@ pub async fn detect_and_notify_quotes(
                     &title,
                     &message,
                     Some(&url),
-                );
+                )
+                .await;
             }
 
             // Check if user wants email notifications for quotes
             let prefs = get_user_preferences(quoted_user_id, &NotificationType::Quote).await?;
-            if prefs.email && prefs.frequency == "immediate" {
+            if prefs.email
+                && prefs.frequency == "immediate"
+                && !is_do_not_disturb(quoted_user_id).await?
+            {
                 // Get user's email and check if verified
                 if let Some(user) = users::Entity::find_by_id(quoted_user_id).one(db).await? {
                     if user.email_verified {
@@ -264,6 +371,7 @@ pub async fn detect_and_notify_quotes(
                             // Send quote email
                             if let Err(e) = crate::email::templates::send_quote_email(
                                 email,
+                                quoted_user_id,
                                 &recipient_name,
                                 &author_name,
                                 &thread_title,
@@ -316,38 +424,42 @@ pub async fn notify_thread_reply(
     // Notify thread author if they're not the one posting
     if let Some(thread_author_id) = thread.user_id {
         if thread_author_id != author_id {
-            // Create in-app notification
-            let title = format!("{} replied to your thread", author_name);
-            let message = format!("New reply in: {}", thread.title);
+            // Create in-app notification, grouped per thread
+            let message = format!("New replies in: {}", thread.title);
             let url = format!("/threads/{}#post-{}", thread_id, post_id);
 
-            let notification_id = create_notification(
-                thread_author_id,
-                NotificationType::Reply,
-                title.clone(),
-                message.clone(),
-                Some(url.clone()),
-                Some(author_id),
-                Some("post".to_string()),
-                Some(post_id),
-            )
+            let grouped = grouping::notify_grouped(GroupEvent {
+                user_id: thread_author_id,
+                notification_type: NotificationType::Reply,
+                group_key: grouping::group_key(&NotificationType::Reply, "thread", thread_id),
+                actor_id: author_id,
+                actor_name: author_name.clone(),
+                action: "replied to your thread".to_string(),
+                message: message.clone(),
+                url: Some(url.clone()),
+                source_content_type: Some("post".to_string()),
+                source_content_id: Some(post_id),
+            })
             .await?;
 
             // Broadcast real-time notification
-            if notification_id > 0 {
-                broadcast_realtime_notification(
+            if let Some(grouped) = grouped {
+                broadcast_grouped_notification(
                     thread_author_id,
-                    notification_id,
+                    &grouped,
                     "reply",
-                    &title,
                     &message,
                     Some(&url),
-                );
+                )
+                .await;
             }
 
             // Send email to thread author if they want it
             let prefs = get_user_preferences(thread_author_id, &NotificationType::Reply).await?;
-            if prefs.email && prefs.frequency == "immediate" {
+            if prefs.email
+                && prefs.frequency == "immediate"
+                && !is_do_not_disturb(thread_author_id).await?
+            {
                 if let Some(user) = users::Entity::find_by_id(thread_author_id).one(db).await? {
                     if user.email_verified {
                         if let Some(email) = &user.email {
@@ -362,6 +474,7 @@ pub async fn notify_thread_reply(
                             // Send author reply email
                             if let Err(e) = crate::email::templates::send_author_reply_email(
                                 email,
+                                thread_author_id,
                                 &recipient_name,
                                 &author_name,
                                 &thread.title,
@@ -401,32 +514,33 @@ pub async fn notify_thread_reply(
             continue;
         }
 
-        let title = format!("{} replied to a watched thread", author_name);
-        let message = format!("New reply in: {}", thread.title);
+        let message = format!("New replies in: {}", thread.title);
         let url = format!("/threads/{}#post-{}", thread_id, post_id);
 
-        let notification_id = create_notification(
-            watcher.user_id,
-            NotificationType::ThreadWatch,
-            title.clone(),
-            message.clone(),
-            Some(url.clone()),
-            Some(author_id),
-            Some("post".to_string()),
-            Some(post_id),
-        )
+        let grouped = grouping::notify_grouped(GroupEvent {
+            user_id: watcher.user_id,
+            notification_type: NotificationType::ThreadWatch,
+            group_key: grouping::group_key(&NotificationType::ThreadWatch, "thread", thread_id),
+            actor_id: author_id,
+            actor_name: author_name.clone(),
+            action: "replied to a watched thread".to_string(),
+            message: message.clone(),
+            url: Some(url.clone()),
+            source_content_type: Some("post".to_string()),
+            source_content_id: Some(post_id),
+        })
         .await?;
 
         // Broadcast real-time notification
-        if notification_id > 0 {
-            broadcast_realtime_notification(
+        if let Some(grouped) = grouped {
+            broadcast_grouped_notification(
                 watcher.user_id,
-                notification_id,
+                &grouped,
                 "thread_watch",
-                &title,
                 &message,
                 Some(&url),
-            );
+            )
+            .await;
         }
     }
 
@@ -436,6 +550,132 @@ pub async fn notify_thread_reply(
     Ok(())
 }
 
+/// Notify users watching a forum that a new thread was posted in it
+pub async fn notify_forum_watchers(
+    thread_id: i32,
+    post_id: i32,
+    author_id: i32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = get_db_pool();
+
+    let thread = threads::Entity::find_by_id(thread_id)
+        .one(db)
+        .await?
+        .ok_or("Thread not found")?;
+
+    let watchers = watched_forums::Entity::find()
+        .filter(watched_forums::Column::ForumId.eq(thread.forum_id))
+        .all(db)
+        .await?;
+
+    if watchers.is_empty() {
+        return Ok(());
+    }
+
+    let forum_name = forums::Entity::find_by_id(thread.forum_id)
+        .one(db)
+        .await?
+        .map(|f| f.label)
+        .unwrap_or_else(|| "a forum".to_string());
+
+    let author = Profile::get_by_id(db, author_id).await?;
+    let author_name = author
+        .map(|a| a.name)
+        .unwrap_or_else(|| "Someone".to_string());
+
+    let title = format!("{} started a thread in {}", author_name, forum_name);
+    let message = format!("New thread: {}", thread.title);
+    let url = format!("/threads/{}", thread_id);
+    let mut post_content = None;
+
+    for watcher in watchers {
+        // Don't notify the author about their own thread
+        if watcher.user_id == author_id {
+            continue;
+        }
+
+        if watcher.notify_on_thread {
+            let notification_id = create_notification(
+                watcher.user_id,
+                NotificationType::ForumWatch,
+                title.clone(),
+                message.clone(),
+                Some(url.clone()),
+                Some(author_id),
+                Some("thread".to_string()),
+                Some(thread_id),
+            )
+            .await?;
+
+            if notification_id > 0 {
+                broadcast_realtime_notification(
+                    watcher.user_id,
+                    notification_id,
+                    "forum_watch",
+                    &title,
+                    &message,
+                    Some(&url),
+                )
+                .await;
+            }
+        }
+
+        // Per-watch email opt-in, like thread watching, unless the user has
+        // turned off or unsubscribed from watched forum emails
+        if !watcher.email_on_thread
+            || !get_user_preferences(watcher.user_id, &NotificationType::ForumWatch)
+                .await?
+                .email
+            || is_do_not_disturb(watcher.user_id).await?
+        {
+            continue;
+        }
+
+        let user = match users::Entity::find_by_id(watcher.user_id).one(db).await? {
+            Some(user) if user.email_verified => user,
+            _ => continue,
+        };
+        let email = match &user.email {
+            Some(email) => email,
+            None => continue,
+        };
+
+        let username = user_names::Entity::find()
+            .filter(user_names::Column::UserId.eq(watcher.user_id))
+            .one(db)
+            .await?
+            .map(|un| un.name)
+            .unwrap_or_else(|| "User".to_string());
+
+        if post_content.is_none() {
+            post_content = Some(get_post_content(post_id).await.unwrap_or_default());
+        }
+
+        if let Err(e) = crate::email::templates::send_new_thread_email(
+            email,
+            watcher.user_id,
+            &username,
+            &forum_name,
+            thread.forum_id,
+            &thread.title,
+            thread_id,
+            &author_name,
+            post_content.as_deref().unwrap_or_default(),
+            &get_base_url(),
+        )
+        .await
+        {
+            log::error!(
+                "Failed to send new thread email to user {}: {}",
+                watcher.user_id,
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
 /// Get post content from UGC table
 async fn get_post_content(post_id: i32) -> Result<String, Box<dyn std::error::Error>> {
     use crate::orm::posts;
@@ -504,8 +744,17 @@ async fn send_thread_reply_emails(
         std::env::var("SITE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
 
     for watcher in email_watchers {
-        // Skip the author - don't email yourself
-        if watcher.user_id == author_id {
+        // Skip the author - don't email yourself, and anyone in quiet hours
+        if watcher.user_id == author_id || is_do_not_disturb(watcher.user_id).await? {
+            continue;
+        }
+
+        // Watched thread emails can be turned off, or unsubscribed from, for
+        // every thread at once
+        if !get_user_preferences(watcher.user_id, &NotificationType::ThreadWatch)
+            .await?
+            .email
+        {
             continue;
         }
 
@@ -529,6 +778,7 @@ async fn send_thread_reply_emails(
                 // Send email (don't block on errors)
                 if let Err(e) = crate::email::templates::send_thread_reply_email(
                     email,
+                    watcher.user_id,
                     &username,
                     thread_title,
                     thread_id,
@@ -551,6 +801,243 @@ async fn send_thread_reply_emails(
     Ok(())
 }
 
+/// Notify a post's author that someone reacted to it. Reactions to the same
+/// post collapse into one notification.
+pub async fn notify_reaction(
+    ugc_id: i32,
+    reactor_id: i32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::orm::posts;
+
+    let db = get_db_pool();
+
+    let post = match posts::Entity::find()
+        .filter(posts::Column::UgcId.eq(ugc_id))
+        .one(db)
+        .await?
+    {
+        Some(post) => post,
+        None => return Ok(()),
+    };
+    let author_id = match post.user_id {
+        Some(author_id) if author_id != reactor_id => author_id,
+        _ => return Ok(()),
+    };
+
+    let thread = threads::Entity::find_by_id(post.thread_id)
+        .one(db)
+        .await?
+        .ok_or("Thread not found")?;
+    let reactor_name = Profile::get_by_id(db, reactor_id)
+        .await?
+        .map(|p| p.name)
+        .unwrap_or_else(|| "Someone".to_string());
+
+    let message = format!("Your post in: {}", thread.title);
+    let url = format!("/threads/{}#post-{}", post.thread_id, post.id);
+
+    let grouped = grouping::notify_grouped(GroupEvent {
+        user_id: author_id,
+        notification_type: NotificationType::Reaction,
+        group_key: grouping::group_key(&NotificationType::Reaction, "ugc", ugc_id),
+        actor_id: reactor_id,
+        actor_name: reactor_name,
+        action: "reacted to your post".to_string(),
+        message: message.clone(),
+        url: Some(url.clone()),
+        source_content_type: Some("post".to_string()),
+        source_content_id: Some(post.id),
+    })
+    .await?;
+
+    if let Some(grouped) = grouped {
+        broadcast_grouped_notification(author_id, &grouped, "reaction", &message, Some(&url)).await;
+    }
+
+    Ok(())
+}
+
+/// Tell a member of a private chat room about a message they missed. Messages
+/// in one room collapse into a single notification until it is read.
+#[allow(clippy::too_many_arguments)]
+pub async fn notify_chat_message(
+    recipient_id: i32,
+    room_id: i32,
+    room_title: &str,
+    is_direct: bool,
+    sender_id: i32,
+    sender_name: String,
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let action = if is_direct {
+        "messaged you in chat".to_string()
+    } else {
+        format!("posted in {}", room_title)
+    };
+    let url = format!("/chat#{}", room_id);
+
+    let grouped = grouping::notify_grouped(GroupEvent {
+        user_id: recipient_id,
+        notification_type: NotificationType::ChatMessage,
+        group_key: grouping::group_key(&NotificationType::ChatMessage, "chat_room", room_id),
+        actor_id: sender_id,
+        actor_name: sender_name,
+        action,
+        message: message.to_string(),
+        url: Some(url.clone()),
+        source_content_type: Some("chat_room".to_string()),
+        source_content_id: Some(room_id),
+    })
+    .await?;
+
+    if let Some(grouped) = grouped {
+        broadcast_grouped_notification(recipient_id, &grouped, "chat", message, Some(&url)).await;
+    }
+
+    Ok(())
+}
+
+/// Tell a user they were added to a private chat room
+pub async fn notify_chat_invite(
+    recipient_id: i32,
+    room_id: i32,
+    room_title: &str,
+    inviter_id: i32,
+    inviter_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let title = format!("{} invited you to a chat room", inviter_name);
+    let message = format!("You can now join: {}", room_title);
+    let url = format!("/chat#{}", room_id);
+
+    let notification_id = create_notification(
+        recipient_id,
+        NotificationType::ChatMessage,
+        title.clone(),
+        message.clone(),
+        Some(url.clone()),
+        Some(inviter_id),
+        Some("chat_room".to_string()),
+        Some(room_id),
+    )
+    .await?;
+
+    if notification_id > 0 {
+        broadcast_realtime_notification(
+            recipient_id,
+            notification_id,
+            "chat",
+            &title,
+            &message,
+            Some(&url),
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+/// Tell a member someone wrote on their profile wall
+pub async fn notify_profile_post(
+    profile_user_id: i32,
+    profile_post_id: i32,
+    author_id: i32,
+    author_name: &str,
+    preview: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if profile_user_id == author_id {
+        return Ok(());
+    }
+
+    let title = format!("{} posted on your profile", author_name);
+    let url = format!(
+        "/members/{}/#profile-post-{}",
+        profile_user_id, profile_post_id
+    );
+
+    let notification_id = create_notification(
+        profile_user_id,
+        NotificationType::ProfilePost,
+        title.clone(),
+        preview.to_string(),
+        Some(url.clone()),
+        Some(author_id),
+        Some("profile_post".to_string()),
+        Some(profile_post_id),
+    )
+    .await?;
+
+    if notification_id > 0 {
+        broadcast_realtime_notification(
+            profile_user_id,
+            notification_id,
+            "profile_post",
+            &title,
+            preview,
+            Some(&url),
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+/// Tell the wall's owner and the post's author about a comment on a profile
+/// post. Comments on one post collapse into a single notification until read.
+pub async fn notify_profile_post_comment(
+    profile_user_id: i32,
+    profile_post_id: i32,
+    post_author_id: Option<i32>,
+    commenter_id: i32,
+    commenter_name: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let url = format!(
+        "/members/{}/#profile-post-{}",
+        profile_user_id, profile_post_id
+    );
+    let message = "On a profile post".to_string();
+
+    let mut recipients = vec![profile_user_id];
+    recipients.extend(post_author_id.filter(|id| *id != profile_user_id));
+
+    for recipient_id in recipients.into_iter().filter(|id| *id != commenter_id) {
+        let action = if recipient_id == profile_user_id {
+            "commented on a post on your profile"
+        } else {
+            "commented on your profile post"
+        };
+        let grouped = grouping::notify_grouped(GroupEvent {
+            user_id: recipient_id,
+            notification_type: NotificationType::ProfilePost,
+            group_key: grouping::group_key(
+                &NotificationType::ProfilePost,
+                "profile_post",
+                profile_post_id,
+            ),
+            actor_id: commenter_id,
+            actor_name: commenter_name.clone(),
+            action: action.to_string(),
+            message: message.clone(),
+            url: Some(url.clone()),
+            source_content_type: Some("profile_post".to_string()),
+            source_content_id: Some(profile_post_id),
+        })
+        .await?;
+
+        if let Some(grouped) = grouped {
+            broadcast_grouped_notification(
+                recipient_id,
+                &grouped,
+                "profile_post",
+                &message,
+                Some(&url),
+            )
+            .await;
+        }
+    }
+
+    Ok(())
+}
+
 /// Create a notification for a moderation action
 pub async fn notify_moderation_action(
     target_user_id: i32,
@@ -593,7 +1080,94 @@ pub async fn notify_moderation_action(
             &title,
             &message,
             None,
-        );
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+/// Users who review reports, and so hear about new reports and quarantined
+/// uploads.
+pub(super) async fn get_moderator_ids() -> Result<Vec<i32>, sea_orm::DbErr> {
+    use sea_orm::{DbBackend, FromQueryResult, Statement};
+
+    #[derive(FromQueryResult)]
+    struct Moderator {
+        user_id: i32,
+    }
+
+    let moderators = Moderator::find_by_statement(Statement::from_string(
+        DbBackend::Postgres,
+        r#"
+        SELECT DISTINCT ug.user_id
+        FROM user_groups ug
+        JOIN permission_collections pc ON pc.group_id = ug.group_id
+        JOIN permission_values pv ON pv.collection_id = pc.id
+        JOIN permissions p ON p.id = pv.permission_id
+        WHERE p.label = 'moderate.reports.view' AND pv.value = 'yes'
+        ORDER BY ug.user_id
+        "#
+        .to_owned(),
+    ))
+    .all(get_db_pool())
+    .await?;
+
+    Ok(moderators.into_iter().map(|m| m.user_id).collect())
+}
+
+/// Notify moderators that an upload failed its virus scan and was quarantined
+pub async fn notify_attachment_quarantined(
+    attachment_id: i32,
+    filename: &str,
+    signature: &str,
+    uploader_id: Option<i32>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = get_db_pool();
+
+    let uploader_name = match uploader_id {
+        Some(uploader_id) => Profile::get_by_id(db, uploader_id)
+            .await?
+            .map(|uploader| uploader.name),
+        None => None,
+    };
+
+    let title = "Upload quarantined".to_string();
+    let message = match uploader_name {
+        Some(name) => format!(
+            "{} uploaded {}, which was quarantined by the virus scanner: {}",
+            name, filename, signature
+        ),
+        None => format!(
+            "{} was quarantined by the virus scanner: {}",
+            filename, signature
+        ),
+    };
+
+    for moderator_id in get_moderator_ids().await? {
+        let notification_id = create_notification(
+            moderator_id,
+            NotificationType::ModAction,
+            title.clone(),
+            message.clone(),
+            None,
+            uploader_id,
+            Some("attachment".to_string()),
+            Some(attachment_id),
+        )
+        .await?;
+
+        if notification_id > 0 {
+            broadcast_realtime_notification(
+                moderator_id,
+                notification_id,
+                "mod_action",
+                &title,
+                &message,
+                None,
+            )
+            .await;
+        }
     }
 
     Ok(())
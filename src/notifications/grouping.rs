@@ -0,0 +1,218 @@
+//! Grouped notifications
+//!
+//! Events on the same target ("reacted to your post", "replied to your
+//! thread") update a single unread notification instead of adding a row each
+//! time: the title is rewritten to "alice and 4 others reacted to your post"
+//! and the notification moves back to the top. Once it is read, the next
+//! event starts a new one.
+
+use super::{wanted_preferences, NotificationType};
+use crate::db::get_db_pool;
+use crate::orm::{notification_actors, notifications};
+use sea_orm::{entity::*, query::*, DbErr, Set};
+
+/// One event to fold into a group
+pub struct GroupEvent {
+    pub user_id: i32,
+    pub notification_type: NotificationType,
+    /// Identifies the target, e.g. `reaction:ugc:42`
+    pub group_key: String,
+    pub actor_id: i32,
+    pub actor_name: String,
+    /// Past-tense action following the actor names, e.g. "reacted to your post"
+    pub action: String,
+    pub message: String,
+    pub url: Option<String>,
+    pub source_content_type: Option<String>,
+    pub source_content_id: Option<i32>,
+}
+
+/// The notification an event ended up in
+#[derive(Debug, Clone)]
+pub struct GroupedNotification {
+    pub id: i32,
+    pub title: String,
+    pub actor_count: i32,
+    /// False when an existing unread notification the user can see was updated
+    pub is_new: bool,
+}
+
+/// Group key for events on one target
+pub fn group_key(
+    notification_type: &NotificationType,
+    content_type: &str,
+    content_id: i32,
+) -> String {
+    format!(
+        "{}:{}:{}",
+        notification_type.as_str(),
+        content_type,
+        content_id
+    )
+}
+
+/// "alice reacted", "alice and 1 other reacted", "alice and 4 others reacted"
+pub fn group_title(actor_name: &str, actor_count: i32, action: &str) -> String {
+    match actor_count {
+        n if n <= 1 => format!("{} {}", actor_name, action),
+        2 => format!("{} and 1 other {}", actor_name, action),
+        n => format!("{} and {} others {}", actor_name, n - 1, action),
+    }
+}
+
+/// Record an event, adding to the user's open notification for its group or
+/// starting one. Returns None if the user doesn't want the notification.
+pub async fn notify_grouped(event: GroupEvent) -> Result<Option<GroupedNotification>, DbErr> {
+    if wanted_preferences(
+        event.user_id,
+        &event.notification_type,
+        event.source_content_type.as_deref(),
+        event.source_content_id,
+    )
+    .await?
+    .is_none()
+    {
+        return Ok(None);
+    }
+
+    if let Some(grouped) = add_to_open_group(&event).await? {
+        return Ok(Some(grouped));
+    }
+
+    match start_group(&event).await {
+        Ok(grouped) => Ok(Some(grouped)),
+        // Another event opened the group first; join it instead
+        Err(DbErr::Query(e)) | Err(DbErr::Exec(e))
+            if e.contains("idx_notifications_open_group") =>
+        {
+            add_to_open_group(&event).await
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Fold the event into the open notification for its group, if there is one
+async fn add_to_open_group(event: &GroupEvent) -> Result<Option<GroupedNotification>, DbErr> {
+    let db = get_db_pool();
+
+    let open = notifications::Entity::find()
+        .filter(notifications::Column::UserId.eq(event.user_id))
+        .filter(notifications::Column::GroupKey.eq(event.group_key.as_str()))
+        .filter(notifications::Column::IsRead.eq(false))
+        .one(db)
+        .await?;
+
+    let open = match open {
+        Some(open) => open,
+        None => return Ok(None),
+    };
+
+    let known_actor = notification_actors::Entity::find_by_id((open.id, event.actor_id))
+        .one(db)
+        .await?
+        .is_some();
+    if !known_actor {
+        notification_actors::ActiveModel {
+            notification_id: Set(open.id),
+            user_id: Set(event.actor_id),
+        }
+        .insert(db)
+        .await?;
+    }
+
+    let actor_count = notification_actors::Entity::find()
+        .filter(notification_actors::Column::NotificationId.eq(open.id))
+        .count(db)
+        .await? as i32;
+    let title = group_title(&event.actor_name, actor_count, &event.action);
+
+    let id = open.id;
+    let was_snoozed = open.snoozed_until.is_some();
+    let mut active: notifications::ActiveModel = open.into();
+    active.title = Set(title.clone());
+    active.message = Set(event.message.clone());
+    active.source_user_id = Set(Some(event.actor_id));
+    active.actor_count = Set(actor_count);
+    active.created_at = Set(chrono::Utc::now().naive_utc());
+    // New activity goes into the next digest, and wakes a snoozed group
+    active.is_emailed = Set(false);
+    active.snoozed_until = Set(None);
+    active.update(db).await?;
+
+    Ok(Some(GroupedNotification {
+        id,
+        title,
+        actor_count,
+        // A snoozed group wasn't counted by the client, so it shows as new
+        is_new: was_snoozed,
+    }))
+}
+
+/// Create the first notification of a group
+async fn start_group(event: &GroupEvent) -> Result<GroupedNotification, DbErr> {
+    let txn = get_db_pool().begin().await?;
+
+    let title = group_title(&event.actor_name, 1, &event.action);
+    let notification = notifications::ActiveModel {
+        user_id: Set(event.user_id),
+        type_: Set(event.notification_type.as_str().to_string()),
+        title: Set(title.clone()),
+        message: Set(event.message.clone()),
+        url: Set(event.url.clone()),
+        source_user_id: Set(Some(event.actor_id)),
+        source_content_type: Set(event.source_content_type.clone()),
+        source_content_id: Set(event.source_content_id),
+        is_read: Set(false),
+        is_emailed: Set(false),
+        group_key: Set(Some(event.group_key.clone())),
+        actor_count: Set(1),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    notification_actors::ActiveModel {
+        notification_id: Set(notification.id),
+        user_id: Set(event.actor_id),
+    }
+    .insert(&txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(GroupedNotification {
+        id: notification.id,
+        title,
+        actor_count: 1,
+        is_new: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_title() {
+        assert_eq!(
+            group_title("alice", 1, "reacted to your post"),
+            "alice reacted to your post"
+        );
+        assert_eq!(
+            group_title("alice", 2, "reacted to your post"),
+            "alice and 1 other reacted to your post"
+        );
+        assert_eq!(
+            group_title("alice", 5, "reacted to your post"),
+            "alice and 4 others reacted to your post"
+        );
+    }
+
+    #[test]
+    fn test_group_key() {
+        assert_eq!(
+            group_key(&NotificationType::Reaction, "ugc", 42),
+            "reaction:ugc:42"
+        );
+    }
+}
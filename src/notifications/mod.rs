@@ -1,21 +1,103 @@
 //! Notification system for user engagement
 
+pub mod digest;
 pub mod dispatcher;
+pub mod grouping;
+pub mod mobile;
+pub mod push;
+pub mod quiet_hours;
+pub mod reports;
+pub mod snooze;
 pub mod types;
 
 use crate::db::get_db_pool;
-use crate::orm::{notification_preferences, notifications, watched_threads};
+use crate::orm::{
+    conversation_participants, notification_preferences, notifications, watched_forums,
+    watched_threads,
+};
 use sea_orm::{entity::*, query::*, sea_query::Expr, DbErr, Set};
 
+pub use quiet_hours::is_do_not_disturb;
 pub use types::NotificationType;
 
+/// Accepted values for a preference's email frequency, with their labels
+pub const FREQUENCIES: &[(&str, &str)] = &[
+    ("immediate", "Immediately"),
+    ("hourly", "Hourly digest"),
+    ("daily", "Daily digest"),
+    ("weekly", "Weekly digest"),
+    ("never", "Never"),
+];
+
+/// Notification types users can configure, with their labels and descriptions
+pub const PREFERENCE_TYPES: &[(&str, &str, &str)] = &[
+    ("reply", "Thread Replies", "Someone replies to your thread"),
+    ("mention", "Mentions", "Someone mentions you with @username"),
+    (
+        "pm",
+        "Private Messages",
+        "Someone sends you a private message",
+    ),
+    ("quote", "Quotes", "Someone quotes your post"),
+    (
+        "thread_watch",
+        "Watched Threads",
+        "New replies in threads you're watching",
+    ),
+    (
+        "forum_watch",
+        "Watched Forums",
+        "New threads in forums you're watching",
+    ),
+    ("reaction", "Reactions", "Someone reacts to your post"),
+    (
+        "chat",
+        "Chat Messages",
+        "New messages in your private chat rooms and DMs",
+    ),
+    (
+        "profile_post",
+        "Profile Posts",
+        "Someone posts on your profile or comments on your profile post",
+    ),
+];
+
+/// Whether a frequency value is one we accept
+pub fn is_valid_frequency(frequency: &str) -> bool {
+    FREQUENCIES.iter().any(|(value, _)| *value == frequency)
+}
+
 /// Notification preferences for a user
 pub struct NotificationPreferences {
     pub in_app: bool,
     pub email: bool,
+    pub push: bool,
     pub frequency: String,
 }
 
+impl Default for NotificationPreferences {
+    /// Everything on and emailed immediately, for types the user never configured
+    fn default() -> Self {
+        Self {
+            in_app: true,
+            email: true,
+            push: true,
+            frequency: "immediate".to_string(),
+        }
+    }
+}
+
+impl From<notification_preferences::Model> for NotificationPreferences {
+    fn from(model: notification_preferences::Model) -> Self {
+        Self {
+            in_app: model.in_app,
+            email: model.email,
+            push: model.push,
+            frequency: model.frequency,
+        }
+    }
+}
+
 /// Create a notification for a user
 #[allow(clippy::too_many_arguments)]
 pub async fn create_notification(
@@ -30,12 +112,17 @@ pub async fn create_notification(
 ) -> Result<i32, DbErr> {
     let db = get_db_pool();
 
-    // Check user preferences
-    let prefs = get_user_preferences(user_id, &notification_type).await?;
-
-    if !prefs.in_app {
-        return Ok(0); // User has disabled this notification type
-    }
+    let prefs = match wanted_preferences(
+        user_id,
+        &notification_type,
+        source_content_type.as_deref(),
+        source_content_id,
+    )
+    .await?
+    {
+        Some(prefs) => prefs,
+        None => return Ok(0),
+    };
 
     // Create notification
     let notification = notifications::ActiveModel {
@@ -55,7 +142,7 @@ pub async fn create_notification(
     let result = notification.insert(db).await?;
 
     // Send email if preferences allow
-    if prefs.email && prefs.frequency == "immediate" {
+    if prefs.email && prefs.frequency == "immediate" && !is_do_not_disturb(user_id).await? {
         // Email sending will be handled by a background task or in the dispatcher
         log::info!(
             "Email notification queued for user {} (notification {})",
@@ -67,7 +154,45 @@ pub async fn create_notification(
     Ok(result.id)
 }
 
-/// Get user's notification preferences for a specific type
+/// The user's preferences for a notification, or None if they don't want it
+pub(crate) async fn wanted_preferences(
+    user_id: i32,
+    notification_type: &NotificationType,
+    source_content_type: Option<&str>,
+    source_content_id: Option<i32>,
+) -> Result<Option<NotificationPreferences>, DbErr> {
+    // Check user preferences
+    let prefs = get_user_preferences(user_id, notification_type).await?;
+
+    if !prefs.in_app {
+        return Ok(None); // User has disabled this notification type
+    }
+
+    // Muted conversations still show as unread in the inbox, but raise nothing
+    if source_content_type == Some("conversation") {
+        if let Some(conversation_id) = source_content_id {
+            if is_conversation_muted(user_id, conversation_id).await? {
+                return Ok(None);
+            }
+        }
+    }
+
+    Ok(Some(prefs))
+}
+
+/// Check whether a user has muted a conversation
+pub async fn is_conversation_muted(user_id: i32, conversation_id: i32) -> Result<bool, DbErr> {
+    let participant = conversation_participants::Entity::find()
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .filter(conversation_participants::Column::UserId.eq(user_id))
+        .one(get_db_pool())
+        .await?;
+
+    Ok(participant.map(|p| p.is_muted).unwrap_or(false))
+}
+
+/// Get user's notification preferences for a specific type. Types the user
+/// never configured get the defaults; nothing is written.
 pub async fn get_user_preferences(
     user_id: i32,
     notification_type: &NotificationType,
@@ -80,30 +205,7 @@ pub async fn get_user_preferences(
         .one(db)
         .await?;
 
-    match pref {
-        Some(p) => Ok(NotificationPreferences {
-            in_app: p.in_app,
-            email: p.email,
-            frequency: p.frequency,
-        }),
-        None => {
-            // Create default preferences if they don't exist
-            let default_pref = notification_preferences::ActiveModel {
-                user_id: Set(user_id),
-                notification_type: Set(notification_type.as_str().to_string()),
-                in_app: Set(true),
-                email: Set(true),
-                frequency: Set("immediate".to_string()),
-            };
-            default_pref.insert(db).await?;
-
-            Ok(NotificationPreferences {
-                in_app: true,
-                email: true,
-                frequency: "immediate".to_string(),
-            })
-        }
-    }
+    Ok(pref.map(NotificationPreferences::from).unwrap_or_default())
 }
 
 /// Count unread notifications for a user
@@ -113,6 +215,7 @@ pub async fn count_unread_notifications(user_id: i32) -> Result<i64, DbErr> {
     let count = notifications::Entity::find()
         .filter(notifications::Column::UserId.eq(user_id))
         .filter(notifications::Column::IsRead.eq(false))
+        .filter(notifications::Column::SnoozedUntil.is_null())
         .count(db)
         .await?;
 
@@ -137,6 +240,25 @@ pub async fn mark_notification_read(notification_id: i32, user_id: i32) -> Resul
     Ok(())
 }
 
+/// Mark a user's unread notifications in one group as read
+pub async fn mark_group_read(user_id: i32, group_key: &str) -> Result<(), DbErr> {
+    let db = get_db_pool();
+
+    notifications::Entity::update_many()
+        .col_expr(notifications::Column::IsRead, Expr::value(true))
+        .col_expr(
+            notifications::Column::ReadAt,
+            Expr::value(chrono::Utc::now().naive_utc()),
+        )
+        .filter(notifications::Column::UserId.eq(user_id))
+        .filter(notifications::Column::GroupKey.eq(group_key))
+        .filter(notifications::Column::IsRead.eq(false))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
 /// Mark all notifications as read for a user
 pub async fn mark_all_read(user_id: i32) -> Result<(), DbErr> {
     let db = get_db_pool();
@@ -155,7 +277,7 @@ pub async fn mark_all_read(user_id: i32) -> Result<(), DbErr> {
     Ok(())
 }
 
-/// Fetch recent notifications for a user
+/// Fetch recent notifications for a user, leaving out snoozed ones
 pub async fn get_user_notifications(
     user_id: i32,
     limit: u64,
@@ -165,6 +287,7 @@ pub async fn get_user_notifications(
 
     let mut query = notifications::Entity::find()
         .filter(notifications::Column::UserId.eq(user_id))
+        .filter(notifications::Column::SnoozedUntil.is_null())
         .order_by_desc(notifications::Column::CreatedAt)
         .limit(limit);
 
@@ -283,6 +406,73 @@ pub async fn count_watched_threads(user_id: i32) -> Result<i64, DbErr> {
     Ok(count as i64)
 }
 
+// Forum Watching Functions
+
+/// Add a forum to user's watch list
+pub async fn watch_forum(user_id: i32, forum_id: i32) -> Result<(), DbErr> {
+    let db = get_db_pool();
+
+    if get_forum_watch_status(user_id, forum_id).await?.is_some() {
+        return Ok(()); // Already watching
+    }
+
+    let watch = watched_forums::ActiveModel {
+        user_id: Set(user_id),
+        forum_id: Set(forum_id),
+        notify_on_thread: Set(true),
+        email_on_thread: Set(false), // Off by default, user can enable
+        ..Default::default()
+    };
+
+    watch.insert(db).await?;
+    Ok(())
+}
+
+/// Toggle email notifications for a watched forum
+pub async fn toggle_forum_email(user_id: i32, forum_id: i32, enable: bool) -> Result<(), DbErr> {
+    let db = get_db_pool();
+
+    watched_forums::Entity::update_many()
+        .col_expr(watched_forums::Column::EmailOnThread, Expr::value(enable))
+        .filter(watched_forums::Column::UserId.eq(user_id))
+        .filter(watched_forums::Column::ForumId.eq(forum_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Get watch status for a forum including email preference
+pub async fn get_forum_watch_status(
+    user_id: i32,
+    forum_id: i32,
+) -> Result<Option<watched_forums::Model>, DbErr> {
+    watched_forums::Entity::find_by_id((user_id, forum_id))
+        .one(get_db_pool())
+        .await
+}
+
+/// Remove a forum from user's watch list
+pub async fn unwatch_forum(user_id: i32, forum_id: i32) -> Result<(), DbErr> {
+    let db = get_db_pool();
+
+    watched_forums::Entity::delete_many()
+        .filter(watched_forums::Column::UserId.eq(user_id))
+        .filter(watched_forums::Column::ForumId.eq(forum_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Get all forums a user is watching
+pub async fn get_watched_forums(user_id: i32) -> Result<Vec<watched_forums::Model>, DbErr> {
+    watched_forums::Entity::find()
+        .filter(watched_forums::Column::UserId.eq(user_id))
+        .all(get_db_pool())
+        .await
+}
+
 // Notification Preference Management
 
 /// Preference display model
@@ -293,9 +483,31 @@ pub struct NotificationPreferenceDisplay {
     pub type_description: String,
     pub in_app: bool,
     pub email: bool,
+    pub push: bool,
     pub frequency: String,
 }
 
+/// Frequency choice for the preferences form
+#[derive(Debug, Clone)]
+pub struct FrequencyOption {
+    pub value: &'static str,
+    pub label: &'static str,
+    pub selected: bool,
+}
+
+impl NotificationPreferenceDisplay {
+    pub fn frequency_options(&self) -> Vec<FrequencyOption> {
+        FREQUENCIES
+            .iter()
+            .map(|(value, label)| FrequencyOption {
+                value,
+                label,
+                selected: *value == self.frequency,
+            })
+            .collect()
+    }
+}
+
 /// Get all notification preferences for a user
 pub async fn get_all_user_preferences(
     user_id: i32,
@@ -308,43 +520,127 @@ pub async fn get_all_user_preferences(
         .all(db)
         .await?;
 
-    // Convert to display models with labels
-    let mut displays = Vec::new();
-
-    // Define all notification types with labels
-    let notification_types = vec![
-        ("reply", "Thread Replies", "Someone replies to your thread"),
-        ("mention", "Mentions", "Someone mentions you with @username"),
-        (
-            "pm",
-            "Private Messages",
-            "Someone sends you a private message",
-        ),
-        ("quote", "Quotes", "Someone quotes your post"),
-        (
-            "thread_watch",
-            "Watched Threads",
-            "New replies in threads you're watching",
-        ),
-    ];
-
-    for (type_str, label, description) in notification_types {
-        // Find existing preference or use defaults
-        let pref = prefs.iter().find(|p| p.notification_type == type_str);
-
-        displays.push(NotificationPreferenceDisplay {
-            notification_type: type_str.to_string(),
-            type_label: label.to_string(),
-            type_description: description.to_string(),
-            in_app: pref.map(|p| p.in_app).unwrap_or(true),
-            email: pref.map(|p| p.email).unwrap_or(true),
-            frequency: pref
-                .map(|p| p.frequency.clone())
-                .unwrap_or_else(|| "immediate".to_string()),
-        });
+    Ok(PREFERENCE_TYPES
+        .iter()
+        .map(|(type_str, label, description)| {
+            // Find existing preference or use defaults
+            let pref = prefs
+                .iter()
+                .find(|p| p.notification_type == *type_str)
+                .cloned()
+                .map(NotificationPreferences::from)
+                .unwrap_or_default();
+
+            NotificationPreferenceDisplay {
+                notification_type: type_str.to_string(),
+                type_label: label.to_string(),
+                type_description: description.to_string(),
+                in_app: pref.in_app,
+                email: pref.email,
+                push: pref.push,
+                frequency: pref.frequency,
+            }
+        })
+        .collect())
+}
+
+/// One row of a batch preference update
+#[derive(Debug, Clone)]
+pub struct PreferenceUpdate {
+    pub notification_type: String,
+    pub in_app: bool,
+    pub email: bool,
+    pub push: bool,
+    pub frequency: String,
+}
+
+/// Save several of a user's preferences at once
+pub async fn update_preferences(user_id: i32, updates: &[PreferenceUpdate]) -> Result<(), DbErr> {
+    for update in updates {
+        if !PREFERENCE_TYPES
+            .iter()
+            .any(|(type_str, _, _)| *type_str == update.notification_type)
+        {
+            return Err(DbErr::Custom(format!(
+                "Unknown notification type: {}",
+                update.notification_type
+            )));
+        }
+        if !is_valid_frequency(&update.frequency) {
+            return Err(DbErr::Custom(format!(
+                "Invalid frequency: {}",
+                update.frequency
+            )));
+        }
+    }
+
+    let txn = get_db_pool().begin().await?;
+
+    let existing = notification_preferences::Entity::find()
+        .filter(notification_preferences::Column::UserId.eq(user_id))
+        .all(&txn)
+        .await?;
+
+    for update in updates {
+        let model = notification_preferences::ActiveModel {
+            user_id: Set(user_id),
+            notification_type: Set(update.notification_type.clone()),
+            in_app: Set(update.in_app),
+            email: Set(update.email),
+            frequency: Set(update.frequency.clone()),
+            push: Set(update.push),
+        };
+
+        if existing
+            .iter()
+            .any(|p| p.notification_type == update.notification_type)
+        {
+            model.update(&txn).await?;
+        } else {
+            model.insert(&txn).await?;
+        }
+    }
+
+    txn.commit().await
+}
+
+/// Stop emailing a user about the given notification types, keeping the
+/// rest of their preferences
+pub async fn disable_email(user_id: i32, notification_types: &[&str]) -> Result<(), DbErr> {
+    let txn = get_db_pool().begin().await?;
+
+    let existing = notification_preferences::Entity::find()
+        .filter(notification_preferences::Column::UserId.eq(user_id))
+        .all(&txn)
+        .await?;
+
+    for notification_type in notification_types {
+        match existing
+            .iter()
+            .find(|p| p.notification_type == *notification_type)
+        {
+            Some(pref) => {
+                let mut active: notification_preferences::ActiveModel = pref.clone().into();
+                active.email = Set(false);
+                active.update(&txn).await?;
+            }
+            None => {
+                let defaults = NotificationPreferences::default();
+                notification_preferences::ActiveModel {
+                    user_id: Set(user_id),
+                    notification_type: Set(notification_type.to_string()),
+                    in_app: Set(defaults.in_app),
+                    email: Set(false),
+                    frequency: Set(defaults.frequency),
+                    push: Set(defaults.push),
+                }
+                .insert(&txn)
+                .await?;
+            }
+        }
     }
 
-    Ok(displays)
+    txn.commit().await
 }
 
 /// Update a user's notification preference
@@ -379,6 +675,7 @@ pub async fn update_preference(
             in_app: Set(in_app),
             email: Set(email),
             frequency: Set(frequency.to_string()),
+            ..Default::default()
         };
         new_pref.insert(db).await?;
     }
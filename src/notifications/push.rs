@@ -0,0 +1,479 @@
+//! Browser push notifications (Web Push)
+//!
+//! Browsers subscribe through the Push API and hand us an endpoint on their
+//! vendor's push service along with a P-256 public key and auth secret.
+//! Payloads are encrypted for the browser with `aes128gcm` (RFC 8291) and the
+//! request is signed with the site's VAPID key (RFC 8292), so pushes arrive
+//! even when no tab is open.
+
+use crate::db::get_db_pool;
+use crate::orm::push_subscriptions;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL;
+use base64::Engine;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{aead, agreement, hkdf, signature};
+use sea_orm::{entity::*, query::*, DbErr, Set};
+use serde::Serialize;
+use std::time::Duration;
+
+/// Record size advertised in the encryption header; a single record is sent
+const RECORD_SIZE: u32 = 4096;
+
+/// Longest message body put in a push payload
+const MAX_BODY_LENGTH: usize = 1000;
+
+/// VAPID tokens are valid for at most 24 hours; stay well inside that
+const VAPID_TOKEN_LIFETIME: i64 = 12 * 60 * 60;
+
+/// What the service worker shows
+#[derive(Debug, Clone, Serialize)]
+pub struct PushPayload {
+    pub title: String,
+    pub body: String,
+    pub url: Option<String>,
+    /// Notifications with the same tag replace each other
+    pub tag: String,
+}
+
+#[derive(Debug)]
+pub enum PushError {
+    Config(&'static str),
+    Crypto,
+    Http(reqwest::Error),
+}
+
+impl std::fmt::Display for PushError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PushError::Config(message) => write!(f, "push configuration: {}", message),
+            PushError::Crypto => write!(f, "push encryption failed"),
+            PushError::Http(e) => write!(f, "push request failed: {}", e),
+        }
+    }
+}
+
+impl From<ring::error::Unspecified> for PushError {
+    fn from(_: ring::error::Unspecified) -> Self {
+        PushError::Crypto
+    }
+}
+
+/// Whether push is switched on and has a key pair
+pub fn is_enabled() -> bool {
+    let config = crate::app_config::push();
+    config.enabled && !config.vapid_public_key.is_empty() && !config.vapid_private_key.is_empty()
+}
+
+/// The VAPID public key browsers need to subscribe, when push is enabled
+pub fn public_key() -> Option<String> {
+    is_enabled().then(|| crate::app_config::push().vapid_public_key)
+}
+
+fn decode(value: &str) -> Option<Vec<u8>> {
+    BASE64URL.decode(value.trim().trim_end_matches('=')).ok()
+}
+
+/// Check a subscription from the browser before storing it
+pub fn validate_subscription(endpoint: &str, p256dh: &str, auth: &str) -> Result<(), &'static str> {
+    match url::Url::parse(endpoint) {
+        Ok(url) if url.scheme() == "https" && url.host_str().is_some() => {}
+        _ => return Err("Push endpoint must be an https URL"),
+    }
+
+    match decode(p256dh) {
+        Some(key) if key.len() == 65 && key[0] == 0x04 => {}
+        _ => return Err("Invalid p256dh key"),
+    }
+
+    match decode(auth) {
+        Some(secret) if secret.len() == 16 => {}
+        _ => return Err("Invalid auth secret"),
+    }
+
+    Ok(())
+}
+
+/// Save a browser's subscription for a user, taking it over if another
+/// account on the same browser had it
+pub async fn subscribe(
+    user_id: i32,
+    endpoint: &str,
+    p256dh: &str,
+    auth: &str,
+) -> Result<(), DbErr> {
+    validate_subscription(endpoint, p256dh, auth)
+        .map_err(|message| DbErr::Custom(message.to_string()))?;
+
+    let db = get_db_pool();
+
+    let existing = push_subscriptions::Entity::find()
+        .filter(push_subscriptions::Column::Endpoint.eq(endpoint))
+        .one(db)
+        .await?;
+
+    match existing {
+        Some(subscription) => {
+            let mut active: push_subscriptions::ActiveModel = subscription.into();
+            active.user_id = Set(user_id);
+            active.p256dh = Set(p256dh.to_string());
+            active.auth = Set(auth.to_string());
+            active.update(db).await?;
+        }
+        None => {
+            push_subscriptions::ActiveModel {
+                user_id: Set(user_id),
+                endpoint: Set(endpoint.to_string()),
+                p256dh: Set(p256dh.to_string()),
+                auth: Set(auth.to_string()),
+                created_at: Set(chrono::Utc::now().naive_utc()),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove one of a user's subscriptions. Returns whether it existed.
+pub async fn unsubscribe(user_id: i32, endpoint: &str) -> Result<bool, DbErr> {
+    let result = push_subscriptions::Entity::delete_many()
+        .filter(push_subscriptions::Column::UserId.eq(user_id))
+        .filter(push_subscriptions::Column::Endpoint.eq(endpoint))
+        .exec(get_db_pool())
+        .await?;
+
+    Ok(result.rows_affected > 0)
+}
+
+/// Get a user's subscriptions
+pub async fn get_subscriptions(user_id: i32) -> Result<Vec<push_subscriptions::Model>, DbErr> {
+    push_subscriptions::Entity::find()
+        .filter(push_subscriptions::Column::UserId.eq(user_id))
+        .all(get_db_pool())
+        .await
+}
+
+/// Push to all of a user's browsers in the background
+pub fn dispatch(user_id: i32, payload: PushPayload) {
+    if !is_enabled() {
+        return;
+    }
+
+    actix_web::rt::spawn(async move {
+        if let Err(e) = send_to_user(user_id, &payload).await {
+            log::warn!(
+                "Failed to send push notifications to user {}: {}",
+                user_id,
+                e
+            );
+        }
+    });
+}
+
+/// Push to all of a user's browsers. Subscriptions the push service reports
+/// as gone are removed.
+pub async fn send_to_user(user_id: i32, payload: &PushPayload) -> Result<(), DbErr> {
+    let subscriptions = get_subscriptions(user_id).await?;
+    if subscriptions.is_empty() {
+        return Ok(());
+    }
+
+    let mut payload = payload.clone();
+    if payload.body.chars().count() > MAX_BODY_LENGTH {
+        payload.body = payload.body.chars().take(MAX_BODY_LENGTH).collect();
+        payload.body.push('…');
+    }
+    let message = serde_json::to_vec(&payload).map_err(|e| DbErr::Custom(e.to_string()))?;
+
+    let db = get_db_pool();
+    for subscription in subscriptions {
+        match send(&subscription, &message).await {
+            Ok(reqwest::StatusCode::NOT_FOUND) | Ok(reqwest::StatusCode::GONE) => {
+                push_subscriptions::Entity::delete_by_id(subscription.id)
+                    .exec(db)
+                    .await?;
+            }
+            Ok(status) if status.is_success() => {
+                let mut active: push_subscriptions::ActiveModel = subscription.into();
+                active.last_used_at = Set(Some(chrono::Utc::now().naive_utc()));
+                active.update(db).await?;
+            }
+            Ok(status) => log::warn!(
+                "Push service rejected message for subscription {}: {}",
+                subscription.id,
+                status
+            ),
+            Err(e) => log::warn!("Push to subscription {} failed: {}", subscription.id, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Encrypt and deliver one message to one browser
+async fn send(
+    subscription: &push_subscriptions::Model,
+    message: &[u8],
+) -> Result<reqwest::StatusCode, PushError> {
+    let config = crate::app_config::push();
+
+    let ua_public = decode(&subscription.p256dh).ok_or(PushError::Config("bad p256dh"))?;
+    let auth_secret = decode(&subscription.auth).ok_or(PushError::Config("bad auth secret"))?;
+    let body = encrypt(&ua_public, &auth_secret, message)?;
+
+    let endpoint =
+        url::Url::parse(&subscription.endpoint).map_err(|_| PushError::Config("bad endpoint"))?;
+    let authorization = vapid_authorization(&endpoint, &config)?;
+
+    let response = reqwest::Client::new()
+        .post(endpoint)
+        .timeout(Duration::from_secs(10))
+        .header("Authorization", authorization)
+        .header("Content-Encoding", "aes128gcm")
+        .header("Content-Type", "application/octet-stream")
+        .header("TTL", config.ttl_seconds.to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(PushError::Http)?;
+
+    Ok(response.status())
+}
+
+/// Output length for ring's HKDF expansion
+struct OkmLength(usize);
+
+impl hkdf::KeyType for OkmLength {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], length: usize) -> Result<Vec<u8>, PushError> {
+    let mut out = vec![0; length];
+    hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+        .extract(ikm)
+        .expand(&[info], OkmLength(length))?
+        .fill(&mut out)?;
+    Ok(out)
+}
+
+/// Derive the content encryption key and nonce shared with the browser
+fn derive_keys(
+    ecdh_secret: &[u8],
+    auth_secret: &[u8],
+    ua_public: &[u8],
+    as_public: &[u8],
+    salt: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), PushError> {
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(ua_public);
+    key_info.extend_from_slice(as_public);
+    let ikm = hkdf_sha256(auth_secret, ecdh_secret, &key_info, 32)?;
+
+    let cek = hkdf_sha256(salt, &ikm, b"Content-Encoding: aes128gcm\0", 16)?;
+    let nonce = hkdf_sha256(salt, &ikm, b"Content-Encoding: nonce\0", 12)?;
+    Ok((cek, nonce))
+}
+
+/// Encrypt a message for a browser as a single `aes128gcm` record
+fn encrypt(ua_public: &[u8], auth_secret: &[u8], message: &[u8]) -> Result<Vec<u8>, PushError> {
+    let rng = SystemRandom::new();
+
+    let as_private = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng)?;
+    let as_public = as_private.compute_public_key()?;
+    let ua_key = agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, ua_public);
+    let ecdh_secret = agreement::agree_ephemeral(as_private, &ua_key, |secret| secret.to_vec())?;
+
+    let mut salt = [0u8; 16];
+    rng.fill(&mut salt)?;
+
+    let (cek, nonce) = derive_keys(
+        &ecdh_secret,
+        auth_secret,
+        ua_public,
+        as_public.as_ref(),
+        &salt,
+    )?;
+
+    // Padding delimiter 0x02 marks the last (and only) record
+    let mut record = message.to_vec();
+    record.push(0x02);
+
+    let key = aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_128_GCM, &cek)?);
+    key.seal_in_place_append_tag(
+        aead::Nonce::try_assume_unique_for_key(&nonce)?,
+        aead::Aad::empty(),
+        &mut record,
+    )?;
+
+    let mut body = Vec::with_capacity(16 + 4 + 1 + 65 + record.len());
+    body.extend_from_slice(&salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(as_public.as_ref().len() as u8);
+    body.extend_from_slice(as_public.as_ref());
+    body.extend_from_slice(&record);
+    Ok(body)
+}
+
+/// Load the site's VAPID signing key
+fn vapid_key_pair(
+    config: &crate::app_config::PushConfig,
+) -> Result<signature::EcdsaKeyPair, PushError> {
+    let private_key =
+        decode(&config.vapid_private_key).ok_or(PushError::Config("bad VAPID private key"))?;
+    let public_key =
+        decode(&config.vapid_public_key).ok_or(PushError::Config("bad VAPID public key"))?;
+
+    signature::EcdsaKeyPair::from_private_key_and_public_key(
+        &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+        &private_key,
+        &public_key,
+        &SystemRandom::new(),
+    )
+    .map_err(|_| PushError::Config("VAPID keys don't form a P-256 key pair"))
+}
+
+/// Sign a VAPID JWT for a push service origin
+fn vapid_token(
+    key_pair: &signature::EcdsaKeyPair,
+    audience: &str,
+    subject: &str,
+    expires: i64,
+) -> Result<String, PushError> {
+    let header = BASE64URL.encode(br#"{"typ":"JWT","alg":"ES256"}"#);
+    let claims = BASE64URL
+        .encode(serde_json::json!({ "aud": audience, "exp": expires, "sub": subject }).to_string());
+    let signing_input = format!("{}.{}", header, claims);
+
+    let signature = key_pair.sign(&SystemRandom::new(), signing_input.as_bytes())?;
+    Ok(format!(
+        "{}.{}",
+        signing_input,
+        BASE64URL.encode(signature.as_ref())
+    ))
+}
+
+/// `Authorization` header value for a request to a push endpoint
+fn vapid_authorization(
+    endpoint: &url::Url,
+    config: &crate::app_config::PushConfig,
+) -> Result<String, PushError> {
+    let key_pair = vapid_key_pair(config)?;
+    let audience = endpoint.origin().ascii_serialization();
+    let expires = chrono::Utc::now().timestamp() + VAPID_TOKEN_LIFETIME;
+    let token = vapid_token(&key_pair, &audience, &config.vapid_subject, expires)?;
+
+    Ok(format!(
+        "vapid t={}, k={}",
+        token,
+        config.vapid_public_key.trim_end_matches('=')
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decrypt as the browser would, to check the record round-trips
+    fn decrypt(
+        ua_private: agreement::EphemeralPrivateKey,
+        ua_public: &[u8],
+        auth_secret: &[u8],
+        body: &[u8],
+    ) -> Vec<u8> {
+        let salt = &body[..16];
+        assert_eq!(&body[16..20], &RECORD_SIZE.to_be_bytes());
+        let id_length = body[20] as usize;
+        let as_public = &body[21..21 + id_length];
+        let mut record = body[21 + id_length..].to_vec();
+
+        let peer = agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, as_public);
+        let ecdh_secret = agreement::agree_ephemeral(ua_private, &peer, |s| s.to_vec()).unwrap();
+        let (cek, nonce) =
+            derive_keys(&ecdh_secret, auth_secret, ua_public, as_public, salt).unwrap();
+
+        let key = aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_128_GCM, &cek).unwrap());
+        let plaintext = key
+            .open_in_place(
+                aead::Nonce::try_assume_unique_for_key(&nonce).unwrap(),
+                aead::Aad::empty(),
+                &mut record,
+            )
+            .unwrap();
+
+        assert_eq!(plaintext.last(), Some(&0x02));
+        plaintext[..plaintext.len() - 1].to_vec()
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let rng = SystemRandom::new();
+        let ua_private =
+            agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng).unwrap();
+        let ua_public = ua_private.compute_public_key().unwrap().as_ref().to_vec();
+        let mut auth_secret = [0u8; 16];
+        rng.fill(&mut auth_secret).unwrap();
+
+        let body = encrypt(&ua_public, &auth_secret, b"{\"title\":\"Hello\"}").unwrap();
+        assert_eq!(
+            decrypt(ua_private, &ua_public, &auth_secret, &body),
+            b"{\"title\":\"Hello\"}"
+        );
+    }
+
+    #[test]
+    fn test_vapid_token_verifies() {
+        let rng = SystemRandom::new();
+        let pkcs8 = signature::EcdsaKeyPair::generate_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &rng,
+        )
+        .unwrap();
+        let key_pair = signature::EcdsaKeyPair::from_pkcs8(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            pkcs8.as_ref(),
+            &rng,
+        )
+        .unwrap();
+        use signature::KeyPair;
+
+        let token = vapid_token(
+            &key_pair,
+            "https://push.example.net",
+            "mailto:admin@example.com",
+            1_800_000_000,
+        )
+        .unwrap();
+        let (signing_input, signature_part) = token.rsplit_once('.').unwrap();
+
+        signature::UnparsedPublicKey::new(
+            &signature::ECDSA_P256_SHA256_FIXED,
+            key_pair.public_key().as_ref(),
+        )
+        .verify(
+            signing_input.as_bytes(),
+            &BASE64URL.decode(signature_part).unwrap(),
+        )
+        .unwrap();
+
+        let claims = signing_input.split('.').nth(1).unwrap();
+        let claims: serde_json::Value =
+            serde_json::from_slice(&BASE64URL.decode(claims).unwrap()).unwrap();
+        assert_eq!(claims["aud"], "https://push.example.net");
+        assert_eq!(claims["exp"], 1_800_000_000);
+    }
+
+    #[test]
+    fn test_validate_subscription() {
+        let key = BASE64URL.encode([4u8; 65]);
+        let auth = BASE64URL.encode([1u8; 16]);
+
+        assert!(validate_subscription("https://push.example.net/abc", &key, &auth).is_ok());
+        assert!(validate_subscription("http://push.example.net/abc", &key, &auth).is_err());
+        assert!(validate_subscription("https://push.example.net/abc", &auth, &auth).is_err());
+        assert!(validate_subscription("https://push.example.net/abc", &key, &key).is_err());
+    }
+}
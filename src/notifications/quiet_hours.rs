@@ -0,0 +1,226 @@
+//! Do-not-disturb quiet hours
+//!
+//! During a user's quiet hours notifications are still recorded, so counts
+//! stay accurate when they check in, but nothing is pushed over the WebSocket
+//! and no immediate emails are sent.
+
+use crate::db::get_db_pool;
+use crate::orm::notification_quiet_hours;
+use chrono::{Duration, NaiveDateTime, Timelike};
+use sea_orm::{entity::*, DbErr, Set};
+
+/// Quiet hours display model
+#[derive(Debug, Clone)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub start_minute: i16,
+    pub end_minute: i16,
+    pub utc_offset_minutes: i16,
+}
+
+impl Default for QuietHours {
+    /// 22:00 to 07:00 UTC, switched off
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start_minute: 22 * 60,
+            end_minute: 7 * 60,
+            utc_offset_minutes: 0,
+        }
+    }
+}
+
+impl From<notification_quiet_hours::Model> for QuietHours {
+    fn from(model: notification_quiet_hours::Model) -> Self {
+        Self {
+            enabled: model.enabled,
+            start_minute: model.start_minute,
+            end_minute: model.end_minute,
+            utc_offset_minutes: model.utc_offset_minutes,
+        }
+    }
+}
+
+impl QuietHours {
+    /// Whether the quiet window covers the given UTC time
+    pub fn is_active_at(&self, now_utc: NaiveDateTime) -> bool {
+        if !self.enabled || self.start_minute == self.end_minute {
+            return false;
+        }
+
+        let local = now_utc + Duration::minutes(self.utc_offset_minutes as i64);
+        let minute = (local.hour() * 60 + local.minute()) as i16;
+
+        if self.start_minute < self.end_minute {
+            minute >= self.start_minute && minute < self.end_minute
+        } else {
+            // Window wraps past midnight
+            minute >= self.start_minute || minute < self.end_minute
+        }
+    }
+
+    /// Start time as HH:MM, for form inputs
+    pub fn start_time(&self) -> String {
+        format_minute(self.start_minute)
+    }
+
+    /// End time as HH:MM, for form inputs
+    pub fn end_time(&self) -> String {
+        format_minute(self.end_minute)
+    }
+
+    /// Selectable UTC offsets, from UTC-12:00 to UTC+14:00
+    pub fn offset_options(&self) -> Vec<OffsetOption> {
+        (-720..=840)
+            .step_by(15)
+            .map(|offset: i16| {
+                let sign = if offset < 0 { '-' } else { '+' };
+                OffsetOption {
+                    value: offset,
+                    label: format!("UTC{}{}", sign, format_minute(offset.abs())),
+                    selected: offset == self.utc_offset_minutes,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Time zone choice for the quiet hours form
+#[derive(Debug, Clone)]
+pub struct OffsetOption {
+    pub value: i16,
+    pub label: String,
+    pub selected: bool,
+}
+
+/// Format minutes after midnight as HH:MM
+pub fn format_minute(minute: i16) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+/// Parse an HH:MM time into minutes after midnight
+pub fn parse_time(value: &str) -> Option<i16> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let hours: i16 = hours.parse().ok()?;
+    let minutes: i16 = minutes.parse().ok()?;
+
+    if !(0..24).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+
+    Some(hours * 60 + minutes)
+}
+
+/// Whether a UTC offset in minutes is one that exists somewhere (UTC-12 to UTC+14)
+pub fn is_valid_offset(offset: i16) -> bool {
+    (-720..=840).contains(&offset) && offset % 15 == 0
+}
+
+/// Get a user's quiet hours, or the defaults if they never set any
+pub async fn get_quiet_hours(user_id: i32) -> Result<QuietHours, DbErr> {
+    Ok(notification_quiet_hours::Entity::find_by_id(user_id)
+        .one(get_db_pool())
+        .await?
+        .map(QuietHours::from)
+        .unwrap_or_default())
+}
+
+/// Save a user's quiet hours
+pub async fn update_quiet_hours(user_id: i32, quiet_hours: &QuietHours) -> Result<(), DbErr> {
+    let db = get_db_pool();
+
+    let model = notification_quiet_hours::ActiveModel {
+        user_id: Set(user_id),
+        enabled: Set(quiet_hours.enabled),
+        start_minute: Set(quiet_hours.start_minute),
+        end_minute: Set(quiet_hours.end_minute),
+        utc_offset_minutes: Set(quiet_hours.utc_offset_minutes),
+    };
+
+    if notification_quiet_hours::Entity::find_by_id(user_id)
+        .one(db)
+        .await?
+        .is_some()
+    {
+        model.update(db).await?;
+    } else {
+        model.insert(db).await?;
+    }
+
+    Ok(())
+}
+
+/// Whether the user is inside their quiet hours right now
+pub async fn is_do_not_disturb(user_id: i32) -> Result<bool, DbErr> {
+    Ok(get_quiet_hours(user_id)
+        .await?
+        .is_active_at(chrono::Utc::now().naive_utc()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 1, 15)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_window_wrapping_midnight() {
+        let quiet = QuietHours {
+            enabled: true,
+            ..Default::default()
+        };
+
+        assert!(quiet.is_active_at(at(23, 0)));
+        assert!(quiet.is_active_at(at(3, 30)));
+        assert!(!quiet.is_active_at(at(7, 0)));
+        assert!(!quiet.is_active_at(at(12, 0)));
+    }
+
+    #[test]
+    fn test_window_same_day() {
+        let quiet = QuietHours {
+            enabled: true,
+            start_minute: 9 * 60,
+            end_minute: 17 * 60,
+            utc_offset_minutes: 0,
+        };
+
+        assert!(quiet.is_active_at(at(9, 0)));
+        assert!(!quiet.is_active_at(at(17, 0)));
+        assert!(!quiet.is_active_at(at(8, 59)));
+    }
+
+    #[test]
+    fn test_offset_applied() {
+        // 22:00-07:00 at UTC-5 is 03:00-12:00 UTC
+        let quiet = QuietHours {
+            enabled: true,
+            utc_offset_minutes: -300,
+            ..Default::default()
+        };
+
+        assert!(quiet.is_active_at(at(4, 0)));
+        assert!(!quiet.is_active_at(at(23, 0)));
+    }
+
+    #[test]
+    fn test_disabled_never_active() {
+        assert!(!QuietHours::default().is_active_at(at(23, 0)));
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("07:30"), Some(450));
+        assert_eq!(parse_time("00:00"), Some(0));
+        assert_eq!(parse_time("24:00"), None);
+        assert_eq!(parse_time("7"), None);
+        assert!(is_valid_offset(330));
+        assert!(!is_valid_offset(900));
+    }
+}
@@ -0,0 +1,287 @@
+//! Telling moderators about content reports
+//!
+//! Each new report notifies the moderators with `moderate.reports.view` and
+//! the moderators of the forum the content is in. A background worker
+//! reminds the former about reports still open after `[reports] sla_hours`,
+//! once per `reminder_interval_hours`. Both can be emailed as well, when
+//! `email_moderators` is on.
+
+use super::dispatcher::{broadcast_realtime_notification, get_base_url, get_moderator_ids};
+use super::{create_notification, get_user_preferences, is_do_not_disturb, NotificationType};
+use crate::db::get_db_pool;
+use crate::email::templates::send_moderator_alert_email;
+use crate::orm::{forums, reports, user_names, users};
+use chrono::{Duration, NaiveDateTime};
+use sea_orm::{entity::*, query::*, sea_query::Expr, DbBackend, DbErr, FromQueryResult, Statement};
+use std::time::Duration as StdDuration;
+
+/// How often the worker looks for overdue reports
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(5 * 60);
+
+/// Where moderators go to deal with open reports
+const OPEN_REPORTS_URL: &str = "/admin/reports?status=open";
+
+/// How long a report may stay open, if reminders are on
+fn sla() -> Option<Duration> {
+    let hours = crate::app_config::reports().sla_hours;
+    (hours > 0).then(|| Duration::hours(hours))
+}
+
+/// Moderators of a forum and of the forums above it
+async fn forum_moderator_ids(forum_id: i32) -> Result<Vec<i32>, DbErr> {
+    #[derive(FromQueryResult)]
+    struct Moderator {
+        user_id: i32,
+    }
+
+    let moderators = Moderator::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id FROM forums WHERE id = $1
+            UNION ALL
+            SELECT f.id, f.parent_id FROM forums f JOIN ancestors a ON f.id = a.parent_id
+        )
+        SELECT DISTINCT fm.user_id
+        FROM forum_moderators fm
+        JOIN ancestors a ON a.id = fm.forum_id
+        ORDER BY fm.user_id
+        "#,
+        vec![forum_id.into()],
+    ))
+    .all(get_db_pool())
+    .await?;
+
+    Ok(moderators.into_iter().map(|m| m.user_id).collect())
+}
+
+/// Notify a moderator in-app, and by email if moderators are emailed and
+/// they take moderation emails.
+async fn alert_moderator(
+    user_id: i32,
+    title: &str,
+    message: &str,
+    url: &str,
+    source_user_id: Option<i32>,
+    report_id: Option<i32>,
+) -> Result<(), DbErr> {
+    let notification_id = create_notification(
+        user_id,
+        NotificationType::ModAction,
+        title.to_string(),
+        message.to_string(),
+        Some(url.to_string()),
+        source_user_id,
+        Some("report".to_string()),
+        report_id,
+    )
+    .await?;
+
+    if notification_id > 0 {
+        broadcast_realtime_notification(
+            user_id,
+            notification_id,
+            "mod_action",
+            title,
+            message,
+            Some(url),
+        )
+        .await;
+    }
+
+    if !crate::app_config::reports().email_moderators {
+        return Ok(());
+    }
+    let prefs = get_user_preferences(user_id, &NotificationType::ModAction).await?;
+    if !prefs.email || is_do_not_disturb(user_id).await? {
+        return Ok(());
+    }
+
+    let db = get_db_pool();
+    let Some(user) = users::Entity::find_by_id(user_id).one(db).await? else {
+        return Ok(());
+    };
+    let Some(email) = user.email.as_deref().filter(|_| user.email_verified) else {
+        return Ok(());
+    };
+    let username = user_names::Entity::find()
+        .filter(user_names::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+        .map(|un| un.name)
+        .unwrap_or_else(|| "Moderator".to_string());
+
+    if let Err(e) =
+        send_moderator_alert_email(email, &username, title, message, url, &get_base_url()).await
+    {
+        log::error!("Failed to email moderator {} about reports: {}", user_id, e);
+    }
+
+    Ok(())
+}
+
+/// Notify moderators of a new report: those who review all reports, and
+/// those of the forum the content is in, if it's in one. The reporter is
+/// left out.
+pub async fn notify_new_report(
+    report: &reports::Model,
+    forum_id: Option<i32>,
+) -> Result<(), DbErr> {
+    let mut moderator_ids = get_moderator_ids().await?;
+    let mut forum_name = None;
+    if let Some(forum_id) = forum_id {
+        moderator_ids.extend(forum_moderator_ids(forum_id).await?);
+        forum_name = forums::Entity::find_by_id(forum_id)
+            .one(get_db_pool())
+            .await?
+            .map(|f| f.label);
+    }
+    moderator_ids.sort_unstable();
+    moderator_ids.dedup();
+    moderator_ids.retain(|id| *id != report.reporter_id);
+
+    let title = "New report";
+    let message = new_report_message(&report.content_type, forum_name.as_deref(), &report.reason);
+    let url = format!("/admin/reports/{}", report.id);
+
+    for moderator_id in moderator_ids {
+        alert_moderator(
+            moderator_id,
+            title,
+            &message,
+            &url,
+            Some(report.reporter_id),
+            Some(report.id),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+fn new_report_message(content_type: &str, forum_name: Option<&str>, reason: &str) -> String {
+    match forum_name {
+        Some(forum_name) => format!(
+            "A {} in {} was reported for {}",
+            content_type, forum_name, reason
+        ),
+        None => format!("A {} was reported for {}", content_type, reason),
+    }
+}
+
+fn reminder_message(count: usize, sla_hours: i64) -> String {
+    if count == 1 {
+        format!("1 report has been open for more than {} hours", sla_hours)
+    } else {
+        format!(
+            "{} reports have been open for more than {} hours",
+            count, sla_hours
+        )
+    }
+}
+
+/// Open reports older than the response time, or None if there is none.
+fn overdue(now: NaiveDateTime) -> Option<Select<reports::Entity>> {
+    let sla = sla()?;
+    Some(
+        reports::Entity::find()
+            .filter(reports::Column::Status.eq("open"))
+            .filter(reports::Column::CreatedAt.lte(now - sla)),
+    )
+}
+
+/// Number of open reports older than the response time.
+pub async fn overdue_count(now: NaiveDateTime) -> Result<u64, DbErr> {
+    match overdue(now) {
+        Some(select) => select.count(get_db_pool()).await,
+        None => Ok(0),
+    }
+}
+
+/// Remind moderators about overdue reports they haven't been reminded about
+/// within the reminder interval. Returns how many reports were included.
+pub async fn send_reminders(now: NaiveDateTime) -> Result<usize, DbErr> {
+    let Some(select) = overdue(now) else {
+        return Ok(0);
+    };
+    let config = crate::app_config::reports();
+    let remind_before = now - Duration::hours(config.reminder_interval_hours.max(1));
+
+    let db = get_db_pool();
+    let due: Vec<i32> = select
+        .filter(
+            Condition::any()
+                .add(reports::Column::RemindedAt.is_null())
+                .add(reports::Column::RemindedAt.lte(remind_before)),
+        )
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    if due.is_empty() {
+        return Ok(0);
+    }
+
+    let overdue_total = overdue_count(now).await? as usize;
+    let message = reminder_message(overdue_total, config.sla_hours);
+    for moderator_id in get_moderator_ids().await? {
+        alert_moderator(
+            moderator_id,
+            "Reports overdue",
+            &message,
+            OPEN_REPORTS_URL,
+            None,
+            None,
+        )
+        .await?;
+    }
+
+    reports::Entity::update_many()
+        .col_expr(reports::Column::RemindedAt, Expr::value(now))
+        .filter(reports::Column::Id.is_in(due.iter().copied()))
+        .exec(db)
+        .await?;
+
+    Ok(due.len())
+}
+
+/// Start the background overdue report reminder worker
+pub fn spawn_worker() {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match send_reminders(chrono::Utc::now().naive_utc()).await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Reminded moderators about {} overdue report(s)", count),
+                Err(e) => log::error!("Failed to send overdue report reminders: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(
+            new_report_message("post", Some("General"), "spam"),
+            "A post in General was reported for spam"
+        );
+        assert_eq!(
+            new_report_message("user", None, "harassment"),
+            "A user was reported for harassment"
+        );
+        assert_eq!(
+            reminder_message(1, 24),
+            "1 report has been open for more than 24 hours"
+        );
+        assert_eq!(
+            reminder_message(3, 48),
+            "3 reports have been open for more than 48 hours"
+        );
+    }
+}
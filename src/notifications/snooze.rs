@@ -0,0 +1,148 @@
+//! Snoozed notifications
+//!
+//! Snoozing an unread notification hides it from the list and the unread
+//! count for an hour, a day or a week. The periodic maintenance task wakes
+//! expired snoozes: the notification moves back to the top of the list and is
+//! pushed again as if it were new.
+
+use crate::db::get_db_pool;
+use crate::orm::notifications;
+use chrono::{Duration, NaiveDateTime};
+use sea_orm::{entity::*, query::*, sea_query::Expr, DbErr, Set};
+
+/// How long to snooze a notification for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnoozeDuration {
+    Hour,
+    Day,
+    Week,
+}
+
+impl SnoozeDuration {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SnoozeDuration::Hour => "1h",
+            SnoozeDuration::Day => "1d",
+            SnoozeDuration::Week => "1w",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "1h" => Some(SnoozeDuration::Hour),
+            "1d" => Some(SnoozeDuration::Day),
+            "1w" => Some(SnoozeDuration::Week),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SnoozeDuration::Hour => "1 hour",
+            SnoozeDuration::Day => "1 day",
+            SnoozeDuration::Week => "1 week",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            SnoozeDuration::Hour => Duration::hours(1),
+            SnoozeDuration::Day => Duration::days(1),
+            SnoozeDuration::Week => Duration::weeks(1),
+        }
+    }
+}
+
+/// Choices offered next to each notification
+pub const SNOOZE_DURATIONS: &[SnoozeDuration] = &[
+    SnoozeDuration::Hour,
+    SnoozeDuration::Day,
+    SnoozeDuration::Week,
+];
+
+/// Snooze one of the user's unread notifications. Returns when it will wake,
+/// or None if there was no such unread notification.
+pub async fn snooze_notification(
+    user_id: i32,
+    notification_id: i32,
+    duration: SnoozeDuration,
+) -> Result<Option<NaiveDateTime>, DbErr> {
+    let until = chrono::Utc::now().naive_utc() + duration.duration();
+
+    let result = notifications::Entity::update_many()
+        .col_expr(notifications::Column::SnoozedUntil, Expr::value(until))
+        .filter(notifications::Column::Id.eq(notification_id))
+        .filter(notifications::Column::UserId.eq(user_id))
+        .filter(notifications::Column::IsRead.eq(false))
+        .exec(get_db_pool())
+        .await?;
+
+    Ok((result.rows_affected > 0).then_some(until))
+}
+
+/// Bring a snoozed notification back straight away, without pushing it
+pub async fn unsnooze_notification(user_id: i32, notification_id: i32) -> Result<bool, DbErr> {
+    let result = notifications::Entity::update_many()
+        .col_expr(
+            notifications::Column::SnoozedUntil,
+            Expr::value(Option::<NaiveDateTime>::None),
+        )
+        .filter(notifications::Column::Id.eq(notification_id))
+        .filter(notifications::Column::UserId.eq(user_id))
+        .filter(notifications::Column::SnoozedUntil.is_not_null())
+        .exec(get_db_pool())
+        .await?;
+
+    Ok(result.rows_affected > 0)
+}
+
+/// The user's snoozed notifications, soonest to wake first
+pub async fn get_snoozed_notifications(user_id: i32) -> Result<Vec<notifications::Model>, DbErr> {
+    notifications::Entity::find()
+        .filter(notifications::Column::UserId.eq(user_id))
+        .filter(notifications::Column::SnoozedUntil.is_not_null())
+        .order_by_asc(notifications::Column::SnoozedUntil)
+        .all(get_db_pool())
+        .await
+}
+
+/// Wake every notification whose snooze has expired, pushing the unread ones
+/// again. Returns how many were woken.
+pub async fn wake_snoozed_notifications(now: NaiveDateTime) -> Result<usize, DbErr> {
+    let db = get_db_pool();
+
+    let expired = notifications::Entity::find()
+        .filter(notifications::Column::SnoozedUntil.lte(now))
+        .all(db)
+        .await?;
+
+    for notification in &expired {
+        let mut active: notifications::ActiveModel = notification.clone().into();
+        active.snoozed_until = Set(None);
+        if !notification.is_read {
+            // Re-surface at the top of the list
+            active.created_at = Set(now);
+        }
+        let woken = active.update(db).await?;
+
+        if !woken.is_read {
+            super::dispatcher::broadcast_woken_notification(&woken).await;
+        }
+    }
+
+    Ok(expired.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        for duration in SNOOZE_DURATIONS {
+            assert_eq!(SnoozeDuration::parse(duration.as_str()), Some(*duration));
+        }
+        assert_eq!(SnoozeDuration::parse("1y"), None);
+        assert_eq!(SnoozeDuration::Day.duration(), Duration::hours(24));
+    }
+}
@@ -10,6 +10,10 @@ pub enum NotificationType {
     PrivateMessage, // New private message
     ThreadWatch,    // Update in watched thread
     ModAction,      // Moderation action on your content
+    Reaction,       // Someone reacted to your post
+    ForumWatch,     // New thread in watched forum
+    ChatMessage,    // Activity in a private chat room or DM
+    ProfilePost,    // Someone posted or commented on your profile wall
 }
 
 impl NotificationType {
@@ -21,6 +25,10 @@ impl NotificationType {
             Self::PrivateMessage => "pm",
             Self::ThreadWatch => "thread_watch",
             Self::ModAction => "mod_action",
+            Self::Reaction => "reaction",
+            Self::ForumWatch => "forum_watch",
+            Self::ChatMessage => "chat",
+            Self::ProfilePost => "profile_post",
         }
     }
 
@@ -32,6 +40,10 @@ impl NotificationType {
             "pm" => Some(Self::PrivateMessage),
             "thread_watch" => Some(Self::ThreadWatch),
             "mod_action" => Some(Self::ModAction),
+            "reaction" => Some(Self::Reaction),
+            "forum_watch" => Some(Self::ForumWatch),
+            "chat" => Some(Self::ChatMessage),
+            "profile_post" => Some(Self::ProfilePost),
             _ => None,
         }
     }
@@ -19,6 +19,41 @@ pub enum ActivityType {
 }
 
 impl ActivityType {
+    /// Every activity type, in the order feed filters list them
+    pub const ALL: [ActivityType; 5] = [
+        Self::ThreadCreated,
+        Self::PostCreated,
+        Self::ReactionGiven,
+        Self::ProfilePostCreated,
+        Self::UserFollowed,
+    ];
+
+    /// The database value, also used in feed URLs and forms
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PostCreated => "post_created",
+            Self::ThreadCreated => "thread_created",
+            Self::ProfilePostCreated => "profile_post_created",
+            Self::UserFollowed => "user_followed",
+            Self::ReactionGiven => "reaction_given",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == value)
+    }
+
+    /// Plural name for filters and settings
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::PostCreated => "Replies",
+            Self::ThreadCreated => "New threads",
+            Self::ProfilePostCreated => "Profile posts",
+            Self::UserFollowed => "Follows",
+            Self::ReactionGiven => "Reactions",
+        }
+    }
+
     /// Get a human-readable description of the activity
     pub fn description(&self) -> &'static str {
         match self {
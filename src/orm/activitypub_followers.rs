@@ -0,0 +1,40 @@
+//! SeaORM Entity for activitypub_followers table
+//!
+//! Remote actors following a federated forum.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "activitypub_followers")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub forum_id: i32,
+    #[sea_orm(column_type = "Text")]
+    pub actor_uri: String,
+    #[sea_orm(column_type = "Text")]
+    pub inbox_url: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub shared_inbox_url: Option<String>,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::forums::Entity",
+        from = "Column::ForumId",
+        to = "super::forums::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Forum,
+}
+
+impl Related<super::forums::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Forum.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
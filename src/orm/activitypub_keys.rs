@@ -0,0 +1,37 @@
+//! SeaORM Entity for activitypub_keys table
+//!
+//! The key pair a federated forum's actor signs its deliveries with.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "activitypub_keys")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub forum_id: i32,
+    #[sea_orm(column_type = "Text")]
+    pub public_key_pem: String,
+    #[sea_orm(column_type = "Text")]
+    pub private_key_pem: String,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::forums::Entity",
+        from = "Column::ForumId",
+        to = "super::forums::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Forum,
+}
+
+impl Related<super::forums::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Forum.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
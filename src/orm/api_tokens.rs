@@ -0,0 +1,44 @@
+//! SeaORM Entity for api_tokens table
+//!
+//! Personal access tokens for the JSON API. Only a hash of each token is kept.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "api_tokens")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    #[sea_orm(unique)]
+    pub token_hash: String,
+    /// Start of the token, shown so its owner can tell tokens apart
+    pub token_prefix: String,
+    /// Space separated scopes
+    #[sea_orm(column_type = "Text")]
+    pub scopes: String,
+    pub created_at: DateTime,
+    pub last_used_at: Option<DateTime>,
+    pub expires_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
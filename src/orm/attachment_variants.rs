@@ -0,0 +1,44 @@
+//! SeaORM Entity for attachment_variants table
+//!
+//! Links an image attachment to re-encoded copies of it in other formats.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "attachment_variants")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub attachment_id: i32,
+    pub variant_id: i32,
+    #[sea_orm(primary_key, auto_increment = false, column_type = "Text")]
+    pub mime: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::attachments::Entity",
+        from = "Column::AttachmentId",
+        to = "super::attachments::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Original,
+    #[sea_orm(
+        belongs_to = "super::attachments::Entity",
+        from = "Column::VariantId",
+        to = "super::attachments::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Variant,
+}
+
+// Joining from a variant row leads to the re-encoded file.
+impl Related<super::attachments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Variant.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,6 +1,7 @@
 //! SeaORM Entity. Generated by sea-orm-codegen 0.4.1
 
 use sea_orm::entity::prelude::*;
+use serde::Serialize;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "attachments")]
@@ -19,6 +20,80 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub mime: String,
     pub meta: serde_json::Value,
+    pub reference_count: i32,
+    pub visibility: AttachmentVisibility,
+    pub scan_status: ScanStatus,
+    /// Signature that matched an infected file, or clamd's error for a failed scan.
+    #[sea_orm(column_type = "Text", nullable)]
+    pub scan_result: Option<String>,
+    pub scanned_at: Option<DateTime>,
+}
+
+/// Who may fetch an attachment's file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Default, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentVisibility {
+    /// Anyone, through a permanent URL.
+    #[sea_orm(string_value = "public")]
+    #[default]
+    Public,
+    /// Signed-in users, through signed URLs.
+    #[sea_orm(string_value = "members")]
+    Members,
+    /// Participants of the conversations it was sent in, through signed URLs.
+    #[sea_orm(string_value = "conversation")]
+    Conversation,
+}
+
+impl AttachmentVisibility {
+    /// Whether the file must be served through a signed URL.
+    pub fn is_restricted(&self) -> bool {
+        !matches!(self, AttachmentVisibility::Public)
+    }
+
+    /// Parses a configured visibility name, e.g. `members`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "public" => Some(AttachmentVisibility::Public),
+            "members" => Some(AttachmentVisibility::Members),
+            "conversation" => Some(AttachmentVisibility::Conversation),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of the antivirus scan of an uploaded file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Default, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum ScanStatus {
+    /// Not scanned: generated files, or uploads made while scanning was off.
+    #[sea_orm(string_value = "unscanned")]
+    #[default]
+    Unscanned,
+    /// Waiting for the scan worker.
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    /// Claimed by the scan worker.
+    #[sea_orm(string_value = "scanning")]
+    Scanning,
+    /// Passed the scan.
+    #[sea_orm(string_value = "clean")]
+    Clean,
+    /// Failed the scan; the file is quarantined and no longer served.
+    #[sea_orm(string_value = "infected")]
+    Infected,
+    /// clamd could not scan the file, e.g. because it is over its size limit.
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+impl ScanStatus {
+    /// Whether the file failed its scan and must not be served or restored.
+    pub fn is_quarantined(&self) -> bool {
+        matches!(self, ScanStatus::Infected)
+    }
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,38 @@
+//! SeaORM Entity for avatar_gallery table
+//!
+//! Stock avatars uploaded by administrators which users may pick instead of
+//! uploading their own image.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "avatar_gallery")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub attachment_id: i32,
+    pub label: String,
+    pub display_order: i32,
+    pub is_enabled: bool,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::attachments::Entity",
+        from = "Column::AttachmentId",
+        to = "super::attachments::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Attachment,
+}
+
+impl Related<super::attachments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachment.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -18,12 +18,26 @@ pub struct Model {
     pub min_account_age_hours: i32,
     /// Whether this room is restricted to staff members only
     pub is_staff_only: bool,
+    /// Topic shown to users when they join the room
+    #[sea_orm(column_type = "Text", nullable)]
+    pub topic: Option<String>,
+    /// Private rooms are only visible to their members
+    pub is_private: bool,
+    /// User who created a private room
+    pub owner_id: Option<i32>,
+    /// Set on direct message rooms, unique per pair of users
+    #[sea_orm(column_type = "Text", nullable)]
+    pub direct_key: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::chat_messages::Entity")]
     ChatMessages,
+    #[sea_orm(has_many = "super::chat_room_members::Entity")]
+    ChatRoomMembers,
+    #[sea_orm(has_many = "super::chat_room_permissions::Entity")]
+    ChatRoomPermissions,
 }
 
 impl Related<super::chat_messages::Entity> for Entity {
@@ -32,4 +46,16 @@ impl Related<super::chat_messages::Entity> for Entity {
     }
 }
 
+impl Related<super::chat_room_members::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatRoomMembers.def()
+    }
+}
+
+impl Related<super::chat_room_permissions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatRoomPermissions.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
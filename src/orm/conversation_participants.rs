@@ -10,8 +10,25 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub user_id: i32,
     pub joined_at: DateTime,
-    pub last_read_at: Option<DateTime>,
+    /// Latest message the participant has read; everything up to it counts as read.
+    pub last_read_message_id: Option<i32>,
     pub is_archived: bool,
+    /// Muted conversations raise no notifications but still count as unread.
+    pub is_muted: bool,
+    pub role: ParticipantRole,
+}
+
+/// What a participant may do in a conversation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Default)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+pub enum ParticipantRole {
+    /// Can rename the conversation and invite or remove participants.
+    #[sea_orm(string_value = "owner")]
+    Owner,
+    /// Can read and send messages.
+    #[sea_orm(string_value = "member")]
+    #[default]
+    Member,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,46 @@
+//! SeaORM Entity for email_changes table
+//!
+//! A member's request to change their email address.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "email_changes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub old_email: Option<String>,
+    pub new_email: String,
+    /// Sent to the new address to confirm the change
+    pub confirm_token: String,
+    /// Sent to the old address to cancel or undo the change
+    pub revert_token: String,
+    /// pending, confirmed, cancelled or reverted
+    pub status: String,
+    pub created_at: DateTime,
+    /// When the confirmation link stops working
+    pub expires_at: DateTime,
+    pub confirmed_at: Option<DateTime>,
+    pub reverted_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Users,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
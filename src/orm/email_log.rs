@@ -0,0 +1,50 @@
+//! SeaORM Entity for email_log table
+//!
+//! One email sent, or being sent, by the job queue. Bodies are not kept.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "email_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub recipient: String,
+    pub subject: String,
+    /// pending, sent, retrying or failed
+    pub status: String,
+    pub attempts: i32,
+    pub created_at: DateTime,
+    pub last_attempt_at: Option<DateTime>,
+    pub sent_at: Option<DateTime>,
+    /// The announcement this email was part of
+    pub mass_email_id: Option<i32>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::email_log_attempts::Entity")]
+    Attempts,
+    #[sea_orm(
+        belongs_to = "super::mass_emails::Entity",
+        from = "Column::MassEmailId",
+        to = "super::mass_emails::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    MassEmail,
+}
+
+impl Related<super::email_log_attempts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attempts.def()
+    }
+}
+
+impl Related<super::mass_emails::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MassEmail.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
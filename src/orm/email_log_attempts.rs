@@ -0,0 +1,39 @@
+//! SeaORM Entity for email_log_attempts table
+//!
+//! One attempt at sending a logged email, with what the SMTP server said.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "email_log_attempts")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub email_id: i32,
+    pub attempt: i32,
+    pub succeeded: bool,
+    /// The SMTP response, or why the message could not be sent
+    #[sea_orm(column_type = "Text")]
+    pub response: String,
+    pub attempted_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::email_log::Entity",
+        from = "Column::EmailId",
+        to = "super::email_log::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Email,
+}
+
+impl Related<super::email_log::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Email.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,65 @@
+//! SeaORM Entity for forum_permission_template_values table
+
+use crate::permission::flag::Flag;
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "forum_permission_template_values")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub template_id: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub group_id: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub permission_id: i32,
+    #[sea_orm(rs_type = "i32", db_type = "Enum")]
+    pub value: Flag,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::forum_permission_templates::Entity",
+        from = "Column::TemplateId",
+        to = "super::forum_permission_templates::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    ForumPermissionTemplates,
+    #[sea_orm(
+        belongs_to = "super::groups::Entity",
+        from = "Column::GroupId",
+        to = "super::groups::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Groups,
+    #[sea_orm(
+        belongs_to = "super::permissions::Entity",
+        from = "Column::PermissionId",
+        to = "super::permissions::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Permissions,
+}
+
+impl Related<super::forum_permission_templates::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ForumPermissionTemplates.def()
+    }
+}
+
+impl Related<super::groups::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Groups.def()
+    }
+}
+
+impl Related<super::permissions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Permissions.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,43 @@
+//! SeaORM Entity for forum_permission_templates table
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "forum_permission_templates")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    /// Applied to new forums when they are created
+    pub is_default: bool,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::CreatedBy",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Creator,
+    #[sea_orm(has_many = "super::forum_permission_template_values::Entity")]
+    Values,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Creator.def()
+    }
+}
+
+impl Related<super::forum_permission_template_values::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Values.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -32,6 +32,8 @@ pub struct Model {
     /// Template/placeholder text shown in new thread content box
     #[sea_orm(column_type = "Text", nullable)]
     pub thread_template: Option<String>,
+    /// Whether new threads are published over ActivityPub
+    pub is_federated: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
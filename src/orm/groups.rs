@@ -10,6 +10,11 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub label: String,
     pub group_type: crate::group::GroupType,
+    pub storage_quota_mb: Option<i32>,
+    pub display_priority: i32,
+    pub parent_id: Option<i32>,
+    pub username_color: Option<String>,
+    pub banner_text: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,29 @@
+//! SeaORM Entity for jobs table
+//!
+//! Work queued for the background job worker.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "jobs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub kind: String,
+    pub payload: Json,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime,
+    /// Set while a worker runs the job
+    pub locked_until: Option<DateTime>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_error: Option<String>,
+    /// Set once the job has run out of attempts
+    pub failed_at: Option<DateTime>,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,45 @@
+//! SeaORM Entity for mass_emails table
+//!
+//! An announcement emailed to a filtered set of members, sent in batches.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "mass_emails")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub subject: String,
+    /// BBCode
+    #[sea_orm(column_type = "Text")]
+    pub body: String,
+    /// Only members of this group
+    pub group_id: Option<i32>,
+    /// Only members active within this many days
+    pub active_within_days: Option<i32>,
+    /// Only members who accept announcement emails
+    pub opted_in_only: bool,
+    /// sending, done or cancelled
+    pub status: String,
+    pub total_recipients: i32,
+    pub queued_count: i32,
+    /// Highest user id queued so far; the next batch starts after it
+    pub last_user_id: i32,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime,
+    pub finished_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::email_log::Entity")]
+    Emails,
+}
+
+impl Related<super::email_log::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Emails.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
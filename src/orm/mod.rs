@@ -3,46 +3,75 @@
 pub mod prelude;
 
 pub mod activities;
+pub mod activitypub_followers;
+pub mod activitypub_keys;
+pub mod api_tokens;
 pub mod attachment_thumbnails;
+pub mod attachment_variants;
 pub mod attachments;
+pub mod avatar_gallery;
 pub mod badges;
 pub mod chat_messages;
+pub mod chat_room_members;
+pub mod chat_room_permissions;
 pub mod chat_rooms;
 pub mod conversation_participants;
 pub mod conversations;
+pub mod email_changes;
+pub mod email_log;
+pub mod email_log_attempts;
 pub mod email_verification_tokens;
 pub mod feature_flags;
 pub mod forum_moderators;
+pub mod forum_permission_template_values;
+pub mod forum_permission_templates;
 pub mod forum_permissions;
 pub mod forum_read;
 pub mod forums;
 pub mod groups;
 pub mod ip;
 pub mod ip_bans;
+pub mod jobs;
+pub mod mass_emails;
 pub mod mod_log;
 pub mod moderator_notes;
+pub mod notice_dismissals;
+pub mod notices;
+pub mod notification_actors;
+pub mod notification_digests;
 pub mod notification_preferences;
+pub mod notification_quiet_hours;
 pub mod notifications;
 pub mod password_reset_tokens;
+pub mod permission_audit;
 pub mod permission_categories;
 pub mod permission_collections;
 pub mod permission_values;
 pub mod permissions;
 pub mod poll_options;
+pub mod poll_runoff_results;
 pub mod poll_votes;
 pub mod polls;
 pub mod posts;
 pub mod private_messages;
+pub mod profile_post_comments;
 pub mod profile_posts;
+pub mod push_devices;
+pub mod push_subscriptions;
+pub mod rate_limit_exemptions;
+pub mod rate_limit_policies;
 pub mod reaction_types;
 pub mod report_reasons;
 pub mod reports;
+pub mod saved_searches;
 pub mod sessions;
 pub mod setting_history;
 pub mod settings;
 pub mod tag_forums;
 pub mod tags;
+pub mod theme_revisions;
 pub mod themes;
+pub mod thread_permissions;
 pub mod thread_read;
 pub mod thread_tags;
 pub mod threads;
@@ -52,16 +81,24 @@ pub mod ugc_deletions;
 pub mod ugc_reactions;
 pub mod ugc_revisions;
 pub mod unfurl_cache;
+pub mod upload_session_parts;
+pub mod upload_sessions;
 pub mod user_2fa;
 pub mod user_avatars;
 pub mod user_badges;
 pub mod user_bans;
 pub mod user_follows;
 pub mod user_groups;
+pub mod user_ignores;
 pub mod user_name_history;
 pub mod user_names;
 pub mod user_social_links;
+pub mod user_uploads;
 pub mod user_warnings;
 pub mod users;
+pub mod video_transcodes;
+pub mod watched_forums;
 pub mod watched_threads;
+pub mod webhook_deliveries;
+pub mod webhooks;
 pub mod word_filters;
@@ -13,6 +13,7 @@ pub struct Model {
     pub target_id: i32,
     pub reason: Option<String>,
     pub metadata: Option<Json>,
+    pub request_id: Option<String>,
     pub created_at: DateTime,
 }
 
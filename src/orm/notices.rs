@@ -0,0 +1,64 @@
+//! SeaORM Entity for notices table
+//!
+//! A notice shown across the site or in one forum.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "notices")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub title: String,
+    /// BBCode
+    pub body: String,
+    /// info, warning or critical
+    pub style: String,
+    /// None for a site-wide banner
+    pub forum_id: Option<i32>,
+    pub starts_at: Option<DateTime>,
+    pub ends_at: Option<DateTime>,
+    pub is_dismissible: bool,
+    /// Whether members are asked to acknowledge it, which is recorded
+    pub requires_acknowledgment: bool,
+    pub is_active: bool,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::forums::Entity",
+        from = "Column::ForumId",
+        to = "super::forums::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Forum,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::CreatedBy",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Creator,
+    #[sea_orm(has_many = "super::notice_dismissals::Entity")]
+    Dismissals,
+}
+
+impl Related<super::forums::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Forum.def()
+    }
+}
+
+impl Related<super::notice_dismissals::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Dismissals.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -12,6 +12,7 @@ pub struct Model {
     pub in_app: bool,
     pub email: bool,
     pub frequency: String,
+    pub push: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -20,6 +20,12 @@ pub struct Model {
     pub is_emailed: bool,
     pub created_at: DateTime,
     pub read_at: Option<DateTime>,
+    /// Target shared by events that collapse into this notification
+    pub group_key: Option<String>,
+    /// Number of different users behind the events
+    pub actor_count: i32,
+    /// Hidden until this time, then pushed again
+    pub snoozed_until: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
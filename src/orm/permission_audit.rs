@@ -0,0 +1,37 @@
+//! SeaORM Entity for permission_audit table
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "permission_audit")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub actor_id: Option<i32>,
+    pub change_type: String,
+    pub target_id: i32,
+    pub before: Json,
+    pub after: Json,
+    pub request_id: Option<String>,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::ActorId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Actor,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Actor.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -9,6 +9,8 @@ pub struct Model {
     pub id: i32,
     pub group_id: Option<i32>,
     pub user_id: Option<i32>,
+    /// When the collection lapses; None if it never does
+    pub expires_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
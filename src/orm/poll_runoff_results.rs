@@ -0,0 +1,37 @@
+//! SeaORM Entity for poll_runoff_results table
+//!
+//! The instant-runoff count of a ranked poll, taken when it closed.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "poll_runoff_results")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub poll_id: i32,
+    /// The rounds of the count, as `runoff::Round`s
+    pub rounds: Json,
+    pub winner_option_id: Option<i32>,
+    pub ballot_count: i32,
+    pub tabulated_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::polls::Entity",
+        from = "Column::PollId",
+        to = "super::polls::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Poll,
+}
+
+impl Related<super::polls::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Poll.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
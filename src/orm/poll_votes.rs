@@ -10,6 +10,8 @@ pub struct Model {
     pub poll_id: i32,
     pub option_id: i32,
     pub user_id: i32,
+    /// Position on a ranked ballot, 1 for the first preference
+    pub rank: Option<i32>,
     pub created_at: DateTime,
 }
 
@@ -11,7 +11,13 @@ pub struct Model {
     pub question: String,
     pub max_choices: i32,
     pub allow_change_vote: bool,
-    pub show_results_before_vote: bool,
+    /// choice, or ranked for instant-runoff polls
+    pub poll_type: String,
+    /// When results are shown: always, after_vote or after_close
+    pub results_visibility: String,
+    /// Whether everyone who can see the results can see who voted for what
+    pub public_votes: bool,
+    /// Voting stops at this time
     pub closes_at: Option<DateTime>,
     pub created_at: DateTime,
 }
@@ -5,6 +5,8 @@ pub use super::attachment_thumbnails::Entity as AttachmentThumbnails;
 pub use super::attachments::Entity as Attachments;
 pub use super::badges::Entity as Badges;
 pub use super::chat_messages::Entity as ChatMessages;
+pub use super::chat_room_members::Entity as ChatRoomMembers;
+pub use super::chat_room_permissions::Entity as ChatRoomPermissions;
 pub use super::chat_rooms::Entity as ChatRooms;
 pub use super::forum_permissions::Entity as ForumPermissions;
 pub use super::forums::Entity as Forums;
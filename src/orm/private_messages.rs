@@ -11,6 +11,39 @@ pub struct Model {
     pub ugc_id: i32,
     pub user_id: Option<i32>,
     pub created_at: DateTime,
+    pub kind: MessageKind,
+}
+
+/// Whether a message was written by a participant or records a change to the conversation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Default)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+pub enum MessageKind {
+    /// Written by its author.
+    #[sea_orm(string_value = "message")]
+    #[default]
+    Message,
+    /// A participant was invited.
+    #[sea_orm(string_value = "invited")]
+    Invited,
+    /// A participant was removed by the owner.
+    #[sea_orm(string_value = "removed")]
+    Removed,
+    /// A participant left.
+    #[sea_orm(string_value = "left")]
+    Left,
+    /// Ownership passed to another participant.
+    #[sea_orm(string_value = "owner_changed")]
+    OwnerChanged,
+    /// The conversation title changed.
+    #[sea_orm(string_value = "renamed")]
+    Renamed,
+}
+
+impl MessageKind {
+    /// Whether the message was generated by the forum rather than written.
+    pub fn is_system(&self) -> bool {
+        !matches!(self, MessageKind::Message)
+    }
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
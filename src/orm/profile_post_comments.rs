@@ -0,0 +1,70 @@
+//! SeaORM Entity for profile_post_comments table
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "profile_post_comments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub profile_post_id: i32,
+    pub author_id: Option<i32>,
+    pub ugc_id: i32,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::profile_posts::Entity",
+        from = "Column::ProfilePostId",
+        to = "super::profile_posts::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    ProfilePost,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::AuthorId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Author,
+    #[sea_orm(
+        belongs_to = "super::ugc::Entity",
+        from = "Column::UgcId",
+        to = "super::ugc::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Ugc,
+    #[sea_orm(
+        belongs_to = "super::user_names::Entity",
+        from = "Column::AuthorId",
+        to = "super::user_names::Column::UserId",
+        on_update = "NoAction",
+        on_delete = "NoAction"
+    )]
+    AuthorName,
+}
+
+impl Related<super::profile_posts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProfilePost.def()
+    }
+}
+
+impl Related<super::ugc::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Ugc.def()
+    }
+}
+
+impl Related<super::user_names::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AuthorName.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -55,6 +55,8 @@ pub enum Relation {
         on_delete = "NoAction"
     )]
     AuthorAvatar,
+    #[sea_orm(has_many = "super::profile_post_comments::Entity")]
+    Comments,
 }
 
 impl Related<super::users::Entity> for Entity {
@@ -63,6 +65,12 @@ impl Related<super::users::Entity> for Entity {
     }
 }
 
+impl Related<super::profile_post_comments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Comments.def()
+    }
+}
+
 impl Related<super::ugc::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Ugc.def()
@@ -0,0 +1,74 @@
+//! SeaORM Entity for push_devices table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Push service a mobile device is reached through.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    utoipa::ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(8))")]
+#[serde(rename_all = "lowercase")]
+pub enum PushPlatform {
+    /// Firebase Cloud Messaging, for Android.
+    #[sea_orm(string_value = "fcm")]
+    Fcm,
+    /// Apple Push Notification service, for iOS.
+    #[sea_orm(string_value = "apns")]
+    Apns,
+}
+
+impl PushPlatform {
+    /// Name shown to users
+    pub fn label(&self) -> &'static str {
+        match self {
+            PushPlatform::Fcm => "Android",
+            PushPlatform::Apns => "iOS",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "push_devices")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub user_id: i32,
+    pub platform: PushPlatform,
+    /// Registration token from the push service
+    #[sea_orm(column_type = "Text", unique)]
+    pub token: String,
+    pub name: Option<String>,
+    pub is_enabled: bool,
+    pub created_at: DateTime,
+    pub last_used_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,48 @@
+//! SeaORM Entity for rate_limit_exemptions table
+//!
+//! Groups whose members a route class doesn't limit.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "rate_limit_exemptions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub route_class: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub group_id: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::rate_limit_policies::Entity",
+        from = "Column::RouteClass",
+        to = "super::rate_limit_policies::Column::RouteClass",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Policy,
+    #[sea_orm(
+        belongs_to = "super::groups::Entity",
+        from = "Column::GroupId",
+        to = "super::groups::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Group,
+}
+
+impl Related<super::rate_limit_policies::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Policy.def()
+    }
+}
+
+impl Related<super::groups::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Group.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
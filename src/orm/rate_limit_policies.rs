@@ -0,0 +1,32 @@
+//! SeaORM Entity for rate_limit_policies table
+//!
+//! The request budget of each route class, such as posting or search.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "rate_limit_policies")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub route_class: String,
+    pub max_requests: i32,
+    pub window_seconds: i32,
+    /// Requests that may come back to back before the rest are spaced out
+    pub burst: i32,
+    pub updated_at: DateTime,
+    pub updated_by: Option<i32>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::rate_limit_exemptions::Entity")]
+    Exemptions,
+}
+
+impl Related<super::rate_limit_exemptions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Exemptions.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
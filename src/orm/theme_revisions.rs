@@ -0,0 +1,44 @@
+//! SeaORM Entity for theme_revisions table
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "theme_revisions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub theme_id: i32,
+    /// Matches `themes.version` at the time this CSS was saved
+    pub version: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub css_variables: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub css_custom: Option<String>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::themes::Entity",
+        from = "Column::ThemeId",
+        to = "super::themes::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Theme,
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::CreatedBy",
+        to = "super::users::Column::Id"
+    )]
+    Creator,
+}
+
+impl Related<super::themes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Theme.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -25,6 +25,8 @@ pub struct Model {
     pub created_by: Option<i32>,
     /// Parent theme ID for inheritance (child themes inherit parent's CSS)
     pub parent_id: Option<i32>,
+    /// Bumped each time the theme's CSS is saved; see theme_revisions
+    pub version: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,46 @@
+//! SeaORM Entity for thread_permissions table
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "thread_permissions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub thread_id: i32,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub collection_id: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::permission_collections::Entity",
+        from = "Column::CollectionId",
+        to = "super::permission_collections::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    PermissionCollections,
+    #[sea_orm(
+        belongs_to = "super::threads::Entity",
+        from = "Column::ThreadId",
+        to = "super::threads::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Threads,
+}
+
+impl Related<super::permission_collections::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PermissionCollections.def()
+    }
+}
+
+impl Related<super::threads::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Threads.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
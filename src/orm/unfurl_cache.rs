@@ -17,6 +17,10 @@ pub struct Model {
     pub fetched_at: DateTime,
     pub error_message: Option<String>,
     pub created_at: DateTime,
+    pub author_name: Option<String>,
+    /// oEmbed type: photo, video, rich or link
+    pub embed_type: Option<String>,
+    pub expires_at: DateTime,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
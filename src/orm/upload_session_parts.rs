@@ -0,0 +1,37 @@
+//! SeaORM Entity for upload_session_parts table
+//!
+//! Parts of a chunked upload the storage backend has accepted.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "upload_session_parts")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub upload_id: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub part_number: i32,
+    pub size: i32,
+    #[sea_orm(column_type = "Text")]
+    pub e_tag: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::upload_sessions::Entity",
+        from = "Column::UploadId",
+        to = "super::upload_sessions::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Session,
+}
+
+impl Related<super::upload_sessions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Session.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
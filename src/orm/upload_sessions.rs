@@ -0,0 +1,56 @@
+//! SeaORM Entity for upload_sessions table
+//!
+//! A chunked upload in progress. Parts are staged in the storage backend's
+//! multipart upload until the client finalizes the session.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "upload_sessions")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub user_id: i32,
+    #[sea_orm(column_type = "Text")]
+    pub filename: String,
+    pub mime: String,
+    pub filesize: i64,
+    pub hash: String,
+    /// Key the parts are joined under, unique to the session. The file only
+    /// moves to its content address once its hash is verified.
+    #[sea_orm(column_type = "Text")]
+    pub storage_key: String,
+    #[sea_orm(column_type = "Text")]
+    pub storage_upload_id: String,
+    pub chunk_size: i32,
+    pub created_at: DateTime,
+    pub expires_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(has_many = "super::upload_session_parts::Entity")]
+    Parts,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::upload_session_parts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Parts.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
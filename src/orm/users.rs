@@ -13,11 +13,20 @@ pub struct Model {
     pub password_cipher: Cipher,
     pub failed_login_attempts: i32,
     pub locked_until: Option<DateTime>,
+    pub last_failed_login_at: Option<DateTime>,
     pub email: Option<String>,
     pub email_verified: bool,
     pub posts_per_page: i32,
     pub theme: Option<String>,
     pub theme_auto: bool,
+    /// Dark theme used in auto mode, where `theme` is the light one
+    pub theme_dark: Option<String>,
+    /// Whether auto mode goes by the time of day rather than the system setting
+    pub theme_schedule: bool,
+    /// Minutes after local midnight when the scheduled dark theme starts
+    pub theme_dark_start: i16,
+    /// Minutes after local midnight when the scheduled dark theme ends
+    pub theme_dark_end: i16,
     pub bio: Option<String>,
     pub location: Option<String>,
     pub website_url: Option<String>,
@@ -32,11 +41,14 @@ pub struct Model {
     pub last_activity_at: Option<DateTimeWithTimeZone>,
     pub show_online: bool,
     pub reputation_score: i32,
-    pub allow_profile_posts: bool,
+    pub profile_post_privacy: String,
     pub follower_count: i32,
     pub following_count: i32,
     pub first_post_approved: bool,
     pub default_chat_room: Option<i32>,
+    pub avatar_source: AvatarSource,
+    /// Whether the member accepts announcement emails
+    pub email_announcements: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, EnumIter, DeriveActiveEnum)]
@@ -50,6 +62,22 @@ pub enum ApprovalStatus {
     Rejected,
 }
 
+/// Where a user's avatar image comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Default)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+pub enum AvatarSource {
+    /// An image the user uploaded, stored through `user_avatars`.
+    #[sea_orm(string_value = "upload")]
+    #[default]
+    Upload,
+    /// Gravatar image derived from the user's email address.
+    #[sea_orm(string_value = "gravatar")]
+    Gravatar,
+    /// A stock image from the admin gallery, also stored through `user_avatars`.
+    #[sea_orm(string_value = "gallery")]
+    Gallery,
+}
+
 #[derive(Debug, Clone, PartialEq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "password_cipher")]
 pub enum Cipher {
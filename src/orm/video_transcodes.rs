@@ -0,0 +1,68 @@
+//! SeaORM Entity for video_transcodes table
+//!
+//! Queue of uploaded videos waiting to be converted into streamable
+//! renditions, and the outcome once they have been.
+
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "video_transcodes")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub attachment_id: i32,
+    pub status: TranscodeStatus,
+    pub attempts: i32,
+    pub duration_ms: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub error: Option<String>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+/// Where a video is in the transcode queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Default, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum TranscodeStatus {
+    /// Waiting for a worker.
+    #[sea_orm(string_value = "pending")]
+    #[default]
+    Pending,
+    /// Claimed by a worker.
+    #[sea_orm(string_value = "processing")]
+    Processing,
+    /// Renditions and poster are available.
+    #[sea_orm(string_value = "ready")]
+    Ready,
+    /// Gave up after too many attempts; the original is still served.
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+
+impl TranscodeStatus {
+    /// Whether the video is still waiting on the queue.
+    pub fn is_processing(&self) -> bool {
+        matches!(self, TranscodeStatus::Pending | TranscodeStatus::Processing)
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::attachments::Entity",
+        from = "Column::AttachmentId",
+        to = "super::attachments::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Attachment,
+}
+
+impl Related<super::attachments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachment.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,37 @@
+//! SeaORM Entity for webhooks table
+//!
+//! Admin-configured URLs that receive signed JSON payloads for events.
+
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "webhooks")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    #[sea_orm(column_type = "Text")]
+    pub url: String,
+    /// Key for the HMAC-SHA256 signature of each delivery
+    pub secret: String,
+    /// Space separated event names
+    #[sea_orm(column_type = "Text")]
+    pub events: String,
+    pub is_active: bool,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::webhook_deliveries::Entity")]
+    Deliveries,
+}
+
+impl Related<super::webhook_deliveries::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Deliveries.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -54,6 +54,25 @@ pub struct Model {
     pub created_at: DateTime,
     #[sea_orm(column_type = "Text", nullable)]
     pub notes: Option<String>,
+    pub applies_to_posts: bool,
+    pub applies_to_titles: bool,
+    pub applies_to_usernames: bool,
+    pub applies_to_chat: bool,
+    pub applies_to_signatures: bool,
+}
+
+impl Model {
+    /// Returns true if this filter applies to content of the given kind
+    pub fn applies_to(&self, scope: crate::word_filter::FilterScope) -> bool {
+        use crate::word_filter::FilterScope;
+        match scope {
+            FilterScope::Posts => self.applies_to_posts,
+            FilterScope::ThreadTitles => self.applies_to_titles,
+            FilterScope::Usernames => self.applies_to_usernames,
+            FilterScope::Chat => self.applies_to_chat,
+            FilterScope::Signatures => self.applies_to_signatures,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
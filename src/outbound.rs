@@ -0,0 +1,234 @@
+//! Outbound links
+//!
+//! With `[outbound_links] enabled`, links to other sites in posts, whether
+//! from `[url]` tags or unfurled bare URLs, are sent through `/goto?url=`,
+//! which counts the click for the thread's author and, for domains that
+//! aren't trusted, warns the reader before they leave. The address is signed
+//! with [`crate::signing`] keyed by `SECRET_KEY` along with the thread, so
+//! `/goto` can't be used to dress up arbitrary links as ours or to inflate
+//! another thread's counts.
+//!
+//! Post HTML is cached without this, and links are rewritten as the page is
+//! rendered, so changing the settings takes effect at once.
+
+use crate::app_config::OutboundLinksConfig;
+use crate::db::get_db_pool;
+use crate::signing::Signer;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use sea_orm::{ConnectionTrait, DbBackend, DbErr, FromQueryResult, Statement};
+
+/// Links made from `[url]` tags, and the links of unfurled bare URLs, in
+/// BBCode. The preview itself is fetched from `data-url` and is left alone.
+static LINK_HREF: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(<a class="(?:bbCode tagUrl" rel="nofollow|unfurl-link)" href=")([^"]*)""#)
+        .unwrap()
+});
+
+static SIGNER: Lazy<Signer> = Lazy::new(|| Signer::from_secret_key("outbound links"));
+
+fn message(thread_id: i32, url: &str) -> String {
+    format!("goto:{}:{}", thread_id, url)
+}
+
+/// Whether `signature` was made for `url` posted in `thread_id`.
+pub fn verify(thread_id: i32, url: &str, signature: &str) -> bool {
+    SIGNER.verify(&message(thread_id, url), signature)
+}
+
+/// Address sending a reader to `url` by way of `/goto`.
+pub fn goto_url(thread_id: i32, url: &str) -> String {
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("url", url)
+        .append_pair("thread", &thread_id.to_string())
+        .append_pair("sig", &SIGNER.sign(&message(thread_id, url)))
+        .finish();
+    format!("/goto?{}", query)
+}
+
+/// Whether `host` is one of `domains` or a subdomain of one.
+pub fn is_trusted(host: &str, domains: &[String]) -> bool {
+    let host = host.to_ascii_lowercase();
+    domains.iter().any(|domain| {
+        let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+        host == domain || host.ends_with(&format!(".{}", domain))
+    })
+}
+
+/// Host of a link to another site, or None for links to `own_host`.
+fn external_host(href: &str, own_host: Option<&str>) -> Option<String> {
+    let url = url::Url::parse(href).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = url.host_str()?;
+    if Some(host) == own_host {
+        return None;
+    }
+    Some(host.to_string())
+}
+
+/// An attribute value as the browser reads it. BBCode escapes the text links
+/// are made from, so an address with a query has `&amp;` in its `href`.
+fn unescape_href(href: &str) -> String {
+    href.replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Send links to other sites in a post's HTML through `/goto`. Nothing is
+/// changed when the feature is off, and links to trusted domains are kept
+/// when clicks aren't counted, as there'd be nothing for `/goto` to do.
+pub fn rewrite_links(html: &str, thread_id: i32, config: &OutboundLinksConfig) -> String {
+    if !config.enabled || !(html.contains("tagUrl") || html.contains("unfurl-link")) {
+        return html.to_string();
+    }
+
+    let base_url = crate::notifications::dispatcher::get_base_url();
+    let own_host = url::Url::parse(&base_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string));
+
+    LINK_HREF
+        .replace_all(html, |caps: &Captures| {
+            let href = unescape_href(&caps[2]);
+            match external_host(&href, own_host.as_deref()) {
+                Some(host)
+                    if config.track_clicks || !is_trusted(&host, &config.trusted_domains) =>
+                {
+                    format!(
+                        r#"{}{}""#,
+                        &caps[1],
+                        goto_url(thread_id, &href).replace('&', "&amp;")
+                    )
+                }
+                _ => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Count a click on `url` in `thread_id`.
+pub async fn record_click(thread_id: i32, url: &str) -> Result<(), DbErr> {
+    get_db_pool()
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "INSERT INTO link_clicks (thread_id, url, clicks) VALUES ($1, $2, 1)
+             ON CONFLICT (thread_id, url) DO UPDATE SET
+                clicks = link_clicks.clicks + 1,
+                last_clicked_at = NOW()",
+            vec![thread_id.into(), url.into()],
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Clicks counted on a link
+#[derive(Debug, FromQueryResult)]
+pub struct LinkClicks {
+    pub url: String,
+    pub clicks: i32,
+    pub last_clicked_at: DateTime<Utc>,
+}
+
+/// Links clicked in a thread, most clicked first.
+pub async fn thread_clicks(thread_id: i32) -> Result<Vec<LinkClicks>, DbErr> {
+    LinkClicks::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT url, clicks, last_clicked_at FROM link_clicks
+         WHERE thread_id = $1
+         ORDER BY clicks DESC, last_clicked_at DESC",
+        vec![thread_id.into()],
+    ))
+    .all(get_db_pool())
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(track_clicks: bool) -> OutboundLinksConfig {
+        OutboundLinksConfig {
+            enabled: true,
+            track_clicks,
+            warn_untrusted: true,
+            trusted_domains: vec!["example.org".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_signature() {
+        let sig = SIGNER.sign(&message(5, "https://example.com/"));
+        assert!(verify(5, "https://example.com/", &sig));
+        assert!(!verify(6, "https://example.com/", &sig));
+        assert!(!verify(5, "https://example.net/", &sig));
+        assert!(!verify(5, "https://example.com/", ""));
+    }
+
+    #[test]
+    fn test_is_trusted() {
+        let domains = vec!["example.org".to_string()];
+        assert!(is_trusted("example.org", &domains));
+        assert!(is_trusted("www.Example.org", &domains));
+        assert!(!is_trusted("badexample.org", &domains));
+        assert!(!is_trusted("example.org.evil.com", &domains));
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let html = r#"<a class="bbCode tagUrl" rel="nofollow" href="https://example.com/a?b=1&c=2">link</a>"#;
+        let rewritten = rewrite_links(html, 7, &config(true));
+        assert!(rewritten.starts_with(
+            r#"<a class="bbCode tagUrl" rel="nofollow" href="/goto?url=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1%26c%3D2&amp;thread=7&amp;sig="#
+        ));
+        assert!(rewritten.ends_with(">link</a>"));
+
+        // Nothing changes with the feature off
+        let off = OutboundLinksConfig::default();
+        assert_eq!(rewrite_links(html, 7, &off), html);
+    }
+
+    /// The address and signature a rewritten link sends to `/goto`.
+    fn goto_target(html: &str) -> (String, String) {
+        let href = LINK_HREF.captures(html).expect("no link")[2].replace("&amp;", "&");
+        let query = href.strip_prefix("/goto?").expect("not rewritten");
+        let pairs: std::collections::HashMap<_, _> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        (pairs["url"].clone(), pairs["sig"].clone())
+    }
+
+    #[test]
+    fn test_rewrite_parsed_bbcode() {
+        let html = crate::bbcode::parse("[url]https://example.com/a?b=1&c=2[/url]");
+        let (url, sig) = goto_target(&rewrite_links(&html, 7, &config(true)));
+        assert_eq!(url, "https://example.com/a?b=1&c=2");
+        assert!(verify(7, &url, &sig));
+
+        // Bare URLs unfurl, and their link goes through `/goto` too
+        let html = crate::bbcode::parse("See https://example.com/x?y=1&z=2");
+        assert!(html.contains("unfurl-link"));
+        let rewritten = rewrite_links(&html, 7, &config(true));
+        let (url, sig) = goto_target(&rewritten);
+        assert_eq!(url, "https://example.com/x?y=1&z=2");
+        assert!(verify(7, &url, &sig));
+        // The preview is still fetched for the site itself
+        let container = &html[..html.find(r#"<a class="unfurl-link""#).unwrap()];
+        assert!(rewritten.starts_with(container));
+    }
+
+    #[test]
+    fn test_trusted_links_kept_without_tracking() {
+        let trusted =
+            r#"<a class="bbCode tagUrl" rel="nofollow" href="https://www.example.org/">x</a>"#;
+        assert_eq!(rewrite_links(trusted, 7, &config(false)), trusted);
+        assert_ne!(rewrite_links(trusted, 7, &config(true)), trusted);
+
+        let other = r#"<a class="bbCode tagUrl" rel="nofollow" href="ftp://example.com/">x</a>"#;
+        assert_eq!(rewrite_links(other, 7, &config(true)), other);
+    }
+}
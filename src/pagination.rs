@@ -0,0 +1,75 @@
+//! Cursor pagination
+//!
+//! With `OFFSET`, the database reads and throws away every row before the
+//! page, so deep pages get slower the further in they are. A cursor holds the
+//! sort key of the last item shown instead, and the next page is read from
+//! the index right after it. Cursors are opaque to clients: the key is JSON,
+//! base64url-encoded, and only has to survive the round trip.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Where a page starts: after skipping some items, or after an item's key.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PageStart<K> {
+    Offset(u64),
+    After(K),
+}
+
+/// A cursor for the position after the item with this sort key.
+pub fn encode<K: Serialize>(key: &K) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(key).unwrap_or_default())
+}
+
+/// The sort key in a cursor, or `None` if it isn't one of ours.
+pub fn decode<K: DeserializeOwned>(cursor: &str) -> Option<K> {
+    let json = URL_SAFE_NO_PAD.decode(cursor.trim()).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+/// Split a page fetched with one item more than `limit`: the items to show,
+/// and the cursor for the next page when that extra item was there.
+pub fn split_page<T, K: Serialize>(
+    mut items: Vec<T>,
+    limit: usize,
+    key: impl Fn(&T) -> K,
+) -> (Vec<T>, Option<String>) {
+    if items.len() <= limit {
+        return (items, None);
+    }
+    items.truncate(limit);
+    let next_cursor = items.last().map(|item| encode(&key(item)));
+    (items, next_cursor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let key = (true, "2026-01-02T03:04:05".to_string(), 42);
+        let cursor = encode(&key);
+        assert!(cursor
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(decode::<(bool, String, i32)>(&cursor), Some(key));
+    }
+
+    #[test]
+    fn test_foreign_cursor() {
+        assert_eq!(decode::<(i32, i32)>("not a cursor"), None);
+        assert_eq!(decode::<(i32, i32)>(&encode(&"text")), None);
+    }
+
+    #[test]
+    fn test_split_page() {
+        let (items, next) = split_page(vec![1, 2, 3], 3, |n| *n);
+        assert_eq!((items, next), (vec![1, 2, 3], None));
+
+        let (items, next) = split_page(vec![1, 2, 3, 4], 3, |n| *n);
+        assert_eq!(items, vec![1, 2, 3]);
+        assert_eq!(next.and_then(|c| decode::<i32>(&c)), Some(3));
+    }
+}
@@ -0,0 +1,459 @@
+//! Permission audit log
+//!
+//! Every change to permission values, group membership and forum, thread or
+//! chat room permissions is written to `permission_audit` with who made it and
+//! the state before and after, so privilege escalations can be traced.
+//! Callers take a [`Snapshot`] of what they are about to change, make the
+//! change, take another and pass both to [`record`], which stores nothing
+//! if they match.
+
+use crate::orm::permission_audit;
+use chrono::NaiveDateTime;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ConnectionTrait, DbBackend, DbErr, FromQueryResult,
+    Statement, Value,
+};
+use std::collections::BTreeMap;
+
+/// State of whatever a change touched, as name → value. Permission values
+/// map the permission label to "yes", "no" or "never" (unset permissions are
+/// left out), and memberships map each group or member name to "member".
+pub type Snapshot = BTreeMap<String, String>;
+
+/// What a change touched. The target ID is a group, user, forum or chat room
+/// depending on the kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// A group's global permission values
+    GroupPermissions,
+    /// Global permission values set on a single member
+    UserPermissions,
+    /// The members of a group, recorded when it is deleted
+    GroupMembers,
+    /// The groups a member belongs to
+    UserGroups,
+    /// Per-group permission values on a forum
+    ForumPermissions,
+    /// Per-group permission values on a chat room
+    ChatRoomPermissions,
+    /// Per-group permission values on a thread
+    ThreadPermissions,
+}
+
+impl Change {
+    pub fn all() -> [Change; 7] {
+        [
+            Change::GroupPermissions,
+            Change::UserPermissions,
+            Change::GroupMembers,
+            Change::UserGroups,
+            Change::ForumPermissions,
+            Change::ChatRoomPermissions,
+            Change::ThreadPermissions,
+        ]
+    }
+
+    /// Value of `permission_audit.change_type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Change::GroupPermissions => "group_permissions",
+            Change::UserPermissions => "user_permissions",
+            Change::GroupMembers => "group_members",
+            Change::UserGroups => "user_groups",
+            Change::ForumPermissions => "forum_permissions",
+            Change::ChatRoomPermissions => "chat_room_permissions",
+            Change::ThreadPermissions => "thread_permissions",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|change| change.as_str() == name)
+    }
+
+    /// Name shown in the admin panel
+    pub fn label(&self) -> &'static str {
+        match self {
+            Change::GroupPermissions => "Group permissions",
+            Change::UserPermissions => "Member's permissions",
+            Change::GroupMembers => "Group members",
+            Change::UserGroups => "Member's groups",
+            Change::ForumPermissions => "Forum permissions",
+            Change::ChatRoomPermissions => "Chat room permissions",
+            Change::ThreadPermissions => "Thread permissions",
+        }
+    }
+
+    /// Admin page for the target
+    pub fn target_url(&self, target_id: i32) -> String {
+        match self {
+            Change::GroupPermissions | Change::GroupMembers => {
+                format!("/admin/groups/{}/edit", target_id)
+            }
+            Change::UserPermissions => format!("/admin/users/{}/permissions", target_id),
+            Change::UserGroups => format!("/admin/users/{}/edit", target_id),
+            Change::ForumPermissions => format!("/admin/forums/{}/permissions", target_id),
+            Change::ChatRoomPermissions => format!("/admin/chat-rooms/{}/permissions", target_id),
+            Change::ThreadPermissions => format!("/admin/threads/{}/permissions", target_id),
+        }
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+struct SnapshotRow {
+    key: String,
+    value: String,
+}
+
+async fn snapshot<C: ConnectionTrait>(conn: &C, sql: &str, id: i32) -> Result<Snapshot, DbErr> {
+    let rows = SnapshotRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        sql,
+        vec![id.into()],
+    ))
+    .all(conn)
+    .await?;
+    Ok(rows.into_iter().map(|row| (row.key, row.value)).collect())
+}
+
+/// Values set in one permission collection, and when it expires under
+/// "expires at" if it does.
+pub async fn collection<C: ConnectionTrait>(
+    conn: &C,
+    collection_id: i32,
+) -> Result<Snapshot, DbErr> {
+    snapshot(
+        conn,
+        r#"SELECT p.label AS key, pv.value::TEXT AS value
+            FROM permission_values pv
+            JOIN permissions p ON p.id = pv.permission_id
+            WHERE pv.collection_id = $1
+            UNION ALL
+            SELECT 'expires at' AS key, TO_CHAR(expires_at, 'YYYY-MM-DD HH24:MI') AS value
+            FROM permission_collections
+            WHERE id = $1 AND expires_at IS NOT NULL"#,
+        collection_id,
+    )
+    .await
+}
+
+/// A group's global permission values, leaving out its forum, thread and chat
+/// room overrides.
+pub async fn group_permissions<C: ConnectionTrait>(
+    conn: &C,
+    group_id: i32,
+) -> Result<Snapshot, DbErr> {
+    snapshot(
+        conn,
+        r#"SELECT p.label AS key, pv.value::TEXT AS value
+            FROM permission_collections pc
+            JOIN permission_values pv ON pv.collection_id = pc.id
+            JOIN permissions p ON p.id = pv.permission_id
+            WHERE pc.group_id = $1
+                AND NOT EXISTS (SELECT 1 FROM forum_permissions fp WHERE fp.collection_id = pc.id)
+                AND NOT EXISTS (SELECT 1 FROM chat_room_permissions cp WHERE cp.collection_id = pc.id)
+                AND NOT EXISTS (SELECT 1 FROM thread_permissions tp WHERE tp.collection_id = pc.id)"#,
+        group_id,
+    )
+    .await
+}
+
+/// Names of a group's members.
+pub async fn group_members<C: ConnectionTrait>(conn: &C, group_id: i32) -> Result<Snapshot, DbErr> {
+    snapshot(
+        conn,
+        r#"SELECT n.name AS key, 'member' AS value
+            FROM user_groups ug
+            JOIN user_names n ON n.user_id = ug.user_id
+            WHERE ug.group_id = $1"#,
+        group_id,
+    )
+    .await
+}
+
+/// Names of the groups a member belongs to.
+pub async fn user_groups<C: ConnectionTrait>(conn: &C, user_id: i32) -> Result<Snapshot, DbErr> {
+    snapshot(
+        conn,
+        r#"SELECT g.label AS key, 'member' AS value
+            FROM user_groups ug
+            JOIN groups g ON g.id = ug.group_id
+            WHERE ug.user_id = $1"#,
+        user_id,
+    )
+    .await
+}
+
+/// Permission values set on a forum, keyed "group: permission", or
+/// "member: permission" for those given to its moderators.
+pub async fn forum<C: ConnectionTrait>(conn: &C, forum_id: i32) -> Result<Snapshot, DbErr> {
+    snapshot(
+        conn,
+        r#"SELECT COALESCE(g.label, n.name) || ': ' || p.label AS key, pv.value::TEXT AS value
+            FROM forum_permissions fp
+            JOIN permission_collections pc ON pc.id = fp.collection_id
+            LEFT JOIN groups g ON g.id = pc.group_id
+            LEFT JOIN user_names n ON n.user_id = pc.user_id
+            JOIN permission_values pv ON pv.collection_id = pc.id
+            JOIN permissions p ON p.id = pv.permission_id
+            WHERE fp.forum_id = $1"#,
+        forum_id,
+    )
+    .await
+}
+
+/// Permission values set on a chat room, keyed "group: permission".
+pub async fn chat_room<C: ConnectionTrait>(conn: &C, room_id: i32) -> Result<Snapshot, DbErr> {
+    snapshot(
+        conn,
+        r#"SELECT g.label || ': ' || p.label AS key, pv.value::TEXT AS value
+            FROM chat_room_permissions cp
+            JOIN permission_collections pc ON pc.id = cp.collection_id
+            JOIN groups g ON g.id = pc.group_id
+            JOIN permission_values pv ON pv.collection_id = pc.id
+            JOIN permissions p ON p.id = pv.permission_id
+            WHERE cp.chat_room_id = $1"#,
+        room_id,
+    )
+    .await
+}
+
+/// Permission values set on a thread, keyed "group: permission".
+pub async fn thread<C: ConnectionTrait>(conn: &C, thread_id: i32) -> Result<Snapshot, DbErr> {
+    snapshot(
+        conn,
+        r#"SELECT g.label || ': ' || p.label AS key, pv.value::TEXT AS value
+            FROM thread_permissions tp
+            JOIN permission_collections pc ON pc.id = tp.collection_id
+            JOIN groups g ON g.id = pc.group_id
+            JOIN permission_values pv ON pv.collection_id = pc.id
+            JOIN permissions p ON p.id = pv.permission_id
+            WHERE tp.thread_id = $1"#,
+        thread_id,
+    )
+    .await
+}
+
+/// Record a change made by `actor_id` (None for the CLI). Returns whether
+/// anything was recorded, which it isn't when the snapshots match.
+pub async fn record<C: ConnectionTrait>(
+    conn: &C,
+    actor_id: Option<i32>,
+    change: Change,
+    target_id: i32,
+    before: &Snapshot,
+    after: &Snapshot,
+) -> Result<bool, DbErr> {
+    if before == after {
+        return Ok(false);
+    }
+
+    permission_audit::ActiveModel {
+        actor_id: Set(actor_id),
+        change_type: Set(change.as_str().to_string()),
+        target_id: Set(target_id),
+        before: Set(serde_json::json!(before)),
+        after: Set(serde_json::json!(after)),
+        request_id: Set(crate::middleware::request_id::current()),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+
+    Ok(true)
+}
+
+/// One name whose value changed. None means it wasn't there.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Difference {
+    pub key: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Names added, removed or changed between two snapshots, by name.
+pub fn diff(before: &Snapshot, after: &Snapshot) -> Vec<Difference> {
+    let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(|key| Difference {
+            key: key.clone(),
+            before: before.get(key).cloned(),
+            after: after.get(key).cloned(),
+        })
+        .collect()
+}
+
+/// Which entries to show in the audit viewer.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub change: Option<Change>,
+    /// Username of whoever made the change, case-insensitive
+    pub actor: Option<String>,
+    pub target_id: Option<i32>,
+}
+
+impl Filter {
+    fn values(&self) -> Vec<Value> {
+        vec![
+            self.change.map(|change| change.as_str().to_string()).into(),
+            self.actor.clone().into(),
+            self.target_id.into(),
+        ]
+    }
+}
+
+const FILTER_SQL: &str = r#"FROM permission_audit a
+    LEFT JOIN user_names an ON an.user_id = a.actor_id
+    WHERE ($1::TEXT IS NULL OR a.change_type = $1)
+        AND ($2::TEXT IS NULL OR LOWER(an.name) = LOWER($2))
+        AND ($3::INT IS NULL OR a.target_id = $3)"#;
+
+/// An audit log entry with the names of its actor and target.
+#[derive(Debug, FromQueryResult)]
+pub struct Entry {
+    pub id: i32,
+    pub actor_id: Option<i32>,
+    pub actor_name: Option<String>,
+    pub change_type: String,
+    pub target_id: i32,
+    /// Gone if the target has since been deleted
+    pub target_name: Option<String>,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+    pub request_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Entry {
+    pub fn change(&self) -> Option<Change> {
+        Change::from_name(&self.change_type)
+    }
+
+    pub fn differences(&self) -> Vec<Difference> {
+        let before: Snapshot = serde_json::from_value(self.before.clone()).unwrap_or_default();
+        let after: Snapshot = serde_json::from_value(self.after.clone()).unwrap_or_default();
+        diff(&before, &after)
+    }
+}
+
+/// Entries matching `filter`, newest first.
+pub async fn list<C: ConnectionTrait>(
+    conn: &C,
+    filter: &Filter,
+    limit: u64,
+    offset: u64,
+) -> Result<Vec<Entry>, DbErr> {
+    let mut values = filter.values();
+    values.push((limit as i64).into());
+    values.push((offset as i64).into());
+
+    Entry::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            r#"SELECT a.id, a.actor_id, an.name AS actor_name, a.change_type, a.target_id,
+                CASE
+                    WHEN a.change_type IN ('user_groups', 'user_permissions') THEN
+                        (SELECT name FROM user_names WHERE user_id = a.target_id LIMIT 1)
+                    WHEN a.change_type = 'forum_permissions' THEN
+                        (SELECT label FROM forums WHERE id = a.target_id)
+                    WHEN a.change_type = 'chat_room_permissions' THEN
+                        (SELECT title FROM chat_rooms WHERE id = a.target_id)
+                    WHEN a.change_type = 'thread_permissions' THEN
+                        (SELECT title FROM threads WHERE id = a.target_id)
+                    ELSE (SELECT label FROM groups WHERE id = a.target_id)
+                END AS target_name,
+                a.before, a.after, a.request_id, a.created_at
+            {}
+            ORDER BY a.created_at DESC, a.id DESC
+            LIMIT $4 OFFSET $5"#,
+            FILTER_SQL
+        ),
+        values,
+    ))
+    .all(conn)
+    .await
+}
+
+#[derive(Debug, FromQueryResult)]
+struct Count {
+    count: i64,
+}
+
+/// Number of entries matching `filter`.
+pub async fn count<C: ConnectionTrait>(conn: &C, filter: &Filter) -> Result<i64, DbErr> {
+    let row = Count::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!("SELECT COUNT(*) AS count {}", FILTER_SQL),
+        filter.values(),
+    ))
+    .one(conn)
+    .await?;
+    Ok(row.map(|row| row.count).unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(pairs: &[(&str, &str)]) -> Snapshot {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_lists_added_removed_and_changed() {
+        let before = snapshot(&[
+            ("forum.view", "yes"),
+            ("post.edit", "yes"),
+            ("post.delete", "no"),
+        ]);
+        let after = snapshot(&[
+            ("forum.view", "yes"),
+            ("post.edit", "never"),
+            ("admin.settings", "yes"),
+        ]);
+
+        let differences = diff(&before, &after);
+        assert_eq!(
+            differences,
+            vec![
+                Difference {
+                    key: "admin.settings".to_string(),
+                    before: None,
+                    after: Some("yes".to_string()),
+                },
+                Difference {
+                    key: "post.delete".to_string(),
+                    before: Some("no".to_string()),
+                    after: None,
+                },
+                Difference {
+                    key: "post.edit".to_string(),
+                    before: Some("yes".to_string()),
+                    after: Some("never".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_of_matching_snapshots_is_empty() {
+        let values = snapshot(&[("forum.view", "yes")]);
+        assert!(diff(&values, &values).is_empty());
+    }
+
+    #[test]
+    fn test_change_names_round_trip() {
+        for change in Change::all() {
+            assert_eq!(Change::from_name(change.as_str()), Some(change));
+        }
+        assert_eq!(Change::from_name("nonsense"), None);
+    }
+}
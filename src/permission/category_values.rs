@@ -56,6 +56,17 @@ impl CategoryValues {
         Self { yes, no, never }
     }
 
+    /// Combines values by precedence.
+    /// Anything set here wins; the rest is taken from `below`.
+    pub fn overlay(&self, below: &Self) -> Self {
+        let explicit = self.yes | self.no | self.never;
+        Self {
+            yes: self.yes | (below.yes & !explicit),
+            no: self.no | (below.no & !explicit),
+            never: self.never | (below.never & !explicit),
+        }
+    }
+
     pub fn set_flag(&mut self, item: u8, flag: Flag) {
         let bit: u64 = 1 << item; // 0b0001
         let not: u64 = !bit; // 0b1110
@@ -49,6 +49,18 @@ impl CollectionValues {
         Self { categories }
     }
 
+    /// Combines permission sets by precedence.
+    /// Values set here win over those of `below`.
+    pub fn overlay(&self, below: &Self) -> Self {
+        let mut categories: [CategoryValues; GROUP_LIMIT as usize] = Default::default();
+
+        for (i, values) in categories.iter_mut().enumerate() {
+            *values = self.categories[i].overlay(&below.categories[i]);
+        }
+
+        Self { categories }
+    }
+
     pub fn set_flag(&mut self, category: u8, item: u8, flag: Flag) {
         self.categories[category as usize].set_flag(item, flag)
     }
@@ -60,6 +72,21 @@ impl CollectionValues {
         (cat.yes | cat.no | cat.never) & bit != 0
     }
 
+    /// The value a permission is set to, DEFAULT if it isn't set
+    pub fn flag(&self, category: usize, item: u8) -> Flag {
+        let cat = &self.categories[category];
+        let bit: u64 = 1 << item;
+        if cat.never & bit != 0 {
+            Flag::NEVER
+        } else if cat.no & bit != 0 {
+            Flag::NO
+        } else if cat.yes & bit != 0 {
+            Flag::YES
+        } else {
+            Flag::DEFAULT
+        }
+    }
+
     /// Check if permission is explicitly granted (yes and not overridden by no/never)
     pub fn can(&self, category: usize, item: u8) -> bool {
         self.categories[category].can(item)
@@ -0,0 +1,132 @@
+//! Explaining permission checks
+//!
+//! [`PermissionData::explain`] works out which groups or overrides decided a
+//! client's permission, for the admin permission tester. Whether the
+//! permission is allowed always comes from the real checks, so the tester
+//! can't disagree with them; the rest retraces the steps they take.
+
+use super::collection_values::CollectionValues;
+use super::flag::Flag;
+use super::PermissionData;
+use crate::middleware::ClientCtx;
+
+/// Where a permission check was decided.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decider {
+    /// The client's global group and user values
+    Global,
+    /// Overrides on this forum, the given one or the nearest ancestor
+    /// setting the permission
+    Forum(i32),
+    /// Moderating this forum grants every `moderate.*` permission in it and
+    /// its children
+    ForumModerator(i32),
+}
+
+/// A value one of the client's groups, or the client themself, sets for the
+/// permission.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contribution {
+    /// None for a value set on the user
+    pub group_id: Option<i32>,
+    pub flag: Flag,
+}
+
+#[derive(Clone, Debug)]
+pub struct Explanation {
+    pub allowed: bool,
+    pub decided_by: Decider,
+    /// Values set where it was decided. Empty for forum moderators, and when
+    /// nothing sets the permission, which leaves it denied.
+    pub contributions: Vec<Contribution>,
+}
+
+/// Values `groups` and `user_id` set for the permission at `indices`, with
+/// `values` looking up what a (group, user) key sets.
+fn contributions(
+    values: impl Fn(&(i32, i32)) -> Option<CollectionValues>,
+    groups: &[i32],
+    user_id: Option<i32>,
+    indices: (u8, u8),
+) -> Vec<Contribution> {
+    let keys = groups
+        .iter()
+        .map(|group| ((*group, 0), Some(*group)))
+        .chain(user_id.map(|id| ((0, id), None)));
+
+    keys.filter_map(|(key, group_id)| {
+        let flag = values(&key)?.flag(indices.0 as usize, indices.1);
+        (flag != Flag::DEFAULT).then(|| Contribution { group_id, flag })
+    })
+    .collect()
+}
+
+impl PermissionData {
+    /// Explain `permission` for `client`, globally or in `forum_id`. None if
+    /// there is no such permission.
+    pub fn explain(
+        &self,
+        client: &ClientCtx,
+        permission: &str,
+        forum_id: Option<i32>,
+    ) -> Option<Explanation> {
+        let indices = *self.collection.dictionary.get(permission)?;
+        let allowed = match forum_id {
+            Some(forum_id) => self.can_in_forum(client, forum_id, permission),
+            None => self.can_by_indices(client, &indices),
+        };
+        let groups = client.get_groups();
+        let user_id = client.get_id();
+
+        if let Some(forum_id) = forum_id {
+            if let Some(uid) = user_id.filter(|_| permission.starts_with("moderate.")) {
+                let mut current = Some(forum_id);
+                while let Some(fid) = current {
+                    if self
+                        .forum_moderators
+                        .get(&fid)
+                        .map_or(false, |moderators| moderators.contains(&uid))
+                    {
+                        return Some(Explanation {
+                            allowed,
+                            decided_by: Decider::ForumModerator(fid),
+                            contributions: Vec::new(),
+                        });
+                    }
+                    current = self.forum_parents.get(&fid).copied().flatten();
+                }
+            }
+
+            let mut current = Some(forum_id);
+            while let Some(fid) = current {
+                if let Some(forum_perms) = self.forum_permissions.get(&fid) {
+                    let set = contributions(
+                        |key| forum_perms.get(key).map(|values| values.clone()),
+                        &groups,
+                        user_id,
+                        indices,
+                    );
+                    if !set.is_empty() {
+                        return Some(Explanation {
+                            allowed,
+                            decided_by: Decider::Forum(fid),
+                            contributions: set,
+                        });
+                    }
+                }
+                current = self.forum_parents.get(&fid).copied().flatten();
+            }
+        }
+
+        Some(Explanation {
+            allowed,
+            decided_by: Decider::Global,
+            contributions: contributions(
+                |key| self.values_at(key, chrono::Utc::now().naive_utc()),
+                &groups,
+                user_id,
+                indices,
+            ),
+        })
+    }
+}
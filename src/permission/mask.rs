@@ -2,6 +2,7 @@ use super::collection_values::CollectionValues;
 use super::GROUP_LIMIT;
 
 /// Data struct containing all permission categories as final, evaluated masks.
+#[derive(Clone, Copy, Debug)]
 pub struct Mask {
     pub categories: [u64; GROUP_LIMIT as usize],
 }
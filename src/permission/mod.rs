@@ -1,14 +1,18 @@
+pub mod audit;
 pub mod category;
 pub mod category_values;
 pub mod collection;
 pub mod collection_values;
 pub mod error;
+pub mod explain;
 pub mod flag;
 pub mod item;
 pub mod item_values;
 pub mod mask;
 pub mod resource;
+pub mod template;
 mod test;
+pub mod transfer;
 
 pub use category::Category;
 pub use category_values::CategoryValues;
@@ -27,34 +31,169 @@ pub const PERM_LIMIT: u32 = u64::BITS;
 /// Total maximum number of permissions defined as GROUP_LIMIT*PERM_LIMIT
 pub const MAX_PERMS: u32 = GROUP_LIMIT * PERM_LIMIT;
 
+/// Setting that makes the highest priority group setting a permission decide
+/// it, instead of joining every group's values
+pub const PRIORITY_WINS_SETTING: &str = "permission_priority_wins";
+
 use crate::middleware::ClientCtx;
+use chrono::NaiveDateTime;
 use dashmap::DashMap;
-use once_cell::sync::OnceCell;
 use std::collections::{HashMap, HashSet};
-use std::sync::RwLock;
-
-/// Global permission data store
-static PERMISSION_DATA: OnceCell<RwLock<PermissionData>> = OnceCell::new();
-
-/// Get a read guard to the global permission data
-pub fn get_permission_data() -> std::sync::RwLockReadGuard<'static, PermissionData> {
-    PERMISSION_DATA
-        .get()
-        .expect("Permission data not initialized")
-        .read()
-        .expect("Permission data lock poisoned")
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Most masks cached before the cache is emptied and starts over
+const MASK_CACHE_LIMIT: usize = 10_000;
+
+/// (user ID or 0, sorted group IDs, forum ID or 0, thread ID or 0)
+type MaskKey = (i32, Vec<i32>, i32, i32);
+
+/// A resolved mask and when it stops being valid because an expiring
+/// collection it may include lapses.
+#[derive(Clone, Copy, Debug)]
+struct CachedMask {
+    until: Option<NaiveDateTime>,
+    mask: mask::Mask,
 }
 
-/// Initialize the global permission data (call once at startup)
-pub fn init_permission_data(data: PermissionData) {
-    PERMISSION_DATA
-        .set(RwLock::new(data))
-        .expect("Permission data already initialized");
+/// Values of a permission collection with an expiry, applied until it lapses
+#[derive(Clone, Debug)]
+struct ExpiringValues {
+    expires_at: NaiveDateTime,
+    values: collection_values::CollectionValues,
 }
 
-/// Reload forum permissions from database
-/// Call this after modifying forum permissions via admin UI
-pub async fn reload_forum_permissions() -> Result<(), sea_orm::error::DbErr> {
+/// True if a collection expiring at `expires_at` has lapsed by `now`.
+fn lapsed(expires_at: Option<NaiveDateTime>, now: NaiveDateTime) -> bool {
+    expires_at.map_or(false, |expires_at| expires_at <= now)
+}
+
+/// Handle to the permission data of one running forum. Clones share the
+/// data, so overrides reloaded through one are seen through every other;
+/// separate handles, such as one per test, share nothing.
+#[derive(Clone, Debug, Default)]
+pub struct Permissions(Arc<RwLock<PermissionData>>);
+
+impl Permissions {
+    pub fn new(data: PermissionData) -> Self {
+        Self(Arc::new(RwLock::new(data)))
+    }
+
+    /// Read guard to the data. Don't hold it across an await.
+    pub fn read(&self) -> RwLockReadGuard<'_, PermissionData> {
+        self.0.read().expect("Permission data lock poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, PermissionData> {
+        self.0.write().expect("Permission data lock poisoned")
+    }
+
+    /// Reload forum permissions, parents and moderators from the database.
+    /// Call this after modifying forum permissions via admin UI
+    pub async fn reload_forum_permissions(&self) -> Result<(), sea_orm::error::DbErr> {
+        log::info!("Reloading forum permissions from database...");
+
+        // Clone the lookup table first (brief read lock, no await)
+        let lookup = self.read().collection.lookup.clone();
+        let forums = load_forum_permissions(&lookup).await?;
+
+        // Take the write lock only for the final update (no awaits after this)
+        let mut perm_data = self.write();
+        perm_data.forum_permissions = forums.permissions;
+        perm_data.forum_parents = forums.parents;
+        perm_data.forum_moderators = forums.moderators;
+        perm_data.invalidate_masks();
+        drop(perm_data);
+
+        log::info!("Forum permissions reloaded successfully");
+
+        Ok(())
+    }
+
+    /// Reload group display priorities from the database.
+    /// Call this after changing groups via admin UI
+    pub async fn reload_group_priorities(&self) -> Result<(), sea_orm::error::DbErr> {
+        let priorities = load_group_priorities().await?;
+
+        let mut perm_data = self.write();
+        perm_data.group_priorities = priorities;
+        perm_data.invalidate_masks();
+
+        Ok(())
+    }
+
+    /// Switch between joining the values of every group and taking each
+    /// permission from the highest priority group that sets it.
+    pub fn set_priority_wins(&self, priority_wins: bool) {
+        let mut perm_data = self.write();
+        if perm_data.priority_wins != priority_wins {
+            perm_data.priority_wins = priority_wins;
+            perm_data.invalidate_masks();
+        }
+    }
+
+    /// Reload chat room permissions from database
+    /// Call this after modifying chat room permissions via admin UI
+    pub async fn reload_chat_room_permissions(&self) -> Result<(), sea_orm::error::DbErr> {
+        log::info!("Reloading chat room permissions from database...");
+
+        let lookup = self.read().collection.lookup.clone();
+        let room_perms_map = load_chat_room_permissions(&lookup).await?;
+        self.write().chat_room_permissions = room_perms_map;
+
+        log::info!("Chat room permissions reloaded successfully");
+
+        Ok(())
+    }
+
+    /// Reload thread permissions from database
+    /// Call this after modifying thread permissions via admin UI
+    pub async fn reload_thread_permissions(&self) -> Result<(), sea_orm::error::DbErr> {
+        log::info!("Reloading thread permissions from database...");
+
+        let lookup = self.read().collection.lookup.clone();
+        let thread_perms_map = load_thread_permissions(&lookup).await?;
+
+        let mut perm_data = self.write();
+        perm_data.thread_permissions = thread_perms_map;
+        perm_data.invalidate_masks();
+        drop(perm_data);
+
+        log::info!("Thread permissions reloaded successfully");
+
+        Ok(())
+    }
+}
+
+/// Delete permission collections that have lapsed, along with their values
+/// and the forum and chat room overrides they made. Returns how many went.
+/// Global permissions stop applying at expiry without this; forum and chat
+/// room overrides need a reload afterwards.
+pub async fn delete_expired_collections<C: sea_orm::ConnectionTrait>(
+    conn: &C,
+) -> Result<u64, sea_orm::error::DbErr> {
+    use crate::orm::permission_collections;
+    use sea_orm::ColumnTrait;
+    use sea_orm::{EntityTrait, QueryFilter};
+
+    let result = permission_collections::Entity::delete_many()
+        .filter(permission_collections::Column::ExpiresAt.lte(chrono::Utc::now().naive_utc()))
+        .exec(conn)
+        .await?;
+
+    Ok(result.rows_affected)
+}
+
+/// Forum overrides, parents and moderators, as loaded together
+struct ForumData {
+    permissions: HashMap<i32, OverrideValues>,
+    parents: HashMap<i32, Option<i32>>,
+    moderators: HashMap<i32, HashSet<i32>>,
+}
+
+/// Load per-forum permission overrides with the forum tree and moderators
+async fn load_forum_permissions(
+    lookup: &DashMap<i32, (u8, u8)>,
+) -> Result<ForumData, sea_orm::error::DbErr> {
     use crate::db::get_db_pool;
     use crate::orm::forum_moderators;
     use crate::orm::forum_permissions;
@@ -65,19 +204,6 @@ pub async fn reload_forum_permissions() -> Result<(), sea_orm::error::DbErr> {
     use sea_orm::entity::*;
     use sea_orm::QueryFilter;
 
-    log::info!("Reloading forum permissions from database...");
-
-    // Clone the lookup table first (brief read lock, no await)
-    let lookup = {
-        let perm_data = PERMISSION_DATA
-            .get()
-            .expect("Permission data not initialized")
-            .read()
-            .expect("Permission data lock poisoned");
-        perm_data.collection.lookup.clone()
-    };
-
-    // Fetch all data from database (no lock held during awaits)
     let forum_perm_rows = forum_permissions::Entity::find()
         .find_with_related(permission_collections::Entity)
         .all(get_db_pool())
@@ -89,35 +215,32 @@ pub async fn reload_forum_permissions() -> Result<(), sea_orm::error::DbErr> {
         .flat_map(|(_, collections)| collections.iter().map(|pc| pc.id))
         .collect();
 
-    // Bulk fetch all permission values
-    let all_permission_values = if !collection_ids.is_empty() {
-        permission_values::Entity::find()
+    // Group permission values by collection_id for efficient lookup
+    let mut pv_by_collection: HashMap<i32, Vec<permission_values::Model>> = HashMap::new();
+    if !collection_ids.is_empty() {
+        for pv in permission_values::Entity::find()
             .filter(permission_values::Column::CollectionId.is_in(collection_ids))
             .all(get_db_pool())
             .await?
-    } else {
-        Vec::new()
-    };
-
-    // Group permission values by collection_id for efficient lookup
-    let mut pv_by_collection: HashMap<i32, Vec<permission_values::Model>> = HashMap::new();
-    for pv in all_permission_values {
-        pv_by_collection
-            .entry(pv.collection_id)
-            .or_default()
-            .push(pv);
+        {
+            pv_by_collection
+                .entry(pv.collection_id)
+                .or_default()
+                .push(pv);
+        }
     }
 
     let forum_rows = forums::Entity::find().all(get_db_pool()).await?;
     let forum_mod_rows = forum_moderators::Entity::find().all(get_db_pool()).await?;
 
-    // Process data into temporary structures (no lock, no await)
-    let mut forum_perms_map: HashMap<i32, DashMap<(i32, i32), CollectionValues>> = HashMap::new();
+    let now = chrono::Utc::now().naive_utc();
+    let mut forum_perms_map: HashMap<i32, OverrideValues> = HashMap::new();
 
     for (fp, collections) in forum_perm_rows {
-        let forum_id = fp.forum_id;
-
-        for pc in collections {
+        for pc in collections
+            .into_iter()
+            .filter(|pc| !lapsed(pc.expires_at, now))
+        {
             let mut cv = CollectionValues::default();
 
             if let Some(pvs) = pv_by_collection.get(&pc.id) {
@@ -129,7 +252,7 @@ pub async fn reload_forum_permissions() -> Result<(), sea_orm::error::DbErr> {
             }
 
             let val_key = (pc.group_id.unwrap_or(0), pc.user_id.unwrap_or(0));
-            let forum_vals = forum_perms_map.entry(forum_id).or_default();
+            let forum_vals = forum_perms_map.entry(fp.forum_id).or_default();
 
             if forum_vals.contains_key(&val_key) {
                 forum_vals.alter(&val_key, |_, v| cv.join(&v));
@@ -139,11 +262,6 @@ pub async fn reload_forum_permissions() -> Result<(), sea_orm::error::DbErr> {
         }
     }
 
-    let forum_parents: HashMap<i32, Option<i32>> = forum_rows
-        .into_iter()
-        .map(|f| (f.id, f.parent_id))
-        .collect();
-
     let mut forum_moderators_map: HashMap<i32, HashSet<i32>> = HashMap::new();
     for fm in forum_mod_rows {
         forum_moderators_map
@@ -152,20 +270,232 @@ pub async fn reload_forum_permissions() -> Result<(), sea_orm::error::DbErr> {
             .insert(fm.user_id);
     }
 
-    // Acquire write lock only for final update (no awaits after this)
-    let mut perm_data = PERMISSION_DATA
-        .get()
-        .expect("Permission data not initialized")
-        .write()
-        .expect("Permission data lock poisoned");
+    Ok(ForumData {
+        permissions: forum_perms_map,
+        parents: forum_rows
+            .into_iter()
+            .map(|f| (f.id, f.parent_id))
+            .collect(),
+        moderators: forum_moderators_map,
+    })
+}
+
+/// (Group, User) -> CollectionValues overrides for a single chat room
+type OverrideValues = DashMap<(i32, i32), collection_values::CollectionValues>;
+
+/// Load per-room chat permission overrides, keyed by room ID
+async fn load_chat_room_permissions(
+    lookup: &DashMap<i32, (u8, u8)>,
+) -> Result<HashMap<i32, OverrideValues>, sea_orm::error::DbErr> {
+    use crate::db::get_db_pool;
+    use crate::orm::chat_room_permissions;
+    use crate::orm::permission_collections;
+    use crate::orm::permission_values;
+    use collection_values::CollectionValues;
+    use sea_orm::entity::*;
+    use sea_orm::QueryFilter;
+
+    let room_perm_rows = chat_room_permissions::Entity::find()
+        .find_with_related(permission_collections::Entity)
+        .all(get_db_pool())
+        .await?;
+
+    let collection_ids: Vec<i32> = room_perm_rows
+        .iter()
+        .flat_map(|(_, collections)| collections.iter().map(|pc| pc.id))
+        .collect();
+
+    let mut pv_by_collection: HashMap<i32, Vec<permission_values::Model>> = HashMap::new();
+    if !collection_ids.is_empty() {
+        for pv in permission_values::Entity::find()
+            .filter(permission_values::Column::CollectionId.is_in(collection_ids))
+            .all(get_db_pool())
+            .await?
+        {
+            pv_by_collection
+                .entry(pv.collection_id)
+                .or_default()
+                .push(pv);
+        }
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let mut room_perms_map: HashMap<i32, OverrideValues> = HashMap::new();
+
+    for (rp, collections) in room_perm_rows {
+        for pc in collections
+            .into_iter()
+            .filter(|pc| !lapsed(pc.expires_at, now))
+        {
+            let mut cv = CollectionValues::default();
+
+            if let Some(pvs) = pv_by_collection.get(&pc.id) {
+                for pv in pvs {
+                    if let Some(pindices) = lookup.get(&pv.permission_id) {
+                        cv.set_flag(pindices.0, pindices.1, pv.value);
+                    }
+                }
+            }
 
-    perm_data.forum_permissions = forum_perms_map;
-    perm_data.forum_parents = forum_parents;
-    perm_data.forum_moderators = forum_moderators_map;
+            let val_key = (pc.group_id.unwrap_or(0), pc.user_id.unwrap_or(0));
+            let room_vals = room_perms_map.entry(rp.chat_room_id).or_default();
 
-    log::info!("Forum permissions reloaded successfully");
+            if room_vals.contains_key(&val_key) {
+                room_vals.alter(&val_key, |_, v| cv.join(&v));
+            } else {
+                room_vals.insert(val_key, cv);
+            }
+        }
+    }
 
-    Ok(())
+    Ok(room_perms_map)
+}
+
+/// Load per-thread permission overrides, keyed by thread ID
+async fn load_thread_permissions(
+    lookup: &DashMap<i32, (u8, u8)>,
+) -> Result<HashMap<i32, OverrideValues>, sea_orm::error::DbErr> {
+    use crate::db::get_db_pool;
+    use crate::orm::permission_collections;
+    use crate::orm::permission_values;
+    use crate::orm::thread_permissions;
+    use collection_values::CollectionValues;
+    use sea_orm::entity::*;
+    use sea_orm::QueryFilter;
+
+    let thread_perm_rows = thread_permissions::Entity::find()
+        .find_with_related(permission_collections::Entity)
+        .all(get_db_pool())
+        .await?;
+
+    let collection_ids: Vec<i32> = thread_perm_rows
+        .iter()
+        .flat_map(|(_, collections)| collections.iter().map(|pc| pc.id))
+        .collect();
+
+    let mut pv_by_collection: HashMap<i32, Vec<permission_values::Model>> = HashMap::new();
+    if !collection_ids.is_empty() {
+        for pv in permission_values::Entity::find()
+            .filter(permission_values::Column::CollectionId.is_in(collection_ids))
+            .all(get_db_pool())
+            .await?
+        {
+            pv_by_collection
+                .entry(pv.collection_id)
+                .or_default()
+                .push(pv);
+        }
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let mut thread_perms_map: HashMap<i32, OverrideValues> = HashMap::new();
+
+    for (tp, collections) in thread_perm_rows {
+        for pc in collections
+            .into_iter()
+            .filter(|pc| !lapsed(pc.expires_at, now))
+        {
+            let mut cv = CollectionValues::default();
+
+            if let Some(pvs) = pv_by_collection.get(&pc.id) {
+                for pv in pvs {
+                    if let Some(pindices) = lookup.get(&pv.permission_id) {
+                        cv.set_flag(pindices.0, pindices.1, pv.value);
+                    }
+                }
+            }
+
+            let val_key = (pc.group_id.unwrap_or(0), pc.user_id.unwrap_or(0));
+            let thread_vals = thread_perms_map.entry(tp.thread_id).or_default();
+
+            if thread_vals.contains_key(&val_key) {
+                thread_vals.alter(&val_key, |_, v| cv.join(&v));
+            } else {
+                thread_vals.insert(val_key, cv);
+            }
+        }
+    }
+
+    Ok(thread_perms_map)
+}
+
+/// Load the display priority of every group
+async fn load_group_priorities() -> Result<HashMap<i32, i32>, sea_orm::error::DbErr> {
+    use crate::db::get_db_pool;
+    use crate::orm::groups;
+    use sea_orm::entity::*;
+
+    Ok(groups::Entity::find()
+        .all(get_db_pool())
+        .await?
+        .into_iter()
+        .map(|group| (group.id, group.display_priority))
+        .collect())
+}
+
+/// Join the values `values_of` gives `groups`. With `priorities`, groups of
+/// higher priority are laid over those below instead, so the highest that
+/// sets a permission decides it. Groups of equal priority are joined.
+fn join_groups(
+    groups: &[i32],
+    priorities: Option<&HashMap<i32, i32>>,
+    values_of: impl Fn(i32) -> Option<collection_values::CollectionValues>,
+) -> collection_values::CollectionValues {
+    use collection_values::CollectionValues;
+
+    let Some(priorities) = priorities else {
+        return groups
+            .iter()
+            .filter_map(|group| values_of(*group))
+            .fold(CollectionValues::default(), |joined, values| {
+                joined.join(&values)
+            });
+    };
+
+    let mut ranked: Vec<(i32, i32)> = groups
+        .iter()
+        .map(|group| (priorities.get(group).copied().unwrap_or(0), *group))
+        .collect();
+    ranked.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut result = CollectionValues::default();
+    for tier in ranked.chunk_by(|a, b| a.0 == b.0) {
+        let joined = tier
+            .iter()
+            .filter_map(|(_, group)| values_of(*group))
+            .fold(CollectionValues::default(), |joined, values| {
+                joined.join(&values)
+            });
+        result = result.overlay(&joined);
+    }
+    result
+}
+
+/// Apply the values `overrides` sets for `groups` and `user_id` to `mask`,
+/// except for permissions already in `decided`, which a nearer thread or
+/// forum set. Adds those it sets to `decided`.
+fn apply_overrides(
+    mask: &mut mask::Mask,
+    decided: &mut [u64; GROUP_LIMIT as usize],
+    overrides: &OverrideValues,
+    groups: &[i32],
+    user_id: Option<i32>,
+    priorities: Option<&HashMap<i32, i32>>,
+) {
+    let mut values = join_groups(groups, priorities, |group| {
+        overrides.get(&(group, 0)).map(|values| values.clone())
+    });
+    if let Some(uid) = user_id {
+        if let Some(user_values) = overrides.get(&(0, uid)) {
+            values = values.join(&user_values);
+        }
+    }
+
+    for (i, category) in values.categories.iter().enumerate() {
+        let explicit = (category.yes | category.no | category.never) & !decided[i];
+        mask.categories[i] = (mask.categories[i] & !explicit) | (u64::from(category) & explicit);
+        decided[i] |= explicit;
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -176,10 +506,24 @@ pub struct PermissionData {
     collection_values: DashMap<(i32, i32), collection_values::CollectionValues>,
     /// Forum-specific permissions: forum_id -> (group_id, user_id) -> CollectionValues
     forum_permissions: HashMap<i32, DashMap<(i32, i32), collection_values::CollectionValues>>,
+    /// Thread-specific permissions: thread_id -> (group_id, user_id) -> CollectionValues
+    thread_permissions: HashMap<i32, OverrideValues>,
     /// Forum parent relationships for inheritance: forum_id -> parent_id
     forum_parents: HashMap<i32, Option<i32>>,
     /// Forum moderators: forum_id -> set of user_ids who are moderators for that forum
     forum_moderators: HashMap<i32, HashSet<i32>>,
+    /// Chat room permissions: room_id -> (group_id, user_id) -> CollectionValues
+    chat_room_permissions: HashMap<i32, OverrideValues>,
+    /// Group -> display priority
+    group_priorities: HashMap<i32, i32>,
+    /// Whether the highest priority group setting a permission decides it
+    priority_wins: bool,
+    /// Resolved masks by client, forum and thread, so a page making dozens
+    /// of checks joins groups once
+    masks: DashMap<MaskKey, CachedMask>,
+    /// (Group, User) -> values of collections with an expiry, kept apart
+    /// from collection_values so they stop applying when they lapse
+    expiring_values: DashMap<(i32, i32), Vec<ExpiringValues>>,
 }
 
 impl PermissionData {
@@ -213,33 +557,214 @@ impl PermissionData {
 
     /// Accepts Client/Guest and specific permission indices for permission check.
     pub fn can_by_indices(&self, client: &ClientCtx, indices: &(u8, u8)) -> bool {
-        let groups = client.get_groups();
-        let values = match client.get_id() {
-            Some(id) => {
-                let group_values = self.join_for_groups(&groups);
-                let user_values = self.join_for_user(id);
-                group_values.join(&user_values)
+        self.can_by_indices_for(&client.get_groups(), client.get_id(), indices)
+    }
+
+    /// Permission check for a set of groups and an optional user, for callers
+    /// without a ClientCtx such as the chat server.
+    pub fn can_by_indices_for(
+        &self,
+        groups: &[i32],
+        user_id: Option<i32>,
+        indices: &(u8, u8),
+    ) -> bool {
+        self.global_mask(groups, user_id)
+            .can(indices.0 as usize, indices.1 as i32)
+    }
+
+    /// Forget every cached permission mask. Call after changing anything they
+    /// are resolved from.
+    pub fn invalidate_masks(&self) {
+        self.masks.clear();
+    }
+
+    /// Cached mask for `groups` and `user_id`, in `forum_id` and
+    /// `thread_id` if given, resolving it with `resolve` if there isn't a
+    /// valid one.
+    fn cached_mask(
+        &self,
+        groups: &[i32],
+        user_id: Option<i32>,
+        forum_id: Option<i32>,
+        thread_id: Option<i32>,
+        resolve: impl FnOnce() -> mask::Mask,
+    ) -> mask::Mask {
+        let mut sorted = groups.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        let key = (
+            user_id.unwrap_or(0),
+            sorted,
+            forum_id.unwrap_or(0),
+            thread_id.unwrap_or(0),
+        );
+        let now = chrono::Utc::now().naive_utc();
+
+        if let Some(entry) = self.masks.get(&key) {
+            if entry.until.map_or(true, |until| until > now) {
+                return entry.mask;
             }
-            None => self.join_for_groups(&groups),
-        };
+        }
 
-        let mask = mask::Mask::from(values);
-        mask.can(indices.0 as usize, indices.1 as i32)
+        let mask = resolve();
+        if self.masks.len() >= MASK_CACHE_LIMIT {
+            self.masks.clear();
+        }
+        self.masks.insert(
+            key,
+            CachedMask {
+                until: self.next_expiry(now),
+                mask,
+            },
+        );
+        mask
     }
 
-    pub fn join_for_groups(&self, groups: &Vec<i32>) -> collection_values::CollectionValues {
-        use collection_values::CollectionValues;
-        let mut return_values = CollectionValues::default();
+    /// Values set for a (group, user) key, with those of expiring
+    /// collections that haven't lapsed by `now` joined in.
+    fn values_at(
+        &self,
+        key: &(i32, i32),
+        now: NaiveDateTime,
+    ) -> Option<collection_values::CollectionValues> {
+        let mut values = self.collection_values.get(key).map(|values| values.clone());
+        if let Some(expiring) = self.expiring_values.get(key) {
+            for grant in expiring.iter().filter(|grant| grant.expires_at > now) {
+                values = Some(values.unwrap_or_default().join(&grant.values));
+            }
+        }
+        values
+    }
+
+    /// When the next expiring collection after `now` lapses.
+    fn next_expiry(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.expiring_values
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .value()
+                    .iter()
+                    .map(|grant| grant.expires_at)
+                    .filter(|expires_at| *expires_at > now)
+                    .min()
+            })
+            .min()
+    }
+
+    /// Global permissions of `groups` and `user_id` joined into a mask.
+    pub fn global_mask(&self, groups: &[i32], user_id: Option<i32>) -> mask::Mask {
+        self.cached_mask(groups, user_id, None, None, || {
+            let values = match user_id {
+                Some(id) => {
+                    let group_values = self.join_for_groups(groups);
+                    let user_values = self.join_for_user(id);
+                    group_values.join(&user_values)
+                }
+                None => self.join_for_groups(groups),
+            };
+            mask::Mask::from(values)
+        })
+    }
+
+    /// Permissions of `groups` and `user_id` in a forum, as a mask. Each
+    /// permission takes its value from the nearest forum up the hierarchy
+    /// that sets it, or from global permissions if none does. Forum
+    /// moderators get every moderate.* permission in their forums.
+    pub fn forum_mask(&self, groups: &[i32], user_id: Option<i32>, forum_id: i32) -> mask::Mask {
+        self.cached_mask(groups, user_id, Some(forum_id), None, || {
+            self.resolve_mask(groups, user_id, forum_id, None)
+        })
+    }
+
+    /// Permissions of `groups` and `user_id` in a thread, as a mask. The
+    /// thread's own overrides come first in the walk up from its forum, so
+    /// they win over any forum's. Threads without overrides share their
+    /// forum's mask.
+    pub fn thread_mask(
+        &self,
+        groups: &[i32],
+        user_id: Option<i32>,
+        thread_id: i32,
+        forum_id: i32,
+    ) -> mask::Mask {
+        if !self.thread_permissions.contains_key(&thread_id) {
+            return self.forum_mask(groups, user_id, forum_id);
+        }
+
+        self.cached_mask(groups, user_id, Some(forum_id), Some(thread_id), || {
+            self.resolve_mask(groups, user_id, forum_id, Some(thread_id))
+        })
+    }
 
-        for group in groups {
-            let val_key = (group.to_owned(), 0);
+    /// Resolve a forum or thread mask without the cache.
+    fn resolve_mask(
+        &self,
+        groups: &[i32],
+        user_id: Option<i32>,
+        forum_id: i32,
+        thread_id: Option<i32>,
+    ) -> mask::Mask {
+        let mut mask = self.global_mask(groups, user_id);
+
+        // Bits already taken from a thread or forum nearer the one asked about
+        let mut decided = [0u64; GROUP_LIMIT as usize];
+        if let Some(thread_perms) = thread_id.and_then(|tid| self.thread_permissions.get(&tid)) {
+            apply_overrides(
+                &mut mask,
+                &mut decided,
+                thread_perms,
+                groups,
+                user_id,
+                self.ranks(),
+            );
+        }
 
-            if let Some(group_values) = self.collection_values.get(&val_key) {
-                return_values = return_values.join(&group_values);
+        let mut current_forum_id = Some(forum_id);
+        while let Some(fid) = current_forum_id {
+            if let Some(forum_perms) = self.forum_permissions.get(&fid) {
+                apply_overrides(
+                    &mut mask,
+                    &mut decided,
+                    forum_perms,
+                    groups,
+                    user_id,
+                    self.ranks(),
+                );
             }
+            current_forum_id = self.forum_parents.get(&fid).copied().flatten();
         }
 
-        return_values
+        if let Some(uid) = user_id {
+            let mut check_forum_id = Some(forum_id);
+            while let Some(fid) = check_forum_id {
+                if let Some(moderators) = self.forum_moderators.get(&fid) {
+                    if moderators.contains(&uid) {
+                        for entry in self.collection.dictionary.iter() {
+                            if entry.key().starts_with("moderate.") {
+                                let (category, item) = *entry.value();
+                                mask.categories[category as usize] |= 1 << item;
+                            }
+                        }
+                        break;
+                    }
+                }
+                check_forum_id = self.forum_parents.get(&fid).copied().flatten();
+            }
+        }
+
+        mask
+    }
+
+    /// Group priorities if the highest priority group decides permissions
+    fn ranks(&self) -> Option<&HashMap<i32, i32>> {
+        self.priority_wins.then_some(&self.group_priorities)
+    }
+
+    pub fn join_for_groups(&self, groups: &[i32]) -> collection_values::CollectionValues {
+        let now = chrono::Utc::now().naive_utc();
+        join_groups(groups, self.ranks(), |group| {
+            self.values_at(&(group, 0), now)
+        })
     }
 
     pub fn join_for_user(&self, id: i32) -> collection_values::CollectionValues {
@@ -247,17 +772,15 @@ impl PermissionData {
         let mut return_values = CollectionValues::default();
         let val_key = (0, id);
 
-        if let Some(group_values) = self.collection_values.get(&val_key) {
-            return_values = return_values.join(&group_values);
+        if let Some(user_values) = self.values_at(&val_key, chrono::Utc::now().naive_utc()) {
+            return_values = return_values.join(&user_values);
         }
 
         return_values
     }
 
     /// Check permission in forum context with parent inheritance.
-    /// Walks up the forum hierarchy until an override is found.
-    /// Uses global permission store for forum data to support live reloading.
-    /// Forum moderators automatically get moderate.* permissions in their assigned forums.
+    /// See [`PermissionData::forum_mask`] for how it's resolved.
     pub fn can_in_forum(&self, client: &ClientCtx, forum_id: i32, permission: &str) -> bool {
         // Look up the permission's indices by name
         let pindices = match self.collection.dictionary.get(permission) {
@@ -271,81 +794,81 @@ impl PermissionData {
             }
         };
 
-        let groups = client.get_groups();
-        let user_id = client.get_id();
-        let mut current_forum_id = Some(forum_id);
-
-        // Access the global permission data for forum-specific checks
-        // This allows live reloading of forum permissions without server restart
-        let global_perm_data = get_permission_data();
+        self.forum_mask(&client.get_groups(), client.get_id(), forum_id)
+            .can(pindices.0 as usize, pindices.1 as i32)
+    }
 
-        // Check if this is a moderation permission and user is a forum moderator
-        // Forum moderators get all moderate.* permissions in their assigned forums
-        if permission.starts_with("moderate.") {
-            if let Some(uid) = user_id {
-                // Check if user is a moderator for this forum or any parent forum
-                let mut check_forum_id = Some(forum_id);
-                while let Some(fid) = check_forum_id {
-                    if let Some(moderators) = global_perm_data.forum_moderators.get(&fid) {
-                        if moderators.contains(&uid) {
-                            return true;
-                        }
-                    }
-                    // Move to parent forum
-                    check_forum_id = global_perm_data.forum_parents.get(&fid).copied().flatten();
-                }
+    /// Check permission in thread context, with the thread's overrides
+    /// applied before its forum's. See [`PermissionData::thread_mask`].
+    pub fn can_in_thread(
+        &self,
+        client: &ClientCtx,
+        thread_id: i32,
+        forum_id: i32,
+        permission: &str,
+    ) -> bool {
+        let pindices = match self.collection.dictionary.get(permission) {
+            Some(indices) => *indices,
+            None => {
+                log::warn!(
+                    "Bad permission check on name '{:?}', which is not present in our dictionary.",
+                    permission
+                );
+                return false;
             }
-        }
+        };
 
-        // Walk up the forum hierarchy
-        while let Some(fid) = current_forum_id {
-            // Check if this forum has permission overrides
-            if let Some(forum_perms) = global_perm_data.forum_permissions.get(&fid) {
-                // Build values from forum-specific group permissions
-                let mut forum_values = collection_values::CollectionValues::default();
-                let mut has_override = false;
-
-                // Check group permissions for this forum
-                for group in &groups {
-                    let val_key = (*group, 0);
-                    if let Some(group_values) = forum_perms.get(&val_key) {
-                        forum_values = forum_values.join(&group_values);
-                        has_override = true;
-                    }
-                }
+        self.thread_mask(&client.get_groups(), client.get_id(), thread_id, forum_id)
+            .can(pindices.0 as usize, pindices.1 as i32)
+    }
 
-                // Check user-specific permissions for this forum
-                if let Some(uid) = user_id {
-                    let val_key = (0, uid);
-                    if let Some(user_values) = forum_perms.get(&val_key) {
-                        forum_values = forum_values.join(&user_values);
-                        has_override = true;
-                    }
-                }
+    /// Check permission in a chat room. Room overrides win when they set the
+    /// permission explicitly, otherwise global permissions apply.
+    pub fn can_in_chat_room(
+        &self,
+        groups: &[i32],
+        user_id: Option<i32>,
+        room_id: i32,
+        permission: &str,
+    ) -> bool {
+        let pindices = match self.collection.dictionary.get(permission) {
+            Some(indices) => *indices,
+            None => {
+                log::warn!(
+                    "Bad permission check on name '{:?}', which is not present in our dictionary.",
+                    permission
+                );
+                return false;
+            }
+        };
 
-                // If we found any overrides for this forum, check if this permission is explicitly set
-                if has_override && forum_values.has_explicit_value(pindices.0 as usize, pindices.1)
-                {
-                    return forum_values.can(pindices.0 as usize, pindices.1);
+        if let Some(room_perms) = self.chat_room_permissions.get(&room_id) {
+            let mut room_values = join_groups(groups, self.ranks(), |group| {
+                room_perms.get(&(group, 0)).map(|values| values.clone())
+            });
+
+            if let Some(uid) = user_id {
+                if let Some(user_values) = room_perms.get(&(0, uid)) {
+                    room_values = room_values.join(&user_values);
                 }
             }
 
-            // Move to parent forum
-            current_forum_id = global_perm_data.forum_parents.get(&fid).copied().flatten();
+            if room_values.has_explicit_value(pindices.0 as usize, pindices.1) {
+                return room_values.can(pindices.0 as usize, pindices.1);
+            }
         }
 
-        // No forum overrides in chain - fall back to global permissions
-        self.can_by_indices(client, &pindices)
+        self.can_by_indices_for(groups, user_id, &pindices)
+    }
+
+    /// Threads with permission overrides of their own
+    pub fn threads_with_overrides(&self) -> Vec<i32> {
+        self.thread_permissions.keys().copied().collect()
     }
 
     /// Get the parent forum ID for a given forum
     pub fn get_forum_parent(&self, forum_id: i32) -> Option<i32> {
-        // Use global store for live reloading support
-        get_permission_data()
-            .forum_parents
-            .get(&forum_id)
-            .copied()
-            .flatten()
+        self.forum_parents.get(&forum_id).copied().flatten()
     }
 }
 
@@ -394,15 +917,42 @@ pub async fn new() -> Result<PermissionData, sea_orm::error::DbErr> {
     }
 
     // Import data
+    let now = chrono::Utc::now().naive_utc();
     let vals: DashMap<(i32, i32), CollectionValues> = Default::default();
+    let expiring_values: DashMap<(i32, i32), Vec<ExpiringValues>> = Default::default();
+    // Forum, thread and chat room overrides are loaded separately and must not apply globally
+    let mut override_collection_ids: HashSet<i32> =
+        crate::orm::chat_room_permissions::Entity::find()
+            .all(get_db_pool())
+            .await?
+            .into_iter()
+            .map(|rp| rp.collection_id)
+            .collect();
+    override_collection_ids.extend(
+        forum_permissions::Entity::find()
+            .all(get_db_pool())
+            .await?
+            .into_iter()
+            .map(|fp| fp.collection_id),
+    );
+    override_collection_ids.extend(
+        crate::orm::thread_permissions::Entity::find()
+            .all(get_db_pool())
+            .await?
+            .into_iter()
+            .map(|tp| tp.collection_id),
+    );
     let perm_collections = permission_collections::Entity::find()
         .find_with_related(permission_values::Entity)
         .all(get_db_pool())
-        .await?;
+        .await?
+        .into_iter()
+        .filter(|(pc, _)| !override_collection_ids.contains(&pc.id))
+        .filter(|(pc, _)| !lapsed(pc.expires_at, now));
 
     // convert ORM data into permission system structs
     // loop through the collection-<values relations
-    for (perm_collection, pvs) in perm_collections.iter() {
+    for (perm_collection, pvs) in perm_collections {
         // Create collection values record to set flags on
         let mut cv = CollectionValues::default();
 
@@ -426,6 +976,18 @@ pub async fn new() -> Result<PermissionData, sea_orm::error::DbErr> {
             perm_collection.user_id.unwrap_or(0),
         );
 
+        // Expiring collections stay apart so they can lapse on their own
+        if let Some(expires_at) = perm_collection.expires_at {
+            expiring_values
+                .entry(val_key)
+                .or_default()
+                .push(ExpiringValues {
+                    expires_at,
+                    values: cv,
+                });
+            continue;
+        }
+
         if vals.contains_key(&val_key) {
             // Join permission with same key.
             vals.alter(&val_key, |_, v| cv.join(&v));
@@ -446,7 +1008,10 @@ pub async fn new() -> Result<PermissionData, sea_orm::error::DbErr> {
     for (fp, collections) in forum_perm_rows {
         let forum_id = fp.forum_id;
 
-        for pc in collections {
+        for pc in collections
+            .into_iter()
+            .filter(|pc| !lapsed(pc.expires_at, now))
+        {
             // Load permission values for this collection
             let pvs = permission_values::Entity::find()
                 .filter(permission_values::Column::CollectionId.eq(pc.id))
@@ -493,11 +1058,21 @@ pub async fn new() -> Result<PermissionData, sea_orm::error::DbErr> {
             .insert(fm.user_id);
     }
 
+    let chat_room_permissions = load_chat_room_permissions(&col.lookup).await?;
+    let thread_permissions = load_thread_permissions(&col.lookup).await?;
+    let group_priorities = load_group_priorities().await?;
+
     Ok(PermissionData {
         collection: col,
         collection_values: vals,
         forum_permissions: forum_perms_map,
+        thread_permissions,
         forum_parents,
         forum_moderators: forum_moderators_map,
+        chat_room_permissions,
+        group_priorities,
+        priority_wins: false,
+        masks: DashMap::new(),
+        expiring_values,
     })
 }
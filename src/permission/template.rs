@@ -0,0 +1,227 @@
+//! Forum permission templates
+//!
+//! A template is a named copy of a forum's per-group permission matrix.
+//! Applying one, or copying another forum's matrix, replaces the group
+//! collections on the target forum; member collections such as those given
+//! to forum moderators are left alone. The default template is applied to
+//! new forums by a database trigger, so forums created outside the admin
+//! panel get it too.
+
+use super::flag::Flag;
+use crate::orm::{
+    forum_permission_template_values, forum_permission_templates, forum_permissions,
+    permission_collections, permission_values,
+};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    EntityTrait, QueryFilter, QueryOrder,
+};
+use std::collections::BTreeMap;
+
+/// One cell of a forum's permission matrix
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatrixValue {
+    pub group_id: i32,
+    pub permission_id: i32,
+    pub value: Flag,
+}
+
+/// Group collections linked to a forum
+async fn group_collections<C: ConnectionTrait>(
+    conn: &C,
+    forum_id: i32,
+) -> Result<Vec<permission_collections::Model>, DbErr> {
+    let collection_ids: Vec<i32> = forum_permissions::Entity::find()
+        .filter(forum_permissions::Column::ForumId.eq(forum_id))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|fp| fp.collection_id)
+        .collect();
+
+    if collection_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    permission_collections::Entity::find()
+        .filter(permission_collections::Column::Id.is_in(collection_ids))
+        .filter(permission_collections::Column::GroupId.is_not_null())
+        .filter(permission_collections::Column::UserId.is_null())
+        .all(conn)
+        .await
+}
+
+/// Values set on a forum for each group, ignoring "default"
+pub async fn forum_matrix<C: ConnectionTrait>(
+    conn: &C,
+    forum_id: i32,
+) -> Result<Vec<MatrixValue>, DbErr> {
+    let collection_groups: BTreeMap<i32, i32> = group_collections(conn, forum_id)
+        .await?
+        .into_iter()
+        .filter_map(|c| c.group_id.map(|group_id| (c.id, group_id)))
+        .collect();
+
+    if collection_groups.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut matrix: Vec<MatrixValue> = permission_values::Entity::find()
+        .filter(permission_values::Column::CollectionId.is_in(collection_groups.keys().copied()))
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter(|pv| pv.value != Flag::DEFAULT)
+        .filter_map(|pv| {
+            collection_groups
+                .get(&pv.collection_id)
+                .map(|&group_id| MatrixValue {
+                    group_id,
+                    permission_id: pv.permission_id,
+                    value: pv.value,
+                })
+        })
+        .collect();
+    matrix.sort_by_key(|v| (v.group_id, v.permission_id));
+    matrix.dedup_by_key(|v| (v.group_id, v.permission_id));
+
+    Ok(matrix)
+}
+
+/// Values saved in a template
+pub async fn template_matrix<C: ConnectionTrait>(
+    conn: &C,
+    template_id: i32,
+) -> Result<Vec<MatrixValue>, DbErr> {
+    Ok(forum_permission_template_values::Entity::find()
+        .filter(forum_permission_template_values::Column::TemplateId.eq(template_id))
+        .order_by_asc(forum_permission_template_values::Column::GroupId)
+        .order_by_asc(forum_permission_template_values::Column::PermissionId)
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|v| MatrixValue {
+            group_id: v.group_id,
+            permission_id: v.permission_id,
+            value: v.value,
+        })
+        .collect())
+}
+
+/// Replace a forum's group collections with one per group in `matrix`.
+/// Call `reload_forum_permissions` afterwards.
+pub async fn replace_forum_matrix<C: ConnectionTrait>(
+    conn: &C,
+    forum_id: i32,
+    matrix: &[MatrixValue],
+) -> Result<(), DbErr> {
+    let old_ids: Vec<i32> = group_collections(conn, forum_id)
+        .await?
+        .into_iter()
+        .map(|c| c.id)
+        .collect();
+    if !old_ids.is_empty() {
+        // Cascades to the forum links and values
+        permission_collections::Entity::delete_many()
+            .filter(permission_collections::Column::Id.is_in(old_ids))
+            .exec(conn)
+            .await?;
+    }
+
+    let mut by_group: BTreeMap<i32, Vec<&MatrixValue>> = BTreeMap::new();
+    for value in matrix.iter().filter(|v| v.value != Flag::DEFAULT) {
+        by_group.entry(value.group_id).or_default().push(value);
+    }
+
+    for (group_id, values) in by_group {
+        let collection = permission_collections::ActiveModel {
+            group_id: Set(Some(group_id)),
+            user_id: Set(None),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await?;
+
+        forum_permissions::ActiveModel {
+            forum_id: Set(forum_id),
+            collection_id: Set(collection.id),
+        }
+        .insert(conn)
+        .await?;
+
+        permission_values::Entity::insert_many(values.into_iter().map(|v| {
+            permission_values::ActiveModel {
+                permission_id: Set(v.permission_id),
+                collection_id: Set(collection.id),
+                value: Set(v.value),
+            }
+        }))
+        .exec(conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Save a forum's current matrix as a new template
+pub async fn save_template<C: ConnectionTrait>(
+    conn: &C,
+    forum_id: i32,
+    name: &str,
+    created_by: Option<i32>,
+) -> Result<forum_permission_templates::Model, DbErr> {
+    let matrix = forum_matrix(conn, forum_id).await?;
+
+    let template = forum_permission_templates::ActiveModel {
+        name: Set(name.to_string()),
+        is_default: Set(false),
+        created_by: Set(created_by),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+
+    if !matrix.is_empty() {
+        forum_permission_template_values::Entity::insert_many(matrix.iter().map(|v| {
+            forum_permission_template_values::ActiveModel {
+                template_id: Set(template.id),
+                group_id: Set(v.group_id),
+                permission_id: Set(v.permission_id),
+                value: Set(v.value),
+            }
+        }))
+        .exec(conn)
+        .await?;
+    }
+
+    Ok(template)
+}
+
+/// Make a template the one applied to new forums, or clear the default
+pub async fn set_default_template<C: ConnectionTrait>(
+    conn: &C,
+    template_id: Option<i32>,
+) -> Result<(), DbErr> {
+    forum_permission_templates::Entity::update_many()
+        .col_expr(
+            forum_permission_templates::Column::IsDefault,
+            Expr::value(false),
+        )
+        .filter(forum_permission_templates::Column::IsDefault.eq(true))
+        .exec(conn)
+        .await?;
+
+    if let Some(template_id) = template_id {
+        forum_permission_templates::Entity::update_many()
+            .col_expr(
+                forum_permission_templates::Column::IsDefault,
+                Expr::value(true),
+            )
+            .filter(forum_permission_templates::Column::Id.eq(template_id))
+            .exec(conn)
+            .await?;
+    }
+
+    Ok(())
+}
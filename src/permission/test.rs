@@ -265,3 +265,307 @@ fn test_values_stack_negatives() {
     assert_eq!(group3.no, 0b00010u64);
     assert_eq!(group3.never, 0b01001u64);
 }
+
+#[test]
+fn test_chat_room_override() {
+    use super::collection_values::CollectionValues;
+    use super::{Flag, PermissionData};
+    use dashmap::DashMap;
+
+    let mut data = PermissionData::default();
+    if data.collection.categories[0]
+        .add_item(50, "chat.post")
+        .is_err()
+    {
+        panic!("Category overflow?");
+    }
+    data.collection.build_dictionary();
+    let (cat, item) = data.collection.get_item_pos("chat.post").unwrap_or((9, 9));
+
+    // Registered users may post globally
+    let mut global = CollectionValues::default();
+    global.set_flag(cat as u8, item as u8, Flag::YES);
+    data.collection_values.insert((2, 0), global);
+
+    // ...but not in room 7
+    let mut room = CollectionValues::default();
+    room.set_flag(cat as u8, item as u8, Flag::NO);
+    let room_values = DashMap::new();
+    room_values.insert((2, 0), room);
+    data.chat_room_permissions.insert(7, room_values);
+
+    assert!(data.can_in_chat_room(&[2], Some(1), 8, "chat.post"));
+    assert!(!data.can_in_chat_room(&[2], Some(1), 7, "chat.post"));
+    assert!(!data.can_in_chat_room(&[1], None, 8, "chat.post"));
+}
+
+#[test]
+fn test_collection_flag() {
+    use super::collection_values::CollectionValues;
+    use super::flag::Flag;
+
+    let mut cv = CollectionValues::default();
+    cv.set_flag(0, 0, Flag::YES);
+    cv.set_flag(0, 1, Flag::NO);
+    cv.set_flag(1, 2, Flag::NEVER);
+
+    assert_eq!(cv.flag(0, 0), Flag::YES);
+    assert_eq!(cv.flag(0, 1), Flag::NO);
+    assert_eq!(cv.flag(1, 2), Flag::NEVER);
+    assert_eq!(cv.flag(0, 2), Flag::DEFAULT);
+}
+
+#[test]
+fn test_explain_global() {
+    use super::collection::Collection;
+    use super::collection_values::CollectionValues;
+    use super::explain::{Contribution, Decider};
+    use super::flag::Flag;
+    use super::{PermissionData, Permissions};
+    use crate::middleware::ClientCtx;
+
+    let mut col = Collection::default();
+    col.categories[0].id = 1;
+    col.categories[0]
+        .add_item(1, "forum.view")
+        .expect("Category overflow?");
+    col.categories[0]
+        .add_item(2, "post.create")
+        .expect("Category overflow?");
+    col.build_dictionary();
+
+    let data = PermissionData {
+        collection: col,
+        ..Default::default()
+    };
+    // Group 1 grants both, group 2 takes post.create away
+    let mut members = CollectionValues::default();
+    members.set_flag(0, 0, Flag::YES);
+    members.set_flag(0, 1, Flag::YES);
+    data.collection_values.insert((1, 0), members);
+    let mut muted = CollectionValues::default();
+    muted.set_flag(0, 1, Flag::NEVER);
+    data.collection_values.insert((2, 0), muted);
+    let data = Permissions::new(data);
+
+    let client = ClientCtx::impersonate(data.clone(), None, vec![1, 2]);
+
+    let view = data.read().explain(&client, "forum.view", None).unwrap();
+    assert_eq!(view.allowed, true);
+    assert_eq!(view.decided_by, Decider::Global);
+    assert_eq!(
+        view.contributions,
+        vec![Contribution {
+            group_id: Some(1),
+            flag: Flag::YES
+        }]
+    );
+
+    let post = data.read().explain(&client, "post.create", None).unwrap();
+    assert_eq!(post.allowed, false);
+    assert_eq!(post.allowed, client.can("post.create"));
+    assert_eq!(post.contributions.len(), 2);
+    assert_eq!(post.contributions[1].flag, Flag::NEVER);
+
+    // Without group 1 nothing sets forum.view
+    let guest = ClientCtx::impersonate(data.clone(), None, vec![2]);
+    let view = data.read().explain(&guest, "forum.view", None).unwrap();
+    assert_eq!(view.allowed, false);
+    assert!(view.contributions.is_empty());
+
+    assert!(data
+        .read()
+        .explain(&client, "no.such.permission", None)
+        .is_none());
+}
+
+#[test]
+fn test_mask_cache() {
+    use super::collection_values::CollectionValues;
+    use super::{Flag, PermissionData};
+
+    let mut data = PermissionData::default();
+    if data.collection.categories[0]
+        .add_item(1, "forum.view")
+        .is_err()
+    {
+        panic!("Category overflow?");
+    }
+    data.collection.build_dictionary();
+    let indices = *data.collection.dictionary.get("forum.view").unwrap();
+
+    let mut members = CollectionValues::default();
+    members.set_flag(indices.0, indices.1, Flag::YES);
+    data.collection_values.insert((1, 0), members);
+
+    // Group order doesn't make a new entry
+    assert!(data.can_by_indices_for(&[1, 2], Some(5), &indices));
+    assert!(data.can_by_indices_for(&[2, 1, 1], Some(5), &indices));
+    assert_eq!(data.masks.len(), 1);
+    assert!(!data.can_by_indices_for(&[2], None, &indices));
+    assert_eq!(data.masks.len(), 2);
+
+    // Changes apply once cached masks are invalidated
+    let mut banned = CollectionValues::default();
+    banned.set_flag(indices.0, indices.1, Flag::NEVER);
+    data.collection_values.insert((0, 5), banned);
+    data.invalidate_masks();
+    assert!(!data.can_by_indices_for(&[1, 2], Some(5), &indices));
+    assert!(data.can_by_indices_for(&[1], None, &indices));
+}
+
+#[test]
+fn test_permissions_handles() {
+    use super::{PermissionData, Permissions};
+
+    let mut data = PermissionData::default();
+    if data.collection.categories[0]
+        .add_item(1, "forum.view")
+        .is_err()
+    {
+        panic!("Category overflow?");
+    }
+    data.collection.build_dictionary();
+
+    // Clones see each other's changes, separate handles don't
+    let first = Permissions::new(data);
+    let shared = first.clone();
+    let second = Permissions::default();
+    first.write().forum_parents.insert(5, Some(1));
+    assert_eq!(shared.read().get_forum_parent(5), Some(1));
+    assert_eq!(second.read().get_forum_parent(5), None);
+    assert!(second
+        .read()
+        .collection
+        .dictionary
+        .get("forum.view")
+        .is_none());
+}
+
+#[test]
+fn test_expiring_values() {
+    use super::collection_values::CollectionValues;
+    use super::{ExpiringValues, Flag, PermissionData};
+
+    let mut data = PermissionData::default();
+    if data.collection.categories[0]
+        .add_item(1, "moderate.post.delete")
+        .is_err()
+    {
+        panic!("Category overflow?");
+    }
+    data.collection.build_dictionary();
+    let indices = *data
+        .collection
+        .dictionary
+        .get("moderate.post.delete")
+        .unwrap();
+
+    let mut grant = CollectionValues::default();
+    grant.set_flag(indices.0, indices.1, Flag::YES);
+    let now = chrono::Utc::now().naive_utc();
+
+    // An event moderator's grant still running, and one that lapsed
+    data.expiring_values.insert(
+        (0, 5),
+        vec![ExpiringValues {
+            expires_at: now + chrono::Duration::hours(1),
+            values: grant.clone(),
+        }],
+    );
+    data.expiring_values.insert(
+        (0, 6),
+        vec![ExpiringValues {
+            expires_at: now - chrono::Duration::hours(1),
+            values: grant,
+        }],
+    );
+
+    assert!(data.can_by_indices_for(&[], Some(5), &indices));
+    assert!(!data.can_by_indices_for(&[], Some(6), &indices));
+    assert_eq!(
+        data.next_expiry(now),
+        Some(now + chrono::Duration::hours(1))
+    );
+    // Nothing lapses after the last grant
+    assert!(data.next_expiry(now + chrono::Duration::hours(2)).is_none());
+}
+
+#[test]
+fn test_thread_overrides_win_over_forum() {
+    use super::collection_values::CollectionValues;
+    use super::mask::Mask;
+    use super::{apply_overrides, Flag, GROUP_LIMIT};
+    use dashmap::DashMap;
+
+    // Guests (1) may view and post globally
+    let mut global = CollectionValues::default();
+    global.set_flag(0, 0, Flag::YES);
+    global.set_flag(0, 1, Flag::YES);
+    let mut mask = Mask::from(global.clone());
+    let mut decided = [0u64; GROUP_LIMIT as usize];
+
+    // The thread is member-only...
+    let mut thread = CollectionValues::default();
+    thread.set_flag(0, 0, Flag::NO);
+    let thread_values = DashMap::new();
+    thread_values.insert((1, 0), thread);
+    apply_overrides(&mut mask, &mut decided, &thread_values, &[1], None, None);
+
+    // ...while its forum allows viewing but not posting
+    let mut forum = CollectionValues::default();
+    forum.set_flag(0, 0, Flag::YES);
+    forum.set_flag(0, 1, Flag::NO);
+    let forum_values = DashMap::new();
+    forum_values.insert((1, 0), forum);
+    apply_overrides(&mut mask, &mut decided, &forum_values, &[1], None, None);
+
+    assert!(!mask.can(0, 0));
+    assert!(!mask.can(0, 1));
+
+    // Overrides for other groups don't apply
+    let mut mask = Mask::from(global);
+    let mut decided = [0u64; GROUP_LIMIT as usize];
+    apply_overrides(&mut mask, &mut decided, &thread_values, &[2], Some(9), None);
+    assert!(mask.can(0, 0));
+}
+
+#[test]
+fn test_priority_wins() {
+    use super::collection_values::CollectionValues;
+    use super::{Flag, PermissionData};
+    use std::collections::HashMap;
+
+    let mut data = PermissionData::default();
+    if data.collection.categories[0]
+        .add_item(1, "post.create")
+        .is_err()
+    {
+        panic!("Category overflow?");
+    }
+    data.collection.build_dictionary();
+    let indices = *data.collection.dictionary.get("post.create").unwrap();
+
+    // Muted (1) takes posting away for good; Staff (2) grants it
+    let mut muted = CollectionValues::default();
+    muted.set_flag(indices.0, indices.1, Flag::NEVER);
+    data.collection_values.insert((1, 0), muted);
+    let mut staff = CollectionValues::default();
+    staff.set_flag(indices.0, indices.1, Flag::YES);
+    data.collection_values.insert((2, 0), staff);
+    data.group_priorities = HashMap::from([(1, 0), (2, 10), (3, 10)]);
+
+    // Joined, Never wins
+    assert!(!data.can_by_indices_for(&[1, 2], None, &indices));
+
+    // By priority, Staff decides, joined with groups of the same priority
+    data.priority_wins = true;
+    data.invalidate_masks();
+    assert!(data.can_by_indices_for(&[1, 2], None, &indices));
+    let mut denied = CollectionValues::default();
+    denied.set_flag(indices.0, indices.1, Flag::NEVER);
+    data.collection_values.insert((3, 0), denied);
+    data.invalidate_masks();
+    assert!(!data.can_by_indices_for(&[1, 2, 3], None, &indices));
+    assert!(!data.can_by_indices_for(&[1], None, &indices));
+}
@@ -0,0 +1,616 @@
+//! Permission export and import
+//!
+//! The permission configuration can be exported as JSON and imported on
+//! another instance, so changes tried on staging can be promoted to
+//! production. IDs differ between instances, so everything is keyed by name:
+//! permissions and groups by label, and forums by their path from the root,
+//! such as "Community / Off Topic".
+//!
+//! Categories and permissions are created by migrations, so an import checks
+//! them against this instance instead of writing them. It replaces the global
+//! values of each group in the export and the group overrides of each forum in
+//! it. Groups and forums missing from the export, member grants, forum
+//! moderators and chat room and thread overrides are left alone.
+
+use super::audit::{self, Snapshot};
+use super::flag::Flag;
+use super::template::{self, MatrixValue};
+use crate::orm::{
+    forums, groups, permission_categories, permission_collections, permission_values, permissions,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbBackend, DbErr,
+    EntityTrait, FromQueryResult, QueryFilter, QueryOrder, Statement,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Version of the export format, bumped when it changes incompatibly
+pub const VERSION: u32 = 1;
+
+/// Permission label → "yes", "no" or "never"
+pub type Values = BTreeMap<String, String>;
+
+/// The permission configuration of an instance.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Export {
+    pub version: u32,
+    pub categories: Vec<ExportCategory>,
+    /// Global values of every group, by group label
+    pub groups: BTreeMap<String, Values>,
+    /// Group overrides of every forum, by forum path then group label
+    pub forums: BTreeMap<String, BTreeMap<String, Values>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportCategory {
+    pub label: String,
+    pub sort: i32,
+    pub permissions: Vec<ExportPermission>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportPermission {
+    pub label: String,
+    pub sort: i32,
+}
+
+impl Export {
+    /// Every value as one snapshot, keyed "group: permission" for global
+    /// values and "forum / group: permission" for forum overrides, so two
+    /// exports can be compared with [`audit::diff`].
+    pub fn snapshot(&self) -> Snapshot {
+        let mut snapshot = Snapshot::new();
+        for (group, values) in &self.groups {
+            for (permission, value) in values {
+                snapshot.insert(format!("{}: {}", group, permission), value.clone());
+            }
+        }
+        for (forum, groups) in &self.forums {
+            for (group, values) in groups {
+                for (permission, value) in values {
+                    snapshot.insert(
+                        format!("{} / {}: {}", forum, group, permission),
+                        value.clone(),
+                    );
+                }
+            }
+        }
+        snapshot
+    }
+}
+
+/// Parse a value from an export. "default" is the same as leaving it out.
+fn parse_flag(value: &str) -> Option<Flag> {
+    match value {
+        "yes" => Some(Flag::YES),
+        "no" => Some(Flag::NO),
+        "never" => Some(Flag::NEVER),
+        "default" => Some(Flag::DEFAULT),
+        _ => None,
+    }
+}
+
+/// Each forum's path from the root, labels joined with " / ", given each
+/// forum's (ID, label, parent ID)
+fn forum_paths(forums: &[(i32, String, Option<i32>)]) -> HashMap<i32, String> {
+    let by_id: HashMap<i32, &(i32, String, Option<i32>)> =
+        forums.iter().map(|forum| (forum.0, forum)).collect();
+
+    forums
+        .iter()
+        .map(|(id, label, parent_id)| {
+            let mut labels = vec![label.as_str()];
+            let mut parent_id = *parent_id;
+            // Bounded in case of a parent loop
+            while let Some((_, parent_label, grandparent_id)) =
+                parent_id.and_then(|id| by_id.get(&id).copied())
+            {
+                if labels.len() > forums.len() {
+                    break;
+                }
+                labels.push(parent_label.as_str());
+                parent_id = *grandparent_id;
+            }
+            labels.reverse();
+            (*id, labels.join(" / "))
+        })
+        .collect()
+}
+
+/// Paths of every forum here
+async fn load_forum_paths<C: ConnectionTrait>(conn: &C) -> Result<HashMap<i32, String>, DbErr> {
+    let forums: Vec<(i32, String, Option<i32>)> = forums::Entity::find()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|f| (f.id, f.label, f.parent_id))
+        .collect();
+    Ok(forum_paths(&forums))
+}
+
+/// Convert exported values to (permission ID, value) for the permissions
+/// here, adding an error under `context` for each one that can't be.
+/// Permissions in the export but not here are skipped; a warning covers them.
+fn convert_values(
+    context: &str,
+    values: &Values,
+    permission_ids: &HashMap<String, i32>,
+    exported_permissions: &HashSet<&str>,
+    errors: &mut Vec<String>,
+) -> Vec<(i32, Flag)> {
+    let mut converted = Vec::new();
+    for (label, value) in values {
+        let Some(flag) = parse_flag(value) else {
+            errors.push(format!(
+                "{}: {} has an unknown value \"{}\".",
+                context, label, value
+            ));
+            continue;
+        };
+        match permission_ids.get(label) {
+            Some(&id) if flag != Flag::DEFAULT => converted.push((id, flag)),
+            Some(_) => {}
+            None if exported_permissions.contains(label.as_str()) => {}
+            None => errors.push(format!(
+                "{}: {} isn't a permission here or in the export.",
+                context, label
+            )),
+        }
+    }
+    converted
+}
+
+#[derive(Debug, FromQueryResult)]
+struct GroupValueRow {
+    group_label: String,
+    permission: String,
+    value: String,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct ForumValueRow {
+    forum_id: i32,
+    group_label: String,
+    permission: String,
+    value: String,
+}
+
+/// Export this instance's permission configuration.
+pub async fn export<C: ConnectionTrait>(conn: &C) -> Result<Export, DbErr> {
+    let all_permissions = permissions::Entity::find()
+        .order_by_asc(permissions::Column::Sort)
+        .all(conn)
+        .await?;
+    let categories = permission_categories::Entity::find()
+        .order_by_asc(permission_categories::Column::Sort)
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|category| ExportCategory {
+            permissions: all_permissions
+                .iter()
+                .filter(|p| p.category_id == category.id)
+                .map(|p| ExportPermission {
+                    label: p.label.clone(),
+                    sort: p.sort,
+                })
+                .collect(),
+            label: category.label,
+            sort: category.sort,
+        })
+        .collect();
+
+    let mut group_values: BTreeMap<String, Values> = groups::Entity::find()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|g| (g.label, Values::new()))
+        .collect();
+    for row in GroupValueRow::find_by_statement(Statement::from_string(
+        DbBackend::Postgres,
+        r#"SELECT g.label AS group_label, p.label AS permission, pv.value::TEXT AS value
+            FROM permission_collections pc
+            JOIN groups g ON g.id = pc.group_id
+            JOIN permission_values pv ON pv.collection_id = pc.id
+            JOIN permissions p ON p.id = pv.permission_id
+            WHERE pc.user_id IS NULL
+                AND pv.value <> 'default'
+                AND NOT EXISTS (SELECT 1 FROM forum_permissions fp WHERE fp.collection_id = pc.id)
+                AND NOT EXISTS (SELECT 1 FROM chat_room_permissions cp WHERE cp.collection_id = pc.id)
+                AND NOT EXISTS (SELECT 1 FROM thread_permissions tp WHERE tp.collection_id = pc.id)"#
+            .to_string(),
+    ))
+    .all(conn)
+    .await?
+    {
+        group_values
+            .entry(row.group_label)
+            .or_default()
+            .insert(row.permission, row.value);
+    }
+
+    let paths = load_forum_paths(conn).await?;
+    let mut forum_values: BTreeMap<String, BTreeMap<String, Values>> = paths
+        .values()
+        .map(|path| (path.clone(), BTreeMap::new()))
+        .collect();
+    for row in ForumValueRow::find_by_statement(Statement::from_string(
+        DbBackend::Postgres,
+        r#"SELECT fp.forum_id, g.label AS group_label, p.label AS permission, pv.value::TEXT AS value
+            FROM forum_permissions fp
+            JOIN permission_collections pc ON pc.id = fp.collection_id
+            JOIN groups g ON g.id = pc.group_id
+            JOIN permission_values pv ON pv.collection_id = pc.id
+            JOIN permissions p ON p.id = pv.permission_id
+            WHERE pc.user_id IS NULL AND pv.value <> 'default'"#
+            .to_string(),
+    ))
+    .all(conn)
+    .await?
+    {
+        if let Some(path) = paths.get(&row.forum_id) {
+            forum_values
+                .entry(path.clone())
+                .or_default()
+                .entry(row.group_label)
+                .or_default()
+                .insert(row.permission, row.value);
+        }
+    }
+
+    Ok(Export {
+        version: VERSION,
+        categories,
+        groups: group_values,
+        forums: forum_values,
+    })
+}
+
+/// Global values an import sets on a group
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupImport {
+    pub group_id: i32,
+    pub label: String,
+    /// (permission ID, value), leaving out "default"
+    pub values: Vec<(i32, Flag)>,
+}
+
+/// Group overrides an import sets on a forum
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForumImport {
+    pub forum_id: i32,
+    pub path: String,
+    pub matrix: Vec<MatrixValue>,
+}
+
+/// A checked import. It can only be applied if there are no errors.
+#[derive(Clone, Debug, Default)]
+pub struct Import {
+    /// Problems that stop the import, such as a group missing here
+    pub errors: Vec<String>,
+    /// Things worth knowing that don't stop it
+    pub warnings: Vec<String>,
+    pub groups: Vec<GroupImport>,
+    pub forums: Vec<ForumImport>,
+    /// What the import replaces, in the shape of [`Export::snapshot`]
+    pub current: Snapshot,
+    /// What it replaces it with
+    pub incoming: Snapshot,
+}
+
+impl Import {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Values the import adds, removes or changes
+    pub fn differences(&self) -> Vec<audit::Difference> {
+        audit::diff(&self.current, &self.incoming)
+    }
+}
+
+/// Check `incoming` against this instance and work out what importing it
+/// would change. Nothing is written.
+pub async fn prepare<C: ConnectionTrait>(conn: &C, incoming: &Export) -> Result<Import, DbErr> {
+    let mut import = Import::default();
+
+    if incoming.version != VERSION {
+        import.errors.push(format!(
+            "The export is version {}, but only version {} can be imported.",
+            incoming.version, VERSION
+        ));
+        return Ok(import);
+    }
+
+    let local = export(conn).await?;
+
+    let permission_ids: HashMap<String, i32> = permissions::Entity::find()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|p| (p.label, p.id))
+        .collect();
+    let exported_permissions: HashSet<&str> = incoming
+        .categories
+        .iter()
+        .flat_map(|c| c.permissions.iter().map(|p| p.label.as_str()))
+        .collect();
+    let mut missing: Vec<&str> = exported_permissions
+        .iter()
+        .filter(|label| !permission_ids.contains_key(**label))
+        .copied()
+        .collect();
+    missing.sort_unstable();
+    for label in missing {
+        import.warnings.push(format!(
+            "Permission {} doesn't exist here; values for it are skipped.",
+            label
+        ));
+    }
+    let mut unexported: Vec<&String> = permission_ids
+        .keys()
+        .filter(|label| !exported_permissions.contains(label.as_str()))
+        .collect();
+    unexported.sort_unstable();
+    for label in unexported {
+        import.warnings.push(format!(
+            "Permission {} isn't in the export, so it will be unset on imported groups and forums.",
+            label
+        ));
+    }
+
+    let group_ids: HashMap<String, i32> = groups::Entity::find()
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|g| (g.label, g.id))
+        .collect();
+    let mut group_imports = Vec::new();
+    for (label, values) in &incoming.groups {
+        let converted = convert_values(
+            label,
+            values,
+            &permission_ids,
+            &exported_permissions,
+            &mut import.errors,
+        );
+        match group_ids.get(label) {
+            Some(&group_id) => group_imports.push(GroupImport {
+                group_id,
+                label: label.clone(),
+                values: converted,
+            }),
+            None => import
+                .errors
+                .push(format!("Group {} doesn't exist here.", label)),
+        }
+    }
+
+    let mut forum_ids: HashMap<String, Vec<i32>> = HashMap::new();
+    for (id, path) in load_forum_paths(conn).await? {
+        forum_ids.entry(path).or_default().push(id);
+    }
+    let mut forum_imports = Vec::new();
+    for (path, groups) in &incoming.forums {
+        let mut matrix = Vec::new();
+        for (group, values) in groups {
+            let converted = convert_values(
+                &format!("{} / {}", path, group),
+                values,
+                &permission_ids,
+                &exported_permissions,
+                &mut import.errors,
+            );
+            match group_ids.get(group) {
+                Some(&group_id) => matrix.extend(converted.into_iter().map(
+                    |(permission_id, value)| MatrixValue {
+                        group_id,
+                        permission_id,
+                        value,
+                    },
+                )),
+                None => import
+                    .errors
+                    .push(format!("{}: group {} doesn't exist here.", path, group)),
+            }
+        }
+        match forum_ids.get(path).map(Vec::as_slice) {
+            Some(&[forum_id]) => forum_imports.push(ForumImport {
+                forum_id,
+                path: path.clone(),
+                matrix,
+            }),
+            Some(_) => import
+                .errors
+                .push(format!("Forum {} matches more than one forum here.", path)),
+            None => import
+                .errors
+                .push(format!("Forum {} doesn't exist here.", path)),
+        }
+    }
+
+    let mut untouched_groups: Vec<&String> = local
+        .groups
+        .keys()
+        .filter(|label| !incoming.groups.contains_key(*label))
+        .collect();
+    untouched_groups.sort_unstable();
+    for label in untouched_groups {
+        import.warnings.push(format!(
+            "Group {} isn't in the export and is left unchanged.",
+            label
+        ));
+    }
+    for path in local
+        .forums
+        .keys()
+        .filter(|path| !incoming.forums.contains_key(*path))
+    {
+        import.warnings.push(format!(
+            "Forum {} isn't in the export and is left unchanged.",
+            path
+        ));
+    }
+
+    // Only compare what the import replaces
+    let local = Export {
+        groups: local
+            .groups
+            .into_iter()
+            .filter(|(label, _)| incoming.groups.contains_key(label))
+            .collect(),
+        forums: local
+            .forums
+            .into_iter()
+            .filter(|(path, _)| incoming.forums.contains_key(path))
+            .collect(),
+        ..local
+    };
+    import.current = local.snapshot();
+    import.incoming = incoming
+        .snapshot()
+        .into_iter()
+        .filter(|(key, value)| {
+            value != "default"
+                && key
+                    .rsplit_once(": ")
+                    .map_or(false, |(_, label)| permission_ids.contains_key(label))
+        })
+        .collect();
+    import.groups = group_imports;
+    import.forums = forum_imports;
+
+    Ok(import)
+}
+
+/// The collection holding a group's global values, if it has one
+async fn group_collection<C: ConnectionTrait>(
+    conn: &C,
+    group_id: i32,
+) -> Result<Option<i32>, DbErr> {
+    #[derive(FromQueryResult)]
+    struct Row {
+        id: i32,
+    }
+
+    Ok(Row::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"SELECT pc.id
+            FROM permission_collections pc
+            WHERE pc.group_id = $1 AND pc.user_id IS NULL
+                AND NOT EXISTS (SELECT 1 FROM forum_permissions fp WHERE fp.collection_id = pc.id)
+                AND NOT EXISTS (SELECT 1 FROM chat_room_permissions cp WHERE cp.collection_id = pc.id)
+                AND NOT EXISTS (SELECT 1 FROM thread_permissions tp WHERE tp.collection_id = pc.id)
+            ORDER BY pc.id
+            LIMIT 1"#,
+        vec![group_id.into()],
+    ))
+    .one(conn)
+    .await?
+    .map(|row| row.id))
+}
+
+/// Replace a group's global values, creating the collection holding them if
+/// the group has none.
+pub async fn replace_group_values<C: ConnectionTrait>(
+    conn: &C,
+    group_id: i32,
+    values: &[(i32, Flag)],
+) -> Result<(), DbErr> {
+    let collection_id = match group_collection(conn, group_id).await? {
+        Some(id) => id,
+        None => {
+            permission_collections::ActiveModel {
+                group_id: Set(Some(group_id)),
+                user_id: Set(None),
+                ..Default::default()
+            }
+            .insert(conn)
+            .await?
+            .id
+        }
+    };
+
+    permission_values::Entity::delete_many()
+        .filter(permission_values::Column::CollectionId.eq(collection_id))
+        .exec(conn)
+        .await?;
+
+    if !values.is_empty() {
+        permission_values::Entity::insert_many(values.iter().map(|&(permission_id, value)| {
+            permission_values::ActiveModel {
+                permission_id: Set(permission_id),
+                collection_id: Set(collection_id),
+                value: Set(value),
+            }
+        }))
+        .exec(conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Write a valid import. Call `reload_forum_permissions` afterwards.
+pub async fn apply<C: ConnectionTrait>(conn: &C, import: &Import) -> Result<(), DbErr> {
+    debug_assert!(import.is_valid());
+
+    for group in &import.groups {
+        replace_group_values(conn, group.group_id, &group.values).await?;
+    }
+    for forum in &import.forums {
+        template::replace_forum_matrix(conn, forum.forum_id, &forum.matrix).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forum_paths() {
+        let paths = forum_paths(&[
+            (1, "Community".to_string(), None),
+            (2, "Off Topic".to_string(), Some(1)),
+            (3, "Games".to_string(), Some(2)),
+        ]);
+        assert_eq!(paths[&1], "Community");
+        assert_eq!(paths[&2], "Community / Off Topic");
+        assert_eq!(paths[&3], "Community / Off Topic / Games");
+    }
+
+    #[test]
+    fn test_snapshot_keys() {
+        let export = Export {
+            version: VERSION,
+            categories: Vec::new(),
+            groups: BTreeMap::from([(
+                "Guests".to_string(),
+                BTreeMap::from([("post.create".to_string(), "no".to_string())]),
+            )]),
+            forums: BTreeMap::from([(
+                "Community / Staff".to_string(),
+                BTreeMap::from([(
+                    "Guests".to_string(),
+                    BTreeMap::from([("forum.view".to_string(), "never".to_string())]),
+                )]),
+            )]),
+        };
+
+        let snapshot = export.snapshot();
+        assert_eq!(snapshot["Guests: post.create"], "no");
+        assert_eq!(snapshot["Community / Staff / Guests: forum.view"], "never");
+        assert_eq!(snapshot.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_flag() {
+        assert_eq!(parse_flag("yes"), Some(Flag::YES));
+        assert_eq!(parse_flag("never"), Some(Flag::NEVER));
+        assert_eq!(parse_flag("default"), Some(Flag::DEFAULT));
+        assert_eq!(parse_flag("maybe"), None);
+    }
+}
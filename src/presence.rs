@@ -0,0 +1,205 @@
+//! Who is online right now, and what they are looking at
+//!
+//! Alongside the rate-limited `last_activity_at` updates in [`crate::user`],
+//! every page request records its visitor here. Guests are told apart by IP
+//! address. Nothing is written to the database, so thread viewer counts cost
+//! no queries beyond loading the names to show.
+
+use crate::db::get_db_pool;
+use crate::user::{OnlineUser, ONLINE_THRESHOLD_MINUTES};
+use chrono::{DateTime, Duration, Utc};
+use sea_orm::{DbErr, FromQueryResult};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Someone browsing the site
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Visitor {
+    Member(i32),
+    /// A guest, by IP address
+    Guest(String),
+}
+
+/// What we last saw of a visitor
+#[derive(Clone, Debug)]
+pub struct Visit {
+    pub last_seen: DateTime<Utc>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// Path of the last page they loaded
+    pub path: Option<String>,
+    /// The thread that page showed, if it was one
+    pub thread_id: Option<i32>,
+}
+
+/// Visitors of one forum, kept on its [`crate::app_state::AppState`]. Clones
+/// share them.
+#[derive(Clone, Debug, Default)]
+pub struct Presence {
+    visits: Arc<RwLock<HashMap<Visitor, Visit>>>,
+}
+
+fn threshold() -> DateTime<Utc> {
+    Utc::now() - Duration::minutes(ONLINE_THRESHOLD_MINUTES)
+}
+
+/// Who is on a thread's page right now
+#[derive(Clone, Debug, Default)]
+pub struct ThreadViewers {
+    /// Members viewing who show their online status
+    pub members: Vec<OnlineUser>,
+    /// Everyone viewing, including guests and hidden members
+    pub total: usize,
+}
+
+impl Presence {
+    /// Record a request. `page` is the path when the request loaded a page
+    /// rather than an image, script or API call, and moves the visitor there.
+    pub fn record_request(
+        &self,
+        visitor: Visitor,
+        ip: Option<String>,
+        user_agent: Option<String>,
+        page: Option<String>,
+    ) {
+        let mut visits = self.visits.write().unwrap();
+        let visit = visits.entry(visitor).or_insert_with(|| Visit {
+            last_seen: Utc::now(),
+            ip: None,
+            user_agent: None,
+            path: None,
+            thread_id: None,
+        });
+        visit.last_seen = Utc::now();
+        visit.ip = ip.or(visit.ip.take());
+        visit.user_agent = user_agent.or(visit.user_agent.take());
+        if page.is_some() {
+            visit.path = page;
+            visit.thread_id = None;
+        }
+    }
+
+    /// Record that the visitor's current page shows a thread
+    pub fn record_thread_view(&self, visitor: &Visitor, thread_id: i32) {
+        if let Some(visit) = self.visits.write().unwrap().get_mut(visitor) {
+            visit.thread_id = Some(thread_id);
+        }
+    }
+
+    /// Members online now with what we last saw of them, most recent first
+    pub fn members(&self) -> Vec<(i32, Visit)> {
+        let threshold = threshold();
+        let mut members: Vec<(i32, Visit)> = self
+            .visits
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(visitor, visit)| match visitor {
+                Visitor::Member(id) if visit.last_seen > threshold => Some((*id, visit.clone())),
+                _ => None,
+            })
+            .collect();
+        members.sort_by(|a, b| b.1.last_seen.cmp(&a.1.last_seen));
+        members
+    }
+
+    /// Guests online now
+    pub fn guest_count(&self) -> usize {
+        let threshold = threshold();
+        self.visits
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(visitor, visit)| {
+                matches!(visitor, Visitor::Guest(_)) && visit.last_seen > threshold
+            })
+            .count()
+    }
+
+    /// Who is viewing a thread, naming only members who show their online status
+    pub async fn thread_viewers(&self, thread_id: i32) -> Result<ThreadViewers, DbErr> {
+        let threshold = threshold();
+        let (member_ids, total) = {
+            let visits = self.visits.read().unwrap();
+            let viewing: Vec<&Visitor> = visits
+                .iter()
+                .filter(|(_, visit)| {
+                    visit.thread_id == Some(thread_id) && visit.last_seen > threshold
+                })
+                .map(|(visitor, _)| visitor)
+                .collect();
+            let member_ids: Vec<i32> = viewing
+                .iter()
+                .filter_map(|visitor| match visitor {
+                    Visitor::Member(id) => Some(*id),
+                    Visitor::Guest(_) => None,
+                })
+                .collect();
+            (member_ids, viewing.len())
+        };
+
+        let members = if member_ids.is_empty() {
+            Vec::new()
+        } else {
+            let placeholders: Vec<String> =
+                (1..=member_ids.len()).map(|i| format!("${}", i)).collect();
+            OnlineUser::find_by_statement(sea_orm::Statement::from_sql_and_values(
+                sea_orm::DbBackend::Postgres,
+                &format!(
+                    r#"
+                    SELECT u.id, un.name, u.last_activity_at
+                    FROM users u
+                    LEFT JOIN user_names un ON un.user_id = u.id
+                    WHERE u.id IN ({})
+                      AND u.show_online = true
+                    ORDER BY un.name
+                    "#,
+                    placeholders.join(", ")
+                ),
+                member_ids.into_iter().map(Into::into).collect(),
+            ))
+            .all(get_db_pool())
+            .await?
+        };
+
+        Ok(ThreadViewers { members, total })
+    }
+
+    /// Forget visitors who have gone offline
+    /// Should be called periodically to prevent memory growth
+    pub fn prune(&self) {
+        let threshold = threshold();
+        self.visits
+            .write()
+            .unwrap()
+            .retain(|_, visit| visit.last_seen > threshold);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_view_ends_on_next_page() {
+        let presence = Presence::default();
+        let visitor = Visitor::Guest("203.0.113.7".to_string());
+        presence.record_request(
+            visitor.clone(),
+            Some("203.0.113.7".to_string()),
+            None,
+            Some("/threads/5/".to_string()),
+        );
+        presence.record_thread_view(&visitor, 5);
+
+        // Images and scripts the page loads leave the visitor where they are
+        presence.record_request(visitor.clone(), None, None, None);
+        assert_eq!(presence.visits.read().unwrap()[&visitor].thread_id, Some(5));
+
+        presence.record_request(visitor.clone(), None, None, Some("/forums/1/".to_string()));
+        let visit = presence.visits.read().unwrap()[&visitor].clone();
+        assert_eq!(visit.thread_id, None);
+        assert_eq!(visit.ip.as_deref(), Some("203.0.113.7"));
+        assert_eq!(presence.guest_count(), 1);
+    }
+}
@@ -0,0 +1,724 @@
+/// Rate limiting module for preventing abuse and DDoS attacks
+///
+/// Implements sliding window rate limiting using in-memory storage (DashMap),
+/// which only counts requests to this instance. With `backend = "redis"` in
+/// `[rate_limit]`, requests take tokens from buckets in Redis instead, so the
+/// limits hold across every instance ([`redis_limiter`]).
+///
+/// Each route class (posting, search, sign-in, uploads...) has its own budget
+/// in `rate_limit_policies`, edited at `/admin/rate-limits`. Members of exempt
+/// groups are not counted. Policies reload on every instance when changed.
+///
+/// # Example Usage
+///
+/// ```rust,ignore
+/// use crate::rate_limit::{check_login_rate_limit, RateLimitError};
+///
+/// // In a login handler
+/// if let Err(e) = check_login_rate_limit("192.168.1.1", "username").await {
+///     return Err(error::ErrorTooManyRequests(
+///         format!("Too many attempts. Try again in {} seconds", e.retry_after_seconds)
+///     ));
+/// }
+/// ```
+pub mod redis_limiter;
+
+use arc_swap::ArcSwap;
+use dashmap::DashMap;
+use once_cell::sync::{Lazy, OnceCell};
+use redis_limiter::RedisLimiter;
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::orm::{rate_limit_exemptions, rate_limit_policies};
+
+/// Global rate limiter instance
+pub static RATE_LIMITER: Lazy<Arc<RateLimiter>> = Lazy::new(|| Arc::new(RateLimiter::new()));
+
+/// Shared token buckets, when the Redis backend is configured
+static REDIS_LIMITER: OnceCell<RedisLimiter> = OnceCell::new();
+
+/// Global rate limit configuration (hot-reloadable)
+static RATE_LIMIT_CONFIG: Lazy<ArcSwap<RateLimitConfig>> =
+    Lazy::new(|| ArcSwap::from_pointee(RateLimitConfig::default()));
+
+/// Kinds of request that share a budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    Login,
+    TwoFactor,
+    PasswordReset,
+    EmailVerification,
+    Registration,
+    PostCreation,
+    ThreadCreation,
+    Search,
+    Suggest,
+    Api,
+    FileUpload,
+    Report,
+    Reaction,
+}
+
+impl RouteClass {
+    pub fn all() -> [RouteClass; 13] {
+        [
+            RouteClass::Login,
+            RouteClass::TwoFactor,
+            RouteClass::PasswordReset,
+            RouteClass::EmailVerification,
+            RouteClass::Registration,
+            RouteClass::PostCreation,
+            RouteClass::ThreadCreation,
+            RouteClass::Search,
+            RouteClass::Suggest,
+            RouteClass::Api,
+            RouteClass::FileUpload,
+            RouteClass::Report,
+            RouteClass::Reaction,
+        ]
+    }
+
+    /// Key in `rate_limit_policies`, also used to key the limiter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RouteClass::Login => "login",
+            RouteClass::TwoFactor => "two_factor",
+            RouteClass::PasswordReset => "password_reset",
+            RouteClass::EmailVerification => "email_verification",
+            RouteClass::Registration => "registration",
+            RouteClass::PostCreation => "post_creation",
+            RouteClass::ThreadCreation => "thread_creation",
+            RouteClass::Search => "search",
+            RouteClass::Suggest => "suggest",
+            RouteClass::Api => "api",
+            RouteClass::FileUpload => "file_upload",
+            RouteClass::Report => "report",
+            RouteClass::Reaction => "reaction",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().into_iter().find(|class| class.as_str() == name)
+    }
+
+    /// Name shown in the admin panel
+    pub fn label(&self) -> &'static str {
+        match self {
+            RouteClass::Login => "Sign in",
+            RouteClass::TwoFactor => "Two-factor codes",
+            RouteClass::PasswordReset => "Password resets",
+            RouteClass::EmailVerification => "Verification emails",
+            RouteClass::Registration => "Registration",
+            RouteClass::PostCreation => "Posts and messages",
+            RouteClass::ThreadCreation => "New threads",
+            RouteClass::Search => "Search",
+            RouteClass::Suggest => "Search suggestions",
+            RouteClass::Api => "API and lookups",
+            RouteClass::FileUpload => "Uploads",
+            RouteClass::Report => "Reports",
+            RouteClass::Reaction => "Reactions",
+        }
+    }
+
+    /// Whether requests are counted per member, so groups can be exempted.
+    /// Sign-in and account recovery are counted per address before anyone is
+    /// signed in.
+    pub fn is_per_member(&self) -> bool {
+        !matches!(
+            self,
+            RouteClass::Login
+                | RouteClass::TwoFactor
+                | RouteClass::PasswordReset
+                | RouteClass::EmailVerification
+                | RouteClass::Registration
+        )
+    }
+
+    /// Budget used until the policies are loaded, matching the migration
+    fn default_policy(&self) -> RateLimitPolicy {
+        let (max_requests, window_seconds) = match self {
+            RouteClass::Login | RouteClass::TwoFactor => (5, 300),
+            RouteClass::PasswordReset
+            | RouteClass::EmailVerification
+            | RouteClass::Registration => (3, 3600),
+            RouteClass::PostCreation => (10, 60),
+            RouteClass::ThreadCreation | RouteClass::Report => (5, 300),
+            RouteClass::Search | RouteClass::Reaction => (30, 60),
+            RouteClass::Suggest => (120, 60),
+            RouteClass::Api => (60, 60),
+            RouteClass::FileUpload => (20, 60),
+        };
+        RateLimitPolicy {
+            max_requests,
+            window: Duration::from_secs(window_seconds),
+            burst: 0,
+            exempt_groups: Vec::new(),
+        }
+    }
+}
+
+/// Budget of one route class
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitPolicy {
+    pub max_requests: usize,
+    pub window: Duration,
+    /// Requests that may come back to back; 0 leaves requests unspaced
+    pub burst: usize,
+    pub exempt_groups: Vec<i32>,
+}
+
+impl RateLimitPolicy {
+    pub fn is_exempt(&self, groups: &[i32]) -> bool {
+        groups
+            .iter()
+            .any(|group| self.exempt_groups.contains(group))
+    }
+}
+
+/// Rate limit configuration loaded from `rate_limit_policies`
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    policies: HashMap<RouteClass, RateLimitPolicy>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            policies: RouteClass::all()
+                .into_iter()
+                .map(|class| (class, class.default_policy()))
+                .collect(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Build from stored rows. Unknown classes are ignored and missing ones
+    /// keep their defaults.
+    pub fn from_models(
+        policies: Vec<rate_limit_policies::Model>,
+        exemptions: Vec<rate_limit_exemptions::Model>,
+    ) -> Self {
+        let mut config = Self::default();
+        for row in policies {
+            if let Some(class) = RouteClass::from_name(&row.route_class) {
+                config.policies.insert(
+                    class,
+                    RateLimitPolicy {
+                        max_requests: row.max_requests.max(1) as usize,
+                        window: Duration::from_secs(row.window_seconds.max(1) as u64),
+                        burst: row.burst.max(0) as usize,
+                        exempt_groups: Vec::new(),
+                    },
+                );
+            }
+        }
+        for row in exemptions {
+            if let Some(policy) = RouteClass::from_name(&row.route_class)
+                .and_then(|class| config.policies.get_mut(&class))
+            {
+                policy.exempt_groups.push(row.group_id);
+            }
+        }
+        config
+    }
+
+    pub fn policy(&self, class: RouteClass) -> &RateLimitPolicy {
+        &self.policies[&class]
+    }
+}
+
+/// Load rate limit policies (call at startup and whenever they change)
+pub async fn reload_rate_limits(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let policies = rate_limit_policies::Entity::find().all(db).await?;
+    let exemptions = rate_limit_exemptions::Entity::find().all(db).await?;
+    RATE_LIMIT_CONFIG.store(Arc::new(RateLimitConfig::from_models(policies, exemptions)));
+    log::info!("Rate limit policies loaded");
+    Ok(())
+}
+
+/// Get the current rate limit configuration
+pub fn get_rate_limit_config() -> Arc<RateLimitConfig> {
+    RATE_LIMIT_CONFIG.load_full()
+}
+
+/// Pick the backend. Called once at startup; limits stay in process memory
+/// unless `backend = "redis"` and Redis can be reached.
+pub async fn init(redis_url: Option<&str>) {
+    match crate::app_config::rate_limit().backend.as_str() {
+        "memory" => {}
+        "redis" => match redis_url {
+            Some(url) => match RedisLimiter::connect(url).await {
+                Ok(limiter) => {
+                    log::info!("Rate limits enforced through Redis.");
+                    if REDIS_LIMITER.set(limiter).is_err() {
+                        log::warn!("Rate limiter already initialized.");
+                    }
+                }
+                Err(err) => log::error!(
+                    "Failed to connect the rate limiter to Redis, counting per instance: {}",
+                    err
+                ),
+            },
+            None => log::warn!(
+                "Rate limit backend is redis but REDIS_URL is not set; counting per instance."
+            ),
+        },
+        other => log::warn!("Unsupported rate limit backend {:?}; using memory.", other),
+    }
+}
+
+/// Rate limiter using in-memory storage
+pub struct RateLimiter {
+    /// Map of (action_type:identifier) -> Request timestamps
+    requests: DashMap<String, Vec<Instant>>,
+}
+
+/// Error returned when rate limit is exceeded
+#[derive(Debug, Clone)]
+pub struct RateLimitError {
+    /// Number of seconds until the rate limit resets
+    pub retry_after_seconds: u64,
+}
+
+impl RateLimiter {
+    /// Create a new rate limiter
+    pub fn new() -> Self {
+        Self {
+            requests: DashMap::new(),
+        }
+    }
+
+    /// Check if a request should be rate limited
+    ///
+    /// # Arguments
+    /// * `action` - The action being rate limited (e.g., "login", "post", "thread")
+    /// * `identifier` - Unique identifier for the requester (e.g., IP address, user ID)
+    /// * `max_requests` - Maximum number of requests allowed in the window
+    /// * `window` - Time window for the rate limit
+    ///
+    /// # Returns
+    /// * `Ok(())` if the request is allowed
+    /// * `Err(RateLimitError)` if the rate limit is exceeded
+    pub fn check_rate_limit(
+        &self,
+        action: &str,
+        identifier: &str,
+        max_requests: usize,
+        window: Duration,
+    ) -> Result<(), RateLimitError> {
+        self.check_rate_limit_with_burst(action, identifier, max_requests, window, 0)
+    }
+
+    /// Like `check_rate_limit`, but only `burst` requests may come back to
+    /// back. After that each must come at least `window / max_requests` after
+    /// the one before. A burst of 0 leaves requests unspaced.
+    pub fn check_rate_limit_with_burst(
+        &self,
+        action: &str,
+        identifier: &str,
+        max_requests: usize,
+        window: Duration,
+        burst: usize,
+    ) -> Result<(), RateLimitError> {
+        let key = format!("{}:{}", action, identifier);
+        let now = Instant::now();
+
+        // Get or create entry for this key
+        let mut entry = self.requests.entry(key).or_default();
+
+        // Remove requests outside the time window (sliding window)
+        entry.retain(|&timestamp| now.duration_since(timestamp) < window);
+
+        // Check if we've exceeded the limit
+        if entry.len() >= max_requests {
+            // Calculate how long until the oldest request expires
+            let oldest = entry[0];
+            let retry_after = window.saturating_sub(now.duration_since(oldest));
+
+            return Err(RateLimitError {
+                retry_after_seconds: retry_after.as_secs() + 1, // Round up
+            });
+        }
+
+        // Past the burst, space the remaining requests evenly over the window
+        if burst > 0 && entry.len() >= burst {
+            let spacing = window / max_requests.max(1) as u32;
+            let since_last = now.duration_since(entry[entry.len() - 1]);
+            if since_last < spacing {
+                return Err(RateLimitError {
+                    retry_after_seconds: (spacing - since_last).as_secs() + 1,
+                });
+            }
+        }
+
+        // Add current request
+        entry.push(now);
+
+        Ok(())
+    }
+
+    /// Clean up old entries to prevent memory leaks
+    ///
+    /// This should be called periodically (e.g., every 5 minutes) to remove
+    /// entries for keys that haven't been used recently.
+    pub fn cleanup_old_entries(&self) {
+        self.requests.retain(|_, timestamps| {
+            // Keep entries that have recent requests
+            !timestamps.is_empty()
+        });
+    }
+
+    /// Get the current request count for a specific action/identifier
+    ///
+    /// Returns the number of requests within the time window
+    pub fn get_request_count(&self, action: &str, identifier: &str, window: Duration) -> u32 {
+        let key = format!("{}:{}", action, identifier);
+        let now = Instant::now();
+
+        if let Some(entry) = self.requests.get(&key) {
+            entry
+                .iter()
+                .filter(|&&timestamp| now.duration_since(timestamp) < window)
+                .count() as u32
+        } else {
+            0
+        }
+    }
+
+    /// Clear all requests for a specific action/identifier
+    pub fn clear_requests(&self, action: &str, identifier: &str) {
+        let key = format!("{}:{}", action, identifier);
+        self.requests.remove(&key);
+    }
+
+    /// Get the number of tracked keys (for monitoring/debugging)
+    pub fn tracked_keys_count(&self) -> usize {
+        self.requests.len()
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Helper functions for common rate-limited actions
+// ============================================================================
+
+/// Check a request against its route class's policy. Members of an exempt
+/// group pass without being counted. Should Redis fail, the request is
+/// counted by this instance alone rather than let through unchecked.
+pub async fn check(
+    class: RouteClass,
+    identifier: &str,
+    groups: &[i32],
+) -> Result<(), RateLimitError> {
+    let config = get_rate_limit_config();
+    let policy = config.policy(class);
+    if policy.is_exempt(groups) {
+        return Ok(());
+    }
+
+    if let Some(limiter) = REDIS_LIMITER.get() {
+        match limiter.take(class.as_str(), identifier, policy).await {
+            Ok(None) => return Ok(()),
+            Ok(Some(wait)) => {
+                return Err(RateLimitError {
+                    retry_after_seconds: wait.as_secs() + 1, // Round up
+                });
+            }
+            Err(err) => log::error!("Redis rate limit check failed: {}", err),
+        }
+    }
+
+    RATE_LIMITER.check_rate_limit_with_burst(
+        class.as_str(),
+        identifier,
+        policy.max_requests,
+        policy.window,
+        policy.burst,
+    )
+}
+
+/// Check rate limit for login attempts
+///
+/// Uses configurable limit per IP+username combination
+pub async fn check_login_rate_limit(ip: &str, username: &str) -> Result<(), RateLimitError> {
+    check(RouteClass::Login, &format!("{}:{}", ip, username), &[]).await
+}
+
+/// Check rate limit for two-factor authentication attempts
+///
+/// Uses configurable limit per IP address
+pub async fn check_two_factor_rate_limit(ip: &str) -> Result<(), RateLimitError> {
+    check(RouteClass::TwoFactor, ip, &[]).await
+}
+
+/// Check rate limit for password reset requests
+///
+/// Uses configurable limit per IP address
+pub async fn check_password_reset_rate_limit(ip: &str) -> Result<(), RateLimitError> {
+    check(RouteClass::PasswordReset, ip, &[]).await
+}
+
+/// Check rate limit for email verification resend requests
+///
+/// Uses configurable limit per IP address
+pub async fn check_email_verification_rate_limit(ip: &str) -> Result<(), RateLimitError> {
+    check(RouteClass::EmailVerification, ip, &[]).await
+}
+
+/// Check rate limit for user registration
+///
+/// Uses configurable limit per IP address
+pub async fn check_registration_rate_limit(ip: &str) -> Result<(), RateLimitError> {
+    check(RouteClass::Registration, ip, &[]).await
+}
+
+/// Check rate limit for post creation
+///
+/// Uses configurable limit per user. Applies to:
+/// - Forum posts
+/// - Profile posts
+/// - Conversation messages
+pub async fn check_post_rate_limit(user_id: i32, groups: &[i32]) -> Result<(), RateLimitError> {
+    check(RouteClass::PostCreation, &user_id.to_string(), groups).await
+}
+
+/// Check rate limit for thread creation
+///
+/// Uses configurable limit per user
+pub async fn check_thread_rate_limit(user_id: i32, groups: &[i32]) -> Result<(), RateLimitError> {
+    check(RouteClass::ThreadCreation, &user_id.to_string(), groups).await
+}
+
+/// Check rate limit for search queries
+///
+/// Uses configurable limit per IP or user
+pub async fn check_search_rate_limit(
+    identifier: &str,
+    groups: &[i32],
+) -> Result<(), RateLimitError> {
+    check(RouteClass::Search, identifier, groups).await
+}
+
+/// Check rate limit for general API requests
+///
+/// Applies to: user search, URL unfurl, etc.
+pub async fn check_api_rate_limit(identifier: &str, groups: &[i32]) -> Result<(), RateLimitError> {
+    check(RouteClass::Api, identifier, groups).await
+}
+
+/// Check rate limit for search-while-you-type suggestions
+///
+/// Kept apart from full search, since a user sends one per pause in typing
+pub async fn check_suggest_rate_limit(
+    identifier: &str,
+    groups: &[i32],
+) -> Result<(), RateLimitError> {
+    check(RouteClass::Suggest, identifier, groups).await
+}
+
+/// Check rate limit for file uploads
+///
+/// Uses configurable limit per user
+pub async fn check_file_upload_rate_limit(
+    user_id: i32,
+    groups: &[i32],
+) -> Result<(), RateLimitError> {
+    check(RouteClass::FileUpload, &user_id.to_string(), groups).await
+}
+
+/// Check rate limit for report submissions
+///
+/// Uses configurable limit per user
+pub async fn check_report_rate_limit(user_id: i32, groups: &[i32]) -> Result<(), RateLimitError> {
+    check(RouteClass::Report, &user_id.to_string(), groups).await
+}
+
+/// Check rate limit for reaction toggles
+///
+/// Uses configurable limit per user
+pub async fn check_reaction_rate_limit(user_id: i32, groups: &[i32]) -> Result<(), RateLimitError> {
+    check(RouteClass::Reaction, &user_id.to_string(), groups).await
+}
+
+/// Start background cleanup task
+///
+/// This function should be called once at application startup to spawn
+/// a background task that periodically cleans up old rate limit entries.
+///
+/// Example (in main.rs or similar binary):
+/// ```rust,ignore
+/// // Spawn cleanup task
+/// actix_web::rt::spawn(async {
+///     let mut interval = actix_rt::time::interval(Duration::from_secs(300));
+///     loop {
+///         interval.tick().await;
+///         dumpster::rate_limit::RATE_LIMITER.cleanup_old_entries();
+///         log::debug!("Rate limiter cleanup completed");
+///     }
+/// });
+/// ```
+pub fn cleanup_old_entries_public() {
+    RATE_LIMITER.cleanup_old_entries();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_allows_requests_within_limit() {
+        let limiter = RateLimiter::new();
+
+        // Should allow first 3 requests
+        for i in 0..3 {
+            assert!(
+                limiter
+                    .check_rate_limit("test", "user1", 3, Duration::from_secs(10))
+                    .is_ok(),
+                "Request {} should be allowed",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_blocks_requests_over_limit() {
+        let limiter = RateLimiter::new();
+
+        // Allow first 3 requests
+        for _ in 0..3 {
+            limiter
+                .check_rate_limit("test", "user1", 3, Duration::from_secs(10))
+                .unwrap();
+        }
+
+        // 4th request should be blocked
+        let result = limiter.check_rate_limit("test", "user1", 3, Duration::from_secs(10));
+        assert!(result.is_err(), "4th request should be blocked");
+
+        if let Err(err) = result {
+            assert!(err.retry_after_seconds > 0, "Should have retry_after time");
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_different_identifiers_independent() {
+        let limiter = RateLimiter::new();
+
+        // Use up limit for user1
+        for _ in 0..3 {
+            limiter
+                .check_rate_limit("test", "user1", 3, Duration::from_secs(10))
+                .unwrap();
+        }
+
+        // user2 should still be allowed
+        assert!(
+            limiter
+                .check_rate_limit("test", "user2", 3, Duration::from_secs(10))
+                .is_ok(),
+            "Different identifier should have independent limit"
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_cleanup() {
+        let limiter = RateLimiter::new();
+
+        // Create some entries
+        limiter
+            .check_rate_limit("test", "user1", 10, Duration::from_secs(10))
+            .unwrap();
+        limiter
+            .check_rate_limit("test", "user2", 10, Duration::from_secs(10))
+            .unwrap();
+
+        assert_eq!(limiter.tracked_keys_count(), 2);
+
+        // Clean up - entries should remain since they have recent requests
+        limiter.cleanup_old_entries();
+        assert_eq!(limiter.tracked_keys_count(), 2);
+    }
+
+    #[test]
+    fn test_rate_limit_burst_spaces_requests() {
+        let limiter = RateLimiter::new();
+        let window = Duration::from_secs(60);
+
+        // Two back to back, then the third must wait window / max = 6 seconds
+        for _ in 0..2 {
+            limiter
+                .check_rate_limit_with_burst("test", "user1", 10, window, 2)
+                .unwrap();
+        }
+        let err = limiter
+            .check_rate_limit_with_burst("test", "user1", 10, window, 2)
+            .unwrap_err();
+        assert!(err.retry_after_seconds > 0 && err.retry_after_seconds <= 7);
+
+        // Without a burst the whole budget is available at once
+        for _ in 0..10 {
+            limiter
+                .check_rate_limit_with_burst("test", "user2", 10, window, 0)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_default_rate_limit_config() {
+        let config = RateLimitConfig::default();
+
+        // Verify defaults match migration values
+        let login = config.policy(RouteClass::Login);
+        assert_eq!(login.max_requests, 5);
+        assert_eq!(login.window, Duration::from_secs(300));
+        assert_eq!(config.policy(RouteClass::Registration).max_requests, 3);
+        assert_eq!(config.policy(RouteClass::PostCreation).max_requests, 10);
+        assert_eq!(config.policy(RouteClass::ThreadCreation).max_requests, 5);
+        assert_eq!(config.policy(RouteClass::Search).max_requests, 30);
+        assert_eq!(config.policy(RouteClass::Suggest).max_requests, 120);
+        assert_eq!(config.policy(RouteClass::FileUpload).max_requests, 20);
+    }
+
+    #[test]
+    fn test_config_from_models() {
+        let policy = |route_class: &str, max_requests| rate_limit_policies::Model {
+            route_class: route_class.to_owned(),
+            max_requests,
+            window_seconds: 120,
+            burst: 3,
+            updated_at: chrono::Utc::now().naive_utc(),
+            updated_by: None,
+        };
+        let config = RateLimitConfig::from_models(
+            vec![policy("search", 90), policy("retired", 1)],
+            vec![rate_limit_exemptions::Model {
+                route_class: "search".to_owned(),
+                group_id: 4,
+            }],
+        );
+
+        let search = config.policy(RouteClass::Search);
+        assert_eq!(search.max_requests, 90);
+        assert_eq!(search.window, Duration::from_secs(120));
+        assert_eq!(search.burst, 3);
+        assert!(search.is_exempt(&[1, 4]));
+        assert!(!search.is_exempt(&[1]));
+        // Classes without a row keep their defaults
+        assert_eq!(config.policy(RouteClass::Api).max_requests, 60);
+        assert!(!RouteClass::Login.is_per_member());
+        assert_eq!(
+            RouteClass::from_name("file_upload"),
+            Some(RouteClass::FileUpload)
+        );
+    }
+}
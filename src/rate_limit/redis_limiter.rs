@@ -0,0 +1,111 @@
+//! Token buckets in Redis, shared by every instance pointed at the same server.
+//!
+//! A policy becomes a bucket holding `burst` tokens (or `max_requests` when
+//! the burst is 0) that refills at `max_requests` per window. Each request
+//! takes a token. The script runs atomically on the server, using its clock,
+//! so instances with drifting clocks still agree.
+
+use super::RateLimitPolicy;
+use once_cell::sync::Lazy;
+use redis::aio::MultiplexedConnection;
+use std::time::Duration;
+
+/// Prefix of every bucket key
+const KEY_PREFIX: &str = "dumpster:rate_limit:";
+
+/// Takes a token from the bucket in KEYS[1], refilled since it was last
+/// touched. Returns 0 when a token was taken, or the milliseconds until one
+/// will be there.
+static TAKE_TOKEN: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+local capacity = tonumber(ARGV[1])
+local interval = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'at')
+local tokens = tonumber(bucket[1])
+local at = tonumber(bucket[2])
+if tokens == nil or at == nil then
+    tokens = capacity
+    at = now
+end
+
+tokens = math.min(capacity, tokens + (now - at) / interval)
+if tokens < 1 then
+    return math.ceil((1 - tokens) * interval)
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens - 1), 'at', tostring(now))
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity * interval))
+return 0
+"#,
+    )
+});
+
+pub struct RedisLimiter {
+    connection: MultiplexedConnection,
+}
+
+impl RedisLimiter {
+    pub async fn connect(url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(url)?;
+        let connection = client.get_multiplexed_tokio_connection().await?;
+        Ok(Self { connection })
+    }
+
+    /// Take a token for `identifier` from the bucket of `action`. Returns
+    /// how long to wait when the bucket is empty.
+    pub async fn take(
+        &self,
+        action: &str,
+        identifier: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<Option<Duration>, redis::RedisError> {
+        let (capacity, interval) = bucket_shape(policy);
+        let mut connection = self.connection.clone();
+        let wait_ms: u64 = TAKE_TOKEN
+            .key(format!("{}{}:{}", KEY_PREFIX, action, identifier))
+            .arg(capacity)
+            .arg(interval)
+            .invoke_async(&mut connection)
+            .await?;
+
+        Ok(match wait_ms {
+            0 => None,
+            wait_ms => Some(Duration::from_millis(wait_ms)),
+        })
+    }
+}
+
+/// Tokens the bucket holds and milliseconds it takes to refill one.
+fn bucket_shape(policy: &RateLimitPolicy) -> (usize, f64) {
+    let max_requests = policy.max_requests.max(1);
+    let capacity = match policy.burst {
+        0 => max_requests,
+        burst => burst.min(max_requests),
+    };
+    let interval = policy.window.as_millis() as f64 / max_requests as f64;
+    (capacity, interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_shape() {
+        let policy = |max_requests, burst| RateLimitPolicy {
+            max_requests,
+            window: Duration::from_secs(60),
+            burst,
+            exempt_groups: Vec::new(),
+        };
+
+        assert_eq!(bucket_shape(&policy(10, 0)), (10, 6000.0));
+        assert_eq!(bucket_shape(&policy(10, 3)), (3, 6000.0));
+        // A burst above the budget can't hold more than the budget
+        assert_eq!(bucket_shape(&policy(10, 50)), (10, 6000.0));
+    }
+}
@@ -0,0 +1,90 @@
+//! Reputation recalculation
+//!
+//! A trigger keeps `users.reputation_score` current as reactions come and go,
+//! using each reaction type's `reputation_value` at the time. When an admin
+//! changes a reaction's weight, existing reactions keep their old value until
+//! the nightly recalculation recomputes every score from the current weights.
+
+use crate::db::get_db_pool;
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+use sea_orm::{ConnectionTrait, DbBackend, DbErr, Statement};
+
+/// Hour of the day, in UTC, at which reputation is recalculated
+const RECALCULATE_HOUR: u32 = 4;
+
+/// Recomputes every user's reputation, touching only rows that changed
+pub const RECALCULATE_SQL: &str = r#"UPDATE users u
+    SET reputation_score = s.reputation_score
+    FROM (
+        SELECT u2.id, COALESCE((
+            SELECT SUM(rt.reputation_value)
+            FROM ugc_reactions ur
+            JOIN reaction_types rt ON rt.id = ur.reaction_type_id
+            JOIN posts p ON p.ugc_id = ur.ugc_id
+            WHERE p.user_id = u2.id
+        ), 0)::INT AS reputation_score
+        FROM users u2
+    ) s
+    WHERE s.id = u.id AND u.reputation_score <> s.reputation_score"#;
+
+/// Recompute all reputation scores from current reaction weights. Returns
+/// the number of users whose score changed.
+pub async fn recalculate() -> Result<u64, DbErr> {
+    let result = get_db_pool()
+        .execute(Statement::from_string(
+            DbBackend::Postgres,
+            RECALCULATE_SQL.to_owned(),
+        ))
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// The first scheduled recalculation strictly after `now`
+fn next_run(now: NaiveDateTime) -> NaiveDateTime {
+    let today = now
+        .date()
+        .and_time(NaiveTime::from_hms_opt(RECALCULATE_HOUR, 0, 0).unwrap());
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+/// Start the nightly reputation recalculation worker
+pub fn spawn_worker() {
+    actix_web::rt::spawn(async move {
+        loop {
+            let now = chrono::Utc::now().naive_utc();
+            let wait = (next_run(now) - now)
+                .to_std()
+                .unwrap_or(std::time::Duration::ZERO);
+            actix_web::rt::time::sleep(wait).await;
+            match recalculate().await {
+                Ok(0) => {}
+                Ok(count) => log::info!("Recalculated reputation for {} user(s)", count),
+                Err(e) => log::error!("Failed to recalculate reputation: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_run() {
+        assert_eq!(next_run(at(10, 1, 30)), at(10, 4, 0));
+        assert_eq!(next_run(at(10, 4, 0)), at(11, 4, 0));
+        assert_eq!(next_run(at(10, 23, 59)), at(11, 4, 0));
+    }
+}
@@ -0,0 +1,172 @@
+//! Instant-runoff tabulation for ranked polls
+//!
+//! Each ballot lists options from most to least preferred. Every round,
+//! each ballot counts for its highest ranked option still in the running.
+//! An option with more than half of those votes wins. Otherwise the option
+//! with the fewest votes is eliminated and its ballots move to their next
+//! choice. Ballots with no choices left are exhausted.
+//!
+//! Ties for elimination go to the option that had fewer votes in the latest
+//! earlier round where they differed, then to the one listed last, so the
+//! same ballots always give the same result.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One round of counting
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Round {
+    /// Votes for each option still in the running, in listed order
+    pub tallies: Vec<(i32, i64)>,
+    /// Ballots with no choices left in the running
+    pub exhausted: i64,
+    /// The option eliminated at the end of the round, if no option won
+    pub eliminated: Option<i32>,
+}
+
+/// The full count of a ranked poll
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Runoff {
+    pub rounds: Vec<Round>,
+    pub winner: Option<i32>,
+    pub ballots: i64,
+}
+
+/// Count ranked ballots. `options` are the poll's option ids in listed
+/// order; choices on a ballot that aren't among them are skipped.
+pub fn tabulate(options: &[i32], ballots: &[Vec<i32>]) -> Runoff {
+    let mut runoff = Runoff {
+        ballots: ballots.len() as i64,
+        ..Default::default()
+    };
+    let mut running: Vec<i32> = options.to_vec();
+    // Each option's votes in every round so far, for breaking ties
+    let mut history: HashMap<i32, Vec<i64>> = HashMap::new();
+
+    while !running.is_empty() {
+        let mut counts: HashMap<i32, i64> = running.iter().map(|id| (*id, 0)).collect();
+        let mut exhausted = 0;
+        for ballot in ballots {
+            match ballot.iter().find(|id| counts.contains_key(id)) {
+                Some(id) => *counts.get_mut(id).unwrap() += 1,
+                None => exhausted += 1,
+            }
+        }
+
+        let tallies: Vec<(i32, i64)> = running.iter().map(|id| (*id, counts[id])).collect();
+        for (id, votes) in &tallies {
+            history.entry(*id).or_default().push(*votes);
+        }
+
+        let active: i64 = tallies.iter().map(|(_, votes)| votes).sum();
+        if active == 0 {
+            runoff.rounds.push(Round {
+                tallies,
+                exhausted,
+                eliminated: None,
+            });
+            break;
+        }
+
+        if let Some((id, _)) = tallies.iter().find(|(_, votes)| votes * 2 > active) {
+            runoff.winner = Some(*id);
+            runoff.rounds.push(Round {
+                tallies,
+                exhausted,
+                eliminated: None,
+            });
+            break;
+        }
+
+        let loser = weakest(&running, &history);
+        running.retain(|id| *id != loser);
+        runoff.rounds.push(Round {
+            tallies,
+            exhausted,
+            eliminated: Some(loser),
+        });
+    }
+
+    runoff
+}
+
+/// The option to eliminate from those running
+fn weakest(running: &[i32], history: &HashMap<i32, Vec<i64>>) -> i32 {
+    let mut weakest = running[running.len() - 1];
+    for id in running.iter().rev().skip(1) {
+        // Later rounds decide first, walking back to the first round
+        let fewer = history[id]
+            .iter()
+            .rev()
+            .zip(history[&weakest].iter().rev())
+            .find(|(a, b)| a != b)
+            .is_some_and(|(a, b)| a < b);
+        if fewer {
+            weakest = *id;
+        }
+    }
+    weakest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_majority_in_first_round() {
+        let runoff = tabulate(&[1, 2, 3], &[vec![1, 2], vec![1], vec![2, 1]]);
+        assert_eq!(runoff.winner, Some(1));
+        assert_eq!(runoff.ballots, 3);
+        assert_eq!(runoff.rounds.len(), 1);
+        assert_eq!(runoff.rounds[0].tallies, vec![(1, 2), (2, 1), (3, 0)]);
+    }
+
+    #[test]
+    fn test_votes_transfer_after_elimination() {
+        let ballots = vec![
+            vec![1],
+            vec![1],
+            vec![1],
+            vec![2, 3],
+            vec![2, 3],
+            vec![3, 2],
+            vec![3, 2],
+            vec![3, 2],
+            vec![4, 2],
+        ];
+        let runoff = tabulate(&[1, 2, 3, 4], &ballots);
+
+        assert_eq!(runoff.rounds[0].eliminated, Some(4));
+        assert_eq!(runoff.rounds[1].tallies, vec![(1, 3), (2, 3), (3, 3)]);
+        // Three-way tie, broken by the first round where 2 had fewer votes
+        assert_eq!(runoff.rounds[1].eliminated, Some(2));
+        assert_eq!(runoff.rounds[2].tallies, vec![(1, 3), (3, 5)]);
+        assert_eq!(runoff.rounds[2].exhausted, 1);
+        assert_eq!(runoff.winner, Some(3));
+    }
+
+    #[test]
+    fn test_ties_are_reproducible() {
+        let ballots = vec![vec![1, 2], vec![2, 1]];
+        let first = tabulate(&[1, 2], &ballots);
+        assert_eq!(first.rounds[0].eliminated, Some(2));
+        assert_eq!(first.winner, Some(1));
+        assert_eq!(first, tabulate(&[1, 2], &ballots));
+    }
+
+    #[test]
+    fn test_no_ballots() {
+        let runoff = tabulate(&[1, 2], &[]);
+        assert_eq!(runoff.winner, None);
+        assert_eq!(runoff.rounds.len(), 1);
+        assert_eq!(runoff.rounds[0].tallies, vec![(1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn test_unknown_choices_are_skipped() {
+        let runoff = tabulate(&[1, 2], &[vec![9, 2], vec![9]]);
+        assert_eq!(runoff.rounds[0].tallies, vec![(1, 0), (2, 1)]);
+        assert_eq!(runoff.rounds[0].exhausted, 1);
+        assert_eq!(runoff.winner, Some(2));
+    }
+}
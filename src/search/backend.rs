@@ -0,0 +1,188 @@
+//! The interface every search backend provides.
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Search operation errors.
+#[derive(Debug)]
+pub enum SearchError {
+    /// Database error
+    Database(sea_orm::DbErr),
+    /// Request to the search server failed
+    Http(reqwest::Error),
+    /// The search server answered with an error
+    Server(String),
+}
+
+impl std::fmt::Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchError::Database(e) => write!(f, "database error: {}", e),
+            SearchError::Http(e) => write!(f, "search request failed: {}", e),
+            SearchError::Server(msg) => write!(f, "search server error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+impl From<sea_orm::DbErr> for SearchError {
+    fn from(e: sea_orm::DbErr) -> Self {
+        SearchError::Database(e)
+    }
+}
+
+impl From<reqwest::Error> for SearchError {
+    fn from(e: reqwest::Error) -> Self {
+        SearchError::Http(e)
+    }
+}
+
+/// What kind of content a document is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DocumentKind {
+    Thread,
+    Post,
+}
+
+impl DocumentKind {
+    /// Name used in the indexing queue and for index names
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentKind::Thread => "thread",
+            DocumentKind::Post => "post",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "thread" => Some(DocumentKind::Thread),
+            "post" => Some(DocumentKind::Post),
+            _ => None,
+        }
+    }
+}
+
+/// Restrictions on a search, beyond its words.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchFilter {
+    /// Only content by this user
+    pub user_id: Option<i32>,
+    /// Only content in these forums
+    pub forum_ids: Option<Vec<i32>>,
+    /// Only content created at or after this time
+    pub since: Option<NaiveDateTime>,
+    /// Only content created before this time
+    pub before: Option<NaiveDateTime>,
+    /// Only content with at least this many reactions. Threads count the
+    /// reactions on their first post.
+    pub min_reactions: Option<i32>,
+}
+
+/// A thread matching a search.
+#[derive(Debug, Clone, sea_orm::FromQueryResult)]
+pub struct ThreadHit {
+    pub id: i32,
+    pub title: String,
+    pub forum_id: i32,
+    pub user_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub rank: f32,
+}
+
+/// A post matching a search.
+#[derive(Debug, Clone, sea_orm::FromQueryResult)]
+pub struct PostHit {
+    pub id: i32,
+    pub thread_id: i32,
+    /// The start of the post
+    pub content: String,
+    pub user_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub rank: f32,
+}
+
+/// A thread as sent to an external index.
+#[derive(Debug, Clone, Serialize, Deserialize, sea_orm::FromQueryResult)]
+pub struct ThreadDocument {
+    pub id: i32,
+    pub title: String,
+    pub forum_id: i32,
+    pub user_id: Option<i32>,
+    /// Unix timestamp
+    pub created_at: i64,
+    pub reaction_count: i32,
+}
+
+/// A post as sent to an external index.
+#[derive(Debug, Clone, Serialize, Deserialize, sea_orm::FromQueryResult)]
+pub struct PostDocument {
+    pub id: i32,
+    pub thread_id: i32,
+    pub forum_id: i32,
+    pub content: String,
+    pub user_id: Option<i32>,
+    /// Unix timestamp
+    pub created_at: i64,
+    pub reaction_count: i32,
+}
+
+/// Trait for search backends.
+///
+/// Every backend answers searches. Backends with an index of their own also
+/// take documents from the indexing worker; the database backend reads the
+/// live tables and keeps the defaults.
+#[async_trait]
+pub trait SearchBackend: Send + Sync {
+    /// Threads whose titles match the query and filter, best first.
+    async fn search_threads(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<ThreadHit>, SearchError>;
+
+    /// Threads with titles like `title`, best first. Unlike a search, a
+    /// thread need not contain every word to be returned.
+    async fn similar_threads(
+        &self,
+        title: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<ThreadHit>, SearchError>;
+
+    /// Posts whose content matches the query and filter, best first.
+    async fn search_posts(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<PostHit>, SearchError>;
+
+    /// Whether the indexing worker should feed this backend.
+    fn uses_index(&self) -> bool {
+        false
+    }
+
+    /// Create indexes and apply their settings. Returns true if the index is
+    /// empty and everything needs to be indexed.
+    async fn prepare(&self) -> Result<bool, SearchError> {
+        Ok(false)
+    }
+
+    /// Add or replace threads in the index.
+    async fn index_threads(&self, _threads: &[ThreadDocument]) -> Result<(), SearchError> {
+        Ok(())
+    }
+
+    /// Add or replace posts in the index.
+    async fn index_posts(&self, _posts: &[PostDocument]) -> Result<(), SearchError> {
+        Ok(())
+    }
+
+    /// Remove documents from the index. Missing IDs are ignored.
+    async fn remove(&self, _kind: DocumentKind, _ids: &[i32]) -> Result<(), SearchError> {
+        Ok(())
+    }
+}
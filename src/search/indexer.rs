@@ -0,0 +1,260 @@
+//! Feeds thread and post changes to the search backend.
+//!
+//! Database triggers queue a `search.index` job whenever a thread or post is
+//! created, edited, moved, moderated or deleted. The job reads the current
+//! state of each document it names and either sends it to the backend or
+//! removes it from the index. Backends that read the live tables have nothing
+//! to do, and their jobs finish at once.
+
+use super::backend::{DocumentKind, PostDocument, SearchBackend, SearchError, ThreadDocument};
+use super::get_search_backend;
+use crate::db::get_db_pool;
+use crate::jobs::Job;
+use sea_orm::{ConnectionTrait, DbBackend, DbErr, FromQueryResult, Statement};
+use std::collections::HashSet;
+
+/// Attempts at indexing a job's documents before it is given up. Jobs queued
+/// by the triggers take the `jobs.max_attempts` column default, which is the
+/// same.
+const INDEX_ATTEMPTS: i32 = 8;
+
+#[derive(Debug, FromQueryResult)]
+struct DocumentId {
+    id: i32,
+}
+
+/// Comma separated IDs for an `IN (...)` clause.
+fn id_list(ids: &[i32]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Table holding the documents of `kind`.
+fn table(kind: DocumentKind) -> &'static str {
+    match kind {
+        DocumentKind::Thread => "threads",
+        DocumentKind::Post => "posts",
+    }
+}
+
+/// Queues every thread and post, for filling an empty index, in jobs of
+/// `batch_size` documents.
+pub async fn queue_everything(batch_size: u64) -> Result<(), DbErr> {
+    let db = get_db_pool();
+    for kind in [DocumentKind::Thread, DocumentKind::Post] {
+        let sql = format!(
+            r#"INSERT INTO jobs (kind, payload, max_attempts)
+                SELECT 'search.index', jsonb_build_object('kind', $1::TEXT, 'ids', jsonb_agg(id)), $3
+                FROM (
+                    SELECT id, (ROW_NUMBER() OVER (ORDER BY id) - 1) / $2 AS chunk
+                    FROM {}
+                ) documents
+                GROUP BY chunk"#,
+            table(kind)
+        );
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            &sql,
+            vec![
+                kind.as_str().into(),
+                (batch_size.max(1) as i64).into(),
+                INDEX_ATTEMPTS.into(),
+            ],
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+/// Visible threads among `ids`.
+async fn load_threads(ids: &[i32]) -> Result<Vec<ThreadDocument>, DbErr> {
+    let sql = format!(
+        r#"SELECT
+                t.id,
+                t.title,
+                t.forum_id,
+                t.user_id,
+                EXTRACT(EPOCH FROM t.created_at)::BIGINT AS created_at,
+                COALESCE(fu.reaction_count, 0) AS reaction_count
+            FROM threads t
+            LEFT JOIN posts fp ON fp.id = t.first_post_id
+            LEFT JOIN ugc fu ON fu.id = fp.ugc_id
+            WHERE t.id IN ({})
+              AND t.deleted_at IS NULL
+              AND t.merged_into_id IS NULL"#,
+        id_list(ids)
+    );
+    ThreadDocument::find_by_statement(Statement::from_string(DbBackend::Postgres, sql))
+        .all(get_db_pool())
+        .await
+}
+
+/// Visible posts among `ids`, with the content of their current revision.
+async fn load_posts(ids: &[i32]) -> Result<Vec<PostDocument>, DbErr> {
+    let sql = format!(
+        r#"SELECT
+                p.id,
+                p.thread_id,
+                t.forum_id,
+                ur.content,
+                p.user_id,
+                EXTRACT(EPOCH FROM p.created_at)::BIGINT AS created_at,
+                u.reaction_count
+            FROM posts p
+            JOIN threads t ON t.id = p.thread_id
+            JOIN ugc u ON u.id = p.ugc_id
+            JOIN ugc_revisions ur ON ur.id = u.ugc_revision_id
+            LEFT JOIN ugc_deletions d ON d.id = p.ugc_id
+            WHERE p.id IN ({})
+              AND p.moderation_status = 'approved'
+              AND d.id IS NULL
+              AND t.deleted_at IS NULL
+              AND t.merged_into_id IS NULL"#,
+        id_list(ids)
+    );
+    PostDocument::find_by_statement(Statement::from_string(DbBackend::Postgres, sql))
+        .all(get_db_pool())
+        .await
+}
+
+/// IDs that were queued but are no longer visible, and so leave the index.
+fn hidden(ids: &[i32], visible: impl Iterator<Item = i32>) -> Vec<i32> {
+    let visible: HashSet<i32> = visible.collect();
+    ids.iter()
+        .copied()
+        .filter(|id| !visible.contains(id))
+        .collect()
+}
+
+/// Brings the index up to date for documents of one kind.
+async fn index_documents(
+    backend: &dyn SearchBackend,
+    kind: DocumentKind,
+    ids: &[i32],
+) -> Result<(), SearchError> {
+    match kind {
+        DocumentKind::Thread => {
+            let threads = load_threads(ids).await?;
+            let gone = hidden(ids, threads.iter().map(|t| t.id));
+            backend.index_threads(&threads).await?;
+            backend.remove(kind, &gone).await?;
+        }
+        DocumentKind::Post => {
+            let posts = load_posts(ids).await?;
+            let gone = hidden(ids, posts.iter().map(|p| p.id));
+            backend.index_posts(&posts).await?;
+            backend.remove(kind, &gone).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs a queued `search.index` job.
+pub async fn run_index_job(job: &Job) -> Result<(), String> {
+    let backend = get_search_backend();
+    if !backend.uses_index() {
+        return Ok(());
+    }
+
+    let kind = job
+        .payload
+        .get("kind")
+        .and_then(|kind| kind.as_str())
+        .and_then(DocumentKind::parse)
+        .ok_or_else(|| "job has no document kind".to_owned())?;
+    let ids: Vec<i32> = job
+        .payload
+        .get("ids")
+        .and_then(|ids| ids.as_array())
+        .ok_or_else(|| "job has no ids".to_owned())?
+        .iter()
+        .filter_map(|id| id.as_i64())
+        .map(|id| id as i32)
+        .collect();
+
+    let batch_size = crate::app_config::search().batch_size.max(1) as usize;
+    for batch in ids.chunks(batch_size) {
+        index_documents(backend, kind, batch)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Sends every thread and post to the backend in batches of `batch_size`,
+/// without going through the job queue. Returns the number of documents
+/// indexed or removed.
+pub async fn index_everything(
+    backend: &dyn SearchBackend,
+    batch_size: u64,
+) -> Result<usize, SearchError> {
+    let mut indexed = 0;
+    for kind in [DocumentKind::Thread, DocumentKind::Post] {
+        let mut after = 0;
+        loop {
+            let ids: Vec<i32> = DocumentId::find_by_statement(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                &format!(
+                    "SELECT id FROM {} WHERE id > $1 ORDER BY id LIMIT $2",
+                    table(kind)
+                ),
+                vec![after.into(), (batch_size.max(1) as i64).into()],
+            ))
+            .all(get_db_pool())
+            .await?
+            .into_iter()
+            .map(|row| row.id)
+            .collect();
+
+            let Some(&last) = ids.last() else {
+                break;
+            };
+            index_documents(backend, kind, &ids).await?;
+            indexed += ids.len();
+            after = last;
+        }
+    }
+    Ok(indexed)
+}
+
+/// Prepares the index in the background. An empty index gets every thread
+/// and post queued; the job queue keeps it up to date from then on.
+pub fn spawn_prepare() {
+    let config = crate::app_config::search();
+
+    actix_web::rt::spawn(async move {
+        let backend = get_search_backend();
+        if !backend.uses_index() {
+            return;
+        }
+
+        match backend.prepare().await {
+            Ok(true) => {
+                log::info!("Search index is empty; queueing all threads and posts.");
+                if let Err(e) = queue_everything(config.batch_size).await {
+                    log::error!("search::indexer::queue_everything: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => log::error!("search::indexer::prepare: {}", e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_list() {
+        assert_eq!(id_list(&[3, 1, 2]), "3,1,2");
+    }
+
+    #[test]
+    fn test_hidden() {
+        assert_eq!(hidden(&[1, 2, 3, 4], [2, 4].into_iter()), vec![1, 3]);
+        assert!(hidden(&[1], [1].into_iter()).is_empty());
+    }
+}
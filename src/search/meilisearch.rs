@@ -0,0 +1,341 @@
+//! Meilisearch backend.
+//!
+//! Threads and posts are kept in two indexes, `{prefix}_threads` and
+//! `{prefix}_posts`, fed by the indexing worker. Meilisearch applies writes
+//! asynchronously, so requests here only check that a task was accepted.
+
+use super::backend::{
+    DocumentKind, PostDocument, PostHit, SearchBackend, SearchError, SearchFilter, ThreadDocument,
+    ThreadHit,
+};
+use crate::app_config::SearchConfig;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// Characters of a post returned with a search hit.
+const EXCERPT_LENGTH: usize = 200;
+
+/// Meilisearch backend.
+pub struct MeilisearchSearch {
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    prefix: String,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse<T> {
+    hits: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct Scored<T> {
+    #[serde(flatten)]
+    document: T,
+    #[serde(rename = "_rankingScore", default)]
+    score: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexStats {
+    number_of_documents: u64,
+}
+
+fn timestamp(seconds: i64) -> NaiveDateTime {
+    chrono::DateTime::from_timestamp(seconds, 0)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+fn excerpt(content: &str) -> String {
+    content.chars().take(EXCERPT_LENGTH).collect()
+}
+
+/// Meilisearch filter expression for `filter`, if it restricts anything.
+fn filter_expression(filter: &SearchFilter) -> Option<String> {
+    let mut conditions = Vec::new();
+
+    if let Some(user_id) = filter.user_id {
+        conditions.push(format!("user_id = {}", user_id));
+    }
+    if let Some(forum_ids) = &filter.forum_ids {
+        let ids = forum_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        conditions.push(format!("forum_id IN [{}]", ids));
+    }
+    if let Some(since) = filter.since {
+        conditions.push(format!("created_at >= {}", since.and_utc().timestamp()));
+    }
+    if let Some(before) = filter.before {
+        conditions.push(format!("created_at < {}", before.and_utc().timestamp()));
+    }
+    if let Some(min_reactions) = filter.min_reactions {
+        conditions.push(format!("reaction_count >= {}", min_reactions));
+    }
+
+    match conditions.is_empty() {
+        true => None,
+        false => Some(conditions.join(" AND ")),
+    }
+}
+
+impl MeilisearchSearch {
+    pub fn new(config: &SearchConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: config.meilisearch_url.trim_end_matches('/').to_owned(),
+            api_key: config.meilisearch_api_key.to_owned(),
+            prefix: config.index_prefix.to_owned(),
+        }
+    }
+
+    fn index_name(&self, kind: DocumentKind) -> String {
+        match kind {
+            DocumentKind::Thread => format!("{}_threads", self.prefix),
+            DocumentKind::Post => format!("{}_posts", self.prefix),
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .request(method, format!("{}{}", self.url, path))
+            .timeout(Duration::from_secs(30));
+        match self.api_key.is_empty() {
+            true => request,
+            false => request.bearer_auth(&self.api_key),
+        }
+    }
+
+    async fn check(response: Response) -> Result<Response, SearchError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(SearchError::Server(format!("{}: {}", status, body)))
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<(), SearchError> {
+        Self::check(request.send().await?).await?;
+        Ok(())
+    }
+
+    async fn search<T: DeserializeOwned>(
+        &self,
+        kind: DocumentKind,
+        query: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<Scored<T>>, SearchError> {
+        let path = format!("/indexes/{}/search", self.index_name(kind));
+        let response = self
+            .request(Method::POST, &path)
+            .json(&json!({
+                "q": query,
+                "filter": filter_expression(filter),
+                "limit": limit,
+                "showRankingScore": true,
+            }))
+            .send()
+            .await?;
+        let results: SearchResponse<Scored<T>> = Self::check(response).await?.json().await?;
+        Ok(results.hits)
+    }
+
+    /// Number of documents in an index, or `None` if it does not exist yet.
+    async fn document_count(&self, kind: DocumentKind) -> Result<Option<u64>, SearchError> {
+        let path = format!("/indexes/{}/stats", self.index_name(kind));
+        let response = self.request(Method::GET, &path).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let stats: IndexStats = Self::check(response).await?.json().await?;
+        Ok(Some(stats.number_of_documents))
+    }
+}
+
+#[async_trait]
+impl SearchBackend for MeilisearchSearch {
+    async fn search_threads(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<ThreadHit>, SearchError> {
+        let hits = self
+            .search::<ThreadDocument>(DocumentKind::Thread, query, filter, limit)
+            .await?;
+        Ok(hits
+            .into_iter()
+            .map(|hit| ThreadHit {
+                id: hit.document.id,
+                title: hit.document.title,
+                forum_id: hit.document.forum_id,
+                user_id: hit.document.user_id,
+                created_at: timestamp(hit.document.created_at),
+                rank: hit.score,
+            })
+            .collect())
+    }
+
+    async fn similar_threads(
+        &self,
+        title: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<ThreadHit>, SearchError> {
+        // Meilisearch already drops trailing words until something matches.
+        self.search_threads(title, filter, limit).await
+    }
+
+    async fn search_posts(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<PostHit>, SearchError> {
+        let hits = self
+            .search::<PostDocument>(DocumentKind::Post, query, filter, limit)
+            .await?;
+        Ok(hits
+            .into_iter()
+            .map(|hit| PostHit {
+                id: hit.document.id,
+                thread_id: hit.document.thread_id,
+                content: excerpt(&hit.document.content),
+                user_id: hit.document.user_id,
+                created_at: timestamp(hit.document.created_at),
+                rank: hit.score,
+            })
+            .collect())
+    }
+
+    fn uses_index(&self) -> bool {
+        true
+    }
+
+    async fn prepare(&self) -> Result<bool, SearchError> {
+        let empty = !matches!(
+            self.document_count(DocumentKind::Thread).await?,
+            Some(count) if count > 0
+        );
+
+        for (kind, searchable) in [
+            (DocumentKind::Thread, json!(["title"])),
+            (DocumentKind::Post, json!(["content"])),
+        ] {
+            let uid = self.index_name(kind);
+            if self.document_count(kind).await?.is_none() {
+                self.send(
+                    self.request(Method::POST, "/indexes")
+                        .json(&json!({ "uid": uid, "primaryKey": "id" })),
+                )
+                .await?;
+            }
+            self.send(
+                self.request(Method::PATCH, &format!("/indexes/{}/settings", uid))
+                    .json(&json!({
+                        "searchableAttributes": searchable,
+                        "filterableAttributes": ["forum_id", "user_id", "created_at", "reaction_count"],
+                        "sortableAttributes": ["created_at"],
+                    })),
+            )
+            .await?;
+        }
+
+        Ok(empty)
+    }
+
+    async fn index_threads(&self, threads: &[ThreadDocument]) -> Result<(), SearchError> {
+        if threads.is_empty() {
+            return Ok(());
+        }
+        let path = format!(
+            "/indexes/{}/documents",
+            self.index_name(DocumentKind::Thread)
+        );
+        self.send(self.request(Method::POST, &path).json(threads))
+            .await
+    }
+
+    async fn index_posts(&self, posts: &[PostDocument]) -> Result<(), SearchError> {
+        if posts.is_empty() {
+            return Ok(());
+        }
+        let path = format!("/indexes/{}/documents", self.index_name(DocumentKind::Post));
+        self.send(self.request(Method::POST, &path).json(posts))
+            .await
+    }
+
+    async fn remove(&self, kind: DocumentKind, ids: &[i32]) -> Result<(), SearchError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let path = format!("/indexes/{}/documents/delete-batch", self.index_name(kind));
+        self.send(self.request(Method::POST, &path).json(ids)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_names() {
+        let search = MeilisearchSearch::new(&SearchConfig {
+            index_prefix: "board".to_string(),
+            meilisearch_url: "http://search:7700/".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(search.url, "http://search:7700");
+        assert_eq!(search.index_name(DocumentKind::Thread), "board_threads");
+        assert_eq!(search.index_name(DocumentKind::Post), "board_posts");
+    }
+
+    #[test]
+    fn test_scored_hit() {
+        let hit: Scored<ThreadDocument> = serde_json::from_value(json!({
+            "id": 7,
+            "title": "Hello",
+            "forum_id": 2,
+            "user_id": null,
+            "created_at": 1700000000,
+            "reaction_count": 0,
+            "_rankingScore": 0.75,
+        }))
+        .unwrap();
+        assert_eq!(hit.document.id, 7);
+        assert_eq!(hit.score, 0.75);
+        assert_eq!(
+            timestamp(hit.document.created_at).and_utc().timestamp(),
+            1700000000
+        );
+    }
+
+    #[test]
+    fn test_filter_expression() {
+        assert_eq!(filter_expression(&SearchFilter::default()), None);
+        let filter = SearchFilter {
+            user_id: Some(4),
+            forum_ids: Some(vec![1, 2]),
+            since: chrono::DateTime::from_timestamp(1700000000, 0).map(|t| t.naive_utc()),
+            min_reactions: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter_expression(&filter).unwrap(),
+            "user_id = 4 AND forum_id IN [1, 2] AND created_at >= 1700000000 \
+             AND reaction_count >= 3"
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! Search backend abstraction.
+//!
+//! Supports multiple backends:
+//! - `postgres`: PostgreSQL full-text search over the live tables
+//! - `meilisearch`: A Meilisearch server, fed by the indexing worker
+
+pub mod backend;
+pub mod indexer;
+pub mod meilisearch;
+pub mod postgres;
+
+pub use backend::{SearchBackend, SearchError, SearchFilter};
+
+use once_cell::sync::OnceCell;
+
+static SEARCH: OnceCell<Box<dyn SearchBackend>> = OnceCell::new();
+
+#[inline(always)]
+pub fn get_search_backend() -> &'static dyn SearchBackend {
+    unsafe { SEARCH.get_unchecked().as_ref() }
+}
+
+/// MUST be called ONCE before using functions in this module
+pub fn init() {
+    let config = crate::app_config::search();
+
+    let backend: Box<dyn SearchBackend> = match config.backend.as_str() {
+        "postgres" => Box::new(postgres::PostgresSearch),
+        "meilisearch" => {
+            log::info!(
+                "Initializing Meilisearch search: {}",
+                config.meilisearch_url
+            );
+            Box::new(meilisearch::MeilisearchSearch::new(&config))
+        }
+        other => {
+            log::warn!("Unsupported search backend {:?}; using postgres.", other);
+            Box::new(postgres::PostgresSearch)
+        }
+    };
+
+    if SEARCH.set(backend).is_err() {
+        panic!("Search backend already initialized");
+    }
+}
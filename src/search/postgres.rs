@@ -0,0 +1,267 @@
+//! PostgreSQL full-text search backend.
+//!
+//! Searches the `tsvector` columns kept up to date by database triggers, so
+//! there is no separate index to feed.
+
+use super::backend::{PostHit, SearchBackend, SearchError, SearchFilter, ThreadHit};
+use crate::db::get_db_read_pool;
+use async_trait::async_trait;
+use sea_orm::{DbBackend, FromQueryResult, Statement, Value};
+
+/// PostgreSQL full-text search backend.
+pub struct PostgresSearch;
+
+/// Convert a search query to tsquery format (words joined with &)
+fn to_tsquery(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<&str>>().join(" & ")
+}
+
+/// Columns a filter applies to in one of the search queries.
+struct FilterColumns {
+    user_id: &'static str,
+    forum_id: &'static str,
+    created_at: &'static str,
+    reaction_count: &'static str,
+}
+
+/// SQL conditions for `filter`, appended to `values` as `$n` parameters.
+fn conditions(filter: &SearchFilter, columns: &FilterColumns, values: &mut Vec<Value>) -> String {
+    let mut conditions = Vec::new();
+
+    if let Some(user_id) = filter.user_id {
+        values.push(user_id.into());
+        conditions.push(format!("{} = ${}", columns.user_id, values.len()));
+    }
+    if let Some(forum_ids) = &filter.forum_ids {
+        // An empty list matches nothing, rather than every forum.
+        let ids = forum_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        conditions.push(match ids.is_empty() {
+            true => "FALSE".to_owned(),
+            false => format!("{} IN ({})", columns.forum_id, ids),
+        });
+    }
+    if let Some(since) = filter.since {
+        values.push(since.into());
+        conditions.push(format!("{} >= ${}", columns.created_at, values.len()));
+    }
+    if let Some(before) = filter.before {
+        values.push(before.into());
+        conditions.push(format!("{} < ${}", columns.created_at, values.len()));
+    }
+    if let Some(min_reactions) = filter.min_reactions {
+        values.push(min_reactions.into());
+        conditions.push(format!("{} >= ${}", columns.reaction_count, values.len()));
+    }
+
+    conditions
+        .into_iter()
+        .map(|condition| format!(" AND {}", condition))
+        .collect()
+}
+
+#[async_trait]
+impl SearchBackend for PostgresSearch {
+    async fn search_threads(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<ThreadHit>, SearchError> {
+        let mut values: Vec<Value> = vec![to_tsquery(query).into(), (limit as i64).into()];
+        let conditions = conditions(
+            filter,
+            &FilterColumns {
+                user_id: "t.user_id",
+                forum_id: "t.forum_id",
+                created_at: "t.created_at",
+                reaction_count: "COALESCE(fu.reaction_count, 0)",
+            },
+            &mut values,
+        );
+
+        // ts_rank calculates relevance score
+        let sql = format!(
+            r#"
+            SELECT
+                t.id,
+                t.title,
+                t.forum_id,
+                t.user_id,
+                t.created_at,
+                ts_rank(t.title_tsv, to_tsquery('english', $1)) as rank
+            FROM threads t
+            LEFT JOIN posts fp ON fp.id = t.first_post_id
+            LEFT JOIN ugc fu ON fu.id = fp.ugc_id
+            WHERE t.title_tsv @@ to_tsquery('english', $1){}
+            ORDER BY rank DESC, t.created_at DESC
+            LIMIT $2
+        "#,
+            conditions
+        );
+
+        Ok(ThreadHit::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            &sql,
+            values,
+        ))
+        .all(get_db_read_pool())
+        .await?)
+    }
+
+    async fn similar_threads(
+        &self,
+        title: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<ThreadHit>, SearchError> {
+        let mut values: Vec<Value> = vec![title.into(), (limit as i64).into()];
+        let conditions = conditions(
+            filter,
+            &FilterColumns {
+                user_id: "t.user_id",
+                forum_id: "t.forum_id",
+                created_at: "t.created_at",
+                reaction_count: "COALESCE(fu.reaction_count, 0)",
+            },
+            &mut values,
+        );
+
+        // Any word may match, so the stemmed words are joined with | rather than &.
+        let sql = format!(
+            r#"
+            WITH q AS (
+                SELECT NULLIF(REPLACE(plainto_tsquery('english', $1)::text, ' & ', ' | '), '')::tsquery AS query
+            )
+            SELECT
+                t.id,
+                t.title,
+                t.forum_id,
+                t.user_id,
+                t.created_at,
+                ts_rank(t.title_tsv, q.query) as rank
+            FROM q, threads t
+            LEFT JOIN posts fp ON fp.id = t.first_post_id
+            LEFT JOIN ugc fu ON fu.id = fp.ugc_id
+            WHERE t.title_tsv @@ q.query
+              AND t.deleted_at IS NULL
+              AND t.merged_into_id IS NULL{}
+            ORDER BY rank DESC, t.last_post_at DESC NULLS LAST
+            LIMIT $2
+        "#,
+            conditions
+        );
+
+        Ok(ThreadHit::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            &sql,
+            values,
+        ))
+        .all(get_db_read_pool())
+        .await?)
+    }
+
+    async fn search_posts(
+        &self,
+        query: &str,
+        filter: &SearchFilter,
+        limit: u64,
+    ) -> Result<Vec<PostHit>, SearchError> {
+        let mut values: Vec<Value> = vec![to_tsquery(query).into(), (limit as i64).into()];
+        let conditions = conditions(
+            filter,
+            &FilterColumns {
+                user_id: "p.user_id",
+                forum_id: "t.forum_id",
+                created_at: "p.created_at",
+                reaction_count: "u.reaction_count",
+            },
+            &mut values,
+        );
+
+        // Join ugc_revisions with posts to get thread_id
+        let sql = format!(
+            r#"
+            SELECT
+                p.id,
+                p.thread_id,
+                SUBSTRING(ur.content, 1, 200) as content,
+                ur.user_id,
+                ur.created_at,
+                ts_rank(ur.content_tsv, to_tsquery('english', $1)) as rank
+            FROM posts p
+            JOIN threads t ON t.id = p.thread_id
+            JOIN ugc u ON p.ugc_id = u.id
+            JOIN ugc_revisions ur ON u.ugc_revision_id = ur.id
+            WHERE ur.content_tsv @@ to_tsquery('english', $1){}
+            ORDER BY rank DESC, ur.created_at DESC
+            LIMIT $2
+        "#,
+            conditions
+        );
+
+        Ok(PostHit::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            &sql,
+            values,
+        ))
+        .all(get_db_read_pool())
+        .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLUMNS: FilterColumns = FilterColumns {
+        user_id: "p.user_id",
+        forum_id: "t.forum_id",
+        created_at: "p.created_at",
+        reaction_count: "u.reaction_count",
+    };
+
+    #[test]
+    fn test_to_tsquery() {
+        assert_eq!(to_tsquery("  rust   forum "), "rust & forum");
+        assert_eq!(to_tsquery("single"), "single");
+    }
+
+    #[test]
+    fn test_conditions() {
+        let mut values: Vec<Value> = vec!["query".into(), 50i64.into()];
+        let sql = conditions(
+            &SearchFilter {
+                user_id: Some(4),
+                forum_ids: Some(vec![1, 2]),
+                min_reactions: Some(3),
+                ..Default::default()
+            },
+            &COLUMNS,
+            &mut values,
+        );
+        assert_eq!(
+            sql,
+            " AND p.user_id = $3 AND t.forum_id IN (1,2) AND u.reaction_count >= $4"
+        );
+        assert_eq!(values.len(), 4);
+    }
+
+    #[test]
+    fn test_conditions_empty() {
+        let mut values = Vec::new();
+        assert_eq!(
+            conditions(&SearchFilter::default(), &COLUMNS, &mut values),
+            ""
+        );
+        let no_forums = SearchFilter {
+            forum_ids: Some(Vec::new()),
+            ..Default::default()
+        };
+        assert_eq!(conditions(&no_forums, &COLUMNS, &mut values), " AND FALSE");
+        assert!(values.is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+//! HMAC signatures for links handed out by the forum
+//!
+//! Unsubscribe links, outbound `/goto` links and restricted attachment URLs
+//! carry a hex signature made here. Keys shorter than [`MIN_KEY_LENGTH`], the
+//! length required of `SECRET_KEY` for session cookies, are refused in favour
+//! of a random key, which keeps the links working only until a restart.
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+
+/// Shortest key accepted, in bytes
+pub const MIN_KEY_LENGTH: usize = 64;
+
+/// Signs and checks messages with one key
+#[derive(Clone)]
+pub struct Signer {
+    key: Vec<u8>,
+}
+
+impl std::fmt::Debug for Signer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Signer").finish_non_exhaustive()
+    }
+}
+
+impl Signer {
+    /// Signer keyed by `key`. `purpose` names what is signed in the warning
+    /// logged when the key is too short to use.
+    pub fn new(key: &[u8], purpose: &str) -> Self {
+        if key.len() >= MIN_KEY_LENGTH {
+            return Self { key: key.to_vec() };
+        }
+
+        log::warn!(
+            "No signing key of at least {} bytes for {}; they will not survive a restart.",
+            MIN_KEY_LENGTH,
+            purpose
+        );
+        let mut key = vec![0u8; MIN_KEY_LENGTH];
+        rand::thread_rng().fill_bytes(&mut key);
+        Self { key }
+    }
+
+    /// Signer keyed by the `SECRET_KEY` environment variable.
+    pub fn from_secret_key(purpose: &str) -> Self {
+        Self::new(
+            std::env::var("SECRET_KEY").unwrap_or_default().as_bytes(),
+            purpose,
+        )
+    }
+
+    fn mac(&self, message: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(message.as_bytes());
+        mac
+    }
+
+    /// Hex signature of `message`.
+    pub fn sign(&self, message: &str) -> String {
+        self.mac(message)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Whether `signature` is the hex signature of `message`, compared in
+    /// constant time.
+    pub fn verify(&self, message: &str, signature: &str) -> bool {
+        match decode_hex(signature) {
+            Some(signature) => self.mac(message).verify_slice(&signature).is_ok(),
+            None => false,
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_tampering() {
+        let signer = Signer::new(&[7u8; MIN_KEY_LENGTH], "tests");
+        let signature = signer.sign("goto:1:https://example.com/");
+        assert!(signer.verify("goto:1:https://example.com/", &signature));
+        assert!(signer.verify("goto:1:https://example.com/", &signature.to_uppercase()));
+        assert!(!signer.verify("goto:2:https://example.com/", &signature));
+        assert!(!signer.verify("goto:1:https://example.com/", &signature[..62]));
+        assert!(!signer.verify("goto:1:https://example.com/", "zz"));
+        assert!(!signer.verify("goto:1:https://example.com/", ""));
+    }
+
+    #[test]
+    fn test_short_keys_are_refused() {
+        let short = Signer::new(b"too short", "tests");
+        let again = Signer::new(b"too short", "tests");
+        assert_eq!(short.key.len(), MIN_KEY_LENGTH);
+        assert_ne!(short.sign("message"), again.sign("message"));
+
+        let long = Signer::new(&[7u8; MIN_KEY_LENGTH], "tests");
+        assert_eq!(
+            long.sign("message"),
+            Signer::new(&[7u8; MIN_KEY_LENGTH], "tests").sign("message")
+        );
+    }
+}
@@ -0,0 +1,473 @@
+//! Azure Blob Storage backend.
+//!
+//! Uses the Blob REST API directly with Shared Key authorization. Multipart
+//! uploads are staged as uncommitted blocks and joined with a block list;
+//! Azure discards blocks that are never committed after a week.
+
+use super::{get_key_path, ByteStream, CompletedPart, StorageBackend, StorageError, StorageObject};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures::TryStreamExt;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, StatusCode};
+use sha2::Sha256;
+use url::Url;
+
+/// REST API version requests are made against.
+const API_VERSION: &str = "2021-08-06";
+
+/// How often, and how many times, to check on a blob copy that is pending.
+const COPY_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+const COPY_POLL_LIMIT: u32 = 120;
+
+/// Azure Blob Storage backend.
+pub struct AzureStorage {
+    client: reqwest::Client,
+    account: String,
+    key: Vec<u8>,
+    container: String,
+    /// Blob service endpoint, e.g. `https://{account}.blob.core.windows.net`
+    endpoint: Url,
+}
+
+impl AzureStorage {
+    /// Create a new Azure Blob Storage backend. `access_key` is the base64
+    /// account key; `endpoint` defaults to the account's public endpoint and
+    /// can point at Azurite for development.
+    pub fn new(
+        account: String,
+        access_key: &str,
+        container: String,
+        endpoint: Option<String>,
+    ) -> Result<Self, StorageError> {
+        let key = BASE64
+            .decode(access_key.trim())
+            .map_err(|e| StorageError::Azure(format!("Invalid account key: {}", e)))?;
+        let endpoint = endpoint
+            .filter(|endpoint| !endpoint.is_empty())
+            .unwrap_or_else(|| format!("https://{}.blob.core.windows.net", account));
+        let endpoint = Url::parse(endpoint.trim_end_matches('/'))
+            .map_err(|e| StorageError::Azure(format!("Invalid endpoint: {}", e)))?;
+
+        log::info!("AzureStorage initialized for container: {}", container);
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            account,
+            key,
+            container,
+            endpoint,
+        })
+    }
+
+    /// URL of the blob holding a file.
+    fn blob_url(&self, filename: &str) -> Url {
+        let mut url = self.endpoint.clone();
+        url.path_segments_mut()
+            .expect("Endpoint is a base URL")
+            .pop_if_empty()
+            .push(&self.container)
+            .extend(get_key_path(filename).split('/'));
+        url
+    }
+
+    /// ID of a block staged for a multipart upload. IDs in one blob must all
+    /// be the same length, which the zero-padded part number ensures.
+    pub fn block_id(upload_id: &str, part_number: i32) -> String {
+        BASE64.encode(format!("{}-{:05}", upload_id, part_number))
+    }
+
+    fn sign(&self, string_to_sign: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key");
+        mac.update(string_to_sign.as_bytes());
+        BASE64.encode(mac.finalize().into_bytes())
+    }
+
+    /// Sends an authorized request, turning error statuses into `StorageError`s.
+    async fn send(
+        &self,
+        method: Method,
+        url: Url,
+        mut headers: HeaderMap,
+        body: Option<Vec<u8>>,
+    ) -> Result<reqwest::Response, StorageError> {
+        let date = chrono::Utc::now()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        headers.insert(
+            HeaderName::from_static("x-ms-date"),
+            HeaderValue::from_str(&date).expect("Dates are valid header values"),
+        );
+        headers.insert(
+            HeaderName::from_static("x-ms-version"),
+            HeaderValue::from_static(API_VERSION),
+        );
+
+        let content_length = match body.as_ref().map(Vec::len) {
+            Some(len) if len > 0 => len.to_string(),
+            _ => String::new(),
+        };
+        let ms_headers: Vec<(String, String)> = headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
+        let string_to_sign = string_to_sign(
+            method.as_str(),
+            &content_length,
+            &ms_headers,
+            &url,
+            &self.account,
+        );
+        let authorization = format!("SharedKey {}:{}", self.account, self.sign(&string_to_sign));
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            HeaderValue::from_str(&authorization)
+                .map_err(|e| StorageError::Azure(e.to_string()))?,
+        );
+
+        let mut request = self.client.request(method, url).headers(headers);
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| StorageError::Azure(e.to_string()))?;
+
+        match response.status() {
+            status if status.is_success() => Ok(response),
+            StatusCode::NOT_FOUND => Err(StorageError::NotFound(response.url().path().to_owned())),
+            StatusCode::RANGE_NOT_SATISFIABLE => Err(StorageError::InvalidRange(
+                "Range not satisfiable".to_owned(),
+            )),
+            status => {
+                let detail = response.text().await.unwrap_or_default();
+                Err(StorageError::Azure(format!("{}: {}", status, detail)))
+            }
+        }
+    }
+}
+
+/// Builds the Shared Key string-to-sign for a request. Only `x-ms-*` headers
+/// take part; the request sets no other signed standard headers, and dates
+/// travel in `x-ms-date`.
+pub fn string_to_sign(
+    method: &str,
+    content_length: &str,
+    headers: &[(String, String)],
+    url: &Url,
+    account: &str,
+) -> String {
+    let mut ms_headers: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_owned()))
+        .filter(|(name, _)| name.starts_with("x-ms-"))
+        .collect();
+    ms_headers.sort();
+
+    let canonical_headers: String = ms_headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.into_owned()))
+        .collect();
+    params.sort();
+
+    let mut canonical_resource = format!("/{}{}", account, url.path());
+    let mut previous: Option<&str> = None;
+    for (name, value) in &params {
+        if previous == Some(name.as_str()) {
+            canonical_resource.push(',');
+        } else {
+            canonical_resource.push_str(&format!("\n{}:", name));
+        }
+        canonical_resource.push_str(value);
+        previous = Some(name);
+    }
+
+    // VERB, Content-Encoding, Content-Language, Content-Length, Content-MD5,
+    // Content-Type, Date, If-Modified-Since, If-Match, If-None-Match,
+    // If-Unmodified-Since, Range
+    format!(
+        "{}\n\n\n{}\n\n\n\n\n\n\n\n\n{}{}",
+        method, content_length, canonical_headers, canonical_resource
+    )
+}
+
+fn header(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+#[async_trait]
+impl StorageBackend for AzureStorage {
+    async fn put_object(&self, data: Vec<u8>, filename: &str) -> Result<(), StorageError> {
+        log::info!("AzureStorage: put_object: {}", filename);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("x-ms-blob-type"),
+            HeaderValue::from_static("BlockBlob"),
+        );
+
+        self.send(Method::PUT, self.blob_url(filename), headers, Some(data))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        range: Option<String>,
+    ) -> Result<StorageObject, StorageError> {
+        log::debug!("AzureStorage: get_object: {}", key);
+
+        let mut headers = HeaderMap::new();
+        if let Some(range) = range {
+            headers.insert(
+                HeaderName::from_static("x-ms-range"),
+                HeaderValue::from_str(&range)
+                    .map_err(|_| StorageError::InvalidRange(range.to_owned()))?,
+            );
+        }
+
+        let response = self
+            .send(Method::GET, self.blob_url(key), headers, None)
+            .await?;
+
+        use reqwest::header;
+        let content_length =
+            header(&response, header::CONTENT_LENGTH).and_then(|len| len.parse().ok());
+        let content_type = header(&response, header::CONTENT_TYPE);
+        let e_tag = header(&response, header::ETAG);
+        let content_range = header(&response, header::CONTENT_RANGE);
+        let accept_ranges =
+            header(&response, header::ACCEPT_RANGES).or_else(|| Some("bytes".to_owned()));
+        let last_modified = header(&response, header::LAST_MODIFIED);
+
+        let body: ByteStream = Box::pin(
+            response
+                .bytes_stream()
+                .map_err(|e| std::io::Error::other(e.to_string())),
+        );
+
+        Ok(StorageObject {
+            body,
+            content_length,
+            content_type,
+            e_tag,
+            content_range,
+            accept_ranges,
+            last_modified,
+        })
+    }
+
+    async fn exists(&self, filename: &str) -> Result<bool, StorageError> {
+        log::debug!("AzureStorage: exists: {}", filename);
+
+        match self
+            .send(
+                Method::HEAD,
+                self.blob_url(filename),
+                HeaderMap::new(),
+                None,
+            )
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(StorageError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn delete_object(&self, filename: &str) -> Result<(), StorageError> {
+        log::info!("AzureStorage: delete_object: {}", filename);
+
+        self.send(
+            Method::DELETE,
+            self.blob_url(filename),
+            HeaderMap::new(),
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn rename_object(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        log::info!("AzureStorage: rename_object: {} -> {}", from, to);
+
+        // Blobs can't be renamed; copy within the account, then drop the original.
+        let target = self.blob_url(to);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static("x-ms-copy-source"),
+            HeaderValue::from_str(self.blob_url(from).as_str())
+                .map_err(|e| StorageError::Azure(e.to_string()))?,
+        );
+        let response = self
+            .send(Method::PUT, target.clone(), headers, None)
+            .await?;
+
+        // Copies within an account usually finish at once, but may be pending.
+        let mut status = header(&response, HeaderName::from_static("x-ms-copy-status"));
+        let mut polls = 0;
+        while status.as_deref() == Some("pending") && polls < COPY_POLL_LIMIT {
+            actix_web::rt::time::sleep(COPY_POLL_INTERVAL).await;
+            let response = self
+                .send(Method::HEAD, target.clone(), HeaderMap::new(), None)
+                .await?;
+            status = header(&response, HeaderName::from_static("x-ms-copy-status"));
+            polls += 1;
+        }
+        match status.as_deref() {
+            None | Some("success") => {}
+            Some(status) => {
+                return Err(StorageError::Azure(format!(
+                    "Copy of {} did not finish: {}",
+                    from, status
+                )))
+            }
+        }
+
+        self.delete_object(from).await
+    }
+
+    async fn create_multipart_upload(&self, filename: &str) -> Result<String, StorageError> {
+        log::info!("AzureStorage: create_multipart_upload: {}", filename);
+
+        // Blocks need no setup; the ID only keeps concurrent uploads apart.
+        Ok(uuid::Uuid::new_v4().to_string())
+    }
+
+    async fn upload_part(
+        &self,
+        filename: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart, StorageError> {
+        log::debug!(
+            "AzureStorage: upload_part: {} part {}",
+            filename,
+            part_number
+        );
+
+        let block_id = Self::block_id(upload_id, part_number);
+        let mut url = self.blob_url(filename);
+        url.query_pairs_mut()
+            .append_pair("comp", "block")
+            .append_pair("blockid", &block_id);
+
+        self.send(Method::PUT, url, HeaderMap::new(), Some(data))
+            .await?;
+
+        Ok(CompletedPart {
+            part_number,
+            e_tag: block_id,
+        })
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        filename: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<(), StorageError> {
+        log::info!("AzureStorage: complete_multipart_upload: {}", filename);
+
+        let mut block_list = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?><BlockList>");
+        for part in &parts {
+            block_list.push_str(&format!(
+                "<Latest>{}</Latest>",
+                Self::block_id(upload_id, part.part_number)
+            ));
+        }
+        block_list.push_str("</BlockList>");
+
+        let mut url = self.blob_url(filename);
+        url.query_pairs_mut().append_pair("comp", "blocklist");
+
+        self.send(
+            Method::PUT,
+            url,
+            HeaderMap::new(),
+            Some(block_list.into_bytes()),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        filename: &str,
+        _upload_id: &str,
+    ) -> Result<(), StorageError> {
+        log::info!("AzureStorage: abort_multipart_upload: {}", filename);
+
+        // Uncommitted blocks cannot be deleted individually; Azure garbage
+        // collects them, and committing a later upload discards them too.
+        Ok(())
+    }
+
+    fn signed_url(&self, key: &str, filename: &str, expires: i64) -> String {
+        const SAS_VERSION: &str = "2020-12-06";
+
+        let expiry = chrono::DateTime::from_timestamp(expires, 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string();
+        let disposition = format!("inline; filename=\"{}\"", filename.replace('"', ""));
+        let resource = format!(
+            "/blob/{}/{}/{}",
+            self.account,
+            self.container,
+            get_key_path(key)
+        );
+
+        // Permissions, start, expiry, resource, identifier, IP, protocol,
+        // version, resource type, snapshot time, encryption scope, then the
+        // Cache-Control, Content-Disposition, Content-Encoding,
+        // Content-Language and Content-Type overrides.
+        let string_to_sign = [
+            "r",
+            "",
+            &expiry,
+            &resource,
+            "",
+            "",
+            "",
+            SAS_VERSION,
+            "b",
+            "",
+            "",
+            "",
+            &disposition,
+            "",
+            "",
+            "",
+        ]
+        .join("\n");
+
+        let mut url = self.blob_url(key);
+        url.query_pairs_mut()
+            .append_pair("sv", SAS_VERSION)
+            .append_pair("sr", "b")
+            .append_pair("sp", "r")
+            .append_pair("se", &expiry)
+            .append_pair("rscd", &disposition)
+            .append_pair("sig", &self.sign(&string_to_sign));
+        url.to_string()
+    }
+}
@@ -0,0 +1,109 @@
+//! Google Cloud Storage backend.
+//!
+//! GCS serves an S3-compatible XML API that accepts HMAC keys in place of AWS
+//! credentials, so requests, multipart uploads and presigned URLs all go
+//! through the S3 client pointed at Google's endpoint.
+
+use super::s3::S3Storage;
+use super::{CompletedPart, StorageBackend, StorageError, StorageObject};
+use async_trait::async_trait;
+use rusoto_core::credential::AwsCredentials;
+use rusoto_core::Region;
+
+/// Google Cloud Storage backend.
+pub struct GcsStorage {
+    inner: S3Storage,
+}
+
+impl GcsStorage {
+    /// Create a new GCS backend from a bucket and a service account HMAC key.
+    pub fn new(
+        bucket_name: String,
+        endpoint: String,
+        access_id: String,
+        secret: String,
+    ) -> GcsStorage {
+        log::info!("GcsStorage initialized for bucket: {}", bucket_name);
+
+        let endpoint = endpoint.trim_end_matches('/').to_owned();
+        let pub_url = format!("{}/{}", endpoint, bucket_name);
+
+        GcsStorage {
+            inner: S3Storage::new(
+                Region::Custom {
+                    name: "auto".to_owned(),
+                    endpoint,
+                },
+                bucket_name,
+                pub_url,
+                Some(AwsCredentials::new(access_id, secret, None, None)),
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for GcsStorage {
+    async fn put_object(&self, data: Vec<u8>, filename: &str) -> Result<(), StorageError> {
+        self.inner.put_object(data, filename).await
+    }
+
+    async fn get_object(
+        &self,
+        key: &str,
+        range: Option<String>,
+    ) -> Result<StorageObject, StorageError> {
+        self.inner.get_object(key, range).await
+    }
+
+    async fn exists(&self, filename: &str) -> Result<bool, StorageError> {
+        self.inner.exists(filename).await
+    }
+
+    async fn delete_object(&self, filename: &str) -> Result<(), StorageError> {
+        self.inner.delete_object(filename).await
+    }
+
+    async fn rename_object(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        self.inner.rename_object(from, to).await
+    }
+
+    async fn create_multipart_upload(&self, filename: &str) -> Result<String, StorageError> {
+        self.inner.create_multipart_upload(filename).await
+    }
+
+    async fn upload_part(
+        &self,
+        filename: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart, StorageError> {
+        self.inner
+            .upload_part(filename, upload_id, part_number, data)
+            .await
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        filename: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<(), StorageError> {
+        self.inner
+            .complete_multipart_upload(filename, upload_id, parts)
+            .await
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        filename: &str,
+        upload_id: &str,
+    ) -> Result<(), StorageError> {
+        self.inner.abort_multipart_upload(filename, upload_id).await
+    }
+
+    fn signed_url(&self, key: &str, filename: &str, expires: i64) -> String {
+        self.inner.signed_url(key, filename, expires)
+    }
+}
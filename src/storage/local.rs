@@ -1,6 +1,6 @@
 //! Local filesystem storage backend.
 
-use super::{ByteStream, StorageBackend, StorageError, StorageObject};
+use super::{ByteStream, CompletedPart, StorageBackend, StorageError, StorageObject};
 use actix_web::web::{self, Bytes};
 use async_trait::async_trait;
 use futures::stream;
@@ -37,6 +37,22 @@ impl LocalStorage {
         }
     }
 
+    /// Get the staging directory for the parts of a multipart upload.
+    fn get_multipart_path(&self, upload_id: &str) -> Result<PathBuf, StorageError> {
+        // Upload IDs come back from clients, so never let one leave the staging directory.
+        if upload_id.is_empty()
+            || !upload_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(StorageError::NotFound(format!(
+                "Invalid upload ID: {}",
+                upload_id
+            )));
+        }
+        Ok(self.base_path.join(".multipart").join(upload_id))
+    }
+
     /// Parse HTTP Range header.
     /// Supports formats like "bytes=0-499" or "bytes=500-"
     fn parse_range(range: &str, file_size: u64) -> Result<(u64, u64), StorageError> {
@@ -210,4 +226,123 @@ impl StorageBackend for LocalStorage {
         let path = self.get_file_path(filename);
         Ok(path.exists())
     }
+
+    async fn delete_object(&self, filename: &str) -> Result<(), StorageError> {
+        let path = self.get_file_path(filename);
+        log::info!("LocalStorage: delete_object: {:?}", path);
+
+        web::block(move || fs::remove_file(path))
+            .await
+            .map_err(|e| StorageError::Io(std::io::Error::other(e)))??;
+
+        Ok(())
+    }
+
+    async fn rename_object(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        let from = self.get_file_path(from);
+        let to = self.get_file_path(to);
+        log::info!("LocalStorage: rename_object: {:?} -> {:?}", from, to);
+
+        web::block(move || {
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(from, to)
+        })
+        .await
+        .map_err(|e| StorageError::Io(std::io::Error::other(e)))??;
+
+        Ok(())
+    }
+
+    async fn create_multipart_upload(&self, filename: &str) -> Result<String, StorageError> {
+        let upload_id = uuid::Uuid::new_v4().to_string();
+        let path = self.get_multipart_path(&upload_id)?;
+        log::info!(
+            "LocalStorage: create_multipart_upload: {} -> {:?}",
+            filename,
+            path
+        );
+
+        web::block(move || fs::create_dir_all(path))
+            .await
+            .map_err(|e| StorageError::Io(std::io::Error::other(e)))??;
+
+        Ok(upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        _filename: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart, StorageError> {
+        let dir = self.get_multipart_path(upload_id)?;
+        let e_tag = format!("\"{}\"", blake3::hash(&data).to_hex());
+
+        web::block(move || {
+            if !dir.is_dir() {
+                return Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+            }
+            fs::write(dir.join(format!("{:05}", part_number)), data)
+        })
+        .await
+        .map_err(|e| StorageError::Io(std::io::Error::other(e)))??;
+
+        Ok(CompletedPart { part_number, e_tag })
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        filename: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<(), StorageError> {
+        let dir = self.get_multipart_path(upload_id)?;
+        let path = self.get_file_path(filename);
+        log::info!("LocalStorage: complete_multipart_upload: {:?}", path);
+
+        web::block(move || {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            // Assemble next to the destination so a half-written file is never visible.
+            let partial = path.with_extension("partial");
+            let mut out = fs::File::create(&partial)?;
+            for part in &parts {
+                let mut file = fs::File::open(dir.join(format!("{:05}", part.part_number)))?;
+                std::io::copy(&mut file, &mut out)?;
+            }
+            out.sync_all()?;
+            fs::rename(&partial, &path)?;
+            fs::remove_dir_all(&dir)
+        })
+        .await
+        .map_err(|e| StorageError::Io(std::io::Error::other(e)))??;
+
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        _filename: &str,
+        upload_id: &str,
+    ) -> Result<(), StorageError> {
+        let dir = self.get_multipart_path(upload_id)?;
+
+        web::block(move || match fs::remove_dir_all(dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        })
+        .await
+        .map_err(|e| StorageError::Io(std::io::Error::other(e)))??;
+
+        Ok(())
+    }
+
+    fn signed_url(&self, key: &str, filename: &str, expires: i64) -> String {
+        super::signing::signed_content_url(key, filename, expires)
+    }
 }
@@ -3,9 +3,14 @@
 //! Supports multiple backends:
 //! - `local`: Local filesystem storage
 //! - `s3`: S3-compatible object storage (MinIO, AWS S3, etc.)
+//! - `gcs`: Google Cloud Storage
+//! - `azure`: Azure Blob Storage
 
+pub mod azure;
+pub mod gcs;
 pub mod local;
 pub mod s3;
+pub mod signing;
 
 use actix_web::web::Bytes;
 use async_trait::async_trait;
@@ -33,6 +38,15 @@ pub struct StorageObject {
     pub last_modified: Option<String>,
 }
 
+/// A stored part of a multipart upload, as returned by `upload_part`.
+#[derive(Clone, Debug)]
+pub struct CompletedPart {
+    /// 1-based position of the part in the final object
+    pub part_number: i32,
+    /// Entity tag the backend returned for the part
+    pub e_tag: String,
+}
+
 /// Storage operation errors.
 #[derive(Debug)]
 pub enum StorageError {
@@ -42,6 +56,8 @@ pub enum StorageError {
     Io(std::io::Error),
     /// S3 error
     S3(String),
+    /// Azure Blob Storage error
+    Azure(String),
     /// Invalid range request
     InvalidRange(String),
 }
@@ -52,6 +68,7 @@ impl std::fmt::Display for StorageError {
             StorageError::NotFound(msg) => write!(f, "Not found: {}", msg),
             StorageError::Io(e) => write!(f, "I/O error: {}", e),
             StorageError::S3(msg) => write!(f, "S3 error: {}", msg),
+            StorageError::Azure(msg) => write!(f, "Azure error: {}", msg),
             StorageError::InvalidRange(msg) => write!(f, "Invalid range: {}", msg),
         }
     }
@@ -69,6 +86,18 @@ impl From<std::io::Error> for StorageError {
     }
 }
 
+/// Object key for a filename in bucket-style backends, using the same
+/// prefix structure as local storage: `{filename[0:2]}/{filename[2:4]}/{filename}`
+pub fn get_key_path(filename: &str) -> String {
+    if filename.len() < 4 {
+        filename.to_string()
+    } else {
+        let prefix1 = &filename[0..2];
+        let prefix2 = &filename[2..4];
+        format!("{}/{}/{}", prefix1, prefix2, filename)
+    }
+}
+
 /// Trait for storage backends.
 ///
 /// All storage backends must implement this trait to provide
@@ -93,4 +122,41 @@ pub trait StorageBackend: Send + Sync {
 
     /// Check if a file exists.
     async fn exists(&self, filename: &str) -> Result<bool, StorageError>;
+
+    /// Delete a file.
+    async fn delete_object(&self, filename: &str) -> Result<(), StorageError>;
+
+    /// Move a file to a new name, replacing any file already stored there.
+    async fn rename_object(&self, from: &str, to: &str) -> Result<(), StorageError>;
+
+    /// Begin uploading a file in parts. Returns the backend's upload ID.
+    async fn create_multipart_upload(&self, filename: &str) -> Result<String, StorageError>;
+
+    /// Store one part of a multipart upload. Re-sending a part replaces it.
+    async fn upload_part(
+        &self,
+        filename: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart, StorageError>;
+
+    /// Join the parts, in the given order, into the final file.
+    async fn complete_multipart_upload(
+        &self,
+        filename: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<(), StorageError>;
+
+    /// Discard a multipart upload and any parts stored so far.
+    async fn abort_multipart_upload(
+        &self,
+        filename: &str,
+        upload_id: &str,
+    ) -> Result<(), StorageError>;
+
+    /// Build a URL granting access to a restricted file until `expires`
+    /// (Unix time). `filename` is the name the browser is given.
+    fn signed_url(&self, key: &str, filename: &str, expires: i64) -> String;
 }
@@ -1,39 +1,60 @@
 //! S3-compatible storage backend.
 
-use super::{ByteStream, StorageBackend, StorageError, StorageObject};
+use super::{get_key_path, ByteStream, CompletedPart, StorageBackend, StorageError, StorageObject};
 use actix_web::web::Bytes;
 use async_trait::async_trait;
 use futures::TryStreamExt;
-use rusoto_core::Region;
-use rusoto_s3::{GetObjectRequest, ListObjectsV2Request, PutObjectRequest, S3Client, S3};
+use rusoto_core::credential::{AwsCredentials, StaticProvider};
+use rusoto_core::{HttpClient, Region};
+use rusoto_s3::util::{PreSignedRequest, PreSignedRequestOption};
+use rusoto_s3::{
+    AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload,
+    CopyObjectRequest, CreateMultipartUploadRequest, DeleteObjectRequest, GetObjectRequest,
+    ListObjectsV2Request, PutObjectRequest, S3Client, UploadPartRequest, S3,
+};
 
 /// S3-compatible storage backend.
 pub struct S3Storage {
     s3: S3Client,
+    region: Region,
     bucket_name: String,
     pub pub_url: String,
+    /// Configured keys. Without them requests use the AWS environment, and
+    /// restricted files are signed by the forum and streamed through it.
+    credentials: Option<AwsCredentials>,
 }
 
 impl S3Storage {
     /// Create a new S3 storage backend.
-    pub fn new(region: Region, bucket_name: String, pub_url: String) -> S3Storage {
+    pub fn new(
+        region: Region,
+        bucket_name: String,
+        pub_url: String,
+        credentials: Option<AwsCredentials>,
+    ) -> S3Storage {
         log::info!("S3Storage initialized for bucket: {}", bucket_name);
 
+        // Configured keys take precedence over the AWS environment and profile.
+        let s3 = match &credentials {
+            Some(credentials) => S3Client::new_with(
+                HttpClient::new().expect("Failed to create S3 HTTP client"),
+                StaticProvider::new(
+                    credentials.aws_access_key_id().to_owned(),
+                    credentials.aws_secret_access_key().to_owned(),
+                    None,
+                    None,
+                ),
+                region.clone(),
+            ),
+            None => S3Client::new(region.clone()),
+        };
+
         S3Storage {
-            s3: S3Client::new(region),
+            s3,
+            region,
             bucket_name,
             pub_url,
-        }
-    }
-
-    /// Get the S3 key path for a filename.
-    fn get_key_path(filename: &str) -> String {
-        if filename.len() < 4 {
-            filename.to_string()
-        } else {
-            let prefix1 = &filename[0..2];
-            let prefix2 = &filename[2..4];
-            format!("{}/{}/{}", prefix1, prefix2, filename)
+            credentials,
         }
     }
 }
@@ -43,7 +64,7 @@ impl StorageBackend for S3Storage {
     async fn put_object(&self, data: Vec<u8>, filename: &str) -> Result<(), StorageError> {
         log::info!("S3Storage: put_object: {}", filename);
 
-        let key = Self::get_key_path(filename);
+        let key = get_key_path(filename);
         let put_request = PutObjectRequest {
             bucket: self.bucket_name.clone(),
             key,
@@ -66,7 +87,7 @@ impl StorageBackend for S3Storage {
     ) -> Result<StorageObject, StorageError> {
         log::debug!("S3Storage: get_object: {}", key);
 
-        let key_path = Self::get_key_path(key);
+        let key_path = get_key_path(key);
         let request = GetObjectRequest {
             bucket: self.bucket_name.clone(),
             key: key_path,
@@ -111,7 +132,7 @@ impl StorageBackend for S3Storage {
         // https://www.peterbe.com/plog/fastest-way-to-find-out-if-a-file-exists-in-s3
         let list_request = ListObjectsV2Request {
             bucket: self.bucket_name.clone(),
-            prefix: Some(filename.to_owned()),
+            prefix: Some(get_key_path(filename)),
             ..Default::default()
         };
 
@@ -124,4 +145,172 @@ impl StorageBackend for S3Storage {
         let count = result.key_count.unwrap_or(0);
         Ok(count > 0)
     }
+
+    async fn delete_object(&self, filename: &str) -> Result<(), StorageError> {
+        log::info!("S3Storage: delete_object: {}", filename);
+
+        let request = DeleteObjectRequest {
+            bucket: self.bucket_name.clone(),
+            key: get_key_path(filename),
+            ..Default::default()
+        };
+
+        self.s3
+            .delete_object(request)
+            .await
+            .map_err(|e| StorageError::S3(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn rename_object(&self, from: &str, to: &str) -> Result<(), StorageError> {
+        log::info!("S3Storage: rename_object: {} -> {}", from, to);
+
+        // S3 has no rename; copy within the bucket, then drop the original.
+        let request = CopyObjectRequest {
+            bucket: self.bucket_name.clone(),
+            key: get_key_path(to),
+            copy_source: format!("{}/{}", self.bucket_name, get_key_path(from)),
+            ..Default::default()
+        };
+
+        self.s3
+            .copy_object(request)
+            .await
+            .map_err(|e| StorageError::S3(e.to_string()))?;
+
+        self.delete_object(from).await
+    }
+
+    async fn create_multipart_upload(&self, filename: &str) -> Result<String, StorageError> {
+        log::info!("S3Storage: create_multipart_upload: {}", filename);
+
+        let request = CreateMultipartUploadRequest {
+            bucket: self.bucket_name.clone(),
+            key: get_key_path(filename),
+            ..Default::default()
+        };
+
+        self.s3
+            .create_multipart_upload(request)
+            .await
+            .map_err(|e| StorageError::S3(e.to_string()))?
+            .upload_id
+            .ok_or_else(|| StorageError::S3("No upload ID returned".into()))
+    }
+
+    async fn upload_part(
+        &self,
+        filename: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<CompletedPart, StorageError> {
+        log::debug!("S3Storage: upload_part: {} part {}", filename, part_number);
+
+        let request = UploadPartRequest {
+            bucket: self.bucket_name.clone(),
+            key: get_key_path(filename),
+            upload_id: upload_id.to_owned(),
+            part_number: part_number as i64,
+            content_length: Some(data.len() as i64),
+            body: Some(data.into()),
+            ..Default::default()
+        };
+
+        let output = self
+            .s3
+            .upload_part(request)
+            .await
+            .map_err(|e| StorageError::S3(e.to_string()))?;
+
+        Ok(CompletedPart {
+            part_number,
+            e_tag: output
+                .e_tag
+                .ok_or_else(|| StorageError::S3("No ETag returned for part".into()))?,
+        })
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        filename: &str,
+        upload_id: &str,
+        parts: Vec<CompletedPart>,
+    ) -> Result<(), StorageError> {
+        log::info!("S3Storage: complete_multipart_upload: {}", filename);
+
+        let request = CompleteMultipartUploadRequest {
+            bucket: self.bucket_name.clone(),
+            key: get_key_path(filename),
+            upload_id: upload_id.to_owned(),
+            multipart_upload: Some(CompletedMultipartUpload {
+                parts: Some(
+                    parts
+                        .into_iter()
+                        .map(|part| rusoto_s3::CompletedPart {
+                            e_tag: Some(part.e_tag),
+                            part_number: Some(part.part_number as i64),
+                        })
+                        .collect(),
+                ),
+            }),
+            ..Default::default()
+        };
+
+        self.s3
+            .complete_multipart_upload(request)
+            .await
+            .map_err(|e| StorageError::S3(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        filename: &str,
+        upload_id: &str,
+    ) -> Result<(), StorageError> {
+        log::info!("S3Storage: abort_multipart_upload: {}", filename);
+
+        let request = AbortMultipartUploadRequest {
+            bucket: self.bucket_name.clone(),
+            key: get_key_path(filename),
+            upload_id: upload_id.to_owned(),
+            ..Default::default()
+        };
+
+        self.s3
+            .abort_multipart_upload(request)
+            .await
+            .map_err(|e| StorageError::S3(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn signed_url(&self, key: &str, filename: &str, expires: i64) -> String {
+        let credentials = match &self.credentials {
+            Some(credentials) => credentials,
+            None => return super::signing::signed_content_url(key, filename, expires),
+        };
+
+        let expires_in = (expires - chrono::Utc::now().timestamp()).max(1) as u64;
+        let request = GetObjectRequest {
+            bucket: self.bucket_name.clone(),
+            key: get_key_path(key),
+            response_content_disposition: Some(format!(
+                "inline; filename=\"{}\"",
+                filename.replace('"', "")
+            )),
+            ..Default::default()
+        };
+
+        request.get_presigned_url(
+            &self.region,
+            credentials,
+            &PreSignedRequestOption {
+                expires_in: std::time::Duration::from_secs(expires_in),
+            },
+        )
+    }
 }
@@ -0,0 +1,47 @@
+//! HMAC-signed, expiring URLs for restricted files served through `/content`.
+//!
+//! A signature covers the file's hash and expiry time, so a link works for
+//! any filename and any rendition of the file until it expires, and cannot
+//! be extended or moved to another file.
+
+use crate::signing::Signer;
+use once_cell::sync::OnceCell;
+
+static SIGNER: OnceCell<Signer> = OnceCell::new();
+
+const PURPOSE: &str = "signed attachment links";
+
+/// Sets the key used to sign URLs. Without one long enough, a random key is
+/// generated and links stop working when the server restarts.
+pub fn init(key: &[u8]) {
+    if SIGNER.set(Signer::new(key, PURPOSE)).is_err() {
+        log::warn!("URL signing key already initialized");
+    }
+}
+
+fn signer() -> &'static Signer {
+    SIGNER.get_or_init(|| Signer::new(&[], PURPOSE))
+}
+
+/// Signature for access to the file with `hash` until `expires` (Unix time).
+pub fn sign(hash: &str, expires: i64) -> String {
+    signer().sign(&format!("{}:{}", hash, expires))
+}
+
+/// Whether `signature` grants access to the file with `hash` at time `now`.
+pub fn verify(hash: &str, expires: i64, signature: &str, now: i64) -> bool {
+    expires >= now && signer().verify(&format!("{}:{}", hash, expires), signature)
+}
+
+/// Signed `/content` URL for a stored file. `key` is the canonical filename,
+/// which begins with the file's hash.
+pub fn signed_content_url(key: &str, filename: &str, expires: i64) -> String {
+    let hash = &key[0..=63];
+    format!(
+        "/content/{}/{}?expires={}&signature={}",
+        hash,
+        filename,
+        expires,
+        sign(hash, expires)
+    )
+}
@@ -0,0 +1,204 @@
+//! Structured logging and tracing
+//!
+//! Log output goes through `tracing`; `log` records from dependencies and
+//! older modules are forwarded into it, so they carry the span they were
+//! written in. Every HTTP request runs in a root span holding its request ID,
+//! route and user ID. With `[telemetry] otlp_endpoint` set, spans are also
+//! exported to an OpenTelemetry collector, where a slow page can be followed
+//! from the request down to each query it ran.
+
+use crate::app_config::TelemetryConfig;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::{Error, HttpMessage};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{self, Sampler};
+use opentelemetry_sdk::Resource;
+use tracing::Span;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpan, RootSpanBuilder};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Filter used when `RUST_LOG` is not set
+const DEFAULT_FILTER: &str = "debug";
+
+/// Install the global subscriber. Called once at startup, before anything
+/// worth logging happens.
+pub fn init() {
+    let config = crate::app_config::telemetry();
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+
+    let tracer = tracer(&config);
+    let otlp = match &tracer {
+        Some(Ok(tracer)) => Some(tracing_opentelemetry::layer().with_tracer(tracer.clone())),
+        _ => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(otlp)
+        .init();
+
+    match tracer {
+        Some(Ok(_)) => tracing::info!(
+            endpoint = %config.otlp_endpoint,
+            sample_ratio = config.sample_ratio,
+            "Exporting spans over OTLP"
+        ),
+        Some(Err(err)) => {
+            tracing::error!(error = %err, "Failed to set up OTLP export, spans stay local")
+        }
+        None => {}
+    }
+}
+
+/// Tracer exporting over OTLP, or `None` when no endpoint is configured.
+fn tracer(
+    config: &TelemetryConfig,
+) -> Option<Result<opentelemetry_sdk::trace::Tracer, opentelemetry::trace::TraceError>> {
+    (!config.otlp_endpoint.is_empty()).then(|| build_tracer(config))
+}
+
+fn build_tracer(
+    config: &TelemetryConfig,
+) -> Result<opentelemetry_sdk::trace::Tracer, opentelemetry::trace::TraceError> {
+    // Continue traces started by a proxy or client that sends `traceparent`.
+    opentelemetry::global::set_text_map_propagator(
+        opentelemetry_sdk::propagation::TraceContextPropagator::new(),
+    );
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(&config.otlp_endpoint),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    config.sample_ratio.clamp(0.0, 1.0),
+                ))))
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    config.service_name.clone(),
+                )])),
+        )
+        // Actix runs on single-threaded runtimes; export from a thread of its
+        // own so flushing on shutdown can't wait on itself.
+        .install_batch(opentelemetry_sdk::runtime::TokioCurrentThread)
+}
+
+/// Send spans still waiting in the export batch. Called on shutdown.
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Root span for HTTP requests: the default fields plus the user making
+/// the request, filled in once the client context knows who that is.
+pub struct RequestSpan;
+
+impl RootSpanBuilder for RequestSpan {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        tracing_actix_web::root_span!(request, user_id = tracing::field::Empty)
+    }
+
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+/// Record the signed-in user on the request's root span.
+pub fn record_user(req: &ServiceRequest, user_id: Option<i32>) {
+    if let (Some(user_id), Some(span)) = (user_id, req.extensions().get::<RootSpan>()) {
+        span.record("user_id", user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::dev::Service;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Id, Record};
+    use tracing::Subscriber;
+    use tracing_actix_web::TracingLogger;
+    use tracing_subscriber::layer::{Context, Layer};
+
+    /// Collects every value recorded onto a span after it was created.
+    #[derive(Clone, Default)]
+    struct Recorded(Arc<Mutex<Vec<(String, String)>>>);
+
+    impl Visit for Recorded {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for Recorded {
+        fn on_record(&self, _span: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    #[test]
+    fn test_empty_endpoint_skips_exporter() {
+        assert!(tracer(&TelemetryConfig::default()).is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_request_span_records_user() {
+        let recorded = Recorded::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorded.clone()));
+
+        // Stands in for the client context, which records whoever the
+        // session belongs to.
+        let app = test::init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    let user_id = req
+                        .headers()
+                        .get("x-user")
+                        .and_then(|v| v.to_str().ok()?.parse().ok());
+                    record_user(&req, user_id);
+                    srv.call(req)
+                })
+                .wrap(TracingLogger::<RequestSpan>::new())
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        test::call_service(&app, test::TestRequest::get().uri("/").to_request()).await;
+        assert!(!recorded
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(name, _)| name == "user_id"));
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header(("x-user", "42"))
+            .to_request();
+        test::call_service(&app, req).await;
+        let user_ids: Vec<_> = recorded
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| name == "user_id")
+            .map(|(_, value)| value.clone())
+            .collect();
+        assert_eq!(user_ids, vec!["42".to_string()]);
+    }
+}
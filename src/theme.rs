@@ -1,9 +1,9 @@
 //! Theme management service with caching
 
 use crate::db::get_db_pool;
-use crate::orm::themes;
+use crate::orm::{theme_revisions, themes};
 use once_cell::sync::OnceCell;
-use sea_orm::{entity::*, query::*};
+use sea_orm::{entity::*, query::*, ConnectionTrait, DbErr};
 use std::collections::HashMap;
 use std::sync::RwLock;
 
@@ -13,10 +13,14 @@ static THEME_CACHE: OnceCell<RwLock<HashMap<String, themes::Model>>> = OnceCell:
 /// Cache of active themes by ID (id -> Model) for parent lookups
 static THEME_CACHE_BY_ID: OnceCell<RwLock<HashMap<i32, themes::Model>>> = OnceCell::new();
 
+/// Cache of each active theme's full CSS, parents included (id -> CSS)
+static THEME_CSS_CACHE: OnceCell<RwLock<HashMap<i32, String>>> = OnceCell::new();
+
 /// Initialize the theme caches (call once at startup)
 fn init_cache() {
     let _ = THEME_CACHE.set(RwLock::new(HashMap::new()));
     let _ = THEME_CACHE_BY_ID.set(RwLock::new(HashMap::new()));
+    let _ = THEME_CSS_CACHE.set(RwLock::new(HashMap::new()));
 }
 
 /// Load all active themes from database into cache
@@ -62,6 +66,21 @@ pub async fn load_themes() -> Result<(), sea_orm::DbErr> {
         }
     }
 
+    // Render each theme's CSS once rather than on every page
+    {
+        let by_id = get_theme_cache_by_id();
+        let mut cache = THEME_CSS_CACHE
+            .get()
+            .expect("Theme CSS cache not initialized")
+            .write()
+            .expect("Theme CSS cache lock poisoned");
+
+        cache.clear();
+        for theme in by_id.values() {
+            cache.insert(theme.id, theme.get_full_css_with_cache(&by_id));
+        }
+    }
+
     log::info!(
         "Loaded {} themes into cache",
         THEME_CACHE
@@ -153,8 +172,55 @@ pub fn get_theme_cache_by_id() -> HashMap<i32, themes::Model> {
 
 /// Get the full CSS for a theme including inherited parent CSS
 pub fn get_theme_full_css(theme: &themes::Model) -> String {
-    let cache = get_theme_cache_by_id();
-    theme.get_full_css_with_cache(&cache)
+    let cached = THEME_CSS_CACHE
+        .get()
+        .and_then(|cache| cache.read().ok())
+        .and_then(|cache| cache.get(&theme.id).cloned());
+    match cached {
+        // Themes outside the cache, such as inactive ones being previewed
+        // or ones edited since, are rendered as they are
+        Some(css) if get_theme_by_id(theme.id).as_ref() == Some(theme) => css,
+        _ => theme.get_full_css_with_cache(&get_theme_cache_by_id()),
+    }
+}
+
+/// When a member in auto mode gets their dark theme, by the clock on their
+/// device rather than its color scheme setting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThemeSchedule {
+    /// Minutes after local midnight when the dark theme starts
+    pub dark_start: i16,
+    /// Minutes after local midnight when the dark theme ends
+    pub dark_end: i16,
+}
+
+impl ThemeSchedule {
+    /// Start time as HH:MM, for form inputs
+    pub fn start_time(&self) -> String {
+        crate::notifications::quiet_hours::format_minute(self.dark_start)
+    }
+
+    /// End time as HH:MM, for form inputs
+    pub fn end_time(&self) -> String {
+        crate::notifications::quiet_hours::format_minute(self.dark_end)
+    }
+}
+
+/// The light and dark themes auto mode switches between. Slugs that no
+/// longer name an active theme of the right kind fall back to the defaults.
+pub fn get_auto_themes(
+    light: Option<&str>,
+    dark: Option<&str>,
+) -> (Option<themes::Model>, Option<themes::Model>) {
+    let light = light
+        .and_then(get_theme)
+        .filter(|t| !t.is_dark)
+        .or_else(get_default_light_theme);
+    let dark = dark
+        .and_then(get_theme)
+        .filter(|t| t.is_dark)
+        .or_else(get_default_dark_theme);
+    (light, dark)
 }
 
 /// Check if a theme has any CSS including inherited from parents
@@ -203,3 +269,206 @@ fn is_descendant_of(
 
     false
 }
+
+/// Variables the admin theme editor offers color pickers for, with labels
+/// and the light theme's value from `var.scss` to start the picker at.
+pub const COLOR_VARIABLES: &[(&str, &str, &str)] = &[
+    ("--bg-primary", "Background", "#ffffff"),
+    ("--bg-secondary", "Secondary background", "#f8f9fa"),
+    ("--bg-tertiary", "Tertiary background", "#f0f2f5"),
+    ("--bg-header", "Header background", "#ffffff"),
+    ("--bg-input", "Input background", "#ffffff"),
+    ("--text-primary", "Text", "#212529"),
+    ("--text-secondary", "Secondary text", "#495057"),
+    ("--text-muted", "Muted text", "#6c757d"),
+    ("--border-primary", "Borders", "#dee2e6"),
+    ("--border-secondary", "Secondary borders", "#ced4da"),
+    ("--link-color", "Links", "#0d6efd"),
+    ("--link-hover", "Hovered links", "#0a58ca"),
+    ("--accent-focus", "Focus outline", "#0d6efd"),
+    ("--success-btn", "Success buttons", "#198754"),
+    ("--danger-btn", "Danger buttons", "#dc3545"),
+    ("--warning-bg", "Warning background", "#fff3cd"),
+];
+
+/// A color variable as shown in the theme editor
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorVariable {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub default: &'static str,
+    /// The theme's value, or empty when it doesn't override the variable
+    pub value: String,
+}
+
+impl ColorVariable {
+    /// Value for an `<input type="color">`, which only takes `#rrggbb`.
+    pub fn picker_value(&self) -> &str {
+        let is_hex = |v: &str| {
+            v.len() == 7 && v.starts_with('#') && v[1..].chars().all(|c| c.is_ascii_hexdigit())
+        };
+        if is_hex(&self.value) {
+            self.value.as_str()
+        } else {
+            self.default
+        }
+    }
+}
+
+/// Name and value of a `--name: value;` declaration
+fn parse_declaration(decl: &str) -> Option<(&str, &str)> {
+    let (name, value) = decl.trim().trim_end_matches(';').split_once(':')?;
+    let (name, value) = (name.trim(), value.trim());
+    (name.starts_with("--") && !value.is_empty()).then_some((name, value))
+}
+
+/// Split a theme's `css_variables` into the editor's colors and the other
+/// declarations, which are kept as written.
+pub fn split_variables(css: &str) -> (Vec<ColorVariable>, String) {
+    let mut colors: Vec<ColorVariable> = COLOR_VARIABLES
+        .iter()
+        .map(|&(name, label, default)| ColorVariable {
+            name,
+            label,
+            default,
+            value: String::new(),
+        })
+        .collect();
+    let mut other = String::new();
+
+    for decl in css.split_inclusive(';') {
+        let color = parse_declaration(decl).and_then(|(name, value)| {
+            let index = colors.iter().position(|c| c.name == name)?;
+            Some((index, value))
+        });
+        match color {
+            Some((index, value)) => colors[index].value = value.to_string(),
+            None => other.push_str(decl),
+        }
+    }
+
+    (colors, other.trim().to_string())
+}
+
+/// Write editor colors back in front of the other declarations. None when
+/// there's nothing left, as the theme then has no variables.
+pub fn compose_variables(colors: &[(&str, &str)], other: &str) -> Option<String> {
+    let mut css: String = colors
+        .iter()
+        .filter(|(_, value)| !value.trim().is_empty())
+        .map(|(name, value)| format!("{}: {};\n", name, value.trim()))
+        .collect();
+    css.push_str(other.trim());
+    let css = css.trim_end().to_string();
+    (!css.is_empty()).then_some(css)
+}
+
+/// Whether a color value can go in a declaration without ending it, or the
+/// `<style>` element it's written into.
+pub fn is_safe_value(value: &str) -> bool {
+    !value.contains(|c: char| matches!(c, ';' | '{' | '}' | '<' | '>' | '\n' | '\r'))
+}
+
+/// Save a theme's current CSS as the revision for its version.
+pub async fn record_revision<C: ConnectionTrait>(
+    db: &C,
+    theme: &themes::Model,
+    user_id: Option<i32>,
+) -> Result<theme_revisions::Model, DbErr> {
+    theme_revisions::ActiveModel {
+        theme_id: Set(theme.id),
+        version: Set(theme.version),
+        css_variables: Set(theme.css_variables.clone()),
+        css_custom: Set(theme.css_custom.clone()),
+        created_by: Set(user_id),
+        created_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+/// A theme's saved revisions, newest first.
+pub async fn get_revisions(theme_id: i32) -> Result<Vec<theme_revisions::Model>, DbErr> {
+    theme_revisions::Entity::find()
+        .filter(theme_revisions::Column::ThemeId.eq(theme_id))
+        .order_by_desc(theme_revisions::Column::Version)
+        .all(get_db_pool())
+        .await
+}
+
+/// Any theme by slug, active or not, for admins previewing it.
+pub async fn find_preview_theme(slug: &str) -> Option<themes::Model> {
+    if let Some(theme) = get_theme(slug) {
+        return Some(theme);
+    }
+    themes::Entity::find()
+        .filter(themes::Column::Slug.eq(slug))
+        .one(get_db_pool())
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to load theme for preview: {}", e);
+            None
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value<'a>(colors: &'a [ColorVariable], name: &str) -> &'a str {
+        &colors.iter().find(|c| c.name == name).unwrap().value
+    }
+
+    #[test]
+    fn test_split_variables() {
+        let css = "--bg-primary: #111111;\n--my-spacing: 4px;\n  --link-color:red ;\n/* note */";
+        let (colors, other) = split_variables(css);
+        assert_eq!(colors.len(), COLOR_VARIABLES.len());
+        assert_eq!(value(&colors, "--bg-primary"), "#111111");
+        assert_eq!(value(&colors, "--link-color"), "red");
+        assert_eq!(value(&colors, "--text-primary"), "");
+        assert_eq!(other, "--my-spacing: 4px;\n/* note */");
+    }
+
+    #[test]
+    fn test_compose_variables_round_trip() {
+        let css = compose_variables(
+            &[("--bg-primary", "#111111"), ("--text-primary", " ")],
+            "--my-spacing: 4px;",
+        )
+        .unwrap();
+        assert_eq!(css, "--bg-primary: #111111;\n--my-spacing: 4px;");
+
+        let (colors, other) = split_variables(&css);
+        assert_eq!(value(&colors, "--bg-primary"), "#111111");
+        assert_eq!(other, "--my-spacing: 4px;");
+
+        assert_eq!(compose_variables(&[("--bg-primary", "")], "  "), None);
+    }
+
+    #[test]
+    fn test_picker_value() {
+        let (colors, _) = split_variables("--bg-primary: #abcdef; --text-primary: red;");
+        let color = |name| colors.iter().find(|c| c.name == name).unwrap();
+        assert_eq!(color("--bg-primary").picker_value(), "#abcdef");
+        assert_eq!(color("--text-primary").picker_value(), "#212529");
+    }
+
+    #[test]
+    fn test_schedule_times() {
+        let schedule = ThemeSchedule {
+            dark_start: 20 * 60,
+            dark_end: 7 * 60 + 30,
+        };
+        assert_eq!(schedule.start_time(), "20:00");
+        assert_eq!(schedule.end_time(), "07:30");
+    }
+
+    #[test]
+    fn test_is_safe_value() {
+        assert!(is_safe_value("rgb(1, 2, 3)"));
+        assert!(!is_safe_value("red; } body { display: none"));
+        assert!(!is_safe_value("</style>"));
+    }
+}
@@ -0,0 +1,85 @@
+//! Certificate loading for the HTTPS listeners.
+
+use crate::app_config::ServerConfig;
+use rustls::{Certificate, PrivateKey};
+use std::fs::File;
+use std::io::{self, BufReader};
+
+/// Build the rustls configuration from `tls_cert_file` and `tls_key_file`.
+pub fn load_server_config(config: &ServerConfig) -> io::Result<rustls::ServerConfig> {
+    if config.tls_cert_file.is_empty() || config.tls_key_file.is_empty() {
+        return Err(invalid(
+            "tls_listen needs both tls_cert_file and tls_key_file".to_string(),
+        ));
+    }
+
+    let certs = load_certs(&config.tls_cert_file)?;
+    let key = load_key(&config.tls_key_file)?;
+
+    rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid(format!("Unusable TLS certificate or key: {}", e)))
+}
+
+fn load_certs(path: &str) -> io::Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader)?;
+    if certs.is_empty() {
+        return Err(invalid(format!("No certificates found in {}", path)));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_key(path: &str) -> io::Result<PrivateKey> {
+    let mut reader = BufReader::new(open(path)?);
+    loop {
+        match rustls_pemfile::read_one(&mut reader)? {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            Some(_) => continue,
+            None => return Err(invalid(format!("No private key found in {}", path))),
+        }
+    }
+}
+
+fn open(path: &str) -> io::Result<File> {
+    File::open(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_requires_cert_and_key() {
+        let config = ServerConfig {
+            tls_cert_file: "cert.pem".to_string(),
+            ..Default::default()
+        };
+        let err = load_server_config(&config).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_missing_file_names_path() {
+        let err = load_certs("/nonexistent/cert.pem").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("/nonexistent/cert.pem"));
+    }
+
+    #[test]
+    fn test_file_without_key() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "not a key").unwrap();
+        let err = load_key(file.path().to_str().unwrap()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,429 @@
+//! Background transcoding of uploaded videos.
+//!
+//! New video uploads are recorded in `video_transcodes` and queued as jobs.
+//! The job converts it with the ffmpeg executable into streamable renditions,
+//! grabs a poster frame, and records the duration and dimensions. Until then
+//! posts show the video as processing and link to the original.
+
+use crate::app_config::VideoConfig;
+use crate::db::get_db_pool;
+use crate::filesystem::{
+    enqueue_image_variants, get_storage, get_tmp_path, insert_generated_attachment,
+    link_attachment_thumbnail, link_attachment_variant,
+};
+use crate::jobs::{self, Job, JobKind};
+use crate::orm::attachments;
+use crate::orm::video_transcodes::{self, TranscodeStatus};
+use chrono::Utc;
+use futures::StreamExt;
+use sea_orm::{entity::*, query::*, DbBackend, DbErr};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Streamable formats videos are converted into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rendition {
+    /// H.264/AAC with the index at the front, playable everywhere.
+    Mp4,
+    /// VP9/Opus, usually smaller.
+    WebM,
+}
+
+impl Rendition {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Rendition::Mp4 => "video/mp4",
+            Rendition::WebM => "video/webm",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Rendition::Mp4 => "mp4",
+            Rendition::WebM => "webm",
+        }
+    }
+
+    /// Renditions switched on in the `[video]` config section.
+    pub fn enabled(config: &VideoConfig) -> Vec<Rendition> {
+        let mut renditions = vec![Rendition::Mp4];
+        if config.webm {
+            renditions.push(Rendition::WebM);
+        }
+        renditions
+    }
+
+    /// ffmpeg arguments converting `input` into this format at `output`.
+    pub fn ffmpeg_args(&self, input: &Path, output: &Path, max_height: u32) -> Vec<String> {
+        let codecs = match self {
+            Rendition::Mp4 => {
+                "-c:v libx264 -preset veryfast -crf 23 -pix_fmt yuv420p \
+                 -c:a aac -b:a 128k -movflags +faststart -f mp4"
+            }
+            Rendition::WebM => {
+                "-c:v libvpx-vp9 -crf 33 -b:v 0 -row-mt 1 -c:a libopus -b:a 96k -f webm"
+            }
+        };
+
+        let mut args = input_args(input);
+        args.extend(["-map", "0:v:0", "-map", "0:a:0?", "-vf"].map(String::from));
+        args.push(scale_filter(max_height));
+        args.extend(codecs.split_whitespace().map(String::from));
+        args.push(output.to_string_lossy().into_owned());
+        args
+    }
+}
+
+fn input_args(input: &Path) -> Vec<String> {
+    vec![
+        "-nostdin".to_string(),
+        "-y".to_string(),
+        "-v".to_string(),
+        "error".to_string(),
+        "-i".to_string(),
+        input.to_string_lossy().into_owned(),
+    ]
+}
+
+/// Scales down to `max_height`, never up, keeping the width even for the encoders.
+fn scale_filter(max_height: u32) -> String {
+    format!("scale=-2:'min({},ih)'", max_height)
+}
+
+/// Dimensions of a video after `scale_filter` is applied.
+pub fn scaled_dimensions((width, height): (u32, u32), max_height: u32) -> (u32, u32) {
+    if height <= max_height || height == 0 {
+        return (width, height);
+    }
+    let scaled = (width as u64 * max_height as u64 / height as u64) as u32;
+    (scaled + (scaled & 1), max_height)
+}
+
+/// Where in the video the poster frame is taken, in seconds.
+/// One second in skips fade-ins; short clips use their midpoint.
+pub fn poster_offset(duration_ms: Option<i32>) -> f64 {
+    match duration_ms {
+        Some(ms) if ms > 0 => (ms as f64 / 2000.0).min(1.0),
+        _ => 0.0,
+    }
+}
+
+/// ffmpeg arguments extracting a single JPEG frame from `input` at `output`.
+pub fn poster_args(input: &Path, output: &Path, offset: f64, max_height: u32) -> Vec<String> {
+    let mut args = vec!["-ss".to_string(), format!("{:.3}", offset)];
+    args.extend(input_args(input));
+    args.extend(["-frames:v", "1", "-vf"].map(String::from));
+    args.push(scale_filter(max_height));
+    args.extend(["-q:v", "3", "-f", "image2", "-c:v", "mjpeg"].map(String::from));
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// Queues a video attachment for transcoding. Already queued videos are left alone.
+pub async fn enqueue(attachment_id: i32) {
+    if !crate::app_config::video().transcode {
+        return;
+    }
+
+    let db = get_db_pool();
+    match video_transcodes::Entity::find_by_id(attachment_id)
+        .one(db)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let now = Utc::now().naive_utc();
+            if let Err(e) = video_transcodes::Entity::insert(video_transcodes::ActiveModel {
+                attachment_id: Set(attachment_id),
+                status: Set(TranscodeStatus::Pending),
+                attempts: Set(0),
+                duration_ms: Set(None),
+                error: Set(None),
+                created_at: Set(now),
+                updated_at: Set(now),
+            })
+            .exec(db)
+            .await
+            {
+                log::error!("transcode::enqueue: {}", e);
+                return;
+            }
+            if let Err(e) = jobs::enqueue(
+                JobKind::VideoTranscode,
+                json!({ "attachment_id": attachment_id }),
+                crate::app_config::video().max_attempts,
+            )
+            .await
+            {
+                log::error!("transcode::enqueue: {}", e);
+            }
+        }
+        Err(e) => log::error!("transcode::enqueue: {}", e),
+    }
+}
+
+/// Returns the transcode state of an attachment, if it was ever queued.
+pub async fn get_status(attachment_id: i32) -> Option<video_transcodes::Model> {
+    video_transcodes::Entity::find_by_id(attachment_id)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| log::error!("transcode::get_status: {}", e))
+        .unwrap_or_default()
+}
+
+/// Marks a queued video as processing for the given attempt. None once it is
+/// ready or its attachment is gone.
+pub async fn start_job(
+    attachment_id: i32,
+    attempt: i32,
+) -> Result<Option<video_transcodes::Model>, DbErr> {
+    video_transcodes::Entity::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"UPDATE video_transcodes
+                SET status = 'processing', attempts = $2, updated_at = NOW()
+                WHERE attachment_id = $1 AND status <> 'ready'
+                RETURNING *"#,
+            vec![attachment_id.into(), attempt.into()],
+        ))
+        .one(get_db_pool())
+        .await
+}
+
+/// Records the outcome of a started job. Failures go back on the queue until
+/// `max_attempts` is reached.
+pub async fn finish_job(
+    job: &video_transcodes::Model,
+    result: Result<Option<i32>, String>,
+    max_attempts: i32,
+) {
+    let mut model: video_transcodes::ActiveModel = job.clone().into();
+    model.updated_at = Set(Utc::now().naive_utc());
+    match result {
+        Ok(duration_ms) => {
+            model.status = Set(TranscodeStatus::Ready);
+            model.duration_ms = Set(duration_ms);
+            model.error = Set(None);
+        }
+        Err(e) => {
+            log::error!(
+                "transcode: attachment {} attempt {}: {}",
+                job.attachment_id,
+                job.attempts,
+                e
+            );
+            model.status = Set(if job.attempts >= max_attempts {
+                TranscodeStatus::Failed
+            } else {
+                TranscodeStatus::Pending
+            });
+            model.error = Set(Some(e));
+        }
+    }
+
+    if let Err(e) = model.update(get_db_pool()).await {
+        log::error!("transcode::finish_job: {}", e);
+    }
+}
+
+/// Runs a queued transcode. Failures are retried by the job queue until the
+/// video runs out of attempts.
+pub async fn run_transcode_job(job: &Job) -> Result<(), String> {
+    let attachment_id = job
+        .payload
+        .get("attachment_id")
+        .and_then(|id| id.as_i64())
+        .ok_or_else(|| "job has no attachment_id".to_owned())? as i32;
+
+    let Some(transcode) = start_job(attachment_id, job.attempts)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(());
+    };
+
+    let result = transcode_attachment(attachment_id, &crate::app_config::video()).await;
+    finish_job(&transcode, result.clone(), job.max_attempts).await;
+    result.map(|_| ())
+}
+
+/// Temporary file removed when dropped.
+struct TmpFile(PathBuf);
+
+impl TmpFile {
+    fn new(extension: &str) -> Self {
+        Self(get_tmp_path().with_extension(extension))
+    }
+}
+
+impl Drop for TmpFile {
+    fn drop(&mut self) {
+        if self.0.exists() {
+            if let Err(e) = std::fs::remove_file(&self.0) {
+                log::error!("transcode: delete tmp file error: {}", e);
+            }
+        }
+    }
+}
+
+async fn run_ffmpeg(ffmpeg_path: &str, args: Vec<String>) -> Result<(), String> {
+    let ffmpeg_path = ffmpeg_path.to_owned();
+    let output =
+        actix_web::web::block(move || std::process::Command::new(ffmpeg_path).args(args).output())
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("failed to run ffmpeg: {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_owned())
+    }
+}
+
+/// Streams a stored object into a local file a chunk at a time, so large
+/// videos are never held in memory.
+async fn download_to(key: &str, path: &Path) -> Result<(), String> {
+    let object = get_storage()
+        .get_object(key, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let path = path.to_owned();
+    let mut file = actix_web::web::block(move || std::fs::File::create(path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let mut body = object.body;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        file = actix_web::web::block(move || {
+            use std::io::Write;
+            file.write_all(&chunk).map(|_| file)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+async fn read_output(path: &Path) -> Result<Vec<u8>, String> {
+    let path = path.to_owned();
+    actix_web::web::block(move || std::fs::read(path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Converts one video and links its renditions and poster. Returns the duration.
+async fn transcode_attachment(
+    attachment_id: i32,
+    config: &VideoConfig,
+) -> Result<Option<i32>, String> {
+    let db = get_db_pool();
+    let attachment = attachments::Entity::find_by_id(attachment_id)
+        .one(db)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("attachment no longer exists")?;
+
+    // ffmpeg needs a seekable file, so pull the original out of storage.
+    let input = TmpFile::new("src");
+    download_to(&attachment.filename, &input.0).await?;
+
+    let input_path = input.0.clone();
+    let (dimensions, duration_ms) = actix_web::web::block(move || {
+        crate::ffmpeg::open_with_ffmpeg(&input_path).map(|ctx| {
+            (
+                crate::ffmpeg::get_dimensions_from_input(&ctx),
+                crate::ffmpeg::get_duration_from_input(&ctx),
+            )
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or("not a readable video")?;
+    let scaled = dimensions.map(|dimensions| scaled_dimensions(dimensions, config.max_height));
+
+    for rendition in Rendition::enabled(config) {
+        let output = TmpFile::new(rendition.extension());
+        run_ffmpeg(
+            &config.ffmpeg_path,
+            rendition.ffmpeg_args(&input.0, &output.0, config.max_height),
+        )
+        .await?;
+
+        let file = insert_generated_attachment(
+            read_output(&output.0).await?,
+            rendition.mime(),
+            rendition.extension(),
+            scaled,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+        link_attachment_variant(attachment_id, file.id, rendition.mime())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let poster = TmpFile::new("jpeg");
+    run_ffmpeg(
+        &config.ffmpeg_path,
+        poster_args(
+            &input.0,
+            &poster.0,
+            poster_offset(duration_ms),
+            config.max_height,
+        ),
+    )
+    .await?;
+    let poster_data = read_output(&poster.0).await?;
+    let file = insert_generated_attachment(poster_data, "image/jpeg", "jpeg", scaled)
+        .await
+        .map_err(|e| e.to_string())?;
+    link_attachment_thumbnail(attachment_id, file.id)
+        .await
+        .map_err(|e| e.to_string())?;
+    enqueue_image_variants(file.id).await;
+
+    if let (Some((width, height)), None) = (dimensions, attachment.file_width) {
+        let mut model: attachments::ActiveModel = attachment.into();
+        model.file_width = Set(Some(width as i32));
+        model.file_height = Set(Some(height as i32));
+        model.update(db).await.map_err(|e| e.to_string())?;
+    }
+
+    Ok(duration_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_dimensions_keep_aspect_and_even_width() {
+        assert_eq!(scaled_dimensions((1920, 1080), 720), (1280, 720));
+        assert_eq!(scaled_dimensions((640, 360), 720), (640, 360));
+        // 1001 * 720 / 1080 = 667, rounded up to even
+        assert_eq!(scaled_dimensions((1001, 1080), 720), (668, 720));
+    }
+
+    #[test]
+    fn poster_offset_stays_inside_short_clips() {
+        assert_eq!(poster_offset(None), 0.0);
+        assert_eq!(poster_offset(Some(60_000)), 1.0);
+        assert_eq!(poster_offset(Some(500)), 0.25);
+    }
+
+    #[test]
+    fn mp4_args_are_streamable() {
+        let args = Rendition::Mp4.ffmpeg_args(Path::new("in"), Path::new("out.mp4"), 720);
+        assert_eq!(args.last().map(String::as_str), Some("out.mp4"));
+        assert!(args.windows(2).any(|w| w == ["-movflags", "+faststart"]));
+        assert!(args.windows(2).any(|w| w == ["-i", "in"]));
+        assert!(args.contains(&"scale=-2:'min(720,ih)'".to_string()));
+    }
+}
@@ -1,9 +1,10 @@
 use crate::orm::{ugc, ugc_revisions};
 use actix_web::{error, Error};
 use chrono::prelude::Utc;
+use chrono::NaiveDateTime;
 use sea_orm::sea_query::Expr;
-use sea_orm::ConnectionTrait;
 use sea_orm::{entity::*, query::*, Set};
+use sea_orm::{ConnectionTrait, DbErr};
 
 /// Contains only the UGC we can get from a form submission.
 pub struct NewUgcPartial<'a> {
@@ -67,6 +68,43 @@ where
     Ok(new_revision)
 }
 
+/// Creates a new UGC whose first revision is dated `created_at`, for content
+/// written before it arrived here, such as imported or generated posts.
+pub async fn create_dated_ugc<C>(
+    conn: &C,
+    user_id: Option<i32>,
+    content: &str,
+    created_at: NaiveDateTime,
+) -> Result<i32, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let new_ugc = ugc::ActiveModel {
+        ugc_revision_id: Set(None),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+
+    let revision = ugc_revisions::ActiveModel {
+        ugc_id: Set(new_ugc.id),
+        user_id: Set(user_id),
+        created_at: Set(created_at),
+        content: Set(content.to_owned()),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+
+    ugc::Entity::update_many()
+        .col_expr(ugc::Column::UgcRevisionId, Expr::value(revision.id))
+        .filter(ugc::Column::Id.eq(new_ugc.id))
+        .exec(conn)
+        .await?;
+
+    Ok(new_ugc.id)
+}
+
 fn validate_ugc(revision: NewUgcPartial) -> Result<NewUgcPartial, Error> {
     let content = revision.content;
     let clean_content = content.trim();
@@ -1,10 +1,11 @@
-use crate::attachment::AttachmentSize;
+use crate::attachment::{AttachmentSize, AvatarFile};
 use crate::db::get_db_pool;
-use crate::orm::{attachments, user_avatars, user_names, users};
+use crate::orm::users::AvatarSource;
+use crate::orm::{attachments, user_avatars, user_bans, user_names, users};
 use crate::url::UrlToken;
 use chrono::{DateTime, Duration, Utc};
 use once_cell::sync::Lazy;
-use sea_orm::{entity::*, query::*, DatabaseConnection, FromQueryResult};
+use sea_orm::{entity::*, query::*, ConnectionTrait, DatabaseConnection, DbErr, FromQueryResult};
 use std::collections::HashMap;
 use std::sync::RwLock;
 
@@ -46,6 +47,10 @@ where
         .column_as(attachments::Column::Filename, "B_avatar_filename")
         .column_as(attachments::Column::FileHeight, "B_avatar_height")
         .column_as(attachments::Column::FileWidth, "B_avatar_width")
+        .column_as(
+            Expr::cust(&crate::attachment::avatar_renditions_sql("attachments")),
+            "B_avatar_renditions",
+        )
         // Add post count subquery
         .column_as(
             Expr::cust_with_values(
@@ -63,13 +68,20 @@ pub struct Profile {
     pub name: String,
     pub created_at: chrono::NaiveDateTime,
     pub password_cipher: String,
+    pub email: Option<String>,
+    pub avatar_source: AvatarSource,
     pub avatar_filename: Option<String>,
     pub avatar_height: Option<i32>,
     pub avatar_width: Option<i32>,
+    pub avatar_renditions: Option<String>,
     pub posts_per_page: i32,
     pub post_count: Option<i64>,
     pub theme: Option<String>,
     pub theme_auto: bool,
+    pub theme_dark: Option<String>,
+    pub theme_schedule: bool,
+    pub theme_dark_start: i16,
+    pub theme_dark_end: i16,
     pub bio: Option<String>,
     pub location: Option<String>,
     pub website_url: Option<String>,
@@ -77,7 +89,7 @@ pub struct Profile {
     pub custom_title: Option<String>,
     pub show_online: bool,
     pub reputation_score: i32,
-    pub allow_profile_posts: bool,
+    pub profile_post_privacy: String,
     pub follower_count: i32,
     pub following_count: i32,
     pub default_chat_room: Option<i32>,
@@ -92,19 +104,27 @@ impl Profile {
         use sea_orm::{DbBackend, Statement};
 
         // Use raw SQL to include post count
-        let sql = r#"
+        let sql = format!(
+            r#"
             SELECT
                 u.id,
                 un.name,
                 u.created_at,
                 u.password_cipher::text as password_cipher,
+                u.email,
+                u.avatar_source,
                 a.filename as avatar_filename,
                 a.file_height as avatar_height,
                 a.file_width as avatar_width,
+                {} as avatar_renditions,
                 u.posts_per_page,
                 COUNT(p.id) as post_count,
                 u.theme,
                 u.theme_auto,
+                u.theme_dark,
+                u.theme_schedule,
+                u.theme_dark_start,
+                u.theme_dark_end,
                 u.bio,
                 u.location,
                 u.website_url,
@@ -112,7 +132,7 @@ impl Profile {
                 u.custom_title,
                 u.show_online,
                 u.reputation_score,
-                u.allow_profile_posts,
+                u.profile_post_privacy,
                 u.follower_count,
                 u.following_count,
                 u.default_chat_room
@@ -122,29 +142,50 @@ impl Profile {
             LEFT JOIN attachments a ON a.id = ua.attachment_id
             LEFT JOIN posts p ON p.user_id = u.id
             WHERE u.id = $1
-            GROUP BY u.id, un.name, u.created_at, u.password_cipher, a.filename, a.file_height, a.file_width, u.posts_per_page, u.theme, u.theme_auto, u.bio, u.location, u.website_url, u.signature, u.custom_title, u.show_online, u.reputation_score, u.allow_profile_posts, u.follower_count, u.following_count, u.default_chat_room
-        "#;
+            GROUP BY u.id, un.name, u.created_at, u.password_cipher, u.email, u.avatar_source, a.id, a.filename, a.file_height, a.file_width, u.posts_per_page, u.theme, u.theme_auto, u.theme_dark, u.theme_schedule, u.theme_dark_start, u.theme_dark_end, u.bio, u.location, u.website_url, u.signature, u.custom_title, u.show_online, u.reputation_score, u.profile_post_privacy, u.follower_count, u.following_count, u.default_chat_room
+        "#,
+            crate::attachment::avatar_renditions_sql("a")
+        );
 
         Self::find_by_statement(Statement::from_sql_and_values(
             DbBackend::Postgres,
-            sql,
+            &sql,
             vec![id.into()],
         ))
         .one(db)
         .await
     }
 
+    /// When auto theme mode switches to the dark theme, if it goes by the
+    /// time of day rather than the system setting.
+    pub fn theme_schedule(&self) -> Option<crate::theme::ThemeSchedule> {
+        self.theme_schedule.then_some(crate::theme::ThemeSchedule {
+            dark_start: self.theme_dark_start,
+            dark_end: self.theme_dark_end,
+        })
+    }
+
     /// Provides semantically correct HTML for an avatar.
     pub fn get_avatar_html(&self, size: AttachmentSize) -> String {
-        if let (Some(filename), Some(width), Some(height)) = (
-            self.avatar_filename.as_ref(),
-            self.avatar_width,
+        let file = match (
+            self.avatar_filename.as_deref(),
             self.avatar_width,
+            self.avatar_height,
         ) {
-            crate::attachment::get_avatar_html(filename, (width, height), size)
-        } else {
-            "".to_owned()
-        }
+            (Some(filename), Some(width), Some(height)) => Some(AvatarFile {
+                filename,
+                dimensions: (width, height),
+                renditions: self.avatar_renditions.as_deref(),
+            }),
+            _ => None,
+        };
+
+        crate::attachment::get_avatar_html_for_source(
+            self.avatar_source,
+            file,
+            self.email.as_deref(),
+            size,
+        )
     }
 
     /// Provides a URL token for this resource.
@@ -166,6 +207,23 @@ impl Profile {
     }
 }
 
+/// The most recent ban in force on `user_id`: permanent or not yet expired.
+pub async fn get_active_ban<C>(db: &C, user_id: i32) -> Result<Option<user_bans::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    user_bans::Entity::find()
+        .filter(user_bans::Column::UserId.eq(user_id))
+        .filter(
+            user_bans::Column::IsPermanent
+                .eq(true)
+                .or(user_bans::Column::ExpiresAt.gt(Utc::now().naive_utc())),
+        )
+        .order_by_desc(user_bans::Column::CreatedAt)
+        .one(db)
+        .await
+}
+
 pub async fn get_user_id_from_name(db: &DatabaseConnection, name: &str) -> Option<i32> {
     user_names::Entity::find()
         .filter(user_names::Column::Name.eq(name))
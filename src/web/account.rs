@@ -1,22 +1,34 @@
+use crate::api_token::{self, ApiScope};
 use crate::db::get_db_pool;
 use crate::middleware::ClientCtx;
+use crate::orm::api_tokens;
 use crate::orm::chat_rooms;
+use crate::orm::email_changes;
+use crate::orm::saved_searches;
 use crate::orm::themes;
 use crate::orm::user_social_links::{self, SocialPlatform};
+use crate::orm::users::AvatarSource;
+use crate::orm::{avatar_gallery, user_avatars, users};
 use crate::user::Profile as UserProfile;
 use actix_multipart::Multipart;
 use actix_web::{error, get, post, Error, HttpResponse, Responder};
 use askama_actix::{Template, TemplateToResponse};
 use chrono::Utc;
-use sea_orm::{entity::*, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use sea_orm::{
+    entity::*, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    TransactionTrait,
+};
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
     conf.service(update_avatar)
         .service(delete_avatar)
+        .service(update_avatar_source)
         .service(update_preferences)
         .service(update_profile)
         .service(update_social_links)
         .service(delete_social_link)
+        .service(create_api_token)
+        .service(revoke_api_token)
         .service(view_account);
 }
 
@@ -29,6 +41,132 @@ pub struct AccountTemplate {
     pub available_platforms: Vec<SocialPlatform>,
     pub available_themes: Vec<themes::Model>,
     pub chat_rooms: Vec<chat_rooms::Model>,
+    pub avatar_gallery: Vec<AvatarGalleryItem>,
+    pub storage: crate::filesystem::quota::StorageUsage,
+    pub saved_searches: Vec<saved_searches::Model>,
+    pub api_tokens: Vec<api_tokens::Model>,
+    /// Change of email address waiting for confirmation
+    pub pending_email_change: Option<email_changes::Model>,
+    /// Activity types kept off the activity feeds
+    pub hidden_activity_types: Vec<crate::activities::Type>,
+}
+
+impl AccountTemplate {
+    /// Scopes offered when creating an API token.
+    pub fn api_scopes(&self) -> [ApiScope; 5] {
+        ApiScope::all()
+    }
+
+    /// Choices for who may post on the member's profile wall.
+    pub fn profile_post_privacy_options(&self) -> &'static [(&'static str, &'static str)] {
+        super::member::PROFILE_POST_PRIVACY
+    }
+
+    /// Activity types the member can choose to publish.
+    pub fn activity_types(&self) -> [crate::activities::Type; 5] {
+        crate::activities::Type::ALL
+    }
+
+    pub fn publishes_activity(&self, activity_type: &crate::activities::Type) -> bool {
+        !self.hidden_activity_types.contains(activity_type)
+    }
+
+    /// The member's auto theme schedule, shown even while it's switched off.
+    pub fn theme_schedule(&self) -> crate::theme::ThemeSchedule {
+        crate::theme::ThemeSchedule {
+            dark_start: self.profile.theme_dark_start,
+            dark_end: self.profile.theme_dark_end,
+        }
+    }
+}
+
+/// Shows a new API token, the only time it can be seen.
+#[derive(Template)]
+#[template(path = "account_api_token.html")]
+pub struct ApiTokenCreatedTemplate {
+    pub client: ClientCtx,
+    pub name: String,
+    pub token: String,
+}
+
+/// Stock avatar as shown in the account page picker.
+pub struct AvatarGalleryItem {
+    pub id: i32,
+    pub label: String,
+    pub filename: String,
+}
+
+impl AvatarGalleryItem {
+    pub fn get_url(&self) -> String {
+        crate::filesystem::get_file_url_by_filename(&self.filename, &self.filename)
+    }
+}
+
+/// Fetches enabled stock avatars in display order.
+pub async fn get_avatar_gallery(db: &DatabaseConnection) -> Result<Vec<AvatarGalleryItem>, DbErr> {
+    use crate::orm::attachments;
+
+    let items = avatar_gallery::Entity::find()
+        .find_also_related(attachments::Entity)
+        .filter(avatar_gallery::Column::IsEnabled.eq(true))
+        .order_by_asc(avatar_gallery::Column::DisplayOrder)
+        .order_by_asc(avatar_gallery::Column::Id)
+        .all(db)
+        .await?;
+
+    Ok(items
+        .into_iter()
+        .filter_map(|(item, attachment)| {
+            attachment.map(|a| AvatarGalleryItem {
+                id: item.id,
+                label: item.label,
+                filename: a.filename,
+            })
+        })
+        .collect())
+}
+
+/// Replaces a user's avatar attachment and records where it came from.
+async fn set_user_avatar(
+    db: &DatabaseConnection,
+    user_id: i32,
+    attachment_id: Option<i32>,
+    source: AvatarSource,
+) -> Result<(), DbErr> {
+    let txn = db.begin().await?;
+
+    user_avatars::Entity::delete_many()
+        .filter(user_avatars::Column::UserId.eq(user_id))
+        .exec(&txn)
+        .await?;
+
+    if let Some(attachment_id) = attachment_id {
+        user_avatars::Entity::insert(user_avatars::ActiveModel {
+            user_id: Set(user_id),
+            attachment_id: Set(attachment_id),
+            created_at: Set(Utc::now().naive_utc()),
+        })
+        .exec(&txn)
+        .await?;
+    }
+
+    users::Entity::update_many()
+        .col_expr(
+            users::Column::AvatarSource,
+            sea_orm::sea_query::Expr::value(source),
+        )
+        .filter(users::Column::Id.eq(user_id))
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+
+    // Avatars are shown to everyone
+    if let Some(attachment_id) = attachment_id {
+        crate::filesystem::visibility::refresh_attachments_visibility([attachment_id]).await;
+    }
+
+    Ok(())
 }
 
 #[post("/account/avatar")]
@@ -37,10 +175,8 @@ async fn update_avatar(
     cookies: actix_session::Session,
     mutipart: Option<Multipart>,
 ) -> impl Responder {
-    use crate::filesystem::{
-        deduplicate_payload, insert_payload_as_attachment, save_field_as_temp_file,
-    };
-    use crate::orm::user_avatars;
+    use crate::filesystem::{insert_payload_as_avatar, save_field_as_temp_file};
+    use crate::imaging::CropBox;
     use futures::{StreamExt, TryStreamExt};
     use std::str;
 
@@ -53,7 +189,9 @@ async fn update_avatar(
     let user_id = client.get_id().unwrap(); // Safe after is_user() check
 
     // Rate limiting - prevent upload spam
-    if let Err(e) = crate::rate_limit::check_file_upload_rate_limit(user_id) {
+    if let Err(e) =
+        crate::rate_limit::check_file_upload_rate_limit(user_id, &client.get_groups()).await
+    {
         log::warn!("Avatar upload rate limit exceeded for user: {}", user_id);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many uploads. Please try again in {} seconds.",
@@ -62,14 +200,16 @@ async fn update_avatar(
     }
 
     let mut csrf_token: Option<String> = None;
-    let mut avatar_processed = false;
+    let mut crop = CropBox::default();
+    let mut payload = None;
 
     if let Some(mut fields) = mutipart {
         while let Ok(Some(mut field)) = fields.try_next().await {
             let disposition = field.content_disposition();
             if let Some(field_name) = disposition.get_name() {
                 match field_name {
-                    "csrf_token" => {
+                    "csrf_token" | "crop_x" | "crop_y" | "crop_size" => {
+                        let field_name = field_name.to_owned();
                         let mut buf: Vec<u8> = Vec::with_capacity(128);
                         while let Some(chunk) = field.next().await {
                             let bytes = chunk.map_err(|e| {
@@ -78,7 +218,17 @@ async fn update_avatar(
                             })?;
                             buf.extend(bytes.to_owned());
                         }
-                        csrf_token = Some(str::from_utf8(&buf).unwrap().to_owned());
+                        let value = str::from_utf8(&buf)
+                            .map_err(|_| error::ErrorBadRequest("Error interpreting user input."))?
+                            .trim()
+                            .to_owned();
+                        match field_name.as_str() {
+                            "csrf_token" => csrf_token = Some(value),
+                            // Crop coordinates are optional; bad values fall back to a centered crop.
+                            "crop_x" => crop.x = value.parse().unwrap_or(0),
+                            "crop_y" => crop.y = value.parse().unwrap_or(0),
+                            _ => crop.size = value.parse().unwrap_or(0),
+                        }
                     }
                     "avatar" => {
                         // Validate CSRF token before processing avatar
@@ -90,39 +240,18 @@ async fn update_avatar(
                         let token = csrf_token.as_ref().unwrap();
                         crate::middleware::csrf::validate_csrf_token(&cookies, token)?;
 
-                        avatar_processed = true;
-                        // Save the file to a temporary location and get payload data.
-                        let payload = match save_field_as_temp_file(&mut field).await? {
-                            Some(payload) => payload,
+                        // Save the file to a temporary location; it is cropped once all fields are read.
+                        payload = match save_field_as_temp_file(
+                            &mut field,
+                            client.preserve_image_orientation(),
+                        )
+                        .await?
+                        {
+                            Some(payload) => Some(payload),
                             None => {
                                 return Err(error::ErrorBadRequest("Upload is empty or improper."))
                             }
                         };
-
-                        // Pass file through deduplication and receive a response..
-                        let response = match deduplicate_payload(&payload).await {
-                            Some(response) => response,
-                            None => match insert_payload_as_attachment(payload, None).await? {
-                                Some(response) => response,
-                                None => {
-                                    return Err(error::ErrorBadRequest(
-                                        "Upload is empty or improper.",
-                                    ))
-                                }
-                            },
-                        };
-
-                        match user_avatars::Entity::insert(user_avatars::ActiveModel {
-                            user_id: Set(client.get_id().unwrap()),
-                            attachment_id: Set(response.id),
-                            created_at: Set(Utc::now().naive_utc()),
-                        })
-                        .exec(get_db_pool())
-                        .await
-                        {
-                            Ok(_) => {}
-                            Err(err) => log::warn!("SQL error when inserting avatar: {:?}", err),
-                        };
                     }
                     _ => {
                         return Err(error::ErrorBadRequest(format!(
@@ -135,10 +264,30 @@ async fn update_avatar(
         }
     }
 
-    // Validate CSRF token if avatar wasn't processed (token might come after avatar in stream)
-    if !avatar_processed {
-        let token = csrf_token.ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
-        crate::middleware::csrf::validate_csrf_token(&cookies, &token)?;
+    match payload {
+        Some(payload) => {
+            let crop = if crop.size > 0 { Some(crop) } else { None };
+            let response = match insert_payload_as_avatar(payload, crop).await? {
+                Some(response) => response,
+                None => return Err(error::ErrorBadRequest("Upload is empty or improper.")),
+            };
+
+            if let Err(err) = set_user_avatar(
+                get_db_pool(),
+                user_id,
+                Some(response.id),
+                AvatarSource::Upload,
+            )
+            .await
+            {
+                log::warn!("SQL error when inserting avatar: {:?}", err);
+            }
+        }
+        // Validate CSRF token if no avatar was sent
+        None => {
+            let token = csrf_token.ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+            crate::middleware::csrf::validate_csrf_token(&cookies, &token)?;
+        }
     }
 
     Ok(HttpResponse::Found()
@@ -152,8 +301,6 @@ async fn delete_avatar(
     cookies: actix_session::Session,
     form: actix_web::web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<impl Responder, Error> {
-    use crate::orm::user_avatars;
-
     let user_id = client.require_login()?;
 
     // Validate CSRF token
@@ -166,9 +313,7 @@ async fn delete_avatar(
     // Note: We don't delete the attachment file itself because it might be
     // deduplicated and used by other users. Attachment cleanup should be
     // handled by a separate garbage collection process.
-    user_avatars::Entity::delete_many()
-        .filter(user_avatars::Column::UserId.eq(user_id))
-        .exec(get_db_pool())
+    set_user_avatar(get_db_pool(), user_id, None, AvatarSource::Upload)
         .await
         .map_err(error::ErrorInternalServerError)?;
 
@@ -177,6 +322,54 @@ async fn delete_avatar(
         .finish())
 }
 
+#[post("/account/avatar/source")]
+async fn update_avatar_source(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: actix_web::web::Form<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+
+    // Validate CSRF token
+    let csrf_token = form
+        .get("csrf_token")
+        .ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, csrf_token)?;
+
+    let db = get_db_pool();
+
+    match form.get("source").map(String::as_str) {
+        Some("gravatar") => {
+            // Gravatar is resolved from the account email; drop any stored image.
+            set_user_avatar(db, user_id, None, AvatarSource::Gravatar)
+                .await
+                .map_err(error::ErrorInternalServerError)?;
+        }
+        Some("gallery") => {
+            let gallery_id: i32 = form
+                .get("gallery_id")
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| error::ErrorBadRequest("Please choose an avatar"))?;
+
+            let item = avatar_gallery::Entity::find_by_id(gallery_id)
+                .filter(avatar_gallery::Column::IsEnabled.eq(true))
+                .one(db)
+                .await
+                .map_err(error::ErrorInternalServerError)?
+                .ok_or_else(|| error::ErrorNotFound("Avatar not found"))?;
+
+            set_user_avatar(db, user_id, Some(item.attachment_id), AvatarSource::Gallery)
+                .await
+                .map_err(error::ErrorInternalServerError)?;
+        }
+        _ => return Err(error::ErrorBadRequest("Invalid avatar source")),
+    }
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", "/account"))
+        .finish())
+}
+
 #[post("/account/preferences")]
 async fn update_preferences(
     client: ClientCtx,
@@ -214,9 +407,17 @@ async fn update_preferences(
         .get("theme")
         .ok_or_else(|| error::ErrorBadRequest("theme missing"))?;
 
-    // Handle "auto" specially - set theme_auto flag and use light as fallback
+    // Handle "auto" specially - set theme_auto flag and keep the chosen light
+    // theme in `theme`, falling back to light
     let (theme_value, theme_auto) = if theme_slug == "auto" {
-        (Some("light".to_string()), true)
+        let light = match form.get("theme_light").filter(|slug| !slug.is_empty()) {
+            Some(slug) if crate::theme::get_theme(slug).is_some_and(|t| !t.is_dark) => {
+                slug.to_string()
+            }
+            Some(_) => return Err(error::ErrorBadRequest("Invalid light theme selection")),
+            None => "light".to_string(),
+        };
+        (Some(light), true)
     } else {
         // Validate theme slug exists and is active
         if !crate::theme::theme_exists(theme_slug) {
@@ -225,12 +426,52 @@ async fn update_preferences(
         (Some(theme_slug.to_string()), false)
     };
 
+    // Dark theme and schedule for auto mode, kept while another theme is picked
+    let theme_dark = match form.get("theme_dark").filter(|slug| !slug.is_empty()) {
+        Some(slug) if crate::theme::get_theme(slug).is_some_and(|t| t.is_dark) => {
+            Some(slug.to_string())
+        }
+        Some(_) => return Err(error::ErrorBadRequest("Invalid dark theme selection")),
+        None => None,
+    };
+    let theme_schedule = form
+        .get("theme_schedule")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    let parse_time = |field: &str| match form.get(field) {
+        Some(value) => crate::notifications::quiet_hours::parse_time(value)
+            .map(Some)
+            .ok_or_else(|| error::ErrorBadRequest(format!("{} must be a time", field))),
+        None => Ok(None),
+    };
+    let theme_dark_start = parse_time("theme_dark_start")?;
+    let theme_dark_end = parse_time("theme_dark_end")?;
+
     // Get show_online preference (checkbox, so may not be present if unchecked)
     let show_online = form
         .get("show_online")
         .map(|v| v == "true")
         .unwrap_or(false);
 
+    // Get who may post on their profile wall
+    let profile_post_privacy = match form.get("profile_post_privacy") {
+        Some(value)
+            if super::member::PROFILE_POST_PRIVACY
+                .iter()
+                .any(|(option, _)| option == value) =>
+        {
+            value.to_string()
+        }
+        Some(_) => return Err(error::ErrorBadRequest("Invalid profile post privacy")),
+        None => "everyone".to_string(),
+    };
+
+    // Activity types to keep off the feeds (checkboxes are only sent when ticked)
+    let hidden_activity_types: Vec<crate::activities::Type> = crate::activities::Type::ALL
+        .into_iter()
+        .filter(|t| !form.contains_key(&format!("publish_{}", t.as_str())))
+        .collect();
+
     // Get default chat room preference
     let default_chat_room: Option<i32> = form
         .get("default_chat_room")
@@ -238,22 +479,40 @@ async fn update_preferences(
         .and_then(|v| if v > 0 { Some(v) } else { None });
 
     // Update the user's preferences
-    let mut user: users::ActiveModel = users::Entity::find_by_id(user_id)
+    let existing = users::Entity::find_by_id(user_id)
         .one(get_db_pool())
         .await
         .map_err(error::ErrorInternalServerError)?
-        .ok_or_else(|| error::ErrorNotFound("User not found"))?
-        .into();
+        .ok_or_else(|| error::ErrorNotFound("User not found"))?;
+    let online_visibility_changed = existing.show_online != show_online;
+    let mut user: users::ActiveModel = existing.into();
 
     user.posts_per_page = Set(posts_per_page);
     user.theme = Set(theme_value);
     user.theme_auto = Set(theme_auto);
+    user.theme_dark = Set(theme_dark);
+    user.theme_schedule = Set(theme_schedule);
+    if let Some(start) = theme_dark_start {
+        user.theme_dark_start = Set(start);
+    }
+    if let Some(end) = theme_dark_end {
+        user.theme_dark_end = Set(end);
+    }
     user.show_online = Set(show_online);
+    user.profile_post_privacy = Set(profile_post_privacy);
     user.default_chat_room = Set(default_chat_room);
     user.update(get_db_pool())
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    crate::activities::set_hidden_types(user_id, &hidden_activity_types)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    if online_visibility_changed {
+        crate::cache::fragment::invalidate(crate::cache::fragment::Fragment::OnlineUsers).await;
+    }
+
     Ok(HttpResponse::Found()
         .append_header(("Location", "/account"))
         .finish())
@@ -339,6 +598,30 @@ async fn update_profile(
         }
     }
 
+    // Word filter: check and apply filters to the signature
+    let signature = match signature {
+        Some(sig) => {
+            let filter_result = crate::word_filter::apply_filters(
+                &sig,
+                crate::word_filter::FilterScope::Signatures,
+            );
+            if filter_result.blocked {
+                log::warn!(
+                    "Signature blocked by word filter: user_id={}, patterns={:?}",
+                    user_id,
+                    filter_result.matched_patterns
+                );
+                return Err(error::ErrorBadRequest(
+                    filter_result
+                        .block_reason
+                        .unwrap_or_else(|| "Your signature contains blocked content.".to_string()),
+                ));
+            }
+            Some(filter_result.content)
+        }
+        None => None,
+    };
+
     // Get and validate custom title (max 100 chars)
     let custom_title = form
         .get("custom_title")
@@ -527,6 +810,109 @@ async fn delete_social_link(
         .finish())
 }
 
+/// Longest a new API token can be set to last
+const MAX_API_TOKEN_DAYS: i64 = 365;
+
+/// POST /account/api-tokens - Create a personal access token for the JSON API
+#[post("/account/api-tokens")]
+async fn create_api_token(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: actix_web::web::Form<Vec<(String, String)>>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+
+    // Scope checkboxes repeat the same field name, so the form is read as pairs.
+    let mut csrf_token = String::new();
+    let mut name = String::new();
+    let mut scopes = Vec::new();
+    let mut expires_days = String::new();
+    for (field, value) in form.into_inner() {
+        match field.as_str() {
+            "csrf_token" => csrf_token = value,
+            "name" => name = value,
+            "scope" => scopes.extend(ApiScope::parse(&value)),
+            "expires_days" => expires_days = value,
+            _ => {}
+        }
+    }
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &csrf_token)?;
+
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > api_token::MAX_NAME_LENGTH {
+        return Err(error::ErrorBadRequest(format!(
+            "Token name must be between 1 and {} characters.",
+            api_token::MAX_NAME_LENGTH
+        )));
+    }
+    if scopes.is_empty() {
+        return Err(error::ErrorBadRequest("Choose at least one scope."));
+    }
+
+    let expires_at = match expires_days.trim() {
+        "" => None,
+        days => {
+            let days = days
+                .parse::<i64>()
+                .ok()
+                .filter(|days| (1..=MAX_API_TOKEN_DAYS).contains(days))
+                .ok_or_else(|| {
+                    error::ErrorBadRequest(format!(
+                        "Expiry must be between 1 and {} days.",
+                        MAX_API_TOKEN_DAYS
+                    ))
+                })?;
+            Some(Utc::now().naive_utc() + chrono::Duration::days(days))
+        }
+    };
+
+    let count = api_token::count_user_tokens(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    if count >= api_token::MAX_TOKENS_PER_USER {
+        return Err(error::ErrorBadRequest(format!(
+            "You can hold at most {} API tokens. Revoke one first.",
+            api_token::MAX_TOKENS_PER_USER
+        )));
+    }
+
+    let (model, token) = api_token::create_token(user_id, name, &scopes, expires_at)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    log::info!("User {} created API token {}", user_id, model.id);
+
+    Ok(ApiTokenCreatedTemplate {
+        client,
+        name: model.name,
+        token,
+    }
+    .to_response())
+}
+
+/// POST /account/api-tokens/{id}/revoke - Revoke one of the user's API tokens
+#[post("/account/api-tokens/{id}/revoke")]
+async fn revoke_api_token(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: actix_web::web::Path<i32>,
+    form: actix_web::web::Form<super::forum::CsrfForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let revoked = api_token::revoke_token(user_id, path.into_inner())
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    if !revoked {
+        return Err(error::ErrorNotFound("API token not found."));
+    }
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", "/account"))
+        .finish())
+}
+
 #[get("/account")]
 async fn view_account(client: ClientCtx) -> Result<impl Responder, Error> {
     if !client.is_user() {
@@ -563,11 +949,39 @@ async fn view_account(client: ClientCtx) -> Result<impl Responder, Error> {
 
     // Get chat rooms for default room selection
     let chat_rooms = chat_rooms::Entity::find()
+        .filter(chat_rooms::Column::IsPrivate.eq(false))
         .order_by_asc(chat_rooms::Column::DisplayOrder)
         .all(db)
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    let avatar_gallery = get_avatar_gallery(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let storage = crate::filesystem::quota::get_storage_usage(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let saved_searches = saved_searches::Entity::find()
+        .filter(saved_searches::Column::UserId.eq(user_id))
+        .order_by_asc(saved_searches::Column::Name)
+        .all(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let api_tokens = api_token::get_user_tokens(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let pending_email_change = crate::email::change::pending_for_user(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let hidden_activity_types = crate::activities::get_hidden_types(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
     Ok(AccountTemplate {
         client,
         profile,
@@ -575,6 +989,12 @@ async fn view_account(client: ClientCtx) -> Result<impl Responder, Error> {
         available_platforms,
         available_themes,
         chat_rooms,
+        avatar_gallery,
+        storage,
+        saved_searches,
+        api_tokens,
+        pending_email_change,
+        hidden_activity_types,
     }
     .to_response())
 }
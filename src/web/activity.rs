@@ -1,13 +1,16 @@
 //! Activity feed routes
 
 use crate::activities::{
-    get_global_feed, get_personal_feed, get_user_feed, ActivityCursor, ActivityDisplay,
+    aggregate, get_global_feed, get_personal_feed, get_user_feed, ActivityCursor, ActivityDisplay,
+    FeedFilter, Type as ActivityType,
 };
 use crate::db::get_db_pool;
 use crate::middleware::ClientCtx;
+use crate::orm::{forums, threads};
 use crate::user::Profile as UserProfile;
 use actix_web::{error, get, web, Error, Responder};
 use askama_actix::{Template, TemplateToResponse};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
     conf.service(view_personal_feed)
@@ -18,6 +21,15 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
 #[derive(serde::Deserialize)]
 pub struct FeedQuery {
     pub cursor: Option<String>,
+    /// Only show activities of this type
+    #[serde(rename = "type")]
+    pub activity_type: Option<String>,
+}
+
+impl FeedQuery {
+    fn activity_type(&self) -> Option<ActivityType> {
+        self.activity_type.as_deref().and_then(ActivityType::parse)
+    }
 }
 
 #[derive(Template)]
@@ -28,6 +40,27 @@ pub struct ActivityFeedTemplate {
     pub next_cursor: Option<String>,
     pub feed_type: FeedType,
     pub profile_user: Option<UserProfile>,
+    /// The type the feed is filtered to, if any
+    pub activity_type: Option<ActivityType>,
+}
+
+impl ActivityFeedTemplate {
+    /// Types offered as filters
+    pub fn activity_types(&self) -> [ActivityType; 5] {
+        ActivityType::ALL
+    }
+
+    pub fn is_filtered_to(&self, activity_type: &ActivityType) -> bool {
+        self.activity_type.as_ref() == Some(activity_type)
+    }
+
+    /// Query string for the next page, keeping the type filter
+    pub fn next_page_query(&self, cursor: &str) -> String {
+        match &self.activity_type {
+            Some(activity_type) => format!("cursor={}&type={}", cursor, activity_type.as_str()),
+            None => format!("cursor={}", cursor),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -69,7 +102,8 @@ async fn view_personal_feed(
     let cursor = query.cursor.as_ref().and_then(|s| ActivityCursor::parse(s));
     let limit = 25;
 
-    let activities = get_personal_feed(user_id, cursor, limit + 1)
+    let filter = feed_filter(&client, query.activity_type()).await?;
+    let activities = get_personal_feed(user_id, &filter, cursor, limit + 1)
         .await
         .map_err(|e| error::ErrorInternalServerError(format!("Database error: {}", e)))?;
 
@@ -81,6 +115,7 @@ async fn view_personal_feed(
         next_cursor,
         feed_type: FeedType::Personal,
         profile_user: None,
+        activity_type: filter.activity_type,
     }
     .to_response())
 }
@@ -94,7 +129,8 @@ async fn view_global_feed(
     let cursor = query.cursor.as_ref().and_then(|s| ActivityCursor::parse(s));
     let limit = 25;
 
-    let activities = get_global_feed(cursor, limit + 1)
+    let filter = feed_filter(&client, query.activity_type()).await?;
+    let activities = get_global_feed(&filter, cursor, limit + 1)
         .await
         .map_err(|e| error::ErrorInternalServerError(format!("Database error: {}", e)))?;
 
@@ -106,6 +142,7 @@ async fn view_global_feed(
         next_cursor,
         feed_type: FeedType::Global,
         profile_user: None,
+        activity_type: filter.activity_type,
     }
     .to_response())
 }
@@ -129,7 +166,8 @@ async fn view_user_activity(
     let cursor = query.cursor.as_ref().and_then(|s| ActivityCursor::parse(s));
     let limit = 25;
 
-    let activities = get_user_feed(profile_user_id, cursor, limit + 1)
+    let filter = feed_filter(&client, query.activity_type()).await?;
+    let activities = get_user_feed(profile_user_id, &filter, cursor, limit + 1)
         .await
         .map_err(|e| error::ErrorInternalServerError(format!("Database error: {}", e)))?;
 
@@ -141,31 +179,62 @@ async fn view_user_activity(
         next_cursor,
         feed_type: FeedType::User,
         profile_user: Some(profile_user),
+        activity_type: filter.activity_type,
     }
     .to_response())
 }
 
-/// Helper to paginate activities and generate next cursor
-fn paginate_activities(
-    mut activities: Vec<ActivityDisplay>,
-    limit: u64,
-) -> (Vec<ActivityDisplay>, Option<String>) {
-    let has_more = activities.len() > limit as usize;
-    if has_more {
-        activities.truncate(limit as usize);
-    }
-
-    let next_cursor = if has_more {
-        activities.last().map(|a| {
-            ActivityCursor {
-                created_at: a.created_at,
-                id: a.id,
-            }
-            .to_string()
+/// The feed filter for a viewer: their own id, the chosen type, and the
+/// forums and threads whose activity they can't see
+async fn feed_filter(
+    client: &ClientCtx,
+    activity_type: Option<ActivityType>,
+) -> Result<FeedFilter, Error> {
+    let db = get_db_pool();
+    let hidden_forum_ids = forums::Entity::find()
+        .all(db)
+        .await
+        .map_err(|e| error::ErrorInternalServerError(format!("Database error: {}", e)))?
+        .into_iter()
+        .filter(|forum| {
+            !(client.can_view_forum(&forum.id) && client.can_in_forum(&forum.id, "thread.view"))
         })
+        .map(|forum| forum.id)
+        .collect();
+
+    // Threads with their own overrides can be hidden inside a visible forum
+    let override_thread_ids = client.threads_with_overrides();
+    let hidden_thread_ids = if override_thread_ids.is_empty() {
+        Vec::new()
     } else {
-        None
+        threads::Entity::find()
+            .filter(threads::Column::Id.is_in(override_thread_ids))
+            .all(db)
+            .await
+            .map_err(|e| error::ErrorInternalServerError(format!("Database error: {}", e)))?
+            .into_iter()
+            .filter(|thread| !client.can_view_thread(&thread.id, &thread.forum_id))
+            .map(|thread| thread.id)
+            .collect()
     };
 
-    (activities, next_cursor)
+    Ok(FeedFilter {
+        viewer_id: client.get_id(),
+        activity_type,
+        hidden_forum_ids,
+        hidden_thread_ids,
+    })
+}
+
+/// Helper to paginate activities, collapse bursts and generate next cursor
+fn paginate_activities(
+    activities: Vec<ActivityDisplay>,
+    limit: u64,
+) -> (Vec<ActivityDisplay>, Option<String>) {
+    let (activities, next_cursor) =
+        crate::pagination::split_page(activities, limit as usize, |a| ActivityCursor {
+            created_at: a.created_at,
+            id: a.id,
+        });
+    (aggregate(activities), next_cursor)
 }
@@ -0,0 +1,228 @@
+//! ActivityPub endpoints: WebFinger, forum actors and their collections, thread
+//! Notes, and the inbox that takes follows. Everything 404s unless federation
+//! is on and the forum is federated and visible to guests.
+
+use crate::activitypub::{self, signatures, signatures::SignatureHeader, CONTENT_TYPE};
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::orm::threads;
+use actix_web::{error, get, post, web, Error, HttpRequest, HttpResponse};
+use sea_orm::EntityTrait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Furthest a signed request's `Date` may be from now, in seconds
+const MAX_CLOCK_SKEW_SECS: i64 = 60 * 60;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(view_webfinger)
+        .service(view_actor)
+        .service(view_outbox)
+        .service(view_followers)
+        .service(view_note)
+        .service(post_inbox);
+}
+
+fn activity_json(value: Value) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(CONTENT_TYPE)
+        .body(value.to_string())
+}
+
+async fn require_federated(client: &ClientCtx, forum_id: i32) -> Result<ClientCtx, Error> {
+    let guest = client.as_guest().await;
+    activitypub::federated_forum(&guest, forum_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Actor not found."))?;
+    Ok(guest)
+}
+
+#[derive(Deserialize)]
+struct WebfingerQuery {
+    resource: String,
+}
+
+/// GET /.well-known/webfinger?resource= - Finds a forum's actor from its handle
+#[get("/.well-known/webfinger")]
+async fn view_webfinger(
+    client: ClientCtx,
+    query: web::Query<WebfingerQuery>,
+) -> Result<HttpResponse, Error> {
+    let base_url = crate::notifications::dispatcher::get_base_url();
+    let forum_id = activitypub::parse_resource(&query.resource, &base_url)
+        .ok_or_else(|| error::ErrorNotFound("Resource not found."))?;
+    require_federated(&client, forum_id).await?;
+    let authority = activitypub::authority(&base_url)
+        .ok_or_else(|| error::ErrorInternalServerError("SITE_URL has no host"))?;
+
+    let actor = activitypub::actor_url(forum_id);
+    Ok(HttpResponse::Ok()
+        .content_type("application/jrd+json")
+        .body(
+            json!({
+                "subject": format!("acct:{}@{}", activitypub::username(forum_id), authority),
+                "aliases": [actor],
+                "links": [
+                    { "rel": "self", "type": CONTENT_TYPE, "href": actor },
+                    {
+                        "rel": "http://webfinger.net/rel/profile-page",
+                        "type": "text/html",
+                        "href": format!("{}/forums/{}/", base_url, forum_id),
+                    },
+                ],
+            })
+            .to_string(),
+        ))
+}
+
+/// GET /ap/forums/{id} - A forum's actor
+#[get("/ap/forums/{forum_id}")]
+async fn view_actor(client: ClientCtx, path: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let forum_id = path.into_inner();
+    let guest = client.as_guest().await;
+    let forum = activitypub::federated_forum(&guest, forum_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Actor not found."))?;
+    let key = activitypub::forum_key(forum_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(activity_json(activitypub::actor(&forum, &key)))
+}
+
+/// GET /ap/forums/{id}/outbox - A forum's recent threads
+#[get("/ap/forums/{forum_id}/outbox")]
+async fn view_outbox(client: ClientCtx, path: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let forum_id = path.into_inner();
+    let guest = require_federated(&client, forum_id).await?;
+    let outbox = activitypub::outbox(&guest, forum_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(activity_json(activitypub::document(outbox)))
+}
+
+/// GET /ap/forums/{id}/followers - How many follow a forum
+#[get("/ap/forums/{forum_id}/followers")]
+async fn view_followers(client: ClientCtx, path: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let forum_id = path.into_inner();
+    require_federated(&client, forum_id).await?;
+    let followers = activitypub::followers(forum_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(activity_json(activitypub::document(followers)))
+}
+
+/// GET /ap/threads/{id} - The Note for a thread
+#[get("/ap/threads/{thread_id}")]
+async fn view_note(client: ClientCtx, path: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let thread = threads::Entity::find_by_id(path.into_inner())
+        .one(get_db_pool())
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Note not found."))?;
+    let guest = require_federated(&client, thread.forum_id).await?;
+    let note = activitypub::note(&guest, &thread)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Note not found."))?;
+
+    Ok(activity_json(activitypub::document(note)))
+}
+
+fn header_str<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// The text the sender signed, rebuilt from the request as received.
+fn signed_text(req: &HttpRequest, signature: &SignatureHeader) -> Option<String> {
+    let target = format!(
+        "{} {}",
+        req.method().as_str().to_lowercase(),
+        req.uri()
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or_else(|| req.path())
+    );
+
+    let mut lines = Vec::with_capacity(signature.headers.len());
+    for name in &signature.headers {
+        let value = match name.as_str() {
+            "(request-target)" => target.as_str(),
+            name => header_str(req, name)?,
+        };
+        lines.push((name.as_str(), value));
+    }
+    Some(signatures::signing_string(&lines))
+}
+
+/// POST /ap/forums/{id}/inbox - Takes signed follows and unfollows
+#[post("/ap/forums/{forum_id}/inbox")]
+async fn post_inbox(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+    body: web::Bytes,
+) -> Result<HttpResponse, Error> {
+    let ip = crate::ip::extract_client_ip(&req)
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    if let Err(e) = crate::rate_limit::check_api_rate_limit(&ip, &client.get_groups()).await {
+        return Err(error::ErrorTooManyRequests(format!(
+            "Too many requests. Please try again in {} seconds.",
+            e.retry_after_seconds
+        )));
+    }
+
+    let forum_id = path.into_inner();
+    require_federated(&client, forum_id).await?;
+
+    let signature = header_str(&req, "signature")
+        .and_then(SignatureHeader::parse)
+        .filter(SignatureHeader::covers_required_headers)
+        .ok_or_else(|| error::ErrorUnauthorized("Missing or incomplete signature."))?;
+
+    // A stale date means a replayed request.
+    let date = header_str(&req, "date")
+        .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+        .ok_or_else(|| error::ErrorUnauthorized("Missing or invalid Date."))?;
+    if (chrono::Utc::now().timestamp() - date.timestamp()).abs() > MAX_CLOCK_SKEW_SECS {
+        return Err(error::ErrorUnauthorized("Date is too far from now."));
+    }
+
+    if !header_str(&req, "digest").is_some_and(|digest| signatures::digest_matches(digest, &body)) {
+        return Err(error::ErrorUnauthorized("Digest does not match the body."));
+    }
+
+    let activity: Value =
+        serde_json::from_slice(&body).map_err(|_| error::ErrorBadRequest("Invalid JSON."))?;
+
+    let sender = activitypub::fetch_signer(&signature.key_id)
+        .await
+        .map_err(|e| {
+            log::debug!("Rejected ActivityPub inbox request: {}", e);
+            error::ErrorUnauthorized("Could not fetch the signing key.")
+        })?;
+    if activity["actor"].as_str() != Some(sender.id.as_str()) {
+        return Err(error::ErrorUnauthorized(
+            "Activity was not signed by its actor.",
+        ));
+    }
+
+    let signed = signed_text(&req, &signature)
+        .ok_or_else(|| error::ErrorUnauthorized("A signed header is missing."))?;
+    if !signatures::verify(&sender.public_key_pem, &signed, &signature.signature) {
+        return Err(error::ErrorUnauthorized("Signature does not verify."));
+    }
+
+    activitypub::receive(forum_id, &sender, &activity)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Accepted().finish())
+}
@@ -1,22 +1,33 @@
 /// Administration and moderation tools
 ///
 /// This module provides endpoints for moderators and administrators.
+use crate::app_state::AppState;
+use crate::cache::CacheScope;
 use crate::config::{Config, SettingValue};
 use crate::db::get_db_pool;
 use crate::group::GroupType;
 use crate::middleware::ClientCtx;
 use crate::orm::{
-    attachments, badges, chat_rooms, feature_flags, forum_moderators, forum_permissions, forums,
-    groups, ip_bans, mod_log, moderator_notes, permission_categories, permission_collections,
-    permission_values, permissions, posts, reaction_types, reports, sessions, settings, tag_forums,
-    tags, themes, threads, user_bans, user_groups, user_names, user_warnings, users, word_filters,
+    attachments, avatar_gallery, badges, chat_room_permissions, chat_rooms, feature_flags,
+    forum_moderators, forum_permission_templates, forum_permissions, forums, groups, ip_bans,
+    mod_log, moderator_notes, permission_categories, permission_collections, permission_values,
+    permissions, posts, rate_limit_exemptions, rate_limit_policies, reaction_types, reports,
+    sessions, settings, tag_forums, tags, themes, thread_permissions, threads, user_bans,
+    user_groups, user_names, user_warnings, users, word_filters,
 };
+use crate::permission::audit;
+use crate::permission::explain;
 use crate::permission::flag::Flag;
+use crate::permission::template;
+use crate::permission::transfer;
+use crate::permission::Permissions;
+use crate::rate_limit::RouteClass;
+use crate::word_filter::{FilterScope, FilterTest};
 use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
 use askama::Template;
 use askama_actix::TemplateToResponse;
 use chrono::{Duration, Utc};
-use sea_orm::{entity::*, query::*, ActiveValue::Set, DatabaseConnection};
+use sea_orm::{entity::*, query::*, ActiveValue::Set, DatabaseConnection, DbErr};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::sync::Arc;
 
@@ -104,6 +115,9 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         .service(update_setting)
         .service(view_feature_flags)
         .service(toggle_feature_flag)
+        .service(view_rate_limits)
+        .service(update_rate_limit)
+        .service(clear_caches)
         // IP ban management
         .service(view_ip_bans)
         .service(view_ip_ban_form)
@@ -111,6 +125,7 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         .service(lift_ip_ban)
         // Word filter management
         .service(view_word_filters)
+        .service(view_word_filter_test)
         .service(view_word_filter_form)
         .service(create_word_filter)
         .service(view_edit_word_filter)
@@ -120,6 +135,12 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         .service(view_users)
         .service(view_edit_user)
         .service(update_user)
+        .service(view_user_permissions)
+        .service(update_user_permissions)
+        .service(view_lockouts)
+        .service(clear_account_lockout)
+        .service(clear_ip_lockout)
+        .service(view_online_members)
         // Moderator notes
         .service(view_user_notes)
         .service(create_user_note)
@@ -146,8 +167,15 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         .service(view_edit_group)
         .service(update_group)
         .service(delete_group)
+        .service(reorder_groups)
+        // Permission audit log
+        .service(view_permission_audit)
+        .service(export_permissions)
+        .service(view_permission_import)
+        .service(import_permissions)
         // Permission hierarchy viewer
         .service(view_permission_hierarchy)
+        .service(view_permission_test)
         .service(get_user_permissions)
         .service(get_group_permissions)
         .service(search_users_autocomplete)
@@ -158,6 +186,17 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         .service(update_reaction_type)
         .service(view_create_reaction_type_form)
         .service(create_reaction_type)
+        // Avatar gallery management
+        .service(view_avatar_gallery)
+        .service(create_avatar_gallery_item)
+        .service(toggle_avatar_gallery_item)
+        .service(delete_avatar_gallery_item)
+        // Storage usage report
+        .service(view_storage_usage)
+        // Attachment browser
+        .service(view_attachments)
+        .service(delete_attachments)
+        .service(view_attachment_uses)
         // Badge management
         .service(view_badges)
         .service(view_create_badge_form)
@@ -174,10 +213,19 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         // Forum permissions management
         .service(view_forum_permissions)
         .service(save_forum_permissions)
+        // Forum permission templates
+        .service(save_permission_template)
+        .service(apply_permission_template)
+        .service(copy_parent_permissions)
+        .service(toggle_default_permission_template)
+        .service(delete_permission_template)
         // Forum moderators management
         .service(view_forum_moderators)
         .service(add_forum_moderator)
         .service(remove_forum_moderator)
+        .service(view_moderators)
+        .service(assign_moderator)
+        .service(unassign_moderator)
         // Tag management
         .service(view_tags)
         .service(view_create_tag_form)
@@ -192,13 +240,23 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         .service(view_edit_chat_room)
         .service(update_chat_room)
         .service(delete_chat_room)
+        .service(view_chat_room_permissions)
+        .service(save_chat_room_permissions)
+        // Thread permissions management
+        .service(view_thread_permissions)
+        .service(save_thread_permissions)
+        // Chat transcripts
+        .service(view_chat_transcript)
+        .service(export_chat_transcript)
         // Theme management
         .service(view_themes)
         .service(view_create_theme_form)
         .service(create_theme)
         .service(view_edit_theme)
         .service(update_theme)
-        .service(delete_theme);
+        .service(delete_theme)
+        .service(view_theme_revisions)
+        .service(restore_theme_revision);
 }
 
 // ============================================================================
@@ -218,6 +276,8 @@ struct DashboardStats {
     active_bans: i64,
     active_ip_bans: i64,
     open_reports: i64,
+    /// Open reports older than the response time in `[reports]`
+    overdue_reports: i64,
     pending_users: i64,
     pending_posts: i64,
     word_filters: i64,
@@ -238,6 +298,8 @@ struct RecentModAction {
     target_type: String,
     target_id: i32,
     created_at: chrono::NaiveDateTime,
+    /// Matches the request_id in the server logs
+    request_id: Option<String>,
 }
 
 /// Open report for dashboard display
@@ -272,7 +334,8 @@ async fn view_dashboard(client: ClientCtx) -> Result<impl Responder, Error> {
         || client.can("admin.word_filters.view")
         || client.can("admin.permissions.manage")
         || client.can("moderate.reports.view")
-        || client.can("moderate.approval.view");
+        || client.can("moderate.approval.view")
+        || client.can("moderate.attachments.manage");
 
     if !has_admin_access {
         return Err(error::ErrorForbidden("Access denied"));
@@ -340,6 +403,10 @@ async fn view_dashboard(client: ClientCtx) -> Result<impl Responder, Error> {
         .await
         .unwrap_or(0) as i64;
 
+    let overdue_reports_count = crate::notifications::reports::overdue_count(now)
+        .await
+        .unwrap_or(0) as i64;
+
     let pending_users_count = users::Entity::find()
         .filter(users::Column::ApprovalStatus.eq(users::ApprovalStatus::Pending))
         .count(db)
@@ -369,10 +436,15 @@ async fn view_dashboard(client: ClientCtx) -> Result<impl Responder, Error> {
         use sea_orm::{ConnectionTrait, Statement};
         let sql = "SELECT pg_size_pretty(pg_database_size(current_database())) as size";
         match db
-            .query_one(Statement::from_string(db.get_database_backend(), sql.to_string()))
+            .query_one(Statement::from_string(
+                db.get_database_backend(),
+                sql.to_string(),
+            ))
             .await
         {
-            Ok(Some(row)) => row.try_get::<String>("", "size").unwrap_or_else(|_| "N/A".to_string()),
+            Ok(Some(row)) => row
+                .try_get::<String>("", "size")
+                .unwrap_or_else(|_| "N/A".to_string()),
             _ => "N/A".to_string(),
         }
     };
@@ -388,6 +460,7 @@ async fn view_dashboard(client: ClientCtx) -> Result<impl Responder, Error> {
         active_bans,
         active_ip_bans,
         open_reports: open_reports_count,
+        overdue_reports: overdue_reports_count,
         pending_users: pending_users_count,
         pending_posts: pending_posts_count,
         word_filters: word_filter_count,
@@ -436,6 +509,7 @@ async fn view_dashboard(client: ClientCtx) -> Result<impl Responder, Error> {
             target_type: m.target_type,
             target_id: m.target_id,
             created_at: m.created_at,
+            request_id: m.request_id,
         })
         .collect();
 
@@ -845,6 +919,7 @@ pub async fn move_thread(
         reason: Set(form.reason.clone()),
         metadata: Set(Some(metadata)),
         created_at: Set(chrono::Utc::now().naive_utc()),
+        request_id: Set(crate::middleware::request_id::current()),
         ..Default::default()
     };
 
@@ -864,13 +939,15 @@ pub async fn move_thread(
         moderator_id
     );
 
+    crate::cache::fragment::posts_changed().await;
+
     Ok(HttpResponse::SeeOther()
         .append_header(("Location", format!("/threads/{}/", thread_id)))
         .finish())
 }
 
 /// Helper function to log moderation actions
-async fn log_moderation_action(
+pub(crate) async fn log_moderation_action(
     db: &DatabaseConnection,
     moderator_id: i32,
     action: &str,
@@ -886,6 +963,7 @@ async fn log_moderation_action(
         reason: Set(reason.map(|s| s.to_string())),
         metadata: Set(None),
         created_at: Set(chrono::Utc::now().naive_utc()),
+        request_id: Set(crate::middleware::request_id::current()),
         ..Default::default()
     };
 
@@ -900,6 +978,35 @@ async fn log_moderation_action(
     Ok(())
 }
 
+/// Read the state a permission change touches, for the audit log
+async fn audit_snapshot(
+    snapshot: impl std::future::Future<Output = Result<audit::Snapshot, DbErr>>,
+) -> Result<audit::Snapshot, Error> {
+    snapshot.await.map_err(|e| {
+        log::error!("Failed to read permissions for the audit log: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })
+}
+
+/// Record a permission change in the audit log, if anything changed
+async fn audit_permission_change(
+    db: &DatabaseConnection,
+    actor_id: i32,
+    change: audit::Change,
+    target_id: i32,
+    before: &audit::Snapshot,
+    after: &audit::Snapshot,
+) -> Result<(), Error> {
+    audit::record(db, Some(actor_id), change, target_id, before, after)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to record permission change: {}", e);
+            error::ErrorInternalServerError("Failed to log action")
+        })?;
+
+    Ok(())
+}
+
 // =============================================================================
 // Ban Management
 // =============================================================================
@@ -1231,6 +1338,43 @@ struct FeatureFlagsTemplate {
     flags: Vec<feature_flags::Model>,
 }
 
+/// A route class's policy as the rate limits page shows it
+struct RateLimitRow {
+    route_class: &'static str,
+    label: &'static str,
+    max_requests: i32,
+    window_seconds: i32,
+    burst: i32,
+    /// Whether groups can be exempted, see `RouteClass::is_per_member`
+    per_member: bool,
+    exempt_groups: Vec<i32>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/rate_limits.html")]
+struct RateLimitsTemplate {
+    client: ClientCtx,
+    policies: Vec<RateLimitRow>,
+    groups: Vec<groups::Model>,
+    updated: bool,
+}
+
+#[derive(Deserialize)]
+struct RateLimitsQuery {
+    updated: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateRateLimitForm {
+    csrf_token: String,
+    route_class: String,
+    max_requests: i32,
+    window_seconds: i32,
+    burst: i32,
+    #[serde(default, deserialize_with = "deserialize_vec_or_single")]
+    exempt_groups: Vec<i32>,
+}
+
 #[derive(Deserialize)]
 struct UpdateSettingForm {
     csrf_token: String,
@@ -1262,6 +1406,7 @@ async fn view_settings(
 
     // Fetch chat rooms for the chat_default_room dropdown
     let chat_rooms_list = chat_rooms::Entity::find()
+        .filter(chat_rooms::Column::IsPrivate.eq(false))
         .order_by_asc(chat_rooms::Column::DisplayOrder)
         .all(db)
         .await
@@ -1318,10 +1463,10 @@ async fn update_setting(
             error::ErrorInternalServerError("Failed to update setting")
         })?;
 
-    // Hot reload for specific setting categories
-    if form.key.starts_with("rate_limit.") {
-        crate::rate_limit::reload_rate_limits(&config);
-    }
+    client
+        .get_permissions()
+        .set_priority_wins(config.get_bool_or(crate::permission::PRIORITY_WINS_SETTING, false));
+    crate::cache::invalidate(CacheScope::Settings).await;
 
     log::info!("Setting '{}' updated by user {}", form.key, user_id);
 
@@ -1330,6 +1475,62 @@ async fn update_setting(
         .finish())
 }
 
+/// Form carrying only the CSRF token
+#[derive(Deserialize)]
+struct ClearCachesForm {
+    csrf_token: String,
+}
+
+/// POST /admin/cache/clear - Empty every cache, on every instance
+#[post("/admin/cache/clear")]
+async fn clear_caches(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    config: web::Data<Arc<Config>>,
+    form: web::Form<ClearCachesForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    // Reload what this process holds itself; invalidate tells the others
+    if let Err(e) = config.load_from_database(get_db_pool()).await {
+        log::error!("Failed to reload settings: {}", e);
+    }
+    if let Err(e) = crate::rate_limit::reload_rate_limits(get_db_pool()).await {
+        log::error!("Failed to reload rate limit policies: {}", e);
+    }
+    if let Err(e) = crate::word_filter::reload_filters(get_db_pool()).await {
+        log::error!("Failed to reload word filters: {}", e);
+    }
+    let permissions = client.get_permissions();
+    if let Err(e) = permissions.reload_forum_permissions().await {
+        log::error!("Failed to reload forum permissions cache: {}", e);
+    }
+    if let Err(e) = permissions.reload_chat_room_permissions().await {
+        log::error!("Failed to reload chat room permissions cache: {}", e);
+    }
+    if let Err(e) = permissions.reload_thread_permissions().await {
+        log::error!("Failed to reload thread permissions cache: {}", e);
+    }
+    if let Err(e) = permissions.reload_group_priorities().await {
+        log::error!("Failed to reload group priorities: {}", e);
+    }
+    permissions
+        .set_priority_wins(config.get_bool_or(crate::permission::PRIORITY_WINS_SETTING, false));
+    for scope in CacheScope::all() {
+        crate::cache::invalidate(scope).await;
+    }
+
+    log::info!("Caches cleared by user {}", user_id);
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin"))
+        .finish())
+}
+
 /// GET /admin/feature-flags - View feature flags
 #[get("/admin/feature-flags")]
 async fn view_feature_flags(
@@ -1373,6 +1574,7 @@ async fn toggle_feature_flag(
             log::error!("Failed to toggle feature flag: {}", e);
             error::ErrorInternalServerError("Failed to toggle feature flag")
         })?;
+    crate::cache::invalidate(CacheScope::Settings).await;
 
     log::info!(
         "Feature flag '{}' set to {} by user {}",
@@ -1386,6 +1588,170 @@ async fn toggle_feature_flag(
         .finish())
 }
 
+/// GET /admin/rate-limits - View rate limit policies
+#[get("/admin/rate-limits")]
+async fn view_rate_limits(
+    client: ClientCtx,
+    query: web::Query<RateLimitsQuery>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+
+    let stored = rate_limit_policies::Entity::find()
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch rate limit policies: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+    let exemptions = rate_limit_exemptions::Entity::find()
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch rate limit exemptions: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+    let all_groups = groups::Entity::find()
+        .order_by_asc(groups::Column::Label)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch groups: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    // Listed in RouteClass order rather than by key
+    let policies = RouteClass::all()
+        .into_iter()
+        .filter_map(|class| {
+            let policy = stored.iter().find(|p| p.route_class == class.as_str())?;
+            Some(RateLimitRow {
+                route_class: class.as_str(),
+                label: class.label(),
+                max_requests: policy.max_requests,
+                window_seconds: policy.window_seconds,
+                burst: policy.burst,
+                per_member: class.is_per_member(),
+                exempt_groups: exemptions
+                    .iter()
+                    .filter(|e| e.route_class == policy.route_class)
+                    .map(|e| e.group_id)
+                    .collect(),
+            })
+        })
+        .collect();
+
+    Ok(RateLimitsTemplate {
+        client,
+        policies,
+        groups: all_groups,
+        updated: query.updated.is_some(),
+    }
+    .to_response())
+}
+
+/// POST /admin/rate-limits - Update one route class's policy
+#[post("/admin/rate-limits")]
+async fn update_rate_limit(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<UpdateRateLimitForm>,
+) -> Result<impl Responder, Error> {
+    use sea_orm::{sea_query::Expr, TransactionTrait};
+
+    let user_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let class = RouteClass::from_name(&form.route_class)
+        .ok_or_else(|| error::ErrorNotFound("Unknown route class"))?;
+    if form.max_requests < 1 || form.window_seconds < 1 || form.burst < 0 {
+        return Err(error::ErrorBadRequest(
+            "Requests and window must be at least 1, and burst can't be negative",
+        ));
+    }
+
+    let db = get_db_pool();
+    let txn = db.begin().await.map_err(error::ErrorInternalServerError)?;
+
+    let result = rate_limit_policies::Entity::update_many()
+        .col_expr(
+            rate_limit_policies::Column::MaxRequests,
+            Expr::value(form.max_requests),
+        )
+        .col_expr(
+            rate_limit_policies::Column::WindowSeconds,
+            Expr::value(form.window_seconds),
+        )
+        .col_expr(rate_limit_policies::Column::Burst, Expr::value(form.burst))
+        .col_expr(
+            rate_limit_policies::Column::UpdatedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .col_expr(rate_limit_policies::Column::UpdatedBy, Expr::value(user_id))
+        .filter(rate_limit_policies::Column::RouteClass.eq(class.as_str()))
+        .exec(&txn)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to update rate limit policy: {}", e);
+            error::ErrorInternalServerError("Failed to update rate limit")
+        })?;
+    if result.rows_affected == 0 {
+        return Err(error::ErrorNotFound("Rate limit policy not found"));
+    }
+
+    // Requests signed-out visitors make can't be exempted by group
+    let exempt_groups = if class.is_per_member() {
+        form.exempt_groups.clone()
+    } else {
+        Vec::new()
+    };
+    rate_limit_exemptions::Entity::delete_many()
+        .filter(rate_limit_exemptions::Column::RouteClass.eq(class.as_str()))
+        .exec(&txn)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    if !exempt_groups.is_empty() {
+        rate_limit_exemptions::Entity::insert_many(exempt_groups.iter().map(|&group_id| {
+            rate_limit_exemptions::ActiveModel {
+                route_class: Set(class.as_str().to_owned()),
+                group_id: Set(group_id),
+            }
+        }))
+        .exec(&txn)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to save rate limit exemptions: {}", e);
+            error::ErrorBadRequest("Invalid group")
+        })?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    if let Err(e) = crate::rate_limit::reload_rate_limits(db).await {
+        log::error!("Failed to reload rate limit policies: {}", e);
+    }
+    crate::cache::invalidate(CacheScope::RateLimits).await;
+
+    log::info!(
+        "Rate limit '{}' set to {} per {}s (burst {}) by user {}",
+        class.as_str(),
+        form.max_requests,
+        form.window_seconds,
+        form.burst,
+        user_id
+    );
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/rate-limits?updated=1"))
+        .finish())
+}
+
 // =============================================================================
 // IP Ban Management
 // =============================================================================
@@ -1648,6 +2014,7 @@ async fn create_ip_ban(
         reason: Set(Some(form.reason.trim().to_string())),
         metadata: Set(Some(metadata)),
         created_at: Set(chrono::Utc::now().naive_utc()),
+        request_id: Set(crate::middleware::request_id::current()),
         ..Default::default()
     };
 
@@ -1734,6 +2101,7 @@ async fn lift_ip_ban(
         reason: Set(form.reason.clone()),
         metadata: Set(Some(metadata)),
         created_at: Set(chrono::Utc::now().naive_utc()),
+        request_id: Set(crate::middleware::request_id::current()),
         ..Default::default()
     };
 
@@ -1768,12 +2136,51 @@ struct WordFiltersTemplate {
     filters: Vec<word_filters::Model>,
 }
 
+impl WordFiltersTemplate {
+    /// The kinds of content a filter applies to, for display
+    fn scope_labels(&self, filter: &word_filters::Model) -> String {
+        FilterScope::ALL
+            .into_iter()
+            .filter(|scope| filter.applies_to(*scope))
+            .map(|scope| scope.label())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
 #[derive(Template)]
 #[template(path = "admin/word_filter_form.html")]
 struct WordFilterFormTemplate {
     client: ClientCtx,
     filter: Option<word_filters::Model>,
     error: Option<String>,
+    scopes: [FilterScope; 5],
+}
+
+impl WordFilterFormTemplate {
+    /// Whether a scope starts ticked: as saved, or as new filters default
+    fn applies_to(&self, scope: &FilterScope) -> bool {
+        match &self.filter {
+            Some(filter) => filter.applies_to(*scope),
+            None => !matches!(scope, FilterScope::Usernames | FilterScope::Signatures),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/word_filter_test.html")]
+struct WordFilterTestTemplate {
+    client: ClientCtx,
+    text: String,
+    scope: FilterScope,
+    scopes: [FilterScope; 5],
+    test: Option<FilterTest>,
+}
+
+impl WordFilterTestTemplate {
+    fn is_scope_selected(&self, scope: &FilterScope) -> bool {
+        self.scope == *scope
+    }
 }
 
 #[derive(Deserialize)]
@@ -1787,6 +2194,17 @@ struct WordFilterForm {
     is_whole_word: Option<String>,
     is_enabled: Option<String>,
     notes: Option<String>,
+    applies_to_posts: Option<String>,
+    applies_to_titles: Option<String>,
+    applies_to_usernames: Option<String>,
+    applies_to_chat: Option<String>,
+    applies_to_signatures: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WordFilterTestQuery {
+    text: Option<String>,
+    scope: Option<String>,
 }
 
 /// GET /admin/word-filters - View all word filters
@@ -1808,6 +2226,46 @@ async fn view_word_filters(client: ClientCtx) -> Result<impl Responder, Error> {
     Ok(WordFiltersTemplate { client, filters }.to_response())
 }
 
+/// GET /admin/word-filters/test - Try sample text against the word filters
+#[get("/admin/word-filters/test")]
+async fn view_word_filter_test(
+    client: ClientCtx,
+    query: web::Query<WordFilterTestQuery>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.word_filters.view")?;
+
+    let query = query.into_inner();
+    let scope = query
+        .scope
+        .as_deref()
+        .and_then(FilterScope::from_name)
+        .unwrap_or(FilterScope::Posts);
+    let text = query.text.unwrap_or_default();
+
+    let test = if text.is_empty() {
+        None
+    } else {
+        let filters = word_filters::Entity::find()
+            .order_by_asc(word_filters::Column::Pattern)
+            .all(get_db_pool())
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch word filters: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?;
+        Some(crate::word_filter::test_filters(&filters, &text, scope))
+    };
+
+    Ok(WordFilterTestTemplate {
+        client,
+        text,
+        scope,
+        scopes: FilterScope::ALL,
+        test,
+    }
+    .to_response())
+}
+
 /// GET /admin/word-filters/new - Show word filter creation form
 #[get("/admin/word-filters/new")]
 async fn view_word_filter_form(client: ClientCtx) -> Result<impl Responder, Error> {
@@ -1817,6 +2275,7 @@ async fn view_word_filter_form(client: ClientCtx) -> Result<impl Responder, Erro
         client,
         filter: None,
         error: None,
+        scopes: FilterScope::ALL,
     }
     .to_response())
 }
@@ -1873,6 +2332,11 @@ async fn create_word_filter(
         created_by: Set(Some(user_id)),
         created_at: Set(Utc::now().naive_utc()),
         notes: Set(form.notes.as_ref().map(|n| n.trim().to_string())),
+        applies_to_posts: Set(form.applies_to_posts.is_some()),
+        applies_to_titles: Set(form.applies_to_titles.is_some()),
+        applies_to_usernames: Set(form.applies_to_usernames.is_some()),
+        applies_to_chat: Set(form.applies_to_chat.is_some()),
+        applies_to_signatures: Set(form.applies_to_signatures.is_some()),
         ..Default::default()
     };
 
@@ -1881,8 +2345,9 @@ async fn create_word_filter(
         error::ErrorInternalServerError("Failed to create word filter")
     })?;
 
-    // Reload filters in cache
+    // Reload filters here; invalidate tells the other instances
     crate::word_filter::reload_filters(db).await.ok();
+    crate::cache::invalidate(CacheScope::WordFilters).await;
 
     log::info!(
         "Word filter '{}' created by user {}",
@@ -1919,6 +2384,7 @@ async fn view_edit_word_filter(
         client,
         filter: Some(filter),
         error: None,
+        scopes: FilterScope::ALL,
     }
     .to_response())
 }
@@ -1984,14 +2450,20 @@ async fn update_word_filter(
     active_filter.action = Set(action);
     active_filter.is_enabled = Set(form.is_enabled.is_some());
     active_filter.notes = Set(form.notes.as_ref().map(|n| n.trim().to_string()));
+    active_filter.applies_to_posts = Set(form.applies_to_posts.is_some());
+    active_filter.applies_to_titles = Set(form.applies_to_titles.is_some());
+    active_filter.applies_to_usernames = Set(form.applies_to_usernames.is_some());
+    active_filter.applies_to_chat = Set(form.applies_to_chat.is_some());
+    active_filter.applies_to_signatures = Set(form.applies_to_signatures.is_some());
 
     active_filter.update(db).await.map_err(|e| {
         log::error!("Failed to update word filter: {}", e);
         error::ErrorInternalServerError("Failed to update word filter")
     })?;
 
-    // Reload filters in cache
+    // Reload filters here; invalidate tells the other instances
     crate::word_filter::reload_filters(db).await.ok();
+    crate::cache::invalidate(CacheScope::WordFilters).await;
 
     log::info!("Word filter {} updated by user {}", filter_id, user_id);
 
@@ -2037,8 +2509,9 @@ async fn delete_word_filter(
             error::ErrorInternalServerError("Failed to delete word filter")
         })?;
 
-    // Reload filters in cache
+    // Reload filters here; invalidate tells the other instances
     crate::word_filter::reload_filters(db).await.ok();
+    crate::cache::invalidate(CacheScope::WordFilters).await;
 
     log::info!(
         "Word filter '{}' (id: {}) deleted by user {}",
@@ -2358,6 +2831,12 @@ async fn update_user(
 
     // If username changed, update the username record
     if new_username != current_username {
+        if !crate::word_filter::is_username_allowed(new_username) {
+            return Err(error::ErrorBadRequest(
+                "That username isn't allowed. Please choose another.",
+            ));
+        }
+
         // Check if username is already taken by another user
         let existing = user_names::Entity::find()
             .filter(user_names::Column::Name.eq(new_username))
@@ -2447,6 +2926,7 @@ async fn update_user(
     if form.reset_lockout.is_some() {
         active_user.failed_login_attempts = Set(0);
         active_user.locked_until = Set(None);
+        active_user.last_failed_login_at = Set(None);
         log::info!(
             "Account lockout reset for user {} by admin {}",
             user_id,
@@ -2492,6 +2972,8 @@ async fn update_user(
     })?;
 
     // Update user groups
+    let groups_before = audit_snapshot(audit::user_groups(db, user_id)).await?;
+
     // First, delete all existing group memberships
     user_groups::Entity::delete_many()
         .filter(user_groups::Column::UserId.eq(user_id))
@@ -2513,6 +2995,18 @@ async fn update_user(
             error::ErrorInternalServerError("Failed to update groups")
         })?;
     }
+    crate::cache::remove(CacheScope::GroupIds, &user_id.to_string()).await;
+
+    let groups_after = audit_snapshot(audit::user_groups(db, user_id)).await?;
+    audit_permission_change(
+        db,
+        admin_id,
+        audit::Change::UserGroups,
+        user_id,
+        &groups_before,
+        &groups_after,
+    )
+    .await?;
 
     // Log the moderation action
     log_moderation_action(db, admin_id, "edit_user", "user", user_id, None).await?;
@@ -2527,68 +3021,461 @@ async fn update_user(
         .finish())
 }
 
-// =============================================================================
-// Moderator Notes
-// =============================================================================
-
-/// Note display for templates
-#[allow(dead_code)]
-struct NoteDisplay {
-    id: i32,
-    author_id: Option<i32>,
-    author_name: String,
-    content: String,
-    created_at: chrono::NaiveDateTime,
-}
-
+/// Template for a member's own global permissions
 #[derive(Template)]
-#[template(path = "admin/user_notes.html")]
-struct UserNotesTemplate {
+#[template(path = "admin/user_permissions.html")]
+struct UserPermissionsTemplate {
     client: ClientCtx,
     user_id: i32,
     username: String,
-    notes: Vec<NoteDisplay>,
-    can_manage: bool,
+    categories: Vec<CategoryDisplay>,
+    /// When the member's permissions lapse
+    expires_at: Option<chrono::NaiveDateTime>,
+    success: bool,
+}
+
+impl UserPermissionsTemplate {
+    fn expires_at_input(&self) -> String {
+        format_expiry_input(self.expires_at)
+    }
 }
 
+/// Form for a member's own global permissions
 #[derive(Deserialize)]
-struct NoteForm {
+struct UserPermissionsForm {
     csrf_token: String,
-    content: String,
+    #[serde(default)]
+    expires_at: String,
+    #[serde(default)]
+    permissions: std::collections::HashMap<String, String>,
 }
 
-/// GET /admin/users/{id}/notes - View moderator notes for a user
-#[get("/admin/users/{id}/notes")]
-async fn view_user_notes(
+/// The collection holding a member's own global permissions, leaving out
+/// any forum, thread or chat room overrides set on them.
+async fn find_user_collection(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<permission_collections::Model>, Error> {
+    permission_collections::Entity::find()
+        .from_raw_sql(sea_orm::Statement::from_sql_and_values(
+            sea_orm::DbBackend::Postgres,
+            r#"SELECT * FROM permission_collections pc
+                WHERE pc.user_id = $1 AND pc.group_id IS NULL
+                    AND NOT EXISTS (SELECT 1 FROM forum_permissions fp WHERE fp.collection_id = pc.id)
+                    AND NOT EXISTS (SELECT 1 FROM chat_room_permissions cp WHERE cp.collection_id = pc.id)
+                    AND NOT EXISTS (SELECT 1 FROM thread_permissions tp WHERE tp.collection_id = pc.id)
+                ORDER BY pc.id
+                LIMIT 1"#,
+            vec![user_id.into()],
+        ))
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission collection: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })
+}
+
+/// GET /admin/users/{id}/permissions - Edit a member's own permissions
+#[get("/admin/users/{id}/permissions")]
+async fn view_user_permissions(
     client: ClientCtx,
     user_id: web::Path<i32>,
+    query: web::Query<std::collections::HashMap<String, String>>,
 ) -> Result<impl Responder, Error> {
-    client.require_permission("moderate.notes.view")?;
+    client.require_permission("admin.permissions.manage")?;
 
     let db = get_db_pool();
     let user_id = user_id.into_inner();
 
-    // Get username
     let username = user_names::Entity::find()
         .filter(user_names::Column::UserId.eq(user_id))
         .one(db)
         .await
-        .ok()
-        .flatten()
+        .map_err(|e| {
+            log::error!("Failed to fetch user name: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
         .map(|un| un.name)
         .ok_or_else(|| error::ErrorNotFound("User not found"))?;
 
-    // Check if user can manage notes
-    let can_manage = client.can("moderate.notes.manage");
+    let collection = find_user_collection(db, user_id).await?;
+    let expires_at = collection.as_ref().and_then(|c| c.expires_at);
+    let categories = load_permission_categories_with_values(db, collection.map(|c| c.id)).await?;
 
-    // Get notes
-    let note_models = moderator_notes::Entity::find()
-        .filter(moderator_notes::Column::UserId.eq(user_id))
-        .order_by_desc(moderator_notes::Column::CreatedAt)
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch notes: {}", e);
+    Ok(UserPermissionsTemplate {
+        client,
+        user_id,
+        username,
+        categories,
+        expires_at,
+        success: query.contains_key("success"),
+    }
+    .to_response())
+}
+
+/// POST /admin/users/{id}/permissions - Save a member's own permissions
+#[post("/admin/users/{id}/permissions")]
+async fn update_user_permissions(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    user_id: web::Path<i32>,
+    form: web::Form<UserPermissionsForm>,
+) -> Result<impl Responder, Error> {
+    let admin_id = client.require_login()?;
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+    let user_id = user_id.into_inner();
+    let expires_at = parse_expiry_input(&form.expires_at)?;
+
+    users::Entity::find_by_id(user_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch user: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("User not found"))?;
+
+    let collection_id = match find_user_collection(db, user_id).await? {
+        Some(c) => c.id,
+        None => {
+            let new_collection = permission_collections::ActiveModel {
+                group_id: Set(None),
+                user_id: Set(Some(user_id)),
+                ..Default::default()
+            };
+            let c = new_collection.insert(db).await.map_err(|e| {
+                log::error!("Failed to create permission collection: {}", e);
+                error::ErrorInternalServerError("Failed to create permission collection")
+            })?;
+            c.id
+        }
+    };
+
+    let permissions_before = audit_snapshot(audit::collection(db, collection_id)).await?;
+    permission_collections::Entity::update_many()
+        .col_expr(
+            permission_collections::Column::ExpiresAt,
+            sea_orm::sea_query::Expr::value(expires_at),
+        )
+        .filter(permission_collections::Column::Id.eq(collection_id))
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to update permission collection: {}", e);
+            error::ErrorInternalServerError("Failed to update permissions")
+        })?;
+    save_group_permissions(db, collection_id, &form.permissions).await?;
+    let permissions_after = audit_snapshot(audit::collection(db, collection_id)).await?;
+    audit_permission_change(
+        db,
+        admin_id,
+        audit::Change::UserPermissions,
+        user_id,
+        &permissions_before,
+        &permissions_after,
+    )
+    .await?;
+
+    log_moderation_action(
+        db,
+        admin_id,
+        "update_user_permissions",
+        "user",
+        user_id,
+        None,
+    )
+    .await?;
+
+    log::info!(
+        "Permissions of user {} updated by admin {}",
+        user_id,
+        admin_id
+    );
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/admin/users/{}/permissions?success=1", user_id),
+        ))
+        .finish())
+}
+
+// =============================================================================
+// Login Lockouts
+// =============================================================================
+
+#[derive(Template)]
+#[template(path = "admin/lockouts.html")]
+struct LockoutsTemplate {
+    client: ClientCtx,
+    accounts: Vec<crate::login_throttle::Lockout>,
+    addresses: Vec<crate::login_throttle::Lockout>,
+    failure_window_minutes: u32,
+}
+
+#[derive(Deserialize)]
+struct ClearIpLockoutForm {
+    csrf_token: String,
+    ip_address: String,
+}
+
+/// GET /admin/lockouts - Accounts and addresses with recent failed logins
+#[get("/admin/lockouts")]
+async fn view_lockouts(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.user.manage")?;
+
+    let (accounts, addresses) = crate::login_throttle::list().await.map_err(|e| {
+        log::error!("Failed to fetch login lockouts: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    Ok(LockoutsTemplate {
+        client,
+        accounts,
+        addresses,
+        failure_window_minutes: crate::app_config::security().failure_window_minutes,
+    }
+    .to_response())
+}
+
+/// POST /admin/lockouts/accounts/{id}/clear - Forget an account's failed logins
+#[post("/admin/lockouts/accounts/{id}/clear")]
+async fn clear_account_lockout(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    user_id: web::Path<i32>,
+    form: web::Form<ClearCachesForm>,
+) -> Result<impl Responder, Error> {
+    let admin_id = client.require_login()?;
+    client.require_permission("admin.user.manage")?;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let user_id = user_id.into_inner();
+    crate::login_throttle::unlock_account(user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to clear account lockout: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    log::info!(
+        "Account lockout reset for user {} by admin {}",
+        user_id,
+        admin_id
+    );
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/lockouts"))
+        .finish())
+}
+
+/// POST /admin/lockouts/addresses/clear - Forget an IP address's failed logins
+#[post("/admin/lockouts/addresses/clear")]
+async fn clear_ip_lockout(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<ClearIpLockoutForm>,
+) -> Result<impl Responder, Error> {
+    let admin_id = client.require_login()?;
+    client.require_permission("admin.user.manage")?;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    crate::login_throttle::clear_ip(&form.ip_address)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to clear IP lockout: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    log::info!(
+        "IP lockout reset for {} by admin {}",
+        form.ip_address,
+        admin_id
+    );
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/lockouts"))
+        .finish())
+}
+
+// =============================================================================
+// Members Online
+// =============================================================================
+
+/// A member online now, with their sessions and what we last saw of them
+struct OnlineMemberRow {
+    user_id: i32,
+    name: String,
+    /// Whether they show their online status to others
+    show_online: bool,
+    visit: crate::presence::Visit,
+    thread_title: Option<String>,
+    sessions: usize,
+    session_expires_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/online.html")]
+struct OnlineMembersTemplate {
+    client: ClientCtx,
+    members: Vec<OnlineMemberRow>,
+    guests: usize,
+    online_threshold_minutes: i64,
+}
+
+/// GET /admin/online - Members online now, including those hiding it
+#[get("/admin/online")]
+async fn view_online_members(
+    client: ClientCtx,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, Error> {
+    use std::collections::HashMap;
+
+    client.require_permission("admin.user.manage")?;
+
+    let db = get_db_pool();
+    let visits = state.presence.members();
+    let user_ids: Vec<i32> = visits.iter().map(|(id, _)| *id).collect();
+    let thread_ids: Vec<i32> = visits.iter().filter_map(|(_, v)| v.thread_id).collect();
+
+    let (names, show_online, thread_titles) = if user_ids.is_empty() {
+        Default::default()
+    } else {
+        let names: HashMap<i32, String> = user_names::Entity::find()
+            .filter(user_names::Column::UserId.is_in(user_ids.clone()))
+            .all(db)
+            .await
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|n| (n.user_id, n.name))
+            .collect();
+        let show_online: HashMap<i32, bool> = users::Entity::find()
+            .filter(users::Column::Id.is_in(user_ids))
+            .all(db)
+            .await
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|u| (u.id, u.show_online))
+            .collect();
+        let thread_titles: HashMap<i32, String> = threads::Entity::find()
+            .filter(threads::Column::Id.is_in(thread_ids))
+            .all(db)
+            .await
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|t| (t.id, t.title))
+            .collect();
+        (names, show_online, thread_titles)
+    };
+
+    // Live sessions per member, and when the longest-lived one expires
+    let mut sessions: HashMap<i32, (usize, chrono::NaiveDateTime)> = HashMap::new();
+    for session in crate::session::get_sess().read().unwrap().values() {
+        let entry = sessions
+            .entry(session.user_id)
+            .or_insert((0, session.expires_at));
+        entry.0 += 1;
+        entry.1 = entry.1.max(session.expires_at);
+    }
+
+    let members = visits
+        .into_iter()
+        .map(|(user_id, visit)| OnlineMemberRow {
+            user_id,
+            name: names
+                .get(&user_id)
+                .cloned()
+                .unwrap_or_else(|| format!("User #{}", user_id)),
+            show_online: show_online.get(&user_id).copied().unwrap_or(true),
+            thread_title: visit
+                .thread_id
+                .and_then(|id| thread_titles.get(&id).cloned()),
+            sessions: sessions.get(&user_id).map_or(0, |s| s.0),
+            session_expires_at: sessions.get(&user_id).map(|s| s.1),
+            visit,
+        })
+        .collect();
+
+    Ok(OnlineMembersTemplate {
+        client,
+        members,
+        guests: state.presence.guest_count(),
+        online_threshold_minutes: crate::user::ONLINE_THRESHOLD_MINUTES,
+    }
+    .to_response())
+}
+
+// =============================================================================
+// Moderator Notes
+// =============================================================================
+
+/// Note display for templates
+#[allow(dead_code)]
+struct NoteDisplay {
+    id: i32,
+    author_id: Option<i32>,
+    author_name: String,
+    content: String,
+    created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Template)]
+#[template(path = "admin/user_notes.html")]
+struct UserNotesTemplate {
+    client: ClientCtx,
+    user_id: i32,
+    username: String,
+    notes: Vec<NoteDisplay>,
+    can_manage: bool,
+}
+
+#[derive(Deserialize)]
+struct NoteForm {
+    csrf_token: String,
+    content: String,
+}
+
+/// GET /admin/users/{id}/notes - View moderator notes for a user
+#[get("/admin/users/{id}/notes")]
+async fn view_user_notes(
+    client: ClientCtx,
+    user_id: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("moderate.notes.view")?;
+
+    let db = get_db_pool();
+    let user_id = user_id.into_inner();
+
+    // Get username
+    let username = user_names::Entity::find()
+        .filter(user_names::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .ok()
+        .flatten()
+        .map(|un| un.name)
+        .ok_or_else(|| error::ErrorNotFound("User not found"))?;
+
+    // Check if user can manage notes
+    let can_manage = client.can("moderate.notes.manage");
+
+    // Get notes
+    let note_models = moderator_notes::Entity::find()
+        .filter(moderator_notes::Column::UserId.eq(user_id))
+        .order_by_desc(moderator_notes::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch notes: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
@@ -2977,6 +3864,7 @@ async fn issue_warning(
 
     // Update user's warning points
     let new_points = user.warning_points + points;
+    let recipient = user.email.clone().filter(|_| user.email_verified);
     let mut active_user: users::ActiveModel = user.into();
     active_user.warning_points = Set(new_points);
     active_user.last_warning_at = Set(Some(now));
@@ -3004,16 +3892,42 @@ async fn issue_warning(
         new_points
     );
 
-    // Check if user should be auto-banned
-    let threshold = config.get_int("warning_threshold").unwrap_or(10) as i32;
-    if new_points >= threshold {
-        // Auto-ban the user
-        let ban_days = config.get_int("warning_ban_duration_days").unwrap_or(7);
-        let (expires_at, is_permanent) = if ban_days == 0 {
-            (None, true)
-        } else {
-            (Some(now + Duration::days(ban_days)), false)
-        };
+    // Let the user know by email
+    if let Some(email) = recipient {
+        let username = user_names::Entity::find()
+            .filter(user_names::Column::UserId.eq(user_id))
+            .one(db)
+            .await
+            .ok()
+            .flatten()
+            .map(|un| un.name)
+            .unwrap_or_else(|| format!("User #{}", user_id));
+
+        if let Err(e) = crate::email::templates::send_warning_email(
+            &email,
+            &username,
+            reason,
+            points,
+            new_points,
+            expires_at,
+            &crate::notifications::dispatcher::get_base_url(),
+        )
+        .await
+        {
+            log::error!("Failed to send warning email: {}", e);
+        }
+    }
+
+    // Check if user should be auto-banned
+    let threshold = config.get_int("warning_threshold").unwrap_or(10) as i32;
+    if new_points >= threshold {
+        // Auto-ban the user
+        let ban_days = config.get_int("warning_ban_duration_days").unwrap_or(7);
+        let (expires_at, is_permanent) = if ban_days == 0 {
+            (None, true)
+        } else {
+            (Some(now + Duration::days(ban_days)), false)
+        };
 
         let ban = user_bans::ActiveModel {
             user_id: Set(user_id),
@@ -3540,6 +4454,8 @@ async fn approve_post(
 
     log::info!("Post {} approved by moderator {}", post_id, moderator_id);
 
+    crate::cache::fragment::posts_changed().await;
+
     Ok(HttpResponse::SeeOther()
         .append_header(("Location", "/admin/post-approval-queue"))
         .finish())
@@ -3861,6 +4777,11 @@ struct GroupDisplay {
     group_type: GroupType,
     is_system: bool,
     member_count: i64,
+    storage_quota: String,
+    /// Label of the group it inherits from
+    parent: Option<String>,
+    /// How it styles its members' names, if it does
+    style: Option<crate::group::GroupStyle>,
 }
 
 /// Template for listing groups
@@ -3892,9 +4813,19 @@ struct CategoryDisplay {
 struct GroupFormTemplate {
     client: ClientCtx,
     group: Option<groups::Model>,
+    /// Groups it may inherit from
+    parents: Vec<groups::Model>,
     categories: Vec<CategoryDisplay>,
     is_edit: bool,
     is_system: bool,
+    /// When the group's permissions lapse
+    expires_at: Option<chrono::NaiveDateTime>,
+}
+
+impl GroupFormTemplate {
+    fn expires_at_input(&self) -> String {
+        format_expiry_input(self.expires_at)
+    }
 }
 
 /// Form for creating/updating a group
@@ -3902,10 +4833,138 @@ struct GroupFormTemplate {
 struct GroupForm {
     csrf_token: String,
     label: String,
+    /// Attachment storage quota in megabytes; blank for unlimited
+    #[serde(default)]
+    storage_quota_mb: String,
+    /// When the group's permissions lapse, as a UTC datetime-local value;
+    /// blank if they don't
+    #[serde(default)]
+    expires_at: String,
+    /// Group this one inherits from; blank for none
+    #[serde(default)]
+    parent_id: String,
+    /// Colour of members' names as `#rrggbb`; blank for the default
+    #[serde(default)]
+    username_color: String,
+    /// Banner shown under members' names; blank for none
+    #[serde(default)]
+    banner_text: String,
     #[serde(default)]
     permissions: std::collections::HashMap<String, String>,
 }
 
+/// Value of a datetime-local input for a permission expiry
+fn format_expiry_input(expires_at: Option<chrono::NaiveDateTime>) -> String {
+    expires_at
+        .map(|at| at.format("%Y-%m-%dT%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Parse a permission expiry from a datetime-local input. Blank means the
+/// permissions don't expire; otherwise it must be in the future.
+fn parse_expiry_input(value: &str) -> Result<Option<chrono::NaiveDateTime>, Error> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    let expires_at = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .map_err(|_| error::ErrorBadRequest("Invalid expiry date format"))?;
+    if expires_at <= Utc::now().naive_utc() {
+        return Err(error::ErrorBadRequest("Expiry must be in the future"));
+    }
+
+    Ok(Some(expires_at))
+}
+
+impl GroupForm {
+    fn parse_storage_quota(&self) -> Result<Option<i32>, Error> {
+        match self.storage_quota_mb.trim() {
+            "" => Ok(None),
+            value => value
+                .parse::<i32>()
+                .ok()
+                .filter(|mb| *mb >= 0)
+                .map(Some)
+                .ok_or_else(|| {
+                    error::ErrorBadRequest("Storage quota must be a whole number of megabytes")
+                }),
+        }
+    }
+
+    fn parse_username_color(&self) -> Result<Option<String>, Error> {
+        match self.username_color.trim() {
+            "" => Ok(None),
+            color if crate::group::is_valid_color(color) => Ok(Some(color.to_lowercase())),
+            _ => Err(error::ErrorBadRequest(
+                "Username colour must be a hex colour such as #3366cc",
+            )),
+        }
+    }
+
+    fn parse_banner_text(&self) -> Result<Option<String>, Error> {
+        match self.banner_text.trim() {
+            "" => Ok(None),
+            banner if banner.chars().count() > MAX_GROUP_BANNER_LENGTH => {
+                Err(error::ErrorBadRequest(format!(
+                    "Banner must be at most {} characters",
+                    MAX_GROUP_BANNER_LENGTH
+                )))
+            }
+            banner => Ok(Some(banner.to_string())),
+        }
+    }
+
+    /// The parent picked, checked to exist and not to be `group_id` or below
+    /// it.
+    async fn parse_parent(
+        &self,
+        db: &DatabaseConnection,
+        group_id: Option<i32>,
+    ) -> Result<Option<i32>, Error> {
+        let parent_id = match self.parent_id.trim() {
+            "" => return Ok(None),
+            value => value
+                .parse::<i32>()
+                .map_err(|_| error::ErrorBadRequest("Invalid parent group"))?,
+        };
+
+        let all_groups = groups::Entity::find().all(db).await.map_err(|e| {
+            log::error!("Failed to fetch groups: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+        if !all_groups.iter().any(|g| g.id == parent_id) {
+            return Err(error::ErrorBadRequest("Parent group not found"));
+        }
+        if let Some(group_id) = group_id {
+            let parents = all_groups
+                .iter()
+                .filter_map(|g| Some((g.id, g.parent_id?)))
+                .collect();
+            if crate::group::would_cycle(group_id, parent_id, &parents) {
+                return Err(error::ErrorBadRequest(
+                    "A group can't inherit from itself or a group below it",
+                ));
+            }
+        }
+
+        Ok(Some(parent_id))
+    }
+}
+
+/// Longest banner a group may give its members, in characters
+const MAX_GROUP_BANNER_LENGTH: usize = 50;
+
+/// Apply a change to groups' priorities or parents to permission checks and
+/// memberships on every instance
+async fn reload_group_changes(permissions: &Permissions) {
+    if let Err(e) = permissions.reload_group_priorities().await {
+        log::error!("Failed to reload group priorities: {}", e);
+    }
+    crate::cache::invalidate(CacheScope::GroupIds).await;
+    crate::cache::invalidate(CacheScope::Permissions).await;
+}
+
 /// GET /admin/groups - List all groups
 #[get("/admin/groups")]
 async fn view_groups(client: ClientCtx) -> Result<impl Responder, Error> {
@@ -3913,8 +4972,9 @@ async fn view_groups(client: ClientCtx) -> Result<impl Responder, Error> {
 
     let db = get_db_pool();
 
-    // Get all groups with member counts
+    // Get all groups with member counts, highest priority first
     let all_groups = groups::Entity::find()
+        .order_by_desc(groups::Column::DisplayPriority)
         .order_by_asc(groups::Column::Id)
         .all(db)
         .await
@@ -3924,7 +4984,7 @@ async fn view_groups(client: ClientCtx) -> Result<impl Responder, Error> {
         })?;
 
     let mut group_displays = Vec::new();
-    for group in all_groups {
+    for group in all_groups.iter().cloned() {
         // Count members in this group
         let member_count = user_groups::Entity::find()
             .filter(user_groups::Column::GroupId.eq(group.id))
@@ -3936,6 +4996,15 @@ async fn view_groups(client: ClientCtx) -> Result<impl Responder, Error> {
 
         group_displays.push(GroupDisplay {
             id: group.id,
+            parent: group
+                .parent_id
+                .and_then(|id| all_groups.iter().find(|g| g.id == id))
+                .map(|g| g.label.clone()),
+            style: crate::group::display_style(&all_groups, &[group.id]),
+            storage_quota: group
+                .storage_quota_mb
+                .map(|mb| crate::filesystem::quota::format_bytes(mb as i64 * 1024 * 1024))
+                .unwrap_or_else(|| "Unlimited".to_string()),
             label: group.label,
             group_type: group.group_type,
             is_system,
@@ -3959,13 +5028,16 @@ async fn view_create_group_form(client: ClientCtx) -> Result<impl Responder, Err
 
     // Get all permission categories with their permissions
     let categories = load_permission_categories(db).await?;
+    let parents = load_parent_choices(db, None).await?;
 
     Ok(GroupFormTemplate {
         client,
         group: None,
+        parents,
         categories,
         is_edit: false,
         is_system: false,
+        expires_at: None,
     }
     .to_response())
 }
@@ -3989,11 +5061,20 @@ async fn create_group(
     if label.is_empty() {
         return Err(error::ErrorBadRequest("Group name cannot be empty"));
     }
+    let storage_quota_mb = form.parse_storage_quota()?;
+    let expires_at = parse_expiry_input(&form.expires_at)?;
+    let username_color = form.parse_username_color()?;
+    let banner_text = form.parse_banner_text()?;
+    let parent_id = form.parse_parent(db, None).await?;
 
     // Create the group
     let new_group = groups::ActiveModel {
         label: Set(label.to_string()),
         group_type: Set(GroupType::Normal),
+        storage_quota_mb: Set(storage_quota_mb),
+        parent_id: Set(parent_id),
+        username_color: Set(username_color),
+        banner_text: Set(banner_text),
         ..Default::default()
     };
 
@@ -4006,6 +5087,7 @@ async fn create_group(
     let collection = permission_collections::ActiveModel {
         group_id: Set(Some(group.id)),
         user_id: Set(None),
+        expires_at: Set(expires_at),
         ..Default::default()
     };
 
@@ -4016,6 +5098,16 @@ async fn create_group(
 
     // Save permissions
     save_group_permissions(db, collection.id, &form.permissions).await?;
+    let permissions_after = audit_snapshot(audit::collection(db, collection.id)).await?;
+    audit_permission_change(
+        db,
+        moderator_id,
+        audit::Change::GroupPermissions,
+        group.id,
+        &audit::Snapshot::new(),
+        &permissions_after,
+    )
+    .await?;
 
     // Log moderation action
     log_moderation_action(
@@ -4030,6 +5122,8 @@ async fn create_group(
 
     log::info!("Group {} created by user {}", group.id, moderator_id);
 
+    reload_group_changes(client.get_permissions()).await;
+
     Ok(HttpResponse::SeeOther()
         .append_header(("Location", format!("/admin/groups/{}/edit", group.id)))
         .finish())
@@ -4068,15 +5162,20 @@ async fn view_edit_group(
             error::ErrorInternalServerError("Database error")
         })?;
 
+    let expires_at = collection.as_ref().and_then(|c| c.expires_at);
+
     // Load categories with current permission values
     let categories = load_permission_categories_with_values(db, collection.map(|c| c.id)).await?;
+    let parents = load_parent_choices(db, Some(group_id)).await?;
 
     Ok(GroupFormTemplate {
         client,
         group: Some(group),
+        parents,
         categories,
         is_edit: true,
         is_system,
+        expires_at,
     }
     .to_response())
 }
@@ -4107,18 +5206,28 @@ async fn update_group(
         })?
         .ok_or_else(|| error::ErrorNotFound("Group not found"))?;
 
-    // Update group label (only for non-system groups)
-    if group.group_type == GroupType::Normal {
+    // Update group label (only for non-system groups), storage quota and display
+    let storage_quota_mb = form.parse_storage_quota()?;
+    let expires_at = parse_expiry_input(&form.expires_at)?;
+    let username_color = form.parse_username_color()?;
+    let banner_text = form.parse_banner_text()?;
+    let parent_id = form.parse_parent(db, Some(group_id)).await?;
+    let is_normal = group.group_type == GroupType::Normal;
+    let mut active_group: groups::ActiveModel = group.into();
+    if is_normal {
         let label = form.label.trim();
         if !label.is_empty() {
-            let mut active_group: groups::ActiveModel = group.into();
             active_group.label = Set(label.to_string());
-            active_group.update(db).await.map_err(|e| {
-                log::error!("Failed to update group: {}", e);
-                error::ErrorInternalServerError("Failed to update group")
-            })?;
         }
     }
+    active_group.storage_quota_mb = Set(storage_quota_mb);
+    active_group.parent_id = Set(parent_id);
+    active_group.username_color = Set(username_color);
+    active_group.banner_text = Set(banner_text);
+    active_group.update(db).await.map_err(|e| {
+        log::error!("Failed to update group: {}", e);
+        error::ErrorInternalServerError("Failed to update group")
+    })?;
 
     // Get or create permission collection
     let collection = permission_collections::Entity::find()
@@ -4131,12 +5240,23 @@ async fn update_group(
         })?;
 
     let collection_id = match collection {
-        Some(c) => c.id,
+        Some(c) => {
+            if c.expires_at != expires_at {
+                let mut active_collection: permission_collections::ActiveModel = c.clone().into();
+                active_collection.expires_at = Set(expires_at);
+                active_collection.update(db).await.map_err(|e| {
+                    log::error!("Failed to update permission collection: {}", e);
+                    error::ErrorInternalServerError("Failed to update permissions")
+                })?;
+            }
+            c.id
+        }
         None => {
             // Create collection if it doesn't exist
             let new_collection = permission_collections::ActiveModel {
                 group_id: Set(Some(group_id)),
                 user_id: Set(None),
+                expires_at: Set(expires_at),
                 ..Default::default()
             };
             let c = new_collection.insert(db).await.map_err(|e| {
@@ -4148,7 +5268,18 @@ async fn update_group(
     };
 
     // Save permissions
+    let permissions_before = audit_snapshot(audit::collection(db, collection_id)).await?;
     save_group_permissions(db, collection_id, &form.permissions).await?;
+    let permissions_after = audit_snapshot(audit::collection(db, collection_id)).await?;
+    audit_permission_change(
+        db,
+        moderator_id,
+        audit::Change::GroupPermissions,
+        group_id,
+        &permissions_before,
+        &permissions_after,
+    )
+    .await?;
 
     // Log moderation action
     log_moderation_action(
@@ -4163,6 +5294,8 @@ async fn update_group(
 
     log::info!("Group {} updated by user {}", group_id, moderator_id);
 
+    reload_group_changes(client.get_permissions()).await;
+
     Ok(HttpResponse::SeeOther()
         .append_header(("Location", format!("/admin/groups/{}/edit", group_id)))
         .finish())
@@ -4206,6 +5339,8 @@ async fn delete_group(
     }
 
     let group_label = group.label.clone();
+    let permissions_before = audit_snapshot(audit::group_permissions(db, group_id)).await?;
+    let members_before = audit_snapshot(audit::group_members(db, group_id)).await?;
 
     // Delete the group (cascades to user_groups and permission_collections)
     groups::Entity::delete_by_id(group_id)
@@ -4216,6 +5351,25 @@ async fn delete_group(
             error::ErrorInternalServerError("Failed to delete group")
         })?;
 
+    audit_permission_change(
+        db,
+        moderator_id,
+        audit::Change::GroupPermissions,
+        group_id,
+        &permissions_before,
+        &audit::Snapshot::new(),
+    )
+    .await?;
+    audit_permission_change(
+        db,
+        moderator_id,
+        audit::Change::GroupMembers,
+        group_id,
+        &members_before,
+        &audit::Snapshot::new(),
+    )
+    .await?;
+
     // Log moderation action
     log_moderation_action(
         db,
@@ -4229,39 +5383,20 @@ async fn delete_group(
 
     log::info!("Group {} deleted by user {}", group_id, moderator_id);
 
+    // Members lost the group along with it, and groups below it their parent
+    reload_group_changes(client.get_permissions()).await;
+
     Ok(HttpResponse::SeeOther()
         .append_header(("Location", "/admin/groups"))
         .finish())
 }
 
-// ============================================================================
-// Permission Hierarchy Viewer
-// ============================================================================
-
-#[derive(Template)]
-#[template(path = "admin/permission_hierarchy.html")]
-struct PermissionHierarchyTemplate {
-    client: ClientCtx,
-    groups: Vec<groups::Model>,
-    forums: Vec<ForumTreeItem>,
-}
-
-/// Forum item for hierarchy display
-#[derive(Clone)]
-struct ForumTreeItem {
-    id: i32,
-    label: String,
-    depth: i32,
-    indent: String,
-}
-
-/// GET /admin/permissions/hierarchy - Permission hierarchy viewer page
-#[get("/admin/permissions/hierarchy")]
-async fn view_permission_hierarchy(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
-
-    let db = get_db_pool();
-
+/// Groups that `group_id`, or a new group if None, may inherit from: any
+/// but itself and those below it.
+async fn load_parent_choices(
+    db: &DatabaseConnection,
+    group_id: Option<i32>,
+) -> Result<Vec<groups::Model>, Error> {
     let all_groups = groups::Entity::find()
         .order_by_asc(groups::Column::Label)
         .all(db)
@@ -4271,185 +5406,424 @@ async fn view_permission_hierarchy(client: ClientCtx) -> Result<impl Responder,
             error::ErrorInternalServerError("Database error")
         })?;
 
-    // Fetch forums with hierarchy
-    let all_forums = forums::Entity::find()
-        .order_by_asc(forums::Column::DisplayOrder)
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch forums: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+    let Some(group_id) = group_id else {
+        return Ok(all_groups);
+    };
+    let parents = all_groups
+        .iter()
+        .filter_map(|g| Some((g.id, g.parent_id?)))
+        .collect();
+    Ok(all_groups
+        .into_iter()
+        .filter(|g| !crate::group::would_cycle(group_id, g.id, &parents))
+        .collect())
+}
 
-    // Build parent map for depth calculation
-    let parent_map: std::collections::HashMap<i32, Option<i32>> =
-        all_forums.iter().map(|f| (f.id, f.parent_id)).collect();
+/// Form for reordering groups
+#[derive(Deserialize)]
+struct ReorderGroupsForm {
+    csrf_token: String,
+    /// Every group ID, comma separated, highest priority first
+    order: String,
+}
 
-    fn get_depth(forum_id: i32, parent_map: &std::collections::HashMap<i32, Option<i32>>) -> i32 {
-        let mut depth = 0;
-        let mut current = parent_map.get(&forum_id).copied().flatten();
-        while current.is_some() {
-            depth += 1;
-            current = parent_map.get(&current.unwrap()).copied().flatten();
-        }
-        depth
+/// POST /admin/groups/reorder - Set group display priorities from their order
+#[post("/admin/groups/reorder")]
+async fn reorder_groups(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<ReorderGroupsForm>,
+) -> Result<impl Responder, Error> {
+    use sea_orm::{sea_query::Expr, TransactionTrait};
+
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+
+    let order = form
+        .order
+        .split(',')
+        .map(|id| id.trim().parse::<i32>())
+        .collect::<Result<Vec<i32>, _>>()
+        .map_err(|_| error::ErrorBadRequest("Invalid group order"))?;
+
+    let all_groups = groups::Entity::find().all(db).await.map_err(|e| {
+        log::error!("Failed to fetch groups: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    // The order must hold every group once, so none is left at an old priority
+    let mut sorted = order.clone();
+    sorted.sort_unstable();
+    let mut expected: Vec<i32> = all_groups.iter().map(|g| g.id).collect();
+    expected.sort_unstable();
+    if sorted != expected {
+        return Err(error::ErrorBadRequest(
+            "Group order must list every group once",
+        ));
     }
 
-    let forum_tree: Vec<ForumTreeItem> = all_forums
+    // Top of the list gets the highest priority
+    let count = order.len() as i32;
+    let moved: Vec<(&groups::Model, i32)> = order
         .iter()
-        .map(|f| {
-            let depth = get_depth(f.id, &parent_map);
-            ForumTreeItem {
-                id: f.id,
-                label: f.label.clone(),
-                depth,
-                indent: "—".repeat(depth as usize),
-            }
+        .enumerate()
+        .filter_map(|(position, id)| {
+            let group = all_groups.iter().find(|g| g.id == *id)?;
+            let priority = count - position as i32;
+            (group.display_priority != priority).then_some((group, priority))
         })
         .collect();
 
-    Ok(PermissionHierarchyTemplate {
-        client,
-        groups: all_groups,
-        forums: forum_tree,
+    let txn = db.begin().await.map_err(|e| {
+        log::error!("Failed to start transaction: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+    for (group, priority) in &moved {
+        groups::Entity::update_many()
+            .col_expr(groups::Column::DisplayPriority, Expr::value(*priority))
+            .filter(groups::Column::Id.eq(group.id))
+            .exec(&txn)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to update group priority: {}", e);
+                error::ErrorInternalServerError("Failed to reorder groups")
+            })?;
     }
-    .to_response())
-}
+    txn.commit()
+        .await
+        .map_err(error::ErrorInternalServerError)?;
 
-/// JSON response for user permission hierarchy
-#[derive(Serialize)]
-struct UserPermissionHierarchy {
-    username: String,
-    user_id: i32,
-    groups: Vec<UserGroupInfo>,
-    forums: Vec<ForumModStatus>,
-    permissions: std::collections::HashMap<String, std::collections::HashMap<String, String>>,
-    permission_sources: std::collections::HashMap<String, String>,
+    for (group, _) in &moved {
+        log_moderation_action(
+            db,
+            moderator_id,
+            "reorder_group",
+            "group",
+            group.id,
+            Some(&group.label),
+        )
+        .await?;
+    }
+
+    log::info!("Groups reordered by user {}", moderator_id);
+
+    reload_group_changes(client.get_permissions()).await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/groups"))
+        .finish())
 }
 
-#[derive(Serialize)]
-struct UserGroupInfo {
-    id: i32,
-    label: String,
-    is_primary: bool,
+// ============================================================================
+// Permission Audit Log
+// ============================================================================
+
+#[derive(Template)]
+#[template(path = "admin/permission_audit.html")]
+struct PermissionAuditTemplate {
+    client: ClientCtx,
+    entries: Vec<audit::Entry>,
+    changes: [audit::Change; 7],
+    change: String,
+    actor: String,
+    target: String,
+    page: i64,
+    total_pages: i64,
 }
 
-#[derive(Serialize)]
-struct ForumModStatus {
-    id: i32,
-    label: String,
-    depth: i32,
-    is_moderator: bool,
-    inherits_mod: bool,
+#[derive(Deserialize)]
+struct PermissionAuditQuery {
+    page: Option<i64>,
+    change: Option<String>,
+    actor: Option<String>,
+    target: Option<String>,
 }
 
-/// GET /admin/permissions/hierarchy/user - Get user permission hierarchy (AJAX)
-#[get("/admin/permissions/hierarchy/user")]
-async fn get_user_permissions(
+/// GET /admin/permissions/audit - Changes to permissions and group membership
+#[get("/admin/permissions/audit")]
+async fn view_permission_audit(
     client: ClientCtx,
-    query: web::Query<std::collections::HashMap<String, String>>,
+    query: web::Query<PermissionAuditQuery>,
 ) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+    client.require_permission("admin.permissions.manage")?;
 
-    let username = query.get("username").map(|s| s.trim()).unwrap_or("");
+    let db = get_db_pool();
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = 50;
 
-    if username.is_empty() {
-        return Ok(web::Json(serde_json::json!({"error": "Username required"})));
-    }
+    let change = query.change.clone().unwrap_or_default();
+    let actor = query.actor.clone().unwrap_or_default().trim().to_string();
+    let target = query.target.clone().unwrap_or_default().trim().to_string();
+    let filter = audit::Filter {
+        change: audit::Change::from_name(&change),
+        actor: Some(actor.clone()).filter(|a| !a.is_empty()),
+        target_id: target.parse().ok(),
+    };
 
-    let db = get_db_pool();
+    let total_count = audit::count(db, &filter).await.map_err(|e| {
+        log::error!("Failed to count permission audit entries: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+    let total_pages = ((total_count + per_page - 1) / per_page).max(1);
 
-    // Find user by username
-    let user_name = user_names::Entity::find()
-        .filter(user_names::Column::Name.eq(username))
-        .one(db)
+    let entries = audit::list(db, &filter, per_page as u64, ((page - 1) * per_page) as u64)
         .await
         .map_err(|e| {
-            log::error!("Failed to look up user: {}", e);
+            log::error!("Failed to fetch permission audit entries: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    let user_name = match user_name {
-        Some(u) => u,
-        None => return Ok(web::Json(serde_json::json!({"error": "User not found"}))),
+    Ok(PermissionAuditTemplate {
+        client,
+        entries,
+        changes: audit::Change::all(),
+        change,
+        actor,
+        target,
+        page,
+        total_pages,
+    }
+    .to_response())
+}
+
+// ============================================================================
+// Permission Export/Import
+// ============================================================================
+
+#[derive(Template)]
+#[template(path = "admin/permission_import.html")]
+struct PermissionImportTemplate {
+    client: ClientCtx,
+    /// The pasted export, kept so it can be confirmed or corrected
+    data: String,
+    import: Option<transfer::Import>,
+    success: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PermissionImportQuery {
+    imported: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct PermissionImportForm {
+    csrf_token: String,
+    data: String,
+    /// Set by the preview's import button
+    confirm: Option<String>,
+}
+
+/// GET /admin/permissions/export - Download the permission configuration as JSON
+#[get("/admin/permissions/export")]
+async fn export_permissions(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.permissions.manage")?;
+
+    let export = transfer::export(get_db_pool()).await.map_err(|e| {
+        log::error!("Failed to export permissions: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+    let body = serde_json::to_string_pretty(&export).map_err(|e| {
+        log::error!("Failed to serialize permission export: {}", e);
+        error::ErrorInternalServerError("Failed to export permissions")
+    })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"permissions-{}.json\"",
+                chrono::Utc::now().format("%Y-%m-%d")
+            ),
+        ))
+        .body(body))
+}
+
+/// GET /admin/permissions/import - Form to paste an export into
+#[get("/admin/permissions/import")]
+async fn view_permission_import(
+    client: ClientCtx,
+    query: web::Query<PermissionImportQuery>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.permissions.manage")?;
+
+    Ok(PermissionImportTemplate {
+        client,
+        data: String::new(),
+        import: None,
+        success: query
+            .imported
+            .map(|count| format!("Imported {} permission changes.", count)),
+    }
+    .to_response())
+}
+
+/// POST /admin/permissions/import - Preview an import, or apply it once confirmed
+#[post("/admin/permissions/import")]
+async fn import_permissions(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<PermissionImportForm>,
+) -> Result<impl Responder, Error> {
+    use sea_orm::TransactionTrait;
+
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+    let form = form.into_inner();
+
+    let import = match serde_json::from_str::<transfer::Export>(&form.data) {
+        Ok(export) => transfer::prepare(db, &export).await.map_err(|e| {
+            log::error!("Failed to check permission import: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?,
+        Err(e) => transfer::Import {
+            errors: vec![format!("This isn't a permission export: {}", e)],
+            ..Default::default()
+        },
     };
 
-    let user_id = user_name.user_id;
+    if form.confirm.is_none() || !import.is_valid() {
+        return Ok(PermissionImportTemplate {
+            client,
+            data: form.data,
+            import: Some(import),
+            success: None,
+        }
+        .to_response());
+    }
 
-    // Get user's groups
-    let user_group_rows = user_groups::Entity::find()
-        .filter(user_groups::Column::UserId.eq(user_id))
-        .find_also_related(groups::Entity)
-        .all(db)
+    let mut groups_before = Vec::with_capacity(import.groups.len());
+    for group in &import.groups {
+        groups_before.push(audit_snapshot(audit::group_permissions(db, group.group_id)).await?);
+    }
+    let mut forums_before = Vec::with_capacity(import.forums.len());
+    for forum in &import.forums {
+        forums_before.push(audit_snapshot(audit::forum(db, forum.forum_id)).await?);
+    }
+
+    let txn = db.begin().await.map_err(error::ErrorInternalServerError)?;
+    transfer::apply(&txn, &import).await.map_err(|e| {
+        log::error!("Failed to import permissions: {}", e);
+        error::ErrorInternalServerError("Failed to update permissions")
+    })?;
+    txn.commit()
         .await
-        .map_err(|e| {
-            log::error!("Failed to fetch user groups: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+        .map_err(error::ErrorInternalServerError)?;
 
-    let mut user_groups_info: Vec<UserGroupInfo> = user_group_rows
-        .into_iter()
-        .filter_map(|(_, group)| {
-            group.map(|g| UserGroupInfo {
-                id: g.id,
-                label: g.label,
-                is_primary: false,
-            })
-        })
-        .collect();
+    for (group, before) in import.groups.iter().zip(&groups_before) {
+        let after = audit_snapshot(audit::group_permissions(db, group.group_id)).await?;
+        audit_permission_change(
+            db,
+            moderator_id,
+            audit::Change::GroupPermissions,
+            group.group_id,
+            before,
+            &after,
+        )
+        .await?;
+    }
+    for (forum, before) in import.forums.iter().zip(&forums_before) {
+        let after = audit_snapshot(audit::forum(db, forum.forum_id)).await?;
+        audit_permission_change(
+            db,
+            moderator_id,
+            audit::Change::ForumPermissions,
+            forum.forum_id,
+            before,
+            &after,
+        )
+        .await?;
+    }
 
-    // Sort by label
-    user_groups_info.sort_by(|a, b| a.label.cmp(&b.label));
+    let changes = import.differences().len();
+    log::info!(
+        "Permission import with {} changes applied by user {}",
+        changes,
+        moderator_id
+    );
 
-    // Mark first group as primary (if any)
-    if !user_groups_info.is_empty() {
-        user_groups_info[0].is_primary = true;
+    if let Err(e) = client.get_permissions().reload_forum_permissions().await {
+        log::error!("Failed to reload forum permissions cache: {}", e);
     }
+    crate::cache::invalidate(CacheScope::Permissions).await;
 
-    // Get all forums with hierarchy
-    let forums = forums::Entity::find()
-        .order_by_asc(forums::Column::DisplayOrder)
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/admin/permissions/import?imported={}", changes),
+        ))
+        .finish())
+}
+
+// ============================================================================
+// Permission Hierarchy Viewer
+// ============================================================================
+
+#[derive(Template)]
+#[template(path = "admin/permission_hierarchy.html")]
+struct PermissionHierarchyTemplate {
+    client: ClientCtx,
+    groups: Vec<groups::Model>,
+    forums: Vec<ForumTreeItem>,
+}
+
+/// Forum item for hierarchy display
+#[derive(Clone)]
+struct ForumTreeItem {
+    id: i32,
+    label: String,
+    depth: i32,
+    indent: String,
+}
+
+/// GET /admin/permissions/hierarchy - Permission hierarchy viewer page
+#[get("/admin/permissions/hierarchy")]
+async fn view_permission_hierarchy(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+
+    let all_groups = groups::Entity::find()
+        .order_by_asc(groups::Column::Label)
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forums: {}", e);
+            log::error!("Failed to fetch groups: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    // Get user's direct moderator assignments
-    let mod_assignments: std::collections::HashSet<i32> = forum_moderators::Entity::find()
-        .filter(forum_moderators::Column::UserId.eq(user_id))
+    // Fetch forums with hierarchy
+    let all_forums = forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch moderator status: {}", e);
+            log::error!("Failed to fetch forums: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?
-        .into_iter()
-        .map(|m| m.forum_id)
-        .collect();
-
-    // Build parent map for inheritance
-    let parent_map: std::collections::HashMap<i32, Option<i32>> =
-        forums.iter().map(|f| (f.id, f.parent_id)).collect();
+        })?;
 
-    // Check if a forum inherits mod status from parent
-    fn inherits_mod(
-        forum_id: i32,
-        direct_mods: &std::collections::HashSet<i32>,
-        parent_map: &std::collections::HashMap<i32, Option<i32>>,
-    ) -> bool {
-        let mut current = parent_map.get(&forum_id).copied().flatten();
-        while let Some(parent_id) = current {
-            if direct_mods.contains(&parent_id) {
-                return true;
-            }
-            current = parent_map.get(&parent_id).copied().flatten();
-        }
-        false
+    Ok(PermissionHierarchyTemplate {
+        client,
+        groups: all_groups,
+        forums: build_forum_tree(&all_forums),
     }
+    .to_response())
+}
+
+/// Forums with their depth for indented select lists
+fn build_forum_tree(all_forums: &[forums::Model]) -> Vec<ForumTreeItem> {
+    // Build parent map for depth calculation
+    let parent_map: std::collections::HashMap<i32, Option<i32>> =
+        all_forums.iter().map(|f| (f.id, f.parent_id)).collect();
 
-    // Build forum tree with depths
     fn get_depth(forum_id: i32, parent_map: &std::collections::HashMap<i32, Option<i32>>) -> i32 {
         let mut depth = 0;
         let mut current = parent_map.get(&forum_id).copied().flatten();
@@ -4460,1993 +5834,2328 @@ async fn get_user_permissions(
         depth
     }
 
-    let forum_status: Vec<ForumModStatus> = forums
+    all_forums
         .iter()
         .map(|f| {
-            let is_mod = mod_assignments.contains(&f.id);
-            let inherits = !is_mod && inherits_mod(f.id, &mod_assignments, &parent_map);
-            ForumModStatus {
+            let depth = get_depth(f.id, &parent_map);
+            ForumTreeItem {
                 id: f.id,
                 label: f.label.clone(),
-                depth: get_depth(f.id, &parent_map),
-                is_moderator: is_mod,
-                inherits_mod: inherits,
+                depth,
+                indent: "—".repeat(depth as usize),
             }
         })
-        .collect();
-
-    // Get effective permissions
-    let group_ids: Vec<i32> = user_groups_info.iter().map(|g| g.id).collect();
-    let (permissions, sources) =
-        compute_effective_permissions(db, &group_ids, Some(user_id)).await?;
+        .collect()
+}
 
-    Ok(web::Json(serde_json::json!(UserPermissionHierarchy {
-        username: user_name.name,
-        user_id,
-        groups: user_groups_info,
-        forums: forum_status,
-        permissions,
-        permission_sources: sources,
-    })))
+#[derive(Template)]
+#[template(path = "admin/permission_test.html")]
+struct PermissionTestTemplate {
+    client: ClientCtx,
+    groups: Vec<groups::Model>,
+    forums: Vec<ForumTreeItem>,
+    /// Username tested, empty for a guest
+    username: String,
+    /// Groups the tested client is in
+    tested_groups: Vec<i32>,
+    /// Whether the groups were picked rather than the member's own
+    custom: bool,
+    forum_id: Option<i32>,
+    categories: Vec<PermissionTestCategory>,
+    error: Option<String>,
 }
 
-/// JSON response for group permission info
-#[derive(Serialize)]
-struct GroupPermissionInfo {
-    id: i32,
+struct PermissionTestCategory {
     label: String,
-    user_count: i64,
-    users: Vec<GroupUserInfo>,
-    permissions: std::collections::HashMap<String, std::collections::HashMap<String, String>>,
+    permissions: Vec<PermissionTestRow>,
 }
 
-#[derive(Serialize)]
-struct GroupUserInfo {
-    id: i32,
-    username: String,
+struct PermissionTestRow {
+    label: String,
+    global: explain::Explanation,
+    /// Only when a forum is selected
+    forum: Option<explain::Explanation>,
 }
 
-/// GET /admin/permissions/hierarchy/group - Get group permission info (AJAX)
-#[get("/admin/permissions/hierarchy/group")]
-async fn get_group_permissions(
-    client: ClientCtx,
-    query: web::Query<std::collections::HashMap<String, String>>,
-) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+impl PermissionTestTemplate {
+    fn group_label(&self, group_id: i32) -> String {
+        self.groups
+            .iter()
+            .find(|g| g.id == group_id)
+            .map(|g| g.label.clone())
+            .unwrap_or_else(|| format!("Group #{}", group_id))
+    }
 
-    let group_id_str = query.get("group_id").map(|s| s.as_str()).unwrap_or("");
-    let group_id: i32 = group_id_str.parse().unwrap_or(0);
+    fn forum_label(&self, forum_id: i32) -> String {
+        self.forums
+            .iter()
+            .find(|f| f.id == forum_id)
+            .map(|f| f.label.clone())
+            .unwrap_or_else(|| format!("Forum #{}", forum_id))
+    }
 
-    if group_id == 0 {
-        return Ok(web::Json(serde_json::json!({"error": "Invalid group ID"})));
+    fn is_forum_selected(&self, forum_id: &i32) -> bool {
+        self.forum_id == Some(*forum_id)
+    }
+
+    fn flag_label(&self, flag: &Flag) -> &'static str {
+        match flag {
+            Flag::YES => "Yes",
+            Flag::NO => "No",
+            Flag::NEVER => "Never",
+            Flag::DEFAULT => "Default",
+        }
+    }
+
+    /// Who set a value, for display
+    fn contributor(&self, contribution: &explain::Contribution) -> String {
+        match contribution.group_id {
+            Some(group_id) => self.group_label(group_id),
+            None => "User override".to_string(),
+        }
     }
 
+    /// Where a check was decided, for display
+    fn decided_by(&self, explanation: &explain::Explanation) -> String {
+        match explanation.decided_by {
+            explain::Decider::Global => "Global".to_string(),
+            explain::Decider::Forum(forum_id) => {
+                format!("Override in {}", self.forum_label(forum_id))
+            }
+            explain::Decider::ForumModerator(forum_id) => {
+                format!("Moderator of {}", self.forum_label(forum_id))
+            }
+        }
+    }
+}
+
+/// GET /admin/permissions/test - Effective permissions for any member in any groups
+#[get("/admin/permissions/test")]
+async fn view_permission_test(
+    client: ClientCtx,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.permissions.manage")?;
+
     let db = get_db_pool();
+    let username = query
+        .get("username")
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default();
+    let forum_id: Option<i32> = query.get("forum").and_then(|f| f.parse().ok());
 
-    // Get group info
-    let group = groups::Entity::find_by_id(group_id)
-        .one(db)
+    let all_groups = groups::Entity::find()
+        .order_by_asc(groups::Column::Label)
+        .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch group: {}", e);
+            log::error!("Failed to fetch groups: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    let group = match group {
-        Some(g) => g,
-        None => return Ok(web::Json(serde_json::json!({"error": "Group not found"}))),
-    };
-
-    // Count users in group
-    let user_count: i64 = user_groups::Entity::find()
-        .filter(user_groups::Column::GroupId.eq(group_id))
-        .count(db)
+    let all_forums = forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
+        .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to count users: {}", e);
+            log::error!("Failed to fetch forums: {}", e);
             error::ErrorInternalServerError("Database error")
-        })? as i64;
-
-    // Get first 20 users in group
-    use sea_orm::{DbBackend, FromQueryResult, Statement};
-
-    #[derive(Debug, FromQueryResult)]
-    struct UserRow {
-        id: i32,
-        username: Option<String>,
-    }
-
-    let users: Vec<UserRow> = UserRow::find_by_statement(Statement::from_sql_and_values(
-        DbBackend::Postgres,
-        r#"
-            SELECT ug.user_id as id, un.name as username
-            FROM user_groups ug
-            LEFT JOIN user_names un ON un.user_id = ug.user_id
-            WHERE ug.group_id = $1
-            ORDER BY un.name
-            LIMIT 20
-        "#,
-        [group_id.into()],
-    ))
-    .all(db)
-    .await
-    .map_err(|e| {
-        log::error!("Failed to fetch group users: {}", e);
-        error::ErrorInternalServerError("Database error")
-    })?;
-
-    let group_users: Vec<GroupUserInfo> = users
-        .into_iter()
-        .map(|u| GroupUserInfo {
-            id: u.id,
-            username: u.username.unwrap_or_else(|| format!("User #{}", u.id)),
-        })
-        .collect();
-
-    // Get group permissions
-    let (permissions, _) = compute_effective_permissions(db, &[group_id], None).await?;
-
-    Ok(web::Json(serde_json::json!(GroupPermissionInfo {
-        id: group.id,
-        label: group.label,
-        user_count,
-        users: group_users,
-        permissions,
-    })))
-}
-
-/// GET /admin/permissions/hierarchy/users/search - Search users for autocomplete
-#[get("/admin/permissions/hierarchy/users/search")]
-async fn search_users_autocomplete(
-    client: ClientCtx,
-    query: web::Query<std::collections::HashMap<String, String>>,
-) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
-
-    let q = query.get("q").map(|s| s.trim()).unwrap_or("");
+        })?;
 
-    if q.len() < 2 {
-        return Ok(web::Json(serde_json::json!({"users": []})));
-    }
+    // Test as the named member, or as a guest
+    let mut error = None;
+    let user = if username.is_empty() {
+        None
+    } else {
+        match crate::user::get_user_id_from_name(db, &username).await {
+            Some(user_id) => crate::user::Profile::get_by_id(db, user_id)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to fetch user: {}", e);
+                    error::ErrorInternalServerError("Database error")
+                })?,
+            None => {
+                error = Some(format!("No member is named {}; showing a guest.", username));
+                None
+            }
+        }
+    };
 
-    let db = get_db_pool();
+    // Their own groups, unless a combination was picked
+    let custom = query.contains_key("custom");
+    let tested_groups: Vec<i32> = if custom {
+        all_groups
+            .iter()
+            .filter(|g| query.contains_key(&format!("group_{}", g.id)))
+            .map(|g| g.id)
+            .collect()
+    } else {
+        crate::group::get_group_ids_for_client(db, &user).await
+    };
 
-    use sea_orm::{DbBackend, FromQueryResult, Statement};
+    let tested = ClientCtx::impersonate(
+        client.get_permissions().clone(),
+        user,
+        tested_groups.clone(),
+    );
 
-    #[derive(Debug, FromQueryResult, Serialize)]
-    struct UserSuggestion {
-        user_id: i32,
-        name: String,
-    }
+    let all_categories = permission_categories::Entity::find()
+        .order_by_asc(permission_categories::Column::Sort)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission categories: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
 
-    let users: Vec<UserSuggestion> =
-        UserSuggestion::find_by_statement(Statement::from_sql_and_values(
-            DbBackend::Postgres,
-            r#"
-            SELECT user_id, name
-            FROM user_names
-            WHERE LOWER(name) LIKE LOWER($1 || '%')
-            ORDER BY name
-            LIMIT 10
-        "#,
-            [q.into()],
-        ))
+    let all_permissions = permissions::Entity::find()
+        .order_by_asc(permissions::Column::Sort)
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to search users: {}", e);
+            log::error!("Failed to fetch permissions: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    Ok(web::Json(serde_json::json!({"users": users})))
+    let categories = {
+        let permission_data = client.get_permissions().read();
+        all_categories
+            .into_iter()
+            .map(|category| PermissionTestCategory {
+                label: category.label,
+                permissions: all_permissions
+                    .iter()
+                    .filter(|p| p.category_id == category.id)
+                    .filter_map(|p| {
+                        Some(PermissionTestRow {
+                            label: p.label.clone(),
+                            global: permission_data.explain(&tested, &p.label, None)?,
+                            forum: forum_id.and_then(|forum_id| {
+                                permission_data.explain(&tested, &p.label, Some(forum_id))
+                            }),
+                        })
+                    })
+                    .collect(),
+            })
+            .filter(|category| !category.permissions.is_empty())
+            .collect()
+    };
+
+    Ok(PermissionTestTemplate {
+        client,
+        groups: all_groups,
+        forums: build_forum_tree(&all_forums),
+        username,
+        tested_groups,
+        custom,
+        forum_id,
+        categories,
+        error,
+    }
+    .to_response())
 }
 
-/// JSON response for forum permission info
+/// JSON response for user permission hierarchy
 #[derive(Serialize)]
-struct ForumPermissionInfo {
-    id: i32,
-    label: String,
-    parent_label: Option<String>,
-    moderators: Vec<ForumModeratorInfo>,
-    groups: Vec<ForumGroupPermInfo>,
+struct UserPermissionHierarchy {
+    username: String,
+    user_id: i32,
+    groups: Vec<UserGroupInfo>,
+    forums: Vec<ForumModStatus>,
+    permissions: std::collections::HashMap<String, std::collections::HashMap<String, String>>,
+    permission_sources: std::collections::HashMap<String, String>,
 }
 
 #[derive(Serialize)]
-struct ForumModeratorInfo {
-    user_id: i32,
-    username: String,
-    source: String, // "direct", "inherited", or "global"
-    source_forum: Option<String>,
+struct UserGroupInfo {
+    id: i32,
+    label: String,
+    is_primary: bool,
 }
 
 #[derive(Serialize)]
-struct ForumGroupPermInfo {
+struct ForumModStatus {
     id: i32,
     label: String,
-    permissions: std::collections::HashMap<String, std::collections::HashMap<String, String>>,
+    depth: i32,
+    is_moderator: bool,
+    inherits_mod: bool,
 }
 
-/// GET /admin/permissions/hierarchy/forum - Get forum permission info (AJAX)
-#[get("/admin/permissions/hierarchy/forum")]
-async fn get_forum_permissions(
+/// GET /admin/permissions/hierarchy/user - Get user permission hierarchy (AJAX)
+#[get("/admin/permissions/hierarchy/user")]
+async fn get_user_permissions(
     client: ClientCtx,
     query: web::Query<std::collections::HashMap<String, String>>,
 ) -> Result<impl Responder, Error> {
     client.require_permission("admin.settings")?;
 
-    let forum_id_str = query.get("forum_id").map(|s| s.as_str()).unwrap_or("");
-    let forum_id: i32 = forum_id_str.parse().unwrap_or(0);
+    let username = query.get("username").map(|s| s.trim()).unwrap_or("");
 
-    if forum_id == 0 {
-        return Ok(web::Json(serde_json::json!({"error": "Invalid forum ID"})));
+    if username.is_empty() {
+        return Ok(web::Json(serde_json::json!({"error": "Username required"})));
     }
 
     let db = get_db_pool();
 
-    // Get forum info
-    let forum = forums::Entity::find_by_id(forum_id)
+    // Find user by username
+    let user_name = user_names::Entity::find()
+        .filter(user_names::Column::Name.eq(username))
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forum: {}", e);
+            log::error!("Failed to look up user: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    let forum = match forum {
-        Some(f) => f,
-        None => return Ok(web::Json(serde_json::json!({"error": "Forum not found"}))),
-    };
-
-    // Get parent forum label if exists
-    let parent_label = if let Some(parent_id) = forum.parent_id {
-        forums::Entity::find_by_id(parent_id)
-            .one(db)
-            .await
-            .ok()
-            .flatten()
-            .map(|p| p.label)
-    } else {
-        None
+    let user_name = match user_name {
+        Some(u) => u,
+        None => return Ok(web::Json(serde_json::json!({"error": "User not found"}))),
     };
 
-    // Build parent chain for inherited moderators
-    let mut parent_chain: Vec<(i32, String)> = Vec::new();
-    let mut current_parent_id = forum.parent_id;
-    while let Some(pid) = current_parent_id {
-        if let Some(parent) = forums::Entity::find_by_id(pid).one(db).await.ok().flatten() {
-            parent_chain.push((parent.id, parent.label.clone()));
-            current_parent_id = parent.parent_id;
-        } else {
-            break;
-        }
-    }
+    let user_id = user_name.user_id;
 
-    // Get direct moderators for this forum
-    let direct_mods = forum_moderators::Entity::find()
-        .filter(forum_moderators::Column::ForumId.eq(forum_id))
+    // Get user's groups
+    let user_group_rows = user_groups::Entity::find()
+        .filter(user_groups::Column::UserId.eq(user_id))
+        .find_also_related(groups::Entity)
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forum moderators: {}", e);
+            log::error!("Failed to fetch user groups: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    // Get inherited moderators from parent forums
-    let parent_forum_ids: Vec<i32> = parent_chain.iter().map(|(id, _)| *id).collect();
-    let inherited_mods = if !parent_forum_ids.is_empty() {
-        forum_moderators::Entity::find()
-            .filter(forum_moderators::Column::ForumId.is_in(parent_forum_ids.clone()))
-            .all(db)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to fetch inherited moderators: {}", e);
-                error::ErrorInternalServerError("Database error")
-            })?
-    } else {
-        Vec::new()
-    };
-
-    // Collect all moderator user IDs
-    let mut all_mod_user_ids: Vec<i32> = direct_mods.iter().map(|m| m.user_id).collect();
-    all_mod_user_ids.extend(inherited_mods.iter().map(|m| m.user_id));
-
-    // Deduplicate
-    all_mod_user_ids.sort();
-    all_mod_user_ids.dedup();
-
-    // Fetch usernames for all moderators
-    let mod_usernames: std::collections::HashMap<i32, String> = if !all_mod_user_ids.is_empty() {
-        user_names::Entity::find()
-            .filter(user_names::Column::UserId.is_in(all_mod_user_ids.clone()))
-            .all(db)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to fetch moderator usernames: {}", e);
-                error::ErrorInternalServerError("Database error")
-            })?
-            .into_iter()
-            .map(|un| (un.user_id, un.name))
-            .collect()
-    } else {
-        std::collections::HashMap::new()
-    };
+    let mut user_groups_info: Vec<UserGroupInfo> = user_group_rows
+        .into_iter()
+        .filter_map(|(_, group)| {
+            group.map(|g| UserGroupInfo {
+                id: g.id,
+                label: g.label,
+                is_primary: false,
+            })
+        })
+        .collect();
 
-    // Build moderator list
-    let mut moderators: Vec<ForumModeratorInfo> = Vec::new();
-    let mut seen_user_ids: std::collections::HashSet<i32> = std::collections::HashSet::new();
+    // Sort by label
+    user_groups_info.sort_by(|a, b| a.label.cmp(&b.label));
 
-    // Add direct moderators first
-    for m in &direct_mods {
-        if seen_user_ids.insert(m.user_id) {
-            moderators.push(ForumModeratorInfo {
-                user_id: m.user_id,
-                username: mod_usernames
-                    .get(&m.user_id)
-                    .cloned()
-                    .unwrap_or_else(|| format!("User #{}", m.user_id)),
-                source: "direct".to_string(),
-                source_forum: None,
-            });
-        }
+    // Mark first group as primary (if any)
+    if !user_groups_info.is_empty() {
+        user_groups_info[0].is_primary = true;
     }
 
-    // Add inherited moderators (in order from closest parent to furthest)
-    for (parent_id, parent_name) in &parent_chain {
-        for m in inherited_mods.iter().filter(|m| m.forum_id == *parent_id) {
-            if seen_user_ids.insert(m.user_id) {
-                moderators.push(ForumModeratorInfo {
-                    user_id: m.user_id,
-                    username: mod_usernames
-                        .get(&m.user_id)
-                        .cloned()
-                        .unwrap_or_else(|| format!("User #{}", m.user_id)),
-                    source: "inherited".to_string(),
-                    source_forum: Some(parent_name.clone()),
-                });
-            }
-        }
-    }
+    // Get all forums with hierarchy
+    let forums = forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forums: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
 
-    // Get global moderators (users in the Moderators group, id=3)
-    let global_mod_user_ids: Vec<i32> = user_groups::Entity::find()
-        .filter(user_groups::Column::GroupId.eq(3)) // Moderators group
+    // Get user's direct moderator assignments
+    let mod_assignments: std::collections::HashSet<i32> = forum_moderators::Entity::find()
+        .filter(forum_moderators::Column::UserId.eq(user_id))
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch global moderators: {}", e);
+            log::error!("Failed to fetch moderator status: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
         .into_iter()
-        .map(|ug| ug.user_id)
+        .map(|m| m.forum_id)
         .collect();
 
-    // Fetch usernames for global moderators not already fetched
-    let new_global_mod_ids: Vec<i32> = global_mod_user_ids
-        .iter()
-        .filter(|id| !mod_usernames.contains_key(id))
-        .cloned()
-        .collect();
+    // Build parent map for inheritance
+    let parent_map: std::collections::HashMap<i32, Option<i32>> =
+        forums.iter().map(|f| (f.id, f.parent_id)).collect();
 
-    let mut global_mod_usernames = mod_usernames;
-    if !new_global_mod_ids.is_empty() {
-        let additional_names: std::collections::HashMap<i32, String> = user_names::Entity::find()
-            .filter(user_names::Column::UserId.is_in(new_global_mod_ids))
-            .all(db)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to fetch global moderator usernames: {}", e);
-                error::ErrorInternalServerError("Database error")
-            })?
-            .into_iter()
-            .map(|un| (un.user_id, un.name))
-            .collect();
-        global_mod_usernames.extend(additional_names);
+    // Check if a forum inherits mod status from parent
+    fn inherits_mod(
+        forum_id: i32,
+        direct_mods: &std::collections::HashSet<i32>,
+        parent_map: &std::collections::HashMap<i32, Option<i32>>,
+    ) -> bool {
+        let mut current = parent_map.get(&forum_id).copied().flatten();
+        while let Some(parent_id) = current {
+            if direct_mods.contains(&parent_id) {
+                return true;
+            }
+            current = parent_map.get(&parent_id).copied().flatten();
+        }
+        false
     }
 
-    // Add global moderators
-    for user_id in global_mod_user_ids {
-        if seen_user_ids.insert(user_id) {
-            moderators.push(ForumModeratorInfo {
-                user_id,
-                username: global_mod_usernames
-                    .get(&user_id)
-                    .cloned()
-                    .unwrap_or_else(|| format!("User #{}", user_id)),
-                source: "global".to_string(),
-                source_forum: None,
-            });
+    // Build forum tree with depths
+    fn get_depth(forum_id: i32, parent_map: &std::collections::HashMap<i32, Option<i32>>) -> i32 {
+        let mut depth = 0;
+        let mut current = parent_map.get(&forum_id).copied().flatten();
+        while current.is_some() {
+            depth += 1;
+            current = parent_map.get(&current.unwrap()).copied().flatten();
         }
+        depth
     }
 
-    // Get all groups
-    let all_groups = groups::Entity::find()
-        .order_by_asc(groups::Column::Label)
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch groups: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+    let forum_status: Vec<ForumModStatus> = forums
+        .iter()
+        .map(|f| {
+            let is_mod = mod_assignments.contains(&f.id);
+            let inherits = !is_mod && inherits_mod(f.id, &mod_assignments, &parent_map);
+            ForumModStatus {
+                id: f.id,
+                label: f.label.clone(),
+                depth: get_depth(f.id, &parent_map),
+                is_moderator: is_mod,
+                inherits_mod: inherits,
+            }
+        })
+        .collect();
 
-    // Get all permissions with categories
-    let all_perms = permissions::Entity::find()
-        .find_also_related(permission_categories::Entity)
-        .all(db)
+    // Get effective permissions
+    let group_ids: Vec<i32> = user_groups_info.iter().map(|g| g.id).collect();
+    let (permissions, sources) =
+        compute_effective_permissions(db, &group_ids, Some(user_id)).await?;
+
+    Ok(web::Json(serde_json::json!(UserPermissionHierarchy {
+        username: user_name.name,
+        user_id,
+        groups: user_groups_info,
+        forums: forum_status,
+        permissions,
+        permission_sources: sources,
+    })))
+}
+
+/// JSON response for group permission info
+#[derive(Serialize)]
+struct GroupPermissionInfo {
+    id: i32,
+    label: String,
+    user_count: i64,
+    users: Vec<GroupUserInfo>,
+    permissions: std::collections::HashMap<String, std::collections::HashMap<String, String>>,
+}
+
+#[derive(Serialize)]
+struct GroupUserInfo {
+    id: i32,
+    username: String,
+}
+
+/// GET /admin/permissions/hierarchy/group - Get group permission info (AJAX)
+#[get("/admin/permissions/hierarchy/group")]
+async fn get_group_permissions(
+    client: ClientCtx,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let group_id_str = query.get("group_id").map(|s| s.as_str()).unwrap_or("");
+    let group_id: i32 = group_id_str.parse().unwrap_or(0);
+
+    if group_id == 0 {
+        return Ok(web::Json(serde_json::json!({"error": "Invalid group ID"})));
+    }
+
+    let db = get_db_pool();
+
+    // Get group info
+    let group = groups::Entity::find_by_id(group_id)
+        .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch permissions: {}", e);
+            log::error!("Failed to fetch group: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    // Get forum-specific permission collections
-    let forum_perm_links = forum_permissions::Entity::find()
-        .filter(forum_permissions::Column::ForumId.eq(forum_id))
-        .all(db)
+    let group = match group {
+        Some(g) => g,
+        None => return Ok(web::Json(serde_json::json!({"error": "Group not found"}))),
+    };
+
+    // Count users in group
+    let user_count: i64 = user_groups::Entity::find()
+        .filter(user_groups::Column::GroupId.eq(group_id))
+        .count(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forum permissions: {}", e);
+            log::error!("Failed to count users: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?;
+        })? as i64;
 
-    // Map collection_id -> forum_permission link for this forum
-    let forum_collection_ids: Vec<i32> =
-        forum_perm_links.iter().map(|fp| fp.collection_id).collect();
+    // Get first 20 users in group
+    use sea_orm::{DbBackend, FromQueryResult, Statement};
 
-    // Get all permission collections (both global and forum-specific)
-    let all_collections = permission_collections::Entity::find()
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch permission collections: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+    #[derive(Debug, FromQueryResult)]
+    struct UserRow {
+        id: i32,
+        username: Option<String>,
+    }
 
-    // Map group_id -> global collection_id
-    let global_collection_map: std::collections::HashMap<i32, i32> = all_collections
-        .iter()
-        .filter_map(|c| c.group_id.map(|gid| (gid, c.id)))
-        .collect();
+    let users: Vec<UserRow> = UserRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+            SELECT ug.user_id as id, un.name as username
+            FROM user_groups ug
+            LEFT JOIN user_names un ON un.user_id = ug.user_id
+            WHERE ug.group_id = $1
+            ORDER BY un.name
+            LIMIT 20
+        "#,
+        [group_id.into()],
+    ))
+    .all(db)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to fetch group users: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
 
-    // Map collection_id -> group_id (for forum collections)
-    let collection_to_group: std::collections::HashMap<i32, i32> = all_collections
-        .iter()
-        .filter_map(|c| c.group_id.map(|gid| (c.id, gid)))
+    let group_users: Vec<GroupUserInfo> = users
+        .into_iter()
+        .map(|u| GroupUserInfo {
+            id: u.id,
+            username: u.username.unwrap_or_else(|| format!("User #{}", u.id)),
+        })
         .collect();
 
-    // Collect all collection IDs we need
-    let mut all_collection_ids: Vec<i32> = global_collection_map.values().cloned().collect();
-    all_collection_ids.extend(forum_collection_ids.iter().cloned());
+    // Get group permissions
+    let (permissions, _) = compute_effective_permissions(db, &[group_id], None).await?;
 
-    // Get all permission values for these collections
-    let all_perm_values = permission_values::Entity::find()
-        .filter(permission_values::Column::CollectionId.is_in(all_collection_ids))
+    Ok(web::Json(serde_json::json!(GroupPermissionInfo {
+        id: group.id,
+        label: group.label,
+        user_count,
+        users: group_users,
+        permissions,
+    })))
+}
+
+/// GET /admin/permissions/hierarchy/users/search - Search users for autocomplete
+#[get("/admin/permissions/hierarchy/users/search")]
+async fn search_users_autocomplete(
+    client: ClientCtx,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let q = query.get("q").map(|s| s.trim()).unwrap_or("");
+
+    if q.len() < 2 {
+        return Ok(web::Json(serde_json::json!({"users": []})));
+    }
+
+    let db = get_db_pool();
+
+    use sea_orm::{DbBackend, FromQueryResult, Statement};
+
+    #[derive(Debug, FromQueryResult, Serialize)]
+    struct UserSuggestion {
+        user_id: i32,
+        name: String,
+    }
+
+    let users: Vec<UserSuggestion> =
+        UserSuggestion::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"
+            SELECT user_id, name
+            FROM user_names
+            WHERE LOWER(name) LIKE LOWER($1 || '%')
+            ORDER BY name
+            LIMIT 10
+        "#,
+            [q.into()],
+        ))
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch permission values: {}", e);
+            log::error!("Failed to search users: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    // Build value map: (collection_id, permission_id) -> value
-    let value_map: std::collections::HashMap<(i32, i32), crate::permission::Flag> = all_perm_values
-        .iter()
-        .map(|pv| ((pv.collection_id, pv.permission_id), pv.value))
-        .collect();
-
-    // Build forum collection map: group_id -> forum_collection_id
-    let forum_collection_map: std::collections::HashMap<i32, i32> = forum_collection_ids
-        .iter()
-        .filter_map(|cid| collection_to_group.get(cid).map(|gid| (*gid, *cid)))
-        .collect();
-
-    // Build result for each group
-    let mut group_perms: Vec<ForumGroupPermInfo> = Vec::new();
+    Ok(web::Json(serde_json::json!({"users": users})))
+}
 
-    for group in &all_groups {
-        let global_cid = global_collection_map.get(&group.id);
-        let forum_cid = forum_collection_map.get(&group.id);
+/// JSON response for forum permission info
+#[derive(Serialize)]
+struct ForumPermissionInfo {
+    id: i32,
+    label: String,
+    parent_label: Option<String>,
+    moderators: Vec<ForumModeratorInfo>,
+    groups: Vec<ForumGroupPermInfo>,
+}
 
-        let mut permissions: std::collections::HashMap<
-            String,
-            std::collections::HashMap<String, String>,
-        > = std::collections::HashMap::new();
+#[derive(Serialize)]
+struct ForumModeratorInfo {
+    user_id: i32,
+    username: String,
+    source: String, // "direct", "inherited", or "global"
+    source_forum: Option<String>,
+}
 
-        for (perm, category) in &all_perms {
-            let category_name = category
-                .as_ref()
-                .map(|c| c.label.clone())
-                .unwrap_or_else(|| "Other".to_string());
+#[derive(Serialize)]
+struct ForumGroupPermInfo {
+    id: i32,
+    label: String,
+    permissions: std::collections::HashMap<String, std::collections::HashMap<String, String>>,
+}
 
-            // Check forum-specific collection first, then fall back to global
-            let value = forum_cid
-                .and_then(|cid| value_map.get(&(*cid, perm.id)))
-                .and_then(|v| match v {
-                    crate::permission::Flag::DEFAULT => None, // Fall back to global
-                    crate::permission::Flag::YES => Some("yes"),
-                    crate::permission::Flag::NEVER => Some("never"),
-                    crate::permission::Flag::NO => Some("no"),
-                })
-                .or_else(|| {
-                    global_cid
-                        .and_then(|cid| value_map.get(&(*cid, perm.id)))
-                        .map(|v| match v {
-                            crate::permission::Flag::YES => "yes",
-                            crate::permission::Flag::NEVER => "never",
-                            _ => "no",
-                        })
-                })
-                .unwrap_or("no");
+/// GET /admin/permissions/hierarchy/forum - Get forum permission info (AJAX)
+#[get("/admin/permissions/hierarchy/forum")]
+async fn get_forum_permissions(
+    client: ClientCtx,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
 
-            permissions
-                .entry(category_name)
-                .or_default()
-                .insert(perm.label.clone(), value.to_string());
-        }
+    let forum_id_str = query.get("forum_id").map(|s| s.as_str()).unwrap_or("");
+    let forum_id: i32 = forum_id_str.parse().unwrap_or(0);
 
-        group_perms.push(ForumGroupPermInfo {
-            id: group.id,
-            label: group.label.clone(),
-            permissions,
-        });
+    if forum_id == 0 {
+        return Ok(web::Json(serde_json::json!({"error": "Invalid forum ID"})));
     }
 
-    Ok(web::Json(serde_json::json!(ForumPermissionInfo {
-        id: forum.id,
-        label: forum.label,
-        parent_label,
-        moderators,
-        groups: group_perms,
-    })))
-}
-
-/// Compute effective permissions for a set of groups and optional user
-async fn compute_effective_permissions(
-    db: &sea_orm::DatabaseConnection,
-    group_ids: &[i32],
-    user_id: Option<i32>,
-) -> Result<
-    (
-        std::collections::HashMap<String, std::collections::HashMap<String, String>>,
-        std::collections::HashMap<String, String>,
-    ),
-    Error,
-> {
-    use crate::permission::Flag;
-
-    // Get all permissions with categories
-    let all_perms = permissions::Entity::find()
-        .find_also_related(permission_categories::Entity)
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch permissions: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+    let db = get_db_pool();
 
-    // Get permission values for the groups
-    let collections = permission_collections::Entity::find()
-        .filter(
-            sea_orm::Condition::any()
-                .add(permission_collections::Column::GroupId.is_in(group_ids.to_vec()))
-                .add_option(user_id.map(|uid| permission_collections::Column::UserId.eq(uid))),
-        )
-        .all(db)
+    // Get forum info
+    let forum = forums::Entity::find_by_id(forum_id)
+        .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch permission collections: {}", e);
+            log::error!("Failed to fetch forum: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    let collection_ids: Vec<i32> = collections.iter().map(|c| c.id).collect();
-
-    // Map collection_id to group label for source tracking
-    let all_groups = groups::Entity::find().all(db).await.map_err(|e| {
-        log::error!("Failed to fetch groups: {}", e);
-        error::ErrorInternalServerError("Database error")
-    })?;
-
-    let group_labels: std::collections::HashMap<i32, String> =
-        all_groups.iter().map(|g| (g.id, g.label.clone())).collect();
+    let forum = match forum {
+        Some(f) => f,
+        None => return Ok(web::Json(serde_json::json!({"error": "Forum not found"}))),
+    };
 
-    let collection_sources: std::collections::HashMap<i32, String> = collections
-        .iter()
-        .map(|c| {
-            let source = if let Some(gid) = c.group_id {
-                group_labels
-                    .get(&gid)
-                    .cloned()
-                    .unwrap_or_else(|| "Unknown".to_string())
-            } else if c.user_id.is_some() {
-                "User-specific".to_string()
-            } else {
-                "Unknown".to_string()
-            };
-            (c.id, source)
-        })
-        .collect();
-
-    let perm_values = permission_values::Entity::find()
-        .filter(permission_values::Column::CollectionId.is_in(collection_ids))
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch permission values: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
-
-    // Build effective permission map
-    // Permission resolution: Never > Yes > No
-    let mut effective: std::collections::HashMap<i32, (Flag, i32)> =
-        std::collections::HashMap::new(); // perm_id -> (flag, collection_id)
-
-    for pv in perm_values {
-        let existing = effective.get(&pv.permission_id);
-        let should_update = match existing {
-            None => true,
-            Some((existing_flag, _)) => {
-                // Never overrides everything
-                if pv.value == Flag::NEVER {
-                    true
-                } else if *existing_flag == Flag::NEVER {
-                    false
-                } else if pv.value == Flag::YES {
-                    // Yes overrides No but not Never
-                    *existing_flag != Flag::YES
-                } else {
-                    false
-                }
-            }
-        };
-
-        if should_update {
-            effective.insert(pv.permission_id, (pv.value, pv.collection_id));
-        }
-    }
-
-    // Organize by category
-    let mut result: std::collections::HashMap<String, std::collections::HashMap<String, String>> =
-        std::collections::HashMap::new();
-    let mut sources: std::collections::HashMap<String, String> = std::collections::HashMap::new();
-
-    for (perm, category) in all_perms {
-        let category_label = category
-            .map(|c| c.label)
-            .unwrap_or_else(|| "Other".to_string());
-        let perm_label = perm.label.clone();
+    // Get parent forum label if exists
+    let parent_label = if let Some(parent_id) = forum.parent_id {
+        forums::Entity::find_by_id(parent_id)
+            .one(db)
+            .await
+            .ok()
+            .flatten()
+            .map(|p| p.label)
+    } else {
+        None
+    };
 
-        let (value_str, source) = if let Some((flag, coll_id)) = effective.get(&perm.id) {
-            let v = match flag {
-                Flag::YES => "yes",
-                Flag::NO => "no",
-                Flag::NEVER => "never",
-                _ => "no",
-            };
-            let src = collection_sources.get(coll_id).cloned().unwrap_or_default();
-            (v.to_string(), src)
+    // Build parent chain for inherited moderators
+    let mut parent_chain: Vec<(i32, String)> = Vec::new();
+    let mut current_parent_id = forum.parent_id;
+    while let Some(pid) = current_parent_id {
+        if let Some(parent) = forums::Entity::find_by_id(pid).one(db).await.ok().flatten() {
+            parent_chain.push((parent.id, parent.label.clone()));
+            current_parent_id = parent.parent_id;
         } else {
-            ("no".to_string(), String::new())
-        };
-
-        result
-            .entry(category_label)
-            .or_default()
-            .insert(perm_label.clone(), value_str);
-
-        if !source.is_empty() {
-            sources.insert(perm_label, source);
+            break;
         }
     }
 
-    Ok((result, sources))
-}
-
-/// Helper to load permission categories
-async fn load_permission_categories(
-    db: &DatabaseConnection,
-) -> Result<Vec<CategoryDisplay>, Error> {
-    load_permission_categories_with_values(db, None).await
-}
-
-/// Helper to load permission categories with current values for a collection
-async fn load_permission_categories_with_values(
-    db: &DatabaseConnection,
-    collection_id: Option<i32>,
-) -> Result<Vec<CategoryDisplay>, Error> {
-    // Get all categories
-    let categories = permission_categories::Entity::find()
-        .order_by_asc(permission_categories::Column::Sort)
+    // Get direct moderators for this forum
+    let direct_mods = forum_moderators::Entity::find()
+        .filter(forum_moderators::Column::ForumId.eq(forum_id))
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch permission categories: {}", e);
+            log::error!("Failed to fetch forum moderators: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    // Get all permissions
-    let all_permissions = permissions::Entity::find()
-        .order_by_asc(permissions::Column::Sort)
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch permissions: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+    // Get inherited moderators from parent forums
+    let parent_forum_ids: Vec<i32> = parent_chain.iter().map(|(id, _)| *id).collect();
+    let inherited_mods = if !parent_forum_ids.is_empty() {
+        forum_moderators::Entity::find()
+            .filter(forum_moderators::Column::ForumId.is_in(parent_forum_ids.clone()))
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch inherited moderators: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?
+    } else {
+        Vec::new()
+    };
 
-    // Get current values if collection_id provided
-    let current_values: std::collections::HashMap<i32, String> = if let Some(cid) = collection_id {
-        permission_values::Entity::find()
-            .filter(permission_values::Column::CollectionId.eq(cid))
+    // Collect all moderator user IDs
+    let mut all_mod_user_ids: Vec<i32> = direct_mods.iter().map(|m| m.user_id).collect();
+    all_mod_user_ids.extend(inherited_mods.iter().map(|m| m.user_id));
+
+    // Deduplicate
+    all_mod_user_ids.sort();
+    all_mod_user_ids.dedup();
+
+    // Fetch usernames for all moderators
+    let mod_usernames: std::collections::HashMap<i32, String> = if !all_mod_user_ids.is_empty() {
+        user_names::Entity::find()
+            .filter(user_names::Column::UserId.is_in(all_mod_user_ids.clone()))
             .all(db)
             .await
             .map_err(|e| {
-                log::error!("Failed to fetch permission values: {}", e);
+                log::error!("Failed to fetch moderator usernames: {}", e);
                 error::ErrorInternalServerError("Database error")
             })?
             .into_iter()
-            .map(|pv| {
-                let value_str = match pv.value {
-                    Flag::YES => "yes",
-                    Flag::NO => "no",
-                    Flag::NEVER => "never",
-                    Flag::DEFAULT => "default",
-                };
-                (pv.permission_id, value_str.to_string())
-            })
+            .map(|un| (un.user_id, un.name))
             .collect()
     } else {
         std::collections::HashMap::new()
     };
 
-    // Build category displays
-    let mut category_displays = Vec::new();
-    for cat in categories {
-        let perms: Vec<PermissionDisplay> = all_permissions
-            .iter()
-            .filter(|p| p.category_id == cat.id)
-            .map(|p| PermissionDisplay {
-                id: p.id,
-                label: p.label.clone(),
-                value: current_values
-                    .get(&p.id)
-                    .cloned()
-                    .unwrap_or_else(|| "default".to_string()),
-            })
-            .collect();
+    // Build moderator list
+    let mut moderators: Vec<ForumModeratorInfo> = Vec::new();
+    let mut seen_user_ids: std::collections::HashSet<i32> = std::collections::HashSet::new();
 
-        if !perms.is_empty() {
-            category_displays.push(CategoryDisplay {
-                id: cat.id,
-                label: cat.label,
-                permissions: perms,
+    // Add direct moderators first
+    for m in &direct_mods {
+        if seen_user_ids.insert(m.user_id) {
+            moderators.push(ForumModeratorInfo {
+                user_id: m.user_id,
+                username: mod_usernames
+                    .get(&m.user_id)
+                    .cloned()
+                    .unwrap_or_else(|| format!("User #{}", m.user_id)),
+                source: "direct".to_string(),
+                source_forum: None,
             });
         }
     }
 
-    Ok(category_displays)
-}
+    // Add inherited moderators (in order from closest parent to furthest)
+    for (parent_id, parent_name) in &parent_chain {
+        for m in inherited_mods.iter().filter(|m| m.forum_id == *parent_id) {
+            if seen_user_ids.insert(m.user_id) {
+                moderators.push(ForumModeratorInfo {
+                    user_id: m.user_id,
+                    username: mod_usernames
+                        .get(&m.user_id)
+                        .cloned()
+                        .unwrap_or_else(|| format!("User #{}", m.user_id)),
+                    source: "inherited".to_string(),
+                    source_forum: Some(parent_name.clone()),
+                });
+            }
+        }
+    }
 
-/// Helper to save group permissions
-async fn save_group_permissions(
-    db: &DatabaseConnection,
-    collection_id: i32,
-    permissions_map: &std::collections::HashMap<String, String>,
-) -> Result<(), Error> {
-    // Delete existing permission values for this collection
-    permission_values::Entity::delete_many()
-        .filter(permission_values::Column::CollectionId.eq(collection_id))
-        .exec(db)
+    // Get global moderators (users in the Moderators group, id=3)
+    let global_mod_user_ids: Vec<i32> = user_groups::Entity::find()
+        .filter(user_groups::Column::GroupId.eq(3)) // Moderators group
+        .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to delete old permission values: {}", e);
-            error::ErrorInternalServerError("Failed to update permissions")
-        })?;
+            log::error!("Failed to fetch global moderators: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .into_iter()
+        .map(|ug| ug.user_id)
+        .collect();
 
-    // Insert new permission values
-    for (perm_id_str, value_str) in permissions_map {
-        let perm_id: i32 = match perm_id_str.parse() {
-            Ok(id) => id,
-            Err(_) => continue,
-        };
-
-        let flag = match value_str.as_str() {
-            "yes" => Flag::YES,
-            "no" => Flag::NO,
-            "never" => Flag::NEVER,
-            _ => continue, // Skip "default" values - don't store them
-        };
+    // Fetch usernames for global moderators not already fetched
+    let new_global_mod_ids: Vec<i32> = global_mod_user_ids
+        .iter()
+        .filter(|id| !mod_usernames.contains_key(id))
+        .cloned()
+        .collect();
 
-        let pv = permission_values::ActiveModel {
-            permission_id: Set(perm_id),
-            collection_id: Set(collection_id),
-            value: Set(flag),
-        };
+    let mut global_mod_usernames = mod_usernames;
+    if !new_global_mod_ids.is_empty() {
+        let additional_names: std::collections::HashMap<i32, String> = user_names::Entity::find()
+            .filter(user_names::Column::UserId.is_in(new_global_mod_ids))
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch global moderator usernames: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?
+            .into_iter()
+            .map(|un| (un.user_id, un.name))
+            .collect();
+        global_mod_usernames.extend(additional_names);
+    }
 
-        let _ = pv.insert(db).await;
+    // Add global moderators
+    for user_id in global_mod_user_ids {
+        if seen_user_ids.insert(user_id) {
+            moderators.push(ForumModeratorInfo {
+                user_id,
+                username: global_mod_usernames
+                    .get(&user_id)
+                    .cloned()
+                    .unwrap_or_else(|| format!("User #{}", user_id)),
+                source: "global".to_string(),
+                source_forum: None,
+            });
+        }
     }
 
-    Ok(())
-}
+    // Get all groups
+    let all_groups = groups::Entity::find()
+        .order_by_asc(groups::Column::Label)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch groups: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
 
-// ============================================================================
-// Reaction Types Management
-// ============================================================================
+    // Get all permissions with categories
+    let all_perms = permissions::Entity::find()
+        .find_also_related(permission_categories::Entity)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permissions: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
 
-#[derive(Template)]
-#[template(path = "admin/reaction_types.html")]
-struct ReactionTypesTemplate {
-    client: ClientCtx,
-    reaction_types: Vec<(reaction_types::Model, Option<attachments::Model>)>,
-}
+    // Get forum-specific permission collections
+    let forum_perm_links = forum_permissions::Entity::find()
+        .filter(forum_permissions::Column::ForumId.eq(forum_id))
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum permissions: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
 
-#[derive(Template)]
-#[template(path = "admin/reaction_type_form.html")]
-struct ReactionTypeFormTemplate {
-    client: ClientCtx,
-    reaction_type: Option<reaction_types::Model>,
-    attachment: Option<attachments::Model>,
-    error: Option<String>,
-}
+    // Map collection_id -> forum_permission link for this forum
+    let forum_collection_ids: Vec<i32> =
+        forum_perm_links.iter().map(|fp| fp.collection_id).collect();
 
-/// GET /admin/reaction-types - List all reaction types
-#[get("/admin/reaction-types")]
-async fn view_reaction_types(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+    // Get all permission collections (both global and forum-specific)
+    let all_collections = permission_collections::Entity::find()
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission collections: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
 
-    let db = get_db_pool();
+    // Map group_id -> global collection_id
+    let global_collection_map: std::collections::HashMap<i32, i32> = all_collections
+        .iter()
+        .filter_map(|c| c.group_id.map(|gid| (gid, c.id)))
+        .collect();
 
-    let types = reaction_types::Entity::find()
-        .order_by_asc(reaction_types::Column::DisplayOrder)
-        .find_also_related(attachments::Entity)
+    // Map collection_id -> group_id (for forum collections)
+    let collection_to_group: std::collections::HashMap<i32, i32> = all_collections
+        .iter()
+        .filter_map(|c| c.group_id.map(|gid| (c.id, gid)))
+        .collect();
+
+    // Collect all collection IDs we need
+    let mut all_collection_ids: Vec<i32> = global_collection_map.values().cloned().collect();
+    all_collection_ids.extend(forum_collection_ids.iter().cloned());
+
+    // Get all permission values for these collections
+    let all_perm_values = permission_values::Entity::find()
+        .filter(permission_values::Column::CollectionId.is_in(all_collection_ids))
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch reaction types: {}", e);
+            log::error!("Failed to fetch permission values: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    Ok(ReactionTypesTemplate {
-        client,
-        reaction_types: types,
-    }
-    .to_response())
-}
+    // Build value map: (collection_id, permission_id) -> value
+    let value_map: std::collections::HashMap<(i32, i32), crate::permission::Flag> = all_perm_values
+        .iter()
+        .map(|pv| ((pv.collection_id, pv.permission_id), pv.value))
+        .collect();
 
-/// GET /admin/reaction-types/new - Show form to create new reaction type
-#[get("/admin/reaction-types/new")]
-async fn view_create_reaction_type_form(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+    // Build forum collection map: group_id -> forum_collection_id
+    let forum_collection_map: std::collections::HashMap<i32, i32> = forum_collection_ids
+        .iter()
+        .filter_map(|cid| collection_to_group.get(cid).map(|gid| (*gid, *cid)))
+        .collect();
 
-    Ok(ReactionTypeFormTemplate {
-        client,
-        reaction_type: None,
-        attachment: None,
-        error: None,
-    }
-    .to_response())
-}
+    // Build result for each group
+    let mut group_perms: Vec<ForumGroupPermInfo> = Vec::new();
 
-/// POST /admin/reaction-types - Create a new reaction type
-#[post("/admin/reaction-types")]
-async fn create_reaction_type(
-    client: ClientCtx,
-    cookies: actix_session::Session,
-    mut multipart: actix_multipart::Multipart,
-) -> Result<impl Responder, Error> {
-    use crate::filesystem::{
-        deduplicate_payload, insert_payload_as_attachment, save_field_as_temp_file,
-    };
-    use futures::{StreamExt, TryStreamExt};
+    for group in &all_groups {
+        let global_cid = global_collection_map.get(&group.id);
+        let forum_cid = forum_collection_map.get(&group.id);
 
-    client.require_login()?;
-    client.require_permission("admin.settings")?;
+        let mut permissions: std::collections::HashMap<
+            String,
+            std::collections::HashMap<String, String>,
+        > = std::collections::HashMap::new();
 
-    let db = get_db_pool();
+        for (perm, category) in &all_perms {
+            let category_name = category
+                .as_ref()
+                .map(|c| c.label.clone())
+                .unwrap_or_else(|| "Other".to_string());
 
-    // Parse multipart form
-    let mut csrf_token: Option<String> = None;
-    let mut name: Option<String> = None;
-    let mut emoji: Option<String> = None;
-    let mut display_order: i32 = 0;
-    let mut is_positive = false;
-    let mut is_active = false;
-    let mut reputation_value: i32 = 0;
-    let mut attachment_id: Option<i32> = None;
+            // Check forum-specific collection first, then fall back to global
+            let value = forum_cid
+                .and_then(|cid| value_map.get(&(*cid, perm.id)))
+                .and_then(|v| match v {
+                    crate::permission::Flag::DEFAULT => None, // Fall back to global
+                    crate::permission::Flag::YES => Some("yes"),
+                    crate::permission::Flag::NEVER => Some("never"),
+                    crate::permission::Flag::NO => Some("no"),
+                })
+                .or_else(|| {
+                    global_cid
+                        .and_then(|cid| value_map.get(&(*cid, perm.id)))
+                        .map(|v| match v {
+                            crate::permission::Flag::YES => "yes",
+                            crate::permission::Flag::NEVER => "never",
+                            _ => "no",
+                        })
+                })
+                .unwrap_or("no");
 
-    while let Ok(Some(mut field)) = multipart.try_next().await {
-        let field_name = field
-            .content_disposition()
-            .get_name()
-            .unwrap_or("")
-            .to_string();
+            permissions
+                .entry(category_name)
+                .or_default()
+                .insert(perm.label.clone(), value.to_string());
+        }
 
-        match field_name.as_str() {
-            "csrf_token" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                csrf_token = Some(String::from_utf8_lossy(&buf).to_string());
-            }
-            "name" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                name = Some(String::from_utf8_lossy(&buf).to_string());
-            }
-            "emoji" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                emoji = Some(String::from_utf8_lossy(&buf).to_string());
-            }
-            "display_order" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                display_order = String::from_utf8_lossy(&buf).parse().unwrap_or(0);
-            }
-            "reputation_value" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                reputation_value = String::from_utf8_lossy(&buf).parse().unwrap_or(0);
-            }
-            "is_positive" => {
-                is_positive = true;
-            }
-            "is_active" => {
-                is_active = true;
-            }
-            "image" => {
-                // Handle file upload
-                if let Some(payload) = save_field_as_temp_file(&mut field).await? {
-                    // Check if it's an image
-                    if !payload.is_image() {
-                        return Ok(ReactionTypeFormTemplate {
-                            client,
-                            reaction_type: None,
-                            attachment: None,
-                            error: Some("Only image files are allowed".to_string()),
-                        }
-                        .to_response());
-                    }
-
-                    let response = match deduplicate_payload(&payload).await {
-                        Some(response) => response,
-                        None => match insert_payload_as_attachment(payload, None).await? {
-                            Some(response) => response,
-                            None => {
-                                return Ok(ReactionTypeFormTemplate {
-                                    client,
-                                    reaction_type: None,
-                                    attachment: None,
-                                    error: Some("Failed to process image".to_string()),
-                                }
-                                .to_response());
-                            }
-                        },
-                    };
-                    attachment_id = Some(response.id);
-                }
-            }
-            _ => {}
-        }
-    }
-
-    // Validate CSRF
-    let token = csrf_token.ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
-    crate::middleware::csrf::validate_csrf_token(&cookies, &token)?;
-
-    // Validate input
-    let name = name.unwrap_or_default();
-    if name.trim().is_empty() {
-        return Ok(ReactionTypeFormTemplate {
-            client,
-            reaction_type: None,
-            attachment: None,
-            error: Some("Name is required".to_string()),
-        }
-        .to_response());
+        group_perms.push(ForumGroupPermInfo {
+            id: group.id,
+            label: group.label.clone(),
+            permissions,
+        });
     }
 
-    let emoji = emoji.unwrap_or_default();
-
-    let new_reaction_type = reaction_types::ActiveModel {
-        name: Set(name.trim().to_string()),
-        emoji: Set(emoji.trim().to_string()),
-        display_order: Set(display_order),
-        is_positive: Set(is_positive),
-        is_active: Set(is_active),
-        reputation_value: Set(reputation_value),
-        attachment_id: Set(attachment_id),
-        ..Default::default()
-    };
-
-    new_reaction_type.insert(db).await.map_err(|e| {
-        log::error!("Failed to create reaction type: {}", e);
-        error::ErrorInternalServerError("Failed to create reaction type")
-    })?;
-
-    Ok(HttpResponse::SeeOther()
-        .insert_header(("Location", "/admin/reaction-types"))
-        .finish())
+    Ok(web::Json(serde_json::json!(ForumPermissionInfo {
+        id: forum.id,
+        label: forum.label,
+        parent_label,
+        moderators,
+        groups: group_perms,
+    })))
 }
 
-/// GET /admin/reaction-types/{id}/edit - Show form to edit reaction type
-#[get("/admin/reaction-types/{id}/edit")]
-async fn view_edit_reaction_type(
-    client: ClientCtx,
-    path: web::Path<i32>,
-) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
-
-    let id = path.into_inner();
-    let db = get_db_pool();
+/// Compute effective permissions for a set of groups and optional user
+async fn compute_effective_permissions(
+    db: &sea_orm::DatabaseConnection,
+    group_ids: &[i32],
+    user_id: Option<i32>,
+) -> Result<
+    (
+        std::collections::HashMap<String, std::collections::HashMap<String, String>>,
+        std::collections::HashMap<String, String>,
+    ),
+    Error,
+> {
+    use crate::permission::Flag;
 
-    let reaction_type = reaction_types::Entity::find_by_id(id)
-        .one(db)
+    // Get all permissions with categories
+    let all_perms = permissions::Entity::find()
+        .find_also_related(permission_categories::Entity)
+        .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch reaction type: {}", e);
+            log::error!("Failed to fetch permissions: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| error::ErrorNotFound("Reaction type not found"))?;
+        })?;
 
-    // Load attachment if exists
-    let attachment = if let Some(att_id) = reaction_type.attachment_id {
-        attachments::Entity::find_by_id(att_id)
-            .one(db)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to fetch attachment: {}", e);
-                error::ErrorInternalServerError("Database error")
-            })?
-    } else {
-        None
-    };
+    // Get permission values for the groups
+    let collections = permission_collections::Entity::find()
+        .filter(
+            sea_orm::Condition::any()
+                .add(permission_collections::Column::GroupId.is_in(group_ids.to_vec()))
+                .add_option(user_id.map(|uid| permission_collections::Column::UserId.eq(uid))),
+        )
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission collections: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
 
-    Ok(ReactionTypeFormTemplate {
-        client,
-        reaction_type: Some(reaction_type),
-        attachment,
-        error: None,
-    }
-    .to_response())
-}
+    let collection_ids: Vec<i32> = collections.iter().map(|c| c.id).collect();
 
-/// POST /admin/reaction-types/{id} - Update a reaction type
-#[post("/admin/reaction-types/{id}")]
-async fn update_reaction_type(
-    client: ClientCtx,
-    cookies: actix_session::Session,
-    path: web::Path<i32>,
-    mut multipart: actix_multipart::Multipart,
-) -> Result<impl Responder, Error> {
-    use crate::filesystem::{
-        deduplicate_payload, insert_payload_as_attachment, save_field_as_temp_file,
-    };
-    use futures::{StreamExt, TryStreamExt};
+    // Map collection_id to group label for source tracking
+    let all_groups = groups::Entity::find().all(db).await.map_err(|e| {
+        log::error!("Failed to fetch groups: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
 
-    client.require_login()?;
-    client.require_permission("admin.settings")?;
+    let group_labels: std::collections::HashMap<i32, String> =
+        all_groups.iter().map(|g| (g.id, g.label.clone())).collect();
 
-    let id = path.into_inner();
-    let db = get_db_pool();
+    let collection_sources: std::collections::HashMap<i32, String> = collections
+        .iter()
+        .map(|c| {
+            let source = if let Some(gid) = c.group_id {
+                group_labels
+                    .get(&gid)
+                    .cloned()
+                    .unwrap_or_else(|| "Unknown".to_string())
+            } else if c.user_id.is_some() {
+                "User-specific".to_string()
+            } else {
+                "Unknown".to_string()
+            };
+            (c.id, source)
+        })
+        .collect();
 
-    // Fetch existing reaction type
-    let existing = reaction_types::Entity::find_by_id(id)
-        .one(db)
+    let perm_values = permission_values::Entity::find()
+        .filter(permission_values::Column::CollectionId.is_in(collection_ids))
+        .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch reaction type: {}", e);
+            log::error!("Failed to fetch permission values: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| error::ErrorNotFound("Reaction type not found"))?;
-
-    // Parse multipart form
-    let mut csrf_token: Option<String> = None;
-    let mut name: Option<String> = None;
-    let mut emoji: Option<String> = None;
-    let mut display_order: i32 = existing.display_order;
-    let mut is_positive = false;
-    let mut is_active = false;
-    let mut reputation_value: i32 = existing.reputation_value;
-    let mut new_attachment_id: Option<i32> = None;
-    let mut remove_image = false;
+        })?;
 
-    while let Ok(Some(mut field)) = multipart.try_next().await {
-        let field_name = field
-            .content_disposition()
-            .get_name()
-            .unwrap_or("")
-            .to_string();
+    // Build effective permission map
+    // Permission resolution: Never > Yes > No
+    let mut effective: std::collections::HashMap<i32, (Flag, i32)> =
+        std::collections::HashMap::new(); // perm_id -> (flag, collection_id)
 
-        match field_name.as_str() {
-            "csrf_token" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                csrf_token = Some(String::from_utf8_lossy(&buf).to_string());
-            }
-            "name" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                name = Some(String::from_utf8_lossy(&buf).to_string());
-            }
-            "emoji" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                emoji = Some(String::from_utf8_lossy(&buf).to_string());
-            }
-            "display_order" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                display_order = String::from_utf8_lossy(&buf)
-                    .parse()
-                    .unwrap_or(existing.display_order);
-            }
-            "reputation_value" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
+    for pv in perm_values {
+        let existing = effective.get(&pv.permission_id);
+        let should_update = match existing {
+            None => true,
+            Some((existing_flag, _)) => {
+                // Never overrides everything
+                if pv.value == Flag::NEVER {
+                    true
+                } else if *existing_flag == Flag::NEVER {
+                    false
+                } else if pv.value == Flag::YES {
+                    // Yes overrides No but not Never
+                    *existing_flag != Flag::YES
+                } else {
+                    false
                 }
-                reputation_value = String::from_utf8_lossy(&buf)
-                    .parse()
-                    .unwrap_or(existing.reputation_value);
-            }
-            "is_positive" => {
-                is_positive = true;
             }
-            "is_active" => {
-                is_active = true;
-            }
-            "remove_image" => {
-                remove_image = true;
-            }
-            "image" => {
-                // Handle file upload
-                if let Some(payload) = save_field_as_temp_file(&mut field).await? {
-                    // Check if it's an image
-                    if !payload.is_image() {
-                        // Load attachment for error display
-                        let attachment = if let Some(att_id) = existing.attachment_id {
-                            attachments::Entity::find_by_id(att_id)
-                                .one(db)
-                                .await
-                                .ok()
-                                .flatten()
-                        } else {
-                            None
-                        };
-                        return Ok(ReactionTypeFormTemplate {
-                            client,
-                            reaction_type: Some(existing),
-                            attachment,
-                            error: Some("Only image files are allowed".to_string()),
-                        }
-                        .to_response());
-                    }
+        };
 
-                    let response = match deduplicate_payload(&payload).await {
-                        Some(response) => response,
-                        None => match insert_payload_as_attachment(payload, None).await? {
-                            Some(response) => response,
-                            None => {
-                                let attachment = if let Some(att_id) = existing.attachment_id {
-                                    attachments::Entity::find_by_id(att_id)
-                                        .one(db)
-                                        .await
-                                        .ok()
-                                        .flatten()
-                                } else {
-                                    None
-                                };
-                                return Ok(ReactionTypeFormTemplate {
-                                    client,
-                                    reaction_type: Some(existing),
-                                    attachment,
-                                    error: Some("Failed to process image".to_string()),
-                                }
-                                .to_response());
-                            }
-                        },
-                    };
-                    new_attachment_id = Some(response.id);
-                }
-            }
-            _ => {}
+        if should_update {
+            effective.insert(pv.permission_id, (pv.value, pv.collection_id));
         }
     }
 
-    // Validate CSRF
-    let token = csrf_token.ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
-    crate::middleware::csrf::validate_csrf_token(&cookies, &token)?;
+    // Organize by category
+    let mut result: std::collections::HashMap<String, std::collections::HashMap<String, String>> =
+        std::collections::HashMap::new();
+    let mut sources: std::collections::HashMap<String, String> = std::collections::HashMap::new();
 
-    // Validate input
-    let name = name.unwrap_or_default();
-    if name.trim().is_empty() {
-        let attachment = if let Some(att_id) = existing.attachment_id {
-            attachments::Entity::find_by_id(att_id)
-                .one(db)
-                .await
-                .ok()
-                .flatten()
+    for (perm, category) in all_perms {
+        let category_label = category
+            .map(|c| c.label)
+            .unwrap_or_else(|| "Other".to_string());
+        let perm_label = perm.label.clone();
+
+        let (value_str, source) = if let Some((flag, coll_id)) = effective.get(&perm.id) {
+            let v = match flag {
+                Flag::YES => "yes",
+                Flag::NO => "no",
+                Flag::NEVER => "never",
+                _ => "no",
+            };
+            let src = collection_sources.get(coll_id).cloned().unwrap_or_default();
+            (v.to_string(), src)
         } else {
-            None
+            ("no".to_string(), String::new())
         };
-        return Ok(ReactionTypeFormTemplate {
-            client,
-            reaction_type: Some(existing),
-            attachment,
-            error: Some("Name is required".to_string()),
-        }
-        .to_response());
-    }
-
-    let emoji = emoji.unwrap_or_default();
-
-    // Determine final attachment_id
-    let final_attachment_id = if remove_image {
-        None
-    } else if new_attachment_id.is_some() {
-        new_attachment_id
-    } else {
-        existing.attachment_id
-    };
-
-    let mut updated: reaction_types::ActiveModel = existing.into();
-    updated.name = Set(name.trim().to_string());
-    updated.emoji = Set(emoji.trim().to_string());
-    updated.display_order = Set(display_order);
-    updated.is_positive = Set(is_positive);
-    updated.is_active = Set(is_active);
-    updated.reputation_value = Set(reputation_value);
-    updated.attachment_id = Set(final_attachment_id);
 
-    updated.update(db).await.map_err(|e| {
-        log::error!("Failed to update reaction type: {}", e);
-        error::ErrorInternalServerError("Failed to update reaction type")
-    })?;
-
-    Ok(HttpResponse::SeeOther()
-        .insert_header(("Location", "/admin/reaction-types"))
-        .finish())
-}
+        result
+            .entry(category_label)
+            .or_default()
+            .insert(perm_label.clone(), value_str);
 
-// ============================================================================
-// Badge Management
-// ============================================================================
+        if !source.is_empty() {
+            sources.insert(perm_label, source);
+        }
+    }
 
-#[derive(Template)]
-#[template(path = "admin/badges.html")]
-struct BadgesTemplate {
-    client: ClientCtx,
-    badges: Vec<badges::Model>,
+    Ok((result, sources))
 }
 
-#[derive(Template)]
-#[template(path = "admin/badge_form.html")]
-struct BadgeFormTemplate {
-    client: ClientCtx,
-    badge: Option<badges::Model>,
-    error: Option<String>,
+/// Helper to load permission categories
+async fn load_permission_categories(
+    db: &DatabaseConnection,
+) -> Result<Vec<CategoryDisplay>, Error> {
+    load_permission_categories_with_values(db, None).await
 }
 
-#[derive(Template)]
-#[template(path = "admin/badge_award.html")]
-struct BadgeAwardTemplate {
-    client: ClientCtx,
-    badge: badges::Model,
-    current_holders: Vec<BadgeHolder>,
-    error: Option<String>,
-    success: Option<String>,
-}
+/// Helper to load permission categories with current values for a collection
+async fn load_permission_categories_with_values(
+    db: &DatabaseConnection,
+    collection_id: Option<i32>,
+) -> Result<Vec<CategoryDisplay>, Error> {
+    // Get all categories
+    let categories = permission_categories::Entity::find()
+        .order_by_asc(permission_categories::Column::Sort)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission categories: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
 
-#[derive(Debug)]
-struct BadgeHolder {
-    user_id: i32,
-    username: String,
-    awarded_at: chrono::DateTime<chrono::Utc>,
-}
+    // Get all permissions
+    let all_permissions = permissions::Entity::find()
+        .order_by_asc(permissions::Column::Sort)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permissions: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
 
-#[derive(Deserialize)]
-struct BadgeForm {
-    csrf_token: String,
-    name: String,
-    slug: String,
-    description: Option<String>,
-    icon: String,
-    color: Option<String>,
-    condition_type: String,
-    condition_value: Option<i32>,
-    display_order: i32,
-    is_active: Option<String>,
-}
-
-#[derive(Deserialize)]
-struct AwardBadgeForm {
-    csrf_token: String,
-    username: String,
-}
+    // Get current values if collection_id provided
+    let current_values: std::collections::HashMap<i32, String> = if let Some(cid) = collection_id {
+        permission_values::Entity::find()
+            .filter(permission_values::Column::CollectionId.eq(cid))
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch permission values: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?
+            .into_iter()
+            .map(|pv| {
+                let value_str = match pv.value {
+                    Flag::YES => "yes",
+                    Flag::NO => "no",
+                    Flag::NEVER => "never",
+                    Flag::DEFAULT => "default",
+                };
+                (pv.permission_id, value_str.to_string())
+            })
+            .collect()
+    } else {
+        std::collections::HashMap::new()
+    };
 
-#[derive(Deserialize)]
-struct RevokeBadgeForm {
-    csrf_token: String,
-    user_id: i32,
-}
+    // Build category displays
+    let mut category_displays = Vec::new();
+    for cat in categories {
+        let perms: Vec<PermissionDisplay> = all_permissions
+            .iter()
+            .filter(|p| p.category_id == cat.id)
+            .map(|p| PermissionDisplay {
+                id: p.id,
+                label: p.label.clone(),
+                value: current_values
+                    .get(&p.id)
+                    .cloned()
+                    .unwrap_or_else(|| "default".to_string()),
+            })
+            .collect();
 
-/// GET /admin/badges - List all badges
-#[get("/admin/badges")]
-async fn view_badges(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.badges.manage")?;
+        if !perms.is_empty() {
+            category_displays.push(CategoryDisplay {
+                id: cat.id,
+                label: cat.label,
+                permissions: perms,
+            });
+        }
+    }
 
-    let db = get_db_pool();
+    Ok(category_displays)
+}
 
-    let all_badges = badges::Entity::find()
-        .order_by_asc(badges::Column::DisplayOrder)
-        .all(db)
+/// Helper to save group permissions
+async fn save_group_permissions(
+    db: &DatabaseConnection,
+    collection_id: i32,
+    permissions_map: &std::collections::HashMap<String, String>,
+) -> Result<(), Error> {
+    // Delete existing permission values for this collection
+    permission_values::Entity::delete_many()
+        .filter(permission_values::Column::CollectionId.eq(collection_id))
+        .exec(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch badges: {}", e);
-            error::ErrorInternalServerError("Database error")
+            log::error!("Failed to delete old permission values: {}", e);
+            error::ErrorInternalServerError("Failed to update permissions")
         })?;
 
-    Ok(BadgesTemplate {
-        client,
-        badges: all_badges,
-    }
-    .to_response())
-}
-
-/// GET /admin/badges/new - Show form to create new badge
-#[get("/admin/badges/new")]
-async fn view_create_badge_form(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.badges.manage")?;
-
-    Ok(BadgeFormTemplate {
-        client,
-        badge: None,
-        error: None,
-    }
-    .to_response())
-}
-
-/// POST /admin/badges - Create a new badge
-#[post("/admin/badges")]
-async fn create_badge(
-    client: ClientCtx,
-    cookies: actix_session::Session,
-    form: web::Form<BadgeForm>,
-) -> Result<impl Responder, Error> {
-    client.require_login()?;
-    client.require_permission("admin.badges.manage")?;
-
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+    // Insert new permission values
+    for (perm_id_str, value_str) in permissions_map {
+        let perm_id: i32 = match perm_id_str.parse() {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
 
-    let db = get_db_pool();
+        let flag = match value_str.as_str() {
+            "yes" => Flag::YES,
+            "no" => Flag::NO,
+            "never" => Flag::NEVER,
+            _ => continue, // Skip "default" values - don't store them
+        };
 
-    // Validate input
-    if form.name.trim().is_empty() {
-        return Ok(BadgeFormTemplate {
-            client,
-            badge: None,
-            error: Some("Name is required".to_string()),
-        }
-        .to_response());
-    }
+        let pv = permission_values::ActiveModel {
+            permission_id: Set(perm_id),
+            collection_id: Set(collection_id),
+            value: Set(flag),
+        };
 
-    if form.slug.trim().is_empty() {
-        return Ok(BadgeFormTemplate {
-            client,
-            badge: None,
-            error: Some("Slug is required".to_string()),
-        }
-        .to_response());
+        let _ = pv.insert(db).await;
     }
 
-    // Parse condition type
-    let condition_type = match form.condition_type.as_str() {
-        "manual" => badges::BadgeConditionType::Manual,
-        "post_count" => badges::BadgeConditionType::PostCount,
-        "thread_count" => badges::BadgeConditionType::ThreadCount,
-        "time_member" => badges::BadgeConditionType::TimeMember,
-        "reputation" => badges::BadgeConditionType::Reputation,
-        _ => badges::BadgeConditionType::Manual,
-    };
+    Ok(())
+}
 
-    let new_badge = badges::ActiveModel {
-        name: Set(form.name.trim().to_string()),
-        slug: Set(form.slug.trim().to_lowercase().replace(' ', "-")),
-        description: Set(form.description.clone().filter(|s| !s.trim().is_empty())),
-        icon: Set(form.icon.trim().to_string()),
-        color: Set(form.color.clone().filter(|s| !s.trim().is_empty())),
-        condition_type: Set(condition_type),
-        condition_value: Set(form.condition_value),
-        display_order: Set(form.display_order),
-        is_active: Set(form.is_active.is_some()),
-        ..Default::default()
-    };
+// ============================================================================
+// Reaction Types Management
+// ============================================================================
 
-    new_badge.insert(db).await.map_err(|e| {
-        log::error!("Failed to create badge: {}", e);
-        error::ErrorInternalServerError("Failed to create badge")
-    })?;
+#[derive(Template)]
+#[template(path = "admin/reaction_types.html")]
+struct ReactionTypesTemplate {
+    client: ClientCtx,
+    reaction_types: Vec<(reaction_types::Model, Option<attachments::Model>)>,
+}
 
-    Ok(HttpResponse::SeeOther()
-        .insert_header(("Location", "/admin/badges"))
-        .finish())
+#[derive(Template)]
+#[template(path = "admin/reaction_type_form.html")]
+struct ReactionTypeFormTemplate {
+    client: ClientCtx,
+    reaction_type: Option<reaction_types::Model>,
+    attachment: Option<attachments::Model>,
+    error: Option<String>,
 }
 
-/// GET /admin/badges/{id}/edit - Show form to edit badge
-#[get("/admin/badges/{id}/edit")]
-async fn view_edit_badge(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
-    client.require_permission("admin.badges.manage")?;
+/// GET /admin/reaction-types - List all reaction types
+#[get("/admin/reaction-types")]
+async fn view_reaction_types(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
 
-    let id = path.into_inner();
     let db = get_db_pool();
 
-    let badge = badges::Entity::find_by_id(id)
-        .one(db)
+    let types = reaction_types::Entity::find()
+        .order_by_asc(reaction_types::Column::DisplayOrder)
+        .find_also_related(attachments::Entity)
+        .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch badge: {}", e);
+            log::error!("Failed to fetch reaction types: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| error::ErrorNotFound("Badge not found"))?;
+        })?;
 
-    Ok(BadgeFormTemplate {
+    Ok(ReactionTypesTemplate {
         client,
-        badge: Some(badge),
+        reaction_types: types,
+    }
+    .to_response())
+}
+
+/// GET /admin/reaction-types/new - Show form to create new reaction type
+#[get("/admin/reaction-types/new")]
+async fn view_create_reaction_type_form(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    Ok(ReactionTypeFormTemplate {
+        client,
+        reaction_type: None,
+        attachment: None,
         error: None,
     }
     .to_response())
 }
 
-/// POST /admin/badges/{id} - Update a badge
-#[post("/admin/badges/{id}")]
-async fn update_badge(
+/// POST /admin/reaction-types - Create a new reaction type
+#[post("/admin/reaction-types")]
+async fn create_reaction_type(
     client: ClientCtx,
     cookies: actix_session::Session,
-    path: web::Path<i32>,
-    form: web::Form<BadgeForm>,
+    mut multipart: actix_multipart::Multipart,
 ) -> Result<impl Responder, Error> {
+    use crate::filesystem::{
+        deduplicate_payload, insert_payload_as_attachment, save_field_as_temp_file,
+    };
+    use futures::{StreamExt, TryStreamExt};
+
     client.require_login()?;
-    client.require_permission("admin.badges.manage")?;
+    client.require_permission("admin.settings")?;
 
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
-
-    let id = path.into_inner();
     let db = get_db_pool();
 
-    // Fetch existing badge
-    let existing = badges::Entity::find_by_id(id)
-        .one(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch badge: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| error::ErrorNotFound("Badge not found"))?;
+    // Parse multipart form
+    let mut csrf_token: Option<String> = None;
+    let mut name: Option<String> = None;
+    let mut emoji: Option<String> = None;
+    let mut display_order: i32 = 0;
+    let mut is_positive = false;
+    let mut is_active = false;
+    let mut reputation_value: i32 = 0;
+    let mut attachment_id: Option<i32> = None;
+
+    while let Ok(Some(mut field)) = multipart.try_next().await {
+        let field_name = field
+            .content_disposition()
+            .get_name()
+            .unwrap_or("")
+            .to_string();
+
+        match field_name.as_str() {
+            "csrf_token" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                csrf_token = Some(String::from_utf8_lossy(&buf).to_string());
+            }
+            "name" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                name = Some(String::from_utf8_lossy(&buf).to_string());
+            }
+            "emoji" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                emoji = Some(String::from_utf8_lossy(&buf).to_string());
+            }
+            "display_order" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                display_order = String::from_utf8_lossy(&buf).parse().unwrap_or(0);
+            }
+            "reputation_value" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                reputation_value = String::from_utf8_lossy(&buf).parse().unwrap_or(0);
+            }
+            "is_positive" => {
+                is_positive = true;
+            }
+            "is_active" => {
+                is_active = true;
+            }
+            "image" => {
+                // Handle file upload
+                if let Some(payload) =
+                    save_field_as_temp_file(&mut field, client.preserve_image_orientation()).await?
+                {
+                    // Check if it's an image
+                    if !payload.is_image() {
+                        return Ok(ReactionTypeFormTemplate {
+                            client,
+                            reaction_type: None,
+                            attachment: None,
+                            error: Some("Only image files are allowed".to_string()),
+                        }
+                        .to_response());
+                    }
+
+                    let response = match deduplicate_payload(&payload).await {
+                        Some(response) => response,
+                        None => match insert_payload_as_attachment(payload, None).await? {
+                            Some(response) => response,
+                            None => {
+                                return Ok(ReactionTypeFormTemplate {
+                                    client,
+                                    reaction_type: None,
+                                    attachment: None,
+                                    error: Some("Failed to process image".to_string()),
+                                }
+                                .to_response());
+                            }
+                        },
+                    };
+                    attachment_id = Some(response.id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Validate CSRF
+    let token = csrf_token.ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &token)?;
 
     // Validate input
-    if form.name.trim().is_empty() {
-        return Ok(BadgeFormTemplate {
+    let name = name.unwrap_or_default();
+    if name.trim().is_empty() {
+        return Ok(ReactionTypeFormTemplate {
             client,
-            badge: Some(existing),
+            reaction_type: None,
+            attachment: None,
             error: Some("Name is required".to_string()),
         }
         .to_response());
     }
 
-    // Parse condition type
-    let condition_type = match form.condition_type.as_str() {
-        "manual" => badges::BadgeConditionType::Manual,
-        "post_count" => badges::BadgeConditionType::PostCount,
-        "thread_count" => badges::BadgeConditionType::ThreadCount,
-        "time_member" => badges::BadgeConditionType::TimeMember,
-        "reputation" => badges::BadgeConditionType::Reputation,
-        _ => badges::BadgeConditionType::Manual,
-    };
+    let emoji = emoji.unwrap_or_default();
 
-    let mut updated: badges::ActiveModel = existing.into();
-    updated.name = Set(form.name.trim().to_string());
-    updated.slug = Set(form.slug.trim().to_lowercase().replace(' ', "-"));
-    updated.description = Set(form.description.clone().filter(|s| !s.trim().is_empty()));
-    updated.icon = Set(form.icon.trim().to_string());
-    updated.color = Set(form.color.clone().filter(|s| !s.trim().is_empty()));
-    updated.condition_type = Set(condition_type);
-    updated.condition_value = Set(form.condition_value);
-    updated.display_order = Set(form.display_order);
-    updated.is_active = Set(form.is_active.is_some());
+    let new_reaction_type = reaction_types::ActiveModel {
+        name: Set(name.trim().to_string()),
+        emoji: Set(emoji.trim().to_string()),
+        display_order: Set(display_order),
+        is_positive: Set(is_positive),
+        is_active: Set(is_active),
+        reputation_value: Set(reputation_value),
+        attachment_id: Set(attachment_id),
+        ..Default::default()
+    };
 
-    updated.update(db).await.map_err(|e| {
-        log::error!("Failed to update badge: {}", e);
-        error::ErrorInternalServerError("Failed to update badge")
+    new_reaction_type.insert(db).await.map_err(|e| {
+        log::error!("Failed to create reaction type: {}", e);
+        error::ErrorInternalServerError("Failed to create reaction type")
     })?;
 
     Ok(HttpResponse::SeeOther()
-        .insert_header(("Location", "/admin/badges"))
+        .insert_header(("Location", "/admin/reaction-types"))
         .finish())
 }
 
-/// GET /admin/badges/{id}/award - Show form to award badge to users
-#[get("/admin/badges/{id}/award")]
-async fn view_award_badge_form(
+/// GET /admin/reaction-types/{id}/edit - Show form to edit reaction type
+#[get("/admin/reaction-types/{id}/edit")]
+async fn view_edit_reaction_type(
     client: ClientCtx,
     path: web::Path<i32>,
 ) -> Result<impl Responder, Error> {
-    client.require_permission("admin.badges.manage")?;
+    client.require_permission("admin.settings")?;
 
     let id = path.into_inner();
     let db = get_db_pool();
 
-    let badge = badges::Entity::find_by_id(id)
+    let reaction_type = reaction_types::Entity::find_by_id(id)
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch badge: {}", e);
+            log::error!("Failed to fetch reaction type: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Badge not found"))?;
+        .ok_or_else(|| error::ErrorNotFound("Reaction type not found"))?;
 
-    // Get current badge holders
-    let holders = get_badge_holders(db, id).await.map_err(|e| {
-        log::error!("Failed to fetch badge holders: {}", e);
-        error::ErrorInternalServerError("Database error")
-    })?;
+    // Load attachment if exists
+    let attachment = if let Some(att_id) = reaction_type.attachment_id {
+        attachments::Entity::find_by_id(att_id)
+            .one(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch attachment: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?
+    } else {
+        None
+    };
 
-    Ok(BadgeAwardTemplate {
+    Ok(ReactionTypeFormTemplate {
         client,
-        badge,
-        current_holders: holders,
+        reaction_type: Some(reaction_type),
+        attachment,
         error: None,
-        success: None,
     }
     .to_response())
 }
 
-async fn get_badge_holders(
-    db: &DatabaseConnection,
-    badge_id: i32,
-) -> Result<Vec<BadgeHolder>, sea_orm::DbErr> {
-    use sea_orm::FromQueryResult;
-
-    #[derive(Debug, FromQueryResult)]
-    struct HolderRow {
-        user_id: i32,
-        username: String,
-        awarded_at: chrono::DateTime<chrono::Utc>,
-    }
-
-    let rows = HolderRow::find_by_statement(sea_orm::Statement::from_sql_and_values(
-        sea_orm::DbBackend::Postgres,
-        r#"
-        SELECT ub.user_id, un.name as username, ub.awarded_at
-        FROM user_badges ub
-        JOIN user_names un ON un.user_id = ub.user_id
-        WHERE ub.badge_id = $1
-        ORDER BY ub.awarded_at DESC
-        "#,
-        vec![badge_id.into()],
-    ))
-    .all(db)
-    .await?;
-
-    Ok(rows
-        .into_iter()
-        .map(|r| BadgeHolder {
-            user_id: r.user_id,
-            username: r.username,
-            awarded_at: r.awarded_at,
-        })
-        .collect())
-}
-
-/// POST /admin/badges/{id}/award - Award badge to a user
-#[post("/admin/badges/{id}/award")]
-async fn award_badge_to_user(
+/// POST /admin/reaction-types/{id} - Update a reaction type
+#[post("/admin/reaction-types/{id}")]
+async fn update_reaction_type(
     client: ClientCtx,
     cookies: actix_session::Session,
     path: web::Path<i32>,
-    form: web::Form<AwardBadgeForm>,
+    mut multipart: actix_multipart::Multipart,
 ) -> Result<impl Responder, Error> {
-    client.require_login()?;
-    client.require_permission("admin.badges.manage")?;
+    use crate::filesystem::{
+        deduplicate_payload, insert_payload_as_attachment, save_field_as_temp_file,
+    };
+    use futures::{StreamExt, TryStreamExt};
 
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+    client.require_login()?;
+    client.require_permission("admin.settings")?;
 
-    let badge_id = path.into_inner();
+    let id = path.into_inner();
     let db = get_db_pool();
 
-    // Fetch badge
-    let badge = badges::Entity::find_by_id(badge_id)
+    // Fetch existing reaction type
+    let existing = reaction_types::Entity::find_by_id(id)
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch badge: {}", e);
+            log::error!("Failed to fetch reaction type: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Badge not found"))?;
+        .ok_or_else(|| error::ErrorNotFound("Reaction type not found"))?;
 
-    // Look up user by username
-    let user_id = crate::user::get_user_id_from_name(db, &form.username).await;
+    // Parse multipart form
+    let mut csrf_token: Option<String> = None;
+    let mut name: Option<String> = None;
+    let mut emoji: Option<String> = None;
+    let mut display_order: i32 = existing.display_order;
+    let mut is_positive = false;
+    let mut is_active = false;
+    let mut reputation_value: i32 = existing.reputation_value;
+    let mut new_attachment_id: Option<i32> = None;
+    let mut remove_image = false;
 
-    let holders = get_badge_holders(db, badge_id).await.map_err(|e| {
-        log::error!("Failed to fetch badge holders: {}", e);
-        error::ErrorInternalServerError("Database error")
-    })?;
+    while let Ok(Some(mut field)) = multipart.try_next().await {
+        let field_name = field
+            .content_disposition()
+            .get_name()
+            .unwrap_or("")
+            .to_string();
 
-    let user_id = match user_id {
-        Some(id) => id,
-        None => {
-            return Ok(BadgeAwardTemplate {
-                client,
-                badge,
-                current_holders: holders,
-                error: Some(format!("User '{}' not found", form.username)),
-                success: None,
+        match field_name.as_str() {
+            "csrf_token" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                csrf_token = Some(String::from_utf8_lossy(&buf).to_string());
             }
-            .to_response());
-        }
-    };
-
-    // Award the badge
-    let awarded_by = client.get_id();
-    match crate::badges::award_badge(db, user_id, badge_id, awarded_by).await {
-        Ok(true) => {
-            // Refresh holders list
-            let holders = get_badge_holders(db, badge_id).await.map_err(|e| {
-                log::error!("Failed to fetch badge holders: {}", e);
-                error::ErrorInternalServerError("Database error")
-            })?;
+            "name" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                name = Some(String::from_utf8_lossy(&buf).to_string());
+            }
+            "emoji" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                emoji = Some(String::from_utf8_lossy(&buf).to_string());
+            }
+            "display_order" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                display_order = String::from_utf8_lossy(&buf)
+                    .parse()
+                    .unwrap_or(existing.display_order);
+            }
+            "reputation_value" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                reputation_value = String::from_utf8_lossy(&buf)
+                    .parse()
+                    .unwrap_or(existing.reputation_value);
+            }
+            "is_positive" => {
+                is_positive = true;
+            }
+            "is_active" => {
+                is_active = true;
+            }
+            "remove_image" => {
+                remove_image = true;
+            }
+            "image" => {
+                // Handle file upload
+                if let Some(payload) =
+                    save_field_as_temp_file(&mut field, client.preserve_image_orientation()).await?
+                {
+                    // Check if it's an image
+                    if !payload.is_image() {
+                        // Load attachment for error display
+                        let attachment = if let Some(att_id) = existing.attachment_id {
+                            attachments::Entity::find_by_id(att_id)
+                                .one(db)
+                                .await
+                                .ok()
+                                .flatten()
+                        } else {
+                            None
+                        };
+                        return Ok(ReactionTypeFormTemplate {
+                            client,
+                            reaction_type: Some(existing),
+                            attachment,
+                            error: Some("Only image files are allowed".to_string()),
+                        }
+                        .to_response());
+                    }
 
-            Ok(BadgeAwardTemplate {
-                client,
-                badge,
-                current_holders: holders,
-                error: None,
-                success: Some(format!("Badge awarded to {}", form.username)),
+                    let response = match deduplicate_payload(&payload).await {
+                        Some(response) => response,
+                        None => match insert_payload_as_attachment(payload, None).await? {
+                            Some(response) => response,
+                            None => {
+                                let attachment = if let Some(att_id) = existing.attachment_id {
+                                    attachments::Entity::find_by_id(att_id)
+                                        .one(db)
+                                        .await
+                                        .ok()
+                                        .flatten()
+                                } else {
+                                    None
+                                };
+                                return Ok(ReactionTypeFormTemplate {
+                                    client,
+                                    reaction_type: Some(existing),
+                                    attachment,
+                                    error: Some("Failed to process image".to_string()),
+                                }
+                                .to_response());
+                            }
+                        },
+                    };
+                    new_attachment_id = Some(response.id);
+                }
             }
-            .to_response())
+            _ => {}
         }
-        Ok(false) => Ok(BadgeAwardTemplate {
+    }
+
+    // Validate CSRF
+    let token = csrf_token.ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &token)?;
+
+    // Validate input
+    let name = name.unwrap_or_default();
+    if name.trim().is_empty() {
+        let attachment = if let Some(att_id) = existing.attachment_id {
+            attachments::Entity::find_by_id(att_id)
+                .one(db)
+                .await
+                .ok()
+                .flatten()
+        } else {
+            None
+        };
+        return Ok(ReactionTypeFormTemplate {
             client,
-            badge,
-            current_holders: holders,
-            error: Some(format!("User '{}' already has this badge", form.username)),
-            success: None,
-        }
-        .to_response()),
-        Err(e) => {
-            log::error!("Failed to award badge: {}", e);
-            Ok(BadgeAwardTemplate {
-                client,
-                badge,
-                current_holders: holders,
-                error: Some("Failed to award badge".to_string()),
-                success: None,
-            }
-            .to_response())
+            reaction_type: Some(existing),
+            attachment,
+            error: Some("Name is required".to_string()),
         }
+        .to_response());
     }
-}
 
-/// POST /admin/badges/{id}/revoke - Revoke badge from a user
-#[post("/admin/badges/{id}/revoke")]
-async fn revoke_badge_from_user(
-    client: ClientCtx,
-    cookies: actix_session::Session,
-    path: web::Path<i32>,
-    form: web::Form<RevokeBadgeForm>,
-) -> Result<impl Responder, Error> {
-    client.require_login()?;
-    client.require_permission("admin.badges.manage")?;
+    let emoji = emoji.unwrap_or_default();
 
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+    // Determine final attachment_id
+    let final_attachment_id = if remove_image {
+        None
+    } else if new_attachment_id.is_some() {
+        new_attachment_id
+    } else {
+        existing.attachment_id
+    };
 
-    let badge_id = path.into_inner();
-    let db = get_db_pool();
+    let mut updated: reaction_types::ActiveModel = existing.into();
+    updated.name = Set(name.trim().to_string());
+    updated.emoji = Set(emoji.trim().to_string());
+    updated.display_order = Set(display_order);
+    updated.is_positive = Set(is_positive);
+    updated.is_active = Set(is_active);
+    updated.reputation_value = Set(reputation_value);
+    updated.attachment_id = Set(final_attachment_id);
 
-    // Revoke the badge
-    crate::badges::revoke_badge(db, form.user_id, badge_id)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to revoke badge: {}", e);
-            error::ErrorInternalServerError("Failed to revoke badge")
-        })?;
+    updated.update(db).await.map_err(|e| {
+        log::error!("Failed to update reaction type: {}", e);
+        error::ErrorInternalServerError("Failed to update reaction type")
+    })?;
 
     Ok(HttpResponse::SeeOther()
-        .insert_header(("Location", format!("/admin/badges/{}/award", badge_id)))
+        .insert_header(("Location", "/admin/reaction-types"))
         .finish())
 }
 
 // ============================================================================
-// Forum Management
+// Badge Management
 // ============================================================================
 
 #[derive(Template)]
-#[template(path = "admin/forums.html")]
-struct ForumsAdminTemplate {
+#[template(path = "admin/badges.html")]
+struct BadgesTemplate {
     client: ClientCtx,
-    forums: Vec<forums::Model>,
+    badges: Vec<badges::Model>,
 }
 
 #[derive(Template)]
-#[template(path = "admin/forum_form.html")]
-struct ForumFormTemplate {
+#[template(path = "admin/badge_form.html")]
+struct BadgeFormTemplate {
     client: ClientCtx,
-    forum: forums::Model,
-    all_forums: Vec<forums::Model>,
-    selected_parent_id: i32,
-    icon_attachment: Option<attachments::Model>,
-    icon_new_attachment: Option<attachments::Model>,
+    badge: Option<badges::Model>,
     error: Option<String>,
 }
 
-/// GET /admin/forums - List all forums
-#[get("/admin/forums")]
-async fn view_forums_admin(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+#[derive(Template)]
+#[template(path = "admin/badge_award.html")]
+struct BadgeAwardTemplate {
+    client: ClientCtx,
+    badge: badges::Model,
+    current_holders: Vec<BadgeHolder>,
+    error: Option<String>,
+    success: Option<String>,
+}
+
+#[derive(Debug)]
+struct BadgeHolder {
+    user_id: i32,
+    username: String,
+    awarded_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Deserialize)]
+struct BadgeForm {
+    csrf_token: String,
+    name: String,
+    slug: String,
+    description: Option<String>,
+    icon: String,
+    color: Option<String>,
+    condition_type: String,
+    condition_value: Option<i32>,
+    display_order: i32,
+    is_active: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AwardBadgeForm {
+    csrf_token: String,
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct RevokeBadgeForm {
+    csrf_token: String,
+    user_id: i32,
+}
+
+/// GET /admin/badges - List all badges
+#[get("/admin/badges")]
+async fn view_badges(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.badges.manage")?;
 
     let db = get_db_pool();
 
-    let forums_list = forums::Entity::find()
-        .order_by_asc(forums::Column::DisplayOrder)
+    let all_badges = badges::Entity::find()
+        .order_by_asc(badges::Column::DisplayOrder)
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forums: {}", e);
+            log::error!("Failed to fetch badges: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    Ok(ForumsAdminTemplate {
+    Ok(BadgesTemplate {
         client,
-        forums: forums_list,
+        badges: all_badges,
     }
     .to_response())
 }
 
-/// GET /admin/forums/{id}/edit - Show form to edit forum
-#[get("/admin/forums/{id}/edit")]
-async fn view_edit_forum(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+/// GET /admin/badges/new - Show form to create new badge
+#[get("/admin/badges/new")]
+async fn view_create_badge_form(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.badges.manage")?;
+
+    Ok(BadgeFormTemplate {
+        client,
+        badge: None,
+        error: None,
+    }
+    .to_response())
+}
+
+/// POST /admin/badges - Create a new badge
+#[post("/admin/badges")]
+async fn create_badge(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<BadgeForm>,
+) -> Result<impl Responder, Error> {
+    client.require_login()?;
+    client.require_permission("admin.badges.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+
+    // Validate input
+    if form.name.trim().is_empty() {
+        return Ok(BadgeFormTemplate {
+            client,
+            badge: None,
+            error: Some("Name is required".to_string()),
+        }
+        .to_response());
+    }
+
+    if form.slug.trim().is_empty() {
+        return Ok(BadgeFormTemplate {
+            client,
+            badge: None,
+            error: Some("Slug is required".to_string()),
+        }
+        .to_response());
+    }
+
+    // Parse condition type
+    let condition_type = match form.condition_type.as_str() {
+        "manual" => badges::BadgeConditionType::Manual,
+        "post_count" => badges::BadgeConditionType::PostCount,
+        "thread_count" => badges::BadgeConditionType::ThreadCount,
+        "time_member" => badges::BadgeConditionType::TimeMember,
+        "reputation" => badges::BadgeConditionType::Reputation,
+        _ => badges::BadgeConditionType::Manual,
+    };
+
+    let new_badge = badges::ActiveModel {
+        name: Set(form.name.trim().to_string()),
+        slug: Set(form.slug.trim().to_lowercase().replace(' ', "-")),
+        description: Set(form.description.clone().filter(|s| !s.trim().is_empty())),
+        icon: Set(form.icon.trim().to_string()),
+        color: Set(form.color.clone().filter(|s| !s.trim().is_empty())),
+        condition_type: Set(condition_type),
+        condition_value: Set(form.condition_value),
+        display_order: Set(form.display_order),
+        is_active: Set(form.is_active.is_some()),
+        ..Default::default()
+    };
+
+    new_badge.insert(db).await.map_err(|e| {
+        log::error!("Failed to create badge: {}", e);
+        error::ErrorInternalServerError("Failed to create badge")
+    })?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header(("Location", "/admin/badges"))
+        .finish())
+}
+
+/// GET /admin/badges/{id}/edit - Show form to edit badge
+#[get("/admin/badges/{id}/edit")]
+async fn view_edit_badge(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
+    client.require_permission("admin.badges.manage")?;
 
     let id = path.into_inner();
     let db = get_db_pool();
 
-    let forum = forums::Entity::find_by_id(id)
+    let badge = badges::Entity::find_by_id(id)
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forum: {}", e);
+            log::error!("Failed to fetch badge: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
-
-    let all_forums = forums::Entity::find()
-        .order_by_asc(forums::Column::DisplayOrder)
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch forums: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
-
-    // Load attachments for icon images
-    let icon_attachment = if let Some(att_id) = forum.icon_attachment_id {
-        attachments::Entity::find_by_id(att_id)
-            .one(db)
-            .await
-            .ok()
-            .flatten()
-    } else {
-        None
-    };
-
-    let icon_new_attachment = if let Some(att_id) = forum.icon_new_attachment_id {
-        attachments::Entity::find_by_id(att_id)
-            .one(db)
-            .await
-            .ok()
-            .flatten()
-    } else {
-        None
-    };
+        .ok_or_else(|| error::ErrorNotFound("Badge not found"))?;
 
-    let selected_parent_id = forum.parent_id.unwrap_or(0);
-    Ok(ForumFormTemplate {
+    Ok(BadgeFormTemplate {
         client,
-        forum,
-        all_forums,
-        selected_parent_id,
-        icon_attachment,
-        icon_new_attachment,
+        badge: Some(badge),
         error: None,
     }
     .to_response())
 }
 
-/// POST /admin/forums/{id} - Update a forum
-#[post("/admin/forums/{id}")]
-async fn update_forum(
+/// POST /admin/badges/{id} - Update a badge
+#[post("/admin/badges/{id}")]
+async fn update_badge(
     client: ClientCtx,
     cookies: actix_session::Session,
     path: web::Path<i32>,
-    mut multipart: actix_multipart::Multipart,
+    form: web::Form<BadgeForm>,
 ) -> Result<impl Responder, Error> {
-    use crate::filesystem::{
-        deduplicate_payload, insert_payload_as_attachment, save_field_as_temp_file,
-    };
-    use futures::{StreamExt, TryStreamExt};
-
     client.require_login()?;
-    client.require_permission("admin.settings")?;
+    client.require_permission("admin.badges.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
 
     let id = path.into_inner();
     let db = get_db_pool();
 
-    // Fetch existing forum
-    let existing = forums::Entity::find_by_id(id)
+    // Fetch existing badge
+    let existing = badges::Entity::find_by_id(id)
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forum: {}", e);
+            log::error!("Failed to fetch badge: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+        .ok_or_else(|| error::ErrorNotFound("Badge not found"))?;
 
-    // Store selected_parent_id before any moves
-    let selected_parent_id = existing.parent_id.unwrap_or(0);
+    // Validate input
+    if form.name.trim().is_empty() {
+        return Ok(BadgeFormTemplate {
+            client,
+            badge: Some(existing),
+            error: Some("Name is required".to_string()),
+        }
+        .to_response());
+    }
 
-    // Parse multipart form
-    let mut csrf_token: Option<String> = None;
-    let mut label: Option<String> = None;
-    let mut description: Option<String> = None;
-    let mut icon = existing.icon.clone();
-    let mut icon_new = existing.icon_new.clone();
-    let mut display_order: i32 = existing.display_order;
-    let mut parent_id: Option<i32> = existing.parent_id;
-    let mut new_icon_attachment_id: Option<i32> = None;
-    let mut new_icon_new_attachment_id: Option<i32> = None;
-    let mut remove_icon_image = false;
-    let mut remove_icon_new_image = false;
-    let mut tags_enabled = false;
-    let mut restrict_tags = false;
-    let mut thread_template: Option<String> = existing.thread_template.clone();
+    // Parse condition type
+    let condition_type = match form.condition_type.as_str() {
+        "manual" => badges::BadgeConditionType::Manual,
+        "post_count" => badges::BadgeConditionType::PostCount,
+        "thread_count" => badges::BadgeConditionType::ThreadCount,
+        "time_member" => badges::BadgeConditionType::TimeMember,
+        "reputation" => badges::BadgeConditionType::Reputation,
+        _ => badges::BadgeConditionType::Manual,
+    };
 
-    // Helper to load attachments for error display
-    async fn load_attachments(
-        forum: &forums::Model,
-        db: &DatabaseConnection,
-    ) -> (Option<attachments::Model>, Option<attachments::Model>) {
-        let icon_att = if let Some(att_id) = forum.icon_attachment_id {
-            attachments::Entity::find_by_id(att_id)
-                .one(db)
-                .await
-                .ok()
-                .flatten()
-        } else {
-            None
-        };
-        let icon_new_att = if let Some(att_id) = forum.icon_new_attachment_id {
-            attachments::Entity::find_by_id(att_id)
-                .one(db)
-                .await
-                .ok()
-                .flatten()
-        } else {
-            None
-        };
-        (icon_att, icon_new_att)
+    let mut updated: badges::ActiveModel = existing.into();
+    updated.name = Set(form.name.trim().to_string());
+    updated.slug = Set(form.slug.trim().to_lowercase().replace(' ', "-"));
+    updated.description = Set(form.description.clone().filter(|s| !s.trim().is_empty()));
+    updated.icon = Set(form.icon.trim().to_string());
+    updated.color = Set(form.color.clone().filter(|s| !s.trim().is_empty()));
+    updated.condition_type = Set(condition_type);
+    updated.condition_value = Set(form.condition_value);
+    updated.display_order = Set(form.display_order);
+    updated.is_active = Set(form.is_active.is_some());
+
+    updated.update(db).await.map_err(|e| {
+        log::error!("Failed to update badge: {}", e);
+        error::ErrorInternalServerError("Failed to update badge")
+    })?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header(("Location", "/admin/badges"))
+        .finish())
+}
+
+/// GET /admin/badges/{id}/award - Show form to award badge to users
+#[get("/admin/badges/{id}/award")]
+async fn view_award_badge_form(
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.badges.manage")?;
+
+    let id = path.into_inner();
+    let db = get_db_pool();
+
+    let badge = badges::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch badge: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Badge not found"))?;
+
+    // Get current badge holders
+    let holders = get_badge_holders(db, id).await.map_err(|e| {
+        log::error!("Failed to fetch badge holders: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    Ok(BadgeAwardTemplate {
+        client,
+        badge,
+        current_holders: holders,
+        error: None,
+        success: None,
     }
+    .to_response())
+}
 
-    while let Ok(Some(mut field)) = multipart.try_next().await {
-        let field_name = field
-            .content_disposition()
-            .get_name()
-            .unwrap_or("")
-            .to_string();
+async fn get_badge_holders(
+    db: &DatabaseConnection,
+    badge_id: i32,
+) -> Result<Vec<BadgeHolder>, sea_orm::DbErr> {
+    use sea_orm::FromQueryResult;
 
-        match field_name.as_str() {
-            "csrf_token" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                csrf_token = Some(String::from_utf8_lossy(&buf).to_string());
-            }
-            "label" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                label = Some(String::from_utf8_lossy(&buf).to_string());
+    #[derive(Debug, FromQueryResult)]
+    struct HolderRow {
+        user_id: i32,
+        username: String,
+        awarded_at: chrono::DateTime<chrono::Utc>,
+    }
+
+    let rows = HolderRow::find_by_statement(sea_orm::Statement::from_sql_and_values(
+        sea_orm::DbBackend::Postgres,
+        r#"
+        SELECT ub.user_id, un.name as username, ub.awarded_at
+        FROM user_badges ub
+        JOIN user_names un ON un.user_id = ub.user_id
+        WHERE ub.badge_id = $1
+        ORDER BY ub.awarded_at DESC
+        "#,
+        vec![badge_id.into()],
+    ))
+    .all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| BadgeHolder {
+            user_id: r.user_id,
+            username: r.username,
+            awarded_at: r.awarded_at,
+        })
+        .collect())
+}
+
+/// POST /admin/badges/{id}/award - Award badge to a user
+#[post("/admin/badges/{id}/award")]
+async fn award_badge_to_user(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<AwardBadgeForm>,
+) -> Result<impl Responder, Error> {
+    client.require_login()?;
+    client.require_permission("admin.badges.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let badge_id = path.into_inner();
+    let db = get_db_pool();
+
+    // Fetch badge
+    let badge = badges::Entity::find_by_id(badge_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch badge: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Badge not found"))?;
+
+    // Look up user by username
+    let user_id = crate::user::get_user_id_from_name(db, &form.username).await;
+
+    let holders = get_badge_holders(db, badge_id).await.map_err(|e| {
+        log::error!("Failed to fetch badge holders: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    let user_id = match user_id {
+        Some(id) => id,
+        None => {
+            return Ok(BadgeAwardTemplate {
+                client,
+                badge,
+                current_holders: holders,
+                error: Some(format!("User '{}' not found", form.username)),
+                success: None,
             }
-            "description" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                let desc = String::from_utf8_lossy(&buf).to_string();
-                description = if desc.trim().is_empty() {
-                    None
-                } else {
-                    Some(desc)
-                };
+            .to_response());
+        }
+    };
+
+    // Award the badge
+    let awarded_by = client.get_id();
+    match crate::badges::award_badge(db, user_id, badge_id, awarded_by).await {
+        Ok(true) => {
+            // Refresh holders list
+            let holders = get_badge_holders(db, badge_id).await.map_err(|e| {
+                log::error!("Failed to fetch badge holders: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?;
+
+            Ok(BadgeAwardTemplate {
+                client,
+                badge,
+                current_holders: holders,
+                error: None,
+                success: Some(format!("Badge awarded to {}", form.username)),
             }
-            "icon" => {
-                let mut buf = Vec::new();
-                while let Some(chunk) = field.next().await {
-                    buf.extend_from_slice(
-                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
-                    );
-                }
-                let val = String::from_utf8_lossy(&buf).to_string();
-                if !val.trim().is_empty() {
-                    icon = val.trim().to_string();
-                }
+            .to_response())
+        }
+        Ok(false) => Ok(BadgeAwardTemplate {
+            client,
+            badge,
+            current_holders: holders,
+            error: Some(format!("User '{}' already has this badge", form.username)),
+            success: None,
+        }
+        .to_response()),
+        Err(e) => {
+            log::error!("Failed to award badge: {}", e);
+            Ok(BadgeAwardTemplate {
+                client,
+                badge,
+                current_holders: holders,
+                error: Some("Failed to award badge".to_string()),
+                success: None,
             }
-            "icon_new" => {
+            .to_response())
+        }
+    }
+}
+
+/// POST /admin/badges/{id}/revoke - Revoke badge from a user
+#[post("/admin/badges/{id}/revoke")]
+async fn revoke_badge_from_user(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<RevokeBadgeForm>,
+) -> Result<impl Responder, Error> {
+    client.require_login()?;
+    client.require_permission("admin.badges.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let badge_id = path.into_inner();
+    let db = get_db_pool();
+
+    // Revoke the badge
+    crate::badges::revoke_badge(db, form.user_id, badge_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to revoke badge: {}", e);
+            error::ErrorInternalServerError("Failed to revoke badge")
+        })?;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header(("Location", format!("/admin/badges/{}/award", badge_id)))
+        .finish())
+}
+
+// ============================================================================
+// Forum Management
+// ============================================================================
+
+#[derive(Template)]
+#[template(path = "admin/forums.html")]
+struct ForumsAdminTemplate {
+    client: ClientCtx,
+    forums: Vec<forums::Model>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/forum_form.html")]
+struct ForumFormTemplate {
+    client: ClientCtx,
+    forum: forums::Model,
+    all_forums: Vec<forums::Model>,
+    selected_parent_id: i32,
+    icon_attachment: Option<attachments::Model>,
+    icon_new_attachment: Option<attachments::Model>,
+    error: Option<String>,
+}
+
+/// GET /admin/forums - List all forums
+#[get("/admin/forums")]
+async fn view_forums_admin(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+
+    let forums_list = forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forums: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(ForumsAdminTemplate {
+        client,
+        forums: forums_list,
+    }
+    .to_response())
+}
+
+/// GET /admin/forums/{id}/edit - Show form to edit forum
+#[get("/admin/forums/{id}/edit")]
+async fn view_edit_forum(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let id = path.into_inner();
+    let db = get_db_pool();
+
+    let forum = forums::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+
+    let all_forums = forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forums: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    // Load attachments for icon images
+    let icon_attachment = if let Some(att_id) = forum.icon_attachment_id {
+        attachments::Entity::find_by_id(att_id)
+            .one(db)
+            .await
+            .ok()
+            .flatten()
+    } else {
+        None
+    };
+
+    let icon_new_attachment = if let Some(att_id) = forum.icon_new_attachment_id {
+        attachments::Entity::find_by_id(att_id)
+            .one(db)
+            .await
+            .ok()
+            .flatten()
+    } else {
+        None
+    };
+
+    let selected_parent_id = forum.parent_id.unwrap_or(0);
+    Ok(ForumFormTemplate {
+        client,
+        forum,
+        all_forums,
+        selected_parent_id,
+        icon_attachment,
+        icon_new_attachment,
+        error: None,
+    }
+    .to_response())
+}
+
+/// POST /admin/forums/{id} - Update a forum
+#[post("/admin/forums/{id}")]
+async fn update_forum(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    mut multipart: actix_multipart::Multipart,
+) -> Result<impl Responder, Error> {
+    use crate::filesystem::{
+        deduplicate_payload, insert_payload_as_attachment, save_field_as_temp_file,
+    };
+    use futures::{StreamExt, TryStreamExt};
+
+    client.require_login()?;
+    client.require_permission("admin.settings")?;
+
+    let id = path.into_inner();
+    let db = get_db_pool();
+
+    // Fetch existing forum
+    let existing = forums::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+
+    // Store selected_parent_id before any moves
+    let selected_parent_id = existing.parent_id.unwrap_or(0);
+
+    // Parse multipart form
+    let mut csrf_token: Option<String> = None;
+    let mut label: Option<String> = None;
+    let mut description: Option<String> = None;
+    let mut icon = existing.icon.clone();
+    let mut icon_new = existing.icon_new.clone();
+    let mut display_order: i32 = existing.display_order;
+    let mut parent_id: Option<i32> = existing.parent_id;
+    let mut new_icon_attachment_id: Option<i32> = None;
+    let mut new_icon_new_attachment_id: Option<i32> = None;
+    let mut remove_icon_image = false;
+    let mut remove_icon_new_image = false;
+    let mut tags_enabled = false;
+    let mut restrict_tags = false;
+    let mut is_federated = false;
+    let mut thread_template: Option<String> = existing.thread_template.clone();
+
+    // Helper to load attachments for error display
+    async fn load_attachments(
+        forum: &forums::Model,
+        db: &DatabaseConnection,
+    ) -> (Option<attachments::Model>, Option<attachments::Model>) {
+        let icon_att = if let Some(att_id) = forum.icon_attachment_id {
+            attachments::Entity::find_by_id(att_id)
+                .one(db)
+                .await
+                .ok()
+                .flatten()
+        } else {
+            None
+        };
+        let icon_new_att = if let Some(att_id) = forum.icon_new_attachment_id {
+            attachments::Entity::find_by_id(att_id)
+                .one(db)
+                .await
+                .ok()
+                .flatten()
+        } else {
+            None
+        };
+        (icon_att, icon_new_att)
+    }
+
+    while let Ok(Some(mut field)) = multipart.try_next().await {
+        let field_name = field
+            .content_disposition()
+            .get_name()
+            .unwrap_or("")
+            .to_string();
+
+        match field_name.as_str() {
+            "csrf_token" => {
                 let mut buf = Vec::new();
                 while let Some(chunk) = field.next().await {
                     buf.extend_from_slice(
                         &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
                     );
                 }
-                let val = String::from_utf8_lossy(&buf).to_string();
-                if !val.trim().is_empty() {
-                    icon_new = val.trim().to_string();
+                csrf_token = Some(String::from_utf8_lossy(&buf).to_string());
+            }
+            "label" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
                 }
+                label = Some(String::from_utf8_lossy(&buf).to_string());
             }
-            "display_order" => {
+            "description" => {
                 let mut buf = Vec::new();
                 while let Some(chunk) = field.next().await {
                     buf.extend_from_slice(
                         &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
                     );
                 }
-                display_order = String::from_utf8_lossy(&buf)
-                    .parse()
-                    .unwrap_or(existing.display_order);
+                let desc = String::from_utf8_lossy(&buf).to_string();
+                description = if desc.trim().is_empty() {
+                    None
+                } else {
+                    Some(desc)
+                };
             }
-            "parent_id" => {
+            "icon" => {
                 let mut buf = Vec::new();
                 while let Some(chunk) = field.next().await {
                     buf.extend_from_slice(
@@ -6454,11 +8163,46 @@ async fn update_forum(
                     );
                 }
                 let val = String::from_utf8_lossy(&buf).to_string();
-                parent_id = val
-                    .trim()
-                    .parse()
-                    .ok()
-                    .filter(|&pid: &i32| pid != 0 && pid != id);
+                if !val.trim().is_empty() {
+                    icon = val.trim().to_string();
+                }
+            }
+            "icon_new" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                let val = String::from_utf8_lossy(&buf).to_string();
+                if !val.trim().is_empty() {
+                    icon_new = val.trim().to_string();
+                }
+            }
+            "display_order" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                display_order = String::from_utf8_lossy(&buf)
+                    .parse()
+                    .unwrap_or(existing.display_order);
+            }
+            "parent_id" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                let val = String::from_utf8_lossy(&buf).to_string();
+                parent_id = val
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|&pid: &i32| pid != 0 && pid != id);
             }
             "remove_icon_image" => {
                 remove_icon_image = true;
@@ -6472,6 +8216,9 @@ async fn update_forum(
             "restrict_tags" => {
                 restrict_tags = true;
             }
+            "is_federated" => {
+                is_federated = true;
+            }
             "thread_template" => {
                 let mut buf = Vec::new();
                 while let Some(chunk) = field.next().await {
@@ -6487,7 +8234,63 @@ async fn update_forum(
                 };
             }
             "icon_image" => {
-                if let Some(payload) = save_field_as_temp_file(&mut field).await? {
+                if let Some(payload) =
+                    save_field_as_temp_file(&mut field, client.preserve_image_orientation()).await?
+                {
+                    // Check if it's an image or SVG
+                    if !payload.is_image_or_svg() {
+                        let all_forums = forums::Entity::find()
+                            .order_by_asc(forums::Column::DisplayOrder)
+                            .all(db)
+                            .await
+                            .map_err(error::ErrorInternalServerError)?;
+                        let (icon_att, icon_new_att) = load_attachments(&existing, db).await;
+                        return Ok(ForumFormTemplate {
+                            client,
+                            forum: existing,
+                            all_forums,
+                            selected_parent_id,
+                            icon_attachment: icon_att,
+                            icon_new_attachment: icon_new_att,
+                            error: Some(
+                                "Only image files (PNG, GIF, WebP, SVG) are allowed".to_string(),
+                            ),
+                        }
+                        .to_response());
+                    }
+
+                    let response = match deduplicate_payload(&payload).await {
+                        Some(response) => response,
+                        None => match insert_payload_as_attachment(payload, None).await? {
+                            Some(response) => response,
+                            None => {
+                                let all_forums = forums::Entity::find()
+                                    .order_by_asc(forums::Column::DisplayOrder)
+                                    .all(db)
+                                    .await
+                                    .map_err(error::ErrorInternalServerError)?;
+                                let (icon_att, icon_new_att) =
+                                    load_attachments(&existing, db).await;
+                                return Ok(ForumFormTemplate {
+                                    client,
+                                    forum: existing,
+                                    all_forums,
+                                    selected_parent_id,
+                                    icon_attachment: icon_att,
+                                    icon_new_attachment: icon_new_att,
+                                    error: Some("Failed to process icon image".to_string()),
+                                }
+                                .to_response());
+                            }
+                        },
+                    };
+                    new_icon_attachment_id = Some(response.id);
+                }
+            }
+            "icon_new_image" => {
+                if let Some(payload) =
+                    save_field_as_temp_file(&mut field, client.preserve_image_orientation()).await?
+                {
                     // Check if it's an image or SVG
                     if !payload.is_image_or_svg() {
                         let all_forums = forums::Entity::find()
@@ -6510,406 +8313,2502 @@ async fn update_forum(
                         .to_response());
                     }
 
-                    let response = match deduplicate_payload(&payload).await {
-                        Some(response) => response,
-                        None => match insert_payload_as_attachment(payload, None).await? {
-                            Some(response) => response,
-                            None => {
-                                let all_forums = forums::Entity::find()
-                                    .order_by_asc(forums::Column::DisplayOrder)
-                                    .all(db)
-                                    .await
-                                    .map_err(error::ErrorInternalServerError)?;
-                                let (icon_att, icon_new_att) =
-                                    load_attachments(&existing, db).await;
-                                return Ok(ForumFormTemplate {
-                                    client,
-                                    forum: existing,
-                                    all_forums,
-                                    selected_parent_id,
-                                    icon_attachment: icon_att,
-                                    icon_new_attachment: icon_new_att,
-                                    error: Some("Failed to process icon image".to_string()),
-                                }
-                                .to_response());
-                            }
-                        },
-                    };
-                    new_icon_attachment_id = Some(response.id);
-                }
-            }
-            "icon_new_image" => {
-                if let Some(payload) = save_field_as_temp_file(&mut field).await? {
-                    // Check if it's an image or SVG
-                    if !payload.is_image_or_svg() {
-                        let all_forums = forums::Entity::find()
-                            .order_by_asc(forums::Column::DisplayOrder)
-                            .all(db)
-                            .await
-                            .map_err(error::ErrorInternalServerError)?;
-                        let (icon_att, icon_new_att) = load_attachments(&existing, db).await;
-                        return Ok(ForumFormTemplate {
-                            client,
-                            forum: existing,
-                            all_forums,
-                            selected_parent_id,
-                            icon_attachment: icon_att,
-                            icon_new_attachment: icon_new_att,
-                            error: Some(
-                                "Only image files (PNG, GIF, WebP, SVG) are allowed".to_string(),
-                            ),
-                        }
-                        .to_response());
-                    }
+                    let response = match deduplicate_payload(&payload).await {
+                        Some(response) => response,
+                        None => match insert_payload_as_attachment(payload, None).await? {
+                            Some(response) => response,
+                            None => {
+                                let all_forums = forums::Entity::find()
+                                    .order_by_asc(forums::Column::DisplayOrder)
+                                    .all(db)
+                                    .await
+                                    .map_err(error::ErrorInternalServerError)?;
+                                let (icon_att, icon_new_att) =
+                                    load_attachments(&existing, db).await;
+                                return Ok(ForumFormTemplate {
+                                    client,
+                                    forum: existing,
+                                    all_forums,
+                                    selected_parent_id,
+                                    icon_attachment: icon_att,
+                                    icon_new_attachment: icon_new_att,
+                                    error: Some(
+                                        "Failed to process new content icon image".to_string(),
+                                    ),
+                                }
+                                .to_response());
+                            }
+                        },
+                    };
+                    new_icon_new_attachment_id = Some(response.id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Validate CSRF
+    let token = csrf_token.ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &token)?;
+
+    // Validate input
+    let label = label.unwrap_or_default();
+    if label.trim().is_empty() {
+        let all_forums = forums::Entity::find()
+            .order_by_asc(forums::Column::DisplayOrder)
+            .all(db)
+            .await
+            .map_err(error::ErrorInternalServerError)?;
+        let (icon_att, icon_new_att) = load_attachments(&existing, db).await;
+        return Ok(ForumFormTemplate {
+            client,
+            forum: existing,
+            all_forums,
+            selected_parent_id,
+            icon_attachment: icon_att,
+            icon_new_attachment: icon_new_att,
+            error: Some("Forum name is required".to_string()),
+        }
+        .to_response());
+    }
+
+    // Determine final attachment IDs
+    let final_icon_attachment_id = if remove_icon_image {
+        None
+    } else if new_icon_attachment_id.is_some() {
+        new_icon_attachment_id
+    } else {
+        existing.icon_attachment_id
+    };
+
+    let final_icon_new_attachment_id = if remove_icon_new_image {
+        None
+    } else if new_icon_new_attachment_id.is_some() {
+        new_icon_new_attachment_id
+    } else {
+        existing.icon_new_attachment_id
+    };
+
+    // Update forum
+    let mut updated: forums::ActiveModel = existing.into();
+    updated.label = Set(label.trim().to_string());
+    updated.description = Set(description);
+    updated.icon = Set(if icon.trim().is_empty() {
+        "📁".to_string()
+    } else {
+        icon
+    });
+    updated.icon_new = Set(if icon_new.trim().is_empty() {
+        "📂".to_string()
+    } else {
+        icon_new
+    });
+    updated.display_order = Set(display_order);
+    updated.parent_id = Set(parent_id);
+    updated.icon_attachment_id = Set(final_icon_attachment_id);
+    updated.icon_new_attachment_id = Set(final_icon_new_attachment_id);
+    updated.tags_enabled = Set(tags_enabled);
+    updated.restrict_tags = Set(restrict_tags);
+    updated.is_federated = Set(is_federated);
+    updated.thread_template = Set(thread_template);
+
+    updated.update(db).await.map_err(|e| {
+        log::error!("Failed to update forum: {}", e);
+        error::ErrorInternalServerError("Failed to update forum")
+    })?;
+
+    crate::cache::invalidate(CacheScope::ForumTree).await;
+    crate::cache::fragment::invalidate(crate::cache::fragment::Fragment::ForumList).await;
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header(("Location", "/admin/forums"))
+        .finish())
+}
+
+// ============================================================================
+// Forum Permissions Management
+// ============================================================================
+
+/// Group info for column headers
+struct ForumPermGroupInfo {
+    id: i32,
+    label: String,
+}
+
+/// Permission value for a specific group
+struct ForumPermGroupValue {
+    group_id: i32,
+    value: String,
+}
+
+/// Permission row with values per group
+struct ForumPermissionRow {
+    id: i32,
+    label: String,
+    /// Values in same order as groups
+    values: Vec<ForumPermGroupValue>,
+}
+
+/// Category with permissions for forum permission matrix
+struct ForumPermCategoryDisplay {
+    label: String,
+    permissions: Vec<ForumPermissionRow>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/forum_permissions.html")]
+struct ForumPermissionsTemplate {
+    client: ClientCtx,
+    forum: forums::Model,
+    groups: Vec<ForumPermGroupInfo>,
+    categories: Vec<ForumPermCategoryDisplay>,
+    moderators: Vec<ModeratorDisplay>,
+    mod_success: Option<String>,
+    mod_error: Option<String>,
+    parent: Option<forums::Model>,
+    templates: Vec<PermissionTemplateDisplay>,
+    template_success: Option<String>,
+    template_error: Option<String>,
+}
+
+/// Form for updating forum permissions
+#[derive(Deserialize)]
+struct ForumPermissionsForm {
+    csrf_token: String,
+    /// Map of "perm_{permission_id}_{group_id}" -> value
+    #[serde(flatten)]
+    permissions: std::collections::HashMap<String, String>,
+}
+
+/// GET /admin/forums/{id}/permissions - View/edit forum permissions
+#[get("/admin/forums/{id}/permissions")]
+async fn view_forum_permissions(
+    client: ClientCtx,
+    forum_id: web::Path<i32>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.permissions.manage")?;
+
+    let db = get_db_pool();
+    let forum_id = forum_id.into_inner();
+
+    // Get query params for moderator messages
+    let mod_success = query.get("mod_success").cloned();
+    let mod_error = query.get("mod_error").cloned();
+    let template_success = query.get("template_success").cloned();
+    let template_error = query.get("template_error").cloned();
+
+    // Find the forum
+    let forum = forums::Entity::find_by_id(forum_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+
+    // Get all groups
+    let all_groups = groups::Entity::find()
+        .order_by_asc(groups::Column::Id)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch groups: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    let groups_info: Vec<ForumPermGroupInfo> = all_groups
+        .iter()
+        .map(|g| ForumPermGroupInfo {
+            id: g.id,
+            label: g.label.clone(),
+        })
+        .collect();
+
+    // Get all categories
+    let categories = permission_categories::Entity::find()
+        .order_by_asc(permission_categories::Column::Sort)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission categories: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    // Get all permissions
+    let all_permissions = permissions::Entity::find()
+        .order_by_asc(permissions::Column::Sort)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permissions: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    // Get forum permission collections for this forum
+    let forum_perms = forum_permissions::Entity::find()
+        .filter(forum_permissions::Column::ForumId.eq(forum_id))
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum permissions: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    // Build a map of collection_id -> group_id for this forum's collections
+    let collection_ids: Vec<i32> = forum_perms.iter().map(|fp| fp.collection_id).collect();
+
+    let collections = if !collection_ids.is_empty() {
+        permission_collections::Entity::find()
+            .filter(permission_collections::Column::Id.is_in(collection_ids.clone()))
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch permission collections: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?
+    } else {
+        Vec::new()
+    };
+
+    // Map: group_id -> collection_id
+    let group_to_collection: std::collections::HashMap<i32, i32> = collections
+        .into_iter()
+        .filter_map(|c| c.group_id.map(|gid| (gid, c.id)))
+        .collect();
+
+    // Map: collection_id -> group_id (inverse)
+    let collection_to_group: std::collections::HashMap<i32, i32> = group_to_collection
+        .iter()
+        .map(|(&gid, &cid)| (cid, gid))
+        .collect();
+
+    // Get permission values for these collections
+    let perm_values = if !collection_ids.is_empty() {
+        permission_values::Entity::find()
+            .filter(permission_values::Column::CollectionId.is_in(collection_ids))
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch permission values: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?
+    } else {
+        Vec::new()
+    };
+
+    // Build map: (group_id, permission_id) -> value_string
+    let mut value_map: std::collections::HashMap<(i32, i32), String> =
+        std::collections::HashMap::new();
+    for pv in perm_values {
+        if let Some(&group_id) = collection_to_group.get(&pv.collection_id) {
+            let value_str = match pv.value {
+                Flag::YES => "yes",
+                Flag::NO => "no",
+                Flag::NEVER => "never",
+                Flag::DEFAULT => "default",
+            };
+            value_map.insert((group_id, pv.permission_id), value_str.to_string());
+        }
+    }
+
+    // Build category displays
+    let mut category_displays = Vec::new();
+    for cat in categories {
+        let perms: Vec<ForumPermissionRow> = all_permissions
+            .iter()
+            .filter(|p| p.category_id == cat.id)
+            .map(|p| {
+                let values: Vec<ForumPermGroupValue> = all_groups
+                    .iter()
+                    .map(|group| {
+                        let value = value_map
+                            .get(&(group.id, p.id))
+                            .cloned()
+                            .unwrap_or_else(|| "default".to_string());
+                        ForumPermGroupValue {
+                            group_id: group.id,
+                            value,
+                        }
+                    })
+                    .collect();
+                ForumPermissionRow {
+                    id: p.id,
+                    label: p.label.clone(),
+                    values,
+                }
+            })
+            .collect();
+
+        if !perms.is_empty() {
+            category_displays.push(ForumPermCategoryDisplay {
+                label: cat.label,
+                permissions: perms,
+            });
+        }
+    }
+
+    // Get forum moderators
+    let moderators = get_forum_moderators_with_details(forum_id).await?;
+
+    let parent = match forum.parent_id {
+        Some(parent_id) => forums::Entity::find_by_id(parent_id)
+            .one(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch parent forum: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?,
+        None => None,
+    };
+
+    let templates = get_permission_templates().await?;
+
+    Ok(ForumPermissionsTemplate {
+        client,
+        forum,
+        groups: groups_info,
+        categories: category_displays,
+        moderators,
+        mod_success,
+        mod_error,
+        parent,
+        templates,
+        template_success,
+        template_error,
+    }
+    .to_response())
+}
+
+/// POST /admin/forums/{id}/permissions - Save forum permissions
+#[post("/admin/forums/{id}/permissions")]
+async fn save_forum_permissions(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    forum_id: web::Path<i32>,
+    form: web::Form<ForumPermissionsForm>,
+) -> Result<impl Responder, Error> {
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+    let forum_id = forum_id.into_inner();
+
+    // Verify forum exists
+    let forum = forums::Entity::find_by_id(forum_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+
+    // Get all groups
+    let all_groups = groups::Entity::find().all(db).await.map_err(|e| {
+        log::error!("Failed to fetch groups: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    // Parse form data: perm_{permission_id}_{group_id} -> value
+    // Build map: group_id -> HashMap<permission_id, value>
+    let mut group_permissions: std::collections::HashMap<
+        i32,
+        std::collections::HashMap<i32, String>,
+    > = std::collections::HashMap::new();
+
+    for (key, value) in &form.permissions {
+        if !key.starts_with("perm_") {
+            continue;
+        }
+        let parts: Vec<&str> = key.split('_').collect();
+        if parts.len() != 3 {
+            continue;
+        }
+        let perm_id: i32 = match parts[1].parse() {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
+        let group_id: i32 = match parts[2].parse() {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
+        group_permissions
+            .entry(group_id)
+            .or_default()
+            .insert(perm_id, value.clone());
+    }
+
+    let permissions_before = audit_snapshot(audit::forum(db, forum_id)).await?;
+
+    // Get existing forum permission links
+    let existing_forum_perms = forum_permissions::Entity::find()
+        .filter(forum_permissions::Column::ForumId.eq(forum_id))
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum permissions: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    let existing_collection_ids: Vec<i32> = existing_forum_perms
+        .iter()
+        .map(|fp| fp.collection_id)
+        .collect();
+
+    // Get existing collections for these IDs
+    let existing_collections = if !existing_collection_ids.is_empty() {
+        permission_collections::Entity::find()
+            .filter(permission_collections::Column::Id.is_in(existing_collection_ids))
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch permission collections: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?
+    } else {
+        Vec::new()
+    };
+
+    // Map: group_id -> collection_id
+    let mut group_to_collection: std::collections::HashMap<i32, i32> = existing_collections
+        .into_iter()
+        .filter_map(|c| c.group_id.map(|gid| (gid, c.id)))
+        .collect();
+
+    // For each group, update or create permission collection
+    for group in &all_groups {
+        let group_perms = match group_permissions.get(&group.id) {
+            Some(perms) => perms,
+            None => continue, // No permissions for this group
+        };
+
+        // Check if all values are "default" - if so, skip/delete
+        let has_non_default = group_perms.values().any(|v| v != "default");
+
+        if !has_non_default {
+            // All default - delete collection if exists
+            if let Some(collection_id) = group_to_collection.remove(&group.id) {
+                // Delete permission values
+                permission_values::Entity::delete_many()
+                    .filter(permission_values::Column::CollectionId.eq(collection_id))
+                    .exec(db)
+                    .await
+                    .ok();
+
+                // Delete forum_permission link
+                forum_permissions::Entity::delete_many()
+                    .filter(forum_permissions::Column::ForumId.eq(forum_id))
+                    .filter(forum_permissions::Column::CollectionId.eq(collection_id))
+                    .exec(db)
+                    .await
+                    .ok();
+
+                // Delete collection
+                permission_collections::Entity::delete_by_id(collection_id)
+                    .exec(db)
+                    .await
+                    .ok();
+            }
+            continue;
+        }
+
+        // Get or create collection for this group
+        let collection_id = if let Some(&cid) = group_to_collection.get(&group.id) {
+            cid
+        } else {
+            // Create new collection
+            let new_collection = permission_collections::ActiveModel {
+                group_id: Set(Some(group.id)),
+                user_id: Set(None),
+                ..Default::default()
+            };
+            let c = new_collection.insert(db).await.map_err(|e| {
+                log::error!("Failed to create permission collection: {}", e);
+                error::ErrorInternalServerError("Failed to create permission collection")
+            })?;
+
+            // Link to forum
+            let fp = forum_permissions::ActiveModel {
+                forum_id: Set(forum_id),
+                collection_id: Set(c.id),
+            };
+            fp.insert(db).await.map_err(|e| {
+                log::error!("Failed to link collection to forum: {}", e);
+                error::ErrorInternalServerError("Failed to link collection to forum")
+            })?;
+
+            c.id
+        };
+
+        // Delete existing permission values for this collection
+        permission_values::Entity::delete_many()
+            .filter(permission_values::Column::CollectionId.eq(collection_id))
+            .exec(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to delete old permission values: {}", e);
+                error::ErrorInternalServerError("Failed to update permissions")
+            })?;
+
+        // Insert new permission values
+        for (perm_id, value_str) in group_perms {
+            let flag = match value_str.as_str() {
+                "yes" => Flag::YES,
+                "no" => Flag::NO,
+                "never" => Flag::NEVER,
+                _ => continue, // Skip "default" values
+            };
+
+            let pv = permission_values::ActiveModel {
+                permission_id: Set(*perm_id),
+                collection_id: Set(collection_id),
+                value: Set(flag),
+            };
+
+            let _ = pv.insert(db).await;
+        }
+    }
+
+    let permissions_after = audit_snapshot(audit::forum(db, forum_id)).await?;
+    audit_permission_change(
+        db,
+        moderator_id,
+        audit::Change::ForumPermissions,
+        forum_id,
+        &permissions_before,
+        &permissions_after,
+    )
+    .await?;
+
+    // Log moderation action
+    log_moderation_action(
+        db,
+        moderator_id,
+        "update_forum_permissions",
+        "forum",
+        forum_id,
+        Some(&forum.label),
+    )
+    .await?;
+
+    log::info!(
+        "Forum {} permissions updated by user {}",
+        forum_id,
+        moderator_id
+    );
+
+    // Reload forum permissions cache so changes take effect immediately
+    if let Err(e) = client.get_permissions().reload_forum_permissions().await {
+        log::error!("Failed to reload forum permissions cache: {}", e);
+        // Continue anyway - changes are saved, just need server restart
+    }
+    crate::cache::invalidate(CacheScope::Permissions).await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/admin/forums/{}/permissions", forum_id),
+        ))
+        .finish())
+}
+
+// =============================================================================
+// Forum Permission Templates
+// =============================================================================
+
+/// Saved permission matrix offered on the forum permissions page
+struct PermissionTemplateDisplay {
+    id: i32,
+    name: String,
+    is_default: bool,
+    created_at: chrono::NaiveDateTime,
+}
+
+/// Templates for the forum permissions page, default first
+async fn get_permission_templates() -> Result<Vec<PermissionTemplateDisplay>, Error> {
+    let db = get_db_pool();
+
+    let templates = forum_permission_templates::Entity::find()
+        .order_by_desc(forum_permission_templates::Column::IsDefault)
+        .order_by_asc(forum_permission_templates::Column::Name)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission templates: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(templates
+        .into_iter()
+        .map(|t| PermissionTemplateDisplay {
+            id: t.id,
+            name: t.name,
+            is_default: t.is_default,
+            created_at: t.created_at,
+        })
+        .collect())
+}
+
+/// Redirect back to a forum's permissions page with a template message
+fn redirect_to_forum_permissions(forum_id: i32, query: &str) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/admin/forums/{}/permissions?{}", forum_id, query),
+        ))
+        .finish()
+}
+
+/// Replace a forum's group permissions, recording the change under `action`
+async fn replace_forum_permissions(
+    db: &DatabaseConnection,
+    permissions: &Permissions,
+    actor_id: i32,
+    forum: &forums::Model,
+    matrix: &[template::MatrixValue],
+    action: &str,
+) -> Result<(), Error> {
+    use sea_orm::TransactionTrait;
+
+    let permissions_before = audit_snapshot(audit::forum(db, forum.id)).await?;
+
+    let txn = db.begin().await.map_err(error::ErrorInternalServerError)?;
+    template::replace_forum_matrix(&txn, forum.id, matrix)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to replace forum permissions: {}", e);
+            error::ErrorInternalServerError("Failed to update permissions")
+        })?;
+    txn.commit()
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let permissions_after = audit_snapshot(audit::forum(db, forum.id)).await?;
+    audit_permission_change(
+        db,
+        actor_id,
+        audit::Change::ForumPermissions,
+        forum.id,
+        &permissions_before,
+        &permissions_after,
+    )
+    .await?;
+
+    log_moderation_action(db, actor_id, action, "forum", forum.id, Some(&forum.label)).await?;
+
+    if let Err(e) = permissions.reload_forum_permissions().await {
+        log::error!("Failed to reload forum permissions cache: {}", e);
+    }
+    crate::cache::invalidate(CacheScope::Permissions).await;
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct SavePermissionTemplateForm {
+    csrf_token: String,
+    name: String,
+    is_default: Option<String>, // checkbox
+}
+
+/// POST /admin/forums/{id}/permissions/templates - Save the forum's matrix as a template
+#[post("/admin/forums/{id}/permissions/templates")]
+async fn save_permission_template(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    forum_id: web::Path<i32>,
+    form: web::Form<SavePermissionTemplateForm>,
+) -> Result<impl Responder, Error> {
+    use sea_orm::TransactionTrait;
+
+    let actor_id = client.require_login()?;
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+    let forum_id = forum_id.into_inner();
+
+    let forum = forums::Entity::find_by_id(forum_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+
+    let name = form.name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return Ok(redirect_to_forum_permissions(
+            forum_id,
+            "template_error=invalid_name",
+        ));
+    }
+
+    let existing = forum_permission_templates::Entity::find()
+        .filter(forum_permission_templates::Column::Name.eq(name))
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to check template name: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+    if existing.is_some() {
+        return Ok(redirect_to_forum_permissions(
+            forum_id,
+            "template_error=name_taken",
+        ));
+    }
+
+    let txn = db.begin().await.map_err(error::ErrorInternalServerError)?;
+    let saved = template::save_template(&txn, forum_id, name, Some(actor_id))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to save permission template: {}", e);
+            error::ErrorInternalServerError("Failed to save template")
+        })?;
+    if form.is_default.is_some() {
+        template::set_default_template(&txn, Some(saved.id))
+            .await
+            .map_err(|e| {
+                log::error!("Failed to set default permission template: {}", e);
+                error::ErrorInternalServerError("Failed to save template")
+            })?;
+    }
+    txn.commit()
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    log_moderation_action(
+        db,
+        actor_id,
+        "save_forum_permission_template",
+        "forum",
+        forum_id,
+        Some(&format!("{}: {}", forum.label, name)),
+    )
+    .await?;
+
+    Ok(redirect_to_forum_permissions(
+        forum_id,
+        "template_success=saved",
+    ))
+}
+
+#[derive(Deserialize)]
+struct ApplyPermissionTemplateForm {
+    csrf_token: String,
+    template_id: i32,
+}
+
+/// POST /admin/forums/{id}/permissions/apply-template - Replace the forum's matrix with a template
+#[post("/admin/forums/{id}/permissions/apply-template")]
+async fn apply_permission_template(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    forum_id: web::Path<i32>,
+    form: web::Form<ApplyPermissionTemplateForm>,
+) -> Result<impl Responder, Error> {
+    let actor_id = client.require_login()?;
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+    let forum_id = forum_id.into_inner();
+
+    let forum = forums::Entity::find_by_id(forum_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+
+    let exists = forum_permission_templates::Entity::find_by_id(form.template_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission template: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .is_some();
+    if !exists {
+        return Ok(redirect_to_forum_permissions(
+            forum_id,
+            "template_error=not_found",
+        ));
+    }
+
+    let matrix = template::template_matrix(db, form.template_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission template values: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    replace_forum_permissions(
+        db,
+        client.get_permissions(),
+        actor_id,
+        &forum,
+        &matrix,
+        "apply_forum_permission_template",
+    )
+    .await?;
+
+    Ok(redirect_to_forum_permissions(
+        forum_id,
+        "template_success=applied",
+    ))
+}
+
+#[derive(Deserialize)]
+struct CopyParentPermissionsForm {
+    csrf_token: String,
+}
+
+/// POST /admin/forums/{id}/permissions/copy-parent - Replace the forum's matrix with its parent's
+#[post("/admin/forums/{id}/permissions/copy-parent")]
+async fn copy_parent_permissions(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    forum_id: web::Path<i32>,
+    form: web::Form<CopyParentPermissionsForm>,
+) -> Result<impl Responder, Error> {
+    let actor_id = client.require_login()?;
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+    let forum_id = forum_id.into_inner();
+
+    let forum = forums::Entity::find_by_id(forum_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+
+    let parent_id = match forum.parent_id {
+        Some(parent_id) => parent_id,
+        None => {
+            return Ok(redirect_to_forum_permissions(
+                forum_id,
+                "template_error=no_parent",
+            ))
+        }
+    };
+
+    let matrix = template::forum_matrix(db, parent_id).await.map_err(|e| {
+        log::error!("Failed to fetch parent forum permissions: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    replace_forum_permissions(
+        db,
+        client.get_permissions(),
+        actor_id,
+        &forum,
+        &matrix,
+        "copy_forum_permissions",
+    )
+    .await?;
+
+    Ok(redirect_to_forum_permissions(
+        forum_id,
+        "template_success=copied",
+    ))
+}
+
+#[derive(Deserialize)]
+struct ManagePermissionTemplateForm {
+    csrf_token: String,
+    /// Forum page to return to
+    forum_id: i32,
+}
+
+/// POST /admin/permission-templates/{id}/default - Toggle whether new forums get a template
+#[post("/admin/permission-templates/{id}/default")]
+async fn toggle_default_permission_template(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    template_id: web::Path<i32>,
+    form: web::Form<ManagePermissionTemplateForm>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+
+    let Some(saved) = forum_permission_templates::Entity::find_by_id(template_id.into_inner())
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission template: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+    else {
+        return Ok(redirect_to_forum_permissions(
+            form.forum_id,
+            "template_error=not_found",
+        ));
+    };
+
+    let new_default = if saved.is_default {
+        None
+    } else {
+        Some(saved.id)
+    };
+    template::set_default_template(db, new_default)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to set default permission template: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(redirect_to_forum_permissions(
+        form.forum_id,
+        "template_success=default_changed",
+    ))
+}
+
+/// POST /admin/permission-templates/{id}/delete - Delete a template
+#[post("/admin/permission-templates/{id}/delete")]
+async fn delete_permission_template(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    template_id: web::Path<i32>,
+    form: web::Form<ManagePermissionTemplateForm>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+
+    // Values cascade
+    let result = forum_permission_templates::Entity::delete_by_id(template_id.into_inner())
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete permission template: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(redirect_to_forum_permissions(
+        form.forum_id,
+        if result.rows_affected > 0 {
+            "template_success=deleted"
+        } else {
+            "template_error=not_found"
+        },
+    ))
+}
+
+// =============================================================================
+// Forum Moderators Management
+// =============================================================================
+
+struct ModeratorDisplay {
+    user_id: i32,
+    username: String,
+    created_at: chrono::NaiveDateTime,
+}
+
+/// GET /admin/forums/{id}/moderators - Redirect to permissions page (moderators are now integrated there)
+#[get("/admin/forums/{id}/moderators")]
+async fn view_forum_moderators(
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+    let forum_id = path.into_inner();
+
+    // Redirect to the permissions page which now includes moderators section
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/admin/forums/{}/permissions", forum_id),
+        ))
+        .finish())
+}
+
+async fn get_forum_moderators_with_details(forum_id: i32) -> Result<Vec<ModeratorDisplay>, Error> {
+    use sea_orm::{DbBackend, FromQueryResult, Statement};
+
+    let db = get_db_pool();
+
+    #[derive(Debug, FromQueryResult)]
+    struct ModeratorRow {
+        user_id: i32,
+        username: Option<String>,
+        created_at: chrono::NaiveDateTime,
+    }
+
+    let sql = r#"
+        SELECT fm.user_id, un.name as username, fm.created_at
+        FROM forum_moderators fm
+        LEFT JOIN user_names un ON un.user_id = fm.user_id
+        WHERE fm.forum_id = $1
+        ORDER BY fm.created_at DESC
+    "#;
+
+    let rows = ModeratorRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        sql,
+        [forum_id.into()],
+    ))
+    .all(db)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to fetch forum moderators: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| ModeratorDisplay {
+            user_id: r.user_id,
+            username: r.username.unwrap_or_else(|| "Unknown".to_string()),
+            created_at: r.created_at,
+        })
+        .collect())
+}
+
+/// Forum permission collections `user_id` was given as a moderator of
+/// `forum_id`. Member overrides on forums only come from moderation.
+async fn moderator_collection_ids(
+    db: &DatabaseConnection,
+    forum_id: i32,
+    user_id: i32,
+) -> Result<Vec<i32>, Error> {
+    let forum_collection_ids: Vec<i32> = forum_permissions::Entity::find()
+        .filter(forum_permissions::Column::ForumId.eq(forum_id))
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum permissions: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .into_iter()
+        .map(|fp| fp.collection_id)
+        .collect();
+
+    if forum_collection_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(permission_collections::Entity::find()
+        .filter(permission_collections::Column::Id.is_in(forum_collection_ids))
+        .filter(permission_collections::Column::UserId.eq(user_id))
+        .filter(permission_collections::Column::GroupId.is_null())
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch permission collections: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .into_iter()
+        .map(|c| c.id)
+        .collect())
+}
+
+/// Make a member a moderator of a forum, giving them a permission collection
+/// on it that grants every moderate.* permission. Returns false if they
+/// already moderate it.
+async fn assign_forum_moderator(
+    db: &DatabaseConnection,
+    permissions: &Permissions,
+    actor_id: i32,
+    forum_id: i32,
+    user_id: i32,
+) -> Result<bool, Error> {
+    let existing = forum_moderators::Entity::find()
+        .filter(forum_moderators::Column::ForumId.eq(forum_id))
+        .filter(forum_moderators::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to check existing moderator: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    if existing.is_some() {
+        return Ok(false);
+    }
+
+    let permissions_before = audit_snapshot(audit::forum(db, forum_id)).await?;
+
+    let new_mod = forum_moderators::ActiveModel {
+        forum_id: Set(forum_id),
+        user_id: Set(user_id),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        ..Default::default()
+    };
+
+    forum_moderators::Entity::insert(new_mod)
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to add moderator: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    if moderator_collection_ids(db, forum_id, user_id)
+        .await?
+        .is_empty()
+    {
+        let collection = permission_collections::ActiveModel {
+            group_id: Set(None),
+            user_id: Set(Some(user_id)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to create permission collection: {}", e);
+            error::ErrorInternalServerError("Failed to create permission collection")
+        })?;
+
+        forum_permissions::ActiveModel {
+            forum_id: Set(forum_id),
+            collection_id: Set(collection.id),
+        }
+        .insert(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to link collection to forum: {}", e);
+            error::ErrorInternalServerError("Failed to link collection to forum")
+        })?;
+
+        let moderation_permissions = permissions::Entity::find()
+            .filter(permissions::Column::Label.starts_with("moderate."))
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch permissions: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?;
+
+        for permission in moderation_permissions {
+            permission_values::ActiveModel {
+                permission_id: Set(permission.id),
+                collection_id: Set(collection.id),
+                value: Set(Flag::YES),
+            }
+            .insert(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to save permission value: {}", e);
+                error::ErrorInternalServerError("Failed to update permissions")
+            })?;
+        }
+    }
+
+    let permissions_after = audit_snapshot(audit::forum(db, forum_id)).await?;
+    audit_permission_change(
+        db,
+        actor_id,
+        audit::Change::ForumPermissions,
+        forum_id,
+        &permissions_before,
+        &permissions_after,
+    )
+    .await?;
+
+    log::info!(
+        "User {} added {} as moderator for forum {}",
+        actor_id,
+        user_id,
+        forum_id
+    );
+
+    reload_moderator_permissions(permissions).await;
+
+    Ok(true)
+}
+
+/// Stop a member moderating a forum and remove the permissions it gave them.
+/// Returns false if they weren't a moderator of it.
+async fn unassign_forum_moderator(
+    db: &DatabaseConnection,
+    permissions: &Permissions,
+    actor_id: i32,
+    forum_id: i32,
+    user_id: i32,
+) -> Result<bool, Error> {
+    let permissions_before = audit_snapshot(audit::forum(db, forum_id)).await?;
+
+    let result = forum_moderators::Entity::delete_many()
+        .filter(forum_moderators::Column::ForumId.eq(forum_id))
+        .filter(forum_moderators::Column::UserId.eq(user_id))
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to remove moderator: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    if result.rows_affected == 0 {
+        return Ok(false);
+    }
+
+    let collection_ids = moderator_collection_ids(db, forum_id, user_id).await?;
+    if !collection_ids.is_empty() {
+        // Cascades to the values and the forum link
+        permission_collections::Entity::delete_many()
+            .filter(permission_collections::Column::Id.is_in(collection_ids))
+            .exec(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to delete permission collection: {}", e);
+                error::ErrorInternalServerError("Failed to update permissions")
+            })?;
+    }
+
+    let permissions_after = audit_snapshot(audit::forum(db, forum_id)).await?;
+    audit_permission_change(
+        db,
+        actor_id,
+        audit::Change::ForumPermissions,
+        forum_id,
+        &permissions_before,
+        &permissions_after,
+    )
+    .await?;
+
+    log::info!(
+        "User {} removed user {} as moderator from forum {}",
+        actor_id,
+        user_id,
+        forum_id
+    );
+
+    reload_moderator_permissions(permissions).await;
+
+    Ok(true)
+}
+
+/// Apply moderator changes to permission checks on every instance
+async fn reload_moderator_permissions(permissions: &Permissions) {
+    if let Err(e) = permissions.reload_forum_permissions().await {
+        log::error!("Failed to reload permissions cache: {}", e);
+    }
+    crate::cache::invalidate(CacheScope::Permissions).await;
+}
+
+#[derive(Deserialize)]
+struct AddModeratorForm {
+    csrf_token: String,
+    username: String,
+}
+
+/// POST /admin/forums/{id}/moderators/add - Add a forum moderator
+#[post("/admin/forums/{id}/moderators/add")]
+async fn add_forum_moderator(
+    client: ClientCtx,
+    session: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<AddModeratorForm>,
+) -> Result<impl Responder, Error> {
+    let actor_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+
+    let forum_id = path.into_inner();
+    let db = get_db_pool();
+
+    // Verify forum exists
+    let _forum = forums::Entity::find_by_id(forum_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+
+    // Look up user by username
+    let user = user_names::Entity::find()
+        .filter(user_names::Column::Name.eq(form.username.trim()))
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to look up user: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    let user = match user {
+        Some(u) => u,
+        None => {
+            return Ok(HttpResponse::SeeOther()
+                .append_header((
+                    "Location",
+                    format!(
+                        "/admin/forums/{}/permissions?mod_error=user_not_found",
+                        forum_id
+                    ),
+                ))
+                .finish());
+        }
+    };
+
+    if !assign_forum_moderator(
+        db,
+        client.get_permissions(),
+        actor_id,
+        forum_id,
+        user.user_id,
+    )
+    .await?
+    {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((
+                "Location",
+                format!(
+                    "/admin/forums/{}/permissions?mod_error=already_moderator",
+                    forum_id
+                ),
+            ))
+            .finish());
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/admin/forums/{}/permissions?mod_success=added", forum_id),
+        ))
+        .finish())
+}
+
+#[derive(Deserialize)]
+struct RemoveModeratorForm {
+    csrf_token: String,
+    user_id: i32,
+}
+
+/// POST /admin/forums/{id}/moderators/remove - Remove a forum moderator
+#[post("/admin/forums/{id}/moderators/remove")]
+async fn remove_forum_moderator(
+    client: ClientCtx,
+    session: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<RemoveModeratorForm>,
+) -> Result<impl Responder, Error> {
+    let actor_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+
+    let forum_id = path.into_inner();
+    let db = get_db_pool();
+
+    if !unassign_forum_moderator(
+        db,
+        client.get_permissions(),
+        actor_id,
+        forum_id,
+        form.user_id,
+    )
+    .await?
+    {
+        return Ok(HttpResponse::SeeOther()
+            .append_header((
+                "Location",
+                format!("/admin/forums/{}/permissions?mod_error=not_found", forum_id),
+            ))
+            .finish());
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/admin/forums/{}/permissions?mod_success=removed", forum_id),
+        ))
+        .finish())
+}
+
+/// A forum and who moderates it, for the moderators page
+struct ForumModeratorsRow {
+    forum: ForumTreeItem,
+    moderators: Vec<ModeratorDisplay>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/forum_moderators.html")]
+struct ForumModeratorsTemplate {
+    client: ClientCtx,
+    rows: Vec<ForumModeratorsRow>,
+    success: Option<String>,
+    error: Option<String>,
+}
+
+/// GET /admin/moderators - Every forum and its moderators
+#[get("/admin/moderators")]
+async fn view_moderators(
+    client: ClientCtx,
+    query: web::Query<std::collections::HashMap<String, String>>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+
+    let all_forums = forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forums: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    let mut rows = Vec::new();
+    for forum in build_forum_tree(&all_forums) {
+        let moderators = get_forum_moderators_with_details(forum.id).await?;
+        rows.push(ForumModeratorsRow { forum, moderators });
+    }
+
+    let success = match query.get("success").map(String::as_str) {
+        Some("added") => Some("Moderator added.".to_string()),
+        Some("removed") => Some("Moderator removed.".to_string()),
+        _ => None,
+    };
+    let error = match query.get("error").map(String::as_str) {
+        Some("user_not_found") => Some("No member has that username.".to_string()),
+        Some("already_moderator") => Some("They already moderate that forum.".to_string()),
+        Some("not_found") => Some("They don't moderate that forum.".to_string()),
+        _ => None,
+    };
+
+    Ok(ForumModeratorsTemplate {
+        client,
+        rows,
+        success,
+        error,
+    }
+    .to_response())
+}
+
+#[derive(Deserialize)]
+struct AssignModeratorForm {
+    csrf_token: String,
+    forum_id: i32,
+    username: String,
+}
+
+/// POST /admin/moderators/add - Make a member a moderator of a forum
+#[post("/admin/moderators/add")]
+async fn assign_moderator(
+    client: ClientCtx,
+    session: actix_session::Session,
+    form: web::Form<AssignModeratorForm>,
+) -> Result<impl Responder, Error> {
+    let actor_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+
+    let db = get_db_pool();
+
+    forums::Entity::find_by_id(form.forum_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+
+    let user = user_names::Entity::find()
+        .filter(user_names::Column::Name.eq(form.username.trim()))
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to look up user: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    let location = match user {
+        None => "/admin/moderators?error=user_not_found",
+        Some(user) => {
+            if assign_forum_moderator(
+                db,
+                client.get_permissions(),
+                actor_id,
+                form.forum_id,
+                user.user_id,
+            )
+            .await?
+            {
+                "/admin/moderators?success=added"
+            } else {
+                "/admin/moderators?error=already_moderator"
+            }
+        }
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", location))
+        .finish())
+}
+
+#[derive(Deserialize)]
+struct UnassignModeratorForm {
+    csrf_token: String,
+    forum_id: i32,
+    user_id: i32,
+}
+
+/// POST /admin/moderators/remove - Stop a member moderating a forum
+#[post("/admin/moderators/remove")]
+async fn unassign_moderator(
+    client: ClientCtx,
+    session: actix_session::Session,
+    form: web::Form<UnassignModeratorForm>,
+) -> Result<impl Responder, Error> {
+    let actor_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+
+    let db = get_db_pool();
+    let location = if unassign_forum_moderator(
+        db,
+        client.get_permissions(),
+        actor_id,
+        form.forum_id,
+        form.user_id,
+    )
+    .await?
+    {
+        "/admin/moderators?success=removed"
+    } else {
+        "/admin/moderators?error=not_found"
+    };
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", location))
+        .finish())
+}
+
+// =============================================================================
+// Tag Management
+// =============================================================================
+
+#[derive(Template)]
+#[template(path = "admin/tags.html")]
+struct TagsAdminTemplate {
+    client: ClientCtx,
+    tags: Vec<TagWithForum>,
+}
+
+struct TagWithForum {
+    id: i32,
+    name: String,
+    slug: String,
+    color: String,
+    is_global: bool,
+    forum_names: Vec<String>,
+    use_count: i32,
+}
+
+/// GET /admin/tags - List all tags
+#[get("/admin/tags")]
+async fn view_tags(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+
+    // Fetch all tags
+    let tags_raw = tags::Entity::find()
+        .order_by_asc(tags::Column::Name)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch tags: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    // Fetch all tag_forums associations with forum data
+    let tag_forum_associations: Vec<(tag_forums::Model, Option<forums::Model>)> =
+        tag_forums::Entity::find()
+            .find_also_related(forums::Entity)
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch tag_forums: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?;
+
+    // Build a map of tag_id -> Vec<forum_name>
+    let mut tag_forum_map: std::collections::HashMap<i32, Vec<String>> =
+        std::collections::HashMap::new();
+    for (tf, forum_opt) in tag_forum_associations {
+        if let Some(forum) = forum_opt {
+            tag_forum_map
+                .entry(tf.tag_id)
+                .or_default()
+                .push(forum.label);
+        }
+    }
+
+    let tags_list: Vec<TagWithForum> = tags_raw
+        .into_iter()
+        .map(|tag| {
+            let forum_names = tag_forum_map.remove(&tag.id).unwrap_or_default();
+            TagWithForum {
+                id: tag.id,
+                name: tag.name,
+                slug: tag.slug,
+                color: tag.color.unwrap_or_else(|| "#6c757d".to_string()),
+                is_global: tag.is_global,
+                forum_names,
+                use_count: tag.use_count,
+            }
+        })
+        .collect();
+
+    Ok(TagsAdminTemplate {
+        client,
+        tags: tags_list,
+    }
+    .to_response())
+}
+
+#[derive(Template)]
+#[template(path = "admin/tag_form.html")]
+struct TagFormTemplate {
+    client: ClientCtx,
+    tag: Option<tags::Model>,
+    forums: Vec<forums::Model>,
+    selected_forum_ids: Vec<i32>,
+    is_edit: bool,
+}
+
+/// GET /admin/tags/create - Show create tag form
+#[get("/admin/tags/create")]
+async fn view_create_tag_form(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+
+    let forums_list = forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forums: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(TagFormTemplate {
+        client,
+        tag: None,
+        forums: forums_list,
+        selected_forum_ids: Vec::new(),
+        is_edit: false,
+    }
+    .to_response())
+}
+
+#[derive(Deserialize)]
+struct TagFormData {
+    csrf_token: String,
+    name: String,
+    color: String,
+    is_global: Option<String>,
+    #[serde(default)]
+    forum_ids: String,
+}
+
+impl TagFormData {
+    /// Parse the comma-separated forum_ids string into a Vec<i32>
+    fn parse_forum_ids(&self) -> Vec<i32> {
+        if self.forum_ids.is_empty() {
+            return Vec::new();
+        }
+        self.forum_ids
+            .split(',')
+            .filter_map(|s| s.trim().parse::<i32>().ok())
+            .collect()
+    }
+}
+
+/// POST /admin/tags/create - Create a new tag
+#[post("/admin/tags/create")]
+async fn create_tag(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<TagFormData>,
+) -> Result<impl Responder, Error> {
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+
+    // Validate name
+    let name = form.name.trim().to_string();
+    if name.is_empty() || name.len() > 50 {
+        return Err(error::ErrorBadRequest("Tag name must be 1-50 characters"));
+    }
+
+    // Create slug from name
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        return Err(error::ErrorBadRequest(
+            "Tag name must contain valid characters",
+        ));
+    }
+
+    // Validate color (should be hex color)
+    let color = if form.color.starts_with('#') && form.color.len() == 7 {
+        form.color.clone()
+    } else {
+        "#6c757d".to_string()
+    };
+
+    // Determine if global
+    let is_global = form.is_global.is_some();
+
+    // Check for duplicate slug (global tags must have unique slugs)
+    let existing = tags::Entity::find()
+        .filter(tags::Column::Slug.eq(slug.clone()))
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to check for duplicate tag: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    if existing.is_some() {
+        return Err(error::ErrorBadRequest(
+            "A tag with this name already exists",
+        ));
+    }
+
+    // Create the tag
+    let new_tag = tags::ActiveModel {
+        name: Set(name.clone()),
+        slug: Set(slug),
+        color: Set(Some(color)),
+        is_global: Set(is_global),
+        use_count: Set(0),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        ..Default::default()
+    };
+
+    let insert_result = tags::Entity::insert(new_tag).exec(db).await.map_err(|e| {
+        log::error!("Failed to create tag: {}", e);
+        error::ErrorInternalServerError("Failed to create tag")
+    })?;
+
+    let tag_id = insert_result.last_insert_id;
+
+    // If not global, create forum associations
+    let forum_ids = form.parse_forum_ids();
+    if !is_global && !forum_ids.is_empty() {
+        for forum_id in &forum_ids {
+            let tag_forum = tag_forums::ActiveModel {
+                tag_id: Set(tag_id),
+                forum_id: Set(*forum_id),
+                ..Default::default()
+            };
+            tag_forums::Entity::insert(tag_forum)
+                .exec(db)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to create tag_forum association: {}", e);
+                    error::ErrorInternalServerError("Failed to associate tag with forum")
+                })?;
+        }
+    }
+
+    log_moderation_action(db, moderator_id, "create_tag", "tag", tag_id, Some(&name)).await?;
+
+    log::info!("Tag '{}' created by user {}", name, moderator_id);
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/tags"))
+        .finish())
+}
+
+/// GET /admin/tags/{id}/edit - Show edit tag form
+#[get("/admin/tags/{id}/edit")]
+async fn view_edit_tag(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+    let tag_id = path.into_inner();
+
+    let tag = tags::Entity::find_by_id(tag_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch tag: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Tag not found"))?;
+
+    let forums_list = forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forums: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    // Fetch the forum IDs associated with this tag
+    let selected_forum_ids: Vec<i32> = tag_forums::Entity::find()
+        .filter(tag_forums::Column::TagId.eq(tag_id))
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch tag_forums: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .into_iter()
+        .map(|tf| tf.forum_id)
+        .collect();
+
+    Ok(TagFormTemplate {
+        client,
+        tag: Some(tag),
+        forums: forums_list,
+        selected_forum_ids,
+        is_edit: true,
+    }
+    .to_response())
+}
+
+/// POST /admin/tags/{id} - Update a tag
+#[post("/admin/tags/{id}")]
+async fn update_tag(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<TagFormData>,
+) -> Result<impl Responder, Error> {
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+    let tag_id = path.into_inner();
+
+    let tag = tags::Entity::find_by_id(tag_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch tag: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Tag not found"))?;
+
+    // Validate name
+    let name = form.name.trim().to_string();
+    if name.is_empty() || name.len() > 50 {
+        return Err(error::ErrorBadRequest("Tag name must be 1-50 characters"));
+    }
+
+    // Create slug from name
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        return Err(error::ErrorBadRequest(
+            "Tag name must contain valid characters",
+        ));
+    }
+
+    // Validate color
+    let color = if form.color.starts_with('#') && form.color.len() == 7 {
+        form.color.clone()
+    } else {
+        "#6c757d".to_string()
+    };
+
+    // Determine if global
+    let is_global = form.is_global.is_some();
+
+    // Check for duplicate slug (excluding current tag)
+    let existing = tags::Entity::find()
+        .filter(tags::Column::Slug.eq(slug.clone()))
+        .filter(tags::Column::Id.ne(tag_id))
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to check for duplicate tag: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    if existing.is_some() {
+        return Err(error::ErrorBadRequest(
+            "A tag with this name already exists",
+        ));
+    }
+
+    // Update the tag
+    let mut active_tag: tags::ActiveModel = tag.into();
+    active_tag.name = Set(name.clone());
+    active_tag.slug = Set(slug);
+    active_tag.color = Set(Some(color));
+    active_tag.is_global = Set(is_global);
+
+    active_tag.update(db).await.map_err(|e| {
+        log::error!("Failed to update tag: {}", e);
+        error::ErrorInternalServerError("Failed to update tag")
+    })?;
+
+    // Update forum associations: delete old ones and insert new ones
+    tag_forums::Entity::delete_many()
+        .filter(tag_forums::Column::TagId.eq(tag_id))
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete old tag_forums: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    // If not global, create new forum associations
+    let forum_ids = form.parse_forum_ids();
+    if !is_global && !forum_ids.is_empty() {
+        for forum_id in &forum_ids {
+            let tag_forum = tag_forums::ActiveModel {
+                tag_id: Set(tag_id),
+                forum_id: Set(*forum_id),
+                ..Default::default()
+            };
+            tag_forums::Entity::insert(tag_forum)
+                .exec(db)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to create tag_forum association: {}", e);
+                    error::ErrorInternalServerError("Failed to associate tag with forum")
+                })?;
+        }
+    }
+
+    log_moderation_action(db, moderator_id, "update_tag", "tag", tag_id, Some(&name)).await?;
+
+    log::info!("Tag {} updated by user {}", tag_id, moderator_id);
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/tags"))
+        .finish())
+}
+
+/// POST /admin/tags/{id}/delete - Delete a tag
+#[post("/admin/tags/{id}/delete")]
+async fn delete_tag(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<ModerationForm>,
+) -> Result<impl Responder, Error> {
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let db = get_db_pool();
+    let tag_id = path.into_inner();
+
+    let tag = tags::Entity::find_by_id(tag_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch tag: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Tag not found"))?;
+
+    let tag_name = tag.name.clone();
+
+    // Delete the tag (thread_tags entries will cascade delete)
+    tags::Entity::delete_by_id(tag_id)
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete tag: {}", e);
+            error::ErrorInternalServerError("Failed to delete tag")
+        })?;
+
+    log_moderation_action(
+        db,
+        moderator_id,
+        "delete_tag",
+        "tag",
+        tag_id,
+        Some(&tag_name),
+    )
+    .await?;
+
+    log::info!(
+        "Tag {} ('{}') deleted by user {}",
+        tag_id,
+        tag_name,
+        moderator_id
+    );
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/tags"))
+        .finish())
+}
+
+// ============================================================================
+// Chat Room Management
+// ============================================================================
+
+#[derive(Template)]
+#[template(path = "admin/chat_rooms.html")]
+struct ChatRoomsTemplate {
+    client: ClientCtx,
+    rooms: Vec<chat_rooms::Model>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/chat_room_form.html")]
+struct ChatRoomFormTemplate {
+    client: ClientCtx,
+    room: Option<chat_rooms::Model>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatRoomForm {
+    csrf_token: String,
+    title: String,
+    description: Option<String>,
+    topic: Option<String>,
+    display_order: i16,
+    min_posts_required: i32,
+    min_account_age_hours: i32,
+    is_staff_only: Option<String>,
+}
+
+/// GET /admin/chat-rooms - List all chat rooms
+#[get("/admin/chat-rooms")]
+async fn view_chat_rooms(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+
+    let rooms = chat_rooms::Entity::find()
+        .filter(chat_rooms::Column::IsPrivate.eq(false))
+        .order_by_asc(chat_rooms::Column::DisplayOrder)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch chat rooms: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(ChatRoomsTemplate { client, rooms }.to_response())
+}
+
+/// GET /admin/chat-rooms/new - Show form to create new chat room
+#[get("/admin/chat-rooms/new")]
+async fn view_create_chat_room_form(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    Ok(ChatRoomFormTemplate {
+        client,
+        room: None,
+        error: None,
+    }
+    .to_response())
+}
+
+/// POST /admin/chat-rooms - Create a new chat room
+#[post("/admin/chat-rooms")]
+async fn create_chat_room(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<ChatRoomForm>,
+) -> Result<impl Responder, Error> {
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
 
-                    let response = match deduplicate_payload(&payload).await {
-                        Some(response) => response,
-                        None => match insert_payload_as_attachment(payload, None).await? {
-                            Some(response) => response,
-                            None => {
-                                let all_forums = forums::Entity::find()
-                                    .order_by_asc(forums::Column::DisplayOrder)
-                                    .all(db)
-                                    .await
-                                    .map_err(error::ErrorInternalServerError)?;
-                                let (icon_att, icon_new_att) =
-                                    load_attachments(&existing, db).await;
-                                return Ok(ForumFormTemplate {
-                                    client,
-                                    forum: existing,
-                                    all_forums,
-                                    selected_parent_id,
-                                    icon_attachment: icon_att,
-                                    icon_new_attachment: icon_new_att,
-                                    error: Some(
-                                        "Failed to process new content icon image".to_string(),
-                                    ),
-                                }
-                                .to_response());
-                            }
-                        },
-                    };
-                    new_icon_new_attachment_id = Some(response.id);
-                }
-            }
-            _ => {}
+    let db = get_db_pool();
+
+    // Validate input
+    if form.title.trim().is_empty() {
+        return Ok(ChatRoomFormTemplate {
+            client,
+            room: None,
+            error: Some("Title is required".to_string()),
         }
+        .to_response());
     }
 
-    // Validate CSRF
-    let token = csrf_token.ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
-    crate::middleware::csrf::validate_csrf_token(&cookies, &token)?;
+    let new_room = chat_rooms::ActiveModel {
+        title: Set(form.title.trim().to_string()),
+        description: Set(form.description.clone().filter(|s| !s.trim().is_empty())),
+        topic: Set(form.topic.clone().filter(|s| !s.trim().is_empty())),
+        display_order: Set(form.display_order),
+        min_posts_required: Set(form.min_posts_required),
+        min_account_age_hours: Set(form.min_account_age_hours),
+        is_staff_only: Set(form.is_staff_only.is_some()),
+        ..Default::default()
+    };
+
+    let room = new_room.insert(db).await.map_err(|e| {
+        log::error!("Failed to create chat room: {}", e);
+        error::ErrorInternalServerError("Failed to create chat room")
+    })?;
+
+    log_moderation_action(
+        db,
+        moderator_id,
+        "create_chat_room",
+        "chat_room",
+        room.id,
+        Some(&room.title),
+    )
+    .await?;
+
+    log::info!(
+        "Chat room {} ('{}') created by user {}",
+        room.id,
+        room.title,
+        moderator_id
+    );
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header(("Location", "/admin/chat-rooms"))
+        .finish())
+}
+
+/// GET /admin/chat-rooms/{id}/edit - Show form to edit chat room
+#[get("/admin/chat-rooms/{id}/edit")]
+async fn view_edit_chat_room(
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let id = path.into_inner();
+    let db = get_db_pool();
+
+    let room = chat_rooms::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch chat room: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Chat room not found"))?;
+
+    Ok(ChatRoomFormTemplate {
+        client,
+        room: Some(room),
+        error: None,
+    }
+    .to_response())
+}
+
+/// POST /admin/chat-rooms/{id} - Update a chat room
+#[post("/admin/chat-rooms/{id}")]
+async fn update_chat_room(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<ChatRoomForm>,
+) -> Result<impl Responder, Error> {
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let id = path.into_inner();
+    let db = get_db_pool();
+
+    // Fetch existing room
+    let existing = chat_rooms::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch chat room: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Chat room not found"))?;
 
     // Validate input
-    let label = label.unwrap_or_default();
-    if label.trim().is_empty() {
-        let all_forums = forums::Entity::find()
-            .order_by_asc(forums::Column::DisplayOrder)
-            .all(db)
-            .await
-            .map_err(error::ErrorInternalServerError)?;
-        let (icon_att, icon_new_att) = load_attachments(&existing, db).await;
-        return Ok(ForumFormTemplate {
+    if form.title.trim().is_empty() {
+        return Ok(ChatRoomFormTemplate {
             client,
-            forum: existing,
-            all_forums,
-            selected_parent_id,
-            icon_attachment: icon_att,
-            icon_new_attachment: icon_new_att,
-            error: Some("Forum name is required".to_string()),
+            room: Some(existing),
+            error: Some("Title is required".to_string()),
         }
         .to_response());
     }
 
-    // Determine final attachment IDs
-    let final_icon_attachment_id = if remove_icon_image {
-        None
-    } else if new_icon_attachment_id.is_some() {
-        new_icon_attachment_id
-    } else {
-        existing.icon_attachment_id
-    };
+    let mut updated: chat_rooms::ActiveModel = existing.into();
+    updated.title = Set(form.title.trim().to_string());
+    updated.description = Set(form.description.clone().filter(|s| !s.trim().is_empty()));
+    updated.topic = Set(form.topic.clone().filter(|s| !s.trim().is_empty()));
+    updated.display_order = Set(form.display_order);
+    updated.min_posts_required = Set(form.min_posts_required);
+    updated.min_account_age_hours = Set(form.min_account_age_hours);
+    updated.is_staff_only = Set(form.is_staff_only.is_some());
 
-    let final_icon_new_attachment_id = if remove_icon_new_image {
-        None
-    } else if new_icon_new_attachment_id.is_some() {
-        new_icon_new_attachment_id
-    } else {
-        existing.icon_new_attachment_id
-    };
+    updated.update(db).await.map_err(|e| {
+        log::error!("Failed to update chat room: {}", e);
+        error::ErrorInternalServerError("Failed to update chat room")
+    })?;
 
-    // Update forum
-    let mut updated: forums::ActiveModel = existing.into();
-    updated.label = Set(label.trim().to_string());
-    updated.description = Set(description);
-    updated.icon = Set(if icon.trim().is_empty() {
-        "📁".to_string()
-    } else {
-        icon
-    });
-    updated.icon_new = Set(if icon_new.trim().is_empty() {
-        "📂".to_string()
-    } else {
-        icon_new
-    });
-    updated.display_order = Set(display_order);
-    updated.parent_id = Set(parent_id);
-    updated.icon_attachment_id = Set(final_icon_attachment_id);
-    updated.icon_new_attachment_id = Set(final_icon_new_attachment_id);
-    updated.tags_enabled = Set(tags_enabled);
-    updated.restrict_tags = Set(restrict_tags);
-    updated.thread_template = Set(thread_template);
+    log_moderation_action(
+        db,
+        moderator_id,
+        "update_chat_room",
+        "chat_room",
+        id,
+        Some(&form.title),
+    )
+    .await?;
 
-    updated.update(db).await.map_err(|e| {
-        log::error!("Failed to update forum: {}", e);
-        error::ErrorInternalServerError("Failed to update forum")
-    })?;
+    log::info!("Chat room {} updated by user {}", id, moderator_id);
 
     Ok(HttpResponse::SeeOther()
-        .insert_header(("Location", "/admin/forums"))
+        .insert_header(("Location", "/admin/chat-rooms"))
         .finish())
 }
 
-// ============================================================================
-// Forum Permissions Management
-// ============================================================================
+/// POST /admin/chat-rooms/{id}/delete - Delete a chat room
+#[post("/admin/chat-rooms/{id}/delete")]
+async fn delete_chat_room(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<ModerationForm>,
+) -> Result<impl Responder, Error> {
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
 
-/// Group info for column headers
-struct ForumPermGroupInfo {
-    id: i32,
-    label: String,
-}
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
 
-/// Permission value for a specific group
-struct ForumPermGroupValue {
-    group_id: i32,
-    value: String,
-}
+    let db = get_db_pool();
+    let room_id = path.into_inner();
 
-/// Permission row with values per group
-struct ForumPermissionRow {
-    id: i32,
-    label: String,
-    /// Values in same order as groups
-    values: Vec<ForumPermGroupValue>,
-}
+    let room = chat_rooms::Entity::find_by_id(room_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch chat room: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Chat room not found"))?;
 
-/// Category with permissions for forum permission matrix
-struct ForumPermCategoryDisplay {
-    label: String,
-    permissions: Vec<ForumPermissionRow>,
+    let room_title = room.title.clone();
+
+    // Delete the chat room (messages will remain but room reference will be gone)
+    chat_rooms::Entity::delete_by_id(room_id)
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete chat room: {}", e);
+            error::ErrorInternalServerError("Failed to delete chat room")
+        })?;
+
+    log_moderation_action(
+        db,
+        moderator_id,
+        "delete_chat_room",
+        "chat_room",
+        room_id,
+        Some(&room_title),
+    )
+    .await?;
+
+    log::info!(
+        "Chat room {} ('{}') deleted by user {}",
+        room_id,
+        room_title,
+        moderator_id
+    );
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/chat-rooms"))
+        .finish())
 }
 
 #[derive(Template)]
-#[template(path = "admin/forum_permissions.html")]
-struct ForumPermissionsTemplate {
+#[template(path = "admin/chat_room_permissions.html")]
+struct ChatRoomPermissionsTemplate {
     client: ClientCtx,
-    forum: forums::Model,
+    room: chat_rooms::Model,
     groups: Vec<ForumPermGroupInfo>,
-    categories: Vec<ForumPermCategoryDisplay>,
-    moderators: Vec<ModeratorDisplay>,
-    mod_success: Option<String>,
-    mod_error: Option<String>,
+    permissions: Vec<ForumPermissionRow>,
 }
 
-/// Form for updating forum permissions
+/// Form for updating chat room permissions
 #[derive(Deserialize)]
-struct ForumPermissionsForm {
+struct ChatRoomPermissionsForm {
     csrf_token: String,
     /// Map of "perm_{permission_id}_{group_id}" -> value
     #[serde(flatten)]
     permissions: std::collections::HashMap<String, String>,
 }
 
-/// GET /admin/forums/{id}/permissions - View/edit forum permissions
-#[get("/admin/forums/{id}/permissions")]
-async fn view_forum_permissions(
-    client: ClientCtx,
-    forum_id: web::Path<i32>,
-    query: web::Query<std::collections::HashMap<String, String>>,
-) -> Result<impl Responder, Error> {
-    client.require_permission("admin.permissions.manage")?;
-
-    let db = get_db_pool();
-    let forum_id = forum_id.into_inner();
-
-    // Get query params for moderator messages
-    let mod_success = query.get("mod_success").cloned();
-    let mod_error = query.get("mod_error").cloned();
-
-    // Find the forum
-    let forum = forums::Entity::find_by_id(forum_id)
-        .one(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch forum: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
-
-    // Get all groups
-    let all_groups = groups::Entity::find()
-        .order_by_asc(groups::Column::Id)
+/// Permissions which can be overridden per chat room
+async fn fetch_chat_permissions(db: &DatabaseConnection) -> Result<Vec<permissions::Model>, Error> {
+    permissions::Entity::find()
+        .filter(permissions::Column::Label.starts_with("chat."))
+        .order_by_asc(permissions::Column::Sort)
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch groups: {}", e);
+            log::error!("Failed to fetch chat permissions: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?;
-
-    let groups_info: Vec<ForumPermGroupInfo> = all_groups
-        .iter()
-        .map(|g| ForumPermGroupInfo {
-            id: g.id,
-            label: g.label.clone(),
         })
-        .collect();
+}
 
-    // Get all categories
-    let categories = permission_categories::Entity::find()
-        .order_by_asc(permission_categories::Column::Sort)
+/// Map of group_id -> collection_id for a chat room's permission overrides
+async fn fetch_chat_room_collections(
+    db: &DatabaseConnection,
+    room_id: i32,
+) -> Result<std::collections::HashMap<i32, i32>, Error> {
+    let collections = chat_room_permissions::Entity::find()
+        .filter(chat_room_permissions::Column::ChatRoomId.eq(room_id))
+        .find_with_related(permission_collections::Entity)
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch permission categories: {}", e);
+            log::error!("Failed to fetch chat room permissions: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    // Get all permissions
-    let all_permissions = permissions::Entity::find()
-        .order_by_asc(permissions::Column::Sort)
-        .all(db)
+    Ok(collections
+        .into_iter()
+        .flat_map(|(_, collections)| collections)
+        .filter_map(|c| c.group_id.map(|gid| (gid, c.id)))
+        .collect())
+}
+
+/// GET /admin/chat-rooms/{id}/permissions - View/edit chat room permissions
+#[get("/admin/chat-rooms/{id}/permissions")]
+async fn view_chat_room_permissions(
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.permissions.manage")?;
+
+    let db = get_db_pool();
+    let room_id = path.into_inner();
+
+    let room = chat_rooms::Entity::find_by_id(room_id)
+        .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch permissions: {}", e);
+            log::error!("Failed to fetch chat room: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?;
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Chat room not found"))?;
 
-    // Get forum permission collections for this forum
-    let forum_perms = forum_permissions::Entity::find()
-        .filter(forum_permissions::Column::ForumId.eq(forum_id))
+    let all_groups = groups::Entity::find()
+        .order_by_asc(groups::Column::Id)
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forum permissions: {}", e);
+            log::error!("Failed to fetch groups: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    // Build a map of collection_id -> group_id for this forum's collections
-    let collection_ids: Vec<i32> = forum_perms.iter().map(|fp| fp.collection_id).collect();
-
-    let collections = if !collection_ids.is_empty() {
-        permission_collections::Entity::find()
-            .filter(permission_collections::Column::Id.is_in(collection_ids.clone()))
-            .all(db)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to fetch permission collections: {}", e);
-                error::ErrorInternalServerError("Database error")
-            })?
-    } else {
-        Vec::new()
-    };
-
-    // Map: group_id -> collection_id
-    let group_to_collection: std::collections::HashMap<i32, i32> = collections
-        .into_iter()
-        .filter_map(|c| c.group_id.map(|gid| (gid, c.id)))
-        .collect();
-
-    // Map: collection_id -> group_id (inverse)
-    let collection_to_group: std::collections::HashMap<i32, i32> = group_to_collection
-        .iter()
-        .map(|(&gid, &cid)| (cid, gid))
-        .collect();
-
-    // Get permission values for these collections
-    let perm_values = if !collection_ids.is_empty() {
-        permission_values::Entity::find()
-            .filter(permission_values::Column::CollectionId.is_in(collection_ids))
-            .all(db)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to fetch permission values: {}", e);
-                error::ErrorInternalServerError("Database error")
-            })?
-    } else {
-        Vec::new()
-    };
+    let chat_permissions = fetch_chat_permissions(db).await?;
+    let group_to_collection = fetch_chat_room_collections(db, room_id).await?;
 
     // Build map: (group_id, permission_id) -> value_string
     let mut value_map: std::collections::HashMap<(i32, i32), String> =
-        std::collections::HashMap::new();
-    for pv in perm_values {
-        if let Some(&group_id) = collection_to_group.get(&pv.collection_id) {
-            let value_str = match pv.value {
-                Flag::YES => "yes",
-                Flag::NO => "no",
-                Flag::NEVER => "never",
-                Flag::DEFAULT => "default",
-            };
-            value_map.insert((group_id, pv.permission_id), value_str.to_string());
-        }
-    }
-
-    // Build category displays
-    let mut category_displays = Vec::new();
-    for cat in categories {
-        let perms: Vec<ForumPermissionRow> = all_permissions
-            .iter()
-            .filter(|p| p.category_id == cat.id)
-            .map(|p| {
-                let values: Vec<ForumPermGroupValue> = all_groups
-                    .iter()
-                    .map(|group| {
-                        let value = value_map
-                            .get(&(group.id, p.id))
-                            .cloned()
-                            .unwrap_or_else(|| "default".to_string());
-                        ForumPermGroupValue {
-                            group_id: group.id,
-                            value,
-                        }
-                    })
-                    .collect();
-                ForumPermissionRow {
-                    id: p.id,
-                    label: p.label.clone(),
-                    values,
-                }
-            })
-            .collect();
+        std::collections::HashMap::new();
+    for (&group_id, &collection_id) in &group_to_collection {
+        let values = permission_values::Entity::find()
+            .filter(permission_values::Column::CollectionId.eq(collection_id))
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch permission values: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?;
 
-        if !perms.is_empty() {
-            category_displays.push(ForumPermCategoryDisplay {
-                label: cat.label,
-                permissions: perms,
-            });
+        for pv in values {
+            let value_str = match pv.value {
+                Flag::YES => "yes",
+                Flag::NO => "no",
+                Flag::NEVER => "never",
+                Flag::DEFAULT => "default",
+            };
+            value_map.insert((group_id, pv.permission_id), value_str.to_string());
         }
     }
 
-    // Get forum moderators
-    let moderators = get_forum_moderators_with_details(forum_id).await?;
+    let permission_rows = chat_permissions
+        .iter()
+        .map(|p| ForumPermissionRow {
+            id: p.id,
+            label: p.label.clone(),
+            values: all_groups
+                .iter()
+                .map(|group| ForumPermGroupValue {
+                    group_id: group.id,
+                    value: value_map
+                        .get(&(group.id, p.id))
+                        .cloned()
+                        .unwrap_or_else(|| "default".to_string()),
+                })
+                .collect(),
+        })
+        .collect();
 
-    Ok(ForumPermissionsTemplate {
+    Ok(ChatRoomPermissionsTemplate {
         client,
-        forum,
-        groups: groups_info,
-        categories: category_displays,
-        moderators,
-        mod_success,
-        mod_error,
+        room,
+        groups: all_groups
+            .into_iter()
+            .map(|g| ForumPermGroupInfo {
+                id: g.id,
+                label: g.label,
+            })
+            .collect(),
+        permissions: permission_rows,
     }
     .to_response())
 }
 
-/// POST /admin/forums/{id}/permissions - Save forum permissions
-#[post("/admin/forums/{id}/permissions")]
-async fn save_forum_permissions(
+/// POST /admin/chat-rooms/{id}/permissions - Save chat room permissions
+#[post("/admin/chat-rooms/{id}/permissions")]
+async fn save_chat_room_permissions(
     client: ClientCtx,
     cookies: actix_session::Session,
-    forum_id: web::Path<i32>,
-    form: web::Form<ForumPermissionsForm>,
+    path: web::Path<i32>,
+    form: web::Form<ChatRoomPermissionsForm>,
 ) -> Result<impl Responder, Error> {
     let moderator_id = client.require_login()?;
     client.require_permission("admin.permissions.manage")?;
@@ -6917,154 +10816,90 @@ async fn save_forum_permissions(
     crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
 
     let db = get_db_pool();
-    let forum_id = forum_id.into_inner();
+    let room_id = path.into_inner();
 
-    // Verify forum exists
-    let forum = forums::Entity::find_by_id(forum_id)
+    let room = chat_rooms::Entity::find_by_id(room_id)
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forum: {}", e);
+            log::error!("Failed to fetch chat room: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+        .ok_or_else(|| error::ErrorNotFound("Chat room not found"))?;
+
+    let permissions_before = audit_snapshot(audit::chat_room(db, room_id)).await?;
 
-    // Get all groups
     let all_groups = groups::Entity::find().all(db).await.map_err(|e| {
         log::error!("Failed to fetch groups: {}", e);
         error::ErrorInternalServerError("Database error")
     })?;
 
-    // Parse form data: perm_{permission_id}_{group_id} -> value
-    // Build map: group_id -> HashMap<permission_id, value>
-    let mut group_permissions: std::collections::HashMap<
-        i32,
-        std::collections::HashMap<i32, String>,
-    > = std::collections::HashMap::new();
-
-    for (key, value) in &form.permissions {
-        if !key.starts_with("perm_") {
-            continue;
-        }
-        let parts: Vec<&str> = key.split('_').collect();
-        if parts.len() != 3 {
-            continue;
-        }
-        let perm_id: i32 = match parts[1].parse() {
-            Ok(id) => id,
-            Err(_) => continue,
-        };
-        let group_id: i32 = match parts[2].parse() {
-            Ok(id) => id,
-            Err(_) => continue,
-        };
-        group_permissions
-            .entry(group_id)
-            .or_default()
-            .insert(perm_id, value.clone());
-    }
-
-    // Get existing forum permission links
-    let existing_forum_perms = forum_permissions::Entity::find()
-        .filter(forum_permissions::Column::ForumId.eq(forum_id))
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch forum permissions: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
-
-    let existing_collection_ids: Vec<i32> = existing_forum_perms
-        .iter()
-        .map(|fp| fp.collection_id)
-        .collect();
-
-    // Get existing collections for these IDs
-    let existing_collections = if !existing_collection_ids.is_empty() {
-        permission_collections::Entity::find()
-            .filter(permission_collections::Column::Id.is_in(existing_collection_ids))
-            .all(db)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to fetch permission collections: {}", e);
-                error::ErrorInternalServerError("Database error")
-            })?
-    } else {
-        Vec::new()
-    };
-
-    // Map: group_id -> collection_id
-    let mut group_to_collection: std::collections::HashMap<i32, i32> = existing_collections
+    let chat_permission_ids: Vec<i32> = fetch_chat_permissions(db)
+        .await?
         .into_iter()
-        .filter_map(|c| c.group_id.map(|gid| (gid, c.id)))
+        .map(|p| p.id)
         .collect();
+    let mut group_to_collection = fetch_chat_room_collections(db, room_id).await?;
 
-    // For each group, update or create permission collection
     for group in &all_groups {
-        let group_perms = match group_permissions.get(&group.id) {
-            Some(perms) => perms,
-            None => continue, // No permissions for this group
-        };
-
-        // Check if all values are "default" - if so, skip/delete
-        let has_non_default = group_perms.values().any(|v| v != "default");
+        // Only chat permissions can be set on a room; anything else is ignored
+        let flags: Vec<(i32, Flag)> = chat_permission_ids
+            .iter()
+            .filter_map(|perm_id| {
+                let key = format!("perm_{}_{}", perm_id, group.id);
+                let flag = match form.permissions.get(&key).map(String::as_str) {
+                    Some("yes") => Flag::YES,
+                    Some("no") => Flag::NO,
+                    Some("never") => Flag::NEVER,
+                    _ => return None,
+                };
+                Some((*perm_id, flag))
+            })
+            .collect();
 
-        if !has_non_default {
-            // All default - delete collection if exists
+        if flags.is_empty() {
+            // All default - delete the group's override if it exists
             if let Some(collection_id) = group_to_collection.remove(&group.id) {
-                // Delete permission values
-                permission_values::Entity::delete_many()
-                    .filter(permission_values::Column::CollectionId.eq(collection_id))
-                    .exec(db)
-                    .await
-                    .ok();
-
-                // Delete forum_permission link
-                forum_permissions::Entity::delete_many()
-                    .filter(forum_permissions::Column::ForumId.eq(forum_id))
-                    .filter(forum_permissions::Column::CollectionId.eq(collection_id))
-                    .exec(db)
-                    .await
-                    .ok();
-
-                // Delete collection
                 permission_collections::Entity::delete_by_id(collection_id)
                     .exec(db)
                     .await
-                    .ok();
+                    .map_err(|e| {
+                        log::error!("Failed to delete permission collection: {}", e);
+                        error::ErrorInternalServerError("Failed to update permissions")
+                    })?;
             }
             continue;
         }
 
-        // Get or create collection for this group
         let collection_id = if let Some(&cid) = group_to_collection.get(&group.id) {
             cid
         } else {
-            // Create new collection
-            let new_collection = permission_collections::ActiveModel {
+            let c = permission_collections::ActiveModel {
                 group_id: Set(Some(group.id)),
                 user_id: Set(None),
                 ..Default::default()
-            };
-            let c = new_collection.insert(db).await.map_err(|e| {
+            }
+            .insert(db)
+            .await
+            .map_err(|e| {
                 log::error!("Failed to create permission collection: {}", e);
                 error::ErrorInternalServerError("Failed to create permission collection")
             })?;
 
-            // Link to forum
-            let fp = forum_permissions::ActiveModel {
-                forum_id: Set(forum_id),
+            chat_room_permissions::ActiveModel {
+                chat_room_id: Set(room_id),
                 collection_id: Set(c.id),
-            };
-            fp.insert(db).await.map_err(|e| {
-                log::error!("Failed to link collection to forum: {}", e);
-                error::ErrorInternalServerError("Failed to link collection to forum")
+            }
+            .insert(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to link collection to chat room: {}", e);
+                error::ErrorInternalServerError("Failed to link collection to chat room")
             })?;
 
             c.id
         };
 
-        // Delete existing permission values for this collection
         permission_values::Entity::delete_many()
             .filter(permission_values::Column::CollectionId.eq(collection_id))
             .exec(db)
@@ -7074,1386 +10909,1471 @@ async fn save_forum_permissions(
                 error::ErrorInternalServerError("Failed to update permissions")
             })?;
 
-        // Insert new permission values
-        for (perm_id, value_str) in group_perms {
-            let flag = match value_str.as_str() {
-                "yes" => Flag::YES,
-                "no" => Flag::NO,
-                "never" => Flag::NEVER,
-                _ => continue, // Skip "default" values
-            };
-
-            let pv = permission_values::ActiveModel {
-                permission_id: Set(*perm_id),
+        for (perm_id, flag) in flags {
+            permission_values::ActiveModel {
+                permission_id: Set(perm_id),
                 collection_id: Set(collection_id),
                 value: Set(flag),
-            };
-
-            let _ = pv.insert(db).await;
+            }
+            .insert(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to insert permission value: {}", e);
+                error::ErrorInternalServerError("Failed to update permissions")
+            })?;
         }
     }
 
-    // Log moderation action
+    let permissions_after = audit_snapshot(audit::chat_room(db, room_id)).await?;
+    audit_permission_change(
+        db,
+        moderator_id,
+        audit::Change::ChatRoomPermissions,
+        room_id,
+        &permissions_before,
+        &permissions_after,
+    )
+    .await?;
+
     log_moderation_action(
         db,
         moderator_id,
-        "update_forum_permissions",
-        "forum",
-        forum_id,
-        Some(&forum.label),
+        "update_chat_room_permissions",
+        "chat_room",
+        room_id,
+        Some(&room.title),
     )
     .await?;
 
     log::info!(
-        "Forum {} permissions updated by user {}",
-        forum_id,
-        moderator_id
-    );
-
-    // Reload forum permissions cache so changes take effect immediately
-    if let Err(e) = crate::permission::reload_forum_permissions().await {
-        log::error!("Failed to reload forum permissions cache: {}", e);
-        // Continue anyway - changes are saved, just need server restart
-    }
-
-    Ok(HttpResponse::SeeOther()
-        .append_header((
-            "Location",
-            format!("/admin/forums/{}/permissions", forum_id),
-        ))
-        .finish())
-}
-
-// =============================================================================
-// Forum Moderators Management
-// =============================================================================
-
-struct ModeratorDisplay {
-    user_id: i32,
-    username: String,
-    created_at: chrono::NaiveDateTime,
-}
-
-/// GET /admin/forums/{id}/moderators - Redirect to permissions page (moderators are now integrated there)
-#[get("/admin/forums/{id}/moderators")]
-async fn view_forum_moderators(
-    client: ClientCtx,
-    path: web::Path<i32>,
-) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
-    let forum_id = path.into_inner();
-
-    // Redirect to the permissions page which now includes moderators section
-    Ok(HttpResponse::SeeOther()
-        .append_header((
-            "Location",
-            format!("/admin/forums/{}/permissions", forum_id),
-        ))
-        .finish())
-}
-
-async fn get_forum_moderators_with_details(forum_id: i32) -> Result<Vec<ModeratorDisplay>, Error> {
-    use sea_orm::{DbBackend, FromQueryResult, Statement};
-
-    let db = get_db_pool();
-
-    #[derive(Debug, FromQueryResult)]
-    struct ModeratorRow {
-        user_id: i32,
-        username: Option<String>,
-        created_at: chrono::NaiveDateTime,
+        "Chat room {} permissions updated by user {}",
+        room_id,
+        moderator_id
+    );
+
+    // Reload so changes apply to connected chat users immediately
+    if let Err(e) = client
+        .get_permissions()
+        .reload_chat_room_permissions()
+        .await
+    {
+        log::error!("Failed to reload chat room permissions cache: {}", e);
     }
+    crate::cache::invalidate(CacheScope::Permissions).await;
 
-    let sql = r#"
-        SELECT fm.user_id, un.name as username, fm.created_at
-        FROM forum_moderators fm
-        LEFT JOIN user_names un ON un.user_id = fm.user_id
-        WHERE fm.forum_id = $1
-        ORDER BY fm.created_at DESC
-    "#;
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/admin/chat-rooms/{}/permissions", room_id),
+        ))
+        .finish())
+}
 
-    let rows = ModeratorRow::find_by_statement(Statement::from_sql_and_values(
-        DbBackend::Postgres,
-        sql,
-        [forum_id.into()],
-    ))
-    .all(db)
-    .await
-    .map_err(|e| {
-        log::error!("Failed to fetch forum moderators: {}", e);
-        error::ErrorInternalServerError("Database error")
-    })?;
+// ============================================================================
+// Thread Permissions
+// ============================================================================
 
-    Ok(rows
-        .into_iter()
-        .map(|r| ModeratorDisplay {
-            user_id: r.user_id,
-            username: r.username.unwrap_or_else(|| "Unknown".to_string()),
-            created_at: r.created_at,
-        })
-        .collect())
+#[derive(Template)]
+#[template(path = "admin/thread_permissions.html")]
+struct ThreadPermissionsTemplate {
+    client: ClientCtx,
+    thread: threads::Model,
+    forum: forums::Model,
+    groups: Vec<ForumPermGroupInfo>,
+    permissions: Vec<ForumPermissionRow>,
 }
 
+/// Form for updating thread permissions
 #[derive(Deserialize)]
-struct AddModeratorForm {
+struct ThreadPermissionsForm {
     csrf_token: String,
-    username: String,
+    /// Map of "perm_{permission_id}_{group_id}" -> value
+    #[serde(flatten)]
+    permissions: std::collections::HashMap<String, String>,
 }
 
-/// POST /admin/forums/{id}/moderators/add - Add a forum moderator
-#[post("/admin/forums/{id}/moderators/add")]
-async fn add_forum_moderator(
-    client: ClientCtx,
-    session: actix_session::Session,
-    path: web::Path<i32>,
-    form: web::Form<AddModeratorForm>,
-) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
-    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
-
-    let forum_id = path.into_inner();
-    let db = get_db_pool();
-
-    // Verify forum exists
-    let _forum = forums::Entity::find_by_id(forum_id)
-        .one(db)
+/// Permissions which can be overridden per thread
+async fn fetch_thread_permissions(
+    db: &DatabaseConnection,
+) -> Result<Vec<permissions::Model>, Error> {
+    permissions::Entity::find()
+        .filter(
+            sea_orm::Condition::any()
+                .add(permissions::Column::Label.eq("thread.view"))
+                .add(permissions::Column::Label.starts_with("post.")),
+        )
+        .order_by_asc(permissions::Column::Sort)
+        .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forum: {}", e);
+            log::error!("Failed to fetch thread permissions: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
+        })
+}
 
-    // Look up user by username
-    let user = user_names::Entity::find()
-        .filter(user_names::Column::Name.eq(form.username.trim()))
-        .one(db)
+/// Map of group_id -> collection_id for a thread's permission overrides
+async fn fetch_thread_collections(
+    db: &DatabaseConnection,
+    thread_id: i32,
+) -> Result<std::collections::HashMap<i32, i32>, Error> {
+    let collections = thread_permissions::Entity::find()
+        .filter(thread_permissions::Column::ThreadId.eq(thread_id))
+        .find_with_related(permission_collections::Entity)
+        .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to look up user: {}", e);
+            log::error!("Failed to fetch thread permissions: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    let user = match user {
-        Some(u) => u,
-        None => {
-            return Ok(HttpResponse::SeeOther()
-                .append_header((
-                    "Location",
-                    format!(
-                        "/admin/forums/{}/permissions?mod_error=user_not_found",
-                        forum_id
-                    ),
-                ))
-                .finish());
-        }
-    };
+    Ok(collections
+        .into_iter()
+        .flat_map(|(_, collections)| collections)
+        .filter_map(|c| c.group_id.map(|gid| (gid, c.id)))
+        .collect())
+}
 
-    // Check if already a moderator
-    let existing = forum_moderators::Entity::find()
-        .filter(forum_moderators::Column::ForumId.eq(forum_id))
-        .filter(forum_moderators::Column::UserId.eq(user.user_id))
+/// A thread and its forum, for the thread permissions page
+async fn find_thread_and_forum(
+    db: &DatabaseConnection,
+    thread_id: i32,
+) -> Result<(threads::Model, forums::Model), Error> {
+    let thread = threads::Entity::find_by_id(thread_id)
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to check existing moderator: {}", e);
+            log::error!("Failed to fetch thread: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?;
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Thread not found"))?;
 
-    if existing.is_some() {
-        return Ok(HttpResponse::SeeOther()
-            .append_header((
-                "Location",
-                format!(
-                    "/admin/forums/{}/permissions?mod_error=already_moderator",
-                    forum_id
-                ),
-            ))
-            .finish());
-    }
+    let forum = forums::Entity::find_by_id(thread.forum_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forum: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found"))?;
 
-    // Add moderator
-    let new_mod = forum_moderators::ActiveModel {
-        forum_id: sea_orm::ActiveValue::Set(forum_id),
-        user_id: sea_orm::ActiveValue::Set(user.user_id),
-        created_at: sea_orm::ActiveValue::Set(chrono::Utc::now().naive_utc()),
-        ..Default::default()
-    };
+    Ok((thread, forum))
+}
 
-    forum_moderators::Entity::insert(new_mod)
-        .exec(db)
+/// GET /admin/threads/{id}/permissions - View/edit thread permissions
+#[get("/admin/threads/{id}/permissions")]
+async fn view_thread_permissions(
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.permissions.manage")?;
+
+    let db = get_db_pool();
+    let thread_id = path.into_inner();
+
+    let (thread, forum) = find_thread_and_forum(db, thread_id).await?;
+
+    let all_groups = groups::Entity::find()
+        .order_by_asc(groups::Column::Id)
+        .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to add moderator: {}", e);
+            log::error!("Failed to fetch groups: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    log::info!(
-        "User {} added {} as moderator for forum {}",
-        client.get_id().unwrap_or(0),
-        user.user_id,
-        forum_id
-    );
+    let thread_permissions = fetch_thread_permissions(db).await?;
+    let group_to_collection = fetch_thread_collections(db, thread_id).await?;
 
-    // Reload permissions cache
-    if let Err(e) = crate::permission::reload_forum_permissions().await {
-        log::error!("Failed to reload permissions cache: {}", e);
+    // Build map: (group_id, permission_id) -> value_string
+    let mut value_map: std::collections::HashMap<(i32, i32), String> =
+        std::collections::HashMap::new();
+    for (&group_id, &collection_id) in &group_to_collection {
+        let values = permission_values::Entity::find()
+            .filter(permission_values::Column::CollectionId.eq(collection_id))
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch permission values: {}", e);
+                error::ErrorInternalServerError("Database error")
+            })?;
+
+        for pv in values {
+            let value_str = match pv.value {
+                Flag::YES => "yes",
+                Flag::NO => "no",
+                Flag::NEVER => "never",
+                Flag::DEFAULT => "default",
+            };
+            value_map.insert((group_id, pv.permission_id), value_str.to_string());
+        }
     }
 
-    Ok(HttpResponse::SeeOther()
-        .append_header((
-            "Location",
-            format!("/admin/forums/{}/permissions?mod_success=added", forum_id),
-        ))
-        .finish())
-}
+    let permission_rows = thread_permissions
+        .iter()
+        .map(|p| ForumPermissionRow {
+            id: p.id,
+            label: p.label.clone(),
+            values: all_groups
+                .iter()
+                .map(|group| ForumPermGroupValue {
+                    group_id: group.id,
+                    value: value_map
+                        .get(&(group.id, p.id))
+                        .cloned()
+                        .unwrap_or_else(|| "default".to_string()),
+                })
+                .collect(),
+        })
+        .collect();
 
-#[derive(Deserialize)]
-struct RemoveModeratorForm {
-    csrf_token: String,
-    user_id: i32,
+    Ok(ThreadPermissionsTemplate {
+        client,
+        thread,
+        forum,
+        groups: all_groups
+            .into_iter()
+            .map(|g| ForumPermGroupInfo {
+                id: g.id,
+                label: g.label,
+            })
+            .collect(),
+        permissions: permission_rows,
+    }
+    .to_response())
 }
 
-/// POST /admin/forums/{id}/moderators/remove - Remove a forum moderator
-#[post("/admin/forums/{id}/moderators/remove")]
-async fn remove_forum_moderator(
+/// POST /admin/threads/{id}/permissions - Save thread permissions
+#[post("/admin/threads/{id}/permissions")]
+async fn save_thread_permissions(
     client: ClientCtx,
-    session: actix_session::Session,
+    cookies: actix_session::Session,
     path: web::Path<i32>,
-    form: web::Form<RemoveModeratorForm>,
+    form: web::Form<ThreadPermissionsForm>,
 ) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
-    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+    let moderator_id = client.require_login()?;
+    client.require_permission("admin.permissions.manage")?;
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
 
-    let forum_id = path.into_inner();
     let db = get_db_pool();
+    let thread_id = path.into_inner();
+
+    let (thread, _) = find_thread_and_forum(db, thread_id).await?;
+
+    let permissions_before = audit_snapshot(audit::thread(db, thread_id)).await?;
+
+    let all_groups = groups::Entity::find().all(db).await.map_err(|e| {
+        log::error!("Failed to fetch groups: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    let thread_permission_ids: Vec<i32> = fetch_thread_permissions(db)
+        .await?
+        .into_iter()
+        .map(|p| p.id)
+        .collect();
+    let mut group_to_collection = fetch_thread_collections(db, thread_id).await?;
+
+    for group in &all_groups {
+        // Only thread permissions can be set on a thread; anything else is ignored
+        let flags: Vec<(i32, Flag)> = thread_permission_ids
+            .iter()
+            .filter_map(|perm_id| {
+                let key = format!("perm_{}_{}", perm_id, group.id);
+                let flag = match form.permissions.get(&key).map(String::as_str) {
+                    Some("yes") => Flag::YES,
+                    Some("no") => Flag::NO,
+                    Some("never") => Flag::NEVER,
+                    _ => return None,
+                };
+                Some((*perm_id, flag))
+            })
+            .collect();
+
+        if flags.is_empty() {
+            // All default - delete the group's override if it exists
+            if let Some(collection_id) = group_to_collection.remove(&group.id) {
+                permission_collections::Entity::delete_by_id(collection_id)
+                    .exec(db)
+                    .await
+                    .map_err(|e| {
+                        log::error!("Failed to delete permission collection: {}", e);
+                        error::ErrorInternalServerError("Failed to update permissions")
+                    })?;
+            }
+            continue;
+        }
+
+        let collection_id = if let Some(&cid) = group_to_collection.get(&group.id) {
+            cid
+        } else {
+            let c = permission_collections::ActiveModel {
+                group_id: Set(Some(group.id)),
+                user_id: Set(None),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to create permission collection: {}", e);
+                error::ErrorInternalServerError("Failed to create permission collection")
+            })?;
+
+            thread_permissions::ActiveModel {
+                thread_id: Set(thread_id),
+                collection_id: Set(c.id),
+            }
+            .insert(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to link collection to thread: {}", e);
+                error::ErrorInternalServerError("Failed to link collection to thread")
+            })?;
 
-    // Delete the moderator assignment
-    let result = forum_moderators::Entity::delete_many()
-        .filter(forum_moderators::Column::ForumId.eq(forum_id))
-        .filter(forum_moderators::Column::UserId.eq(form.user_id))
-        .exec(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to remove moderator: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+            c.id
+        };
 
-    if result.rows_affected == 0 {
-        return Ok(HttpResponse::SeeOther()
-            .append_header((
-                "Location",
-                format!("/admin/forums/{}/permissions?mod_error=not_found", forum_id),
-            ))
-            .finish());
+        permission_values::Entity::delete_many()
+            .filter(permission_values::Column::CollectionId.eq(collection_id))
+            .exec(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to delete old permission values: {}", e);
+                error::ErrorInternalServerError("Failed to update permissions")
+            })?;
+
+        for (perm_id, flag) in flags {
+            permission_values::ActiveModel {
+                permission_id: Set(perm_id),
+                collection_id: Set(collection_id),
+                value: Set(flag),
+            }
+            .insert(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to insert permission value: {}", e);
+                error::ErrorInternalServerError("Failed to update permissions")
+            })?;
+        }
     }
 
+    let permissions_after = audit_snapshot(audit::thread(db, thread_id)).await?;
+    audit_permission_change(
+        db,
+        moderator_id,
+        audit::Change::ThreadPermissions,
+        thread_id,
+        &permissions_before,
+        &permissions_after,
+    )
+    .await?;
+
+    log_moderation_action(
+        db,
+        moderator_id,
+        "update_thread_permissions",
+        "thread",
+        thread_id,
+        Some(&thread.title),
+    )
+    .await?;
+
     log::info!(
-        "User {} removed user {} as moderator from forum {}",
-        client.get_id().unwrap_or(0),
-        form.user_id,
-        forum_id
+        "Thread {} permissions updated by user {}",
+        thread_id,
+        moderator_id
     );
 
-    // Reload permissions cache
-    if let Err(e) = crate::permission::reload_forum_permissions().await {
-        log::error!("Failed to reload permissions cache: {}", e);
+    // Reload so changes apply immediately
+    if let Err(e) = client.get_permissions().reload_thread_permissions().await {
+        log::error!("Failed to reload thread permissions cache: {}", e);
     }
+    crate::cache::invalidate(CacheScope::Permissions).await;
 
     Ok(HttpResponse::SeeOther()
         .append_header((
             "Location",
-            format!("/admin/forums/{}/permissions?mod_success=removed", forum_id),
+            format!("/admin/threads/{}/permissions", thread_id),
         ))
         .finish())
 }
 
-// =============================================================================
-// Tag Management
-// =============================================================================
+// ============================================================================
+// Chat Transcripts
+// ============================================================================
 
 #[derive(Template)]
-#[template(path = "admin/tags.html")]
-struct TagsAdminTemplate {
+#[template(path = "admin/chat_transcript.html")]
+struct ChatTranscriptTemplate {
     client: ClientCtx,
-    tags: Vec<TagWithForum>,
+    room: chat_rooms::Model,
+    messages: Vec<crate::web::chat::transcript::TranscriptMessage>,
+    actions: Vec<crate::web::chat::transcript::TranscriptAction>,
+    filter: crate::web::chat::transcript::TranscriptFilter,
+    filter_query: String,
+    page: i64,
+    total_pages: i64,
+    total: i64,
 }
 
-struct TagWithForum {
-    id: i32,
-    name: String,
-    slug: String,
-    color: String,
-    is_global: bool,
-    forum_names: Vec<String>,
-    use_count: i32,
+#[derive(Deserialize)]
+struct ChatTranscriptPageQuery {
+    page: Option<i64>,
 }
 
-/// GET /admin/tags - List all tags
-#[get("/admin/tags")]
-async fn view_tags(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+/// The room a transcript is for. Private rooms and direct messages are only
+/// open to administrators.
+async fn find_transcript_room(
+    client: &ClientCtx,
+    room_id: i32,
+) -> Result<chat_rooms::Model, Error> {
+    client.require_permission("chat.moderate")?;
 
-    let db = get_db_pool();
-
-    // Fetch all tags
-    let tags_raw = tags::Entity::find()
-        .order_by_asc(tags::Column::Name)
-        .all(db)
+    let room = chat_rooms::Entity::find_by_id(room_id)
+        .one(get_db_pool())
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch tags: {}", e);
+            log::error!("Failed to fetch chat room: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?;
-
-    // Fetch all tag_forums associations with forum data
-    let tag_forum_associations: Vec<(tag_forums::Model, Option<forums::Model>)> =
-        tag_forums::Entity::find()
-            .find_also_related(forums::Entity)
-            .all(db)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to fetch tag_forums: {}", e);
-                error::ErrorInternalServerError("Database error")
-            })?;
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Chat room not found"))?;
 
-    // Build a map of tag_id -> Vec<forum_name>
-    let mut tag_forum_map: std::collections::HashMap<i32, Vec<String>> =
-        std::collections::HashMap::new();
-    for (tf, forum_opt) in tag_forum_associations {
-        if let Some(forum) = forum_opt {
-            tag_forum_map
-                .entry(tf.tag_id)
-                .or_default()
-                .push(forum.label);
-        }
+    if room.is_private {
+        client.require_permission("admin.settings")?;
     }
 
-    let tags_list: Vec<TagWithForum> = tags_raw
-        .into_iter()
-        .map(|tag| {
-            let forum_names = tag_forum_map.remove(&tag.id).unwrap_or_default();
-            TagWithForum {
-                id: tag.id,
-                name: tag.name,
-                slug: tag.slug,
-                color: tag.color.unwrap_or_else(|| "#6c757d".to_string()),
-                is_global: tag.is_global,
-                forum_names,
-                use_count: tag.use_count,
-            }
-        })
-        .collect();
-
-    Ok(TagsAdminTemplate {
-        client,
-        tags: tags_list,
-    }
-    .to_response())
+    Ok(room)
 }
 
-#[derive(Template)]
-#[template(path = "admin/tag_form.html")]
-struct TagFormTemplate {
+/// GET /admin/chat/rooms/{id}/transcript - Search a room's chat history
+#[get("/admin/chat/rooms/{id}/transcript")]
+async fn view_chat_transcript(
     client: ClientCtx,
-    tag: Option<tags::Model>,
-    forums: Vec<forums::Model>,
-    selected_forum_ids: Vec<i32>,
-    is_edit: bool,
-}
-
-/// GET /admin/tags/create - Show create tag form
-#[get("/admin/tags/create")]
-async fn view_create_tag_form(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+    path: web::Path<i32>,
+    query: web::Query<ChatTranscriptPageQuery>,
+    filter: web::Query<crate::web::chat::transcript::TranscriptFilter>,
+) -> Result<impl Responder, Error> {
+    use crate::web::chat::transcript::{find_actions, search_messages, PAGE_SIZE};
 
-    let db = get_db_pool();
+    let room = find_transcript_room(&client, path.into_inner()).await?;
+    let filter = filter.into_inner();
+    let page = query.page.unwrap_or(1).max(1);
 
-    let forums_list = forums::Entity::find()
-        .order_by_asc(forums::Column::DisplayOrder)
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch forums: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+    let (messages, total) = search_messages(room.id, &filter, page).await.map_err(|e| {
+        log::error!("Failed to fetch chat transcript: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+    let actions = find_actions(room.id, &filter).await.map_err(|e| {
+        log::error!("Failed to fetch chat moderation log: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
 
-    Ok(TagFormTemplate {
+    Ok(ChatTranscriptTemplate {
         client,
-        tag: None,
-        forums: forums_list,
-        selected_forum_ids: Vec::new(),
-        is_edit: false,
+        room,
+        messages,
+        actions,
+        filter_query: filter.query_string(),
+        filter,
+        page,
+        total_pages: (total + PAGE_SIZE - 1) / PAGE_SIZE,
+        total,
     }
     .to_response())
 }
 
-#[derive(Deserialize)]
-struct TagFormData {
-    csrf_token: String,
-    name: String,
-    color: String,
-    is_global: Option<String>,
-    #[serde(default)]
-    forum_ids: String,
-}
-
-impl TagFormData {
-    /// Parse the comma-separated forum_ids string into a Vec<i32>
-    fn parse_forum_ids(&self) -> Vec<i32> {
-        if self.forum_ids.is_empty() {
-            return Vec::new();
-        }
-        self.forum_ids
-            .split(',')
-            .filter_map(|s| s.trim().parse::<i32>().ok())
-            .collect()
-    }
-}
-
-/// POST /admin/tags/create - Create a new tag
-#[post("/admin/tags/create")]
-async fn create_tag(
+/// GET /admin/chat/rooms/{id}/transcript/export - Download a transcript as plain text
+#[get("/admin/chat/rooms/{id}/transcript/export")]
+async fn export_chat_transcript(
     client: ClientCtx,
-    cookies: actix_session::Session,
-    form: web::Form<TagFormData>,
+    path: web::Path<i32>,
+    filter: web::Query<crate::web::chat::transcript::TranscriptFilter>,
 ) -> Result<impl Responder, Error> {
-    let moderator_id = client.require_login()?;
-    client.require_permission("admin.settings")?;
-
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
-
-    let db = get_db_pool();
-
-    // Validate name
-    let name = form.name.trim().to_string();
-    if name.is_empty() || name.len() > 50 {
-        return Err(error::ErrorBadRequest("Tag name must be 1-50 characters"));
-    }
-
-    // Create slug from name
-    let slug: String = name
-        .to_lowercase()
-        .chars()
-        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
-        .collect::<String>()
-        .split_whitespace()
-        .collect::<Vec<_>>()
-        .join("-");
-
-    if slug.is_empty() {
-        return Err(error::ErrorBadRequest(
-            "Tag name must contain valid characters",
-        ));
-    }
-
-    // Validate color (should be hex color)
-    let color = if form.color.starts_with('#') && form.color.len() == 7 {
-        form.color.clone()
-    } else {
-        "#6c757d".to_string()
-    };
+    use crate::web::chat::transcript::{export_messages, find_actions, to_plain_text};
 
-    // Determine if global
-    let is_global = form.is_global.is_some();
+    let moderator_id = client.require_login()?;
+    let room = find_transcript_room(&client, path.into_inner()).await?;
+    let filter = filter.into_inner();
 
-    // Check for duplicate slug (global tags must have unique slugs)
-    let existing = tags::Entity::find()
-        .filter(tags::Column::Slug.eq(slug.clone()))
-        .one(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to check for duplicate tag: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+    let messages = export_messages(room.id, &filter).await.map_err(|e| {
+        log::error!("Failed to export chat transcript: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+    let actions = find_actions(room.id, &filter).await.map_err(|e| {
+        log::error!("Failed to fetch chat moderation log: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
 
-    if existing.is_some() {
-        return Err(error::ErrorBadRequest(
-            "A tag with this name already exists",
-        ));
-    }
+    // Transcripts can hold private conversations, so exports are logged.
+    log_moderation_action(
+        get_db_pool(),
+        moderator_id,
+        "export_chat_transcript",
+        "chat_room",
+        room.id,
+        Some(&filter.describe()),
+    )
+    .await?;
 
-    // Create the tag
-    let new_tag = tags::ActiveModel {
-        name: Set(name.clone()),
-        slug: Set(slug),
-        color: Set(Some(color)),
-        is_global: Set(is_global),
-        use_count: Set(0),
-        created_at: Set(chrono::Utc::now().naive_utc()),
-        ..Default::default()
-    };
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"chat-room-{}-transcript.txt\"",
+                room.id
+            ),
+        ))
+        .body(to_plain_text(&room.title, &filter, &messages, &actions)))
+}
 
-    let insert_result = tags::Entity::insert(new_tag).exec(db).await.map_err(|e| {
-        log::error!("Failed to create tag: {}", e);
-        error::ErrorInternalServerError("Failed to create tag")
-    })?;
+// ============================================================================
+// Theme Management
+// ============================================================================
 
-    let tag_id = insert_result.last_insert_id;
+#[derive(Template)]
+#[template(path = "admin/themes.html")]
+struct ThemesTemplate {
+    client: ClientCtx,
+    themes_list: Vec<themes::Model>,
+}
 
-    // If not global, create forum associations
-    let forum_ids = form.parse_forum_ids();
-    if !is_global && !forum_ids.is_empty() {
-        for forum_id in &forum_ids {
-            let tag_forum = tag_forums::ActiveModel {
-                tag_id: Set(tag_id),
-                forum_id: Set(*forum_id),
-                ..Default::default()
-            };
-            tag_forums::Entity::insert(tag_forum)
-                .exec(db)
-                .await
-                .map_err(|e| {
-                    log::error!("Failed to create tag_forum association: {}", e);
-                    error::ErrorInternalServerError("Failed to associate tag with forum")
-                })?;
+#[derive(Template)]
+#[template(path = "admin/theme_form.html")]
+struct ThemeFormTemplate {
+    client: ClientCtx,
+    theme: Option<themes::Model>,
+    error: Option<String>,
+    available_parents: Vec<themes::Model>,
+    colors: Vec<crate::theme::ColorVariable>,
+    /// Variable declarations that don't have a color picker
+    other_variables: String,
+}
+
+impl ThemeFormTemplate {
+    fn new(
+        client: ClientCtx,
+        theme: Option<themes::Model>,
+        error: Option<String>,
+        available_parents: Vec<themes::Model>,
+    ) -> Self {
+        let (colors, other_variables) = crate::theme::split_variables(
+            theme
+                .as_ref()
+                .and_then(|t| t.css_variables.as_deref())
+                .unwrap_or_default(),
+        );
+        Self {
+            client,
+            theme,
+            error,
+            available_parents,
+            colors,
+            other_variables,
         }
     }
+}
 
-    log_moderation_action(db, moderator_id, "create_tag", "tag", tag_id, Some(&name)).await?;
-
-    log::info!("Tag '{}' created by user {}", name, moderator_id);
-
-    Ok(HttpResponse::SeeOther()
-        .append_header(("Location", "/admin/tags"))
-        .finish())
+#[derive(Template)]
+#[template(path = "admin/theme_revisions.html")]
+struct ThemeRevisionsTemplate {
+    client: ClientCtx,
+    theme: themes::Model,
+    revisions: Vec<crate::orm::theme_revisions::Model>,
+}
+
+/// The `css_variables` a theme form describes: its color pickers, then the
+/// other declarations as written.
+fn theme_variables_from_form(
+    form: &std::collections::HashMap<String, String>,
+) -> Result<Option<String>, Error> {
+    let mut colors = Vec::new();
+    for (name, label, _) in crate::theme::COLOR_VARIABLES {
+        if let Some(value) = form.get(&format!("color:{}", name)) {
+            if !crate::theme::is_safe_value(value) {
+                return Err(error::ErrorBadRequest(format!(
+                    "{} is not a valid color",
+                    label
+                )));
+            }
+            colors.push((*name, value.as_str()));
+        }
+    }
+    Ok(crate::theme::compose_variables(
+        &colors,
+        form.get("css_variables").map_or("", String::as_str),
+    ))
 }
 
-/// GET /admin/tags/{id}/edit - Show edit tag form
-#[get("/admin/tags/{id}/edit")]
-async fn view_edit_tag(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
+/// GET /admin/themes - List all themes
+#[get("/admin/themes")]
+async fn view_themes(client: ClientCtx) -> Result<impl Responder, Error> {
     client.require_permission("admin.settings")?;
 
     let db = get_db_pool();
-    let tag_id = path.into_inner();
-
-    let tag = tags::Entity::find_by_id(tag_id)
-        .one(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch tag: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| error::ErrorNotFound("Tag not found"))?;
 
-    let forums_list = forums::Entity::find()
-        .order_by_asc(forums::Column::DisplayOrder)
+    let themes_list = themes::Entity::find()
+        .order_by_asc(themes::Column::DisplayOrder)
         .all(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch forums: {}", e);
+            log::error!("Failed to fetch themes: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    // Fetch the forum IDs associated with this tag
-    let selected_forum_ids: Vec<i32> = tag_forums::Entity::find()
-        .filter(tag_forums::Column::TagId.eq(tag_id))
-        .all(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch tag_forums: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?
-        .into_iter()
-        .map(|tf| tf.forum_id)
-        .collect();
-
-    Ok(TagFormTemplate {
+    Ok(ThemesTemplate {
         client,
-        tag: Some(tag),
-        forums: forums_list,
-        selected_forum_ids,
-        is_edit: true,
+        themes_list,
     }
     .to_response())
 }
 
-/// POST /admin/tags/{id} - Update a tag
-#[post("/admin/tags/{id}")]
-async fn update_tag(
+/// GET /admin/themes/new - Show form to create new theme
+#[get("/admin/themes/new")]
+async fn view_create_theme_form(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    Ok(ThemeFormTemplate::new(
+        client,
+        None,
+        None,
+        crate::theme::get_available_parents(None),
+    )
+    .to_response())
+}
+
+/// POST /admin/themes - Create a new theme
+#[post("/admin/themes")]
+async fn create_theme(
     client: ClientCtx,
     cookies: actix_session::Session,
-    path: web::Path<i32>,
-    form: web::Form<TagFormData>,
+    form: web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<impl Responder, Error> {
+    use sea_orm::TransactionTrait;
+
     let moderator_id = client.require_login()?;
     client.require_permission("admin.settings")?;
 
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+    // Validate CSRF
+    let csrf_token = form
+        .get("csrf_token")
+        .ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, csrf_token)?;
 
     let db = get_db_pool();
-    let tag_id = path.into_inner();
 
-    let tag = tags::Entity::find_by_id(tag_id)
-        .one(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch tag: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| error::ErrorNotFound("Tag not found"))?;
+    // Get form values
+    let name = form
+        .get("name")
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| error::ErrorBadRequest("Name is required"))?;
 
-    // Validate name
-    let name = form.name.trim().to_string();
-    if name.is_empty() || name.len() > 50 {
-        return Err(error::ErrorBadRequest("Tag name must be 1-50 characters"));
-    }
+    let slug = form
+        .get("slug")
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| error::ErrorBadRequest("Slug is required"))?;
 
-    // Create slug from name
-    let slug: String = name
-        .to_lowercase()
+    // Validate slug format (lowercase letters, numbers, hyphens only)
+    if !slug
         .chars()
-        .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == ' ')
-        .collect::<String>()
-        .split_whitespace()
-        .collect::<Vec<_>>()
-        .join("-");
-
-    if slug.is_empty() {
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
         return Err(error::ErrorBadRequest(
-            "Tag name must contain valid characters",
+            "Slug must contain only lowercase letters, numbers, and hyphens",
         ));
     }
 
-    // Validate color
-    let color = if form.color.starts_with('#') && form.color.len() == 7 {
-        form.color.clone()
-    } else {
-        "#6c757d".to_string()
-    };
-
-    // Determine if global
-    let is_global = form.is_global.is_some();
-
-    // Check for duplicate slug (excluding current tag)
-    let existing = tags::Entity::find()
-        .filter(tags::Column::Slug.eq(slug.clone()))
-        .filter(tags::Column::Id.ne(tag_id))
+    // Check for duplicate slug
+    let existing = themes::Entity::find()
+        .filter(themes::Column::Slug.eq(slug.as_str()))
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to check for duplicate tag: {}", e);
+            log::error!("Failed to check slug: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
     if existing.is_some() {
-        return Err(error::ErrorBadRequest(
-            "A tag with this name already exists",
-        ));
+        return Ok(ThemeFormTemplate::new(
+            client,
+            None,
+            Some("A theme with this slug already exists".to_string()),
+            crate::theme::get_available_parents(None),
+        )
+        .to_response());
     }
 
-    // Update the tag
-    let mut active_tag: tags::ActiveModel = tag.into();
-    active_tag.name = Set(name.clone());
-    active_tag.slug = Set(slug);
-    active_tag.color = Set(Some(color));
-    active_tag.is_global = Set(is_global);
+    let description = form.get("description").cloned();
+    let is_dark = form.contains_key("is_dark");
+    let is_active = form.contains_key("is_active");
+    let display_order: i32 = form
+        .get("display_order")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(10);
+
+    let css_variables = theme_variables_from_form(&form)?;
+    let css_custom = form.get("css_custom").filter(|s| !s.is_empty()).cloned();
+
+    // Parse parent_id (empty string means no parent)
+    let parent_id = form
+        .get("parent_id")
+        .filter(|s| !s.is_empty())
+        .and_then(|s| s.parse::<i32>().ok());
+
+    // Create the theme
+    let new_theme = themes::ActiveModel {
+        slug: Set(slug.to_string()),
+        name: Set(name.to_string()),
+        description: Set(description),
+        is_system: Set(false),
+        is_dark: Set(is_dark),
+        is_active: Set(is_active),
+        display_order: Set(display_order),
+        css_variables: Set(css_variables),
+        css_custom: Set(css_custom),
+        parent_id: Set(parent_id),
+        created_at: Set(chrono::Utc::now().into()),
+        updated_at: Set(chrono::Utc::now().into()),
+        created_by: Set(Some(moderator_id)),
+        ..Default::default()
+    };
+
+    let txn = db.begin().await.map_err(|e| {
+        log::error!("Failed to start transaction: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+    let new_theme = new_theme.insert(&txn).await.map_err(|e| {
+        log::error!("Failed to create theme: {}", e);
+        error::ErrorInternalServerError("Failed to create theme")
+    })?;
+    crate::theme::record_revision(&txn, &new_theme, Some(moderator_id))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to record theme revision: {}", e);
+            error::ErrorInternalServerError("Failed to create theme")
+        })?;
+    txn.commit()
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    // Reload theme cache
+    crate::theme::reload_cache().await;
+
+    log::info!("Theme '{}' created by user {}", slug, moderator_id);
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/themes"))
+        .finish())
+}
 
-    active_tag.update(db).await.map_err(|e| {
-        log::error!("Failed to update tag: {}", e);
-        error::ErrorInternalServerError("Failed to update tag")
-    })?;
+/// GET /admin/themes/{id}/edit - Show form to edit theme
+#[get("/admin/themes/{id}/edit")]
+async fn view_edit_theme(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
 
-    // Update forum associations: delete old ones and insert new ones
-    tag_forums::Entity::delete_many()
-        .filter(tag_forums::Column::TagId.eq(tag_id))
-        .exec(db)
+    let db = get_db_pool();
+    let theme_id = path.into_inner();
+
+    let theme = themes::Entity::find_by_id(theme_id)
+        .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to delete old tag_forums: {}", e);
+            log::error!("Failed to fetch theme: {}", e);
             error::ErrorInternalServerError("Database error")
-        })?;
-
-    // If not global, create new forum associations
-    let forum_ids = form.parse_forum_ids();
-    if !is_global && !forum_ids.is_empty() {
-        for forum_id in &forum_ids {
-            let tag_forum = tag_forums::ActiveModel {
-                tag_id: Set(tag_id),
-                forum_id: Set(*forum_id),
-                ..Default::default()
-            };
-            tag_forums::Entity::insert(tag_forum)
-                .exec(db)
-                .await
-                .map_err(|e| {
-                    log::error!("Failed to create tag_forum association: {}", e);
-                    error::ErrorInternalServerError("Failed to associate tag with forum")
-                })?;
-        }
-    }
-
-    log_moderation_action(db, moderator_id, "update_tag", "tag", tag_id, Some(&name)).await?;
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Theme not found"))?;
 
-    log::info!("Tag {} updated by user {}", tag_id, moderator_id);
+    // Get available parents, excluding self and descendants to prevent cycles
+    let available_parents = crate::theme::get_available_parents(Some(theme_id));
 
-    Ok(HttpResponse::SeeOther()
-        .append_header(("Location", "/admin/tags"))
-        .finish())
+    Ok(ThemeFormTemplate::new(client, Some(theme), None, available_parents).to_response())
 }
 
-/// POST /admin/tags/{id}/delete - Delete a tag
-#[post("/admin/tags/{id}/delete")]
-async fn delete_tag(
+/// POST /admin/themes/{id} - Update a theme
+#[post("/admin/themes/{id}")]
+async fn update_theme(
     client: ClientCtx,
     cookies: actix_session::Session,
     path: web::Path<i32>,
-    form: web::Form<ModerationForm>,
+    form: web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<impl Responder, Error> {
+    use sea_orm::TransactionTrait;
+
     let moderator_id = client.require_login()?;
     client.require_permission("admin.settings")?;
 
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+    // Validate CSRF
+    let csrf_token = form
+        .get("csrf_token")
+        .ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, csrf_token)?;
 
     let db = get_db_pool();
-    let tag_id = path.into_inner();
+    let theme_id = path.into_inner();
 
-    let tag = tags::Entity::find_by_id(tag_id)
+    let existing = themes::Entity::find_by_id(theme_id)
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch tag: {}", e);
+            log::error!("Failed to fetch theme: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Tag not found"))?;
-
-    let tag_name = tag.name.clone();
-
-    // Delete the tag (thread_tags entries will cascade delete)
-    tags::Entity::delete_by_id(tag_id)
-        .exec(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to delete tag: {}", e);
-            error::ErrorInternalServerError("Failed to delete tag")
-        })?;
-
-    log_moderation_action(
-        db,
-        moderator_id,
-        "delete_tag",
-        "tag",
-        tag_id,
-        Some(&tag_name),
-    )
-    .await?;
-
-    log::info!(
-        "Tag {} ('{}') deleted by user {}",
-        tag_id,
-        tag_name,
-        moderator_id
-    );
-
-    Ok(HttpResponse::SeeOther()
-        .append_header(("Location", "/admin/tags"))
-        .finish())
-}
+        .ok_or_else(|| error::ErrorNotFound("Theme not found"))?;
 
-// ============================================================================
-// Chat Room Management
-// ============================================================================
+    // Get form values
+    let name = form
+        .get("name")
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| error::ErrorBadRequest("Name is required"))?;
 
-#[derive(Template)]
-#[template(path = "admin/chat_rooms.html")]
-struct ChatRoomsTemplate {
-    client: ClientCtx,
-    rooms: Vec<chat_rooms::Model>,
-}
+    let description = form.get("description").cloned();
+    let is_dark = form.contains_key("is_dark");
+    let is_active = form.contains_key("is_active");
+    let display_order: i32 = form
+        .get("display_order")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(existing.display_order);
 
-#[derive(Template)]
-#[template(path = "admin/chat_room_form.html")]
-struct ChatRoomFormTemplate {
-    client: ClientCtx,
-    room: Option<chat_rooms::Model>,
-    error: Option<String>,
-}
+    let css_variables = theme_variables_from_form(&form)?;
+    let css_custom = form.get("css_custom").filter(|s| !s.is_empty()).cloned();
 
-#[derive(Deserialize)]
-struct ChatRoomForm {
-    csrf_token: String,
-    title: String,
-    description: Option<String>,
-    display_order: i16,
-    min_posts_required: i32,
-    min_account_age_hours: i32,
-    is_staff_only: Option<String>,
-}
+    // Parse parent_id (empty string means no parent)
+    let parent_id = form
+        .get("parent_id")
+        .filter(|s| !s.is_empty())
+        .and_then(|s| s.parse::<i32>().ok());
 
-/// GET /admin/chat-rooms - List all chat rooms
-#[get("/admin/chat-rooms")]
-async fn view_chat_rooms(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+    // Changing the CSS saves it as the theme's next version
+    let css_changed = css_variables != existing.css_variables || css_custom != existing.css_custom;
+    let version = existing.version + i32::from(css_changed);
 
-    let db = get_db_pool();
+    // Update the theme
+    let mut theme: themes::ActiveModel = existing.into();
+    theme.name = Set(name.to_string());
+    theme.description = Set(description);
+    theme.is_dark = Set(is_dark);
+    theme.is_active = Set(is_active);
+    theme.display_order = Set(display_order);
+    theme.css_variables = Set(css_variables);
+    theme.css_custom = Set(css_custom);
+    theme.parent_id = Set(parent_id);
+    theme.version = Set(version);
+    theme.updated_at = Set(chrono::Utc::now().into());
 
-    let rooms = chat_rooms::Entity::find()
-        .order_by_asc(chat_rooms::Column::DisplayOrder)
-        .all(db)
+    let txn = db.begin().await.map_err(|e| {
+        log::error!("Failed to start transaction: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+    let theme = theme.update(&txn).await.map_err(|e| {
+        log::error!("Failed to update theme: {}", e);
+        error::ErrorInternalServerError("Failed to update theme")
+    })?;
+    if css_changed {
+        crate::theme::record_revision(&txn, &theme, Some(moderator_id))
+            .await
+            .map_err(|e| {
+                log::error!("Failed to record theme revision: {}", e);
+                error::ErrorInternalServerError("Failed to update theme")
+            })?;
+    }
+    txn.commit()
         .await
-        .map_err(|e| {
-            log::error!("Failed to fetch chat rooms: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+        .map_err(error::ErrorInternalServerError)?;
 
-    Ok(ChatRoomsTemplate { client, rooms }.to_response())
-}
+    // Reload theme cache
+    crate::theme::reload_cache().await;
 
-/// GET /admin/chat-rooms/new - Show form to create new chat room
-#[get("/admin/chat-rooms/new")]
-async fn view_create_chat_room_form(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+    log::info!("Theme {} updated by user {}", theme_id, moderator_id);
 
-    Ok(ChatRoomFormTemplate {
-        client,
-        room: None,
-        error: None,
-    }
-    .to_response())
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/themes"))
+        .finish())
 }
 
-/// POST /admin/chat-rooms - Create a new chat room
-#[post("/admin/chat-rooms")]
-async fn create_chat_room(
+/// POST /admin/themes/{id}/delete - Delete a theme
+#[post("/admin/themes/{id}/delete")]
+async fn delete_theme(
     client: ClientCtx,
     cookies: actix_session::Session,
-    form: web::Form<ChatRoomForm>,
+    path: web::Path<i32>,
+    form: web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<impl Responder, Error> {
     let moderator_id = client.require_login()?;
     client.require_permission("admin.settings")?;
 
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+    // Validate CSRF
+    let csrf_token = form
+        .get("csrf_token")
+        .ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, csrf_token)?;
 
     let db = get_db_pool();
+    let theme_id = path.into_inner();
 
-    // Validate input
-    if form.title.trim().is_empty() {
-        return Ok(ChatRoomFormTemplate {
-            client,
-            room: None,
-            error: Some("Title is required".to_string()),
-        }
-        .to_response());
+    let theme = themes::Entity::find_by_id(theme_id)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch theme: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Theme not found"))?;
+
+    // Cannot delete system themes
+    if theme.is_system {
+        return Err(error::ErrorForbidden("Cannot delete system themes"));
     }
 
-    let new_room = chat_rooms::ActiveModel {
-        title: Set(form.title.trim().to_string()),
-        description: Set(form.description.clone().filter(|s| !s.trim().is_empty())),
-        display_order: Set(form.display_order),
-        min_posts_required: Set(form.min_posts_required),
-        min_account_age_hours: Set(form.min_account_age_hours),
-        is_staff_only: Set(form.is_staff_only.is_some()),
-        ..Default::default()
-    };
+    let theme_name = theme.name.clone();
 
-    let room = new_room.insert(db).await.map_err(|e| {
-        log::error!("Failed to create chat room: {}", e);
-        error::ErrorInternalServerError("Failed to create chat room")
-    })?;
+    themes::Entity::delete_by_id(theme_id)
+        .exec(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete theme: {}", e);
+            error::ErrorInternalServerError("Failed to delete theme")
+        })?;
 
-    log_moderation_action(
-        db,
-        moderator_id,
-        "create_chat_room",
-        "chat_room",
-        room.id,
-        Some(&room.title),
-    )
-    .await?;
+    // Reload theme cache
+    crate::theme::reload_cache().await;
 
     log::info!(
-        "Chat room {} ('{}') created by user {}",
-        room.id,
-        room.title,
+        "Theme {} ('{}') deleted by user {}",
+        theme_id,
+        theme_name,
         moderator_id
     );
 
     Ok(HttpResponse::SeeOther()
-        .insert_header(("Location", "/admin/chat-rooms"))
+        .append_header(("Location", "/admin/themes"))
         .finish())
 }
 
-/// GET /admin/chat-rooms/{id}/edit - Show form to edit chat room
-#[get("/admin/chat-rooms/{id}/edit")]
-async fn view_edit_chat_room(
+/// GET /admin/themes/{id}/revisions - Saved versions of a theme's CSS
+#[get("/admin/themes/{id}/revisions")]
+async fn view_theme_revisions(
     client: ClientCtx,
     path: web::Path<i32>,
 ) -> Result<impl Responder, Error> {
     client.require_permission("admin.settings")?;
 
-    let id = path.into_inner();
-    let db = get_db_pool();
-
-    let room = chat_rooms::Entity::find_by_id(id)
-        .one(db)
+    let theme = themes::Entity::find_by_id(path.into_inner())
+        .one(get_db_pool())
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch chat room: {}", e);
+            log::error!("Failed to fetch theme: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Chat room not found"))?;
+        .ok_or_else(|| error::ErrorNotFound("Theme not found"))?;
 
-    Ok(ChatRoomFormTemplate {
+    let revisions = crate::theme::get_revisions(theme.id).await.map_err(|e| {
+        log::error!("Failed to fetch theme revisions: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    Ok(ThemeRevisionsTemplate {
         client,
-        room: Some(room),
-        error: None,
+        theme,
+        revisions,
     }
     .to_response())
 }
 
-/// POST /admin/chat-rooms/{id} - Update a chat room
-#[post("/admin/chat-rooms/{id}")]
-async fn update_chat_room(
+/// POST /admin/themes/{id}/revisions/{version}/restore - Save an earlier
+/// version's CSS as the theme's next version
+#[post("/admin/themes/{id}/revisions/{version}/restore")]
+async fn restore_theme_revision(
     client: ClientCtx,
     cookies: actix_session::Session,
-    path: web::Path<i32>,
-    form: web::Form<ChatRoomForm>,
+    path: web::Path<(i32, i32)>,
+    form: web::Form<std::collections::HashMap<String, String>>,
 ) -> Result<impl Responder, Error> {
+    use crate::orm::theme_revisions;
+    use sea_orm::TransactionTrait;
+
     let moderator_id = client.require_login()?;
     client.require_permission("admin.settings")?;
 
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+    // Validate CSRF
+    let csrf_token = form
+        .get("csrf_token")
+        .ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, csrf_token)?;
 
-    let id = path.into_inner();
     let db = get_db_pool();
+    let (theme_id, version) = path.into_inner();
 
-    // Fetch existing room
-    let existing = chat_rooms::Entity::find_by_id(id)
+    let existing = themes::Entity::find_by_id(theme_id)
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch chat room: {}", e);
+            log::error!("Failed to fetch theme: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Chat room not found"))?;
-
-    // Validate input
-    if form.title.trim().is_empty() {
-        return Ok(ChatRoomFormTemplate {
-            client,
-            room: Some(existing),
-            error: Some("Title is required".to_string()),
-        }
-        .to_response());
-    }
-
-    let mut updated: chat_rooms::ActiveModel = existing.into();
-    updated.title = Set(form.title.trim().to_string());
-    updated.description = Set(form.description.clone().filter(|s| !s.trim().is_empty()));
-    updated.display_order = Set(form.display_order);
-    updated.min_posts_required = Set(form.min_posts_required);
-    updated.min_account_age_hours = Set(form.min_account_age_hours);
-    updated.is_staff_only = Set(form.is_staff_only.is_some());
-
-    updated.update(db).await.map_err(|e| {
-        log::error!("Failed to update chat room: {}", e);
-        error::ErrorInternalServerError("Failed to update chat room")
-    })?;
-
-    log_moderation_action(
-        db,
-        moderator_id,
-        "update_chat_room",
-        "chat_room",
-        id,
-        Some(&form.title),
-    )
-    .await?;
-
-    log::info!("Chat room {} updated by user {}", id, moderator_id);
-
-    Ok(HttpResponse::SeeOther()
-        .insert_header(("Location", "/admin/chat-rooms"))
-        .finish())
-}
-
-/// POST /admin/chat-rooms/{id}/delete - Delete a chat room
-#[post("/admin/chat-rooms/{id}/delete")]
-async fn delete_chat_room(
-    client: ClientCtx,
-    cookies: actix_session::Session,
-    path: web::Path<i32>,
-    form: web::Form<ModerationForm>,
-) -> Result<impl Responder, Error> {
-    let moderator_id = client.require_login()?;
-    client.require_permission("admin.settings")?;
-
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
-
-    let db = get_db_pool();
-    let room_id = path.into_inner();
+        .ok_or_else(|| error::ErrorNotFound("Theme not found"))?;
 
-    let room = chat_rooms::Entity::find_by_id(room_id)
+    let revision = theme_revisions::Entity::find()
+        .filter(theme_revisions::Column::ThemeId.eq(theme_id))
+        .filter(theme_revisions::Column::Version.eq(version))
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch chat room: {}", e);
+            log::error!("Failed to fetch theme revision: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Chat room not found"))?;
+        .ok_or_else(|| error::ErrorNotFound("Revision not found"))?;
 
-    let room_title = room.title.clone();
+    let next_version = existing.version + 1;
+    let mut theme: themes::ActiveModel = existing.into();
+    theme.css_variables = Set(revision.css_variables);
+    theme.css_custom = Set(revision.css_custom);
+    theme.version = Set(next_version);
+    theme.updated_at = Set(chrono::Utc::now().into());
 
-    // Delete the chat room (messages will remain but room reference will be gone)
-    chat_rooms::Entity::delete_by_id(room_id)
-        .exec(db)
+    let txn = db.begin().await.map_err(|e| {
+        log::error!("Failed to start transaction: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+    let theme = theme.update(&txn).await.map_err(|e| {
+        log::error!("Failed to restore theme: {}", e);
+        error::ErrorInternalServerError("Failed to restore theme")
+    })?;
+    crate::theme::record_revision(&txn, &theme, Some(moderator_id))
         .await
         .map_err(|e| {
-            log::error!("Failed to delete chat room: {}", e);
-            error::ErrorInternalServerError("Failed to delete chat room")
+            log::error!("Failed to record theme revision: {}", e);
+            error::ErrorInternalServerError("Failed to restore theme")
         })?;
+    txn.commit()
+        .await
+        .map_err(error::ErrorInternalServerError)?;
 
-    log_moderation_action(
-        db,
-        moderator_id,
-        "delete_chat_room",
-        "chat_room",
-        room_id,
-        Some(&room_title),
-    )
-    .await?;
+    crate::theme::reload_cache().await;
 
     log::info!(
-        "Chat room {} ('{}') deleted by user {}",
-        room_id,
-        room_title,
+        "Theme {} restored to version {} by user {}",
+        theme_id,
+        version,
         moderator_id
     );
 
     Ok(HttpResponse::SeeOther()
-        .append_header(("Location", "/admin/chat-rooms"))
+        .append_header(("Location", format!("/admin/themes/{}/revisions", theme_id)))
         .finish())
 }
 
 // ============================================================================
-// Theme Management
+// Storage Usage Report
 // ============================================================================
 
 #[derive(Template)]
-#[template(path = "admin/themes.html")]
-struct ThemesTemplate {
-    client: ClientCtx,
-    themes_list: Vec<themes::Model>,
-}
-
-#[derive(Template)]
-#[template(path = "admin/theme_form.html")]
-struct ThemeFormTemplate {
+#[template(path = "admin/storage.html")]
+struct StorageUsageTemplate {
     client: ClientCtx,
-    theme: Option<themes::Model>,
-    error: Option<String>,
-    available_parents: Vec<themes::Model>,
+    consumers: Vec<crate::filesystem::quota::StorageConsumer>,
 }
 
-/// GET /admin/themes - List all themes
-#[get("/admin/themes")]
-async fn view_themes(client: ClientCtx) -> Result<impl Responder, Error> {
+/// GET /admin/storage - Members using the most attachment storage
+#[get("/admin/storage")]
+async fn view_storage_usage(client: ClientCtx) -> Result<impl Responder, Error> {
     client.require_permission("admin.settings")?;
 
-    let db = get_db_pool();
-
-    let themes_list = themes::Entity::find()
-        .order_by_asc(themes::Column::DisplayOrder)
-        .all(db)
+    let consumers = crate::filesystem::quota::get_largest_consumers(50)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch themes: {}", e);
+            log::error!("Failed to fetch storage usage: {}", e);
             error::ErrorInternalServerError("Database error")
         })?;
 
-    Ok(ThemesTemplate {
-        client,
-        themes_list,
-    }
-    .to_response())
+    Ok(StorageUsageTemplate { client, consumers }.to_response())
 }
 
-/// GET /admin/themes/new - Show form to create new theme
-#[get("/admin/themes/new")]
-async fn view_create_theme_form(client: ClientCtx) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+// ============================================================================
+// Attachment Browser
+// ============================================================================
 
-    Ok(ThemeFormTemplate {
-        client,
-        theme: None,
-        error: None,
-        available_parents: crate::theme::get_available_parents(None),
-    }
-    .to_response())
+#[derive(Template)]
+#[template(path = "admin/attachments.html")]
+struct AttachmentsTemplate {
+    client: ClientCtx,
+    attachments: Vec<crate::filesystem::browser::AttachmentListing>,
+    filter: crate::filesystem::browser::AttachmentFilter,
+    filter_query: String,
+    page: i64,
+    total_pages: i64,
+    total: i64,
 }
 
-/// POST /admin/themes - Create a new theme
-#[post("/admin/themes")]
-async fn create_theme(
+#[derive(Deserialize)]
+struct AttachmentPageQuery {
+    page: Option<i64>,
+}
+
+/// GET /admin/attachments - Browse uploaded files
+#[get("/admin/attachments")]
+async fn view_attachments(
     client: ClientCtx,
-    cookies: actix_session::Session,
-    form: web::Form<std::collections::HashMap<String, String>>,
+    query: web::Query<AttachmentPageQuery>,
+    filter: web::Query<crate::filesystem::browser::AttachmentFilter>,
 ) -> Result<impl Responder, Error> {
-    let moderator_id = client.require_login()?;
-    client.require_permission("admin.settings")?;
-
-    // Validate CSRF
-    let csrf_token = form
-        .get("csrf_token")
-        .ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
-    crate::middleware::csrf::validate_csrf_token(&cookies, csrf_token)?;
-
-    let db = get_db_pool();
-
-    // Get form values
-    let name = form
-        .get("name")
-        .filter(|s| !s.is_empty())
-        .ok_or_else(|| error::ErrorBadRequest("Name is required"))?;
+    use crate::filesystem::browser::{search_attachments, PAGE_SIZE};
 
-    let slug = form
-        .get("slug")
-        .filter(|s| !s.is_empty())
-        .ok_or_else(|| error::ErrorBadRequest("Slug is required"))?;
+    client.require_permission("moderate.attachments.manage")?;
 
-    // Validate slug format (lowercase letters, numbers, hyphens only)
-    if !slug
-        .chars()
-        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
-    {
-        return Err(error::ErrorBadRequest(
-            "Slug must contain only lowercase letters, numbers, and hyphens",
-        ));
-    }
+    let filter = filter.into_inner();
+    let page = query.page.unwrap_or(1).max(1);
 
-    // Check for duplicate slug
-    let existing = themes::Entity::find()
-        .filter(themes::Column::Slug.eq(slug.as_str()))
-        .one(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to check slug: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?;
+    let (attachments, total) = search_attachments(&filter, page).await.map_err(|e| {
+        log::error!("Failed to fetch attachments: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
 
-    if existing.is_some() {
-        return Ok(ThemeFormTemplate {
-            client,
-            theme: None,
-            error: Some("A theme with this slug already exists".to_string()),
-            available_parents: crate::theme::get_available_parents(None),
-        }
-        .to_response());
+    Ok(AttachmentsTemplate {
+        client,
+        attachments,
+        filter_query: filter.query_string(),
+        filter,
+        page,
+        total_pages: (total + PAGE_SIZE - 1) / PAGE_SIZE,
+        total,
     }
+    .to_response())
+}
 
-    let description = form.get("description").cloned();
-    let is_dark = form.contains_key("is_dark");
-    let is_active = form.contains_key("is_active");
-    let display_order: i32 = form
-        .get("display_order")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(10);
+/// POST /admin/attachments/delete - Delete the selected files and every use of them
+#[post("/admin/attachments/delete")]
+async fn delete_attachments(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<Vec<(String, String)>>,
+) -> Result<impl Responder, Error> {
+    let moderator_id = client.require_login()?;
+    client.require_permission("moderate.attachments.manage")?;
+
+    // Checkboxes repeat the same field name, so the form is read as pairs.
+    let mut csrf_token = String::new();
+    let mut attachment_ids = Vec::new();
+    let mut return_to = String::new();
+    for (name, value) in form.into_inner() {
+        match name.as_str() {
+            "csrf_token" => csrf_token = value,
+            "attachment_ids" => attachment_ids.extend(value.parse::<i32>().ok()),
+            "return_to" => return_to = value,
+            _ => {}
+        }
+    }
 
-    let css_variables = form.get("css_variables").filter(|s| !s.is_empty()).cloned();
-    let css_custom = form.get("css_custom").filter(|s| !s.is_empty()).cloned();
+    crate::middleware::csrf::validate_csrf_token(&cookies, &csrf_token)?;
 
-    // Parse parent_id (empty string means no parent)
-    let parent_id = form
-        .get("parent_id")
-        .filter(|s| !s.is_empty())
-        .and_then(|s| s.parse::<i32>().ok());
+    if attachment_ids.is_empty() {
+        return Err(error::ErrorBadRequest("No attachments selected"));
+    }
 
-    // Create the theme
-    let new_theme = themes::ActiveModel {
-        slug: Set(slug.to_string()),
-        name: Set(name.to_string()),
-        description: Set(description),
-        is_system: Set(false),
-        is_dark: Set(is_dark),
-        is_active: Set(is_active),
-        display_order: Set(display_order),
-        css_variables: Set(css_variables),
-        css_custom: Set(css_custom),
-        parent_id: Set(parent_id),
-        created_at: Set(chrono::Utc::now().into()),
-        updated_at: Set(chrono::Utc::now().into()),
-        created_by: Set(Some(moderator_id)),
-        ..Default::default()
-    };
+    let db = get_db_pool();
+    let mut deleted = 0;
+    for attachment_id in attachment_ids {
+        let removed = crate::filesystem::browser::remove_attachment(attachment_id)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to delete attachment {}: {}", attachment_id, e);
+                error::ErrorInternalServerError("Failed to delete attachment")
+            })?;
 
-    new_theme.insert(db).await.map_err(|e| {
-        log::error!("Failed to create theme: {}", e);
-        error::ErrorInternalServerError("Failed to create theme")
-    })?;
+        if removed {
+            deleted += 1;
+            let _ = log_moderation_action(
+                db,
+                moderator_id,
+                "delete_attachment",
+                "attachment",
+                attachment_id,
+                None,
+            )
+            .await;
+        }
+    }
 
-    // Reload theme cache
-    crate::theme::reload_cache().await;
+    log::info!(
+        "{} attachments deleted by moderator {}",
+        deleted,
+        moderator_id
+    );
 
-    log::info!("Theme '{}' created by user {}", slug, moderator_id);
+    // Only return to the attachment list, never to an arbitrary URL.
+    let location = if return_to.starts_with("/admin/attachments?") {
+        return_to
+    } else {
+        "/admin/attachments".to_string()
+    };
 
     Ok(HttpResponse::SeeOther()
-        .append_header(("Location", "/admin/themes"))
+        .append_header(("Location", location))
         .finish())
 }
 
-/// GET /admin/themes/{id}/edit - Show form to edit theme
-#[get("/admin/themes/{id}/edit")]
-async fn view_edit_theme(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
-    client.require_permission("admin.settings")?;
+#[derive(Template)]
+#[template(path = "admin/attachment_uses.html")]
+struct AttachmentUsesTemplate {
+    client: ClientCtx,
+    attachment: crate::filesystem::browser::AttachmentListing,
+    uses: crate::filesystem::browser::AttachmentUses,
+}
 
-    let db = get_db_pool();
-    let theme_id = path.into_inner();
+/// GET /admin/attachments/{id} - Posts, messages and avatars using a file
+#[get("/admin/attachments/{id}")]
+async fn view_attachment_uses(
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    use crate::filesystem::browser::{find_attachment_uses, get_attachment_listing};
 
-    let theme = themes::Entity::find_by_id(theme_id)
-        .one(db)
+    client.require_permission("moderate.attachments.manage")?;
+
+    let attachment_id = path.into_inner();
+    let attachment = get_attachment_listing(attachment_id)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch theme: {}", e);
+            log::error!("Failed to fetch attachment: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Theme not found"))?;
+        .ok_or_else(|| error::ErrorNotFound("Attachment not found"))?;
 
-    // Get available parents, excluding self and descendants to prevent cycles
-    let available_parents = crate::theme::get_available_parents(Some(theme_id));
+    let uses = find_attachment_uses(attachment_id).await.map_err(|e| {
+        log::error!("Failed to fetch attachment uses: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
 
-    Ok(ThemeFormTemplate {
+    Ok(AttachmentUsesTemplate {
         client,
-        theme: Some(theme),
-        error: None,
-        available_parents,
+        attachment,
+        uses,
     }
     .to_response())
 }
 
-/// POST /admin/themes/{id} - Update a theme
-#[post("/admin/themes/{id}")]
-async fn update_theme(
+// ============================================================================
+// Avatar Gallery Management
+// ============================================================================
+
+#[derive(Template)]
+#[template(path = "admin/avatar_gallery.html")]
+struct AvatarGalleryTemplate {
+    client: ClientCtx,
+    avatars: Vec<(avatar_gallery::Model, Option<attachments::Model>)>,
+    error: Option<String>,
+}
+
+async fn render_avatar_gallery(
+    client: ClientCtx,
+    error: Option<String>,
+) -> Result<HttpResponse, Error> {
+    let avatars = avatar_gallery::Entity::find()
+        .order_by_asc(avatar_gallery::Column::DisplayOrder)
+        .order_by_asc(avatar_gallery::Column::Id)
+        .find_also_related(attachments::Entity)
+        .all(get_db_pool())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch avatar gallery: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(AvatarGalleryTemplate {
+        client,
+        avatars,
+        error,
+    }
+    .to_response())
+}
+
+/// GET /admin/avatars - List stock avatars
+#[get("/admin/avatars")]
+async fn view_avatar_gallery(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    render_avatar_gallery(client, None).await
+}
+
+/// POST /admin/avatars - Upload a new stock avatar
+#[post("/admin/avatars")]
+async fn create_avatar_gallery_item(
     client: ClientCtx,
     cookies: actix_session::Session,
-    path: web::Path<i32>,
-    form: web::Form<std::collections::HashMap<String, String>>,
+    mut multipart: actix_multipart::Multipart,
 ) -> Result<impl Responder, Error> {
-    let moderator_id = client.require_login()?;
+    use crate::filesystem::{insert_payload_as_avatar, save_field_as_temp_file};
+    use futures::{StreamExt, TryStreamExt};
+
+    client.require_login()?;
     client.require_permission("admin.settings")?;
 
-    // Validate CSRF
-    let csrf_token = form
-        .get("csrf_token")
-        .ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
-    crate::middleware::csrf::validate_csrf_token(&cookies, csrf_token)?;
+    let mut csrf_token: Option<String> = None;
+    let mut label: Option<String> = None;
+    let mut display_order: i32 = 0;
+    let mut attachment_id: Option<i32> = None;
 
-    let db = get_db_pool();
-    let theme_id = path.into_inner();
+    while let Ok(Some(mut field)) = multipart.try_next().await {
+        let field_name = field
+            .content_disposition()
+            .get_name()
+            .unwrap_or("")
+            .to_string();
 
-    let existing = themes::Entity::find_by_id(theme_id)
-        .one(db)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to fetch theme: {}", e);
-            error::ErrorInternalServerError("Database error")
-        })?
-        .ok_or_else(|| error::ErrorNotFound("Theme not found"))?;
+        match field_name.as_str() {
+            "csrf_token" | "label" | "display_order" => {
+                let mut buf = Vec::new();
+                while let Some(chunk) = field.next().await {
+                    buf.extend_from_slice(
+                        &chunk.map_err(|_| error::ErrorBadRequest("Read error"))?,
+                    );
+                }
+                let value = String::from_utf8_lossy(&buf).to_string();
+                match field_name.as_str() {
+                    "csrf_token" => csrf_token = Some(value),
+                    "label" => label = Some(value),
+                    _ => display_order = value.trim().parse().unwrap_or(0),
+                }
+            }
+            "image" => {
+                // Validate CSRF token before storing the upload
+                let token = csrf_token.as_ref().ok_or_else(|| {
+                    error::ErrorBadRequest("CSRF token must be provided before file upload")
+                })?;
+                crate::middleware::csrf::validate_csrf_token(&cookies, token)?;
 
-    // Get form values
-    let name = form
-        .get("name")
-        .filter(|s| !s.is_empty())
-        .ok_or_else(|| error::ErrorBadRequest("Name is required"))?;
+                if let Some(payload) =
+                    save_field_as_temp_file(&mut field, client.preserve_image_orientation()).await?
+                {
+                    if !payload.is_image() {
+                        return render_avatar_gallery(
+                            client,
+                            Some("Only image files are allowed".to_string()),
+                        )
+                        .await;
+                    }
 
-    let description = form.get("description").cloned();
-    let is_dark = form.contains_key("is_dark");
-    let is_active = form.contains_key("is_active");
-    let display_order: i32 = form
-        .get("display_order")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(existing.display_order);
+                    // Stock avatars go through the same crop and resize pipeline as uploads.
+                    match insert_payload_as_avatar(payload, None).await {
+                        Ok(Some(response)) => attachment_id = Some(response.id),
+                        _ => {
+                            return render_avatar_gallery(
+                                client,
+                                Some("Failed to process image".to_string()),
+                            )
+                            .await;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
 
-    let css_variables = form.get("css_variables").filter(|s| !s.is_empty()).cloned();
-    let css_custom = form.get("css_custom").filter(|s| !s.is_empty()).cloned();
+    // Validate CSRF
+    let token = csrf_token.ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &token)?;
 
-    // Parse parent_id (empty string means no parent)
-    let parent_id = form
-        .get("parent_id")
-        .filter(|s| !s.is_empty())
-        .and_then(|s| s.parse::<i32>().ok());
+    let label = label.unwrap_or_default().trim().to_string();
+    if label.is_empty() {
+        return render_avatar_gallery(client, Some("Label is required".to_string())).await;
+    }
+    if label.chars().count() > 100 {
+        return render_avatar_gallery(
+            client,
+            Some("Label must be 100 characters or fewer".to_string()),
+        )
+        .await;
+    }
 
-    // Update the theme
-    let mut theme: themes::ActiveModel = existing.into();
-    theme.name = Set(name.to_string());
-    theme.description = Set(description);
-    theme.is_dark = Set(is_dark);
-    theme.is_active = Set(is_active);
-    theme.display_order = Set(display_order);
-    theme.css_variables = Set(css_variables);
-    theme.css_custom = Set(css_custom);
-    theme.parent_id = Set(parent_id);
-    theme.updated_at = Set(chrono::Utc::now().into());
+    let attachment_id = match attachment_id {
+        Some(id) => id,
+        None => {
+            return render_avatar_gallery(client, Some("An image is required".to_string())).await
+        }
+    };
 
-    theme.update(db).await.map_err(|e| {
-        log::error!("Failed to update theme: {}", e);
-        error::ErrorInternalServerError("Failed to update theme")
+    avatar_gallery::ActiveModel {
+        attachment_id: Set(attachment_id),
+        label: Set(label),
+        display_order: Set(display_order),
+        is_enabled: Set(true),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(get_db_pool())
+    .await
+    .map_err(|e| {
+        log::error!("Failed to create stock avatar: {}", e);
+        error::ErrorInternalServerError("Failed to create stock avatar")
     })?;
 
-    // Reload theme cache
-    crate::theme::reload_cache().await;
-
-    log::info!("Theme {} updated by user {}", theme_id, moderator_id);
+    crate::filesystem::visibility::refresh_attachments_visibility([attachment_id]).await;
 
     Ok(HttpResponse::SeeOther()
-        .append_header(("Location", "/admin/themes"))
+        .append_header(("Location", "/admin/avatars"))
         .finish())
 }
 
-/// POST /admin/themes/{id}/delete - Delete a theme
-#[post("/admin/themes/{id}/delete")]
-async fn delete_theme(
+/// POST /admin/avatars/{id}/toggle - Enable or disable a stock avatar
+#[post("/admin/avatars/{id}/toggle")]
+async fn toggle_avatar_gallery_item(
     client: ClientCtx,
     cookies: actix_session::Session,
     path: web::Path<i32>,
-    form: web::Form<std::collections::HashMap<String, String>>,
+    form: web::Form<ModerationForm>,
 ) -> Result<impl Responder, Error> {
-    let moderator_id = client.require_login()?;
+    client.require_login()?;
     client.require_permission("admin.settings")?;
 
-    // Validate CSRF
-    let csrf_token = form
-        .get("csrf_token")
-        .ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
-    crate::middleware::csrf::validate_csrf_token(&cookies, csrf_token)?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
 
     let db = get_db_pool();
-    let theme_id = path.into_inner();
 
-    let theme = themes::Entity::find_by_id(theme_id)
+    let item = avatar_gallery::Entity::find_by_id(path.into_inner())
         .one(db)
         .await
         .map_err(|e| {
-            log::error!("Failed to fetch theme: {}", e);
+            log::error!("Failed to fetch stock avatar: {}", e);
             error::ErrorInternalServerError("Database error")
         })?
-        .ok_or_else(|| error::ErrorNotFound("Theme not found"))?;
+        .ok_or_else(|| error::ErrorNotFound("Avatar not found"))?;
+
+    let is_enabled = item.is_enabled;
+    let mut active: avatar_gallery::ActiveModel = item.into();
+    active.is_enabled = Set(!is_enabled);
+    active.update(db).await.map_err(|e| {
+        log::error!("Failed to update stock avatar: {}", e);
+        error::ErrorInternalServerError("Failed to update stock avatar")
+    })?;
 
-    // Cannot delete system themes
-    if theme.is_system {
-        return Err(error::ErrorForbidden("Cannot delete system themes"));
-    }
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/avatars"))
+        .finish())
+}
 
-    let theme_name = theme.name.clone();
+/// POST /admin/avatars/{id}/delete - Remove a stock avatar from the gallery
+#[post("/admin/avatars/{id}/delete")]
+async fn delete_avatar_gallery_item(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<ModerationForm>,
+) -> Result<impl Responder, Error> {
+    client.require_login()?;
+    client.require_permission("admin.settings")?;
 
-    themes::Entity::delete_by_id(theme_id)
-        .exec(db)
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    // Members who already picked this avatar keep it; only the gallery entry goes.
+    avatar_gallery::Entity::delete_by_id(path.into_inner())
+        .exec(get_db_pool())
         .await
         .map_err(|e| {
-            log::error!("Failed to delete theme: {}", e);
-            error::ErrorInternalServerError("Failed to delete theme")
+            log::error!("Failed to delete stock avatar: {}", e);
+            error::ErrorInternalServerError("Failed to delete stock avatar")
         })?;
 
-    // Reload theme cache
-    crate::theme::reload_cache().await;
-
-    log::info!(
-        "Theme {} ('{}') deleted by user {}",
-        theme_id,
-        theme_name,
-        moderator_id
-    );
-
     Ok(HttpResponse::SeeOther()
-        .append_header(("Location", "/admin/themes"))
+        .append_header(("Location", "/admin/avatars"))
         .finish())
 }
@@ -0,0 +1,215 @@
+//! The token owner's conversations.
+
+use super::{begin, db_error, Page, PageQuery, PAGE_SIZE};
+use crate::api_token::ApiScope;
+use crate::conversations;
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::pagination::split_page;
+use actix_web::{error, get, post, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use sea_orm::ActiveEnum;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Most conversations returned at once
+const CONVERSATION_LIMIT: u64 = 50;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(list_conversations)
+        .service(list_messages)
+        .service(send_message);
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiConversation {
+    pub id: i32,
+    pub title: Option<String>,
+    pub participants: Vec<String>,
+    pub last_message_content: Option<String>,
+    pub last_message_at: Option<NaiveDateTime>,
+    pub is_unread: bool,
+    pub unread_count: i64,
+    pub is_muted: bool,
+}
+
+impl From<conversations::ConversationPreview> for ApiConversation {
+    fn from(preview: conversations::ConversationPreview) -> Self {
+        Self {
+            id: preview.id,
+            title: preview.title,
+            participants: preview.participants,
+            last_message_content: preview.last_message_content,
+            last_message_at: preview.last_message_at,
+            is_unread: preview.is_unread,
+            unread_count: preview.unread_count,
+            is_muted: preview.is_muted,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiMessage {
+    pub id: i32,
+    pub user_id: Option<i32>,
+    pub author_name: String,
+    /// `message` for written messages, otherwise the membership change it records
+    pub kind: String,
+    /// BBCode source, empty once deleted
+    pub content: String,
+    pub created_at: NaiveDateTime,
+    pub is_deleted: bool,
+}
+
+impl From<conversations::MessageDisplay> for ApiMessage {
+    fn from(message: conversations::MessageDisplay) -> Self {
+        Self {
+            id: message.id,
+            user_id: message.user_id,
+            author_name: message.author_name,
+            kind: message.kind.to_value(),
+            content: match message.is_deleted {
+                true => String::new(),
+                false => message.content,
+            },
+            created_at: message.created_at,
+            is_deleted: message.is_deleted,
+        }
+    }
+}
+
+/// GET /api/v1/conversations - Recent conversations in the inbox
+#[utoipa::path(
+    tag = "conversations",
+    responses((status = 200, description = "Recent conversations", body = [ApiConversation])),
+    security(("bearer_token" = []))
+)]
+#[get("/api/v1/conversations")]
+async fn list_conversations(req: HttpRequest, client: ClientCtx) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::ConversationsRead).await?;
+
+    let conversations: Vec<ApiConversation> =
+        conversations::get_user_conversations(user_id, CONVERSATION_LIMIT)
+            .await
+            .map_err(|e| db_error("list_conversations", e))?
+            .into_iter()
+            .map(ApiConversation::from)
+            .collect();
+
+    Ok(HttpResponse::Ok().json(conversations))
+}
+
+/// GET /api/v1/conversations/{id}/messages?page= - Messages in a
+/// conversation, oldest first
+#[utoipa::path(
+    tag = "conversations",
+    params(("id" = i32, Path, description = "Conversation id"), PageQuery),
+    responses(
+        (status = 200, description = "One page of messages", body = super::MessagePage),
+        (status = 400, description = "Invalid cursor"),
+        (status = 404, description = "No conversation the token's owner is in"),
+    ),
+    security(("bearer_token" = []))
+)]
+#[get("/api/v1/conversations/{id}/messages")]
+async fn list_messages(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::ConversationsRead).await?;
+
+    let conversation_id = path.into_inner();
+    conversations::verify_participant(get_db_pool(), user_id, conversation_id)
+        .await
+        .map_err(|_| error::ErrorNotFound("Conversation not found."))?;
+
+    let messages =
+        conversations::get_conversation_messages(conversation_id, PAGE_SIZE + 1, query.start()?)
+            .await
+            .map_err(|e| db_error("list_messages", e))?;
+    let (messages, next_cursor) = split_page(messages, PAGE_SIZE as usize, |message| {
+        (message.created_at, message.id)
+    });
+    let messages: Vec<ApiMessage> = messages.into_iter().map(ApiMessage::from).collect();
+
+    Ok(HttpResponse::Ok().json(Page::new(messages, &query, None).with_next_cursor(next_cursor)))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewMessage {
+    pub content: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedMessage {
+    pub id: i32,
+}
+
+/// POST /api/v1/conversations/{id}/messages - Send a message, as JSON
+/// `{"content": "..."}`
+#[utoipa::path(
+    tag = "conversations",
+    params(("id" = i32, Path, description = "Conversation id")),
+    request_body = NewMessage,
+    responses(
+        (status = 201, description = "Message sent", body = CreatedMessage),
+        (status = 400, description = "Empty message, or blocked by a word filter"),
+        (status = 404, description = "No conversation the token's owner is in"),
+    ),
+    security(("bearer_token" = []))
+)]
+#[post("/api/v1/conversations/{id}/messages")]
+async fn send_message(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+    body: web::Json<NewMessage>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::ConversationsWrite).await?;
+
+    // Messages sent through the API count against the same limit as the site.
+    if let Err(e) = crate::rate_limit::check_post_rate_limit(user_id, &client.get_groups()).await {
+        return Err(error::ErrorTooManyRequests(format!(
+            "Too many messages. Please try again in {} seconds.",
+            e.retry_after_seconds
+        )));
+    }
+
+    let content = body.into_inner().content;
+    if content.trim().is_empty() {
+        return Err(error::ErrorBadRequest("Message cannot be empty"));
+    }
+    let content =
+        crate::word_filter::filter_for_saving(&content, crate::word_filter::FilterScope::Posts)
+            .map_err(error::ErrorBadRequest)?;
+
+    let conversation_id = path.into_inner();
+    conversations::verify_participant(get_db_pool(), user_id, conversation_id)
+        .await
+        .map_err(|_| error::ErrorNotFound("Conversation not found."))?;
+
+    let message_id = conversations::send_message(conversation_id, user_id, &content)
+        .await
+        .map_err(|e| db_error("send_message", e))?;
+    let created_at = conversations::get_message(message_id)
+        .await
+        .map_err(|e| db_error("send_message", e))?
+        .map(|message| message.created_at)
+        .unwrap_or_else(|| chrono::Utc::now().naive_utc());
+
+    crate::web::conversations::announce_message(
+        conversation_id,
+        user_id,
+        message_id,
+        &content,
+        created_at,
+    )
+    .await?;
+
+    Ok(HttpResponse::Created().json(CreatedMessage { id: message_id }))
+}
@@ -0,0 +1,172 @@
+//! Mobile devices registered for the token owner's push notifications.
+
+use super::{begin, db_error};
+use crate::api_token::ApiScope;
+use crate::middleware::ClientCtx;
+use crate::notifications::mobile;
+use crate::orm::push_devices::{self, PushPlatform};
+use actix_web::{error, get, post, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(list_devices)
+        .service(register_device)
+        .service(update_device)
+        .service(delete_device);
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiDevice {
+    pub id: i32,
+    pub platform: PushPlatform,
+    pub name: Option<String>,
+    /// Whether pushes are sent to this device
+    pub enabled: bool,
+    pub created_at: NaiveDateTime,
+    /// When a push last reached the push service for this device
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+impl From<push_devices::Model> for ApiDevice {
+    fn from(device: push_devices::Model) -> Self {
+        Self {
+            id: device.id,
+            platform: device.platform,
+            name: device.name,
+            enabled: device.is_enabled,
+            created_at: device.created_at,
+            last_used_at: device.last_used_at,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewDevice {
+    pub platform: PushPlatform,
+    /// FCM registration token, or APNs device token in hex
+    pub token: String,
+    /// Shown when listing devices, e.g. "Pixel 8"
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct DeviceUpdate {
+    pub enabled: bool,
+}
+
+/// GET /api/v1/devices - The token owner's registered devices
+#[utoipa::path(
+    tag = "devices",
+    responses((status = 200, description = "Registered devices, oldest first", body = [ApiDevice])),
+    security(("bearer_token" = []))
+)]
+#[get("/api/v1/devices")]
+async fn list_devices(req: HttpRequest, client: ClientCtx) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::Push).await?;
+
+    let devices: Vec<ApiDevice> = mobile::get_devices(user_id)
+        .await
+        .map_err(|e| db_error("list_devices", e))?
+        .into_iter()
+        .map(ApiDevice::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(devices))
+}
+
+/// POST /api/v1/devices - Register a device, as JSON
+/// `{"platform": "fcm", "token": "...", "name": "..."}`
+#[utoipa::path(
+    tag = "devices",
+    request_body = NewDevice,
+    responses(
+        (status = 201, description = "Device registered, or taken over from another account", body = ApiDevice),
+        (status = 400, description = "Malformed token"),
+        (status = 404, description = "Push is not set up for this platform"),
+    ),
+    security(("bearer_token" = []))
+)]
+#[post("/api/v1/devices")]
+async fn register_device(
+    req: HttpRequest,
+    client: ClientCtx,
+    body: web::Json<NewDevice>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::Push).await?;
+
+    if !mobile::platform_enabled(body.platform) {
+        return Err(error::ErrorNotFound(
+            "Push notifications are not enabled for this platform",
+        ));
+    }
+    mobile::validate_token(body.platform, &body.token).map_err(error::ErrorBadRequest)?;
+
+    let device = mobile::register(user_id, body.platform, &body.token, body.name.as_deref())
+        .await
+        .map_err(|e| db_error("register_device", e))?;
+
+    Ok(HttpResponse::Created().json(ApiDevice::from(device)))
+}
+
+/// POST /api/v1/devices/{id} - Switch pushes to a device on or off, as JSON
+/// `{"enabled": false}`
+#[utoipa::path(
+    tag = "devices",
+    params(("id" = i32, Path, description = "Device id")),
+    request_body = DeviceUpdate,
+    responses(
+        (status = 200, description = "Device updated", body = ApiDevice),
+        (status = 404, description = "No such device of the token's owner"),
+    ),
+    security(("bearer_token" = []))
+)]
+#[post("/api/v1/devices/{id}")]
+async fn update_device(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+    body: web::Json<DeviceUpdate>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::Push).await?;
+
+    let device = mobile::set_enabled(user_id, path.into_inner(), body.enabled)
+        .await
+        .map_err(|e| db_error("update_device", e))?
+        .ok_or_else(|| error::ErrorNotFound("Device not found."))?;
+
+    Ok(HttpResponse::Ok().json(ApiDevice::from(device)))
+}
+
+/// POST /api/v1/devices/{id}/delete - Unregister a device, e.g. on sign out
+#[utoipa::path(
+    tag = "devices",
+    params(("id" = i32, Path, description = "Device id")),
+    responses(
+        (status = 204, description = "Device unregistered"),
+        (status = 404, description = "No such device of the token's owner"),
+    ),
+    security(("bearer_token" = []))
+)]
+#[post("/api/v1/devices/{id}/delete")]
+async fn delete_device(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::Push).await?;
+
+    if !mobile::unregister(user_id, path.into_inner())
+        .await
+        .map_err(|e| db_error("delete_device", e))?
+    {
+        return Err(error::ErrorNotFound("Device not found."));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
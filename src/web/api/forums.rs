@@ -0,0 +1,181 @@
+//! Forums and their thread lists.
+
+use super::threads::ApiThread;
+use super::{begin, db_error, Page, PageQuery, PAGE_SIZE};
+use crate::api_token::ApiScope;
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::orm::{forums, threads};
+use crate::pagination::{split_page, PageStart};
+use actix_web::{error, get, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use sea_orm::{entity::*, query::*, DbBackend, Statement};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(list_forums)
+        .service(view_forum)
+        .service(list_forum_threads);
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiForum {
+    pub id: i32,
+    pub label: String,
+    pub description: Option<String>,
+    pub parent_id: Option<i32>,
+    pub display_order: i32,
+}
+
+impl From<forums::Model> for ApiForum {
+    fn from(forum: forums::Model) -> Self {
+        Self {
+            id: forum.id,
+            label: forum.label,
+            description: forum.description,
+            parent_id: forum.parent_id,
+            display_order: forum.display_order,
+        }
+    }
+}
+
+/// GET /api/v1/forums - Every forum the client can view
+#[utoipa::path(
+    tag = "forums",
+    responses((status = 200, description = "Forums the client can view", body = [ApiForum])),
+    security((), ("bearer_token" = []))
+)]
+#[get("/api/v1/forums")]
+async fn list_forums(req: HttpRequest, client: ClientCtx) -> Result<HttpResponse, Error> {
+    begin(&req, &client, ApiScope::Read).await?;
+
+    let forums: Vec<ApiForum> = forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
+        .order_by_asc(forums::Column::Id)
+        .all(get_db_pool())
+        .await
+        .map_err(|e| db_error("list_forums", e))?
+        .into_iter()
+        .filter(|forum| client.can_view_forum(&forum.id))
+        .map(ApiForum::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(forums))
+}
+
+async fn visible_forum(client: &ClientCtx, forum_id: i32) -> Result<forums::Model, Error> {
+    if !client.can_view_forum(&forum_id) {
+        return Err(error::ErrorNotFound("Forum not found."));
+    }
+    forums::Entity::find_by_id(forum_id)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| db_error("visible_forum", e))?
+        .ok_or_else(|| error::ErrorNotFound("Forum not found."))
+}
+
+/// GET /api/v1/forums/{id} - One forum
+#[utoipa::path(
+    tag = "forums",
+    params(("id" = i32, Path, description = "Forum id")),
+    responses(
+        (status = 200, description = "The forum", body = ApiForum),
+        (status = 404, description = "No forum the client can view"),
+    ),
+    security((), ("bearer_token" = []))
+)]
+#[get("/api/v1/forums/{id}")]
+async fn view_forum(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    begin(&req, &client, ApiScope::Read).await?;
+
+    let forum = visible_forum(&client, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiForum::from(forum)))
+}
+
+/// Sort key of a thread in a forum's list: pinned, time of the latest post
+/// (or creation, before any), id.
+type ThreadKey = (bool, NaiveDateTime, i32);
+
+/// GET /api/v1/forums/{id}/threads?page= - Threads in a forum, pinned first,
+/// then by latest post
+#[utoipa::path(
+    tag = "forums",
+    params(("id" = i32, Path, description = "Forum id"), PageQuery),
+    responses(
+        (status = 200, description = "One page of threads", body = super::ThreadPage),
+        (status = 400, description = "Invalid cursor"),
+        (status = 404, description = "No forum the client can view"),
+    ),
+    security((), ("bearer_token" = []))
+)]
+#[get("/api/v1/forums/{id}/threads")]
+async fn list_forum_threads(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, Error> {
+    begin(&req, &client, ApiScope::Read).await?;
+
+    let forum = visible_forum(&client, path.into_inner()).await?;
+    let start = query.start::<ThreadKey>()?;
+
+    let total = threads::Entity::find()
+        .filter(threads::Column::ForumId.eq(forum.id))
+        .filter(threads::Column::DeletedAt.is_null())
+        .filter(threads::Column::MergedIntoId.is_null())
+        .count(get_db_pool())
+        .await
+        .map_err(|e| db_error("list_forum_threads", e))?;
+
+    let mut values: Vec<sea_orm::Value> = vec![forum.id.into(), ((PAGE_SIZE + 1) as i64).into()];
+    let (cursor_clause, offset_clause) = match start {
+        PageStart::After((is_pinned, sorted_at, id)) => {
+            values.extend([is_pinned.into(), sorted_at.into(), id.into()]);
+            (
+                "AND (is_pinned, COALESCE(last_post_at, created_at), id) < ($3, $4, $5)",
+                "",
+            )
+        }
+        PageStart::Offset(offset) => {
+            values.push((offset as i64).into());
+            ("", "OFFSET $3")
+        }
+    };
+
+    let threads = threads::Entity::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            &format!(
+                r#"
+                SELECT * FROM threads
+                WHERE forum_id = $1 AND deleted_at IS NULL AND merged_into_id IS NULL
+                {}
+                ORDER BY is_pinned DESC, COALESCE(last_post_at, created_at) DESC, id DESC
+                LIMIT $2 {}
+                "#,
+                cursor_clause, offset_clause
+            ),
+            values,
+        ))
+        .all(get_db_pool())
+        .await
+        .map_err(|e| db_error("list_forum_threads", e))?;
+
+    let (threads, next_cursor) = split_page(threads, PAGE_SIZE as usize, |thread| {
+        (
+            thread.is_pinned,
+            thread.last_post_at.unwrap_or(thread.created_at),
+            thread.id,
+        )
+    });
+    let threads: Vec<ApiThread> = threads.into_iter().map(ApiThread::from).collect();
+
+    Ok(HttpResponse::Ok()
+        .json(Page::new(threads, &query, Some(total)).with_next_cursor(next_cursor)))
+}
@@ -0,0 +1,131 @@
+//! Member profiles.
+
+use super::{begin, db_error};
+use crate::api_token::ApiScope;
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::user::Profile;
+use actix_web::{error, get, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(view_me).service(view_member);
+}
+
+/// A member's public profile.
+#[derive(Serialize, ToSchema)]
+pub struct ApiMember {
+    pub id: i32,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub custom_title: Option<String>,
+    pub bio: Option<String>,
+    pub location: Option<String>,
+    pub website_url: Option<String>,
+    pub post_count: i64,
+    pub reputation_score: i32,
+    pub follower_count: i32,
+    pub following_count: i32,
+}
+
+impl From<Profile> for ApiMember {
+    fn from(profile: Profile) -> Self {
+        Self {
+            id: profile.id,
+            name: profile.name,
+            created_at: profile.created_at,
+            custom_title: profile.custom_title,
+            bio: profile.bio,
+            location: profile.location,
+            website_url: profile.website_url,
+            post_count: profile.post_count.unwrap_or(0),
+            reputation_score: profile.reputation_score,
+            follower_count: profile.follower_count,
+            following_count: profile.following_count,
+        }
+    }
+}
+
+/// The token's owner, with what is waiting for them.
+#[derive(Serialize, ToSchema)]
+pub struct ApiMe {
+    #[serde(flatten)]
+    pub member: ApiMember,
+    pub unread_notifications: i64,
+    pub unread_messages: i64,
+    /// Scopes of the token making the request
+    pub scopes: Vec<&'static str>,
+}
+
+/// GET /api/v1/me - The member the token belongs to
+#[utoipa::path(
+    tag = "members",
+    responses(
+        (status = 200, description = "The token's owner", body = ApiMe),
+        (status = 401, description = "No token, or an invalid one"),
+    ),
+    security(("bearer_token" = []))
+)]
+#[get("/api/v1/me")]
+async fn view_me(req: HttpRequest, client: ClientCtx) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::Read).await?;
+
+    let profile = Profile::get_by_id(get_db_pool(), user_id)
+        .await
+        .map_err(|e| db_error("view_me", e))?
+        .ok_or_else(|| error::ErrorNotFound("Member not found."))?;
+
+    // Counts the token cannot read are left at zero.
+    let unread_notifications = match client.has_api_scope(ApiScope::NotificationsRead) {
+        true => crate::notifications::count_unread_notifications(user_id)
+            .await
+            .map_err(|e| db_error("view_me", e))?,
+        false => 0,
+    };
+    let unread_messages = match client.has_api_scope(ApiScope::ConversationsRead) {
+        true => crate::conversations::count_unread_messages(user_id)
+            .await
+            .map_err(|e| db_error("view_me", e))?,
+        false => 0,
+    };
+
+    Ok(HttpResponse::Ok().json(ApiMe {
+        member: ApiMember::from(profile),
+        unread_notifications,
+        unread_messages,
+        scopes: ApiScope::all()
+            .into_iter()
+            .filter(|scope| client.has_api_scope(*scope))
+            .map(|scope| scope.as_str())
+            .collect(),
+    }))
+}
+
+/// GET /api/v1/members/{id} - A member's public profile
+#[utoipa::path(
+    tag = "members",
+    params(("id" = i32, Path, description = "Member id")),
+    responses(
+        (status = 200, description = "The member", body = ApiMember),
+        (status = 404, description = "No such member"),
+    ),
+    security((), ("bearer_token" = []))
+)]
+#[get("/api/v1/members/{id}")]
+async fn view_member(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    begin(&req, &client, ApiScope::Read).await?;
+
+    let profile = Profile::get_by_id(get_db_pool(), path.into_inner())
+        .await
+        .map_err(|e| db_error("view_member", e))?
+        .ok_or_else(|| error::ErrorNotFound("Member not found."))?;
+
+    Ok(HttpResponse::Ok().json(ApiMember::from(profile)))
+}
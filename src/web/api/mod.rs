@@ -0,0 +1,300 @@
+//! JSON API, version 1.
+//!
+//! Requests authenticate with a personal access token, sent as
+//! `Authorization: Bearer <token>`, and act as the token's owner within the
+//! token's scopes. Requests without a token are made as a guest. The client
+//! middleware builds the same `ClientCtx` from the token that the HTML pages
+//! build from the session, so both share one set of permission checks.
+//!
+//! The OpenAPI description at `/api/openapi.json`, browsable at `/api/docs/`,
+//! is generated from the `utoipa` annotations on the handlers below.
+
+mod conversations;
+mod devices;
+mod forums;
+mod members;
+mod notifications;
+mod posts;
+mod threads;
+
+use crate::api_token::ApiScope;
+use crate::middleware::ClientCtx;
+use crate::orm::threads as thread_orm;
+use crate::pagination::PageStart;
+use actix_web::{error, Error, HttpRequest};
+use sea_orm::{DbErr, EntityTrait};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{IntoParams, Modify, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(SwaggerUi::new("/api/docs/{_:.*}").url("/api/openapi.json", ApiDoc::openapi()));
+    conversations::configure(conf);
+    devices::configure(conf);
+    forums::configure(conf);
+    members::configure(conf);
+    notifications::configure(conf);
+    posts::configure(conf);
+    threads::configure(conf);
+}
+
+/// Items in one page of a list
+const PAGE_SIZE: u64 = 25;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    /// Page to return, from 1
+    page: Option<u64>,
+    /// `next_cursor` of the previous page. Takes the place of `page`, and
+    /// stays fast however deep into the list it is.
+    cursor: Option<String>,
+}
+
+impl PageQuery {
+    pub fn page(&self) -> u64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn offset(&self) -> u64 {
+        (self.page() - 1) * PAGE_SIZE
+    }
+
+    /// Where the requested page starts. Fails for a cursor this list didn't
+    /// hand out.
+    pub fn start<K: DeserializeOwned>(&self) -> Result<PageStart<K>, Error> {
+        match &self.cursor {
+            Some(cursor) => crate::pagination::decode(cursor)
+                .map(PageStart::After)
+                .ok_or_else(|| error::ErrorBadRequest("Invalid cursor")),
+            None => Ok(PageStart::Offset(self.offset())),
+        }
+    }
+}
+
+/// One page of a list.
+#[derive(Serialize, ToSchema)]
+#[aliases(
+    ThreadPage = Page<threads::ApiThread>,
+    PostPage = Page<posts::ApiPost>,
+    MessagePage = Page<conversations::ApiMessage>
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: u64,
+    pub per_page: u64,
+    /// Items in the whole list, where counting them is cheap
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    /// Pass as `cursor` for the page after this one. Absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, query: &PageQuery, total: Option<u64>) -> Self {
+        Self {
+            items,
+            page: query.page(),
+            per_page: PAGE_SIZE,
+            total,
+            next_cursor: None,
+        }
+    }
+
+    pub fn with_next_cursor(mut self, next_cursor: Option<String>) -> Self {
+        self.next_cursor = next_cursor;
+        self
+    }
+}
+
+/// The OpenAPI description of this API.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Dumpster API", version = "1"),
+    paths(
+        conversations::list_conversations,
+        conversations::list_messages,
+        conversations::send_message,
+        devices::list_devices,
+        devices::register_device,
+        devices::update_device,
+        devices::delete_device,
+        forums::list_forums,
+        forums::view_forum,
+        forums::list_forum_threads,
+        members::view_me,
+        members::view_member,
+        notifications::list_notifications,
+        notifications::mark_all_read,
+        notifications::mark_read,
+        posts::view_post,
+        threads::view_thread,
+        threads::list_thread_posts,
+    ),
+    components(schemas(
+        conversations::ApiConversation,
+        conversations::ApiMessage,
+        conversations::NewMessage,
+        conversations::CreatedMessage,
+        devices::ApiDevice,
+        devices::NewDevice,
+        devices::DeviceUpdate,
+        crate::orm::push_devices::PushPlatform,
+        forums::ApiForum,
+        members::ApiMember,
+        members::ApiMe,
+        notifications::ApiNotification,
+        posts::ApiPost,
+        threads::ApiThread,
+        ThreadPage,
+        PostPage,
+        MessagePage,
+    )),
+    modifiers(&BearerToken),
+    tags(
+        (name = "conversations", description = "Scopes `conversations:read` and `conversations:write`"),
+        (name = "devices", description = "Scope `push`"),
+        (name = "forums", description = "Scope `read`"),
+        (name = "members", description = "Scope `read`"),
+        (name = "notifications", description = "Scopes `notifications:read` and `notifications:write`"),
+        (name = "posts", description = "Scope `read`"),
+        (name = "threads", description = "Scope `read`"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Declares the personal access token scheme the paths refer to.
+struct BearerToken;
+
+impl Modify for BearerToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("A personal access token from the account page"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Checks the rate limit and the token's scope before answering a request.
+pub async fn begin(req: &HttpRequest, client: &ClientCtx, scope: ApiScope) -> Result<(), Error> {
+    let rate_limit_id = match client.get_id() {
+        Some(user_id) => format!("user:{}", user_id),
+        None => crate::ip::extract_client_ip(req)
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_string()),
+    };
+
+    if let Err(e) =
+        crate::rate_limit::check_api_rate_limit(&rate_limit_id, &client.get_groups()).await
+    {
+        log::warn!("API rate limit exceeded for: {}", rate_limit_id);
+        return Err(error::ErrorTooManyRequests(format!(
+            "Too many requests. Please try again in {} seconds.",
+            e.retry_after_seconds
+        )));
+    }
+
+    client.require_api_scope(scope)
+}
+
+/// Logs a database error and answers with a generic one.
+pub fn db_error(context: &str, e: DbErr) -> Error {
+    log::error!("api::{}: {}", context, e);
+    error::ErrorInternalServerError("Database error")
+}
+
+/// A thread the client may read. Deleted threads, and threads in forums the
+/// client cannot view, are not found.
+pub async fn visible_thread(
+    client: &ClientCtx,
+    thread_id: i32,
+) -> Result<thread_orm::Model, Error> {
+    let thread = thread_orm::Entity::find_by_id(thread_id)
+        .one(crate::db::get_db_pool())
+        .await
+        .map_err(|e| db_error("visible_thread", e))?
+        .filter(|thread| thread.deleted_at.is_none())
+        .ok_or_else(|| error::ErrorNotFound("Thread not found."))?;
+
+    if !client.can_view_thread(&thread.id, &thread.forum_id) {
+        return Err(error::ErrorNotFound("Thread not found."));
+    }
+
+    Ok(thread)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_query() {
+        let query = PageQuery {
+            page: None,
+            cursor: None,
+        };
+        assert_eq!((query.page(), query.offset()), (1, 0));
+        let query = PageQuery {
+            page: Some(0),
+            cursor: None,
+        };
+        assert_eq!((query.page(), query.offset()), (1, 0));
+        let query = PageQuery {
+            page: Some(3),
+            cursor: None,
+        };
+        assert_eq!((query.page(), query.offset()), (3, 2 * PAGE_SIZE));
+    }
+
+    #[test]
+    fn test_page_query_cursor() {
+        let query = PageQuery {
+            page: Some(3),
+            cursor: Some(crate::pagination::encode(&7)),
+        };
+        assert_eq!(query.start::<i32>().unwrap(), PageStart::After(7));
+        let query = PageQuery {
+            page: None,
+            cursor: Some("bogus".to_string()),
+        };
+        assert!(query.start::<i32>().is_err());
+    }
+
+    #[test]
+    fn test_openapi_lists_every_route() {
+        let spec = ApiDoc::openapi();
+        for path in [
+            "/api/v1/conversations",
+            "/api/v1/conversations/{id}/messages",
+            "/api/v1/devices",
+            "/api/v1/devices/{id}",
+            "/api/v1/devices/{id}/delete",
+            "/api/v1/forums",
+            "/api/v1/forums/{id}",
+            "/api/v1/forums/{id}/threads",
+            "/api/v1/me",
+            "/api/v1/members/{id}",
+            "/api/v1/notifications",
+            "/api/v1/notifications/read",
+            "/api/v1/notifications/{id}/read",
+            "/api/v1/posts/{id}",
+            "/api/v1/threads/{id}",
+            "/api/v1/threads/{id}/posts",
+        ] {
+            assert!(spec.paths.paths.contains_key(path), "missing {}", path);
+        }
+
+        let components = spec.components.expect("components");
+        assert!(components.security_schemes.contains_key("bearer_token"));
+        assert!(components.schemas.contains_key("ThreadPage"));
+        assert!(spec.to_json().is_ok());
+    }
+}
@@ -0,0 +1,122 @@
+//! The token owner's notifications.
+
+use super::{begin, db_error};
+use crate::api_token::ApiScope;
+use crate::middleware::ClientCtx;
+use crate::orm::notifications;
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Most notifications returned at once
+const NOTIFICATION_LIMIT: u64 = 50;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(list_notifications)
+        .service(mark_all_read)
+        .service(mark_read);
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiNotification {
+    pub id: i32,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub title: String,
+    pub message: String,
+    pub url: Option<String>,
+    pub is_read: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<notifications::Model> for ApiNotification {
+    fn from(notification: notifications::Model) -> Self {
+        Self {
+            id: notification.id,
+            type_: notification.type_,
+            title: notification.title,
+            message: notification.message,
+            url: notification.url,
+            is_read: notification.is_read,
+            created_at: notification.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct NotificationQuery {
+    /// Include notifications already read
+    #[serde(default)]
+    all: bool,
+}
+
+/// GET /api/v1/notifications?all= - Recent unread notifications, or all
+/// recent ones with `all=true`
+#[utoipa::path(
+    tag = "notifications",
+    params(NotificationQuery),
+    responses((status = 200, description = "Recent notifications, newest first", body = [ApiNotification])),
+    security(("bearer_token" = []))
+)]
+#[get("/api/v1/notifications")]
+async fn list_notifications(
+    req: HttpRequest,
+    client: ClientCtx,
+    query: web::Query<NotificationQuery>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::NotificationsRead).await?;
+
+    let notifications: Vec<ApiNotification> =
+        crate::notifications::get_user_notifications(user_id, NOTIFICATION_LIMIT, query.all)
+            .await
+            .map_err(|e| db_error("list_notifications", e))?
+            .into_iter()
+            .map(ApiNotification::from)
+            .collect();
+
+    Ok(HttpResponse::Ok().json(notifications))
+}
+
+/// POST /api/v1/notifications/read - Mark every notification as read
+#[utoipa::path(
+    tag = "notifications",
+    responses((status = 204, description = "All notifications marked as read")),
+    security(("bearer_token" = []))
+)]
+#[post("/api/v1/notifications/read")]
+async fn mark_all_read(req: HttpRequest, client: ClientCtx) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::NotificationsWrite).await?;
+
+    crate::notifications::mark_all_read(user_id)
+        .await
+        .map_err(|e| db_error("mark_all_read", e))?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// POST /api/v1/notifications/{id}/read - Mark one notification as read
+#[utoipa::path(
+    tag = "notifications",
+    params(("id" = i32, Path, description = "Notification id")),
+    responses((status = 204, description = "Notification marked as read")),
+    security(("bearer_token" = []))
+)]
+#[post("/api/v1/notifications/{id}/read")]
+async fn mark_read(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    begin(&req, &client, ApiScope::NotificationsWrite).await?;
+
+    crate::notifications::mark_notification_read(path.into_inner(), user_id)
+        .await
+        .map_err(|e| db_error("mark_read", e))?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,101 @@
+//! Single posts.
+
+use super::{begin, db_error, visible_thread};
+use crate::api_token::ApiScope;
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::orm::posts;
+use crate::user::Profile as UserProfile;
+use crate::web::post::PostForTemplate;
+use actix_web::{error, get, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use sea_orm::EntityTrait;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(view_post);
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiPost {
+    pub id: i32,
+    pub thread_id: i32,
+    pub position: i32,
+    pub user_id: Option<i32>,
+    pub author_name: Option<String>,
+    pub created_at: NaiveDateTime,
+    /// When the current revision was written
+    pub updated_at: NaiveDateTime,
+    /// BBCode source
+    pub content: String,
+    pub content_html: String,
+    pub is_deleted: bool,
+}
+
+impl ApiPost {
+    pub fn new(post: PostForTemplate, author: Option<UserProfile>) -> Self {
+        let content = post.content.unwrap_or_default();
+        Self {
+            id: post.id,
+            thread_id: post.thread_id,
+            position: post.position,
+            user_id: post.user_id,
+            author_name: author.map(|author| author.name),
+            created_at: post.created_at,
+            updated_at: post.updated_at,
+            content_html: crate::bbcode::parse(&content),
+            content,
+            is_deleted: post.deleted_at.is_some(),
+        }
+    }
+}
+
+/// GET /api/v1/posts/{id} - One post
+#[utoipa::path(
+    tag = "posts",
+    params(("id" = i32, Path, description = "Post id")),
+    responses(
+        (status = 200, description = "The post", body = ApiPost),
+        (status = 404, description = "No post the client can read"),
+    ),
+    security((), ("bearer_token" = []))
+)]
+#[get("/api/v1/posts/{id}")]
+async fn view_post(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    use crate::web::post::get_post_and_author_for_template;
+
+    begin(&req, &client, ApiScope::Read).await?;
+
+    let db = get_db_pool();
+    let post_id = path.into_inner();
+    let post = posts::Entity::find_by_id(post_id)
+        .one(db)
+        .await
+        .map_err(|e| db_error("view_post", e))?
+        .ok_or_else(|| error::ErrorNotFound("Post not found."))?;
+
+    visible_thread(&client, post.thread_id).await?;
+
+    // Posts awaiting approval are only shown to their authors and moderators.
+    let approved = post.moderation_status == posts::ModerationStatus::Approved;
+    let own = post.user_id.is_some() && post.user_id == client.get_id();
+    if !approved && !own && !client.can("moderate.approval.view") {
+        return Err(error::ErrorNotFound("Post not found."));
+    }
+
+    let (post, author) = get_post_and_author_for_template(db, post_id)
+        .await
+        .map_err(|e| db_error("view_post", e))?
+        .ok_or_else(|| error::ErrorNotFound("Post not found."))?;
+
+    if !client.can_read_post(&post) {
+        return Err(error::ErrorNotFound("Post not found."));
+    }
+
+    Ok(HttpResponse::Ok().json(ApiPost::new(post, author)))
+}
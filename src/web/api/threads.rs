@@ -0,0 +1,133 @@
+//! Threads and their posts.
+
+use super::posts::ApiPost;
+use super::{begin, db_error, visible_thread, Page, PageQuery, PAGE_SIZE};
+use crate::api_token::ApiScope;
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::orm::threads;
+use crate::pagination::PageStart;
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(view_thread).service(list_thread_posts);
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiThread {
+    pub id: i32,
+    pub forum_id: i32,
+    pub user_id: Option<i32>,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_post_at: Option<NaiveDateTime>,
+    pub post_count: i32,
+    pub view_count: i32,
+    pub is_locked: bool,
+    pub is_pinned: bool,
+    /// Thread this one was merged into, which holds its posts
+    pub merged_into_id: Option<i32>,
+}
+
+impl From<threads::Model> for ApiThread {
+    fn from(thread: threads::Model) -> Self {
+        Self {
+            id: thread.id,
+            forum_id: thread.forum_id,
+            user_id: thread.user_id,
+            title: thread.title,
+            subtitle: thread.subtitle,
+            created_at: thread.created_at,
+            last_post_at: thread.last_post_at,
+            post_count: thread.post_count,
+            view_count: thread.view_count,
+            is_locked: thread.is_locked,
+            is_pinned: thread.is_pinned,
+            merged_into_id: thread.merged_into_id,
+        }
+    }
+}
+
+/// GET /api/v1/threads/{id} - One thread
+#[utoipa::path(
+    tag = "threads",
+    params(("id" = i32, Path, description = "Thread id")),
+    responses(
+        (status = 200, description = "The thread", body = ApiThread),
+        (status = 404, description = "No thread the client can view"),
+    ),
+    security((), ("bearer_token" = []))
+)]
+#[get("/api/v1/threads/{id}")]
+async fn view_thread(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, Error> {
+    begin(&req, &client, ApiScope::Read).await?;
+
+    let thread = visible_thread(&client, path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(ApiThread::from(thread)))
+}
+
+/// GET /api/v1/threads/{id}/posts?page= - Posts in a thread, oldest first.
+/// Pages follow post positions, so a page may hold fewer posts than
+/// `per_page` where some are hidden from the client.
+#[utoipa::path(
+    tag = "threads",
+    params(("id" = i32, Path, description = "Thread id"), PageQuery),
+    responses(
+        (status = 200, description = "One page of posts", body = super::PostPage),
+        (status = 400, description = "Invalid cursor"),
+        (status = 404, description = "No thread the client can view"),
+    ),
+    security((), ("bearer_token" = []))
+)]
+#[get("/api/v1/threads/{id}/posts")]
+async fn list_thread_posts(
+    req: HttpRequest,
+    client: ClientCtx,
+    path: web::Path<i32>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, Error> {
+    use crate::web::post::get_replies_and_author_from_position;
+
+    begin(&req, &client, ApiScope::Read).await?;
+
+    let thread = visible_thread(&client, path.into_inner()).await?;
+    // The cursor is the last position on the previous page.
+    let first_position = match query.start::<i32>()? {
+        PageStart::After(position) => position + 1,
+        PageStart::Offset(offset) => offset as i32 + 1,
+    };
+    let last_position = first_position + PAGE_SIZE as i32 - 1;
+
+    let posts = get_replies_and_author_from_position(
+        get_db_pool(),
+        thread.id,
+        first_position,
+        PAGE_SIZE as i32,
+        client.can("moderate.approval.view"),
+        client.get_id(),
+    )
+    .await
+    .map_err(|e| db_error("list_thread_posts", e))?;
+
+    let posts: Vec<ApiPost> = posts
+        .into_iter()
+        .filter(|(post, _)| client.can_read_post(post))
+        .map(|(post, author)| ApiPost::new(post, author))
+        .collect();
+
+    let next_cursor =
+        (last_position < thread.post_count).then(|| crate::pagination::encode(&last_position));
+
+    Ok(HttpResponse::Ok().json(
+        Page::new(posts, &query, Some(thread.post_count.max(0) as u64))
+            .with_next_cursor(next_cursor),
+    ))
+}
@@ -1,30 +1,137 @@
 use actix_files as fs;
-use actix_web::http::{header, header::ContentEncoding, StatusCode};
-use actix_web::{get, Error, HttpRequest, HttpResponse, Responder};
+use actix_web::http::header::{self, ContentEncoding, HeaderMap};
+use actix_web::http::StatusCode;
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
 use std::path::PathBuf;
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
     conf.service(view_file_by_hash).service(view_public_file);
 }
 
+/// Signature parameters of a link to a restricted file.
+#[derive(Deserialize)]
+struct SignedQuery {
+    expires: i64,
+    signature: String,
+}
+
+/// Seconds left on a valid signed link for the file with `hash`, if the
+/// request carries one.
+fn signed_link_lifetime(req: &HttpRequest, hash: &str) -> Option<i64> {
+    let query = web::Query::<SignedQuery>::from_query(req.query_string()).ok()?;
+    let now = chrono::Utc::now().timestamp();
+    crate::storage::signing::verify(hash, query.expires, &query.signature, now)
+        .then_some(query.expires - now)
+}
+
+/// Entity tag of a stored file. Keys are named by the file's content hash, so
+/// the key identifies the version served whichever backend holds it.
+fn content_e_tag(key: &str) -> String {
+    format!("\"{}\"", key)
+}
+
+/// Whether the copy a client has cached, going by its conditional headers,
+/// matches the stored object. `If-None-Match` decides when present;
+/// `If-Modified-Since` only counts without it (RFC 9110, section 13.2.2).
+fn is_not_modified(headers: &HeaderMap, e_tag: Option<&str>, last_modified: Option<&str>) -> bool {
+    if let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+    {
+        let Some(e_tag) = e_tag else {
+            return false;
+        };
+        // Weak comparison: a W/ prefix on either side doesn't matter.
+        let e_tag = e_tag.trim_start_matches("W/");
+        return if_none_match
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag.trim_start_matches("W/") == e_tag);
+    }
+
+    let since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok());
+    let modified = last_modified.and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok());
+    match (since, modified) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
 /// Route for passing local assets through the webserver.
 /// /content/9e0834c0d3dd1f6a775b9af7523eff7b35e750afb8fcd2753eef06735e13c46f/whatever.jpg
+/// Images are served as WebP or AVIF when the browser accepts them; `?original` skips this.
+/// Restricted files need a signed link: `?expires={unix time}&signature={hmac}`.
+/// Files are named by their content hash, so they never change and public ones
+/// are cached for a year; revalidation with `If-None-Match` is answered with
+/// 304 without touching storage, and with `If-Modified-Since` once the stored
+/// object has been looked up.
 #[get("/content/{hash:.*}/{filename:.*}")]
 async fn view_file_by_hash(req: HttpRequest) -> impl Responder {
     let hash: String = req.match_info().query("hash").parse().expect("Bad hash.");
-    let key: String = match crate::attachment::get_attachment_by_hash(hash).await {
-        Some(attachment) => attachment.filename,
+    let attachment = match crate::attachment::get_attachment_by_hash(hash).await {
+        Some(attachment) => attachment,
         None => {
             return HttpResponse::NotFound().body("404 - Resource not found");
         }
     };
 
+    if attachment.scan_status.is_quarantined() {
+        return HttpResponse::Gone().body("410 - File quarantined");
+    }
+
+    let signed_lifetime = if attachment.visibility.is_restricted() {
+        match signed_link_lifetime(&req, &attachment.hash) {
+            Some(lifetime) => Some(lifetime),
+            None => return HttpResponse::Forbidden().body("403 - Link expired or invalid"),
+        }
+    } else {
+        None
+    };
+
+    let negotiable = attachment.mime.starts_with("image/")
+        && !req.query_string().split('&').any(|pair| pair == "original");
+    let mut key = attachment.filename;
+    if negotiable {
+        if let Some(accept) = req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|a| a.to_str().ok())
+        {
+            let variants = crate::attachment::get_attachment_variants(attachment.id).await;
+            if let Some(variant) = crate::attachment::preferred_variant(accept, &variants) {
+                key = variant.to_owned();
+            }
+        }
+    }
+
     //let name: String = req
     //    .match_info()
     //    .query("filename")
     //    .parse()
     //    .expect("Bad filename.");
 
+    let cache_control = match signed_lifetime {
+        Some(lifetime) => format!("private, max-age={}", lifetime),
+        None => "public, max-age=31536000, immutable".to_owned(),
+    };
+    let e_tag = content_e_tag(&key);
+
+    if req.headers().contains_key(header::IF_NONE_MATCH)
+        && is_not_modified(req.headers(), Some(&e_tag), None)
+    {
+        let mut builder = HttpResponse::NotModified();
+        builder.append_header((header::ETAG, e_tag));
+        builder.append_header((header::CACHE_CONTROL, cache_control));
+        if negotiable {
+            builder.append_header((header::VARY, "Accept"));
+        }
+        return builder.finish();
+    }
+
     // Multimedia range
     let range: Option<String> = req
         .headers()
@@ -43,6 +150,19 @@ async fn view_file_by_hash(req: HttpRequest) -> impl Responder {
         }
     };
 
+    if is_not_modified(req.headers(), Some(&e_tag), res.last_modified.as_deref()) {
+        let mut builder = HttpResponse::NotModified();
+        builder.append_header((header::ETAG, e_tag));
+        if let Some(last_modified) = res.last_modified {
+            builder.append_header((header::LAST_MODIFIED, last_modified));
+        }
+        builder.append_header((header::CACHE_CONTROL, cache_control));
+        if negotiable {
+            builder.append_header((header::VARY, "Accept"));
+        }
+        return builder.finish();
+    }
+
     let body = res.body;
     let mut builder = HttpResponse::Ok();
 
@@ -67,9 +187,7 @@ async fn view_file_by_hash(req: HttpRequest) -> impl Responder {
             builder.content_type(content_type.as_str());
         }
     }
-    if let Some(e_tag) = res.e_tag {
-        builder.append_header((header::ETAG, e_tag));
-    }
+    builder.append_header((header::ETAG, e_tag));
     if let Some(content_range) = res.content_range {
         builder.append_header((header::CONTENT_RANGE, content_range));
         builder.status(StatusCode::PARTIAL_CONTENT);
@@ -81,19 +199,116 @@ async fn view_file_by_hash(req: HttpRequest) -> impl Responder {
         builder.append_header((header::LAST_MODIFIED, last_modified));
     }
 
-    builder.append_header(("Cache-Control", "public, max-age=31536000"));
+    builder.append_header((header::CACHE_CONTROL, cache_control));
+    if negotiable {
+        builder.append_header((header::VARY, "Accept"));
+    }
 
     builder.streaming(body)
 }
 
 /// Dynamically access public files through the webserver.
+/// Fingerprinted names (see [`crate::assets`]) change with the file, so they
+/// are cached for a year. Plain names don't change between builds, so
+/// browsers revalidate them on each use and get a 304 while the file is
+/// unchanged.
 #[get("/public/assets/{filename:.*}")]
-async fn view_public_file(req: HttpRequest) -> Result<fs::NamedFile, Error> {
-    let mut path: PathBuf = PathBuf::from("public/assets/");
+async fn view_public_file(req: HttpRequest) -> Result<impl Responder, Error> {
+    let mut path: PathBuf = PathBuf::from(crate::assets::PUBLIC_ASSET_DIR);
     let req_path: PathBuf = req.match_info().query("filename").parse().unwrap();
-    path.push(req_path.file_name().unwrap());
+    let file_name = req_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| actix_web::error::ErrorNotFound("404 - Resource not found"))?;
+
+    let cache_control = match crate::assets::resolve(file_name) {
+        Some(original) => {
+            path.push(original);
+            "public, max-age=31536000, immutable"
+        }
+        None => {
+            path.push(file_name);
+            "public, no-cache"
+        }
+    };
 
     let file = fs::NamedFile::open(path)?;
 
-    Ok(file.use_last_modified(true))
+    Ok(file
+        .use_etag(true)
+        .use_last_modified(true)
+        .customize()
+        .insert_header((header::CACHE_CONTROL, cache_control)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    const E_TAG: &str = "\"1700000000\"";
+    const LAST_MODIFIED: &str = "Tue, 14 Nov 2023 22:13:20 GMT";
+
+    fn check(headers: &[(header::HeaderName, &str)]) -> bool {
+        let mut req = TestRequest::default();
+        for (name, value) in headers {
+            req = req.insert_header((name.clone(), *value));
+        }
+        is_not_modified(
+            req.to_http_request().headers(),
+            Some(E_TAG),
+            Some(LAST_MODIFIED),
+        )
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        assert!(check(&[(header::IF_NONE_MATCH, E_TAG)]));
+        assert!(check(&[(header::IF_NONE_MATCH, "\"x\", W/\"1700000000\"")]));
+        assert!(check(&[(header::IF_NONE_MATCH, "*")]));
+        assert!(!check(&[(header::IF_NONE_MATCH, "\"1600000000\"")]));
+    }
+
+    #[test]
+    fn test_if_modified_since() {
+        assert!(check(&[(header::IF_MODIFIED_SINCE, LAST_MODIFIED)]));
+        assert!(check(&[(
+            header::IF_MODIFIED_SINCE,
+            "Wed, 15 Nov 2023 00:00:00 GMT"
+        )]));
+        assert!(!check(&[(
+            header::IF_MODIFIED_SINCE,
+            "Mon, 13 Nov 2023 00:00:00 GMT"
+        )]));
+        assert!(!check(&[(header::IF_MODIFIED_SINCE, "yesterday")]));
+    }
+
+    #[test]
+    fn test_if_none_match_takes_precedence() {
+        assert!(!check(&[
+            (header::IF_NONE_MATCH, "\"1600000000\""),
+            (header::IF_MODIFIED_SINCE, LAST_MODIFIED),
+        ]));
+    }
+
+    #[test]
+    fn test_unconditional_request() {
+        assert!(!check(&[]));
+    }
+
+    #[test]
+    fn test_content_e_tag_matches_without_storage() {
+        let e_tag = content_e_tag("9e08.webp");
+        assert_eq!(e_tag, "\"9e08.webp\"");
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"9e08.webp\""))
+            .to_http_request();
+        assert!(is_not_modified(req.headers(), Some(&e_tag), None));
+        assert!(!is_not_modified(
+            req.headers(),
+            Some(&content_e_tag("9e08.avif")),
+            None
+        ));
+    }
 }
@@ -1,5 +1,6 @@
 use super::implement::Session;
 use super::message;
+use super::moderation::{self, ModerationAction};
 use super::server::ChatServer;
 use super::{CLIENT_TIMEOUT, HEARTBEAT_INTERVAL};
 use actix::*;
@@ -18,8 +19,6 @@ pub struct Connection {
     pub room: Option<usize>,
     /// Chat server
     pub addr: Addr<ChatServer>,
-    /// Last command (any) sent
-    pub last_command: Instant,
     /// Maximum message length in bytes (from config)
     pub max_message_length: usize,
 }
@@ -48,6 +47,22 @@ impl Connection {
         });
     }
 
+    fn cmd_create_room(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
+        if args.len() != 2 {
+            ctx.text("Invalid command (no room title specified)");
+            return;
+        }
+
+        self.send_or_reply(
+            ctx,
+            message::CreateRoom {
+                id: self.id,
+                session: self.session.to_owned(),
+                title: args[1].trim().to_string(),
+            },
+        );
+    }
+
     fn cmd_delete(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
         if args.len() != 2 {
             ctx.text("Invalid command (no message specified?)");
@@ -69,6 +84,22 @@ impl Connection {
         }
     }
 
+    fn cmd_direct(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
+        if args.len() != 2 {
+            ctx.text("Invalid command (no user specified)");
+            return;
+        }
+
+        self.send_or_reply(
+            ctx,
+            message::OpenDirect {
+                id: self.id,
+                session: self.session.to_owned(),
+                username: args[1].trim().to_string(),
+            },
+        );
+    }
+
     fn cmd_edit(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
         if args.len() != 2 {
             ctx.text("Invalid command (no data supplied)");
@@ -101,6 +132,73 @@ impl Connection {
         };
     }
 
+    fn cmd_history(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
+        if args.len() != 2 {
+            ctx.text("Invalid command (no message specified?)");
+            return;
+        }
+
+        let room_id = match self.room {
+            Some(room_id) => room_id,
+            None => {
+                ctx.text("You are not in a room.");
+                return;
+            }
+        };
+
+        match args[1].parse::<u32>() {
+            Ok(before) => {
+                self.send_or_reply(
+                    ctx,
+                    message::History {
+                        id: self.id,
+                        session: self.session.to_owned(),
+                        room_id: room_id as u32,
+                        before,
+                    },
+                );
+            }
+            Err(_) => ctx.text("Invalid message specified."),
+        }
+    }
+
+    fn cmd_ignore(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
+        if args.len() != 2 {
+            ctx.text("Invalid command (no user specified)");
+            return;
+        }
+
+        self.send_or_reply(
+            ctx,
+            message::Ignore {
+                id: self.id,
+                session: self.session.to_owned(),
+                username: args[1].trim().to_string(),
+                ignore: args[0] == "/ignore",
+            },
+        );
+    }
+
+    fn cmd_invite(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
+        if args.len() != 2 {
+            ctx.text("Invalid command (no user specified)");
+            return;
+        }
+
+        match self.room {
+            Some(room_id) => self.send_or_reply(
+                ctx,
+                message::Invite {
+                    id: self.id,
+                    session: self.session.to_owned(),
+                    room_id: room_id as u32,
+                    username: args[1].trim().to_string(),
+                },
+            ),
+            None => ctx.text("You are not in a room."),
+        }
+    }
+
     fn cmd_join(&mut self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
         if args.len() != 2 {
             ctx.text("Invalid command (no room specified)");
@@ -123,6 +221,113 @@ impl Connection {
         }
     }
 
+    fn cmd_leave(&self, ctx: &mut ws::WebsocketContext<Self>, _: Vec<&str>) {
+        match self.room {
+            Some(room_id) => self.send_or_reply(
+                ctx,
+                message::Leave {
+                    id: self.id,
+                    session: self.session.to_owned(),
+                    room_id: room_id as u32,
+                },
+            ),
+            None => ctx.text("You are not in a room."),
+        }
+    }
+
+    /// `/kick`, `/ban`, `/unban`, `/mute`, `/unmute` and `/purge`, as
+    /// `/command username [duration] [reason]`. Only bans and mutes take a
+    /// duration.
+    fn cmd_moderate(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
+        if args.len() != 2 {
+            ctx.text("Invalid command (no user specified)");
+            return;
+        }
+
+        let room_id = match self.room {
+            Some(room_id) => room_id,
+            None => {
+                ctx.text("You are not in a room.");
+                return;
+            }
+        };
+
+        let mut words = args[1].split_whitespace();
+        let username = match words.next() {
+            Some(username) => username.to_string(),
+            None => {
+                ctx.text("Invalid command (no user specified)");
+                return;
+            }
+        };
+        let mut rest: Vec<&str> = words.collect();
+
+        let takes_duration = matches!(args[0], "/ban" | "/mute");
+        let duration = match rest.first() {
+            Some(word) if takes_duration => moderation::parse_duration(word),
+            _ => None,
+        };
+        if duration.is_some() {
+            rest.remove(0);
+        }
+        let reason = Some(rest.join(" ")).filter(|reason| !reason.is_empty());
+
+        let action = match args[0] {
+            "/kick" => ModerationAction::Kick,
+            "/ban" => ModerationAction::Ban(duration),
+            "/unban" => ModerationAction::Unban,
+            "/mute" => ModerationAction::Mute(duration.unwrap_or(moderation::DEFAULT_MUTE_SECONDS)),
+            "/unmute" => ModerationAction::Unmute,
+            _ => ModerationAction::Purge,
+        };
+
+        self.send_or_reply(
+            ctx,
+            message::Moderate {
+                id: self.id,
+                session: self.session.to_owned(),
+                room_id: room_id as u32,
+                username,
+                action,
+                reason,
+            },
+        );
+    }
+
+    fn cmd_react(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
+        let ids: Vec<u32> = match args.get(1) {
+            Some(args) => args
+                .split_whitespace()
+                .filter_map(|arg| arg.parse::<u32>().ok())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if ids.len() != 2 {
+            ctx.text("Invalid command (usage: /react <message> <reaction>)");
+            return;
+        }
+
+        let room_id = match self.room {
+            Some(room_id) => room_id as u32,
+            None => {
+                ctx.text("You are not in a room.");
+                return;
+            }
+        };
+
+        self.send_or_reply(
+            ctx,
+            message::React {
+                id: self.id,
+                session: self.session.to_owned(),
+                room_id,
+                message_id: ids[0],
+                reaction_type_id: ids[1],
+            },
+        );
+    }
+
     fn cmd_restart(&mut self, ctx: &mut ws::WebsocketContext<Self>, _: Vec<&str>) {
         self.send_or_reply(
             ctx,
@@ -133,6 +338,32 @@ impl Connection {
         );
     }
 
+    /// `/typing start` and `/typing stop`. Clients renew `start` while the
+    /// user keeps typing, or it times out.
+    fn cmd_typing(&self, ctx: &mut ws::WebsocketContext<Self>, args: Vec<&str>) {
+        let typing = match args.get(1).map(|arg| arg.trim()) {
+            Some("start") => true,
+            Some("stop") => false,
+            _ => {
+                ctx.text("Invalid command (expected start or stop)");
+                return;
+            }
+        };
+
+        // Typing outside a room goes nowhere.
+        if let Some(room_id) = self.room {
+            self.send_or_reply(
+                ctx,
+                message::Typing {
+                    id: self.id,
+                    session: self.session.to_owned(),
+                    room_id: room_id as u32,
+                    typing,
+                },
+            );
+        }
+    }
+
     /// Try to send message
     ///
     /// This method fails if actor's mailbox is full or closed. This method
@@ -236,10 +467,21 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Connection {
                 if m.starts_with('/') {
                     let v: Vec<&str> = m.splitn(2, ' ').collect();
                     match v[0] {
+                        "/ban" | "/kick" | "/mute" | "/purge" | "/unban" | "/unmute" => {
+                            self.cmd_moderate(ctx, v)
+                        }
                         "/delete" => self.cmd_delete(ctx, v),
+                        "/dm" => self.cmd_direct(ctx, v),
                         "/edit" => self.cmd_edit(ctx, v),
+                        "/history" => self.cmd_history(ctx, v),
+                        "/ignore" | "/unignore" => self.cmd_ignore(ctx, v),
+                        "/invite" => self.cmd_invite(ctx, v),
                         "/join" => self.cmd_join(ctx, v),
+                        "/leave" => self.cmd_leave(ctx, v),
+                        "/react" => self.cmd_react(ctx, v),
                         "/reset" => self.cmd_restart(ctx, v),
+                        "/room" => self.cmd_create_room(ctx, v),
+                        "/typing" => self.cmd_typing(ctx, v),
                         _ => ctx.text(format!("Unknown command: {:?}", m)),
                     }
                 }
@@ -0,0 +1,316 @@
+//! Chat flood control
+//!
+//! Each user and each room has a token bucket that refills over a ten second
+//! window, so short bursts are fine but a steady stream is not. Users also
+//! can't repeat a message straight away, and new accounts can be kept from
+//! posting links. Like sanctions, this state lives in the `ChatServer` and is
+//! per instance.
+
+use crate::config::Config;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// The window message limits are counted over
+pub const FLOOD_WINDOW: Duration = Duration::from_secs(10);
+
+/// Limits from the chat settings. A limit of 0 turns that check off, except
+/// for links, where 0 means new users can't post any.
+#[derive(Clone, Debug)]
+pub struct FloodLimits {
+    /// Messages one user can send per `FLOOD_WINDOW`
+    pub user_messages: u32,
+    /// Messages a room takes per `FLOOD_WINDOW`, from everyone
+    pub room_messages: u32,
+    /// How long the same user can't send the same message again
+    pub duplicate_window: Duration,
+    /// How old an account must be before link limits stop applying
+    pub new_user_age: Duration,
+    /// Links a new user can put in one message
+    pub new_user_max_links: usize,
+}
+
+impl FloodLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            user_messages: config.chat_user_messages_per_10s(),
+            room_messages: config.chat_room_messages_per_10s(),
+            duplicate_window: Duration::from_secs(config.chat_duplicate_window_seconds()),
+            new_user_age: Duration::from_secs(config.chat_new_user_hours() * 60 * 60),
+            new_user_max_links: config.chat_new_user_max_links(),
+        }
+    }
+
+    /// Whether an account this old still counts as new
+    pub fn is_new_user(&self, account_age: Duration) -> bool {
+        account_age < self.new_user_age
+    }
+}
+
+/// Why a message was held back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// The user is over their limit, and can post again in this many seconds.
+    UserRate(u64),
+    /// The room is over its limit.
+    RoomRate,
+    /// The user just sent the same message.
+    Duplicate,
+    /// A new user sent more links than allowed.
+    TooManyLinks(usize),
+}
+
+impl Rejection {
+    /// What the sender is told
+    pub fn message(&self) -> String {
+        match self {
+            Self::UserRate(seconds) => format!(
+                "You are sending messages too quickly. Please wait {} seconds.",
+                seconds
+            ),
+            Self::RoomRate => {
+                "This room is very busy. Please wait a moment before posting.".to_owned()
+            }
+            Self::Duplicate => "You just sent that message.".to_owned(),
+            Self::TooManyLinks(0) => "New accounts can't post links yet.".to_owned(),
+            Self::TooManyLinks(max) => {
+                format!("New accounts can post at most {} links per message.", max)
+            }
+        }
+    }
+}
+
+/// Tokens that refill at `capacity` per `FLOOD_WINDOW`.
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, now: Instant) -> Self {
+        Self {
+            tokens: capacity as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, capacity: u32, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let rate = capacity as f64 / FLOOD_WINDOW.as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity as f64);
+        self.updated = now;
+    }
+
+    /// Seconds until a token is free, or None if one is free now.
+    fn wait(&self, capacity: u32) -> Option<u64> {
+        if self.tokens >= 1.0 {
+            return None;
+        }
+
+        let rate = capacity as f64 / FLOOD_WINDOW.as_secs_f64();
+        Some(((1.0 - self.tokens) / rate).ceil() as u64)
+    }
+
+    fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+#[derive(Default)]
+pub struct FloodControl {
+    /// User Id -> Bucket
+    users: HashMap<u32, TokenBucket>,
+    /// Room Id -> Bucket
+    rooms: HashMap<u32, TokenBucket>,
+    /// User Id -> Hash of their last message, and when it was sent
+    last_message: HashMap<u32, (u64, Instant)>,
+}
+
+impl FloodControl {
+    /// Check a message against the limits. If it passes, it is counted
+    /// against the user and the room; if not, nothing is counted.
+    pub fn check(
+        &mut self,
+        limits: &FloodLimits,
+        user_id: u32,
+        room_id: u32,
+        message: &str,
+        is_new_user: bool,
+        now: Instant,
+    ) -> Result<(), Rejection> {
+        if is_new_user && count_links(message) > limits.new_user_max_links {
+            return Err(Rejection::TooManyLinks(limits.new_user_max_links));
+        }
+
+        let hash = message_hash(message);
+        if !limits.duplicate_window.is_zero() {
+            if let Some((last_hash, sent)) = self.last_message.get(&user_id) {
+                if *last_hash == hash
+                    && now.saturating_duration_since(*sent) < limits.duplicate_window
+                {
+                    return Err(Rejection::Duplicate);
+                }
+            }
+        }
+
+        if limits.user_messages > 0 {
+            let bucket = self
+                .users
+                .entry(user_id)
+                .or_insert_with(|| TokenBucket::new(limits.user_messages, now));
+            bucket.refill(limits.user_messages, now);
+            if let Some(seconds) = bucket.wait(limits.user_messages) {
+                return Err(Rejection::UserRate(seconds));
+            }
+        }
+
+        if limits.room_messages > 0 {
+            let bucket = self
+                .rooms
+                .entry(room_id)
+                .or_insert_with(|| TokenBucket::new(limits.room_messages, now));
+            bucket.refill(limits.room_messages, now);
+            if bucket.wait(limits.room_messages).is_some() {
+                return Err(Rejection::RoomRate);
+            }
+        }
+
+        // Every check passed, so count the message.
+        if let Some(bucket) = self.users.get_mut(&user_id) {
+            bucket.take();
+        }
+        if let Some(bucket) = self.rooms.get_mut(&room_id) {
+            bucket.take();
+        }
+        self.last_message.insert(user_id, (hash, now));
+
+        Ok(())
+    }
+
+    /// Forget users and rooms that have been quiet long enough for their
+    /// buckets to refill and their last message to stop counting.
+    pub fn prune(&mut self, limits: &FloodLimits, now: Instant) {
+        let keep = FLOOD_WINDOW.max(limits.duplicate_window);
+
+        self.users
+            .retain(|_, bucket| now.saturating_duration_since(bucket.updated) < keep);
+        self.rooms
+            .retain(|_, bucket| now.saturating_duration_since(bucket.updated) < keep);
+        self.last_message
+            .retain(|_, (_, sent)| now.saturating_duration_since(*sent) < keep);
+    }
+}
+
+/// Links in a message, as the BbCode parser would find them
+fn count_links(message: &str) -> usize {
+    let mut finder = linkify::LinkFinder::new();
+    finder.kinds(&[linkify::LinkKind::Url]);
+    finder.links(message).count()
+}
+
+/// Messages differing only in case or surrounding whitespace are the same
+fn message_hash(message: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    message.trim().to_lowercase().hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> FloodLimits {
+        FloodLimits {
+            user_messages: 3,
+            room_messages: 5,
+            duplicate_window: Duration::from_secs(30),
+            new_user_age: Duration::from_secs(60 * 60 * 24),
+            new_user_max_links: 0,
+        }
+    }
+
+    #[test]
+    fn test_user_bucket_refills() {
+        let mut flood = FloodControl::default();
+        let limits = limits();
+        let now = Instant::now();
+
+        for n in 0..3 {
+            assert!(flood
+                .check(&limits, 1, 1, &format!("hello {}", n), false, now)
+                .is_ok());
+        }
+        assert_eq!(
+            flood.check(&limits, 1, 1, "hello 3", false, now),
+            Err(Rejection::UserRate(4))
+        );
+
+        let later = now + Duration::from_secs(4);
+        assert!(flood.check(&limits, 1, 1, "hello 3", false, later).is_ok());
+    }
+
+    #[test]
+    fn test_room_bucket_is_shared() {
+        let mut flood = FloodControl::default();
+        let limits = limits();
+        let now = Instant::now();
+
+        for user_id in 1..=5 {
+            assert!(flood.check(&limits, user_id, 1, "hi", false, now).is_ok());
+        }
+        assert_eq!(
+            flood.check(&limits, 6, 1, "hi", false, now),
+            Err(Rejection::RoomRate)
+        );
+        assert!(flood.check(&limits, 6, 2, "hi", false, now).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_suppressed() {
+        let mut flood = FloodControl::default();
+        let limits = limits();
+        let now = Instant::now();
+
+        assert!(flood.check(&limits, 1, 1, "spam", false, now).is_ok());
+        assert_eq!(
+            flood.check(&limits, 1, 1, " SPAM ", false, now),
+            Err(Rejection::Duplicate)
+        );
+        assert!(flood.check(&limits, 2, 1, "spam", false, now).is_ok());
+
+        let later = now + Duration::from_secs(31);
+        assert!(flood.check(&limits, 1, 1, "spam", false, later).is_ok());
+    }
+
+    #[test]
+    fn test_new_user_links() {
+        let mut flood = FloodControl::default();
+        let mut limits = limits();
+        let now = Instant::now();
+        let message = "see https://example.com";
+
+        assert_eq!(
+            flood.check(&limits, 1, 1, message, true, now),
+            Err(Rejection::TooManyLinks(0))
+        );
+        assert!(flood.check(&limits, 2, 1, message, false, now).is_ok());
+
+        limits.new_user_max_links = 1;
+        assert!(flood.check(&limits, 1, 1, message, true, now).is_ok());
+    }
+
+    #[test]
+    fn test_rejected_message_is_not_counted() {
+        let mut flood = FloodControl::default();
+        let mut limits = limits();
+        limits.user_messages = 1;
+        let now = Instant::now();
+
+        assert!(flood
+            .check(&limits, 1, 1, "https://example.com", true, now)
+            .is_err());
+        assert!(flood.check(&limits, 1, 1, "hello", true, now).is_ok());
+    }
+}
@@ -1,4 +1,5 @@
 use super::message;
+use super::moderation::ModerationAction;
 use crate::user::Profile;
 use actix::prelude::*;
 use chrono::{NaiveDateTime, Utc};
@@ -30,7 +31,7 @@ impl From<&Session> for Author {
 }
 
 /// Author data exposed to the client through chat.
-#[derive(Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct UserActivity {
     pub id: u32,
     pub username: String,
@@ -93,12 +94,35 @@ pub struct MessagePgSql {
     pub message: String,
 }
 
+/// A reaction users can put on chat messages.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReactionType {
+    pub id: u32,
+    pub name: String,
+    pub emoji: String,
+}
+
+/// Reactions of one type on a message.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReactionTally {
+    /// Reaction type ID
+    pub id: u32,
+    pub emoji: String,
+    pub count: u32,
+    /// Users who reacted, so clients can highlight their own
+    pub users: Vec<u32>,
+}
+
 pub struct Room {
     pub id: u32,
     pub title: String,
     pub description: String,
     pub motd: Option<String>,
     pub display_order: u32,
+    /// Only members can see or join a private room
+    pub is_private: bool,
+    /// Messages arrived since the user last read the room
+    pub unread: bool,
 }
 
 /// Private session data for chat.
@@ -110,6 +134,8 @@ pub struct Session {
     pub avatar_url: String,
     pub ignored_users: Vec<u32>,
     pub is_staff: bool,
+    /// Unix timestamp the account was made, if known
+    pub joined_at: Option<u64>,
 }
 
 impl Default for Session {
@@ -120,6 +146,7 @@ impl Default for Session {
             avatar_url: String::new(),
             ignored_users: Default::default(),
             is_staff: false,
+            joined_at: None,
         }
     }
 }
@@ -128,6 +155,10 @@ impl Session {
     pub fn can_send_message(&self) -> bool {
         self.id > 0
     }
+
+    pub fn is_ignoring(&self, user_id: u32) -> bool {
+        self.ignored_users.contains(&user_id)
+    }
 }
 
 #[derive(Debug)]
@@ -181,18 +212,131 @@ impl From<&serde_json::Value> for SpriteParams {
 
 #[async_trait::async_trait]
 pub trait ChatLayer {
-    async fn can_send_message(&self, session: &Session) -> bool;
+    async fn can_send_message(&self, session: &Session, room_id: u32) -> bool;
     async fn can_view(&self, session_id: u32, room_id: u32) -> bool;
     async fn delete_message(&self, id: u32);
     async fn edit_message(&self, id: u32, author: Author, message: String) -> Option<Message>;
     async fn get_message(&self, message_id: u32) -> Option<Message>;
-    async fn get_room_history(&self, room_id: u32, limit: usize) -> Vec<(Author, Message)>;
+    /// Newest messages in a room, oldest first. With `before`, only messages
+    /// older than that message ID are returned, for scrollback.
+    async fn get_room_history(
+        &self,
+        room_id: u32,
+        before: Option<u32>,
+        limit: usize,
+    ) -> Vec<(Author, Message)>;
     async fn get_room_list(&self) -> Vec<Room>;
     async fn get_session_from_user_id(&self, id: u32) -> Session;
     async fn get_smilie_list(&self) -> Vec<Smilie>;
     fn get_session_key_from_request(&self, req: &actix_web::HttpRequest) -> Option<String>;
     async fn get_user_id_from_token(&self, cookie: Option<String>) -> u32;
     async fn insert_chat_message(&self, message: &message::Post) -> Option<Message>;
+
+    /// Whether the session user can use moderation commands in a room.
+    async fn can_moderate(&self, session: &Session, _room_id: u32) -> bool {
+        session.is_staff
+    }
+    /// Look up a user by name.
+    async fn find_author_by_name(&self, _username: &str) -> Option<Author> {
+        None
+    }
+    /// Record a moderation command in the moderation log.
+    async fn log_moderation(
+        &self,
+        _moderator: &Session,
+        _action: &ModerationAction,
+        _target_id: u32,
+        _room_id: u32,
+        _reason: Option<&str>,
+    ) {
+    }
+
+    // Reactions are optional. Layers without them keep these defaults.
+
+    /// Reactions users can choose from.
+    async fn get_reaction_types(&self) -> Vec<ReactionType> {
+        Vec::new()
+    }
+    /// Reactions on each of the given messages, by message ID. Messages
+    /// without reactions may be left out.
+    async fn get_reactions(&self, _message_ids: &[u32]) -> HashMap<u32, Vec<ReactionTally>> {
+        HashMap::new()
+    }
+    /// Add the user's reaction to a message, or take it back if they already
+    /// reacted with it. Returns whether it was added, or None if it failed.
+    async fn toggle_reaction(
+        &self,
+        _user_id: u32,
+        _message_id: u32,
+        _reaction_type_id: u32,
+    ) -> Option<bool> {
+        None
+    }
+
+    // Private rooms are optional. Layers without them keep these defaults.
+
+    /// Private rooms and direct messages the user is a member of.
+    async fn get_private_rooms(&self, _user_id: u32) -> Vec<Room> {
+        Vec::new()
+    }
+    /// Members of a private room. Public rooms have none.
+    async fn get_room_members(&self, _room_id: u32) -> Vec<u32> {
+        Vec::new()
+    }
+    /// The other member of a direct message room. Other rooms have none.
+    async fn get_direct_partner(&self, _room_id: u32, _user_id: u32) -> Option<u32> {
+        None
+    }
+    /// Find or create the direct message room between the session user and
+    /// another user. Returns the room, titled for the session user, and the
+    /// other user.
+    async fn open_direct_room(
+        &self,
+        _session: &Session,
+        _username: &str,
+    ) -> Option<(Room, Author)> {
+        None
+    }
+    /// Create a private room with the session user as its first member.
+    async fn create_private_room(&self, _session: &Session, _title: &str) -> Option<Room> {
+        None
+    }
+    /// Add a user to a private room the session user is a member of.
+    async fn invite_to_room(
+        &self,
+        _session: &Session,
+        _room_id: u32,
+        _username: &str,
+    ) -> Option<(Room, Author)> {
+        None
+    }
+    /// Remove the session user from a private room.
+    async fn leave_room(&self, _session: &Session, _room_id: u32) -> bool {
+        false
+    }
+    /// Record that a member has seen everything in a room.
+    async fn mark_room_read(&self, _user_id: u32, _room_id: u32) {}
+    /// Notify members of a private room about a message they weren't there for.
+    async fn notify_room_message(
+        &self,
+        _author: &Author,
+        _room_id: u32,
+        _message: &str,
+        _recipients: &[u32],
+    ) {
+    }
+
+    // Ignore lists are optional. Layers without them keep these defaults.
+
+    /// Whether a user is ignoring another.
+    async fn is_ignoring(&self, _user_id: u32, _other_id: u32) -> bool {
+        false
+    }
+    /// Start or stop ignoring another user. Returns false if that failed or
+    /// the layer can't change ignore lists.
+    async fn set_ignoring(&self, _user_id: u32, _other_id: u32, _ignore: bool) -> bool {
+        false
+    }
 }
 
 // When we diverge from the XF compat, this can probably be compressed out of a trait.
@@ -201,7 +345,12 @@ pub mod default {
     use super::*;
     use crate::config::Config;
     use crate::middleware::ClientCtx;
-    use crate::orm::{chat_messages, chat_rooms, posts, ugc_deletions, ugc_revisions, users};
+    use crate::notifications::{grouping, NotificationType};
+    use crate::orm::{
+        attachments, chat_messages, chat_room_members, chat_rooms, mod_log, posts, reaction_types,
+        ugc_attachments, ugc_deletions, ugc_reactions, ugc_revisions, user_ignores, user_uploads,
+        users,
+    };
     use crate::ugc::{create_ugc, create_ugc_revision, NewUgcPartial};
     use crate::user::{find_also_user, Profile as UserProfile};
     use sea_orm::{entity::*, query::*, DatabaseConnection, EntityTrait, QuerySelect, Set};
@@ -210,6 +359,24 @@ pub mod default {
     pub struct Layer {
         pub db: DatabaseConnection,
         pub config: Arc<Config>,
+        pub permissions: crate::permission::Permissions,
+    }
+
+    /// Longest title a user may give a private room, in characters
+    const PRIVATE_ROOM_TITLE_LENGTH: usize = 64;
+    /// Longest excerpt of a chat message put in a notification, in characters
+    const NOTIFICATION_EXCERPT_LENGTH: usize = 100;
+
+    fn to_room(room: chat_rooms::Model, unread: bool) -> Room {
+        Room {
+            id: room.id as u32,
+            title: room.title,
+            description: room.description.unwrap_or_default(),
+            motd: room.topic,
+            display_order: room.display_order as u32,
+            is_private: room.is_private,
+            unread,
+        }
     }
 
     impl Layer {
@@ -278,13 +445,196 @@ pub mod default {
 
             true
         }
+
+        /// Check a chat permission for a user in a room, with room overrides
+        async fn can_in_room(&self, user_id: u32, room_id: u32, permission: &str) -> bool {
+            let profile = if user_id > 0 {
+                UserProfile::get_by_id(&self.db, user_id as i32)
+                    .await
+                    .ok()
+                    .flatten()
+            } else {
+                None
+            };
+            let groups = crate::group::get_group_ids_for_client(&self.db, &profile).await;
+            let user_id = (user_id > 0).then_some(user_id as i32);
+
+            self.permissions
+                .read()
+                .can_in_chat_room(&groups, user_id, room_id as i32, permission)
+        }
+
+        async fn is_member(&self, user_id: u32, room_id: u32) -> bool {
+            if user_id == 0 {
+                return false;
+            }
+
+            chat_room_members::Entity::find_by_id((room_id as i32, user_id as i32))
+                .one(&self.db)
+                .await
+                .map(|member| member.is_some())
+                .unwrap_or(false)
+        }
+
+        /// Add a member to a private room. Existing members are left alone.
+        async fn add_member(&self, room_id: i32, user_id: i32, invited_by_id: Option<i32>) -> bool {
+            match chat_room_members::Entity::find_by_id((room_id, user_id))
+                .one(&self.db)
+                .await
+            {
+                Ok(Some(_)) => true,
+                Ok(None) => {
+                    match chat_room_members::Entity::insert(chat_room_members::ActiveModel {
+                        chat_room_id: Set(room_id),
+                        user_id: Set(user_id),
+                        invited_by_id: Set(invited_by_id),
+                        joined_at: Set(Utc::now().naive_utc()),
+                        last_read_message_id: Set(None),
+                    })
+                    .exec(&self.db)
+                    .await
+                    {
+                        Ok(_) => true,
+                        Err(err) => {
+                            log::error!(
+                                "Failed to add user {} to chat room {}: {:?}",
+                                user_id,
+                                room_id,
+                                err
+                            );
+                            false
+                        }
+                    }
+                }
+                Err(err) => {
+                    log::error!("Failed to check chat room {} members: {:?}", room_id, err);
+                    false
+                }
+            }
+        }
+
+        async fn insert_private_room(
+            &self,
+            title: String,
+            owner_id: i32,
+            direct_key: Option<String>,
+        ) -> Option<chat_rooms::Model> {
+            match (chat_rooms::ActiveModel {
+                title: Set(title),
+                description: Set(None),
+                topic: Set(None),
+                display_order: Set(0),
+                min_posts_required: Set(0),
+                min_account_age_hours: Set(0),
+                is_staff_only: Set(false),
+                is_private: Set(true),
+                owner_id: Set(Some(owner_id)),
+                direct_key: Set(direct_key),
+                ..Default::default()
+            })
+            .insert(&self.db)
+            .await
+            {
+                Ok(room) => Some(room),
+                Err(err) => {
+                    log::error!("Failed to create private chat room: {:?}", err);
+                    None
+                }
+            }
+        }
+
+        /// The other member of a direct message room, for its title
+        async fn direct_partner(&self, room_id: i32, user_id: u32) -> Option<Author> {
+            let member = chat_room_members::Entity::find()
+                .filter(chat_room_members::Column::ChatRoomId.eq(room_id))
+                .filter(chat_room_members::Column::UserId.ne(user_id as i32))
+                .one(&self.db)
+                .await
+                .ok()
+                .flatten()?;
+            let session = self.get_session_from_user_id(member.user_id as u32).await;
+
+            Some(Author::from(&session))
+        }
+
+        /// Users the user is ignoring
+        async fn get_ignored_users(&self, user_id: u32) -> Vec<u32> {
+            if user_id == 0 {
+                return Vec::new();
+            }
+
+            user_ignores::Entity::find()
+                .filter(user_ignores::Column::UserId.eq(user_id as i32))
+                .all(&self.db)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|ignore| ignore.ignored_user_id as u32)
+                .collect()
+        }
+
+        /// Attach the files a message links to, so they are kept while it is.
+        /// Only files the author uploaded count, so a link can't make someone
+        /// else's conversation attachment visible in chat.
+        async fn link_shared_files(&self, revision: &ugc_revisions::Model) {
+            let user_id = match revision.user_id {
+                Some(user_id) => user_id,
+                None => return,
+            };
+            let hashes = super::super::upload::shared_file_hashes(&revision.content);
+            if hashes.is_empty() {
+                return;
+            }
+
+            let files = attachments::Entity::find()
+                .filter(attachments::Column::Hash.is_in(hashes))
+                .all(&self.db)
+                .await
+                .unwrap_or_default();
+            let uploaded: Vec<i32> = user_uploads::Entity::find()
+                .filter(user_uploads::Column::UserId.eq(user_id))
+                .filter(
+                    user_uploads::Column::AttachmentId
+                        .is_in(files.iter().map(|file| file.id).collect::<Vec<i32>>()),
+                )
+                .all(&self.db)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|upload| upload.attachment_id)
+                .collect();
+
+            for file in files.iter().filter(|file| uploaded.contains(&file.id)) {
+                if let Err(err) = (ugc_attachments::ActiveModel {
+                    attachment_id: Set(file.id),
+                    ugc_id: Set(revision.ugc_id),
+                    user_id: Set(Some(user_id)),
+                    created_at: Set(revision.created_at),
+                    filename: Set(file.filename.to_owned()),
+                    ..Default::default()
+                })
+                .insert(&self.db)
+                .await
+                {
+                    log::error!("Failed to attach file to chat message: {:?}", err);
+                }
+            }
+
+            crate::filesystem::visibility::refresh_attachments_visibility(uploaded).await;
+        }
     }
 
     #[async_trait::async_trait]
     impl super::ChatLayer for Layer {
-        async fn can_send_message(&self, session: &Session) -> bool {
-            // User must be logged in
+        async fn can_send_message(&self, session: &Session, room_id: u32) -> bool {
+            // User must be logged in and able to see the room
             session.id > 0
+                && self.can_view(session.id, room_id).await
+                && self.can_in_room(session.id, room_id, "chat.post").await
+        }
+
+        async fn can_moderate(&self, session: &Session, room_id: u32) -> bool {
+            session.id > 0 && self.can_in_room(session.id, room_id, "chat.moderate").await
         }
 
         async fn can_view(&self, session_id: u32, room_id: u32) -> bool {
@@ -297,7 +647,14 @@ pub mod default {
                 _ => return false, // Room not found
             };
 
+            // Private rooms are for their members only
+            if room.is_private {
+                return self.is_member(session_id, room_id).await
+                    && self.can_in_room(session_id, room_id, "chat.view").await;
+            }
+
             self.check_room_access(session_id, &room).await
+                && self.can_in_room(session_id, room_id, "chat.view").await
         }
 
         async fn delete_message(&self, id: u32) {
@@ -395,6 +752,17 @@ pub mod default {
             })
         }
 
+        async fn find_author_by_name(&self, username: &str) -> Option<Author> {
+            let user_id = crate::user::get_user_id_from_name(&self.db, username.trim()).await?;
+            let session = self.get_session_from_user_id(user_id as u32).await;
+
+            if session.id > 0 {
+                Some(Author::from(&session))
+            } else {
+                None
+            }
+        }
+
         async fn get_message(&self, id: u32) -> Option<super::Message> {
             chat_messages::Entity::find_by_id(id as i32)
                 .select_only()
@@ -414,20 +782,12 @@ pub mod default {
 
         async fn get_room_list(&self) -> Vec<Room> {
             match chat_rooms::Entity::find()
+                .filter(chat_rooms::Column::IsPrivate.eq(false))
                 .order_by_asc(chat_rooms::Column::DisplayOrder)
                 .all(&self.db)
                 .await
             {
-                Ok(rooms) => rooms
-                    .into_iter()
-                    .map(|r| super::Room {
-                        id: r.id as u32,
-                        title: r.title,
-                        description: r.description.unwrap_or_default(),
-                        motd: None,
-                        display_order: r.display_order as u32,
-                    })
-                    .collect(),
+                Ok(rooms) => rooms.into_iter().map(|r| to_room(r, false)).collect(),
                 Err(err) => {
                     log::error!("Failed to get chat room list: {:?}", err);
                     Vec::new()
@@ -435,8 +795,13 @@ pub mod default {
             }
         }
 
-        async fn get_room_history(&self, id: u32, limit: usize) -> Vec<(Author, super::Message)> {
-            let sneed = find_also_user(
+        async fn get_room_history(
+            &self,
+            id: u32,
+            before: Option<u32>,
+            limit: usize,
+        ) -> Vec<(Author, super::Message)> {
+            let mut select = find_also_user(
                 chat_messages::Entity::find()
                     .select_only()
                     .column_as(chat_messages::Column::UserId, "user_id")
@@ -445,40 +810,49 @@ pub mod default {
                     .column_as(chat_messages::Column::CreatedAt, "message_date")
                     .left_join(ugc_revisions::Entity)
                     .column_as(ugc_revisions::Column::Content, "message")
-                    .column_as(ugc_revisions::Column::CreatedAt, "message_edit_date"),
+                    .column_as(ugc_revisions::Column::CreatedAt, "message_edit_date")
+                    .left_join(ugc_deletions::Entity),
                 chat_messages::Column::UserId,
             )
             .filter(chat_messages::Column::ChatRoomId.eq(id as i32))
-            .limit(limit as u64)
-            .order_by_desc(chat_messages::Column::CreatedAt)
-            .into_model::<super::MessagePgSql, UserProfile>()
-            .all(&self.db)
-            .await
-            .unwrap_or_default()
-            .into_iter()
-            .rev()
-            .map(|(message, user)| {
-                (
-                    match user {
-                        Some(user) => super::Author {
-                            id: user.id as u32,
-                            username: user.name,
-                            avatar_url: user
-                                .avatar_filename
-                                .as_ref()
-                                .map(|f| crate::filesystem::get_file_url_by_filename(f, f))
-                                .unwrap_or_default(),
-                        },
-                        None => super::Author {
-                            id: 0,
-                            username: crate::constants::GUEST_USERNAME.to_owned(),
-                            avatar_url: String::new(),
+            // Deleted messages must not come back on reconnect
+            .filter(ugc_deletions::Column::Id.is_null());
+
+            if let Some(before) = before {
+                select = select.filter(chat_messages::Column::Id.lt(before as i32));
+            }
+
+            let sneed = select
+                .limit(limit as u64)
+                .order_by_desc(chat_messages::Column::Id)
+                .into_model::<super::MessagePgSql, UserProfile>()
+                .all(&self.db)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .rev()
+                .map(|(message, user)| {
+                    (
+                        match user {
+                            Some(user) => super::Author {
+                                id: user.id as u32,
+                                username: user.name,
+                                avatar_url: user
+                                    .avatar_filename
+                                    .as_ref()
+                                    .map(|f| crate::filesystem::get_file_url_by_filename(f, f))
+                                    .unwrap_or_default(),
+                            },
+                            None => super::Author {
+                                id: 0,
+                                username: crate::constants::GUEST_USERNAME.to_owned(),
+                                avatar_url: String::new(),
+                            },
                         },
-                    },
-                    message.into(),
-                )
-            })
-            .collect();
+                        message.into(),
+                    )
+                })
+                .collect();
 
             sneed
         }
@@ -497,8 +871,9 @@ pub mod default {
                         .as_ref()
                         .map(|f| crate::filesystem::get_file_url_by_filename(f, f))
                         .unwrap_or_default(),
-                    ignored_users: Vec::new(),
+                    ignored_users: self.get_ignored_users(id).await,
                     is_staff: false,
+                    joined_at: Some(user.created_at.and_utc().timestamp().max(0) as u64),
                 }
             } else {
                 Session::default()
@@ -557,6 +932,8 @@ pub mod default {
                 }
             };
 
+            self.link_shared_files(&ugc_revision).await;
+
             Some(super::Message {
                 user_id: chat_message.user_id.unwrap_or(0) as u32,
                 room_id: chat_message.chat_room_id as u32,
@@ -566,5 +943,529 @@ pub mod default {
                 message_id: chat_message.id as u32,
             })
         }
+
+        async fn log_moderation(
+            &self,
+            moderator: &Session,
+            action: &ModerationAction,
+            target_id: u32,
+            room_id: u32,
+            reason: Option<&str>,
+        ) {
+            let metadata = serde_json::json!({
+                "chat_room_id": room_id,
+                "duration_seconds": action.duration(),
+            });
+
+            let log_entry = mod_log::ActiveModel {
+                moderator_id: Set(Some(moderator.id as i32)),
+                action: Set(action.log_name().to_string()),
+                target_type: Set("user".to_string()),
+                target_id: Set(target_id as i32),
+                reason: Set(reason.map(|s| s.to_string())),
+                metadata: Set(Some(metadata)),
+                created_at: Set(Utc::now().naive_utc()),
+                ..Default::default()
+            };
+
+            if let Err(err) = mod_log::Entity::insert(log_entry).exec(&self.db).await {
+                log::error!("Failed to log chat moderation action: {:?}", err);
+            }
+        }
+
+        async fn get_reaction_types(&self) -> Vec<ReactionType> {
+            match reaction_types::Entity::find()
+                .filter(reaction_types::Column::IsActive.eq(true))
+                .order_by_asc(reaction_types::Column::DisplayOrder)
+                .all(&self.db)
+                .await
+            {
+                Ok(types) => types
+                    .into_iter()
+                    .map(|t| ReactionType {
+                        id: t.id as u32,
+                        name: t.name,
+                        emoji: t.emoji,
+                    })
+                    .collect(),
+                Err(err) => {
+                    log::error!("Failed to get reaction types for chat: {:?}", err);
+                    Vec::new()
+                }
+            }
+        }
+
+        async fn get_reactions(&self, message_ids: &[u32]) -> HashMap<u32, Vec<ReactionTally>> {
+            let mut tallies: HashMap<u32, Vec<ReactionTally>> = HashMap::new();
+            if message_ids.is_empty() {
+                return tallies;
+            }
+
+            // Reactions are kept against the UGC behind each message
+            let messages: HashMap<i32, u32> = match chat_messages::Entity::find()
+                .filter(chat_messages::Column::Id.is_in(message_ids.iter().map(|id| *id as i32)))
+                .all(&self.db)
+                .await
+            {
+                Ok(messages) => messages
+                    .into_iter()
+                    .map(|m| (m.ugc_id, m.id as u32))
+                    .collect(),
+                Err(err) => {
+                    log::error!("Failed to find chat messages for reactions: {:?}", err);
+                    return tallies;
+                }
+            };
+
+            let reactions = match ugc_reactions::Entity::find()
+                .filter(ugc_reactions::Column::UgcId.is_in(messages.keys().copied()))
+                .find_also_related(reaction_types::Entity)
+                .order_by_asc(ugc_reactions::Column::CreatedAt)
+                .all(&self.db)
+                .await
+            {
+                Ok(reactions) => reactions,
+                Err(err) => {
+                    log::error!("Failed to get chat message reactions: {:?}", err);
+                    return tallies;
+                }
+            };
+
+            // Tallies are in the order each reaction was first given
+            for (reaction, reaction_type) in reactions {
+                if let (Some(message_id), Some(reaction_type)) =
+                    (messages.get(&reaction.ugc_id), reaction_type)
+                {
+                    let message_tallies = tallies.entry(*message_id).or_default();
+                    match message_tallies
+                        .iter_mut()
+                        .find(|tally| tally.id == reaction_type.id as u32)
+                    {
+                        Some(tally) => {
+                            tally.count += 1;
+                            tally.users.push(reaction.user_id as u32);
+                        }
+                        None => message_tallies.push(ReactionTally {
+                            id: reaction_type.id as u32,
+                            emoji: reaction_type.emoji,
+                            count: 1,
+                            users: vec![reaction.user_id as u32],
+                        }),
+                    }
+                }
+            }
+
+            tallies
+        }
+
+        async fn toggle_reaction(
+            &self,
+            user_id: u32,
+            message_id: u32,
+            reaction_type_id: u32,
+        ) -> Option<bool> {
+            let chat_message = chat_messages::Entity::find_by_id(message_id as i32)
+                .one(&self.db)
+                .await
+                .ok()
+                .flatten()?;
+
+            // No reacting to deleted messages
+            let deleted = ugc_deletions::Entity::find_by_id(chat_message.ugc_id)
+                .one(&self.db)
+                .await
+                .map(|deletion| deletion.is_some())
+                .unwrap_or(true);
+            if deleted {
+                return None;
+            }
+
+            let reaction_type = reaction_types::Entity::find_by_id(reaction_type_id as i32)
+                .one(&self.db)
+                .await
+                .ok()
+                .flatten()
+                .filter(|t| t.is_active)?;
+
+            let existing = match ugc_reactions::Entity::find()
+                .filter(ugc_reactions::Column::UgcId.eq(chat_message.ugc_id))
+                .filter(ugc_reactions::Column::UserId.eq(user_id as i32))
+                .filter(ugc_reactions::Column::ReactionTypeId.eq(reaction_type.id))
+                .one(&self.db)
+                .await
+            {
+                Ok(existing) => existing,
+                Err(err) => {
+                    log::error!("Failed to find chat reaction: {:?}", err);
+                    return None;
+                }
+            };
+
+            match existing {
+                Some(existing) => {
+                    match ugc_reactions::Entity::delete_by_id(existing.id)
+                        .exec(&self.db)
+                        .await
+                    {
+                        Ok(_) => Some(false),
+                        Err(err) => {
+                            log::error!("Failed to remove chat reaction: {:?}", err);
+                            None
+                        }
+                    }
+                }
+                None => {
+                    let reaction = ugc_reactions::ActiveModel {
+                        ugc_id: Set(chat_message.ugc_id),
+                        user_id: Set(user_id as i32),
+                        reaction_type_id: Set(reaction_type.id),
+                        created_at: Set(Utc::now().naive_utc()),
+                        ..Default::default()
+                    };
+
+                    match reaction.insert(&self.db).await {
+                        Ok(_) => Some(true),
+                        Err(err) => {
+                            log::error!("Failed to add chat reaction: {:?}", err);
+                            None
+                        }
+                    }
+                }
+            }
+        }
+
+        async fn get_private_rooms(&self, user_id: u32) -> Vec<Room> {
+            let memberships = match chat_room_members::Entity::find()
+                .filter(chat_room_members::Column::UserId.eq(user_id as i32))
+                .order_by_asc(chat_room_members::Column::JoinedAt)
+                .find_also_related(chat_rooms::Entity)
+                .all(&self.db)
+                .await
+            {
+                Ok(memberships) => memberships,
+                Err(err) => {
+                    log::error!("Failed to get private chat rooms: {:?}", err);
+                    return Vec::new();
+                }
+            };
+
+            let mut rooms = Vec::with_capacity(memberships.len());
+            for (member, room) in memberships {
+                let mut room = match room {
+                    Some(room) => room,
+                    None => continue,
+                };
+
+                // Direct messages are titled after the other person
+                if room.direct_key.is_some() {
+                    match self.direct_partner(room.id, user_id).await {
+                        Some(partner) => room.title = partner.username,
+                        None => continue,
+                    }
+                }
+
+                let unread = chat_messages::Entity::find()
+                    .filter(chat_messages::Column::ChatRoomId.eq(room.id))
+                    .filter(chat_messages::Column::UserId.ne(user_id as i32))
+                    .filter(chat_messages::Column::Id.gt(member.last_read_message_id.unwrap_or(0)))
+                    .count(&self.db)
+                    .await
+                    .unwrap_or(0)
+                    > 0;
+
+                rooms.push(to_room(room, unread));
+            }
+
+            rooms
+        }
+
+        async fn get_room_members(&self, room_id: u32) -> Vec<u32> {
+            chat_room_members::Entity::find()
+                .filter(chat_room_members::Column::ChatRoomId.eq(room_id as i32))
+                .all(&self.db)
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|member| member.user_id as u32)
+                .collect()
+        }
+
+        async fn get_direct_partner(&self, room_id: u32, user_id: u32) -> Option<u32> {
+            match chat_rooms::Entity::find_by_id(room_id as i32)
+                .one(&self.db)
+                .await
+            {
+                Ok(Some(room)) if room.direct_key.is_some() => {}
+                _ => return None,
+            }
+
+            chat_room_members::Entity::find()
+                .filter(chat_room_members::Column::ChatRoomId.eq(room_id as i32))
+                .filter(chat_room_members::Column::UserId.ne(user_id as i32))
+                .one(&self.db)
+                .await
+                .ok()
+                .flatten()
+                .map(|member| member.user_id as u32)
+        }
+
+        async fn open_direct_room(
+            &self,
+            session: &Session,
+            username: &str,
+        ) -> Option<(Room, Author)> {
+            if session.id == 0 {
+                return None;
+            }
+
+            let partner = self.find_author_by_name(username).await?;
+            if partner.id == session.id {
+                return None;
+            }
+
+            // One room per pair of users, whoever opens it
+            let direct_key = format!(
+                "{}:{}",
+                session.id.min(partner.id),
+                session.id.max(partner.id)
+            );
+            let room = match chat_rooms::Entity::find()
+                .filter(chat_rooms::Column::DirectKey.eq(direct_key.as_str()))
+                .one(&self.db)
+                .await
+            {
+                Ok(Some(room)) => room,
+                Ok(None) => {
+                    self.insert_private_room(
+                        "Direct message".to_string(),
+                        session.id as i32,
+                        Some(direct_key),
+                    )
+                    .await?
+                }
+                Err(err) => {
+                    log::error!("Failed to find direct message room: {:?}", err);
+                    return None;
+                }
+            };
+
+            // Either side may have left; opening the room brings both back.
+            if !self.add_member(room.id, session.id as i32, None).await
+                || !self
+                    .add_member(room.id, partner.id as i32, Some(session.id as i32))
+                    .await
+            {
+                return None;
+            }
+
+            let mut room = to_room(room, false);
+            room.title = partner.username.to_owned();
+
+            Some((room, partner))
+        }
+
+        async fn create_private_room(&self, session: &Session, title: &str) -> Option<Room> {
+            let title: String = title
+                .trim()
+                .chars()
+                .take(PRIVATE_ROOM_TITLE_LENGTH)
+                .collect();
+            if session.id == 0 || title.is_empty() {
+                return None;
+            }
+
+            let room = self
+                .insert_private_room(title, session.id as i32, None)
+                .await?;
+
+            if self.add_member(room.id, session.id as i32, None).await {
+                Some(to_room(room, false))
+            } else {
+                None
+            }
+        }
+
+        async fn invite_to_room(
+            &self,
+            session: &Session,
+            room_id: u32,
+            username: &str,
+        ) -> Option<(Room, Author)> {
+            let room = chat_rooms::Entity::find_by_id(room_id as i32)
+                .one(&self.db)
+                .await
+                .ok()
+                .flatten()?;
+
+            // Direct messages stay between two people
+            if !room.is_private || room.direct_key.is_some() {
+                return None;
+            }
+            if !self.is_member(session.id, room_id).await {
+                return None;
+            }
+
+            let invitee = self.find_author_by_name(username).await?;
+            if self.is_member(invitee.id, room_id).await {
+                return None;
+            }
+            if !self
+                .add_member(room.id, invitee.id as i32, Some(session.id as i32))
+                .await
+            {
+                return None;
+            }
+
+            if let Err(err) = crate::notifications::dispatcher::notify_chat_invite(
+                invitee.id as i32,
+                room.id,
+                &room.title,
+                session.id as i32,
+                &session.username,
+            )
+            .await
+            {
+                log::warn!("Failed to notify chat room invite: {:?}", err);
+            }
+
+            Some((to_room(room, true), invitee))
+        }
+
+        async fn leave_room(&self, session: &Session, room_id: u32) -> bool {
+            match chat_room_members::Entity::delete_many()
+                .filter(chat_room_members::Column::ChatRoomId.eq(room_id as i32))
+                .filter(chat_room_members::Column::UserId.eq(session.id as i32))
+                .exec(&self.db)
+                .await
+            {
+                Ok(res) => res.rows_affected > 0,
+                Err(err) => {
+                    log::error!(
+                        "Failed to remove user {} from chat room {}: {:?}",
+                        session.id,
+                        room_id,
+                        err
+                    );
+                    false
+                }
+            }
+        }
+
+        async fn mark_room_read(&self, user_id: u32, room_id: u32) {
+            if !self.is_member(user_id, room_id).await {
+                return;
+            }
+
+            let last_message_id = chat_messages::Entity::find()
+                .filter(chat_messages::Column::ChatRoomId.eq(room_id as i32))
+                .order_by_desc(chat_messages::Column::Id)
+                .one(&self.db)
+                .await
+                .ok()
+                .flatten()
+                .map(|message| message.id);
+
+            if let Err(err) = chat_room_members::Entity::update_many()
+                .col_expr(
+                    chat_room_members::Column::LastReadMessageId,
+                    sea_orm::sea_query::Expr::value(last_message_id),
+                )
+                .filter(chat_room_members::Column::ChatRoomId.eq(room_id as i32))
+                .filter(chat_room_members::Column::UserId.eq(user_id as i32))
+                .exec(&self.db)
+                .await
+            {
+                log::error!("Failed to mark chat room {} read: {:?}", room_id, err);
+            }
+
+            if let Err(err) = crate::notifications::mark_group_read(
+                user_id as i32,
+                &grouping::group_key(&NotificationType::ChatMessage, "chat_room", room_id as i32),
+            )
+            .await
+            {
+                log::warn!("Failed to clear chat notifications: {:?}", err);
+            }
+        }
+
+        async fn notify_room_message(
+            &self,
+            author: &Author,
+            room_id: u32,
+            message: &str,
+            recipients: &[u32],
+        ) {
+            let room = match chat_rooms::Entity::find_by_id(room_id as i32)
+                .one(&self.db)
+                .await
+            {
+                Ok(Some(room)) if room.is_private => room,
+                _ => return,
+            };
+            let excerpt: String = message.chars().take(NOTIFICATION_EXCERPT_LENGTH).collect();
+
+            for recipient in recipients {
+                if let Err(err) = crate::notifications::dispatcher::notify_chat_message(
+                    *recipient as i32,
+                    room.id,
+                    &room.title,
+                    room.direct_key.is_some(),
+                    author.id as i32,
+                    author.username.to_owned(),
+                    &excerpt,
+                )
+                .await
+                {
+                    log::warn!("Failed to notify chat message: {:?}", err);
+                }
+            }
+        }
+
+        async fn is_ignoring(&self, user_id: u32, other_id: u32) -> bool {
+            matches!(
+                user_ignores::Entity::find_by_id((user_id as i32, other_id as i32))
+                    .one(&self.db)
+                    .await,
+                Ok(Some(_))
+            )
+        }
+
+        async fn set_ignoring(&self, user_id: u32, other_id: u32, ignore: bool) -> bool {
+            if user_id == 0 || user_id == other_id {
+                return false;
+            }
+
+            if !ignore {
+                return match user_ignores::Entity::delete_by_id((user_id as i32, other_id as i32))
+                    .exec(&self.db)
+                    .await
+                {
+                    Ok(_) => true,
+                    Err(err) => {
+                        log::error!("Failed to unignore user: {:?}", err);
+                        false
+                    }
+                };
+            }
+
+            if self.is_ignoring(user_id, other_id).await {
+                return true;
+            }
+
+            match (user_ignores::ActiveModel {
+                user_id: Set(user_id as i32),
+                ignored_user_id: Set(other_id as i32),
+                created_at: Set(Utc::now().naive_utc()),
+            })
+            .insert(&self.db)
+            .await
+            {
+                Ok(_) => true,
+                Err(err) => {
+                    log::error!("Failed to ignore user: {:?}", err);
+                    false
+                }
+            }
+        }
     }
 }
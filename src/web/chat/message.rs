@@ -1,6 +1,8 @@
 use super::implement;
+use super::moderation::{ModerationAction, Sanction};
 use actix::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 // Regarding Integers:
 // Database keys should be u32.
@@ -30,6 +32,18 @@ impl Message for Delete {
     type Result = ();
 }
 
+/// Request to create a private room.
+pub struct CreateRoom {
+    pub id: usize,
+    pub session: implement::Session,
+
+    pub title: String,
+}
+
+impl Message for CreateRoom {
+    type Result = ();
+}
+
 /// Announce disconnect
 pub struct Disconnect {
     pub id: usize,
@@ -53,6 +67,47 @@ impl Message for Edit {
     type Result = ();
 }
 
+/// Request older messages in a room for scrollback.
+pub struct History {
+    pub id: usize,
+    pub session: implement::Session,
+
+    pub room_id: u32,
+    /// Oldest message ID the client already has
+    pub before: u32,
+}
+
+impl Message for History {
+    type Result = ();
+}
+
+/// Request to start or stop ignoring another user.
+pub struct Ignore {
+    pub id: usize,
+    pub session: implement::Session,
+
+    pub username: String,
+    /// False to stop ignoring them
+    pub ignore: bool,
+}
+
+impl Message for Ignore {
+    type Result = ();
+}
+
+/// Request to add a user to the current private room.
+pub struct Invite {
+    pub id: usize,
+    pub session: implement::Session,
+
+    pub room_id: u32,
+    pub username: String,
+}
+
+impl Message for Invite {
+    type Result = ();
+}
+
 /// Request to join a room.
 pub struct Join {
     pub id: usize,
@@ -65,6 +120,45 @@ impl Message for Join {
     type Result = ();
 }
 
+/// Request to leave a private room for good.
+pub struct Leave {
+    pub id: usize,
+    pub session: implement::Session,
+
+    pub room_id: u32,
+}
+
+impl Message for Leave {
+    type Result = ();
+}
+
+/// Moderation command against a user in a room.
+pub struct Moderate {
+    pub id: usize,
+    pub session: implement::Session,
+
+    pub room_id: u32,
+    pub username: String,
+    pub action: ModerationAction,
+    pub reason: Option<String>,
+}
+
+impl Message for Moderate {
+    type Result = ();
+}
+
+/// Request to open a direct message room with another user.
+pub struct OpenDirect {
+    pub id: usize,
+    pub session: implement::Session,
+
+    pub username: String,
+}
+
+impl Message for OpenDirect {
+    type Result = ();
+}
+
 #[derive(Serialize)]
 pub struct Post {
     /// Conn Id
@@ -82,6 +176,55 @@ impl Message for Post {
     type Result = ();
 }
 
+/// Request to add or take back a reaction on a message.
+pub struct React {
+    pub id: usize,
+    pub session: implement::Session,
+
+    /// Room the message must be in
+    pub room_id: u32,
+    pub message_id: u32,
+    pub reaction_type_id: u32,
+}
+
+impl Message for React {
+    type Result = ();
+}
+
+/// Chat traffic shared with other instances over the backplane.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Relay {
+    /// Text for every connection in a room, except those ignoring `sender`
+    Room {
+        room_id: u32,
+        #[serde(default)]
+        sender: Option<u32>,
+        message: String,
+    },
+    /// Text for every connection a user has
+    User { user_id: u32, message: String },
+    /// A user's ignore list changed
+    Ignores {
+        user_id: u32,
+        ignored_users: Vec<u32>,
+    },
+    /// Who an instance has in each room, sent every `PRESENCE_INTERVAL`
+    Roster {
+        origin: String,
+        rooms: HashMap<u32, Vec<implement::UserActivity>>,
+    },
+    /// A user was kicked or banned out of a room
+    Remove { room_id: u32, user_id: u32 },
+    /// A ban or mute was placed, or lifted when `sanction` is None
+    Sanction {
+        room_id: u32,
+        user_id: u32,
+        ban: bool,
+        sanction: Option<Sanction>,
+    },
+}
+
 /// Server response to clientsl
 /// Usually a serialized JSON string.
 pub struct Reply(pub String);
@@ -101,6 +244,19 @@ impl Message for Restart {
     type Result = ();
 }
 
+/// The user started or stopped typing in a room.
+pub struct Typing {
+    pub id: usize,
+    pub session: implement::Session,
+
+    pub room_id: u32,
+    pub typing: bool,
+}
+
+impl Message for Typing {
+    type Result = ();
+}
+
 /// A post from the server containing public, sanitized data.
 #[derive(serde::Serialize)]
 pub struct SanitaryPost {
@@ -119,6 +275,9 @@ pub struct SanitaryPost {
     pub message_raw: String,
     /// Recipient room
     pub room_id: u32,
+    /// Reactions on the message
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reactions: Vec<implement::ReactionTally>,
 }
 
 impl Message for SanitaryPost {
@@ -133,3 +292,33 @@ pub struct SanitaryPosts {
 impl Message for SanitaryPosts {
     type Result = ();
 }
+
+/// A page of older messages for scrollback.
+#[derive(serde::Serialize)]
+pub struct SanitaryHistory {
+    pub history: Vec<SanitaryPost>,
+    /// True when there are no older messages left to load
+    pub complete: bool,
+}
+
+/// Current reactions on messages, by message ID. An empty list clears them.
+#[derive(serde::Serialize)]
+pub struct SanitaryReactions {
+    pub reactions: HashMap<u32, Vec<implement::ReactionTally>>,
+}
+
+/// A private room the client should add to its room list.
+#[derive(serde::Serialize)]
+pub struct SanitaryRoom {
+    pub id: u32,
+    /// Sanitized title
+    pub title: String,
+    pub unread: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct RoomAnnouncement {
+    pub room: SanitaryRoom,
+    /// True when the client should switch to the room
+    pub join: bool,
+}
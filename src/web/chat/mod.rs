@@ -1,7 +1,11 @@
 pub mod connection;
+pub mod flood;
 pub mod implement;
 pub mod message;
+pub mod moderation;
 pub mod server;
+pub mod transcript;
+pub mod upload;
 
 use actix::Addr;
 use actix_web::{get, web, web::Data, Error, HttpRequest, HttpResponse, Responder};
@@ -21,9 +25,15 @@ use crate::orm::users;
 pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
 /// How long before lack of client response causes a timeout
 pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a typing indicator lasts unless the client renews it
+pub const TYPING_TIMEOUT: Duration = Duration::from_secs(6);
+/// How often each instance shares its room rosters over the backplane
+pub const PRESENCE_INTERVAL: Duration = Duration::from_secs(10);
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
-    conf.service(view_chat_socket).service(view_chat);
+    conf.service(view_chat_socket)
+        .service(view_chat)
+        .service(upload::post_chat_upload);
 }
 
 /// Entry point for our websocket route
@@ -54,7 +64,6 @@ pub async fn view_chat_socket(
                 .app_data::<Addr<server::ChatServer>>()
                 .expect("No chat server.")
                 .clone(),
-            last_command: Instant::now(),
             max_message_length: config.chat_max_message_length(),
         },
         &req,
@@ -92,7 +101,6 @@ pub async fn view_xf_chat_socket(
                 .app_data::<Addr<server::ChatServer>>()
                 .expect("No chat server.")
                 .clone(),
-            last_command: Instant::now(),
             max_message_length,
         },
         &req,
@@ -133,6 +141,19 @@ pub async fn view_chat(client: ClientCtx, req: HttpRequest) -> Result<impl Respo
     // Determine effective default room: user preference first, then site default
     let default_room = user_default_room.unwrap_or_else(|| config.chat_default_room());
 
+    // Only list rooms this user is allowed to join
+    let mut rooms = Vec::new();
+    for room in layer.get_room_list().await {
+        if layer.can_view(user_id as u32, room.id).await {
+            rooms.push(room);
+        }
+    }
+    // Followed by the user's own private rooms and direct messages
+    rooms.extend(layer.get_private_rooms(user_id as u32).await);
+
+    let csrf_token =
+        serde_json::to_string(client.get_csrf_token()).expect("CSRF token stringify failed");
+
     Ok(ChatTemplate {
         client,
         app_json: format!(
@@ -140,12 +161,21 @@ pub async fn view_chat(client: ClientCtx, req: HttpRequest) -> Result<impl Respo
                 chat_ws_url: \"{}\",
                 user: {},
                 default_room: {},
+                reaction_types: {},
+                edit_window: {},
+                uploads: {},
+                csrf_token: {},
             }}",
             std::env::var("CHAT_WS_URL").expect("CHAT_WS_URL needs to be set in .env"),
             serde_json::to_string(&session).expect("XfSession stringify failed"),
             default_room,
+            serde_json::to_string(&layer.get_reaction_types().await)
+                .expect("ReactionType stringify failed"),
+            config.chat_edit_window_seconds(),
+            config.chat_uploads_enabled(),
+            csrf_token,
         ),
-        rooms: layer.get_room_list().await,
+        rooms,
     })
 }
 
@@ -155,7 +185,9 @@ struct ChatTestTemplate {
     rooms: Vec<Room>,
     app_json: String,
     nonce: String,
-    webpack_time: u64,
+    /// Content hashes of the stylesheet and script, to bust caches
+    style_version: &'static str,
+    script_version: &'static str,
     style: String,
 }
 
@@ -186,29 +218,6 @@ pub async fn view_chat_shim(
     if !test_endpoints_enabled {
         return Err(actix_web::error::ErrorNotFound("Endpoint not available"));
     }
-    let webpack_time: u64 = match std::fs::metadata(format!(
-        "{}/chat.js",
-        std::env::var("CHAT_ASSET_DIR").unwrap_or_else(|_| ".".to_string())
-    )) {
-        Ok(metadata) => match metadata.modified() {
-            Ok(time) => match time.duration_since(std::time::UNIX_EPOCH) {
-                Ok(distance) => distance.as_secs(),
-                Err(_) => {
-                    log::warn!("Unable to do math on webpack chat.js modified at timestamp");
-                    0
-                }
-            },
-            Err(_) => {
-                log::warn!("Unable to read metadata on webpack chat.js");
-                0
-            }
-        },
-        Err(_) => {
-            log::warn!("Unable to open webpack chat.js for timestamp");
-            0
-        }
-    };
-
     let layer = req
         .app_data::<Data<Arc<dyn ChatLayer>>>()
         .expect("No chat layer.");
@@ -249,7 +258,8 @@ pub async fn view_chat_shim(
             serde_json::to_string(&session).expect("XfSession stringify failed"),
         ),
         nonce: hasher.finalize().to_string(),
-        webpack_time,
+        style_version: crate::assets::version("style.css").unwrap_or_default(),
+        script_version: crate::assets::version("chat.js").unwrap_or_default(),
         style: if query.style == Some("dark".to_string()) {
             "dark"
         } else {
@@ -0,0 +1,178 @@
+//! Chat moderation commands
+//!
+//! Mutes and bans apply to one room and are held by the `ChatServer`, so they
+//! end when they expire, are lifted, or the process restarts. Longer-lasting
+//! bans belong in the site-wide user bans.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+/// How many of a room's newest messages `/purge` looks through
+pub const PURGE_SCAN_LIMIT: usize = 500;
+/// How long a mute lasts when no duration is given, in seconds
+pub const DEFAULT_MUTE_SECONDS: u64 = 600;
+
+/// A moderation command issued from chat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModerationAction {
+    /// Remove the user from the room. They may join again.
+    Kick,
+    /// Keep the user out of the room, for a number of seconds or until lifted.
+    Ban(Option<u64>),
+    Unban,
+    /// Stop the user from posting in the room for a number of seconds.
+    Mute(u64),
+    Unmute,
+    /// Delete the user's recent messages in the room.
+    Purge,
+}
+
+impl ModerationAction {
+    /// Name recorded in the moderation log
+    pub fn log_name(&self) -> &'static str {
+        match self {
+            Self::Kick => "chat_kick",
+            Self::Ban(_) => "chat_ban",
+            Self::Unban => "chat_unban",
+            Self::Mute(_) => "chat_mute",
+            Self::Unmute => "chat_unmute",
+            Self::Purge => "chat_purge",
+        }
+    }
+
+    /// Length of a ban or mute, in seconds
+    pub fn duration(&self) -> Option<u64> {
+        match self {
+            Self::Ban(duration) => *duration,
+            Self::Mute(duration) => Some(*duration),
+            _ => None,
+        }
+    }
+}
+
+/// A mute or ban in one room.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Sanction {
+    /// Unix timestamp the sanction ends at; None lasts until lifted
+    pub until: Option<u64>,
+}
+
+impl Sanction {
+    pub fn new(duration: Option<u64>) -> Self {
+        Self {
+            until: duration.map(|duration| now() + duration),
+        }
+    }
+
+    /// " for another 9 minutes", or nothing if it lasts until lifted
+    pub fn remaining(&self) -> String {
+        match self.until {
+            Some(until) => format!(
+                " for another {}",
+                format_duration(until.saturating_sub(now()))
+            ),
+            None => String::new(),
+        }
+    }
+}
+
+/// Sanctions by (room ID, user ID)
+pub type Sanctions = HashMap<(u32, u32), Sanction>;
+
+/// The user's sanction in a room, if one is in force. Expired ones are dropped.
+pub fn active(sanctions: &mut Sanctions, room_id: u32, user_id: u32) -> Option<Sanction> {
+    let key = (room_id, user_id);
+    let sanction = *sanctions.get(&key)?;
+
+    match sanction.until {
+        Some(until) if until <= now() => {
+            sanctions.remove(&key);
+            None
+        }
+        _ => Some(sanction),
+    }
+}
+
+/// Parse a duration like `30s`, `10m`, `2h` or `7d` into seconds. A bare
+/// number is minutes.
+pub fn parse_duration(input: &str) -> Option<u64> {
+    let input = input.trim().to_lowercase();
+    let (number, unit) = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => input.split_at(pos),
+        None => (input.as_str(), "m"),
+    };
+    let number: u64 = number.parse().ok().filter(|n| *n > 0)?;
+
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => return None,
+    };
+
+    number.checked_mul(multiplier)
+}
+
+/// "45 seconds", "10 minutes", "2 hours", "7 days"
+pub fn format_duration(seconds: u64) -> String {
+    let (count, unit) = if seconds >= 60 * 60 * 24 {
+        (seconds / (60 * 60 * 24), "day")
+    } else if seconds >= 60 * 60 {
+        (seconds / (60 * 60), "hour")
+    } else if seconds >= 60 {
+        (seconds / 60, "minute")
+    } else {
+        (seconds, "second")
+    };
+
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Some(30));
+        assert_eq!(parse_duration("10m"), Some(600));
+        assert_eq!(parse_duration("10"), Some(600));
+        assert_eq!(parse_duration("2H"), Some(7200));
+        assert_eq!(parse_duration("7d"), Some(604800));
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("10y"), None);
+        assert_eq!(parse_duration("spam"), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(45), "45 seconds");
+        assert_eq!(format_duration(600), "10 minutes");
+        assert_eq!(format_duration(3600), "1 hour");
+        assert_eq!(format_duration(604800), "7 days");
+    }
+
+    #[test]
+    fn test_expired_sanction_is_dropped() {
+        let mut sanctions = Sanctions::new();
+        sanctions.insert((1, 2), Sanction { until: Some(1) });
+        sanctions.insert((1, 3), Sanction::new(None));
+
+        assert!(active(&mut sanctions, 1, 2).is_none());
+        assert!(!sanctions.contains_key(&(1, 2)));
+        assert!(active(&mut sanctions, 1, 3).is_some());
+    }
+}
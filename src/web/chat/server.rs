@@ -1,13 +1,17 @@
+use super::flood::{FloodControl, FloodLimits, Rejection, FLOOD_WINDOW};
 use super::implement::{self, UserActivity};
-use super::implement::{ChatLayer, Connection};
-use super::message::{self, SanitaryPost, SanitaryPosts};
+use super::implement::{ChatLayer, Connection, Session};
+use super::message::{self, SanitaryHistory, SanitaryPost, SanitaryPosts};
+use super::moderation::{self, ModerationAction, Sanction, Sanctions};
+use super::{HEARTBEAT_INTERVAL, PRESENCE_INTERVAL, TYPING_TIMEOUT};
+use crate::backplane::{get_backplane, CHAT_CHANNEL, RESUBSCRIBE_DELAY};
 use crate::bbcode::{tokenize, Constructor, Parser, Smilies};
 use crate::config::Config;
 use actix::prelude::*;
 use rand::{self, rngs::ThreadRng, Rng};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
 /// `ChatServer` manages chat rooms and responsible for coordinating chat
 /// session. implementation is super primitive
@@ -20,8 +24,19 @@ pub struct ChatServer {
     pub connections: HashMap<usize, Connection>,
     /// Room Id -> Vec<Conn Ids>
     pub rooms: HashMap<u32, HashSet<usize>>,
-    /// User Id -> Last message timestamp (for rate limiting)
-    pub user_last_message: HashMap<u32, u64>,
+    /// Room Id -> User Id -> Open connections, so a user only leaves the
+    /// member list when their last tab does
+    pub roster: HashMap<u32, HashMap<u32, usize>>,
+    /// Room Id -> User Id -> Last typing notice
+    pub typing: HashMap<u32, HashMap<u32, Instant>>,
+    /// Instance Id -> When its roster arrived, and Room Id -> Users it has there
+    pub remote_rosters: HashMap<String, (Instant, HashMap<u32, Vec<UserActivity>>)>,
+    /// Message limits per user and per room
+    pub flood: FloodControl,
+    /// Users kept out of a room
+    pub bans: Sanctions,
+    /// Users who can't post in a room
+    pub mutes: Sanctions,
     // Message BbCode Constructor
     pub constructor: Constructor,
 }
@@ -36,6 +51,7 @@ impl ChatServer {
         // Constructor - use inline spoilers (blur-based) for chat
         // YouTube embeds can be toggled via chat_embed_youtube setting
         // Image domain whitelist controls which domains can show thumbnails
+        // Only the tags in chat_allowed_bbcode render; the rest stay as text
         let constructor = Constructor {
             smilies: Smilies::new_from_tuples(
                 layer
@@ -48,13 +64,19 @@ impl ChatServer {
             inline_spoilers: true,
             enable_youtube_embeds: config.chat_embed_youtube(),
             image_domain_whitelist: config.chat_image_domain_whitelist(),
+            allowed_tags: config.chat_allowed_bbcode(),
         };
 
         Self {
             rng: rand::thread_rng(),
             connections: HashMap::new(),
             rooms: HashMap::from_iter(rooms.into_iter().map(|r| (r.id, Default::default()))),
-            user_last_message: HashMap::new(),
+            roster: HashMap::new(),
+            typing: HashMap::new(),
+            remote_rosters: HashMap::new(),
+            flood: FloodControl::default(),
+            bans: Sanctions::new(),
+            mutes: Sanctions::new(),
             constructor,
             layer,
             config,
@@ -64,6 +86,13 @@ impl ChatServer {
     fn connect_message(&mut self, room: u32, id: usize) {
         if let Some(conn) = self.connections.get(&id) {
             if conn.session.id > 0 {
+                *self
+                    .roster
+                    .entry(room)
+                    .or_default()
+                    .entry(conn.session.id)
+                    .or_insert(0) += 1;
+
                 self.send_message_to_room(
                     room,
                     format!(
@@ -84,6 +113,9 @@ impl ChatServer {
                         users.insert(tconn.session.id, implement::UserActivity::from(tconn));
                     }
                 }
+                for user in self.remote_users(room) {
+                    users.entry(user.id).or_insert_with(|| user.clone());
+                }
 
                 self.send_message_to_conn(
                     id,
@@ -104,15 +136,100 @@ impl ChatServer {
             }
         }
 
+        let user_id = match self.connections.get(&id) {
+            Some(conn) if conn.session.id > 0 => conn.session.id,
+            _ => return,
+        };
+
         for room_id in left_rooms {
-            if let Some(conn) = self.connections.get(&id) {
-                if conn.session.id > 0 {
+            // Other tabs may still be in the room, here or on another instance.
+            if self.roster_leave(room_id, user_id) {
+                self.typing_stop(room_id, user_id);
+                if !self.is_present_elsewhere(room_id, user_id) {
                     self.send_message_to_room(
                         room_id,
-                        format!("{{\"user\":{{\"{}\":false}}}}", conn.session.id),
+                        format!("{{\"user\":{{\"{}\":false}}}}", user_id),
                     );
                 }
             }
+
+            // Whatever was posted while they were here has been seen.
+            let layer = self.layer.clone();
+            actix::spawn(async move {
+                layer.mark_room_read(user_id, room_id).await;
+            });
+        }
+    }
+
+    /// Count one of a user's connections out of a room. True when it was
+    /// their last one there.
+    fn roster_leave(&mut self, room: u32, user_id: u32) -> bool {
+        if let Some(users) = self.roster.get_mut(&room) {
+            if let Some(count) = users.get_mut(&user_id) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    users.remove(&user_id);
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Mark a user as typing, telling the room if they weren't already.
+    fn typing_start(&mut self, room: u32, user_id: u32, username: &str) {
+        let started = self
+            .typing
+            .entry(room)
+            .or_default()
+            .insert(user_id, Instant::now())
+            .is_none();
+
+        if started {
+            self.send_message_to_room_from(
+                room,
+                user_id,
+                format!(
+                    "{{\"typing\":{{\"{}\":{}}}}}",
+                    user_id,
+                    serde_json::to_string(username).expect("Failed to serialize typing username.")
+                ),
+            );
+        }
+    }
+
+    /// Clear a user's typing indicator, telling the room if one was showing.
+    fn typing_stop(&mut self, room: u32, user_id: u32) {
+        let stopped = match self.typing.get_mut(&room) {
+            Some(users) => users.remove(&user_id).is_some(),
+            None => false,
+        };
+
+        if stopped {
+            self.send_message_to_room_from(
+                room,
+                user_id,
+                format!("{{\"typing\":{{\"{}\":false}}}}", user_id),
+            );
+        }
+    }
+
+    /// Drop typing indicators that clients stopped renewing.
+    fn expire_typing(&mut self) {
+        let expired: Vec<(u32, u32)> = self
+            .typing
+            .iter()
+            .flat_map(|(room, users)| {
+                users
+                    .iter()
+                    .filter(|(_, since)| since.elapsed() > TYPING_TIMEOUT)
+                    .map(move |(user_id, _)| (*room, *user_id))
+            })
+            .collect();
+
+        for (room, user_id) in expired {
+            self.typing_stop(room, user_id);
         }
     }
 
@@ -141,9 +258,39 @@ impl ChatServer {
             message_edit_date: message.message_edit_date,
             message: self.constructor.build(ast),
             message_raw: Constructor::sanitize(&message.message),
+            reactions: Vec::new(),
         }
     }
 
+    /// Prepare a page of history, with each message's reactions.
+    fn prepare_history(
+        &self,
+        unsanitized: Vec<(implement::Author, implement::Message)>,
+        mut reactions: HashMap<u32, Vec<implement::ReactionTally>>,
+    ) -> Vec<SanitaryPost> {
+        unsanitized
+            .into_iter()
+            .map(|(author, message)| {
+                let mut post = self.prepare_message(author, message);
+                post.reactions = reactions.remove(&post.message_id).unwrap_or_default();
+                post
+            })
+            .collect()
+    }
+
+    /// Tell a client about a private room it can now see.
+    fn room_announcement(room_id: u32, title: &str, unread: bool, join: bool) -> String {
+        serde_json::to_string(&message::RoomAnnouncement {
+            room: message::SanitaryRoom {
+                id: room_id,
+                title: Constructor::sanitize(title),
+                unread,
+            },
+            join,
+        })
+        .expect("RoomAnnouncement serialize failure")
+    }
+
     /// Send message to specific user
     fn send_message_to_conn(&self, recipient: usize, message: String) {
         if let Some(conn) = self.connections.get(&recipient) {
@@ -151,47 +298,451 @@ impl ChatServer {
         }
     }
 
-    /// Send message to all users in a room
+    /// Send message to all users in a room, on every instance
     fn send_message_to_room(&self, room: u32, message: String) {
+        self.relay_to_room(room, None, message);
+    }
+
+    /// Send a user's message to all users in a room who aren't ignoring them
+    fn send_message_to_room_from(&self, room: u32, sender: u32, message: String) {
+        self.relay_to_room(room, Some(sender), message);
+    }
+
+    fn relay_to_room(&self, room: u32, sender: Option<u32>, message: String) {
+        if let Some(backplane) = get_backplane() {
+            backplane.publish(
+                CHAT_CHANNEL,
+                &message::Relay::Room {
+                    room_id: room,
+                    sender,
+                    message: message.to_owned(),
+                },
+            );
+        }
+
+        self.deliver_to_room(room, sender, message);
+    }
+
+    /// Send message to the users in a room connected to this instance
+    fn deliver_to_room(&self, room: u32, sender: Option<u32>, message: String) {
         if let Some(connections) = self.rooms.get(&room) {
             for id in connections {
                 if let Some(conn) = self.connections.get(id) {
+                    if matches!(sender, Some(sender) if conn.session.is_ignoring(sender)) {
+                        continue;
+                    }
                     conn.recipient.do_send(message::Reply(message.to_owned()));
                 }
             }
         }
     }
 
-    /// Check if user is rate limited. Returns seconds remaining if limited.
-    fn check_rate_limit(&self, user_id: u32) -> Option<u64> {
-        let rate_limit_seconds = self.config.chat_rate_limit_seconds();
-        if rate_limit_seconds == 0 {
-            return None; // Rate limiting disabled
+    /// Whether the user on a connection is ignoring another user
+    fn conn_is_ignoring(&self, id: usize, user_id: u32) -> bool {
+        matches!(self.connections.get(&id), Some(conn) if conn.session.is_ignoring(user_id))
+    }
+
+    /// Replace the ignore list on every connection a user has on this instance
+    fn set_ignored_users(&mut self, user_id: u32, ignored_users: &[u32]) {
+        for conn in self.connections.values_mut() {
+            if conn.session.id == user_id {
+                conn.session.ignored_users = ignored_users.to_vec();
+            }
+        }
+    }
+
+    /// Send message to every connection a user has open, on every instance
+    fn send_message_to_user(&self, user_id: u32, message: String) {
+        if let Some(backplane) = get_backplane() {
+            backplane.publish(
+                CHAT_CHANNEL,
+                &message::Relay::User {
+                    user_id,
+                    message: message.to_owned(),
+                },
+            );
         }
 
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        self.deliver_to_user(user_id, message);
+    }
 
-        if let Some(&last_message_time) = self.user_last_message.get(&user_id) {
-            let elapsed = now.saturating_sub(last_message_time);
-            if elapsed < rate_limit_seconds {
-                return Some(rate_limit_seconds - elapsed);
+    /// Send message to every connection a user has open on this instance
+    fn deliver_to_user(&self, user_id: u32, message: String) {
+        for conn in self.connections.values() {
+            if conn.session.id == user_id {
+                conn.recipient.do_send(message::Reply(message.to_owned()));
             }
         }
+    }
+
+    /// Take every connection a user has out of a room, on every instance
+    fn remove_user_from_room(&mut self, room_id: u32, user_id: u32) {
+        if let Some(backplane) = get_backplane() {
+            backplane.publish(CHAT_CHANNEL, &message::Relay::Remove { room_id, user_id });
+        }
 
-        None
+        self.remove_local_user_from_room(room_id, user_id);
+        self.send_message_to_room(room_id, format!("{{\"user\":{{\"{}\":false}}}}", user_id));
     }
 
-    /// Update the last message time for a user
-    fn update_last_message_time(&mut self, user_id: u32) {
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        self.user_last_message.insert(user_id, now);
+    /// Take the connections a user has on this instance out of a room
+    fn remove_local_user_from_room(&mut self, room_id: u32, user_id: u32) {
+        let user_conns: HashSet<usize> = self
+            .connections
+            .iter()
+            .filter(|(_, conn)| conn.session.id == user_id)
+            .map(|(conn_id, _)| *conn_id)
+            .collect();
+        if let Some(room_conns) = self.rooms.get_mut(&room_id) {
+            room_conns.retain(|conn_id| !user_conns.contains(conn_id));
+        }
+        if let Some(users) = self.roster.get_mut(&room_id) {
+            users.remove(&user_id);
+        }
+        self.typing_stop(room_id, user_id);
+    }
+
+    /// Place or lift a ban or mute, on every instance
+    fn set_sanction(&mut self, room_id: u32, user_id: u32, ban: bool, sanction: Option<Sanction>) {
+        if let Some(backplane) = get_backplane() {
+            backplane.publish(
+                CHAT_CHANNEL,
+                &message::Relay::Sanction {
+                    room_id,
+                    user_id,
+                    ban,
+                    sanction,
+                },
+            );
+        }
+
+        self.apply_sanction(room_id, user_id, ban, sanction);
+    }
+
+    fn apply_sanction(
+        &mut self,
+        room_id: u32,
+        user_id: u32,
+        ban: bool,
+        sanction: Option<Sanction>,
+    ) {
+        let sanctions = if ban { &mut self.bans } else { &mut self.mutes };
+        match sanction {
+            Some(sanction) => {
+                sanctions.insert((room_id, user_id), sanction);
+            }
+            None => {
+                sanctions.remove(&(room_id, user_id));
+            }
+        }
+    }
+
+    /// Users other instances have in a room
+    fn remote_users(&self, room: u32) -> impl Iterator<Item = &UserActivity> {
+        self.remote_rosters
+            .values()
+            .filter_map(move |(_, rooms)| rooms.get(&room))
+            .flatten()
+    }
+
+    /// Whether another instance has the user in a room
+    fn is_present_elsewhere(&self, room: u32, user_id: u32) -> bool {
+        self.remote_users(room).any(|user| user.id == user_id)
+    }
+
+    /// Share who this instance has in each room with the others.
+    fn publish_roster(&self) {
+        let backplane = match get_backplane() {
+            Some(backplane) => backplane,
+            None => return,
+        };
+
+        let mut rooms: HashMap<u32, Vec<UserActivity>> = HashMap::new();
+        for (room_id, conns) in &self.rooms {
+            let mut users: HashMap<u32, UserActivity> = HashMap::new();
+            for conn in conns.iter().filter_map(|id| self.connections.get(id)) {
+                if conn.session.id > 0 {
+                    users
+                        .entry(conn.session.id)
+                        .or_insert_with(|| UserActivity::from(conn));
+                }
+            }
+            if !users.is_empty() {
+                rooms.insert(*room_id, users.into_values().collect());
+            }
+        }
+
+        backplane.publish(
+            CHAT_CHANNEL,
+            &message::Relay::Roster {
+                origin: backplane.origin().to_owned(),
+                rooms,
+            },
+        );
+    }
+
+    /// Take in another instance's roster.
+    fn apply_roster(&mut self, origin: String, rooms: HashMap<u32, Vec<UserActivity>>) {
+        if let Some((_, previous)) = self.remote_rosters.insert(origin, (Instant::now(), rooms)) {
+            self.announce_departures(previous);
+        }
+    }
+
+    /// Forget instances that stopped sharing their roster, most likely
+    /// because they went down.
+    fn expire_remote_rosters(&mut self) {
+        let expired: Vec<String> = self
+            .remote_rosters
+            .iter()
+            .filter(|(_, (received, _))| received.elapsed() > PRESENCE_INTERVAL * 3)
+            .map(|(origin, _)| origin.to_owned())
+            .collect();
+
+        for origin in expired {
+            log::warn!("Chat instance {} stopped sharing its roster.", origin);
+            if let Some((_, rooms)) = self.remote_rosters.remove(&origin) {
+                self.announce_departures(rooms);
+            }
+        }
+    }
+
+    /// Tell local clients about users from an old remote roster who are no
+    /// longer in the room anywhere.
+    fn announce_departures(&self, previous: HashMap<u32, Vec<UserActivity>>) {
+        for (room_id, users) in previous {
+            for user in users {
+                let here = self
+                    .roster
+                    .get(&room_id)
+                    .is_some_and(|users| users.contains_key(&user.id));
+                if !here && !self.is_present_elsewhere(room_id, user.id) {
+                    self.deliver_to_room(
+                        room_id,
+                        None,
+                        format!("{{\"user\":{{\"{}\":false}}}}", user.id),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Receive chat traffic from other instances, when there are any.
+    fn subscribe_backplane(&self, ctx: &mut Context<Self>) {
+        if let Some(backplane) = get_backplane() {
+            ctx.add_stream(backplane.subscribe::<message::Relay>(CHAT_CHANNEL));
+        }
+    }
+
+    /// A connected user by name, so moderators can act on anyone in chat
+    fn find_connected_author(&self, username: &str) -> Option<implement::Author> {
+        self.connections
+            .values()
+            .find(|conn| {
+                conn.session.id > 0 && conn.session.username.eq_ignore_ascii_case(username)
+            })
+            .map(|conn| implement::Author::from(&conn.session))
+    }
+
+    /// Apply a moderation command that has been checked and logged, and tell
+    /// the room about it.
+    fn apply_moderation(
+        &mut self,
+        id: usize,
+        msg: &message::Moderate,
+        target: &implement::Author,
+        purged: Vec<u32>,
+    ) {
+        let room_id = msg.room_id;
+        let moderator = Constructor::sanitize(&msg.session.username);
+        let name = Constructor::sanitize(&target.username);
+        let because = msg
+            .reason
+            .as_ref()
+            .map(|reason| format!(" Reason: {}", Constructor::sanitize(reason)))
+            .unwrap_or_default();
+        let lasting = |duration: Option<u64>| {
+            duration
+                .map(|duration| format!(" for {}", moderation::format_duration(duration)))
+                .unwrap_or_default()
+        };
+
+        match msg.action {
+            ModerationAction::Kick => {
+                self.send_message_to_room(
+                    room_id,
+                    format!("{} was kicked by {}.{}", name, moderator, because),
+                );
+                self.remove_user_from_room(room_id, target.id);
+            }
+            ModerationAction::Ban(duration) => {
+                self.set_sanction(room_id, target.id, true, Some(Sanction::new(duration)));
+                self.send_message_to_room(
+                    room_id,
+                    format!(
+                        "{} was banned from the room by {}{}.{}",
+                        name,
+                        moderator,
+                        lasting(duration),
+                        because
+                    ),
+                );
+                self.remove_user_from_room(room_id, target.id);
+            }
+            ModerationAction::Unban => {
+                self.set_sanction(room_id, target.id, true, None);
+                self.send_message_to_room(
+                    room_id,
+                    format!("{} was unbanned by {}.", name, moderator),
+                );
+                self.send_message_to_user(
+                    target.id,
+                    format!("You were unbanned from room {} by {}.", room_id, moderator),
+                );
+            }
+            ModerationAction::Mute(duration) => {
+                self.set_sanction(
+                    room_id,
+                    target.id,
+                    false,
+                    Some(Sanction::new(Some(duration))),
+                );
+                self.send_message_to_room(
+                    room_id,
+                    format!(
+                        "{} was muted by {}{}.{}",
+                        name,
+                        moderator,
+                        lasting(Some(duration)),
+                        because
+                    ),
+                );
+            }
+            ModerationAction::Unmute => {
+                self.set_sanction(room_id, target.id, false, None);
+                self.send_message_to_room(
+                    room_id,
+                    format!("{} was unmuted by {}.", name, moderator),
+                );
+            }
+            ModerationAction::Purge => {
+                if purged.is_empty() {
+                    self.send_message_to_conn(
+                        id,
+                        format!("{} has no recent messages in this room.", name),
+                    );
+                    return;
+                }
+
+                self.send_message_to_room(
+                    room_id,
+                    format!(
+                        "{{\"delete\":[{}]}}",
+                        purged
+                            .iter()
+                            .map(|message_id| message_id.to_string())
+                            .collect::<Vec<String>>()
+                            .join(",")
+                    ),
+                );
+                self.send_message_to_room(
+                    room_id,
+                    format!(
+                        "{} removed {} messages from {}.{}",
+                        moderator,
+                        purged.len(),
+                        name,
+                        because
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Users with a connection in a room
+    fn users_in_room(&self, room: u32) -> HashSet<u32> {
+        self.rooms
+            .get(&room)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.connections.get(id))
+            .map(|conn| conn.session.id)
+            .chain(self.remote_users(room).map(|user| user.id))
+            .collect()
+    }
+
+    /// Run a message through the word filter. Blocked messages are refused
+    /// with the filter's reason and return None.
+    fn filter_words(&self, id: usize, user_id: u32, message: &str) -> Option<String> {
+        let result =
+            crate::word_filter::apply_filters(message, crate::word_filter::FilterScope::Chat);
+
+        if result.blocked {
+            log::warn!(
+                "Chat message blocked by word filter: user_id={}, patterns={:?}",
+                user_id,
+                result.matched_patterns
+            );
+            self.send_message_to_conn(
+                id,
+                result
+                    .block_reason
+                    .unwrap_or_else(|| "Your message contains blocked content.".to_string()),
+            );
+            return None;
+        }
+
+        if result.flagged {
+            log::warn!(
+                "Chat message flagged by word filter: user_id={}, patterns={:?}",
+                user_id,
+                result.matched_patterns
+            );
+        }
+
+        Some(result.content)
     }
+
+    /// Check a message against the flood limits, counting it if it passes.
+    /// Staff are exempt.
+    fn check_flood(
+        &mut self,
+        session: &Session,
+        room_id: u32,
+        message: &str,
+    ) -> Result<(), Rejection> {
+        if session.is_staff {
+            return Ok(());
+        }
+
+        let limits = FloodLimits::from_config(&self.config);
+        let is_new_user = match session.joined_at {
+            Some(joined_at) => {
+                let now = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs();
+                limits.is_new_user(Duration::from_secs(now.saturating_sub(joined_at)))
+            }
+            None => false,
+        };
+
+        self.flood.check(
+            &limits,
+            session.id,
+            room_id,
+            message,
+            is_new_user,
+            Instant::now(),
+        )
+    }
+}
+
+/// IDs of the messages in a page of history
+fn message_ids(history: &[(implement::Author, implement::Message)]) -> Vec<u32> {
+    history
+        .iter()
+        .map(|(_, message)| message.message_id)
+        .collect()
 }
 
 /// Make actor from `ChatServer`
@@ -201,6 +752,52 @@ impl Actor for ChatServer {
 
     fn started(&mut self, ctx: &mut Self::Context) {
         ctx.set_mailbox_capacity(32);
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, _ctx| act.expire_typing());
+        ctx.run_interval(FLOOD_WINDOW, |act, _ctx| {
+            let limits = FloodLimits::from_config(&act.config);
+            act.flood.prune(&limits, Instant::now());
+        });
+
+        if get_backplane().is_some() {
+            self.subscribe_backplane(ctx);
+            ctx.run_interval(PRESENCE_INTERVAL, |act, _ctx| {
+                act.publish_roster();
+                act.expire_remote_rosters();
+            });
+        }
+    }
+}
+
+/// Chat traffic from other instances. Nothing here is relayed again.
+impl StreamHandler<message::Relay> for ChatServer {
+    fn handle(&mut self, relay: message::Relay, _: &mut Context<Self>) {
+        match relay {
+            message::Relay::Room {
+                room_id,
+                sender,
+                message,
+            } => self.deliver_to_room(room_id, sender, message),
+            message::Relay::User { user_id, message } => self.deliver_to_user(user_id, message),
+            message::Relay::Ignores {
+                user_id,
+                ignored_users,
+            } => self.set_ignored_users(user_id, &ignored_users),
+            message::Relay::Roster { origin, rooms } => self.apply_roster(origin, rooms),
+            message::Relay::Remove { room_id, user_id } => {
+                self.remove_local_user_from_room(room_id, user_id)
+            }
+            message::Relay::Sanction {
+                room_id,
+                user_id,
+                ban,
+                sanction,
+            } => self.apply_sanction(room_id, user_id, ban, sanction),
+        }
+    }
+
+    fn finished(&mut self, ctx: &mut Context<Self>) {
+        log::warn!("Chat lost its backplane subscription.");
+        ctx.run_later(RESUBSCRIBE_DELAY, |act, ctx| act.subscribe_backplane(ctx));
     }
 }
 
@@ -228,6 +825,32 @@ impl Handler<message::Connect> for ChatServer {
     }
 }
 
+/// Create a private room and move its owner into it.
+impl Handler<message::CreateRoom> for ChatServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: message::CreateRoom, _: &mut Context<Self>) -> Self::Result {
+        let message::CreateRoom { id, session, title } = msg;
+
+        let layer = self.layer.clone();
+        Box::pin(
+            async move { layer.create_private_room(&session, &title).await }
+                .into_actor(self)
+                .map(move |room, actor, _ctx| match room {
+                    Some(room) => {
+                        actor.send_message_to_conn(
+                            id,
+                            ChatServer::room_announcement(room.id, &room.title, false, true),
+                        );
+                    }
+                    None => {
+                        actor.send_message_to_conn(id, "Could not create the room.".to_string());
+                    }
+                }),
+        )
+    }
+}
+
 /// Handler for Delete message.
 impl Handler<message::Delete> for ChatServer {
     type Result = ResponseActFuture<Self, ()>;
@@ -242,7 +865,9 @@ impl Handler<message::Delete> for ChatServer {
 
                 // If we got the message, check if we can delete it.
                 if let Some(message) = &res {
-                    if message.user_id == msg.session.id || msg.session.is_staff {
+                    if message.user_id == msg.session.id
+                        || layer.can_moderate(&msg.session, message.room_id).await
+                    {
                         // Delete message.
                         layer.delete_message(message.message_id).await;
                     } else {
@@ -289,117 +914,329 @@ impl Handler<message::Disconnect> for ChatServer {
 impl Handler<message::Edit> for ChatServer {
     type Result = ResponseActFuture<Self, ()>;
 
-    fn handle(&mut self, msg: message::Edit, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, mut msg: message::Edit, _: &mut Context<Self>) -> Self::Result {
+        msg.message = match self.filter_words(msg.id, msg.session.id, &msg.message) {
+            Some(message) => message,
+            None => return Box::pin(async {}.into_actor(self)),
+        };
+
         let layer = self.layer.to_owned();
         let session = msg.session.to_owned();
         let author = implement::Author::from(&session);
+        let edit_window = self.config.chat_edit_window_seconds();
 
         Box::pin(
             async move {
                 // Get the message.
-                let res = layer.get_message(msg.message_id).await;
-
-                // If we got the message, check if we can edit it.
-                if let Some(message) = &res {
-                    log::debug!(
-                        "Edit check: message.user_id={}, session.id={}, message_id={}",
-                        message.user_id,
-                        session.id,
-                        msg.message_id
-                    );
-                    if message.user_id == session.id {
-                        // Edit message.
-                        let result = layer
-                            .edit_message(message.message_id, author, msg.message)
-                            .await;
-                        if result.is_none() {
-                            log::warn!(
-                                "edit_message returned None for message_id={}",
-                                message.message_id
-                            );
-                        }
-                        return result;
-                    } else {
+                let message = match layer.get_message(msg.message_id).await {
+                    Some(message) => message,
+                    None => {
                         log::warn!(
-                            "User {} (session) tried to edit message {} owned by user {}",
-                            session.id,
-                            msg.message_id,
-                            message.user_id
+                            "get_message returned None for message_id={}",
+                            msg.message_id
                         );
-                        return None;
+                        return Err("Could not edit message.");
                     }
-                } else {
+                };
+
+                // Only the author may edit, and only for a while after posting.
+                if message.user_id != session.id {
                     log::warn!(
-                        "get_message returned None for message_id={}",
-                        msg.message_id
+                        "User {} (session) tried to edit message {} owned by user {}",
+                        session.id,
+                        msg.message_id,
+                        message.user_id
                     );
+                    return Err("Could not edit message.");
                 }
 
-                res
+                let now = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs();
+                if edit_window > 0 && now.saturating_sub(message.message_date as u64) > edit_window
+                {
+                    return Err("This message is too old to edit.");
+                }
+
+                match layer
+                    .edit_message(message.message_id, author, msg.message)
+                    .await
+                {
+                    // Clients replace the whole message, so its reactions come along.
+                    Some(message) => {
+                        let reactions = layer.get_reactions(&[message.message_id]).await;
+                        Ok((message, reactions))
+                    }
+                    None => {
+                        log::warn!(
+                            "edit_message returned None for message_id={}",
+                            message.message_id
+                        );
+                        Err("Could not edit message.")
+                    }
+                }
             }
             .into_actor(self)
-            .map(move |message, actor, _ctx| {
-                if let Some(message) = message {
-                    actor.send_message_to_room(
-                        message.room_id,
+            .map(move |message, actor, _ctx| match message {
+                Ok((message, mut reactions)) => {
+                    let room_id = message.room_id;
+                    let mut post =
+                        actor.prepare_message(implement::Author::from(&session), message);
+                    post.reactions = reactions.remove(&post.message_id).unwrap_or_default();
+
+                    actor.send_message_to_room_from(
+                        room_id,
+                        session.id,
                         serde_json::to_string(&message::SanitaryPosts {
-                            messages: vec![
-                                actor.prepare_message(implement::Author::from(&session), message)
-                            ],
+                            messages: vec![post],
                         })
                         .expect("ClientMessages serialize failure"),
                     );
-                } else {
-                    actor.send_message_to_conn(msg.id, "Could not edit message.".to_string());
+                }
+                Err(reason) => {
+                    actor.send_message_to_conn(msg.id, reason.to_string());
                 }
             }),
         )
     }
 }
 
-/// Join room, send disconnect message to old room
-/// send join message to new room
-impl Handler<message::Join> for ChatServer {
+/// Send a page of older messages to a client scrolling back through a room.
+impl Handler<message::History> for ChatServer {
     type Result = ResponseActFuture<Self, ()>;
 
-    fn handle(&mut self, msg: message::Join, _: &mut Context<Self>) -> Self::Result {
-        let message::Join {
+    fn handle(&mut self, msg: message::History, _: &mut Context<Self>) -> Self::Result {
+        let message::History {
             id,
             session,
             room_id,
+            before,
         } = msg;
 
-        // Send disconnection alert to users in room.
-        self.disconnect_message(msg.id);
-
         let layer = self.layer.clone();
         let history_limit = self.config.chat_history_limit();
         Box::pin(
             async move {
+                // Room access can change while connected, so check it again.
                 if layer.can_view(session.id, room_id).await {
-                    (true, layer.get_room_history(room_id, history_limit).await)
+                    let history = layer
+                        .get_room_history(room_id, Some(before), history_limit)
+                        .await;
+                    let reactions = layer.get_reactions(&message_ids(&history)).await;
+                    Some((history, reactions))
                 } else {
-                    (false, Vec::default())
+                    None
                 }
             }
             .into_actor(self)
-            .map(move |(can_view, unsanitized), actor, _ctx| {
-                if can_view {
-                    let mut messages: Vec<SanitaryPost> = Vec::with_capacity(unsanitized.len());
-
-                    for (author, message) in unsanitized {
-                        messages.push(actor.prepare_message(author, message));
-                    }
+            .map(move |unsanitized, actor, _ctx| match unsanitized {
+                Some((mut unsanitized, reactions)) => {
+                    let complete = unsanitized.len() < history_limit;
+                    unsanitized.retain(|(author, _)| !actor.conn_is_ignoring(id, author.id));
+                    let history = actor.prepare_history(unsanitized, reactions);
 
                     actor.send_message_to_conn(
                         id,
-                        serde_json::to_string(&SanitaryPosts { messages })
-                            .expect("SanitaryPosts serialize failure"),
+                        serde_json::to_string(&SanitaryHistory { history, complete })
+                            .expect("SanitaryHistory serialize failure"),
                     );
+                }
+                None => {
+                    actor.send_message_to_conn(id, "You cannot view this room.".to_string());
+                }
+            }),
+        )
+    }
+}
 
-                    // Put user in room now so messages don't load in during history.
-                    actor
-                        .rooms
+/// Start or stop ignoring a user, on every connection the ignoring user has.
+impl Handler<message::Ignore> for ChatServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: message::Ignore, _: &mut Context<Self>) -> Self::Result {
+        let message::Ignore {
+            id,
+            session,
+            username,
+            ignore,
+        } = msg;
+
+        if !session.can_send_message() {
+            self.send_message_to_conn(id, "You must be logged in to ignore users.".to_string());
+            return Box::pin(async {}.into_actor(self));
+        }
+
+        let layer = self.layer.clone();
+        Box::pin(
+            async move {
+                let target = layer
+                    .find_author_by_name(&username)
+                    .await
+                    .ok_or("Could not find that user.")?;
+                if target.id == session.id {
+                    return Err("You cannot ignore yourself.");
+                }
+                if !layer.set_ignoring(session.id, target.id, ignore).await {
+                    return Err("Could not update your ignore list.");
+                }
+
+                let ignored_users = layer
+                    .get_session_from_user_id(session.id)
+                    .await
+                    .ignored_users;
+                Ok((target, ignored_users))
+            }
+            .into_actor(self)
+            .map(move |result, actor, _ctx| match result {
+                Ok((target, ignored_users)) => {
+                    actor.set_ignored_users(session.id, &ignored_users);
+                    if let Some(backplane) = get_backplane() {
+                        backplane.publish(
+                            CHAT_CHANNEL,
+                            &message::Relay::Ignores {
+                                user_id: session.id,
+                                ignored_users: ignored_users.to_owned(),
+                            },
+                        );
+                    }
+
+                    // Clients collapse history from ignored users themselves.
+                    actor.send_message_to_user(
+                        session.id,
+                        format!(
+                            "{{\"ignored\":{}}}",
+                            serde_json::to_string(&ignored_users)
+                                .expect("Failed to serialize ignored users.")
+                        ),
+                    );
+                    actor.send_message_to_conn(
+                        id,
+                        format!(
+                            "You are {} ignoring {}.",
+                            if ignore { "now" } else { "no longer" },
+                            Constructor::sanitize(&target.username)
+                        ),
+                    );
+                }
+                Err(reason) => {
+                    actor.send_message_to_conn(id, reason.to_string());
+                }
+            }),
+        )
+    }
+}
+
+/// Add a user to a private room and announce it to the room.
+impl Handler<message::Invite> for ChatServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: message::Invite, _: &mut Context<Self>) -> Self::Result {
+        let message::Invite {
+            id,
+            session,
+            room_id,
+            username,
+        } = msg;
+        let inviter = session.username.to_owned();
+
+        let layer = self.layer.clone();
+        Box::pin(
+            async move { layer.invite_to_room(&session, room_id, &username).await }
+                .into_actor(self)
+                .map(move |invited, actor, _ctx| match invited {
+                    Some((room, invitee)) => {
+                        actor.send_message_to_room(
+                            room_id,
+                            format!(
+                                "{} invited {} to the room.",
+                                Constructor::sanitize(&inviter),
+                                Constructor::sanitize(&invitee.username)
+                            ),
+                        );
+                        actor.send_message_to_user(
+                            invitee.id,
+                            ChatServer::room_announcement(room.id, &room.title, true, false),
+                        );
+                    }
+                    None => {
+                        actor.send_message_to_conn(
+                            id,
+                            "Could not invite that user to this room.".to_string(),
+                        );
+                    }
+                }),
+        )
+    }
+}
+
+/// Join room, send disconnect message to old room
+/// send join message to new room
+impl Handler<message::Join> for ChatServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: message::Join, _: &mut Context<Self>) -> Self::Result {
+        let message::Join {
+            id,
+            session,
+            room_id,
+        } = msg;
+
+        if let Some(ban) = moderation::active(&mut self.bans, room_id, session.id) {
+            self.send_message_to_conn(
+                id,
+                format!("You are banned from this room{}.", ban.remaining()),
+            );
+            return Box::pin(async {}.into_actor(self));
+        }
+
+        // Send disconnection alert to users in room.
+        self.disconnect_message(msg.id);
+
+        let layer = self.layer.clone();
+        let history_limit = self.config.chat_history_limit();
+        Box::pin(
+            async move {
+                if layer.can_view(session.id, room_id).await {
+                    layer.mark_room_read(session.id, room_id).await;
+
+                    let topic = layer
+                        .get_room_list()
+                        .await
+                        .into_iter()
+                        .find(|room| room.id == room_id)
+                        .and_then(|room| room.motd);
+
+                    let history = layer.get_room_history(room_id, None, history_limit).await;
+                    let reactions = layer.get_reactions(&message_ids(&history)).await;
+
+                    (true, history, reactions, topic)
+                } else {
+                    (false, Vec::default(), HashMap::default(), None)
+                }
+            }
+            .into_actor(self)
+            .map(move |(can_view, mut unsanitized, reactions, topic), actor, _ctx| {
+                if can_view {
+                    unsanitized.retain(|(author, _)| !actor.conn_is_ignoring(id, author.id));
+                    let messages = actor.prepare_history(unsanitized, reactions);
+
+                    actor.send_message_to_conn(
+                        id,
+                        serde_json::to_string(&SanitaryPosts { messages })
+                            .expect("SanitaryPosts serialize failure"),
+                    );
+
+                    if let Some(topic) = topic.filter(|topic| !topic.trim().is_empty()) {
+                        actor.send_message_to_conn(
+                            id,
+                            format!("Topic: {}", Constructor::sanitize(&topic)),
+                        );
+                    }
+
+                    // Put user in room now so messages don't load in during history.
+                    actor
+                        .rooms
                         .entry(room_id)
                         .or_insert_with(HashSet::new)
                         .insert(id);
@@ -419,57 +1256,360 @@ impl Handler<message::Join> for ChatServer {
     }
 }
 
+/// Leave a private room, dropping it from every connection the user has.
+impl Handler<message::Leave> for ChatServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: message::Leave, _: &mut Context<Self>) -> Self::Result {
+        let message::Leave {
+            id,
+            session,
+            room_id,
+        } = msg;
+
+        let layer = self.layer.clone();
+        Box::pin(
+            async move {
+                let left = layer.leave_room(&session, room_id).await;
+                (left, session)
+            }
+            .into_actor(self)
+            .map(move |(left, session), actor, _ctx| {
+                if !left {
+                    actor.send_message_to_conn(
+                        id,
+                        "You can only leave private rooms you are a member of.".to_string(),
+                    );
+                    return;
+                }
+
+                actor.remove_user_from_room(room_id, session.id);
+                actor.send_message_to_user(session.id, format!("{{\"left\":{}}}", room_id));
+                actor.send_message_to_room(
+                    room_id,
+                    format!(
+                        "{} left the room.",
+                        Constructor::sanitize(&session.username)
+                    ),
+                );
+            }),
+        )
+    }
+}
+
+/// Moderation commands, for users allowed to moderate the room.
+impl Handler<message::Moderate> for ChatServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: message::Moderate, _: &mut Context<Self>) -> Self::Result {
+        let id = msg.id;
+        let online = self.find_connected_author(&msg.username);
+
+        let layer = self.layer.clone();
+        Box::pin(
+            async move {
+                if !layer.can_moderate(&msg.session, msg.room_id).await {
+                    return Err("You cannot moderate this room.");
+                }
+
+                let target = match online {
+                    Some(target) => target,
+                    None => match layer.find_author_by_name(&msg.username).await {
+                        Some(target) => target,
+                        None => return Err("Could not find that user."),
+                    },
+                };
+                if target.id == msg.session.id {
+                    return Err("You cannot moderate yourself.");
+                }
+
+                let mut purged = Vec::new();
+                if msg.action == ModerationAction::Purge {
+                    for (author, message) in layer
+                        .get_room_history(msg.room_id, None, moderation::PURGE_SCAN_LIMIT)
+                        .await
+                    {
+                        if author.id == target.id {
+                            layer.delete_message(message.message_id).await;
+                            purged.push(message.message_id);
+                        }
+                    }
+                }
+
+                layer
+                    .log_moderation(
+                        &msg.session,
+                        &msg.action,
+                        target.id,
+                        msg.room_id,
+                        msg.reason.as_deref(),
+                    )
+                    .await;
+
+                Ok((msg, target, purged))
+            }
+            .into_actor(self)
+            .map(move |res, actor, _ctx| match res {
+                Ok((msg, target, purged)) => actor.apply_moderation(id, &msg, &target, purged),
+                Err(reason) => actor.send_message_to_conn(id, reason.to_string()),
+            }),
+        )
+    }
+}
+
+/// Open a direct message room and add it to both users' room lists.
+impl Handler<message::OpenDirect> for ChatServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: message::OpenDirect, _: &mut Context<Self>) -> Self::Result {
+        let message::OpenDirect {
+            id,
+            session,
+            username,
+        } = msg;
+        let requester = implement::Author::from(&session);
+
+        let layer = self.layer.clone();
+        Box::pin(
+            async move {
+                if let Some(partner) = layer.find_author_by_name(&username).await {
+                    if layer.is_ignoring(partner.id, session.id).await {
+                        return Err("That user is not accepting direct messages from you.");
+                    }
+                }
+
+                layer
+                    .open_direct_room(&session, &username)
+                    .await
+                    .ok_or("Could not open a direct message with that user.")
+            }
+            .into_actor(self)
+            .map(move |opened, actor, _ctx| match opened {
+                Ok((room, partner)) => {
+                    actor.send_message_to_conn(
+                        id,
+                        ChatServer::room_announcement(room.id, &room.title, false, true),
+                    );
+                    // The other side sees the room under the requester's name.
+                    actor.send_message_to_user(
+                        partner.id,
+                        ChatServer::room_announcement(room.id, &requester.username, false, false),
+                    );
+                }
+                Err(reason) => {
+                    actor.send_message_to_conn(id, reason.to_string());
+                }
+            }),
+        )
+    }
+}
+
 /// Handler for Message message.
 impl Handler<message::Post> for ChatServer {
     type Result = ResponseActFuture<Self, ()>;
 
-    fn handle(&mut self, msg: message::Post, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, mut msg: message::Post, _: &mut Context<Self>) -> Self::Result {
         if !msg.session.can_send_message() {
             self.send_message_to_conn(msg.id, "You cannot send messages.".to_string());
             return Box::pin(async {}.into_actor(self));
         }
 
-        // Check rate limit
-        if let Some(seconds_remaining) = self.check_rate_limit(msg.session.id) {
+        if let Some(mute) = moderation::active(&mut self.mutes, msg.room_id, msg.session.id)
+            .or_else(|| moderation::active(&mut self.bans, msg.room_id, msg.session.id))
+        {
             self.send_message_to_conn(
                 msg.id,
-                format!(
-                    "Please wait {} seconds before sending another message.",
-                    seconds_remaining
-                ),
+                format!("You cannot post in this room{}.", mute.remaining()),
             );
             return Box::pin(async {}.into_actor(self));
         }
 
-        // Update rate limit timestamp before sending (optimistic)
-        self.update_last_message_time(msg.session.id);
+        msg.message = match self.filter_words(msg.id, msg.session.id, &msg.message) {
+            Some(message) => message,
+            None => return Box::pin(async {}.into_actor(self)),
+        };
+
+        if let Err(rejection) = self.check_flood(&msg.session, msg.room_id, &msg.message) {
+            self.send_message_to_conn(msg.id, rejection.message());
+            return Box::pin(async {}.into_actor(self));
+        }
+
+        self.typing_stop(msg.room_id, msg.session.id);
 
         let id = msg.id;
         let layer = self.layer.to_owned();
         let session = msg.session.to_owned();
 
         Box::pin(
-            async move { layer.insert_chat_message(&msg).await }
-                .into_actor(self)
-                .map(move |message, actor, _| {
-                    if let Some(message) = message {
-                        let room_id = message.room_id;
+            async move {
+                // Send rights can differ per room, so check them against the target room.
+                if !layer.can_send_message(&msg.session, msg.room_id).await {
+                    return Err("You cannot send messages in this room.");
+                }
 
-                        actor.send_message_to_room(
-                            room_id,
-                            serde_json::to_string(&message::SanitaryPosts {
-                                messages: vec![actor
-                                    .prepare_message(implement::Author::from(&session), message)],
-                            })
-                            .expect("message::Post serialize failure"),
-                        );
-                    } else {
-                        actor.send_message_to_conn(id, "Failed to send message.".to_string());
+                // Direct messages can't reach someone ignoring the sender.
+                if let Some(partner) = layer.get_direct_partner(msg.room_id, msg.session.id).await {
+                    if layer.is_ignoring(partner, msg.session.id).await {
+                        return Err("That user is not accepting direct messages from you.");
                     }
-                }),
+                }
+
+                let message = layer.insert_chat_message(&msg).await;
+                let members = match message {
+                    Some(_) => layer.get_room_members(msg.room_id).await,
+                    None => Vec::new(),
+                };
+                Ok((message, members))
+            }
+            .into_actor(self)
+            .map(move |message, actor, _| match message {
+                Ok((Some(message), members)) => {
+                    let room_id = message.room_id;
+
+                    // Private room members who aren't in the room are told
+                    // the room has unread messages, and notified.
+                    if !members.is_empty() {
+                        let present = actor.users_in_room(room_id);
+                        let absent: Vec<u32> = members
+                            .into_iter()
+                            .filter(|member| *member != session.id && !present.contains(member))
+                            .collect();
+
+                        for member in &absent {
+                            actor.send_message_to_user(
+                                *member,
+                                format!("{{\"unread\":{}}}", room_id),
+                            );
+                        }
+
+                        if !absent.is_empty() {
+                            let layer = actor.layer.clone();
+                            let author = implement::Author::from(&session);
+                            let text = message.message.to_owned();
+                            actix::spawn(async move {
+                                layer
+                                    .notify_room_message(&author, room_id, &text, &absent)
+                                    .await;
+                            });
+                        }
+                    }
+
+                    actor.send_message_to_room_from(
+                        room_id,
+                        session.id,
+                        serde_json::to_string(&message::SanitaryPosts {
+                            messages: vec![
+                                actor.prepare_message(implement::Author::from(&session), message)
+                            ],
+                        })
+                        .expect("message::Post serialize failure"),
+                    );
+                }
+                Ok((None, _)) => {
+                    actor.send_message_to_conn(id, "Failed to send message.".to_string());
+                }
+                Err(reason) => {
+                    actor.send_message_to_conn(id, reason.to_string());
+                }
+            }),
         )
     }
 }
+/// Toggle a reaction and send the message's new tallies to its room.
+impl Handler<message::React> for ChatServer {
+    type Result = ResponseActFuture<Self, ()>;
+
+    fn handle(&mut self, msg: message::React, _: &mut Context<Self>) -> Self::Result {
+        let message::React {
+            id,
+            session,
+            room_id,
+            message_id,
+            reaction_type_id,
+        } = msg;
+
+        if !session.can_send_message()
+            || moderation::active(&mut self.mutes, room_id, session.id).is_some()
+            || moderation::active(&mut self.bans, room_id, session.id).is_some()
+        {
+            self.send_message_to_conn(id, "You cannot react in this room.".to_string());
+            return Box::pin(async {}.into_actor(self));
+        }
+
+        let layer = self.layer.clone();
+        Box::pin(
+            async move {
+                // Chat sessions don't carry groups, so exemptions don't reach here.
+                if let Err(err) =
+                    crate::rate_limit::check_reaction_rate_limit(session.id as i32, &[]).await
+                {
+                    return Err(format!(
+                        "Too many reactions. Please try again in {} seconds.",
+                        err.retry_after_seconds
+                    ));
+                }
+
+                let tallies = async {
+                    let message = layer.get_message(message_id).await?;
+
+                    // Reacting needs the same access as posting in the room.
+                    if message.room_id != room_id
+                        || !layer.can_send_message(&session, room_id).await
+                    {
+                        return None;
+                    }
+
+                    layer
+                        .toggle_reaction(session.id, message_id, reaction_type_id)
+                        .await?;
+
+                    let mut reactions = layer.get_reactions(&[message_id]).await;
+                    Some(reactions.remove(&message_id).unwrap_or_default())
+                }
+                .await;
+                tallies.ok_or_else(|| "Could not react to message.".to_string())
+            }
+            .into_actor(self)
+            .map(move |tallies, actor, _ctx| match tallies {
+                Ok(tallies) => {
+                    actor.send_message_to_room(
+                        room_id,
+                        serde_json::to_string(&message::SanitaryReactions {
+                            reactions: HashMap::from([(message_id, tallies)]),
+                        })
+                        .expect("SanitaryReactions serialize failure"),
+                    );
+                }
+                Err(reason) => {
+                    actor.send_message_to_conn(id, reason);
+                }
+            }),
+        )
+    }
+}
+
+/// Typing notices, relayed to the room the connection is in.
+impl Handler<message::Typing> for ChatServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: message::Typing, _: &mut Context<Self>) {
+        let in_room = self
+            .rooms
+            .get(&msg.room_id)
+            .is_some_and(|conns| conns.contains(&msg.id));
+        if !in_room || msg.session.id == 0 {
+            return;
+        }
+
+        if msg.typing && moderation::active(&mut self.mutes, msg.room_id, msg.session.id).is_none()
+        {
+            self.typing_start(msg.room_id, msg.session.id, &msg.session.username);
+        } else {
+            self.typing_stop(msg.room_id, msg.session.id);
+        }
+    }
+}
+
 impl Handler<message::Restart> for ChatServer {
     type Result = ();
 
@@ -489,3 +1629,735 @@ impl Supervised for ChatServer {
         log::warn!("Restarting the ChatServer.");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Chat layer kept in memory. Users are named `user<id>`.
+    #[derive(Default)]
+    struct MockLayer {
+        messages: Mutex<Vec<implement::Message>>,
+        /// Room Id -> Members, for private rooms
+        private_rooms: HashMap<u32, Vec<u32>>,
+        /// Private rooms that are direct messages
+        direct_rooms: HashSet<u32>,
+        /// (User Id, Ignored User Id)
+        ignores: HashSet<(u32, u32)>,
+        /// Users who may moderate every room
+        moderators: HashSet<u32>,
+    }
+
+    impl MockLayer {
+        /// Store a message posted `age` seconds ago and return its ID.
+        fn add_message(&self, room_id: u32, user_id: u32, text: &str, age: i64) -> u32 {
+            let mut messages = self.messages.lock().unwrap();
+            let message_id = messages.len() as u32 + 1;
+            let date = chrono::Utc::now().timestamp() - age;
+            messages.push(implement::Message {
+                user_id,
+                room_id,
+                message_id,
+                message_date: date,
+                message_edit_date: date,
+                message: text.to_owned(),
+            });
+            message_id
+        }
+
+        fn message(&self, message_id: u32) -> Option<implement::Message> {
+            self.messages
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|message| message.message_id == message_id)
+                .map(copy_message)
+        }
+    }
+
+    fn copy_message(message: &implement::Message) -> implement::Message {
+        implement::Message {
+            user_id: message.user_id,
+            room_id: message.room_id,
+            message_id: message.message_id,
+            message_date: message.message_date,
+            message_edit_date: message.message_edit_date,
+            message: message.message.to_owned(),
+        }
+    }
+
+    fn author(user_id: u32) -> implement::Author {
+        implement::Author {
+            id: user_id,
+            username: format!("user{}", user_id),
+            avatar_url: String::new(),
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ChatLayer for MockLayer {
+        async fn can_send_message(&self, session: &Session, room_id: u32) -> bool {
+            session.id > 0 && self.can_view(session.id, room_id).await
+        }
+
+        async fn can_view(&self, session_id: u32, room_id: u32) -> bool {
+            match self.private_rooms.get(&room_id) {
+                Some(members) => members.contains(&session_id),
+                None => true,
+            }
+        }
+
+        async fn delete_message(&self, id: u32) {
+            self.messages
+                .lock()
+                .unwrap()
+                .retain(|message| message.message_id != id);
+        }
+
+        async fn edit_message(
+            &self,
+            id: u32,
+            _author: implement::Author,
+            text: String,
+        ) -> Option<implement::Message> {
+            let mut messages = self.messages.lock().unwrap();
+            let message = messages
+                .iter_mut()
+                .find(|message| message.message_id == id)?;
+            message.message = text;
+            message.message_edit_date = chrono::Utc::now().timestamp();
+            Some(copy_message(message))
+        }
+
+        async fn get_message(&self, message_id: u32) -> Option<implement::Message> {
+            self.message(message_id)
+        }
+
+        async fn get_room_history(
+            &self,
+            room_id: u32,
+            before: Option<u32>,
+            limit: usize,
+        ) -> Vec<(implement::Author, implement::Message)> {
+            let messages = self.messages.lock().unwrap();
+            let page: Vec<&implement::Message> = messages
+                .iter()
+                .filter(|message| message.room_id == room_id)
+                .filter(|message| !matches!(before, Some(before) if message.message_id >= before))
+                .collect();
+            page[page.len().saturating_sub(limit)..]
+                .iter()
+                .map(|message| (author(message.user_id), copy_message(message)))
+                .collect()
+        }
+
+        async fn get_room_list(&self) -> Vec<implement::Room> {
+            Vec::new()
+        }
+
+        async fn get_session_from_user_id(&self, id: u32) -> Session {
+            Session {
+                id,
+                username: format!("user{}", id),
+                avatar_url: String::new(),
+                ignored_users: self
+                    .ignores
+                    .iter()
+                    .filter(|(user_id, _)| *user_id == id)
+                    .map(|(_, ignored)| *ignored)
+                    .collect(),
+                is_staff: false,
+                joined_at: None,
+            }
+        }
+
+        async fn get_smilie_list(&self) -> Vec<implement::Smilie> {
+            Vec::new()
+        }
+
+        fn get_session_key_from_request(&self, _req: &actix_web::HttpRequest) -> Option<String> {
+            None
+        }
+
+        async fn get_user_id_from_token(&self, _cookie: Option<String>) -> u32 {
+            0
+        }
+
+        async fn insert_chat_message(&self, post: &message::Post) -> Option<implement::Message> {
+            let message_id = self.add_message(post.room_id, post.session.id, &post.message, 0);
+            self.message(message_id)
+        }
+
+        async fn can_moderate(&self, session: &Session, _room_id: u32) -> bool {
+            self.moderators.contains(&session.id)
+        }
+
+        async fn find_author_by_name(&self, username: &str) -> Option<implement::Author> {
+            username
+                .strip_prefix("user")
+                .and_then(|id| id.parse().ok())
+                .map(author)
+        }
+
+        async fn get_room_members(&self, room_id: u32) -> Vec<u32> {
+            self.private_rooms
+                .get(&room_id)
+                .cloned()
+                .unwrap_or_default()
+        }
+
+        async fn get_direct_partner(&self, room_id: u32, user_id: u32) -> Option<u32> {
+            if !self.direct_rooms.contains(&room_id) {
+                return None;
+            }
+            self.private_rooms
+                .get(&room_id)?
+                .iter()
+                .copied()
+                .find(|member| *member != user_id)
+        }
+
+        async fn is_ignoring(&self, user_id: u32, other_id: u32) -> bool {
+            self.ignores.contains(&(user_id, other_id))
+        }
+    }
+
+    /// Stands in for a websocket connection, keeping what it is sent.
+    #[derive(Default)]
+    struct Client {
+        replies: Vec<String>,
+    }
+
+    impl Actor for Client {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<message::Reply> for Client {
+        type Result = ();
+
+        fn handle(&mut self, msg: message::Reply, _: &mut Context<Self>) {
+            self.replies.push(msg.0);
+        }
+    }
+
+    /// Takes what a client has been sent so far.
+    struct Take;
+
+    impl Message for Take {
+        type Result = Vec<String>;
+    }
+
+    impl Handler<Take> for Client {
+        type Result = MessageResult<Take>;
+
+        fn handle(&mut self, _: Take, _: &mut Context<Self>) -> Self::Result {
+            MessageResult(std::mem::take(&mut self.replies))
+        }
+    }
+
+    /// A user's connection to a test server.
+    struct TestConn {
+        id: usize,
+        session: Session,
+        client: Addr<Client>,
+    }
+
+    impl TestConn {
+        /// Replies sent to the connection since the last call. Replies are
+        /// queued before the server answers, so they are all here.
+        async fn replies(&self) -> Vec<String> {
+            self.client.send(Take).await.unwrap()
+        }
+    }
+
+    async fn start(layer: &Arc<MockLayer>) -> Addr<ChatServer> {
+        ChatServer::new(layer.clone(), Arc::new(Config::new()))
+            .await
+            .start()
+    }
+
+    async fn connect(server: &Addr<ChatServer>, layer: &MockLayer, user_id: u32) -> TestConn {
+        let session = layer.get_session_from_user_id(user_id).await;
+        let client = Client::default().start();
+        let id = server
+            .send(message::Connect {
+                addr: client.clone().recipient(),
+                session: session.clone(),
+            })
+            .await
+            .unwrap();
+        TestConn {
+            id,
+            session,
+            client,
+        }
+    }
+
+    /// Connect a user and join them to a room, dropping the replies to joining.
+    async fn join(
+        server: &Addr<ChatServer>,
+        layer: &MockLayer,
+        user_id: u32,
+        room_id: u32,
+    ) -> TestConn {
+        let conn = connect(server, layer, user_id).await;
+        server
+            .send(message::Join {
+                id: conn.id,
+                session: conn.session.clone(),
+                room_id,
+            })
+            .await
+            .unwrap();
+        conn.replies().await;
+        conn
+    }
+
+    async fn post(server: &Addr<ChatServer>, conn: &TestConn, room_id: u32, text: &str) {
+        server
+            .send(message::Post {
+                id: conn.id,
+                session: conn.session.clone(),
+                message: text.to_owned(),
+                room_id,
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn edit(server: &Addr<ChatServer>, conn: &TestConn, message_id: u32, text: &str) {
+        server
+            .send(message::Edit {
+                id: conn.id,
+                session: conn.session.clone(),
+                message: text.to_owned(),
+                message_id,
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn delete(server: &Addr<ChatServer>, conn: &TestConn, message_id: u32) {
+        server
+            .send(message::Delete {
+                id: conn.id,
+                session: conn.session.clone(),
+                message_id,
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn history(server: &Addr<ChatServer>, conn: &TestConn, room_id: u32, before: u32) {
+        server
+            .send(message::History {
+                id: conn.id,
+                session: conn.session.clone(),
+                room_id,
+                before,
+            })
+            .await
+            .unwrap();
+    }
+
+    async fn typing(server: &Addr<ChatServer>, conn: &TestConn, room_id: u32, typing: bool) {
+        server
+            .send(message::Typing {
+                id: conn.id,
+                session: conn.session.clone(),
+                room_id,
+                typing,
+            })
+            .await
+            .unwrap();
+    }
+
+    /// Replies that are JSON objects with `key`
+    fn json_with(replies: &[String], key: &str) -> Vec<serde_json::Value> {
+        replies
+            .iter()
+            .filter_map(|reply| serde_json::from_str::<serde_json::Value>(reply).ok())
+            .filter(|reply| reply.get(key).is_some())
+            .collect()
+    }
+
+    fn mentions(replies: &[String], text: &str) -> bool {
+        replies.iter().any(|reply| reply.contains(text))
+    }
+
+    #[actix_rt::test]
+    async fn test_ignored_authors_are_not_delivered() {
+        let layer = Arc::new(MockLayer {
+            ignores: HashSet::from([(2, 1)]),
+            ..Default::default()
+        });
+        let server = start(&layer).await;
+        let alice = join(&server, &layer, 1, 1).await;
+        let bob = join(&server, &layer, 2, 1).await;
+        let carol = join(&server, &layer, 3, 1).await;
+
+        post(&server, &alice, 1, "hello from alice").await;
+
+        assert!(!mentions(&bob.replies().await, "hello from alice"));
+        assert!(mentions(&carol.replies().await, "hello from alice"));
+        assert!(mentions(&alice.replies().await, "hello from alice"));
+    }
+
+    #[actix_rt::test]
+    async fn test_ignored_authors_are_left_out_of_history() {
+        let layer = Arc::new(MockLayer {
+            ignores: HashSet::from([(2, 1)]),
+            ..Default::default()
+        });
+        layer.add_message(1, 1, "older from alice", 60);
+        layer.add_message(1, 3, "older from carol", 60);
+        let newest = layer.add_message(1, 3, "newest from carol", 0);
+        let server = start(&layer).await;
+
+        let bob = connect(&server, &layer, 2).await;
+        server
+            .send(message::Join {
+                id: bob.id,
+                session: bob.session.clone(),
+                room_id: 1,
+            })
+            .await
+            .unwrap();
+        let replies = bob.replies().await;
+        assert!(!mentions(&replies, "older from alice"));
+        assert!(mentions(&replies, "older from carol"));
+
+        server
+            .send(message::History {
+                id: bob.id,
+                session: bob.session.clone(),
+                room_id: 1,
+                before: newest,
+            })
+            .await
+            .unwrap();
+        let replies = bob.replies().await;
+        assert!(!mentions(&replies, "older from alice"));
+        assert!(mentions(&replies, "older from carol"));
+    }
+
+    #[actix_rt::test]
+    async fn test_direct_messages_from_ignored_users_are_refused() {
+        let layer = Arc::new(MockLayer {
+            private_rooms: HashMap::from([(5, vec![1, 2])]),
+            direct_rooms: HashSet::from([5]),
+            ignores: HashSet::from([(2, 1)]),
+            ..Default::default()
+        });
+        let server = start(&layer).await;
+        let alice = join(&server, &layer, 1, 5).await;
+        let bob = join(&server, &layer, 2, 5).await;
+
+        post(&server, &alice, 5, "psst").await;
+        assert!(mentions(
+            &alice.replies().await,
+            "That user is not accepting direct messages from you."
+        ));
+        assert!(!mentions(&bob.replies().await, "psst"));
+        assert!(layer.messages.lock().unwrap().is_empty());
+
+        server
+            .send(message::OpenDirect {
+                id: alice.id,
+                session: alice.session.clone(),
+                username: "user2".to_owned(),
+            })
+            .await
+            .unwrap();
+        assert!(mentions(
+            &alice.replies().await,
+            "That user is not accepting direct messages from you."
+        ));
+
+        // The ignoring side can still write to the other.
+        post(&server, &bob, 5, "go away").await;
+        assert!(mentions(&alice.replies().await, "go away"));
+    }
+
+    #[actix_rt::test]
+    async fn test_private_rooms_are_for_members() {
+        let layer = Arc::new(MockLayer {
+            private_rooms: HashMap::from([(7, vec![1])]),
+            ..Default::default()
+        });
+        layer.add_message(7, 1, "members only", 60);
+        let server = start(&layer).await;
+        let alice = join(&server, &layer, 1, 7).await;
+        let bob = connect(&server, &layer, 2).await;
+
+        server
+            .send(message::Join {
+                id: bob.id,
+                session: bob.session.clone(),
+                room_id: 7,
+            })
+            .await
+            .unwrap();
+        let replies = bob.replies().await;
+        assert!(mentions(&replies, "You cannot join this room."));
+        assert!(!mentions(&replies, "members only"));
+
+        server
+            .send(message::History {
+                id: bob.id,
+                session: bob.session.clone(),
+                room_id: 7,
+                before: 100,
+            })
+            .await
+            .unwrap();
+        let replies = bob.replies().await;
+        assert!(mentions(&replies, "You cannot view this room."));
+        assert!(!mentions(&replies, "members only"));
+
+        post(&server, &alice, 7, "still secret").await;
+        assert!(mentions(&alice.replies().await, "still secret"));
+        assert!(!mentions(&bob.replies().await, "still secret"));
+
+        post(&server, &bob, 7, "let me in").await;
+        assert!(mentions(
+            &bob.replies().await,
+            "You cannot send messages in this room."
+        ));
+        assert!(!mentions(&alice.replies().await, "let me in"));
+    }
+
+    #[actix_rt::test]
+    async fn test_only_recent_messages_can_be_edited() {
+        let layer = Arc::new(MockLayer::default());
+        let recent = layer.add_message(1, 1, "recent", 10);
+        let old = layer.add_message(1, 1, "old", 600);
+        let server = start(&layer).await;
+        let alice = join(&server, &layer, 1, 1).await;
+        let bob = join(&server, &layer, 2, 1).await;
+
+        edit(&server, &alice, recent, "recent, edited").await;
+        assert!(mentions(&bob.replies().await, "recent, edited"));
+        assert_eq!(layer.message(recent).unwrap().message, "recent, edited");
+
+        // Past the default window of five minutes
+        edit(&server, &alice, old, "old, edited").await;
+        assert!(mentions(
+            &alice.replies().await,
+            "This message is too old to edit."
+        ));
+        assert!(!mentions(&bob.replies().await, "old, edited"));
+        assert_eq!(layer.message(old).unwrap().message, "old");
+    }
+
+    #[actix_rt::test]
+    async fn test_only_authors_edit_and_delete_without_moderation() {
+        let layer = Arc::new(MockLayer {
+            moderators: HashSet::from([3]),
+            ..Default::default()
+        });
+        let message_id = layer.add_message(1, 1, "mine", 10);
+        let server = start(&layer).await;
+        let alice = join(&server, &layer, 1, 1).await;
+        let bob = join(&server, &layer, 2, 1).await;
+        let moderator = join(&server, &layer, 3, 1).await;
+
+        edit(&server, &bob, message_id, "yours now").await;
+        assert!(mentions(&bob.replies().await, "Could not edit message."));
+        assert!(!mentions(&alice.replies().await, "yours now"));
+        assert_eq!(layer.message(message_id).unwrap().message, "mine");
+
+        // Moderation rights allow deleting, not rewriting
+        edit(&server, &moderator, message_id, "moderated").await;
+        assert!(mentions(
+            &moderator.replies().await,
+            "Could not edit message."
+        ));
+        assert_eq!(layer.message(message_id).unwrap().message, "mine");
+
+        delete(&server, &bob, message_id).await;
+        assert!(mentions(&bob.replies().await, "Could not delete message."));
+        assert!(!mentions(&alice.replies().await, "delete"));
+        assert!(layer.message(message_id).is_some());
+
+        delete(&server, &moderator, message_id).await;
+        let deleted = format!("{{\"delete\":[{}]}}", message_id);
+        assert!(mentions(&alice.replies().await, &deleted));
+        assert!(layer.message(message_id).is_none());
+
+        // Authors may delete their own
+        let own = layer.add_message(1, 2, "oops", 10);
+        delete(&server, &bob, own).await;
+        assert!(mentions(&bob.replies().await, &format!("[{}]", own)));
+        assert!(layer.message(own).is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_history_pages_until_complete() {
+        let layer = Arc::new(MockLayer::default());
+        let limit = Config::new().chat_history_limit();
+        let total = limit + 5;
+        for n in 0..total {
+            layer.add_message(1, 1, &format!("message {}", n), 60);
+        }
+        let server = start(&layer).await;
+        let alice = join(&server, &layer, 1, 1).await;
+
+        // A full page may have more behind it
+        history(&server, &alice, 1, total as u32 + 1).await;
+        let page: serde_json::Value = serde_json::from_str(&alice.replies().await[0]).unwrap();
+        let messages = page["history"].as_array().unwrap();
+        assert_eq!(messages.len(), limit);
+        assert_eq!(messages[0]["message_id"], 6);
+        assert_eq!(page["complete"], false);
+
+        // The last page is short and says so
+        history(&server, &alice, 1, 6).await;
+        let page: serde_json::Value = serde_json::from_str(&alice.replies().await[0]).unwrap();
+        let ids: Vec<u64> = page["history"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["message_id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        assert_eq!(page["complete"], true);
+
+        history(&server, &alice, 1, 1).await;
+        let page: serde_json::Value = serde_json::from_str(&alice.replies().await[0]).unwrap();
+        assert!(page["history"].as_array().unwrap().is_empty());
+        assert_eq!(page["complete"], true);
+    }
+
+    #[actix_rt::test]
+    async fn test_typing_notices() {
+        let layer = Arc::new(MockLayer::default());
+        let server = start(&layer).await;
+        let alice = join(&server, &layer, 1, 1).await;
+        let bob = join(&server, &layer, 2, 1).await;
+        let carol = connect(&server, &layer, 3).await;
+
+        typing(&server, &alice, 1, true).await;
+        assert_eq!(
+            json_with(&bob.replies().await, "typing"),
+            vec![serde_json::json!({ "typing": { "1": "user1" } })]
+        );
+
+        // Renewing the notice doesn't repeat it
+        typing(&server, &alice, 1, true).await;
+        assert!(json_with(&bob.replies().await, "typing").is_empty());
+
+        typing(&server, &alice, 1, false).await;
+        assert_eq!(
+            json_with(&bob.replies().await, "typing"),
+            vec![serde_json::json!({ "typing": { "1": false } })]
+        );
+
+        // Posting clears the notice
+        typing(&server, &alice, 1, true).await;
+        bob.replies().await;
+        post(&server, &alice, 1, "done typing").await;
+        let replies = bob.replies().await;
+        assert_eq!(
+            json_with(&replies, "typing"),
+            vec![serde_json::json!({ "typing": { "1": false } })]
+        );
+        assert!(mentions(&replies, "done typing"));
+
+        // Only connections in the room can type there
+        typing(&server, &carol, 1, true).await;
+        assert!(json_with(&bob.replies().await, "typing").is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_roster_join_and_leave() {
+        let layer = Arc::new(MockLayer::default());
+        let server = start(&layer).await;
+        let alice = join(&server, &layer, 1, 1).await;
+
+        let bob = connect(&server, &layer, 2).await;
+        server
+            .send(message::Join {
+                id: bob.id,
+                session: bob.session.clone(),
+                room_id: 1,
+            })
+            .await
+            .unwrap();
+
+        // The room hears about the newcomer
+        let joined = json_with(&alice.replies().await, "users");
+        assert_eq!(joined.len(), 1);
+        assert_eq!(joined[0]["users"]["2"]["id"], 2);
+        assert_eq!(joined[0]["users"]["2"]["username"], "user2");
+        assert!(joined[0]["users"]["2"]["last_activity"].is_u64());
+
+        // The newcomer gets everyone in the room
+        let roster = json_with(&bob.replies().await, "users");
+        let everyone = roster.last().unwrap()["users"].as_object().unwrap();
+        let mut ids: Vec<&String> = everyone.keys().collect();
+        ids.sort();
+        assert_eq!(ids, vec!["1", "2"]);
+
+        // A second tab doesn't announce the user again on the way out
+        let bob_again = join(&server, &layer, 2, 1).await;
+        alice.replies().await;
+        server
+            .send(message::Disconnect { id: bob.id })
+            .await
+            .unwrap();
+        assert!(json_with(&alice.replies().await, "user").is_empty());
+
+        server
+            .send(message::Disconnect { id: bob_again.id })
+            .await
+            .unwrap();
+        assert_eq!(
+            json_with(&alice.replies().await, "user"),
+            vec![serde_json::json!({ "user": { "2": false } })]
+        );
+    }
+
+    #[test]
+    fn test_roster_relay_serialization() {
+        let relay = message::Relay::Roster {
+            origin: "instance-a".to_owned(),
+            rooms: HashMap::from([(
+                1,
+                vec![UserActivity {
+                    id: 2,
+                    username: "user2".to_owned(),
+                    avatar_url: String::new(),
+                    last_activity: 1700000000,
+                }],
+            )]),
+        };
+
+        let json = serde_json::to_value(&relay).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "Roster",
+                "origin": "instance-a",
+                "rooms": {
+                    "1": [{
+                        "id": 2,
+                        "username": "user2",
+                        "avatar_url": "",
+                        "last_activity": 1700000000,
+                    }],
+                },
+            })
+        );
+
+        match serde_json::from_value::<message::Relay>(json).unwrap() {
+            message::Relay::Roster { origin, rooms } => {
+                assert_eq!(origin, "instance-a");
+                assert_eq!(rooms[&1][0].username, "user2");
+                assert_eq!(rooms[&1][0].last_activity, 1700000000);
+            }
+            _ => panic!("expected a roster relay"),
+        }
+    }
+}
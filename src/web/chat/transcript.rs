@@ -0,0 +1,478 @@
+//! Chat transcripts for the admin panel.
+//!
+//! Reads a room's persisted history, filtered by date, author and text, along
+//! with the chat moderation actions taken in the room over the same period.
+//! Deleted messages are included and marked, since transcripts are mostly
+//! pulled to look into an incident after the fact.
+
+use crate::db::get_db_pool;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use sea_orm::{DbBackend, DbErr, FromQueryResult, Statement, Value};
+use serde::Deserialize;
+use std::fmt::Write;
+
+/// Messages listed per page.
+pub const PAGE_SIZE: i64 = 100;
+/// Most messages put in one export.
+pub const EXPORT_LIMIT: i64 = 20_000;
+
+/// Filters for a transcript, as submitted by the filter form. Empty fields
+/// are ignored.
+#[derive(Debug, Default, Deserialize)]
+pub struct TranscriptFilter {
+    /// Username of the message author
+    pub author: Option<String>,
+    /// Text the message contains, ignoring case
+    pub text: Option<String>,
+    /// Sent on or after this date (YYYY-MM-DD)
+    pub from: Option<String>,
+    /// Sent on or before this date (YYYY-MM-DD)
+    pub to: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+impl TranscriptFilter {
+    pub fn author_value(&self) -> &str {
+        non_empty(&self.author).unwrap_or_default()
+    }
+
+    pub fn text_value(&self) -> &str {
+        non_empty(&self.text).unwrap_or_default()
+    }
+
+    pub fn from_value(&self) -> &str {
+        non_empty(&self.from).unwrap_or_default()
+    }
+
+    pub fn to_value(&self) -> &str {
+        non_empty(&self.to).unwrap_or_default()
+    }
+
+    fn date(value: &Option<String>) -> Option<NaiveDateTime> {
+        non_empty(value)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    }
+
+    /// Query string repeating these filters, for pagination and export links.
+    pub fn query_string(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in [
+            ("author", self.author_value()),
+            ("text", self.text_value()),
+            ("from", self.from_value()),
+            ("to", self.to_value()),
+        ] {
+            if !value.is_empty() {
+                query.append_pair(name, value);
+            }
+        }
+        query.finish()
+    }
+
+    /// One line describing the filters, for the head of an export.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.from_value().is_empty() {
+            parts.push(format!("from {}", self.from_value()));
+        }
+        if !self.to_value().is_empty() {
+            parts.push(format!("to {}", self.to_value()));
+        }
+        if !self.author_value().is_empty() {
+            parts.push(format!("by {}", self.author_value()));
+        }
+        if !self.text_value().is_empty() {
+            parts.push(format!("containing \"{}\"", self.text_value()));
+        }
+
+        if parts.is_empty() {
+            "All messages".to_owned()
+        } else {
+            format!("Messages {}", parts.join(", "))
+        }
+    }
+
+    /// SQL conditions on `timestamp`, and the values bound to their `$n`
+    /// parameters. `values` already holds the room ID.
+    fn date_conditions(&self, timestamp: &str, values: &mut Vec<Value>) -> Vec<String> {
+        let mut conditions = Vec::new();
+
+        if let Some(from) = Self::date(&self.from) {
+            values.push(from.into());
+            conditions.push(format!("{} >= ${}", timestamp, values.len()));
+        }
+        if let Some(to) = Self::date(&self.to) {
+            values.push((to + Duration::days(1)).into());
+            conditions.push(format!("{} < ${}", timestamp, values.len()));
+        }
+
+        conditions
+    }
+
+    /// SQL conditions on `chat_messages m` and `ugc_revisions r` in a room,
+    /// and the values bound to their `$n` parameters.
+    fn message_conditions(&self, room_id: i32) -> (String, Vec<Value>) {
+        let mut values: Vec<Value> = vec![room_id.into()];
+        let mut conditions = vec!["m.chat_room_id = $1".to_owned()];
+        conditions.extend(self.date_conditions("m.created_at", &mut values));
+
+        if !self.author_value().is_empty() {
+            values.push(self.author_value().to_owned().into());
+            conditions.push(format!("LOWER(un.name) = LOWER(${})", values.len()));
+        }
+        if !self.text_value().is_empty() {
+            values.push(self.text_value().to_owned().into());
+            conditions.push(format!(
+                "POSITION(LOWER(${}) IN LOWER(r.content)) > 0",
+                values.len()
+            ));
+        }
+
+        (conditions.join(" AND "), values)
+    }
+}
+
+/// A message in a transcript.
+#[derive(Debug, FromQueryResult)]
+pub struct TranscriptMessage {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub user_id: Option<i32>,
+    pub username: Option<String>,
+    /// Current text, after any edits
+    pub content: Option<String>,
+    pub edited: bool,
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+impl TranscriptMessage {
+    pub fn author(&self) -> &str {
+        self.username
+            .as_deref()
+            .unwrap_or(crate::constants::GUEST_USERNAME)
+    }
+
+    pub fn text(&self) -> &str {
+        self.content.as_deref().unwrap_or_default()
+    }
+}
+
+/// A chat moderation action taken in the room.
+#[derive(Debug, FromQueryResult)]
+pub struct TranscriptAction {
+    pub created_at: NaiveDateTime,
+    pub action: String,
+    pub moderator_name: Option<String>,
+    pub target_id: i32,
+    pub target_name: Option<String>,
+    pub reason: Option<String>,
+    pub duration_seconds: Option<i64>,
+}
+
+impl TranscriptAction {
+    /// "mute", "ban", "purge"...
+    pub fn action_name(&self) -> &str {
+        self.action.strip_prefix("chat_").unwrap_or(&self.action)
+    }
+
+    /// "Moderator muted Target for 10 minutes: reason"
+    pub fn describe(&self) -> String {
+        let verb = match self.action_name() {
+            "kick" => "kicked",
+            "ban" => "banned",
+            "unban" => "unbanned",
+            "mute" => "muted",
+            "unmute" => "unmuted",
+            "purge" => "purged messages from",
+            other => other,
+        };
+        let mut line = format!(
+            "{} {} {}",
+            self.moderator_name.as_deref().unwrap_or("(unknown)"),
+            verb,
+            self.target_name
+                .to_owned()
+                .unwrap_or_else(|| format!("#{}", self.target_id))
+        );
+
+        if let Some(seconds) = self.duration_seconds.filter(|seconds| *seconds > 0) {
+            let _ = write!(
+                line,
+                " for {}",
+                super::moderation::format_duration(seconds as u64)
+            );
+        }
+        if let Some(reason) = self.reason.as_deref().filter(|r| !r.trim().is_empty()) {
+            let _ = write!(line, ": {}", reason.trim());
+        }
+
+        line
+    }
+}
+
+const MESSAGE_COLUMNS: &str = r#"
+    m.id, m.created_at, m.user_id, un.name AS username, r.content,
+    EXISTS (SELECT 1 FROM ugc_revisions er WHERE er.ugc_id = m.ugc_id AND er.id <> r.id) AS edited,
+    d.deleted_at
+"#;
+
+const MESSAGE_JOINS: &str = r#"
+    LEFT JOIN ugc u ON u.id = m.ugc_id
+    LEFT JOIN ugc_revisions r ON r.id = u.ugc_revision_id
+    LEFT JOIN ugc_deletions d ON d.id = m.ugc_id
+    LEFT JOIN user_names un ON un.user_id = m.user_id
+"#;
+
+/// Messages in a room matching `filter`, oldest first, and the total number
+/// of matches.
+pub async fn search_messages(
+    room_id: i32,
+    filter: &TranscriptFilter,
+    page: i64,
+) -> Result<(Vec<TranscriptMessage>, i64), DbErr> {
+    #[derive(FromQueryResult)]
+    struct Total {
+        count: i64,
+    }
+
+    let db = get_db_pool();
+    let (conditions, values) = filter.message_conditions(room_id);
+
+    let total = Total::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            "SELECT COUNT(*) AS count FROM chat_messages m {} WHERE {}",
+            MESSAGE_JOINS, conditions
+        ),
+        values.clone(),
+    ))
+    .one(db)
+    .await?
+    .map(|total| total.count)
+    .unwrap_or(0);
+
+    let messages =
+        find_messages(conditions, values, PAGE_SIZE, (page.max(1) - 1) * PAGE_SIZE).await?;
+
+    Ok((messages, total))
+}
+
+/// Messages in a room matching `filter`, oldest first, up to `EXPORT_LIMIT`.
+pub async fn export_messages(
+    room_id: i32,
+    filter: &TranscriptFilter,
+) -> Result<Vec<TranscriptMessage>, DbErr> {
+    let (conditions, values) = filter.message_conditions(room_id);
+    find_messages(conditions, values, EXPORT_LIMIT, 0).await
+}
+
+async fn find_messages(
+    conditions: String,
+    mut values: Vec<Value>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<TranscriptMessage>, DbErr> {
+    values.push(limit.into());
+    values.push(offset.into());
+
+    TranscriptMessage::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            "SELECT {} FROM chat_messages m {} WHERE {}
+             ORDER BY m.created_at ASC, m.id ASC
+             LIMIT ${} OFFSET ${}",
+            MESSAGE_COLUMNS,
+            MESSAGE_JOINS,
+            conditions,
+            values.len() - 1,
+            values.len()
+        ),
+        values,
+    ))
+    .all(get_db_pool())
+    .await
+}
+
+/// Chat moderation actions taken in a room within the filter's dates, oldest
+/// first. The author and text filters don't apply to these.
+pub async fn find_actions(
+    room_id: i32,
+    filter: &TranscriptFilter,
+) -> Result<Vec<TranscriptAction>, DbErr> {
+    let mut values: Vec<Value> = vec![room_id.to_string().into()];
+    let mut conditions = vec![
+        "l.action LIKE 'chat\\_%'".to_owned(),
+        "l.metadata->>'chat_room_id' = $1".to_owned(),
+    ];
+    conditions.extend(filter.date_conditions("l.created_at", &mut values));
+
+    TranscriptAction::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            "SELECT l.created_at, l.action, mn.name AS moderator_name, l.target_id,
+                tn.name AS target_name, l.reason,
+                (l.metadata->>'duration_seconds')::BIGINT AS duration_seconds
+             FROM mod_log l
+             LEFT JOIN user_names mn ON mn.user_id = l.moderator_id
+             LEFT JOIN user_names tn ON tn.user_id = l.target_id
+             WHERE {}
+             ORDER BY l.created_at ASC, l.id ASC
+             LIMIT {}",
+            conditions.join(" AND "),
+            EXPORT_LIMIT
+        ),
+        values,
+    ))
+    .all(get_db_pool())
+    .await
+}
+
+/// The transcript as plain text, with moderation actions between the
+/// messages at the time they were taken.
+pub fn to_plain_text(
+    room_title: &str,
+    filter: &TranscriptFilter,
+    messages: &[TranscriptMessage],
+    actions: &[TranscriptAction],
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Chat transcript: {}", room_title);
+    let _ = writeln!(out, "{}", filter.describe());
+    let _ = writeln!(
+        out,
+        "{} message(s), {} moderation action(s). Times are UTC.",
+        messages.len(),
+        actions.len()
+    );
+    out.push('\n');
+
+    let mut actions = actions.iter().peekable();
+    for message in messages {
+        while let Some(action) = actions.next_if(|a| a.created_at <= message.created_at) {
+            write_action(&mut out, action);
+        }
+
+        let _ = write!(
+            out,
+            "[{}] <{}> {}",
+            message.created_at.format("%Y-%m-%d %H:%M:%S"),
+            message.author(),
+            message.text().replace('\n', "\n    ")
+        );
+        if message.edited {
+            out.push_str(" (edited)");
+        }
+        if let Some(deleted_at) = message.deleted_at {
+            let _ = write!(out, " (deleted {})", deleted_at.format("%Y-%m-%d %H:%M:%S"));
+        }
+        out.push('\n');
+    }
+    for action in actions {
+        write_action(&mut out, action);
+    }
+
+    if messages.len() as i64 >= EXPORT_LIMIT {
+        let _ = writeln!(
+            out,
+            "\nOnly the first {} messages were exported. Narrow the dates for the rest.",
+            EXPORT_LIMIT
+        );
+    }
+
+    out
+}
+
+fn write_action(out: &mut String, action: &TranscriptAction) {
+    let _ = writeln!(
+        out,
+        "[{}] *** {}",
+        action.created_at.format("%Y-%m-%d %H:%M:%S"),
+        action.describe()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn message(id: i32, time: &str, name: &str, content: &str) -> TranscriptMessage {
+        TranscriptMessage {
+            id,
+            created_at: at(time),
+            user_id: Some(id),
+            username: Some(name.to_owned()),
+            content: Some(content.to_owned()),
+            edited: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_query_string_skips_empty_fields() {
+        let filter = TranscriptFilter {
+            author: Some(" alice ".to_owned()),
+            text: Some(String::new()),
+            from: Some("2026-01-01".to_owned()),
+            to: None,
+        };
+
+        assert_eq!(filter.query_string(), "author=alice&from=2026-01-01");
+        assert_eq!(filter.describe(), "Messages from 2026-01-01, by alice");
+    }
+
+    #[test]
+    fn test_conditions_bind_in_order() {
+        let filter = TranscriptFilter {
+            author: Some("alice".to_owned()),
+            text: Some("spam".to_owned()),
+            from: Some("2026-01-01".to_owned()),
+            to: Some("not a date".to_owned()),
+        };
+
+        let (conditions, values) = filter.message_conditions(7);
+        assert_eq!(values.len(), 4);
+        assert!(conditions.contains("m.created_at >= $2"));
+        assert!(conditions.contains("LOWER(un.name) = LOWER($3)"));
+        assert!(conditions.contains("POSITION(LOWER($4) IN LOWER(r.content)) > 0"));
+    }
+
+    #[test]
+    fn test_plain_text_interleaves_actions() {
+        let mut deleted = message(2, "2026-01-01 12:01:00", "bob", "buy now");
+        deleted.deleted_at = Some(at("2026-01-01 12:03:00"));
+        let messages = vec![
+            message(1, "2026-01-01 12:00:00", "alice", "hello\nthere"),
+            deleted,
+        ];
+        let actions = vec![TranscriptAction {
+            created_at: at("2026-01-01 12:02:00"),
+            action: "chat_mute".to_owned(),
+            moderator_name: Some("mod".to_owned()),
+            target_id: 2,
+            target_name: Some("bob".to_owned()),
+            reason: Some("spam".to_owned()),
+            duration_seconds: Some(600),
+        }];
+
+        let text = to_plain_text("General", &TranscriptFilter::default(), &messages, &actions);
+        let lines: Vec<&str> = text.lines().skip(4).collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "[2026-01-01 12:00:00] <alice> hello",
+                "    there",
+                "[2026-01-01 12:01:00] <bob> buy now (deleted 2026-01-01 12:03:00)",
+                "[2026-01-01 12:02:00] *** mod muted bob for 10 minutes: spam",
+            ]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! File sharing in chat
+//!
+//! Files dropped into chat are stored like any other attachment, so they are
+//! deduplicated, virus scanned, thumbnailed and counted against the uploader's
+//! quota. The client then posts a message linking to the file, which renders
+//! as an inline thumbnail for images and a link for everything else.
+
+use crate::config::Config;
+use crate::filesystem::insert_field_as_attachment;
+use crate::middleware::ClientCtx;
+use actix_multipart::Multipart;
+use actix_web::{error, post, web::Data, Error, HttpRequest, Responder};
+use futures::{StreamExt, TryStreamExt};
+use mime::Mime;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Links to stored files, as the client writes them into messages
+static SHARED_FILE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"/content/([0-9a-f]{64})/").unwrap());
+
+#[derive(Serialize)]
+pub struct ChatUploadResponse {
+    pub hash: String,
+    /// Name of the file as the user sent it
+    pub filename: String,
+    pub is_image: bool,
+}
+
+/// Whether a MIME type matches one of the allowed types. `image/*` matches
+/// every image and `*` matches anything.
+pub fn is_mime_allowed(allowed: &[String], mime: &Mime) -> bool {
+    let essence = mime.essence_str().to_lowercase();
+
+    allowed.iter().any(|pattern| match pattern.as_str() {
+        "*" | "*/*" => true,
+        pattern => match pattern.strip_suffix("/*") {
+            Some(type_) => mime.type_().as_str().eq_ignore_ascii_case(type_),
+            None => pattern == essence,
+        },
+    })
+}
+
+/// Hashes of the stored files a message links to, so they can be attached to
+/// it and kept for as long as the message is.
+pub fn shared_file_hashes(message: &str) -> Vec<String> {
+    let mut hashes: Vec<String> = SHARED_FILE_REGEX
+        .captures_iter(message)
+        .map(|captures| captures[1].to_owned())
+        .collect();
+    hashes.sort();
+    hashes.dedup();
+    hashes
+}
+
+#[post("/chat/upload")]
+pub async fn post_chat_upload(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    req: HttpRequest,
+    mut multipart: Multipart,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+
+    let config = req.app_data::<Data<Arc<Config>>>().expect("No config.");
+    if !config.chat_uploads_enabled() {
+        return Err(error::ErrorForbidden("File sharing is disabled in chat."));
+    }
+
+    if let Err(e) =
+        crate::rate_limit::check_file_upload_rate_limit(user_id, &client.get_groups()).await
+    {
+        log::warn!("Chat upload rate limit exceeded for user: {}", user_id);
+        return Err(error::ErrorTooManyRequests(format!(
+            "Too many uploads. Please try again in {} seconds.",
+            e.retry_after_seconds
+        )));
+    }
+
+    let mut csrf_token: Option<String> = None;
+
+    while let Ok(Some(mut field)) = multipart.try_next().await {
+        match field.content_disposition().get_name() {
+            Some("csrf_token") => {
+                let mut buf: Vec<u8> = Vec::with_capacity(128);
+                while let Some(chunk) = field.next().await {
+                    let bytes = chunk.map_err(|e| {
+                        log::error!("post_chat_upload: multipart read error: {}", e);
+                        error::ErrorBadRequest("Error interpreting user input.")
+                    })?;
+                    buf.extend(bytes.to_owned());
+                }
+                csrf_token = Some(
+                    std::str::from_utf8(&buf)
+                        .map_err(|_| error::ErrorBadRequest("Error interpreting user input."))?
+                        .trim()
+                        .to_owned(),
+                );
+            }
+            Some("file") => {
+                let token = csrf_token.as_ref().ok_or_else(|| {
+                    error::ErrorBadRequest("CSRF token must be provided before file upload")
+                })?;
+                crate::middleware::csrf::validate_csrf_token(&cookies, token)?;
+
+                let mime = field
+                    .content_type()
+                    .cloned()
+                    .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+                if !is_mime_allowed(&config.chat_upload_mime_types(), &mime) {
+                    return Err(error::ErrorUnsupportedMediaType(
+                        "That type of file can't be shared in chat.",
+                    ));
+                }
+
+                let filename = field
+                    .content_disposition()
+                    .get_filename()
+                    .unwrap_or_default()
+                    .to_owned();
+
+                let upload = insert_field_as_attachment(&mut field, &client)
+                    .await?
+                    .ok_or_else(|| error::ErrorBadRequest("Upload is empty or improper."))?;
+
+                return Ok(actix_web::web::Json(ChatUploadResponse {
+                    filename: if filename.is_empty() {
+                        upload.filename
+                    } else {
+                        filename
+                    },
+                    hash: upload.hash,
+                    is_image: mime.type_() == mime::IMAGE,
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    Err(error::ErrorBadRequest("No file was sent."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(types: &[&str]) -> Vec<String> {
+        types.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_wildcard_subtype() {
+        let types = allowed(&["image/*", "application/pdf"]);
+
+        assert!(is_mime_allowed(&types, &mime::IMAGE_PNG));
+        assert!(is_mime_allowed(&types, &mime::APPLICATION_PDF));
+        assert!(!is_mime_allowed(&types, &mime::TEXT_HTML));
+        assert!(!is_mime_allowed(&types, &mime::APPLICATION_OCTET_STREAM));
+    }
+
+    #[test]
+    fn test_parameters_ignored() {
+        let types = allowed(&["text/plain"]);
+
+        assert!(is_mime_allowed(&types, &mime::TEXT_PLAIN_UTF_8));
+    }
+
+    #[test]
+    fn test_shared_file_hashes() {
+        let hash = "ab".repeat(32);
+        let message = format!(
+            "[thumb]/content/{hash}/cat.png[/thumb] and [url=https://example.com/content/{hash}/cat.png]again[/url] /content/nothex/x",
+            hash = hash
+        );
+
+        assert_eq!(shared_file_hashes(&message), vec![hash]);
+    }
+
+    #[test]
+    fn test_allow_everything() {
+        assert!(is_mime_allowed(&allowed(&["*"]), &mime::TEXT_HTML));
+        assert!(!is_mime_allowed(&allowed(&[]), &mime::IMAGE_PNG));
+    }
+}
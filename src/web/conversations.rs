@@ -2,9 +2,12 @@
 
 use crate::conversations;
 use crate::middleware::ClientCtx;
+use crate::pagination::PageStart;
+use crate::word_filter::{filter_for_saving, FilterScope};
 use actix_multipart::Multipart;
 use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
 use askama_actix::{Template, TemplateToResponse};
+use sea_orm::DbErr;
 use serde::Deserialize;
 
 mod filters {
@@ -26,8 +29,26 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         .service(leave_conversation_handler)
         .service(archive_conversation_handler)
         .service(unarchive_conversation_handler)
+        .service(mute_conversation_handler)
+        .service(unmute_conversation_handler)
         .service(kick_participant_handler)
-        .service(invite_participant_handler);
+        .service(invite_participant_handler)
+        .service(transfer_ownership_handler)
+        .service(rename_conversation_handler);
+}
+
+/// Map errors from the conversations module onto HTTP responses. Rule
+/// violations are shown to the user as 403s and missing records as 404s;
+/// anything else is logged and reported as a 500.
+fn conversation_error(action: &str, e: DbErr) -> Error {
+    match e {
+        DbErr::Custom(message) => error::ErrorForbidden(message),
+        DbErr::RecordNotFound(message) => error::ErrorNotFound(message),
+        e => {
+            tracing::error!(error = %e, "Failed to {}", action);
+            error::ErrorInternalServerError(format!("Failed to {}", action))
+        }
+    }
 }
 
 /// Template for inbox (conversation list)
@@ -37,6 +58,7 @@ struct InboxTemplate {
     client: ClientCtx,
     conversations: Vec<conversations::ConversationPreview>,
     unread_count: i64,
+    unread_messages: i64,
 }
 
 /// Template for archived conversations
@@ -57,8 +79,11 @@ struct ConversationViewTemplate {
     participants: Vec<conversations::ParticipantInfo>,
     title: Option<String>,
     is_archived: bool,
-    is_creator: bool,
+    is_muted: bool,
+    is_owner: bool,
+    max_participants: usize,
     attachments: std::collections::HashMap<i32, Vec<crate::attachment::AttachmentForTemplate>>,
+    seen_by: std::collections::HashMap<i32, Vec<String>>,
 }
 
 /// Template for new conversation form
@@ -83,10 +108,15 @@ pub async fn view_inbox(client: ClientCtx) -> Result<impl Responder, Error> {
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    let unread_messages = conversations::count_unread_messages(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
     Ok(InboxTemplate {
         client,
         conversations,
         unread_count,
+        unread_messages,
     }
     .to_response())
 }
@@ -115,40 +145,47 @@ pub async fn view_conversation(
         .ok_or_else(|| error::ErrorForbidden("You are not a participant in this conversation"))?;
 
     let is_archived = user_participant.is_archived;
+    let is_muted = user_participant.is_muted;
 
     // Get messages
-    let messages = conversations::get_conversation_messages(conv_id, 100, 0)
+    let messages = conversations::get_conversation_messages(conv_id, 100, PageStart::Offset(0))
         .await
         .map_err(error::ErrorInternalServerError)?;
 
     // Get attachments for messages
     use crate::attachment::get_attachments_for_ugc_by_id;
-    let attachments =
+    let mut attachments =
         get_attachments_for_ugc_by_id(messages.iter().map(|m| m.ugc_id).collect()).await;
+    for list in attachments.values_mut() {
+        list.retain(|attachment| attachment.is_visible_to(&client));
+    }
 
-    // Get participant info (includes creator status)
+    // Get participant info (includes roles)
     let participants = conversations::get_participant_info(conv_id)
         .await
-        .map_err(error::ErrorInternalServerError)?;
+        .map_err(|e| conversation_error("load participants", e))?;
 
-    // Get conversation title and creator
+    // Get conversation title
     use crate::orm::conversations as conv_orm;
-    let conversation = conv_orm::Entity::find_by_id(conv_id)
+    let title = conv_orm::Entity::find_by_id(conv_id)
         .one(db)
         .await
-        .map_err(error::ErrorInternalServerError)?;
+        .map_err(error::ErrorInternalServerError)?
+        .and_then(|c| c.title);
 
-    let (title, is_creator) = if let Some(c) = conversation {
-        (c.title, c.creator_id == Some(user_id))
-    } else {
-        (None, false)
-    };
+    let is_owner = user_participant.role == conversation_participants::ParticipantRole::Owner;
+
+    let seen_by = conversations::get_seen_by(&messages, &participants, user_id);
 
     // Mark as read
     conversations::mark_conversation_read(user_id, conv_id)
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    if let Err(e) = conversations::realtime::broadcast_read_receipt(user_id, conv_id).await {
+        tracing::warn!(error = %e, "Failed to broadcast read receipt");
+    }
+
     Ok(ConversationViewTemplate {
         client,
         conversation_id: conv_id,
@@ -156,8 +193,11 @@ pub async fn view_conversation(
         participants,
         title,
         is_archived,
-        is_creator,
+        is_muted,
+        is_owner,
+        max_participants: conversations::max_participants(),
         attachments,
+        seen_by,
     }
     .to_response())
 }
@@ -187,8 +227,8 @@ pub async fn create_conversation(
     let user_id = client.require_login()?;
 
     // Rate limiting - uses post_creation limit (covers posts, profile posts, messages)
-    if let Err(e) = crate::rate_limit::check_post_rate_limit(user_id) {
-        log::warn!("Conversation creation rate limit exceeded for user: {}", user_id);
+    if let Err(e) = crate::rate_limit::check_post_rate_limit(user_id, &client.get_groups()).await {
+        tracing::warn!("Conversation creation rate limit exceeded");
         return Err(error::ErrorTooManyRequests(format!(
             "Too many messages. Please try again in {} seconds.",
             e.retry_after_seconds
@@ -207,6 +247,15 @@ pub async fn create_conversation(
         return Err(error::ErrorBadRequest("At least one recipient is required"));
     }
 
+    let title = form
+        .title
+        .as_deref()
+        .map(|title| filter_for_saving(title, FilterScope::ThreadTitles))
+        .transpose()
+        .map_err(error::ErrorBadRequest)?;
+    let message =
+        filter_for_saving(&form.message, FilterScope::Posts).map_err(error::ErrorBadRequest)?;
+
     // Look up user IDs
     use crate::orm::user_names;
     use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
@@ -233,12 +282,12 @@ pub async fn create_conversation(
 
     // Create conversation
     let conversation_id =
-        conversations::create_conversation(user_id, &recipient_ids, form.title.as_deref())
+        conversations::create_conversation(user_id, &recipient_ids, title.as_deref())
             .await
-            .map_err(error::ErrorInternalServerError)?;
+            .map_err(|e| conversation_error("create conversation", e))?;
 
     // Send first message
-    conversations::send_message(conversation_id, user_id, &form.message)
+    conversations::send_message(conversation_id, user_id, &message)
         .await
         .map_err(error::ErrorInternalServerError)?;
 
@@ -254,16 +303,13 @@ pub async fn create_conversation(
                 .map(|p| p.name)
                 .unwrap_or_else(|| "Someone".to_string());
 
-            // Create notification
-            let _ = crate::notifications::create_notification(
+            // Create notification, grouped per conversation
+            let _ = notify_conversation_message(
                 recipient_id,
-                crate::notifications::NotificationType::PrivateMessage,
-                format!("New message from {}", sender_name),
-                "You have received a new private message".to_string(),
-                Some(format!("/conversations/{}", conversation_id)),
-                Some(user_id),
-                Some("conversation".to_string()),
-                Some(conversation_id),
+                conversation_id,
+                user_id,
+                sender_name,
+                "You have received a new private message",
             )
             .await;
         }
@@ -274,6 +320,100 @@ pub async fn create_conversation(
         .finish())
 }
 
+/// Notify a participant of a new message. Messages in the same conversation
+/// collapse into one unread notification.
+async fn notify_conversation_message(
+    recipient_id: i32,
+    conversation_id: i32,
+    sender_id: i32,
+    sender_name: String,
+    message: &str,
+) -> Result<(), DbErr> {
+    use crate::notifications::grouping::{self, GroupEvent};
+    use crate::notifications::NotificationType;
+
+    grouping::notify_grouped(GroupEvent {
+        user_id: recipient_id,
+        notification_type: NotificationType::PrivateMessage,
+        group_key: grouping::group_key(
+            &NotificationType::PrivateMessage,
+            "conversation",
+            conversation_id,
+        ),
+        actor_id: sender_id,
+        actor_name: sender_name,
+        action: "messaged you".to_string(),
+        message: message.to_string(),
+        url: Some(format!("/conversations/{}", conversation_id)),
+        source_content_type: Some("conversation".to_string()),
+        source_content_id: Some(conversation_id),
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Pushes a new message to open conversation pages and notifies the other
+/// participants.
+pub(crate) async fn announce_message(
+    conversation_id: i32,
+    sender_id: i32,
+    message_id: i32,
+    content: &str,
+    created_at: chrono::NaiveDateTime,
+) -> Result<(), Error> {
+    use crate::orm::conversation_participants;
+    use crate::user::Profile;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    let db = crate::db::get_db_pool();
+
+    // Get participants to notify
+    let participants = conversation_participants::Entity::find()
+        .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
+        .filter(conversation_participants::Column::UserId.ne(sender_id))
+        .all(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    // Get sender name
+    let sender_name = Profile::get_by_id(db, sender_id)
+        .await
+        .ok()
+        .flatten()
+        .map(|p| p.name)
+        .unwrap_or_else(|| "Someone".to_string());
+
+    // Push the message to open conversation pages
+    let event = crate::web::notifications_ws::ConversationEvent::Message {
+        conversation_id,
+        message_id,
+        user_id: sender_id,
+        author_name: sender_name.clone(),
+        content_html: crate::bbcode::parse(content),
+        created_at: created_at.to_string(),
+    };
+    if let Err(e) =
+        conversations::realtime::broadcast_to_participants(conversation_id, event, None).await
+    {
+        tracing::warn!(error = %e, "Failed to broadcast conversation message");
+    }
+
+    // Send notifications
+    for participant in participants {
+        let _ = notify_conversation_message(
+            participant.user_id,
+            conversation_id,
+            sender_id,
+            sender_name.clone(),
+            "You have new messages in a conversation",
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
 /// POST /conversations/{id}/send - Send a message in a conversation
 #[post("/conversations/{id}/send")]
 pub async fn send_message_handler(
@@ -297,8 +437,8 @@ pub async fn send_message_handler(
     let conv_id = *conversation_id;
 
     // Rate limiting - uses post_creation limit (covers posts, profile posts, messages)
-    if let Err(e) = crate::rate_limit::check_post_rate_limit(user_id) {
-        log::warn!("Message send rate limit exceeded for user: {}", user_id);
+    if let Err(e) = crate::rate_limit::check_post_rate_limit(user_id, &client.get_groups()).await {
+        tracing::warn!("Message send rate limit exceeded");
         return Err(error::ErrorTooManyRequests(format!(
             "Too many messages. Please try again in {} seconds.",
             e.retry_after_seconds
@@ -317,7 +457,7 @@ pub async fn send_message_handler(
                     let mut buf: Vec<u8> = Vec::with_capacity(128);
                     while let Some(chunk) = field.next().await {
                         let bytes = chunk.map_err(|e| {
-                            log::error!("send_message: multipart read error: {}", e);
+                            tracing::error!(error = %e, "send_message: multipart read error");
                             error::ErrorBadRequest("Error interpreting user input.")
                         })?;
                         buf.extend(bytes.to_owned());
@@ -328,7 +468,7 @@ pub async fn send_message_handler(
                     let mut buf: Vec<u8> = Vec::with_capacity(65536);
                     while let Some(chunk) = field.next().await {
                         let bytes = chunk.map_err(|e| {
-                            log::error!("send_message: multipart read error: {}", e);
+                            tracing::error!(error = %e, "send_message: multipart read error");
                             error::ErrorBadRequest("Error interpreting user input.")
                         })?;
                         buf.extend(bytes.to_owned());
@@ -336,7 +476,7 @@ pub async fn send_message_handler(
                     content = std::str::from_utf8(&buf).unwrap().to_owned();
                 }
                 "attachment" => {
-                    if let Some(upload) = insert_field_as_attachment(&mut field).await? {
+                    if let Some(upload) = insert_field_as_attachment(&mut field, &client).await? {
                         let filename = field
                             .content_disposition()
                             .get_filename()
@@ -345,6 +485,16 @@ pub async fn send_message_handler(
                         uploads.push((filename, upload));
                     }
                 }
+                "uploaded_attachment" => {
+                    if let Some(upload) =
+                        crate::filesystem::chunked::read_uploaded_attachment_field(
+                            &mut field, user_id,
+                        )
+                        .await?
+                    {
+                        uploads.push(upload);
+                    }
+                }
                 _ => {}
             }
         }
@@ -358,6 +508,8 @@ pub async fn send_message_handler(
     if content.trim().is_empty() {
         return Err(error::ErrorBadRequest("Message cannot be empty"));
     }
+    let content =
+        filter_for_saving(&content, FilterScope::Posts).map_err(error::ErrorBadRequest)?;
 
     let db = get_db_pool();
     let txn = db.begin().await.map_err(error::ErrorInternalServerError)?;
@@ -387,7 +539,7 @@ pub async fn send_message_handler(
         created_at: Set(ugc_revision.created_at),
         ..Default::default()
     };
-    message
+    let message = message
         .insert(&txn)
         .await
         .map_err(error::ErrorInternalServerError)?;
@@ -421,11 +573,11 @@ pub async fn send_message_handler(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
-    // Update sender's last_read_at so they don't see their own message as unread
+    // The sender has read everything up to their own message
     conversation_participants::Entity::update_many()
         .col_expr(
-            conversation_participants::Column::LastReadAt,
-            Expr::value(ugc_revision.created_at),
+            conversation_participants::Column::LastReadMessageId,
+            Expr::value(message.id),
         )
         .filter(conversation_participants::Column::ConversationId.eq(conv_id))
         .filter(conversation_participants::Column::UserId.eq(user_id))
@@ -437,37 +589,10 @@ pub async fn send_message_handler(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
-    // Get participants to notify
-    let participants = conversation_participants::Entity::find()
-        .filter(conversation_participants::Column::ConversationId.eq(conv_id))
-        .filter(conversation_participants::Column::UserId.ne(user_id))
-        .all(db)
-        .await
-        .map_err(error::ErrorInternalServerError)?;
-
-    // Get sender name
-    use crate::user::Profile;
-    let sender_name = Profile::get_by_id(db, user_id)
-        .await
-        .ok()
-        .flatten()
-        .map(|p| p.name)
-        .unwrap_or_else(|| "Someone".to_string());
-
-    // Send notifications
-    for participant in participants {
-        let _ = crate::notifications::create_notification(
-            participant.user_id,
-            crate::notifications::NotificationType::PrivateMessage,
-            format!("New message from {}", sender_name),
-            "You have a new message in a conversation".to_string(),
-            Some(format!("/conversations/{}", conv_id)),
-            Some(user_id),
-            Some("message".to_string()),
-            None,
-        )
+    crate::filesystem::visibility::refresh_attachments_visibility(uploads.iter().map(|u| u.1.id))
         .await;
-    }
+
+    announce_message(conv_id, user_id, message.id, &content, message.created_at).await?;
 
     Ok(HttpResponse::SeeOther()
         .append_header(("Location", format!("/conversations/{}", conv_id)))
@@ -493,6 +618,8 @@ pub async fn edit_message_handler(
     if form.content.trim().is_empty() {
         return Err(error::ErrorBadRequest("Message cannot be empty"));
     }
+    let content =
+        filter_for_saving(&form.content, FilterScope::Posts).map_err(error::ErrorBadRequest)?;
 
     // Get the message to find the conversation ID for redirect
     let message = conversations::get_message(msg_id)
@@ -503,14 +630,11 @@ pub async fn edit_message_handler(
     let conv_id = message.conversation_id;
 
     // Update the message
-    conversations::update_message(msg_id, user_id, &form.content)
+    conversations::update_message(msg_id, user_id, &content)
         .await
-        .map_err(|e| {
-            log::error!("Failed to edit message: {}", e);
-            error::ErrorForbidden(e.to_string())
-        })?;
+        .map_err(|e| conversation_error("edit message", e))?;
 
-    log::info!("User {} edited message {}", user_id, msg_id);
+    tracing::info!(message_id = msg_id, "Message edited");
 
     Ok(HttpResponse::SeeOther()
         .append_header(("Location", format!("/conversations/{}", conv_id)))
@@ -540,12 +664,9 @@ pub async fn delete_message_handler(
     // Delete the message (owner can always delete, moderators can delete any)
     conversations::delete_message(msg_id, user_id, can_moderate)
         .await
-        .map_err(|e| {
-            log::error!("Failed to delete message: {}", e);
-            error::ErrorForbidden(e.to_string())
-        })?;
+        .map_err(|e| conversation_error("delete message", e))?;
 
-    log::info!("User {} deleted message {}", user_id, msg_id);
+    tracing::info!(message_id = msg_id, "Message deleted");
 
     Ok(HttpResponse::SeeOther()
         .append_header(("Location", format!("/conversations/{}", conv_id)))
@@ -575,12 +696,9 @@ pub async fn leave_conversation_handler(
     // Leave the conversation
     conversations::leave_conversation(user_id, conv_id)
         .await
-        .map_err(|e| {
-            log::error!("Failed to leave conversation: {}", e);
-            error::ErrorInternalServerError("Failed to leave conversation")
-        })?;
+        .map_err(|e| conversation_error("leave conversation", e))?;
 
-    log::info!("User {} left conversation {}", user_id, conv_id);
+    tracing::info!(conversation_id = conv_id, "Left conversation");
 
     // Redirect to inbox
     Ok(HttpResponse::SeeOther()
@@ -628,12 +746,9 @@ pub async fn archive_conversation_handler(
     // Archive the conversation
     conversations::archive_conversation(user_id, conv_id)
         .await
-        .map_err(|e| {
-            log::error!("Failed to archive conversation: {}", e);
-            error::ErrorInternalServerError("Failed to archive conversation")
-        })?;
+        .map_err(|e| conversation_error("archive conversation", e))?;
 
-    log::info!("User {} archived conversation {}", user_id, conv_id);
+    tracing::info!(conversation_id = conv_id, "Conversation archived");
 
     // Redirect to inbox
     Ok(HttpResponse::SeeOther()
@@ -658,12 +773,9 @@ pub async fn unarchive_conversation_handler(
     // Unarchive the conversation
     conversations::unarchive_conversation(user_id, conv_id)
         .await
-        .map_err(|e| {
-            log::error!("Failed to unarchive conversation: {}", e);
-            error::ErrorInternalServerError("Failed to unarchive conversation")
-        })?;
+        .map_err(|e| conversation_error("unarchive conversation", e))?;
 
-    log::info!("User {} unarchived conversation {}", user_id, conv_id);
+    tracing::info!(conversation_id = conv_id, "Conversation unarchived");
 
     // Redirect to the conversation
     Ok(HttpResponse::SeeOther()
@@ -671,6 +783,49 @@ pub async fn unarchive_conversation_handler(
         .finish())
 }
 
+/// POST /conversations/{id}/mute - Stop notifications for a conversation
+#[post("/conversations/{id}/mute")]
+pub async fn mute_conversation_handler(
+    client: ClientCtx,
+    session: actix_session::Session,
+    conversation_id: web::Path<i32>,
+    form: web::Form<ArchiveConversationForm>,
+) -> Result<impl Responder, Error> {
+    set_muted(client, session, *conversation_id, &form.csrf_token, true).await
+}
+
+/// POST /conversations/{id}/unmute - Resume notifications for a conversation
+#[post("/conversations/{id}/unmute")]
+pub async fn unmute_conversation_handler(
+    client: ClientCtx,
+    session: actix_session::Session,
+    conversation_id: web::Path<i32>,
+    form: web::Form<ArchiveConversationForm>,
+) -> Result<impl Responder, Error> {
+    set_muted(client, session, *conversation_id, &form.csrf_token, false).await
+}
+
+async fn set_muted(
+    client: ClientCtx,
+    session: actix_session::Session,
+    conv_id: i32,
+    csrf_token: &str,
+    muted: bool,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&session, csrf_token)?;
+
+    conversations::set_conversation_muted(user_id, conv_id, muted)
+        .await
+        .map_err(|e| conversation_error("change conversation mute", e))?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/conversations/{}", conv_id)))
+        .finish())
+}
+
 /// Form data for kicking a participant
 #[derive(Deserialize)]
 pub struct KickParticipantForm {
@@ -695,16 +850,49 @@ pub async fn kick_participant_handler(
     // Kick the participant
     conversations::kick_participant(user_id, conv_id, form.user_id)
         .await
-        .map_err(|e| {
-            log::error!("Failed to kick participant: {}", e);
-            error::ErrorForbidden(e.to_string())
-        })?;
+        .map_err(|e| conversation_error("kick participant", e))?;
+
+    tracing::info!(
+        conversation_id = conv_id,
+        kicked_user_id = form.user_id,
+        "Participant kicked"
+    );
+
+    // Redirect back to the conversation
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/conversations/{}", conv_id)))
+        .finish())
+}
+
+/// Form data for transferring ownership
+#[derive(Deserialize)]
+pub struct TransferOwnershipForm {
+    csrf_token: String,
+    user_id: i32,
+}
+
+/// POST /conversations/{id}/transfer - Make another participant the owner (creator only)
+#[post("/conversations/{id}/transfer")]
+pub async fn transfer_ownership_handler(
+    client: ClientCtx,
+    session: actix_session::Session,
+    conversation_id: web::Path<i32>,
+    form: web::Form<TransferOwnershipForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    let conv_id = *conversation_id;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+
+    conversations::transfer_ownership(user_id, conv_id, form.user_id)
+        .await
+        .map_err(|e| conversation_error("transfer conversation ownership", e))?;
 
-    log::info!(
-        "User {} kicked user {} from conversation {}",
-        user_id,
-        form.user_id,
-        conv_id
+    tracing::info!(
+        conversation_id = conv_id,
+        new_owner_id = form.user_id,
+        "Conversation ownership transferred"
     );
 
     // Redirect back to the conversation
@@ -749,16 +937,12 @@ pub async fn invite_participant_handler(
     // Invite the participant
     conversations::invite_participant(user_id, conv_id, target_user.user_id)
         .await
-        .map_err(|e| {
-            log::error!("Failed to invite participant: {}", e);
-            error::ErrorForbidden(e.to_string())
-        })?;
+        .map_err(|e| conversation_error("invite participant", e))?;
 
-    log::info!(
-        "User {} invited user {} to conversation {}",
-        user_id,
-        target_user.user_id,
-        conv_id
+    tracing::info!(
+        conversation_id = conv_id,
+        invited_user_id = target_user.user_id,
+        "Participant invited"
     );
 
     // Send notification to the invited user
@@ -787,3 +971,38 @@ pub async fn invite_participant_handler(
         .append_header(("Location", format!("/conversations/{}", conv_id)))
         .finish())
 }
+
+/// Form data for renaming a conversation
+#[derive(Deserialize)]
+pub struct RenameConversationForm {
+    csrf_token: String,
+    title: String,
+}
+
+/// POST /conversations/{id}/rename - Change the conversation title (owner only)
+#[post("/conversations/{id}/rename")]
+pub async fn rename_conversation_handler(
+    client: ClientCtx,
+    session: actix_session::Session,
+    conversation_id: web::Path<i32>,
+    form: web::Form<RenameConversationForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    let conv_id = *conversation_id;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+
+    let title = filter_for_saving(&form.title, FilterScope::ThreadTitles)
+        .map_err(error::ErrorBadRequest)?;
+    conversations::rename_conversation(user_id, conv_id, Some(title.as_str()))
+        .await
+        .map_err(|e| conversation_error("rename conversation", e))?;
+
+    tracing::info!(conversation_id = conv_id, "Conversation renamed");
+
+    // Redirect back to the conversation
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", format!("/conversations/{}", conv_id)))
+        .finish())
+}
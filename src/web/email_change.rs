@@ -0,0 +1,281 @@
+//! Changing the email address on an account.
+//!
+//! See `crate::email::change` for how a change is confirmed and undone. The
+//! links sent to either address work without logging in; the one sent to
+//! the old address opens a confirmation page first, so mail scanners
+//! following links don't cancel anything.
+
+use crate::db::get_db_pool;
+use crate::email::change::{self, ChangeError};
+use crate::email::templates::{
+    send_email_change_notice_email, send_email_change_verification_email,
+};
+use crate::middleware::ClientCtx;
+use crate::orm::{user_names, users};
+use crate::session::get_argon2;
+use actix_web::{error, get, post, web, Error, HttpRequest, HttpResponse, Responder};
+use argon2::password_hash::{PasswordHash, PasswordVerifier};
+use askama_actix::{Template, TemplateToResponse};
+use sea_orm::{entity::*, query::*};
+use serde::Deserialize;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(request_change)
+        .service(cancel_change)
+        .service(confirm_change)
+        .service(revert_form)
+        .service(revert_change);
+}
+
+#[derive(Template)]
+#[template(path = "email_change.html")]
+struct EmailChangeTemplate {
+    client: ClientCtx,
+    message: Option<String>,
+    error: Option<String>,
+    /// Set when asking whether to cancel or undo a change
+    revert_token: Option<String>,
+    new_email: String,
+}
+
+impl EmailChangeTemplate {
+    fn message(client: ClientCtx, message: &str) -> Self {
+        EmailChangeTemplate {
+            client,
+            message: Some(message.to_string()),
+            error: None,
+            revert_token: None,
+            new_email: String::new(),
+        }
+    }
+
+    fn error(client: ClientCtx, error: &str) -> Self {
+        EmailChangeTemplate {
+            client,
+            message: None,
+            error: Some(error.to_string()),
+            revert_token: None,
+            new_email: String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ChangeEmailForm {
+    csrf_token: String,
+    new_email: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct CancelForm {
+    csrf_token: String,
+}
+
+async fn username(user_id: i32) -> Result<String, Error> {
+    let name = user_names::Entity::find()
+        .filter(user_names::Column::UserId.eq(user_id))
+        .one(get_db_pool())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to find username: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+    Ok(name.map(|n| n.name).unwrap_or_default())
+}
+
+/// POST /account/email - Ask to change email address
+#[post("/account/email")]
+async fn request_change(
+    req: HttpRequest,
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<ChangeEmailForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let ip = crate::ip::extract_client_ip(&req)
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    if let Err(e) = crate::rate_limit::check_email_verification_rate_limit(&ip).await {
+        log::warn!("Email change rate limit exceeded for IP: {}", ip);
+        return Err(error::ErrorTooManyRequests(format!(
+            "Too many email change requests. Please try again in {} seconds.",
+            e.retry_after_seconds
+        )));
+    }
+
+    let user = users::Entity::find_by_id(user_id)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch user: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("User not found"))?;
+
+    // Someone at an unattended session can't take over the account this way
+    let parsed_hash = PasswordHash::new(&user.password)
+        .map_err(|_| error::ErrorInternalServerError("Invalid password hash"))?;
+    if get_argon2()
+        .verify_password(form.password.as_bytes(), &parsed_hash)
+        .is_err()
+    {
+        return Err(error::ErrorBadRequest("Incorrect password"));
+    }
+
+    let pending = change::request(&user, &form.new_email)
+        .await
+        .map_err(|e| match e {
+            ChangeError::Database(e) => {
+                log::error!("Failed to save email change: {}", e);
+                error::ErrorInternalServerError("Database error")
+            }
+            e => error::ErrorBadRequest(e.to_string()),
+        })?;
+
+    let username = username(user_id).await?;
+    let base_url = crate::notifications::dispatcher::get_base_url();
+    send_email_change_verification_email(
+        &pending.new_email,
+        &username,
+        &pending.confirm_token,
+        &base_url,
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to send email change verification: {}", e);
+        error::ErrorInternalServerError("Failed to send verification email")
+    })?;
+
+    if let Some(old_email) = &pending.old_email {
+        if let Err(e) = send_email_change_notice_email(
+            old_email,
+            &username,
+            &pending.new_email,
+            &pending.revert_token,
+            &base_url,
+        )
+        .await
+        {
+            log::error!("Failed to send email change notice: {}", e);
+        }
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/account"))
+        .finish())
+}
+
+/// POST /account/email/cancel - Cancel an unconfirmed change
+#[post("/account/email/cancel")]
+async fn cancel_change(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<CancelForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    change::cancel_pending(user_id).await.map_err(|e| {
+        log::error!("Failed to cancel email change: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/account"))
+        .finish())
+}
+
+/// GET /account/email/confirm/{token} - Confirm the new address
+#[get("/account/email/confirm/{token}")]
+async fn confirm_change(
+    client: ClientCtx,
+    token: web::Path<String>,
+) -> Result<impl Responder, Error> {
+    let template = match change::confirm(&token.into_inner()).await {
+        Ok(Some(_)) => EmailChangeTemplate::message(client, "Your email address has been changed."),
+        Ok(None) => {
+            EmailChangeTemplate::error(client, "This confirmation link is invalid or has expired.")
+        }
+        Err(ChangeError::Database(e)) => {
+            log::error!("Failed to confirm email change: {}", e);
+            return Err(error::ErrorInternalServerError("Database error"));
+        }
+        Err(e) => EmailChangeTemplate::error(client, &e.to_string()),
+    };
+
+    Ok(template.to_response())
+}
+
+/// GET /account/email/revert/{token} - Ask whether to cancel or undo a change
+#[get("/account/email/revert/{token}")]
+async fn revert_form(client: ClientCtx, token: web::Path<String>) -> Result<impl Responder, Error> {
+    let token = token.into_inner();
+    let pending = change::find_revertible(&token).await.map_err(|e| {
+        log::error!("Failed to find email change: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    let template = match pending {
+        Some(pending) => EmailChangeTemplate {
+            client,
+            message: None,
+            error: None,
+            revert_token: Some(token),
+            new_email: pending.new_email,
+        },
+        None => EmailChangeTemplate::error(client, "This link is invalid or has expired."),
+    };
+
+    Ok(template.to_response())
+}
+
+/// POST /account/email/revert/{token} - Cancel or undo a change and sign the
+/// account out everywhere. No login or CSRF token is needed; the token is
+/// the authorisation.
+#[post("/account/email/revert/{token}")]
+async fn revert_change(
+    client: ClientCtx,
+    token: web::Path<String>,
+) -> Result<impl Responder, Error> {
+    let pending = change::find_revertible(&token.into_inner())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to find email change: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+    let Some(pending) = pending else {
+        return Ok(
+            EmailChangeTemplate::error(client, "This link is invalid or has expired.")
+                .to_response(),
+        );
+    };
+
+    let user_id = pending.user_id;
+    let reverted = match change::revert(pending).await {
+        Ok(reverted) => reverted,
+        Err(ChangeError::Database(e)) => {
+            log::error!("Failed to revert email change: {}", e);
+            return Err(error::ErrorInternalServerError("Database error"));
+        }
+        Err(e) => return Ok(EmailChangeTemplate::error(client, &e.to_string()).to_response()),
+    };
+
+    // Whoever asked for the change may still be signed in
+    let sessions = crate::session::get_sess();
+    if let Err(e) = crate::session::invalidate_user_sessions(sessions, user_id).await {
+        log::error!(
+            "Failed to invalidate sessions after email change revert: {}",
+            e
+        );
+    }
+
+    let message = if reverted.confirmed_at.is_some() {
+        "Your old email address has been restored and the account has been signed out everywhere. Please reset your password."
+    } else {
+        "The email change has been cancelled and the account has been signed out everywhere. Please reset your password."
+    };
+    Ok(EmailChangeTemplate::message(client, message).to_response())
+}
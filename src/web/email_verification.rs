@@ -146,7 +146,7 @@ pub async fn resend_verification(
         .unwrap_or_else(|| "unknown".to_string());
 
     // Rate limiting - prevent abuse
-    if let Err(e) = crate::rate_limit::check_email_verification_rate_limit(&ip) {
+    if let Err(e) = crate::rate_limit::check_email_verification_rate_limit(&ip).await {
         log::warn!("Email verification rate limit exceeded for IP: {}", ip);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many verification requests. Please try again in {} seconds.",
@@ -0,0 +1,537 @@
+//! Admin pages for the log of outgoing email, for announcements emailed to
+//! many members, and for members' changes of email address.
+
+use crate::db::get_db_pool;
+use crate::email::change;
+use crate::email::mass::{self, Audience, DeliveryCounts};
+use crate::middleware::ClientCtx;
+use crate::orm::{email_changes, email_log, email_log_attempts, groups, mass_emails};
+use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
+use askama_actix::{Template, TemplateToResponse};
+use sea_orm::{entity::*, query::*};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Emails per page of the log
+const EMAILS_PAGE_SIZE: u64 = 50;
+
+/// Announcements listed on the progress page
+const ANNOUNCEMENTS_SHOWN: u64 = 50;
+
+/// Email address changes listed
+const CHANGES_SHOWN: u64 = 100;
+
+/// Longest announcement subject
+const MAX_SUBJECT_LENGTH: usize = 255;
+
+/// Longest announcement body, in characters
+const MAX_BODY_LENGTH: usize = 50_000;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(view_emails)
+        .service(compose_form)
+        .service(compose_submit)
+        .service(view_announcements)
+        .service(cancel_announcement)
+        .service(view_changes);
+}
+
+/// Filters for the email log. Empty or unrecognised fields are ignored.
+#[derive(Debug, Default, Deserialize)]
+pub struct EmailFilter {
+    /// Part of the recipient's address
+    pub recipient: Option<String>,
+    /// `pending`, `sent`, `retrying` or `failed`
+    pub status: Option<String>,
+    pub page: Option<u64>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+impl EmailFilter {
+    pub fn recipient_value(&self) -> &str {
+        non_empty(&self.recipient).unwrap_or_default()
+    }
+
+    pub fn status_value(&self) -> &str {
+        non_empty(&self.status)
+            .filter(|status| matches!(*status, "pending" | "sent" | "retrying" | "failed"))
+            .unwrap_or_default()
+    }
+
+    pub fn page(&self) -> u64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    /// Query string repeating these filters, for pagination links.
+    pub fn query_string(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in [
+            ("recipient", self.recipient_value()),
+            ("status", self.status_value()),
+        ] {
+            if !value.is_empty() {
+                query.append_pair(name, value);
+            }
+        }
+        query.finish()
+    }
+
+    fn condition(&self) -> Condition {
+        let mut condition = Condition::all();
+        if !self.recipient_value().is_empty() {
+            condition =
+                condition.add(email_log::Column::Recipient.contains(self.recipient_value()));
+        }
+        if !self.status_value().is_empty() {
+            condition = condition.add(email_log::Column::Status.eq(self.status_value()));
+        }
+        condition
+    }
+}
+
+/// A row of the email log with its attempts, oldest first.
+pub struct EmailView {
+    pub email: email_log::Model,
+    pub attempts: Vec<email_log_attempts::Model>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/emails.html")]
+struct EmailsTemplate {
+    client: ClientCtx,
+    emails: Vec<EmailView>,
+    filter: EmailFilter,
+    filter_query: String,
+    page: u64,
+    total_pages: u64,
+    total: u64,
+}
+
+/// GET /admin/emails - The email log
+#[get("/admin/emails")]
+async fn view_emails(
+    client: ClientCtx,
+    filter: web::Query<EmailFilter>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+    let filter = filter.into_inner();
+
+    let select = email_log::Entity::find().filter(filter.condition());
+    let total = select
+        .clone()
+        .count(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let page = filter.page();
+    let emails = select
+        .order_by_desc(email_log::Column::Id)
+        .limit(EMAILS_PAGE_SIZE)
+        .offset((page - 1) * EMAILS_PAGE_SIZE)
+        .all(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut attempts: HashMap<i32, Vec<email_log_attempts::Model>> = HashMap::new();
+    if !emails.is_empty() {
+        for attempt in email_log_attempts::Entity::find()
+            .filter(email_log_attempts::Column::EmailId.is_in(emails.iter().map(|e| e.id)))
+            .order_by_asc(email_log_attempts::Column::Id)
+            .all(db)
+            .await
+            .map_err(error::ErrorInternalServerError)?
+        {
+            attempts.entry(attempt.email_id).or_default().push(attempt);
+        }
+    }
+
+    let emails = emails
+        .into_iter()
+        .map(|email| EmailView {
+            attempts: attempts.remove(&email.id).unwrap_or_default(),
+            email,
+        })
+        .collect();
+
+    Ok(EmailsTemplate {
+        client,
+        emails,
+        filter_query: filter.query_string(),
+        filter,
+        page,
+        total_pages: total.div_ceil(EMAILS_PAGE_SIZE),
+        total,
+    }
+    .to_response())
+}
+
+/// Form for composing an announcement. Empty audience fields mean no limit.
+#[derive(Debug, Default, Deserialize)]
+pub struct ComposeForm {
+    #[serde(default)]
+    csrf_token: String,
+    #[serde(default)]
+    pub subject: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub group_id: String,
+    #[serde(default)]
+    pub active_within_days: String,
+    pub opted_in_only: Option<String>,
+    /// `preview` to count the audience and show the body, `send` to send
+    #[serde(default)]
+    action: String,
+}
+
+impl ComposeForm {
+    /// The audience the form describes, or why it doesn't describe one.
+    pub fn audience(&self) -> Result<Audience, &'static str> {
+        let group_id = match self.group_id.trim() {
+            "" => None,
+            id => Some(id.parse().map_err(|_| "Unknown group")?),
+        };
+        let active_within_days = match self.active_within_days.trim() {
+            "" => None,
+            days => match days.parse::<i32>() {
+                Ok(days) if days > 0 => Some(days),
+                _ => return Err("Activity must be a number of days"),
+            },
+        };
+        Ok(Audience {
+            group_id,
+            active_within_days,
+            opted_in_only: self.opted_in_only.is_some(),
+        })
+    }
+
+    /// Checks the subject and body.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let subject = self.subject.trim();
+        if subject.is_empty() {
+            return Err("A subject is required");
+        }
+        if subject.chars().count() > MAX_SUBJECT_LENGTH {
+            return Err("The subject is too long");
+        }
+        if self.body.trim().is_empty() {
+            return Err("A message is required");
+        }
+        if self.body.chars().count() > MAX_BODY_LENGTH {
+            return Err("The message is too long");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/email_compose.html")]
+struct ComposeTemplate {
+    client: ClientCtx,
+    form: ComposeForm,
+    groups: Vec<groups::Model>,
+    error: Option<String>,
+    /// Members the audience matches, after a preview
+    audience_count: Option<u64>,
+    /// The body rendered as it will appear, after a preview
+    preview_html: Option<String>,
+}
+
+async fn load_groups() -> Result<Vec<groups::Model>, Error> {
+    groups::Entity::find()
+        .order_by_asc(groups::Column::Label)
+        .all(get_db_pool())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to load groups: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })
+}
+
+/// GET /admin/emails/compose - Write an announcement
+#[get("/admin/emails/compose")]
+async fn compose_form(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    Ok(ComposeTemplate {
+        client,
+        form: ComposeForm {
+            opted_in_only: Some("on".to_owned()),
+            ..Default::default()
+        },
+        groups: load_groups().await?,
+        error: None,
+        audience_count: None,
+        preview_html: None,
+    }
+    .to_response())
+}
+
+/// POST /admin/emails/compose - Preview or send an announcement
+#[post("/admin/emails/compose")]
+async fn compose_submit(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<ComposeForm>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let form = form.into_inner();
+    let checked = form.validate().and_then(|_| form.audience());
+    let audience = match checked {
+        Ok(audience) => audience,
+        Err(message) => {
+            return Ok(ComposeTemplate {
+                client,
+                form,
+                groups: load_groups().await?,
+                error: Some(message.to_owned()),
+                audience_count: None,
+                preview_html: None,
+            }
+            .to_response());
+        }
+    };
+
+    if form.action != "send" {
+        let audience_count = audience.count().await.map_err(|e| {
+            log::error!("Failed to count announcement audience: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+        return Ok(ComposeTemplate {
+            client,
+            preview_html: Some(crate::bbcode::parse(&form.body)),
+            form,
+            groups: load_groups().await?,
+            error: None,
+            audience_count: Some(audience_count),
+        }
+        .to_response());
+    }
+
+    mass::create(form.subject.trim(), &form.body, &audience, user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to start announcement: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/emails/announcements"))
+        .finish())
+}
+
+/// An announcement with how its emails are getting on.
+pub struct AnnouncementView {
+    pub mass: mass_emails::Model,
+    pub group: Option<String>,
+    pub counts: DeliveryCounts,
+}
+
+impl AnnouncementView {
+    /// Share of the audience queued so far, as a whole percentage
+    pub fn percent_queued(&self) -> i64 {
+        percent(
+            self.mass.queued_count.into(),
+            self.mass.total_recipients.into(),
+        )
+    }
+}
+
+fn percent(part: i64, total: i64) -> i64 {
+    if total <= 0 {
+        return 100;
+    }
+    (part * 100 / total).clamp(0, 100)
+}
+
+#[derive(Template)]
+#[template(path = "admin/email_announcements.html")]
+struct AnnouncementsTemplate {
+    client: ClientCtx,
+    announcements: Vec<AnnouncementView>,
+}
+
+/// GET /admin/emails/announcements - Progress of recent announcements
+#[get("/admin/emails/announcements")]
+async fn view_announcements(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+    let announcements = mass_emails::Entity::find()
+        .order_by_desc(mass_emails::Column::Id)
+        .limit(ANNOUNCEMENTS_SHOWN)
+        .all(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let ids: Vec<i32> = announcements.iter().map(|m| m.id).collect();
+    let mut counts = mass::delivery_counts(&ids)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let group_names: HashMap<i32, String> = groups::Entity::find()
+        .filter(groups::Column::Id.is_in(announcements.iter().filter_map(|m| m.group_id)))
+        .all(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|group| (group.id, group.label))
+        .collect();
+
+    let announcements = announcements
+        .into_iter()
+        .map(|mass| AnnouncementView {
+            group: mass.group_id.map(|id| {
+                group_names
+                    .get(&id)
+                    .cloned()
+                    .unwrap_or_else(|| format!("Group #{} (deleted)", id))
+            }),
+            counts: counts.remove(&mass.id).unwrap_or_default(),
+            mass,
+        })
+        .collect();
+
+    Ok(AnnouncementsTemplate {
+        client,
+        announcements,
+    }
+    .to_response())
+}
+
+#[derive(Deserialize)]
+struct CancelForm {
+    csrf_token: String,
+}
+
+/// POST /admin/emails/announcements/{id}/cancel - Stop queueing an announcement
+#[post("/admin/emails/announcements/{id}/cancel")]
+async fn cancel_announcement(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    id: web::Path<i32>,
+    form: web::Form<CancelForm>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let id = id.into_inner();
+    if mass::cancel(id).await.map_err(|e| {
+        log::error!("Failed to cancel announcement {}: {}", id, e);
+        error::ErrorInternalServerError("Database error")
+    })? {
+        log::info!("Announcement {} cancelled", id);
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/emails/announcements"))
+        .finish())
+}
+
+/// A change of email address as listed for admins
+struct EmailChangeView {
+    change: email_changes::Model,
+    username: String,
+    /// Never confirmed, and the link has stopped working
+    expired: bool,
+}
+
+#[derive(Template)]
+#[template(path = "admin/email_changes.html")]
+struct EmailChangesTemplate {
+    client: ClientCtx,
+    changes: Vec<EmailChangeView>,
+}
+
+/// GET /admin/emails/changes - Pending and recent changes of email address
+#[get("/admin/emails/changes")]
+async fn view_changes(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let now = chrono::Utc::now().naive_utc();
+    let changes = change::recent(CHANGES_SHOWN)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|(change, username)| EmailChangeView {
+            expired: change.status == change::STATUS_PENDING && change.expires_at <= now,
+            change,
+            username,
+        })
+        .collect();
+
+    Ok(EmailChangesTemplate { client, changes }.to_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_email_filter() {
+        let filter = EmailFilter {
+            recipient: Some(" alice@example.com ".to_owned()),
+            status: Some("failed".to_owned()),
+            page: Some(0),
+        };
+        assert_eq!(filter.recipient_value(), "alice@example.com");
+        assert_eq!(filter.page(), 1);
+        assert_eq!(
+            filter.query_string(),
+            "recipient=alice%40example.com&status=failed"
+        );
+
+        let filter = EmailFilter {
+            recipient: Some("  ".to_owned()),
+            status: Some("delivered".to_owned()),
+            page: None,
+        };
+        assert_eq!(filter.status_value(), "");
+        assert_eq!(filter.query_string(), "");
+    }
+
+    #[test]
+    fn test_compose_form() {
+        let form = ComposeForm {
+            subject: " News ".to_owned(),
+            body: "[b]Hello[/b]".to_owned(),
+            group_id: "3".to_owned(),
+            active_within_days: " 30 ".to_owned(),
+            opted_in_only: Some("on".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(form.validate(), Ok(()));
+        assert_eq!(
+            form.audience(),
+            Ok(Audience {
+                group_id: Some(3),
+                active_within_days: Some(30),
+                opted_in_only: true,
+            })
+        );
+
+        let form = ComposeForm {
+            subject: "  ".to_owned(),
+            active_within_days: "0".to_owned(),
+            ..Default::default()
+        };
+        assert!(form.validate().is_err());
+        assert!(form.audience().is_err());
+        assert_eq!(ComposeForm::default().audience(), Ok(Audience::default()));
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(0, 200), 0);
+        assert_eq!(percent(50, 200), 25);
+        // Members who join mid-send can push the count past the estimate
+        assert_eq!(percent(210, 200), 100);
+        assert_eq!(percent(0, 0), 100);
+    }
+}
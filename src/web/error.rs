@@ -12,14 +12,27 @@ struct ErrorTemplate<'a> {
     client: ClientCtx,
     status: StatusCode,
     error: Option<&'a Error>,
+    /// Request ID, for users to quote when reporting the problem
+    reference: Option<String>,
 }
 
 pub fn error_document<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> {
+    let reference = crate::middleware::request_id::of_request(res.request());
+    if res.status().is_server_error() {
+        tracing::error!(
+            status = res.status().as_u16(),
+            reference = reference.as_deref().unwrap_or_default(),
+            error = %res.response().error().map(ToString::to_string).unwrap_or_default(),
+            "Served error page"
+        );
+    }
+
     let body = BoxBody::new(
         ErrorTemplate {
             client: ClientCtx::default(),
             status: res.status(),
             error: res.response().error(),
+            reference,
         }
         .to_string(),
     );
@@ -1,4 +1,7 @@
 use super::thread::{validate_thread_form, NewThreadFormData, ThreadForTemplate};
+use crate::app_state::AppState;
+use crate::cache::fragment::{self, Fragment};
+use crate::cache::CacheScope;
 use crate::config::Config;
 use crate::db::get_db_pool;
 use crate::middleware::ClientCtx;
@@ -6,12 +9,18 @@ use crate::orm::{
     forum_read, forums, poll_options, polls, posts, tag_forums, tags, thread_tags, threads,
     user_names, users,
 };
+use crate::presence::Presence;
+use crate::word_filter::FilterScope;
 use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
 use askama_actix::{Template, TemplateToResponse};
 use sea_orm::{entity::*, query::*, sea_query::Expr, DatabaseConnection, FromQueryResult};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::sync::Arc;
+use tracing::Instrument;
+
+/// Posts in the latest posts list on the forum index
+const LATEST_POSTS_LIMIT: usize = 5;
 
 /// Helper struct for pending post query
 #[derive(Debug, FromQueryResult)]
@@ -89,6 +98,10 @@ pub struct ForumTemplate<'a> {
     pub moderators: Vec<ModeratorForTemplate>,
     pub sub_forums: Vec<ForumWithStats>,
     pub available_tags: Vec<super::thread::TagForTemplate>,
+    pub is_watching: bool,
+    pub email_on_thread: bool,
+    /// Notices posted in this forum
+    pub notices: Vec<crate::notices::ActiveNotice>,
 }
 
 #[derive(Template)]
@@ -99,6 +112,15 @@ pub struct NewThreadFormTemplate<'a> {
     pub breadcrumbs: Vec<super::thread::Breadcrumb>,
     pub available_tags: Vec<super::thread::TagForTemplate>,
     pub error: Option<String>,
+    /// Title to start with, such as one carried over from a search
+    pub title: String,
+    /// Existing threads with titles like `title`
+    pub similar_threads: Vec<super::search::ThreadSuggestion>,
+}
+
+#[derive(Deserialize)]
+pub struct NewThreadQuery {
+    pub title: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -113,7 +135,7 @@ pub struct ModeratorForTemplate {
     pub username: String,
 }
 
-/// Fetch moderators for a forum
+/// Fetch moderators for a forum, including those of its parent forums
 pub async fn get_forum_moderators(
     forum_id: i32,
 ) -> Result<Vec<ModeratorForTemplate>, sea_orm::DbErr> {
@@ -121,13 +143,18 @@ pub async fn get_forum_moderators(
 
     let db = get_db_pool();
 
-    // Use raw SQL to join forum_moderators with user_names
+    // Moderators of parent forums moderate this one too
     let sql = r#"
-        SELECT fm.user_id, un.name as username
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id FROM forums WHERE id = $1
+            UNION ALL
+            SELECT f.id, f.parent_id FROM forums f JOIN ancestors a ON f.id = a.parent_id
+        )
+        SELECT DISTINCT fm.user_id, un.name as username
         FROM forum_moderators fm
+        JOIN ancestors a ON a.id = fm.forum_id
         LEFT JOIN user_names un ON un.user_id = fm.user_id
-        WHERE fm.forum_id = $1
-        ORDER BY un.name
+        ORDER BY username
     "#;
 
     let moderators = ModeratorQueryResult::find_by_statement(Statement::from_sql_and_values(
@@ -292,7 +319,7 @@ pub async fn get_sub_forums(parent_forum_id: i32) -> Result<Vec<ForumWithStats>,
     .await
 }
 
-#[derive(Debug, Clone, FromQueryResult)]
+#[derive(Debug, Clone, FromQueryResult, Serialize, Deserialize)]
 pub struct ForumWithStats {
     pub id: i32,
     pub label: String,
@@ -328,13 +355,36 @@ pub struct ForumWithChildren {
 
 #[derive(Template)]
 #[template(path = "forums.html")]
-pub struct ForumIndexTemplate<'a> {
+pub struct ForumIndexTemplate {
     pub client: ClientCtx,
-    pub forums: &'a Vec<ForumWithChildren>,
-    pub unread_forums: HashSet<i32>,
-    pub online_users: Vec<crate::user::OnlineUser>,
-    pub online_count: i64,
-    pub online_users_len: i64,
+    /// Rendered `fragments/forum_list.html`
+    pub forum_list: String,
+    /// Rendered `fragments/online_users.html`
+    pub online_users: String,
+    /// Rendered `fragments/latest_posts.html`
+    pub latest_posts: String,
+}
+
+#[derive(Template)]
+#[template(path = "fragments/forum_list.html")]
+struct ForumListFragment<'a> {
+    forums: &'a Vec<ForumWithChildren>,
+    unread_forums: &'a HashSet<i32>,
+}
+
+#[derive(Template)]
+#[template(path = "fragments/online_users.html")]
+struct OnlineUsersFragment {
+    online_users: Vec<crate::user::OnlineUser>,
+    online_count: i64,
+    online_users_len: i64,
+    online_guests: usize,
+}
+
+#[derive(Template)]
+#[template(path = "fragments/latest_posts.html")]
+struct LatestPostsFragment {
+    posts: Vec<super::recent::LatestPost>,
 }
 
 #[post("/forums/{forum}/post-thread")]
@@ -362,11 +412,9 @@ pub async fn create_thread(
     };
 
     // Rate limiting - prevent thread spam
-    if let Err(e) = crate::rate_limit::check_thread_rate_limit(user_id) {
-        log::warn!(
-            "Rate limit exceeded for thread creation: user_id={}",
-            user_id
-        );
+    if let Err(e) = crate::rate_limit::check_thread_rate_limit(user_id, &client.get_groups()).await
+    {
+        tracing::warn!("Rate limit exceeded for thread creation");
         return Err(error::ErrorTooManyRequests(format!(
             "You're creating threads too quickly. Please wait {} seconds.",
             e.retry_after_seconds
@@ -418,11 +466,10 @@ pub async fn create_thread(
     let content_spam = crate::spam::analyze_content(&form.content, user_post_count);
 
     if title_spam.is_spam || content_spam.is_spam {
-        log::warn!(
-            "Spam detected in thread: user_id={}, title_score={:.2}, content_score={:.2}",
-            user_id,
-            title_spam.score,
-            content_spam.score
+        tracing::warn!(
+            title_score = title_spam.score,
+            content_score = content_spam.score,
+            "Spam detected in thread"
         );
         return Err(error::ErrorBadRequest(
             "Your thread has been flagged as potential spam. Please revise your content.",
@@ -430,12 +477,11 @@ pub async fn create_thread(
     }
 
     // Word filter: check title and content
-    let title_filter = crate::word_filter::apply_filters(&form.title);
+    let title_filter = crate::word_filter::apply_filters(&form.title, FilterScope::ThreadTitles);
     if title_filter.blocked {
-        log::warn!(
-            "Thread title blocked by word filter: user_id={}, patterns={:?}",
-            user_id,
-            title_filter.matched_patterns
+        tracing::warn!(
+            patterns = ?title_filter.matched_patterns,
+            "Thread title blocked by word filter"
         );
         return Err(error::ErrorBadRequest(
             title_filter
@@ -444,12 +490,11 @@ pub async fn create_thread(
         ));
     }
 
-    let content_filter = crate::word_filter::apply_filters(&form.content);
+    let content_filter = crate::word_filter::apply_filters(&form.content, FilterScope::Posts);
     if content_filter.blocked {
-        log::warn!(
-            "Thread content blocked by word filter: user_id={}, patterns={:?}",
-            user_id,
-            content_filter.matched_patterns
+        tracing::warn!(
+            patterns = ?content_filter.matched_patterns,
+            "Thread content blocked by word filter"
         );
         return Err(error::ErrorBadRequest(
             content_filter
@@ -554,7 +599,9 @@ pub async fn create_thread(
             question: Set(poll_data.question),
             max_choices: Set(poll_data.max_choices),
             allow_change_vote: Set(poll_data.allow_change_vote),
-            show_results_before_vote: Set(poll_data.show_results_before_vote),
+            poll_type: Set(poll_data.poll_type),
+            results_visibility: Set(poll_data.results_visibility),
+            public_votes: Set(poll_data.public_votes),
             closes_at: Set(poll_data.closes_at),
             created_at: Set(revision.created_at),
             ..Default::default()
@@ -654,10 +701,9 @@ pub async fn create_thread(
 
     // If post is pending approval, show a message instead of redirecting to thread
     if needs_approval {
-        log::info!(
-            "Thread {} by user {} is pending first post approval",
-            thread_res.last_insert_id,
-            user_id
+        tracing::info!(
+            thread_id = thread_res.last_insert_id,
+            "Thread is pending first post approval"
         );
         return Ok(HttpResponse::Ok().content_type("text/html").body(format!(
             r#"<!DOCTYPE html>
@@ -675,25 +721,59 @@ pub async fn create_thread(
     }
 
     // Check and award any automatic badges the user may have earned (async, non-blocking)
-    actix::spawn(async move {
-        crate::badges::check_and_award_automatic_badges(user_id).await;
-    });
+    actix::spawn(
+        async move {
+            crate::badges::check_and_award_automatic_badges(user_id).await;
+        }
+        .in_current_span(),
+    );
 
     // Record activity for the feed (async, non-blocking)
     let thread_id = thread_res.last_insert_id;
     let title_for_activity = filtered_title.clone();
-    actix::spawn(async move {
-        if let Err(e) = crate::activities::record_thread_created(
-            user_id,
-            thread_id,
-            forum_id,
-            &title_for_activity,
-        )
-        .await
-        {
-            log::warn!("Failed to record thread creation activity: {}", e);
+    actix::spawn(
+        async move {
+            if let Err(e) = crate::activities::record_thread_created(
+                user_id,
+                thread_id,
+                forum_id,
+                &title_for_activity,
+            )
+            .await
+            {
+                tracing::warn!(error = %e, "Failed to record thread creation activity");
+            }
         }
-    });
+        .in_current_span(),
+    );
+
+    // Notify users watching the forum (async, non-blocking)
+    let first_post_id = new_post.id;
+    actix::spawn(
+        async move {
+            if let Err(e) = crate::notifications::dispatcher::notify_forum_watchers(
+                thread_id,
+                first_post_id,
+                user_id,
+            )
+            .await
+            {
+                tracing::error!(error = %e, "Failed to send new thread notifications");
+            }
+        }
+        .in_current_span(),
+    );
+
+    crate::webhooks::thread_created(
+        thread_id,
+        forum_id,
+        user_id,
+        filtered_title.trim(),
+        first_post_id,
+    );
+    crate::activitypub::thread_created(&client, forum_id, thread_id);
+
+    crate::cache::fragment::posts_changed().await;
 
     Ok(HttpResponse::Found()
         .append_header((
@@ -708,6 +788,7 @@ pub async fn create_thread(
 pub async fn new_thread_form(
     client: ClientCtx,
     path: web::Path<i32>,
+    query: web::Query<NewThreadQuery>,
 ) -> Result<impl Responder, Error> {
     let forum_id = path.into_inner();
 
@@ -741,12 +822,22 @@ pub async fn new_thread_form(
         Vec::new()
     };
 
+    let title = query.into_inner().title.unwrap_or_default();
+    let similar_threads = super::search::find_similar_threads(&client, &title)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!(error = %e, "Failed to find similar threads");
+            Vec::new()
+        });
+
     Ok(NewThreadFormTemplate {
         client,
         forum: &forum,
         breadcrumbs,
         available_tags,
         error: None,
+        title,
+        similar_threads,
     }
     .to_response())
 }
@@ -884,6 +975,7 @@ pub async fn view_forum(
     let threads: Vec<ThreadForTemplate> = threads
         .into_iter()
         .filter(|t| !hidden_threads.contains(&t.id))
+        .filter(|t| client.can_view_thread(&t.id, &forum_id))
         .collect();
 
     // Build breadcrumbs (including parent forums)
@@ -915,6 +1007,21 @@ pub async fn view_forum(
         .await
         .unwrap_or_default();
 
+    // Check if user is watching this forum and email preference
+    let (is_watching, email_on_thread) = match client.get_id() {
+        Some(user_id) => {
+            match crate::notifications::get_forum_watch_status(user_id, forum_id).await {
+                Ok(Some(watch)) => (true, watch.email_on_thread),
+                _ => (false, false),
+            }
+        }
+        None => (false, false),
+    };
+
+    let notices = crate::notices::current(client.get_id(), Some(forum_id))
+        .await
+        .unwrap_or_default();
+
     Ok(ForumTemplate {
         client: client.to_owned(),
         forum: &forum,
@@ -924,16 +1031,25 @@ pub async fn view_forum(
         moderators,
         sub_forums,
         available_tags,
+        is_watching,
+        email_on_thread,
+        notices,
     }
     .to_response())
 }
 
 #[get("/forums")]
-pub async fn view_forums(client: ClientCtx) -> Result<impl Responder, Error> {
-    render_forum_list(client).await
+pub async fn view_forums(
+    client: ClientCtx,
+    state: web::Data<AppState>,
+) -> Result<impl Responder, Error> {
+    render_forum_list(client, &state.presence).await
 }
 
-pub async fn render_forum_list(client: ClientCtx) -> Result<impl Responder, Error> {
+pub async fn render_forum_list(
+    client: ClientCtx,
+    presence: &Presence,
+) -> Result<impl Responder, Error> {
     #[allow(unused_imports)]
     use sea_orm::sea_query::Alias;
     use sea_orm::{DbBackend, Statement};
@@ -988,13 +1104,32 @@ pub async fn render_forum_list(client: ClientCtx) -> Result<impl Responder, Erro
         ORDER BY f.display_order, f.id
     "#;
 
-    let all_forums = ForumWithStats::find_by_statement(Statement::from_string(
-        DbBackend::Postgres,
-        sql.to_string(),
-    ))
-    .all(db)
-    .await
-    .unwrap_or_default();
+    // The same for everyone, so kept for a short while in the cache
+    let all_forums: Vec<ForumWithStats> =
+        match crate::cache::get(CacheScope::ForumTree, "all").await {
+            Some(forums) => forums,
+            None => match ForumWithStats::find_by_statement(Statement::from_string(
+                DbBackend::Postgres,
+                sql.to_string(),
+            ))
+            .all(db)
+            .await
+            {
+                Ok(forums) => {
+                    crate::cache::set(CacheScope::ForumTree, "all", &forums).await;
+                    forums
+                }
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to load forum list");
+                    Vec::new()
+                }
+            },
+        };
+
+    let all_forums: Vec<ForumWithStats> = all_forums
+        .into_iter()
+        .filter(|forum| client.can_view_forum(&forum.id))
+        .collect();
 
     // Get unread forums for logged-in users
     let unread_forums = if let Some(user_id) = client.get_id() {
@@ -1005,22 +1140,57 @@ pub async fn render_forum_list(client: ClientCtx) -> Result<impl Responder, Erro
         HashSet::new()
     };
 
-    // Organize forums into hierarchical structure
-    // Top-level forums (parent_id = NULL) with their children
-    let forums = organize_forums_hierarchy(&all_forums);
+    let groups_key = fragment::groups_key(&client.get_groups());
+    let (all_forums, unread_forums) = (&all_forums, &unread_forums);
+    let render_list = || async move {
+        // Organize forums into hierarchical structure
+        // Top-level forums (parent_id = NULL) with their children
+        let forums = organize_forums_hierarchy(all_forums);
+        ForumListFragment {
+            forums: &forums,
+            unread_forums,
+        }
+        .render()
+    };
+    // Unread markers differ from member to member, so only the list without
+    // any is shared.
+    let forum_list = if unread_forums.is_empty() {
+        fragment::get_or_render(Fragment::ForumList, &groups_key, render_list).await
+    } else {
+        render_list().await
+    }
+    .map_err(error::ErrorInternalServerError)?;
 
-    // Get online users for display
-    let online_users = crate::user::get_online_users(20).await.unwrap_or_default();
-    let online_count = crate::user::count_online_users().await.unwrap_or(0);
+    let online_users = fragment::get_or_render(Fragment::OnlineUsers, "all", || async {
+        let online_users = crate::user::get_online_users(20).await.unwrap_or_default();
+        let online_count = crate::user::count_online_users().await.unwrap_or(0);
+        let online_users_len = online_users.len() as i64;
+        OnlineUsersFragment {
+            online_users,
+            online_count,
+            online_users_len,
+            online_guests: presence.guest_count(),
+        }
+        .render()
+    })
+    .await
+    .map_err(error::ErrorInternalServerError)?;
+
+    let viewer = &client;
+    let latest_posts = fragment::get_or_render(Fragment::LatestPosts, &groups_key, || async move {
+        let posts = super::recent::get_latest_posts(viewer, LATEST_POSTS_LIMIT)
+            .await
+            .unwrap_or_default();
+        LatestPostsFragment { posts }.render()
+    })
+    .await
+    .map_err(error::ErrorInternalServerError)?;
 
-    let online_users_len = online_users.len() as i64;
     Ok(ForumIndexTemplate {
-        client: client.to_owned(),
-        forums: &forums,
-        unread_forums,
+        client,
+        forum_list,
         online_users,
-        online_count,
-        online_users_len,
+        latest_posts,
     }
     .to_response())
 }
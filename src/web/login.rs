@@ -1,6 +1,6 @@
 use crate::db::get_db_pool;
 use crate::middleware::ClientCtx;
-use crate::orm::{user_2fa, user_bans, user_names, users};
+use crate::orm::{user_2fa, user_names, users};
 use crate::session;
 use crate::session::{authenticate_by_cookie, get_argon2, get_sess};
 use actix_web::{error, get, post, web, Error, Responder};
@@ -135,10 +135,6 @@ pub async fn login<S: AsRef<str>>(
     totp: &Option<S>,
 ) -> Result<LoginResult, DbErr> {
     use chrono::Utc;
-    use sea_orm::ActiveValue::Set;
-
-    const MAX_FAILED_ATTEMPTS: i32 = 5;
-    const LOCKOUT_DURATION_MINUTES: i64 = 15;
 
     // Trim whitespace from username for consistent lookups
     let name = name.trim();
@@ -170,17 +166,7 @@ pub async fn login<S: AsRef<str>>(
     };
 
     // Check if user is banned
-    let active_ban = user_bans::Entity::find()
-        .filter(user_bans::Column::UserId.eq(user_id))
-        .filter(
-            // Permanent ban OR not yet expired
-            user_bans::Column::IsPermanent
-                .eq(true)
-                .or(user_bans::Column::ExpiresAt.gt(Utc::now().naive_utc())),
-        )
-        .order_by_desc(user_bans::Column::CreatedAt)
-        .one(db)
-        .await?;
+    let active_ban = crate::user::get_active_ban(db, user_id).await?;
 
     if let Some(ban) = active_ban {
         return Ok(LoginResult::fail(LoginResultStatus::Banned(BanInfo {
@@ -196,10 +182,7 @@ pub async fn login<S: AsRef<str>>(
             return Ok(LoginResult::fail(LoginResultStatus::AccountLocked));
         } else {
             // Lock has expired, reset failed attempts
-            let mut active_user: users::ActiveModel = user.clone().into();
-            active_user.failed_login_attempts = Set(0);
-            active_user.locked_until = Set(None);
-            active_user.update(db).await?;
+            crate::login_throttle::clear_account(user.id).await?;
         }
     }
 
@@ -208,24 +191,8 @@ pub async fn login<S: AsRef<str>>(
         .verify_password(pass.as_bytes(), &parsed_hash)
         .is_err()
     {
-        // Increment failed login attempts
-        let mut active_user: users::ActiveModel = user.clone().into();
-        let new_attempts = user.failed_login_attempts + 1;
-        active_user.failed_login_attempts = Set(new_attempts);
-
-        // Lock account if max attempts reached
-        if new_attempts >= MAX_FAILED_ATTEMPTS {
-            let lock_until =
-                Utc::now().naive_utc() + chrono::Duration::minutes(LOCKOUT_DURATION_MINUTES);
-            active_user.locked_until = Set(Some(lock_until));
-            log::warn!(
-                "Account locked due to {} failed login attempts: user_id={}",
-                new_attempts,
-                user.id
-            );
-        }
-
-        active_user.update(db).await?;
+        // Count the failure, locking the account past the limit
+        crate::login_throttle::record_account_failure(user.id).await?;
         return Ok(LoginResult::fail(LoginResultStatus::BadPassword));
     }
 
@@ -250,12 +217,7 @@ pub async fn login<S: AsRef<str>>(
                 let verify = auth.verify_code(secret.secret.trim(), totp.as_ref(), 60, 0);
                 if verify {
                     // Reset failed login attempts on successful login
-                    if user.failed_login_attempts > 0 || user.locked_until.is_some() {
-                        let mut active_user: users::ActiveModel = user.clone().into();
-                        active_user.failed_login_attempts = Set(0);
-                        active_user.locked_until = Set(None);
-                        active_user.update(db).await?;
-                    }
+                    crate::login_throttle::clear_account(user.id).await?;
                     return Ok(LoginResult::success(user.id));
                 }
                 return Ok(LoginResult::fail(LoginResultStatus::Bad2FA));
@@ -270,16 +232,26 @@ pub async fn login<S: AsRef<str>>(
     }
 
     // Reset failed login attempts on successful login
-    if user.failed_login_attempts > 0 || user.locked_until.is_some() {
-        let mut active_user: users::ActiveModel = user.into();
-        active_user.failed_login_attempts = Set(0);
-        active_user.locked_until = Set(None);
-        active_user.update(db).await?;
-    }
+    crate::login_throttle::clear_account(user.id).await?;
 
     Ok(LoginResult::success(user_id))
 }
 
+/// Count a failed attempt from `ip`. A database error is only logged, so it
+/// doesn't change the answer to the attempt.
+async fn record_ip_failure(ip: &str) {
+    if let Err(e) = crate::login_throttle::record_ip_failure(ip).await {
+        log::error!("Failed to record failed login: {}", e);
+    }
+}
+
+/// Forget failed attempts from `ip` after a successful login.
+async fn clear_ip_failures(ip: &str) {
+    if let Err(e) = crate::login_throttle::clear_ip(ip).await {
+        log::error!("Failed to clear failed logins: {}", e);
+    }
+}
+
 /// Check if an IP address is banned
 ///
 /// Returns Some(BanInfo) if the IP is banned, None otherwise.
@@ -353,7 +325,7 @@ pub async fn post_login(
         .unwrap_or_else(|| "unknown".to_string());
 
     // Rate limiting - prevent brute force attacks
-    if let Err(e) = crate::rate_limit::check_login_rate_limit(&ip, username) {
+    if let Err(e) = crate::rate_limit::check_login_rate_limit(&ip, username).await {
         log::warn!(
             "Rate limit exceeded for login: ip={}, username={}",
             ip,
@@ -391,9 +363,29 @@ pub async fn post_login(
         return Err(error::ErrorForbidden(message));
     }
 
+    // Progressive delays and lockouts after failed attempts
+    let throttle = crate::login_throttle::check(&ip, username)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to check failed logins: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+    if let Some(until) = throttle.ip_locked_until {
+        log::warn!("Login attempt from locked out IP: {}", ip);
+        return Err(error::ErrorForbidden(format!(
+            "Too many failed login attempts from your address. Please try again after {}.",
+            until.format("%Y-%m-%d %H:%M UTC")
+        )));
+    }
+    if let Some(wait) = throttle.wait {
+        return Err(error::ErrorTooManyRequests(format!(
+            "Too many failed login attempts. Please wait {} seconds before trying again.",
+            wait.as_secs() + 1
+        )));
+    }
+
     // Check if CAPTCHA is required based on failed attempts
-    let failed_attempts = crate::rate_limit::get_failed_login_count(&ip);
-    if crate::captcha::should_require_for_login(failed_attempts) {
+    if crate::captcha::should_require_for_login(throttle.failures) {
         let captcha_response = form
             .hcaptcha_response
             .as_deref()
@@ -424,13 +416,13 @@ pub async fn post_login(
     let user_id = match user_id.result {
         LoginResultStatus::Success => {
             // Clear failed login attempts on success
-            crate::rate_limit::clear_failed_logins(&ip);
+            clear_ip_failures(&ip).await;
             user_id.user_id.unwrap()
         }
         LoginResultStatus::Missing2FA => {
             // Password was correct, clear failed attempts
             // (2FA failures are tracked separately)
-            crate::rate_limit::clear_failed_logins(&ip);
+            clear_ip_failures(&ip).await;
 
             // User has 2FA enabled but didn't provide TOTP code
             // Store pending auth state in session
@@ -451,9 +443,11 @@ pub async fn post_login(
             .to_response());
         }
         LoginResultStatus::AccountLocked => {
-            crate::rate_limit::record_failed_login(&ip);
+            record_ip_failure(&ip).await;
             log::warn!("Login attempt on locked account: {}", form.username);
-            return Err(error::ErrorForbidden("Account locked due to too many failed login attempts. Please try again in 15 minutes."));
+            return Err(error::ErrorForbidden(
+                "Account locked due to too many failed login attempts. Please try again later.",
+            ));
         }
         LoginResultStatus::Banned(ban_info) | LoginResultStatus::IpBanned(ban_info) => {
             log::warn!("Login attempt on banned account/IP: {}", form.username);
@@ -478,14 +472,14 @@ pub async fn post_login(
             return Err(error::ErrorForbidden("Please verify your email address before logging in. Check your email for a verification link, or <a href=\"/verify-email/resend\">request a new one</a>."));
         }
         LoginResultStatus::Bad2FA => {
-            crate::rate_limit::record_failed_login(&ip);
+            record_ip_failure(&ip).await;
             log::debug!("login failure: invalid 2FA code for {}", form.username);
             return Err(error::ErrorUnauthorized(
                 "Invalid two-factor authentication code.",
             ));
         }
         LoginResultStatus::BadName | LoginResultStatus::BadPassword => {
-            crate::rate_limit::record_failed_login(&ip);
+            record_ip_failure(&ip).await;
             log::debug!("login failure: {:?} for {}", user_id.result, form.username);
             // Use generic message to avoid username enumeration
             return Err(error::ErrorUnauthorized("Invalid username or password."));
@@ -536,7 +530,7 @@ pub async fn post_login_2fa(
         .map(|addr| addr.ip().to_string())
         .unwrap_or_else(|| "unknown".to_string());
 
-    if let Err(e) = crate::rate_limit::check_login_rate_limit(&ip, "2fa") {
+    if let Err(e) = crate::rate_limit::check_login_rate_limit(&ip, "2fa").await {
         log::warn!("Rate limit exceeded for 2FA: ip={}", ip);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many 2FA attempts. Please try again in {} seconds.",
@@ -581,6 +575,7 @@ pub async fn post_login_2fa(
     // Trim secret (DB uses CHAR which pads with spaces)
     if !auth.verify_code(secret.secret.trim(), &form.totp, 60, 0) {
         log::debug!("Invalid 2FA code for user {}", user_id);
+        record_ip_failure(&ip).await;
         return Ok(Login2FATemplate {
             client,
             error: Some("Invalid authentication code. Please try again."),
@@ -592,15 +587,8 @@ pub async fn post_login_2fa(
     cookies.remove("pending_2fa_user_id");
 
     // Reset any failed login attempts (user successfully authenticated)
-    use sea_orm::ActiveValue::Set;
-    use users::Entity as Users;
-    if let Ok(Some(user)) = Users::find_by_id(user_id).one(db).await {
-        if user.failed_login_attempts > 0 || user.locked_until.is_some() {
-            let mut active_user: users::ActiveModel = user.into();
-            active_user.failed_login_attempts = Set(0);
-            active_user.locked_until = Set(None);
-            let _ = active_user.update(db).await;
-        }
+    if let Err(e) = crate::login_throttle::clear_account(user_id).await {
+        log::error!("Failed to reset failed logins: {}", e);
     }
 
     // Retrieve remember_me preference from session (stored during initial login)
@@ -659,7 +647,12 @@ pub async fn view_login(
     let ip = crate::ip::extract_client_ip(&req)
         .map(|ip| ip.to_string())
         .unwrap_or_else(|| "unknown".to_string());
-    let failed_attempts = crate::rate_limit::get_failed_login_count(&ip);
+    let failed_attempts = crate::login_throttle::ip_failures(&ip)
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Failed to count failed logins: {}", e);
+            0
+        });
     let captcha_required = crate::captcha::should_require_for_login(failed_attempts);
 
     let mut tmpl = LoginTemplate {
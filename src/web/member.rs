@@ -1,17 +1,19 @@
 use crate::db::get_db_pool;
 use crate::middleware::ClientCtx;
 use crate::orm::{
-    attachments, posts, profile_posts, threads, ugc_revisions, user_follows, user_names,
-    user_social_links, users,
+    attachments, badges, groups, posts, profile_post_comments, profile_posts, threads,
+    ugc_revisions, user_follows, user_names, user_social_links, users,
 };
 use crate::ugc::{create_ugc, NewUgcPartial};
 use crate::user::Profile as UserProfile;
+use crate::word_filter::FilterScope;
 use actix_web::{error, get, post, web, Error, HttpRequest, HttpResponse, Responder};
 use askama_actix::{Template, TemplateToResponse};
 use chrono::{DateTime, Utc};
 use sea_orm::prelude::DateTimeWithTimeZone;
-use sea_orm::{entity::*, query::*, sea_query::Expr, DatabaseConnection, QueryOrder, Set};
+use sea_orm::{entity::*, query::*, DatabaseConnection, QueryOrder, Set};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
     conf.service(view_member)
@@ -20,6 +22,8 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         .service(search_usernames)
         .service(create_profile_post)
         .service(delete_profile_post)
+        .service(create_profile_post_comment)
+        .service(delete_profile_post_comment)
         .service(follow_user)
         .service(unfollow_user)
         .service(view_followers)
@@ -32,6 +36,39 @@ pub struct UserStatistics {
     pub post_count: i64,
     pub thread_count: i64,
     pub member_since: chrono::NaiveDateTime,
+    /// Reactions to their posts, by type
+    pub reactions_received: Vec<super::reactions::ReactionStat>,
+    /// Reactions they gave to posts, by type
+    pub reactions_given: Vec<super::reactions::ReactionStat>,
+}
+
+/// Who may post on a member's profile wall, with labels for the settings page
+pub const PROFILE_POST_PRIVACY: &[(&str, &str)] = &[
+    ("everyone", "Any member"),
+    ("following", "Members I follow"),
+    ("nobody", "Only me"),
+];
+
+/// Whether `author_id` may post or comment on the wall of a member with the
+/// given privacy setting. Members may always write on their own wall.
+pub async fn can_post_on_profile(
+    profile_user_id: i32,
+    profile_post_privacy: &str,
+    author_id: i32,
+) -> Result<bool, sea_orm::DbErr> {
+    if profile_user_id == author_id {
+        return Ok(true);
+    }
+    match profile_post_privacy {
+        "everyone" => Ok(true),
+        "following" => Ok(user_follows::Entity::find()
+            .filter(user_follows::Column::FollowerId.eq(profile_user_id))
+            .filter(user_follows::Column::FollowingId.eq(author_id))
+            .one(get_db_pool())
+            .await?
+            .is_some()),
+        _ => Ok(false),
+    }
 }
 
 /// Display data for a profile wall post
@@ -43,6 +80,19 @@ pub struct ProfilePostDisplay {
     pub author_avatar_filename: Option<String>,
     pub content: String,
     pub created_at: DateTime<Utc>,
+    /// Oldest first
+    pub comments: Vec<ProfilePostCommentDisplay>,
+}
+
+/// Display data for a comment on a profile wall post
+#[derive(Debug, Clone, sea_orm::FromQueryResult)]
+pub struct ProfilePostCommentDisplay {
+    pub id: i32,
+    pub profile_post_id: i32,
+    pub author_id: Option<i32>,
+    pub author_name: Option<String>,
+    pub content: String,
+    pub created_at: DateTimeWithTimeZone,
 }
 
 /// Get user statistics for profile display
@@ -63,10 +113,14 @@ async fn get_user_statistics(
         .count(db)
         .await?;
 
+    let (reactions_received, reactions_given) = super::reactions::reaction_stats(user_id).await?;
+
     Ok(UserStatistics {
         post_count: post_count as i64,
         thread_count: thread_count as i64,
         member_since: created_at,
+        reactions_received,
+        reactions_given,
     })
 }
 
@@ -116,6 +170,9 @@ async fn get_profile_posts(
         .all(db)
         .await?;
 
+    let mut comments =
+        get_profile_post_comments(db, rows.iter().map(|row| row.id).collect()).await?;
+
     Ok(rows
         .into_iter()
         .map(|row| ProfilePostDisplay {
@@ -125,10 +182,49 @@ async fn get_profile_posts(
             author_avatar_filename: row.author_avatar_filename,
             content: row.content,
             created_at: row.created_at.with_timezone(&Utc),
+            comments: comments.remove(&row.id).unwrap_or_default(),
         })
         .collect())
 }
 
+/// Comments on the given profile posts, oldest first, by post
+pub async fn get_profile_post_comments(
+    db: &DatabaseConnection,
+    profile_post_ids: Vec<i32>,
+) -> Result<HashMap<i32, Vec<ProfilePostCommentDisplay>>, sea_orm::DbErr> {
+    let mut by_post: HashMap<i32, Vec<ProfilePostCommentDisplay>> = HashMap::new();
+    if profile_post_ids.is_empty() {
+        return Ok(by_post);
+    }
+
+    let comments = profile_post_comments::Entity::find()
+        .filter(profile_post_comments::Column::ProfilePostId.is_in(profile_post_ids))
+        .left_join(user_names::Entity)
+        .column_as(user_names::Column::Name, "author_name")
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            profile_post_comments::Relation::Ugc.def(),
+        )
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            crate::orm::ugc::Relation::UgcRevisions.def(),
+        )
+        .column_as(ugc_revisions::Column::Content, "content")
+        .order_by_asc(profile_post_comments::Column::CreatedAt)
+        .order_by_asc(profile_post_comments::Column::Id)
+        .into_model::<ProfilePostCommentDisplay>()
+        .all(db)
+        .await?;
+
+    for comment in comments {
+        by_post
+            .entry(comment.profile_post_id)
+            .or_default()
+            .push(comment);
+    }
+    Ok(by_post)
+}
+
 #[get("/members/{user_id}/")]
 pub async fn view_member(
     client: ClientCtx,
@@ -143,15 +239,20 @@ pub async fn view_member(
         pub badges: Vec<crate::badges::UserBadge>,
         pub social_links: Vec<user_social_links::Model>,
         pub profile_posts: Vec<ProfilePostDisplay>,
-        pub allow_profile_posts: bool,
+        /// Whether the viewer may post and comment on this wall
+        pub can_post: bool,
+        /// Whether the viewer may delete anything on this wall
+        pub can_moderate: bool,
         pub is_following: bool,
+        /// How their name is shown, if one of their groups styles it
+        pub style: Option<crate::group::GroupStyle>,
     }
 
     let user_id = path.into_inner().0;
     let current_user_id = client.get_id();
     let db = get_db_pool();
 
-    // Use Profile::get_by_id for full user data including allow_profile_posts
+    // Use Profile::get_by_id for full user data including profile_post_privacy
     let user = UserProfile::get_by_id(db, user_id)
         .await
         .map_err(|e| {
@@ -160,7 +261,16 @@ pub async fn view_member(
         })?
         .ok_or_else(|| error::ErrorNotFound("User not found."))?;
 
-    let allow_profile_posts = user.allow_profile_posts;
+    let can_post = match current_user_id {
+        Some(current_id) => can_post_on_profile(user_id, &user.profile_post_privacy, current_id)
+            .await
+            .map_err(|e| {
+                log::error!("error checking profile post privacy: {:?}", e);
+                error::ErrorInternalServerError("Couldn't load user.")
+            })?,
+        None => false,
+    };
+    let can_moderate = current_user_id == Some(user_id) || client.can(MODERATE_PERMISSION);
 
     // Get user statistics
     let stats = get_user_statistics(db, user_id, user.created_at)
@@ -213,6 +323,14 @@ pub async fn view_member(
         false
     };
 
+    let style = crate::group::display_styles(db, &[user.id])
+        .await
+        .map_err(|e| {
+            log::error!("error {:?}", e);
+            error::ErrorInternalServerError("Couldn't load user.")
+        })?
+        .remove(&user.id);
+
     Ok(MemberTemplate {
         client,
         user,
@@ -220,8 +338,10 @@ pub async fn view_member(
         badges,
         social_links,
         profile_posts,
-        allow_profile_posts,
+        can_post,
+        can_moderate,
         is_following,
+        style,
     }
     .to_response())
 }
@@ -249,43 +369,285 @@ pub async fn view_member_by_name(path: web::Path<String>) -> Result<impl Respond
         .finish())
 }
 
+/// Members listed per page of the directory.
+pub const MEMBERS_PAGE_SIZE: i64 = 30;
+
+/// Filters and sorting for the member directory, as submitted by its form.
+/// Empty or unrecognised fields are ignored.
+#[derive(Debug, Default, Deserialize)]
+pub struct MemberFilter {
+    /// First letter of the username, or `#` for names not starting with a letter
+    pub letter: Option<String>,
+    /// `joined`, `posts` or `reputation`; names are sorted alphabetically otherwise
+    pub sort: Option<String>,
+    /// Only members of this group
+    pub group: Option<String>,
+    /// Only members holding this badge
+    pub badge: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// Letters for the jump links above the directory.
+pub fn jump_letters() -> Vec<String> {
+    std::iter::once("#".to_owned())
+        .chain(('A'..='Z').map(String::from))
+        .collect()
+}
+
+impl MemberFilter {
+    pub fn letter_value(&self) -> String {
+        non_empty(&self.letter)
+            .map(str::to_uppercase)
+            .filter(|letter| letter == "#" || matches!(letter.as_bytes(), [b'A'..=b'Z']))
+            .unwrap_or_default()
+    }
+
+    pub fn sort_value(&self) -> &str {
+        non_empty(&self.sort)
+            .filter(|sort| matches!(*sort, "joined" | "posts" | "reputation"))
+            .unwrap_or_default()
+    }
+
+    pub fn group_id(&self) -> Option<i32> {
+        non_empty(&self.group).and_then(|id| id.parse().ok())
+    }
+
+    pub fn badge_id(&self) -> Option<i32> {
+        non_empty(&self.badge).and_then(|id| id.parse().ok())
+    }
+
+    /// Query string repeating these filters, for pagination links.
+    pub fn query_string(&self) -> String {
+        self.query_string_for_letter(&self.letter_value())
+    }
+
+    /// Query string for the same sorting and filters starting at another
+    /// letter, or at any letter when `letter` is empty.
+    pub fn query_string_for_letter(&self, letter: &str) -> String {
+        let group = self.group_id().map(|id| id.to_string()).unwrap_or_default();
+        let badge = self.badge_id().map(|id| id.to_string()).unwrap_or_default();
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in [
+            ("letter", letter),
+            ("sort", self.sort_value()),
+            ("group", group.as_str()),
+            ("badge", badge.as_str()),
+        ] {
+            if !value.is_empty() {
+                query.append_pair(name, value);
+            }
+        }
+        query.finish()
+    }
+
+    /// SQL conditions on `users u` joined to `user_names un`, and the values
+    /// bound to their `$n` parameters.
+    fn conditions(&self) -> (String, Vec<sea_orm::Value>) {
+        let mut conditions = vec!["TRUE".to_owned()];
+        let mut values: Vec<sea_orm::Value> = Vec::new();
+
+        match self.letter_value().as_str() {
+            "" => {}
+            "#" => conditions.push("un.name !~* '^[a-z]'".to_owned()),
+            letter => {
+                values.push(letter.to_owned().into());
+                conditions.push(format!("UPPER(LEFT(un.name, 1)) = ${}", values.len()));
+            }
+        }
+        if let Some(group_id) = self.group_id() {
+            values.push(group_id.into());
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM user_groups ug WHERE ug.user_id = u.id AND ug.group_id = ${})",
+                values.len()
+            ));
+        }
+        if let Some(badge_id) = self.badge_id() {
+            values.push(badge_id.into());
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM user_badges ub WHERE ub.user_id = u.id AND ub.badge_id = ${})",
+                values.len()
+            ));
+        }
+
+        (conditions.join(" AND "), values)
+    }
+
+    /// ORDER BY clause for the chosen sort, with the user ID breaking ties so
+    /// pages don't overlap.
+    fn order_by(&self) -> &'static str {
+        match self.sort_value() {
+            "joined" => "u.created_at DESC, u.id DESC",
+            "posts" => "post_count DESC, u.id ASC",
+            "reputation" => "u.reputation_score DESC, u.id ASC",
+            _ => "LOWER(un.name) ASC, u.id ASC",
+        }
+    }
+}
+
+/// Members matching `filter` for one page of the directory, and the total
+/// number of matches.
+async fn search_members(
+    filter: &MemberFilter,
+    page: i64,
+) -> Result<(Vec<UserProfile>, i64), sea_orm::DbErr> {
+    use sea_orm::{DbBackend, FromQueryResult, Statement};
+
+    #[derive(FromQueryResult)]
+    struct Total {
+        count: i64,
+    }
+
+    let db = get_db_pool();
+    let (conditions, values) = filter.conditions();
+
+    let total = Total::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &format!(
+            "SELECT COUNT(*) AS count FROM users u
+             JOIN user_names un ON un.user_id = u.id
+             WHERE {}",
+            conditions
+        ),
+        values.clone(),
+    ))
+    .one(db)
+    .await?
+    .map(|total| total.count)
+    .unwrap_or(0);
+
+    let mut values = values;
+    values.push(MEMBERS_PAGE_SIZE.into());
+    values.push(((page.max(1) - 1) * MEMBERS_PAGE_SIZE).into());
+    let sql = format!(
+        r#"
+        SELECT
+            u.id,
+            un.name,
+            u.created_at,
+            u.password_cipher::text as password_cipher,
+            u.email,
+            u.avatar_source,
+            a.filename as avatar_filename,
+            a.file_height as avatar_height,
+            a.file_width as avatar_width,
+            {} as avatar_renditions,
+            u.posts_per_page,
+            (SELECT COUNT(*) FROM posts p WHERE p.user_id = u.id) as post_count,
+            u.theme,
+            u.theme_auto,
+            u.theme_dark,
+            u.theme_schedule,
+            u.theme_dark_start,
+            u.theme_dark_end,
+            u.bio,
+            u.location,
+            u.website_url,
+            u.signature,
+            u.custom_title,
+            u.show_online,
+            u.reputation_score,
+            u.profile_post_privacy,
+            u.follower_count,
+            u.following_count,
+            u.default_chat_room
+        FROM users u
+        JOIN user_names un ON un.user_id = u.id
+        LEFT JOIN user_avatars ua ON ua.user_id = u.id
+        LEFT JOIN attachments a ON a.id = ua.attachment_id
+        WHERE {}
+        ORDER BY {}
+        LIMIT ${} OFFSET ${}
+        "#,
+        crate::attachment::avatar_renditions_sql("a"),
+        conditions,
+        filter.order_by(),
+        values.len() - 1,
+        values.len()
+    );
+
+    let members = UserProfile::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await?;
+
+    Ok((members, total))
+}
+
+#[derive(Deserialize)]
+pub struct MembersPageQuery {
+    page: Option<i64>,
+}
+
 #[get("/members")]
-pub async fn view_members(client: ClientCtx) -> impl Responder {
+pub async fn view_members(
+    client: ClientCtx,
+    query: web::Query<MembersPageQuery>,
+    filter: web::Query<MemberFilter>,
+) -> Result<impl Responder, Error> {
     #[derive(Template)]
     #[template(path = "members.html")]
     pub struct MembersTemplate {
         pub client: ClientCtx,
         pub users: Vec<UserProfile>,
+        pub groups: Vec<groups::Model>,
+        pub badges: Vec<badges::Model>,
+        pub letters: Vec<String>,
+        pub filter: MemberFilter,
+        pub filter_query: String,
+        pub page: i64,
+        pub total_pages: i64,
+        pub total: i64,
     }
 
-    match users::Entity::find()
-        .left_join(user_names::Entity)
-        .column_as(user_names::Column::Name, "name")
-        .left_join(attachments::Entity)
-        .column_as(attachments::Column::Filename, "avatar_filename")
-        .column_as(attachments::Column::FileHeight, "avatar_height")
-        .column_as(attachments::Column::FileWidth, "avatar_width")
-        .column(users::Column::Bio)
-        .column(users::Column::Location)
-        .column(users::Column::WebsiteUrl)
-        .column(users::Column::Signature)
-        .column_as(
-            Expr::cust_with_values(
-                "(SELECT COUNT(*) FROM posts WHERE posts.user_id = users.id)",
-                std::iter::empty::<sea_orm::Value>(),
-            ),
-            "post_count",
-        )
-        .into_model::<UserProfile>()
-        .all(get_db_pool())
+    let db = get_db_pool();
+    let filter = filter.into_inner();
+    let page = query.page.unwrap_or(1).max(1);
+
+    let (users, total) = search_members(&filter, page).await.map_err(|e| {
+        log::error!("error loading members: {:?}", e);
+        error::ErrorInternalServerError("Couldn't load users")
+    })?;
+
+    let groups = groups::Entity::find()
+        .filter(groups::Column::GroupType.eq(crate::group::GroupType::Normal))
+        .order_by_asc(groups::Column::Label)
+        .all(db)
         .await
-    {
-        Ok(users) => Ok(MembersTemplate { client, users }.to_response()),
-        Err(e) => {
-            log::error!("error {:?}", e);
-            Err(error::ErrorInternalServerError("Couldn't load users"))
-        }
+        .map_err(|e| {
+            log::error!("error loading groups: {:?}", e);
+            error::ErrorInternalServerError("Couldn't load groups")
+        })?;
+
+    let badges = badges::Entity::find()
+        .filter(badges::Column::IsActive.eq(true))
+        .order_by_asc(badges::Column::DisplayOrder)
+        .order_by_asc(badges::Column::Name)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("error loading badges: {:?}", e);
+            error::ErrorInternalServerError("Couldn't load badges")
+        })?;
+
+    Ok(MembersTemplate {
+        client,
+        users,
+        groups,
+        badges,
+        letters: jump_letters(),
+        filter_query: filter.query_string(),
+        filter,
+        page,
+        total_pages: (total + MEMBERS_PAGE_SIZE - 1) / MEMBERS_PAGE_SIZE,
+        total,
     }
+    .to_response())
 }
 
 /// Query parameters for username search
@@ -323,7 +685,9 @@ pub async fn search_usernames(
                 .unwrap_or_else(|| "unknown".to_string())
         });
 
-    if let Err(e) = crate::rate_limit::check_api_rate_limit(&rate_limit_id) {
+    if let Err(e) =
+        crate::rate_limit::check_api_rate_limit(&rate_limit_id, &client.get_groups()).await
+    {
         log::warn!("User search rate limit exceeded for: {}", rate_limit_id);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many requests. Please try again in {} seconds.",
@@ -376,32 +740,53 @@ pub async fn search_usernames(
     Ok(HttpResponse::Ok().json(response))
 }
 
-/// Form data for creating a profile post
+/// Deletes anyone's profile posts and comments
+const MODERATE_PERMISSION: &str = "moderate.profile_posts.manage";
+
+/// Longest profile post or comment, in bytes
+const MAX_WALL_CONTENT_LENGTH: usize = 10000;
+
+/// Form data for creating a profile post or comment
 #[derive(Deserialize)]
 pub struct NewProfilePostForm {
     pub content: String,
     pub csrf_token: String,
 }
 
-/// Create a new profile wall post
-#[post("/members/{user_id}/posts")]
-pub async fn create_profile_post(
-    client: ClientCtx,
-    session: actix_session::Session,
-    req: HttpRequest,
-    path: web::Path<(i32,)>,
-    form: web::Form<NewProfilePostForm>,
-) -> Result<impl Responder, Error> {
-    // Validate CSRF token
-    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+/// Trimmed and word filtered content for a profile post or comment, if it's
+/// acceptable
+fn validate_wall_content(content: &str) -> Result<String, Error> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(error::ErrorBadRequest("Post content cannot be empty"));
+    }
+    if content.len() > MAX_WALL_CONTENT_LENGTH {
+        return Err(error::ErrorBadRequest(
+            "Post content too long (max 10000 characters)",
+        ));
+    }
+    crate::word_filter::filter_for_saving(content, FilterScope::Posts)
+        .map_err(error::ErrorBadRequest)
+}
 
-    // Require authentication
+/// The first 200 characters of wall content, for activities and notifications
+fn wall_preview(content: &str) -> String {
+    if content.chars().count() > 200 {
+        format!("{}...", content.chars().take(197).collect::<String>())
+    } else {
+        content.to_string()
+    }
+}
+
+/// Check the logged in member may write on a wall, returning their id
+async fn check_wall_access(client: &ClientCtx, profile_user_id: i32) -> Result<i32, Error> {
     let author_id = client
         .get_id()
         .ok_or_else(|| error::ErrorUnauthorized("Must be logged in to post on profiles"))?;
 
     // Rate limiting - uses post_creation rate limit (covers posts, profile posts, messages)
-    if let Err(e) = crate::rate_limit::check_post_rate_limit(author_id) {
+    if let Err(e) = crate::rate_limit::check_post_rate_limit(author_id, &client.get_groups()).await
+    {
         log::warn!("Profile post rate limit exceeded for user: {}", author_id);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many posts. Please try again in {} seconds.",
@@ -409,22 +794,56 @@ pub async fn create_profile_post(
         )));
     }
 
-    let profile_user_id = path.into_inner().0;
-    let db = get_db_pool();
-
-    // Check if profile user exists and allows profile posts
     let profile_user = users::Entity::find_by_id(profile_user_id)
-        .one(db)
+        .one(get_db_pool())
         .await
         .map_err(error::ErrorInternalServerError)?
         .ok_or_else(|| error::ErrorNotFound("User not found"))?;
 
-    if !profile_user.allow_profile_posts {
+    let allowed = can_post_on_profile(
+        profile_user_id,
+        &profile_user.profile_post_privacy,
+        author_id,
+    )
+    .await
+    .map_err(error::ErrorInternalServerError)?;
+    if !allowed {
         return Err(error::ErrorForbidden(
-            "This user has disabled profile posts",
+            "This user doesn't allow you to post on their profile",
         ));
     }
 
+    Ok(author_id)
+}
+
+/// The IP record for a request, for moderation
+async fn request_ip_id(req: &HttpRequest) -> Option<i32> {
+    match crate::ip::extract_client_ip(req) {
+        Some(ip_addr) => crate::ip::get_or_create_ip_id(&ip_addr)
+            .await
+            .ok()
+            .flatten(),
+        None => None,
+    }
+}
+
+/// Create a new profile wall post
+#[post("/members/{user_id}/posts")]
+pub async fn create_profile_post(
+    client: ClientCtx,
+    session: actix_session::Session,
+    req: HttpRequest,
+    path: web::Path<(i32,)>,
+    form: web::Form<NewProfilePostForm>,
+) -> Result<impl Responder, Error> {
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+
+    let profile_user_id = path.into_inner().0;
+    let author_id = check_wall_access(&client, profile_user_id).await?;
+    let content = validate_wall_content(&form.content)?;
+    let db = get_db_pool();
+
     // Get the profile user's name for activity recording
     let profile_user_name = user_names::Entity::find()
         .filter(user_names::Column::UserId.eq(profile_user_id))
@@ -434,34 +853,13 @@ pub async fn create_profile_post(
         .map(|n| n.name)
         .unwrap_or_else(|| "Unknown".to_string());
 
-    // Validate content
-    let content = form.content.trim();
-    if content.is_empty() {
-        return Err(error::ErrorBadRequest("Post content cannot be empty"));
-    }
-    if content.len() > 10000 {
-        return Err(error::ErrorBadRequest(
-            "Post content too long (max 10000 characters)",
-        ));
-    }
-
-    // Get IP address for moderation
-    let ip_id = if let Some(ip_addr) = crate::ip::extract_client_ip(&req) {
-        crate::ip::get_or_create_ip_id(&ip_addr)
-            .await
-            .ok()
-            .flatten()
-    } else {
-        None
-    };
-
     // Create UGC content
     let ugc_revision = create_ugc(
         db,
         NewUgcPartial {
-            ip_id,
+            ip_id: request_ip_id(&req).await,
             user_id: Some(author_id),
-            content,
+            content: &content,
         },
     )
     .await?;
@@ -474,18 +872,27 @@ pub async fn create_profile_post(
         created_at: Set(Utc::now().into()),
         ..Default::default()
     };
-    new_post
+    let post_id = new_post
         .insert(db)
         .await
-        .map_err(error::ErrorInternalServerError)?;
+        .map_err(error::ErrorInternalServerError)?
+        .id;
 
-    // Record activity for the feed (async, non-blocking)
-    let content_preview = if content.len() > 200 {
-        format!("{}...", &content[..197])
-    } else {
-        content.to_string()
-    };
+    // Record activity and notify the owner (async, non-blocking)
+    let content_preview = wall_preview(&content);
+    let author_name = client.get_name();
     actix::spawn(async move {
+        if let Err(e) = crate::notifications::dispatcher::notify_profile_post(
+            profile_user_id,
+            post_id,
+            author_id,
+            &author_name,
+            &content_preview,
+        )
+        .await
+        {
+            log::warn!("Failed to send profile post notification: {}", e);
+        }
         if let Err(e) = crate::activities::record_profile_post_created(
             author_id,
             profile_user_id,
@@ -500,16 +907,30 @@ pub async fn create_profile_post(
 
     // Redirect back to profile
     Ok(HttpResponse::SeeOther()
-        .append_header(("Location", format!("/members/{}/", profile_user_id)))
+        .append_header((
+            "Location",
+            format!("/members/{}/#profile-post-{}", profile_user_id, post_id),
+        ))
         .finish())
 }
 
-/// Form data for deleting a profile post
+/// Form data for deleting a profile post or comment
 #[derive(Deserialize)]
 pub struct DeleteProfilePostForm {
     pub csrf_token: String,
 }
 
+/// Whether the user may delete content on a wall: their own, anything on
+/// their own wall, or anything with the moderation permission
+fn can_delete_wall_content(
+    client: &ClientCtx,
+    user_id: i32,
+    author_id: Option<i32>,
+    profile_user_id: i32,
+) -> bool {
+    author_id == Some(user_id) || profile_user_id == user_id || client.can(MODERATE_PERMISSION)
+}
+
 /// Delete a profile wall post
 #[post("/members/{user_id}/posts/{post_id}/delete")]
 pub async fn delete_profile_post(
@@ -534,29 +955,163 @@ pub async fn delete_profile_post(
         .one(db)
         .await
         .map_err(error::ErrorInternalServerError)?
+        .filter(|post| post.profile_user_id == profile_user_id)
         .ok_or_else(|| error::ErrorNotFound("Post not found"))?;
 
-    // Check authorization: can delete if author or profile owner or admin
-    let can_delete = post.author_id == Some(user_id)
-        || post.profile_user_id == user_id
-        || client.can("admin.user.edit");
-
-    if !can_delete {
+    if !can_delete_wall_content(&client, user_id, post.author_id, post.profile_user_id) {
         return Err(error::ErrorForbidden("You cannot delete this post"));
     }
 
-    // Delete the post (UGC will be cascade deleted)
+    // Delete the post (comments will be cascade deleted)
     profile_posts::Entity::delete_by_id(post_id)
         .exec(db)
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    // Removing someone else's post from someone else's wall is moderation
+    if post.author_id != Some(user_id) && post.profile_user_id != user_id {
+        super::admin::log_moderation_action(
+            db,
+            user_id,
+            "delete_profile_post",
+            "profile_post",
+            post_id,
+            None,
+        )
+        .await?;
+    }
+
     // Redirect back to profile
     Ok(HttpResponse::SeeOther()
         .append_header(("Location", format!("/members/{}/", profile_user_id)))
         .finish())
 }
 
+/// Comment on a profile wall post
+#[post("/members/{user_id}/posts/{post_id}/comments")]
+pub async fn create_profile_post_comment(
+    client: ClientCtx,
+    session: actix_session::Session,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+    form: web::Form<NewProfilePostForm>,
+) -> Result<impl Responder, Error> {
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+
+    let (profile_user_id, post_id) = path.into_inner();
+    let author_id = check_wall_access(&client, profile_user_id).await?;
+    let content = validate_wall_content(&form.content)?;
+    let db = get_db_pool();
+
+    let post = profile_posts::Entity::find_by_id(post_id)
+        .one(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .filter(|post| post.profile_user_id == profile_user_id)
+        .ok_or_else(|| error::ErrorNotFound("Post not found"))?;
+
+    let ugc_revision = create_ugc(
+        db,
+        NewUgcPartial {
+            ip_id: request_ip_id(&req).await,
+            user_id: Some(author_id),
+            content: &content,
+        },
+    )
+    .await?;
+
+    profile_post_comments::ActiveModel {
+        profile_post_id: Set(post.id),
+        author_id: Set(Some(author_id)),
+        ugc_id: Set(ugc_revision.ugc_id),
+        created_at: Set(Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(error::ErrorInternalServerError)?;
+
+    let author_name = client.get_name();
+    let post_author_id = post.author_id;
+    actix::spawn(async move {
+        if let Err(e) = crate::notifications::dispatcher::notify_profile_post_comment(
+            profile_user_id,
+            post_id,
+            post_author_id,
+            author_id,
+            author_name,
+        )
+        .await
+        {
+            log::warn!("Failed to send profile post comment notification: {}", e);
+        }
+    });
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/members/{}/#profile-post-{}", profile_user_id, post.id),
+        ))
+        .finish())
+}
+
+/// Delete a comment on a profile wall post
+#[post("/members/{user_id}/comments/{comment_id}/delete")]
+pub async fn delete_profile_post_comment(
+    client: ClientCtx,
+    session: actix_session::Session,
+    path: web::Path<(i32, i32)>,
+    form: web::Form<DeleteProfilePostForm>,
+) -> Result<impl Responder, Error> {
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
+
+    let user_id = client
+        .get_id()
+        .ok_or_else(|| error::ErrorUnauthorized("Must be logged in to delete comments"))?;
+
+    let (profile_user_id, comment_id) = path.into_inner();
+    let db = get_db_pool();
+
+    let (comment, post) = profile_post_comments::Entity::find_by_id(comment_id)
+        .find_also_related(profile_posts::Entity)
+        .one(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .and_then(|(comment, post)| post.map(|post| (comment, post)))
+        .filter(|(_, post)| post.profile_user_id == profile_user_id)
+        .ok_or_else(|| error::ErrorNotFound("Comment not found"))?;
+
+    if !can_delete_wall_content(&client, user_id, comment.author_id, post.profile_user_id) {
+        return Err(error::ErrorForbidden("You cannot delete this comment"));
+    }
+
+    profile_post_comments::Entity::delete_by_id(comment_id)
+        .exec(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    if comment.author_id != Some(user_id) && post.profile_user_id != user_id {
+        super::admin::log_moderation_action(
+            db,
+            user_id,
+            "delete_profile_post_comment",
+            "profile_post_comment",
+            comment_id,
+            None,
+        )
+        .await?;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header((
+            "Location",
+            format!("/members/{}/#profile-post-{}", profile_user_id, post.id),
+        ))
+        .finish())
+}
+
 // =============================================================================
 // User Follow/Unfollow
 // =============================================================================
@@ -880,3 +1435,55 @@ pub async fn view_following(
     }
     .to_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(letter: &str, sort: &str, group: &str, badge: &str) -> MemberFilter {
+        MemberFilter {
+            letter: Some(letter.to_owned()),
+            sort: Some(sort.to_owned()),
+            group: Some(group.to_owned()),
+            badge: Some(badge.to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_member_filter_values() {
+        let f = filter("b", "posts", "3", "x");
+        assert_eq!(f.letter_value(), "B");
+        assert_eq!(f.sort_value(), "posts");
+        assert_eq!(f.group_id(), Some(3));
+        assert_eq!(f.badge_id(), None);
+
+        let f = filter("ab", "password_cipher", "", "");
+        assert_eq!(f.letter_value(), "");
+        assert_eq!(f.sort_value(), "");
+        assert_eq!(f.order_by(), "LOWER(un.name) ASC, u.id ASC");
+    }
+
+    #[test]
+    fn test_member_filter_query_string() {
+        let f = filter("#", "reputation", "2", "");
+        assert_eq!(f.query_string(), "letter=%23&sort=reputation&group=2");
+        assert_eq!(f.query_string_for_letter(""), "sort=reputation&group=2");
+        assert_eq!(MemberFilter::default().query_string(), "");
+    }
+
+    #[test]
+    fn test_member_filter_conditions() {
+        let (sql, values) = filter("c", "", "2", "5").conditions();
+        assert_eq!(
+            sql,
+            "TRUE AND UPPER(LEFT(un.name, 1)) = $1 \
+             AND EXISTS (SELECT 1 FROM user_groups ug WHERE ug.user_id = u.id AND ug.group_id = $2) \
+             AND EXISTS (SELECT 1 FROM user_badges ub WHERE ub.user_id = u.id AND ub.badge_id = $3)"
+        );
+        assert_eq!(values.len(), 3);
+
+        let (sql, values) = MemberFilter::default().conditions();
+        assert_eq!(sql, "TRUE");
+        assert!(values.is_empty());
+    }
+}
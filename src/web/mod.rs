@@ -1,10 +1,14 @@
 pub mod account;
 pub mod activity;
+pub mod activitypub;
 pub mod admin;
+pub mod api;
 pub mod asset;
 pub mod chat;
 pub mod conversations;
+pub mod email_change;
 pub mod email_verification;
+pub mod emails;
 pub mod error;
 pub mod feed;
 pub mod forum;
@@ -12,8 +16,11 @@ pub mod index;
 pub mod login;
 pub mod logout;
 pub mod member;
+pub mod notices;
 pub mod notifications;
 pub mod notifications_ws;
+pub mod oembed;
+pub mod outbound;
 pub mod password_reset;
 pub mod polls;
 pub mod post;
@@ -23,6 +30,8 @@ pub mod reports;
 pub mod search;
 pub mod thread;
 pub mod unfurl;
+pub mod unsubscribe;
+pub mod webhooks;
 
 /// Configures the web app by adding services from each web file.
 ///
@@ -33,18 +42,25 @@ pub fn configure(conf: &mut actix_web::web::ServiceConfig) {
     index::configure(conf);
     account::configure(conf);
     activity::configure(conf);
+    activitypub::configure(conf);
     admin::configure(conf);
+    api::configure(conf);
     asset::configure(conf);
     chat::configure(conf);
     conversations::configure(conf);
+    email_change::configure(conf);
     email_verification::configure(conf);
+    emails::configure(conf);
     feed::configure(conf);
     forum::configure(conf);
     login::configure(conf);
     logout::configure(conf);
     member::configure(conf);
+    notices::configure(conf);
     notifications::configure(conf);
     notifications_ws::configure(conf);
+    oembed::configure(conf);
+    outbound::configure(conf);
     password_reset::configure(conf);
     polls::configure(conf);
     post::configure(conf);
@@ -54,11 +70,19 @@ pub fn configure(conf: &mut actix_web::web::ServiceConfig) {
     search::configure(conf);
     thread::configure(conf);
     unfurl::configure(conf);
+    unsubscribe::configure(conf);
+    webhooks::configure(conf);
 
     conf.service(crate::create_user::create_user_get)
         .service(crate::create_user::create_user_post)
         .service(crate::auth_2fa::user_enable_2fa)
         .service(crate::filesystem::post_file_hash)
+        .service(crate::filesystem::get_transcode_status)
         .service(crate::filesystem::put_file)
+        .service(crate::filesystem::chunked::create_upload)
+        .service(crate::filesystem::chunked::view_upload)
+        .service(crate::filesystem::chunked::put_upload_part)
+        .service(crate::filesystem::chunked::complete_upload)
+        .service(crate::filesystem::chunked::delete_upload)
         .service(crate::session::view_task_expire_sessions);
 }
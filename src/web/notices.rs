@@ -0,0 +1,543 @@
+//! Site and forum notices: dismissing and acknowledging them, and the admin
+//! pages for posting them. See `crate::notices`.
+
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::notices::{self, STYLES};
+use crate::orm::{forums, notice_dismissals, notices as notice_rows};
+use actix_web::http::header;
+use actix_web::{error, get, post, web, Error, HttpRequest, HttpResponse, Responder};
+use askama_actix::{Template, TemplateToResponse};
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{entity::*, query::*};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Longest notice title, in characters
+const MAX_TITLE_LENGTH: usize = 255;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(dismiss_notice)
+        .service(acknowledge_notice)
+        .service(view_notices)
+        .service(view_new_notice)
+        .service(create_notice)
+        .service(view_edit_notice)
+        .service(update_notice)
+        .service(delete_notice)
+        .service(view_acknowledgments);
+}
+
+#[derive(Deserialize)]
+struct CsrfForm {
+    csrf_token: String,
+}
+
+/// Where to send a member back to after acting on a notice: the page they
+/// were on, if the browser says, or the index.
+fn return_path(req: &HttpRequest) -> String {
+    req.headers()
+        .get(header::REFERER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| url::Url::parse(value).ok())
+        .map(|url| match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        })
+        // Only ever a path on this site
+        .filter(|path| path.starts_with('/') && !path.starts_with("//"))
+        .unwrap_or_else(|| "/".to_string())
+}
+
+/// POST /notices/{id}/dismiss - Hide a notice
+#[post("/notices/{id}/dismiss")]
+async fn dismiss_notice(
+    req: HttpRequest,
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<CsrfForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let dismissed = notices::dismiss(path.into_inner(), user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to dismiss notice: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+    if !dismissed {
+        return Err(error::ErrorBadRequest("This notice can't be dismissed"));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", return_path(&req)))
+        .finish())
+}
+
+/// POST /notices/{id}/acknowledge - Record having read a notice
+#[post("/notices/{id}/acknowledge")]
+async fn acknowledge_notice(
+    req: HttpRequest,
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<CsrfForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let acknowledged = notices::acknowledge(path.into_inner(), user_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to acknowledge notice: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+    if !acknowledged {
+        return Err(error::ErrorBadRequest(
+            "This notice doesn't need acknowledging",
+        ));
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", return_path(&req)))
+        .finish())
+}
+
+// ============================================================================
+// Admin
+// ============================================================================
+
+/// A notice on the admin list
+struct NoticeRow {
+    notice: notice_rows::Model,
+    /// Forum it's shown in; None for site-wide
+    forum_label: Option<String>,
+    /// Scheduled, Showing, Ended or Disabled
+    state: &'static str,
+    acknowledgments: i64,
+}
+
+#[derive(Template)]
+#[template(path = "admin/notices.html")]
+struct NoticesTemplate {
+    client: ClientCtx,
+    notices: Vec<NoticeRow>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/notice_form.html")]
+struct NoticeFormTemplate {
+    client: ClientCtx,
+    notice: Option<notice_rows::Model>,
+    forums: Vec<forums::Model>,
+    styles: [&'static str; 3],
+}
+
+impl NoticeFormTemplate {
+    fn starts_at_input(&self) -> String {
+        format_datetime_input(self.notice.as_ref().and_then(|n| n.starts_at))
+    }
+
+    fn ends_at_input(&self) -> String {
+        format_datetime_input(self.notice.as_ref().and_then(|n| n.ends_at))
+    }
+
+    fn style_is(&self, style: &str) -> bool {
+        self.notice
+            .as_ref()
+            .map_or(style == "info", |n| n.style == style)
+    }
+
+    fn forum_is(&self, forum_id: i32) -> bool {
+        self.notice
+            .as_ref()
+            .is_some_and(|n| n.forum_id == Some(forum_id))
+    }
+}
+
+#[derive(Template)]
+#[template(path = "admin/notice_acknowledgments.html")]
+struct AcknowledgmentsTemplate {
+    client: ClientCtx,
+    notice: notice_rows::Model,
+    acknowledgments: Vec<(notice_dismissals::Model, String)>,
+}
+
+#[derive(Deserialize)]
+struct NoticeForm {
+    csrf_token: String,
+    title: String,
+    /// BBCode
+    body: String,
+    style: String,
+    /// Forum to show it in; blank for site-wide
+    #[serde(default)]
+    forum_id: String,
+    /// UTC datetime-local values; blank for no limit
+    #[serde(default)]
+    starts_at: String,
+    #[serde(default)]
+    ends_at: String,
+    is_dismissible: Option<String>,
+    requires_acknowledgment: Option<String>,
+    is_active: Option<String>,
+}
+
+/// Value of a datetime-local input for a start or end time
+fn format_datetime_input(at: Option<NaiveDateTime>) -> String {
+    at.map(|at| at.format("%Y-%m-%dT%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Parse a start or end time from a datetime-local input. Blank means none.
+fn parse_datetime_input(value: &str) -> Result<Option<NaiveDateTime>, Error> {
+    match value.trim() {
+        "" => Ok(None),
+        value => NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+            .map(Some)
+            .map_err(|_| error::ErrorBadRequest("Invalid date format")),
+    }
+}
+
+/// A notice's fields, checked
+struct ValidNotice {
+    title: String,
+    body: String,
+    style: String,
+    forum_id: Option<i32>,
+    starts_at: Option<NaiveDateTime>,
+    ends_at: Option<NaiveDateTime>,
+}
+
+impl NoticeForm {
+    async fn validate(&self) -> Result<ValidNotice, Error> {
+        let title = self.title.trim();
+        if title.is_empty() {
+            return Err(error::ErrorBadRequest("Title is required"));
+        }
+        if title.chars().count() > MAX_TITLE_LENGTH {
+            return Err(error::ErrorBadRequest(format!(
+                "Title must be at most {} characters",
+                MAX_TITLE_LENGTH
+            )));
+        }
+        let body = self.body.trim();
+        if body.is_empty() {
+            return Err(error::ErrorBadRequest("Message is required"));
+        }
+        if !STYLES.contains(&self.style.as_str()) {
+            return Err(error::ErrorBadRequest("Invalid style"));
+        }
+
+        let forum_id = match self.forum_id.trim() {
+            "" => None,
+            value => {
+                let forum_id = value
+                    .parse::<i32>()
+                    .map_err(|_| error::ErrorBadRequest("Invalid forum"))?;
+                forums::Entity::find_by_id(forum_id)
+                    .one(get_db_pool())
+                    .await
+                    .map_err(|e| {
+                        log::error!("Failed to fetch forum: {}", e);
+                        error::ErrorInternalServerError("Database error")
+                    })?
+                    .ok_or_else(|| error::ErrorBadRequest("Forum not found"))?;
+                Some(forum_id)
+            }
+        };
+
+        let starts_at = parse_datetime_input(&self.starts_at)?;
+        let ends_at = parse_datetime_input(&self.ends_at)?;
+        if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at) {
+            if ends_at <= starts_at {
+                return Err(error::ErrorBadRequest("The end must be after the start"));
+            }
+        }
+
+        Ok(ValidNotice {
+            title: title.to_string(),
+            body: body.to_string(),
+            style: self.style.clone(),
+            forum_id,
+            starts_at,
+            ends_at,
+        })
+    }
+}
+
+fn notice_state(notice: &notice_rows::Model, now: NaiveDateTime) -> &'static str {
+    if !notice.is_active {
+        "Disabled"
+    } else if notice.starts_at.is_some_and(|at| at > now) {
+        "Scheduled"
+    } else if notice.ends_at.is_some_and(|at| at <= now) {
+        "Ended"
+    } else {
+        "Showing"
+    }
+}
+
+async fn find_notice(id: i32) -> Result<notice_rows::Model, Error> {
+    notice_rows::Entity::find_by_id(id)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch notice: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?
+        .ok_or_else(|| error::ErrorNotFound("Notice not found"))
+}
+
+async fn all_forums() -> Result<Vec<forums::Model>, Error> {
+    forums::Entity::find()
+        .order_by_asc(forums::Column::Label)
+        .all(get_db_pool())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch forums: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })
+}
+
+/// GET /admin/notices - List notices
+#[get("/admin/notices")]
+async fn view_notices(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+    let all_notices = notice_rows::Entity::find()
+        .order_by_desc(notice_rows::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch notices: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+    let forum_labels: HashMap<i32, String> = all_forums()
+        .await?
+        .into_iter()
+        .map(|f| (f.id, f.label))
+        .collect();
+    let counts = notices::acknowledgment_counts().await.map_err(|e| {
+        log::error!("Failed to count notice acknowledgments: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    let now = Utc::now().naive_utc();
+    let rows = all_notices
+        .into_iter()
+        .map(|notice| NoticeRow {
+            forum_label: notice
+                .forum_id
+                .and_then(|id| forum_labels.get(&id).cloned()),
+            state: notice_state(&notice, now),
+            acknowledgments: counts.get(&notice.id).copied().unwrap_or(0),
+            notice,
+        })
+        .collect();
+
+    Ok(NoticesTemplate {
+        client,
+        notices: rows,
+    }
+    .to_response())
+}
+
+/// GET /admin/notices/new - Form for a new notice
+#[get("/admin/notices/new")]
+async fn view_new_notice(client: ClientCtx) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    Ok(NoticeFormTemplate {
+        client,
+        notice: None,
+        forums: all_forums().await?,
+        styles: STYLES,
+    }
+    .to_response())
+}
+
+/// POST /admin/notices - Post a notice
+#[post("/admin/notices")]
+async fn create_notice(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<NoticeForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let valid = form.validate().await?;
+    let now = Utc::now().naive_utc();
+    notice_rows::ActiveModel {
+        title: Set(valid.title),
+        body: Set(valid.body),
+        style: Set(valid.style),
+        forum_id: Set(valid.forum_id),
+        starts_at: Set(valid.starts_at),
+        ends_at: Set(valid.ends_at),
+        is_dismissible: Set(form.is_dismissible.is_some()),
+        requires_acknowledgment: Set(form.requires_acknowledgment.is_some()),
+        is_active: Set(form.is_active.is_some()),
+        created_by: Set(Some(user_id)),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(get_db_pool())
+    .await
+    .map_err(|e| {
+        log::error!("Failed to create notice: {}", e);
+        error::ErrorInternalServerError("Failed to create notice")
+    })?;
+    notices::changed().await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/notices"))
+        .finish())
+}
+
+/// GET /admin/notices/{id}/edit - Form for editing a notice
+#[get("/admin/notices/{id}/edit")]
+async fn view_edit_notice(
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let notice = find_notice(path.into_inner()).await?;
+
+    Ok(NoticeFormTemplate {
+        client,
+        notice: Some(notice),
+        forums: all_forums().await?,
+        styles: STYLES,
+    }
+    .to_response())
+}
+
+/// POST /admin/notices/{id} - Update a notice. Who has dismissed or
+/// acknowledged it is kept.
+#[post("/admin/notices/{id}")]
+async fn update_notice(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<NoticeForm>,
+) -> Result<impl Responder, Error> {
+    client.require_login()?;
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let existing = find_notice(path.into_inner()).await?;
+    let valid = form.validate().await?;
+
+    let mut notice: notice_rows::ActiveModel = existing.into();
+    notice.title = Set(valid.title);
+    notice.body = Set(valid.body);
+    notice.style = Set(valid.style);
+    notice.forum_id = Set(valid.forum_id);
+    notice.starts_at = Set(valid.starts_at);
+    notice.ends_at = Set(valid.ends_at);
+    notice.is_dismissible = Set(form.is_dismissible.is_some());
+    notice.requires_acknowledgment = Set(form.requires_acknowledgment.is_some());
+    notice.is_active = Set(form.is_active.is_some());
+    notice.updated_at = Set(Utc::now().naive_utc());
+    notice.update(get_db_pool()).await.map_err(|e| {
+        log::error!("Failed to update notice: {}", e);
+        error::ErrorInternalServerError("Failed to update notice")
+    })?;
+    notices::changed().await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/notices"))
+        .finish())
+}
+
+/// POST /admin/notices/{id}/delete - Delete a notice and its acknowledgments
+#[post("/admin/notices/{id}/delete")]
+async fn delete_notice(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<CsrfForm>,
+) -> Result<impl Responder, Error> {
+    client.require_login()?;
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    notice_rows::Entity::delete_by_id(path.into_inner())
+        .exec(get_db_pool())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete notice: {}", e);
+            error::ErrorInternalServerError("Failed to delete notice")
+        })?;
+    notices::changed().await;
+
+    Ok(HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/notices"))
+        .finish())
+}
+
+/// GET /admin/notices/{id}/acknowledgments - Who has acknowledged a notice
+#[get("/admin/notices/{id}/acknowledgments")]
+async fn view_acknowledgments(
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let notice = find_notice(path.into_inner()).await?;
+    let acknowledgments = notices::acknowledgments(notice.id).await.map_err(|e| {
+        log::error!("Failed to fetch notice acknowledgments: {}", e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    Ok(AcknowledgmentsTemplate {
+        client,
+        notice,
+        acknowledgments,
+    }
+    .to_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_return_path() {
+        let req = TestRequest::default()
+            .insert_header((header::REFERER, "https://example.com/forums/3/?page=2"))
+            .to_http_request();
+        assert_eq!(return_path(&req), "/forums/3/?page=2");
+
+        let req = TestRequest::default()
+            .insert_header((header::REFERER, "https://example.com//evil.example"))
+            .to_http_request();
+        assert_eq!(return_path(&req), "/");
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(return_path(&req), "/");
+    }
+
+    #[test]
+    fn test_parse_datetime_input() {
+        assert_eq!(parse_datetime_input(" ").unwrap(), None);
+        assert_eq!(
+            parse_datetime_input("2026-03-01T09:30").unwrap(),
+            NaiveDateTime::parse_from_str("2026-03-01 09:30", "%Y-%m-%d %H:%M").ok()
+        );
+        assert!(parse_datetime_input("tomorrow").is_err());
+        assert_eq!(
+            format_datetime_input(parse_datetime_input("2026-03-01T09:30").unwrap()),
+            "2026-03-01T09:30"
+        );
+    }
+}
@@ -3,21 +3,35 @@
 /// This module provides routes for viewing and managing notifications.
 use crate::middleware::ClientCtx;
 use crate::notifications;
+use crate::notifications::snooze::{self, SnoozeDuration};
 use crate::orm::notifications as notification_orm;
 use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
 use askama_actix::{Template, TemplateToResponse};
 use serde::Deserialize;
+use std::collections::HashMap;
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
     conf.service(view_notifications)
+        .service(get_unread_count)
         .service(mark_read)
+        .service(snooze_notification)
+        .service(unsnooze_notification)
         .service(mark_all_read)
         .service(watch_thread)
         .service(unwatch_thread)
         .service(toggle_thread_email)
+        .service(watch_forum)
+        .service(unwatch_forum)
+        .service(toggle_forum_email)
         .service(view_watched_threads)
+        .service(redirect_preferences)
         .service(view_preferences)
-        .service(update_preferences);
+        .service(update_preferences)
+        .service(update_quiet_hours)
+        .service(subscribe_push)
+        .service(unsubscribe_push)
+        .service(update_device)
+        .service(delete_device);
 }
 
 /// Template for notification list
@@ -27,6 +41,10 @@ struct NotificationsTemplate {
     client: ClientCtx,
     notifications: Vec<NotificationDisplay>,
     unread_count: i64,
+    /// Listing snoozed notifications instead of the usual ones
+    show_snoozed: bool,
+    snoozed_count: usize,
+    snooze_durations: &'static [SnoozeDuration],
 }
 
 /// Notification display struct for templates
@@ -40,6 +58,7 @@ struct NotificationDisplay {
     is_read: bool,
     created_at: chrono::NaiveDateTime,
     notification_type: String,
+    snoozed_until: Option<chrono::NaiveDateTime>,
 }
 
 impl From<notification_orm::Model> for NotificationDisplay {
@@ -52,6 +71,7 @@ impl From<notification_orm::Model> for NotificationDisplay {
             is_read: n.is_read,
             created_at: n.created_at,
             notification_type: n.type_,
+            snoozed_until: n.snoozed_until,
         }
     }
 }
@@ -60,6 +80,7 @@ impl From<notification_orm::Model> for NotificationDisplay {
 #[derive(Deserialize)]
 struct NotificationQuery {
     show_read: Option<bool>,
+    snoozed: Option<bool>,
 }
 
 /// GET /notifications - View notification list
@@ -71,11 +92,21 @@ pub async fn view_notifications(
     let user_id = client.require_login()?;
 
     let show_read = query.show_read.unwrap_or(false);
+    let show_snoozed = query.snoozed.unwrap_or(false);
 
-    // Fetch notifications
-    let notifications = notifications::get_user_notifications(user_id, 50, show_read)
+    let snoozed = snooze::get_snoozed_notifications(user_id)
         .await
         .map_err(error::ErrorInternalServerError)?;
+    let snoozed_count = snoozed.len();
+
+    // Fetch notifications
+    let notifications = if show_snoozed {
+        snoozed
+    } else {
+        notifications::get_user_notifications(user_id, 50, show_read)
+            .await
+            .map_err(error::ErrorInternalServerError)?
+    };
 
     // Get unread count
     let unread_count = notifications::count_unread_notifications(user_id)
@@ -89,10 +120,27 @@ pub async fn view_notifications(
         client,
         notifications: notification_displays,
         unread_count,
+        show_snoozed,
+        snoozed_count,
+        snooze_durations: snooze::SNOOZE_DURATIONS,
     }
     .to_response())
 }
 
+/// GET /api/notifications/unread-count - Unread count for the header badge
+#[get("/api/notifications/unread-count")]
+pub async fn get_unread_count(client: ClientCtx) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+
+    let count = notifications::count_unread_notifications(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(serde_json::json!({ "count": count })))
+}
+
 /// POST /notifications/{id}/read - Mark a notification as read
 #[post("/notifications/{id}/read")]
 pub async fn mark_read(
@@ -110,6 +158,59 @@ pub async fn mark_read(
     })))
 }
 
+/// Form data for snoozing a notification
+#[derive(Deserialize)]
+struct SnoozeForm {
+    csrf_token: String,
+    duration: String,
+}
+
+/// POST /notifications/{id}/snooze - Hide a notification for a while
+#[post("/notifications/{id}/snooze")]
+pub async fn snooze_notification(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    notification_id: web::Path<i32>,
+    form: web::Form<SnoozeForm>,
+) -> Result<impl Responder, Error> {
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let user_id = client.require_login()?;
+
+    let duration = SnoozeDuration::parse(&form.duration)
+        .ok_or_else(|| error::ErrorBadRequest("Invalid snooze duration"))?;
+
+    snooze::snooze_notification(user_id, *notification_id, duration)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Notification not found"))?;
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", "/notifications"))
+        .finish())
+}
+
+/// POST /notifications/{id}/unsnooze - Bring a snoozed notification back now
+#[post("/notifications/{id}/unsnooze")]
+pub async fn unsnooze_notification(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    notification_id: web::Path<i32>,
+    form: web::Form<super::forum::CsrfForm>,
+) -> Result<impl Responder, Error> {
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let user_id = client.require_login()?;
+
+    snooze::unsnooze_notification(user_id, *notification_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", "/notifications?snoozed=true"))
+        .finish())
+}
+
 /// POST /notifications/mark-all-read - Mark all notifications as read
 #[post("/notifications/mark-all-read")]
 pub async fn mark_all_read(client: ClientCtx) -> Result<impl Responder, Error> {
@@ -191,12 +292,96 @@ pub async fn toggle_thread_email(
         .finish())
 }
 
+// Forum Watching Routes
+
+/// POST /forums/{forum_id}/watch - Watch a forum for new threads
+#[post("/forums/{forum_id}/watch")]
+pub async fn watch_forum(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<super::forum::CsrfForm>,
+) -> Result<impl Responder, Error> {
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let user_id = client.require_login()?;
+    let forum_id = path.into_inner();
+
+    if !client.can_view_forum(&forum_id) {
+        return Err(error::ErrorForbidden(
+            "You do not have permission to view this forum.",
+        ));
+    }
+
+    notifications::watch_forum(user_id, forum_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    // Redirect back to the forum
+    Ok(HttpResponse::Found()
+        .append_header(("Location", format!("/forums/{}/", forum_id)))
+        .finish())
+}
+
+/// POST /forums/{forum_id}/unwatch - Unwatch a forum
+#[post("/forums/{forum_id}/unwatch")]
+pub async fn unwatch_forum(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<super::forum::CsrfForm>,
+) -> Result<impl Responder, Error> {
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let user_id = client.require_login()?;
+    let forum_id = path.into_inner();
+
+    notifications::unwatch_forum(user_id, forum_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    // Redirect back to the forum
+    Ok(HttpResponse::Found()
+        .append_header(("Location", format!("/forums/{}/", forum_id)))
+        .finish())
+}
+
+/// POST /forums/{forum_id}/toggle-email - Toggle email notifications for a watched forum
+#[post("/forums/{forum_id}/toggle-email")]
+pub async fn toggle_forum_email(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<super::forum::CsrfForm>,
+) -> Result<impl Responder, Error> {
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let user_id = client.require_login()?;
+    let forum_id = path.into_inner();
+
+    let watch_status = notifications::get_forum_watch_status(user_id, forum_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    if let Some(watch) = watch_status {
+        notifications::toggle_forum_email(user_id, forum_id, !watch.email_on_thread)
+            .await
+            .map_err(error::ErrorInternalServerError)?;
+    }
+
+    // Redirect back to the forum
+    Ok(HttpResponse::Found()
+        .append_header(("Location", format!("/forums/{}/", forum_id)))
+        .finish())
+}
+
 /// Template for watched threads page
 #[derive(Template)]
 #[template(path = "watched_threads.html")]
 struct WatchedThreadsTemplate {
     client: ClientCtx,
     threads: Vec<WatchedThreadDisplay>,
+    forums: Vec<WatchedForumDisplay>,
 }
 
 /// Display struct for watched thread
@@ -210,7 +395,15 @@ struct WatchedThreadDisplay {
     last_post_at: Option<chrono::NaiveDateTime>,
 }
 
-/// GET /watched-threads - View all watched threads
+/// Display struct for watched forum
+#[derive(Debug)]
+struct WatchedForumDisplay {
+    id: i32,
+    label: String,
+    email_on_thread: bool,
+}
+
+/// GET /watched-threads - View all watched threads and forums
 #[get("/watched-threads")]
 pub async fn view_watched_threads(client: ClientCtx) -> Result<impl Responder, Error> {
     use crate::orm::{forums, threads};
@@ -219,6 +412,30 @@ pub async fn view_watched_threads(client: ClientCtx) -> Result<impl Responder, E
     let user_id = client.require_login()?;
     let db = crate::db::get_db_pool();
 
+    // Get forums the user is watching
+    let forum_watches = notifications::get_watched_forums(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let forum_displays: Vec<WatchedForumDisplay> = if forum_watches.is_empty() {
+        vec![]
+    } else {
+        forums::Entity::find()
+            .filter(forums::Column::Id.is_in(forum_watches.iter().map(|w| w.forum_id)))
+            .order_by_asc(forums::Column::Label)
+            .all(db)
+            .await
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|forum| WatchedForumDisplay {
+                email_on_thread: forum_watches
+                    .iter()
+                    .any(|w| w.forum_id == forum.id && w.email_on_thread),
+                id: forum.id,
+                label: forum.label,
+            })
+            .collect()
+    };
+
     // Get thread IDs that user is watching
     let watched_thread_ids = notifications::get_watched_threads(user_id)
         .await
@@ -228,6 +445,7 @@ pub async fn view_watched_threads(client: ClientCtx) -> Result<impl Responder, E
         return Ok(WatchedThreadsTemplate {
             client,
             threads: vec![],
+            forums: forum_displays,
         }
         .to_response());
     }
@@ -258,32 +476,44 @@ pub async fn view_watched_threads(client: ClientCtx) -> Result<impl Responder, E
     Ok(WatchedThreadsTemplate {
         client,
         threads: thread_displays,
+        forums: forum_displays,
     }
     .to_response())
 }
 
 // Notification Preference Routes
 
+/// Where the notification preference center lives
+const PREFERENCES_PATH: &str = "/account/preferences/notifications";
+
 /// Template for notification preferences page
 #[derive(Template)]
 #[template(path = "notification_preferences.html")]
 struct NotificationPreferencesTemplate {
     client: ClientCtx,
     preferences: Vec<notifications::NotificationPreferenceDisplay>,
+    quiet_hours: notifications::quiet_hours::QuietHours,
+    /// VAPID key for subscribing this browser, when push is enabled
+    push_public_key: Option<String>,
+    push_device_count: usize,
+    /// Mobile devices registered through the app
+    mobile_devices: Vec<crate::orm::push_devices::Model>,
+    /// Whether anything can be pushed to, so the per-type switches matter
+    push_available: bool,
+    /// Whether announcements from the admin panel are emailed
+    email_announcements: bool,
 }
 
-/// Form data for updating preferences
-#[derive(Deserialize)]
-struct PreferenceUpdateForm {
-    csrf_token: String,
-    notification_type: String,
-    in_app: Option<String>,
-    email: Option<String>,
-    frequency: String,
+/// GET /notifications/preferences - Old address of the preference center
+#[get("/notifications/preferences")]
+pub async fn redirect_preferences() -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .append_header(("Location", PREFERENCES_PATH))
+        .finish()
 }
 
-/// GET /notifications/preferences - View notification preferences
-#[get("/notifications/preferences")]
+/// GET /account/preferences/notifications - View notification preferences
+#[get("/account/preferences/notifications")]
 pub async fn view_preferences(client: ClientCtx) -> Result<impl Responder, Error> {
     let user_id = client.require_login()?;
 
@@ -291,41 +521,266 @@ pub async fn view_preferences(client: ClientCtx) -> Result<impl Responder, Error
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    let quiet_hours = notifications::quiet_hours::get_quiet_hours(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let push_public_key = notifications::push::public_key();
+    let push_device_count = if push_public_key.is_some() {
+        notifications::push::get_subscriptions(user_id)
+            .await
+            .map_err(error::ErrorInternalServerError)?
+            .len()
+    } else {
+        0
+    };
+
+    let mobile_devices = notifications::mobile::get_devices(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let push_available = push_public_key.is_some() || !mobile_devices.is_empty();
+
+    let email_announcements = crate::email::mass::announcements_enabled(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
     Ok(NotificationPreferencesTemplate {
         client,
         preferences,
+        quiet_hours,
+        push_public_key,
+        push_device_count,
+        mobile_devices,
+        push_available,
+        email_announcements,
     }
     .to_response())
 }
 
-/// POST /notifications/preferences - Update notification preferences
-#[post("/notifications/preferences")]
+/// POST /account/preferences/notifications - Save every notification preference at once
+///
+/// Fields per type: `{type}_in_app`, `{type}_email` and `{type}_push` checkboxes,
+/// and `{type}_frequency`.
+#[post("/account/preferences/notifications")]
 pub async fn update_preferences(
     client: ClientCtx,
     cookies: actix_session::Session,
-    form: web::Form<PreferenceUpdateForm>,
+    form: web::Form<HashMap<String, String>>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+
+    // Validate CSRF token
+    let csrf_token = form
+        .get("csrf_token")
+        .ok_or_else(|| error::ErrorBadRequest("CSRF token missing"))?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, csrf_token)?;
+
+    let updates: Vec<notifications::PreferenceUpdate> = notifications::PREFERENCE_TYPES
+        .iter()
+        .map(|(type_str, _, _)| notifications::PreferenceUpdate {
+            notification_type: type_str.to_string(),
+            // Checkboxes are only sent when ticked
+            in_app: form.contains_key(&format!("{}_in_app", type_str)),
+            email: form.contains_key(&format!("{}_email", type_str)),
+            push: form.contains_key(&format!("{}_push", type_str)),
+            frequency: form
+                .get(&format!("{}_frequency", type_str))
+                .cloned()
+                .unwrap_or_else(|| "immediate".to_string()),
+        })
+        .collect();
+
+    if let Some(update) = updates
+        .iter()
+        .find(|u| !notifications::is_valid_frequency(&u.frequency))
+    {
+        return Err(error::ErrorBadRequest(format!(
+            "Invalid frequency for {}",
+            update.notification_type
+        )));
+    }
+
+    notifications::update_preferences(user_id, &updates)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    crate::email::mass::set_announcements(user_id, form.contains_key("email_announcements"))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    // Redirect back to preferences page
+    Ok(HttpResponse::Found()
+        .append_header(("Location", PREFERENCES_PATH))
+        .finish())
+}
+
+/// Form data for quiet hours
+#[derive(Deserialize)]
+struct QuietHoursForm {
+    csrf_token: String,
+    enabled: Option<String>,
+    start_time: String,
+    end_time: String,
+    utc_offset_minutes: i16,
+}
+
+/// POST /account/preferences/notifications/quiet-hours - Update do-not-disturb schedule
+#[post("/account/preferences/notifications/quiet-hours")]
+pub async fn update_quiet_hours(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<QuietHoursForm>,
 ) -> Result<impl Responder, Error> {
+    use notifications::quiet_hours;
+
     let user_id = client.require_login()?;
 
     // Validate CSRF token
     crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
 
-    // Convert checkbox values (Some("on") or None) to boolean
-    let in_app = form.in_app.is_some();
-    let email = form.email.is_some();
+    let start_minute = quiet_hours::parse_time(&form.start_time)
+        .ok_or_else(|| error::ErrorBadRequest("Invalid start time"))?;
+    let end_minute = quiet_hours::parse_time(&form.end_time)
+        .ok_or_else(|| error::ErrorBadRequest("Invalid end time"))?;
+    if !quiet_hours::is_valid_offset(form.utc_offset_minutes) {
+        return Err(error::ErrorBadRequest("Invalid time zone offset"));
+    }
 
-    notifications::update_preference(
+    quiet_hours::update_quiet_hours(
         user_id,
-        &form.notification_type,
-        in_app,
-        email,
-        &form.frequency,
+        &quiet_hours::QuietHours {
+            enabled: form.enabled.is_some(),
+            start_minute,
+            end_minute,
+            utc_offset_minutes: form.utc_offset_minutes,
+        },
     )
     .await
     .map_err(error::ErrorInternalServerError)?;
 
     // Redirect back to preferences page
     Ok(HttpResponse::Found()
-        .append_header(("Location", "/notifications/preferences"))
+        .append_header(("Location", PREFERENCES_PATH))
+        .finish())
+}
+
+/// Form data for a browser push subscription
+#[derive(Deserialize)]
+struct PushSubscriptionForm {
+    csrf_token: String,
+    endpoint: String,
+    p256dh: String,
+    auth: String,
+}
+
+/// POST /account/notifications/push - Subscribe this browser to push notifications
+#[post("/account/notifications/push")]
+pub async fn subscribe_push(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<PushSubscriptionForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    if !notifications::push::is_enabled() {
+        return Err(error::ErrorNotFound("Push notifications are not enabled"));
+    }
+    notifications::push::validate_subscription(&form.endpoint, &form.p256dh, &form.auth)
+        .map_err(error::ErrorBadRequest)?;
+
+    notifications::push::subscribe(user_id, &form.endpoint, &form.p256dh, &form.auth)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "subscribed": true
+    })))
+}
+
+/// Form data for removing a browser push subscription
+#[derive(Deserialize)]
+struct PushUnsubscribeForm {
+    csrf_token: String,
+    endpoint: String,
+}
+
+/// POST /account/notifications/push/delete - Stop push notifications to this browser
+#[post("/account/notifications/push/delete")]
+pub async fn unsubscribe_push(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<PushUnsubscribeForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    notifications::push::unsubscribe(user_id, &form.endpoint)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "subscribed": false
+    })))
+}
+
+/// Form data for pausing or resuming a mobile device
+#[derive(Deserialize)]
+struct DeviceForm {
+    csrf_token: String,
+    enabled: Option<String>,
+}
+
+/// POST /account/notifications/devices/{id} - Pause or resume pushes to a mobile device
+#[post("/account/notifications/devices/{id}")]
+pub async fn update_device(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<DeviceForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    notifications::mobile::set_enabled(user_id, path.into_inner(), form.enabled.is_some())
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Device not found"))?;
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", PREFERENCES_PATH))
+        .finish())
+}
+
+/// Form data carrying only a CSRF token
+#[derive(Deserialize)]
+struct CsrfForm {
+    csrf_token: String,
+}
+
+/// POST /account/notifications/devices/{id}/delete - Stop pushes to a mobile device
+#[post("/account/notifications/devices/{id}/delete")]
+pub async fn delete_device(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<CsrfForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    notifications::mobile::unregister(user_id, path.into_inner())
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", PREFERENCES_PATH))
         .finish())
 }
@@ -1,8 +1,9 @@
 //! WebSocket connection actor for notification clients
 
-use super::message::{Connect, Disconnect, NotificationPush};
+use super::message::{ClientCommand, Connect, Disconnect, NotificationPush};
 use super::server::NotificationServer;
-use super::{CLIENT_TIMEOUT, HEARTBEAT_INTERVAL};
+use super::{CLIENT_TIMEOUT, HEARTBEAT_INTERVAL, TYPING_THROTTLE};
+use crate::conversations::realtime;
 use actix::*;
 use actix_web_actors::ws;
 use std::time::Instant;
@@ -17,6 +18,8 @@ pub struct NotificationConnection {
     pub hb: Instant,
     /// Address of the notification server
     pub server: Addr<NotificationServer>,
+    /// When a typing indicator was last relayed
+    pub last_typing: Option<Instant>,
 }
 
 impl NotificationConnection {
@@ -26,6 +29,36 @@ impl NotificationConnection {
             user_id,
             hb: Instant::now(),
             server,
+            last_typing: None,
+        }
+    }
+
+    /// Handle a JSON command sent by the client
+    fn handle_command(&mut self, command: ClientCommand) {
+        let user_id = self.user_id;
+
+        match command {
+            ClientCommand::Typing { conversation_id } => {
+                if let Some(last) = self.last_typing {
+                    if last.elapsed() < TYPING_THROTTLE {
+                        return;
+                    }
+                }
+                self.last_typing = Some(Instant::now());
+
+                actix::spawn(async move {
+                    if let Err(err) = realtime::broadcast_typing(user_id, conversation_id).await {
+                        log::debug!("Typing indicator rejected: {}", err);
+                    }
+                });
+            }
+            ClientCommand::Read { conversation_id } => {
+                actix::spawn(async move {
+                    if let Err(err) = realtime::mark_read(user_id, conversation_id).await {
+                        log::debug!("Read receipt rejected: {}", err);
+                    }
+                });
+            }
         }
     }
 
@@ -126,8 +159,10 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for NotificationConne
                 if text == "ping" {
                     // Simple ping/pong for keep-alive
                     ctx.text(r#"{"type":"pong"}"#);
+                } else if let Ok(command) = serde_json::from_str::<ClientCommand>(text) {
+                    self.handle_command(command);
                 }
-                // Notifications are server-push only, so we ignore other messages
+                // Anything else is ignored
             }
             ws::Message::Binary(_) => {
                 // Ignore binary messages
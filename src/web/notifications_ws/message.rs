@@ -1,7 +1,7 @@
 //! Message types for the notification WebSocket system
 
 use actix::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// New notification WebSocket connection
 pub struct Connect {
@@ -39,6 +39,14 @@ impl Message for BroadcastNotification {
     type Result = ();
 }
 
+/// Serialized messages for users' connections, shared with other instances
+/// over the backplane.
+#[derive(Serialize, Deserialize)]
+pub struct NotificationRelay {
+    pub user_ids: Vec<i32>,
+    pub message: String,
+}
+
 /// Notification data to send to client
 #[derive(Clone, Serialize)]
 pub struct NotificationData {
@@ -48,6 +56,64 @@ pub struct NotificationData {
     pub message: String,
     pub url: Option<String>,
     pub created_at: String,
+    /// Users behind a grouped notification
+    pub actor_count: i32,
+    /// True when this replaces an unread notification the client already counted
+    pub updated: bool,
+}
+
+/// Push a conversation event to every connection of the given users
+#[derive(Clone)]
+pub struct BroadcastConversationEvent {
+    /// Target user IDs
+    pub user_ids: Vec<i32>,
+    /// Event data
+    pub event: ConversationEvent,
+}
+
+impl Message for BroadcastConversationEvent {
+    type Result = ();
+}
+
+/// Live updates for open conversation pages
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum ConversationEvent {
+    /// A new message was posted
+    #[serde(rename = "conversation_message")]
+    Message {
+        conversation_id: i32,
+        message_id: i32,
+        user_id: i32,
+        author_name: String,
+        content_html: String,
+        created_at: String,
+    },
+    /// A participant is composing a reply
+    #[serde(rename = "conversation_typing")]
+    Typing {
+        conversation_id: i32,
+        user_id: i32,
+        name: String,
+    },
+    /// A participant's read pointer moved
+    #[serde(rename = "conversation_read")]
+    Read {
+        conversation_id: i32,
+        user_id: i32,
+        name: String,
+        last_read_message_id: Option<i32>,
+    },
+}
+
+/// Client -> Server commands
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientCommand {
+    /// The user is typing in a conversation
+    Typing { conversation_id: i32 },
+    /// The user has seen the latest messages in a conversation
+    Read { conversation_id: i32 },
 }
 
 /// Server -> Client push message
@@ -8,6 +8,8 @@
 //! - `NotificationServer` actor maintains user connections
 //! - `NotificationConnection` actor handles individual WebSocket connections
 //! - The dispatcher calls `broadcast_notification()` when creating notifications
+//! - With a Redis backplane (`crate::backplane`), broadcasts also reach users
+//!   connected to other instances
 //!
 //! ## Usage
 //!
@@ -15,19 +17,30 @@
 //! 2. Server registers connection and maps to user_id
 //! 3. When notifications are created, they're pushed to connected clients
 //! 4. Client receives JSON messages with notification data
+//!
+//! The same connection carries live conversation events (new messages,
+//! typing indicators and read receipts). Clients may send `typing` and
+//! `read` commands as JSON, e.g. `{"type":"typing","conversation_id":1}`.
+//!
+//! Clients that can't hold the WebSocket fall back to `/notifications/stream`,
+//! a Server-Sent Events stream carrying the same messages.
 
 pub mod connection;
 pub mod message;
 pub mod server;
+pub mod sse;
 
 use crate::middleware::ClientCtx;
 use actix::Addr;
-use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use actix_web::{error, get, web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
+use futures::StreamExt;
 use once_cell::sync::OnceCell;
 use std::time::Duration;
 
-pub use message::{BroadcastNotification, NotificationData};
+pub use message::{
+    BroadcastConversationEvent, BroadcastNotification, ConversationEvent, NotificationData,
+};
 pub use server::NotificationServer;
 
 /// Global notification server instance
@@ -51,9 +64,12 @@ pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 /// Client timeout - disconnect if no response for 30 seconds
 pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Minimum time between typing indicators relayed for one connection
+pub const TYPING_THROTTLE: Duration = Duration::from_secs(3);
+
 /// Configure notification WebSocket routes
 pub fn configure(conf: &mut web::ServiceConfig) {
-    conf.service(notifications_ws);
+    conf.service(notifications_ws).service(notifications_sse);
 }
 
 /// WebSocket endpoint for real-time notifications
@@ -81,6 +97,46 @@ pub async fn notifications_ws(
     ws::start(connection, &req, stream)
 }
 
+/// Server-Sent Events fallback for real-time notifications
+///
+/// GET /notifications/stream
+///
+/// Requires authentication. Sends the current unread count first, then the
+/// same JSON messages as the WebSocket, one per `data:` event.
+#[get("/notifications/stream")]
+pub async fn notifications_sse(
+    client: ClientCtx,
+    server: web::Data<Addr<NotificationServer>>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+
+    let unread = crate::notifications::count_unread_notifications(user_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    log::debug!("User {} connecting to notification event stream", user_id);
+
+    let stream = sse::SseConnection::start_stream(user_id, server.get_ref().clone());
+    let initial = sse::format_event(
+        &serde_json::json!({
+            "type": "unread_count",
+            "data": { "count": unread }
+        })
+        .to_string(),
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        // Stop nginx from buffering the stream
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(
+            futures::stream::once(async move { initial })
+                .chain(stream)
+                .map(Ok::<_, Error>),
+        ))
+}
+
 /// Broadcast a notification to a user via the notification server
 ///
 /// This function is called by the notification dispatcher when a new
@@ -102,6 +158,8 @@ pub async fn broadcast_notification(
         message: message_text.to_string(),
         url: url.map(|s| s.to_string()),
         created_at: chrono::Utc::now().to_rfc3339(),
+        actor_count: 1,
+        updated: false,
     };
 
     server.do_send(BroadcastNotification {
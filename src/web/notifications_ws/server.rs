@@ -4,8 +4,10 @@
 //! and broadcasts notifications to connected users in real-time.
 
 use super::message::{
-    BroadcastNotification, Connect, Disconnect, GetConnectionCount, NotificationPush,
+    BroadcastConversationEvent, BroadcastNotification, Connect, Disconnect, GetConnectionCount,
+    NotificationPush, NotificationRelay,
 };
+use crate::backplane::{get_backplane, NOTIFICATION_CHANNEL, RESUBSCRIBE_DELAY};
 use actix::prelude::*;
 use std::collections::HashMap;
 
@@ -50,6 +52,31 @@ impl NotificationServer {
             }
         }
     }
+
+    /// Send a message to users connected to this instance, and pass it on to
+    /// the other instances
+    fn send_to_users(&self, user_ids: &[i32], message: String) {
+        if let Some(backplane) = get_backplane() {
+            backplane.publish(
+                NOTIFICATION_CHANNEL,
+                &NotificationRelay {
+                    user_ids: user_ids.to_vec(),
+                    message: message.clone(),
+                },
+            );
+        }
+
+        for user_id in user_ids {
+            self.send_to_user(*user_id, message.clone());
+        }
+    }
+
+    /// Receive notifications from other instances, when there are any
+    fn subscribe_backplane(&self, ctx: &mut Context<Self>) {
+        if let Some(backplane) = get_backplane() {
+            ctx.add_stream(backplane.subscribe::<NotificationRelay>(NOTIFICATION_CHANNEL));
+        }
+    }
 }
 
 impl Default for NotificationServer {
@@ -63,10 +90,25 @@ impl Actor for NotificationServer {
 
     fn started(&mut self, ctx: &mut Self::Context) {
         ctx.set_mailbox_capacity(64);
+        self.subscribe_backplane(ctx);
         log::info!("NotificationServer started");
     }
 }
 
+/// Handle notifications from other instances
+impl StreamHandler<NotificationRelay> for NotificationServer {
+    fn handle(&mut self, msg: NotificationRelay, _: &mut Context<Self>) {
+        for user_id in &msg.user_ids {
+            self.send_to_user(*user_id, msg.message.clone());
+        }
+    }
+
+    fn finished(&mut self, ctx: &mut Context<Self>) {
+        log::warn!("NotificationServer lost its backplane subscription.");
+        ctx.run_later(RESUBSCRIBE_DELAY, |act, ctx| act.subscribe_backplane(ctx));
+    }
+}
+
 /// Handle new connections
 impl Handler<Connect> for NotificationServer {
     type Result = usize;
@@ -137,12 +179,27 @@ impl Handler<BroadcastNotification> for NotificationServer {
         });
 
         if let Ok(message) = serde_json::to_string(&json) {
-            self.send_to_user(msg.user_id, message);
+            self.send_to_users(&[msg.user_id], message);
             log::debug!("Broadcasted notification to user {}", msg.user_id);
         }
     }
 }
 
+/// Handle conversation event broadcasts
+impl Handler<BroadcastConversationEvent> for NotificationServer {
+    type Result = ();
+
+    fn handle(&mut self, msg: BroadcastConversationEvent, _: &mut Context<Self>) {
+        if let Ok(message) = serde_json::to_string(&msg.event) {
+            self.send_to_users(&msg.user_ids, message);
+            log::debug!(
+                "Broadcasted conversation event to {} users",
+                msg.user_ids.len()
+            );
+        }
+    }
+}
+
 /// Get connection count (for monitoring)
 impl Handler<GetConnectionCount> for NotificationServer {
     type Result = usize;
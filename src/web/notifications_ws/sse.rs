@@ -0,0 +1,143 @@
+//! Server-Sent Events fallback for notification clients
+//!
+//! Browsers that can't hold the WebSocket open (old browsers, proxies that
+//! strip the upgrade) read the same JSON messages from a long-lived
+//! `text/event-stream` response instead. The stream is receive-only, so
+//! typing and read commands still need the WebSocket.
+
+use super::message::{Connect, Disconnect, NotificationPush};
+use super::server::NotificationServer;
+use actix::*;
+use actix_web::web::Bytes;
+use futures::channel::mpsc;
+use std::time::Duration;
+
+/// How often a comment line is sent to keep proxies from closing the stream,
+/// and to notice clients that went away
+pub const SSE_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Browsers wait this long before reconnecting a dropped stream
+const SSE_RETRY_MS: u32 = 5000;
+
+/// Format one message as an SSE `data:` event
+pub fn format_event(data: &str) -> Bytes {
+    // Newlines inside data would end the event early
+    let mut event = String::with_capacity(data.len() + 8);
+    for line in data.lines() {
+        event.push_str("data: ");
+        event.push_str(line);
+        event.push('\n');
+    }
+    event.push('\n');
+    Bytes::from(event)
+}
+
+/// Registers one event stream with the notification server and forwards
+/// everything pushed to it into the response body
+pub struct SseConnection {
+    /// Connection ID (assigned by server)
+    pub id: usize,
+    /// User ID for this connection
+    pub user_id: i32,
+    /// Address of the notification server
+    pub server: Addr<NotificationServer>,
+    /// Feeds the response body; closed when the client disconnects
+    pub sender: mpsc::UnboundedSender<Bytes>,
+}
+
+impl SseConnection {
+    /// Start a connection for the user, returning the body stream to respond with
+    pub fn start_stream(
+        user_id: i32,
+        server: Addr<NotificationServer>,
+    ) -> mpsc::UnboundedReceiver<Bytes> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        // Tell the browser how soon to reconnect if the stream drops
+        let _ = sender.unbounded_send(Bytes::from(format!("retry: {}\n\n", SSE_RETRY_MS)));
+
+        SseConnection {
+            id: 0,
+            user_id,
+            server,
+            sender,
+        }
+        .start();
+
+        receiver
+    }
+
+    /// Queue bytes for the client, stopping once the client has gone
+    fn send(&self, bytes: Bytes, ctx: &mut Context<Self>) {
+        if self.sender.unbounded_send(bytes).is_err() {
+            ctx.stop();
+        }
+    }
+}
+
+impl Actor for SseConnection {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(SSE_KEEPALIVE_INTERVAL, |act, ctx| {
+            act.send(Bytes::from_static(b": keepalive\n\n"), ctx);
+        });
+
+        self.server
+            .send(Connect {
+                addr: ctx.address().recipient(),
+                user_id: self.user_id,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(id) => {
+                        act.id = id;
+                        log::debug!(
+                            "Notification event stream established: id={}, user={}",
+                            id,
+                            act.user_id
+                        );
+                    }
+                    Err(err) => {
+                        log::warn!("Failed to register notification event stream: {:?}", err);
+                        ctx.stop();
+                    }
+                }
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        // Notify server of disconnect
+        self.server.do_send(Disconnect { id: self.id });
+        Running::Stop
+    }
+}
+
+/// Handle messages pushed from the notification server
+impl Handler<NotificationPush> for SseConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: NotificationPush, ctx: &mut Self::Context) {
+        self.send(format_event(&msg.0), ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_event, Bytes};
+
+    #[test]
+    fn test_format_event() {
+        assert_eq!(
+            format_event(r#"{"type":"notification"}"#),
+            Bytes::from_static(b"data: {\"type\":\"notification\"}\n\n")
+        );
+        assert_eq!(
+            format_event("one\ntwo"),
+            Bytes::from_static(b"data: one\ndata: two\n\n")
+        );
+    }
+}
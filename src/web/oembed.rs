@@ -0,0 +1,371 @@
+//! oEmbed provider for threads and posts.
+//!
+//! `/oembed?url=` describes a thread or post on this site as an oEmbed "rich"
+//! embed, so that other sites, and our own link unfurler, can show a preview
+//! card for it. Only content a guest could read is described; anything else is
+//! not found, whoever asks. Pages on other sites may fetch embeds from the
+//! browser if their origin is listed in `oembed.allowed_origins`.
+//!
+//! See <https://oembed.com/>.
+
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::orm::{posts, threads};
+use crate::web::post::get_post_and_author_for_template;
+use actix_web::{error, get, http::header, web, Error, HttpRequest, HttpResponse};
+use sea_orm::{DbErr, EntityTrait};
+use serde::{Deserialize, Serialize};
+
+/// Width of an embed when the consumer does not ask for less
+const DEFAULT_WIDTH: u32 = 500;
+
+/// Height of an embed when the consumer does not ask for less
+const DEFAULT_HEIGHT: u32 = 200;
+
+/// Longest excerpt of post content in an embed, in characters
+const EXCERPT_LENGTH: usize = 300;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(view_oembed);
+}
+
+/// The thread or post a URL points at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmbedTarget {
+    Thread(i32),
+    Post(i32),
+}
+
+impl EmbedTarget {
+    /// Recognises thread and post URLs on the site at `base_url`.
+    pub fn from_url(url: &url::Url, base_url: &url::Url) -> Option<Self> {
+        if !matches!(url.scheme(), "http" | "https") || url.host_str() != base_url.host_str() {
+            return None;
+        }
+
+        let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+        match segments.as_slice() {
+            ["threads", thread_id] => thread_id.parse().ok().map(Self::Thread),
+            ["threads", thread_id, rest] => match rest.strip_prefix("post-") {
+                Some(post_id) => post_id.parse().ok().map(Self::Post),
+                None if rest.starts_with("page-") => thread_id.parse().ok().map(Self::Thread),
+                None => None,
+            },
+            ["posts", post_id] => post_id.parse().ok().map(Self::Post),
+            _ => None,
+        }
+    }
+}
+
+/// What an embed shows of a thread or post.
+#[derive(Debug)]
+pub struct Embed {
+    pub title: String,
+    pub author_id: Option<i32>,
+    pub author_name: Option<String>,
+    /// Plain text from the start of the post
+    pub excerpt: String,
+    /// Address of the thread or post
+    pub url: String,
+}
+
+/// Plain text of a post's BBCode, cut to `EXCERPT_LENGTH` characters.
+fn excerpt(content: &str) -> String {
+    let html = crate::bbcode::parse(content);
+    let text: String = scraper::Html::parse_fragment(&html)
+        .root_element()
+        .text()
+        .collect();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if text.chars().count() <= EXCERPT_LENGTH {
+        text
+    } else {
+        let truncated: String = text.chars().take(EXCERPT_LENGTH).collect();
+        format!("{}...", truncated.trim_end())
+    }
+}
+
+/// A thread `client` may read.
+async fn readable_thread(
+    client: &ClientCtx,
+    thread_id: i32,
+) -> Result<Option<threads::Model>, DbErr> {
+    Ok(threads::Entity::find_by_id(thread_id)
+        .one(get_db_pool())
+        .await?
+        .filter(|thread| {
+            thread.deleted_at.is_none() && client.can_view_thread(&thread.id, &thread.forum_id)
+        }))
+}
+
+/// The excerpt and author of an approved, undeleted post.
+async fn readable_post(
+    post_id: i32,
+) -> Result<Option<(String, Option<crate::user::Profile>)>, DbErr> {
+    let db = get_db_pool();
+    let approved = posts::Entity::find_by_id(post_id)
+        .one(db)
+        .await?
+        .is_some_and(|post| post.moderation_status == posts::ModerationStatus::Approved);
+    if !approved {
+        return Ok(None);
+    }
+
+    Ok(get_post_and_author_for_template(db, post_id)
+        .await?
+        .filter(|(post, _)| post.deleted_at.is_none())
+        .map(|(post, author)| (excerpt(&post.content.unwrap_or_default()), author)))
+}
+
+/// Describes a thread or post, if `client` may read it.
+pub async fn find_embed(client: &ClientCtx, target: EmbedTarget) -> Result<Option<Embed>, DbErr> {
+    let base_url = crate::notifications::dispatcher::get_base_url();
+
+    let (thread, post_id, url) = match target {
+        EmbedTarget::Thread(thread_id) => {
+            let Some(thread) = readable_thread(client, thread_id).await? else {
+                return Ok(None);
+            };
+            let url = format!("{}/threads/{}/", base_url, thread.id);
+            let first_post_id = thread.first_post_id;
+            (thread, first_post_id, url)
+        }
+        EmbedTarget::Post(post_id) => {
+            let Some(post) = posts::Entity::find_by_id(post_id)
+                .one(get_db_pool())
+                .await?
+            else {
+                return Ok(None);
+            };
+            let Some(thread) = readable_thread(client, post.thread_id).await? else {
+                return Ok(None);
+            };
+            let url = format!("{}/threads/{}/post-{}", base_url, thread.id, post_id);
+            (thread, Some(post_id), url)
+        }
+    };
+
+    let (excerpt, author) = match post_id {
+        Some(post_id) => match readable_post(post_id).await? {
+            Some(post) => post,
+            // A post that is hidden can't be embedded, but a thread whose
+            // first post is hidden can, without the excerpt.
+            None if matches!(target, EmbedTarget::Post(_)) => return Ok(None),
+            None => (String::new(), None),
+        },
+        None => (String::new(), None),
+    };
+
+    Ok(Some(Embed {
+        title: thread.title,
+        author_id: author.as_ref().map(|author| author.id),
+        author_name: author.map(|author| author.name),
+        excerpt,
+        url,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct OembedQuery {
+    url: String,
+    /// Only `json` is supported
+    format: Option<String>,
+    maxwidth: Option<u32>,
+    maxheight: Option<u32>,
+}
+
+/// An oEmbed 1.0 "rich" response.
+#[derive(Debug, Serialize)]
+pub struct OembedResponse {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub version: &'static str,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_url: Option<String>,
+    pub provider_name: String,
+    pub provider_url: String,
+    pub cache_age: u64,
+    pub html: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Escape text for inclusion in embed HTML
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#x27;")
+}
+
+impl OembedResponse {
+    pub fn new(
+        embed: Embed,
+        provider_name: String,
+        maxwidth: Option<u32>,
+        maxheight: Option<u32>,
+    ) -> Self {
+        let provider_url = crate::notifications::dispatcher::get_base_url();
+        let author_url = embed
+            .author_id
+            .map(|id| format!("{}/members/{}/", provider_url, id));
+
+        let mut html = format!(
+            "<blockquote class=\"dumpster-embed\"><p><a href=\"{}\">{}</a></p>",
+            escape_html(&embed.url),
+            escape_html(&embed.title)
+        );
+        if !embed.excerpt.is_empty() {
+            html.push_str(&format!("<p>{}</p>", escape_html(&embed.excerpt)));
+        }
+        html.push_str("<footer>");
+        if let (Some(name), Some(url)) = (&embed.author_name, &author_url) {
+            html.push_str(&format!(
+                "<a href=\"{}\">{}</a> &middot; ",
+                escape_html(url),
+                escape_html(name)
+            ));
+        }
+        html.push_str(&format!(
+            "<a href=\"{}\">{}</a></footer></blockquote>",
+            escape_html(&provider_url),
+            escape_html(&provider_name)
+        ));
+
+        Self {
+            kind: "rich",
+            version: "1.0",
+            title: embed.title,
+            author_name: embed.author_name,
+            author_url,
+            provider_name,
+            provider_url,
+            cache_age: crate::app_config::oembed().cache_age_seconds,
+            html,
+            width: maxwidth.unwrap_or(DEFAULT_WIDTH).min(DEFAULT_WIDTH),
+            height: maxheight.unwrap_or(DEFAULT_HEIGHT).min(DEFAULT_HEIGHT),
+        }
+    }
+}
+
+/// The `Access-Control-Allow-Origin` value for a request from `origin`.
+fn allowed_origin(origin: &str, allowed: &[String]) -> Option<String> {
+    allowed
+        .iter()
+        .any(|allowed| allowed == "*" || allowed.trim_end_matches('/') == origin)
+        .then(|| origin.to_owned())
+}
+
+/// GET /oembed?url=&format=json&maxwidth=&maxheight= - Embed for a thread or
+/// post URL
+#[get("/oembed")]
+async fn view_oembed(
+    req: HttpRequest,
+    client: ClientCtx,
+    query: web::Query<OembedQuery>,
+) -> Result<HttpResponse, Error> {
+    let ip = crate::ip::extract_client_ip(&req)
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    if let Err(e) = crate::rate_limit::check_api_rate_limit(&ip, &client.get_groups()).await {
+        return Err(error::ErrorTooManyRequests(format!(
+            "Too many requests. Please try again in {} seconds.",
+            e.retry_after_seconds
+        )));
+    }
+
+    // The spec has providers answer 501 for formats they don't serve.
+    if query
+        .format
+        .as_deref()
+        .is_some_and(|format| format != "json")
+    {
+        return Err(error::ErrorNotImplemented("Only JSON is supported."));
+    }
+
+    let base_url = url::Url::parse(&crate::notifications::dispatcher::get_base_url())
+        .map_err(error::ErrorInternalServerError)?;
+    let target = url::Url::parse(&query.url)
+        .ok()
+        .and_then(|url| EmbedTarget::from_url(&url, &base_url))
+        .ok_or_else(|| error::ErrorNotFound("Not a thread or post on this site."))?;
+
+    let embed = find_embed(&client.as_guest().await, target)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Thread or post not found."))?;
+
+    let mut response = HttpResponse::Ok();
+    if let Some(origin) = req
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|origin| origin.to_str().ok())
+        .and_then(|origin| allowed_origin(origin, &crate::app_config::oembed().allowed_origins))
+    {
+        response
+            .insert_header((header::ACCESS_CONTROL_ALLOW_ORIGIN, origin))
+            .insert_header((header::VARY, "Origin"));
+    }
+
+    Ok(response.json(OembedResponse::new(
+        embed,
+        client.site_title(),
+        query.maxwidth,
+        query.maxheight,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_target_from_url() {
+        let base = url::Url::parse("https://forum.example.com").unwrap();
+        let target = |url: &str| EmbedTarget::from_url(&url::Url::parse(url).unwrap(), &base);
+
+        assert_eq!(
+            target("https://forum.example.com/threads/12/"),
+            Some(EmbedTarget::Thread(12))
+        );
+        assert_eq!(
+            target("http://forum.example.com/threads/12/page-3"),
+            Some(EmbedTarget::Thread(12))
+        );
+        assert_eq!(
+            target("https://forum.example.com/threads/12/post-40#post-40"),
+            Some(EmbedTarget::Post(40))
+        );
+        assert_eq!(
+            target("https://forum.example.com/posts/40"),
+            Some(EmbedTarget::Post(40))
+        );
+        assert_eq!(target("https://forum.example.com/threads/12/unread"), None);
+        assert_eq!(target("https://forum.example.com/posts/40/edit"), None);
+        assert_eq!(target("https://elsewhere.example.com/threads/12/"), None);
+        assert_eq!(target("ftp://forum.example.com/threads/12/"), None);
+    }
+
+    #[test]
+    fn test_allowed_origin() {
+        let allowed = vec!["https://blog.example.com/".to_owned()];
+        assert_eq!(
+            allowed_origin("https://blog.example.com", &allowed),
+            Some("https://blog.example.com".to_owned())
+        );
+        assert_eq!(allowed_origin("https://evil.example.com", &allowed), None);
+        assert_eq!(allowed_origin("https://blog.example.com", &[]), None);
+        assert!(allowed_origin("https://any.example.com", &["*".to_owned()]).is_some());
+    }
+
+    #[test]
+    fn test_excerpt_is_plain_text() {
+        assert_eq!(excerpt("[b]Hello[/b]   <world>"), "Hello <world>");
+        assert!(excerpt(&"word ".repeat(200)).ends_with("..."));
+    }
+}
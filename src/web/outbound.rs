@@ -0,0 +1,118 @@
+//! Following links to other sites, and the clicks counted on them.
+//!
+//! `/goto` is where rewritten post links point; see [`crate::outbound`]. A
+//! thread's author, and moderators who can edit posts, can see how often each
+//! link in it was followed at `/threads/{id}/links`.
+
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::orm::threads;
+use crate::outbound::{self, LinkClicks};
+use actix_web::{error, get, web, Error, HttpResponse, Responder};
+use askama_actix::{Template, TemplateToResponse};
+use sea_orm::EntityTrait;
+use serde::Deserialize;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(view_goto).service(view_thread_links);
+}
+
+#[derive(Deserialize)]
+struct GotoQuery {
+    url: String,
+    thread: i32,
+    sig: String,
+}
+
+#[derive(Template)]
+#[template(path = "goto.html")]
+struct GotoTemplate {
+    client: ClientCtx,
+    thread_id: i32,
+    url: String,
+    host: String,
+}
+
+#[derive(Template)]
+#[template(path = "thread_links.html")]
+struct ThreadLinksTemplate {
+    client: ClientCtx,
+    thread: threads::Model,
+    links: Vec<LinkClicks>,
+}
+
+/// Whether the viewer may see which links in a thread were clicked.
+pub fn can_view_link_clicks(client: &ClientCtx, thread: &threads::Model) -> bool {
+    match client.get_id() {
+        Some(user_id) => thread.user_id == Some(user_id) || client.can("moderate.post.edit"),
+        None => false,
+    }
+}
+
+/// GET /goto?url=&thread=&sig= - Follow a link posted in a thread
+#[get("/goto")]
+async fn view_goto(client: ClientCtx, query: web::Query<GotoQuery>) -> Result<HttpResponse, Error> {
+    let config = crate::app_config::outbound_links();
+    let query = query.into_inner();
+    if !outbound::verify(query.thread, &query.url, &query.sig) {
+        return Err(error::ErrorBadRequest("This link is invalid."));
+    }
+
+    let url = url::Url::parse(&query.url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .ok_or_else(|| error::ErrorBadRequest("This link is invalid."))?;
+    let host = url.host_str().unwrap_or_default().to_string();
+
+    if config.track_clicks {
+        if let Err(e) = outbound::record_click(query.thread, url.as_str()).await {
+            log::error!("Failed to record link click: {}", e);
+        }
+    }
+
+    if config.warn_untrusted && !outbound::is_trusted(&host, &config.trusted_domains) {
+        return Ok(GotoTemplate {
+            client,
+            thread_id: query.thread,
+            url: url.to_string(),
+            host,
+        }
+        .to_response());
+    }
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", url.as_str()))
+        .append_header(("Referrer-Policy", "origin"))
+        .finish())
+}
+
+/// GET /threads/{id}/links - Clicks on links in a thread
+#[get("/threads/{thread_id}/links")]
+async fn view_thread_links(
+    client: ClientCtx,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    let thread = threads::Entity::find_by_id(path.into_inner())
+        .one(get_db_pool())
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Thread not found."))?;
+    if !client.can_view_thread(&thread.id, &thread.forum_id)
+        || !can_view_link_clicks(&client, &thread)
+    {
+        return Err(error::ErrorForbidden(
+            "You do not have permission to view this thread's link clicks.",
+        ));
+    }
+
+    let links = outbound::thread_clicks(thread.id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(ThreadLinksTemplate {
+        client,
+        thread,
+        links,
+    }
+    .to_response())
+}
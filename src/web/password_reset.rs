@@ -94,7 +94,7 @@ pub async fn request_reset(
         .unwrap_or_else(|| "unknown".to_string());
 
     // Rate limiting - prevent abuse
-    if let Err(e) = crate::rate_limit::check_password_reset_rate_limit(&ip) {
+    if let Err(e) = crate::rate_limit::check_password_reset_rate_limit(&ip).await {
         log::warn!("Password reset rate limit exceeded for IP: {}", ip);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many password reset requests. Please try again in {} seconds.",
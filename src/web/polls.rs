@@ -1,155 +1,388 @@
 //! Poll voting endpoints
+//!
+//! Polls allow up to `max_choices` options per voter and stop taking votes
+//! at `closes_at`. Voters can change their vote until then if the poll
+//! allows it. Results are shown according to `results_visibility`, and with
+//! `public_votes` the names of each option's voters are shown with them.
+//! Option vote counts are kept by the `trigger_poll_vote_count` trigger.
+//!
+//! Ranked polls have voters put options in order instead, and are decided
+//! by instant runoff (see [`crate::runoff`]). Their count is stored when
+//! they close, so the result doesn't change afterwards.
 
 use crate::db::get_db_pool;
 use crate::middleware::ClientCtx;
-use crate::orm::{poll_options, poll_votes, polls};
+use crate::orm::{poll_options, poll_runoff_results, poll_votes, polls, user_names};
+use crate::runoff::{self, Round, Runoff};
 use actix_web::{error, post, web, Error, HttpResponse, Responder};
-use sea_orm::{entity::*, query::*, sea_query::Expr, ColumnTrait, EntityTrait};
-use serde::Deserialize;
+use chrono::NaiveDateTime;
+use sea_orm::{entity::*, query::*, ConnectionTrait, DbBackend, DbErr, Statement};
+use std::collections::HashMap;
+
+/// Voters pick up to `max_choices` options
+pub const POLL_CHOICE: &str = "choice";
+/// Voters rank options, and the winner is found by instant runoff
+pub const POLL_RANKED: &str = "ranked";
+
+/// Results are shown to everyone
+pub const RESULTS_ALWAYS: &str = "always";
+/// Results are shown to those who have voted, and to everyone once closed
+pub const RESULTS_AFTER_VOTE: &str = "after_vote";
+/// Results are shown to everyone once the poll closes, and not before
+pub const RESULTS_AFTER_CLOSE: &str = "after_close";
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
     conf.service(vote_on_poll);
 }
 
-#[derive(Deserialize)]
-pub struct VoteFormData {
-    pub csrf_token: String,
-    #[serde(default)]
-    pub option_ids: Vec<i32>,
+/// Whether a poll's results may be shown to someone
+pub fn can_see_results(results_visibility: &str, has_voted: bool, is_closed: bool) -> bool {
+    match results_visibility {
+        RESULTS_ALWAYS => true,
+        RESULTS_AFTER_CLOSE => is_closed,
+        _ => has_voted || is_closed,
+    }
 }
 
-#[post("/polls/{poll_id}/vote")]
-pub async fn vote_on_poll(
-    client: ClientCtx,
-    cookies: actix_session::Session,
-    path: web::Path<i32>,
-    form: web::Form<VoteFormData>,
-) -> Result<impl Responder, Error> {
-    // Validate CSRF token
-    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+/// Whether a poll has stopped taking votes
+pub fn is_closed(poll: &polls::Model, now: NaiveDateTime) -> bool {
+    poll.closes_at.is_some_and(|closes_at| closes_at <= now)
+}
 
-    // Require authentication
-    let user_id = client.require_login()?;
+#[derive(Debug)]
+pub enum VoteError {
+    NotFound,
+    Closed,
+    NoOptions,
+    TooManyOptions(i32),
+    InvalidOption,
+    AlreadyVoted,
+    DuplicateRank,
+    Database(DbErr),
+}
 
-    let poll_id = path.into_inner();
-    let db = get_db_pool();
+impl From<DbErr> for VoteError {
+    fn from(e: DbErr) -> Self {
+        VoteError::Database(e)
+    }
+}
 
-    // Fetch the poll
-    let poll = polls::Entity::find_by_id(poll_id)
-        .one(db)
-        .await
-        .map_err(error::ErrorInternalServerError)?
-        .ok_or_else(|| error::ErrorNotFound("Poll not found."))?;
-
-    // Check if poll is closed
-    if let Some(closes_at) = poll.closes_at {
-        if closes_at < chrono::Utc::now().naive_utc() {
-            return Err(error::ErrorForbidden("This poll is closed."));
+impl std::fmt::Display for VoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VoteError::NotFound => write!(f, "Poll not found."),
+            VoteError::Closed => write!(f, "This poll is closed."),
+            VoteError::NoOptions => write!(f, "Please select at least one option."),
+            VoteError::TooManyOptions(max) => {
+                write!(f, "You can only select up to {} option(s).", max)
+            }
+            VoteError::InvalidOption => write!(f, "Invalid poll option(s) selected."),
+            VoteError::AlreadyVoted => write!(
+                f,
+                "You have already voted and this poll does not allow changing your vote."
+            ),
+            VoteError::DuplicateRank => write!(f, "Each option needs a different rank."),
+            VoteError::Database(e) => write!(f, "Database error: {}", e),
         }
     }
+}
+
+/// Record a member's vote, replacing any earlier one if the poll allows it.
+/// On ranked polls `option_ids` is the ballot, most preferred first.
+/// Returns the poll.
+pub async fn cast_vote(
+    poll_id: i32,
+    user_id: i32,
+    option_ids: &[i32],
+) -> Result<polls::Model, VoteError> {
+    let txn = get_db_pool().begin().await?;
+
+    // Locks the poll, so concurrent ballots from anyone are counted one at a time
+    let poll = polls::Entity::find_by_id(poll_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(VoteError::NotFound)?;
 
-    // Validate option_ids
-    if form.option_ids.is_empty() {
-        return Err(error::ErrorBadRequest("Please select at least one option."));
+    if is_closed(&poll, chrono::Utc::now().naive_utc()) {
+        return Err(VoteError::Closed);
     }
 
-    if form.option_ids.len() > poll.max_choices as usize {
-        return Err(error::ErrorBadRequest(format!(
-            "You can only select up to {} option(s).",
-            poll.max_choices
-        )));
+    let ranked = poll.poll_type == POLL_RANKED;
+    let mut option_ids = option_ids.to_vec();
+    if ranked {
+        let mut seen = std::collections::HashSet::new();
+        option_ids.retain(|id| seen.insert(*id));
+    } else {
+        option_ids.sort_unstable();
+        option_ids.dedup();
+    }
+    if option_ids.is_empty() {
+        return Err(VoteError::NoOptions);
+    }
+    // Ranked ballots may order every option
+    if !ranked && option_ids.len() > poll.max_choices as usize {
+        return Err(VoteError::TooManyOptions(poll.max_choices));
     }
 
-    // Verify all selected options belong to this poll
     let valid_options = poll_options::Entity::find()
         .filter(poll_options::Column::PollId.eq(poll_id))
-        .filter(poll_options::Column::Id.is_in(form.option_ids.clone()))
-        .all(db)
-        .await
-        .map_err(error::ErrorInternalServerError)?;
-
-    if valid_options.len() != form.option_ids.len() {
-        return Err(error::ErrorBadRequest("Invalid poll option(s) selected."));
+        .filter(poll_options::Column::Id.is_in(option_ids.clone()))
+        .count(&txn)
+        .await?;
+    if valid_options as usize != option_ids.len() {
+        return Err(VoteError::InvalidOption);
     }
 
-    // Check if user has already voted
     let existing_votes = poll_votes::Entity::find()
         .filter(poll_votes::Column::PollId.eq(poll_id))
         .filter(poll_votes::Column::UserId.eq(user_id))
-        .all(db)
-        .await
-        .map_err(error::ErrorInternalServerError)?;
-
-    let has_voted = !existing_votes.is_empty();
-
-    // If user has voted and poll doesn't allow changing vote, reject
-    if has_voted && !poll.allow_change_vote {
-        return Err(error::ErrorForbidden(
-            "You have already voted and this poll does not allow changing your vote.",
-        ));
-    }
-
-    // Begin transaction
-    let txn = db.begin().await.map_err(error::ErrorInternalServerError)?;
-
-    // If changing vote, delete old votes
-    if has_voted {
-        // Decrement vote counts for old options
-        for old_vote in &existing_votes {
-            poll_options::Entity::update_many()
-                .col_expr(
-                    poll_options::Column::VoteCount,
-                    Expr::col(poll_options::Column::VoteCount).sub(1),
-                )
-                .filter(poll_options::Column::Id.eq(old_vote.option_id))
-                .exec(&txn)
-                .await
-                .map_err(error::ErrorInternalServerError)?;
+        .all(&txn)
+        .await?;
+    if !existing_votes.is_empty() {
+        if !poll.allow_change_vote {
+            return Err(VoteError::AlreadyVoted);
         }
-
-        // Delete old votes
         poll_votes::Entity::delete_many()
             .filter(poll_votes::Column::PollId.eq(poll_id))
             .filter(poll_votes::Column::UserId.eq(user_id))
             .exec(&txn)
-            .await
-            .map_err(error::ErrorInternalServerError)?;
+            .await?;
     }
 
-    // Insert new votes
     let now = chrono::Utc::now().naive_utc();
-    for option_id in &form.option_ids {
-        let vote = poll_votes::ActiveModel {
+    poll_votes::Entity::insert_many(option_ids.iter().enumerate().map(|(i, option_id)| {
+        poll_votes::ActiveModel {
             poll_id: Set(poll_id),
             option_id: Set(*option_id),
             user_id: Set(user_id),
+            rank: Set(ranked.then_some(i as i32 + 1)),
             created_at: Set(now),
             ..Default::default()
+        }
+    }))
+    .exec(&txn)
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(poll)
+}
+
+/// Names of the members who voted for each option of a poll, by option id,
+/// in the order they voted
+pub async fn voters_by_option(poll_id: i32) -> Result<HashMap<i32, Vec<String>>, DbErr> {
+    let db = get_db_pool();
+    let votes = poll_votes::Entity::find()
+        .filter(poll_votes::Column::PollId.eq(poll_id))
+        .order_by_asc(poll_votes::Column::CreatedAt)
+        .order_by_asc(poll_votes::Column::Id)
+        .all(db)
+        .await?;
+
+    let names: HashMap<i32, String> = user_names::Entity::find()
+        .filter(user_names::Column::UserId.is_in(votes.iter().map(|v| v.user_id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|name| (name.user_id, name.name))
+        .collect();
+
+    let mut voters: HashMap<i32, Vec<String>> = HashMap::new();
+    for vote in votes {
+        if let Some(name) = names.get(&vote.user_id) {
+            voters.entry(vote.option_id).or_default().push(name.clone());
+        }
+    }
+    Ok(voters)
+}
+
+/// Every ballot cast in a ranked poll, each most preferred first
+async fn ballots(poll_id: i32) -> Result<Vec<Vec<i32>>, DbErr> {
+    let votes = poll_votes::Entity::find()
+        .filter(poll_votes::Column::PollId.eq(poll_id))
+        .order_by_asc(poll_votes::Column::UserId)
+        .order_by_asc(poll_votes::Column::Rank)
+        .all(get_db_pool())
+        .await?;
+
+    let mut ballots: Vec<Vec<i32>> = Vec::new();
+    let mut last_user_id = None;
+    for vote in votes {
+        if last_user_id != Some(vote.user_id) {
+            ballots.push(Vec::new());
+            last_user_id = Some(vote.user_id);
+        }
+        if let Some(ballot) = ballots.last_mut() {
+            ballot.push(vote.option_id);
+        }
+    }
+    Ok(ballots)
+}
+
+/// The instant-runoff count of a ranked poll. `options` are its option ids
+/// in listed order. Open polls are counted afresh; closed ones are counted
+/// once and the stored count is returned from then on.
+pub async fn runoff_results(poll: &polls::Model, options: &[i32]) -> Result<Runoff, DbErr> {
+    let db = get_db_pool();
+    let closed = is_closed(poll, chrono::Utc::now().naive_utc());
+
+    if closed {
+        if let Some(stored) = poll_runoff_results::Entity::find_by_id(poll.id)
+            .one(db)
+            .await?
+        {
+            let rounds: Vec<Round> = serde_json::from_value(stored.rounds)
+                .map_err(|e| DbErr::Custom(format!("Invalid stored runoff: {}", e)))?;
+            return Ok(Runoff {
+                rounds,
+                winner: stored.winner_option_id,
+                ballots: stored.ballot_count as i64,
+            });
+        }
+    }
+
+    let runoff = runoff::tabulate(options, &ballots(poll.id).await?);
+
+    if closed {
+        let rounds = serde_json::to_value(&runoff.rounds)
+            .map_err(|e| DbErr::Custom(format!("Failed to store runoff: {}", e)))?;
+        // Another request may have stored it first; both counts are the same
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO poll_runoff_results (poll_id, rounds, winner_option_id, ballot_count)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (poll_id) DO NOTHING"#,
+            vec![
+                poll.id.into(),
+                rounds.into(),
+                runoff.winner.into(),
+                (runoff.ballots as i32).into(),
+            ],
+        ))
+        .await?;
+    }
+
+    Ok(runoff)
+}
+
+/// A vote as submitted. Each ticked option repeats `option_ids`, which
+/// `web::Form` can't collect, so the body is parsed here.
+pub struct VoteFormData {
+    pub csrf_token: String,
+    pub option_ids: Vec<i32>,
+    /// Ranked ballots: each ranked option's id and rank, from `rank_{id}`
+    /// fields. Options left blank aren't ranked.
+    pub ranks: Vec<(i32, i32)>,
+}
+
+impl VoteFormData {
+    fn parse(body: &[u8]) -> Self {
+        let mut form = VoteFormData {
+            csrf_token: String::new(),
+            option_ids: Vec::new(),
+            ranks: Vec::new(),
         };
-        poll_votes::Entity::insert(vote)
-            .exec(&txn)
-            .await
-            .map_err(error::ErrorInternalServerError)?;
-
-        // Increment vote count for this option
-        poll_options::Entity::update_many()
-            .col_expr(
-                poll_options::Column::VoteCount,
-                Expr::col(poll_options::Column::VoteCount).add(1),
-            )
-            .filter(poll_options::Column::Id.eq(*option_id))
-            .exec(&txn)
-            .await
-            .map_err(error::ErrorInternalServerError)?;
+        for (key, value) in url::form_urlencoded::parse(body) {
+            match key.as_ref() {
+                "csrf_token" => form.csrf_token = value.into_owned(),
+                "option_ids" => form.option_ids.extend(value.parse::<i32>().ok()),
+                key => {
+                    let option_id = key.strip_prefix("rank_").and_then(|id| id.parse().ok());
+                    if let (Some(option_id), Ok(rank)) = (option_id, value.parse::<i32>()) {
+                        form.ranks.push((option_id, rank));
+                    }
+                }
+            }
+        }
+        form
     }
 
-    // Commit transaction
-    txn.commit()
-        .await
-        .map_err(error::ErrorInternalServerError)?;
+    /// The options voted for. On a ranked ballot they're ordered by rank,
+    /// and two options given the same rank are refused.
+    fn ballot(&self) -> Result<Vec<i32>, VoteError> {
+        if self.ranks.is_empty() {
+            return Ok(self.option_ids.clone());
+        }
+        let mut ranks = self.ranks.clone();
+        ranks.sort_unstable_by_key(|(_, rank)| *rank);
+        if ranks.windows(2).any(|pair| pair[0].1 == pair[1].1) {
+            return Err(VoteError::DuplicateRank);
+        }
+        Ok(ranks.into_iter().map(|(option_id, _)| option_id).collect())
+    }
+}
+
+#[post("/polls/{poll_id}/vote")]
+pub async fn vote_on_poll(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    body: web::Bytes,
+) -> Result<impl Responder, Error> {
+    let form = VoteFormData::parse(&body);
+
+    // Validate CSRF token
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    // Require authentication
+    let user_id = client.require_login()?;
+
+    let ballot = form.ballot();
+    let vote = match ballot {
+        Ok(ballot) => cast_vote(path.into_inner(), user_id, &ballot).await,
+        Err(e) => Err(e),
+    };
+    let poll = vote.map_err(|e| match e {
+        VoteError::NotFound => error::ErrorNotFound(e.to_string()),
+        VoteError::Closed | VoteError::AlreadyVoted => error::ErrorForbidden(e.to_string()),
+        VoteError::Database(e) => {
+            log::error!("Failed to record poll vote: {}", e);
+            error::ErrorInternalServerError("Database error")
+        }
+        e => error::ErrorBadRequest(e.to_string()),
+    })?;
 
     // Redirect back to the thread
     Ok(HttpResponse::Found()
         .append_header(("Location", format!("/threads/{}/", poll.thread_id)))
         .finish())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_can_see_results() {
+        assert!(can_see_results(RESULTS_ALWAYS, false, false));
+        assert!(!can_see_results(RESULTS_AFTER_VOTE, false, false));
+        assert!(can_see_results(RESULTS_AFTER_VOTE, true, false));
+        assert!(can_see_results(RESULTS_AFTER_VOTE, false, true));
+        assert!(!can_see_results(RESULTS_AFTER_CLOSE, true, false));
+        assert!(can_see_results(RESULTS_AFTER_CLOSE, false, true));
+    }
+
+    #[test]
+    fn test_parse_vote_form() {
+        let form = VoteFormData::parse(b"csrf_token=abc&option_ids=3&option_ids=7&option_ids=x");
+        assert_eq!(form.csrf_token, "abc");
+        assert_eq!(form.option_ids, vec![3, 7]);
+
+        let form = VoteFormData::parse(b"csrf_token=abc");
+        assert!(form.option_ids.is_empty());
+    }
+
+    #[test]
+    fn test_ranked_ballot() {
+        let form = VoteFormData::parse(b"csrf_token=abc&rank_5=2&rank_8=&rank_9=1&rank_x=3");
+        assert_eq!(form.ranks, vec![(5, 2), (9, 1)]);
+        assert_eq!(form.ballot().unwrap(), vec![9, 5]);
+
+        let form = VoteFormData::parse(b"csrf_token=abc&rank_5=1&rank_9=1");
+        assert!(matches!(form.ballot(), Err(VoteError::DuplicateRank)));
+
+        let form = VoteFormData::parse(b"csrf_token=abc&option_ids=4");
+        assert_eq!(form.ballot().unwrap(), vec![4]);
+    }
+}
@@ -4,12 +4,14 @@ use crate::middleware::ClientCtx;
 use crate::orm::{posts, ugc_deletions, ugc_revisions};
 use crate::ugc::{create_ugc_revision, NewUgcPartial};
 use crate::user::Profile as UserProfile;
+use crate::word_filter::FilterScope;
 use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
 use askama_actix::{Template, TemplateToResponse};
 use chrono::prelude::Utc;
 use sea_orm::{entity::*, query::*, sea_query::Expr};
 use sea_orm::{DatabaseConnection, DbErr, FromQueryResult, QueryFilter};
 use serde::Deserialize;
+use tracing::Instrument;
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
     conf.service(delete_post)
@@ -229,26 +231,29 @@ pub async fn destroy_post(
         .map_err(error::ErrorInternalServerError)?;
 
         // Spawn a thread to handle post-deletion work.
-        actix_web::rt::spawn(async move {
-            use super::thread::update_thread_after_reply_is_deleted;
-
-            // Update subsequent posts's position.
-            let _post_res = posts::Entity::update_many()
-                .col_expr(posts::Column::Position, Expr::cust("position - 1"))
-                .filter(
-                    Condition::all()
-                        .add(posts::Column::ThreadId.eq(post.thread_id))
-                        .add(posts::Column::Position.gt(post.position)),
-                )
-                .exec(db)
-                .await
-                .map_err(|e| log::error!("destroy_post thread: {}", e));
-
-            // Update post_count and last_post info.
-            let _thread_res = update_thread_after_reply_is_deleted(post.thread_id)
-                .await
-                .map_err(|e| log::error!("destroy_post thread: {}", e));
-        });
+        actix_web::rt::spawn(
+            async move {
+                use super::thread::update_thread_after_reply_is_deleted;
+
+                // Update subsequent posts's position.
+                let _post_res = posts::Entity::update_many()
+                    .col_expr(posts::Column::Position, Expr::cust("position - 1"))
+                    .filter(
+                        Condition::all()
+                            .add(posts::Column::ThreadId.eq(post.thread_id))
+                            .add(posts::Column::Position.gt(post.position)),
+                    )
+                    .exec(db)
+                    .await
+                    .map_err(|e| tracing::error!(error = %e, "destroy_post thread"));
+
+                // Update post_count and last_post info.
+                let _thread_res = update_thread_after_reply_is_deleted(post.thread_id)
+                    .await
+                    .map_err(|e| tracing::error!(error = %e, "destroy_post thread"));
+            }
+            .in_current_span(),
+        );
     }
 
     // For permanent deletion, also clear the content
@@ -264,6 +269,8 @@ pub async fn destroy_post(
             .map_err(error::ErrorInternalServerError)?;
     }
 
+    crate::cache::fragment::posts_changed().await;
+
     Ok(HttpResponse::Found()
         .append_header(("Location", get_url_for_pos(post.thread_id, post.position)))
         .finish())
@@ -326,19 +333,24 @@ pub async fn restore_post(
         .map_err(error::ErrorInternalServerError)?;
 
     // Update post positions
-    actix_web::rt::spawn(async move {
-        // Increment positions of posts that came after this one
-        let _post_res = posts::Entity::update_many()
-            .col_expr(posts::Column::Position, Expr::cust("position + 1"))
-            .filter(
-                Condition::all()
-                    .add(posts::Column::ThreadId.eq(post.thread_id))
-                    .add(posts::Column::Position.gte(post.position)),
-            )
-            .exec(db)
-            .await
-            .map_err(|e| log::error!("restore_post thread: {}", e));
-    });
+    actix_web::rt::spawn(
+        async move {
+            // Increment positions of posts that came after this one
+            let _post_res = posts::Entity::update_many()
+                .col_expr(posts::Column::Position, Expr::cust("position + 1"))
+                .filter(
+                    Condition::all()
+                        .add(posts::Column::ThreadId.eq(post.thread_id))
+                        .add(posts::Column::Position.gte(post.position)),
+                )
+                .exec(db)
+                .await
+                .map_err(|e| tracing::error!(error = %e, "restore_post thread"));
+        }
+        .in_current_span(),
+    );
+
+    crate::cache::fragment::posts_changed().await;
 
     Ok(HttpResponse::Found()
         .append_header(("Location", get_url_for_pos(post.thread_id, post.position)))
@@ -490,6 +502,27 @@ pub async fn edit_post(client: ClientCtx, path: web::Path<i32>) -> Result<impl R
     .to_response())
 }
 
+/// Save edited post content as a new revision, once the word filters for
+/// posts have passed it.
+pub async fn save_post_edit(
+    ugc_id: i32,
+    editor_id: Option<i32>,
+    content: &str,
+) -> Result<ugc_revisions::Model, Error> {
+    let content = crate::word_filter::filter_for_saving(content, FilterScope::Posts)
+        .map_err(error::ErrorBadRequest)?;
+    create_ugc_revision(
+        get_db_pool(),
+        ugc_id,
+        NewUgcPartial {
+            ip_id: None,
+            user_id: editor_id,
+            content: &content,
+        },
+    )
+    .await
+}
+
 #[post("/posts/{post_id}/edit")]
 pub async fn update_post(
     client: ClientCtx,
@@ -512,17 +545,7 @@ pub async fn update_post(
         ));
     }
 
-    create_ugc_revision(
-        db,
-        post.ugc_id,
-        NewUgcPartial {
-            ip_id: None,
-            user_id: client.get_id(),
-            content: &form.content,
-        },
-    )
-    .await
-    .map_err(error::ErrorInternalServerError)?;
+    save_post_edit(post.ugc_id, client.get_id(), &form.content).await?;
 
     Ok(HttpResponse::Found()
         .append_header(("Location", get_url_for_pos(post.thread_id, post.position)))
@@ -643,6 +666,7 @@ pub async fn get_post_and_author_for_template(
     .await
 }
 
+#[tracing::instrument(skip(db))]
 pub async fn get_replies_and_author_for_template(
     db: &DatabaseConnection,
     id: i32,
@@ -650,6 +674,27 @@ pub async fn get_replies_and_author_for_template(
     posts_per_page: i32,
     show_pending: bool,
     current_user_id: Option<i32>,
+) -> Result<Vec<(PostForTemplate, Option<UserProfile>)>, DbErr> {
+    get_replies_and_author_from_position(
+        db,
+        id,
+        (page - 1) * posts_per_page + 1,
+        posts_per_page,
+        show_pending,
+        current_user_id,
+    )
+    .await
+}
+
+/// Posts at `count` positions in a thread from `first_position` on, by
+/// position, so deep pages are as quick to load as the first.
+pub async fn get_replies_and_author_from_position(
+    db: &DatabaseConnection,
+    id: i32,
+    first_position: i32,
+    count: i32,
+    show_pending: bool,
+    current_user_id: Option<i32>,
 ) -> Result<Vec<(PostForTemplate, Option<UserProfile>)>, DbErr> {
     let mut query = crate::user::find_also_user(
         posts::Entity::find()
@@ -670,9 +715,7 @@ pub async fn get_replies_and_author_for_template(
         posts::Column::UserId,
     )
     .filter(posts::Column::ThreadId.eq(id))
-    .filter(
-        posts::Column::Position.between((page - 1) * posts_per_page + 1, page * posts_per_page),
-    );
+    .filter(posts::Column::Position.between(first_position, first_position + count - 1));
 
     // Filter out pending/rejected posts unless user is a moderator or the post author
     if !show_pending {
@@ -1,20 +1,37 @@
 //! Post reaction endpoints
-
+//!
+//! Besides toggling reactions, these list who reacted to a post, count the
+//! reactions a member has given and received for their profile, and rank
+//! the posts that drew the most reactions in the past week. That ranking is
+//! cached as a [`Fragment::MostReacted`] per set of viewer groups, for
+//! `fragment_ttl_seconds`.
+
+use crate::cache::fragment::{self, Fragment};
 use crate::config::Config;
 use crate::db::get_db_pool;
 use crate::middleware::ClientCtx;
 use crate::orm::{attachments, posts, reaction_types, threads, ugc_reactions, user_names, users};
-use actix_web::{error, get, post, web, Error, HttpResponse};
-use chrono::Utc;
-use sea_orm::{entity::*, query::*, ColumnTrait, EntityTrait, QueryFilter};
+use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
+use askama_actix::{Template, TemplateToResponse};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sea_orm::{
+    entity::*, query::*, ColumnTrait, DbBackend, DbErr, EntityTrait, FromQueryResult, QueryFilter,
+    Statement,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Posts listed on the most reacted page
+const MOST_REACTED_LIMIT: usize = 25;
+
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
     // Note: get_reaction_types must be registered before get_reactions
     // because /reactions/types would otherwise match /reactions/{ugc_id}
     conf.service(get_reaction_types)
+        .service(view_most_reacted)
         .service(get_reaction_users)
+        .service(get_reactors)
         .service(toggle_reaction)
         .service(get_reactions);
 }
@@ -65,7 +82,7 @@ struct ReactionUsersResponse {
     reaction_image_url: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 struct ReactionUserInfo {
     id: i32,
     name: String,
@@ -78,6 +95,131 @@ struct ReactionUsersQuery {
     reaction_type_id: i32,
 }
 
+/// Response for everyone who reacted to a post, by reaction type
+#[derive(Serialize)]
+struct ReactorsResponse {
+    reactions: Vec<ReactorGroup>,
+}
+
+#[derive(Serialize)]
+struct ReactorGroup {
+    reaction_type_id: i32,
+    name: String,
+    emoji: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_url: Option<String>,
+    count: usize,
+    users: Vec<ReactionUserInfo>,
+}
+
+fn attachment_url(attachment: attachments::Model) -> String {
+    format!(
+        "/content/{}/{}",
+        &attachment.hash[0..64],
+        attachment.filename
+    )
+}
+
+/// Names and avatars of members, in the order given. Members without a
+/// name are left out.
+async fn reaction_user_infos(user_ids: &[i32]) -> Result<Vec<ReactionUserInfo>, DbErr> {
+    if user_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let db = get_db_pool();
+
+    let name_map: HashMap<i32, String> = user_names::Entity::find()
+        .filter(user_names::Column::UserId.is_in(user_ids.to_vec()))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|un| (un.user_id, un.name))
+        .collect();
+
+    let avatar_map: HashMap<i32, Option<String>> = users::Entity::find()
+        .filter(users::Column::Id.is_in(user_ids.to_vec()))
+        .find_also_related(attachments::Entity)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(u, att)| (u.id, att.map(attachment_url)))
+        .collect();
+
+    Ok(user_ids
+        .iter()
+        .filter_map(|uid| {
+            name_map.get(uid).map(|name| ReactionUserInfo {
+                id: *uid,
+                name: name.clone(),
+                avatar_url: avatar_map.get(uid).cloned().flatten(),
+            })
+        })
+        .collect())
+}
+
+/// Get everyone who reacted to a post, grouped by reaction type, most used
+/// type first and newest reaction first within each
+#[get("/reactions/{ugc_id}/reactors")]
+async fn get_reactors(path: web::Path<i32>) -> Result<HttpResponse, Error> {
+    let ugc_id = path.into_inner();
+    let db = get_db_pool();
+
+    let reactions = ugc_reactions::Entity::find()
+        .filter(ugc_reactions::Column::UgcId.eq(ugc_id))
+        .order_by_desc(ugc_reactions::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut by_type: HashMap<i32, Vec<i32>> = HashMap::new();
+    for reaction in &reactions {
+        by_type
+            .entry(reaction.reaction_type_id)
+            .or_default()
+            .push(reaction.user_id);
+    }
+
+    let types = reaction_types::Entity::find()
+        .filter(reaction_types::Column::Id.is_in(by_type.keys().copied()))
+        .order_by_asc(reaction_types::Column::DisplayOrder)
+        .find_also_related(attachments::Entity)
+        .all(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut user_ids: Vec<i32> = reactions.iter().map(|r| r.user_id).collect();
+    user_ids.sort_unstable();
+    user_ids.dedup();
+    let infos: HashMap<i32, ReactionUserInfo> = reaction_user_infos(&user_ids)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|info| (info.id, info))
+        .collect();
+
+    let mut groups: Vec<ReactorGroup> = types
+        .into_iter()
+        .map(|(rt, att)| {
+            let user_ids = by_type.remove(&rt.id).unwrap_or_default();
+            ReactorGroup {
+                reaction_type_id: rt.id,
+                name: rt.name,
+                emoji: rt.emoji,
+                image_url: att.map(attachment_url),
+                count: user_ids.len(),
+                users: user_ids
+                    .iter()
+                    .filter_map(|uid| infos.get(uid).cloned())
+                    .collect(),
+            }
+        })
+        .collect();
+    // Stable, so equal counts keep display order
+    groups.sort_by(|a, b| b.count.cmp(&a.count));
+
+    Ok(HttpResponse::Ok().json(ReactorsResponse { reactions: groups }))
+}
+
 /// Get users who reacted with a specific reaction type
 #[get("/reactions/{ugc_id}/users")]
 async fn get_reaction_users(
@@ -108,59 +250,11 @@ async fn get_reaction_users(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
-    // Get user IDs
     let user_ids: Vec<i32> = reactions.iter().map(|r| r.user_id).collect();
-
-    if user_ids.is_empty() {
-        return Ok(HttpResponse::Ok().json(ReactionUsersResponse {
-            users: vec![],
-            reaction_name: rt.name,
-            reaction_emoji: rt.emoji,
-            reaction_image_url,
-        }));
-    }
-
-    // Fetch user names
-    let user_names_data = user_names::Entity::find()
-        .filter(user_names::Column::UserId.is_in(user_ids.clone()))
-        .all(db)
-        .await
-        .map_err(error::ErrorInternalServerError)?;
-
-    // Fetch user avatars
-    let users_with_avatars = users::Entity::find()
-        .filter(users::Column::Id.is_in(user_ids.clone()))
-        .find_also_related(attachments::Entity)
-        .all(db)
+    let users_info = reaction_user_infos(&user_ids)
         .await
         .map_err(error::ErrorInternalServerError)?;
 
-    // Build maps
-    let name_map: std::collections::HashMap<i32, String> = user_names_data
-        .into_iter()
-        .map(|un| (un.user_id, un.name))
-        .collect();
-
-    let avatar_map: std::collections::HashMap<i32, Option<String>> = users_with_avatars
-        .into_iter()
-        .map(|(u, att)| {
-            let avatar_url = att.map(|a| format!("/content/{}/{}", &a.hash[0..64], a.filename));
-            (u.id, avatar_url)
-        })
-        .collect();
-
-    // Build user info list (preserving reaction order)
-    let users_info: Vec<ReactionUserInfo> = user_ids
-        .iter()
-        .filter_map(|uid| {
-            name_map.get(uid).map(|name| ReactionUserInfo {
-                id: *uid,
-                name: name.clone(),
-                avatar_url: avatar_map.get(uid).cloned().flatten(),
-            })
-        })
-        .collect();
-
     Ok(HttpResponse::Ok().json(ReactionUsersResponse {
         users: users_info,
         reaction_name: rt.name,
@@ -186,7 +280,9 @@ async fn toggle_reaction(
     crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
 
     // Rate limiting - prevent reaction spam
-    if let Err(e) = crate::rate_limit::check_reaction_rate_limit(user_id) {
+    if let Err(e) =
+        crate::rate_limit::check_reaction_rate_limit(user_id, &client.get_groups()).await
+    {
         log::warn!("Reaction rate limit exceeded for user: {}", user_id);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many reactions. Please try again in {} seconds.",
@@ -297,6 +393,14 @@ async fn toggle_reaction(
             });
         }
 
+        // Let the author know (async, non-blocking)
+        actix::spawn(async move {
+            if let Err(e) = crate::notifications::dispatcher::notify_reaction(ugc_id, user_id).await
+            {
+                log::warn!("Failed to send reaction notification: {}", e);
+            }
+        });
+
         true
     };
 
@@ -434,6 +538,199 @@ async fn get_reaction_types() -> Result<HttpResponse, Error> {
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Reactions of one type a member gave or received
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct ReactionStat {
+    pub reaction_type_id: i32,
+    pub name: String,
+    pub emoji: String,
+    pub count: i64,
+}
+
+/// Reactions to a member's posts, and reactions they gave to posts, by
+/// type, most first
+pub async fn reaction_stats(user_id: i32) -> Result<(Vec<ReactionStat>, Vec<ReactionStat>), DbErr> {
+    let db = get_db_pool();
+    let stats = |member_column: &str| {
+        Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            &format!(
+                r#"
+                SELECT rt.id AS reaction_type_id, rt.name, rt.emoji, COUNT(*) AS count
+                FROM ugc_reactions r
+                INNER JOIN posts p ON p.ugc_id = r.ugc_id
+                INNER JOIN reaction_types rt ON rt.id = r.reaction_type_id
+                WHERE {} = $1
+                GROUP BY rt.id, rt.name, rt.emoji, rt.display_order
+                ORDER BY count DESC, rt.display_order
+                "#,
+                member_column
+            ),
+            vec![user_id.into()],
+        )
+    };
+
+    let received = ReactionStat::find_by_statement(stats("p.user_id"))
+        .all(db)
+        .await?;
+    let given = ReactionStat::find_by_statement(stats("r.user_id"))
+        .all(db)
+        .await?;
+    Ok((received, given))
+}
+
+/// A post among those most reacted to this week
+#[derive(Debug, FromQueryResult)]
+struct MostReactedRow {
+    id: i32,
+    ugc_id: i32,
+    thread_id: i32,
+    thread_title: String,
+    forum_id: i32,
+    created_at: NaiveDateTime,
+    user_id: Option<i32>,
+    username: Option<String>,
+    reaction_count: i64,
+}
+
+/// A post on the most reacted page, with its reactions this week by type
+#[derive(Debug)]
+pub struct MostReactedPost {
+    pub id: i32,
+    pub thread_id: i32,
+    pub thread_title: String,
+    pub created_at: NaiveDateTime,
+    pub user_id: Option<i32>,
+    pub username: Option<String>,
+    pub reaction_count: i64,
+    /// Emoji and count of each reaction type, most first
+    pub reactions: Vec<(String, i64)>,
+}
+
+/// Visible posts with the most reactions given since `since`, most first.
+pub async fn get_most_reacted_posts(
+    client: &ClientCtx,
+    since: NaiveDateTime,
+    limit: usize,
+) -> Result<Vec<MostReactedPost>, DbErr> {
+    let db = get_db_pool();
+    // Fetch extra to fill the list after dropping forums the client can't see.
+    let rows = MostReactedRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+            SELECT p.id, p.ugc_id, p.thread_id, t.title AS thread_title, t.forum_id,
+                   p.created_at, p.user_id, un.name AS username,
+                   COUNT(r.id) AS reaction_count
+            FROM ugc_reactions r
+            INNER JOIN posts p ON p.ugc_id = r.ugc_id
+            INNER JOIN threads t ON t.id = p.thread_id
+            LEFT JOIN user_names un ON un.user_id = p.user_id
+            LEFT JOIN ugc_deletions d ON d.id = p.ugc_id
+            WHERE r.created_at >= $1
+              AND d.id IS NULL
+              AND p.moderation_status = 'approved'
+              AND t.deleted_at IS NULL
+              AND t.merged_into_id IS NULL
+            GROUP BY p.id, t.id, un.name
+            ORDER BY reaction_count DESC, p.id DESC
+            LIMIT $2
+        "#,
+        vec![since.into(), ((limit * 4) as i64).into()],
+    ))
+    .all(db)
+    .await?;
+
+    let rows: Vec<MostReactedRow> = rows
+        .into_iter()
+        .filter(|row| client.can_view_thread(&row.thread_id, &row.forum_id))
+        .take(limit)
+        .collect();
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let reactions = ugc_reactions::Entity::find()
+        .filter(ugc_reactions::Column::UgcId.is_in(rows.iter().map(|row| row.ugc_id)))
+        .filter(ugc_reactions::Column::CreatedAt.gte(since))
+        .find_also_related(reaction_types::Entity)
+        .all(db)
+        .await?;
+
+    // Count by post and type, then order each post's types by count
+    let mut counts: HashMap<(i32, i32), (i32, String, i64)> = HashMap::new();
+    for (reaction, reaction_type) in reactions {
+        if let Some(rt) = reaction_type {
+            counts
+                .entry((reaction.ugc_id, rt.id))
+                .or_insert((rt.display_order, rt.emoji, 0))
+                .2 += 1;
+        }
+    }
+    let mut type_counts: HashMap<i32, Vec<(i32, String, i64)>> = HashMap::new();
+    for ((ugc_id, _), count) in counts {
+        type_counts.entry(ugc_id).or_default().push(count);
+    }
+    let mut type_counts: HashMap<i32, Vec<(String, i64)>> = type_counts
+        .into_iter()
+        .map(|(ugc_id, mut types)| {
+            types.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+            let types = types
+                .into_iter()
+                .map(|(_, emoji, count)| (emoji, count))
+                .collect();
+            (ugc_id, types)
+        })
+        .collect();
+
+    Ok(rows
+        .into_iter()
+        .map(|row| MostReactedPost {
+            reactions: type_counts.remove(&row.ugc_id).unwrap_or_default(),
+            id: row.id,
+            thread_id: row.thread_id,
+            thread_title: row.thread_title,
+            created_at: row.created_at,
+            user_id: row.user_id,
+            username: row.username,
+            reaction_count: row.reaction_count,
+        })
+        .collect())
+}
+
+#[derive(Template)]
+#[template(path = "fragments/most_reacted.html")]
+struct MostReactedFragment {
+    posts: Vec<MostReactedPost>,
+}
+
+#[derive(Template)]
+#[template(path = "recent_most_reacted.html")]
+struct MostReactedTemplate {
+    client: ClientCtx,
+    list: String,
+}
+
+/// Posts with the most reactions in the past week
+#[get("/recent/most-reacted")]
+async fn view_most_reacted(client: ClientCtx) -> Result<impl Responder, Error> {
+    let groups_key = fragment::groups_key(&client.get_groups());
+    let viewer = &client;
+    let list = fragment::get_or_render(Fragment::MostReacted, &groups_key, || async move {
+        let since = Utc::now().naive_utc() - Duration::days(7);
+        let posts = get_most_reacted_posts(viewer, since, MOST_REACTED_LIMIT)
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("Failed to load most reacted posts: {}", e);
+                Vec::new()
+            });
+        MostReactedFragment { posts }.render()
+    })
+    .await
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(MostReactedTemplate { client, list }.to_response())
+}
+
 /// Type alias for reaction summary: (reaction_type_id, name, emoji, count)
 pub type ReactionSummaryTuple = (i32, String, String, i64);
 /// Type alias for reactions data: (summaries, user_reaction_type_ids)
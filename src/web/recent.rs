@@ -120,6 +120,54 @@ impl RecentPost {
     }
 }
 
+/// A post in the latest posts list on the forum index
+#[derive(Debug, FromQueryResult)]
+pub struct LatestPost {
+    pub id: i32,
+    pub thread_id: i32,
+    pub thread_title: String,
+    pub forum_id: i32,
+    pub created_at: NaiveDateTime,
+    pub user_id: Option<i32>,
+    pub username: Option<String>,
+}
+
+/// The newest visible posts in forums the client may view.
+pub async fn get_latest_posts(
+    client: &ClientCtx,
+    limit: usize,
+) -> Result<Vec<LatestPost>, sea_orm::DbErr> {
+    use sea_orm::{DbBackend, Statement};
+
+    // Fetch extra to fill the list after dropping forums the client can't see.
+    let posts = LatestPost::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+            SELECT p.id, p.thread_id, t.title AS thread_title, t.forum_id,
+                   p.created_at, p.user_id, un.name AS username
+            FROM posts p
+            INNER JOIN threads t ON t.id = p.thread_id
+            LEFT JOIN user_names un ON un.user_id = p.user_id
+            LEFT JOIN ugc_deletions d ON d.id = p.ugc_id
+            WHERE d.id IS NULL
+              AND p.moderation_status = 'approved'
+              AND t.deleted_at IS NULL
+              AND t.merged_into_id IS NULL
+            ORDER BY p.created_at DESC, p.id DESC
+            LIMIT $1
+        "#,
+        vec![((limit * 4) as i64).into()],
+    ))
+    .all(get_db_pool())
+    .await?;
+
+    Ok(posts
+        .into_iter()
+        .filter(|post| client.can_view_thread(&post.thread_id, &post.forum_id))
+        .take(limit)
+        .collect())
+}
+
 #[derive(Template)]
 #[template(path = "recent_threads.html")]
 pub struct RecentThreadsTemplate {
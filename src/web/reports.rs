@@ -2,13 +2,14 @@
 
 use crate::db::get_db_pool;
 use crate::middleware::ClientCtx;
-use crate::orm::{posts, report_reasons, reports, threads, user_names, users};
+use crate::orm::{forums, posts, report_reasons, reports, threads, user_names, users};
 use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
 use askama::Template;
 use askama_actix::TemplateToResponse;
 use chrono::Utc;
 use sea_orm::{
-    entity::*, query::*, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, QueryOrder,
+    entity::*, query::*, ActiveValue::Set, ColumnTrait, Condition, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, QueryOrder,
 };
 use serde::{Deserialize, Serialize};
 
@@ -20,6 +21,67 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
         .service(update_report_status);
 }
 
+/// Forum that reported content is in. None for members and content that
+/// has since been deleted.
+pub async fn report_forum_id(
+    db: &DatabaseConnection,
+    content_type: &str,
+    content_id: i32,
+) -> Result<Option<i32>, DbErr> {
+    let thread_id = match content_type {
+        "post" => match posts::Entity::find_by_id(content_id).one(db).await? {
+            Some(post) => post.thread_id,
+            None => return Ok(None),
+        },
+        "thread" => content_id,
+        _ => return Ok(None),
+    };
+
+    Ok(threads::Entity::find_by_id(thread_id)
+        .one(db)
+        .await?
+        .map(|thread| thread.forum_id))
+}
+
+/// Require `permission` globally or in the forum a report is about, so
+/// forum moderators can handle reports in their own forums.
+fn require_report_permission(
+    client: &ClientCtx,
+    forum_id: Option<i32>,
+    permission: &str,
+) -> Result<(), Error> {
+    if forum_id.map_or(false, |forum_id| client.can_in_forum(&forum_id, permission)) {
+        return Ok(());
+    }
+    client.require_permission(permission)
+}
+
+/// Reports about posts and threads in any of `forum_ids`.
+fn in_forums(forum_ids: Vec<i32>) -> Condition {
+    let thread_ids = threads::Entity::find()
+        .select_only()
+        .column(threads::Column::Id)
+        .filter(threads::Column::ForumId.is_in(forum_ids))
+        .into_query();
+    let post_ids = posts::Entity::find()
+        .select_only()
+        .column(posts::Column::Id)
+        .filter(posts::Column::ThreadId.in_subquery(thread_ids.clone()))
+        .into_query();
+
+    Condition::any()
+        .add(
+            Condition::all()
+                .add(reports::Column::ContentType.eq("thread"))
+                .add(reports::Column::ContentId.in_subquery(thread_ids)),
+        )
+        .add(
+            Condition::all()
+                .add(reports::Column::ContentType.eq("post"))
+                .add(reports::Column::ContentId.in_subquery(post_ids)),
+        )
+}
+
 /// Response for report reasons
 #[derive(Serialize)]
 struct ReportReasonResponse {
@@ -90,7 +152,9 @@ async fn submit_report(
     crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
 
     // Rate limiting - prevent report spam
-    if let Err(e) = crate::rate_limit::check_report_rate_limit(reporter_id) {
+    if let Err(e) =
+        crate::rate_limit::check_report_rate_limit(reporter_id, &client.get_groups()).await
+    {
         log::warn!("Report rate limit exceeded for user: {}", reporter_id);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many reports. Please try again in {} seconds.",
@@ -200,6 +264,30 @@ async fn submit_report(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    crate::webhooks::report_filed(
+        result.id,
+        reporter_id,
+        &result.content_type,
+        result.content_id,
+        &result.reason,
+    );
+
+    // Let moderators know, including those of the forum the content is in
+    let report = result.clone();
+    actix::spawn(async move {
+        let forum_id =
+            match report_forum_id(get_db_pool(), &report.content_type, report.content_id).await {
+                Ok(forum_id) => forum_id,
+                Err(e) => {
+                    log::error!("Failed to find forum of report {}: {}", report.id, e);
+                    None
+                }
+            };
+        if let Err(e) = crate::notifications::reports::notify_new_report(&report, forum_id).await {
+            log::error!("Failed to notify moderators of report {}: {}", report.id, e);
+        }
+    });
+
     Ok(HttpResponse::Ok().json(ReportResponse {
         success: true,
         message: "Report submitted successfully. Thank you for helping keep the community safe."
@@ -216,6 +304,17 @@ struct ReportsListTemplate {
     client: ClientCtx,
     reports: Vec<ReportView>,
     filter_status: String,
+    filter_forum: Option<i32>,
+}
+
+impl ReportsListTemplate {
+    /// Link to the reports with `status`, in the same forum if filtered
+    fn status_url(&self, status: &str) -> String {
+        match self.filter_forum {
+            Some(forum_id) => format!("/admin/reports?status={}&forum={}", status, forum_id),
+            None => format!("/admin/reports?status={}", status),
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -239,7 +338,6 @@ async fn view_reports(
     query: web::Query<ReportsQuery>,
 ) -> Result<impl Responder, Error> {
     client.require_login()?;
-    client.require_permission("moderate.reports.view")?;
 
     let db = get_db_pool();
     let status_filter = query.status.clone().unwrap_or_else(|| "open".to_string());
@@ -247,6 +345,26 @@ async fn view_reports(
     // Get reports with filter
     let mut query_builder = reports::Entity::find().order_by_desc(reports::Column::CreatedAt);
 
+    // Forum moderators only see reports from the forums they moderate
+    if !client.can("moderate.reports.view") {
+        let forum_ids: Vec<i32> = forums::Entity::find()
+            .all(db)
+            .await
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|forum| forum.id)
+            .filter(|forum_id| client.can_in_forum(forum_id, "moderate.reports.view"))
+            .collect();
+        if forum_ids.is_empty() {
+            client.require_permission("moderate.reports.view")?;
+        }
+        query_builder = query_builder.filter(in_forums(forum_ids));
+    }
+
+    if let Some(forum_id) = query.forum {
+        query_builder = query_builder.filter(in_forums(vec![forum_id]));
+    }
+
     if status_filter != "all" {
         query_builder = query_builder.filter(reports::Column::Status.eq(status_filter.clone()));
     }
@@ -335,6 +453,7 @@ async fn view_reports(
         client,
         reports: report_views,
         filter_status: status_filter,
+        filter_forum: query.forum,
     }
     .to_response())
 }
@@ -342,6 +461,8 @@ async fn view_reports(
 #[derive(Deserialize)]
 struct ReportsQuery {
     status: Option<String>,
+    /// Only reports about content in this forum
+    forum: Option<i32>,
 }
 
 #[derive(Template)]
@@ -374,7 +495,6 @@ struct ReportDetailView {
 #[get("/admin/reports/{id}")]
 async fn view_report(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
     client.require_login()?;
-    client.require_permission("moderate.reports.view")?;
 
     let db = get_db_pool();
     let report_id = path.into_inner();
@@ -385,6 +505,11 @@ async fn view_report(client: ClientCtx, path: web::Path<i32>) -> Result<impl Res
         .map_err(error::ErrorInternalServerError)?
         .ok_or_else(|| error::ErrorNotFound("Report not found"))?;
 
+    let forum_id = report_forum_id(db, &report.content_type, report.content_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    require_report_permission(&client, forum_id, "moderate.reports.view")?;
+
     // Get reporter name
     let reporter_name = user_names::Entity::find()
         .filter(user_names::Column::UserId.eq(report.reporter_id))
@@ -494,7 +619,6 @@ async fn update_report_status(
     form: web::Form<UpdateReportForm>,
 ) -> Result<impl Responder, Error> {
     let moderator_id = client.require_login()?;
-    client.require_permission("moderate.reports.manage")?;
 
     // Validate CSRF
     crate::middleware::csrf::validate_csrf_token(&session, &form.csrf_token)?;
@@ -514,6 +638,11 @@ async fn update_report_status(
         .map_err(error::ErrorInternalServerError)?
         .ok_or_else(|| error::ErrorNotFound("Report not found"))?;
 
+    let forum_id = report_forum_id(db, &report.content_type, report.content_id)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    require_report_permission(&client, forum_id, "moderate.reports.manage")?;
+
     let now = Utc::now().naive_utc();
     let resolved_at = if form.status == "resolved" || form.status == "dismissed" {
         Some(now)
@@ -1,15 +1,57 @@
-/// Search functionality using PostgreSQL full-text search
+/// Search pages
 ///
-/// This module provides search capabilities for threads and posts.
-use crate::db::get_db_pool;
+/// This module provides search capabilities for threads and posts, answered
+/// by the configured search backend, and lets users save searches to run
+/// again from their account page.
+use crate::db::{get_db_pool, get_db_read_pool};
 use crate::middleware::ClientCtx;
-use actix_web::{error, get, web, Error, HttpRequest, Responder};
+use crate::orm::{forums, saved_searches};
+use crate::search::backend::{PostHit, SearchFilter, ThreadHit};
+use crate::search::get_search_backend;
+use actix_web::{error, get, post, web, Error, HttpRequest, HttpResponse, Responder};
 use askama_actix::{Template, TemplateToResponse};
-use sea_orm::{DatabaseConnection, FromQueryResult};
-use serde::Deserialize;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use sea_orm::{entity::*, query::*, DbBackend, DbErr, FromQueryResult, Statement};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Results returned for each kind of content
+const RESULT_LIMIT: u64 = 50;
+
+/// Searches one user can keep
+const MAX_SAVED_SEARCHES: u64 = 25;
+
+/// Longest name for a saved search
+const MAX_SAVED_NAME_LENGTH: usize = 100;
+
+/// Completions returned for each kind of suggestion
+const SUGGEST_LIMIT: usize = 8;
+
+/// Shortest and longest text completed
+const SUGGEST_MIN_LENGTH: usize = 2;
+const SUGGEST_MAX_LENGTH: usize = 50;
+
+const SUGGEST_CACHE_TTL_SECS: u64 = 60;
+
+/// Cached prefixes kept before expired ones are swept
+const SUGGEST_CACHE_MAX_ENTRIES: usize = 10_000;
+
+/// Existing threads suggested for a new thread title
+const SIMILAR_LIMIT: usize = 5;
+
+/// Shortest and longest title looked up for similar threads
+const SIMILAR_MIN_LENGTH: usize = 4;
+const SIMILAR_MAX_LENGTH: usize = 200;
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
-    conf.service(search_form).service(search_results);
+    conf.service(search_form)
+        .service(search_results)
+        .service(save_search)
+        .service(delete_saved_search)
+        .service(suggest)
+        .service(similar_threads);
 }
 
 /// Template for search form and results
@@ -17,57 +59,240 @@ pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
 #[template(path = "search.html")]
 struct SearchTemplate {
     client: ClientCtx,
-    query: Option<String>,
+    query: SearchQuery,
+    forums: Vec<forums::Model>,
+    /// Why the search could not be run, such as an unknown author
+    notice: Option<String>,
     results: Option<SearchResults>,
 }
 
 #[derive(Debug)]
 struct SearchResults {
-    threads: Vec<ThreadSearchResult>,
-    posts: Vec<PostSearchResult>,
+    threads: Vec<ThreadHit>,
+    posts: Vec<PostHit>,
     total_count: usize,
 }
 
-#[derive(Debug, FromQueryResult)]
-#[allow(dead_code)]
-struct ThreadSearchResult {
-    id: i32,
-    title: String,
-    forum_id: i32,
-    user_id: Option<i32>,
-    created_at: chrono::NaiveDateTime,
-    rank: f32,
+/// Search words and filters, as sent by the search form.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SearchQuery {
+    pub q: Option<String>,
+    /// Username of the author
+    pub author: Option<String>,
+    /// Forum ID
+    pub forum: Option<String>,
+    /// "1" to include the forum's subforums
+    pub subforums: Option<String>,
+    /// Created on or after this date (YYYY-MM-DD)
+    pub from: Option<String>,
+    /// Created on or before this date (YYYY-MM-DD)
+    pub to: Option<String>,
+    /// `threads` or `posts`; both when empty
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    /// Fewest reactions
+    pub min_reactions: Option<String>,
 }
 
-#[derive(Debug, FromQueryResult)]
-#[allow(dead_code)]
-struct PostSearchResult {
-    id: i32,
-    thread_id: i32,
-    content: String,
-    user_id: Option<i32>,
-    created_at: chrono::NaiveDateTime,
-    rank: f32,
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
 }
 
-/// Form data for search query
-#[derive(Deserialize)]
-struct SearchQuery {
-    q: Option<String>,
+fn date(value: &Option<String>) -> Option<NaiveDate> {
+    non_empty(value).and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+}
+
+fn start_of(date: NaiveDate) -> Option<NaiveDateTime> {
+    date.and_hms_opt(0, 0, 0)
+}
+
+impl SearchQuery {
+    pub fn q_value(&self) -> &str {
+        non_empty(&self.q).unwrap_or_default()
+    }
+
+    pub fn author_value(&self) -> &str {
+        non_empty(&self.author).unwrap_or_default()
+    }
+
+    pub fn forum_id(&self) -> Option<i32> {
+        non_empty(&self.forum).and_then(|id| id.parse().ok())
+    }
+
+    pub fn includes_subforums(&self) -> bool {
+        non_empty(&self.subforums).is_some_and(|v| v != "0")
+    }
+
+    pub fn from_value(&self) -> &str {
+        date(&self.from)
+            .and(non_empty(&self.from))
+            .unwrap_or_default()
+    }
+
+    pub fn to_value(&self) -> &str {
+        date(&self.to).and(non_empty(&self.to)).unwrap_or_default()
+    }
+
+    pub fn kind_value(&self) -> &str {
+        non_empty(&self.kind)
+            .filter(|kind| matches!(*kind, "threads" | "posts"))
+            .unwrap_or_default()
+    }
+
+    pub fn min_reactions(&self) -> Option<i32> {
+        non_empty(&self.min_reactions)
+            .and_then(|n| n.parse::<i32>().ok())
+            .filter(|n| *n > 0)
+    }
+
+    pub fn min_reactions_value(&self) -> String {
+        self.min_reactions()
+            .map(|n| n.to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn includes_threads(&self) -> bool {
+        self.kind_value() != "posts"
+    }
+
+    pub fn includes_posts(&self) -> bool {
+        self.kind_value() != "threads"
+    }
+
+    /// Whether anything beyond the search words is set.
+    pub fn has_filters(&self) -> bool {
+        !self.author_value().is_empty()
+            || self.forum_id().is_some()
+            || !self.from_value().is_empty()
+            || !self.to_value().is_empty()
+            || !self.kind_value().is_empty()
+            || self.min_reactions().is_some()
+    }
+
+    /// Query string repeating this search, for links and saved searches.
+    pub fn query_string(&self) -> String {
+        let forum = self.forum_id().map(|id| id.to_string()).unwrap_or_default();
+        let subforums = match self.forum_id().is_some() && self.includes_subforums() {
+            true => "1",
+            false => "",
+        };
+        let min_reactions = self.min_reactions_value();
+
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in [
+            ("q", self.q_value()),
+            ("author", self.author_value()),
+            ("forum", forum.as_str()),
+            ("subforums", subforums),
+            ("from", self.from_value()),
+            ("to", self.to_value()),
+            ("type", self.kind_value()),
+            ("min_reactions", min_reactions.as_str()),
+        ] {
+            if !value.is_empty() {
+                query.append_pair(name, value);
+            }
+        }
+        query.finish()
+    }
+
+    /// The backend filter for this search. Fails with a message for the
+    /// user when the author or forum does not exist.
+    async fn filter(&self, client: &ClientCtx) -> Result<SearchFilter, String> {
+        let db = get_db_read_pool();
+        let mut filter = SearchFilter {
+            since: date(&self.from).and_then(start_of),
+            before: date(&self.to)
+                .and_then(|to| to.checked_add_signed(Duration::days(1)))
+                .and_then(start_of),
+            min_reactions: self.min_reactions(),
+            ..Default::default()
+        };
+
+        if !self.author_value().is_empty() {
+            filter.user_id = Some(
+                crate::user::get_user_id_from_name(db, self.author_value())
+                    .await
+                    .ok_or_else(|| format!("There is no member named {}.", self.author_value()))?,
+            );
+        }
+
+        if let Some(forum_id) = self.forum_id() {
+            if !client.can_view_forum(&forum_id) {
+                return Err("That forum does not exist.".to_owned());
+            }
+            let forum_ids = match self.includes_subforums() {
+                true => forum_tree(forum_id).await.map_err(|e| {
+                    log::error!("search::forum_tree: {}", e);
+                    "Search failed.".to_owned()
+                })?,
+                false => vec![forum_id],
+            };
+            filter.forum_ids = Some(
+                forum_ids
+                    .into_iter()
+                    .filter(|id| client.can_view_forum(id))
+                    .collect(),
+            );
+        }
+
+        Ok(filter)
+    }
+}
+
+/// A forum and every forum below it.
+async fn forum_tree(forum_id: i32) -> Result<Vec<i32>, DbErr> {
+    #[derive(FromQueryResult)]
+    struct ForumId {
+        id: i32,
+    }
+
+    Ok(ForumId::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"WITH RECURSIVE tree AS (
+                SELECT id FROM forums WHERE id = $1
+                UNION
+                SELECT f.id FROM forums f JOIN tree ON f.parent_id = tree.id
+            )
+            SELECT id FROM tree"#,
+        vec![forum_id.into()],
+    ))
+    .all(get_db_read_pool())
+    .await?
+    .into_iter()
+    .map(|forum| forum.id)
+    .collect())
+}
+
+/// Forums the client may search in, for the forum filter.
+async fn visible_forums(client: &ClientCtx) -> Result<Vec<forums::Model>, Error> {
+    Ok(forums::Entity::find()
+        .order_by_asc(forums::Column::DisplayOrder)
+        .order_by_asc(forums::Column::Label)
+        .all(get_db_read_pool())
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .filter(|forum| client.can_view_forum(&forum.id))
+        .collect())
 }
 
 /// GET /search - Show search form
 #[get("/search")]
-pub async fn search_form(client: ClientCtx) -> impl Responder {
-    SearchTemplate {
+pub async fn search_form(client: ClientCtx) -> Result<impl Responder, Error> {
+    let forums = visible_forums(&client).await?;
+
+    Ok(SearchTemplate {
         client,
-        query: None,
+        query: SearchQuery::default(),
+        forums,
+        notice: None,
         results: None,
     }
-    .to_response()
+    .to_response())
 }
 
-/// GET /search?q=query - Perform search and show results
+/// GET /search/results?q=query - Perform search and show results
 #[get("/search/results")]
 pub async fn search_results(
     req: HttpRequest,
@@ -85,7 +310,9 @@ pub async fn search_results(
         });
 
     // Rate limiting - prevent search abuse
-    if let Err(e) = crate::rate_limit::check_search_rate_limit(&rate_limit_id) {
+    if let Err(e) =
+        crate::rate_limit::check_search_rate_limit(&rate_limit_id, &client.get_groups()).await
+    {
         log::warn!("Search rate limit exceeded for: {}", rate_limit_id);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many search requests. Please try again in {} seconds.",
@@ -93,31 +320,68 @@ pub async fn search_results(
         )));
     }
 
-    let search_query = match &query.q {
-        Some(q) if !q.trim().is_empty() => q.trim(),
-        _ => {
+    let query = query.into_inner();
+    let forums = visible_forums(&client).await?;
+
+    if query.q_value().is_empty() {
+        return Ok(SearchTemplate {
+            client,
+            query,
+            forums,
+            notice: None,
+            results: None,
+        }
+        .to_response());
+    }
+
+    let filter = match query.filter(&client).await {
+        Ok(filter) => filter,
+        Err(notice) => {
             return Ok(SearchTemplate {
                 client,
-                query: None,
+                query,
+                forums,
+                notice: Some(notice),
                 results: None,
             }
             .to_response());
         }
     };
 
-    let db = get_db_pool();
+    let backend = get_search_backend();
 
     // Search threads
-    let threads = search_threads(db, search_query).await?;
+    let mut threads = match query.includes_threads() {
+        true => backend
+            .search_threads(query.q_value(), &filter, RESULT_LIMIT)
+            .await
+            .map_err(|e| {
+                log::error!("Thread search error: {}", e);
+                error::ErrorInternalServerError("Search failed")
+            })?,
+        false => Vec::new(),
+    };
+    threads.retain(|thread| client.can_view_thread(&thread.id, &thread.forum_id));
 
     // Search posts
-    let posts = search_posts(db, search_query).await?;
+    let posts = match query.includes_posts() {
+        true => backend
+            .search_posts(query.q_value(), &filter, RESULT_LIMIT)
+            .await
+            .map_err(|e| {
+                log::error!("Post search error: {}", e);
+                error::ErrorInternalServerError("Search failed")
+            })?,
+        false => Vec::new(),
+    };
 
     let total_count = threads.len() + posts.len();
 
     Ok(SearchTemplate {
         client,
-        query: Some(search_query.to_string()),
+        query,
+        forums,
+        notice: None,
         results: Some(SearchResults {
             threads,
             posts,
@@ -127,85 +391,446 @@ pub async fn search_results(
     .to_response())
 }
 
-/// Search threads by title using full-text search
-async fn search_threads(
-    db: &DatabaseConnection,
-    query: &str,
-) -> Result<Vec<ThreadSearchResult>, Error> {
-    use sea_orm::Statement;
-
-    // Use PostgreSQL's to_tsquery for search
-    // ts_rank calculates relevance score
-    let sql = r#"
-        SELECT
-            t.id,
-            t.title,
-            t.forum_id,
-            t.user_id,
-            t.created_at,
-            ts_rank(t.title_tsv, to_tsquery('english', $1)) as rank
-        FROM threads t
-        WHERE t.title_tsv @@ to_tsquery('english', $1)
-        ORDER BY rank DESC, t.created_at DESC
-        LIMIT 50
-    "#;
-
-    // Convert search query to tsquery format (replace spaces with &)
-    let tsquery = query.split_whitespace().collect::<Vec<&str>>().join(" & ");
-
-    let stmt = Statement::from_sql_and_values(
-        sea_orm::DatabaseBackend::Postgres,
-        sql,
-        vec![tsquery.into()],
-    );
+#[derive(Deserialize)]
+struct SaveSearchForm {
+    csrf_token: String,
+    name: String,
+    /// Query string of the search being saved
+    query: String,
+}
 
-    ThreadSearchResult::find_by_statement(stmt)
-        .all(db)
+/// POST /search/save - Save the current search under a name
+#[post("/search/save")]
+async fn save_search(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<SaveSearchForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let name = form.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_SAVED_NAME_LENGTH {
+        return Err(error::ErrorBadRequest(format!(
+            "Search names must be between 1 and {} characters.",
+            MAX_SAVED_NAME_LENGTH
+        )));
+    }
+
+    let search = web::Query::<SearchQuery>::from_query(&form.query)
+        .map_err(|_| error::ErrorBadRequest("Invalid search"))?
+        .into_inner();
+    if search.q_value().is_empty() {
+        return Err(error::ErrorBadRequest("There is nothing to save."));
+    }
+    let query = search.query_string();
+
+    let db = get_db_pool();
+    let existing = saved_searches::Entity::find()
+        .filter(saved_searches::Column::UserId.eq(user_id))
+        .filter(saved_searches::Column::Name.eq(name))
+        .one(db)
         .await
-        .map_err(|e| {
-            log::error!("Thread search error: {}", e);
-            actix_web::error::ErrorInternalServerError("Search failed")
-        })
+        .map_err(error::ErrorInternalServerError)?;
+
+    match existing {
+        // Saving under a name already in use replaces that search.
+        Some(existing) => {
+            let mut active: saved_searches::ActiveModel = existing.into();
+            active.query = Set(query.clone());
+            active.update(db).await
+        }
+        None => {
+            let count = saved_searches::Entity::find()
+                .filter(saved_searches::Column::UserId.eq(user_id))
+                .count(db)
+                .await
+                .map_err(error::ErrorInternalServerError)?;
+            if count >= MAX_SAVED_SEARCHES {
+                return Err(error::ErrorBadRequest(format!(
+                    "You can save up to {} searches. Delete one from your account page first.",
+                    MAX_SAVED_SEARCHES
+                )));
+            }
+
+            saved_searches::ActiveModel {
+                user_id: Set(user_id),
+                name: Set(name.to_owned()),
+                query: Set(query.clone()),
+                created_at: Set(Utc::now().naive_utc()),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+        }
+    }
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", format!("/search/results?{}", query)))
+        .finish())
 }
 
-/// Search posts by content using full-text search
-async fn search_posts(
-    db: &DatabaseConnection,
-    query: &str,
-) -> Result<Vec<PostSearchResult>, Error> {
-    use sea_orm::Statement;
-
-    // Join ugc_revisions with posts to get thread_id
-    let sql = r#"
-        SELECT
-            p.id,
-            p.thread_id,
-            SUBSTRING(ur.content, 1, 200) as content,
-            ur.user_id,
-            ur.created_at,
-            ts_rank(ur.content_tsv, to_tsquery('english', $1)) as rank
-        FROM posts p
-        JOIN ugc u ON p.ugc_id = u.id
-        JOIN ugc_revisions ur ON u.ugc_revision_id = ur.id
-        WHERE ur.content_tsv @@ to_tsquery('english', $1)
-        ORDER BY rank DESC, ur.created_at DESC
-        LIMIT 50
-    "#;
-
-    // Convert search query to tsquery format
-    let tsquery = query.split_whitespace().collect::<Vec<&str>>().join(" & ");
-
-    let stmt = Statement::from_sql_and_values(
-        sea_orm::DatabaseBackend::Postgres,
-        sql,
-        vec![tsquery.into()],
-    );
+#[derive(Deserialize)]
+struct DeleteSavedSearchForm {
+    csrf_token: String,
+}
+
+/// POST /search/saved/{id}/delete - Forget a saved search
+#[post("/search/saved/{id}/delete")]
+async fn delete_saved_search(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<DeleteSavedSearchForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
 
-    PostSearchResult::find_by_statement(stmt)
-        .all(db)
+    // Only if it belongs to this user
+    saved_searches::Entity::delete_many()
+        .filter(saved_searches::Column::Id.eq(path.into_inner()))
+        .filter(saved_searches::Column::UserId.eq(user_id))
+        .exec(get_db_pool())
         .await
-        .map_err(|e| {
-            log::error!("Post search error: {}", e);
-            actix_web::error::ErrorInternalServerError("Search failed")
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", "/account"))
+        .finish())
+}
+
+/// A thread title completing the typed text.
+#[derive(Clone, Debug, Serialize, FromQueryResult)]
+pub struct ThreadSuggestion {
+    pub id: i32,
+    pub title: String,
+    #[serde(skip)]
+    pub forum_id: i32,
+}
+
+/// A member name completing the typed text.
+#[derive(Clone, Debug, Serialize)]
+pub struct MemberSuggestion {
+    pub id: i32,
+    pub username: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Suggestions {
+    pub threads: Vec<ThreadSuggestion>,
+    pub members: Vec<MemberSuggestion>,
+}
+
+/// Completions for one prefix, before permissions are applied.
+struct CachedSuggestions {
+    threads: Vec<ThreadSuggestion>,
+    members: Vec<MemberSuggestion>,
+    cached_at: Instant,
+}
+
+/// Suggestion cache, keyed by lowercased prefix
+static SUGGEST_CACHE: Lazy<DashMap<String, CachedSuggestions>> = Lazy::new(DashMap::new);
+
+#[derive(Deserialize)]
+struct SuggestQuery {
+    q: Option<String>,
+}
+
+/// Escapes `%`, `_` and `\` so `term` matches literally in a LIKE pattern.
+fn escape_like(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Lowercased, trimmed and shortened text to complete, if long enough.
+fn suggest_term(q: Option<&str>) -> Option<String> {
+    let term: String = q?
+        .trim()
+        .to_lowercase()
+        .chars()
+        .take(SUGGEST_MAX_LENGTH)
+        .collect();
+    (term.chars().count() >= SUGGEST_MIN_LENGTH).then_some(term)
+}
+
+/// Threads whose title, or a word in it, starts with `term`. Extra rows are
+/// fetched so there are enough left once hidden forums are filtered out.
+async fn find_thread_suggestions(term: &str) -> Result<Vec<ThreadSuggestion>, DbErr> {
+    let pattern = escape_like(term);
+    ThreadSuggestion::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+            SELECT t.id, t.title, t.forum_id
+            FROM threads t
+            WHERE t.deleted_at IS NULL
+              AND t.merged_into_id IS NULL
+              AND (LOWER(t.title) LIKE $1 || '%' OR LOWER(t.title) LIKE '% ' || $1 || '%')
+            ORDER BY LOWER(t.title) LIKE $1 || '%' DESC, t.last_post_at DESC NULLS LAST
+            LIMIT $2
+        "#,
+        vec![pattern.into(), ((SUGGEST_LIMIT * 4) as i64).into()],
+    ))
+    .all(get_db_read_pool())
+    .await
+}
+
+/// Members whose name starts with `term`.
+async fn find_member_suggestions(term: &str) -> Result<Vec<MemberSuggestion>, DbErr> {
+    #[derive(FromQueryResult)]
+    struct MemberRow {
+        user_id: i32,
+        name: String,
+    }
+
+    Ok(MemberRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r#"
+            SELECT user_id, name
+            FROM user_names
+            WHERE LOWER(name) LIKE $1 || '%'
+            ORDER BY LENGTH(name), name
+            LIMIT $2
+        "#,
+        vec![escape_like(term).into(), (SUGGEST_LIMIT as i64).into()],
+    ))
+    .all(get_db_read_pool())
+    .await?
+    .into_iter()
+    .map(|row| MemberSuggestion {
+        id: row.user_id,
+        username: row.name,
+    })
+    .collect())
+}
+
+/// Cached completions for `term`, looked up again once they expire.
+async fn cached_suggestions(
+    term: &str,
+) -> Result<(Vec<ThreadSuggestion>, Vec<MemberSuggestion>), DbErr> {
+    let ttl = std::time::Duration::from_secs(SUGGEST_CACHE_TTL_SECS);
+    if let Some(entry) = SUGGEST_CACHE.get(term) {
+        if entry.cached_at.elapsed() < ttl {
+            return Ok((entry.threads.clone(), entry.members.clone()));
+        }
+    }
+
+    let threads = find_thread_suggestions(term).await?;
+    let members = find_member_suggestions(term).await?;
+
+    if SUGGEST_CACHE.len() >= SUGGEST_CACHE_MAX_ENTRIES {
+        SUGGEST_CACHE.retain(|_, entry| entry.cached_at.elapsed() < ttl);
+        if SUGGEST_CACHE.len() >= SUGGEST_CACHE_MAX_ENTRIES {
+            SUGGEST_CACHE.clear();
+        }
+    }
+    SUGGEST_CACHE.insert(
+        term.to_owned(),
+        CachedSuggestions {
+            threads: threads.clone(),
+            members: members.clone(),
+            cached_at: Instant::now(),
+        },
+    );
+
+    Ok((threads, members))
+}
+
+/// GET /api/search/suggest?q= - Thread title and member name completions
+/// for the header search box
+#[get("/api/search/suggest")]
+async fn suggest(
+    req: HttpRequest,
+    client: ClientCtx,
+    query: web::Query<SuggestQuery>,
+) -> Result<HttpResponse, Error> {
+    let rate_limit_id = client
+        .get_id()
+        .map(|id: i32| id.to_string())
+        .unwrap_or_else(|| {
+            crate::ip::extract_client_ip(&req)
+                .map(|ip| ip.to_string())
+                .unwrap_or_else(|| "unknown".to_string())
+        });
+
+    if let Err(e) =
+        crate::rate_limit::check_suggest_rate_limit(&rate_limit_id, &client.get_groups()).await
+    {
+        log::warn!(
+            "Search suggestion rate limit exceeded for: {}",
+            rate_limit_id
+        );
+        return Err(error::ErrorTooManyRequests(format!(
+            "Too many requests. Please try again in {} seconds.",
+            e.retry_after_seconds
+        )));
+    }
+
+    let Some(term) = suggest_term(query.q.as_deref()) else {
+        return Ok(HttpResponse::Ok().json(Suggestions::default()));
+    };
+
+    let (threads, members) = cached_suggestions(&term).await.map_err(|e| {
+        log::error!("search::suggest: {}", e);
+        error::ErrorInternalServerError("Couldn't load suggestions")
+    })?;
+
+    Ok(HttpResponse::Ok().json(Suggestions {
+        threads: threads
+            .into_iter()
+            .filter(|thread| client.can_view_thread(&thread.id, &thread.forum_id))
+            .take(SUGGEST_LIMIT)
+            .collect(),
+        // Member names are only completed for members, like mention search.
+        members: match client.is_user() {
+            true => members,
+            false => Vec::new(),
+        },
+    }))
+}
+
+#[derive(Deserialize)]
+struct SimilarQuery {
+    title: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SimilarThreads {
+    pub threads: Vec<ThreadSuggestion>,
+}
+
+/// Trimmed and shortened title to look up, if long enough.
+fn similar_title(title: Option<&str>) -> Option<String> {
+    let title: String = title?.trim().chars().take(SIMILAR_MAX_LENGTH).collect();
+    (title.chars().count() >= SIMILAR_MIN_LENGTH).then_some(title)
+}
+
+/// Existing threads the client can see with titles like `title`, so someone
+/// starting a thread can find an existing discussion first. Extra hits are
+/// requested so there are enough left once hidden forums are filtered out.
+pub async fn find_similar_threads(
+    client: &ClientCtx,
+    title: &str,
+) -> Result<Vec<ThreadSuggestion>, crate::search::SearchError> {
+    let Some(title) = similar_title(Some(title)) else {
+        return Ok(Vec::new());
+    };
+
+    let hits = get_search_backend()
+        .similar_threads(&title, &SearchFilter::default(), (SIMILAR_LIMIT * 4) as u64)
+        .await?;
+
+    Ok(hits
+        .into_iter()
+        .filter(|hit| client.can_view_thread(&hit.id, &hit.forum_id))
+        .take(SIMILAR_LIMIT)
+        .map(|hit| ThreadSuggestion {
+            id: hit.id,
+            title: hit.title,
+            forum_id: hit.forum_id,
         })
+        .collect())
+}
+
+/// GET /api/threads/similar?title= - Existing threads like a new thread's title
+#[get("/api/threads/similar")]
+async fn similar_threads(
+    client: ClientCtx,
+    query: web::Query<SimilarQuery>,
+) -> Result<HttpResponse, Error> {
+    let user_id = client.require_login()?;
+
+    // Looked up as the title is typed, so it shares the suggestion limit.
+    if let Err(e) =
+        crate::rate_limit::check_suggest_rate_limit(&user_id.to_string(), &client.get_groups())
+            .await
+    {
+        log::warn!("Similar thread rate limit exceeded for: {}", user_id);
+        return Err(error::ErrorTooManyRequests(format!(
+            "Too many requests. Please try again in {} seconds.",
+            e.retry_after_seconds
+        )));
+    }
+
+    let title = query.title.as_deref().unwrap_or_default();
+    let threads = find_similar_threads(&client, title).await.map_err(|e| {
+        log::error!("search::similar_threads: {}", e);
+        error::ErrorInternalServerError("Couldn't load similar threads")
+    })?;
+
+    Ok(HttpResponse::Ok().json(SimilarThreads { threads }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> SearchQuery {
+        web::Query::<SearchQuery>::from_query(query)
+            .unwrap()
+            .into_inner()
+    }
+
+    #[test]
+    fn test_query_string_round_trip() {
+        let query = parse(
+            "q=rust+async&author=alice&forum=3&subforums=1&from=2025-01-01&to=&type=posts&min_reactions=2",
+        );
+        assert_eq!(
+            query.query_string(),
+            "q=rust+async&author=alice&forum=3&subforums=1&from=2025-01-01&type=posts&min_reactions=2"
+        );
+        assert_eq!(
+            parse(&query.query_string()).query_string(),
+            query.query_string()
+        );
+        assert!(query.has_filters());
+        assert!(!query.includes_threads());
+        assert!(query.includes_posts());
+    }
+
+    #[test]
+    fn test_invalid_filters_are_dropped() {
+        let query = parse("q=test&forum=abc&subforums=1&from=yesterday&type=all&min_reactions=-4");
+        assert_eq!(query.query_string(), "q=test");
+        assert!(!query.has_filters());
+        assert!(query.includes_threads() && query.includes_posts());
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("100%_done\\"), "100\\%\\_done\\\\");
+        assert_eq!(escape_like("plain"), "plain");
+    }
+
+    #[test]
+    fn test_suggest_term() {
+        assert_eq!(suggest_term(Some("  Rust ")), Some("rust".to_owned()));
+        assert_eq!(suggest_term(Some("r")), None);
+        assert_eq!(suggest_term(None), None);
+        assert_eq!(
+            suggest_term(Some(&"x".repeat(80))).map(|t| t.len()),
+            Some(SUGGEST_MAX_LENGTH)
+        );
+    }
+
+    #[test]
+    fn test_similar_title() {
+        assert_eq!(
+            similar_title(Some("  How do I reset my password? ")),
+            Some("How do I reset my password?".to_owned())
+        );
+        assert_eq!(similar_title(Some("abc")), None);
+        assert_eq!(similar_title(None), None);
+        assert_eq!(
+            similar_title(Some(&"x".repeat(300))).map(|t| t.len()),
+            Some(SIMILAR_MAX_LENGTH)
+        );
+    }
 }
@@ -1,7 +1,10 @@
 use super::post::PostForTemplate;
+use crate::app_state::AppState;
 use crate::attachment::AttachmentForTemplate;
+use crate::cache::{self, CacheScope};
 use crate::config::Config;
-use crate::db::get_db_pool;
+use crate::db::{get_db_pool, get_db_read_pool};
+use crate::group::GroupStyle;
 use crate::middleware::ClientCtx;
 use crate::orm::posts::Entity as Post;
 use crate::orm::threads::Entity as Thread;
@@ -9,6 +12,7 @@ use crate::orm::{
     poll_options, poll_votes, polls, posts, tags, thread_read, thread_tags, threads, ugc_deletions,
     users,
 };
+use crate::presence::Presence;
 use crate::template::{Paginator, PaginatorToHtml};
 use crate::user::Profile as UserProfile;
 use actix_multipart::Multipart;
@@ -17,6 +21,7 @@ use askama_actix::{Template, TemplateToResponse};
 use sea_orm::{entity::*, query::*, sea_query::Expr, DbErr, FromQueryResult, QueryFilter};
 use serde::Deserialize;
 use std::{collections::HashMap, str, sync::Arc};
+use tracing::Instrument;
 
 pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
     conf.service(create_reply)
@@ -86,8 +91,12 @@ pub struct NewThreadFormData {
     pub poll_max_choices: i32,
     #[serde(default)]
     pub poll_allow_change_vote: bool,
+    #[serde(default = "default_poll_type")]
+    pub poll_type: String,
+    #[serde(default = "default_results_visibility")]
+    pub poll_results_visibility: String,
     #[serde(default)]
-    pub poll_show_results_before_vote: bool,
+    pub poll_public_votes: bool,
     pub poll_closes_at: Option<String>,
 }
 
@@ -95,6 +104,14 @@ fn default_max_choices() -> i32 {
     1
 }
 
+fn default_poll_type() -> String {
+    crate::web::polls::POLL_CHOICE.to_owned()
+}
+
+fn default_results_visibility() -> String {
+    crate::web::polls::RESULTS_AFTER_VOTE.to_owned()
+}
+
 /// Validated thread form data ready for processing
 #[derive(Debug, Clone)]
 pub struct ValidatedThreadForm {
@@ -111,7 +128,9 @@ pub struct ValidatedPoll {
     pub options: Vec<String>,
     pub max_choices: i32,
     pub allow_change_vote: bool,
-    pub show_results_before_vote: bool,
+    pub poll_type: String,
+    pub results_visibility: String,
+    pub public_votes: bool,
     pub closes_at: Option<chrono::NaiveDateTime>,
 }
 
@@ -123,6 +142,42 @@ pub struct PollOptionForTemplate {
     pub vote_count: i32,
     pub percentage: f64,
     pub user_selected: bool,
+    /// Where the viewer ranked this option, on ranked polls
+    pub user_rank: Option<i32>,
+    /// Names of those who chose this option, on polls with public votes
+    pub voters: Vec<String>,
+}
+
+impl PollOptionForTemplate {
+    /// Whether the viewer gave this option the rank
+    pub fn is_ranked_at(&self, rank: &i32) -> bool {
+        self.user_rank == Some(*rank)
+    }
+}
+
+/// One option's votes in a round of a ranked poll's count
+#[derive(Debug, Clone)]
+pub struct RunoffTallyForTemplate {
+    pub option_text: String,
+    pub votes: i64,
+    pub percentage: f64,
+    pub eliminated: bool,
+}
+
+/// A round of a ranked poll's count
+#[derive(Debug, Clone)]
+pub struct RunoffRoundForTemplate {
+    pub number: usize,
+    pub tallies: Vec<RunoffTallyForTemplate>,
+    pub exhausted: i64,
+}
+
+/// A ranked poll's count, round by round
+#[derive(Debug, Clone)]
+pub struct RunoffForTemplate {
+    pub rounds: Vec<RunoffRoundForTemplate>,
+    pub winner: Option<String>,
+    pub ballots: i64,
 }
 
 /// Poll data for template display
@@ -132,12 +187,24 @@ pub struct PollForTemplate {
     pub question: String,
     pub max_choices: i32,
     pub allow_change_vote: bool,
-    pub show_results_before_vote: bool,
+    pub is_ranked: bool,
+    /// Ranks a voter can give options on a ranked poll
+    pub ranks: Vec<i32>,
+    pub results_visibility: String,
+    pub public_votes: bool,
     pub closes_at: Option<chrono::NaiveDateTime>,
     pub is_closed: bool,
     pub total_votes: i32,
+    /// Number of members who voted, which differs from `total_votes` when
+    /// voters may choose more than one option
+    pub voter_count: i64,
     pub options: Vec<PollOptionForTemplate>,
     pub has_voted: bool,
+    /// Whether the viewer may see the counts and voters
+    pub results_visible: bool,
+    /// The count so far, or the final one, of a ranked poll whose results
+    /// the viewer may see
+    pub runoff: Option<RunoffForTemplate>,
 }
 
 /// Similar thread for display in sidebar
@@ -164,16 +231,86 @@ pub struct ThreadTemplate<'a> {
     pub poll: Option<PollForTemplate>,
     pub tags: Vec<TagForTemplate>,
     pub similar_threads: Vec<SimilarThreadForTemplate>,
+    /// Post HTML by post id, from the cache or rendered for this page
+    pub rendered_posts: HashMap<i32, String>,
+    /// How posters' names are shown, by user id
+    pub user_styles: HashMap<i32, GroupStyle>,
+    /// Who else is on this thread right now
+    pub viewers: crate::presence::ThreadViewers,
+    /// How links to other sites in posts are sent
+    pub outbound_links: crate::app_config::OutboundLinksConfig,
 }
 
-mod filters {
-    pub fn ugc(s: &str) -> ::askama::Result<String> {
-        Ok(crate::bbcode::parse(s))
+impl ThreadTemplate<'_> {
+    /// A post's content as HTML.
+    pub fn post_html(&self, post: &PostForTemplate) -> String {
+        let html = match self.rendered_posts.get(&post.id) {
+            Some(html) => html.clone(),
+            None => crate::bbcode::parse(post.content.as_deref().unwrap_or_default()),
+        };
+        crate::outbound::rewrite_links(&html, self.thread.id, &self.outbound_links)
+    }
+
+    /// Whether the viewer may see which links in this thread were clicked.
+    pub fn can_view_link_clicks(&self) -> bool {
+        self.outbound_links.enabled
+            && self.outbound_links.track_clicks
+            && super::outbound::can_view_link_clicks(&self.client, &self.thread)
+    }
+
+    /// How a poster's name is shown, if one of their groups styles it.
+    pub fn user_style(&self, user_id: i32) -> Option<&GroupStyle> {
+        self.user_styles.get(&user_id)
+    }
+
+    /// Address of this thread's oEmbed description, for discovery.
+    pub fn oembed_url(&self) -> String {
+        let base_url = crate::notifications::dispatcher::get_base_url();
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("url", &format!("{}/threads/{}/", base_url, self.thread.id))
+            .append_pair("format", "json")
+            .finish();
+        format!("{}/oembed?{}", base_url, query)
     }
 }
 
 pub const DEFAULT_POSTS_PER_PAGE: i32 = 25;
 
+/// Render posts' BBCode, reusing HTML cached for the same revision. The
+/// crate version is part of the key so upgrades don't serve old markup.
+#[tracing::instrument(skip_all, fields(posts = posts.len()))]
+pub async fn render_posts(
+    posts: &[(PostForTemplate, Option<UserProfile>)],
+) -> HashMap<i32, String> {
+    let revisions: Vec<(&PostForTemplate, String)> = posts
+        .iter()
+        .filter_map(|(post, _)| {
+            let revision_id = post.ugc_revision_id?;
+            post.content.as_ref()?;
+            Some((
+                post,
+                format!("{}:{}", env!("CARGO_PKG_VERSION"), revision_id),
+            ))
+        })
+        .collect();
+    let keys: Vec<String> = revisions.iter().map(|(_, key)| key.clone()).collect();
+    let cached: Vec<Option<String>> = cache::get_many(CacheScope::PostHtml, &keys).await;
+
+    let mut rendered = HashMap::with_capacity(revisions.len());
+    for ((post, key), html) in revisions.into_iter().zip(cached) {
+        let html = match html {
+            Some(html) => html,
+            None => {
+                let html = crate::bbcode::parse(post.content.as_deref().unwrap_or_default());
+                cache::set(CacheScope::PostHtml, &key, &html).await;
+                html
+            }
+        };
+        rendered.insert(post.id, html);
+    }
+    rendered
+}
+
 /// Returns which human-readable page number this position will appear in.
 pub fn get_page_for_pos(pos: i32, posts_per_page: i32) -> i32 {
     ((std::cmp::max(1, pos) - 1) / posts_per_page) + 1
@@ -199,6 +336,7 @@ pub fn get_url_for_pos(thread_id: i32, pos: i32) -> String {
 }
 
 /// Fetches poll data for a thread, if one exists.
+#[tracing::instrument]
 pub async fn get_poll_for_thread(
     thread_id: i32,
     user_id: Option<i32>,
@@ -229,36 +367,110 @@ pub async fn get_poll_for_thread(
     let total_votes: i32 = options.iter().map(|o| o.vote_count).sum();
 
     // Check if user has voted
-    let user_voted_options = if let Some(uid) = user_id {
+    let user_votes: HashMap<i32, Option<i32>> = if let Some(uid) = user_id {
         poll_votes::Entity::find()
             .filter(poll_votes::Column::PollId.eq(poll.id))
             .filter(poll_votes::Column::UserId.eq(uid))
             .all(db)
             .await?
             .into_iter()
-            .map(|v| v.option_id)
+            .map(|v| (v.option_id, v.rank))
             .collect()
     } else {
-        Vec::new()
+        HashMap::new()
     };
 
-    // Check if poll is closed
-    let is_closed = poll
-        .closes_at
-        .is_some_and(|closes_at| closes_at < chrono::Utc::now().naive_utc());
+    let is_closed = crate::web::polls::is_closed(&poll, chrono::Utc::now().naive_utc());
+    let has_voted = !user_votes.is_empty();
+    let results_visible =
+        crate::web::polls::can_see_results(&poll.results_visibility, has_voted, is_closed);
+
+    // Percentages are of voters, so on multiple choice polls they can add
+    // up to more than 100
+    #[derive(FromQueryResult)]
+    struct VoterCount {
+        count: i64,
+    }
+    let voter_count = VoterCount::find_by_statement(sea_orm::Statement::from_sql_and_values(
+        sea_orm::DbBackend::Postgres,
+        "SELECT COUNT(DISTINCT user_id) AS count FROM poll_votes WHERE poll_id = $1",
+        vec![poll.id.into()],
+    ))
+    .one(db)
+    .await?
+    .map_or(0, |c| c.count);
+
+    let mut voters = if poll.public_votes && results_visible {
+        crate::web::polls::voters_by_option(poll.id).await?
+    } else {
+        Default::default()
+    };
+
+    let is_ranked = poll.poll_type == crate::web::polls::POLL_RANKED;
+    let runoff = if is_ranked && results_visible {
+        let option_ids: Vec<i32> = options.iter().map(|o| o.id).collect();
+        let runoff = crate::web::polls::runoff_results(&poll, &option_ids).await?;
+        let option_text: HashMap<i32, &str> = options
+            .iter()
+            .map(|o| (o.id, o.option_text.as_str()))
+            .collect();
+        let text_of = |id: i32| {
+            option_text
+                .get(&id)
+                .copied()
+                .unwrap_or("(removed)")
+                .to_owned()
+        };
+
+        Some(RunoffForTemplate {
+            rounds: runoff
+                .rounds
+                .iter()
+                .enumerate()
+                .map(|(i, round)| {
+                    let active: i64 = round.tallies.iter().map(|(_, votes)| votes).sum();
+                    RunoffRoundForTemplate {
+                        number: i + 1,
+                        tallies: round
+                            .tallies
+                            .iter()
+                            .map(|(id, votes)| RunoffTallyForTemplate {
+                                option_text: text_of(*id),
+                                votes: *votes,
+                                percentage: if active > 0 {
+                                    (*votes as f64 / active as f64) * 100.0
+                                } else {
+                                    0.0
+                                },
+                                eliminated: round.eliminated == Some(*id),
+                            })
+                            .collect(),
+                        exhausted: round.exhausted,
+                    }
+                })
+                .collect(),
+            winner: runoff.winner.map(text_of),
+            ballots: runoff.ballots,
+        })
+    } else {
+        None
+    };
 
     // Build options with percentages
     let options_for_template: Vec<PollOptionForTemplate> = options
         .into_iter()
         .map(|opt| {
-            let percentage = if total_votes > 0 {
-                (opt.vote_count as f64 / total_votes as f64) * 100.0
+            let percentage = if voter_count > 0 {
+                (opt.vote_count as f64 / voter_count as f64) * 100.0
             } else {
                 0.0
             };
-            let user_selected = user_voted_options.contains(&opt.id);
+            let user_rank = user_votes.get(&opt.id).copied().flatten();
+            let user_selected = user_votes.contains_key(&opt.id);
             PollOptionForTemplate {
                 id: opt.id,
+                user_rank,
+                voters: voters.remove(&opt.id).unwrap_or_default(),
                 option_text: opt.option_text,
                 vote_count: opt.vote_count,
                 percentage,
@@ -267,23 +479,28 @@ pub async fn get_poll_for_thread(
         })
         .collect();
 
-    let has_voted = !user_voted_options.is_empty();
-
     Ok(Some(PollForTemplate {
         id: poll.id,
         question: poll.question,
         max_choices: poll.max_choices,
         allow_change_vote: poll.allow_change_vote,
-        show_results_before_vote: poll.show_results_before_vote,
+        is_ranked,
+        ranks: (1..=options_for_template.len() as i32).collect(),
+        results_visibility: poll.results_visibility,
+        public_votes: poll.public_votes,
         closes_at: poll.closes_at,
         is_closed,
         total_votes,
+        voter_count,
         options: options_for_template,
         has_voted,
+        results_visible,
+        runoff,
     }))
 }
 
 /// Fetches tags for a thread.
+#[tracing::instrument]
 pub async fn get_tags_for_thread(thread_id: i32) -> Result<Vec<TagForTemplate>, sea_orm::DbErr> {
     use sea_orm::EntityTrait;
 
@@ -385,6 +602,7 @@ pub async fn get_tags_for_threads(
 
 /// Fetches similar threads based on shared tags.
 /// Returns up to 5 threads that share the most tags with the current thread.
+#[tracing::instrument(skip(current_tag_ids))]
 pub async fn get_similar_threads(
     thread_id: i32,
     forum_id: i32,
@@ -460,8 +678,10 @@ pub async fn get_similar_threads(
 }
 
 /// Returns a Responder for a thread at a specific page.
+#[tracing::instrument(skip(client, presence))]
 async fn get_thread_and_replies_for_page(
     client: ClientCtx,
+    presence: &Presence,
     thread_id: i32,
     page: i32,
 ) -> Result<impl Responder, Error> {
@@ -469,12 +689,25 @@ async fn get_thread_and_replies_for_page(
     use crate::attachment::get_attachments_for_ugc_by_id;
     use crate::orm::forums;
 
-    let db = get_db_pool();
+    // Members read from the primary so their own posts show up right away.
+    let db = if client.is_user() {
+        get_db_pool()
+    } else {
+        get_db_read_pool()
+    };
     let thread = Thread::find_by_id(thread_id)
         .one(db)
         .await
         .map_err(error::ErrorInternalServerError)?
         .ok_or_else(|| error::ErrorNotFound("Thread not found."))?;
+    if !client.can_view_thread(&thread.id, &thread.forum_id) {
+        return Err(error::ErrorForbidden(
+            "You do not have permission to view this thread.",
+        ));
+    }
+    if let Some(visitor) = client.get_visitor() {
+        presence.record_thread_view(visitor, thread.id);
+    }
     let forum = forums::Entity::find_by_id(thread.forum_id)
         .one(db)
         .await
@@ -496,7 +729,7 @@ async fn get_thread_and_replies_for_page(
     };
 
     // Update thread to include views.
-    let db_clone = db;
+    let db_clone = get_db_pool();
     actix_web::rt::spawn(async move {
         Thread::update_many()
             .col_expr(
@@ -524,8 +757,11 @@ async fn get_thread_and_replies_for_page(
     .await
     .map_err(error::ErrorInternalServerError)?;
 
-    let attachments =
+    let mut attachments =
         get_attachments_for_ugc_by_id(posts.iter().map(|p| p.0.ugc_id).collect()).await;
+    for list in attachments.values_mut() {
+        list.retain(|attachment| attachment.is_visible_to(&client));
+    }
 
     // Check if user is watching this thread and email preference
     let (is_watching, email_on_reply) = if let Some(user_id) = client.get_id() {
@@ -577,6 +813,20 @@ async fn get_thread_and_replies_for_page(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    let rendered_posts = render_posts(&posts).await;
+
+    let mut poster_ids: Vec<i32> = posts
+        .iter()
+        .filter_map(|(_, user)| user.as_ref().map(|user| user.id))
+        .collect();
+    poster_ids.sort_unstable();
+    poster_ids.dedup();
+    let user_styles = crate::group::display_styles(get_db_pool(), &poster_ids)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let viewers = presence.thread_viewers(thread_id).await.unwrap_or_default();
+
     Ok(ThreadTemplate {
         client,
         forum,
@@ -590,6 +840,10 @@ async fn get_thread_and_replies_for_page(
         poll,
         tags,
         similar_threads,
+        rendered_posts,
+        user_styles,
+        viewers,
+        outbound_links: crate::app_config::outbound_links(),
     }
     .to_response())
 }
@@ -627,14 +881,14 @@ pub async fn update_thread_after_reply_is_deleted(id: i32) -> Result<(), DbErr>
     let post_count = match post_count_res {
         Ok(count) => count,
         Err(err) => {
-            log::error!("post_count error in update_thread: {:#?}", err);
+            tracing::error!(error = ?err, "post_count error in update_thread");
             return Err(err);
         }
     };
 
     match last_post_res {
         Err(err) => {
-            log::error!("last_post error in update_thread: {:#?}", err);
+            tracing::error!(error = ?err, "last_post error in update_thread");
             return Err(err);
         }
         Ok(Some(last_post)) => {
@@ -648,12 +902,15 @@ pub async fn update_thread_after_reply_is_deleted(id: i32) -> Result<(), DbErr>
                 .exec(db)
                 .await
             {
-                log::error!("update query error in update_thread: {:#?}", err);
+                tracing::error!(error = ?err, "update query error in update_thread");
                 return Err(err);
             }
         }
         Ok(None) => {
-            log::error!("thread has no last_post when trying to update thread.");
+            tracing::error!(
+                thread_id = id,
+                "thread has no last_post when trying to update thread."
+            );
         }
     }
 
@@ -682,11 +939,10 @@ pub async fn create_reply(
     };
 
     // Rate limiting - prevent post spam
-    if let Err(e) = crate::rate_limit::check_post_rate_limit(authenticated_user_id) {
-        log::warn!(
-            "Rate limit exceeded for post creation: user_id={}",
-            authenticated_user_id
-        );
+    if let Err(e) =
+        crate::rate_limit::check_post_rate_limit(authenticated_user_id, &client.get_groups()).await
+    {
+        tracing::warn!("Rate limit exceeded for post creation");
         return Err(error::ErrorTooManyRequests(format!(
             "You're posting too quickly. Please wait {} seconds.",
             e.retry_after_seconds
@@ -712,7 +968,7 @@ pub async fn create_reply(
                         let mut buf: Vec<u8> = Vec::with_capacity(128);
                         while let Some(chunk) = field.next().await {
                             let bytes = chunk.map_err(|e| {
-                                log::error!("create_reply: multipart read error: {}", e);
+                                tracing::error!(error = %e, "create_reply: multipart read error");
                                 actix_web::error::ErrorBadRequest("Error interpreting user input.")
                             })?;
                             buf.extend(bytes.to_owned());
@@ -726,7 +982,7 @@ pub async fn create_reply(
 
                         while let Some(chunk) = field.next().await {
                             let bytes = chunk.map_err(|e| {
-                                log::error!("create_reply: multipart read error: {}", e);
+                                tracing::error!(error = %e, "create_reply: multipart read error");
                                 actix_web::error::ErrorBadRequest("Error interpreting user input.")
                             })?;
 
@@ -736,7 +992,9 @@ pub async fn create_reply(
                         content = str::from_utf8(&buf).unwrap().to_owned();
                     }
                     "attachment" => {
-                        if let Some(payload) = insert_field_as_attachment(&mut field).await? {
+                        if let Some(payload) =
+                            insert_field_as_attachment(&mut field, &client).await?
+                        {
                             let filename = field
                                 .content_disposition()
                                 .get_filename()
@@ -745,6 +1003,17 @@ pub async fn create_reply(
                             uploads.push((filename, payload))
                         }
                     }
+                    "uploaded_attachment" => {
+                        if let Some(upload) =
+                            crate::filesystem::chunked::read_uploaded_attachment_field(
+                                &mut field,
+                                authenticated_user_id,
+                            )
+                            .await?
+                        {
+                            uploads.push(upload);
+                        }
+                    }
                     _ => {
                         return Err(error::ErrorBadRequest(format!(
                             "Unrecognized field '{}'",
@@ -779,11 +1048,10 @@ pub async fn create_reply(
 
     let spam_result = crate::spam::analyze_content(&content, user_post_count);
     if spam_result.is_spam {
-        log::warn!(
-            "Spam detected: user_id={}, score={:.2}, reasons={:?}",
-            authenticated_user_id,
-            spam_result.score,
-            spam_result.reasons
+        tracing::warn!(
+            score = spam_result.score,
+            reasons = ?spam_result.reasons,
+            "Spam detected"
         );
         return Err(error::ErrorBadRequest(
             "Your post has been flagged as potential spam. Please revise your content.",
@@ -791,12 +1059,12 @@ pub async fn create_reply(
     }
 
     // Word filter: check and apply filters to content
-    let filter_result = crate::word_filter::apply_filters(&content);
+    let filter_result =
+        crate::word_filter::apply_filters(&content, crate::word_filter::FilterScope::Posts);
     if filter_result.blocked {
-        log::warn!(
-            "Post blocked by word filter: user_id={}, patterns={:?}",
-            authenticated_user_id,
-            filter_result.matched_patterns
+        tracing::warn!(
+            patterns = ?filter_result.matched_patterns,
+            "Post blocked by word filter"
         );
         return Err(error::ErrorBadRequest(
             filter_result
@@ -819,10 +1087,10 @@ pub async fn create_reply(
         .map_err(|_| error::ErrorInternalServerError("Could not look up thread."))?
         .ok_or_else(|| error::ErrorNotFound("Thread not found."))?;
 
-    // Check forum-specific permission for posting (inherits from forum)
-    if !client.can_post_in_forum(&our_thread.forum_id) {
+    // Check thread-specific permission for posting (inherits from forum)
+    if !client.can_post_in_thread(&our_thread) {
         return Err(error::ErrorForbidden(
-            "You do not have permission to post in this forum.",
+            "You do not have permission to reply to this thread.",
         ));
     }
 
@@ -912,13 +1180,12 @@ pub async fn create_reply(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    crate::filesystem::visibility::refresh_attachments_visibility(uploads.iter().map(|u| u.1.id))
+        .await;
+
     // If post is pending approval, show message
     if needs_approval {
-        log::info!(
-            "Reply by user {} in thread {} is pending first post approval",
-            authenticated_user_id,
-            thread_id
-        );
+        tracing::info!(thread_id, "Reply is pending first post approval");
         return Ok(HttpResponse::Ok().content_type("text/html").body(format!(
             r#"<!DOCTYPE html>
 <html>
@@ -953,45 +1220,48 @@ pub async fn create_reply(
 
     // Send notifications asynchronously (don't block on errors)
     let post_content = content.clone();
-    actix::spawn(async move {
-        // Detect and notify mentions
-        if let Err(e) = crate::notifications::dispatcher::detect_and_notify_mentions(
-            &post_content,
-            post_id,
-            thread_id,
-            authenticated_user_id,
-        )
-        .await
-        {
-            log::error!("Failed to send mention notifications: {}", e);
-        }
+    actix::spawn(
+        async move {
+            // Detect and notify mentions
+            if let Err(e) = crate::notifications::dispatcher::detect_and_notify_mentions(
+                &post_content,
+                post_id,
+                thread_id,
+                authenticated_user_id,
+            )
+            .await
+            {
+                tracing::error!(error = %e, "Failed to send mention notifications");
+            }
 
-        // Detect and notify quotes
-        if let Err(e) = crate::notifications::dispatcher::detect_and_notify_quotes(
-            &post_content,
-            post_id,
-            thread_id,
-            authenticated_user_id,
-        )
-        .await
-        {
-            log::error!("Failed to send quote notifications: {}", e);
-        }
+            // Detect and notify quotes
+            if let Err(e) = crate::notifications::dispatcher::detect_and_notify_quotes(
+                &post_content,
+                post_id,
+                thread_id,
+                authenticated_user_id,
+            )
+            .await
+            {
+                tracing::error!(error = %e, "Failed to send quote notifications");
+            }
 
-        // Notify thread participants
-        if let Err(e) = crate::notifications::dispatcher::notify_thread_reply(
-            thread_id,
-            post_id,
-            authenticated_user_id,
-        )
-        .await
-        {
-            log::error!("Failed to send thread reply notifications: {}", e);
-        }
+            // Notify thread participants
+            if let Err(e) = crate::notifications::dispatcher::notify_thread_reply(
+                thread_id,
+                post_id,
+                authenticated_user_id,
+            )
+            .await
+            {
+                tracing::error!(error = %e, "Failed to send thread reply notifications");
+            }
 
-        // Check and award any automatic badges the user may have earned
-        crate::badges::check_and_award_automatic_badges(authenticated_user_id).await;
-    });
+            // Check and award any automatic badges the user may have earned
+            crate::badges::check_and_award_automatic_badges(authenticated_user_id).await;
+        }
+        .in_current_span(),
+    );
 
     // Record activity for the feed (async, non-blocking)
     let forum_id = our_thread.forum_id;
@@ -1001,20 +1271,33 @@ pub async fn create_reply(
     } else {
         content.clone()
     };
-    actix::spawn(async move {
-        if let Err(e) = crate::activities::record_post_created(
-            authenticated_user_id,
-            thread_id,
-            post_id,
-            forum_id,
-            &thread_title,
-            &content_preview,
-        )
-        .await
-        {
-            log::warn!("Failed to record post creation activity: {}", e);
+    actix::spawn(
+        async move {
+            if let Err(e) = crate::activities::record_post_created(
+                authenticated_user_id,
+                thread_id,
+                post_id,
+                forum_id,
+                &thread_title,
+                &content_preview,
+            )
+            .await
+            {
+                tracing::warn!(error = %e, "Failed to record post creation activity");
+            }
         }
-    });
+        .in_current_span(),
+    );
+
+    crate::webhooks::post_created(
+        post_id,
+        thread_id,
+        forum_id,
+        authenticated_user_id,
+        &content,
+    );
+
+    crate::cache::fragment::posts_changed().await;
 
     Ok(HttpResponse::Found()
         .append_header((
@@ -1061,20 +1344,25 @@ pub async fn view_thread_unread(
 }
 
 #[get("/threads/{thread_id}/")]
-pub async fn view_thread(client: ClientCtx, path: web::Path<i32>) -> Result<impl Responder, Error> {
-    get_thread_and_replies_for_page(client, path.into_inner(), 1).await
+pub async fn view_thread(
+    client: ClientCtx,
+    state: web::Data<AppState>,
+    path: web::Path<i32>,
+) -> Result<impl Responder, Error> {
+    get_thread_and_replies_for_page(client, &state.presence, path.into_inner(), 1).await
 }
 
 #[get("/threads/{thread_id}/page-{page}")]
 pub async fn view_thread_page(
     client: ClientCtx,
+    state: web::Data<AppState>,
     path: web::Path<(i32, i32)>,
 ) -> Result<impl Responder, Error> {
     let params = path.into_inner();
     if params.1 > 1 {
-        get_thread_and_replies_for_page(client, params.0, params.1).await
+        get_thread_and_replies_for_page(client, &state.presence, params.0, params.1).await
     } else {
-        get_thread_and_replies_for_page(client, params.0, 1).await
+        get_thread_and_replies_for_page(client, &state.presence, params.0, 1).await
         //Ok(HttpResponse::Found()
         //    .append_header(("Location", format!("/threads/{}/", params.0)))
         //    .finish())
@@ -1152,8 +1440,22 @@ pub fn validate_thread_form(
                 }
             }
 
-            // Validate max_choices
-            let max_choices = form.poll_max_choices.clamp(1, options.len() as i32);
+            let poll_type = form.poll_type.trim();
+            if ![
+                crate::web::polls::POLL_CHOICE,
+                crate::web::polls::POLL_RANKED,
+            ]
+            .contains(&poll_type)
+            {
+                return Err(error::ErrorBadRequest("Invalid poll type."));
+            }
+
+            // Validate max_choices. Ranked ballots may order every option.
+            let max_choices = if poll_type == crate::web::polls::POLL_RANKED {
+                options.len() as i32
+            } else {
+                form.poll_max_choices.clamp(1, options.len() as i32)
+            };
 
             // Parse closes_at if provided
             let closes_at = if let Some(ref closes_str) = form.poll_closes_at {
@@ -1172,12 +1474,36 @@ pub fn validate_thread_form(
                 None
             };
 
+            if closes_at.is_some_and(|closes_at| closes_at <= chrono::Utc::now().naive_utc()) {
+                return Err(error::ErrorBadRequest(
+                    "Poll closing date must be in the future.",
+                ));
+            }
+
+            let results_visibility = form.poll_results_visibility.trim();
+            if ![
+                crate::web::polls::RESULTS_ALWAYS,
+                crate::web::polls::RESULTS_AFTER_VOTE,
+                crate::web::polls::RESULTS_AFTER_CLOSE,
+            ]
+            .contains(&results_visibility)
+            {
+                return Err(error::ErrorBadRequest("Invalid poll results visibility."));
+            }
+            if results_visibility == crate::web::polls::RESULTS_AFTER_CLOSE && closes_at.is_none() {
+                return Err(error::ErrorBadRequest(
+                    "Poll results can only be hidden until close if the poll has a closing date.",
+                ));
+            }
+
             Some(ValidatedPoll {
                 question: question.to_owned(),
                 options,
                 max_choices,
                 allow_change_vote: form.poll_allow_change_vote,
-                show_results_before_vote: form.poll_show_results_before_vote,
+                poll_type: poll_type.to_owned(),
+                results_visibility: results_visibility.to_owned(),
+                public_votes: form.poll_public_votes,
                 closes_at,
             })
         } else {
@@ -1403,6 +1729,8 @@ pub async fn delete_thread(
         }
     }
 
+    crate::cache::fragment::posts_changed().await;
+
     Ok(HttpResponse::Found()
         .append_header(("Location", format!("/forums/{}/", thread.forum_id)))
         .finish())
@@ -1474,6 +1802,8 @@ pub async fn restore_thread(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    crate::cache::fragment::posts_changed().await;
+
     Ok(HttpResponse::Found()
         .append_header(("Location", format!("/threads/{}/", thread_id)))
         .finish())
@@ -1651,6 +1981,8 @@ pub async fn move_thread(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    crate::cache::fragment::posts_changed().await;
+
     Ok(HttpResponse::Found()
         .append_header(("Location", format!("/threads/{}/", thread_id)))
         .finish())
@@ -1765,6 +2097,8 @@ pub async fn merge_threads(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    crate::cache::fragment::posts_changed().await;
+
     Ok(HttpResponse::Found()
         .append_header(("Location", format!("/threads/{}/", target_thread_id)))
         .finish())
@@ -1,10 +1,14 @@
 //! URL unfurling - fetches and caches metadata for URLs
 //!
 //! Provides an API endpoint for getting URL metadata (title, description, image)
-//! which is cached in the database for performance.
+//! which is cached in the database for performance. Pages are described by
+//! their Open Graph tags, and by their oEmbed description when the site is
+//! one of the configured providers. Only public addresses are ever fetched.
 
 use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
 use crate::orm::unfurl_cache;
+use crate::ssrf::{pinned_client, read_limited};
 use actix_web::{error, get, web, Error, HttpRequest, HttpResponse};
 use chrono::Utc;
 use sea_orm::{entity::*, ColumnTrait, EntityTrait, QueryFilter};
@@ -31,6 +35,11 @@ pub struct UnfurlResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub favicon_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_name: Option<String>,
+    /// oEmbed type (photo, video, rich or link) when the provider gave one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embed_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
     /// Site type for special rendering (youtube, twitter, github, or null for generic)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -58,19 +67,23 @@ pub struct UnfurlQuery {
     url: String,
 }
 
-/// Cache duration in hours - refetch after this time
-const CACHE_DURATION_HOURS: i64 = 24;
-
 /// Maximum time to wait for URL fetch
 const FETCH_TIMEOUT_SECS: u64 = 10;
 
 /// Maximum response body size (1MB)
 const MAX_BODY_SIZE: usize = 1024 * 1024;
 
+/// Maximum oEmbed response size
+const MAX_OEMBED_SIZE: usize = 64 * 1024;
+
+/// Redirects followed before a fetch is given up
+const MAX_REDIRECTS: usize = 5;
+
 /// Get unfurl metadata for a URL
 #[get("/api/unfurl")]
 async fn get_unfurl(
     req: HttpRequest,
+    client: ClientCtx,
     query: web::Query<UnfurlQuery>,
 ) -> Result<HttpResponse, Error> {
     // Get client IP for rate limiting
@@ -79,7 +92,7 @@ async fn get_unfurl(
         .unwrap_or_else(|| "unknown".to_string());
 
     // Rate limiting - uses API rate limit
-    if let Err(e) = crate::rate_limit::check_api_rate_limit(&ip) {
+    if let Err(e) = crate::rate_limit::check_api_rate_limit(&ip, &client.get_groups()).await {
         log::warn!("Unfurl rate limit exceeded for IP: {}", ip);
         return Err(error::ErrorTooManyRequests(format!(
             "Too many requests. Please try again in {} seconds.",
@@ -98,6 +111,12 @@ async fn get_unfurl(
         _ => return Err(error::ErrorBadRequest("Only HTTP/HTTPS URLs are supported")),
     }
 
+    // Links to this site are described from the database rather than fetched,
+    // and aren't cached, since what a guest may read can change.
+    if let Some(local) = unfurl_local(&client, url, &parsed_url).await? {
+        return Ok(HttpResponse::Ok().json(local));
+    }
+
     // Compute URL hash for cache lookup
     let url_hash = compute_url_hash(url);
 
@@ -111,8 +130,7 @@ async fn get_unfurl(
         .map_err(error::ErrorInternalServerError)?
     {
         // Check if cache is still fresh
-        let age = Utc::now().naive_utc() - cached.fetched_at;
-        if age.num_hours() < CACHE_DURATION_HOURS {
+        if cached.expires_at > Utc::now().naive_utc() {
             // Detect site type from URL (not cached, but fast)
             let (site_type, embed_data) = detect_site_type(&parsed_url);
 
@@ -125,6 +143,8 @@ async fn get_unfurl(
                 image_url: cached.image_url,
                 site_name: cached.site_name,
                 favicon_url: cached.favicon_url,
+                author_name: cached.author_name,
+                embed_type: cached.embed_type,
                 error: cached.error_message,
                 site_type,
                 embed_data,
@@ -139,9 +159,16 @@ async fn get_unfurl(
     }
 
     // Fetch fresh data
-    let result = fetch_url_metadata(url, &parsed_url).await;
+    let (result, max_age) = fetch_url_metadata(url, &parsed_url).await;
 
-    // Store in cache
+    // Store in cache, for less time if the fetch failed or the provider asked
+    let config = crate::app_config::unfurl();
+    let ttl = if result.error.is_some() {
+        chrono::Duration::hours(config.error_cache_ttl_hours)
+    } else {
+        chrono::Duration::hours(config.cache_ttl_hours)
+    };
+    let ttl = max_age.map_or(ttl, |max_age| ttl.min(max_age));
     let now = Utc::now().naive_utc();
     let cache_entry = unfurl_cache::ActiveModel {
         url_hash: Set(url_hash),
@@ -154,6 +181,9 @@ async fn get_unfurl(
         fetched_at: Set(now),
         error_message: Set(result.error.clone()),
         created_at: Set(now),
+        author_name: Set(result.author_name.clone()),
+        embed_type: Set(result.embed_type.clone()),
+        expires_at: Set(now + ttl),
         ..Default::default()
     };
 
@@ -165,6 +195,44 @@ async fn get_unfurl(
     Ok(HttpResponse::Ok().json(result))
 }
 
+/// Unfurl a link to a thread or post on this site using its oEmbed description
+async fn unfurl_local(
+    client: &ClientCtx,
+    url: &str,
+    parsed_url: &url::Url,
+) -> Result<Option<UnfurlResponse>, Error> {
+    use super::oembed::{find_embed, EmbedTarget};
+
+    let Ok(base_url) = url::Url::parse(&crate::notifications::dispatcher::get_base_url()) else {
+        return Ok(None);
+    };
+    let Some(target) = EmbedTarget::from_url(parsed_url, &base_url) else {
+        return Ok(None);
+    };
+
+    let embed = find_embed(&client.as_guest().await, target)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    let found = embed.is_some();
+    Ok(Some(UnfurlResponse {
+        success: found,
+        url: url.to_string(),
+        title: embed.as_ref().map(|embed| embed.title.clone()),
+        description: embed
+            .map(|embed| embed.excerpt)
+            .filter(|excerpt| !excerpt.is_empty()),
+        image_url: None,
+        site_name: Some(client.site_title()),
+        favicon_url: None,
+        author_name: None,
+        embed_type: None,
+        error: (!found).then(|| "Thread or post not found".to_string()),
+        site_type: None,
+        embed_data: None,
+    }))
+}
+
 /// Compute SHA256 hash of URL for cache key
 fn compute_url_hash(url: &str) -> String {
     use blake3::Hasher;
@@ -173,51 +241,81 @@ fn compute_url_hash(url: &str) -> String {
     hasher.finalize().to_hex().to_string()
 }
 
-/// Fetch URL and extract metadata
-async fn fetch_url_metadata(url: &str, parsed_url: &url::Url) -> UnfurlResponse {
-    // Detect site type upfront (used for all responses, including errors)
-    let (site_type, embed_data) = detect_site_type(parsed_url);
+/// A response for a URL that couldn't be described
+fn failed(
+    url: &str,
+    error: String,
+    site_type: Option<String>,
+    embed_data: Option<EmbedData>,
+) -> UnfurlResponse {
+    UnfurlResponse {
+        success: false,
+        url: url.to_string(),
+        title: None,
+        description: None,
+        image_url: None,
+        site_name: None,
+        favicon_url: None,
+        author_name: None,
+        embed_type: None,
+        error: Some(error),
+        site_type,
+        embed_data,
+    }
+}
 
-    let client = match reqwest::Client::builder()
-        .timeout(Duration::from_secs(FETCH_TIMEOUT_SECS))
-        .user_agent("Mozilla/5.0 (compatible; DumpsterBot/1.0)")
-        .redirect(reqwest::redirect::Policy::limited(5))
-        .build()
-    {
-        Ok(c) => c,
-        Err(e) => {
-            return UnfurlResponse {
-                success: false,
-                url: url.to_string(),
-                title: None,
-                description: None,
-                image_url: None,
-                site_name: None,
-                favicon_url: None,
-                error: Some(format!("Failed to create HTTP client: {}", e)),
-                site_type,
-                embed_data,
-            };
+/// GET a URL, following redirects by hand so that every hop is checked
+/// against private addresses and connects to the address that was checked.
+async fn guarded_get(url: &url::Url) -> Result<reqwest::Response, String> {
+    let mut url = url.clone();
+
+    for _ in 0..=MAX_REDIRECTS {
+        match url.scheme() {
+            "http" | "https" => {}
+            _ => return Err("Only HTTP/HTTPS URLs are supported".to_string()),
         }
-    };
+        let client = pinned_client(
+            &url,
+            reqwest::Client::builder()
+                .timeout(Duration::from_secs(FETCH_TIMEOUT_SECS))
+                .user_agent("Mozilla/5.0 (compatible; DumpsterBot/1.0)"),
+        )
+        .await?;
+
+        let response = client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch URL: {}", e))?;
 
-    // Fetch the URL
-    let response = match client.get(url).send().await {
-        Ok(r) => r,
-        Err(e) => {
-            return UnfurlResponse {
-                success: false,
-                url: url.to_string(),
-                title: None,
-                description: None,
-                image_url: None,
-                site_name: None,
-                favicon_url: None,
-                error: Some(format!("Failed to fetch URL: {}", e)),
-                site_type,
-                embed_data,
-            };
+        if !response.status().is_redirection() {
+            return Ok(response);
         }
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or("Redirect without a location")?;
+        url = url
+            .join(location)
+            .map_err(|_| "Redirect to an invalid URL".to_string())?;
+    }
+
+    Err("Too many redirects".to_string())
+}
+
+/// Fetch URL and extract metadata, with how long the provider asked for it
+/// to be cached if it said
+async fn fetch_url_metadata(
+    url: &str,
+    parsed_url: &url::Url,
+) -> (UnfurlResponse, Option<chrono::Duration>) {
+    // Detect site type upfront (used for all responses, including errors)
+    let (site_type, embed_data) = detect_site_type(parsed_url);
+
+    let response = match guarded_get(parsed_url).await {
+        Ok(r) => r,
+        Err(e) => return (failed(url, e, site_type, embed_data), None),
     };
 
     // Check content type
@@ -228,59 +326,131 @@ async fn fetch_url_metadata(url: &str, parsed_url: &url::Url) -> UnfurlResponse
         .unwrap_or("");
 
     if !content_type.contains("text/html") && !content_type.contains("application/xhtml") {
-        return UnfurlResponse {
-            success: false,
-            url: url.to_string(),
-            title: None,
-            description: None,
-            image_url: None,
-            site_name: None,
-            favicon_url: None,
-            error: Some("URL does not return HTML content".to_string()),
-            site_type,
-            embed_data,
-        };
+        return (
+            failed(
+                url,
+                "URL does not return HTML content".to_string(),
+                site_type,
+                embed_data,
+            ),
+            None,
+        );
     }
 
     // Get body with size limit
-    let body = match response.bytes().await {
-        Ok(b) => {
-            if b.len() > MAX_BODY_SIZE {
-                return UnfurlResponse {
-                    success: false,
-                    url: url.to_string(),
-                    title: None,
-                    description: None,
-                    image_url: None,
-                    site_name: None,
-                    favicon_url: None,
-                    error: Some("Response too large".to_string()),
-                    site_type,
-                    embed_data,
-                };
-            }
-            b
-        }
-        Err(e) => {
-            return UnfurlResponse {
-                success: false,
-                url: url.to_string(),
-                title: None,
-                description: None,
-                image_url: None,
-                site_name: None,
-                favicon_url: None,
-                error: Some(format!("Failed to read response: {}", e)),
-                site_type,
-                embed_data,
-            };
-        }
+    let body = match read_limited(response, MAX_BODY_SIZE).await {
+        Ok(b) => b,
+        Err(e) => return (failed(url, e, site_type, embed_data), None),
     };
 
     let html = String::from_utf8_lossy(&body);
 
     // Parse HTML and extract metadata
-    extract_metadata(&html, url, parsed_url, site_type, embed_data)
+    let mut result = extract_metadata(&html, url, parsed_url, site_type, embed_data);
+
+    // Providers on the whitelist describe their pages better through oEmbed
+    let providers = crate::app_config::unfurl().oembed_providers;
+    let mut max_age = None;
+    if is_oembed_provider(parsed_url, &providers) {
+        if let Some(endpoint) = oembed_endpoint(&html, parsed_url)
+            .filter(|endpoint| is_oembed_provider(endpoint, &providers))
+        {
+            match fetch_oembed(&endpoint).await {
+                Ok(oembed) => {
+                    max_age = oembed.max_age();
+                    oembed.apply_to(&mut result);
+                }
+                Err(e) => log::debug!("oEmbed for {} failed: {}", url, e),
+            }
+        }
+    }
+
+    (result, max_age)
+}
+
+/// Whether a URL's host is one of the oEmbed providers or a subdomain of one
+fn is_oembed_provider(url: &url::Url, providers: &[String]) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let host = host.to_lowercase();
+    providers.iter().any(|provider| {
+        let provider = provider.to_lowercase();
+        host == provider || host.ends_with(&format!(".{}", provider))
+    })
+}
+
+/// The JSON oEmbed endpoint a page advertises, if any
+fn oembed_endpoint(html: &str, base: &url::Url) -> Option<url::Url> {
+    use scraper::{Html, Selector};
+
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("link[type='application/json+oembed']").ok()?;
+    let href = document.select(&selector).next()?.value().attr("href")?;
+    base.join(href).ok()
+}
+
+/// The parts of an oEmbed response a preview uses. Its `html` is never
+/// used: previews are built from plain fields so no provider markup ends up
+/// in a post.
+#[derive(Deserialize, Debug, Default)]
+struct OembedResponse {
+    #[serde(rename = "type")]
+    embed_type: Option<String>,
+    title: Option<String>,
+    author_name: Option<String>,
+    provider_name: Option<String>,
+    thumbnail_url: Option<String>,
+    /// The image itself, for photos
+    url: Option<String>,
+    /// Seconds the provider asks for the description to be cached; some
+    /// send it as a string
+    cache_age: Option<serde_json::Value>,
+}
+
+impl OembedResponse {
+    fn max_age(&self) -> Option<chrono::Duration> {
+        let seconds = match self.cache_age.as_ref()? {
+            serde_json::Value::Number(n) => n.as_i64()?,
+            serde_json::Value::String(s) => s.trim().parse().ok()?,
+            _ => return None,
+        };
+        (seconds > 0).then(|| chrono::Duration::seconds(seconds))
+    }
+
+    /// Fill in a preview from this description, which wins over Open Graph
+    fn apply_to(self, result: &mut UnfurlResponse) {
+        let image = match self.embed_type.as_deref() {
+            Some("photo") => self.url.or(self.thumbnail_url),
+            _ => self.thumbnail_url,
+        }
+        .filter(|image| image.starts_with("https://") || image.starts_with("http://"));
+
+        if let Some(title) = self.title {
+            result.title = Some(truncate_string(&title, 200));
+        }
+        if let Some(provider_name) = self.provider_name {
+            result.site_name = Some(truncate_string(&provider_name, 100));
+        }
+        if let Some(author_name) = self.author_name {
+            result.author_name = Some(truncate_string(&author_name, 100));
+        }
+        result.image_url = image.or(result.image_url.take());
+        result.embed_type = self
+            .embed_type
+            .filter(|t| matches!(t.as_str(), "photo" | "video" | "rich" | "link"));
+        result.success = result.title.is_some() || result.description.is_some();
+    }
+}
+
+/// Fetch and parse an oEmbed description
+async fn fetch_oembed(endpoint: &url::Url) -> Result<OembedResponse, String> {
+    let response = guarded_get(endpoint).await?;
+    if !response.status().is_success() {
+        return Err(format!("oEmbed endpoint answered {}", response.status()));
+    }
+    let body = read_limited(response, MAX_OEMBED_SIZE).await?;
+    serde_json::from_slice(&body).map_err(|e| format!("Invalid oEmbed response: {}", e))
 }
 
 /// Extract Open Graph and meta tags from HTML
@@ -369,6 +539,8 @@ fn extract_metadata(
         image_url,
         site_name,
         favicon_url,
+        author_name: None,
+        embed_type: None,
         error: None,
         site_type,
         embed_data,
@@ -385,7 +557,11 @@ fn truncate_string(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+        let mut end = max_len.saturating_sub(3);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}...", &s[..end])
     }
 }
 
@@ -571,3 +747,76 @@ fn extract_github_repo(url: &url::Url) -> Option<(String, String)> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> url::Url {
+        url::Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_oembed_providers() {
+        let providers = vec!["youtube.com".to_string(), "vimeo.com".to_string()];
+        assert!(is_oembed_provider(
+            &url("https://www.youtube.com/watch?v=x"),
+            &providers
+        ));
+        assert!(is_oembed_provider(&url("https://vimeo.com/1"), &providers));
+        assert!(!is_oembed_provider(
+            &url("https://notyoutube.com/"),
+            &providers
+        ));
+        assert!(!is_oembed_provider(
+            &url("https://youtube.com.evil.test/"),
+            &providers
+        ));
+    }
+
+    #[test]
+    fn test_oembed_endpoint() {
+        let html = r#"<html><head>
+            <link rel="alternate" type="application/json+oembed" href="/oembed?url=x&amp;format=json">
+        </head></html>"#;
+        assert_eq!(
+            oembed_endpoint(html, &url("https://vimeo.com/1")),
+            Some(url("https://vimeo.com/oembed?url=x&format=json"))
+        );
+        assert_eq!(
+            oembed_endpoint("<html></html>", &url("https://vimeo.com/1")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_apply_oembed() {
+        let mut result = failed("https://vimeo.com/1", "unused".to_string(), None, None);
+        result.error = None;
+        result.image_url = Some("https://vimeo.com/og.jpg".to_string());
+
+        let oembed: OembedResponse = serde_json::from_str(
+            r#"{"type": "video", "title": "A Film", "author_name": "Someone",
+                "provider_name": "Vimeo", "thumbnail_url": "https://i.vimeocdn.com/1.jpg",
+                "html": "<iframe src=x></iframe>", "cache_age": "3600"}"#,
+        )
+        .unwrap();
+        assert_eq!(oembed.max_age(), Some(chrono::Duration::hours(1)));
+        oembed.apply_to(&mut result);
+
+        assert!(result.success);
+        assert_eq!(result.title.as_deref(), Some("A Film"));
+        assert_eq!(result.author_name.as_deref(), Some("Someone"));
+        assert_eq!(result.site_name.as_deref(), Some("Vimeo"));
+        assert_eq!(
+            result.image_url.as_deref(),
+            Some("https://i.vimeocdn.com/1.jpg")
+        );
+        assert_eq!(result.embed_type.as_deref(), Some("video"));
+    }
+
+    #[test]
+    fn test_truncate_string_keeps_characters_whole() {
+        assert_eq!(truncate_string("héllo wörld", 6), "hé...");
+    }
+}
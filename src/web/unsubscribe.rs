@@ -0,0 +1,136 @@
+//! One-click unsubscribe from notification emails.
+//!
+//! Links in notification emails open a confirmation page, so that mail
+//! scanners following links don't unsubscribe anyone. Mail clients that
+//! support RFC 8058 POST to the same address, which unsubscribes at once.
+
+use crate::db::get_db_pool;
+use crate::email::mass::set_announcements;
+use crate::email::unsubscribe;
+use crate::middleware::ClientCtx;
+use crate::notifications::{disable_email, PREFERENCE_TYPES};
+use crate::orm::users;
+use actix_web::{error, get, post, web, Error, Responder};
+use askama_actix::{Template, TemplateToResponse};
+use sea_orm::EntityTrait;
+use serde::Deserialize;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(unsubscribe_form).service(unsubscribe_post);
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeQuery {
+    token: String,
+}
+
+#[derive(Template)]
+#[template(path = "email_unsubscribe.html")]
+struct UnsubscribeTemplate {
+    client: ClientCtx,
+    token: String,
+    /// What the link unsubscribes from, or None if the link is bad
+    description: Option<String>,
+    done: bool,
+}
+
+/// Describes an unsubscribe scope for the confirmation page.
+fn describe(scope: &str) -> String {
+    if scope == unsubscribe::ANNOUNCEMENTS {
+        return "announcement emails".to_string();
+    }
+    PREFERENCE_TYPES
+        .iter()
+        .find(|(t, _, _)| *t == scope)
+        .map(|(_, label, _)| format!("emails about {}", label.to_lowercase()))
+        .unwrap_or_else(|| "all notification emails".to_string())
+}
+
+/// The user and scope of a valid token whose user still exists.
+async fn check_token(token: &str) -> Result<Option<(i32, String)>, Error> {
+    let Some((user_id, scope)) = unsubscribe::verify(token) else {
+        return Ok(None);
+    };
+
+    let user = users::Entity::find_by_id(user_id)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch user: {}", e);
+            error::ErrorInternalServerError("Database error")
+        })?;
+
+    Ok(user.map(|_| (user_id, scope)))
+}
+
+/// GET /email/unsubscribe?token= - Confirm unsubscribing
+#[get("/email/unsubscribe")]
+async fn unsubscribe_form(
+    client: ClientCtx,
+    query: web::Query<UnsubscribeQuery>,
+) -> Result<impl Responder, Error> {
+    let query = query.into_inner();
+    let description = check_token(&query.token)
+        .await?
+        .map(|(_, scope)| describe(&scope));
+
+    Ok(UnsubscribeTemplate {
+        client,
+        token: query.token,
+        description,
+        done: false,
+    }
+    .to_response())
+}
+
+/// POST /email/unsubscribe?token= - Unsubscribe, from the confirmation page
+/// or a mail client's one-click request. No login or CSRF token is needed;
+/// the signed token is the authorisation.
+#[post("/email/unsubscribe")]
+async fn unsubscribe_post(
+    client: ClientCtx,
+    query: web::Query<UnsubscribeQuery>,
+) -> Result<impl Responder, Error> {
+    let query = query.into_inner();
+    let Some((user_id, scope)) = check_token(&query.token).await? else {
+        return Ok(UnsubscribeTemplate {
+            client,
+            token: query.token,
+            description: None,
+            done: false,
+        }
+        .to_response());
+    };
+
+    let result = if scope == unsubscribe::ANNOUNCEMENTS {
+        set_announcements(user_id, false).await
+    } else {
+        disable_email(user_id, &unsubscribe::notification_types(&scope)).await
+    };
+    result.map_err(|e| {
+        log::error!("Failed to unsubscribe user {}: {}", user_id, e);
+        error::ErrorInternalServerError("Database error")
+    })?;
+
+    log::info!("User {} unsubscribed from {} emails", user_id, scope);
+
+    Ok(UnsubscribeTemplate {
+        client,
+        token: query.token,
+        description: Some(describe(&scope)),
+        done: true,
+    }
+    .to_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe("thread_watch"), "emails about watched threads");
+        assert_eq!(describe(unsubscribe::ALL), "all notification emails");
+        assert_eq!(describe(unsubscribe::ANNOUNCEMENTS), "announcement emails");
+    }
+}
@@ -0,0 +1,360 @@
+//! Admin pages for outgoing webhooks and their delivery log.
+
+use crate::db::get_db_pool;
+use crate::middleware::ClientCtx;
+use crate::orm::{webhook_deliveries, webhooks};
+use crate::webhooks::{self as hooks, WebhookEvent};
+use actix_web::{error, get, post, web, Error, HttpResponse, Responder};
+use askama_actix::{Template, TemplateToResponse};
+use chrono::Utc;
+use sea_orm::{entity::*, query::*};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Deliveries per page of the log
+const DELIVERIES_PAGE_SIZE: u64 = 50;
+
+pub(super) fn configure(conf: &mut actix_web::web::ServiceConfig) {
+    conf.service(view_webhooks)
+        .service(create_webhook)
+        .service(toggle_webhook)
+        .service(ping_webhook)
+        .service(delete_webhook)
+        .service(redeliver);
+}
+
+/// Filters for the delivery log. Empty or unrecognised fields are ignored.
+#[derive(Debug, Default, Deserialize)]
+pub struct DeliveryFilter {
+    /// Only deliveries to this webhook
+    pub webhook: Option<String>,
+    /// `pending`, `delivered`, `retrying` or `failed`
+    pub status: Option<String>,
+    pub page: Option<u64>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+impl DeliveryFilter {
+    pub fn webhook_id(&self) -> Option<i32> {
+        non_empty(&self.webhook).and_then(|id| id.parse().ok())
+    }
+
+    pub fn status_value(&self) -> &str {
+        non_empty(&self.status)
+            .filter(|status| matches!(*status, "pending" | "delivered" | "retrying" | "failed"))
+            .unwrap_or_default()
+    }
+
+    pub fn page(&self) -> u64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    /// Query string repeating these filters, for pagination links.
+    pub fn query_string(&self) -> String {
+        let webhook = self
+            .webhook_id()
+            .map(|id| id.to_string())
+            .unwrap_or_default();
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in [
+            ("webhook", webhook.as_str()),
+            ("status", self.status_value()),
+        ] {
+            if !value.is_empty() {
+                query.append_pair(name, value);
+            }
+        }
+        query.finish()
+    }
+
+    fn condition(&self) -> Condition {
+        let mut condition = Condition::all();
+        if let Some(webhook_id) = self.webhook_id() {
+            condition = condition.add(webhook_deliveries::Column::WebhookId.eq(webhook_id));
+        }
+        if !self.status_value().is_empty() {
+            condition = condition.add(webhook_deliveries::Column::Status.eq(self.status_value()));
+        }
+        condition
+    }
+}
+
+/// A row of the delivery log.
+pub struct DeliveryView {
+    pub delivery: webhook_deliveries::Model,
+    pub webhook_name: String,
+}
+
+#[derive(Template)]
+#[template(path = "admin/webhooks.html")]
+struct WebhooksTemplate {
+    client: ClientCtx,
+    webhooks: Vec<webhooks::Model>,
+    deliveries: Vec<DeliveryView>,
+    filter: DeliveryFilter,
+    filter_query: String,
+    page: u64,
+    total_pages: u64,
+    total: u64,
+}
+
+impl WebhooksTemplate {
+    /// Events offered when creating a webhook.
+    fn events(&self) -> [WebhookEvent; 4] {
+        WebhookEvent::all()
+    }
+}
+
+/// GET /admin/webhooks - Webhooks and the delivery log
+#[get("/admin/webhooks")]
+async fn view_webhooks(
+    client: ClientCtx,
+    filter: web::Query<DeliveryFilter>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+
+    let db = get_db_pool();
+    let filter = filter.into_inner();
+
+    let webhooks = webhooks::Entity::find()
+        .order_by_asc(webhooks::Column::Name)
+        .all(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let names: HashMap<i32, String> = webhooks
+        .iter()
+        .map(|webhook| (webhook.id, webhook.name.clone()))
+        .collect();
+
+    let select = webhook_deliveries::Entity::find().filter(filter.condition());
+    let total = select
+        .clone()
+        .count(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    let page = filter.page();
+    let deliveries = select
+        .order_by_desc(webhook_deliveries::Column::Id)
+        .limit(DELIVERIES_PAGE_SIZE)
+        .offset((page - 1) * DELIVERIES_PAGE_SIZE)
+        .all(db)
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|delivery| DeliveryView {
+            webhook_name: names.get(&delivery.webhook_id).cloned().unwrap_or_default(),
+            delivery,
+        })
+        .collect();
+
+    Ok(WebhooksTemplate {
+        client,
+        webhooks,
+        deliveries,
+        filter_query: filter.query_string(),
+        filter,
+        page,
+        total_pages: total.div_ceil(DELIVERIES_PAGE_SIZE),
+        total,
+    }
+    .to_response())
+}
+
+fn back_to_webhooks() -> HttpResponse {
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/admin/webhooks"))
+        .finish()
+}
+
+/// POST /admin/webhooks - Register a webhook
+#[post("/admin/webhooks")]
+async fn create_webhook(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    form: web::Form<Vec<(String, String)>>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+
+    // Event checkboxes repeat the same field name, so the form is read as pairs.
+    let mut csrf_token = String::new();
+    let mut name = String::new();
+    let mut url = String::new();
+    let mut events = Vec::new();
+    for (field, value) in form.into_inner() {
+        match field.as_str() {
+            "csrf_token" => csrf_token = value,
+            "name" => name = value,
+            "url" => url = value,
+            "event" => events.push(value),
+            _ => {}
+        }
+    }
+
+    crate::middleware::csrf::validate_csrf_token(&cookies, &csrf_token)?;
+
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > hooks::MAX_NAME_LENGTH {
+        return Err(error::ErrorBadRequest(format!(
+            "Webhook name must be between 1 and {} characters.",
+            hooks::MAX_NAME_LENGTH
+        )));
+    }
+    let url = url.trim();
+    if !hooks::is_valid_url(url) {
+        return Err(error::ErrorBadRequest(
+            "Webhook URL must be an http:// or https:// address.",
+        ));
+    }
+    let events = hooks::parse_events(&events.join(" "));
+    if events.is_empty() {
+        return Err(error::ErrorBadRequest("Choose at least one event."));
+    }
+
+    let webhook = webhooks::ActiveModel {
+        name: Set(name.to_owned()),
+        url: Set(url.to_owned()),
+        secret: Set(hooks::generate_secret()),
+        events: Set(hooks::format_events(&events)),
+        is_active: Set(true),
+        created_by: Set(Some(user_id)),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(get_db_pool())
+    .await
+    .map_err(error::ErrorInternalServerError)?;
+
+    log::info!(
+        "Admin {} created webhook {} ({})",
+        user_id,
+        webhook.id,
+        webhook.url
+    );
+
+    Ok(back_to_webhooks())
+}
+
+async fn find_webhook(webhook_id: i32) -> Result<webhooks::Model, Error> {
+    webhooks::Entity::find_by_id(webhook_id)
+        .one(get_db_pool())
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Webhook not found."))
+}
+
+/// POST /admin/webhooks/{id}/toggle - Pause or resume a webhook
+#[post("/admin/webhooks/{id}/toggle")]
+async fn toggle_webhook(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<super::forum::CsrfForm>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let webhook = find_webhook(path.into_inner()).await?;
+    let is_active = !webhook.is_active;
+    let mut webhook: webhooks::ActiveModel = webhook.into();
+    webhook.is_active = Set(is_active);
+    webhook
+        .update(get_db_pool())
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(back_to_webhooks())
+}
+
+/// POST /admin/webhooks/{id}/ping - Send a test delivery
+#[post("/admin/webhooks/{id}/ping")]
+async fn ping_webhook(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<super::forum::CsrfForm>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let webhook = find_webhook(path.into_inner()).await?;
+    hooks::send_ping(&webhook)
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(back_to_webhooks())
+}
+
+/// POST /admin/webhooks/{id}/delete - Remove a webhook and its delivery log
+#[post("/admin/webhooks/{id}/delete")]
+async fn delete_webhook(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<super::forum::CsrfForm>,
+) -> Result<impl Responder, Error> {
+    let user_id = client.require_login()?;
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    let webhook_id = path.into_inner();
+    let result = webhooks::Entity::delete_by_id(webhook_id)
+        .exec(get_db_pool())
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+    if result.rows_affected == 0 {
+        return Err(error::ErrorNotFound("Webhook not found."));
+    }
+
+    log::info!("Admin {} deleted webhook {}", user_id, webhook_id);
+
+    Ok(back_to_webhooks())
+}
+
+/// POST /admin/webhooks/deliveries/{id}/redeliver - Send a delivery again
+#[post("/admin/webhooks/deliveries/{id}/redeliver")]
+async fn redeliver(
+    client: ClientCtx,
+    cookies: actix_session::Session,
+    path: web::Path<i32>,
+    form: web::Form<super::forum::CsrfForm>,
+) -> Result<impl Responder, Error> {
+    client.require_permission("admin.settings")?;
+    crate::middleware::csrf::validate_csrf_token(&cookies, &form.csrf_token)?;
+
+    hooks::redeliver(path.into_inner())
+        .await
+        .map_err(error::ErrorInternalServerError)?
+        .ok_or_else(|| error::ErrorNotFound("Delivery not found."))?;
+
+    Ok(back_to_webhooks())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delivery_filter() {
+        let filter = DeliveryFilter {
+            webhook: Some(" 3 ".to_owned()),
+            status: Some("failed".to_owned()),
+            page: Some(0),
+        };
+        assert_eq!(filter.webhook_id(), Some(3));
+        assert_eq!(filter.page(), 1);
+        assert_eq!(filter.query_string(), "webhook=3&status=failed");
+
+        let filter = DeliveryFilter {
+            webhook: Some("x".to_owned()),
+            status: Some("exploded".to_owned()),
+            page: None,
+        };
+        assert_eq!(filter.webhook_id(), None);
+        assert_eq!(filter.status_value(), "");
+        assert_eq!(filter.query_string(), "");
+    }
+}
@@ -0,0 +1,472 @@
+//! Outgoing webhooks.
+//!
+//! Administrators register URLs at `/admin/webhooks` and choose the events
+//! each one receives. When an event happens a delivery is recorded for every
+//! active webhook subscribed to it, and a job is queued to POST it. Each
+//! request carries an HMAC-SHA256 signature of its timestamp and body, keyed
+//! with the webhook's secret, so receivers can check it came from the forum.
+//! Failed deliveries are retried by the job queue with growing delays, and
+//! every attempt is recorded in the delivery log.
+
+use crate::db::get_db_pool;
+use crate::jobs::{self, Job, JobKind};
+use crate::orm::{webhook_deliveries, webhooks};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::{entity::*, query::*, DbErr};
+use serde_json::json;
+use sha2::Sha256;
+use std::time::Duration;
+
+/// Length of a generated signing secret
+const SECRET_LENGTH: usize = 48;
+
+/// Longest response body kept in the delivery log
+const MAX_RESPONSE_LENGTH: usize = 1000;
+
+/// Longest post content sent in a payload
+const MAX_CONTENT_LENGTH: usize = 10_000;
+
+/// Longest name for a webhook
+pub const MAX_NAME_LENGTH: usize = 100;
+
+/// Events a webhook can receive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebhookEvent {
+    ThreadCreated,
+    PostCreated,
+    ReportFiled,
+    UserRegistered,
+    /// Sent from the admin page to check a webhook works; always delivered
+    Ping,
+}
+
+impl WebhookEvent {
+    /// Events a webhook can subscribe to.
+    pub fn all() -> [WebhookEvent; 4] {
+        [
+            WebhookEvent::ThreadCreated,
+            WebhookEvent::PostCreated,
+            WebhookEvent::ReportFiled,
+            WebhookEvent::UserRegistered,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::ThreadCreated => "thread.created",
+            WebhookEvent::PostCreated => "post.created",
+            WebhookEvent::ReportFiled => "report.filed",
+            WebhookEvent::UserRegistered => "user.registered",
+            WebhookEvent::Ping => "ping",
+        }
+    }
+
+    pub fn parse(event: &str) -> Option<Self> {
+        match event {
+            "thread.created" => Some(WebhookEvent::ThreadCreated),
+            "post.created" => Some(WebhookEvent::PostCreated),
+            "report.filed" => Some(WebhookEvent::ReportFiled),
+            "user.registered" => Some(WebhookEvent::UserRegistered),
+            "ping" => Some(WebhookEvent::Ping),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            WebhookEvent::ThreadCreated => "A visible thread is started",
+            WebhookEvent::PostCreated => "A visible reply is posted",
+            WebhookEvent::ReportFiled => "Content is reported to moderators",
+            WebhookEvent::UserRegistered => "An account is registered",
+            WebhookEvent::Ping => "A test sent from the admin page",
+        }
+    }
+}
+
+/// Subscribable events in a space separated list, in order and without repeats.
+pub fn parse_events(events: &str) -> Vec<WebhookEvent> {
+    let mut parsed = Vec::new();
+    for event in events.split_whitespace().filter_map(WebhookEvent::parse) {
+        if event != WebhookEvent::Ping && !parsed.contains(&event) {
+            parsed.push(event);
+        }
+    }
+    parsed
+}
+
+pub fn format_events(events: &[WebhookEvent]) -> String {
+    events
+        .iter()
+        .map(|event| event.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A new random signing secret.
+pub fn generate_secret() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Signature sent as `X-Dumpster-Signature`: hex HMAC-SHA256 of
+/// `{timestamp}.{body}`, keyed with the webhook's secret.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Whether `url` can be used for a webhook.
+pub fn is_valid_url(url: &str) -> bool {
+    url::Url::parse(url)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some())
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+/// Records a delivery of `event` to `webhook` and queues it to be sent.
+async fn queue_delivery(
+    webhook: &webhooks::Model,
+    event: WebhookEvent,
+    data: serde_json::Value,
+) -> Result<webhook_deliveries::Model, DbErr> {
+    let delivery = webhook_deliveries::ActiveModel {
+        webhook_id: Set(webhook.id),
+        event: Set(event.as_str().to_owned()),
+        payload: Set(data),
+        status: Set("pending".to_owned()),
+        attempts: Set(0),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(get_db_pool())
+    .await?;
+
+    jobs::enqueue(
+        JobKind::WebhookDelivery,
+        json!({ "delivery_id": delivery.id }),
+        crate::app_config::webhooks().max_attempts,
+    )
+    .await?;
+
+    Ok(delivery)
+}
+
+/// Queues `event` for every active webhook subscribed to it. Returns the
+/// number of deliveries queued.
+pub async fn dispatch(event: WebhookEvent, data: serde_json::Value) -> Result<usize, DbErr> {
+    let subscribed: Vec<webhooks::Model> = webhooks::Entity::find()
+        .filter(webhooks::Column::IsActive.eq(true))
+        .all(get_db_pool())
+        .await?
+        .into_iter()
+        .filter(|webhook| parse_events(&webhook.events).contains(&event))
+        .collect();
+
+    for webhook in &subscribed {
+        queue_delivery(webhook, event, data.clone()).await?;
+    }
+
+    Ok(subscribed.len())
+}
+
+/// Dispatches `event` in the background, so the request that caused it does
+/// not wait on the database.
+fn spawn_dispatch(event: WebhookEvent, data: serde_json::Value) {
+    actix::spawn(async move {
+        if let Err(e) = dispatch(event, data).await {
+            log::error!("Failed to queue {} webhooks: {}", event.as_str(), e);
+        }
+    });
+}
+
+/// Sends a ping to one webhook, whatever it is subscribed to.
+pub async fn send_ping(webhook: &webhooks::Model) -> Result<webhook_deliveries::Model, DbErr> {
+    queue_delivery(
+        webhook,
+        WebhookEvent::Ping,
+        json!({ "webhook_id": webhook.id, "name": webhook.name }),
+    )
+    .await
+}
+
+/// Queues a new delivery with the same event and payload as an earlier one.
+pub async fn redeliver(delivery_id: i32) -> Result<Option<webhook_deliveries::Model>, DbErr> {
+    let Some((delivery, Some(webhook))) = webhook_deliveries::Entity::find_by_id(delivery_id)
+        .find_also_related(webhooks::Entity)
+        .one(get_db_pool())
+        .await?
+    else {
+        return Ok(None);
+    };
+    let Some(event) = WebhookEvent::parse(&delivery.event) else {
+        return Ok(None);
+    };
+
+    queue_delivery(&webhook, event, delivery.payload)
+        .await
+        .map(Some)
+}
+
+/// A visible thread was started.
+pub fn thread_created(thread_id: i32, forum_id: i32, user_id: i32, title: &str, post_id: i32) {
+    let base_url = crate::notifications::dispatcher::get_base_url();
+    spawn_dispatch(
+        WebhookEvent::ThreadCreated,
+        json!({
+            "thread_id": thread_id,
+            "forum_id": forum_id,
+            "user_id": user_id,
+            "title": title,
+            "first_post_id": post_id,
+            "url": format!("{}/threads/{}/", base_url, thread_id),
+        }),
+    );
+}
+
+/// A visible reply was posted.
+pub fn post_created(post_id: i32, thread_id: i32, forum_id: i32, user_id: i32, content: &str) {
+    let base_url = crate::notifications::dispatcher::get_base_url();
+    spawn_dispatch(
+        WebhookEvent::PostCreated,
+        json!({
+            "post_id": post_id,
+            "thread_id": thread_id,
+            "forum_id": forum_id,
+            "user_id": user_id,
+            "content": truncate(content, MAX_CONTENT_LENGTH),
+            "url": format!("{}/posts/{}", base_url, post_id),
+        }),
+    );
+}
+
+/// Content was reported to moderators.
+pub fn report_filed(
+    report_id: i32,
+    reporter_id: i32,
+    content_type: &str,
+    content_id: i32,
+    reason: &str,
+) {
+    let base_url = crate::notifications::dispatcher::get_base_url();
+    spawn_dispatch(
+        WebhookEvent::ReportFiled,
+        json!({
+            "report_id": report_id,
+            "reporter_id": reporter_id,
+            "content_type": content_type,
+            "content_id": content_id,
+            "reason": reason,
+            "url": format!("{}/admin/reports/{}", base_url, report_id),
+        }),
+    );
+}
+
+/// An account was registered.
+pub fn user_registered(user_id: i32, name: &str) {
+    let base_url = crate::notifications::dispatcher::get_base_url();
+    spawn_dispatch(
+        WebhookEvent::UserRegistered,
+        json!({
+            "user_id": user_id,
+            "name": name,
+            "url": format!("{}/members/{}/", base_url, user_id),
+        }),
+    );
+}
+
+/// What came of one attempt at a delivery.
+struct Attempt {
+    response_status: Option<i32>,
+    response_body: Option<String>,
+    error: Option<String>,
+}
+
+/// POSTs a delivery to its webhook.
+async fn send(webhook: &webhooks::Model, delivery: &webhook_deliveries::Model) -> Attempt {
+    let body = json!({
+        "id": delivery.id,
+        "event": delivery.event,
+        "created_at": delivery.created_at.and_utc().to_rfc3339(),
+        "data": delivery.payload,
+    })
+    .to_string();
+    let timestamp = Utc::now().timestamp();
+
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(
+            crate::app_config::webhooks().timeout_seconds.max(1),
+        ))
+        .user_agent("Dumpster-Webhooks/1.0")
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            return Attempt {
+                response_status: None,
+                response_body: None,
+                error: Some(format!("failed to create HTTP client: {}", e)),
+            }
+        }
+    };
+
+    let response = client
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Dumpster-Event", delivery.event.as_str())
+        .header("X-Dumpster-Delivery", delivery.id.to_string())
+        .header("X-Dumpster-Timestamp", timestamp.to_string())
+        .header(
+            "X-Dumpster-Signature",
+            format!("sha256={}", sign(&webhook.secret, timestamp, &body)),
+        )
+        .body(body)
+        .send()
+        .await;
+
+    match response {
+        Ok(response) => {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            Attempt {
+                response_status: Some(status.as_u16() as i32),
+                response_body: Some(truncate(&text, MAX_RESPONSE_LENGTH)),
+                error: (!status.is_success()).then(|| format!("endpoint answered {}", status)),
+            }
+        }
+        Err(e) => Attempt {
+            response_status: None,
+            response_body: None,
+            error: Some(format!("request failed: {}", e)),
+        },
+    }
+}
+
+/// Runs a `webhook.delivery` job. Errors make the job queue try again later.
+pub async fn run_delivery_job(job: &Job) -> Result<(), String> {
+    let delivery_id = job
+        .payload
+        .get("delivery_id")
+        .and_then(|id| id.as_i64())
+        .ok_or_else(|| "job has no delivery_id".to_owned())? as i32;
+
+    let Some((delivery, webhook)) = webhook_deliveries::Entity::find_by_id(delivery_id)
+        .find_also_related(webhooks::Entity)
+        .one(get_db_pool())
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        // Deleted along with its webhook.
+        return Ok(());
+    };
+
+    let now = Utc::now().naive_utc();
+    let mut update = webhook_deliveries::ActiveModel {
+        id: Set(delivery.id),
+        attempts: Set(job.attempts),
+        last_attempt_at: Set(Some(now)),
+        ..Default::default()
+    };
+
+    let attempt = match webhook.filter(|webhook| webhook.is_active) {
+        Some(webhook) => send(&webhook, &delivery).await,
+        None => {
+            update.status = Set("failed".to_owned());
+            update.error = Set(Some("webhook is disabled".to_owned()));
+            update
+                .update(get_db_pool())
+                .await
+                .map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+    };
+
+    update.status = Set(match (&attempt.error, job.is_last_attempt()) {
+        (None, _) => "delivered",
+        (Some(_), false) => "retrying",
+        (Some(_), true) => "failed",
+    }
+    .to_owned());
+    update.response_status = Set(attempt.response_status);
+    update.response_body = Set(attempt.response_body);
+    update.error = Set(attempt.error.clone());
+    update
+        .update(get_db_pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match attempt.error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_round_trip() {
+        for event in WebhookEvent::all() {
+            assert_eq!(WebhookEvent::parse(event.as_str()), Some(event));
+        }
+        assert_eq!(WebhookEvent::parse("ping"), Some(WebhookEvent::Ping));
+        assert_eq!(WebhookEvent::parse("thread.deleted"), None);
+    }
+
+    #[test]
+    fn test_parse_events() {
+        assert_eq!(
+            parse_events("post.created  thread.created post.created ping bogus"),
+            vec![WebhookEvent::PostCreated, WebhookEvent::ThreadCreated]
+        );
+        assert!(parse_events("").is_empty());
+        assert_eq!(
+            format_events(&parse_events("report.filed user.registered")),
+            "report.filed user.registered"
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        // printf '1700000000.{"id":1}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", 1_700_000_000, r#"{"id":1}"#),
+            "3dd1b9aef568d75f6790a84bd2e5dfa1f44409eef3cbdbd3f10b837376100c11"
+        );
+        assert_ne!(
+            sign("secret", 1_700_000_001, r#"{"id":1}"#),
+            sign("secret", 1_700_000_000, r#"{"id":1}"#)
+        );
+    }
+
+    #[test]
+    fn test_generate_secret() {
+        let secret = generate_secret();
+        assert_eq!(secret.len(), SECRET_LENGTH);
+        assert!(secret.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(secret, generate_secret());
+    }
+
+    #[test]
+    fn test_is_valid_url() {
+        assert!(is_valid_url("https://example.com/hooks/forum"));
+        assert!(is_valid_url("http://127.0.0.1:9000/"));
+        assert!(!is_valid_url("ftp://example.com/"));
+        assert!(!is_valid_url("not a url"));
+        assert!(!is_valid_url(""));
+    }
+}
@@ -6,13 +6,66 @@
 //! - **Replace**: Substitute matched text with a replacement (word exchange)
 //! - **Block**: Reject the content entirely
 //! - **Flag**: Allow content but mark it for moderator review
+//!
+//! Each filter also says which kinds of content it applies to (posts, thread
+//! titles, usernames, chat and signatures), and each place content is
+//! submitted passes its [`FilterScope`].
 
 use crate::orm::word_filters::{self, FilterAction};
+use aho_corasick::AhoCorasick;
 use once_cell::sync::OnceCell;
 use regex::Regex;
 use sea_orm::{entity::*, query::*, DatabaseConnection};
 use std::sync::RwLock;
 
+/// The kind of content being filtered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterScope {
+    Posts,
+    ThreadTitles,
+    Usernames,
+    Chat,
+    Signatures,
+}
+
+impl FilterScope {
+    /// Every scope, in the order shown to administrators
+    pub const ALL: [FilterScope; 5] = [
+        FilterScope::Posts,
+        FilterScope::ThreadTitles,
+        FilterScope::Usernames,
+        FilterScope::Chat,
+        FilterScope::Signatures,
+    ];
+
+    /// Name used in forms and URLs
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterScope::Posts => "posts",
+            FilterScope::ThreadTitles => "titles",
+            FilterScope::Usernames => "usernames",
+            FilterScope::Chat => "chat",
+            FilterScope::Signatures => "signatures",
+        }
+    }
+
+    /// Name shown to administrators
+    pub fn label(&self) -> &'static str {
+        match self {
+            FilterScope::Posts => "Posts",
+            FilterScope::ThreadTitles => "Thread titles",
+            FilterScope::Usernames => "Usernames",
+            FilterScope::Chat => "Chat",
+            FilterScope::Signatures => "Signatures",
+        }
+    }
+
+    /// Parse a scope from its form name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.as_str() == name)
+    }
+}
+
 /// Result of filtering content
 #[derive(Debug, Clone)]
 pub struct FilterResult {
@@ -41,21 +94,29 @@ impl FilterResult {
     }
 }
 
-/// Compiled word filter for efficient matching
+/// Compiled word filter
 #[derive(Debug)]
-#[allow(dead_code)]
 struct CompiledFilter {
     id: i32,
     pattern: String,
     replacement: Option<String>,
     action: FilterAction,
-    regex: Option<Regex>,
-    is_case_sensitive: bool,
+    is_enabled: bool,
     is_whole_word: bool,
+    scopes: Vec<FilterScope>,
+    /// Matcher for regex filters, and for case-insensitive plain patterns
+    /// that aren't ASCII. Other plain patterns share the automata in
+    /// [`FilterSet`].
+    regex: Option<Regex>,
 }
 
 impl CompiledFilter {
     fn from_model(model: &word_filters::Model) -> Option<Self> {
+        if model.pattern.is_empty() {
+            log::error!("Word filter {} has an empty pattern", model.id);
+            return None;
+        }
+
         let regex = if model.is_regex {
             // Compile regex pattern
             let pattern = if model.is_case_sensitive {
@@ -70,6 +131,9 @@ impl CompiledFilter {
                     return None;
                 }
             }
+        } else if !model.is_case_sensitive && !model.pattern.is_ascii() {
+            // Aho-Corasick only folds ASCII case
+            Regex::new(&format!("(?i){}", regex::escape(&model.pattern))).ok()
         } else {
             None
         };
@@ -79,57 +143,169 @@ impl CompiledFilter {
             pattern: model.pattern.clone(),
             replacement: model.replacement.clone(),
             action: model.action.clone(),
-            regex,
-            is_case_sensitive: model.is_case_sensitive,
+            is_enabled: model.is_enabled,
             is_whole_word: model.is_whole_word,
+            scopes: FilterScope::ALL
+                .into_iter()
+                .filter(|scope| model.applies_to(*scope))
+                .collect(),
+            regex,
         })
     }
 
-    /// Check if this filter matches the given content and return match positions
-    fn find_matches(&self, content: &str) -> Vec<(usize, usize)> {
-        let mut matches = Vec::new();
+    fn applies_to(&self, scope: FilterScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
 
-        if let Some(ref regex) = self.regex {
-            // Regex matching
-            for m in regex.find_iter(content) {
-                if self.is_whole_word {
-                    // Check word boundaries manually for regex
-                    let start = m.start();
-                    let end = m.end();
-                    if is_word_boundary(content, start, end) {
-                        matches.push((start, end));
-                    }
-                } else {
-                    matches.push((m.start(), m.end()));
+/// Plain patterns compiled into one Aho-Corasick automaton
+#[derive(Debug, Default)]
+struct PatternAutomaton {
+    automaton: Option<AhoCorasick>,
+    /// Indexes of the filters using each pattern, by pattern ID
+    filters: Vec<Vec<usize>>,
+}
+
+impl PatternAutomaton {
+    /// Compile `(pattern, filter index)` pairs, folding ASCII case if asked
+    fn new(patterns: Vec<(String, usize)>, ascii_case_insensitive: bool) -> Self {
+        let mut unique: Vec<String> = Vec::new();
+        let mut filters: Vec<Vec<usize>> = Vec::new();
+        for (pattern, filter) in patterns {
+            match unique.iter().position(|p| *p == pattern) {
+                Some(id) => filters[id].push(filter),
+                None => {
+                    unique.push(pattern);
+                    filters.push(vec![filter]);
                 }
             }
-        } else {
-            // Plain text matching
-            let search_content = if self.is_case_sensitive {
-                content.to_string()
-            } else {
-                content.to_lowercase()
-            };
-            let search_pattern = if self.is_case_sensitive {
-                self.pattern.clone()
-            } else {
-                self.pattern.to_lowercase()
+        }
+
+        if unique.is_empty() {
+            return Self::default();
+        }
+
+        match AhoCorasick::builder()
+            .ascii_case_insensitive(ascii_case_insensitive)
+            .build(&unique)
+        {
+            Ok(automaton) => Self {
+                automaton: Some(automaton),
+                filters,
+            },
+            Err(e) => {
+                log::error!("Failed to build word filter automaton: {}", e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Every filter compiled once when filters are loaded
+///
+/// Plain patterns are searched for together in one pass over the content,
+/// however many filters there are; only regex filters are run one by one.
+#[derive(Debug, Default)]
+struct FilterSet {
+    filters: Vec<CompiledFilter>,
+    /// Plain patterns matched exactly
+    case_sensitive: PatternAutomaton,
+    /// Plain ASCII patterns matched ignoring case
+    case_insensitive: PatternAutomaton,
+    /// IDs of filters that failed to compile
+    invalid: Vec<i32>,
+}
+
+impl FilterSet {
+    fn new(models: &[word_filters::Model]) -> Self {
+        let mut filters = Vec::with_capacity(models.len());
+        let mut invalid = Vec::new();
+        let mut case_sensitive = Vec::new();
+        let mut case_insensitive = Vec::new();
+
+        for model in models {
+            let filter = match CompiledFilter::from_model(model) {
+                Some(filter) => filter,
+                None => {
+                    invalid.push(model.id);
+                    continue;
+                }
             };
+            if filter.regex.is_none() {
+                let index = filters.len();
+                if model.is_case_sensitive {
+                    case_sensitive.push((model.pattern.clone(), index));
+                } else {
+                    case_insensitive.push((model.pattern.to_ascii_lowercase(), index));
+                }
+            }
+            filters.push(filter);
+        }
 
-            let mut start = 0;
-            while let Some(pos) = search_content[start..].find(&search_pattern) {
-                let match_start = start + pos;
-                let match_end = match_start + self.pattern.len();
+        Self {
+            filters,
+            case_sensitive: PatternAutomaton::new(case_sensitive, false),
+            case_insensitive: PatternAutomaton::new(case_insensitive, true),
+            invalid,
+        }
+    }
+
+    /// Byte ranges each filter matches in `content`, by filter index. Filters
+    /// `include` rejects are not matched.
+    fn find_matches(
+        &self,
+        content: &str,
+        include: impl Fn(&CompiledFilter) -> bool,
+    ) -> Vec<Vec<(usize, usize)>> {
+        let mut matches: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.filters.len()];
+
+        for (index, filter) in self.filters.iter().enumerate() {
+            if let Some(ref regex) = filter.regex {
+                if include(filter) {
+                    matches[index] = regex
+                        .find_iter(content)
+                        .map(|m| (m.start(), m.end()))
+                        .filter(|&(start, end)| {
+                            !filter.is_whole_word || is_word_boundary(content, start, end)
+                        })
+                        .collect();
+                }
+            }
+        }
 
-                if self.is_whole_word {
-                    if is_word_boundary(content, match_start, match_end) {
-                        matches.push((match_start, match_end));
+        // Every occurrence of every plain pattern, overlaps included
+        let mut found: Vec<Vec<(usize, usize)>> = vec![Vec::new(); self.filters.len()];
+        for patterns in [&self.case_sensitive, &self.case_insensitive] {
+            let automaton = match patterns.automaton {
+                Some(ref automaton) => automaton,
+                None => continue,
+            };
+            for m in automaton.find_overlapping_iter(content) {
+                for &index in &patterns.filters[m.pattern().as_usize()] {
+                    if include(&self.filters[index]) {
+                        found[index].push((m.start(), m.end()));
                     }
-                } else {
-                    matches.push((match_start, match_end));
                 }
+            }
+        }
 
-                start = match_end;
+        // Like a search for each pattern alone: leftmost first, resuming
+        // after each occurrence whether or not it fell on word boundaries
+        for (index, mut occurrences) in found.into_iter().enumerate() {
+            if occurrences.is_empty() {
+                continue;
+            }
+            let filter = &self.filters[index];
+            occurrences.sort_unstable();
+            let mut resume = 0;
+            for (start, end) in occurrences {
+                if start < resume {
+                    continue;
+                }
+                resume = end;
+                if !filter.is_whole_word || is_word_boundary(content, start, end) {
+                    matches[index].push((start, end));
+                }
             }
         }
 
@@ -139,33 +315,27 @@ impl CompiledFilter {
 
 /// Check if positions represent word boundaries
 fn is_word_boundary(content: &str, start: usize, end: usize) -> bool {
-    let chars: Vec<char> = content.chars().collect();
-    let byte_to_char: Vec<usize> = content
-        .char_indices()
-        .map(|(i, _)| i)
-        .chain(std::iter::once(content.len()))
-        .collect();
-
-    // Find character indices
-    let start_char = byte_to_char.iter().position(|&b| b == start);
-    let end_char = byte_to_char.iter().position(|&b| b == end);
-
-    let (start_char, end_char) = match (start_char, end_char) {
-        (Some(s), Some(e)) => (s, e),
-        _ => return false,
-    };
+    if end > content.len() || !content.is_char_boundary(start) || !content.is_char_boundary(end) {
+        return false;
+    }
 
     // Check start boundary
-    let start_ok = start_char == 0 || !chars[start_char - 1].is_alphanumeric();
+    let start_ok = content[..start]
+        .chars()
+        .next_back()
+        .map_or(true, |c| !c.is_alphanumeric());
 
     // Check end boundary
-    let end_ok = end_char >= chars.len() || !chars[end_char].is_alphanumeric();
+    let end_ok = content[end..]
+        .chars()
+        .next()
+        .map_or(true, |c| !c.is_alphanumeric());
 
     start_ok && end_ok
 }
 
 /// Global filter cache
-static FILTER_CACHE: OnceCell<RwLock<Vec<CompiledFilter>>> = OnceCell::new();
+static FILTER_CACHE: OnceCell<RwLock<FilterSet>> = OnceCell::new();
 
 /// Initialize the filter cache from the database
 pub async fn init_filters(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
@@ -174,14 +344,11 @@ pub async fn init_filters(db: &DatabaseConnection) -> Result<(), sea_orm::DbErr>
         .all(db)
         .await?;
 
-    let compiled: Vec<CompiledFilter> = filters
-        .iter()
-        .filter_map(CompiledFilter::from_model)
-        .collect();
+    let compiled = FilterSet::new(&filters);
 
-    log::info!("Loaded {} word filters", compiled.len());
+    log::info!("Loaded {} word filters", compiled.filters.len());
 
-    let cache = FILTER_CACHE.get_or_init(|| RwLock::new(Vec::new()));
+    let cache = FILTER_CACHE.get_or_init(|| RwLock::new(FilterSet::default()));
     let mut cache_write = cache.write().unwrap();
     *cache_write = compiled;
 
@@ -196,8 +363,8 @@ pub async fn reload_filters(db: &DatabaseConnection) -> Result<(), sea_orm::DbEr
 /// Apply word filters to content
 ///
 /// Returns a FilterResult containing the (possibly modified) content and
-/// information about any matches.
-pub fn apply_filters(content: &str) -> FilterResult {
+/// information about any matches. Only filters applying to `scope` are used.
+pub fn apply_filters(content: &str, scope: FilterScope) -> FilterResult {
     let cache = match FILTER_CACHE.get() {
         Some(c) => c,
         None => return FilterResult::passed(content.to_string()),
@@ -208,83 +375,116 @@ pub fn apply_filters(content: &str) -> FilterResult {
         Err(_) => return FilterResult::passed(content.to_string()),
     };
 
-    if filters.is_empty() {
+    filter_content(&filters, content, |filter| filter.applies_to(scope))
+}
+
+/// Apply the filters for `scope` to content about to be saved, returning the
+/// content to save or, when a filter blocks it, the reason to give its author.
+pub fn filter_for_saving(content: &str, scope: FilterScope) -> Result<String, String> {
+    let result = apply_filters(content, scope);
+    if result.blocked {
+        log::warn!(
+            "{} blocked by word filter: patterns={:?}",
+            scope.label(),
+            result.matched_patterns
+        );
+        return Err(result
+            .block_reason
+            .unwrap_or_else(|| "Your content contains blocked content.".to_string()));
+    }
+    Ok(result.content)
+}
+
+/// Whether a username passes the filters. Usernames are never rewritten, so
+/// a filter that would change one refuses it as a block would.
+pub fn is_username_allowed(username: &str) -> bool {
+    let result = apply_filters(username, FilterScope::Usernames);
+    !result.blocked && result.content == username
+}
+
+/// Apply the filters `include` accepts to content
+fn filter_content(
+    set: &FilterSet,
+    content: &str,
+    include: impl Fn(&CompiledFilter) -> bool,
+) -> FilterResult {
+    if !set.filters.iter().any(&include) {
         return FilterResult::passed(content.to_string());
     }
 
-    let mut result_content = content.to_string();
-    let mut blocked = false;
-    let mut flagged = false;
-    let mut matched_patterns = Vec::new();
-    let mut block_reason = None;
+    let matches = set.find_matches(content, &include);
+    let matched: Vec<(&CompiledFilter, &Vec<(usize, usize)>)> = set
+        .filters
+        .iter()
+        .zip(matches.iter())
+        .filter(|(_, ranges)| !ranges.is_empty())
+        .collect();
 
     // Process filters by action priority: block first, then flag, then replace
     // This ensures blocking takes precedence
 
     // First pass: check for blocks
-    for filter in filters.iter() {
-        if filter.action == FilterAction::Block {
-            let matches = filter.find_matches(&result_content);
-            if !matches.is_empty() {
-                blocked = true;
-                matched_patterns.push(filter.pattern.clone());
-                block_reason = Some(format!("Content contains blocked word: {}", filter.pattern));
-                break; // One block is enough
-            }
-        }
-    }
-
-    // If blocked, return early
-    if blocked {
+    if let Some((filter, _)) = matched
+        .iter()
+        .find(|(filter, _)| filter.action == FilterAction::Block)
+    {
         return FilterResult {
-            content: result_content,
-            blocked,
-            flagged,
-            matched_patterns,
-            block_reason,
+            content: content.to_string(),
+            blocked: true,
+            flagged: false,
+            matched_patterns: vec![filter.pattern.clone()],
+            block_reason: Some(format!("Content contains blocked word: {}", filter.pattern)),
         };
     }
 
+    let mut matched_patterns = Vec::new();
+
     // Second pass: check for flags
-    for filter in filters.iter() {
+    let mut flagged = false;
+    for (filter, _) in matched.iter() {
         if filter.action == FilterAction::Flag {
-            let matches = filter.find_matches(&result_content);
-            if !matches.is_empty() {
-                flagged = true;
-                matched_patterns.push(filter.pattern.clone());
-            }
+            flagged = true;
+            matched_patterns.push(filter.pattern.clone());
         }
     }
 
-    // Third pass: apply replacements
-    for filter in filters.iter() {
+    // Third pass: apply replacements. All are found in the submitted text
+    // and made together, so one filter's replacement is never matched by
+    // another; where matches overlap the earliest, then longest, wins.
+    let mut replacements: Vec<(usize, usize, &str)> = Vec::new();
+    for (filter, ranges) in matched.iter() {
         if filter.action == FilterAction::Replace {
             if let Some(ref replacement) = filter.replacement {
-                let matches = filter.find_matches(&result_content);
-                if !matches.is_empty() {
-                    matched_patterns.push(filter.pattern.clone());
-
-                    // Apply replacements in reverse order to preserve positions
-                    let mut sorted_matches = matches.clone();
-                    sorted_matches.sort_by(|a, b| b.0.cmp(&a.0));
-
-                    for (start, end) in sorted_matches {
-                        // Preserve case if replacement is provided
-                        let original = &result_content[start..end];
-                        let new_replacement = match_case(original, replacement);
-                        result_content.replace_range(start..end, &new_replacement);
-                    }
-                }
+                matched_patterns.push(filter.pattern.clone());
+                replacements.extend(
+                    ranges
+                        .iter()
+                        .map(|&(start, end)| (start, end, replacement.as_str())),
+                );
             }
         }
     }
+    replacements.sort_by_key(|&(start, end, _)| (start, std::cmp::Reverse(end)));
+
+    let mut result_content = String::with_capacity(content.len());
+    let mut position = 0;
+    for (start, end, replacement) in replacements {
+        if start < position {
+            continue;
+        }
+        result_content.push_str(&content[position..start]);
+        // Preserve case if replacement is provided
+        result_content.push_str(&match_case(&content[start..end], replacement));
+        position = end;
+    }
+    result_content.push_str(&content[position..]);
 
     FilterResult {
         content: result_content,
-        blocked,
+        blocked: false,
         flagged,
         matched_patterns,
-        block_reason,
+        block_reason: None,
     }
 }
 
@@ -315,7 +515,7 @@ fn match_case(original: &str, replacement: &str) -> String {
 }
 
 /// Check if content would be blocked by filters (without applying replacements)
-pub fn would_block(content: &str) -> Option<String> {
+pub fn would_block(content: &str, scope: FilterScope) -> Option<String> {
     let cache = FILTER_CACHE.get()?;
 
     let filters = match cache.read() {
@@ -323,22 +523,215 @@ pub fn would_block(content: &str) -> Option<String> {
         Err(_) => return None,
     };
 
-    for filter in filters.iter() {
-        if filter.action == FilterAction::Block {
-            let matches = filter.find_matches(content);
-            if !matches.is_empty() {
-                return Some(format!("Content contains blocked word: {}", filter.pattern));
-            }
-        }
-    }
+    let matches = filters.find_matches(content, |filter| {
+        filter.action == FilterAction::Block && filter.applies_to(scope)
+    });
+    filters
+        .filters
+        .iter()
+        .zip(matches)
+        .find(|(_, ranges)| !ranges.is_empty())
+        .map(|(filter, _)| format!("Content contains blocked word: {}", filter.pattern))
+}
+
+/// How one filter treated sample text in the testing sandbox
+#[derive(Debug, Clone)]
+pub struct FilterTestMatch {
+    pub filter: word_filters::Model,
+    /// The text of each match
+    pub matched: Vec<String>,
+    /// Whether the filter shaped the output, being enabled and applying to
+    /// the scope tested
+    pub applied: bool,
+}
+
+/// Sample text run through the word filters
+#[derive(Debug, Clone)]
+pub struct FilterTest {
+    /// What would happen to the text if it were submitted
+    pub result: FilterResult,
+    /// Filters matching the text, whether or not they were applied
+    pub matches: Vec<FilterTestMatch>,
+    /// Filters that can't be compiled, such as bad regular expressions
+    pub invalid: Vec<word_filters::Model>,
+}
+
+/// Run sample text through `filters` as if it were submitted as `scope`.
+/// Disabled filters and those for other scopes are reported when they match
+/// but don't change the output.
+pub fn test_filters(
+    filters: &[word_filters::Model],
+    content: &str,
+    scope: FilterScope,
+) -> FilterTest {
+    let set = FilterSet::new(filters);
+    let applied = |filter: &CompiledFilter| filter.is_enabled && filter.applies_to(scope);
+
+    let matches = set
+        .filters
+        .iter()
+        .zip(set.find_matches(content, |_| true))
+        .filter(|(_, ranges)| !ranges.is_empty())
+        .filter_map(|(compiled, ranges)| {
+            let model = filters.iter().find(|model| model.id == compiled.id)?;
+            Some(FilterTestMatch {
+                filter: model.clone(),
+                matched: ranges
+                    .into_iter()
+                    .map(|(start, end)| content[start..end].to_string())
+                    .collect(),
+                applied: applied(compiled),
+            })
+        })
+        .collect();
 
-    None
+    FilterTest {
+        result: filter_content(&set, content, applied),
+        matches,
+        invalid: filters
+            .iter()
+            .filter(|model| set.invalid.contains(&model.id))
+            .cloned()
+            .collect(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn filter(id: i32, pattern: &str, action: FilterAction) -> word_filters::Model {
+        word_filters::Model {
+            id,
+            pattern: pattern.to_string(),
+            replacement: Some("****".to_string()),
+            is_regex: false,
+            is_case_sensitive: false,
+            is_whole_word: true,
+            action,
+            is_enabled: true,
+            created_by: None,
+            created_at: chrono::Utc::now().naive_utc(),
+            notes: None,
+            applies_to_posts: true,
+            applies_to_titles: true,
+            applies_to_usernames: false,
+            applies_to_chat: true,
+            applies_to_signatures: false,
+        }
+    }
+
+    #[test]
+    fn test_filters_respect_scope() {
+        let filters = vec![
+            filter(1, "darn", FilterAction::Replace),
+            word_filters::Model {
+                applies_to_posts: false,
+                applies_to_usernames: true,
+                ..filter(2, "admin", FilterAction::Block)
+            },
+        ];
+
+        let test = test_filters(&filters, "Darn, ask an admin", FilterScope::Posts);
+        assert!(!test.result.blocked);
+        assert_eq!(test.result.content, "****, ask an admin");
+        assert_eq!(test.matches.len(), 2);
+        assert!(test.matches[0].applied);
+        assert_eq!(test.matches[0].matched, vec!["Darn"]);
+        assert!(!test.matches[1].applied);
+
+        let test = test_filters(&filters, "Darn, ask an admin", FilterScope::Usernames);
+        assert!(test.result.blocked);
+    }
+
+    #[test]
+    fn test_filters_skip_disabled_and_report_invalid() {
+        let filters = vec![
+            word_filters::Model {
+                is_enabled: false,
+                ..filter(1, "darn", FilterAction::Block)
+            },
+            word_filters::Model {
+                is_regex: true,
+                ..filter(2, "(unclosed", FilterAction::Block)
+            },
+        ];
+
+        let test = test_filters(&filters, "darn", FilterScope::Chat);
+        assert!(!test.result.blocked);
+        assert_eq!(test.result.content, "darn");
+        assert!(!test.matches[0].applied);
+        assert_eq!(test.invalid.len(), 1);
+    }
+
+    fn replace(id: i32, pattern: &str, replacement: &str) -> word_filters::Model {
+        word_filters::Model {
+            replacement: Some(replacement.to_string()),
+            ..filter(id, pattern, FilterAction::Replace)
+        }
+    }
+
+    fn apply(filters: &[word_filters::Model], content: &str) -> FilterResult {
+        filter_content(&FilterSet::new(filters), content, |_| true)
+    }
+
+    #[test]
+    fn test_automaton_matches_many_patterns() {
+        let mut filters: Vec<word_filters::Model> = (0..300)
+            .map(|i| replace(i, &format!("word{}", i), "x"))
+            .collect();
+        filters.push(replace(300, "Cat", "dog"));
+        filters.push(word_filters::Model {
+            is_case_sensitive: true,
+            ..replace(301, "Mouse", "rat")
+        });
+
+        let result = apply(&filters, "word7 CAT mouse Mouse word299 word3000");
+        assert_eq!(result.content, "x DOG mouse Rat x word3000");
+    }
+
+    #[test]
+    fn test_overlapping_patterns() {
+        // Both "he" and "hello" occur at the start; the longer match wins
+        let filters = vec![
+            word_filters::Model {
+                is_whole_word: false,
+                ..replace(1, "he", "HE")
+            },
+            word_filters::Model {
+                is_whole_word: false,
+                ..replace(2, "hello", "bye")
+            },
+        ];
+        assert_eq!(apply(&filters, "hello there").content, "bye tHEre");
+    }
+
+    #[test]
+    fn test_replacements_do_not_chain() {
+        let filters = vec![replace(1, "foo", "bar"), replace(2, "bar", "baz")];
+        assert_eq!(apply(&filters, "foo bar").content, "bar baz");
+    }
+
+    #[test]
+    fn test_whole_word_resumes_after_occurrence() {
+        let filters = vec![replace(1, "aa", "b")];
+        assert_eq!(apply(&filters, "aaa aa").content, "aaa b");
+    }
+
+    #[test]
+    fn test_non_ascii_case_insensitive() {
+        let filters = vec![replace(1, "ÉCOLE", "school")];
+        assert_eq!(apply(&filters, "une école").content, "une school");
+    }
+
+    #[test]
+    fn test_scope_names() {
+        for scope in FilterScope::ALL {
+            assert_eq!(FilterScope::from_name(scope.as_str()), Some(scope));
+        }
+        assert_eq!(FilterScope::from_name("profiles"), None);
+    }
+
     #[test]
     fn test_match_case_uppercase() {
         assert_eq!(match_case("HELLO", "world"), "WORLD");
@@ -258,26 +258,22 @@ async fn test_activity_type_icons() {
 #[serial]
 async fn test_activity_cursor_parsing() {
     // Test valid cursor
-    let timestamp = 1703692800i64; // 2023-12-27 12:00:00 UTC
+    let created_at = chrono::DateTime::from_timestamp(1703692800, 123_000_000).unwrap(); // 2023-12-27 12:00:00.123 UTC
     let id = 42;
-    let cursor_str = format!("{}_{}", timestamp, id);
+    let cursor_str = ActivityCursor { created_at, id }.to_string();
 
     let parsed = ActivityCursor::parse(&cursor_str);
     assert!(parsed.is_some());
 
+    // Sub-second precision survives, so activities in the same second aren't skipped
     let cursor = parsed.unwrap();
-    assert_eq!(cursor.created_at.timestamp(), timestamp);
+    assert_eq!(cursor.created_at, created_at);
     assert_eq!(cursor.id, id);
 
-    // Test cursor to string
-    let back_to_string = cursor.to_string();
-    assert_eq!(back_to_string, cursor_str);
-
     // Test invalid cursors
     assert!(ActivityCursor::parse("invalid").is_none());
     assert!(ActivityCursor::parse("123").is_none());
-    assert!(ActivityCursor::parse("abc_123").is_none());
-    assert!(ActivityCursor::parse("123_abc").is_none());
+    assert!(ActivityCursor::parse("1703692800_42").is_none());
 }
 
 #[actix_rt::test]
@@ -442,3 +438,321 @@ async fn test_activity_ordering() {
 
     cleanup_test_data(&db).await.expect("Failed to cleanup");
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_feed_privacy_and_filters() {
+    use dumpster::activities::{get_user_feed, set_hidden_types, FeedFilter};
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let actor = create_test_user(&db, "feed_actor", "password123")
+        .await
+        .expect("Failed to create user");
+    let viewer = create_test_user(&db, "feed_viewer", "password123")
+        .await
+        .expect("Failed to create user");
+    let (forum, thread) = create_test_forum_and_thread(&db, actor.id, "Feed Thread")
+        .await
+        .expect("Failed to create forum");
+
+    for (activity_type, forum_id) in [
+        (ActivityType::ThreadCreated, Some(forum.id)),
+        (ActivityType::UserFollowed, None),
+        (ActivityType::ReactionGiven, Some(forum.id)),
+    ] {
+        activities::ActiveModel {
+            activity_type: Set(activity_type),
+            user_id: Set(actor.id),
+            target_user_id: Set(Some(viewer.id)),
+            target_thread_id: Set(forum_id.map(|_| thread.id)),
+            target_forum_id: Set(forum_id),
+            title: Set(Some("Feed Thread".to_string())),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to create activity");
+    }
+
+    let actor_id = actor.id;
+    let feed_types = |filter: FeedFilter| async move {
+        get_user_feed(actor_id, &filter, None, 10)
+            .await
+            .expect("Failed to load feed")
+            .into_iter()
+            .map(|a| a.activity_type)
+            .collect::<Vec<_>>()
+    };
+    let as_viewer = FeedFilter {
+        viewer_id: Some(viewer.id),
+        ..Default::default()
+    };
+
+    assert_eq!(feed_types(as_viewer.clone()).await.len(), 3);
+
+    // Filtered to one type
+    assert_eq!(
+        feed_types(FeedFilter {
+            activity_type: Some(ActivityType::UserFollowed),
+            ..as_viewer.clone()
+        })
+        .await,
+        vec![ActivityType::UserFollowed]
+    );
+
+    // Forums the viewer can't see drop their activity
+    assert_eq!(
+        feed_types(FeedFilter {
+            hidden_forum_ids: vec![forum.id],
+            ..as_viewer.clone()
+        })
+        .await,
+        vec![ActivityType::UserFollowed]
+    );
+
+    // Hidden types are left out for others but not for the actor
+    set_hidden_types(actor.id, &[ActivityType::ReactionGiven])
+        .await
+        .expect("Failed to hide activity type");
+    assert!(!feed_types(as_viewer.clone())
+        .await
+        .contains(&ActivityType::ReactionGiven));
+    assert!(!feed_types(FeedFilter::default())
+        .await
+        .contains(&ActivityType::ReactionGiven));
+    assert!(feed_types(FeedFilter {
+        viewer_id: Some(actor.id),
+        ..Default::default()
+    })
+    .await
+    .contains(&ActivityType::ReactionGiven));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_feed_hides_moved_and_overridden_threads() {
+    use dumpster::activities::{get_global_feed, FeedFilter};
+    use dumpster::orm::threads;
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let actor = create_test_user(&db, "feed_actor", "password123")
+        .await
+        .expect("Failed to create user");
+    let (public_forum, thread) = create_test_forum_and_thread(&db, actor.id, "Secret Plans")
+        .await
+        .expect("Failed to create forum");
+    let (private_forum, _) = create_test_forum_and_thread(&db, actor.id, "Staff Only")
+        .await
+        .expect("Failed to create forum");
+
+    activities::ActiveModel {
+        activity_type: Set(ActivityType::ThreadCreated),
+        user_id: Set(actor.id),
+        target_thread_id: Set(Some(thread.id)),
+        target_forum_id: Set(Some(public_forum.id)),
+        title: Set(Some("Secret Plans".to_string())),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create activity");
+
+    let titles = |filter: FeedFilter| async move {
+        get_global_feed(&filter, None, 10)
+            .await
+            .expect("Failed to load feed")
+            .into_iter()
+            .filter_map(|a| a.title)
+            .collect::<Vec<_>>()
+    };
+    let hiding_private = FeedFilter {
+        hidden_forum_ids: vec![private_forum.id],
+        ..Default::default()
+    };
+
+    assert_eq!(titles(hiding_private.clone()).await, vec!["Secret Plans"]);
+
+    // The thread's current forum decides, not the one it was posted in
+    threads::ActiveModel {
+        id: Set(thread.id),
+        forum_id: Set(private_forum.id),
+        ..Default::default()
+    }
+    .update(&db)
+    .await
+    .expect("Failed to move thread");
+    assert!(titles(hiding_private).await.is_empty());
+
+    // Threads hidden by their own overrides are left out too
+    assert!(titles(FeedFilter {
+        hidden_thread_ids: vec![thread.id],
+        ..Default::default()
+    })
+    .await
+    .is_empty());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_hidden_forums_keep_activity_outside_forums() {
+    use dumpster::activities::{get_global_feed, FeedFilter};
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let actor = create_test_user(&db, "feed_actor", "password123")
+        .await
+        .expect("Failed to create user");
+    let other = create_test_user(&db, "feed_other", "password123")
+        .await
+        .expect("Failed to create user");
+    let (forum, thread) = create_test_forum_and_thread(&db, actor.id, "Hidden Thread")
+        .await
+        .expect("Failed to create forum");
+
+    for (activity_type, in_forum) in [
+        (ActivityType::ThreadCreated, true),
+        (ActivityType::UserFollowed, false),
+        (ActivityType::ProfilePostCreated, false),
+    ] {
+        activities::ActiveModel {
+            activity_type: Set(activity_type),
+            user_id: Set(actor.id),
+            target_user_id: Set((!in_forum).then(|| other.id)),
+            target_thread_id: Set(in_forum.then(|| thread.id)),
+            target_forum_id: Set(in_forum.then(|| forum.id)),
+            title: Set(Some("feed_other".to_string())),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to create activity");
+    }
+
+    let mut types = get_global_feed(
+        &FeedFilter {
+            hidden_forum_ids: vec![forum.id],
+            ..Default::default()
+        },
+        None,
+        10,
+    )
+    .await
+    .expect("Failed to load feed")
+    .into_iter()
+    .map(|a| a.activity_type)
+    .collect::<Vec<_>>();
+    types.sort_by_key(|t| t.as_str());
+
+    // Follows and profile posts are in no forum, so hiding one leaves them
+    let mut expected = vec![ActivityType::UserFollowed, ActivityType::ProfilePostCreated];
+    expected.sort_by_key(|t| t.as_str());
+    assert_eq!(types, expected);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_archive_old_activities() {
+    use chrono::{TimeZone, Utc};
+    use dumpster::activities::retention::archive_before;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "archived_actor", "password123")
+        .await
+        .expect("Failed to create user");
+
+    for created_at in [
+        Utc.with_ymd_and_hms(2025, 1, 5, 9, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2025, 1, 5, 23, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2025, 1, 7, 12, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap(),
+    ] {
+        activities::ActiveModel {
+            activity_type: Set(ActivityType::UserFollowed),
+            user_id: Set(user.id),
+            created_at: Set(created_at.into()),
+            title: Set(Some("someone".to_string())),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to create activity");
+    }
+
+    let cutoff = Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap();
+    assert_eq!(archive_before(cutoff).await.expect("Failed to archive"), 3);
+    assert_eq!(archive_before(cutoff).await.expect("Failed to archive"), 0);
+
+    let remaining = activities::Entity::find()
+        .all(&db)
+        .await
+        .expect("Failed to query activities");
+    assert_eq!(remaining.len(), 1);
+
+    // A day archived again gets a chunk of its own
+    activities::ActiveModel {
+        activity_type: Set(ActivityType::UserFollowed),
+        user_id: Set(user.id),
+        created_at: Set(Utc.with_ymd_and_hms(2025, 1, 5, 12, 0, 0).unwrap().into()),
+        title: Set(Some("someone".to_string())),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create activity");
+    assert_eq!(archive_before(cutoff).await.expect("Failed to archive"), 1);
+
+    let chunks: Vec<(String, i32, i32)> = db
+        .query_all(Statement::from_string(
+            DbBackend::Postgres,
+            "SELECT day::text AS day, chunk, activity_count FROM activity_archives
+             ORDER BY day, chunk"
+                .to_string(),
+        ))
+        .await
+        .expect("Failed to query archive")
+        .iter()
+        .map(|row| {
+            (
+                row.try_get("", "day").unwrap(),
+                row.try_get("", "chunk").unwrap(),
+                row.try_get("", "activity_count").unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        chunks,
+        vec![
+            ("2025-01-05".to_string(), 0, 2),
+            ("2025-01-05".to_string(), 1, 1),
+            ("2025-01-07".to_string(), 0, 1),
+        ]
+    );
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,220 @@
+//! Integration tests for antivirus scan verdicts and quarantine
+
+mod common;
+use serial_test::serial;
+
+use chrono::{Duration, Utc};
+use common::{database::*, fixtures::*};
+use dumpster::antivirus::{record_verdict, ScanVerdict};
+use dumpster::filesystem::dedup::{delete_unreferenced_record, find_unreferenced_attachments};
+use dumpster::notifications::dispatcher::notify_attachment_quarantined;
+use dumpster::orm::attachments::{self, ScanStatus};
+use dumpster::orm::notifications;
+use sea_orm::{entity::*, ConnectionTrait, DatabaseConnection, DbBackend, QueryFilter, Statement};
+
+async fn load(db: &DatabaseConnection, attachment_id: i32) -> attachments::Model {
+    attachments::Entity::find_by_id(attachment_id)
+        .one(db)
+        .await
+        .expect("Failed to load attachment")
+        .expect("Attachment exists")
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_verdicts_are_recorded() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let clean = create_test_attachment(
+        &db,
+        &"a".repeat(64),
+        "clean.exe",
+        "application/octet-stream",
+        68,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let infected = create_test_attachment(
+        &db,
+        &"b".repeat(64),
+        "infected.exe",
+        "application/octet-stream",
+        68,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    assert_eq!(clean.scan_status, ScanStatus::Unscanned);
+
+    record_verdict(clean.id, &ScanVerdict::Clean).await.unwrap();
+    record_verdict(
+        infected.id,
+        &ScanVerdict::Infected("Win.Test.EICAR_HDB-1".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let clean = load(&db, clean.id).await;
+    assert_eq!(clean.scan_status, ScanStatus::Clean);
+    assert_eq!(clean.scan_result, None);
+    assert!(clean.scanned_at.is_some());
+
+    let infected = load(&db, infected.id).await;
+    assert!(infected.scan_status.is_quarantined());
+    assert_eq!(
+        infected.scan_result.as_deref(),
+        Some("Win.Test.EICAR_HDB-1")
+    );
+    assert!(infected.scanned_at.is_some());
+
+    // clamd refusing a file is recorded, not quarantined
+    let large = create_test_attachment(
+        &db,
+        &"f".repeat(64),
+        "large.exe",
+        "application/octet-stream",
+        68,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    record_verdict(
+        large.id,
+        &ScanVerdict::Failed("INSTREAM size limit exceeded.".to_string()),
+    )
+    .await
+    .unwrap();
+    let large = load(&db, large.id).await;
+    assert_eq!(large.scan_status, ScanStatus::Failed);
+    assert!(!large.scan_status.is_quarantined());
+    assert_eq!(
+        large.scan_result.as_deref(),
+        Some("INSTREAM size limit exceeded.")
+    );
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_quarantined_files_outlive_cleanup() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let orphan = create_test_attachment(
+        &db,
+        &"c".repeat(64),
+        "orphan.exe",
+        "application/octet-stream",
+        68,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let infected = create_test_attachment(
+        &db,
+        &"d".repeat(64),
+        "infected.exe",
+        "application/octet-stream",
+        68,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    record_verdict(infected.id, &ScanVerdict::Infected("Eicar".to_string()))
+        .await
+        .unwrap();
+
+    // The row is what refuses the file if it is uploaded again.
+    let cutoff = Utc::now().naive_utc() - Duration::days(1);
+    let unreferenced = find_unreferenced_attachments(cutoff, 10).await.unwrap();
+    assert_eq!(
+        unreferenced.iter().map(|a| a.id).collect::<Vec<_>>(),
+        vec![orphan.id]
+    );
+    assert_eq!(
+        delete_unreferenced_record(infected.id, cutoff)
+            .await
+            .unwrap(),
+        None
+    );
+    assert!(load(&db, infected.id).await.scan_status.is_quarantined());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_moderators_are_notified() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let moderator = create_test_user(&db, "av_moderator", "password123")
+        .await
+        .expect("Failed to create user");
+    let member = create_test_user(&db, "av_member", "password123")
+        .await
+        .expect("Failed to create user");
+
+    for sql in [
+        "INSERT INTO permission_categories (id, label) VALUES (1, 'moderate')".to_string(),
+        "INSERT INTO permissions (id, category_id, label) VALUES (32, 1, 'moderate.reports.view')"
+            .to_string(),
+        "INSERT INTO groups (id, label) VALUES (3, 'Moderators')".to_string(),
+        "INSERT INTO permission_collections (id, group_id) VALUES (3, 3)".to_string(),
+        "INSERT INTO permission_values (permission_id, collection_id, value) VALUES (32, 3, 'yes')"
+            .to_string(),
+        format!(
+            "INSERT INTO user_groups (user_id, group_id) VALUES ({}, 3)",
+            moderator.id
+        ),
+    ] {
+        db.execute(Statement::from_string(DbBackend::Postgres, sql))
+            .await
+            .expect("Failed to set up moderators");
+    }
+
+    let file = create_test_attachment(
+        &db,
+        &"e".repeat(64),
+        "file.exe",
+        "application/octet-stream",
+        68,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    notify_attachment_quarantined(file.id, &file.filename, "Eicar", Some(member.id))
+        .await
+        .expect("Failed to notify");
+
+    let sent = notifications::Entity::find()
+        .filter(notifications::Column::UserId.eq(moderator.id))
+        .all(&db)
+        .await
+        .unwrap();
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].message.contains("av_member"));
+    assert!(sent[0].message.contains("Eicar"));
+    assert_eq!(sent[0].source_content_id, Some(file.id));
+
+    let not_sent = notifications::Entity::find()
+        .filter(notifications::Column::UserId.eq(member.id))
+        .all(&db)
+        .await
+        .unwrap();
+    assert!(not_sent.is_empty());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,58 @@
+//! Integration tests for personal access tokens
+
+mod common;
+use serial_test::serial;
+
+use common::{database::*, fixtures::*};
+use dumpster::api_token::{authenticate_token, create_token, ApiScope};
+
+#[actix_rt::test]
+#[serial]
+async fn test_banned_owners_tokens_are_refused() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let member = create_test_user(&db, "token_member", "password123")
+        .await
+        .expect("Failed to create user");
+    let banned = create_banned_test_user(&db, "token_banned", "password123", "Spam", true, None)
+        .await
+        .expect("Failed to create user");
+    let suspended = create_banned_test_user(
+        &db,
+        "token_suspended",
+        "password123",
+        "Cool off",
+        false,
+        Some(60),
+    )
+    .await
+    .expect("Failed to create user");
+
+    let (_, member_token) = create_token(member.id, "Bot", &[ApiScope::Read], None)
+        .await
+        .expect("Failed to create token");
+    let authenticated = authenticate_token(&member_token)
+        .await
+        .expect("Failed to authenticate");
+    assert_eq!(
+        authenticated.map(|(profile, _)| profile.id),
+        Some(member.id)
+    );
+
+    // Permanent and unexpired bans both shut the API out
+    for user_id in [banned.id, suspended.id] {
+        let (_, token) = create_token(user_id, "Bot", &[ApiScope::ConversationsWrite], None)
+            .await
+            .expect("Failed to create token");
+        assert!(authenticate_token(&token)
+            .await
+            .expect("Failed to authenticate")
+            .is_none());
+    }
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
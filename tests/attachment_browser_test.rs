@@ -0,0 +1,312 @@
+//! Integration tests for the admin attachment browser
+
+mod common;
+use serial_test::serial;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use common::{database::*, fixtures::*};
+use dumpster::filesystem::browser::{
+    delete_attachment_record, find_attachment_uses, search_attachments, AttachmentFilter,
+};
+use dumpster::orm::{
+    attachment_thumbnails, attachments, ugc_attachments, user_avatars, user_uploads,
+};
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection, QueryFilter};
+
+async fn record_upload(db: &DatabaseConnection, user_id: i32, attachment_id: i32) {
+    user_uploads::ActiveModel {
+        user_id: Set(user_id),
+        attachment_id: Set(attachment_id),
+        created_at: Set(Utc::now().naive_utc()),
+    }
+    .insert(db)
+    .await
+    .expect("Failed to record upload");
+}
+
+async fn attach_to_post(db: &DatabaseConnection, ugc_id: i32, attachment: &attachments::Model) {
+    ugc_attachments::ActiveModel {
+        attachment_id: Set(attachment.id),
+        ugc_id: Set(ugc_id),
+        user_id: Set(None),
+        ip_id: Set(None),
+        created_at: Set(Utc::now().naive_utc()),
+        filename: Set(attachment.filename.clone()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to attach file");
+}
+
+fn ids(listings: &[dumpster::filesystem::browser::AttachmentListing]) -> Vec<i32> {
+    listings.iter().map(|listing| listing.id).collect()
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_filters() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "browser_alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let bob = create_test_user(&db, "browser_bob", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let now = Utc::now().naive_utc();
+    let photo = create_test_attachment(
+        &db,
+        &"1".repeat(64),
+        "photo.jpg",
+        "image/jpeg",
+        200 * 1024,
+        now,
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let clip = create_test_attachment(
+        &db,
+        &"2".repeat(64),
+        "clip.mp4",
+        "video/mp4",
+        5000 * 1024,
+        now - Duration::days(1),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let old = create_test_attachment(
+        &db,
+        &"3".repeat(64),
+        "archive.zip",
+        "application/zip",
+        10 * 1024,
+        NaiveDateTime::parse_from_str("2020-06-15 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let thumbnail = create_test_attachment(
+        &db,
+        &"4".repeat(64),
+        "photo_thumb.webp",
+        "image/webp",
+        1024,
+        now,
+    )
+    .await
+    .expect("Failed to insert attachment");
+    attachment_thumbnails::ActiveModel {
+        attachment_id: Set(photo.id),
+        thumbnail_id: Set(thumbnail.id),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to link thumbnail");
+
+    record_upload(&db, alice.id, photo.id).await;
+    record_upload(&db, bob.id, clip.id).await;
+    record_upload(&db, alice.id, clip.id).await;
+    record_upload(&db, bob.id, old.id).await;
+
+    // Everything but the thumbnail, newest first
+    let (all, total) = search_attachments(&AttachmentFilter::default(), 1)
+        .await
+        .unwrap();
+    assert_eq!(total, 3);
+    assert_eq!(ids(&all), vec![photo.id, clip.id, old.id]);
+    assert_eq!(
+        all[0].thumbnail_filename.as_deref(),
+        Some("photo_thumb.webp")
+    );
+    assert_eq!(all[1].uploader_name.as_deref(), Some("browser_bob"));
+
+    // Any uploader of the file matches, not only the first
+    let filter = AttachmentFilter {
+        uploader: Some("BROWSER_ALICE".to_string()),
+        ..Default::default()
+    };
+    let (found, _) = search_attachments(&filter, 1).await.unwrap();
+    assert_eq!(ids(&found), vec![photo.id, clip.id]);
+
+    let filter = AttachmentFilter {
+        kind: Some("other".to_string()),
+        ..Default::default()
+    };
+    let (found, _) = search_attachments(&filter, 1).await.unwrap();
+    assert_eq!(ids(&found), vec![old.id]);
+
+    let filter = AttachmentFilter {
+        min_kb: Some("100".to_string()),
+        max_kb: Some("1000".to_string()),
+        ..Default::default()
+    };
+    let (found, _) = search_attachments(&filter, 1).await.unwrap();
+    assert_eq!(ids(&found), vec![photo.id]);
+
+    // The end date includes the whole day
+    let filter = AttachmentFilter {
+        from: Some("2020-06-15".to_string()),
+        to: Some("2020-06-15".to_string()),
+        ..Default::default()
+    };
+    let (found, _) = search_attachments(&filter, 1).await.unwrap();
+    assert_eq!(ids(&found), vec![old.id]);
+    assert_eq!(filter.query_string(), "from=2020-06-15&to=2020-06-15");
+
+    // Unparseable values are ignored
+    let filter = AttachmentFilter {
+        kind: Some("executable".to_string()),
+        min_kb: Some("lots".to_string()),
+        ..Default::default()
+    };
+    let (_, total) = search_attachments(&filter, 1).await.unwrap();
+    assert_eq!(total, 3);
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_find_uses() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "browser_poster", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_, thread) = create_test_forum_and_thread(&db, user.id, "Where is it used")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread.id, user.id, "Look", 1)
+        .await
+        .expect("Failed to create post");
+
+    let image = create_test_attachment(
+        &db,
+        &"5".repeat(64),
+        "used.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    attach_to_post(&db, post.ugc_id, &image).await;
+    user_avatars::ActiveModel {
+        user_id: Set(user.id),
+        attachment_id: Set(image.id),
+        created_at: Set(Utc::now().naive_utc()),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to set avatar");
+
+    let uses = find_attachment_uses(image.id).await.unwrap();
+    assert_eq!(uses.posts.len(), 1);
+    assert_eq!(uses.posts[0].post_id, post.id);
+    assert_eq!(uses.posts[0].thread_id, thread.id);
+    assert_eq!(uses.posts[0].thread_title, "Where is it used");
+    assert_eq!(uses.posts[0].author_name.as_deref(), Some("browser_poster"));
+    assert!(uses.messages.is_empty());
+    assert_eq!(uses.avatars.len(), 1);
+    assert_eq!(uses.avatars[0].user_id, user.id);
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_delete_removes_uses_and_orphaned_renditions() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "browser_deleter", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_, thread) = create_test_forum_and_thread(&db, user.id, "Abusive upload")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread.id, user.id, "Bad", 1)
+        .await
+        .expect("Failed to create post");
+
+    let now = Utc::now().naive_utc();
+    let image = create_test_attachment(&db, &"6".repeat(64), "abusive.png", "image/png", 4096, now)
+        .await
+        .expect("Failed to insert attachment");
+    let other = create_test_attachment(&db, &"7".repeat(64), "other.png", "image/png", 4096, now)
+        .await
+        .expect("Failed to insert attachment");
+    let own_thumb = create_test_attachment(
+        &db,
+        &"8".repeat(64),
+        "abusive_thumb.webp",
+        "image/webp",
+        512,
+        now,
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let shared_thumb = create_test_attachment(
+        &db,
+        &"9".repeat(64),
+        "shared_thumb.webp",
+        "image/webp",
+        512,
+        now,
+    )
+    .await
+    .expect("Failed to insert attachment");
+    for (attachment_id, thumbnail_id) in [
+        (image.id, own_thumb.id),
+        (image.id, shared_thumb.id),
+        (other.id, shared_thumb.id),
+    ] {
+        attachment_thumbnails::ActiveModel {
+            attachment_id: Set(attachment_id),
+            thumbnail_id: Set(thumbnail_id),
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to link thumbnail");
+    }
+    attach_to_post(&db, post.ugc_id, &image).await;
+    record_upload(&db, user.id, image.id).await;
+
+    let deleted = delete_attachment_record(image.id)
+        .await
+        .unwrap()
+        .expect("Attachment existed");
+    assert_eq!(deleted.filename.as_deref(), Some("abusive.png"));
+    assert_eq!(deleted.orphaned_renditions, vec![own_thumb.id]);
+
+    assert!(attachments::Entity::find_by_id(image.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .is_none());
+    let remaining = ugc_attachments::Entity::find()
+        .filter(ugc_attachments::Column::UgcId.eq(post.ugc_id))
+        .all(&db)
+        .await
+        .unwrap();
+    assert!(remaining.is_empty());
+
+    // The thumbnail still used by another file is kept
+    let shared_thumb = attachments::Entity::find_by_id(shared_thumb.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .expect("Shared thumbnail kept");
+    assert_eq!(shared_thumb.reference_count, 1);
+
+    // Deleting again finds nothing
+    assert!(delete_attachment_record(image.id).await.unwrap().is_none());
+}
@@ -0,0 +1,265 @@
+//! Integration tests for attachment reference counting and cleanup
+
+mod common;
+use serial_test::serial;
+
+use chrono::{Duration, Utc};
+use common::{database::*, fixtures::*};
+use dumpster::filesystem::dedup::{delete_unreferenced_record, find_unreferenced_attachments};
+use dumpster::orm::{attachment_thumbnails, attachments, ugc_attachments, user_avatars};
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection, QueryFilter};
+
+async fn attach_to_ugc(db: &DatabaseConnection, ugc_id: i32, attachment_id: i32) -> i32 {
+    ugc_attachments::ActiveModel {
+        attachment_id: Set(attachment_id),
+        ugc_id: Set(ugc_id),
+        user_id: Set(None),
+        ip_id: Set(None),
+        created_at: Set(Utc::now().naive_utc()),
+        filename: Set("shared.png".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to attach file")
+    .id
+}
+
+async fn reference_count(db: &DatabaseConnection, attachment_id: i32) -> Option<i32> {
+    attachments::Entity::find_by_id(attachment_id)
+        .one(db)
+        .await
+        .expect("Failed to load attachment")
+        .map(|attachment| attachment.reference_count)
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_references_are_counted() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "refcount_user", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_forum, thread) = create_test_forum_and_thread(&db, user.id, "Shared files")
+        .await
+        .expect("Failed to create thread");
+    let first = create_test_post(&db, thread.id, user.id, "First", 1)
+        .await
+        .expect("Failed to create post");
+    let second = create_test_post(&db, thread.id, user.id, "Second", 2)
+        .await
+        .expect("Failed to create post");
+
+    let file = create_test_attachment(
+        &db,
+        &"a".repeat(64),
+        "file.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let other = create_test_attachment(
+        &db,
+        &"b".repeat(64),
+        "other.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    assert_eq!(reference_count(&db, file.id).await, Some(0));
+
+    // The same file in two posts and as an avatar
+    let first_link = attach_to_ugc(&db, first.ugc_id, file.id).await;
+    attach_to_ugc(&db, second.ugc_id, file.id).await;
+    user_avatars::ActiveModel {
+        user_id: Set(user.id),
+        attachment_id: Set(file.id),
+        created_at: Set(Utc::now().naive_utc()),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to set avatar");
+    assert_eq!(reference_count(&db, file.id).await, Some(3));
+
+    // Moving a reference releases the old file
+    user_avatars::Entity::update_many()
+        .col_expr(
+            user_avatars::Column::AttachmentId,
+            sea_orm::sea_query::Expr::value(other.id),
+        )
+        .filter(user_avatars::Column::UserId.eq(user.id))
+        .exec(&db)
+        .await
+        .expect("Failed to change avatar");
+    assert_eq!(reference_count(&db, file.id).await, Some(2));
+    assert_eq!(reference_count(&db, other.id).await, Some(1));
+
+    ugc_attachments::Entity::delete_by_id(first_link)
+        .exec(&db)
+        .await
+        .expect("Failed to remove attachment");
+    assert_eq!(reference_count(&db, file.id).await, Some(1));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_only_unreferenced_files_are_released() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "release_user", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_forum, thread) = create_test_forum_and_thread(&db, user.id, "Cleanup")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread.id, user.id, "Kept", 1)
+        .await
+        .expect("Failed to create post");
+
+    let used = create_test_attachment(
+        &db,
+        &"c".repeat(64),
+        "used.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let fresh = create_test_attachment(
+        &db,
+        &"d".repeat(64),
+        "fresh.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let orphan = create_test_attachment(
+        &db,
+        &"e".repeat(64),
+        "orphan.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let thumbnail = create_test_attachment(
+        &db,
+        &"f".repeat(64),
+        "thumbnail.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    attach_to_ugc(&db, post.ugc_id, used.id).await;
+    attachment_thumbnails::ActiveModel {
+        attachment_id: Set(orphan.id),
+        thumbnail_id: Set(thumbnail.id),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to link thumbnail");
+
+    // Recent uploads get time to be attached to their post
+    let cutoff = Utc::now().naive_utc() - Duration::days(1);
+    let unreferenced = find_unreferenced_attachments(cutoff, 10).await.unwrap();
+    assert_eq!(
+        unreferenced.iter().map(|a| a.id).collect::<Vec<_>>(),
+        vec![orphan.id]
+    );
+
+    assert_eq!(
+        delete_unreferenced_record(used.id, cutoff).await.unwrap(),
+        None
+    );
+    assert_eq!(
+        delete_unreferenced_record(fresh.id, cutoff).await.unwrap(),
+        None
+    );
+    assert_eq!(
+        delete_unreferenced_record(orphan.id, cutoff).await.unwrap(),
+        Some(orphan.filename.to_owned())
+    );
+    assert_eq!(reference_count(&db, orphan.id).await, None);
+    assert_eq!(reference_count(&db, used.id).await, Some(1));
+
+    // Removing the original releases its thumbnail for the next pass
+    assert_eq!(reference_count(&db, thumbnail.id).await, Some(0));
+    let unreferenced = find_unreferenced_attachments(cutoff, 10).await.unwrap();
+    assert_eq!(
+        unreferenced.iter().map(|a| a.id).collect::<Vec<_>>(),
+        vec![thumbnail.id]
+    );
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_shared_storage_key_is_kept() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    // Rows left over from before deduplication can share one stored file
+    let hash = "9".repeat(64);
+    let duplicate = create_test_attachment(
+        &db,
+        &hash,
+        "duplicate.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let original = create_test_attachment(
+        &db,
+        &hash,
+        "original.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc() - Duration::days(7),
+    )
+    .await
+    .expect("Failed to insert attachment");
+
+    let cutoff = Utc::now().naive_utc() - Duration::days(1);
+    assert_eq!(
+        delete_unreferenced_record(duplicate.id, cutoff)
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(reference_count(&db, duplicate.id).await, None);
+    assert_eq!(
+        delete_unreferenced_record(original.id, cutoff)
+            .await
+            .unwrap(),
+        Some(original.filename)
+    );
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,117 @@
+//! Integration tests for attachment thumbnail renditions in post embeds
+
+mod common;
+use serial_test::serial;
+
+use chrono::Utc;
+use common::{database::*, fixtures::*};
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection};
+
+async fn create_image_attachment(
+    db: &DatabaseConnection,
+    hash: &str,
+    dimensions: (i32, i32),
+) -> dumpster::orm::attachments::Model {
+    use dumpster::orm::attachments;
+
+    attachments::ActiveModel {
+        filename: Set(format!("{}.jpeg", hash)),
+        hash: Set(hash.to_string()),
+        first_seen_at: Set(Utc::now().naive_utc()),
+        last_seen_at: Set(Utc::now().naive_utc()),
+        filesize: Set(4096),
+        file_width: Set(Some(dimensions.0)),
+        file_height: Set(Some(dimensions.1)),
+        mime: Set("image/jpeg".to_string()),
+        meta: Set(serde_json::json!({})),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to insert attachment")
+}
+
+async fn attach_to_ugc(
+    db: &DatabaseConnection,
+    ugc_id: i32,
+    user_id: i32,
+    attachment_id: i32,
+    filename: &str,
+) {
+    use dumpster::orm::ugc_attachments;
+
+    ugc_attachments::ActiveModel {
+        attachment_id: Set(attachment_id),
+        ugc_id: Set(ugc_id),
+        user_id: Set(Some(user_id)),
+        ip_id: Set(None),
+        created_at: Set(Utc::now().naive_utc()),
+        filename: Set(filename.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to attach file");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_post_attachments_use_smallest_thumbnail() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::attachment::get_attachments_for_ugc_by_id;
+    use dumpster::orm::attachment_thumbnails;
+
+    let user = create_test_user(&db, "thumb_user", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_forum, thread) = create_test_forum_and_thread(&db, user.id, "Thumbnails")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread.id, user.id, "Look at this", 1)
+        .await
+        .expect("Failed to create post");
+
+    let original = create_image_attachment(&db, &"1".repeat(64), (1600, 800)).await;
+    let medium = create_image_attachment(&db, &"2".repeat(64), (800, 400)).await;
+    let small = create_image_attachment(&db, &"3".repeat(64), (200, 100)).await;
+    let tiny = create_image_attachment(&db, &"4".repeat(64), (120, 90)).await;
+
+    for thumbnail in [&medium, &small] {
+        attachment_thumbnails::ActiveModel {
+            attachment_id: Set(original.id),
+            thumbnail_id: Set(thumbnail.id),
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to link thumbnail");
+    }
+
+    attach_to_ugc(&db, post.ugc_id, user.id, original.id, "holiday.jpg").await;
+    attach_to_ugc(&db, post.ugc_id, user.id, tiny.id, "icon.jpg").await;
+
+    let attachments = get_attachments_for_ugc_by_id(vec![post.ugc_id]).await;
+    let attachments = attachments.get(&post.ugc_id).expect("No attachments");
+    assert_eq!(attachments.len(), 2);
+
+    // Large images embed their smallest rendition but still link to the original
+    let holiday = &attachments[0];
+    assert_eq!(
+        holiday.thumbnail_filename.as_deref(),
+        Some(small.filename.as_str())
+    );
+    assert!(holiday.get_thumbnail_url().contains(&small.hash));
+    assert!(holiday.get_download_url().contains(&original.hash));
+    assert!(holiday.get_download_url().ends_with("holiday.jpg"));
+
+    // Images without renditions fall back to the original
+    let icon = &attachments[1];
+    assert!(icon.thumbnail_filename.is_none());
+    assert_eq!(icon.get_thumbnail_url(), icon.get_download_url());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,258 @@
+//! Integration tests for attachment visibility levels and signed URLs
+
+mod common;
+use serial_test::serial;
+
+use chrono::Utc;
+use common::{database::*, fixtures::*};
+use dumpster::conversations;
+use dumpster::filesystem::link_attachment_thumbnail;
+use dumpster::filesystem::visibility::{
+    can_view_attachment, refresh_attachment_visibility, signed_url_expiry, visibility_for_uses,
+};
+use dumpster::orm::attachments::{self, AttachmentVisibility};
+use dumpster::orm::{private_messages, ugc_attachments};
+use dumpster::storage::signing::{sign, signed_content_url, verify};
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection};
+
+// ============================================================================
+// Signing Tests
+// ============================================================================
+
+#[test]
+fn test_signatures_expire_and_bind_to_the_file() {
+    let hash = "a".repeat(64);
+    let other = "b".repeat(64);
+    let now = 1_700_000_000;
+    let signature = sign(&hash, now + 60);
+
+    assert!(verify(&hash, now + 60, &signature, now));
+    // Expired
+    assert!(!verify(&hash, now + 60, &signature, now + 61));
+    // Extended
+    assert!(!verify(&hash, now + 3600, &signature, now));
+    // Another file
+    assert!(!verify(&other, now + 60, &signature, now));
+    assert!(!verify(&hash, now + 60, "", now));
+}
+
+#[test]
+fn test_signed_content_url() {
+    let key = format!("{}.png", "c".repeat(64));
+    let url = signed_content_url(&key, "photo.png", 1234);
+
+    assert_eq!(
+        url,
+        format!(
+            "/content/{}/photo.png?expires=1234&signature={}",
+            "c".repeat(64),
+            sign(&"c".repeat(64), 1234)
+        )
+    );
+}
+
+#[test]
+fn test_signed_urls_are_stable_within_a_window() {
+    let window = 60;
+    let start = 1_699_999_200; // a window boundary
+
+    assert_eq!(signed_url_expiry(start, window), start + 7200);
+    assert_eq!(signed_url_expiry(start + 3599, window), start + 7200);
+    assert_eq!(signed_url_expiry(start + 3600, window), start + 10800);
+    // Always valid for at least one window
+    assert!(signed_url_expiry(start + 3599, window) - (start + 3599) > 3600);
+}
+
+#[test]
+fn test_least_restrictive_use_wins() {
+    use AttachmentVisibility::*;
+
+    assert_eq!(visibility_for_uses(0, 0, 2, Public), Some(Conversation));
+    assert_eq!(visibility_for_uses(0, 1, 2, Members), Some(Members));
+    assert_eq!(visibility_for_uses(0, 1, 0, Public), Some(Public));
+    assert_eq!(visibility_for_uses(1, 0, 1, Members), Some(Public));
+    assert_eq!(visibility_for_uses(0, 0, 0, Public), None);
+}
+
+// ============================================================================
+// Visibility Assignment Tests
+// ============================================================================
+
+async fn attach_to_ugc(db: &DatabaseConnection, ugc_id: i32, attachment_id: i32) {
+    ugc_attachments::ActiveModel {
+        attachment_id: Set(attachment_id),
+        ugc_id: Set(ugc_id),
+        user_id: Set(None),
+        ip_id: Set(None),
+        created_at: Set(Utc::now().naive_utc()),
+        filename: Set("private.png".to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to attach file");
+}
+
+async fn visibility(db: &DatabaseConnection, attachment_id: i32) -> AttachmentVisibility {
+    attachments::Entity::find_by_id(attachment_id)
+        .one(db)
+        .await
+        .expect("Failed to load attachment")
+        .expect("Attachment exists")
+        .visibility
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_conversation_attachments_are_restricted() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "vis_alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let bob = create_test_user(&db, "vis_bob", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let conversation_id = conversations::create_conversation(alice.id, &[bob.id], None)
+        .await
+        .expect("Failed to create conversation");
+    let message_id = conversations::send_message(conversation_id, alice.id, "See attached")
+        .await
+        .expect("Failed to send message");
+    let message = private_messages::Entity::find_by_id(message_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let file = create_test_attachment(
+        &db,
+        &"d".repeat(64),
+        "file.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let thumbnail = create_test_attachment(
+        &db,
+        &"e".repeat(64),
+        "thumbnail.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    link_attachment_thumbnail(file.id, thumbnail.id)
+        .await
+        .expect("Failed to link thumbnail");
+    assert_eq!(visibility(&db, file.id).await, AttachmentVisibility::Public);
+
+    attach_to_ugc(&db, message.ugc_id, file.id).await;
+    refresh_attachment_visibility(file.id).await.unwrap();
+    assert_eq!(
+        visibility(&db, file.id).await,
+        AttachmentVisibility::Conversation
+    );
+    assert_eq!(
+        visibility(&db, thumbnail.id).await,
+        AttachmentVisibility::Conversation
+    );
+
+    // Renditions made afterwards follow the original
+    let medium = create_test_attachment(
+        &db,
+        &"f".repeat(64),
+        "medium.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    link_attachment_thumbnail(file.id, medium.id)
+        .await
+        .expect("Failed to link thumbnail");
+    assert_eq!(
+        visibility(&db, medium.id).await,
+        AttachmentVisibility::Conversation
+    );
+
+    // Posting the same file in the forum makes it public again
+    let (_forum, thread) = create_test_forum_and_thread(&db, alice.id, "Shared")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread.id, alice.id, "Here it is", 1)
+        .await
+        .expect("Failed to create post");
+    attach_to_ugc(&db, post.ugc_id, file.id).await;
+    refresh_attachment_visibility(file.id).await.unwrap();
+    assert_eq!(visibility(&db, file.id).await, AttachmentVisibility::Public);
+    assert_eq!(
+        visibility(&db, thumbnail.id).await,
+        AttachmentVisibility::Public
+    );
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_conversation_attachments_viewable_by_participants_only() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "view_alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let bob = create_test_user(&db, "view_bob", "password123")
+        .await
+        .expect("Failed to create user");
+    let carol = create_test_user(&db, "view_carol", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let file = create_test_attachment(
+        &db,
+        &"d".repeat(64),
+        "file.png",
+        "image/png",
+        1024,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    assert!(can_view_attachment(None, file.id).await.unwrap());
+
+    let conversation_id = conversations::create_conversation(alice.id, &[bob.id], None)
+        .await
+        .expect("Failed to create conversation");
+    let message_id = conversations::send_message(conversation_id, alice.id, "See attached")
+        .await
+        .expect("Failed to send message");
+    let message = private_messages::Entity::find_by_id(message_id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    attach_to_ugc(&db, message.ugc_id, file.id).await;
+    refresh_attachment_visibility(file.id).await.unwrap();
+
+    assert!(can_view_attachment(Some(alice.id), file.id).await.unwrap());
+    assert!(can_view_attachment(Some(bob.id), file.id).await.unwrap());
+    assert!(!can_view_attachment(Some(carol.id), file.id).await.unwrap());
+    assert!(!can_view_attachment(None, file.id).await.unwrap());
+    assert!(!can_view_attachment(Some(alice.id), -1).await.unwrap());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
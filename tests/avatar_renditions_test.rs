@@ -0,0 +1,127 @@
+//! Integration tests for resized avatar renditions and srcset output
+
+mod common;
+use serial_test::serial;
+
+use chrono::Utc;
+use common::{database::*, fixtures::*};
+use dumpster::attachment::{get_avatar_html, AttachmentSize, AvatarFile};
+use dumpster::filesystem::get_file_url_by_filename;
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection};
+
+async fn create_square_attachment(
+    db: &DatabaseConnection,
+    hash: &str,
+    px: i32,
+) -> dumpster::orm::attachments::Model {
+    use dumpster::orm::attachments;
+
+    attachments::ActiveModel {
+        filename: Set(format!("{}.png", hash)),
+        hash: Set(hash.to_string()),
+        first_seen_at: Set(Utc::now().naive_utc()),
+        last_seen_at: Set(Utc::now().naive_utc()),
+        filesize: Set(1024),
+        file_height: Set(Some(px)),
+        file_width: Set(Some(px)),
+        mime: Set("image/png".to_string()),
+        meta: Set(serde_json::json!({})),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to insert attachment")
+}
+
+#[test]
+fn test_avatar_html_without_renditions() {
+    let filename = "d".repeat(64) + ".png";
+    let html = get_avatar_html(
+        AvatarFile {
+            filename: &filename,
+            dimensions: (288, 288),
+            renditions: None,
+        },
+        AttachmentSize::M,
+    );
+
+    assert!(html.contains("width=\"96\" height=\"96\""));
+    assert!(!html.contains("srcset"));
+}
+
+#[test]
+fn test_avatar_html_keeps_aspect_ratio() {
+    let filename = "d".repeat(64) + ".png";
+    let html = get_avatar_html(
+        AvatarFile {
+            filename: &filename,
+            dimensions: (200, 100),
+            renditions: None,
+        },
+        AttachmentSize::L,
+    );
+
+    assert!(html.contains("width=\"144\" height=\"72\""));
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_profile_avatar_srcset_uses_file_urls() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::orm::{attachment_thumbnails, user_avatars};
+    use dumpster::user::Profile;
+
+    let user = create_test_user(&db, "srcset_user", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let master = create_square_attachment(&db, &"e".repeat(64), 288).await;
+    let small = create_square_attachment(&db, &"f".repeat(64), 48).await;
+    let medium = create_square_attachment(&db, &"0".repeat(64), 96).await;
+
+    for thumbnail in [&small, &medium] {
+        attachment_thumbnails::ActiveModel {
+            attachment_id: Set(master.id),
+            thumbnail_id: Set(thumbnail.id),
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to link thumbnail");
+    }
+
+    user_avatars::ActiveModel {
+        user_id: Set(user.id),
+        attachment_id: Set(master.id),
+        created_at: Set(Utc::now().naive_utc()),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to set avatar");
+
+    let profile = Profile::get_by_id(&db, user.id)
+        .await
+        .expect("Failed to load profile")
+        .expect("Profile not found");
+
+    // Smallest first, each with its width
+    assert_eq!(
+        profile.avatar_renditions.as_deref(),
+        Some(format!("{} 48,{} 96", small.filename, medium.filename).as_str())
+    );
+
+    let html = profile.get_avatar_html(AttachmentSize::S);
+    assert!(html.contains(&format!(
+        "srcset=\"{} 48w, {} 96w, ",
+        get_file_url_by_filename(&small.filename, &small.filename),
+        get_file_url_by_filename(&medium.filename, &medium.filename)
+    )));
+    assert!(html.contains(&format!("{} 288w", master.filename)));
+    assert!(html.contains("sizes=\"48px\""));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,285 @@
+//! Integration tests for avatar sources (uploads, Gravatar and the stock gallery)
+
+mod common;
+use serial_test::serial;
+
+use chrono::Utc;
+use common::{database::*, fixtures::*};
+use dumpster::attachment::{
+    get_avatar_html_for_source, get_gravatar_hash, get_gravatar_url, AttachmentSize, AvatarFile,
+};
+use dumpster::orm::users::AvatarSource;
+use sea_orm::{entity::*, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
+
+// ============================================================================
+// Gravatar Tests
+// ============================================================================
+
+#[test]
+fn test_gravatar_hash_normalizes_email() {
+    let hash = get_gravatar_hash("MyEmailAddress@example.com ");
+
+    // SHA-256 of "myemailaddress@example.com", as documented by Gravatar
+    assert_eq!(
+        hash,
+        "84059b07d4be67b806386c0aad8070a23f18836bbaae342275dc0a83414c32ee"
+    );
+    assert_eq!(hash, get_gravatar_hash("myemailaddress@example.com"));
+}
+
+#[test]
+fn test_gravatar_source_renders_gravatar() {
+    let html = get_avatar_html_for_source(
+        AvatarSource::Gravatar,
+        None,
+        Some("user@example.com"),
+        AttachmentSize::M,
+    );
+
+    assert!(html.contains(&get_gravatar_hash("user@example.com")));
+    assert!(html.contains("width=\"96\""));
+}
+
+#[test]
+fn test_gravatar_url_encodes_query() {
+    let url = get_gravatar_url("abc", 96);
+
+    assert_eq!(url, "https://www.gravatar.com/avatar/abc?s=96&d=mp");
+}
+
+#[test]
+fn test_gravatar_source_without_email_renders_nothing() {
+    let html = get_avatar_html_for_source(AvatarSource::Gravatar, None, None, AttachmentSize::M);
+    assert!(html.is_empty());
+}
+
+#[test]
+fn test_upload_source_prefers_file() {
+    let filename = "a".repeat(64) + ".png";
+    let html = get_avatar_html_for_source(
+        AvatarSource::Upload,
+        Some(AvatarFile {
+            filename: &filename,
+            dimensions: (200, 200),
+            renditions: None,
+        }),
+        Some("user@example.com"),
+        AttachmentSize::S,
+    );
+
+    assert!(html.contains(&filename));
+    assert!(!html.contains("gravatar"));
+}
+
+// ============================================================================
+// Profile Tests
+// ============================================================================
+
+async fn create_test_attachment(
+    db: &sea_orm::DatabaseConnection,
+    hash: &str,
+) -> dumpster::orm::attachments::Model {
+    use dumpster::orm::attachments;
+
+    attachments::ActiveModel {
+        filename: Set(format!("{}.png", hash)),
+        hash: Set(hash.to_string()),
+        first_seen_at: Set(Utc::now().naive_utc()),
+        last_seen_at: Set(Utc::now().naive_utc()),
+        filesize: Set(1024),
+        file_height: Set(Some(128)),
+        file_width: Set(Some(128)),
+        mime: Set("image/png".to_string()),
+        meta: Set(serde_json::json!({})),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to insert attachment")
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_profile_loads_avatar_source() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::orm::users;
+    use dumpster::user::Profile;
+
+    let user = create_test_user_with_email(&db, "gravatar_user", "gravatar@example.com", true)
+        .await
+        .expect("Failed to create user");
+    assert_eq!(user.avatar_source, AvatarSource::Upload);
+
+    let profile = Profile::get_by_id(&db, user.id)
+        .await
+        .expect("Failed to load profile")
+        .expect("Profile not found");
+    assert_eq!(profile.avatar_source, AvatarSource::Upload);
+    assert_eq!(profile.email.as_deref(), Some("gravatar@example.com"));
+
+    users::Entity::update_many()
+        .col_expr(
+            users::Column::AvatarSource,
+            sea_orm::sea_query::Expr::value(AvatarSource::Gravatar),
+        )
+        .filter(users::Column::Id.eq(user.id))
+        .exec(&db)
+        .await
+        .expect("Failed to update avatar source");
+
+    let profile = Profile::get_by_id(&db, user.id)
+        .await
+        .expect("Failed to load profile")
+        .expect("Profile not found");
+    assert_eq!(profile.avatar_source, AvatarSource::Gravatar);
+    assert!(profile
+        .get_avatar_html(AttachmentSize::L)
+        .contains(&get_gravatar_hash("gravatar@example.com")));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_gallery_avatar_listing_and_selection() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::orm::{avatar_gallery, user_avatars, users};
+    use dumpster::user::Profile;
+    use dumpster::web::account::get_avatar_gallery;
+
+    let user = create_test_user(&db, "gallery_user", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let enabled = create_test_attachment(&db, &"b".repeat(64)).await;
+    let disabled = create_test_attachment(&db, &"c".repeat(64)).await;
+
+    let item = avatar_gallery::ActiveModel {
+        attachment_id: Set(enabled.id),
+        label: Set("Cat".to_string()),
+        display_order: Set(1),
+        is_enabled: Set(true),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to insert gallery avatar");
+
+    avatar_gallery::ActiveModel {
+        attachment_id: Set(disabled.id),
+        label: Set("Dog".to_string()),
+        display_order: Set(0),
+        is_enabled: Set(false),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to insert gallery avatar");
+
+    // Only enabled entries are offered to members
+    let gallery = get_avatar_gallery(&db)
+        .await
+        .expect("Failed to load gallery");
+    assert_eq!(gallery.len(), 1);
+    assert_eq!(gallery[0].id, item.id);
+    assert_eq!(gallery[0].label, "Cat");
+
+    // Picking a stock avatar stores it like an upload, tagged as gallery
+    user_avatars::ActiveModel {
+        user_id: Set(user.id),
+        attachment_id: Set(enabled.id),
+        created_at: Set(Utc::now().naive_utc()),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to set avatar");
+
+    users::Entity::update_many()
+        .col_expr(
+            users::Column::AvatarSource,
+            sea_orm::sea_query::Expr::value(AvatarSource::Gallery),
+        )
+        .filter(users::Column::Id.eq(user.id))
+        .exec(&db)
+        .await
+        .expect("Failed to update avatar source");
+
+    let profile = Profile::get_by_id(&db, user.id)
+        .await
+        .expect("Failed to load profile")
+        .expect("Profile not found");
+    assert_eq!(profile.avatar_source, AvatarSource::Gallery);
+    assert!(profile
+        .get_avatar_html(AttachmentSize::M)
+        .contains(&enabled.filename));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_profile_avatar_keeps_aspect_ratio() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::orm::{attachments, user_avatars};
+    use dumpster::user::Profile;
+
+    let user = create_test_user(&db, "wide_avatar_user", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let wide = attachments::ActiveModel {
+        filename: Set(format!("{}.png", "d".repeat(64))),
+        hash: Set("d".repeat(64)),
+        first_seen_at: Set(Utc::now().naive_utc()),
+        last_seen_at: Set(Utc::now().naive_utc()),
+        filesize: Set(1024),
+        file_height: Set(Some(100)),
+        file_width: Set(Some(200)),
+        mime: Set("image/png".to_string()),
+        meta: Set(serde_json::json!({})),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to insert attachment");
+
+    user_avatars::ActiveModel {
+        user_id: Set(user.id),
+        attachment_id: Set(wide.id),
+        created_at: Set(Utc::now().naive_utc()),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to set avatar");
+
+    let profile = Profile::get_by_id(&db, user.id)
+        .await
+        .expect("Failed to load profile")
+        .expect("Profile not found");
+    assert_eq!(profile.avatar_width, Some(200));
+    assert_eq!(profile.avatar_height, Some(100));
+
+    // A 2:1 upload is scaled to fit, not squared off
+    let html = profile.get_avatar_html(AttachmentSize::M);
+    assert!(html.contains("width=\"96\""));
+    assert!(html.contains("height=\"48\""));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,63 @@
+//! Integration tests for chat ignore lists
+
+mod common;
+use serial_test::serial;
+
+use common::{database::*, fixtures::*};
+use dumpster::config::create_config;
+use dumpster::permission::Permissions;
+use dumpster::web::chat::implement::{default::Layer, ChatLayer};
+
+fn layer(db: &sea_orm::DatabaseConnection) -> Layer {
+    Layer {
+        db: db.clone(),
+        config: create_config(),
+        permissions: Permissions::new(Default::default()),
+    }
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_set_and_clear_ignoring() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "ignore_alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let bob = create_test_user(&db, "ignore_bob", "password123")
+        .await
+        .expect("Failed to create user");
+    let (alice_id, bob_id) = (alice.id as u32, bob.id as u32);
+    let layer = layer(&db);
+
+    assert!(!layer.is_ignoring(alice_id, bob_id).await);
+    assert!(layer.set_ignoring(alice_id, bob_id, true).await);
+    // Ignoring someone already ignored is not an error
+    assert!(layer.set_ignoring(alice_id, bob_id, true).await);
+    assert!(layer.is_ignoring(alice_id, bob_id).await);
+    // Ignoring goes one way only
+    assert!(!layer.is_ignoring(bob_id, alice_id).await);
+
+    // The list reaches new chat sessions
+    let session = layer.get_session_from_user_id(alice_id).await;
+    assert_eq!(session.ignored_users, vec![bob_id]);
+    assert!(session.is_ignoring(bob_id));
+
+    assert!(layer.set_ignoring(alice_id, bob_id, false).await);
+    assert!(!layer.is_ignoring(alice_id, bob_id).await);
+    assert!(layer
+        .get_session_from_user_id(alice_id)
+        .await
+        .ignored_users
+        .is_empty());
+
+    // Guests and users themselves can't be ignored by them
+    assert!(!layer.set_ignoring(alice_id, alice_id, true).await);
+    assert!(!layer.set_ignoring(0, bob_id, true).await);
+    assert!(!layer.is_ignoring(alice_id, alice_id).await);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -172,3 +172,61 @@ async fn test_chat_message_user_ownership() {
 
     cleanup_test_data(&db).await.unwrap();
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_chat_history_pages_back_from_cursor() {
+    use common::database::{cleanup_test_data, setup_test_database};
+    use common::fixtures::{create_test_chat_message, create_test_chat_room, create_test_user};
+    use dumpster::config::create_config;
+    use dumpster::permission::Permissions;
+    use dumpster::web::chat::implement::{default::Layer, Author, ChatLayer, Message};
+
+    let db = setup_test_database().await.unwrap();
+    cleanup_test_data(&db).await.unwrap();
+
+    let user = create_test_user(&db, "history_user", "password123")
+        .await
+        .unwrap();
+    let room = create_test_chat_room(&db, "History Room").await.unwrap();
+    let other_room = create_test_chat_room(&db, "Other Room").await.unwrap();
+
+    let mut ids = Vec::new();
+    for n in 0..5 {
+        let message = create_test_chat_message(&db, room.id, user.id, &format!("message {}", n))
+            .await
+            .unwrap();
+        ids.push(message.id as u32);
+    }
+    create_test_chat_message(&db, other_room.id, user.id, "elsewhere")
+        .await
+        .unwrap();
+
+    let layer = Layer {
+        db: db.clone(),
+        config: create_config(),
+        permissions: Permissions::new(Default::default()),
+    };
+    let page_ids = |page: &[(Author, Message)]| -> Vec<u32> {
+        page.iter().map(|(_, message)| message.message_id).collect()
+    };
+
+    // The newest messages come first, oldest first within the page
+    let page = layer.get_room_history(room.id as u32, None, 3).await;
+    assert_eq!(page_ids(&page), ids[2..].to_vec());
+    assert_eq!(page[0].1.message, "message 2");
+    assert_eq!(page[0].0.username, "history_user");
+
+    // Paging back from the oldest message shown
+    let page = layer
+        .get_room_history(room.id as u32, Some(ids[2]), 3)
+        .await;
+    assert_eq!(page_ids(&page), ids[..2].to_vec());
+
+    let page = layer
+        .get_room_history(room.id as u32, Some(ids[0]), 3)
+        .await;
+    assert!(page.is_empty());
+
+    cleanup_test_data(&db).await.unwrap();
+}
@@ -0,0 +1,103 @@
+//! Integration tests for private chat rooms and direct messages
+
+mod common;
+use serial_test::serial;
+
+use common::{database::*, fixtures::*};
+use dumpster::config::create_config;
+use dumpster::orm::chat_rooms;
+use dumpster::permission::Permissions;
+use dumpster::web::chat::implement::{default::Layer, ChatLayer, Session};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+fn layer(db: &sea_orm::DatabaseConnection) -> Layer {
+    Layer {
+        db: db.clone(),
+        config: create_config(),
+        permissions: Permissions::new(Default::default()),
+    }
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_direct_rooms_are_shared_by_each_pair() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "dm_alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let bob = create_test_user(&db, "dm_bob", "password123")
+        .await
+        .expect("Failed to create user");
+    let carol = create_test_user(&db, "dm_carol", "password123")
+        .await
+        .expect("Failed to create user");
+    let layer = layer(&db);
+    let alice_session = layer.get_session_from_user_id(alice.id as u32).await;
+    let bob_session = layer.get_session_from_user_id(bob.id as u32).await;
+
+    let (room, partner) = layer
+        .open_direct_room(&alice_session, "dm_bob")
+        .await
+        .expect("Failed to open direct message");
+    assert_eq!(partner.id, bob.id as u32);
+    assert_eq!(room.title, "dm_bob");
+    assert!(room.is_private);
+
+    // Opening it again, from either side, finds the same room
+    let (again, _) = layer
+        .open_direct_room(&alice_session, "dm_bob")
+        .await
+        .expect("Failed to reopen direct message");
+    assert_eq!(again.id, room.id);
+    let (reverse, partner) = layer
+        .open_direct_room(&bob_session, "dm_alice")
+        .await
+        .expect("Failed to open direct message");
+    assert_eq!(reverse.id, room.id);
+    assert_eq!(partner.id, alice.id as u32);
+
+    let key = format!("{}:{}", alice.id.min(bob.id), alice.id.max(bob.id));
+    let rooms = chat_rooms::Entity::find()
+        .filter(chat_rooms::Column::DirectKey.eq(key))
+        .all(&db)
+        .await
+        .expect("Failed to load rooms");
+    assert_eq!(rooms.len(), 1);
+
+    let mut members = layer.get_room_members(room.id).await;
+    members.sort_unstable();
+    let mut expected = vec![alice.id as u32, bob.id as u32];
+    expected.sort_unstable();
+    assert_eq!(members, expected);
+    assert_eq!(
+        layer.get_direct_partner(room.id, alice.id as u32).await,
+        Some(bob.id as u32)
+    );
+
+    // Someone else gets a room of their own, which the others can't see
+    let carol_session = layer.get_session_from_user_id(carol.id as u32).await;
+    let (other, _) = layer
+        .open_direct_room(&carol_session, "dm_alice")
+        .await
+        .expect("Failed to open direct message");
+    assert_ne!(other.id, room.id);
+    assert!(!layer.can_view(carol.id as u32, room.id).await);
+    assert!(!layer.can_view(bob.id as u32, other.id).await);
+    assert!(!layer.can_view(0, room.id).await);
+
+    // Nobody can message themselves, and guests can't message anyone
+    assert!(layer
+        .open_direct_room(&alice_session, "dm_alice")
+        .await
+        .is_none());
+    assert!(layer
+        .open_direct_room(&Session::default(), "dm_alice")
+        .await
+        .is_none());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
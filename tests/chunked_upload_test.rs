@@ -0,0 +1,299 @@
+//! Integration tests for chunked, resumable uploads
+
+mod common;
+use serial_test::serial;
+
+use chrono::{Duration, Utc};
+use common::{database::*, fixtures::*};
+use dumpster::filesystem::chunked::{
+    completed_parts, expected_part_size, find_uploaded_attachment, get_received_parts, get_session,
+    part_count, record_part, staging_key,
+};
+use dumpster::orm::upload_sessions;
+use dumpster::storage::local::LocalStorage;
+use dumpster::storage::{CompletedPart, StorageBackend};
+use futures::StreamExt;
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection};
+
+// ============================================================================
+// Part Size Tests
+// ============================================================================
+
+#[test]
+fn test_part_sizes() {
+    assert_eq!(part_count(10, 4), 3);
+    assert_eq!(part_count(8, 4), 2);
+    assert_eq!(part_count(1, 4), 1);
+
+    assert_eq!(expected_part_size(10, 4, 1), Some(4));
+    assert_eq!(expected_part_size(10, 4, 3), Some(2));
+    assert_eq!(expected_part_size(8, 4, 2), Some(4));
+    assert_eq!(expected_part_size(10, 4, 0), None);
+    assert_eq!(expected_part_size(10, 4, 4), None);
+}
+
+// ============================================================================
+// Session Bookkeeping Tests
+// ============================================================================
+
+async fn create_session(
+    db: &DatabaseConnection,
+    id: &str,
+    user_id: i32,
+    expires_in: Duration,
+) -> upload_sessions::Model {
+    let now = Utc::now().naive_utc();
+    upload_sessions::ActiveModel {
+        id: Set(id.to_string()),
+        user_id: Set(user_id),
+        filename: Set("holiday.mkv".to_string()),
+        mime: Set("video/x-matroska".to_string()),
+        filesize: Set(10),
+        hash: Set("b".repeat(64)),
+        storage_key: Set(staging_key(id)),
+        storage_upload_id: Set("upload".to_string()),
+        chunk_size: Set(4),
+        created_at: Set(now),
+        expires_at: Set(now + expires_in),
+    }
+    .insert(db)
+    .await
+    .expect("Failed to insert upload session")
+}
+
+fn part(part_number: i32, e_tag: &str) -> CompletedPart {
+    CompletedPart {
+        part_number,
+        e_tag: e_tag.to_string(),
+    }
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_sessions_are_private_and_expire() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let owner = create_test_user(&db, "chunk_owner", "password123")
+        .await
+        .expect("Failed to create user");
+    let other = create_test_user(&db, "chunk_other", "password123")
+        .await
+        .expect("Failed to create user");
+
+    create_session(&db, "active", owner.id, Duration::hours(1)).await;
+    create_session(&db, "expired", owner.id, Duration::hours(-1)).await;
+
+    assert!(get_session("active", owner.id).await.is_some());
+    assert!(get_session("active", other.id).await.is_none());
+    assert!(get_session("expired", owner.id).await.is_none());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_only_own_clean_uploads_can_be_attached() {
+    use dumpster::orm::attachments::{self, ScanStatus};
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let owner = create_test_user(&db, "chunk_owner", "password123")
+        .await
+        .expect("Failed to create user");
+    let other = create_test_user(&db, "chunk_other", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let now = Utc::now().naive_utc();
+    let hash = "d".repeat(64);
+    let attachment = create_test_attachment(&db, &hash, "private.pdf", "application/pdf", 10, now)
+        .await
+        .expect("Failed to insert attachment");
+    dumpster::filesystem::quota::record_upload(owner.id, attachment.id).await;
+
+    let found = find_uploaded_attachment(owner.id, &hash)
+        .await
+        .expect("Failed to look up attachment");
+    assert_eq!(found.map(|a| a.id), Some(attachment.id));
+
+    // Knowing the hash of someone else's file isn't enough
+    assert!(find_uploaded_attachment(other.id, &hash)
+        .await
+        .expect("Failed to look up attachment")
+        .is_none());
+
+    // Nor can the uploader attach it once it's quarantined
+    attachments::ActiveModel {
+        id: Set(attachment.id),
+        scan_status: Set(ScanStatus::Infected),
+        ..Default::default()
+    }
+    .update(&db)
+    .await
+    .expect("Failed to quarantine attachment");
+    assert!(find_uploaded_attachment(owner.id, &hash)
+        .await
+        .expect("Failed to look up attachment")
+        .is_none());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_parts_must_all_arrive_before_completion() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "chunk_user", "password123")
+        .await
+        .expect("Failed to create user");
+    let session = create_session(&db, "resume", user.id, Duration::hours(1)).await;
+
+    // Parts may arrive out of order
+    record_part(&session.id, &part(3, "c"), 2).await.unwrap();
+    record_part(&session.id, &part(1, "a"), 4).await.unwrap();
+
+    let received = get_received_parts(&session.id).await;
+    assert_eq!(
+        received.iter().map(|p| p.part_number).collect::<Vec<_>>(),
+        vec![1, 3]
+    );
+    assert!(completed_parts(&session, &received)
+        .unwrap_err()
+        .contains("Part 2"));
+
+    // A truncated part is caught before the storage backend joins anything
+    record_part(&session.id, &part(2, "short"), 3)
+        .await
+        .unwrap();
+    let received = get_received_parts(&session.id).await;
+    assert!(completed_parts(&session, &received)
+        .unwrap_err()
+        .contains("wrong size"));
+
+    // Re-sending a part replaces it
+    record_part(&session.id, &part(2, "b"), 4).await.unwrap();
+    let received = get_received_parts(&session.id).await;
+    assert_eq!(received.len(), 3);
+
+    let parts = completed_parts(&session, &received).expect("Upload is complete");
+    assert_eq!(
+        parts
+            .iter()
+            .map(|p| (p.part_number, p.e_tag.as_str()))
+            .collect::<Vec<_>>(),
+        vec![(1, "a"), (2, "b"), (3, "c")]
+    );
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+// ============================================================================
+// Local Storage Assembly Tests
+// ============================================================================
+
+#[actix_rt::test]
+async fn test_local_storage_joins_parts_in_order() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let storage = LocalStorage::new(dir.path().to_path_buf()).expect("Failed to init storage");
+    let filename = format!("{}.bin", "c".repeat(64));
+
+    let upload_id = storage.create_multipart_upload(&filename).await.unwrap();
+    let second = storage
+        .upload_part(&filename, &upload_id, 2, b"world".to_vec())
+        .await
+        .unwrap();
+    storage
+        .upload_part(&filename, &upload_id, 1, b"HELLO ".to_vec())
+        .await
+        .unwrap();
+    // A retried part overwrites the first attempt
+    let first = storage
+        .upload_part(&filename, &upload_id, 1, b"hello ".to_vec())
+        .await
+        .unwrap();
+
+    storage
+        .complete_multipart_upload(&filename, &upload_id, vec![first, second])
+        .await
+        .unwrap();
+
+    let mut object = storage.get_object(&filename, None).await.unwrap();
+    let mut data = Vec::new();
+    while let Some(chunk) = object.body.next().await {
+        data.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(data, b"hello world");
+
+    // Staged parts are gone once joined
+    assert!(storage
+        .upload_part(&filename, &upload_id, 3, b"!".to_vec())
+        .await
+        .is_err());
+
+    storage.delete_object(&filename).await.unwrap();
+    assert!(!storage.exists(&filename).await.unwrap());
+}
+
+#[actix_rt::test]
+async fn test_local_storage_rejects_unsafe_upload_ids() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let storage = LocalStorage::new(dir.path().to_path_buf()).expect("Failed to init storage");
+
+    assert!(storage
+        .upload_part("file.bin", "../../etc", 1, b"x".to_vec())
+        .await
+        .is_err());
+    assert!(storage
+        .abort_multipart_upload("file.bin", "../..")
+        .await
+        .is_err());
+
+    let upload_id = storage.create_multipart_upload("file.bin").await.unwrap();
+    storage
+        .abort_multipart_upload("file.bin", &upload_id)
+        .await
+        .unwrap();
+    assert!(storage
+        .upload_part("file.bin", &upload_id, 1, b"x".to_vec())
+        .await
+        .is_err());
+}
+
+#[actix_rt::test]
+async fn test_local_storage_moves_verified_upload_into_place() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let storage = LocalStorage::new(dir.path().to_path_buf()).expect("Failed to init storage");
+    let staged = staging_key("0f3c9a2e-5b1d-4c8e-9a7f-2d6b8e1c4a90");
+    let filename = format!("{}.bin", "c".repeat(64));
+
+    // Uploads are joined away from the file they claim to be
+    assert_ne!(staged, filename);
+    storage
+        .put_object(b"old".to_vec(), &filename)
+        .await
+        .unwrap();
+    storage.put_object(b"new".to_vec(), &staged).await.unwrap();
+
+    storage.rename_object(&staged, &filename).await.unwrap();
+    assert!(!storage.exists(&staged).await.unwrap());
+
+    let mut object = storage.get_object(&filename, None).await.unwrap();
+    let mut data = Vec::new();
+    while let Some(chunk) = object.body.next().await {
+        data.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(data, b"new");
+}
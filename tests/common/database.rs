@@ -80,9 +80,14 @@ pub async fn cleanup_test_data(db: &DatabaseConnection) -> Result<(), DbErr> {
     db.execute(Statement::from_string(
         db.get_database_backend(),
         "TRUNCATE TABLE
+            activity_archives,
+            link_clicks,
             chat_messages,
             chat_rooms,
+            forum_permission_templates,
             forum_permissions,
+            thread_permissions,
+            permission_audit,
             permission_values,
             permission_collections,
             user_groups,
@@ -90,15 +95,23 @@ pub async fn cleanup_test_data(db: &DatabaseConnection) -> Result<(), DbErr> {
             user_names,
             user_2fa,
             user_avatars,
+            avatar_gallery,
             sessions,
             posts,
             threads,
+            profile_post_comments,
             profile_posts,
             ugc_deletions,
             ugc_attachments,
             ugc_revisions,
             attachments,
             attachment_thumbnails,
+            attachment_variants,
+            video_transcodes,
+            jobs,
+            upload_session_parts,
+            upload_sessions,
+            user_uploads,
             password_reset_tokens,
             email_verification_tokens,
             user_bans,
@@ -489,6 +489,32 @@ pub async fn create_test_post(
     post.insert(db).await
 }
 
+/// Create an attachment record for a file that was never stored
+pub async fn create_test_attachment(
+    db: &DatabaseConnection,
+    hash: &str,
+    filename: &str,
+    mime: &str,
+    filesize: i64,
+    seen_at: chrono::NaiveDateTime,
+) -> Result<dumpster::orm::attachments::Model, DbErr> {
+    use dumpster::orm::attachments;
+
+    let attachment = attachments::ActiveModel {
+        filename: Set(filename.to_string()),
+        hash: Set(hash.to_string()),
+        first_seen_at: Set(seen_at),
+        last_seen_at: Set(seen_at),
+        filesize: Set(filesize),
+        file_width: Set(None),
+        file_height: Set(None),
+        mime: Set(mime.to_string()),
+        meta: Set(serde_json::json!({})),
+        ..Default::default()
+    };
+    attachment.insert(db).await
+}
+
 /// Create a test chat room
 pub async fn create_test_chat_room(
     db: &DatabaseConnection,
@@ -0,0 +1,113 @@
+/// Integration tests for live conversation events over the notification WebSocket
+/// Tests typing indicators and read receipts reaching the other participants
+mod common;
+use serial_test::serial;
+
+use actix::prelude::*;
+use common::{database::*, fixtures::*};
+use dumpster::conversations::{self, realtime};
+use dumpster::web::notifications_ws::message::{Connect, NotificationPush};
+use dumpster::web::notifications_ws::{
+    get_notification_server, init_notification_server, NotificationServer,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Stands in for a browser connection and records everything pushed to it
+struct Collector {
+    received: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl Actor for Collector {
+    type Context = Context<Self>;
+}
+
+impl Handler<NotificationPush> for Collector {
+    type Result = ();
+
+    fn handle(&mut self, msg: NotificationPush, _: &mut Context<Self>) {
+        self.received
+            .lock()
+            .unwrap()
+            .push(serde_json::from_str(&msg.0).unwrap());
+    }
+}
+
+async fn connect_collector(user_id: i32) -> Arc<Mutex<Vec<serde_json::Value>>> {
+    if get_notification_server().is_none() {
+        init_notification_server(NotificationServer::new().start());
+    }
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let collector = Collector {
+        received: received.clone(),
+    }
+    .start();
+
+    get_notification_server()
+        .unwrap()
+        .send(Connect {
+            addr: collector.recipient(),
+            user_id,
+        })
+        .await
+        .expect("Failed to register collector");
+
+    received
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_typing_and_read_events_reach_other_participants() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create alice");
+    let bob = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+    let charlie = create_test_user_with_email(&db, "charlie", "charlie@example.com", true)
+        .await
+        .expect("Failed to create charlie");
+
+    let conversation_id = conversations::create_conversation(alice.id, &[bob.id], None)
+        .await
+        .expect("Failed to create conversation");
+    let message_id = conversations::send_message(conversation_id, alice.id, "Hello")
+        .await
+        .expect("Failed to send message");
+
+    let alice_events = connect_collector(alice.id).await;
+    let bob_events = connect_collector(bob.id).await;
+
+    realtime::broadcast_typing(bob.id, conversation_id)
+        .await
+        .expect("Failed to broadcast typing");
+    realtime::mark_read(bob.id, conversation_id)
+        .await
+        .expect("Failed to mark read");
+
+    // Outsiders can't send events into the conversation
+    assert!(realtime::broadcast_typing(charlie.id, conversation_id)
+        .await
+        .is_err());
+
+    actix_rt::time::sleep(Duration::from_millis(100)).await;
+
+    let alice_events = alice_events.lock().unwrap();
+    assert_eq!(alice_events.len(), 2);
+    assert_eq!(alice_events[0]["type"], "conversation_typing");
+    assert_eq!(alice_events[0]["data"]["name"], "bob");
+    assert_eq!(alice_events[0]["data"]["conversation_id"], conversation_id);
+    assert_eq!(alice_events[1]["type"], "conversation_read");
+    assert_eq!(alice_events[1]["data"]["user_id"], bob.id);
+    assert_eq!(alice_events[1]["data"]["last_read_message_id"], message_id);
+
+    // The sender doesn't get their own events back
+    assert!(bob_events.lock().unwrap().is_empty());
+}
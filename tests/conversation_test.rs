@@ -207,7 +207,7 @@ async fn test_mark_conversation_read() {
         .expect("Failed to create conversation");
 
     // Send a message
-    conversations::send_message(conversation_id, user1.id, "Test message")
+    let message_id = conversations::send_message(conversation_id, user1.id, "Test message")
         .await
         .expect("Failed to send message");
 
@@ -220,14 +220,14 @@ async fn test_mark_conversation_read() {
         .expect("Failed to find participant")
         .expect("Participant not found");
 
-    assert!(participant_before.last_read_at.is_none());
+    assert!(participant_before.last_read_message_id.is_none());
 
     // Mark conversation as read for user2
     conversations::mark_conversation_read(user2.id, conversation_id)
         .await
         .expect("Failed to mark as read");
 
-    // Verify last_read_at was updated
+    // Verify user2 has read up to the message
     let participant_after = conversation_participants::Entity::find()
         .filter(conversation_participants::Column::ConversationId.eq(conversation_id))
         .filter(conversation_participants::Column::UserId.eq(user2.id))
@@ -236,7 +236,7 @@ async fn test_mark_conversation_read() {
         .expect("Failed to find participant")
         .expect("Participant not found");
 
-    assert!(participant_after.last_read_at.is_some());
+    assert_eq!(participant_after.last_read_message_id, Some(message_id));
 }
 
 #[actix_rt::test]
@@ -355,6 +355,64 @@ async fn test_get_user_conversations() {
     assert!(preview.is_unread);
 }
 
+#[actix_rt::test]
+#[serial]
+async fn test_conversation_previews_batch_participants() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user1 = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create alice");
+
+    let user2 = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+
+    let user3 = create_test_user_with_email(&db, "charlie", "charlie@example.com", true)
+        .await
+        .expect("Failed to create charlie");
+
+    let group = conversations::create_conversation(user1.id, &[user2.id, user3.id], Some("Group"))
+        .await
+        .expect("Failed to create group conversation");
+
+    let direct = conversations::create_conversation(user1.id, &[user2.id], None)
+        .await
+        .expect("Failed to create direct conversation");
+
+    conversations::send_message(group, user2.id, "First")
+        .await
+        .expect("Failed to send message");
+    conversations::send_message(group, user3.id, "Latest")
+        .await
+        .expect("Failed to send message");
+
+    let previews = conversations::get_user_conversations(user1.id, 10)
+        .await
+        .expect("Failed to get conversations");
+
+    assert_eq!(previews.len(), 2);
+
+    let group_preview = previews.iter().find(|c| c.id == group).unwrap();
+    let mut names = group_preview.participants.clone();
+    names.sort();
+    assert_eq!(names, vec!["bob".to_string(), "charlie".to_string()]);
+    assert_eq!(
+        group_preview.last_message_content,
+        Some("Latest".to_string())
+    );
+    assert_eq!(group_preview.unread_count, 2);
+
+    let direct_preview = previews.iter().find(|c| c.id == direct).unwrap();
+    assert_eq!(direct_preview.participants, vec!["bob".to_string()]);
+    assert_eq!(direct_preview.last_message_content, None);
+    assert!(!direct_preview.is_unread);
+}
+
 #[actix_rt::test]
 #[serial]
 async fn test_get_conversation_messages() {
@@ -837,3 +895,499 @@ async fn test_send_message_does_not_mark_sender_unread() {
         "Recipient should have 1 unread conversation after message received"
     );
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_participant_changes_record_system_messages() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user1 = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create alice");
+
+    let user2 = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+
+    let user3 = create_test_user_with_email(&db, "charlie", "charlie@example.com", true)
+        .await
+        .expect("Failed to create charlie");
+
+    let conversation_id = conversations::create_conversation(user1.id, &[user2.id], None)
+        .await
+        .expect("Failed to create conversation");
+
+    // Only the owner can invite
+    let result = conversations::invite_participant(user2.id, conversation_id, user3.id).await;
+    assert!(result.is_err());
+
+    conversations::invite_participant(user1.id, conversation_id, user3.id)
+        .await
+        .expect("Failed to invite charlie");
+    conversations::verify_participant(&db, user3.id, conversation_id)
+        .await
+        .expect("Charlie should be a participant");
+
+    conversations::kick_participant(user1.id, conversation_id, user3.id)
+        .await
+        .expect("Failed to remove charlie");
+    assert!(
+        conversations::verify_participant(&db, user3.id, conversation_id)
+            .await
+            .is_err()
+    );
+
+    let messages = conversations::get_conversation_messages(conversation_id, 10, 0)
+        .await
+        .expect("Failed to get messages");
+    let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(
+        contents,
+        vec![
+            "alice invited charlie to the conversation",
+            "alice removed charlie from the conversation",
+        ]
+    );
+    assert!(messages.iter().all(|m| m.is_system()));
+    assert_eq!(messages[0].kind, private_messages::MessageKind::Invited);
+
+    // System messages cannot be edited or deleted, even by moderators
+    assert!(
+        conversations::update_message(messages[0].id, user1.id, "edited")
+            .await
+            .is_err()
+    );
+    assert!(
+        conversations::delete_message(messages[0].id, user1.id, true)
+            .await
+            .is_err()
+    );
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_transfer_ownership() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user1 = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create alice");
+
+    let user2 = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+
+    let user3 = create_test_user_with_email(&db, "charlie", "charlie@example.com", true)
+        .await
+        .expect("Failed to create charlie");
+
+    let conversation_id = conversations::create_conversation(user1.id, &[user2.id], None)
+        .await
+        .expect("Failed to create conversation");
+
+    // Ownership can only go to a participant
+    let result = conversations::transfer_ownership(user1.id, conversation_id, user3.id).await;
+    assert!(result.is_err());
+
+    conversations::transfer_ownership(user1.id, conversation_id, user2.id)
+        .await
+        .expect("Failed to transfer ownership");
+
+    let owner = conversations::get_conversation_owner(conversation_id)
+        .await
+        .expect("Failed to get owner");
+    assert_eq!(owner, Some(user2.id));
+
+    // The previous owner can no longer manage participants
+    let result = conversations::invite_participant(user1.id, conversation_id, user3.id).await;
+    assert!(result.is_err());
+    let result = conversations::transfer_ownership(user1.id, conversation_id, user1.id).await;
+    assert!(result.is_err());
+
+    let messages = conversations::get_conversation_messages(conversation_id, 10, 0)
+        .await
+        .expect("Failed to get messages");
+    assert_eq!(messages.len(), 1);
+    assert_eq!(
+        messages[0].kind,
+        private_messages::MessageKind::OwnerChanged
+    );
+    assert_eq!(messages[0].content, "bob is now the conversation owner");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_owner_leaving_passes_ownership() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user1 = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create alice");
+
+    let user2 = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+
+    let conversation_id = conversations::create_conversation(user1.id, &[user2.id], None)
+        .await
+        .expect("Failed to create conversation");
+
+    conversations::leave_conversation(user1.id, conversation_id)
+        .await
+        .expect("Failed to leave conversation");
+
+    let owner = conversations::get_conversation_owner(conversation_id)
+        .await
+        .expect("Failed to get owner");
+    assert_eq!(owner, Some(user2.id));
+
+    let messages = conversations::get_conversation_messages(conversation_id, 10, 0)
+        .await
+        .expect("Failed to get messages");
+    let kinds: Vec<_> = messages.iter().map(|m| m.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            private_messages::MessageKind::Left,
+            private_messages::MessageKind::OwnerChanged,
+        ]
+    );
+    assert_eq!(messages[0].content, "alice left the conversation");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_count_unread_messages() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user1 = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create alice");
+
+    let user2 = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+
+    let user3 = create_test_user_with_email(&db, "charlie", "charlie@example.com", true)
+        .await
+        .expect("Failed to create charlie");
+
+    let conv1 = conversations::create_conversation(user1.id, &[user2.id], None)
+        .await
+        .expect("Failed to create conversation 1");
+
+    let conv2 = conversations::create_conversation(user3.id, &[user2.id], None)
+        .await
+        .expect("Failed to create conversation 2");
+
+    for content in ["One", "Two", "Three"] {
+        conversations::send_message(conv1, user1.id, content)
+            .await
+            .expect("Failed to send message");
+    }
+    let deleted = conversations::send_message(conv2, user3.id, "Oops")
+        .await
+        .expect("Failed to send message");
+    conversations::send_message(conv2, user3.id, "Hello")
+        .await
+        .expect("Failed to send message");
+    conversations::delete_message(deleted, user3.id, false)
+        .await
+        .expect("Failed to delete message");
+
+    // System messages do not count as unread
+    conversations::invite_participant(user1.id, conv1, user3.id)
+        .await
+        .expect("Failed to invite charlie");
+
+    assert_eq!(
+        conversations::count_unread_messages(user2.id)
+            .await
+            .expect("Failed to count unread"),
+        4
+    );
+    assert_eq!(
+        conversations::count_unread_conversations(user2.id)
+            .await
+            .expect("Failed to count unread"),
+        2
+    );
+
+    let previews = conversations::get_user_conversations(user2.id, 10)
+        .await
+        .expect("Failed to get conversations");
+    let conv1_preview = previews.iter().find(|p| p.id == conv1).unwrap();
+    assert_eq!(conv1_preview.unread_count, 3);
+
+    // Replying marks everything before the reply as read
+    conversations::send_message(conv1, user2.id, "Reply")
+        .await
+        .expect("Failed to send reply");
+    assert_eq!(
+        conversations::count_unread_messages(user2.id)
+            .await
+            .expect("Failed to count unread"),
+        1
+    );
+    assert_eq!(
+        conversations::count_unread_messages(user1.id)
+            .await
+            .expect("Failed to count unread"),
+        1
+    );
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_seen_by_markers() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user1 = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create alice");
+
+    let user2 = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+
+    let user3 = create_test_user_with_email(&db, "charlie", "charlie@example.com", true)
+        .await
+        .expect("Failed to create charlie");
+
+    let conversation_id = conversations::create_conversation(user1.id, &[user2.id, user3.id], None)
+        .await
+        .expect("Failed to create conversation");
+
+    let first = conversations::send_message(conversation_id, user1.id, "First")
+        .await
+        .expect("Failed to send message");
+    conversations::mark_conversation_read(user2.id, conversation_id)
+        .await
+        .expect("Failed to mark as read");
+    let second = conversations::send_message(conversation_id, user1.id, "Second")
+        .await
+        .expect("Failed to send message");
+    conversations::mark_conversation_read(user3.id, conversation_id)
+        .await
+        .expect("Failed to mark as read");
+
+    let messages = conversations::get_conversation_messages(conversation_id, 10, 0)
+        .await
+        .expect("Failed to get messages");
+    let participants = conversations::get_participant_info(conversation_id)
+        .await
+        .expect("Failed to get participants");
+
+    let seen_by = conversations::get_seen_by(&messages, &participants, user1.id);
+    assert_eq!(seen_by.get(&first), Some(&vec!["bob".to_string()]));
+    assert_eq!(seen_by.get(&second), Some(&vec!["charlie".to_string()]));
+
+    // Readers are not shown under their own messages
+    let reply = conversations::send_message(conversation_id, user2.id, "Reply")
+        .await
+        .expect("Failed to send reply");
+    let messages = conversations::get_conversation_messages(conversation_id, 10, 0)
+        .await
+        .expect("Failed to get messages");
+    let participants = conversations::get_participant_info(conversation_id)
+        .await
+        .expect("Failed to get participants");
+    let seen_by = conversations::get_seen_by(&messages, &participants, user3.id);
+    assert!(!seen_by.contains_key(&reply));
+    assert!(!seen_by.contains_key(&first));
+    assert!(!seen_by
+        .values()
+        .any(|names| names.contains(&"charlie".to_string())));
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_muted_conversation_skips_notifications() {
+    use dumpster::notifications::{self, NotificationType};
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user1 = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create alice");
+
+    let user2 = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+
+    let user3 = create_test_user_with_email(&db, "charlie", "charlie@example.com", true)
+        .await
+        .expect("Failed to create charlie");
+
+    let conversation_id = conversations::create_conversation(user1.id, &[user2.id], None)
+        .await
+        .expect("Failed to create conversation");
+
+    conversations::set_conversation_muted(user2.id, conversation_id, true)
+        .await
+        .expect("Failed to mute conversation");
+
+    // Outsiders can't mute
+    assert!(
+        conversations::set_conversation_muted(user3.id, conversation_id, true)
+            .await
+            .is_err()
+    );
+
+    let notify = || {
+        notifications::create_notification(
+            user2.id,
+            NotificationType::PrivateMessage,
+            "New message from alice".to_string(),
+            "You have a new message in a conversation".to_string(),
+            Some(format!("/conversations/{}", conversation_id)),
+            Some(user1.id),
+            Some("conversation".to_string()),
+            Some(conversation_id),
+        )
+    };
+
+    conversations::send_message(conversation_id, user1.id, "Hello Bob!")
+        .await
+        .expect("Failed to send message");
+    assert_eq!(notify().await.expect("Failed to notify"), 0);
+
+    // The message still counts as unread
+    let previews = conversations::get_user_conversations(user2.id, 10)
+        .await
+        .expect("Failed to get conversations");
+    assert!(previews[0].is_muted);
+    assert_eq!(previews[0].unread_count, 1);
+
+    conversations::set_conversation_muted(user2.id, conversation_id, false)
+        .await
+        .expect("Failed to unmute conversation");
+    assert!(notify().await.expect("Failed to notify") > 0);
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_rename_conversation_owner_only() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user1 = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create alice");
+
+    let user2 = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+
+    let conversation_id = conversations::create_conversation(user1.id, &[user2.id], None)
+        .await
+        .expect("Failed to create conversation");
+
+    // Members can't rename
+    let result = conversations::rename_conversation(user2.id, conversation_id, Some("Plans")).await;
+    assert!(matches!(result, Err(DbErr::Custom(_))));
+
+    conversations::rename_conversation(user1.id, conversation_id, Some("  Plans  "))
+        .await
+        .expect("Failed to rename conversation");
+
+    let conversation = conversation_orm::Entity::find_by_id(conversation_id)
+        .one(&db)
+        .await
+        .expect("Failed to query conversation")
+        .expect("Conversation not found");
+    assert_eq!(conversation.title, Some("Plans".to_string()));
+
+    // Clearing the title
+    conversations::rename_conversation(user1.id, conversation_id, Some(""))
+        .await
+        .expect("Failed to clear title");
+
+    let messages = conversations::get_conversation_messages(conversation_id, 10, 0)
+        .await
+        .expect("Failed to get messages");
+    let system: Vec<&str> = messages
+        .iter()
+        .filter(|m| m.is_system())
+        .map(|m| m.content.as_str())
+        .collect();
+    assert_eq!(
+        system,
+        vec![
+            "alice renamed the conversation to \"Plans\"",
+            "alice removed the conversation title",
+        ]
+    );
+
+    // Unknown conversations are reported as missing
+    let result = conversations::rename_conversation(user1.id, conversation_id + 1000, None).await;
+    assert!(matches!(result, Err(DbErr::RecordNotFound(_))));
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_participant_limit() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let max = conversations::max_participants();
+    let mut users = Vec::new();
+    for i in 0..=max {
+        let name = format!("user{}", i);
+        users.push(
+            create_test_user_with_email(&db, &name, &format!("{}@example.com", name), true)
+                .await
+                .expect("Failed to create user"),
+        );
+    }
+    let owner = users[0].id;
+    let others: Vec<i32> = users[1..].iter().map(|u| u.id).collect();
+
+    // One more than the limit, counting the owner
+    let result = conversations::create_conversation(owner, &others, None).await;
+    assert!(matches!(result, Err(DbErr::Custom(_))));
+
+    // Exactly at the limit
+    let conversation_id = conversations::create_conversation(owner, &others[..max - 1], None)
+        .await
+        .expect("Failed to create conversation");
+
+    let result = conversations::invite_participant(owner, conversation_id, others[max - 1]).await;
+    assert!(matches!(result, Err(DbErr::Custom(_))));
+
+    let participants = conversations::get_participant_info(conversation_id)
+        .await
+        .expect("Failed to get participants");
+    assert_eq!(participants.len(), max);
+    assert_eq!(participants.iter().filter(|p| p.is_owner()).count(), 1);
+}
@@ -0,0 +1,143 @@
+//! Integration tests for changing a member's email address
+
+mod common;
+use serial_test::serial;
+
+use common::{database::*, fixtures::*};
+use dumpster::email::change::{self, ChangeError};
+use dumpster::orm::users;
+use sea_orm::entity::*;
+
+#[actix_rt::test]
+#[serial]
+async fn test_confirm_then_revert() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create user");
+    create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create user");
+
+    assert!(matches!(
+        change::request(&alice, "Alice@Example.com").await,
+        Err(ChangeError::SameEmail)
+    ));
+    assert!(matches!(
+        change::request(&alice, "bob@example.com").await,
+        Err(ChangeError::EmailTaken)
+    ));
+    assert!(matches!(
+        change::request(&alice, "nope").await,
+        Err(ChangeError::InvalidEmail)
+    ));
+
+    // A second request replaces the first
+    let first = change::request(&alice, "alice@new.example.com")
+        .await
+        .expect("Failed to request change");
+    let second = change::request(&alice, " Alice@Newer.example.com ")
+        .await
+        .expect("Failed to request change");
+    assert_eq!(second.new_email, "alice@newer.example.com");
+    assert_eq!(second.old_email.as_deref(), Some("alice@example.com"));
+    assert!(change::confirm(&first.confirm_token)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        change::pending_for_user(alice.id)
+            .await
+            .unwrap()
+            .map(|c| c.id),
+        Some(second.id)
+    );
+
+    // Nothing changes until the new address confirms
+    let user = users::Entity::find_by_id(alice.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.email.as_deref(), Some("alice@example.com"));
+
+    change::confirm(&second.confirm_token)
+        .await
+        .expect("Failed to confirm")
+        .expect("Link should work");
+    let user = users::Entity::find_by_id(alice.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.email.as_deref(), Some("alice@newer.example.com"));
+    assert!(user.email_verified);
+    assert!(change::confirm(&second.confirm_token)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(change::pending_for_user(alice.id).await.unwrap().is_none());
+
+    // The old address can still undo it
+    assert!(change::find_revertible(&first.revert_token)
+        .await
+        .unwrap()
+        .is_none());
+    let confirmed = change::find_revertible(&second.revert_token)
+        .await
+        .unwrap()
+        .expect("Change should be revertible");
+    let reverted = change::revert(confirmed).await.expect("Failed to revert");
+    assert_eq!(reverted.status, change::STATUS_REVERTED);
+    let user = users::Entity::find_by_id(alice.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.email.as_deref(), Some("alice@example.com"));
+    assert!(change::find_revertible(&second.revert_token)
+        .await
+        .unwrap()
+        .is_none());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_reverting_pending_change_cancels_it() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create user");
+
+    let pending = change::request(&alice, "thief@example.com")
+        .await
+        .expect("Failed to request change");
+    let found = change::find_revertible(&pending.revert_token)
+        .await
+        .unwrap()
+        .expect("Change should be revertible");
+    change::revert(found).await.expect("Failed to revert");
+
+    assert!(change::confirm(&pending.confirm_token)
+        .await
+        .unwrap()
+        .is_none());
+    let user = users::Entity::find_by_id(alice.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.email.as_deref(), Some("alice@example.com"));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
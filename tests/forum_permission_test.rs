@@ -6,9 +6,11 @@ use serial_test::serial;
 use common::database::*;
 use dumpster::orm::{
     forum_permissions, forums, groups, permission_collections, permission_values, permissions,
+    thread_permissions, threads,
 };
 use dumpster::permission::flag::Flag;
-use sea_orm::{entity::*, query::*, ActiveValue::Set, DatabaseConnection, DbErr};
+use dumpster::permission::template;
+use sea_orm::{entity::*, query::*, ActiveValue::Set, DatabaseConnection, DbErr, PaginatorTrait};
 
 /// Create a test forum with optional parent
 async fn create_forum(
@@ -271,3 +273,230 @@ async fn test_deep_forum_hierarchy() {
 
     assert_eq!(depth, 2, "Should have walked up 2 levels to reach root");
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_permission_template_save_and_apply() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let permission = match find_permission_by_name(&db, "thread.create")
+        .await
+        .expect("Failed to query permission")
+    {
+        Some(p) => p,
+        None => {
+            println!("Skipping test: thread.create permission not found in database");
+            return;
+        }
+    };
+
+    let source = create_forum(&db, "Source Forum", None)
+        .await
+        .expect("Failed to create forum");
+    let target = create_forum(&db, "Target Forum", None)
+        .await
+        .expect("Failed to create forum");
+    let group = create_test_group(&db, "Test Group")
+        .await
+        .expect("Failed to create test group");
+
+    let collection = create_forum_permission_collection(&db, group.id)
+        .await
+        .expect("Failed to create permission collection");
+    link_collection_to_forum(&db, source.id, collection.id)
+        .await
+        .expect("Failed to link collection to forum");
+    set_permission_value(&db, collection.id, permission.id, Flag::NO)
+        .await
+        .expect("Failed to set permission value");
+
+    let saved = template::save_template(&db, source.id, "Read Only", None)
+        .await
+        .expect("Failed to save template");
+    let expected = vec![template::MatrixValue {
+        group_id: group.id,
+        permission_id: permission.id,
+        value: Flag::NO,
+    }];
+    assert_eq!(
+        template::template_matrix(&db, saved.id)
+            .await
+            .expect("Failed to read template"),
+        expected
+    );
+
+    template::replace_forum_matrix(&db, target.id, &expected)
+        .await
+        .expect("Failed to apply template");
+    assert_eq!(
+        template::forum_matrix(&db, target.id)
+            .await
+            .expect("Failed to read forum matrix"),
+        expected
+    );
+
+    // Applying again replaces rather than adds collections
+    template::replace_forum_matrix(&db, target.id, &expected)
+        .await
+        .expect("Failed to apply template");
+    let links = forum_permissions::Entity::find()
+        .filter(forum_permissions::Column::ForumId.eq(target.id))
+        .count(&db)
+        .await
+        .expect("Failed to count forum permissions");
+    assert_eq!(links, 1);
+
+    // The source forum is untouched
+    assert_eq!(
+        template::forum_matrix(&db, source.id)
+            .await
+            .expect("Failed to read forum matrix"),
+        expected
+    );
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_default_permission_template_applies_to_new_forums() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let permission = match find_permission_by_name(&db, "thread.create")
+        .await
+        .expect("Failed to query permission")
+    {
+        Some(p) => p,
+        None => {
+            println!("Skipping test: thread.create permission not found in database");
+            return;
+        }
+    };
+
+    let source = create_forum(&db, "Source Forum", None)
+        .await
+        .expect("Failed to create forum");
+    let group = create_test_group(&db, "Test Group")
+        .await
+        .expect("Failed to create test group");
+    let matrix = vec![template::MatrixValue {
+        group_id: group.id,
+        permission_id: permission.id,
+        value: Flag::YES,
+    }];
+    template::replace_forum_matrix(&db, source.id, &matrix)
+        .await
+        .expect("Failed to set forum matrix");
+
+    let saved = template::save_template(&db, source.id, "Default", None)
+        .await
+        .expect("Failed to save template");
+
+    // Not the default yet
+    let before = create_forum(&db, "Before Default", None)
+        .await
+        .expect("Failed to create forum");
+    assert!(template::forum_matrix(&db, before.id)
+        .await
+        .expect("Failed to read forum matrix")
+        .is_empty());
+
+    template::set_default_template(&db, Some(saved.id))
+        .await
+        .expect("Failed to set default template");
+    let after = create_forum(&db, "After Default", None)
+        .await
+        .expect("Failed to create forum");
+    assert_eq!(
+        template::forum_matrix(&db, after.id)
+            .await
+            .expect("Failed to read forum matrix"),
+        matrix
+    );
+
+    template::set_default_template(&db, None)
+        .await
+        .expect("Failed to clear default template");
+    let cleared = create_forum(&db, "After Clearing", None)
+        .await
+        .expect("Failed to create forum");
+    assert!(template::forum_matrix(&db, cleared.id)
+        .await
+        .expect("Failed to read forum matrix")
+        .is_empty());
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_thread_permission_collection_removed_with_thread() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let permission = match find_permission_by_name(&db, "thread.view")
+        .await
+        .expect("Failed to query permission")
+    {
+        Some(p) => p,
+        None => {
+            println!("Skipping test: thread.view permission not found in database");
+            return;
+        }
+    };
+
+    let forum = create_forum(&db, "Thread Forum", None)
+        .await
+        .expect("Failed to create forum");
+    let thread = threads::ActiveModel {
+        forum_id: Set(forum.id),
+        user_id: Set(None),
+        title: Set("Members Only".to_string()),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        post_count: Set(0),
+        view_count: Set(0),
+        is_locked: Set(false),
+        is_pinned: Set(false),
+        is_announcement: Set(false),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create thread");
+
+    let group = create_test_group(&db, "Test Group")
+        .await
+        .expect("Failed to create test group");
+    let collection = create_forum_permission_collection(&db, group.id)
+        .await
+        .expect("Failed to create collection");
+    thread_permissions::ActiveModel {
+        thread_id: Set(thread.id),
+        collection_id: Set(collection.id),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to link collection to thread");
+    set_permission_value(&db, collection.id, permission.id, Flag::NO)
+        .await
+        .expect("Failed to set permission value");
+
+    threads::Entity::delete_by_id(thread.id)
+        .exec(&db)
+        .await
+        .expect("Failed to delete thread");
+
+    // The override collection must not outlive the thread and become a global grant
+    assert!(permission_collections::Entity::find_by_id(collection.id)
+        .one(&db)
+        .await
+        .expect("Failed to query collection")
+        .is_none());
+}
@@ -0,0 +1,103 @@
+//! Integration tests for parent groups and group display priority
+
+mod common;
+use serial_test::serial;
+
+use common::{database::*, fixtures::*};
+use dumpster::cache::{self, CacheScope};
+use dumpster::group::{display_styles, get_group_ids_for_client, GroupType};
+use dumpster::orm::{groups, user_groups};
+use dumpster::user::Profile;
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection};
+
+async fn create_group(
+    db: &DatabaseConnection,
+    label: &str,
+    display_priority: i32,
+    parent_id: Option<i32>,
+    username_color: Option<&str>,
+) -> groups::Model {
+    groups::ActiveModel {
+        label: Set(label.to_string()),
+        group_type: Set(GroupType::Normal),
+        display_priority: Set(display_priority),
+        parent_id: Set(parent_id),
+        username_color: Set(username_color.map(str::to_owned)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to create group")
+}
+
+async fn add_user_to_group(db: &DatabaseConnection, user_id: i32, group_id: i32) {
+    user_groups::ActiveModel {
+        user_id: Set(user_id),
+        group_id: Set(group_id),
+    }
+    .insert(db)
+    .await
+    .expect("Failed to add user to group");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_members_of_a_group_are_in_its_parents() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+    cache::invalidate(CacheScope::GroupIds).await;
+
+    let user = create_test_user(&db, "hierarchy_member", "password123")
+        .await
+        .expect("Failed to create user");
+    let staff = create_group(&db, "Staff", 0, None, None).await;
+    let moderators = create_group(&db, "Moderators", 0, Some(staff.id), None).await;
+    let event_moderators =
+        create_group(&db, "Event Moderators", 0, Some(moderators.id), None).await;
+    add_user_to_group(&db, user.id, event_moderators.id).await;
+
+    let profile = Profile::get_by_id(&db, user.id)
+        .await
+        .expect("Failed to load profile");
+    let mut ids = get_group_ids_for_client(&db, &profile).await;
+    ids.sort_unstable();
+    assert_eq!(ids, vec![staff.id, moderators.id, event_moderators.id]);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_highest_priority_group_styles_names() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "hierarchy_styled", "password123")
+        .await
+        .expect("Failed to create user");
+    let plain = create_test_user(&db, "hierarchy_plain", "password123")
+        .await
+        .expect("Failed to create user");
+    let members = create_group(&db, "Members", 1, None, Some("#888888")).await;
+    let admins = create_group(&db, "Admins", 10, None, Some("#cc0000")).await;
+    let unstyled = create_group(&db, "Unstyled", 20, None, None).await;
+    add_user_to_group(&db, user.id, members.id).await;
+    add_user_to_group(&db, user.id, admins.id).await;
+    add_user_to_group(&db, user.id, unstyled.id).await;
+
+    let styles = display_styles(&db, &[user.id, plain.id])
+        .await
+        .expect("Failed to load styles");
+    let style = &styles[&user.id];
+    assert_eq!(style.label, "Admins");
+    assert_eq!(style.color.as_deref(), Some("#cc0000"));
+    assert!(!styles.contains_key(&plain.id));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,121 @@
+//! Integration tests for WebP/AVIF variants of uploaded images
+
+mod common;
+use serial_test::serial;
+
+use chrono::Utc;
+use common::database::*;
+use dumpster::attachment::{get_attachment_variants, preferred_variant};
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection};
+use std::collections::HashMap;
+
+fn variants() -> HashMap<String, String> {
+    HashMap::from([
+        ("image/webp".to_string(), "abc.webp".to_string()),
+        ("image/avif".to_string(), "abc.avif".to_string()),
+    ])
+}
+
+// ============================================================================
+// Content Negotiation Tests
+// ============================================================================
+
+#[test]
+fn test_prefers_avif_over_webp() {
+    let available = variants();
+    let accept = "image/avif,image/webp,image/apng,image/*,*/*;q=0.8";
+    assert_eq!(preferred_variant(accept, &available), Some("abc.avif"));
+}
+
+#[test]
+fn test_falls_back_to_webp() {
+    let mut available = variants();
+    available.remove("image/avif");
+
+    let accept = "image/avif,image/webp,*/*";
+    assert_eq!(preferred_variant(accept, &available), Some("abc.webp"));
+}
+
+#[test]
+fn test_wildcards_get_original() {
+    // Browsers that don't name the format may not be able to decode it.
+    let available = variants();
+    assert_eq!(preferred_variant("image/*,*/*;q=0.8", &available), None);
+}
+
+#[test]
+fn test_zero_quality_is_refused() {
+    let available = variants();
+    assert_eq!(
+        preferred_variant("image/avif;q=0, image/webp", &available),
+        Some("abc.webp")
+    );
+    assert_eq!(preferred_variant("image/webp;q=0", &available), None);
+}
+
+// ============================================================================
+// Variant Lookup Tests
+// ============================================================================
+
+async fn create_image_attachment(
+    db: &DatabaseConnection,
+    hash: &str,
+    extension: &str,
+    mime: &str,
+) -> dumpster::orm::attachments::Model {
+    use dumpster::orm::attachments;
+
+    attachments::ActiveModel {
+        filename: Set(format!("{}.{}", hash, extension)),
+        hash: Set(hash.to_string()),
+        first_seen_at: Set(Utc::now().naive_utc()),
+        last_seen_at: Set(Utc::now().naive_utc()),
+        filesize: Set(4096),
+        file_width: Set(Some(640)),
+        file_height: Set(Some(480)),
+        mime: Set(mime.to_string()),
+        meta: Set(serde_json::json!({})),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to insert attachment")
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_attachment_variants_lookup() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::orm::attachment_variants;
+
+    let original = create_image_attachment(&db, &"d".repeat(64), "png", "image/png").await;
+    let webp = create_image_attachment(&db, &"e".repeat(64), "webp", "image/webp").await;
+
+    assert!(get_attachment_variants(original.id).await.is_empty());
+
+    attachment_variants::ActiveModel {
+        attachment_id: Set(original.id),
+        variant_id: Set(webp.id),
+        mime: Set("image/webp".to_string()),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to link variant");
+
+    let available = get_attachment_variants(original.id).await;
+    assert_eq!(available.len(), 1);
+    assert_eq!(
+        preferred_variant("image/avif,image/webp,*/*", &available),
+        Some(webp.filename.as_str())
+    );
+
+    // The variant itself has no variants of its own
+    assert!(get_attachment_variants(webp.id).await.is_empty());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -262,3 +262,111 @@ async fn test_nonexistent_user_returns_bad_name() {
 
     cleanup_test_data(&db).await.expect("Failed to cleanup");
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_ip_failures_lock_address() {
+    use dumpster::login_throttle;
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+    let ip = "203.0.113.7";
+    login_throttle::clear_ip(ip).await.expect("Failed to clear");
+
+    // Defaults: delays start after 2 failures, the address locks at 20
+    for _ in 0..3 {
+        login_throttle::record_ip_failure(ip)
+            .await
+            .expect("Failed to record failure");
+    }
+    assert_eq!(login_throttle::ip_failures(ip).await.unwrap(), 3);
+
+    let throttle = login_throttle::check(ip, "nobody").await.unwrap();
+    assert_eq!(throttle.failures, 3);
+    assert!(
+        throttle.wait.is_some(),
+        "Third failure should impose a wait"
+    );
+    assert!(throttle.ip_locked_until.is_none());
+
+    for _ in 3..20 {
+        login_throttle::record_ip_failure(ip)
+            .await
+            .expect("Failed to record failure");
+    }
+    let throttle = login_throttle::check(ip, "nobody").await.unwrap();
+    assert!(
+        throttle.ip_locked_until.is_some(),
+        "Address should be locked"
+    );
+
+    // Clearing forgets the failures and lifts the lockout
+    login_throttle::clear_ip(ip).await.expect("Failed to clear");
+    let throttle = login_throttle::check(ip, "nobody").await.unwrap();
+    assert_eq!(throttle.failures, 0);
+    assert!(throttle.ip_locked_until.is_none());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_concurrent_failures_are_all_counted() {
+    use dumpster::login_throttle;
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "raceuser", "correct_password")
+        .await
+        .expect("Failed to create test user");
+
+    let results =
+        futures::future::join_all((0..3).map(|_| login_throttle::record_account_failure(user.id)))
+            .await;
+    assert!(results.iter().all(|result| result.is_ok()));
+
+    let attempts = get_failed_attempts(&db, user.id)
+        .await
+        .expect("Failed to get attempts");
+    assert_eq!(attempts, 3);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_clearing_leaves_running_lockout() {
+    use dumpster::login_throttle;
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_locked_test_user(&db, "stilllocked", "correct_password", 30)
+        .await
+        .expect("Failed to create locked user");
+
+    // A sign-in finishing after a racing failure locked the account
+    login_throttle::clear_account(user.id)
+        .await
+        .expect("Failed to clear");
+    assert!(is_user_locked(&db, user.id).await.unwrap());
+
+    // An administrator can still lift it
+    login_throttle::unlock_account(user.id)
+        .await
+        .expect("Failed to unlock");
+    assert!(!is_user_locked(&db, user.id).await.unwrap());
+    assert_eq!(get_failed_attempts(&db, user.id).await.unwrap(), 0);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,151 @@
+//! Integration tests for announcements emailed to many members
+
+mod common;
+use serial_test::serial;
+
+use common::{database::*, fixtures::*};
+use dumpster::email::mass::{self, Audience};
+use dumpster::jobs::Job;
+use dumpster::orm::{email_log, groups, mass_emails, user_bans, user_groups, users};
+use sea_orm::{entity::*, query::*, ActiveValue::Set};
+
+#[actix_rt::test]
+#[serial]
+async fn test_audience_filters() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create user");
+    let bob = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create user");
+    create_test_user_with_email(&db, "carol", "carol@example.com", false)
+        .await
+        .expect("Failed to create user");
+    let dave = create_test_user_with_email(&db, "dave", "dave@example.com", true)
+        .await
+        .expect("Failed to create user");
+
+    // Bob turned announcements off, Dave is banned
+    mass::set_announcements(bob.id, false)
+        .await
+        .expect("Failed to update setting");
+    assert!(!mass::announcements_enabled(bob.id).await.unwrap());
+    assert!(mass::announcements_enabled(alice.id).await.unwrap());
+    user_bans::ActiveModel {
+        user_id: Set(dave.id),
+        reason: Set("Spam".to_string()),
+        is_permanent: Set(true),
+        created_at: Set(chrono::Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to ban user");
+
+    let opted_in = Audience {
+        opted_in_only: true,
+        ..Default::default()
+    };
+    assert_eq!(opted_in.count().await.unwrap(), 1);
+    assert_eq!(Audience::default().count().await.unwrap(), 2);
+
+    // Only Bob is in the group
+    let group = groups::ActiveModel {
+        label: Set("Subscribers".to_string()),
+        group_type: Set(dumpster::group::GroupType::Normal),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create group");
+    user_groups::ActiveModel {
+        user_id: Set(bob.id),
+        group_id: Set(group.id),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to add user to group");
+    let in_group = Audience {
+        group_id: Some(group.id),
+        ..Default::default()
+    };
+    assert_eq!(in_group.count().await.unwrap(), 1);
+
+    // Only Alice has been active lately
+    users::Entity::update_many()
+        .col_expr(
+            users::Column::LastActivityAt,
+            sea_orm::sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .filter(users::Column::Id.eq(alice.id))
+        .exec(&db)
+        .await
+        .expect("Failed to update activity");
+    let active = Audience {
+        active_within_days: Some(7),
+        ..Default::default()
+    };
+    assert_eq!(active.count().await.unwrap(), 1);
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_batches_queue_each_recipient_once() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user_with_email(&db, "alice", "alice@example.com", true)
+        .await
+        .expect("Failed to create user");
+    create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create user");
+
+    let announcement = mass::create(
+        "Maintenance tonight",
+        "The forum will be down for an hour.",
+        &Audience::default(),
+        alice.id,
+    )
+    .await
+    .expect("Failed to create announcement");
+    assert_eq!(announcement.total_recipients, 2);
+
+    let job = Job {
+        id: 0,
+        kind: "email.mass_batch".to_string(),
+        payload: serde_json::json!({ "mass_email_id": announcement.id }),
+        attempts: 1,
+        max_attempts: 5,
+    };
+    mass::run_batch_job(&job).await.expect("Batch failed");
+    // A repeated batch finds the announcement done and sends nothing more
+    mass::run_batch_job(&job).await.expect("Batch failed");
+
+    let announcement = mass_emails::Entity::find_by_id(announcement.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(announcement.status, mass::STATUS_DONE);
+    assert_eq!(announcement.queued_count, 2);
+
+    let emails = email_log::Entity::find()
+        .filter(email_log::Column::MassEmailId.eq(announcement.id))
+        .count(&db)
+        .await
+        .unwrap();
+    assert_eq!(emails, 2);
+    let counts = mass::delivery_counts(&[announcement.id]).await.unwrap();
+    assert_eq!(counts[&announcement.id].pending, 2);
+
+    // Finished announcements can't be cancelled
+    assert!(!mass::cancel(announcement.id).await.unwrap());
+}
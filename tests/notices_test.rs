@@ -0,0 +1,133 @@
+//! Integration tests for site and forum notices
+
+mod common;
+use serial_test::serial;
+
+use chrono::{Duration, Utc};
+use common::{database::*, fixtures::*};
+use dumpster::notices;
+use dumpster::orm::notices as notice_rows;
+use sea_orm::{entity::*, DatabaseConnection};
+
+async fn create_notice(
+    db: &DatabaseConnection,
+    title: &str,
+    forum_id: Option<i32>,
+    hours_from_now: Option<(i64, i64)>,
+    requires_acknowledgment: bool,
+) -> notice_rows::Model {
+    let now = Utc::now().naive_utc();
+    notice_rows::ActiveModel {
+        title: Set(title.to_string()),
+        body: Set("[b]Read me[/b]".to_string()),
+        style: Set("info".to_string()),
+        forum_id: Set(forum_id),
+        starts_at: Set(hours_from_now.map(|(start, _)| now + Duration::hours(start))),
+        ends_at: Set(hours_from_now.map(|(_, end)| now + Duration::hours(end))),
+        is_dismissible: Set(true),
+        requires_acknowledgment: Set(requires_acknowledgment),
+        is_active: Set(true),
+        created_by: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to create notice")
+}
+
+fn titles(notices: &[notices::ActiveNotice]) -> Vec<&str> {
+    let mut titles: Vec<&str> = notices.iter().map(|n| n.title.as_str()).collect();
+    titles.sort_unstable();
+    titles
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_schedule_and_forum_notices() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let (forum, _) = create_test_forum_and_thread(&db, alice.id, "Hello")
+        .await
+        .expect("Failed to create forum");
+
+    create_notice(&db, "always", None, None, false).await;
+    create_notice(&db, "now", None, Some((-1, 1)), false).await;
+    create_notice(&db, "later", None, Some((1, 2)), false).await;
+    create_notice(&db, "over", None, Some((-2, -1)), false).await;
+    create_notice(&db, "in forum", Some(forum.id), None, false).await;
+    notices::changed().await;
+
+    let site = notices::current(None, None).await.unwrap();
+    assert_eq!(titles(&site), vec!["always", "now"]);
+    assert!(site[0].body_html.contains("<b>Read me</b>"));
+
+    let in_forum = notices::current(Some(alice.id), Some(forum.id))
+        .await
+        .unwrap();
+    assert_eq!(titles(&in_forum), vec!["in forum"]);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_dismiss_and_acknowledge() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let bob = create_test_user(&db, "bob", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let info = create_notice(&db, "info", None, None, false).await;
+    let critical = create_notice(&db, "critical", None, None, true).await;
+    notices::changed().await;
+
+    assert!(notices::dismiss(info.id, alice.id).await.unwrap());
+    // Dismissing again is harmless
+    assert!(notices::dismiss(info.id, alice.id).await.unwrap());
+    // Critical notices can only be acknowledged
+    assert!(!notices::dismiss(critical.id, alice.id).await.unwrap());
+    assert!(!notices::acknowledge(info.id, alice.id).await.unwrap());
+
+    let shown = notices::current(Some(alice.id), None).await.unwrap();
+    assert_eq!(titles(&shown), vec!["critical"]);
+
+    assert!(notices::acknowledge(critical.id, alice.id).await.unwrap());
+    assert!(notices::current(Some(alice.id), None)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Bob and guests still see both
+    let shown = notices::current(Some(bob.id), None).await.unwrap();
+    assert_eq!(titles(&shown), vec!["critical", "info"]);
+    assert_eq!(notices::current(None, None).await.unwrap().len(), 2);
+
+    let acknowledged = notices::acknowledgments(critical.id).await.unwrap();
+    assert_eq!(acknowledged.len(), 1);
+    assert_eq!(acknowledged[0].1, "alice");
+    assert_eq!(
+        notices::acknowledgment_counts()
+            .await
+            .unwrap()
+            .get(&critical.id)
+            .copied(),
+        Some(1)
+    );
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
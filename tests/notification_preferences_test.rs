@@ -26,8 +26,8 @@ async fn test_get_all_user_preferences_with_defaults() {
         .await
         .expect("Failed to get preferences");
 
-    // Should have all 5 notification types
-    assert_eq!(prefs.len(), 5);
+    // Should have all 7 notification types
+    assert_eq!(prefs.len(), 8);
 
     // Check that all have default values
     for pref in &prefs {
@@ -43,6 +43,9 @@ async fn test_get_all_user_preferences_with_defaults() {
     assert!(types.contains(&"pm"));
     assert!(types.contains(&"quote"));
     assert!(types.contains(&"thread_watch"));
+    assert!(types.contains(&"reaction"));
+    assert!(types.contains(&"forum_watch"));
+    assert!(types.contains(&"chat"));
 }
 
 #[actix_rt::test]
@@ -319,3 +322,356 @@ async fn test_preference_display_has_labels() {
         );
     }
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_quiet_hours_saved_and_applied() {
+    use chrono::Timelike;
+    use notifications::quiet_hours::{self, QuietHours};
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user_with_email(&db, "ivy", "ivy@example.com", true)
+        .await
+        .expect("Failed to create user");
+
+    // Defaults are switched off
+    let defaults = quiet_hours::get_quiet_hours(user.id)
+        .await
+        .expect("Failed to get quiet hours");
+    assert!(!defaults.enabled);
+    assert!(!notifications::is_do_not_disturb(user.id).await.unwrap());
+
+    // A window around the current local time (UTC+02:00)
+    let local = chrono::Utc::now().naive_utc() + chrono::Duration::minutes(120);
+    let minute = (local.hour() * 60 + local.minute()) as i16;
+    let settings = QuietHours {
+        enabled: true,
+        start_minute: (minute + 1440 - 60) % 1440,
+        end_minute: (minute + 60) % 1440,
+        utc_offset_minutes: 120,
+    };
+    quiet_hours::update_quiet_hours(user.id, &settings)
+        .await
+        .expect("Failed to save quiet hours");
+    assert!(notifications::is_do_not_disturb(user.id).await.unwrap());
+
+    // Saving again updates the existing row
+    quiet_hours::update_quiet_hours(
+        user.id,
+        &QuietHours {
+            enabled: false,
+            ..settings
+        },
+    )
+    .await
+    .expect("Failed to update quiet hours");
+
+    let saved = quiet_hours::get_quiet_hours(user.id)
+        .await
+        .expect("Failed to get quiet hours");
+    assert!(!saved.enabled);
+    assert_eq!(saved.utc_offset_minutes, 120);
+    assert!(!notifications::is_do_not_disturb(user.id).await.unwrap());
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_daily_digest_marks_notifications_emailed() {
+    use dumpster::notifications::{digest, NotificationType};
+    use dumpster::orm::notifications as notification;
+
+    std::env::set_var("SMTP_MOCK", "true");
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user_with_email(&db, "jack", "jack@example.com", true)
+        .await
+        .expect("Failed to create user");
+
+    notifications::update_preference(user.id, "mention", true, true, "daily")
+        .await
+        .expect("Failed to update preference");
+
+    let create = |notification_type: NotificationType, title: &str| {
+        notifications::create_notification(
+            user.id,
+            notification_type,
+            title.to_string(),
+            "Message".to_string(),
+            Some("/threads/1".to_string()),
+            None,
+            None,
+            None,
+        )
+    };
+
+    let mention_id = create(NotificationType::Mention, "Mentioned")
+        .await
+        .expect("Failed to create mention");
+    let reply_id = create(NotificationType::Reply, "Replied")
+        .await
+        .expect("Failed to create reply");
+
+    let now = chrono::Utc::now().naive_utc();
+    assert_eq!(digest::run_digests(now).await.unwrap(), 1);
+
+    let is_emailed = |id: i32| {
+        let db = db.clone();
+        async move {
+            notification::Entity::find_by_id(id)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap()
+                .is_emailed
+        }
+    };
+    assert!(is_emailed(mention_id).await);
+    // Immediate notifications aren't part of a digest
+    assert!(!is_emailed(reply_id).await);
+
+    // Nothing more is sent until the next daily slot
+    let later_mention = create(NotificationType::Mention, "Mentioned again")
+        .await
+        .expect("Failed to create mention");
+    assert_eq!(digest::run_digests(now).await.unwrap(), 0);
+    assert!(!is_emailed(later_mention).await);
+
+    assert_eq!(
+        digest::run_digests(now + chrono::Duration::days(1))
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(is_emailed(later_mention).await);
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_push_subscriptions_follow_the_browser() {
+    use notifications::push;
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let kate = create_test_user_with_email(&db, "kate", "kate@example.com", true)
+        .await
+        .expect("Failed to create kate");
+    let liam = create_test_user_with_email(&db, "liam", "liam@example.com", true)
+        .await
+        .expect("Failed to create liam");
+
+    let endpoint = "https://push.example.net/send/abc123";
+    // Uncompressed P-256 point (0x04 prefix) and 16-byte auth secret
+    let p256dh = format!("BA{}", "A".repeat(85));
+    let auth = "AAAAAAAAAAAAAAAAAAAAAA";
+
+    push::subscribe(kate.id, endpoint, &p256dh, auth)
+        .await
+        .expect("Failed to subscribe");
+    // Subscribing twice doesn't duplicate the browser
+    push::subscribe(kate.id, endpoint, &p256dh, auth)
+        .await
+        .expect("Failed to resubscribe");
+    assert_eq!(push::get_subscriptions(kate.id).await.unwrap().len(), 1);
+
+    // Plain http endpoints are refused
+    assert!(
+        push::subscribe(kate.id, "http://push.example.net/x", &p256dh, auth)
+            .await
+            .is_err()
+    );
+
+    // Another account signing in on the same browser takes the subscription
+    push::subscribe(liam.id, endpoint, &p256dh, auth)
+        .await
+        .expect("Failed to subscribe liam");
+    assert!(push::get_subscriptions(kate.id).await.unwrap().is_empty());
+    assert_eq!(push::get_subscriptions(liam.id).await.unwrap().len(), 1);
+
+    // Only the owner can remove it
+    assert!(!push::unsubscribe(kate.id, endpoint).await.unwrap());
+    assert!(push::unsubscribe(liam.id, endpoint).await.unwrap());
+    assert!(push::get_subscriptions(liam.id).await.unwrap().is_empty());
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_batch_preference_update() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user_with_email(&db, "dana", "dana@example.com", true)
+        .await
+        .expect("Failed to create user");
+
+    // Reading preferences no longer stores the defaults
+    let prefs =
+        notifications::get_user_preferences(user.id, &notifications::NotificationType::Reply)
+            .await
+            .expect("Failed to get preferences");
+    assert!(prefs.push);
+    let stored = notification_preferences::Entity::find()
+        .filter(notification_preferences::Column::UserId.eq(user.id))
+        .count(&db)
+        .await
+        .expect("Failed to count");
+    assert_eq!(stored, 0);
+
+    notifications::update_preferences(
+        user.id,
+        &[
+            notifications::PreferenceUpdate {
+                notification_type: "reply".to_string(),
+                in_app: true,
+                email: false,
+                push: false,
+                frequency: "immediate".to_string(),
+            },
+            notifications::PreferenceUpdate {
+                notification_type: "reaction".to_string(),
+                in_app: false,
+                email: true,
+                push: true,
+                frequency: "weekly".to_string(),
+            },
+        ],
+    )
+    .await
+    .expect("Failed to save preferences");
+
+    let prefs = notifications::get_all_user_preferences(user.id)
+        .await
+        .expect("Failed to get preferences");
+    let reply = prefs
+        .iter()
+        .find(|p| p.notification_type == "reply")
+        .unwrap();
+    assert!(!reply.email);
+    assert!(!reply.push);
+    let reaction = prefs
+        .iter()
+        .find(|p| p.notification_type == "reaction")
+        .unwrap();
+    assert!(!reaction.in_app);
+    assert_eq!(reaction.frequency, "weekly");
+
+    // A bad entry rejects the whole batch
+    let result = notifications::update_preferences(
+        user.id,
+        &[
+            notifications::PreferenceUpdate {
+                notification_type: "reply".to_string(),
+                in_app: true,
+                email: true,
+                push: true,
+                frequency: "immediate".to_string(),
+            },
+            notifications::PreferenceUpdate {
+                notification_type: "mention".to_string(),
+                in_app: true,
+                email: true,
+                push: true,
+                frequency: "monthly".to_string(),
+            },
+        ],
+    )
+    .await;
+    assert!(result.is_err());
+
+    let reply =
+        notifications::get_user_preferences(user.id, &notifications::NotificationType::Reply)
+            .await
+            .expect("Failed to get preferences");
+    assert!(!reply.push);
+
+    let result = notifications::update_preferences(
+        user.id,
+        &[notifications::PreferenceUpdate {
+            notification_type: "nonsense".to_string(),
+            in_app: true,
+            email: true,
+            push: true,
+            frequency: "immediate".to_string(),
+        }],
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_unsubscribe_disables_email_only() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user_with_email(&db, "frank", "frank@example.com", true)
+        .await
+        .expect("Failed to create user");
+
+    notifications::update_preference(user.id, "mention", false, true, "daily")
+        .await
+        .expect("Failed to update preference");
+
+    let token = dumpster::email::unsubscribe::token(user.id, "mention");
+    let (user_id, scope) =
+        dumpster::email::unsubscribe::verify(&token).expect("Token should verify");
+    notifications::disable_email(
+        user_id,
+        &dumpster::email::unsubscribe::notification_types(&scope),
+    )
+    .await
+    .expect("Failed to unsubscribe");
+
+    let prefs = notifications::get_all_user_preferences(user.id)
+        .await
+        .expect("Failed to get preferences");
+    let mention = prefs
+        .iter()
+        .find(|p| p.notification_type == "mention")
+        .expect("Mention preference missing");
+    assert!(!mention.email);
+    // Other settings are kept
+    assert!(!mention.in_app);
+    assert_eq!(mention.frequency, "daily");
+    assert!(prefs
+        .iter()
+        .filter(|p| p.notification_type != "mention")
+        .all(|p| p.email));
+
+    // Unsubscribing from everything turns off the rest, and can be repeated
+    for _ in 0..2 {
+        notifications::disable_email(
+            user.id,
+            &dumpster::email::unsubscribe::notification_types(dumpster::email::unsubscribe::ALL),
+        )
+        .await
+        .expect("Failed to unsubscribe from all");
+    }
+    let prefs = notifications::get_all_user_preferences(user.id)
+        .await
+        .expect("Failed to get preferences");
+    assert!(prefs.iter().all(|p| !p.email));
+    assert!(prefs
+        .iter()
+        .filter(|p| p.notification_type != "mention")
+        .all(|p| p.in_app));
+}
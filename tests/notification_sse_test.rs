@@ -0,0 +1,58 @@
+/// Integration tests for the Server-Sent Events notification fallback
+/// Tests that notifications pushed by the server reach an event stream
+mod common;
+use serial_test::serial;
+
+use actix::prelude::*;
+use dumpster::web::notifications_ws::sse::SseConnection;
+use dumpster::web::notifications_ws::{
+    get_notification_server, init_notification_server, BroadcastNotification, NotificationData,
+    NotificationServer,
+};
+use futures::StreamExt;
+use std::time::Duration;
+
+#[actix_rt::test]
+#[serial]
+async fn test_event_stream_receives_notifications() {
+    if get_notification_server().is_none() {
+        init_notification_server(NotificationServer::new().start());
+    }
+    let server = get_notification_server().unwrap().clone();
+
+    let mut stream = SseConnection::start_stream(42, server.clone());
+
+    // The browser is told how long to wait before reconnecting
+    let retry = stream.next().await.expect("Stream ended");
+    assert!(String::from_utf8_lossy(&retry).starts_with("retry: "));
+
+    // Give the connection time to register with the server
+    actix_rt::time::sleep(Duration::from_millis(100)).await;
+
+    server.do_send(BroadcastNotification {
+        user_id: 42,
+        notification: NotificationData {
+            id: 7,
+            notification_type: "mention".to_string(),
+            title: "alice mentioned you".to_string(),
+            message: "You were mentioned in: Hello".to_string(),
+            url: Some("/threads/1".to_string()),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            actor_count: 1,
+            updated: false,
+        },
+    });
+
+    let event = actix_rt::time::timeout(Duration::from_secs(2), stream.next())
+        .await
+        .expect("Timed out waiting for event")
+        .expect("Stream ended");
+    let event = String::from_utf8(event.to_vec()).unwrap();
+
+    assert!(event.starts_with("data: "));
+    assert!(event.ends_with("\n\n"));
+    let json: serde_json::Value = serde_json::from_str(event["data: ".len()..].trim()).unwrap();
+    assert_eq!(json["type"], "notification");
+    assert_eq!(json["data"]["id"], 7);
+    assert_eq!(json["data"]["title"], "alice mentioned you");
+}
@@ -22,6 +22,7 @@ async fn create_notification_preferences(
         in_app: Set(in_app),
         email: Set(email),
         frequency: Set("immediate".to_string()),
+        push: Set(true),
     };
 
     prefs.insert(db).await
@@ -788,3 +789,96 @@ My response."#;
 
     cleanup_test_data(&db).await.expect("Failed to cleanup");
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_grouped_notifications_collapse_and_reopen() {
+    use dumpster::notifications::dispatcher;
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let author = create_test_user_with_email(&db, "author", "author@example.com", true)
+        .await
+        .expect("Failed to create author");
+    let bob = create_test_user_with_email(&db, "bob", "bob@example.com", true)
+        .await
+        .expect("Failed to create bob");
+    let carol = create_test_user_with_email(&db, "carol", "carol@example.com", true)
+        .await
+        .expect("Failed to create carol");
+
+    create_notification_preferences(&db, author.id, "reply", true, false)
+        .await
+        .expect("Failed to create preferences");
+    create_notification_preferences(&db, author.id, "reaction", true, false)
+        .await
+        .expect("Failed to create preferences");
+
+    let (_forum, thread) = create_test_forum_and_thread(&db, author.id, "Grouped Thread")
+        .await
+        .expect("Failed to create thread");
+
+    // Three replies from two people collapse into one notification
+    dispatcher::notify_thread_reply(thread.id, 2, bob.id)
+        .await
+        .expect("Failed to notify");
+    dispatcher::notify_thread_reply(thread.id, 3, bob.id)
+        .await
+        .expect("Failed to notify");
+    dispatcher::notify_thread_reply(thread.id, 4, carol.id)
+        .await
+        .expect("Failed to notify");
+
+    let notifs = notifications::get_user_notifications(author.id, 10, true)
+        .await
+        .expect("Failed to get notifications");
+    assert_eq!(notifs.len(), 1);
+    assert_eq!(notifs[0].actor_count, 2);
+    assert_eq!(notifs[0].title, "carol and 1 other replied to your thread");
+
+    // Once read, the next reply starts a new notification
+    notifications::mark_all_read(author.id)
+        .await
+        .expect("Failed to mark read");
+    dispatcher::notify_thread_reply(thread.id, 5, bob.id)
+        .await
+        .expect("Failed to notify");
+
+    let notifs = notifications::get_user_notifications(author.id, 10, true)
+        .await
+        .expect("Failed to get notifications");
+    assert_eq!(notifs.len(), 2);
+    assert_eq!(notifs[0].title, "bob replied to your thread");
+    assert_eq!(
+        notifications::count_unread_notifications(author.id)
+            .await
+            .unwrap(),
+        1
+    );
+
+    // Reactions to one post group the same way; reacting to yourself is ignored
+    let post = create_test_post(&db, thread.id, author.id, "React to me", 1)
+        .await
+        .expect("Failed to create post");
+    for reactor in [bob.id, carol.id, author.id] {
+        dispatcher::notify_reaction(post.ugc_id, reactor)
+            .await
+            .expect("Failed to notify reaction");
+    }
+
+    let reactions: Vec<_> = notifications::get_user_notifications(author.id, 10, false)
+        .await
+        .expect("Failed to get notifications")
+        .into_iter()
+        .filter(|n| n.type_ == "reaction")
+        .collect();
+    assert_eq!(reactions.len(), 1);
+    assert_eq!(reactions[0].actor_count, 2);
+    assert_eq!(reactions[0].title, "carol and 1 other reacted to your post");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,54 @@
+//! Integration tests for outbound link click counts
+
+mod common;
+use serial_test::serial;
+
+use common::{database::*, fixtures::*};
+use dumpster::outbound::{record_click, thread_clicks};
+
+#[actix_rt::test]
+#[serial]
+async fn test_link_clicks_counted_per_thread() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "link_poster", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_, thread) = create_test_forum_and_thread(&db, user.id, "Links")
+        .await
+        .expect("Failed to create thread");
+    let (_, other_thread) = create_test_forum_and_thread(&db, user.id, "More links")
+        .await
+        .expect("Failed to create thread");
+
+    for url in [
+        "https://example.com/",
+        "https://example.org/",
+        "https://example.com/",
+    ] {
+        record_click(thread.id, url)
+            .await
+            .expect("Failed to record click");
+    }
+    record_click(other_thread.id, "https://example.org/")
+        .await
+        .expect("Failed to record click");
+
+    let links = thread_clicks(thread.id)
+        .await
+        .expect("Failed to load clicks");
+    let counts: Vec<(&str, i32)> = links
+        .iter()
+        .map(|link| (link.url.as_str(), link.clicks))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![("https://example.com/", 2), ("https://example.org/", 1)]
+    );
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
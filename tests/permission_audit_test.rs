@@ -0,0 +1,212 @@
+/// Integration tests for the permission audit log
+mod common;
+use serial_test::serial;
+
+use common::database::*;
+use common::fixtures::*;
+use dumpster::orm::{
+    forum_permissions, forums, groups, permission_audit, permission_categories,
+    permission_collections, permission_values, permissions, user_groups,
+};
+use dumpster::permission::audit::{self, Change};
+use dumpster::permission::flag::Flag;
+use sea_orm::{entity::*, query::*, ActiveValue::Set};
+
+#[actix_rt::test]
+#[serial]
+async fn test_forum_permission_change_is_recorded() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let admin = create_test_user(&db, "auditadmin", "password123")
+        .await
+        .expect("Failed to create user");
+    let forum = forums::ActiveModel {
+        label: Set("Audited Forum".to_string()),
+        display_order: Set(0),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create forum");
+    let group = groups::ActiveModel {
+        label: Set("Guests".to_string()),
+        group_type: Set(dumpster::group::GroupType::Normal),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create group");
+    let category = permission_categories::ActiveModel {
+        label: Set("forum".to_string()),
+        sort: Set(0),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create category");
+    let permission = permissions::ActiveModel {
+        category_id: Set(category.id),
+        label: Set("forum.view".to_string()),
+        sort: Set(0),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create permission");
+
+    let before = audit::forum(&db, forum.id)
+        .await
+        .expect("Failed to snapshot");
+    assert!(before.is_empty());
+
+    // Deny guests the forum
+    let collection = permission_collections::ActiveModel {
+        group_id: Set(Some(group.id)),
+        user_id: Set(None),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create collection");
+    forum_permissions::ActiveModel {
+        forum_id: Set(forum.id),
+        collection_id: Set(collection.id),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to link collection");
+    permission_values::ActiveModel {
+        permission_id: Set(permission.id),
+        collection_id: Set(collection.id),
+        value: Set(Flag::NO),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to set permission");
+
+    let after = audit::forum(&db, forum.id)
+        .await
+        .expect("Failed to snapshot");
+    assert_eq!(
+        after.get("Guests: forum.view").map(String::as_str),
+        Some("no")
+    );
+
+    let recorded = audit::record(
+        &db,
+        Some(admin.id),
+        Change::ForumPermissions,
+        forum.id,
+        &before,
+        &after,
+    )
+    .await
+    .expect("Failed to record");
+    assert!(recorded);
+
+    // Saving again without changes records nothing
+    let recorded = audit::record(
+        &db,
+        Some(admin.id),
+        Change::ForumPermissions,
+        forum.id,
+        &after,
+        &after,
+    )
+    .await
+    .expect("Failed to record");
+    assert!(!recorded);
+
+    let filter = audit::Filter {
+        change: Some(Change::ForumPermissions),
+        actor: Some("AUDITADMIN".to_string()),
+        target_id: Some(forum.id),
+    };
+    assert_eq!(audit::count(&db, &filter).await.unwrap(), 1);
+
+    let entries = audit::list(&db, &filter, 50, 0).await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].actor_name.as_deref(), Some("auditadmin"));
+    assert_eq!(entries[0].target_name.as_deref(), Some("Audited Forum"));
+
+    let differences = entries[0].differences();
+    assert_eq!(differences.len(), 1);
+    assert_eq!(differences[0].key, "Guests: forum.view");
+    assert_eq!(differences[0].before, None);
+    assert_eq!(differences[0].after.as_deref(), Some("no"));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_group_membership_change_is_recorded() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let admin = create_test_user(&db, "auditadmin", "password123")
+        .await
+        .expect("Failed to create user");
+    let member = create_test_user(&db, "promoted", "password123")
+        .await
+        .expect("Failed to create user");
+    let group = groups::ActiveModel {
+        label: Set("Administrators".to_string()),
+        group_type: Set(dumpster::group::GroupType::Normal),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create group");
+
+    let before = audit::user_groups(&db, member.id).await.unwrap();
+    user_groups::ActiveModel {
+        user_id: Set(member.id),
+        group_id: Set(group.id),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to add member");
+    let after = audit::user_groups(&db, member.id).await.unwrap();
+
+    audit::record(
+        &db,
+        Some(admin.id),
+        Change::UserGroups,
+        member.id,
+        &before,
+        &after,
+    )
+    .await
+    .expect("Failed to record");
+
+    let entry = permission_audit::Entity::find()
+        .filter(permission_audit::Column::TargetId.eq(member.id))
+        .one(&db)
+        .await
+        .expect("Failed to query audit log")
+        .expect("Change was not recorded");
+    assert_eq!(entry.change_type, "user_groups");
+    assert_eq!(entry.actor_id, Some(admin.id));
+    assert_eq!(entry.before, serde_json::json!({}));
+    assert_eq!(
+        entry.after,
+        serde_json::json!({ "Administrators": "member" })
+    );
+
+    // Filtering by someone else finds nothing
+    let filter = audit::Filter {
+        actor: Some("promoted".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(audit::count(&db, &filter).await.unwrap(), 0);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
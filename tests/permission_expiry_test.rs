@@ -0,0 +1,66 @@
+/// Integration tests for expiring permission collections
+mod common;
+use serial_test::serial;
+
+use chrono::{Duration, Utc};
+use common::database::*;
+use dumpster::orm::{groups, permission_collections};
+use dumpster::permission::delete_expired_collections;
+use sea_orm::{entity::*, ActiveValue::Set};
+
+#[actix_rt::test]
+#[serial]
+async fn test_expired_collections_are_deleted() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let group = groups::ActiveModel {
+        label: Set("Event Moderators".to_string()),
+        group_type: Set(dumpster::group::GroupType::Normal),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create group");
+
+    let now = Utc::now().naive_utc();
+    let mut ids = Vec::new();
+    for expires_at in [
+        Some(now - Duration::hours(1)),
+        Some(now + Duration::hours(1)),
+        None,
+    ] {
+        let collection = permission_collections::ActiveModel {
+            group_id: Set(Some(group.id)),
+            user_id: Set(None),
+            expires_at: Set(expires_at),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to create collection");
+        ids.push(collection.id);
+    }
+
+    let deleted = delete_expired_collections(&db)
+        .await
+        .expect("Failed to delete expired collections");
+    assert_eq!(deleted, 1);
+
+    let remaining: Vec<i32> = permission_collections::Entity::find()
+        .all(&db)
+        .await
+        .expect("Failed to fetch collections")
+        .into_iter()
+        .map(|c| c.id)
+        .collect();
+    assert!(!remaining.contains(&ids[0]));
+    assert!(remaining.contains(&ids[1]));
+    assert!(remaining.contains(&ids[2]));
+
+    // Nothing left to expire
+    assert_eq!(delete_expired_collections(&db).await.unwrap(), 0);
+}
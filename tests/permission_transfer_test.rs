@@ -0,0 +1,157 @@
+/// Integration tests for permission export and import
+mod common;
+use serial_test::serial;
+
+use common::database::*;
+use dumpster::orm::{forums, groups, permission_categories, permissions};
+use dumpster::permission::flag::Flag;
+use dumpster::permission::template::{self, MatrixValue};
+use dumpster::permission::transfer;
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection};
+
+/// A forum, a group and two permissions to configure
+async fn seed(db: &DatabaseConnection) -> (forums::Model, groups::Model, Vec<permissions::Model>) {
+    let parent = forums::ActiveModel {
+        label: Set("Community".to_string()),
+        display_order: Set(0),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to create forum");
+    let forum = forums::ActiveModel {
+        label: Set("Staff".to_string()),
+        parent_id: Set(Some(parent.id)),
+        display_order: Set(0),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to create forum");
+    let group = groups::ActiveModel {
+        label: Set("Guests".to_string()),
+        group_type: Set(dumpster::group::GroupType::Normal),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to create group");
+    let category = permission_categories::ActiveModel {
+        label: Set("forum".to_string()),
+        sort: Set(0),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to create category");
+
+    let mut perms = Vec::new();
+    for (sort, label) in ["forum.view", "post.create"].into_iter().enumerate() {
+        perms.push(
+            permissions::ActiveModel {
+                category_id: Set(category.id),
+                label: Set(label.to_string()),
+                sort: Set(sort as i32),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+            .expect("Failed to create permission"),
+        );
+    }
+
+    (forum, group, perms)
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_export_import_round_trip() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let (forum, group, perms) = seed(&db).await;
+    transfer::replace_group_values(&db, group.id, &[(perms[0].id, Flag::YES)])
+        .await
+        .expect("Failed to set group values");
+    template::replace_forum_matrix(
+        &db,
+        forum.id,
+        &[MatrixValue {
+            group_id: group.id,
+            permission_id: perms[0].id,
+            value: Flag::NEVER,
+        }],
+    )
+    .await
+    .expect("Failed to set forum overrides");
+
+    let exported = transfer::export(&db).await.expect("Failed to export");
+    assert_eq!(exported.groups["Guests"]["forum.view"], "yes");
+    assert_eq!(
+        exported.forums["Community / Staff"]["Guests"]["forum.view"],
+        "never"
+    );
+
+    // Change everything, then import the export back
+    transfer::replace_group_values(&db, group.id, &[(perms[1].id, Flag::NO)])
+        .await
+        .expect("Failed to set group values");
+    template::replace_forum_matrix(&db, forum.id, &[])
+        .await
+        .expect("Failed to clear forum overrides");
+
+    let import = transfer::prepare(&db, &exported)
+        .await
+        .expect("Failed to prepare import");
+    assert!(import.is_valid(), "{:?}", import.errors);
+    let keys: Vec<String> = import.differences().into_iter().map(|d| d.key).collect();
+    assert_eq!(
+        keys,
+        vec![
+            "Community / Staff / Guests: forum.view",
+            "Guests: forum.view",
+            "Guests: post.create",
+        ]
+    );
+
+    transfer::apply(&db, &import)
+        .await
+        .expect("Failed to apply import");
+    assert_eq!(
+        transfer::export(&db).await.expect("Failed to export"),
+        exported
+    );
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_import_rejects_unknown_groups_and_forums() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    seed(&db).await;
+    let mut exported = transfer::export(&db).await.expect("Failed to export");
+    exported
+        .groups
+        .insert("Missing Group".to_string(), Default::default());
+    exported
+        .forums
+        .insert("Community / Missing".to_string(), Default::default());
+    exported
+        .groups
+        .get_mut("Guests")
+        .expect("Guests not exported")
+        .insert("forum.view".to_string(), "maybe".to_string());
+
+    let import = transfer::prepare(&db, &exported)
+        .await
+        .expect("Failed to prepare import");
+    assert!(!import.is_valid());
+    assert_eq!(import.errors.len(), 3, "{:?}", import.errors);
+}
@@ -0,0 +1,274 @@
+//! Integration tests for poll voting
+
+mod common;
+use serial_test::serial;
+
+use chrono::{Duration, Utc};
+use common::{database::*, fixtures::*};
+use dumpster::orm::{poll_options, poll_runoff_results, poll_votes, polls};
+use dumpster::web::polls::{cast_vote, runoff_results, voters_by_option, VoteError};
+use sea_orm::{entity::*, query::*, DatabaseConnection};
+
+async fn create_poll(
+    db: &DatabaseConnection,
+    thread_id: i32,
+    max_choices: i32,
+    allow_change_vote: bool,
+    poll_type: &str,
+) -> (polls::Model, Vec<i32>) {
+    let poll = polls::ActiveModel {
+        thread_id: Set(thread_id),
+        question: Set("Favourite colour?".to_string()),
+        max_choices: Set(max_choices),
+        allow_change_vote: Set(allow_change_vote),
+        poll_type: Set(poll_type.to_string()),
+        results_visibility: Set("after_vote".to_string()),
+        public_votes: Set(true),
+        closes_at: Set(None),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to create poll");
+
+    let mut option_ids = Vec::new();
+    for (i, text) in ["Red", "Green", "Blue", "Yellow"].iter().enumerate() {
+        let option = poll_options::ActiveModel {
+            poll_id: Set(poll.id),
+            option_text: Set(text.to_string()),
+            display_order: Set(i as i32),
+            vote_count: Set(0),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .expect("Failed to create poll option");
+        option_ids.push(option.id);
+    }
+
+    (poll, option_ids)
+}
+
+async fn vote_counts(db: &DatabaseConnection, poll_id: i32) -> Vec<i32> {
+    poll_options::Entity::find()
+        .filter(poll_options::Column::PollId.eq(poll_id))
+        .order_by_asc(poll_options::Column::DisplayOrder)
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|o| o.vote_count)
+        .collect()
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_multiple_choice_and_changing_vote() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let bob = create_test_user(&db, "bob", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_, thread) = create_test_forum_and_thread(&db, alice.id, "Colours")
+        .await
+        .expect("Failed to create thread");
+    let (poll, options) = create_poll(&db, thread.id, 2, true, "choice").await;
+
+    assert!(matches!(
+        cast_vote(poll.id, alice.id, &options).await,
+        Err(VoteError::TooManyOptions(2))
+    ));
+    assert!(matches!(
+        cast_vote(poll.id, alice.id, &[]).await,
+        Err(VoteError::NoOptions)
+    ));
+
+    cast_vote(poll.id, alice.id, &[options[0], options[1], options[0]])
+        .await
+        .expect("Vote should be accepted");
+    cast_vote(poll.id, bob.id, &[options[1]])
+        .await
+        .expect("Vote should be accepted");
+    assert_eq!(vote_counts(&db, poll.id).await, vec![1, 2, 0, 0]);
+
+    // Changing a vote replaces it rather than adding to it
+    cast_vote(poll.id, alice.id, &[options[2]])
+        .await
+        .expect("Vote change should be accepted");
+    assert_eq!(vote_counts(&db, poll.id).await, vec![0, 1, 1, 0]);
+
+    let voters = voters_by_option(poll.id).await.unwrap();
+    assert_eq!(voters.get(&options[1]), Some(&vec!["bob".to_string()]));
+    assert_eq!(voters.get(&options[2]), Some(&vec!["alice".to_string()]));
+    assert!(!voters.contains_key(&options[0]));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_closed_and_unchangeable_polls() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_, thread) = create_test_forum_and_thread(&db, alice.id, "Colours")
+        .await
+        .expect("Failed to create thread");
+    let (poll, options) = create_poll(&db, thread.id, 1, false, "choice").await;
+
+    cast_vote(poll.id, alice.id, &[options[0]])
+        .await
+        .expect("Vote should be accepted");
+    assert!(matches!(
+        cast_vote(poll.id, alice.id, &[options[1]]).await,
+        Err(VoteError::AlreadyVoted)
+    ));
+    assert!(matches!(
+        cast_vote(poll.id, alice.id, &[-1]).await,
+        Err(VoteError::InvalidOption)
+    ));
+
+    polls::ActiveModel {
+        id: Set(poll.id),
+        closes_at: Set(Some(Utc::now().naive_utc() - Duration::minutes(1))),
+        ..Default::default()
+    }
+    .update(&db)
+    .await
+    .expect("Failed to close poll");
+    assert!(matches!(
+        cast_vote(poll.id, alice.id, &[options[0]]).await,
+        Err(VoteError::Closed)
+    ));
+    assert_eq!(vote_counts(&db, poll.id).await, vec![1, 0, 0, 0]);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_concurrent_first_votes_count_once() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let alice = create_test_user(&db, "alice", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_, thread) = create_test_forum_and_thread(&db, alice.id, "Colours")
+        .await
+        .expect("Failed to create thread");
+    let (poll, options) = create_poll(&db, thread.id, 1, false, "choice").await;
+
+    // Neither ballot finds an earlier vote until the other has committed
+    let (first, second) = futures::future::join(
+        cast_vote(poll.id, alice.id, &[options[0]]),
+        cast_vote(poll.id, alice.id, &[options[1]]),
+    )
+    .await;
+    assert_eq!(first.is_ok() as u8 + second.is_ok() as u8, 1);
+    assert!(matches!(
+        first.err().or(second.err()),
+        Some(VoteError::AlreadyVoted)
+    ));
+    assert_eq!(vote_counts(&db, poll.id).await.iter().sum::<i32>(), 1);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_ranked_poll_runoff() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let mut voters = Vec::new();
+    for name in ["alice", "bob", "carol", "dave", "erin"] {
+        voters.push(
+            create_test_user(&db, name, "password123")
+                .await
+                .expect("Failed to create user"),
+        );
+    }
+    let (_, thread) = create_test_forum_and_thread(&db, voters[0].id, "Colours")
+        .await
+        .expect("Failed to create thread");
+    let (poll, options) = create_poll(&db, thread.id, 4, true, "ranked").await;
+    let [red, green, blue, yellow] = [options[0], options[1], options[2], options[3]];
+
+    // Every option may be ranked, in the order given
+    cast_vote(poll.id, voters[0].id, &[red, green, blue, yellow])
+        .await
+        .expect("Ballot should be accepted");
+    cast_vote(poll.id, voters[1].id, &[red])
+        .await
+        .expect("Ballot should be accepted");
+    cast_vote(poll.id, voters[2].id, &[green, blue])
+        .await
+        .expect("Ballot should be accepted");
+    cast_vote(poll.id, voters[3].id, &[blue, green])
+        .await
+        .expect("Ballot should be accepted");
+    cast_vote(poll.id, voters[4].id, &[yellow, green])
+        .await
+        .expect("Ballot should be accepted");
+
+    let ranks: Vec<Option<i32>> = poll_votes::Entity::find()
+        .filter(poll_votes::Column::UserId.eq(voters[3].id))
+        .order_by_asc(poll_votes::Column::Rank)
+        .all(&db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|v| v.rank)
+        .collect();
+    assert_eq!(ranks, vec![Some(1), Some(2)]);
+
+    // Yellow goes first, then blue, and both ballots move to green
+    let open = runoff_results(&poll, &options).await.unwrap();
+    assert_eq!(open.ballots, 5);
+    assert_eq!(open.rounds[0].eliminated, Some(yellow));
+    assert_eq!(open.rounds[1].eliminated, Some(blue));
+    assert_eq!(open.winner, Some(green));
+    assert!(poll_runoff_results::Entity::find_by_id(poll.id)
+        .one(&db)
+        .await
+        .unwrap()
+        .is_none());
+
+    let poll = polls::ActiveModel {
+        id: Set(poll.id),
+        closes_at: Set(Some(Utc::now().naive_utc() - Duration::minutes(1))),
+        ..Default::default()
+    }
+    .update(&db)
+    .await
+    .expect("Failed to close poll");
+    let closed = runoff_results(&poll, &options).await.unwrap();
+    assert_eq!(closed, open);
+
+    // The stored count stands when ballots later go away
+    poll_votes::Entity::delete_many()
+        .filter(poll_votes::Column::UserId.eq(voters[2].id))
+        .exec(&db)
+        .await
+        .unwrap();
+    assert_eq!(runoff_results(&poll, &options).await.unwrap(), open);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
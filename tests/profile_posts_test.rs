@@ -412,52 +412,61 @@ async fn test_profile_posts_cascade_on_profile_owner_delete() {
 
 #[actix_rt::test]
 #[serial]
-async fn test_allow_profile_posts_setting() {
+async fn test_profile_post_privacy_setting() {
     let db = setup_test_database()
         .await
         .expect("Failed to connect to test database");
 
     cleanup_test_data(&db).await.expect("Failed to cleanup");
 
-    use dumpster::orm::users;
+    use dumpster::orm::{user_follows, users};
+    use dumpster::web::member::can_post_on_profile;
 
-    // Create user with default allow_profile_posts (should be true)
-    let user = create_test_user(&db, "profile_user6", "password123")
+    let owner = create_test_user(&db, "profile_user6", "password123")
+        .await
+        .expect("Failed to create user");
+    let friend = create_test_user(&db, "profile_friend", "password123")
+        .await
+        .expect("Failed to create user");
+    let stranger = create_test_user(&db, "profile_stranger", "password123")
         .await
         .expect("Failed to create user");
 
-    // Verify default is true
-    let loaded_user = users::Entity::find_by_id(user.id)
+    // Anyone may post by default
+    let loaded_user = users::Entity::find_by_id(owner.id)
         .one(&db)
         .await
         .expect("Failed to load user")
         .expect("User not found");
-
-    assert!(
-        loaded_user.allow_profile_posts,
-        "Default allow_profile_posts should be true"
-    );
-
-    // Disable profile posts
-    use sea_orm::sea_query::Expr;
-    users::Entity::update_many()
-        .col_expr(users::Column::AllowProfilePosts, Expr::value(false))
-        .filter(users::Column::Id.eq(user.id))
-        .exec(&db)
+    assert_eq!(loaded_user.profile_post_privacy, "everyone");
+    assert!(can_post_on_profile(owner.id, "everyone", stranger.id)
         .await
-        .expect("Failed to update user");
+        .unwrap());
 
-    // Verify it's now false
-    let updated_user = users::Entity::find_by_id(user.id)
-        .one(&db)
+    // Only members the owner follows
+    user_follows::ActiveModel {
+        follower_id: Set(owner.id),
+        following_id: Set(friend.id),
+        created_at: Set(Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to follow");
+    assert!(can_post_on_profile(owner.id, "following", friend.id)
         .await
-        .expect("Failed to load user")
-        .expect("User not found");
+        .unwrap());
+    assert!(!can_post_on_profile(owner.id, "following", stranger.id)
+        .await
+        .unwrap());
 
-    assert!(
-        !updated_user.allow_profile_posts,
-        "allow_profile_posts should be false after update"
-    );
+    // Nobody else, though owners can always post on their own wall
+    assert!(!can_post_on_profile(owner.id, "nobody", friend.id)
+        .await
+        .unwrap());
+    assert!(can_post_on_profile(owner.id, "nobody", owner.id)
+        .await
+        .unwrap());
 
     cleanup_test_data(&db).await.expect("Failed to cleanup");
 }
@@ -623,3 +632,97 @@ async fn test_multiple_users_can_post_on_same_profile() {
 
     cleanup_test_data(&db).await.expect("Failed to cleanup");
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_profile_post_comments() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::orm::{profile_post_comments, profile_posts};
+    use dumpster::ugc::{create_ugc, NewUgcPartial};
+    use dumpster::web::member::get_profile_post_comments;
+
+    let owner = create_test_user(&db, "comment_owner", "password123")
+        .await
+        .expect("Failed to create user");
+    let visitor = create_test_user(&db, "comment_visitor", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let ugc = |user_id: i32, content: &'static str| {
+        let db = &db;
+        async move {
+            create_ugc(
+                db,
+                NewUgcPartial {
+                    ip_id: None,
+                    user_id: Some(user_id),
+                    content,
+                },
+            )
+            .await
+            .expect("Failed to create UGC")
+            .ugc_id
+        }
+    };
+
+    let post = profile_posts::ActiveModel {
+        profile_user_id: Set(owner.id),
+        author_id: Set(Some(visitor.id)),
+        ugc_id: Set(ugc(visitor.id, "Hello!").await),
+        created_at: Set(Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create profile post");
+
+    for (author_id, content) in [(owner.id, "Hi yourself"), (visitor.id, "How are you?")] {
+        profile_post_comments::ActiveModel {
+            profile_post_id: Set(post.id),
+            author_id: Set(Some(author_id)),
+            ugc_id: Set(ugc(author_id, content).await),
+            created_at: Set(Utc::now().into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to create comment");
+    }
+
+    let comments = get_profile_post_comments(&db, vec![post.id])
+        .await
+        .expect("Failed to load comments");
+    let thread: Vec<(Option<String>, String)> = comments[&post.id]
+        .iter()
+        .map(|c| (c.author_name.clone(), c.content.clone()))
+        .collect();
+    assert_eq!(
+        thread,
+        vec![
+            (Some("comment_owner".to_string()), "Hi yourself".to_string()),
+            (
+                Some("comment_visitor".to_string()),
+                "How are you?".to_string()
+            ),
+        ]
+    );
+
+    // Comments go with their post
+    profile_posts::Entity::delete_by_id(post.id)
+        .exec(&db)
+        .await
+        .expect("Failed to delete post");
+    let remaining = profile_post_comments::Entity::find()
+        .filter(profile_post_comments::Column::ProfilePostId.eq(post.id))
+        .all(&db)
+        .await
+        .expect("Failed to query comments");
+    assert!(remaining.is_empty());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
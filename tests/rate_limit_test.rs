@@ -5,11 +5,11 @@ use dumpster::rate_limit::{
 };
 use std::time::Duration;
 
-#[test]
-fn test_login_rate_limit_allows_within_limit() {
+#[actix_rt::test]
+async fn test_login_rate_limit_allows_within_limit() {
     // Should allow 5 login attempts within 5 minutes
     for i in 0..5 {
-        let result = check_login_rate_limit("192.168.1.1", "testuser");
+        let result = check_login_rate_limit("192.168.1.1", "testuser").await;
         assert!(
             result.is_ok(),
             "Login attempt {} should be allowed within rate limit",
@@ -55,11 +55,11 @@ fn test_login_rate_limit_blocks_over_limit() {
     }
 }
 
-#[test]
-fn test_post_rate_limit() {
+#[actix_rt::test]
+async fn test_post_rate_limit() {
     // Should allow 10 posts per minute
     for i in 0..10 {
-        let result = check_post_rate_limit(123);
+        let result = check_post_rate_limit(123, &[]).await;
         assert!(
             result.is_ok(),
             "Post {} should be allowed within rate limit",
@@ -68,15 +68,15 @@ fn test_post_rate_limit() {
     }
 
     // 11th post should be blocked
-    let result = check_post_rate_limit(123);
+    let result = check_post_rate_limit(123, &[]).await;
     assert!(result.is_err(), "11th post should be blocked");
 }
 
-#[test]
-fn test_thread_rate_limit() {
+#[actix_rt::test]
+async fn test_thread_rate_limit() {
     // Should allow 5 threads per 5 minutes
     for i in 0..5 {
-        let result = check_thread_rate_limit(456);
+        let result = check_thread_rate_limit(456, &[]).await;
         assert!(
             result.is_ok(),
             "Thread {} should be allowed within rate limit",
@@ -85,26 +85,26 @@ fn test_thread_rate_limit() {
     }
 
     // 6th thread should be blocked
-    let result = check_thread_rate_limit(456);
+    let result = check_thread_rate_limit(456, &[]).await;
     assert!(result.is_err(), "6th thread should be blocked");
 }
 
-#[test]
-fn test_different_users_independent_limits() {
+#[actix_rt::test]
+async fn test_different_users_independent_limits() {
     // User 1 uses up their post limit
     for _ in 0..10 {
-        check_post_rate_limit(100).unwrap();
+        check_post_rate_limit(100, &[]).await.unwrap();
     }
 
     // User 1's 11th post should be blocked
     assert!(
-        check_post_rate_limit(100).is_err(),
+        check_post_rate_limit(100, &[]).await.is_err(),
         "User 1 should be rate limited"
     );
 
     // User 2 should still be able to post
     assert!(
-        check_post_rate_limit(200).is_ok(),
+        check_post_rate_limit(200, &[]).await.is_ok(),
         "User 2 should not be affected by User 1's rate limit"
     );
 }
@@ -738,3 +738,148 @@ async fn test_min_posts_to_vote_setting_exists() {
     assert_eq!(setting.value, "5", "Default min_posts_to_vote should be 5");
     assert_eq!(setting.value_type, "int", "Setting should be of type int");
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_reaction_stats_for_profile() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::orm::{reaction_types, ugc_reactions};
+    use dumpster::web::reactions::reaction_stats;
+
+    let author = create_test_user(&db, "stats_author", "password123")
+        .await
+        .expect("Failed to create user");
+    let fan = create_test_user(&db, "stats_fan", "password123")
+        .await
+        .expect("Failed to create user");
+    let other_fan = create_test_user(&db, "stats_other_fan", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_, thread) = create_test_forum_and_thread(&db, author.id, "Stats")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread.id, author.id, "Hello", 1)
+        .await
+        .expect("Failed to create post");
+
+    let type_id = |name: &'static str| {
+        let db = &db;
+        async move {
+            reaction_types::Entity::find()
+                .filter(reaction_types::Column::Name.eq(name))
+                .one(db)
+                .await
+                .unwrap()
+                .expect("Reaction type should exist")
+                .id
+        }
+    };
+    let like = type_id("like").await;
+    let thanks = type_id("thanks").await;
+
+    for (user_id, reaction_type_id) in [(fan.id, like), (fan.id, thanks), (other_fan.id, like)] {
+        ugc_reactions::ActiveModel {
+            ugc_id: Set(post.ugc_id),
+            user_id: Set(user_id),
+            reaction_type_id: Set(reaction_type_id),
+            created_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to add reaction");
+    }
+
+    let (received, given) = reaction_stats(author.id).await.unwrap();
+    let received: Vec<(i32, i64)> = received
+        .iter()
+        .map(|s| (s.reaction_type_id, s.count))
+        .collect();
+    assert_eq!(received, vec![(like, 2), (thanks, 1)]);
+    assert!(given.is_empty());
+
+    let (received, given) = reaction_stats(fan.id).await.unwrap();
+    assert!(received.is_empty());
+    assert_eq!(given.iter().map(|s| s.count).sum::<i64>(), 2);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_reputation_recalculated_after_weight_change() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::orm::{reaction_types, ugc_reactions, users};
+
+    let author = create_test_user(&db, "weight_author", "password123")
+        .await
+        .expect("Failed to create user");
+    let fan = create_test_user(&db, "weight_fan", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_, thread) = create_test_forum_and_thread(&db, author.id, "Weights")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread.id, author.id, "Hello", 1)
+        .await
+        .expect("Failed to create post");
+
+    let like = reaction_types::Entity::find()
+        .filter(reaction_types::Column::Name.eq("like"))
+        .one(&db)
+        .await
+        .unwrap()
+        .expect("Reaction type should exist");
+
+    ugc_reactions::ActiveModel {
+        ugc_id: Set(post.ugc_id),
+        user_id: Set(fan.id),
+        reaction_type_id: Set(like.id),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to add reaction");
+
+    let score = |user_id: i32| {
+        let db = &db;
+        async move {
+            users::Entity::find_by_id(user_id)
+                .one(db)
+                .await
+                .unwrap()
+                .expect("User should exist")
+                .reputation_score
+        }
+    };
+    assert_eq!(score(author.id).await, like.reputation_value);
+
+    // Reweighting leaves existing scores alone until the recalculation
+    let mut reweighted: reaction_types::ActiveModel = like.clone().into();
+    reweighted.reputation_value = Set(like.reputation_value + 4);
+    reweighted.update(&db).await.expect("Failed to reweight");
+    assert_eq!(score(author.id).await, like.reputation_value);
+
+    let changed = dumpster::reputation::recalculate().await.unwrap();
+    assert!(changed >= 1);
+    assert_eq!(score(author.id).await, like.reputation_value + 4);
+
+    let mut restored: reaction_types::ActiveModel = like.clone().into();
+    restored.reputation_value = Set(like.reputation_value);
+    restored
+        .update(&db)
+        .await
+        .expect("Failed to restore weight");
+    dumpster::reputation::recalculate().await.unwrap();
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
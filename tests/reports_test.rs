@@ -394,3 +394,89 @@ async fn test_filter_reports_by_status() {
 
     cleanup_test_data(&db).await.expect("Failed to cleanup");
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_report_forum_id() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::web::reports::report_forum_id;
+
+    let user = create_test_user(&db, "forumreporter", "password123")
+        .await
+        .expect("Failed to create user");
+    let (forum, thread) = create_test_forum_and_thread(&db, user.id, "Reported Thread")
+        .await
+        .expect("Failed to create forum and thread");
+    let post = create_test_post(&db, thread.id, user.id, "Reported post", 1)
+        .await
+        .expect("Failed to create post");
+
+    // Posts and threads are routed to their forum's moderators
+    assert_eq!(
+        report_forum_id(&db, "post", post.id).await.unwrap(),
+        Some(forum.id)
+    );
+    assert_eq!(
+        report_forum_id(&db, "thread", thread.id).await.unwrap(),
+        Some(forum.id)
+    );
+
+    // Members aren't in a forum, and deleted content is nowhere
+    assert_eq!(report_forum_id(&db, "user", user.id).await.unwrap(), None);
+    assert_eq!(report_forum_id(&db, "post", -1).await.unwrap(), None);
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_overdue_report_reminders() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::notifications::reports::{overdue_count, send_reminders};
+    use dumpster::orm::reports;
+
+    let reporter = create_test_user(&db, "sla_reporter", "password123")
+        .await
+        .expect("Failed to create reporter");
+    let reported = create_test_user(&db, "sla_reported", "password123")
+        .await
+        .expect("Failed to create reported user");
+
+    // Older than the default 24 hour response time, recent, and dealt with
+    let now = Utc::now().naive_utc();
+    for (hours_ago, status) in [(30, "open"), (1, "open"), (30, "resolved")] {
+        let created_at = now - chrono::Duration::hours(hours_ago);
+        reports::ActiveModel {
+            reporter_id: Set(reporter.id),
+            content_type: Set("user".to_string()),
+            content_id: Set(reported.id),
+            reason: Set("spam".to_string()),
+            status: Set(status.to_string()),
+            created_at: Set(created_at),
+            updated_at: Set(created_at),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to create report");
+    }
+
+    assert_eq!(overdue_count(now).await.unwrap(), 1);
+    assert_eq!(send_reminders(now).await.unwrap(), 1);
+
+    // Not again until the reminder interval has passed
+    assert_eq!(send_reminders(now).await.unwrap(), 0);
+    let later = now + chrono::Duration::hours(25);
+    assert_eq!(overdue_count(later).await.unwrap(), 2);
+    assert_eq!(send_reminders(later).await.unwrap(), 2);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
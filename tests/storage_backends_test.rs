@@ -0,0 +1,110 @@
+//! Tests for the cloud storage backends
+
+use dumpster::storage::azure::{string_to_sign, AzureStorage};
+use dumpster::storage::gcs::GcsStorage;
+use dumpster::storage::{get_key_path, StorageBackend};
+use url::Url;
+
+const ACCOUNT_KEY: &str = "ZHVtcHN0ZXItdGVzdC1rZXk=";
+
+fn filename() -> String {
+    format!("{}{}.mp4", "ab".repeat(2), "0".repeat(60))
+}
+
+#[test]
+fn test_key_path_is_shared_by_backends() {
+    assert_eq!(get_key_path(&filename()), format!("ab/ab/{}", filename()));
+}
+
+#[test]
+fn test_azure_string_to_sign() {
+    let url = Url::parse(
+        "https://acct.blob.core.windows.net/files/ab/ab/video.mp4?comp=block&blockid=YQ%3D%3D",
+    )
+    .unwrap();
+    let headers = vec![
+        ("x-ms-version".to_owned(), "2021-08-06".to_owned()),
+        (
+            "x-ms-date".to_owned(),
+            "Mon, 05 Oct 2026 12:00:00 GMT".to_owned(),
+        ),
+        ("content-type".to_owned(), "video/mp4".to_owned()),
+    ];
+
+    assert_eq!(
+        string_to_sign("PUT", "1024", &headers, &url, "acct"),
+        "PUT\n\n\n1024\n\n\n\n\n\n\n\n\n\
+         x-ms-date:Mon, 05 Oct 2026 12:00:00 GMT\n\
+         x-ms-version:2021-08-06\n\
+         /acct/files/ab/ab/video.mp4\nblockid:YQ==\ncomp:block"
+    );
+}
+
+#[test]
+fn test_azure_block_ids_have_equal_length() {
+    let upload_id = "0b4a5b0e-6b5f-4f53-9f1e-0c2b7a1f9d11";
+    let first = AzureStorage::block_id(upload_id, 1);
+    let last = AzureStorage::block_id(upload_id, 10_000);
+
+    assert_ne!(first, last);
+    assert_eq!(first.len(), last.len());
+    assert_eq!(first, AzureStorage::block_id(upload_id, 1));
+}
+
+#[test]
+fn test_azure_signed_url() {
+    let storage = AzureStorage::new(
+        "acct".to_owned(),
+        ACCOUNT_KEY,
+        "files".to_owned(),
+        Some("http://127.0.0.1:10000/acct".to_owned()),
+    )
+    .expect("Failed to create storage");
+
+    let url = Url::parse(&storage.signed_url(&filename(), "clip.mp4", 1_800_000_000)).unwrap();
+    assert_eq!(url.path(), format!("/acct/files/ab/ab/{}", filename()));
+
+    let params: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_owned())
+    };
+    assert_eq!(param("sp").as_deref(), Some("r"));
+    assert_eq!(param("sr").as_deref(), Some("b"));
+    assert_eq!(param("se").as_deref(), Some("2027-01-15T08:00:00Z"));
+    assert_eq!(
+        param("rscd").as_deref(),
+        Some("inline; filename=\"clip.mp4\"")
+    );
+    assert!(param("sig").is_some());
+
+    // Another expiry gets another signature
+    let later = storage.signed_url(&filename(), "clip.mp4", 1_800_003_600);
+    assert!(!later.contains(&url.query().unwrap().to_owned()));
+}
+
+#[test]
+fn test_azure_rejects_invalid_key() {
+    assert!(AzureStorage::new("acct".to_owned(), "not base64!", "files".to_owned(), None).is_err());
+}
+
+#[actix_rt::test]
+async fn test_gcs_signed_url_is_presigned() {
+    let storage = GcsStorage::new(
+        "forum-files".to_owned(),
+        "https://storage.googleapis.com".to_owned(),
+        "GOOG1EXAMPLE".to_owned(),
+        "secret".to_owned(),
+    );
+
+    let expires = chrono::Utc::now().timestamp() + 3600;
+    let url = storage.signed_url(&filename(), "clip.mp4", expires);
+    assert!(url.starts_with(&format!(
+        "https://storage.googleapis.com/forum-files/ab/ab/{}?",
+        filename()
+    )));
+    assert!(url.contains("X-Amz-Credential=GOOG1EXAMPLE"));
+    assert!(url.contains("X-Amz-Signature="));
+}
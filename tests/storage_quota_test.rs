@@ -0,0 +1,198 @@
+//! Integration tests for per-group attachment storage quotas
+
+mod common;
+use serial_test::serial;
+
+use actix_web::http::StatusCode;
+use chrono::Utc;
+use common::{database::*, fixtures::*};
+use dumpster::filesystem::quota::{
+    check_upload_quota, format_bytes, get_largest_consumers, get_storage_usage, record_upload,
+};
+use dumpster::group::GroupType;
+use dumpster::orm::{groups, user_groups};
+use sea_orm::{entity::*, ActiveValue::Set, DatabaseConnection};
+
+const MB: i64 = 1024 * 1024;
+
+async fn create_group_with_quota(
+    db: &DatabaseConnection,
+    label: &str,
+    quota_mb: Option<i32>,
+) -> groups::Model {
+    groups::ActiveModel {
+        label: Set(label.to_string()),
+        group_type: Set(GroupType::Normal),
+        storage_quota_mb: Set(quota_mb),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("Failed to create group")
+}
+
+async fn add_user_to_group(db: &DatabaseConnection, user_id: i32, group_id: i32) {
+    user_groups::ActiveModel {
+        user_id: Set(user_id),
+        group_id: Set(group_id),
+    }
+    .insert(db)
+    .await
+    .expect("Failed to add user to group");
+}
+
+#[test]
+fn test_format_bytes() {
+    assert_eq!(format_bytes(0), "0 bytes");
+    assert_eq!(format_bytes(1023), "1023 bytes");
+    assert_eq!(format_bytes(1536), "1.5 KB");
+    assert_eq!(format_bytes(5 * MB), "5.0 MB");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_most_generous_group_quota_applies() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "quota_groups", "password123")
+        .await
+        .expect("Failed to create user");
+
+    // No groups, no limit
+    let usage = get_storage_usage(user.id).await.unwrap();
+    assert_eq!(usage.used, 0);
+    assert_eq!(usage.quota, None);
+
+    let small = create_group_with_quota(&db, "Small", Some(1)).await;
+    let large = create_group_with_quota(&db, "Large", Some(5)).await;
+    let unlimited = create_group_with_quota(&db, "Unlimited", None).await;
+
+    add_user_to_group(&db, user.id, small.id).await;
+    assert_eq!(get_storage_usage(user.id).await.unwrap().quota, Some(MB));
+
+    add_user_to_group(&db, user.id, large.id).await;
+    assert_eq!(
+        get_storage_usage(user.id).await.unwrap().quota,
+        Some(5 * MB)
+    );
+
+    add_user_to_group(&db, user.id, unlimited.id).await;
+    assert_eq!(get_storage_usage(user.id).await.unwrap().quota, None);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_uploads_over_quota_are_rejected() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let user = create_test_user(&db, "quota_user", "password123")
+        .await
+        .expect("Failed to create user");
+    let group = create_group_with_quota(&db, "Limited", Some(1)).await;
+    add_user_to_group(&db, user.id, group.id).await;
+
+    let first = create_test_attachment(
+        &db,
+        &"1".repeat(64),
+        "first.bin",
+        "application/octet-stream",
+        600 * 1024,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    check_upload_quota(user.id, &first.hash, first.filesize)
+        .await
+        .expect("First upload fits");
+    record_upload(user.id, first.id).await;
+    // Counting the same file twice changes nothing
+    record_upload(user.id, first.id).await;
+
+    let usage = get_storage_usage(user.id).await.unwrap();
+    assert_eq!(usage.used, 600 * 1024);
+    assert_eq!(usage.files, 1);
+    assert_eq!(usage.percent_used(), Some(58));
+
+    let err = check_upload_quota(user.id, &"2".repeat(64), 500 * 1024)
+        .await
+        .expect_err("Upload should exceed the quota");
+    assert_eq!(
+        err.as_response_error().status_code(),
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+    assert!(err.to_string().contains("storage quota"));
+
+    // Files the user already has are not counted again
+    assert!(check_upload_quota(user.id, &first.hash, first.filesize)
+        .await
+        .is_ok());
+    assert!(check_upload_quota(user.id, &"3".repeat(64), 400 * 1024)
+        .await
+        .is_ok());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_largest_consumers_report() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let light = create_test_user(&db, "light_uploader", "password123")
+        .await
+        .expect("Failed to create user");
+    let heavy = create_test_user(&db, "heavy_uploader", "password123")
+        .await
+        .expect("Failed to create user");
+
+    let small = create_test_attachment(
+        &db,
+        &"4".repeat(64),
+        "small.bin",
+        "application/octet-stream",
+        MB,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let big = create_test_attachment(
+        &db,
+        &"5".repeat(64),
+        "big.bin",
+        "application/octet-stream",
+        3 * MB,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+
+    // A shared file counts for each uploader
+    record_upload(light.id, small.id).await;
+    record_upload(heavy.id, small.id).await;
+    record_upload(heavy.id, big.id).await;
+
+    let consumers = get_largest_consumers(10).await.unwrap();
+    assert_eq!(consumers.len(), 2);
+    assert_eq!(consumers[0].user_id, heavy.id);
+    assert_eq!(consumers[0].name.as_deref(), Some("heavy_uploader"));
+    assert_eq!(consumers[0].files, 2);
+    assert_eq!(consumers[0].bytes, 4 * MB);
+    assert_eq!(consumers[1].user_id, light.id);
+    assert_eq!(consumers[1].bytes, MB);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -0,0 +1,76 @@
+//! Integration tests for versioned theme saves
+
+mod common;
+use serial_test::serial;
+
+use common::database::*;
+use dumpster::orm::themes;
+use dumpster::theme::{get_revisions, record_revision};
+use sea_orm::{entity::*, query::*};
+
+#[actix_rt::test]
+#[serial]
+async fn test_theme_revisions_listed_newest_first() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    themes::Entity::delete_many()
+        .filter(themes::Column::Slug.eq("revision-test"))
+        .exec(&db)
+        .await
+        .expect("Failed to remove old theme");
+
+    let theme = themes::ActiveModel {
+        slug: Set("revision-test".to_string()),
+        name: Set("Revision Test".to_string()),
+        is_system: Set(false),
+        is_dark: Set(false),
+        is_active: Set(false),
+        display_order: Set(99),
+        css_variables: Set(Some("--bg-primary: #111111;".to_string())),
+        created_at: Set(chrono::Utc::now().into()),
+        updated_at: Set(chrono::Utc::now().into()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to create theme");
+    assert_eq!(theme.version, 1);
+    record_revision(&db, &theme, None)
+        .await
+        .expect("Failed to record revision");
+
+    let mut updated: themes::ActiveModel = theme.into();
+    updated.css_variables = Set(Some("--bg-primary: #222222;".to_string()));
+    updated.version = Set(2);
+    let updated = updated.update(&db).await.expect("Failed to update theme");
+    record_revision(&db, &updated, None)
+        .await
+        .expect("Failed to record revision");
+
+    let revisions = get_revisions(updated.id)
+        .await
+        .expect("Failed to load revisions");
+    let saved: Vec<(i32, Option<&str>)> = revisions
+        .iter()
+        .map(|r| (r.version, r.css_variables.as_deref()))
+        .collect();
+    assert_eq!(
+        saved,
+        vec![
+            (2, Some("--bg-primary: #222222;")),
+            (1, Some("--bg-primary: #111111;")),
+        ]
+    );
+
+    // Revisions go with their theme
+    themes::Entity::delete_by_id(updated.id)
+        .exec(&db)
+        .await
+        .expect("Failed to delete theme");
+    assert!(get_revisions(updated.id)
+        .await
+        .expect("Failed to load revisions")
+        .is_empty());
+}
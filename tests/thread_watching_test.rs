@@ -470,3 +470,89 @@ async fn test_get_watch_status_returns_none_for_unwatched() {
         .expect("Failed to get watch status");
     assert!(status.is_none());
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_forum_watchers_notified_of_new_threads() {
+    use dumpster::orm::notifications as notification_orm;
+
+    std::env::set_var("SMTP_MOCK", "true");
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let author = create_test_user_with_email(&db, "forum_author", "author@example.com", true)
+        .await
+        .expect("Failed to create author");
+    let watcher = create_test_user_with_email(&db, "forum_watcher", "watcher@example.com", true)
+        .await
+        .expect("Failed to create watcher");
+
+    let forum_id = create_test_forum(&db, "Watched Forum")
+        .await
+        .expect("Failed to create forum");
+
+    notifications::watch_forum(watcher.id, forum_id)
+        .await
+        .expect("Failed to watch forum");
+    notifications::watch_forum(author.id, forum_id)
+        .await
+        .expect("Failed to watch forum");
+
+    // Email is opt-in per watch
+    let status = notifications::get_forum_watch_status(watcher.id, forum_id)
+        .await
+        .expect("Failed to get watch status")
+        .expect("Watch not found");
+    assert!(!status.email_on_thread);
+    notifications::toggle_forum_email(watcher.id, forum_id, true)
+        .await
+        .expect("Failed to toggle email");
+    let status = notifications::get_forum_watch_status(watcher.id, forum_id)
+        .await
+        .expect("Failed to get watch status")
+        .expect("Watch not found");
+    assert!(status.email_on_thread);
+
+    let thread_id = create_test_thread(&db, forum_id, author.id, "Brand New Thread")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread_id, author.id, "First post", 1)
+        .await
+        .expect("Failed to create post");
+
+    notifications::dispatcher::notify_forum_watchers(thread_id, post.id, author.id)
+        .await
+        .expect("Failed to notify forum watchers");
+
+    let watcher_notifications = notification_orm::Entity::find()
+        .filter(notification_orm::Column::UserId.eq(watcher.id))
+        .all(&db)
+        .await
+        .expect("Failed to load notifications");
+    assert_eq!(watcher_notifications.len(), 1);
+    assert_eq!(watcher_notifications[0].type_, "forum_watch");
+    assert_eq!(
+        watcher_notifications[0].url.as_deref(),
+        Some(format!("/threads/{}", thread_id).as_str())
+    );
+
+    // The author isn't told about their own thread
+    let author_notifications = notification_orm::Entity::find()
+        .filter(notification_orm::Column::UserId.eq(author.id))
+        .all(&db)
+        .await
+        .expect("Failed to load notifications");
+    assert!(author_notifications.is_empty());
+
+    notifications::unwatch_forum(watcher.id, forum_id)
+        .await
+        .expect("Failed to unwatch forum");
+    let watched = notifications::get_watched_forums(watcher.id)
+        .await
+        .expect("Failed to list watched forums");
+    assert!(watched.is_empty());
+}
@@ -0,0 +1,254 @@
+//! Integration tests for the video transcode queue
+
+mod common;
+use serial_test::serial;
+
+use chrono::Utc;
+use common::{database::*, fixtures::*};
+use dumpster::jobs::Job;
+use dumpster::orm::jobs;
+use dumpster::orm::video_transcodes::{self, TranscodeStatus};
+use dumpster::transcode::{enqueue, finish_job, get_status, run_transcode_job, start_job};
+use sea_orm::{entity::*, query::*, ActiveValue::Set};
+
+#[actix_rt::test]
+#[serial]
+async fn test_enqueue_queues_each_video_once() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let first = create_test_attachment(
+        &db,
+        &"5".repeat(64),
+        "first.mkv",
+        "video/x-matroska",
+        1_000_000,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let second = create_test_attachment(
+        &db,
+        &"6".repeat(64),
+        "second.mov",
+        "video/quicktime",
+        1_000_000,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+
+    enqueue(first.id).await;
+    enqueue(second.id).await;
+    // Queuing twice is harmless
+    enqueue(first.id).await;
+
+    let queued = jobs::Entity::find()
+        .filter(jobs::Column::Kind.eq("video.transcode"))
+        .all(&db)
+        .await
+        .expect("Failed to load jobs");
+    assert_eq!(queued.len(), 2);
+    assert_eq!(
+        get_status(first.id).await.unwrap().status,
+        TranscodeStatus::Pending
+    );
+
+    let job = start_job(first.id, 1)
+        .await
+        .expect("Failed to start job")
+        .expect("Nothing to start");
+    assert_eq!(job.status, TranscodeStatus::Processing);
+    assert_eq!(job.attempts, 1);
+
+    finish_job(&job, Ok(Some(12_500)), 3).await;
+    let status = get_status(first.id).await.expect("Job vanished");
+    assert_eq!(status.status, TranscodeStatus::Ready);
+    assert_eq!(status.duration_ms, Some(12_500));
+
+    // A job run again after the video is ready leaves it alone
+    assert!(start_job(first.id, 2).await.unwrap().is_none());
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_failed_jobs_retry_until_max_attempts() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    let video = create_test_attachment(
+        &db,
+        &"7".repeat(64),
+        "video.avi",
+        "video/x-msvideo",
+        1_000_000,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    enqueue(video.id).await;
+
+    let job = start_job(video.id, 1)
+        .await
+        .unwrap()
+        .expect("Nothing to start");
+    finish_job(&job, Err("corrupt input".to_string()), 2).await;
+    let status = get_status(video.id).await.expect("Job vanished");
+    assert_eq!(status.status, TranscodeStatus::Pending);
+    assert_eq!(status.error.as_deref(), Some("corrupt input"));
+
+    let job = start_job(video.id, 2)
+        .await
+        .unwrap()
+        .expect("Nothing to start");
+    finish_job(&job, Err("corrupt input".to_string()), 2).await;
+    let status = get_status(video.id).await.expect("Job vanished");
+    assert_eq!(status.status, TranscodeStatus::Failed);
+
+    // A job for a video that was never queued has nothing to do
+    let stray = Job {
+        id: 0,
+        kind: "video.transcode".to_string(),
+        payload: serde_json::json!({ "attachment_id": video.id + 1 }),
+        attempts: 1,
+        max_attempts: 2,
+    };
+    assert_eq!(run_transcode_job(&stray).await, Ok(()));
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_post_attachments_expose_video_renditions() {
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+
+    use dumpster::attachment::get_attachments_for_ugc_by_id;
+    use dumpster::orm::{attachment_thumbnails, attachment_variants, ugc_attachments};
+
+    let user = create_test_user(&db, "video_user", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_forum, thread) = create_test_forum_and_thread(&db, user.id, "Videos")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread.id, user.id, "Watch this", 1)
+        .await
+        .expect("Failed to create post");
+
+    let original = create_test_attachment(
+        &db,
+        &"8".repeat(64),
+        "original.mkv",
+        "video/x-matroska",
+        1_000_000,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    ugc_attachments::ActiveModel {
+        attachment_id: Set(original.id),
+        ugc_id: Set(post.ugc_id),
+        user_id: Set(Some(user.id)),
+        ip_id: Set(None),
+        created_at: Set(Utc::now().naive_utc()),
+        filename: Set("clip.mkv".to_string()),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to attach file");
+
+    enqueue(original.id).await;
+
+    let attachments = get_attachments_for_ugc_by_id(vec![post.ugc_id]).await;
+    let attachment = &attachments[&post.ugc_id][0];
+    assert!(attachment.is_video());
+    assert!(attachment.is_transcoding());
+    assert!(attachment.get_video_sources().is_empty());
+
+    let mp4 = create_test_attachment(
+        &db,
+        &"9".repeat(64),
+        "mp4.mp4",
+        "video/mp4",
+        1_000_000,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let webm = create_test_attachment(
+        &db,
+        &"a".repeat(64),
+        "webm.webm",
+        "video/webm",
+        1_000_000,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    let poster = create_test_attachment(
+        &db,
+        &"f".repeat(64),
+        "poster.jpeg",
+        "image/jpeg",
+        1_000_000,
+        Utc::now().naive_utc(),
+    )
+    .await
+    .expect("Failed to insert attachment");
+    for (variant, mime) in [(&mp4, "video/mp4"), (&webm, "video/webm")] {
+        attachment_variants::ActiveModel {
+            attachment_id: Set(original.id),
+            variant_id: Set(variant.id),
+            mime: Set(mime.to_string()),
+        }
+        .insert(&db)
+        .await
+        .expect("Failed to link rendition");
+    }
+    attachment_thumbnails::ActiveModel {
+        attachment_id: Set(original.id),
+        thumbnail_id: Set(poster.id),
+    }
+    .insert(&db)
+    .await
+    .expect("Failed to link poster");
+
+    let job = claim_next_job().await.expect("Nothing to claim");
+    finish_job(&job, Ok(Some(3_000)), 3).await;
+
+    let attachments = get_attachments_for_ugc_by_id(vec![post.ugc_id]).await;
+    let attachment = &attachments[&post.ugc_id][0];
+    assert!(!attachment.is_transcoding());
+
+    let sources = attachment.get_video_sources();
+    assert_eq!(sources.len(), 2);
+    assert_eq!(sources[0].1, "video/webm");
+    assert!(sources[0].0.ends_with(&webm.filename));
+    assert_eq!(sources[1].1, "video/mp4");
+    assert!(attachment
+        .get_poster_url()
+        .is_some_and(|url| url.ends_with(&poster.filename)));
+
+    let job = video_transcodes::Entity::find_by_id(original.id)
+        .one(&db)
+        .await
+        .expect("Failed to load job")
+        .expect("Job vanished");
+    assert_eq!(job.status, TranscodeStatus::Ready);
+
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -4,7 +4,7 @@ mod common;
 
 use common::database::setup_test_database;
 use common::fixtures::create_word_filter;
-use dumpster::word_filter::{apply_filters, reload_filters};
+use dumpster::word_filter::{apply_filters, reload_filters, FilterScope};
 use serial_test::serial;
 
 /// Helper to clean up word filters between tests
@@ -32,7 +32,7 @@ async fn test_word_filter_replace_basic() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Test replacement
-    let result = apply_filters("I love Solana cryptocurrency");
+    let result = apply_filters("I love Solana cryptocurrency", FilterScope::Posts);
     assert!(!result.blocked, "Content should not be blocked");
     assert!(!result.flagged, "Content should not be flagged");
     assert_eq!(
@@ -63,14 +63,14 @@ async fn test_word_filter_replace_case_insensitive() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Test lowercase match
-    let result = apply_filters("Check out solana today");
+    let result = apply_filters("Check out solana today", FilterScope::Posts);
     assert_eq!(
         result.content, "Check out Salona today",
         "Lowercase 'solana' should be replaced with titlecase 'Salona'"
     );
 
     // Test uppercase match
-    let result = apply_filters("SOLANA IS GREAT");
+    let result = apply_filters("SOLANA IS GREAT", FilterScope::Posts);
     assert_eq!(
         result.content, "SALONA IS GREAT",
         "Uppercase 'SOLANA' should be replaced with uppercase 'SALONA'"
@@ -95,11 +95,11 @@ async fn test_word_filter_replace_case_sensitive() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Exact case should match
-    let result = apply_filters("I love Solana");
+    let result = apply_filters("I love Solana", FilterScope::Posts);
     assert_eq!(result.content, "I love Salona", "Exact case should match");
 
     // Different case should NOT match
-    let result = apply_filters("I love solana");
+    let result = apply_filters("I love solana", FilterScope::Posts);
     assert_eq!(
         result.content, "I love solana",
         "Different case should not match"
@@ -124,14 +124,14 @@ async fn test_word_filter_whole_word() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Whole word should match
-    let result = apply_filters("The cat sat on the mat");
+    let result = apply_filters("The cat sat on the mat", FilterScope::Posts);
     assert_eq!(
         result.content, "The dog sat on the mat",
         "Whole word 'cat' should match"
     );
 
     // Word within another word should NOT match
-    let result = apply_filters("category and scatter");
+    let result = apply_filters("category and scatter", FilterScope::Posts);
     assert_eq!(
         result.content, "category and scatter",
         "cat within other words should not match"
@@ -156,7 +156,7 @@ async fn test_word_filter_partial_match() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Should match even within words
-    let result = apply_filters("This is badword and verybad");
+    let result = apply_filters("This is badword and verybad", FilterScope::Posts);
     assert_eq!(
         result.content, "This is ***word and very***",
         "Partial matches should be replaced"
@@ -181,7 +181,7 @@ async fn test_word_filter_block() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Content with blocked word should be blocked
-    let result = apply_filters("Buy cheap spam pills");
+    let result = apply_filters("Buy cheap spam pills", FilterScope::Posts);
     assert!(
         result.blocked,
         "Content with blocked word should be blocked"
@@ -196,7 +196,7 @@ async fn test_word_filter_block() {
     );
 
     // Content without blocked word should pass
-    let result = apply_filters("Hello world");
+    let result = apply_filters("Hello world", FilterScope::Posts);
     assert!(!result.blocked, "Clean content should not be blocked");
 
     cleanup_filters(&db).await;
@@ -218,7 +218,7 @@ async fn test_word_filter_flag() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Content with flagged word should be flagged but not blocked
-    let result = apply_filters("This is suspicious content");
+    let result = apply_filters("This is suspicious content", FilterScope::Posts);
     assert!(!result.blocked, "Content should not be blocked");
     assert!(result.flagged, "Content should be flagged for review");
     assert!(
@@ -253,7 +253,10 @@ async fn test_word_filter_regex() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Email addresses should be replaced
-    let result = apply_filters("Contact me at test@example.com for info");
+    let result = apply_filters(
+        "Contact me at test@example.com for info",
+        FilterScope::Posts,
+    );
     assert_eq!(
         result.content, "Contact me at [email] for info",
         "Email should be replaced"
@@ -278,7 +281,7 @@ async fn test_word_filter_multiple_matches() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Multiple occurrences should all be replaced
-    let result = apply_filters("foo and foo and foo");
+    let result = apply_filters("foo and foo and foo", FilterScope::Posts);
     assert_eq!(
         result.content, "bar and bar and bar",
         "All occurrences should be replaced"
@@ -306,11 +309,11 @@ async fn test_word_filter_priority_block_over_replace() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Content with blocked word should be blocked (not just replaced)
-    let result = apply_filters("This is bad but worse is here");
+    let result = apply_filters("This is bad but worse is here", FilterScope::Posts);
     assert!(result.blocked, "Block should take priority");
 
     // Content with only replace word should be replaced
-    let result = apply_filters("This is bad but not terrible");
+    let result = apply_filters("This is bad but not terrible", FilterScope::Posts);
     assert!(!result.blocked, "Should not be blocked");
     assert_eq!(
         result.content, "This is good but not terrible",
@@ -332,7 +335,7 @@ async fn test_word_filter_no_filters() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Content should pass through unchanged
-    let result = apply_filters("Any content is fine");
+    let result = apply_filters("Any content is fine", FilterScope::Posts);
     assert!(!result.blocked, "Should not be blocked");
     assert!(!result.flagged, "Should not be flagged");
     assert_eq!(result.content, "Any content is fine", "Should be unchanged");
@@ -358,7 +361,120 @@ async fn test_word_filter_empty_content() {
     reload_filters(&db).await.expect("Failed to reload filters");
 
     // Empty content should pass
-    let result = apply_filters("");
+    let result = apply_filters("", FilterScope::Posts);
     assert!(!result.blocked, "Empty content should not be blocked");
     assert_eq!(result.content, "", "Empty content should remain empty");
 }
+
+#[actix_rt::test]
+#[serial]
+async fn test_word_filter_scopes() {
+    use dumpster::orm::word_filters;
+    use sea_orm::{ActiveModelTrait, ActiveValue::Set, IntoActiveModel};
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_filters(&db).await;
+
+    // New filters cover posts, titles and chat but not usernames or signatures
+    let filter = create_word_filter(&db, "heck", Some("h*ck"), "replace", false, false, true)
+        .await
+        .expect("Failed to create filter");
+    assert!(filter.applies_to(FilterScope::Posts));
+    assert!(!filter.applies_to(FilterScope::Signatures));
+
+    let mut filter: word_filters::ActiveModel = filter.into_active_model();
+    filter.applies_to_chat = Set(false);
+    filter.applies_to_signatures = Set(true);
+    filter.update(&db).await.expect("Failed to update filter");
+
+    reload_filters(&db).await.expect("Failed to reload filters");
+
+    assert_eq!(
+        apply_filters("oh heck", FilterScope::Posts).content,
+        "oh h*ck"
+    );
+    assert_eq!(
+        apply_filters("oh heck", FilterScope::Signatures).content,
+        "oh h*ck"
+    );
+    assert_eq!(
+        apply_filters("oh heck", FilterScope::Chat).content,
+        "oh heck"
+    );
+    assert_eq!(
+        apply_filters("oh heck", FilterScope::Usernames).content,
+        "oh heck"
+    );
+
+    cleanup_filters(&db).await;
+}
+
+#[actix_rt::test]
+#[serial]
+async fn test_word_filter_applies_to_post_edits() {
+    use common::database::cleanup_test_data;
+    use common::fixtures::{create_test_forum_and_thread, create_test_post, create_test_user};
+    use dumpster::orm::{ugc, ugc_revisions};
+    use dumpster::web::post::save_post_edit;
+    use sea_orm::EntityTrait;
+
+    let db = setup_test_database()
+        .await
+        .expect("Failed to connect to test database");
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+    cleanup_filters(&db).await;
+
+    let user = create_test_user(&db, "filter_editor", "password123")
+        .await
+        .expect("Failed to create user");
+    let (_, thread) = create_test_forum_and_thread(&db, user.id, "Filtered Edits")
+        .await
+        .expect("Failed to create thread");
+    let post = create_test_post(&db, thread.id, user.id, "A clean post", 1)
+        .await
+        .expect("Failed to create post");
+
+    create_word_filter(&db, "Solana", Some("Salona"), "replace", false, false, true)
+        .await
+        .expect("Failed to create filter");
+    create_word_filter(&db, "forbidden", None, "block", false, false, true)
+        .await
+        .expect("Failed to create filter");
+    reload_filters(&db).await.expect("Failed to reload filters");
+
+    let current_content = || async {
+        let ugc = ugc::Entity::find_by_id(post.ugc_id)
+            .one(&db)
+            .await
+            .expect("Failed to load ugc")
+            .expect("ugc missing");
+        ugc_revisions::Entity::find_by_id(ugc.ugc_revision_id.expect("no revision"))
+            .one(&db)
+            .await
+            .expect("Failed to load revision")
+            .expect("revision missing")
+            .content
+    };
+
+    // Edits are rewritten as new posts are
+    save_post_edit(post.ugc_id, Some(user.id), "Now about Solana")
+        .await
+        .expect("Edit should be saved");
+    assert_eq!(current_content().await, "Now about Salona");
+
+    // and refused when a filter blocks them, leaving the post as it was
+    let refused = save_post_edit(post.ugc_id, Some(user.id), "Something forbidden").await;
+    assert_eq!(
+        refused
+            .expect_err("Edit should be blocked")
+            .as_response_error()
+            .status_code(),
+        actix_web::http::StatusCode::BAD_REQUEST
+    );
+    assert_eq!(current_content().await, "Now about Salona");
+
+    cleanup_filters(&db).await;
+    cleanup_test_data(&db).await.expect("Failed to cleanup");
+}
@@ -50,12 +50,16 @@ module.exports = {
             path.resolve(__dirname, './resources/js/mentions.js'),
             path.resolve(__dirname, './resources/js/notifications.js'),
             path.resolve(__dirname, './resources/js/post-edit.js'),
+            path.resolve(__dirname, './resources/js/push.js'),
             path.resolve(__dirname, './resources/js/quote.js'),
             path.resolve(__dirname, './resources/js/reactions.js'),
             path.resolve(__dirname, './resources/js/recipient-autocomplete.js'),
             path.resolve(__dirname, './resources/js/report.js'),
+            path.resolve(__dirname, './resources/js/search-suggest.js'),
+            path.resolve(__dirname, './resources/js/similar-threads.js'),
             path.resolve(__dirname, './resources/js/unfurl.js'),
         ],
+        'push-sw': path.resolve(__dirname, './resources/js/push-sw.js'),
         style: path.resolve(__dirname, './resources/css/main.scss'),
     },
     output: {